@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 use std::fs::{self, OpenOptions};
-use std::io::Write;
+use std::io::{IsTerminal, Write};
 use std::path::PathBuf;
 
 use anyhow::{anyhow, bail, Context};
@@ -8,16 +8,37 @@ use chrono::{DateTime, Utc};
 use clap::{Args, Parser, Subcommand};
 use reqwest::Client;
 use scraper::{Html, Selector};
-use serde::{Deserialize, Serialize};
-use shared::{NewBookmarkRequest, NewBookmarkResponse, SignInResponse};
+use serde::Deserialize;
+use shared::{
+    BatchBookmarkRequest, BatchBookmarkResponse, BatchBookmarkStatus, Bookmark, Bookmarks,
+    ErrorCode, NewBookmarkRequest, NewBookmarkResponse, Problem, RagChunkMatch, RagCitation,
+    RagQueryRequest, RagQueryResponse, RenameTagRequest, RenameTagResponse, SearchRequest,
+    SearchResponse, SearchResultItem, SignInResponse, TagFilter, Tags, TagsWithCounters,
+};
 use tracing::level_filters::LevelFilter;
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::{fmt, EnvFilter};
 use url::Url;
 
+use crate::profile::StoredAuth;
+
+mod profile;
+
+/// Most URLs the server takes in one batch request.
+const MAX_BATCH_URLS: usize = 500;
+
 #[derive(Debug, Clone, Parser)]
 #[command(version)]
 pub struct CliArgs {
+    #[arg(
+        long,
+        global = true,
+        env = "BOOKMARK_HUB_PROFILE",
+        default_value = profile::DEFAULT_PROFILE,
+        help = "Named login, one per server"
+    )]
+    pub profile: String,
+
     #[clap(subcommand)]
     pub command: InnerCommand,
 }
@@ -27,6 +48,9 @@ pub enum InnerCommand {
     #[command(about = "Login with email and password")]
     Login(LoginArgs),
 
+    #[command(about = "Forget the stored login")]
+    Logout,
+
     #[command(about = "Add a bookmark")]
     Add(AddArgs),
 
@@ -35,6 +59,21 @@ pub enum InnerCommand {
 
     #[command(about = "Import bookmarks from Firefox HTML export")]
     ImportFirefox(ImportFirefoxArgs),
+
+    #[command(about = "Search bookmarks")]
+    Search(SearchArgs),
+
+    #[command(about = "List and edit tags")]
+    Tags(TagsArgs),
+
+    #[command(about = "Print the saved content of a bookmark")]
+    Get(GetArgs),
+
+    #[command(about = "Print the saved content of the bookmark of a URL")]
+    Open(OpenArgs),
+
+    #[command(about = "Ask a question answered from your bookmarks")]
+    Ask(AskArgs),
 }
 
 #[derive(Debug, Clone, Args)]
@@ -53,12 +92,18 @@ pub struct LoginArgs {
 pub struct AddArgs {
     #[arg(long, help = "Url to bookmark")]
     pub url: Url,
+
+    #[arg(long, help = "Save the page even if robots.txt or noindex opts out")]
+    pub ignore_robots: bool,
 }
 
 #[derive(Debug, Clone, Args)]
 pub struct AddBatchArgs {
     #[arg(long, help = "File with one URL per line")]
     pub file: PathBuf,
+
+    #[arg(long, help = "Tag every bookmark with this tag, repeat to add several")]
+    pub tag: Vec<String>,
 }
 
 #[derive(Debug, Clone, Args)]
@@ -73,10 +118,109 @@ pub struct ImportFirefoxArgs {
     pub dry_run: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct StoredAuth {
-    pub base_url: Url,
-    pub response: SignInResponse,
+#[derive(Debug, Clone, Args)]
+pub struct SearchArgs {
+    #[arg(long, help = "Full text query")]
+    pub query: Option<String>,
+
+    #[arg(long, help = "Only bookmarks with this tag, repeat to require several")]
+    pub tag: Vec<String>,
+
+    #[arg(long, default_value = "20", help = "Maximum number of results")]
+    pub limit: i32,
+
+    #[arg(long, help = "Print the raw JSON response instead of a table")]
+    pub json: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct TagsArgs {
+    #[clap(subcommand)]
+    pub command: TagsCommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum TagsCommand {
+    #[command(about = "List tags with their bookmark counts")]
+    List(TagsListArgs),
+
+    #[command(about = "Add tags to a bookmark")]
+    Add(TagsEditArgs),
+
+    #[command(about = "Replace the tags of a bookmark, none clears them")]
+    Set(TagsEditArgs),
+
+    #[command(about = "Rename a tag on every bookmark")]
+    Rename(TagsRenameArgs),
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct TagsListArgs {
+    #[arg(long, help = "Print the raw JSON response instead of a table")]
+    pub json: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct TagsEditArgs {
+    #[arg(help = "Bookmark id")]
+    pub bookmark_id: String,
+
+    #[arg(help = "Tags")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct TagsRenameArgs {
+    #[arg(help = "Current tag name")]
+    pub from: String,
+
+    #[arg(help = "New tag name, merged if it already exists")]
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct GetArgs {
+    #[arg(help = "Bookmark id")]
+    pub bookmark_id: String,
+
+    #[command(flatten)]
+    pub output: ContentOutputArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct OpenArgs {
+    #[arg(help = "Bookmarked URL")]
+    pub url: Url,
+
+    #[command(flatten)]
+    pub output: ContentOutputArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ContentOutputArgs {
+    #[arg(long, help = "Print the stored HTML instead of Markdown")]
+    pub html: bool,
+
+    #[arg(long, help = "Write to this file instead of stdout")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct AskArgs {
+    #[arg(help = "Question")]
+    pub question: String,
+
+    #[arg(
+        long,
+        help = "Only use bookmarks with this tag, repeat to allow several"
+    )]
+    pub tag: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Re-rank the passages with the LLM before answering, slower but more precise"
+    )]
+    pub rerank: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -95,20 +239,24 @@ async fn main() -> anyhow::Result<()> {
         )
         .init();
     let args = CliArgs::parse();
+    profile::validate_name(&args.profile)?;
+    let profile = args.profile.as_str();
     match args.command {
-        InnerCommand::Login(args) => handle_login(args).await?,
-        InnerCommand::Add(args) => handle_add(args).await?,
-        InnerCommand::AddBatch(args) => handle_add_batch(args).await?,
-        InnerCommand::ImportFirefox(args) => handle_import_firefox(args).await?,
+        InnerCommand::Login(args) => handle_login(profile, args).await?,
+        InnerCommand::Logout => handle_logout(profile)?,
+        InnerCommand::Add(args) => handle_add(profile, args).await?,
+        InnerCommand::AddBatch(args) => handle_add_batch(profile, args).await?,
+        InnerCommand::ImportFirefox(args) => handle_import_firefox(profile, args).await?,
+        InnerCommand::Search(args) => handle_search(profile, args).await?,
+        InnerCommand::Tags(args) => handle_tags(profile, args).await?,
+        InnerCommand::Get(args) => handle_get(profile, args).await?,
+        InnerCommand::Open(args) => handle_open(profile, args).await?,
+        InnerCommand::Ask(args) => handle_ask(profile, args).await?,
     }
     Ok(())
 }
 
-async fn handle_login(args: LoginArgs) -> anyhow::Result<()> {
-    let home_dir = home::home_dir().context("Expect to have a home dir for user")?;
-    let config_path = home_dir.join(".config/bookmark-hub");
-    fs::create_dir_all(&config_path)?;
-    let config_file = config_path.join("auth.json");
+async fn handle_login(profile: &str, args: LoginArgs) -> anyhow::Result<()> {
     let auth_response = login(&args.url, &args.username, &args.password)
         .await
         .context("Failed to login")?;
@@ -116,18 +264,27 @@ async fn handle_login(args: LoginArgs) -> anyhow::Result<()> {
         base_url: args.url,
         response: auth_response,
     };
-    let json = serde_json::to_string_pretty(&stored)?;
-    fs::write(&config_file, json)?;
-    tracing::info!("Token saved at {}", config_file.display());
+    let path = profile::save(profile, &stored)?;
+    tracing::info!("Profile '{}' saved at {}", profile, path.display());
+    Ok(())
+}
+
+fn handle_logout(profile: &str) -> anyhow::Result<()> {
+    if profile::remove(profile)? {
+        tracing::info!("Profile '{}' logged out", profile);
+    } else {
+        tracing::info!("Profile '{}' was not logged in", profile);
+    }
     Ok(())
 }
 
-async fn handle_add(args: AddArgs) -> anyhow::Result<()> {
-    let (token, base_url) = load_token_and_url()?;
+async fn handle_add(profile: &str, args: AddArgs) -> anyhow::Result<()> {
+    let (token, base_url) = load_token_and_url(profile)?;
     let client = Client::new();
     let request = NewBookmarkRequest {
         url: args.url.into(),
         tags: Default::default(),
+        ignore_robots: args.ignore_robots,
     };
     let response = add_bookmark(&client, &base_url, &token, request)
         .await
@@ -136,31 +293,300 @@ async fn handle_add(args: AddArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn handle_add_batch(args: AddBatchArgs) -> anyhow::Result<()> {
-    let (token, base_url) = load_token_and_url()?;
+async fn handle_add_batch(profile: &str, args: AddBatchArgs) -> anyhow::Result<()> {
+    let (token, base_url) = load_token_and_url(profile)?;
     let client = Client::new();
     let content = fs::read_to_string(&args.file)?;
-    for (idx, line) in content.lines().enumerate() {
-        if line.trim().is_empty() {
-            continue;
-        }
-        let url: Url = line
-            .parse()
-            .with_context(|| format!("Invalid URL on line {}", idx + 1))?;
-        let request = NewBookmarkRequest {
-            url: url.clone().into(),
-            tags: Default::default(),
+    let urls = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    let mut accepted = 0;
+    for urls in urls.chunks(MAX_BATCH_URLS) {
+        let request = BatchBookmarkRequest {
+            urls: urls.to_vec(),
+            tags: Some(args.tag.clone()),
+            ignore_robots: false,
         };
-        match add_bookmark(&client, &base_url, &token, request).await {
-            Ok(response) => tracing::info!(?response, %url, "Add bookmark"),
-            Err(error) => {
-                tracing::error!(?error, %url, "Failed to add bookmark");
-                if is_auth_error(&error) {
-                    return Err(error.context("Authentication failed during batch import"));
+        let response = add_bookmarks(&client, &base_url, &token, &request)
+            .await
+            .context("Failed to add bookmarks")?;
+        for result in response.results {
+            let url = &result.url;
+            match result.status {
+                BatchBookmarkStatus::Accepted => {
+                    accepted += 1;
+                    tracing::info!(%url, task_id = ?result.task_id, "Add bookmark");
+                }
+                BatchBookmarkStatus::Duplicate => {
+                    tracing::warn!(%url, "Bookmark already saved, skipping")
+                }
+                BatchBookmarkStatus::Invalid => {
+                    tracing::error!(%url, reason = ?result.reason, "Invalid URL, skipping")
                 }
+                BatchBookmarkStatus::RateLimited => {
+                    tracing::warn!(%url, "Rate limited, add it again later")
+                }
+            }
+        }
+    }
+    tracing::info!(accepted, total = urls.len(), "Batch added");
+    Ok(())
+}
+
+async fn handle_search(profile: &str, args: SearchArgs) -> anyhow::Result<()> {
+    let (token, base_url) = load_token_and_url(profile)?;
+    let client = Client::new();
+    let request = SearchRequest {
+        query: args.query,
+        tags_filter: (!args.tag.is_empty()).then_some(TagFilter::And(args.tag)),
+        domains: Vec::new(),
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
+        limit: Some(args.limit),
+        offset: None,
+    };
+    let response = search_bookmarks(&client, &base_url, &token, &request)
+        .await
+        .context("Failed to search bookmarks")?;
+    // Printed on stdout rather than logged so the output can be piped
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&response)?);
+    } else {
+        if response.fuzzy {
+            println!("No exact match, showing similar titles and domains");
+        }
+        print!("{}", format_search_table(&response.items));
+        println!("{} of {} bookmarks", response.items.len(), response.total);
+    }
+    Ok(())
+}
+
+async fn handle_tags(profile: &str, args: TagsArgs) -> anyhow::Result<()> {
+    let (token, base_url) = load_token_and_url(profile)?;
+    let client = Client::new();
+    match args.command {
+        TagsCommand::List(args) => {
+            let endpoint = base_url.join("/api/v1/tags")?;
+            let response = client.get(endpoint).bearer_auth(&token).send().await?;
+            let response = ensure_success_response(response)
+                .await
+                .context("Failed to list tags")?
+                .json::<TagsWithCounters>()
+                .await?;
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&response)?);
+            } else {
+                print!("{}", format_tag_counts(response));
             }
         }
+        TagsCommand::Add(args) => {
+            if args.tags.is_empty() {
+                bail!("Expected at least one tag to add");
+            }
+            let bookmark = edit_tags(&client, &base_url, &token, reqwest::Method::PATCH, args)
+                .await
+                .context("Failed to add tags")?;
+            print_bookmark_tags(&bookmark);
+        }
+        TagsCommand::Set(args) => {
+            let bookmark = edit_tags(&client, &base_url, &token, reqwest::Method::POST, args)
+                .await
+                .context("Failed to set tags")?;
+            print_bookmark_tags(&bookmark);
+        }
+        TagsCommand::Rename(args) => {
+            let mut endpoint = base_url.clone();
+            endpoint
+                .path_segments_mut()
+                .map_err(|_| anyhow!("Invalid API base URL"))?
+                .clear()
+                .extend(["api", "v1", "tags", &args.from, "rename"]);
+            let request = RenameTagRequest { new_name: args.to };
+            let response = client
+                .post(endpoint)
+                .bearer_auth(&token)
+                .json(&request)
+                .send()
+                .await?;
+            let response = ensure_success_response(response)
+                .await
+                .context("Failed to rename tag")?
+                .json::<RenameTagResponse>()
+                .await?;
+            println!(
+                "Renamed '{}' to '{}' on {} bookmarks",
+                args.from, response.tag, response.bookmark_count
+            );
+        }
+    }
+    Ok(())
+}
+
+/// `PATCH` appends to the tags of the bookmark, `POST` replaces them.
+async fn edit_tags(
+    client: &Client,
+    base_url: &Url,
+    token: &str,
+    method: reqwest::Method,
+    args: TagsEditArgs,
+) -> anyhow::Result<Bookmark> {
+    let mut endpoint = base_url.clone();
+    endpoint
+        .path_segments_mut()
+        .map_err(|_| anyhow!("Invalid API base URL"))?
+        .clear()
+        .extend(["api", "v1", "bookmarks", &args.bookmark_id, "tags"]);
+    let response = client
+        .request(method, endpoint)
+        .bearer_auth(token)
+        .json(&Tags { tags: args.tags })
+        .send()
+        .await?;
+    let response = ensure_success_response(response).await?;
+    Ok(response.json::<Bookmark>().await?)
+}
+
+fn print_bookmark_tags(bookmark: &Bookmark) {
+    println!(
+        "{}: {}",
+        bookmark.bookmark_id,
+        bookmark.tags.as_deref().unwrap_or_default().join(",")
+    );
+}
+
+async fn handle_get(profile: &str, args: GetArgs) -> anyhow::Result<()> {
+    let auth = load_auth(profile)?;
+    let client = Client::new();
+    let mut endpoint = auth.base_url.clone();
+    endpoint
+        .path_segments_mut()
+        .map_err(|_| anyhow!("Invalid API base URL"))?
+        .clear()
+        .extend(["api", "v1", "bookmarks", &args.bookmark_id]);
+    let response = client
+        .get(endpoint)
+        .bearer_auth(&auth.response.access_token)
+        .send()
+        .await?;
+    let bookmark = ensure_success_response(response)
+        .await
+        .context("Failed to get bookmark")?
+        .json::<Bookmark>()
+        .await?;
+    write_content(&client, &auth, &bookmark, args.output).await
+}
+
+async fn handle_open(profile: &str, args: OpenArgs) -> anyhow::Result<()> {
+    let auth = load_auth(profile)?;
+    let client = Client::new();
+    let mut endpoint = auth.base_url.join("/api/v1/bookmarks")?;
+    endpoint
+        .query_pairs_mut()
+        .append_pair("url", args.url.as_str());
+    let response = client
+        .get(endpoint)
+        .bearer_auth(&auth.response.access_token)
+        .send()
+        .await?;
+    let bookmark = ensure_success_response(response)
+        .await
+        .context("Failed to find bookmark")?
+        .json::<Bookmarks>()
+        .await?
+        .bookmarks
+        .into_iter()
+        .next()
+        .with_context(|| format!("No bookmark saved for {}", args.url))?;
+    write_content(&client, &auth, &bookmark, args.output).await
+}
+
+async fn write_content(
+    client: &Client,
+    auth: &StoredAuth,
+    bookmark: &Bookmark,
+    args: ContentOutputArgs,
+) -> anyhow::Result<()> {
+    let user_id = auth.response.user_id.to_string();
+    let segments = if args.html {
+        vec!["static", &user_id, &bookmark.bookmark_id, "index.html"]
+    } else {
+        vec!["api", "v1", "bookmarks", &bookmark.bookmark_id, "markdown"]
+    };
+    let mut endpoint = auth.base_url.clone();
+    endpoint
+        .path_segments_mut()
+        .map_err(|_| anyhow!("Invalid API base URL"))?
+        .clear()
+        .extend(segments);
+    let response = client
+        .get(endpoint)
+        .bearer_auth(&auth.response.access_token)
+        .send()
+        .await?;
+    let content = ensure_success_response(response)
+        .await
+        .context("Failed to get bookmark content")?
+        .text()
+        .await?;
+    match args.output {
+        Some(path) => {
+            fs::write(&path, content)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            tracing::info!("Saved {} to {}", bookmark.url, path.display());
+        }
+        None => print!("{content}"),
+    }
+    Ok(())
+}
+
+async fn handle_ask(profile: &str, args: AskArgs) -> anyhow::Result<()> {
+    let (token, base_url) = load_token_and_url(profile)?;
+    let client = Client::new();
+    let request = RagQueryRequest {
+        question: args.question,
+        max_chunks: None,
+        similarity_threshold: None,
+        max_context_tokens: None,
+        hybrid_search: None,
+        tags_filter: (!args.tag.is_empty()).then_some(TagFilter::Or(args.tag)),
+        bookmark_ids: None,
+        workspace: None,
+        history: Vec::new(),
+        rerank: args.rerank,
+    };
+    let endpoint = base_url.join("/api/v1/rag/query")?;
+    let response = client
+        .post(endpoint)
+        .bearer_auth(&token)
+        .json(&request)
+        .send()
+        .await?;
+    let response = ensure_success_response(response)
+        .await
+        .context("Failed to ask question")?
+        .json::<RagQueryResponse>()
+        .await?;
+    // Plain Markdown when piped
+    if std::io::stdout().is_terminal() {
+        termimad::print_text(&response.answer);
+    } else {
+        println!("{}", response.answer);
     }
+    print!(
+        "{}",
+        format_sources(&response.relevant_chunks, &response.citations)
+    );
     Ok(())
 }
 
@@ -201,24 +627,61 @@ async fn add_bookmark(
     Ok(response)
 }
 
-fn load_token_and_url() -> anyhow::Result<(String, Url)> {
-    let config_path = home::home_dir()
-        .context("Missing home dir")?
-        .join(".config/bookmark-hub/auth.json");
-    let content = fs::read_to_string(&config_path).context("Expected auth file")?;
-    let stored: StoredAuth = serde_json::from_str(&content)?;
+async fn add_bookmarks(
+    client: &Client,
+    base_url: &Url,
+    token: &str,
+    request: &BatchBookmarkRequest,
+) -> anyhow::Result<BatchBookmarkResponse> {
+    let endpoint = base_url.join("/api/v1/bookmarks/batch")?;
+    let response = client
+        .post(endpoint)
+        .bearer_auth(token)
+        .json(request)
+        .send()
+        .await?;
+    let response = ensure_success_response(response).await?;
+    Ok(response.json::<BatchBookmarkResponse>().await?)
+}
+
+async fn search_bookmarks(
+    client: &Client,
+    base_url: &Url,
+    token: &str,
+    request: &SearchRequest,
+) -> anyhow::Result<SearchResponse> {
+    let endpoint = base_url.join("/api/v1/search")?;
+    let response = client
+        .post(endpoint)
+        .bearer_auth(token)
+        .json(request)
+        .send()
+        .await?;
+    let response = ensure_success_response(response).await?;
+    let response = response.json::<SearchResponse>().await?;
+    Ok(response)
+}
+
+fn load_token_and_url(profile: &str) -> anyhow::Result<(String, Url)> {
+    let stored = load_auth(profile)?;
+    Ok((stored.response.access_token, stored.base_url))
+}
+
+fn load_auth(profile: &str) -> anyhow::Result<StoredAuth> {
+    let stored = profile::load(profile)?;
     if let Some(expiration) = token_expiration_utc(&stored.response.access_token)? {
         if expiration <= Utc::now() {
             bail!(
-                "Stored auth token expired at {}. Run the login command again to refresh ~/.config/bookmark-hub/auth.json.",
+                "Stored auth token of profile '{}' expired at {}. Run the login command again to refresh it.",
+                profile,
                 expiration.to_rfc3339()
             );
         }
     }
-    Ok((stored.response.access_token, stored.base_url))
+    Ok(stored)
 }
 
-async fn handle_import_firefox(args: ImportFirefoxArgs) -> anyhow::Result<()> {
+async fn handle_import_firefox(profile: &str, args: ImportFirefoxArgs) -> anyhow::Result<()> {
     // Parse the HTML file
     let html_content = fs::read_to_string(&args.file)
         .with_context(|| format!("Failed to read file: {}", args.file.display()))?;
@@ -237,7 +700,7 @@ async fn handle_import_firefox(args: ImportFirefoxArgs) -> anyhow::Result<()> {
     );
 
     // Load previously imported URLs
-    let imported = load_imported_urls()?;
+    let imported = load_imported_urls(profile)?;
     let new_urls: Vec<_> = urls.into_iter().filter(|u| !imported.contains(u)).collect();
 
     let total_skipped = total_found - new_urls.len();
@@ -263,7 +726,7 @@ async fn handle_import_firefox(args: ImportFirefoxArgs) -> anyhow::Result<()> {
         return Ok(());
     }
 
-    let (token, base_url) = load_token_and_url()?;
+    let (token, base_url) = load_token_and_url(profile)?;
     let client = Client::new();
 
     let mut success_count = 0;
@@ -282,12 +745,13 @@ async fn handle_import_firefox(args: ImportFirefoxArgs) -> anyhow::Result<()> {
         let request = NewBookmarkRequest {
             url: url.clone().into(),
             tags: Default::default(),
+            ignore_robots: false,
         };
 
         match add_bookmark(&client, &base_url, &token, request).await {
             Ok(response) => {
                 tracing::info!(?response, %url, "Added bookmark");
-                save_imported_url(&url_str)?;
+                save_imported_url(profile, &url_str)?;
                 success_count += 1;
             }
             Err(error) => {
@@ -367,16 +831,17 @@ fn parse_firefox_bookmarks(html: &str, folder_name: &str) -> anyhow::Result<Vec<
     Ok(urls)
 }
 
-fn get_imported_urls_path() -> anyhow::Result<PathBuf> {
-    let config_path = home::home_dir()
-        .context("Missing home dir")?
-        .join(".config/bookmark-hub");
-    fs::create_dir_all(&config_path)?;
-    Ok(config_path.join("imported.txt"))
+/// Per profile, a URL imported into one server is still new to another.
+fn get_imported_urls_path(profile: &str) -> anyhow::Result<PathBuf> {
+    let file_name = match profile {
+        profile::DEFAULT_PROFILE => "imported.txt".to_string(),
+        profile => format!("imported-{profile}.txt"),
+    };
+    Ok(profile::config_dir()?.join(file_name))
 }
 
-fn load_imported_urls() -> anyhow::Result<HashSet<String>> {
-    let path = get_imported_urls_path()?;
+fn load_imported_urls(profile: &str) -> anyhow::Result<HashSet<String>> {
+    let path = get_imported_urls_path(profile)?;
     if !path.exists() {
         return Ok(HashSet::new());
     }
@@ -384,8 +849,8 @@ fn load_imported_urls() -> anyhow::Result<HashSet<String>> {
     Ok(content.lines().map(|s| s.to_string()).collect())
 }
 
-fn save_imported_url(url: &str) -> anyhow::Result<()> {
-    let path = get_imported_urls_path()?;
+fn save_imported_url(profile: &str, url: &str) -> anyhow::Result<()> {
+    let path = get_imported_urls_path(profile)?;
     let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
     writeln!(file, "{}", url)?;
     Ok(())
@@ -398,12 +863,18 @@ async fn ensure_success_response(response: reqwest::Response) -> anyhow::Result<
     }
 
     let body = response.text().await.unwrap_or_default();
-    let body = body.trim();
-
-    if body == "invalid_token" {
-        bail!("Authentication failed: stored auth token is invalid or expired. Run the login command again.");
+    if let Ok(problem) = serde_json::from_str::<Problem>(&body) {
+        if problem.code == ErrorCode::InvalidToken {
+            bail!("Authentication failed: stored auth token is invalid or expired. Run the login command again.");
+        }
+        return Err(anyhow!(
+            "API request failed with status {}: {}",
+            status,
+            problem_message(problem)
+        ));
     }
 
+    let body = body.trim();
     if body.is_empty() {
         bail!("API request failed with status {}", status);
     }
@@ -415,6 +886,20 @@ async fn ensure_success_response(response: reqwest::Response) -> anyhow::Result<
     ))
 }
 
+fn problem_message(problem: Problem) -> String {
+    let mut fields: Vec<String> = problem
+        .errors
+        .into_iter()
+        .map(|(field, messages)| format!("{field}: {}", messages.join(", ")))
+        .collect();
+    fields.sort();
+    match (problem.detail, fields.is_empty()) {
+        (Some(detail), _) => detail,
+        (None, false) => fields.join("; "),
+        (None, true) => problem.code.as_ref().to_string(),
+    }
+}
+
 fn token_expiration_utc(token: &str) -> anyhow::Result<Option<DateTime<Utc>>> {
     let payload = token
         .split('.')
@@ -430,6 +915,105 @@ fn token_expiration_utc(token: &str) -> anyhow::Result<Option<DateTime<Utc>>> {
     Ok(Some(expiration))
 }
 
+const SEARCH_TITLE_WIDTH: usize = 60;
+
+fn format_search_table(items: &[SearchResultItem]) -> String {
+    let header = ["SAVED", "TITLE", "TAGS", "URL"].map(String::from);
+    let rows: Vec<[String; 4]> = items
+        .iter()
+        .map(|item| {
+            let bookmark = &item.bookmark;
+            [
+                bookmark.created_at.format("%Y-%m-%d").to_string(),
+                truncate(&bookmark.title, SEARCH_TITLE_WIDTH),
+                bookmark.tags.as_deref().unwrap_or_default().join(","),
+                bookmark.url.clone(),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.clone().map(|cell| cell.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// The sources cited by the answer under their markers' numbers, or when it
+/// cites none each bookmark once, in the order of its best matching chunk.
+fn format_sources(chunks: &[RagChunkMatch], citations: &[RagCitation]) -> String {
+    if !citations.is_empty() {
+        let mut formatted = String::from("\nSources:\n");
+        for citation in citations {
+            if let Some(bookmark) = chunks.get(citation.number - 1).map(|chunk| &chunk.bookmark) {
+                formatted.push_str(&format!(
+                    "  [{}] {} <{}>\n",
+                    citation.number, bookmark.title, bookmark.url
+                ));
+            }
+        }
+        return formatted;
+    }
+    let mut seen = HashSet::new();
+    let sources: Vec<_> = chunks
+        .iter()
+        .map(|chunk| &chunk.bookmark)
+        .filter(|bookmark| seen.insert(&bookmark.bookmark_id))
+        .collect();
+    if sources.is_empty() {
+        return String::new();
+    }
+    let mut formatted = String::from("\nSources:\n");
+    for (idx, bookmark) in sources.iter().enumerate() {
+        formatted.push_str(&format!(
+            "  [{}] {} <{}>\n",
+            idx + 1,
+            bookmark.title,
+            bookmark.url
+        ));
+    }
+    formatted
+}
+
+/// Most used tags first, then alphabetically.
+fn format_tag_counts(mut response: TagsWithCounters) -> String {
+    response
+        .tags
+        .sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+    let width = response
+        .tags
+        .first()
+        .map_or(0, |tag| tag.count.to_string().len());
+    response
+        .tags
+        .iter()
+        .map(|tag| format!("{:>width$}  {}\n", tag.count, tag.tag))
+        .collect()
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max_chars - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
 fn is_auth_error(error: &anyhow::Error) -> bool {
     error
         .chain()
@@ -438,7 +1022,19 @@ fn is_auth_error(error: &anyhow::Error) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use super::token_expiration_utc;
+    use std::collections::HashMap;
+
+    use chrono::TimeZone;
+    use shared::{
+        Bookmark, BookmarkChunk, ErrorCode, Problem, RagChunkMatch, RagCitation, SearchResultItem,
+        TagCount, TagsWithCounters, Visibility,
+    };
+    use uuid::Uuid;
+
+    use super::{
+        format_search_table, format_sources, format_tag_counts, problem_message,
+        token_expiration_utc,
+    };
 
     #[test]
     fn extracts_jwt_expiration_timestamp() {
@@ -454,4 +1050,161 @@ mod tests {
         let token = "header.e30.signature";
         assert!(token_expiration_utc(token).unwrap().is_none());
     }
+
+    #[test]
+    fn problem_messages_prefer_detail_then_fields() {
+        let problem = Problem {
+            problem_type: "about:blank".to_string(),
+            title: "Bad Request".to_string(),
+            status: 400,
+            code: ErrorCode::UsernameTaken,
+            detail: None,
+            errors: HashMap::from([(
+                "username".to_string(),
+                vec!["username already created".to_string()],
+            )]),
+        };
+        assert_eq!(
+            problem_message(problem.clone()),
+            "username: username already created"
+        );
+        assert_eq!(
+            problem_message(Problem {
+                detail: Some("quota reached".to_string()),
+                ..problem.clone()
+            }),
+            "quota reached"
+        );
+        assert_eq!(
+            problem_message(Problem {
+                errors: HashMap::new(),
+                ..problem
+            }),
+            "username_taken"
+        );
+    }
+
+    #[test]
+    fn search_results_are_aligned_in_columns() {
+        let item = |title: &str, tags: Option<Vec<&str>>, url: &str| SearchResultItem {
+            bookmark: Bookmark {
+                bookmark_id: "id".to_string(),
+                user_id: Uuid::nil(),
+                url: url.to_string(),
+                domain: "example.com".to_string(),
+                title: title.to_string(),
+                tags: tags.map(|tags| tags.into_iter().map(String::from).collect()),
+                summary: None,
+                created_at: chrono::Utc.with_ymd_and_hms(2025, 3, 9, 12, 0, 0).unwrap(),
+                updated_at: None,
+                word_count: 0,
+                reading_time_minutes: 0,
+                kind: None,
+                favorite: false,
+                original_title: None,
+                visibility: Visibility::Private,
+            },
+            snippets: vec![],
+            title_highlight: None,
+            summary_highlight: None,
+        };
+        let items = [
+            item(
+                "Rust",
+                Some(vec!["lang", "systems"]),
+                "https://rust-lang.org",
+            ),
+            item(&"x".repeat(70), None, "https://example.com"),
+        ];
+        let table = format_search_table(&items);
+        let lines: Vec<_> = table.lines().collect();
+        let title = format!("{}…", "x".repeat(59));
+        assert_eq!(
+            lines,
+            [
+                format!("SAVED       TITLE{}  TAGS          URL", " ".repeat(55)),
+                format!(
+                    "2025-03-09  Rust{}  lang,systems  https://rust-lang.org",
+                    " ".repeat(56)
+                ),
+                format!("2025-03-09  {title}                https://example.com"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tag_counts_are_listed_most_used_first() {
+        let tag = |tag: &str, count| TagCount {
+            tag: tag.to_string(),
+            count,
+        };
+        let response = TagsWithCounters {
+            tags: vec![tag("web", 3), tag("rust", 12), tag("async", 3)],
+        };
+        assert_eq!(
+            format_tag_counts(response),
+            "12  rust\n 3  async\n 3  web\n"
+        );
+    }
+
+    #[test]
+    fn sources_list_each_bookmark_once() {
+        let chunk = |bookmark_id: &str, title: &str| {
+            let url = format!("https://example.com/{bookmark_id}");
+            RagChunkMatch {
+                chunk: BookmarkChunk {
+                    chunk_id: Uuid::new_v4(),
+                    bookmark_id: bookmark_id.to_string(),
+                    user_id: Uuid::nil(),
+                    chunk_text: "text".to_string(),
+                    chunk_index: 0,
+                    created_at: chrono::Utc::now(),
+                    updated_at: None,
+                },
+                bookmark: Bookmark {
+                    bookmark_id: bookmark_id.to_string(),
+                    user_id: Uuid::nil(),
+                    url,
+                    domain: "example.com".to_string(),
+                    title: title.to_string(),
+                    tags: None,
+                    summary: None,
+                    created_at: chrono::Utc::now(),
+                    updated_at: None,
+                    word_count: 0,
+                    reading_time_minutes: 0,
+                    kind: None,
+                    favorite: false,
+                    original_title: None,
+                    visibility: Visibility::Private,
+                },
+                similarity_score: 0.9,
+                relevance_explanation: None,
+                vector_score: None,
+                fts_score: None,
+                combined_score: None,
+                rerank_score: None,
+            }
+        };
+        let chunks = [
+            chunk("a", "First"),
+            chunk("b", "Second"),
+            chunk("a", "First"),
+        ];
+        assert_eq!(
+            format_sources(&chunks, &[]),
+            "\nSources:\n  [1] First <https://example.com/a>\n  [2] Second <https://example.com/b>\n"
+        );
+        assert_eq!(format_sources(&[], &[]), "");
+
+        let citations = [2, 3].map(|number| RagCitation {
+            number,
+            bookmark_id: chunks[number - 1].bookmark.bookmark_id.clone(),
+            chunk_id: chunks[number - 1].chunk.chunk_id,
+        });
+        assert_eq!(
+            format_sources(&chunks, &citations),
+            "\nSources:\n  [2] Second <https://example.com/b>\n  [3] First <https://example.com/a>\n"
+        );
+    }
 }
@@ -0,0 +1,197 @@
+//! Credentials of each server the CLI talks to, one named profile per server.
+//! The token is kept in the OS keyring when there is one, the rest goes to
+//! `~/.config/bookmark-hub/profiles/<profile>.json`, readable only by the user.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use shared::SignInResponse;
+use url::Url;
+use uuid::Uuid;
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+const KEYRING_SERVICE: &str = "bookmark-hub";
+
+/// Written by versions without profiles, the token in clear. Still read as the
+/// default profile until the next login replaces it.
+const LEGACY_AUTH_FILE: &str = "auth.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StoredAuth {
+    pub base_url: Url,
+    pub response: SignInResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ProfileFile {
+    base_url: Url,
+    user_id: Uuid,
+    username: String,
+    token_type: String,
+    /// Absent when the OS keyring holds it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    access_token: Option<String>,
+}
+
+pub fn config_dir() -> anyhow::Result<PathBuf> {
+    let config_path = home::home_dir()
+        .context("Missing home dir")?
+        .join(".config/bookmark-hub");
+    fs::create_dir_all(&config_path)?;
+    Ok(config_path)
+}
+
+fn profile_path(profile: &str) -> anyhow::Result<PathBuf> {
+    validate_name(profile)?;
+    let profiles = config_dir()?.join("profiles");
+    fs::create_dir_all(&profiles)?;
+    Ok(profiles.join(format!("{profile}.json")))
+}
+
+/// Names end up in file names.
+pub fn validate_name(profile: &str) -> anyhow::Result<()> {
+    let valid = !profile.is_empty()
+        && profile
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        bail!("Invalid profile name '{profile}', use letters, digits, '-' and '_'");
+    }
+    Ok(())
+}
+
+fn keyring_entry(profile: &str) -> keyring::Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, profile)
+}
+
+/// Returns where the profile was saved.
+pub fn save(profile: &str, auth: &StoredAuth) -> anyhow::Result<PathBuf> {
+    let path = profile_path(profile)?;
+    let in_keyring = keyring_entry(profile)
+        .and_then(|entry| entry.set_password(&auth.response.access_token))
+        .inspect_err(|error| {
+            tracing::warn!(
+                %error,
+                "OS keyring unavailable, storing the token in {}",
+                path.display()
+            )
+        })
+        .is_ok();
+    let file = ProfileFile {
+        base_url: auth.base_url.clone(),
+        user_id: auth.response.user_id,
+        username: auth.response.username.clone(),
+        token_type: auth.response.token_type.clone(),
+        access_token: (!in_keyring).then(|| auth.response.access_token.clone()),
+    };
+    write_private(&path, &serde_json::to_string_pretty(&file)?)?;
+    if profile == DEFAULT_PROFILE {
+        remove_if_exists(&config_dir()?.join(LEGACY_AUTH_FILE))?;
+    }
+    Ok(path)
+}
+
+pub fn load(profile: &str) -> anyhow::Result<StoredAuth> {
+    let path = profile_path(profile)?;
+    if !path.exists() {
+        let legacy_path = config_dir()?.join(LEGACY_AUTH_FILE);
+        if profile == DEFAULT_PROFILE && legacy_path.exists() {
+            let content = fs::read_to_string(&legacy_path)?;
+            return Ok(serde_json::from_str(&content)?);
+        }
+        bail!("Not logged in with profile '{profile}'. Run the login command first.");
+    }
+    let content = fs::read_to_string(&path)?;
+    let file: ProfileFile = serde_json::from_str(&content)?;
+    let access_token = match file.access_token {
+        Some(access_token) => access_token,
+        None => keyring_entry(profile)
+            .and_then(|entry| entry.get_password())
+            .with_context(|| {
+                format!("Failed to read the token of profile '{profile}' from the OS keyring")
+            })?,
+    };
+    Ok(StoredAuth {
+        base_url: file.base_url,
+        response: SignInResponse {
+            user_id: file.user_id,
+            username: file.username,
+            access_token,
+            token_type: file.token_type,
+        },
+    })
+}
+
+/// Returns whether there was anything to remove.
+pub fn remove(profile: &str) -> anyhow::Result<bool> {
+    let mut removed = remove_if_exists(&profile_path(profile)?)?;
+    match keyring_entry(profile).and_then(|entry| entry.delete_credential()) {
+        Ok(()) => removed = true,
+        Err(keyring::Error::NoEntry) => {}
+        Err(error) => tracing::warn!(%error, "Failed to remove the token from the OS keyring"),
+    }
+    if profile == DEFAULT_PROFILE {
+        removed |= remove_if_exists(&config_dir()?.join(LEGACY_AUTH_FILE))?;
+    }
+    Ok(removed)
+}
+
+fn remove_if_exists(path: &Path) -> anyhow::Result<bool> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Creates or replaces `path` with permissions for the owner only.
+fn write_private(path: &Path, content: &str) -> anyhow::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    // The mode only applies to new files
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(content.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_name, write_private};
+
+    #[test]
+    fn profile_names_are_safe_file_names() {
+        assert!(validate_name("work").is_ok());
+        assert!(validate_name("home-lab_2").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("../auth").is_err());
+        assert!(validate_name("a/b").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_files_are_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("bookmark-hub-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "{}").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        write_private(&path, "secret").unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
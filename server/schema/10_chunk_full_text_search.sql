@@ -0,0 +1,22 @@
+-- Chunk-level full-text search so hybrid RAG retrieval can surface keyword
+-- matches that vector similarity misses (trigger for parity with bookmark).
+CREATE OR REPLACE FUNCTION update_bookmark_chunk_search_tokens()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.search_tokens := to_tsvector('english', coalesce(NEW.chunk_text, ''));
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE bookmark_chunk ADD COLUMN IF NOT EXISTS search_tokens TSVECTOR;
+
+UPDATE bookmark_chunk SET search_tokens = to_tsvector('english', coalesce(chunk_text, ''));
+
+CREATE TRIGGER update_bookmark_chunk_search_tokens_trigger
+    BEFORE INSERT OR UPDATE OF chunk_text ON bookmark_chunk
+    FOR EACH ROW EXECUTE FUNCTION update_bookmark_chunk_search_tokens();
+
+CREATE INDEX IF NOT EXISTS idx_bookmark_chunk_search_tokens
+    ON bookmark_chunk USING GIN (search_tokens);
+
+INSERT INTO schema_version (version) VALUES (10);
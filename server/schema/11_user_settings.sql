@@ -0,0 +1,15 @@
+-- Per-user AI preferences consulted by the text AI daemon. A missing row means
+-- every feature is enabled with the server defaults.
+CREATE TABLE IF NOT EXISTS user_settings (
+    user_id UUID NOT NULL,
+    auto_tagging_enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    summaries_enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    summary_language TEXT,
+    preferred_text_model TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id),
+    CONSTRAINT fk_user FOREIGN KEY(user_id) REFERENCES "user"(user_id) ON DELETE CASCADE
+);
+
+INSERT INTO schema_version (version) VALUES (11);
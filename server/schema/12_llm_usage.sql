@@ -0,0 +1,23 @@
+-- One row per LLM request attempt, written by the server after each call.
+CREATE TABLE IF NOT EXISTS llm_usage (
+    usage_id UUID DEFAULT uuid_generate_v4(),
+    user_id UUID,
+    bookmark_id VARCHAR(512),
+    session_id UUID,
+    purpose TEXT NOT NULL,
+    request_kind TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    input_tokens BIGINT,
+    output_tokens BIGINT,
+    duration_ms BIGINT NOT NULL,
+    success BOOLEAN NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (usage_id),
+    CONSTRAINT fk_user FOREIGN KEY(user_id) REFERENCES "user"(user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_llm_usage_user_created_at
+    ON llm_usage (user_id, created_at);
+
+INSERT INTO schema_version (version) VALUES (12);
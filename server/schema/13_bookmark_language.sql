@@ -0,0 +1,29 @@
+-- Per-bookmark language so full-text search can stem with the matching
+-- Postgres text search config instead of always using 'english'.
+ALTER TABLE bookmark ADD COLUMN IF NOT EXISTS language TEXT;
+ALTER TABLE bookmark ADD COLUMN IF NOT EXISTS text_search_config TEXT NOT NULL DEFAULT 'english';
+
+CREATE OR REPLACE FUNCTION update_bookmark_search_tokens()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.search_tokens := setweight(to_tsvector(NEW.text_search_config::regconfig, coalesce(NEW.title, '')), 'A') ||
+                        setweight(to_tsvector(NEW.text_search_config::regconfig, coalesce(NEW.text_content, '')), 'B') ||
+                        setweight(to_tsvector(NEW.text_search_config::regconfig, coalesce(array_to_string(NEW.tags, ' '), '')), 'C');
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION update_bookmark_chunk_search_tokens()
+RETURNS TRIGGER AS $$
+DECLARE
+    config TEXT;
+BEGIN
+    SELECT b.text_search_config INTO config
+    FROM bookmark b
+    WHERE b.bookmark_id = NEW.bookmark_id AND b.user_id = NEW.user_id;
+    NEW.search_tokens := to_tsvector(coalesce(config, 'english')::regconfig, coalesce(NEW.chunk_text, ''));
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+INSERT INTO schema_version (version) VALUES (13);
@@ -0,0 +1,15 @@
+-- Bookmark-level embedding (mean of the chunk embeddings) used to find
+-- related bookmarks without comparing every chunk pair.
+ALTER TABLE bookmark ADD COLUMN IF NOT EXISTS embedding vector;
+
+UPDATE bookmark b
+SET embedding = c.embedding
+FROM (
+    SELECT bookmark_id, user_id, AVG(embedding) AS embedding
+    FROM bookmark_chunk
+    WHERE embedding IS NOT NULL
+    GROUP BY bookmark_id, user_id
+) c
+WHERE b.bookmark_id = c.bookmark_id AND b.user_id = c.user_id;
+
+INSERT INTO schema_version (version) VALUES (14);
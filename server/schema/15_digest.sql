@@ -0,0 +1,22 @@
+-- Weekly LLM-written digests of the bookmarks a user saved in the period.
+CREATE TABLE IF NOT EXISTS digest (
+    digest_id UUID DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL,
+    period_start TIMESTAMPTZ NOT NULL,
+    period_end TIMESTAMPTZ NOT NULL,
+    bookmark_ids TEXT[] NOT NULL,
+    content TEXT NOT NULL,
+    emailed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (digest_id),
+    CONSTRAINT fk_user FOREIGN KEY(user_id) REFERENCES "user"(user_id) ON DELETE CASCADE,
+    CONSTRAINT digest_user_period_unique UNIQUE (user_id, period_start)
+);
+
+CREATE INDEX IF NOT EXISTS idx_digest_user_period_start
+    ON digest (user_id, period_start DESC);
+
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS digest_enabled BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS digest_email TEXT;
+
+INSERT INTO schema_version (version) VALUES (15);
@@ -0,0 +1,18 @@
+-- Optional, verifiable email address per user plus single-use tokens for
+-- email verification and password reset links.
+ALTER TABLE "user" ADD COLUMN IF NOT EXISTS email TEXT;
+ALTER TABLE "user" ADD COLUMN IF NOT EXISTS email_verified_at TIMESTAMPTZ;
+CREATE UNIQUE INDEX IF NOT EXISTS user_email_unique ON "user" (LOWER(email));
+
+CREATE TABLE IF NOT EXISTS user_token (
+    token_hash TEXT NOT NULL,
+    user_id UUID NOT NULL,
+    purpose TEXT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (token_hash),
+    CONSTRAINT fk_user FOREIGN KEY(user_id) REFERENCES "user"(user_id) ON DELETE CASCADE
+);
+
+INSERT INTO schema_version (version) VALUES (16);
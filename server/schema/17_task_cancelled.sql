@@ -0,0 +1,4 @@
+-- Pending bookmark tasks can be cancelled by their owner.
+ALTER TYPE task_status ADD VALUE IF NOT EXISTS 'cancelled';
+
+INSERT INTO schema_version (version) VALUES (17);
@@ -0,0 +1,5 @@
+-- Lets a user save a page even when robots.txt or a noindex meta tag opts
+-- out of crawlers.
+ALTER TABLE bookmark_task ADD COLUMN IF NOT EXISTS ignore_robots BOOL NOT NULL DEFAULT FALSE;
+
+INSERT INTO schema_version (version) VALUES (18);
@@ -0,0 +1,20 @@
+-- Timestamped processing stages per bookmark task, and the bookmark a task
+-- produced so the AI daemons can add their stages to the same timeline.
+ALTER TABLE bookmark_task ADD COLUMN IF NOT EXISTS bookmark_id TEXT;
+CREATE INDEX IF NOT EXISTS idx_bookmark_task_bookmark
+    ON bookmark_task (user_id, bookmark_id) WHERE bookmark_id IS NOT NULL;
+
+CREATE TABLE IF NOT EXISTS task_event (
+    event_id UUID DEFAULT uuid_generate_v4(),
+    task_id UUID NOT NULL,
+    stage TEXT NOT NULL,
+    detail TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT clock_timestamp(),
+    PRIMARY KEY (event_id),
+    CONSTRAINT fk_task FOREIGN KEY(task_id) REFERENCES bookmark_task(task_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_event_task_created_at
+    ON task_event (task_id, created_at);
+
+INSERT INTO schema_version (version) VALUES (19);
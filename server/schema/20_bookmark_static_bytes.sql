@@ -0,0 +1,5 @@
+-- Bytes written to the data dir for each bookmark (compressed HTML and
+-- images), summed with the text content for the per-user storage quota.
+ALTER TABLE bookmark ADD COLUMN IF NOT EXISTS static_bytes BIGINT NOT NULL DEFAULT 0;
+
+INSERT INTO schema_version (version) VALUES (20);
@@ -0,0 +1,10 @@
+-- Word count of the extracted text and the reading time it takes at 238
+-- words per minute, rounded up, for filtering searches by reading time.
+ALTER TABLE bookmark ADD COLUMN IF NOT EXISTS word_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE bookmark ADD COLUMN IF NOT EXISTS reading_time_minutes INTEGER NOT NULL DEFAULT 0;
+
+UPDATE bookmark
+SET word_count = coalesce(array_length(regexp_split_to_array(btrim(text_content, E' \t\r\n'), '\s+'), 1), 0)
+WHERE btrim(text_content, E' \t\r\n') <> '';
+
+UPDATE bookmark SET reading_time_minutes = ceil(word_count / 238.0)::INTEGER;
@@ -0,0 +1,5 @@
+-- Coarse type of content assigned by the AI pipeline along with tags, NULL
+-- until it runs. Kept apart from the tags so it can be filtered on.
+ALTER TABLE bookmark ADD COLUMN IF NOT EXISTS kind TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_bookmark_user_kind ON bookmark (user_id, kind);
@@ -0,0 +1,17 @@
+-- People, projects and organizations the AI pipeline found mentioned in each
+-- bookmark, to browse them and to find articles mentioning one even when it
+-- isn't tagged. Each analyzed slice keeps its own so unchanged text isn't
+-- sent to the model again.
+CREATE TABLE IF NOT EXISTS entity (
+    bookmark_id VARCHAR(512) NOT NULL,
+    user_id UUID NOT NULL,
+    kind TEXT NOT NULL,
+    name TEXT NOT NULL,
+    PRIMARY KEY (bookmark_id, user_id, kind, name),
+    CONSTRAINT fk_entity_bookmark FOREIGN KEY (bookmark_id, user_id)
+        REFERENCES bookmark(bookmark_id, user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_entity_user_name ON entity (user_id, lower(name));
+
+ALTER TABLE bookmark_ai_chunk ADD COLUMN IF NOT EXISTS entities JSONB NOT NULL DEFAULT '[]';
@@ -0,0 +1,11 @@
+-- Canonical URLs each bookmarked article links to, with their domain, so
+-- bookmarks citing one another can be linked in the library graph.
+CREATE TABLE IF NOT EXISTS bookmark_link (
+    bookmark_id VARCHAR(512) NOT NULL,
+    user_id UUID NOT NULL,
+    url TEXT NOT NULL,
+    domain TEXT NOT NULL,
+    PRIMARY KEY (bookmark_id, user_id, url),
+    CONSTRAINT fk_bookmark_link_bookmark FOREIGN KEY (bookmark_id, user_id)
+        REFERENCES bookmark(bookmark_id, user_id) ON DELETE CASCADE
+);
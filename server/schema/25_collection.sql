@@ -0,0 +1,30 @@
+-- Named, ordered reading lists a user curates besides tags, which can be
+-- published through a share link.
+CREATE TABLE IF NOT EXISTS collection (
+    collection_id UUID DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ,
+    PRIMARY KEY (collection_id),
+    CONSTRAINT fk_user FOREIGN KEY(user_id) REFERENCES "user"(user_id) ON DELETE CASCADE,
+    CONSTRAINT collection_id_user_unique UNIQUE (collection_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_collection_user ON collection (user_id);
+
+CREATE TABLE IF NOT EXISTS collection_bookmark (
+    collection_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    bookmark_id VARCHAR(512) NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (collection_id, bookmark_id),
+    CONSTRAINT fk_collection_bookmark_collection FOREIGN KEY (collection_id, user_id)
+        REFERENCES collection(collection_id, user_id) ON DELETE CASCADE,
+    CONSTRAINT fk_collection_bookmark_bookmark FOREIGN KEY (bookmark_id, user_id)
+        REFERENCES bookmark(bookmark_id, user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_collection_bookmark_bookmark
+    ON collection_bookmark (bookmark_id, user_id);
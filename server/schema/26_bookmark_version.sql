@@ -0,0 +1,24 @@
+-- Earlier captures of a bookmark, kept when it is fetched again instead of
+-- being overwritten. captured_at of the bookmark is when its current capture
+-- was taken, NULL until its first refresh meaning created_at.
+CREATE TABLE IF NOT EXISTS bookmark_version (
+    version_id UUID DEFAULT uuid_generate_v4(),
+    bookmark_id VARCHAR(512) NOT NULL,
+    user_id UUID NOT NULL,
+    title TEXT NOT NULL,
+    text_content TEXT NOT NULL,
+    html TEXT,
+    word_count INTEGER NOT NULL,
+    captured_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (version_id),
+    CONSTRAINT fk_bookmark_version_bookmark FOREIGN KEY (bookmark_id, user_id)
+        REFERENCES bookmark(bookmark_id, user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_bookmark_version_bookmark
+    ON bookmark_version (bookmark_id, user_id, captured_at DESC);
+
+ALTER TABLE bookmark ADD COLUMN IF NOT EXISTS captured_at TIMESTAMPTZ;
+
+ALTER TABLE bookmark_task ADD COLUMN IF NOT EXISTS refresh BOOL NOT NULL DEFAULT FALSE;
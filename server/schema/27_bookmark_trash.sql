@@ -0,0 +1,6 @@
+-- Deleted bookmarks stay in the trash, hidden from everything but the trash
+-- listing, until the purge daemon removes them for good.
+ALTER TABLE bookmark ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_bookmark_deleted_at
+    ON bookmark (deleted_at) WHERE deleted_at IS NOT NULL;
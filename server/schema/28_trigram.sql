@@ -0,0 +1,10 @@
+-- Trigram indexes behind the fuzzy fallback of the search, which looks for
+-- titles and domains close to the query when the full-text search finds
+-- nothing.
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX IF NOT EXISTS idx_bookmark_title_trgm
+    ON bookmark USING GIN (title gin_trgm_ops);
+
+CREATE INDEX IF NOT EXISTS idx_bookmark_domain_trgm
+    ON bookmark USING GIN (domain gin_trgm_ops);
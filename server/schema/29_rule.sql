@@ -0,0 +1,19 @@
+-- Rules a user defines to act on new bookmarks before the AI pipeline gets
+-- to them: tagging, marking as favorite or leaving them out of AI
+-- processing, for the URLs, domains and titles they match.
+ALTER TABLE bookmark ADD COLUMN IF NOT EXISTS favorite BOOLEAN NOT NULL DEFAULT false;
+
+CREATE TABLE IF NOT EXISTS rule (
+    rule_id UUID DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL,
+    name TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    conditions JSONB NOT NULL,
+    actions JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ,
+    PRIMARY KEY (rule_id),
+    CONSTRAINT fk_user FOREIGN KEY(user_id) REFERENCES "user"(user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_rule_user ON rule (user_id);
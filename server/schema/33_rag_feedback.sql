@@ -0,0 +1,4 @@
+-- Rating the user gave a RAG answer, with an optional comment.
+ALTER TABLE rag_session ADD COLUMN IF NOT EXISTS feedback_rating TEXT;
+ALTER TABLE rag_session ADD COLUMN IF NOT EXISTS feedback_comment TEXT;
+ALTER TABLE rag_session ADD COLUMN IF NOT EXISTS feedback_at TIMESTAMPTZ;
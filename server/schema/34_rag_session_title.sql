@@ -0,0 +1,9 @@
+-- Short title generated for each RAG session, and the full-text search of the
+-- history over titles, questions and answers.
+ALTER TABLE rag_session ADD COLUMN IF NOT EXISTS title TEXT;
+ALTER TABLE rag_session ADD COLUMN IF NOT EXISTS search_tokens TSVECTOR
+    GENERATED ALWAYS AS (
+        to_tsvector('simple'::regconfig,
+            coalesce(title, '') || ' ' || question || ' ' || coalesce(answer, ''))
+    ) STORED;
+CREATE INDEX IF NOT EXISTS idx_rag_session_search_tokens ON rag_session USING GIN (search_tokens);
@@ -0,0 +1,16 @@
+-- CSS selectors a user sets for the sites whose articles extract badly:
+-- elements to keep or drop before extraction and where the title is.
+CREATE TABLE IF NOT EXISTS extraction_rule (
+    rule_id UUID DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL,
+    domain TEXT NOT NULL,
+    keep_selectors TEXT[] NOT NULL DEFAULT '{}',
+    drop_selectors TEXT[] NOT NULL DEFAULT '{}',
+    title_selector TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ,
+    PRIMARY KEY (rule_id),
+    CONSTRAINT fk_user FOREIGN KEY(user_id) REFERENCES "user"(user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_extraction_rule_user ON extraction_rule (user_id);
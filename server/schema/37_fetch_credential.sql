@@ -0,0 +1,10 @@
+-- Request headers a user attaches when fetching the pages of a domain, such
+-- as a session cookie. They are encrypted by the server before being stored.
+CREATE TABLE IF NOT EXISTS fetch_credential (
+    user_id UUID NOT NULL,
+    domain TEXT NOT NULL,
+    sealed_headers BYTEA NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, domain),
+    CONSTRAINT fk_user FOREIGN KEY(user_id) REFERENCES "user"(user_id) ON DELETE CASCADE
+);
@@ -0,0 +1,25 @@
+-- Sign-ins, credential changes, deletions and share links of a user, for
+-- them to review. Rows are never updated, they go with their user only.
+CREATE TABLE IF NOT EXISTS audit_log (
+    event_id UUID DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL,
+    action TEXT NOT NULL,
+    target TEXT,
+    ip TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (event_id),
+    CONSTRAINT fk_user FOREIGN KEY(user_id) REFERENCES "user"(user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_user_created ON audit_log (user_id, created_at DESC);
+
+CREATE OR REPLACE FUNCTION reject_audit_log_update()
+RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS audit_log_append_only ON audit_log;
+CREATE TRIGGER audit_log_append_only BEFORE UPDATE ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION reject_audit_log_update();
@@ -0,0 +1,15 @@
+-- Signed-in devices. An access token names its session, deleting the row
+-- revokes the token before it expires.
+CREATE TABLE IF NOT EXISTS user_session (
+    session_id UUID DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL,
+    device_label TEXT,
+    ip TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (session_id),
+    CONSTRAINT fk_user FOREIGN KEY(user_id) REFERENCES "user"(user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_session_user ON user_session (user_id);
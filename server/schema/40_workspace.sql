@@ -0,0 +1,38 @@
+-- Shared libraries. Members see the bookmarks saved into the workspace,
+-- which stay owned by the member who saved them.
+CREATE TABLE IF NOT EXISTS workspace (
+    workspace_id UUID DEFAULT uuid_generate_v4(),
+    owner_id UUID NOT NULL,
+    name TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (workspace_id),
+    CONSTRAINT fk_owner FOREIGN KEY(owner_id) REFERENCES "user"(user_id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS workspace_member (
+    workspace_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    role TEXT NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+    joined_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (workspace_id, user_id),
+    CONSTRAINT fk_workspace FOREIGN KEY(workspace_id)
+        REFERENCES workspace(workspace_id) ON DELETE CASCADE,
+    CONSTRAINT fk_user FOREIGN KEY(user_id) REFERENCES "user"(user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_workspace_member_user ON workspace_member (user_id);
+
+CREATE TABLE IF NOT EXISTS workspace_bookmark (
+    workspace_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    bookmark_id VARCHAR(512) NOT NULL,
+    added_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (workspace_id, bookmark_id, user_id),
+    CONSTRAINT fk_workspace FOREIGN KEY(workspace_id)
+        REFERENCES workspace(workspace_id) ON DELETE CASCADE,
+    CONSTRAINT fk_workspace_bookmark_bookmark FOREIGN KEY (bookmark_id, user_id)
+        REFERENCES bookmark(bookmark_id, user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_workspace_bookmark_bookmark
+    ON workspace_bookmark (bookmark_id, user_id);
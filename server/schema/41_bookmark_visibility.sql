@@ -0,0 +1,3 @@
+-- Who may see a bookmark besides its owner, nobody unless they widen it.
+ALTER TABLE bookmark ADD COLUMN IF NOT EXISTS visibility TEXT NOT NULL DEFAULT 'private'
+    CHECK (visibility IN ('private', 'workspace', 'public'));
@@ -0,0 +1,9 @@
+-- Last run of each periodic job, so a restart doesn't rerun a job that
+-- already ran on schedule and instances sharing the database run it once.
+CREATE TABLE IF NOT EXISTS scheduled_job (
+    name TEXT NOT NULL,
+    last_started_at TIMESTAMPTZ NOT NULL,
+    last_finished_at TIMESTAMPTZ,
+    last_error TEXT,
+    PRIMARY KEY (name)
+);
@@ -0,0 +1,5 @@
+-- Lease held by the server instance processing a pending task, so replicas
+-- sharing the database never run a task twice. An expired lease, such as one
+-- of a replica that died, is taken over by the next instance polling.
+ALTER TABLE bookmark_task ADD COLUMN IF NOT EXISTS lease_owner TEXT;
+ALTER TABLE bookmark_task ADD COLUMN IF NOT EXISTS lease_expires_at TIMESTAMPTZ;
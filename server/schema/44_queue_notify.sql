@@ -0,0 +1,27 @@
+-- Wakes the daemons of every replica listening when work is queued, whether
+-- through another replica or direct SQL. Notifications sent in the same
+-- transaction collapse into one.
+CREATE OR REPLACE FUNCTION notify_bookmark_task_queued()
+RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify('bookmark_task_queued', '');
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION notify_bookmark_saved()
+RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify('bookmark_saved', '');
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER notify_bookmark_task_queued_trigger
+    AFTER INSERT OR UPDATE OF status ON bookmark_task
+    FOR EACH ROW WHEN (NEW.status = 'pending' AND NEW.next_delivery <= now())
+    EXECUTE FUNCTION notify_bookmark_task_queued();
+
+CREATE TRIGGER notify_bookmark_saved_trigger
+    AFTER INSERT ON bookmark
+    FOR EACH STATEMENT EXECUTE FUNCTION notify_bookmark_saved();
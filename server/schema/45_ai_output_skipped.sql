@@ -0,0 +1,3 @@
+-- Summaries and tags left out because the user turned the feature off, queued
+-- again once it is turned back on.
+ALTER TYPE task_status ADD VALUE IF NOT EXISTS 'skipped';
@@ -0,0 +1,17 @@
+-- Search tokens written before bookmarks had their own text search config
+-- were stemmed in english, recomputed with the config of their bookmark.
+UPDATE bookmark
+SET search_tokens = setweight(to_tsvector(text_search_config::regconfig, coalesce(title, '')), 'A') ||
+                    setweight(to_tsvector(text_search_config::regconfig, coalesce(text_content, '')), 'B') ||
+                    setweight(to_tsvector(text_search_config::regconfig, coalesce(array_to_string(tags, ' '), '')), 'C')
+WHERE text_search_config <> 'english';
+
+UPDATE bookmark_chunk c
+SET search_tokens = to_tsvector(b.text_search_config::regconfig, coalesce(c.chunk_text, ''))
+FROM bookmark b
+WHERE b.bookmark_id = c.bookmark_id AND b.user_id = c.user_id
+  AND b.text_search_config <> 'english';
+
+-- Searches first list the configs of the bookmarks they go over
+CREATE INDEX IF NOT EXISTS idx_bookmark_user_text_search_config
+    ON bookmark (user_id, text_search_config);
@@ -0,0 +1,15 @@
+-- Digests are emailed to the verified address of the account only, so an
+-- address typed into the settings can't make the server mail strangers.
+ALTER TABLE user_settings DROP COLUMN IF EXISTS digest_email;
+
+-- Secret in the URL of the digest feed, for feed readers that can't send a
+-- bearer token. Only its hash is stored, issuing a new one revokes the old.
+CREATE TABLE IF NOT EXISTS digest_feed_token (
+    user_id UUID NOT NULL,
+    token_hash TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id),
+    CONSTRAINT fk_user FOREIGN KEY(user_id) REFERENCES "user"(user_id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_digest_feed_token_hash ON digest_feed_token (token_hash);
@@ -0,0 +1,20 @@
+-- Cached embeddings belong to the user whose chunks were embedded, so they
+-- go away with the account, and record when they were last reused so the
+-- retention job can drop stale ones. The cache only saves provider calls,
+-- the old shared entries are dropped instead of being assigned an owner.
+DROP TABLE embedding_cache;
+
+CREATE TABLE embedding_cache (
+    user_id UUID NOT NULL,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    dimensions INTEGER NOT NULL,
+    content_hash TEXT NOT NULL,
+    embedding vector NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_used_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, provider, model, dimensions, content_hash),
+    CONSTRAINT fk_user FOREIGN KEY(user_id) REFERENCES "user"(user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_embedding_cache_last_used_at ON embedding_cache (last_used_at);
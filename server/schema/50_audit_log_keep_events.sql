@@ -0,0 +1,38 @@
+-- Events outlive their user: deleting an account clears `user_id` and keeps
+-- the username, and failed sign-ins for usernames without an account are
+-- recorded with a username only. Rows are neither changed nor deleted.
+ALTER TABLE audit_log ADD COLUMN IF NOT EXISTS username TEXT;
+
+ALTER TABLE audit_log DISABLE TRIGGER audit_log_append_only;
+UPDATE audit_log SET username = u.username
+FROM "user" u
+WHERE u.user_id = audit_log.user_id AND audit_log.username IS NULL;
+ALTER TABLE audit_log ENABLE TRIGGER audit_log_append_only;
+
+ALTER TABLE audit_log ALTER COLUMN user_id DROP NOT NULL;
+ALTER TABLE audit_log DROP CONSTRAINT IF EXISTS fk_user;
+ALTER TABLE audit_log ADD CONSTRAINT fk_user
+    FOREIGN KEY(user_id) REFERENCES "user"(user_id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_username_created
+    ON audit_log (username, created_at DESC);
+
+-- The only change allowed is the foreign key clearing `user_id` of the
+-- events of a deleted user, which runs from a trigger on "user".
+CREATE OR REPLACE FUNCTION reject_audit_log_update()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'UPDATE' AND pg_trigger_depth() > 1 AND NEW.user_id IS NULL
+        AND (NEW.event_id, NEW.username, NEW.action, NEW.target, NEW.ip, NEW.created_at)
+            IS NOT DISTINCT FROM
+            (OLD.event_id, OLD.username, OLD.action, OLD.target, OLD.ip, OLD.created_at)
+    THEN
+        RETURN NEW;
+    END IF;
+    RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS audit_log_append_only ON audit_log;
+CREATE TRIGGER audit_log_append_only BEFORE UPDATE OR DELETE ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION reject_audit_log_update();
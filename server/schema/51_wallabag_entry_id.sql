@@ -0,0 +1,13 @@
+-- The integer id a bookmark has in the wallabag-compatible API, the first 31
+-- bits of the SHA-256 of its id, indexed so an entry is found without
+-- loading every bookmark of the user. Must match `wallabag_entry_id` in Rust.
+CREATE OR REPLACE FUNCTION wallabag_entry_id(bookmark_id TEXT)
+RETURNS BIGINT
+LANGUAGE SQL IMMUTABLE STRICT PARALLEL SAFE
+AS $$
+    SELECT ('x' || encode(substring(sha256(convert_to(bookmark_id, 'UTF8')) FROM 1 FOR 4), 'hex'))
+        ::bit(32)::bigint & 2147483647
+$$;
+
+CREATE INDEX IF NOT EXISTS idx_bookmark_wallabag_entry_id
+    ON bookmark (user_id, wallabag_entry_id(bookmark_id));
@@ -0,0 +1,3 @@
+-- Title extracted from the page, set when the user edits the title so the
+-- extracted one stays around for reference.
+ALTER TABLE bookmark ADD COLUMN original_title TEXT;
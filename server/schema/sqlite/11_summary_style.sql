@@ -0,0 +1,3 @@
+-- Style asked for when the user regenerates the summary of a bookmark, until
+-- the text AI daemon writes it.
+ALTER TABLE bookmark ADD COLUMN summary_style TEXT;
@@ -0,0 +1,5 @@
+-- Typography of the reader view in the web interface.
+ALTER TABLE user_settings ADD COLUMN reader_font_family TEXT NOT NULL DEFAULT 'serif';
+ALTER TABLE user_settings ADD COLUMN reader_font_size INTEGER NOT NULL DEFAULT 18;
+ALTER TABLE user_settings ADD COLUMN reader_line_width INTEGER NOT NULL DEFAULT 70;
+ALTER TABLE user_settings ADD COLUMN reader_justify INTEGER NOT NULL DEFAULT 0;
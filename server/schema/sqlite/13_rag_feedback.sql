@@ -0,0 +1,4 @@
+-- Rating the user gave a RAG answer, with an optional comment.
+ALTER TABLE rag_session ADD COLUMN feedback_rating TEXT;
+ALTER TABLE rag_session ADD COLUMN feedback_comment TEXT;
+ALTER TABLE rag_session ADD COLUMN feedback_at TEXT;
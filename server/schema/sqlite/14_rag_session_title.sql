@@ -0,0 +1,25 @@
+-- Short title generated for each RAG session, and the full-text search of the
+-- history over titles, questions and answers. The sessions have no integer
+-- key, so the index keeps its own copy of the text under the session id.
+ALTER TABLE rag_session ADD COLUMN title TEXT;
+CREATE VIRTUAL TABLE rag_session_fts USING fts5 (
+    session_id UNINDEXED,
+    title,
+    question,
+    answer,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+INSERT INTO rag_session_fts (session_id, title, question, answer)
+    SELECT session_id, title, question, answer FROM rag_session;
+CREATE TRIGGER rag_session_fts_insert AFTER INSERT ON rag_session BEGIN
+    INSERT INTO rag_session_fts (session_id, title, question, answer)
+    VALUES (new.session_id, new.title, new.question, new.answer);
+END;
+CREATE TRIGGER rag_session_fts_delete AFTER DELETE ON rag_session BEGIN
+    DELETE FROM rag_session_fts WHERE session_id = old.session_id;
+END;
+CREATE TRIGGER rag_session_fts_update AFTER UPDATE OF title, question, answer ON rag_session BEGIN
+    DELETE FROM rag_session_fts WHERE session_id = old.session_id;
+    INSERT INTO rag_session_fts (session_id, title, question, answer)
+    VALUES (new.session_id, new.title, new.question, new.answer);
+END;
@@ -0,0 +1,11 @@
+-- Embeddings of chunk texts already sent to a model, so reprocessing a
+-- bookmark reuses them instead of calling the provider again.
+CREATE TABLE embedding_cache (
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    dimensions INTEGER NOT NULL,
+    content_hash TEXT NOT NULL,
+    embedding BLOB NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (provider, model, dimensions, content_hash)
+);
@@ -0,0 +1,15 @@
+-- CSS selectors a user sets for the sites whose articles extract badly:
+-- elements to keep or drop before extraction and where the title is.
+CREATE TABLE extraction_rule (
+    rule_id BLOB NOT NULL,
+    user_id BLOB NOT NULL REFERENCES "user" (user_id) ON DELETE CASCADE,
+    domain TEXT NOT NULL,
+    keep_selectors TEXT NOT NULL,
+    drop_selectors TEXT NOT NULL,
+    title_selector TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT,
+    PRIMARY KEY (rule_id)
+);
+
+CREATE INDEX idx_extraction_rule_user ON extraction_rule (user_id);
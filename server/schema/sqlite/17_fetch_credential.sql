@@ -0,0 +1,9 @@
+-- Request headers a user attaches when fetching the pages of a domain, such
+-- as a session cookie. They are encrypted by the server before being stored.
+CREATE TABLE fetch_credential (
+    user_id BLOB NOT NULL REFERENCES "user" (user_id) ON DELETE CASCADE,
+    domain TEXT NOT NULL,
+    sealed_headers BLOB NOT NULL,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (user_id, domain)
+);
@@ -0,0 +1,17 @@
+-- Sign-ins, credential changes, deletions and share links of a user, for
+-- them to review. Rows are never updated, they go with their user only.
+CREATE TABLE audit_log (
+    event_id BLOB NOT NULL,
+    user_id BLOB NOT NULL REFERENCES "user" (user_id) ON DELETE CASCADE,
+    action TEXT NOT NULL,
+    target TEXT,
+    ip TEXT,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (event_id)
+);
+
+CREATE INDEX idx_audit_log_user_created ON audit_log (user_id, created_at DESC);
+
+CREATE TRIGGER audit_log_append_only BEFORE UPDATE ON audit_log BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
@@ -0,0 +1,14 @@
+-- Signed-in devices. An access token names its session, deleting the row
+-- revokes the token before it expires.
+CREATE TABLE user_session (
+    session_id BLOB NOT NULL,
+    user_id BLOB NOT NULL REFERENCES "user" (user_id) ON DELETE CASCADE,
+    device_label TEXT,
+    ip TEXT,
+    created_at TEXT NOT NULL,
+    last_seen_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    PRIMARY KEY (session_id)
+);
+
+CREATE INDEX idx_user_session_user ON user_session (user_id);
@@ -0,0 +1,232 @@
+-- SQLite schema for single-user deployments, the equivalent of the Postgres
+-- schema at version 20. UUIDs are 16 byte blobs, timestamps RFC 3339 text in
+-- UTC, arrays JSON text and embeddings little-endian f32 blobs. Tables indexed
+-- with FTS5 get an INTEGER PRIMARY KEY so their rowid survives a VACUUM.
+
+CREATE TABLE "user" (
+    user_id BLOB NOT NULL,
+    username TEXT NOT NULL,
+    password_hash TEXT NOT NULL,
+    email TEXT,
+    email_verified_at TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (user_id)
+);
+CREATE UNIQUE INDEX user_username_unique ON "user" (lower(username));
+CREATE UNIQUE INDEX user_email_unique ON "user" (lower(email));
+
+CREATE TABLE user_token (
+    token_hash TEXT NOT NULL,
+    user_id BLOB NOT NULL REFERENCES "user" (user_id) ON DELETE CASCADE,
+    purpose TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    used_at TEXT,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (token_hash)
+);
+
+CREATE TABLE user_settings (
+    user_id BLOB NOT NULL REFERENCES "user" (user_id) ON DELETE CASCADE,
+    auto_tagging_enabled INTEGER NOT NULL DEFAULT 1,
+    summaries_enabled INTEGER NOT NULL DEFAULT 1,
+    summary_language TEXT,
+    preferred_text_model TEXT,
+    digest_enabled INTEGER NOT NULL DEFAULT 1,
+    digest_email TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (user_id)
+);
+
+CREATE TABLE bookmark (
+    id INTEGER PRIMARY KEY,
+    bookmark_id TEXT NOT NULL,
+    user_id BLOB NOT NULL REFERENCES "user" (user_id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    canonical_url TEXT NOT NULL,
+    domain TEXT NOT NULL,
+    title TEXT NOT NULL,
+    text_content TEXT NOT NULL,
+    tags TEXT,
+    summary TEXT,
+    summary_status TEXT NOT NULL DEFAULT 'pending',
+    tag_status TEXT NOT NULL DEFAULT 'pending',
+    text_ai_status TEXT NOT NULL DEFAULT 'pending',
+    text_ai_attempts INTEGER NOT NULL DEFAULT 0,
+    text_ai_next_attempt_at TEXT NOT NULL,
+    text_ai_fail_reason TEXT,
+    text_ai_pipeline_version INTEGER NOT NULL DEFAULT 1,
+    embedding_status TEXT NOT NULL DEFAULT 'pending',
+    embedding_attempts INTEGER NOT NULL DEFAULT 0,
+    embedding_next_attempt_at TEXT NOT NULL,
+    embedding_fail_reason TEXT,
+    embedding_pipeline_version INTEGER NOT NULL DEFAULT 1,
+    embedding BLOB,
+    language TEXT,
+    text_search_config TEXT NOT NULL DEFAULT 'english',
+    static_bytes INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    UNIQUE (bookmark_id, user_id),
+    UNIQUE (user_id, canonical_url)
+);
+CREATE INDEX idx_bookmark_user_created_at ON bookmark (user_id, created_at DESC);
+CREATE INDEX idx_bookmark_user_domain ON bookmark (user_id, domain);
+CREATE INDEX idx_bookmark_text_ai_pending ON bookmark (text_ai_next_attempt_at, created_at)
+    WHERE text_ai_status = 'pending';
+CREATE INDEX idx_bookmark_embedding_pending ON bookmark (embedding_next_attempt_at, created_at)
+    WHERE embedding_status = 'pending';
+
+-- Title, text and tags weigh like the A, B and C weights of the Postgres
+-- search tokens, see the bm25() calls
+CREATE VIRTUAL TABLE bookmark_fts USING fts5 (
+    title, text_content, tags,
+    content = 'bookmark', content_rowid = 'id',
+    tokenize = 'porter unicode61 remove_diacritics 2'
+);
+CREATE TRIGGER bookmark_fts_insert AFTER INSERT ON bookmark BEGIN
+    INSERT INTO bookmark_fts (rowid, title, text_content, tags)
+    VALUES (new.id, new.title, new.text_content, new.tags);
+END;
+CREATE TRIGGER bookmark_fts_delete AFTER DELETE ON bookmark BEGIN
+    INSERT INTO bookmark_fts (bookmark_fts, rowid, title, text_content, tags)
+    VALUES ('delete', old.id, old.title, old.text_content, old.tags);
+END;
+CREATE TRIGGER bookmark_fts_update AFTER UPDATE OF title, text_content, tags ON bookmark BEGIN
+    INSERT INTO bookmark_fts (bookmark_fts, rowid, title, text_content, tags)
+    VALUES ('delete', old.id, old.title, old.text_content, old.tags);
+    INSERT INTO bookmark_fts (rowid, title, text_content, tags)
+    VALUES (new.id, new.title, new.text_content, new.tags);
+END;
+
+CREATE TABLE bookmark_ai_chunk (
+    bookmark_id TEXT NOT NULL,
+    user_id BLOB NOT NULL,
+    chunk_index INTEGER NOT NULL,
+    chunk_hash TEXT NOT NULL,
+    pipeline_version INTEGER NOT NULL,
+    summary TEXT NOT NULL,
+    tags TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (bookmark_id, user_id, chunk_index),
+    FOREIGN KEY (bookmark_id, user_id)
+        REFERENCES bookmark (bookmark_id, user_id) ON DELETE CASCADE
+);
+
+CREATE TABLE bookmark_chunk (
+    id INTEGER PRIMARY KEY,
+    chunk_id BLOB NOT NULL UNIQUE,
+    bookmark_id TEXT NOT NULL,
+    user_id BLOB NOT NULL,
+    chunk_text TEXT NOT NULL,
+    chunk_index INTEGER NOT NULL,
+    embedding BLOB,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    UNIQUE (bookmark_id, user_id, chunk_index),
+    FOREIGN KEY (bookmark_id, user_id)
+        REFERENCES bookmark (bookmark_id, user_id) ON DELETE CASCADE
+);
+CREATE INDEX idx_bookmark_chunk_user ON bookmark_chunk (user_id);
+
+CREATE VIRTUAL TABLE bookmark_chunk_fts USING fts5 (
+    chunk_text,
+    content = 'bookmark_chunk', content_rowid = 'id',
+    tokenize = 'porter unicode61 remove_diacritics 2'
+);
+CREATE TRIGGER bookmark_chunk_fts_insert AFTER INSERT ON bookmark_chunk BEGIN
+    INSERT INTO bookmark_chunk_fts (rowid, chunk_text) VALUES (new.id, new.chunk_text);
+END;
+CREATE TRIGGER bookmark_chunk_fts_delete AFTER DELETE ON bookmark_chunk BEGIN
+    INSERT INTO bookmark_chunk_fts (bookmark_chunk_fts, rowid, chunk_text)
+    VALUES ('delete', old.id, old.chunk_text);
+END;
+CREATE TRIGGER bookmark_chunk_fts_update AFTER UPDATE OF chunk_text ON bookmark_chunk BEGIN
+    INSERT INTO bookmark_chunk_fts (bookmark_chunk_fts, rowid, chunk_text)
+    VALUES ('delete', old.id, old.chunk_text);
+    INSERT INTO bookmark_chunk_fts (rowid, chunk_text) VALUES (new.id, new.chunk_text);
+END;
+
+CREATE TABLE embedding_config (
+    embedding_config_id INTEGER NOT NULL DEFAULT 1 CHECK (embedding_config_id = 1),
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    dimensions INTEGER NOT NULL CHECK (dimensions > 0),
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (embedding_config_id)
+);
+
+CREATE TABLE bookmark_task (
+    task_id BLOB NOT NULL,
+    user_id BLOB NOT NULL REFERENCES "user" (user_id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    tags TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    next_delivery TEXT NOT NULL,
+    retries INTEGER,
+    fail_reason TEXT,
+    ignore_robots INTEGER NOT NULL DEFAULT 0,
+    bookmark_id TEXT,
+    PRIMARY KEY (task_id)
+);
+CREATE INDEX idx_task_user_id ON bookmark_task (user_id);
+CREATE INDEX idx_task_next_delivery ON bookmark_task (next_delivery) WHERE status = 'pending';
+CREATE INDEX idx_bookmark_task_bookmark ON bookmark_task (user_id, bookmark_id)
+    WHERE bookmark_id IS NOT NULL;
+
+CREATE TABLE task_event (
+    event_id BLOB NOT NULL,
+    task_id BLOB NOT NULL REFERENCES bookmark_task (task_id) ON DELETE CASCADE,
+    stage TEXT NOT NULL,
+    detail TEXT,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (event_id)
+);
+CREATE INDEX idx_task_event_task_created_at ON task_event (task_id, created_at);
+
+CREATE TABLE rag_session (
+    session_id BLOB NOT NULL,
+    user_id BLOB NOT NULL REFERENCES "user" (user_id) ON DELETE CASCADE,
+    question TEXT NOT NULL,
+    answer TEXT,
+    relevant_chunks TEXT NOT NULL DEFAULT '[]',
+    created_at TEXT NOT NULL,
+    updated_at TEXT,
+    PRIMARY KEY (session_id)
+);
+CREATE INDEX idx_rag_session_user ON rag_session (user_id, created_at DESC);
+
+CREATE TABLE llm_usage (
+    usage_id BLOB NOT NULL,
+    user_id BLOB REFERENCES "user" (user_id) ON DELETE CASCADE,
+    bookmark_id TEXT,
+    session_id BLOB,
+    purpose TEXT NOT NULL,
+    request_kind TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    input_tokens INTEGER,
+    output_tokens INTEGER,
+    duration_ms INTEGER NOT NULL,
+    success INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (usage_id)
+);
+CREATE INDEX idx_llm_usage_user_created_at ON llm_usage (user_id, created_at);
+
+CREATE TABLE digest (
+    digest_id BLOB NOT NULL,
+    user_id BLOB NOT NULL REFERENCES "user" (user_id) ON DELETE CASCADE,
+    period_start TEXT NOT NULL,
+    period_end TEXT NOT NULL,
+    bookmark_ids TEXT NOT NULL,
+    content TEXT NOT NULL,
+    emailed_at TEXT,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (digest_id),
+    UNIQUE (user_id, period_start)
+);
@@ -0,0 +1,31 @@
+-- Shared libraries. Members see the bookmarks saved into the workspace,
+-- which stay owned by the member who saved them.
+CREATE TABLE workspace (
+    workspace_id BLOB NOT NULL,
+    owner_id BLOB NOT NULL REFERENCES "user" (user_id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (workspace_id)
+);
+
+CREATE TABLE workspace_member (
+    workspace_id BLOB NOT NULL REFERENCES workspace (workspace_id) ON DELETE CASCADE,
+    user_id BLOB NOT NULL REFERENCES "user" (user_id) ON DELETE CASCADE,
+    role TEXT NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+    joined_at TEXT NOT NULL,
+    PRIMARY KEY (workspace_id, user_id)
+);
+
+CREATE INDEX idx_workspace_member_user ON workspace_member (user_id);
+
+CREATE TABLE workspace_bookmark (
+    workspace_id BLOB NOT NULL REFERENCES workspace (workspace_id) ON DELETE CASCADE,
+    user_id BLOB NOT NULL,
+    bookmark_id TEXT NOT NULL,
+    added_at TEXT NOT NULL,
+    PRIMARY KEY (workspace_id, bookmark_id, user_id),
+    FOREIGN KEY (bookmark_id, user_id)
+        REFERENCES bookmark (bookmark_id, user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_workspace_bookmark_bookmark ON workspace_bookmark (bookmark_id, user_id);
@@ -0,0 +1,9 @@
+-- Last run of each periodic job, so a restart doesn't rerun a job that
+-- already ran on schedule.
+CREATE TABLE scheduled_job (
+    name TEXT NOT NULL,
+    last_started_at TEXT NOT NULL,
+    last_finished_at TEXT,
+    last_error TEXT,
+    PRIMARY KEY (name)
+);
@@ -0,0 +1,4 @@
+-- Lease held by the server instance processing a pending task, so instances
+-- sharing the database never run a task twice.
+ALTER TABLE bookmark_task ADD COLUMN lease_owner TEXT;
+ALTER TABLE bookmark_task ADD COLUMN lease_expires_at TEXT;
@@ -0,0 +1,12 @@
+-- Digests are emailed to the verified address of the account only.
+ALTER TABLE user_settings DROP COLUMN digest_email;
+
+-- Secret in the URL of the digest feed, only its hash is stored.
+CREATE TABLE digest_feed_token (
+    user_id BLOB NOT NULL REFERENCES "user" (user_id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (user_id)
+);
+
+CREATE UNIQUE INDEX idx_digest_feed_token_hash ON digest_feed_token (token_hash);
@@ -0,0 +1,3 @@
+-- When the bookmark was first opened in the reader, so the random pick can
+-- keep to the ones never read.
+ALTER TABLE bookmark ADD COLUMN read_at TEXT;
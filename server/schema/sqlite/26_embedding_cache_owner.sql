@@ -0,0 +1,17 @@
+-- Cached embeddings belong to the user whose chunks were embedded and
+-- record when they were last reused, the old shared entries are dropped.
+DROP TABLE embedding_cache;
+
+CREATE TABLE embedding_cache (
+    user_id BLOB NOT NULL REFERENCES "user" (user_id) ON DELETE CASCADE,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    dimensions INTEGER NOT NULL,
+    content_hash TEXT NOT NULL,
+    embedding BLOB NOT NULL,
+    created_at TEXT NOT NULL,
+    last_used_at TEXT NOT NULL,
+    PRIMARY KEY (user_id, provider, model, dimensions, content_hash)
+);
+
+CREATE INDEX idx_embedding_cache_last_used_at ON embedding_cache (last_used_at);
@@ -0,0 +1,43 @@
+-- Events outlive their user: deleting an account clears `user_id` and keeps
+-- the username, and failed sign-ins for usernames without an account are
+-- recorded with a username only. Rows are neither changed nor deleted.
+CREATE TABLE audit_log_new (
+    event_id BLOB NOT NULL,
+    user_id BLOB REFERENCES "user" (user_id) ON DELETE SET NULL,
+    username TEXT,
+    action TEXT NOT NULL,
+    target TEXT,
+    ip TEXT,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (event_id)
+);
+
+INSERT INTO audit_log_new (event_id, user_id, username, action, target, ip, created_at)
+SELECT a.event_id, a.user_id, u.username, a.action, a.target, a.ip, a.created_at
+FROM audit_log a LEFT JOIN "user" u ON u.user_id = a.user_id;
+
+DROP TABLE audit_log;
+ALTER TABLE audit_log_new RENAME TO audit_log;
+
+CREATE INDEX idx_audit_log_user_created ON audit_log (user_id, created_at DESC);
+CREATE INDEX idx_audit_log_username_created ON audit_log (username, created_at DESC);
+
+-- The only change allowed is the foreign key clearing `user_id` of the
+-- events of a deleted user.
+CREATE TRIGGER audit_log_append_only BEFORE UPDATE ON audit_log
+WHEN NOT (
+    NEW.user_id IS NULL
+    AND NEW.event_id IS OLD.event_id
+    AND NEW.username IS OLD.username
+    AND NEW.action IS OLD.action
+    AND NEW.target IS OLD.target
+    AND NEW.ip IS OLD.ip
+    AND NEW.created_at IS OLD.created_at
+)
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
+
+CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
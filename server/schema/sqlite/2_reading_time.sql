@@ -0,0 +1,18 @@
+-- Word count of the extracted text and the reading time it takes at 238
+-- words per minute, rounded up, for filtering searches by reading time.
+ALTER TABLE bookmark ADD COLUMN word_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE bookmark ADD COLUMN reading_time_minutes INTEGER NOT NULL DEFAULT 0;
+
+-- SQLite has no regular expressions, existing bookmarks count the words
+-- between single whitespace characters. New ones are counted exactly.
+UPDATE bookmark
+SET word_count = length(normalized) - length(replace(normalized, ' ', '')) + 1
+FROM (
+    SELECT id AS normalized_id,
+           trim(replace(replace(replace(text_content, char(10), ' '), char(13), ' '), char(9), ' '))
+               AS normalized
+    FROM bookmark
+)
+WHERE id = normalized_id AND normalized <> '';
+
+UPDATE bookmark SET reading_time_minutes = (word_count + 237) / 238;
@@ -0,0 +1,17 @@
+-- People, projects and organizations the AI pipeline found mentioned in each
+-- bookmark, to browse them and to find articles mentioning one even when it
+-- isn't tagged. Each analyzed slice keeps its own so unchanged text isn't
+-- sent to the model again.
+CREATE TABLE entity (
+    bookmark_id TEXT NOT NULL,
+    user_id BLOB NOT NULL,
+    kind TEXT NOT NULL,
+    name TEXT NOT NULL,
+    PRIMARY KEY (bookmark_id, user_id, kind, name),
+    FOREIGN KEY (bookmark_id, user_id)
+        REFERENCES bookmark (bookmark_id, user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_entity_user_name ON entity (user_id, lower(name));
+
+ALTER TABLE bookmark_ai_chunk ADD COLUMN entities TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,11 @@
+-- Canonical URLs each bookmarked article links to, with their domain, so
+-- bookmarks citing one another can be linked in the library graph.
+CREATE TABLE bookmark_link (
+    bookmark_id TEXT NOT NULL,
+    user_id BLOB NOT NULL,
+    url TEXT NOT NULL,
+    domain TEXT NOT NULL,
+    PRIMARY KEY (bookmark_id, user_id, url),
+    FOREIGN KEY (bookmark_id, user_id)
+        REFERENCES bookmark (bookmark_id, user_id) ON DELETE CASCADE
+);
@@ -0,0 +1,28 @@
+-- Named, ordered reading lists a user curates besides tags, which can be
+-- published through a share link.
+CREATE TABLE collection (
+    collection_id BLOB NOT NULL,
+    user_id BLOB NOT NULL REFERENCES "user" (user_id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    description TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT,
+    PRIMARY KEY (collection_id),
+    UNIQUE (collection_id, user_id)
+);
+
+CREATE INDEX idx_collection_user ON collection (user_id);
+
+CREATE TABLE collection_bookmark (
+    collection_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    bookmark_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (collection_id, bookmark_id),
+    FOREIGN KEY (collection_id, user_id)
+        REFERENCES collection (collection_id, user_id) ON DELETE CASCADE,
+    FOREIGN KEY (bookmark_id, user_id)
+        REFERENCES bookmark (bookmark_id, user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_collection_bookmark_bookmark ON collection_bookmark (bookmark_id, user_id);
@@ -0,0 +1,24 @@
+-- Earlier captures of a bookmark, kept when it is fetched again instead of
+-- being overwritten. captured_at of the bookmark is when its current capture
+-- was taken, NULL until its first refresh meaning created_at.
+CREATE TABLE bookmark_version (
+    version_id BLOB NOT NULL,
+    bookmark_id TEXT NOT NULL,
+    user_id BLOB NOT NULL,
+    title TEXT NOT NULL,
+    text_content TEXT NOT NULL,
+    html TEXT,
+    word_count INTEGER NOT NULL,
+    captured_at TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (version_id),
+    FOREIGN KEY (bookmark_id, user_id)
+        REFERENCES bookmark (bookmark_id, user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_bookmark_version_bookmark
+    ON bookmark_version (bookmark_id, user_id, captured_at DESC);
+
+ALTER TABLE bookmark ADD COLUMN captured_at TEXT;
+
+ALTER TABLE bookmark_task ADD COLUMN refresh INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,18 @@
+-- Rules a user defines to act on new bookmarks before the AI pipeline gets
+-- to them: tagging, marking as favorite or leaving them out of AI
+-- processing, for the URLs, domains and titles they match.
+ALTER TABLE bookmark ADD COLUMN favorite INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE rule (
+    rule_id BLOB NOT NULL,
+    user_id BLOB NOT NULL REFERENCES "user" (user_id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    conditions TEXT NOT NULL,
+    actions TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT,
+    PRIMARY KEY (rule_id)
+);
+
+CREATE INDEX idx_rule_user ON rule (user_id);
@@ -0,0 +1,443 @@
+use std::path::Path;
+
+use anyhow::{bail, ensure, Context, Result};
+use async_compression::tokio::bufread::GzipDecoder;
+use async_compression::tokio::write::GzipEncoder;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use shared::{AuditAction, Entity, ExtractionRule, Rule, UserSettings, Visibility};
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter};
+use tokio_tar::{Archive, Builder, Header};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::db::bookmark::AiGenerationStatus;
+use crate::db::user::User;
+use crate::db::{self, Database, EmbeddingProfile};
+use crate::endpoints::audit;
+use crate::static_storage::{StorageBackend, StoredObject, GZIP_ENCODING};
+
+/// Bumped when the archive layout or the records change, so an older
+/// release refuses a backup it would restore only part of. Older versions
+/// are still restored, the fields they lack default to empty.
+const FORMAT_VERSION: u32 = 2;
+const MANIFEST_PATH: &str = "manifest.json";
+const USERS_DIR: &str = "users/";
+const STATIC_DIR: &str = "static/";
+
+/// First entry of the archive.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format_version: u32,
+    pub created_at: DateTime<Utc>,
+    /// Model that produced the chunk embeddings, `None` before any was made
+    pub embedding_profile: Option<EmbeddingProfile>,
+}
+
+/// `users/{user_id}.json`, everything a user owns but their static content.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserDump {
+    pub user: User,
+    /// `None` when the user never changed the defaults
+    pub settings: Option<UserSettings>,
+    pub bookmarks: Vec<BookmarkRecord>,
+    pub digests: Vec<DigestRecord>,
+    #[serde(default)]
+    pub collections: Vec<CollectionRecord>,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    #[serde(default)]
+    pub extraction_rules: Vec<ExtractionRule>,
+    #[serde(default)]
+    pub fetch_credentials: Vec<FetchCredentialRecord>,
+    /// Workspaces the user owns
+    #[serde(default)]
+    pub workspaces: Vec<WorkspaceRecord>,
+    /// Workspaces the user is a member of, their own included. Restored once
+    /// every user is, as the workspace may belong to someone else
+    #[serde(default)]
+    pub workspace_memberships: Vec<WorkspaceMembershipRecord>,
+    /// Bookmarks of the user added to a workspace
+    #[serde(default)]
+    pub workspace_bookmarks: Vec<WorkspaceBookmarkRecord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookmarkRecord {
+    pub bookmark_id: String,
+    pub url: String,
+    pub canonical_url: String,
+    pub domain: String,
+    pub title: String,
+    pub text_content: String,
+    pub tags: Option<Vec<String>>,
+    pub summary: Option<String>,
+    /// Absent from backups taken before bookmarks were classified
+    #[serde(default)]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub favorite: bool,
+    #[serde(default)]
+    pub original_title: Option<String>,
+    /// Absent from backups taken before bookmarks had a visibility
+    #[serde(default)]
+    pub visibility: Visibility,
+    pub summary_status: AiGenerationStatus,
+    pub tag_status: AiGenerationStatus,
+    pub text_ai_status: AiGenerationStatus,
+    pub language: Option<String>,
+    pub text_search_config: String,
+    pub static_bytes: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub chunks: Vec<ChunkRecord>,
+    #[serde(default)]
+    pub entities: Vec<Entity>,
+    /// Earlier captures, the oldest first
+    #[serde(default)]
+    pub versions: Vec<BookmarkVersionRecord>,
+    #[serde(default)]
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookmarkVersionRecord {
+    pub version_id: Uuid,
+    pub title: String,
+    pub text_content: String,
+    pub html: Option<String>,
+    pub word_count: i32,
+    pub captured_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkRecord {
+    pub chunk_index: i32,
+    pub chunk_text: String,
+    pub embedding: Option<Vec<f32>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestRecord {
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub bookmark_ids: Vec<String>,
+    pub content: String,
+    pub emailed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionRecord {
+    pub collection_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    /// In their order in the collection
+    pub bookmark_ids: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// The headers stay sealed with the key derived from `HMAC_KEY`, so only an
+/// instance with the same key can use them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FetchCredentialRecord {
+    pub domain: String,
+    pub sealed_headers: Vec<u8>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceRecord {
+    pub workspace_id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceMembershipRecord {
+    pub workspace_id: Uuid,
+    pub role: String,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceBookmarkRecord {
+    pub workspace_id: Uuid,
+    pub bookmark_id: String,
+    pub added_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    pub users: usize,
+    pub bookmarks: usize,
+    pub objects: usize,
+}
+
+/// Writes a gzipped tarball with the manifest, one JSON dump per user and
+/// their static content under `static/`, laid out like `APP_DATA_DIR`.
+pub async fn backup(
+    pool: &Database,
+    storage: &dyn StorageBackend,
+    output: &Path,
+) -> Result<Summary> {
+    let file = tokio::fs::File::create(output)
+        .await
+        .with_context(|| format!("Failed to create {output:?}"))?;
+    let mut archive = Builder::new(GzipEncoder::new(BufWriter::new(file)));
+
+    let manifest = Manifest {
+        format_version: FORMAT_VERSION,
+        created_at: Utc::now(),
+        embedding_profile: db::stored_embedding_profile(pool).await?,
+    };
+    append(
+        &mut archive,
+        MANIFEST_PATH,
+        &serde_json::to_vec_pretty(&manifest)?,
+    )
+    .await?;
+
+    let mut summary = Summary::default();
+    for user_id in db::backup::user_ids(pool).await? {
+        let dump = db::backup::export_user(pool, user_id).await?;
+        append(
+            &mut archive,
+            &format!("{USERS_DIR}{user_id}.json"),
+            &serde_json::to_vec(&dump)?,
+        )
+        .await?;
+        summary.users += 1;
+        summary.bookmarks += dump.bookmarks.len();
+
+        for key in storage.list(&user_id.to_string()).await? {
+            // Gone if the bookmark was deleted since it was listed
+            let Some(object) = storage.get(&key).await? else {
+                continue;
+            };
+            let path = static_path(&key, object.content_encoding.as_deref());
+            append(&mut archive, &path, &object.bytes).await?;
+            summary.objects += 1;
+        }
+    }
+
+    let mut output_stream = archive.into_inner().await?;
+    output_stream.shutdown().await?;
+    info!(
+        output = ?output,
+        users = summary.users,
+        bookmarks = summary.bookmarks,
+        objects = summary.objects,
+        "Backup written"
+    );
+    Ok(summary)
+}
+
+/// Restores a [`backup`] into an instance that has none of its users yet.
+/// Chunk embeddings are kept when this instance has none or already uses
+/// the same model, otherwise, or with `regenerate_embeddings`, the bookmarks
+/// are queued for the embedding daemon.
+pub async fn restore(
+    pool: &Database,
+    storage: &dyn StorageBackend,
+    input: &Path,
+    regenerate_embeddings: bool,
+) -> Result<Summary> {
+    let file = tokio::fs::File::open(input)
+        .await
+        .with_context(|| format!("Failed to open {input:?}"))?;
+    let mut archive = Archive::new(GzipDecoder::new(BufReader::new(file)));
+    let mut entries = archive.entries()?;
+
+    let mut with_chunks = None;
+    let mut memberships = Vec::new();
+    let mut summary = Summary::default();
+    while let Some(entry) = entries.next().await {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes).await?;
+
+        if path == MANIFEST_PATH {
+            let manifest: Manifest =
+                serde_json::from_slice(&bytes).context("Invalid backup manifest")?;
+            ensure!(
+                (1..=FORMAT_VERSION).contains(&manifest.format_version),
+                "Unsupported backup format version {}",
+                manifest.format_version
+            );
+            with_chunks = Some(keep_chunks(pool, &manifest, regenerate_embeddings).await?);
+            continue;
+        }
+        let Some(with_chunks) = with_chunks else {
+            bail!("{input:?} is not a backup, it doesn't start with {MANIFEST_PATH}");
+        };
+
+        if path.starts_with(USERS_DIR) {
+            let dump: UserDump =
+                serde_json::from_slice(&bytes).with_context(|| format!("Invalid {path}"))?;
+            db::backup::import_user(pool, &dump, with_chunks)
+                .await
+                .with_context(|| format!("Failed to restore user {}", dump.user.username))?;
+            audit::record(
+                pool,
+                dump.user.user_id,
+                AuditAction::BackupRestored,
+                Some(&format!("{} bookmarks", dump.bookmarks.len())),
+                None,
+            )
+            .await;
+            summary.users += 1;
+            summary.bookmarks += dump.bookmarks.len();
+            memberships.push((
+                dump.user.user_id,
+                dump.workspace_memberships,
+                dump.workspace_bookmarks,
+            ));
+        } else if let Some((key, content_encoding)) = parse_static_path(&path) {
+            let object = StoredObject {
+                content_type: None,
+                content_encoding,
+                bytes,
+                last_modified: None,
+            };
+            storage.put(&key, object).await?;
+            summary.objects += 1;
+        } else {
+            warn!(path, "Skipping unknown backup entry");
+        }
+    }
+    ensure!(with_chunks.is_some(), "{input:?} is an empty backup");
+    for (user_id, memberships, bookmarks) in memberships {
+        db::backup::import_workspace_memberships(pool, user_id, &memberships, &bookmarks)
+            .await
+            .with_context(|| format!("Failed to restore the workspaces of user {user_id}"))?;
+    }
+
+    info!(
+        input = ?input,
+        users = summary.users,
+        bookmarks = summary.bookmarks,
+        objects = summary.objects,
+        "Backup restored"
+    );
+    Ok(summary)
+}
+
+/// Whether the chunks in the backup can be stored as they are, which also
+/// records their embedding profile on an instance that has none yet.
+async fn keep_chunks(pool: &Database, manifest: &Manifest, regenerate: bool) -> Result<bool> {
+    let Some(profile) = &manifest.embedding_profile else {
+        return Ok(false);
+    };
+    if regenerate {
+        return Ok(false);
+    }
+    match db::stored_embedding_profile(pool).await? {
+        Some(stored) if stored == *profile => Ok(true),
+        Some(stored) => {
+            warn!(
+                backup_model = %profile.model,
+                model = %stored.model,
+                "Backup embeddings come from another model, regenerating them"
+            );
+            Ok(false)
+        }
+        None => {
+            db::adopt_embedding_profile(pool, profile).await?;
+            Ok(true)
+        }
+    }
+}
+
+async fn append<W: AsyncWrite + Unpin + Send + 'static>(
+    archive: &mut Builder<W>,
+    path: &str,
+    bytes: &[u8],
+) -> Result<()> {
+    let mut header = Header::new_gnu();
+    header.set_size(bytes.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(Utc::now().timestamp().max(0) as u64);
+    archive
+        .append_data(&mut header, path, bytes)
+        .await
+        .with_context(|| format!("Failed to add {path} to the backup"))
+}
+
+/// Compressed objects get the `.gz` suffix they have in `APP_DATA_DIR`.
+fn static_path(key: &str, content_encoding: Option<&str>) -> String {
+    match content_encoding {
+        Some(GZIP_ENCODING) => format!("{STATIC_DIR}{key}.gz"),
+        _ => format!("{STATIC_DIR}{key}"),
+    }
+}
+
+fn parse_static_path(path: &str) -> Option<(String, Option<String>)> {
+    let key = path.strip_prefix(STATIC_DIR)?;
+    Some(match key.strip_suffix(".gz") {
+        Some(key) => (key.to_string(), Some(GZIP_ENCODING.to_string())),
+        None => (key.to_string(), None),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn static_paths_keep_the_content_encoding() {
+        for (key, encoding) in [
+            ("u/b/index.html", Some(GZIP_ENCODING)),
+            ("u/b/images/a.webp", None),
+        ] {
+            let path = static_path(key, encoding);
+            assert_eq!(
+                parse_static_path(&path),
+                Some((key.to_string(), encoding.map(str::to_string)))
+            );
+        }
+        assert_eq!(
+            static_path("u/b/index.html", Some("gzip")),
+            "static/u/b/index.html.gz"
+        );
+        assert_eq!(parse_static_path("users/u.json"), None);
+    }
+
+    #[tokio::test]
+    async fn entries_round_trip_through_the_archive() {
+        let long_key = format!("u/{}/index.html", "b".repeat(300));
+        let mut archive = Builder::new(GzipEncoder::new(Vec::new()));
+        append(&mut archive, MANIFEST_PATH, b"{}").await.unwrap();
+        append(&mut archive, &static_path(&long_key, None), b"page")
+            .await
+            .unwrap();
+        let mut encoder = archive.into_inner().await.unwrap();
+        encoder.shutdown().await.unwrap();
+        let bytes = encoder.into_inner();
+
+        let mut archive = Archive::new(GzipDecoder::new(bytes.as_slice()));
+        let mut entries = archive.entries().unwrap();
+        let mut read = Vec::new();
+        while let Some(entry) = entries.next().await {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().to_string_lossy().into_owned();
+            let mut content = String::new();
+            entry.read_to_string(&mut content).await.unwrap();
+            read.push((path, content));
+        }
+        assert_eq!(
+            read,
+            [
+                (MANIFEST_PATH.to_string(), "{}".to_string()),
+                (format!("{STATIC_DIR}{long_key}"), "page".to_string()),
+            ]
+        );
+    }
+}
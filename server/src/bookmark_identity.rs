@@ -54,6 +54,12 @@ pub fn domain_from_url(url: &Url) -> Result<String> {
     Ok(domain_or_host)
 }
 
+/// Whether `domain` is `parent` or one of its subdomains, ignoring case.
+pub fn covers_domain(parent: &str, domain: &str) -> bool {
+    let (parent, domain) = (parent.to_lowercase(), domain.to_lowercase());
+    domain == parent || domain.ends_with(&format!(".{parent}"))
+}
+
 #[cfg(test)]
 mod tests {
     use url::Url;
@@ -1,32 +1,88 @@
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use anyhow::{anyhow, Context, Result};
-use headless_chrome::Browser;
+use headless_chrome::browser::tab::RequestPausedDecision;
+use headless_chrome::protocol::cdp::Fetch;
+use headless_chrome::protocol::cdp::Fetch::events::RequestPausedEvent;
+use headless_chrome::protocol::cdp::Network::{ErrorReason, Request, ResourceType};
+use headless_chrome::{Browser, LaunchOptions, Tab};
 use reqwest::Client;
 use serde_json::Value;
 use tracing::{debug, info};
 use url::Url;
 
+use crate::bookmark_identity::covers_domain;
+use crate::domain_limit::TooManyRequests;
+use crate::fetch_credentials::FetchHeaders;
+use crate::url_guard::UrlGuard;
+use crate::ChromeParams;
+
+/// HTTP status and `Retry-After` header of a navigated page.
+type DocumentResponse = (u32, Option<String>);
+
 #[derive(Debug, Clone)]
 pub enum ChromeConnection {
-    Local,
-    Remote { host: String, port: u16 },
+    /// A local instance, started with `proxy` as its proxy server
+    Local {
+        proxy: Option<Url>,
+    },
+    Remote {
+        host: String,
+        port: u16,
+    },
+}
+
+impl ChromeConnection {
+    /// The remote instance when `CHROME_HOST` is set, a local one otherwise.
+    /// A remote instance uses the proxy it was started with.
+    pub fn from_params(params: Option<&ChromeParams>, proxy: Option<&Url>) -> Self {
+        match params {
+            Some(params) => Self::Remote {
+                host: params.chrome_host.clone(),
+                port: params.chrome_port,
+            },
+            None => Self::Local {
+                proxy: proxy.cloned(),
+            },
+        }
+    }
 }
 
 pub struct ChromeClient {
     connection: ChromeConnection,
+    guard: UrlGuard,
 }
 
 impl ChromeClient {
-    pub fn new(connection: ChromeConnection) -> Self {
-        Self { connection }
+    pub fn new(connection: ChromeConnection, guard: UrlGuard) -> Self {
+        Self { connection, guard }
+    }
+
+    /// Hosts are resolved to check their addresses unless a local instance
+    /// goes through a proxy, which resolves them instead.
+    fn request_guard(&self) -> RequestGuard {
+        RequestGuard {
+            guard: self.guard.clone(),
+            resolve: !matches!(self.connection, ChromeConnection::Local { proxy: Some(_) }),
+            allowed_hosts: Mutex::default(),
+        }
     }
 
     async fn connect_to_browser(&self) -> Result<Browser> {
         match &self.connection {
-            ChromeConnection::Local => {
-                info!("Starting local Chrome instance");
-                Browser::default().context("Failed to start local Chrome instance")
+            ChromeConnection::Local { proxy } => {
+                info!(?proxy, "Starting local Chrome instance");
+                let proxy_server = proxy
+                    .as_ref()
+                    .map(|proxy| format!("--proxy-server={proxy}"));
+                let options = LaunchOptions::default_builder()
+                    .args(proxy_server.iter().map(OsStr::new).collect())
+                    .build()
+                    .context("Invalid Chrome launch options")?;
+                Browser::new(options).context("Failed to start local Chrome instance")
             }
             ChromeConnection::Remote { host, port } => {
                 info!(%host, %port, "Connecting to remote Chrome instance");
@@ -74,43 +130,407 @@ impl ChromeClient {
         Ok(ws_url.to_string())
     }
 
-    pub async fn fetch_rendered_html(&self, url: &Url) -> Result<String> {
+    /// Checks a page could be rendered without opening one: the remote
+    /// instance answers its discovery endpoint, or a local executable exists.
+    pub async fn check_available(&self) -> Result<()> {
+        match &self.connection {
+            ChromeConnection::Local { .. } => headless_chrome::browser::default_executable()
+                .map(|_| ())
+                .map_err(|err| anyhow!("No local Chrome executable, {err}")),
+            ChromeConnection::Remote { host, port } => {
+                self.discover_websocket_url(host, *port).await.map(|_| ())
+            }
+        }
+    }
+
+    /// Renders `url` as `user_agent` when set, attaching `credential` to the
+    /// requests for its domain and subdomains only. Requests to addresses
+    /// the guard refuses fail, redirects and subresources included.
+    pub async fn fetch_rendered_html(
+        &self,
+        url: &Url,
+        credential: Option<&FetchHeaders>,
+        user_agent: Option<&str>,
+    ) -> Result<String> {
         debug!(%url, "Connecting to browser");
         let browser = self.connect_to_browser().await?;
+        // Driving the tab blocks, keep it off the runtime so pages render
+        // concurrently
+        let url = url.clone();
+        let credential = credential.cloned();
+        let user_agent = user_agent.map(str::to_string);
+        let guard = self.request_guard();
+        tokio::task::spawn_blocking(move || render(&browser, &url, guard, credential, user_agent))
+            .await
+            .context("Chrome render task panicked")?
+    }
+}
 
-        debug!(%url, "Creating new tab");
-        let tab = browser
-            .new_tab()
-            .context("Failed to create new browser tab")?;
-
-        debug!(%url, "Navigating to");
-        tab.navigate_to(url.as_str())
-            .with_context(|| format!("Failed to navigate to {url}"))?;
-
-        debug!(%url, "Waiting for navigation to complete (network idle)");
-        tab.wait_until_navigated()
-            .context("Failed waiting for navigation to complete")?;
-
-        debug!(%url, "Waiting for body element to ensure page is loaded");
-        tab.wait_for_element("body")
-            .context("Failed waiting for body element")?;
-
-        // Try to wait for common content indicators, but don't fail if not found
-        // This helps ensure dynamic content has loaded
-        match tab.wait_for_element_with_custom_timeout(
-            "article, main, [role='main'], #content, .content",
-            Duration::from_secs(2),
-        ) {
-            Ok(_) => debug!(%url, "Found main content indicator"),
-            Err(_) => debug!(%url, "No common content indicators found, proceeding anyway"),
+/// Checks the URLs a page requests against the [`UrlGuard`].
+struct RequestGuard {
+    guard: UrlGuard,
+    resolve: bool,
+    /// Hosts and ports already resolved to allowed addresses
+    allowed_hosts: Mutex<HashSet<String>>,
+}
+
+impl RequestGuard {
+    fn check(&self, url: &Url) -> Result<()> {
+        if !self.resolve {
+            return Ok(self.guard.check(url)?);
+        }
+        let authority = format!(
+            "{}:{}",
+            url.host_str().unwrap_or_default(),
+            url.port_or_known_default().unwrap_or_default()
+        );
+        let mut allowed_hosts = self
+            .allowed_hosts
+            .lock()
+            .expect("allowed hosts mutex poisoned");
+        if allowed_hosts.contains(&authority) {
+            return Ok(self.guard.check(url)?);
         }
+        self.guard.check_resolved_blocking(url)?;
+        allowed_hosts.insert(authority);
+        Ok(())
+    }
+}
+
+fn render(
+    browser: &Browser,
+    url: &Url,
+    guard: RequestGuard,
+    credential: Option<FetchHeaders>,
+    user_agent: Option<String>,
+) -> Result<String> {
+    debug!(%url, "Creating new tab");
+    let tab = browser
+        .new_tab()
+        .context("Failed to create new browser tab")?;
+    if let Some(user_agent) = user_agent {
+        tab.set_user_agent(&user_agent, None, None)
+            .context("Failed to set the User-Agent")?;
+    }
+    let guard = Arc::new(guard);
+    let blocked_document = intercept_requests(&tab, Arc::clone(&guard), credential)?;
+
+    // Status and Retry-After of the page itself, to notice rate limiting
+    let document_response: Arc<Mutex<Option<DocumentResponse>>> = Arc::default();
+    let captured_response = Arc::clone(&document_response);
+    tab.register_response_handling(
+        "document-status",
+        Box::new(move |params, _| {
+            if !matches!(params.Type, ResourceType::Document) {
+                return;
+            }
+            let retry_after = params
+                .response
+                .headers
+                .0
+                .as_ref()
+                .and_then(Value::as_object)
+                .and_then(|headers| {
+                    headers
+                        .iter()
+                        .find(|(name, _)| name.eq_ignore_ascii_case("retry-after"))
+                })
+                .and_then(|(_, value)| value.as_str().map(str::to_string));
+            captured_response
+                .lock()
+                .expect("document response mutex poisoned")
+                .get_or_insert((params.response.status, retry_after));
+        }),
+    )
+    .context("Failed to watch page responses")?;
+
+    debug!(%url, "Navigating to");
+    let navigated = tab
+        .navigate_to(url.as_str())
+        .with_context(|| format!("Failed to navigate to {url}"))
+        .and_then(|tab| {
+            debug!(%url, "Waiting for navigation to complete (network idle)");
+            tab.wait_until_navigated()
+                .context("Failed waiting for navigation to complete")
+        });
+    // The page, or a redirect on the way to it, was refused
+    if let Some(error) = blocked_document
+        .lock()
+        .expect("blocked document mutex poisoned")
+        .take()
+    {
+        return Err(error);
+    }
+    navigated?;
+    let final_url = Url::parse(&tab.get_url()).context("Invalid URL of the rendered page")?;
+    guard
+        .check(&final_url)
+        .with_context(|| format!("Refused to render {final_url}"))?;
+
+    let response = document_response
+        .lock()
+        .expect("document response mutex poisoned")
+        .take();
+    if let Some((429, retry_after)) = response {
+        return Err(TooManyRequests::new(url, retry_after.as_deref()).into());
+    }
+
+    debug!(%url, "Waiting for body element to ensure page is loaded");
+    tab.wait_for_element("body")
+        .context("Failed waiting for body element")?;
+
+    // Try to wait for common content indicators, but don't fail if not found
+    // This helps ensure dynamic content has loaded
+    match tab.wait_for_element_with_custom_timeout(
+        "article, main, [role='main'], #content, .content",
+        Duration::from_secs(2),
+    ) {
+        Ok(_) => debug!(%url, "Found main content indicator"),
+        Err(_) => debug!(%url, "No common content indicators found, proceeding anyway"),
+    }
+
+    debug!(%url, "Getting fully rendered page content");
+    let html = tab.get_content().context("Failed to get page content")?;
 
-        debug!(%url, "Getting fully rendered page content");
-        let html = tab.get_content().context("Failed to get page content")?;
+    debug!(%url, size_bytes = %html.len(), "Successfully fetched HTML content");
 
-        debug!(%url, size_bytes = %html.len(), "Successfully fetched HTML content");
+    // Tab is automatically closed when dropped
+    Ok(html)
+}
+
+/// Pauses every request of the tab to fail the ones the guard refuses,
+/// and to add the credential's headers to those for its domain. Returns
+/// where the reason the page itself was refused ends up.
+fn intercept_requests(
+    tab: &Tab,
+    guard: Arc<RequestGuard>,
+    credential: Option<FetchHeaders>,
+) -> Result<Arc<Mutex<Option<anyhow::Error>>>> {
+    let patterns = [Fetch::RequestPattern {
+        url_pattern: Some("*".to_string()),
+        resource_Type: None,
+        request_stage: Some(Fetch::RequestStage::Request),
+    }];
+    tab.enable_fetch(Some(&patterns), None)
+        .context("Failed to enable request interception")?;
+    let blocked_document: Arc<Mutex<Option<anyhow::Error>>> = Arc::default();
+    let interceptor = RequestInterceptor {
+        guard,
+        credential,
+        main_frame: tab.get_target_id().clone(),
+        blocked_document: Arc::clone(&blocked_document),
+    };
+    tab.enable_request_interception(Arc::new(move |_, _, event: RequestPausedEvent| {
+        interceptor.decide(event)
+    }))
+    .context("Failed to intercept requests")?;
+    Ok(blocked_document)
+}
+
+struct RequestInterceptor {
+    guard: Arc<RequestGuard>,
+    credential: Option<FetchHeaders>,
+    /// Chrome names the main frame of a tab after its target
+    main_frame: String,
+    blocked_document: Arc<Mutex<Option<anyhow::Error>>>,
+}
+
+impl RequestInterceptor {
+    fn decide(&self, event: RequestPausedEvent) -> RequestPausedDecision {
+        let params = event.params;
+        let url = Url::parse(&params.request.url).ok();
+        // Only requests over the network are checked, not data: or blob: URLs
+        if let Some(url) = url.as_ref().filter(|url| url.has_host()) {
+            if let Err(error) = self.guard.check(url) {
+                debug!(%url, %error, "Refused a page request");
+                if matches!(params.resource_Type, ResourceType::Document)
+                    && params.frame_id == self.main_frame
+                {
+                    self.blocked_document
+                        .lock()
+                        .expect("blocked document mutex poisoned")
+                        .get_or_insert(error);
+                }
+                return RequestPausedDecision::Fail(Fetch::FailRequest {
+                    request_id: params.request_id,
+                    error_reason: ErrorReason::BlockedByClient,
+                });
+            }
+        }
+        // The host is checked again since a `*` pattern would also match
+        // inside a crafted path or query
+        let headers = self
+            .credential
+            .as_ref()
+            .filter(|credential| {
+                url.as_ref()
+                    .and_then(Url::host_str)
+                    .is_some_and(|host| covers_domain(&credential.domain, host))
+            })
+            .map(|credential| credential_headers(&params.request, credential));
+        RequestPausedDecision::Continue(Some(Fetch::ContinueRequest {
+            request_id: params.request_id,
+            url: None,
+            method: None,
+            post_data: None,
+            headers,
+            intercept_response: None,
+        }))
+    }
+}
+
+/// Headers of `request` with the credential's ones in place of those of the
+/// same name.
+fn credential_headers(request: &Request, credential: &FetchHeaders) -> Vec<Fetch::HeaderEntry> {
+    let mut headers: Vec<Fetch::HeaderEntry> = request
+        .headers
+        .0
+        .as_ref()
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .filter(|(name, _)| {
+            !credential
+                .headers
+                .keys()
+                .any(|attached| attached.eq_ignore_ascii_case(name))
+        })
+        .filter_map(|(name, value)| {
+            value.as_str().map(|value| Fetch::HeaderEntry {
+                name: name.clone(),
+                value: value.to_string(),
+            })
+        })
+        .collect();
+    headers.extend(
+        credential
+            .headers
+            .iter()
+            .map(|(name, value)| Fetch::HeaderEntry {
+                name: name.clone(),
+                value: value.clone(),
+            }),
+    );
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    use headless_chrome::browser::tab::RequestPausedDecision;
+    use headless_chrome::protocol::cdp::Fetch::events::RequestPausedEvent;
+    use headless_chrome::protocol::cdp::Network::ErrorReason;
+    use serde_json::json;
+
+    use super::{RequestGuard, RequestInterceptor};
+    use crate::fetch_credentials::FetchHeaders;
+    use crate::url_guard::{BlockedUrl, UrlGuard};
+
+    const MAIN_FRAME: &str = "main-frame";
+
+    fn interceptor(credential: Option<FetchHeaders>) -> RequestInterceptor {
+        RequestInterceptor {
+            guard: Arc::new(RequestGuard {
+                guard: UrlGuard::default(),
+                resolve: false,
+                allowed_hosts: Mutex::default(),
+            }),
+            credential,
+            main_frame: MAIN_FRAME.to_string(),
+            blocked_document: Arc::default(),
+        }
+    }
+
+    fn paused(url: &str, resource_type: &str, frame_id: &str) -> RequestPausedEvent {
+        serde_json::from_value(json!({
+            "params": {
+                "requestId": "interception-1",
+                "request": {
+                    "url": url,
+                    "method": "GET",
+                    "headers": {"Accept": "text/html"},
+                    "initialPriority": "VeryHigh",
+                    "referrerPolicy": "no-referrer",
+                },
+                "frameId": frame_id,
+                "resourceType": resource_type,
+            }
+        }))
+        .expect("valid requestPaused event")
+    }
+
+    fn is_refused(decision: &RequestPausedDecision) -> bool {
+        matches!(
+            decision,
+            RequestPausedDecision::Fail(fail) if fail.error_reason == ErrorReason::BlockedByClient
+        )
+    }
+
+    fn blocked_document(interceptor: &RequestInterceptor) -> Option<BlockedUrl> {
+        interceptor
+            .blocked_document
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|error| error.downcast_ref::<BlockedUrl>().cloned())
+    }
+
+    #[test]
+    fn redirect_to_a_private_address_fails_the_page() {
+        let interceptor = interceptor(None);
+        let page = interceptor.decide(paused("https://example.com/", "Document", MAIN_FRAME));
+        assert!(matches!(page, RequestPausedDecision::Continue(_)));
+        assert_eq!(blocked_document(&interceptor), None);
+
+        // Chrome pauses the request a redirect leads to like a new one
+        let redirect = interceptor.decide(paused(
+            "http://169.254.169.254/latest/meta-data/",
+            "Document",
+            MAIN_FRAME,
+        ));
+        assert!(is_refused(&redirect));
+        assert_eq!(
+            blocked_document(&interceptor),
+            Some(BlockedUrl::Address("169.254.169.254".parse().unwrap()))
+        );
+    }
+
+    #[test]
+    fn refused_subresources_fail_without_failing_the_page() {
+        let interceptor = interceptor(None);
+        for (url, resource_type, frame_id) in [
+            ("http://10.0.0.1/pixel.png", "Image", MAIN_FRAME),
+            ("http://localhost:8080/admin", "XHR", MAIN_FRAME),
+            ("http://127.0.0.1/", "Document", "embedded-frame"),
+        ] {
+            assert!(is_refused(&interceptor.decide(paused(
+                url,
+                resource_type,
+                frame_id
+            ))));
+        }
+        assert_eq!(blocked_document(&interceptor), None);
+        let inline = interceptor.decide(paused("data:image/png;base64,AA==", "Image", MAIN_FRAME));
+        assert!(matches!(inline, RequestPausedDecision::Continue(_)));
+    }
 
-        // Tab is automatically closed when dropped
-        Ok(html)
+    #[test]
+    fn credential_goes_to_its_domain_only() {
+        let interceptor = interceptor(Some(FetchHeaders {
+            domain: "example.com".to_string(),
+            headers: BTreeMap::from([("Cookie".to_string(), "session=1".to_string())]),
+        }));
+        let headers = |url: &str| match interceptor.decide(paused(url, "Document", MAIN_FRAME)) {
+            RequestPausedDecision::Continue(Some(request)) => request.headers,
+            _ => panic!("{url} was not continued"),
+        };
+        let attached = headers("https://news.example.com/a").expect("credential attached");
+        assert!(attached
+            .iter()
+            .any(|header| header.name == "Cookie" && header.value == "session=1"));
+        assert!(attached.iter().any(|header| header.name == "Accept"));
+        assert_eq!(headers("https://evil.test/?next=example.com/"), None);
     }
 }
@@ -1,26 +1,32 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use std::sync::Arc;
 
 use anyhow::{Context, Result};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use flate2::write::GzEncoder;
 use flate2::Compression;
 use futures::future::join_all;
+use futures::stream::{FuturesUnordered, StreamExt};
 use lol_html::{element, rewrite_str, RewriteStrSettings};
-use reqwest::{Client, Client as HttpClient};
-use shared::{Bookmark, BookmarkTask, BookmarkTaskStatus};
-use tracing::{debug, error, info, warn};
+use shared::{Bookmark, BookmarkTask, BookmarkTaskStatus, ServerEvent, TaskStage, Visibility};
+use tracing::{debug, error, info, instrument, warn};
 use url::Url;
 use uuid::Uuid;
 
-use super::DAEMON_IDLE_SLEEP;
+use super::{record_stage, DAEMON_IDLE_SLEEP};
 use crate::bookmark_identity::{canonicalize_url, domain_from_url, make_bookmark_id};
 use crate::chrome_client::{ChromeClient, ChromeConnection};
-use crate::db::{self, PgPool};
-use crate::{readability, Config};
-
-const TASK_MAX_RETRIES: i16 = 5;
+use crate::db::{self, Database};
+use crate::domain_limit::{DomainLimiter, TooManyRequests};
+use crate::events::EventBus;
+use crate::fetch_client::FetchClient;
+use crate::fetch_credentials::{self, CredentialCipher, FetchHeaders};
+use crate::mailer::Mailer;
+use crate::robots::{self, RobotsCache, RobotsDisallowed};
+use crate::static_storage::{self, StorageBackend, StoredObject, GZIP_ENCODING};
+use crate::url_guard::{BlockedUrl, UrlGuard};
+use crate::{image_optimizer, llm, readability, rules, Config, ImageParams, TaskParams};
 
 #[derive(Debug, Clone)]
 #[allow(dead_code)] // FIXME: use or remove fields
@@ -39,6 +45,23 @@ struct ImageFound {
     url: Url,
 }
 
+/// Per-host rules followed while fetching.
+struct Politeness {
+    limiter: DomainLimiter,
+    /// `None` when robots.txt and noindex are not respected.
+    robots: Option<RobotsCache>,
+}
+
+/// Leases this instance takes on the tasks it processes, renewed while they
+/// run so replicas sharing the database leave them alone.
+struct TaskLease {
+    owner: String,
+    duration: chrono::Duration,
+}
+
+/// Outbound links kept per bookmark, in the order the article has them.
+const MAX_LINKS: usize = 200;
+
 #[derive(Debug, Clone)]
 struct ProcessorOutput {
     bookmark_id: String,
@@ -48,37 +71,86 @@ struct ProcessorOutput {
     text_content: String,
     images: Vec<Image>,
     html: String,
+    /// Canonical URLs the article links to
+    links: Vec<Url>,
+}
+
+pub fn should_retry(task: &BookmarkTask, params: &TaskParams) -> bool {
+    task.retries.unwrap_or(0) < params.task_max_retries
+}
+
+fn robots_disallowed(error: &anyhow::Error) -> Option<RobotsDisallowed> {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<RobotsDisallowed>())
+        .copied()
+}
+
+fn blocked_url(error: &anyhow::Error) -> Option<&BlockedUrl> {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<BlockedUrl>())
 }
 
-pub fn should_retry(task: &BookmarkTask) -> bool {
-    task.retries.unwrap_or(0) < TASK_MAX_RETRIES
+/// When a task that failed `retries` times is delivered again.
+fn next_delivery(params: &TaskParams, retries: i16, now: DateTime<Utc>) -> DateTime<Utc> {
+    let delay = llm::retry_delay(
+        std::time::Duration::from_secs(params.task_retry_base_delay_secs),
+        std::time::Duration::from_secs(params.task_retry_max_delay_secs),
+        retries.saturating_sub(1).max(0) as u32,
+    );
+    now + chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::MAX)
 }
 
 pub async fn run(
-    pool: &PgPool,
+    pool: &Database,
     config: &Config,
+    storage: &dyn StorageBackend,
     mut new_task_rx: tokio::sync::watch::Receiver<()>,
     new_bookmark_tx: tokio::sync::watch::Sender<()>,
+    events: &EventBus,
+    mailer: Option<&Mailer>,
 ) -> Result<()> {
-    let http: HttpClient = HttpClient::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
+    let http = FetchClient::new(&config.fetch, std::time::Duration::from_secs(30))
         .context("Failed to build HTTP client for bookmark ingestion")?;
-    let chrome_connection = match &config.chrome {
-        Some(chrome_params) => ChromeConnection::Remote {
-            host: chrome_params.chrome_host.clone(),
-            port: chrome_params.chrome_port,
-        },
-        None => ChromeConnection::Local,
+    let chrome_client = Arc::new(ChromeClient::new(
+        ChromeConnection::from_params(config.chrome.as_ref(), config.fetch.fetch_proxy.as_ref()),
+        UrlGuard::new(&config.fetch.fetch_allowed_networks),
+    ));
+    let politeness = Politeness {
+        limiter: DomainLimiter::new(&config.fetch),
+        robots: config
+            .fetch
+            .fetch_respect_robots
+            .then(|| RobotsCache::new(http.clone())),
     };
-
-    let chrome_client = Arc::new(ChromeClient::new(chrome_connection));
+    let lease = TaskLease {
+        owner: config
+            .task
+            .task_instance_id
+            .clone()
+            .unwrap_or_else(|| Uuid::new_v4().to_string()),
+        duration: chrono::Duration::seconds(config.task.task_lease_secs as i64),
+    };
+    info!(owner = %lease.owner, lease = ?lease.duration, "Leasing tasks");
     let mut interval = tokio::time::interval(DAEMON_IDLE_SLEEP);
     loop {
         // Process all available tasks continuously
         let mut any_processed = false;
         loop {
-            match execute_step(pool, &http, &chrome_client, config).await {
+            match execute_step(
+                pool,
+                &lease,
+                &http,
+                &chrome_client,
+                &politeness,
+                config,
+                storage,
+                events,
+                mailer,
+            )
+            .await
+            {
                 Ok(has_tasks) => {
                     if !has_tasks {
                         // No more tasks, exit inner loop
@@ -115,85 +187,360 @@ pub async fn run(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn execute_step(
-    pool: &PgPool,
-    http: &HttpClient,
+    pool: &Database,
+    lease: &TaskLease,
+    http: &FetchClient,
     chrome_client: &Arc<ChromeClient>,
+    politeness: &Politeness,
     config: &Config,
+    storage: &dyn StorageBackend,
+    events: &EventBus,
+    mailer: Option<&Mailer>,
 ) -> Result<bool> {
-    let tasks: Vec<BookmarkTask> = db::bookmark_task::peek(pool, Utc::now()).await?;
+    let tasks: Vec<BookmarkTask> =
+        db::bookmark_task::lease(pool, &lease.owner, Utc::now(), lease.duration).await?;
     if tasks.is_empty() {
         info!("No new task");
         return Ok(false);
     }
     info!("New tasks found: {}", tasks.len());
-    for task in tasks {
-        info!(?task, "Executing task");
-        match handle_task(pool, http, chrome_client, config, &task).await {
-            Ok(_) => {
-                db::bookmark_task::update(pool, task.clone(), BookmarkTaskStatus::Done, None, None)
-                    .await?;
-                info!(task_uuid = format!("{}", task.task_id), "Task executed")
-            }
-            Err(error) => {
-                if should_retry(&task) {
-                    let retry_value: i16 = task.retries.unwrap_or(0) + 1;
-                    db::bookmark_task::update(
-                        pool,
-                        task.clone(),
-                        BookmarkTaskStatus::Pending,
-                        Some(retry_value),
-                        None,
-                    )
-                    .await?;
-                    warn!(?task, ?error, "Task failed, retying",)
-                } else {
-                    db::bookmark_task::update(
-                        pool,
-                        task.clone(),
-                        BookmarkTaskStatus::Fail,
-                        None,
-                        Some(format!("{error}")),
-                    )
-                    .await?;
-                    error!(?task, ?error, "Task failed");
+    let concurrency = config.task.task_concurrency as usize;
+    // Queued tasks are leased too, their leases are renewed along with the
+    // running ones
+    let mut leased: HashSet<Uuid> = tasks.iter().map(|task| task.task_id).collect();
+    let heartbeat_period = (lease.duration / 3).to_std()?;
+    let mut heartbeat = tokio::time::interval_at(
+        tokio::time::Instant::now() + heartbeat_period,
+        heartbeat_period,
+    );
+    let task_count = tasks.len();
+    let mut failures = Vec::new();
+    let mut tasks = tasks.into_iter();
+    let mut in_flight = FuturesUnordered::new();
+    loop {
+        while in_flight.len() < concurrency {
+            let Some(task) = tasks.next() else {
+                break;
+            };
+            in_flight.push(async move {
+                let task_id = task.task_id;
+                let result = execute_task(
+                    pool,
+                    lease,
+                    http,
+                    chrome_client,
+                    politeness,
+                    config,
+                    storage,
+                    events,
+                    mailer,
+                    task,
+                )
+                .await;
+                (task_id, result)
+            });
+        }
+        if in_flight.is_empty() {
+            break;
+        }
+        tokio::select! {
+            Some((task_id, result)) = in_flight.next() => {
+                leased.remove(&task_id);
+                // One task failing to record its outcome doesn't abort the others
+                if let Err(error) = result {
+                    error!(task_id = %task_id, ?error, "Failed to execute task");
+                    failures.push(error);
                 }
             }
+            _ = heartbeat.tick() => renew_leases(pool, lease, &leased).await,
+        }
+    }
+    // None of the outcomes could be recorded, most likely the database is
+    // down: back off instead of leasing the same tasks again right away
+    if failures.len() == task_count {
+        if let Some(error) = failures.pop() {
+            return Err(error);
         }
     }
     Ok(true)
 }
 
+/// Heartbeat of the tasks still queued or running. A task whose lease was
+/// taken over keeps running, but its outcome is dropped.
+async fn renew_leases(pool: &Database, lease: &TaskLease, task_ids: &HashSet<Uuid>) {
+    let task_ids: Vec<Uuid> = task_ids.iter().copied().collect();
+    let expires_at = Utc::now() + lease.duration;
+    match db::bookmark_task::renew_leases(pool, &lease.owner, &task_ids, expires_at).await {
+        Ok(renewed) if renewed < task_ids.len() as u64 => warn!(
+            lost = task_ids.len() as u64 - renewed,
+            "Task leases lost, tasks cancelled or taken over"
+        ),
+        Ok(_) => {}
+        Err(error) => error!(?error, "Failed to renew task leases"),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+#[instrument(name = "bookmark_task", skip_all, fields(task_id = %task.task_id, url = %task.url))]
+async fn execute_task(
+    pool: &Database,
+    lease: &TaskLease,
+    http: &FetchClient,
+    chrome_client: &Arc<ChromeClient>,
+    politeness: &Politeness,
+    config: &Config,
+    storage: &dyn StorageBackend,
+    events: &EventBus,
+    mailer: Option<&Mailer>,
+    task: BookmarkTask,
+) -> Result<()> {
+    if let Some(wait) = Url::parse(&task.url)
+        .ok()
+        .and_then(|url| politeness.limiter.blocked_for(&url))
+    {
+        // The site asked us to slow down, this isn't an attempt
+        info!(task_id = %task.task_id, ?wait, "Domain backed off, postponing task");
+        let next_delivery_at = Utc::now() + chrono::Duration::from_std(wait)?;
+        record_stage(
+            pool,
+            task.task_id,
+            TaskStage::Retrying,
+            Some(format!("Domain backed off until {next_delivery_at}")),
+        )
+        .await;
+        db::bookmark_task::schedule_retry(
+            pool,
+            &lease.owner,
+            &task,
+            task.retries.unwrap_or(0),
+            next_delivery_at,
+        )
+        .await?;
+        return Ok(());
+    }
+    info!(?task, "Executing task");
+    let mut retry_after = None;
+    let mut retry_reason = None;
+    let (status, retries, fail_reason) = match handle_task(
+        pool,
+        http,
+        chrome_client,
+        politeness,
+        config,
+        storage,
+        &task,
+    )
+    .await
+    {
+        Ok(bookmark) => {
+            info!(task_uuid = format!("{}", task.task_id), "Task executed");
+            if let Some(bookmark) = bookmark {
+                events.publish(task.user_id, ServerEvent::BookmarkCreated { bookmark });
+            }
+            (BookmarkTaskStatus::Done, None, None)
+        }
+        Err(error) if robots_disallowed(&error).is_some() => {
+            // The site opted out, retrying won't help
+            let reason = robots_disallowed(&error).map(|reason| reason.to_string());
+            info!(task_id = %task.task_id, ?reason, "Task skipped");
+            (BookmarkTaskStatus::Fail, None, reason)
+        }
+        Err(error) if blocked_url(&error).is_some() => {
+            // Retrying won't make the address public
+            let reason = blocked_url(&error).map(|blocked| blocked.to_string());
+            warn!(task_id = %task.task_id, ?reason, "Task refused");
+            (BookmarkTaskStatus::Fail, None, reason)
+        }
+        Err(error) if should_retry(&task, &config.task) => {
+            warn!(?task, ?error, "Task failed, retying");
+            retry_after = error
+                .chain()
+                .find_map(|cause| cause.downcast_ref::<TooManyRequests>())
+                .map(|too_many| too_many.retry_after);
+            retry_reason = Some(format!("{error}"));
+            (
+                BookmarkTaskStatus::Pending,
+                Some(task.retries.unwrap_or(0) + 1),
+                None,
+            )
+        }
+        Err(error) => {
+            error!(?task, ?error, "Task failed");
+            (BookmarkTaskStatus::Fail, None, Some(format!("{error}")))
+        }
+    };
+    let mut next_delivery_at = task.next_delivery;
+    let recorded = match (&status, retries) {
+        (BookmarkTaskStatus::Pending, Some(retries)) => {
+            let now = Utc::now();
+            next_delivery_at = next_delivery(&config.task, retries, now);
+            if let Some(retry_after) = retry_after {
+                next_delivery_at =
+                    next_delivery_at.max(now + chrono::Duration::from_std(retry_after)?);
+            }
+            let recorded = db::bookmark_task::schedule_retry(
+                pool,
+                &lease.owner,
+                &task,
+                retries,
+                next_delivery_at,
+            )
+            .await?;
+            if recorded {
+                let detail = format!(
+                    "Attempt {retries} failed, next delivery at {next_delivery_at}: {}",
+                    retry_reason.unwrap_or_default()
+                );
+                record_stage(pool, task.task_id, TaskStage::Retrying, Some(detail)).await;
+            }
+            recorded
+        }
+        _ => {
+            let recorded = db::bookmark_task::update(
+                pool,
+                &lease.owner,
+                task.clone(),
+                status.clone(),
+                retries,
+                fail_reason.clone(),
+            )
+            .await?;
+            if let (true, Some(reason)) = (recorded, &fail_reason) {
+                record_stage(pool, task.task_id, TaskStage::Failed, Some(reason.clone())).await;
+            }
+            recorded
+        }
+    };
+    if !recorded {
+        warn!(task_id = %task.task_id, "Task cancelled or taken over meanwhile, outcome dropped");
+        return Ok(());
+    }
+    if let (Some(mailer), Some(reason)) = (mailer, fail_reason.as_deref()) {
+        notify_task_failed(pool, mailer, &task, reason).await;
+    }
+    events.publish(
+        task.user_id,
+        ServerEvent::TaskUpdated {
+            task: BookmarkTask {
+                status,
+                retries,
+                fail_reason,
+                next_delivery: next_delivery_at,
+                ..task
+            },
+        },
+    );
+    Ok(())
+}
+
+/// Emails the owner of a permanently failed task when they have a verified
+/// address. Delivery problems are logged, they never fail the task loop.
+async fn notify_task_failed(pool: &Database, mailer: &Mailer, task: &BookmarkTask, reason: &str) {
+    let user = match db::user::get_by_id(pool, &task.user_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return,
+        Err(error) => {
+            error!(?error, user_id = %task.user_id, "Failed to load user for task notification");
+            return;
+        }
+    };
+    let Some(email) = user.email.filter(|_| user.email_verified_at.is_some()) else {
+        return;
+    };
+    if let Err(error) = mailer.send_task_failed(&email, &task.url, reason).await {
+        error!(?error, task_id = %task.task_id, "Failed to send task failure notification");
+    }
+}
+
 async fn handle_task(
-    pool: &PgPool,
-    http: &HttpClient,
+    pool: &Database,
+    http: &FetchClient,
     chrome_client: &Arc<ChromeClient>,
+    politeness: &Politeness,
     config: &Config,
+    storage: &dyn StorageBackend,
     task: &BookmarkTask,
-) -> Result<()> {
-    if db::bookmark::get_by_canonical_url_and_user_id(pool, &task.url, task.user_id)
-        .await?
-        .is_some()
+) -> Result<Option<Bookmark>> {
+    if db::bookmark::restore_by_canonical_url(pool, task.user_id, &task.url).await? {
+        info!(?task, "Bookmark saved again restored from the trash");
+    }
+    if let Some(existing) =
+        db::bookmark::get_by_canonical_url_and_user_id(pool, &task.url, task.user_id).await?
     {
+        if task.refresh {
+            refresh_bookmark(
+                pool,
+                http,
+                chrome_client,
+                politeness,
+                config,
+                storage,
+                task,
+                existing,
+            )
+            .await?;
+            return Ok(None);
+        }
         info!(?task, "Duplicated bookmark");
-        return Ok(());
+        record_duplicate(pool, task, &existing.bookmark_id).await;
+        return Ok(None);
     }
 
     info!("Processing new bookmark for url={}", &task.url);
-    let output = process_url(http, chrome_client, &task.user_id, &task.url)
-        .await
-        .with_context(|| format!("process_url: {}", &task.url))?;
+    let robots = politeness.robots.as_ref().filter(|_| !task.ignore_robots);
+    let output = process_url(
+        pool,
+        http,
+        chrome_client,
+        &politeness.limiter,
+        robots,
+        &config.image,
+        &CredentialCipher::new(&config.hmac_key),
+        task,
+    )
+    .await
+    .with_context(|| format!("process_url: {}", &task.url))?;
+
+    let user_rules = db::rule::list(pool, task.user_id).await?;
+    let applied = rules::evaluate(&user_rules, &output.url, &output.domain, &output.title);
+    if applied.matched > 0 {
+        info!(
+            url = %task.url,
+            rule_count = applied.matched,
+            tags = ?applied.tags,
+            favorite = applied.favorite,
+            skip_ai = applied.skip_ai,
+            "Rules applied to new bookmark"
+        );
+    }
+    let mut tags = task.tags.to_owned();
+    if !applied.tags.is_empty() {
+        let tags = tags.get_or_insert_with(Vec::new);
+        for tag in applied.tags {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
 
     let bookmark = Bookmark {
-        bookmark_id: output.bookmark_id,
+        bookmark_id: output.bookmark_id.clone(),
         user_id: task.user_id,
-        url: output.url,
-        domain: output.domain,
-        title: output.title,
-        tags: task.tags.to_owned(),
+        url: output.url.clone(),
+        domain: output.domain.clone(),
+        title: output.title.clone(),
+        tags,
         summary: None,
         created_at: Utc::now(),
         updated_at: None,
+        word_count: 0,
+        reading_time_minutes: 0,
+        kind: None,
+        favorite: applied.favorite,
+        original_title: None,
+        visibility: Visibility::Private,
     };
 
     let bookmark_saved = match db::bookmark::save(pool, &bookmark, &output.text_content).await {
@@ -202,7 +549,8 @@ async fn handle_task(
             if constraint == "duplicate_bookmark" =>
         {
             info!(url = %bookmark.url, user_id = %bookmark.user_id, "Duplicated bookmark");
-            return Ok(());
+            record_duplicate(pool, task, &bookmark.bookmark_id).await;
+            return Ok(None);
         }
         Err(error) => {
             return Err(error).with_context(|| {
@@ -214,36 +562,118 @@ async fn handle_task(
         }
     };
 
-    save_static_content(
-        config,
-        &bookmark_saved,
+    if applied.skip_ai {
+        db::ai::skip_processing(pool, task.user_id, &bookmark_saved.bookmark_id).await?;
+    }
+    store_capture(pool, storage, task, &bookmark_saved, &output).await?;
+    info!(
+        url = task.url,
+        bookmark_id = format!("{}", &bookmark_saved.bookmark_id),
+        "Bookmark created",
+    );
+    Ok(Some(bookmark_saved))
+}
+
+/// Captures the page of `existing` again, keeping the current capture as a
+/// version of it.
+#[allow(clippy::too_many_arguments)]
+async fn refresh_bookmark(
+    pool: &Database,
+    http: &FetchClient,
+    chrome_client: &Arc<ChromeClient>,
+    politeness: &Politeness,
+    config: &Config,
+    storage: &dyn StorageBackend,
+    task: &BookmarkTask,
+    existing: Bookmark,
+) -> Result<()> {
+    info!(bookmark_id = %existing.bookmark_id, url = %task.url, "Refreshing bookmark");
+    let robots = politeness.robots.as_ref().filter(|_| !task.ignore_robots);
+    let output = process_url(
+        pool,
+        http,
+        chrome_client,
+        &politeness.limiter,
+        robots,
+        &config.image,
+        &CredentialCipher::new(&config.hmac_key),
+        task,
+    )
+    .await
+    .with_context(|| format!("process_url: {}", &task.url))?;
+
+    let page_key = format!("{}/{}/index.html", task.user_id, existing.bookmark_id);
+    let previous_html = match storage.get(&page_key).await? {
+        Some(page) => {
+            let page = static_storage::decode(page.bytes, page.content_encoding)?;
+            Some(String::from_utf8_lossy(&page).into_owned())
+        }
+        None => None,
+    };
+    let bookmark = Bookmark {
+        title: output.title.clone(),
+        ..existing
+    };
+    let refreshed = db::bookmark::refresh_capture(
+        pool,
+        &bookmark,
+        &output.text_content,
+        previous_html.as_deref(),
+    )
+    .await
+    .with_context(|| format!("refresh_capture: bookmark_id={}", &bookmark.bookmark_id))?;
+
+    store_capture(pool, storage, task, &refreshed, &output).await?;
+    info!(bookmark_id = %refreshed.bookmark_id, "Bookmark refreshed");
+    Ok(())
+}
+
+/// Stores what was captured besides the text, and ties the task to the
+/// bookmark.
+async fn store_capture(
+    pool: &Database,
+    storage: &dyn StorageBackend,
+    task: &BookmarkTask,
+    bookmark: &Bookmark,
+    output: &ProcessorOutput,
+) -> Result<()> {
+    let static_bytes = save_static_content(
+        storage,
+        bookmark,
         &output.images,
         &output.html,
         &task.user_id,
     )
     .await
-    .with_context(|| {
-        format!(
-            "save_static_content: bookmark_id={}",
-            &bookmark_saved.bookmark_id
-        )
-    })?;
+    .with_context(|| format!("save_static_content: bookmark_id={}", &bookmark.bookmark_id))?;
+    db::storage::set_static_bytes(pool, task.user_id, &bookmark.bookmark_id, static_bytes).await?;
 
-    info!(
-        url = task.url,
-        bookmark_id = format!("{}", &bookmark_saved.bookmark_id),
-        "Bookmark created",
-    );
+    db::link::replace_for_bookmark(pool, task.user_id, &bookmark.bookmark_id, &output.links)
+        .await?;
+    db::bookmark_task::set_bookmark_id(pool, task.task_id, &bookmark.bookmark_id).await?;
+    record_stage(
+        pool,
+        task.task_id,
+        TaskStage::Saved,
+        Some(bookmark.bookmark_id.clone()),
+    )
+    .await;
     Ok(())
 }
 
+async fn record_duplicate(pool: &Database, task: &BookmarkTask, bookmark_id: &str) {
+    let detail = format!("Already bookmarked as {bookmark_id}");
+    record_stage(pool, task.task_id, TaskStage::Saved, Some(detail)).await;
+}
+
+#[instrument(skip_all, fields(bookmark_id = %bookmark.bookmark_id))]
 async fn save_static_content(
-    config: &Config,
+    storage: &dyn StorageBackend,
     bookmark: &Bookmark,
     images: &[Image],
     content: &str,
     user_id: &Uuid,
-) -> Result<()> {
+) -> Result<i64> {
     info!(
         bookmark_id = %bookmark.bookmark_id,
         user_id = %user_id,
@@ -252,15 +682,7 @@ async fn save_static_content(
         "Saving static content"
     );
 
-    let bookmark_dir = config
-        .data_dir
-        .join(user_id.to_string())
-        .join(&bookmark.bookmark_id);
-
-    if !bookmark_dir.exists() {
-        debug!(bookmark_dir = ?bookmark_dir, "Creating bookmark directory");
-        tokio::fs::create_dir_all(&bookmark_dir).await?;
-    }
+    let bookmark_key = format!("{user_id}/{}", bookmark.bookmark_id);
 
     // Compress HTML content using gzip
     let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
@@ -270,8 +692,17 @@ async fn save_static_content(
     let original_size = content.len();
     let compressed_size = compressed_content.len();
 
-    let index = bookmark_dir.join("index.html.gz");
-    tokio::fs::write(&index, compressed_content).await?;
+    storage
+        .put(
+            &format!("{bookmark_key}/index.html"),
+            StoredObject {
+                content_type: Some("text/html; charset=utf-8".to_string()),
+                content_encoding: Some(GZIP_ENCODING.to_string()),
+                bytes: compressed_content,
+                last_modified: None,
+            },
+        )
+        .await?;
 
     let reduction_pct = (compressed_size * 100)
         .checked_div(original_size)
@@ -285,18 +716,31 @@ async fn save_static_content(
         reduction_pct
     );
 
+    // Counted towards the user's storage quota
+    let mut static_bytes = compressed_size;
     let mut saved_images = 0;
     let mut skipped_images = 0;
     for image in images.iter() {
-        let image_path = bookmark_dir.join(&image.id);
-        if image_path.exists() {
-            debug!(image_path = ?image_path, "Image already exists, skipping");
+        let image_key = format!("{bookmark_key}/{}", image.id);
+        static_bytes += image.bytes.len();
+        if storage.exists(&image_key).await? {
+            debug!(image_key = %image_key, "Image already exists, skipping");
             skipped_images += 1;
             continue;
         }
-        tokio::fs::write(&image_path, &image.bytes).await?;
+        storage
+            .put(
+                &image_key,
+                StoredObject {
+                    content_type: Some(image.content_type.clone()),
+                    content_encoding: None,
+                    bytes: image.bytes.clone(),
+                    last_modified: None,
+                },
+            )
+            .await?;
         debug!(
-            image_path = ?image_path,
+            image_key = %image_key,
             size_bytes = %image.bytes.len(),
             "Image saved"
         );
@@ -309,15 +753,23 @@ async fn save_static_content(
         skipped_images = %skipped_images,
         "Static content saved successfully"
     );
-    Ok(())
+    Ok(static_bytes as i64)
 }
 
+#[allow(clippy::too_many_arguments)]
+#[instrument(name = "fetch", skip_all, fields(url = %task.url))]
 async fn process_url(
-    http: &Client,
+    pool: &Database,
+    http: &FetchClient,
     chrome_client: &Arc<ChromeClient>,
-    user_id: &Uuid,
-    original_url_str: &str,
+    limiter: &DomainLimiter,
+    robots: Option<&RobotsCache>,
+    image_params: &ImageParams,
+    cipher: &CredentialCipher,
+    task: &BookmarkTask,
 ) -> Result<ProcessorOutput> {
+    let user_id = &task.user_id;
+    let original_url_str = task.url.as_str();
     info!(
         url = %original_url_str,
         user_id = %user_id,
@@ -329,17 +781,53 @@ async fn process_url(
     let bookmark_id: String = make_bookmark_id(&original_url)?;
     debug!(bookmark_id = %bookmark_id, "Generated bookmark_id");
 
+    http.check(&original_url).await?;
+
+    if let Some(robots) = robots {
+        if !robots.is_allowed(limiter, &original_url).await {
+            return Err(RobotsDisallowed::RobotsTxt.into());
+        }
+    }
+
+    let domain = domain_from_url(&original_url)?;
+    let credential = fetch_credentials::headers_for(pool, cipher, *user_id, &domain).await?;
+    if let Some(credential) = &credential {
+        debug!(credential_domain = %credential.domain, "Attaching fetch credential");
+    }
+
     debug!(url = %original_url, "Fetching HTML content using Chrome");
-    let raw_html = fetch_html_content(chrome_client, &original_url).await?;
+    let user_agent = original_url
+        .host_str()
+        .and_then(|host| http.user_agent_for(host));
+    let raw_html = fetch_html_content(
+        chrome_client,
+        limiter,
+        &original_url,
+        credential.as_ref(),
+        user_agent,
+    )
+    .await?;
+    if robots.is_some() && robots::is_noindex(&raw_html) {
+        return Err(RobotsDisallowed::NoIndex.into());
+    }
     info!(url = %original_url, size_bytes = %raw_html.len(), "HTML content fetched from Chrome");
+    let detail = format!("{} bytes", raw_html.len());
+    record_stage(pool, task.task_id, TaskStage::Fetched, Some(detail)).await;
 
+    let extraction_rules = db::extraction_rule::list(pool, *user_id).await?;
     debug!("Processing content with readability");
-    let readability_response = readability::process(raw_html).await?;
+    let readability_response =
+        readability::process(raw_html, readability::rule_for(&extraction_rules, &domain)).await?;
     info!(
         title = %readability_response.title,
         text_length = %readability_response.text_content.len(),
         "Content processed"
     );
+    let detail = readability_response.title.clone();
+    record_stage(pool, task.task_id, TaskStage::Readability, Some(detail)).await;
+
+    let links = find_links(&original_url, &readability_response.content)?;
+    debug!(link_count = %links.len(), "Found outbound links");
 
     let images_found = find_images(&original_url, &readability_response.content)?;
     info!(image_count = %images_found.len(), "Found images to process");
@@ -347,7 +835,7 @@ async fn process_url(
     let processed_images = join_all(
         images_found
             .iter()
-            .map(|image_found| process_image_found(http, image_found)),
+            .map(|image_found| process_image_found(http, limiter, image_params, image_found)),
     )
     .await;
 
@@ -364,16 +852,29 @@ async fn process_url(
         failure_count = %images_err.len(),
         "Image processing completed"
     );
+    let detail = format!(
+        "{} downloaded, {} failed",
+        images_ok.len(),
+        images_err.len()
+    );
+    record_stage(pool, task.task_id, TaskStage::Images, Some(detail)).await;
 
     images_err.into_iter().for_each(|error| {
         warn!("Images with error, they will be ignored, error={:?}", error);
     });
 
-    let images_index: HashMap<String, Image> = images_ok
-        .into_iter()
-        .flat_map(|result| result.ok())
-        .map(|image| (image.original_src.clone(), image))
-        .collect();
+    // Images past the per-bookmark budget keep pointing to the original site
+    let mut stored_bytes = 0;
+    let mut images_index: HashMap<String, Image> = HashMap::new();
+    for image in images_ok.into_iter().flat_map(|result| result.ok()) {
+        let size = image.bytes.len() as u64;
+        if stored_bytes + size > image_params.image_max_bookmark_bytes {
+            warn!(url = %image.original_url, size_bytes = %size, "Bookmark image budget exceeded, skipping image");
+            continue;
+        }
+        stored_bytes += size;
+        images_index.insert(image.original_src.clone(), image);
+    }
 
     let (rewrite_html, images) = rewrite_images(
         &bookmark_id,
@@ -386,11 +887,12 @@ async fn process_url(
     Ok(ProcessorOutput {
         bookmark_id,
         url: original_url.to_string(),
-        domain: domain_from_url(&original_url)?,
+        domain,
         title: readability_response.title,
         text_content: readability_response.text_content,
         images,
         html: rewrite_html,
+        links,
     })
 }
 
@@ -414,7 +916,7 @@ async fn rewrite_images(
                     src
                 }
                 None => {
-                    warn!("Something weird happened, processed image not found, img_src={img_src}");
+                    warn!("Image not captured, keeping original, img_src={img_src}");
                     img_src
                 }
             };
@@ -427,15 +929,39 @@ async fn rewrite_images(
     Ok((new_content, images))
 }
 
-async fn process_image_found(http: &Client, image_found: &ImageFound) -> Result<Image> {
+async fn process_image_found(
+    http: &FetchClient,
+    limiter: &DomainLimiter,
+    params: &ImageParams,
+    image_found: &ImageFound,
+) -> Result<Image> {
+    if let Some(wait) = limiter.blocked_for(&image_found.url) {
+        anyhow::bail!(
+            "Image host backed off for {wait:?}, skipping {}",
+            image_found.url
+        );
+    }
+    let _permit = limiter.acquire(&image_found.url).await;
     let start = std::time::Instant::now();
     debug!(url = %image_found.url, "Downloading image");
 
-    let response = http
-        .get(image_found.url.to_string())
-        .send()
-        .await?
-        .error_for_status()?;
+    let response = http.get(&image_found.url)?.send().await?;
+    if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok());
+        let too_many = TooManyRequests::new(&image_found.url, retry_after);
+        limiter.back_off(&image_found.url, too_many.retry_after);
+        return Err(too_many.into());
+    }
+    let mut response = response.error_for_status()?;
+    if response
+        .content_length()
+        .is_some_and(|length| length > params.image_max_bytes)
+    {
+        anyhow::bail!("Image too large, skipping {}", image_found.url);
+    }
 
     let content_type = response
         .headers()
@@ -444,7 +970,14 @@ async fn process_image_found(http: &Client, image_found: &ImageFound) -> Result<
         .unwrap_or("application/octet-stream")
         .to_string();
 
-    let bytes = response.bytes().await?.to_vec();
+    // Servers may omit or lie about Content-Length
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if (bytes.len() + chunk.len()) as u64 > params.image_max_bytes {
+            anyhow::bail!("Image too large, skipping {}", image_found.url);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
     let elapsed = start.elapsed();
 
     info!(
@@ -455,12 +988,26 @@ async fn process_image_found(http: &Client, image_found: &ImageFound) -> Result<
         "Image downloaded"
     );
 
+    let original_size = bytes.len();
+    let max_dimension = params.image_max_dimension;
+    let optimized = tokio::task::spawn_blocking(move || {
+        image_optimizer::optimize(bytes, &content_type, max_dimension)
+    })
+    .await?;
+    debug!(
+        url = %image_found.url,
+        original_size,
+        optimized_size = optimized.bytes.len(),
+        content_type = %optimized.content_type,
+        "Image optimized"
+    );
+
     Ok(Image {
         id: image_found.id.clone(),
         original_url: image_found.url.to_string(),
         original_src: image_found.src.to_string(),
-        content_type,
-        bytes,
+        content_type: optimized.content_type,
+        bytes: optimized.bytes,
     })
 }
 
@@ -503,12 +1050,54 @@ fn find_images(base_url: &Url, content: &str) -> Result<Vec<ImageFound>> {
     Ok(images_found)
 }
 
-async fn fetch_html_content(chrome_client: &Arc<ChromeClient>, url: &Url) -> Result<String> {
+/// Canonical http(s) URLs of the content's anchors, relative ones resolved
+/// against `base_url`, without links back to the page itself.
+fn find_links(base_url: &Url, content: &str) -> Result<Vec<Url>> {
+    let mut seen = HashSet::from([base_url.clone()]);
+    let mut links = Vec::new();
+    let _ = rewrite_str(
+        content,
+        RewriteStrSettings::new().append_element_content_handler(element!("a[href]", |el| {
+            let href = el.get_attribute("href").expect("a[href] was required");
+            let link = base_url
+                .join(href.trim())
+                .ok()
+                .filter(|link| matches!(link.scheme(), "http" | "https"))
+                .and_then(|link| canonicalize_url(link).ok());
+            if let Some(link) = link {
+                if links.len() < MAX_LINKS && seen.insert(link.clone()) {
+                    links.push(link);
+                }
+            }
+            Ok(())
+        })),
+    )?;
+    Ok(links)
+}
+
+#[instrument(skip_all, fields(url = %url))]
+async fn fetch_html_content(
+    chrome_client: &Arc<ChromeClient>,
+    limiter: &DomainLimiter,
+    url: &Url,
+    credential: Option<&FetchHeaders>,
+    user_agent: Option<&str>,
+) -> Result<String> {
+    let _permit = limiter.acquire(url).await;
     let start = std::time::Instant::now();
-    let content = chrome_client
-        .fetch_rendered_html(url)
+    let content = match chrome_client
+        .fetch_rendered_html(url, credential, user_agent)
         .await
-        .with_context(|| format!("Failed to fetch HTML from Chrome for URL: {}", url))?;
+    {
+        Ok(content) => content,
+        Err(error) => {
+            if let Some(too_many) = error.downcast_ref::<TooManyRequests>() {
+                limiter.back_off(url, too_many.retry_after);
+            }
+            return Err(error)
+                .with_context(|| format!("Failed to fetch HTML from Chrome for URL: {}", url));
+        }
+    };
     let elapsed = start.elapsed();
     info!(
         elapsed = ?elapsed,
@@ -518,3 +1107,55 @@ async fn fetch_html_content(chrome_client: &Arc<ChromeClient>, url: &Url) -> Res
     );
     Ok(content)
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use url::Url;
+
+    use super::{find_links, next_delivery};
+    use crate::TaskParams;
+
+    #[test]
+    fn links_are_resolved_canonical_and_unique() {
+        let base = Url::parse("https://example.com/posts/tokio").unwrap();
+        let content = r##"<p>
+            <a href="/posts/tokio#intro">self</a>
+            <a href="../docs">docs</a>
+            <a href="https://github.com:443/tokio-rs/tokio#readme">repo</a>
+            <a href="https://github.com/tokio-rs/tokio">repo again</a>
+            <a href="mailto:someone@example.com">mail</a>
+        </p>"##;
+
+        let links = find_links(&base, content).unwrap();
+
+        let links: Vec<_> = links.iter().map(Url::as_str).collect();
+        assert_eq!(
+            links,
+            [
+                "https://example.com/docs",
+                "https://github.com/tokio-rs/tokio"
+            ]
+        );
+    }
+
+    #[test]
+    fn next_delivery_backs_off_exponentially_up_to_the_cap() {
+        let params = TaskParams {
+            task_max_retries: 5,
+            task_retry_base_delay_secs: 30,
+            task_retry_max_delay_secs: 100,
+            task_concurrency: 1,
+            task_lease_secs: 300,
+            task_instance_id: None,
+        };
+        let now = Utc::now();
+
+        assert_eq!(next_delivery(&params, 1, now), now + Duration::seconds(30));
+        for retries in 2..=10 {
+            let delay = next_delivery(&params, retries, now) - now;
+            assert!(delay >= Duration::seconds(30), "retries={retries}");
+            assert!(delay <= Duration::seconds(100), "retries={retries}");
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, NaiveTime, Utc};
+use tracing::{debug, error, info};
+use uuid::Uuid;
+
+use crate::db::{digest, user, user_settings, Database};
+use crate::llm::{self, LlmClient, UsageContext};
+use crate::mailer::Mailer;
+
+const MAX_DIGEST_BOOKMARKS: usize = 50;
+
+/// Writes the weekly digests of the last full week still due.
+pub async fn run(pool: &Database, client: &LlmClient, mailer: Option<&Mailer>) -> Result<()> {
+    execute_step(pool, client, mailer, Utc::now()).await
+}
+
+/// The last full ISO week (Monday 00:00 UTC to the following Monday) before
+/// `now`.
+fn previous_week(now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let monday =
+        now.date_naive() - ChronoDuration::days(i64::from(now.weekday().num_days_from_monday()));
+    let period_end = monday.and_time(NaiveTime::MIN).and_utc();
+    (period_end - ChronoDuration::weeks(1), period_end)
+}
+
+async fn execute_step(
+    pool: &Database,
+    client: &LlmClient,
+    mailer: Option<&Mailer>,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    let (period_start, period_end) = previous_week(now);
+    let users = digest::users_due(pool, period_start, period_end).await?;
+    if users.is_empty() {
+        info!(%period_start, "No weekly digest due");
+        return Ok(());
+    }
+
+    info!(%period_start, user_count = users.len(), "Writing weekly digests");
+    for user_id in users {
+        if let Err(error) =
+            write_digest(pool, client, mailer, user_id, period_start, period_end).await
+        {
+            error!(user_id = %user_id, ?error, "Failed to write weekly digest");
+        }
+    }
+    Ok(())
+}
+
+async fn write_digest(
+    pool: &Database,
+    client: &LlmClient,
+    mailer: Option<&Mailer>,
+    user_id: Uuid,
+    period_start: DateTime<Utc>,
+    period_end: DateTime<Utc>,
+) -> Result<()> {
+    let settings = user_settings::get(pool, user_id).await?;
+    let bookmarks = digest::bookmarks_in_period(
+        pool,
+        user_id,
+        period_start,
+        period_end,
+        MAX_DIGEST_BOOKMARKS,
+    )
+    .await?;
+    let client = client.with_usage_context(UsageContext::user(user_id));
+    let content =
+        llm::write_digest(&client, &bookmarks, settings.summary_language.as_deref()).await?;
+    let bookmark_ids = bookmarks
+        .iter()
+        .map(|bookmark| bookmark.bookmark_id.clone())
+        .collect::<Vec<_>>();
+
+    let Some(stored) = digest::insert(
+        pool,
+        user_id,
+        period_start,
+        period_end,
+        &bookmark_ids,
+        &content,
+    )
+    .await?
+    else {
+        info!(user_id = %user_id, %period_start, "Weekly digest already written");
+        return Ok(());
+    };
+    info!(
+        user_id = %user_id,
+        digest_id = %stored.digest_id,
+        bookmark_count = bookmark_ids.len(),
+        "Weekly digest written"
+    );
+
+    let Some(mailer) = mailer else {
+        return Ok(());
+    };
+    // Only the address the user proved to own, never one typed in unverified
+    let Some(to) = user::get_by_id(pool, &user_id)
+        .await?
+        .and_then(|user| user.email.filter(|_| user.email_verified_at.is_some()))
+    else {
+        debug!(user_id = %user_id, "No verified email, weekly digest not emailed");
+        return Ok(());
+    };
+    let subject = format!(
+        "Your bookmarks for the week of {}",
+        period_start.format("%Y-%m-%d")
+    );
+    mailer.send(&to, &subject, stored.content).await?;
+    digest::mark_emailed(pool, user_id, stored.digest_id).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::previous_week;
+
+    #[test]
+    fn previous_week_is_last_full_iso_week() {
+        // Wednesday
+        let now = Utc.with_ymd_and_hms(2024, 5, 15, 13, 30, 0).unwrap();
+        let (start, end) = previous_week(now);
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 5, 6, 0, 0, 0).unwrap());
+        assert_eq!(end, Utc.with_ymd_and_hms(2024, 5, 13, 0, 0, 0).unwrap());
+
+        // Monday midnight starts a new week
+        let now = Utc.with_ymd_and_hms(2024, 5, 13, 0, 0, 0).unwrap();
+        assert_eq!(
+            previous_week(now).0,
+            Utc.with_ymd_and_hms(2024, 5, 6, 0, 0, 0).unwrap()
+        );
+    }
+}
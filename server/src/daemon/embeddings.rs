@@ -1,21 +1,23 @@
 use anyhow::{Context, Result};
 use chrono::Utc;
-use tracing::{debug, error, info, warn};
+use shared::TaskStage;
+use tracing::{debug, error, info, instrument, warn};
 
 use super::{
-    ai_generation_backoff, AiDaemonSettings, AI_GENERATION_MAX_RETRIES, DAEMON_IDLE_SLEEP,
+    ai_generation_backoff, hash_chunk, record_bookmark_stage, AiDaemonSettings,
+    AI_GENERATION_MAX_RETRIES, DAEMON_IDLE_SLEEP,
 };
 use crate::db::ai::{self, EmbeddingGenerationCandidate};
 use crate::db::bookmark::AiGenerationStatus;
-use crate::db::chunks::store_chunks_with_embeddings;
-use crate::db::PgPool;
-use crate::llm::{self, LlmClient};
+use crate::db::chunks::{cache_embeddings, get_cached_embeddings, store_chunks_with_embeddings};
+use crate::db::{ensure_embedding_profile, Database};
+use crate::llm::{self, LlmClient, UsageContext};
 use crate::{tokenizer, EMBEDDING_PIPELINE_VERSION};
 
 const QUERY_LIMIT: usize = 5;
 
 pub async fn run(
-    pool: &PgPool,
+    pool: &Database,
     mut new_bookmark_rx: tokio::sync::watch::Receiver<()>,
     client: &LlmClient,
     settings: &AiDaemonSettings,
@@ -55,7 +57,7 @@ pub async fn run(
 }
 
 async fn execute_step(
-    pool: &PgPool,
+    pool: &Database,
     client: &LlmClient,
     settings: &AiDaemonSettings,
 ) -> Result<bool> {
@@ -83,6 +85,14 @@ async fn execute_step(
                     chunk_count,
                     "Successfully processed embeddings"
                 );
+                record_bookmark_stage(
+                    pool,
+                    candidate.user_id,
+                    &candidate.bookmark_id,
+                    TaskStage::Chunked,
+                    Some(format!("{chunk_count} chunks embedded")),
+                )
+                .await;
             }
             Err(error) => {
                 let attempts = candidate.attempts + 1;
@@ -130,9 +140,10 @@ async fn execute_step(
     Ok(true)
 }
 
+#[instrument(name = "chunk", skip_all, fields(bookmark_id = %candidate.bookmark_id))]
 async fn process_bookmark_chunks(
     candidate: &EmbeddingGenerationCandidate,
-    pool: &PgPool,
+    pool: &Database,
     client: &LlmClient,
     settings: &AiDaemonSettings,
 ) -> Result<usize> {
@@ -147,7 +158,8 @@ async fn process_bookmark_chunks(
         return Ok(0);
     }
 
-    let chunks = tokenizer::windowed_chunks(
+    let chunks = tokenizer::chunk_text(
+        settings.embed_chunk_strategy,
         settings.embed_chunk_size,
         settings.embed_chunk_overlap,
         &candidate.text_content,
@@ -165,9 +177,37 @@ async fn process_bookmark_chunks(
         return Ok(0);
     }
 
+    let usage_client = client.with_usage_context(UsageContext::bookmark(
+        candidate.user_id,
+        &candidate.bookmark_id,
+    ));
+    // Unchanged chunks of reprocessed content reuse the embeddings they got
+    let profile = client.embedding_profile();
+    let content_hashes = chunks
+        .iter()
+        .map(|chunk| hash_chunk(chunk))
+        .collect::<Vec<_>>();
+    let mut cached = get_cached_embeddings(pool, candidate.user_id, &profile, &content_hashes)
+        .await
+        .context("Failed to read cached embeddings")?;
+    let cache_hits = content_hashes
+        .iter()
+        .filter(|hash| cached.contains_key(*hash))
+        .count();
+    metrics::counter!("embedding_cache_hits_total").increment(cache_hits as u64);
+    metrics::counter!("embedding_cache_misses_total").increment((chunks.len() - cache_hits) as u64);
+    debug!(
+        chunk_count = chunks.len(),
+        cache_hits, "Looked up cached chunk embeddings"
+    );
+
     let mut embeddings = Vec::with_capacity(chunks.len());
-    for (index, chunk) in chunks.iter().enumerate() {
-        let embedding = llm::embeddings_background(client, chunk)
+    for (index, (chunk, content_hash)) in chunks.iter().zip(&content_hashes).enumerate() {
+        if let Some(embedding) = cached.remove(content_hash) {
+            embeddings.push(embedding);
+            continue;
+        }
+        let embedding = llm::embeddings_background(&usage_client, chunk)
             .await
             .with_context(|| {
                 format!(
@@ -177,6 +217,14 @@ async fn process_bookmark_chunks(
                     candidate.bookmark_id
                 )
             })?;
+        cache_embeddings(
+            pool,
+            candidate.user_id,
+            &profile,
+            vec![(content_hash.clone(), embedding.clone())],
+        )
+        .await
+        .context("Failed to cache chunk embedding")?;
         embeddings.push(embedding);
         ai::refresh_embedding_claim(
             pool,
@@ -187,6 +235,9 @@ async fn process_bookmark_chunks(
         .await?;
     }
 
+    ensure_embedding_profile(pool, &profile)
+        .await
+        .context("Refusing to store embeddings from a different model")?;
     let stored_chunks = store_chunks_with_embeddings(
         pool,
         &candidate.bookmark_id,
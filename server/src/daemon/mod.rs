@@ -1,12 +1,22 @@
 use std::time::Duration;
 
 use chrono::Duration as ChronoDuration;
+use sha2::{Digest, Sha256};
+use shared::TaskStage;
+use tracing::warn;
+use uuid::Uuid;
 
+use crate::db::{task_event, Database};
+use crate::tokenizer::ChunkStrategy;
 use crate::LlmParams;
 
 pub mod add_bookmark;
+pub mod digest;
 pub mod embeddings;
+pub mod retention;
+pub mod scheduler;
 pub mod text_ai;
+pub mod trash;
 
 pub const DAEMON_IDLE_SLEEP: Duration = Duration::from_secs(300);
 pub const AI_GENERATION_MAX_RETRIES: i16 = 5;
@@ -17,6 +27,7 @@ pub struct AiDaemonSettings {
     pub text_chunk_overlap: usize,
     pub embed_chunk_size: usize,
     pub embed_chunk_overlap: usize,
+    pub embed_chunk_strategy: ChunkStrategy,
     pub text_claim_window: ChronoDuration,
     pub embed_claim_window: ChronoDuration,
 }
@@ -29,6 +40,7 @@ impl AiDaemonSettings {
             text_chunk_overlap: params.resolved_text_chunk_overlap(),
             embed_chunk_size: params.ai_embed_chunk_size,
             embed_chunk_overlap: params.ai_embed_chunk_overlap,
+            embed_chunk_strategy: params.ai_embed_chunk_strategy,
             text_claim_window: ChronoDuration::seconds(params.ai_text_claim_window_secs as i64),
             embed_claim_window: ChronoDuration::seconds(params.ai_embed_claim_window_secs as i64),
         })
@@ -45,6 +57,41 @@ pub fn ai_generation_backoff(attempt: i16) -> ChronoDuration {
     }
 }
 
+/// Hex SHA-256 of a chunk text, recognizing the chunks already processed.
+pub fn hash_chunk(chunk: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(chunk.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Adds `stage` to the task timeline. The timeline only helps debugging, a
+/// failed write is logged and the task carries on.
+pub async fn record_stage(
+    pool: &Database,
+    task_id: Uuid,
+    stage: TaskStage,
+    detail: Option<String>,
+) {
+    if let Err(error) = task_event::insert(pool, task_id, stage, detail.as_deref()).await {
+        warn!(?error, task_id = %task_id, ?stage, "Failed to record task stage");
+    }
+}
+
+/// Like [`record_stage`], for the task that produced `bookmark_id`.
+pub async fn record_bookmark_stage(
+    pool: &Database,
+    user_id: Uuid,
+    bookmark_id: &str,
+    stage: TaskStage,
+    detail: Option<String>,
+) {
+    if let Err(error) =
+        task_event::insert_for_bookmark(pool, user_id, bookmark_id, stage, detail.as_deref()).await
+    {
+        warn!(?error, bookmark_id, ?stage, "Failed to record task stage");
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use chrono::Duration as ChronoDuration;
@@ -0,0 +1,67 @@
+use std::future::Future;
+
+use anyhow::Result;
+use chrono::{Duration as ChronoDuration, Utc};
+use tracing::{debug, info};
+
+use crate::db::{bookmark_task, chunks, rag, Database};
+
+const PRUNE_BATCH_SIZE: i64 = 500;
+
+/// Days finished tasks and RAG sessions are kept, `None` keeps them forever,
+/// and days a cached embedding is kept once no longer reused.
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionSettings {
+    pub task_days: Option<u32>,
+    pub rag_days: Option<u32>,
+    pub embedding_cache_days: u32,
+}
+
+/// Deletes the finished tasks, RAG sessions and cached embeddings past their
+/// retention.
+pub async fn run(pool: &Database, settings: RetentionSettings) -> Result<()> {
+    if let Some(days) = settings.task_days {
+        let updated_before = Utc::now() - ChronoDuration::days(i64::from(days));
+        prune("bookmark_task", || {
+            bookmark_task::delete_finished(pool, updated_before, PRUNE_BATCH_SIZE)
+        })
+        .await?;
+    }
+    if let Some(days) = settings.rag_days {
+        let created_before = Utc::now() - ChronoDuration::days(i64::from(days));
+        prune("rag_session", || {
+            rag::delete_sessions_before(pool, created_before, PRUNE_BATCH_SIZE)
+        })
+        .await?;
+    }
+    let used_before = Utc::now() - ChronoDuration::days(i64::from(settings.embedding_cache_days));
+    prune("embedding_cache", || {
+        chunks::delete_cached_embeddings_before(pool, used_before, PRUNE_BATCH_SIZE)
+    })
+    .await?;
+    Ok(())
+}
+
+/// Deletes batches until one comes back short, so a large backlog doesn't
+/// hold locks for long.
+async fn prune<F, Fut>(table: &'static str, delete_batch: F) -> Result<()>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = crate::error::Result<u64>>,
+{
+    let mut total = 0;
+    loop {
+        let deleted = delete_batch().await?;
+        metrics::counter!("retention_rows_deleted_total", "table" => table).increment(deleted);
+        total += deleted;
+        if deleted < PRUNE_BATCH_SIZE as u64 {
+            break;
+        }
+    }
+    if total > 0 {
+        info!(table, count = total, "Pruned expired rows");
+    } else {
+        debug!(table, "No expired rows to prune");
+    }
+    Ok(())
+}
@@ -0,0 +1,309 @@
+use std::fmt;
+use std::future::{pending, Future};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, NaiveDate, Timelike, Utc};
+use futures::future::{try_join_all, BoxFuture};
+use rand::RngExt;
+use tracing::{debug, error, info, warn};
+
+use crate::db::{scheduled_job, Database};
+
+/// How far ahead [`Schedule::next_after`] looks for a matching minute.
+const SCHEDULE_HORIZON_DAYS: i64 = 5 * 366;
+
+/// Five-field cron expression, `minute hour day-of-month month day-of-week`,
+/// in UTC. Fields take `*`, values, `a-b` ranges, `/n` steps and comma
+/// separated lists of them. Days of the week go from 0 (Sunday) to 6, 7 is
+/// Sunday too.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Restricted day fields, cron matching a day on either of them when
+    /// both are
+    day_of_month_set: bool,
+    day_of_week_set: bool,
+}
+
+/// Bit set of the values of a cron field between `min` and `max`.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let value = |text: &str| {
+        text.parse::<u32>()
+            .ok()
+            .filter(|value| (min..=max).contains(value))
+            .ok_or_else(|| format!("{text:?} is not between {min} and {max}"))
+    };
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<usize>() {
+                Ok(step) if step > 0 => (range, Some(step)),
+                _ => return Err(format!("invalid step in {part:?}")),
+            },
+            None => (part, None),
+        };
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (value(start)?, value(end)?),
+            // `5/15` runs from 5 to the end of the field
+            None if step.is_some() => (value(range)?, max),
+            None => (value(range)?, value(range)?),
+        };
+        if start > end {
+            return Err(format!("empty range {range:?}"));
+        }
+        for value in (start..=end).step_by(step.unwrap_or(1)) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days_of_month, months, days_of_week] = fields[..] else {
+            return Err(format!(
+                "expected 5 fields (minute hour day-of-month month day-of-week), got {}",
+                fields.len()
+            ));
+        };
+        let mut days_of_week_bits = parse_field(days_of_week, 0, 7)?;
+        if days_of_week_bits & (1 << 7) != 0 {
+            days_of_week_bits = (days_of_week_bits | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            expression: fields.join(" "),
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)?,
+            days_of_month: parse_field(days_of_month, 1, 31)?,
+            months: parse_field(months, 1, 12)?,
+            days_of_week: days_of_week_bits,
+            day_of_month_set: days_of_month != "*",
+            day_of_week_set: days_of_week != "*",
+        })
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+impl Schedule {
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day_of_month = self.days_of_month & (1 << date.day()) != 0;
+        let day_of_week = self.days_of_week & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.day_of_month_set, self.day_of_week_set) {
+            (true, true) => day_of_month || day_of_week,
+            (true, false) => day_of_month,
+            (false, true) => day_of_week,
+            (false, false) => true,
+        }
+    }
+
+    /// First minute matching the schedule strictly after `after`, none when
+    /// it never comes, such as on February 30th.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        let horizon = start + ChronoDuration::days(SCHEDULE_HORIZON_DAYS);
+        let mut time = start.naive_utc();
+        while time <= horizon.naive_utc() {
+            let date = time.date();
+            if self.months & (1 << date.month()) == 0 {
+                let (year, month) = match date.month() {
+                    12 => (date.year() + 1, 1),
+                    month => (date.year(), month + 1),
+                };
+                time = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.matches_day(date) {
+                time = date.succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if self.hours & (1 << time.hour()) == 0 {
+                time = date.and_hms_opt(time.hour(), 0, 0)? + ChronoDuration::hours(1);
+            } else if self.minutes & (1 << time.minute()) == 0 {
+                time += ChronoDuration::minutes(1);
+            } else {
+                return Some(time.and_utc());
+            }
+        }
+        None
+    }
+}
+
+type JobRunner = Box<dyn Fn() -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+struct Job {
+    name: &'static str,
+    schedule: Schedule,
+    run: JobRunner,
+}
+
+/// Periodic jobs run on their cron schedule. Each run first waits a random
+/// jitter, then is claimed in the database so that instances sharing it run
+/// each occurrence once. A job that never ran, or missed an occurrence while
+/// the server was down, runs at startup.
+pub struct Scheduler {
+    pool: Database,
+    max_jitter: Duration,
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    pub fn new(pool: Database, max_jitter: Duration) -> Self {
+        Self {
+            pool,
+            max_jitter,
+            jobs: Vec::new(),
+        }
+    }
+
+    pub fn add<F, Fut>(&mut self, name: &'static str, schedule: Schedule, job: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.jobs.push(Job {
+            name,
+            schedule,
+            run: Box::new(move || Box::pin(job())),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Runs the jobs until one fails to load its last run.
+    pub async fn run(self) -> Result<()> {
+        let Self {
+            pool,
+            max_jitter,
+            jobs,
+        } = self;
+        try_join_all(jobs.into_iter().map(|job| run_job(&pool, job, max_jitter))).await?;
+        Ok(())
+    }
+}
+
+fn jitter(max_jitter: Duration) -> Duration {
+    let max_ms = max_jitter.as_millis() as u64;
+    Duration::from_millis(rand::rng().random_range(0..=max_ms))
+}
+
+async fn run_job(pool: &Database, job: Job, max_jitter: Duration) -> Result<()> {
+    info!(job = job.name, schedule = %job.schedule, "Scheduling job");
+    let mut due = match scheduled_job::last_run(pool, job.name).await? {
+        Some(run) => job.schedule.next_after(run.last_started_at),
+        None => Some(Utc::now()),
+    };
+    loop {
+        let Some(due_at) = due else {
+            warn!(job = job.name, schedule = %job.schedule, "Schedule never fires, job stopped");
+            return pending().await;
+        };
+        let wait = (due_at - Utc::now()).to_std().unwrap_or_default() + jitter(max_jitter);
+        debug!(job = job.name, %due_at, ?wait, "Waiting for next job run");
+        tokio::time::sleep(wait).await;
+
+        match scheduled_job::claim(pool, job.name, due_at, Utc::now()).await {
+            Ok(true) => run_once(pool, &job).await,
+            Ok(false) => debug!(job = job.name, %due_at, "Job already run by another instance"),
+            Err(error) => error!(job = job.name, ?error, "Failed to claim job run"),
+        }
+        due = job.schedule.next_after(Utc::now());
+    }
+}
+
+async fn run_once(pool: &Database, job: &Job) {
+    let started = Instant::now();
+    let result = (job.run)().await;
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    let outcome = match &result {
+        Ok(()) => {
+            info!(job = job.name, elapsed_ms, "Job done");
+            "success"
+        }
+        Err(error) => {
+            error!(job = job.name, elapsed_ms, ?error, "Job failed");
+            "failure"
+        }
+    };
+    metrics::counter!("scheduled_job_runs_total", "job" => job.name, "outcome" => outcome)
+        .increment(1);
+    let error = result.err().map(|error| format!("{error:#}"));
+    if let Err(error) = scheduled_job::finish(pool, job.name, Utc::now(), error.as_deref()).await {
+        warn!(job = job.name, ?error, "Failed to record job run");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::Schedule;
+
+    fn next(expression: &str, after: (i32, u32, u32, u32, u32)) -> Option<String> {
+        let schedule: Schedule = expression.parse().unwrap();
+        let (year, month, day, hour, minute) = after;
+        let after = Utc
+            .with_ymd_and_hms(year, month, day, hour, minute, 30)
+            .unwrap();
+        schedule
+            .next_after(after)
+            .map(|next| next.format("%Y-%m-%d %H:%M %a").to_string())
+    }
+
+    #[test]
+    fn schedules_find_their_next_minute() {
+        assert_eq!(
+            next("* * * * *", (2024, 5, 15, 13, 30)).as_deref(),
+            Some("2024-05-15 13:31 Wed")
+        );
+        assert_eq!(
+            next("0 * * * *", (2024, 5, 15, 13, 30)).as_deref(),
+            Some("2024-05-15 14:00 Wed")
+        );
+        assert_eq!(
+            next("*/15 9-17 * * 1-5", (2024, 5, 17, 17, 50)).as_deref(),
+            Some("2024-05-20 09:00 Mon")
+        );
+        assert_eq!(
+            next("30 2 1 1,7 *", (2024, 5, 15, 13, 30)).as_deref(),
+            Some("2024-07-01 02:30 Mon")
+        );
+        // Either day field matches once both are restricted
+        assert_eq!(
+            next("0 0 13 * 7", (2024, 5, 6, 0, 0)).as_deref(),
+            Some("2024-05-12 00:00 Sun")
+        );
+        assert_eq!(
+            next("0 0 29 2 *", (2024, 3, 1, 0, 0)).as_deref(),
+            Some("2028-02-29 00:00 Tue")
+        );
+        assert_eq!(next("0 0 30 2 *", (2024, 3, 1, 0, 0)), None);
+    }
+
+    #[test]
+    fn invalid_schedules_are_rejected() {
+        for expression in [
+            "",
+            "* * * *",
+            "60 * * * *",
+            "* * 0 * *",
+            "5-1 * * * *",
+            "*/0 * * * *",
+        ] {
+            assert!(expression.parse::<Schedule>().is_err(), "{expression}");
+        }
+    }
+}
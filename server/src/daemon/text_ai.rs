@@ -2,22 +2,25 @@ use std::collections::{BTreeSet, HashMap, HashSet};
 
 use anyhow::{Context, Result};
 use chrono::Utc;
-use sha2::{Digest, Sha256};
-use tracing::{debug, error, info};
+use shared::{Entity, EntityKind, TaskStage};
+use tracing::{debug, error, info, instrument};
 
 use super::{
-    ai_generation_backoff, AiDaemonSettings, AI_GENERATION_MAX_RETRIES, DAEMON_IDLE_SLEEP,
+    ai_generation_backoff, hash_chunk, record_bookmark_stage, AiDaemonSettings,
+    AI_GENERATION_MAX_RETRIES, DAEMON_IDLE_SLEEP,
 };
 use crate::db::ai::{self, BookmarkAiChunk};
 use crate::db::bookmark::{get_text_content, AiGenerationStatus, BookmarkGenerationCandidate};
-use crate::db::PgPool;
-use crate::llm::{self, LlmClient};
+use crate::db::{entity, user_settings, Database};
+use crate::llm::{self, ChunkEntity, LlmClient, UsageContext};
 use crate::{tokenizer, TEXT_AI_PIPELINE_VERSION};
 
 const QUERY_LIMIT: usize = 10;
+/// Kept per bookmark, in the order the slices first mention them.
+const MAX_ENTITIES: usize = 30;
 
 pub async fn run(
-    pool: &PgPool,
+    pool: &Database,
     mut new_bookmark_rx: tokio::sync::watch::Receiver<()>,
     client: &LlmClient,
     settings: &AiDaemonSettings,
@@ -51,7 +54,7 @@ pub async fn run(
 }
 
 async fn execute_step(
-    pool: &PgPool,
+    pool: &Database,
     client: &LlmClient,
     settings: &AiDaemonSettings,
 ) -> Result<bool> {
@@ -120,12 +123,6 @@ async fn execute_step(
     Ok(true)
 }
 
-fn hash_chunk(chunk: &str) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(chunk.as_bytes());
-    hex::encode(hasher.finalize())
-}
-
 fn normalize_tags(tags: Vec<String>) -> Option<Vec<String>> {
     let mut seen = HashSet::new();
     let normalized = tags
@@ -140,13 +137,89 @@ fn normalize_tags(tags: Vec<String>) -> Option<Vec<String>> {
     }
 }
 
+/// Entities of a known kind with a name, the same name in another case
+/// counting once.
+fn normalize_entities(entities: impl IntoIterator<Item = Entity>) -> Vec<Entity> {
+    let mut seen = HashSet::new();
+    entities
+        .into_iter()
+        .map(|entity| Entity {
+            kind: entity.kind,
+            name: entity.name.trim().to_string(),
+        })
+        .filter(|entity| {
+            !entity.name.is_empty() && seen.insert((entity.kind, entity.name.to_lowercase()))
+        })
+        .take(MAX_ENTITIES)
+        .collect()
+}
+
+fn known_entities(entities: Vec<ChunkEntity>) -> Vec<Entity> {
+    let entities = entities.into_iter().filter_map(|entity| {
+        Some(Entity {
+            kind: entity.kind.trim().parse::<EntityKind>().ok()?,
+            name: entity.name,
+        })
+    });
+    normalize_entities(entities)
+}
+
+/// Status of an output once the pipeline is done with the bookmark, skipped
+/// when it was pending but is turned off.
+fn output_status(pending: bool, wanted: bool) -> AiGenerationStatus {
+    if pending && !wanted {
+        AiGenerationStatus::Skipped
+    } else {
+        AiGenerationStatus::Done
+    }
+}
+
+#[instrument(name = "text_ai", skip_all, fields(bookmark_id = %candidate.bookmark.bookmark_id))]
 async fn handle_task(
-    pool: &PgPool,
+    pool: &Database,
     client: &LlmClient,
     settings: &AiDaemonSettings,
     candidate: &BookmarkGenerationCandidate,
 ) -> Result<()> {
     let bookmark = &candidate.bookmark;
+    let user_settings = user_settings::get(pool, bookmark.user_id).await?;
+    let needs_summary = candidate.needs_summary && user_settings.summaries_enabled;
+    let needs_tags = candidate.needs_tags && user_settings.auto_tagging_enabled;
+    // Outputs the user turned off are generated once turned back on
+    let summary_status = output_status(candidate.needs_summary, needs_summary);
+    let tag_status = output_status(candidate.needs_tags, needs_tags);
+    if !needs_summary && !needs_tags {
+        debug!(
+            bookmark_id = %bookmark.bookmark_id,
+            "Text AI outputs not wanted by user settings, skipping analysis"
+        );
+        ai::complete_text_ai_outputs(
+            pool,
+            bookmark.user_id,
+            &bookmark.bookmark_id,
+            bookmark.summary.as_deref(),
+            bookmark.tags.as_deref(),
+            bookmark.kind,
+            summary_status,
+            tag_status,
+            TEXT_AI_PIPELINE_VERSION,
+        )
+        .await?;
+        return Ok(());
+    }
+    let preferred_client = user_settings
+        .preferred_text_model
+        .as_deref()
+        .map(|model| client.with_text_model(model));
+    let client = preferred_client
+        .as_ref()
+        .unwrap_or(client)
+        .with_usage_context(UsageContext::bookmark(
+            bookmark.user_id,
+            &bookmark.bookmark_id,
+        ));
+    let client = &client;
+
     let text_content = get_text_content(pool, bookmark.user_id, &bookmark.bookmark_id)
         .await?
         .ok_or_else(|| {
@@ -218,6 +291,7 @@ async fn handle_task(
                 pipeline_version: TEXT_AI_PIPELINE_VERSION,
                 summary: response.summary,
                 tags: response.tags,
+                entities: known_entities(response.entities),
             };
             ai::upsert_bookmark_ai_chunk(pool, &stored).await?;
             ai::refresh_text_ai_claim(
@@ -232,19 +306,24 @@ async fn handle_task(
         analyses.push(analysis);
     }
 
-    let summary = if candidate.needs_summary && !analyses.is_empty() {
+    let summary = if needs_summary && !analyses.is_empty() {
         let summaries = analyses
             .iter()
             .map(|analysis| analysis.summary.clone())
             .collect::<Vec<_>>();
-        let summary = llm::consolidate_summary(client, &summaries)
-            .await
-            .with_context(|| {
-                format!(
-                    "Failed to consolidate summary for bookmark_id={}",
-                    bookmark.bookmark_id
-                )
-            })?;
+        let summary = llm::consolidate_summary(
+            client,
+            &summaries,
+            user_settings.summary_language.as_deref(),
+            candidate.summary_style,
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to consolidate summary for bookmark_id={}",
+                bookmark.bookmark_id
+            )
+        })?;
         let summary = summary.trim().to_string();
         if summary.is_empty() {
             None
@@ -255,7 +334,7 @@ async fn handle_task(
         bookmark.summary.clone()
     };
 
-    let tags = if candidate.needs_tags && !analyses.is_empty() {
+    let tags = if needs_tags && !analyses.is_empty() {
         let raw_tags = analyses
             .iter()
             .flat_map(|analysis| analysis.tags.iter().cloned())
@@ -275,6 +354,26 @@ async fn handle_task(
         bookmark.tags.clone()
     };
 
+    // Classified along with the tags, from the opening slice of the text
+    let kind = match chunks.first() {
+        Some(chunk) if needs_tags => llm::classify_kind(client, &bookmark.title, chunk)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to classify kind for bookmark_id={}",
+                    bookmark.bookmark_id
+                )
+            })?,
+        _ => bookmark.kind,
+    };
+
+    let entities = normalize_entities(
+        analyses
+            .iter()
+            .flat_map(|analysis| analysis.entities.iter().cloned()),
+    );
+    entity::replace_for_bookmark(pool, bookmark.user_id, &bookmark.bookmark_id, &entities).await?;
+
     ai::refresh_text_ai_claim(
         pool,
         bookmark.user_id,
@@ -288,6 +387,9 @@ async fn handle_task(
         &bookmark.bookmark_id,
         summary.as_deref(),
         tags.as_deref(),
+        kind,
+        summary_status,
+        tag_status,
         TEXT_AI_PIPELINE_VERSION,
     )
     .await?;
@@ -296,7 +398,34 @@ async fn handle_task(
         bookmark_id = %bookmark.bookmark_id,
         summary_present = summary.is_some(),
         tag_count = tags.as_ref().map(|values| values.len()).unwrap_or(0),
+        kind = ?kind,
+        entity_count = entities.len(),
         "Unified text AI outputs persisted"
     );
+    if needs_summary {
+        if let Some(summary) = &summary {
+            let detail = format!("{} characters", summary.chars().count());
+            record_bookmark_stage(
+                pool,
+                bookmark.user_id,
+                &bookmark.bookmark_id,
+                TaskStage::Summarized,
+                Some(detail),
+            )
+            .await;
+        }
+    }
+    if needs_tags {
+        if let Some(tags) = &tags {
+            record_bookmark_stage(
+                pool,
+                bookmark.user_id,
+                &bookmark.bookmark_id,
+                TaskStage::Tagged,
+                Some(tags.join(", ")),
+            )
+            .await;
+        }
+    }
     Ok(())
 }
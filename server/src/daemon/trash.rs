@@ -0,0 +1,52 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use tracing::{debug, error, info};
+
+use crate::db::{bookmark, Database};
+use crate::static_storage::StorageBackend;
+
+const PURGE_BATCH_SIZE: i64 = 100;
+
+/// Purges the bookmarks kept in the trash for more than `retention_days`.
+pub async fn run(pool: &Database, storage: &dyn StorageBackend, retention_days: u32) -> Result<()> {
+    let retention = ChronoDuration::days(i64::from(retention_days));
+    execute_step(pool, storage, Utc::now() - retention).await
+}
+
+/// Purges bookmarks moved to the trash before `deleted_before`, in batches
+/// until none is left.
+async fn execute_step(
+    pool: &Database,
+    storage: &dyn StorageBackend,
+    deleted_before: DateTime<Utc>,
+) -> Result<()> {
+    loop {
+        let expired = bookmark::get_expired_trash(pool, deleted_before, PURGE_BATCH_SIZE).await?;
+        if expired.is_empty() {
+            debug!(%deleted_before, "No bookmark to purge from the trash");
+            return Ok(());
+        }
+        let batch_size = expired.len();
+        for (user_id, bookmark_id) in expired {
+            if !bookmark::purge(pool, user_id, &bookmark_id).await? {
+                continue;
+            }
+            let static_prefix = format!("{user_id}/{bookmark_id}/");
+            if let Err(error) = storage.delete_prefix(&static_prefix).await {
+                error!(
+                    bookmark_id = %bookmark_id,
+                    prefix = %static_prefix,
+                    ?error,
+                    "Failed to remove static files for purged bookmark"
+                );
+            }
+        }
+        info!(
+            count = batch_size,
+            "Purged expired bookmarks from the trash"
+        );
+        if (batch_size as i64) < PURGE_BATCH_SIZE {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use shared::{ActivityItem, ActivityKind};
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+use super::{dispatch, sqlite, Database};
+use crate::error::{Error, Result};
+
+fn from_row(row: &Row) -> Result<ActivityItem> {
+    let kind: String = row.get("kind");
+    Ok(ActivityItem {
+        kind: ActivityKind::from_str(&kind)
+            .map_err(|_| Error::from(anyhow::anyhow!("unknown activity kind: {kind}")))?,
+        bookmark_id: row.get("bookmark_id"),
+        title: row.get("title"),
+        detail: row.get("detail"),
+        created_at: row.get("created_at"),
+    })
+}
+
+/// Events of `user_id` older than `before`, newest first: bookmarks saved,
+/// summarized and tagged, questions asked and backups restored. Stages are
+/// recorded on every task of a bookmark, they are read from its oldest task
+/// only, which got them all.
+pub async fn list(
+    pool: &Database,
+    user_id: Uuid,
+    before: Option<DateTime<Utc>>,
+    limit: u32,
+) -> Result<Vec<ActivityItem>> {
+    const SQL: &str = r#"
+        SELECT kind, bookmark_id, title, detail, created_at
+        FROM (
+            SELECT 'bookmark_added' AS kind, b.bookmark_id::TEXT AS bookmark_id, b.title,
+                   NULL::TEXT AS detail, b.created_at
+            FROM bookmark b
+            WHERE b.user_id = $1 AND b.deleted_at IS NULL
+            UNION ALL
+            SELECT CASE e.stage WHEN 'summarized' THEN 'summary_generated' ELSE 'tags_applied' END,
+                   b.bookmark_id::TEXT, b.title,
+                   CASE e.stage WHEN 'tagged' THEN e.detail END, e.created_at
+            FROM task_event e
+            JOIN bookmark_task t ON t.task_id = e.task_id
+            JOIN bookmark b ON b.bookmark_id = t.bookmark_id AND b.user_id = t.user_id
+            WHERE t.user_id = $1 AND e.stage IN ('summarized', 'tagged')
+              AND b.deleted_at IS NULL
+              AND t.task_id = (
+                  SELECT oldest.task_id FROM bookmark_task oldest
+                  WHERE oldest.user_id = t.user_id AND oldest.bookmark_id = t.bookmark_id
+                  ORDER BY oldest.created_at, oldest.task_id
+                  LIMIT 1
+              )
+            UNION ALL
+            SELECT 'question_asked', NULL, NULL, question, created_at
+            FROM rag_session
+            WHERE user_id = $1
+            UNION ALL
+            SELECT 'import_completed', NULL, NULL, target, created_at
+            FROM audit_log
+            WHERE user_id = $1 AND action = 'backup_restored'
+        ) activity
+        WHERE $2::TIMESTAMPTZ IS NULL OR created_at < $2
+        ORDER BY created_at DESC, kind
+        LIMIT $3;"#;
+    let pool = dispatch!(pool, |db| sqlite::activity::list(
+        db, user_id, before, limit
+    ));
+    let client = pool.get().await?;
+    let rows = client
+        .query(SQL, &[&user_id, &before, &i64::from(limit)])
+        .await?;
+    rows.iter().map(from_row).collect()
+}
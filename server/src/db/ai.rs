@@ -1,11 +1,14 @@
 use chrono::{DateTime, Duration as ChronoDuration, Utc};
 use postgres_from_row::FromRow;
-use shared::Bookmark;
+use shared::{Bookmark, BookmarkKind, Entity, SummaryStyle};
+use tokio_postgres::types::Json;
 use tracing::{debug, info};
 use uuid::Uuid;
 
-use super::PgPool;
-use crate::db::bookmark::{AiGenerationStatus, BookmarkGenerationCandidate};
+use super::{dispatch, sqlite, Database};
+use crate::db::bookmark::{
+    parse_kind, parse_visibility, AiGenerationStatus, BookmarkGenerationCandidate,
+};
 use crate::error::{Error, Result};
 
 const MAX_FAILURE_REASON_LEN: usize = 2048;
@@ -27,6 +30,7 @@ pub struct BookmarkAiChunk {
     pub pipeline_version: i32,
     pub summary: String,
     pub tags: Vec<String>,
+    pub entities: Vec<Entity>,
 }
 
 #[derive(Debug, Clone, FromRow)]
@@ -40,6 +44,12 @@ struct RowBookmark {
     summary: Option<String>,
     created_at: DateTime<Utc>,
     updated_at: Option<DateTime<Utc>>,
+    word_count: i32,
+    reading_time_minutes: i32,
+    kind: Option<String>,
+    favorite: bool,
+    original_title: Option<String>,
+    visibility: String,
 }
 
 impl From<RowBookmark> for Bookmark {
@@ -54,11 +64,17 @@ impl From<RowBookmark> for Bookmark {
             summary: value.summary,
             created_at: value.created_at,
             updated_at: value.updated_at,
+            word_count: value.word_count,
+            reading_time_minutes: value.reading_time_minutes,
+            kind: parse_kind(value.kind),
+            favorite: value.favorite,
+            original_title: value.original_title,
+            visibility: parse_visibility(value.visibility),
         }
     }
 }
 
-fn truncate_fail_reason(fail_reason: &str) -> String {
+pub(super) fn truncate_fail_reason(fail_reason: &str) -> String {
     if fail_reason.chars().count() <= MAX_FAILURE_REASON_LEN {
         return fail_reason.to_string();
     }
@@ -67,7 +83,7 @@ fn truncate_fail_reason(fail_reason: &str) -> String {
 }
 
 pub async fn claim_bookmarks_pending_text_ai(
-    pool: &PgPool,
+    pool: &Database,
     limit: usize,
     now: DateTime<Utc>,
     claim_window: ChronoDuration,
@@ -78,11 +94,18 @@ pub async fn claim_bookmarks_pending_text_ai(
         WHERE text_ai_status = 'pending'
           AND text_ai_next_attempt_at <= $1
           AND (summary_status = 'pending' OR tag_status = 'pending')
+          AND deleted_at IS NULL
         ORDER BY text_ai_next_attempt_at ASC, created_at ASC
         FOR UPDATE SKIP LOCKED
         LIMIT $2;
     "#;
 
+    let pool = dispatch!(pool, |db| sqlite::ai::claim_bookmarks_pending_text_ai(
+        db,
+        limit,
+        now,
+        claim_window
+    ));
     let mut client = pool.get().await?;
     let tx = client.transaction().await?;
     let rows = tx.query(QUERY, &[&now, &(limit as i64)]).await?;
@@ -96,11 +119,14 @@ pub async fn claim_bookmarks_pending_text_ai(
             let summary_status: AiGenerationStatus =
                 row.try_get("summary_status").map_err(Error::from)?;
             let tag_status: AiGenerationStatus = row.try_get("tag_status").map_err(Error::from)?;
+            let summary_style: Option<String> =
+                row.try_get("summary_style").map_err(Error::from)?;
             Ok(BookmarkGenerationCandidate {
                 bookmark,
                 attempts,
                 needs_summary: summary_status == AiGenerationStatus::Pending,
                 needs_tags: tag_status == AiGenerationStatus::Pending,
+                summary_style: summary_style.and_then(|style| style.parse().ok()),
             })
         })
         .collect::<Result<Vec<_>>>()?;
@@ -132,7 +158,7 @@ pub async fn claim_bookmarks_pending_text_ai(
 }
 
 pub async fn claim_bookmarks_pending_embeddings(
-    pool: &PgPool,
+    pool: &Database,
     limit: usize,
     now: DateTime<Utc>,
     claim_window: ChronoDuration,
@@ -142,11 +168,18 @@ pub async fn claim_bookmarks_pending_embeddings(
         FROM bookmark
         WHERE embedding_status = 'pending'
           AND embedding_next_attempt_at <= $1
+          AND deleted_at IS NULL
         ORDER BY embedding_next_attempt_at ASC, created_at ASC
         FOR UPDATE SKIP LOCKED
         LIMIT $2;
     "#;
 
+    let pool = dispatch!(pool, |db| sqlite::ai::claim_bookmarks_pending_embeddings(
+        db,
+        limit,
+        now,
+        claim_window
+    ));
     let mut client = pool.get().await?;
     let tx = client.transaction().await?;
     let rows = tx.query(QUERY, &[&now, &(limit as i64)]).await?;
@@ -185,11 +218,17 @@ pub async fn claim_bookmarks_pending_embeddings(
 }
 
 pub async fn refresh_text_ai_claim(
-    pool: &PgPool,
+    pool: &Database,
     user_id: Uuid,
     bookmark_id: &str,
     next_attempt_at: DateTime<Utc>,
 ) -> Result<()> {
+    let pool = dispatch!(pool, |db| sqlite::ai::refresh_text_ai_claim(
+        db,
+        user_id,
+        bookmark_id,
+        next_attempt_at
+    ));
     pool.get()
         .await?
         .execute(
@@ -203,11 +242,17 @@ pub async fn refresh_text_ai_claim(
 }
 
 pub async fn refresh_embedding_claim(
-    pool: &PgPool,
+    pool: &Database,
     user_id: Uuid,
     bookmark_id: &str,
     next_attempt_at: DateTime<Utc>,
 ) -> Result<()> {
+    let pool = dispatch!(pool, |db| sqlite::ai::refresh_embedding_claim(
+        db,
+        user_id,
+        bookmark_id,
+        next_attempt_at
+    ));
     pool.get()
         .await?
         .execute(
@@ -221,14 +266,20 @@ pub async fn refresh_embedding_claim(
 }
 
 pub async fn list_bookmark_ai_chunks(
-    pool: &PgPool,
+    pool: &Database,
     user_id: Uuid,
     bookmark_id: &str,
 ) -> Result<Vec<BookmarkAiChunk>> {
+    let pool = dispatch!(pool, |db| sqlite::ai::list_bookmark_ai_chunks(
+        db,
+        user_id,
+        bookmark_id
+    ));
     let client = pool.get().await?;
     let rows = client
         .query(
-            "SELECT bookmark_id, user_id, chunk_index, chunk_hash, pipeline_version, summary, tags
+            "SELECT bookmark_id, user_id, chunk_index, chunk_hash, pipeline_version, summary, tags,
+                    entities
              FROM bookmark_ai_chunk
              WHERE bookmark_id = $1 AND user_id = $2
              ORDER BY chunk_index ASC",
@@ -246,25 +297,31 @@ pub async fn list_bookmark_ai_chunks(
                 pipeline_version: row.try_get("pipeline_version").map_err(Error::from)?,
                 summary: row.try_get("summary").map_err(Error::from)?,
                 tags: row.try_get("tags").map_err(Error::from)?,
+                entities: row
+                    .try_get::<_, Json<Vec<Entity>>>("entities")
+                    .map_err(Error::from)?
+                    .0,
             })
         })
         .collect()
 }
 
-pub async fn upsert_bookmark_ai_chunk(pool: &PgPool, chunk: &BookmarkAiChunk) -> Result<()> {
+pub async fn upsert_bookmark_ai_chunk(pool: &Database, chunk: &BookmarkAiChunk) -> Result<()> {
+    let pool = dispatch!(pool, |db| sqlite::ai::upsert_bookmark_ai_chunk(db, chunk));
     pool.get()
         .await?
         .execute(
             r#"
             INSERT INTO bookmark_ai_chunk
-                (bookmark_id, user_id, chunk_index, chunk_hash, pipeline_version, summary, tags, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, now(), now())
+                (bookmark_id, user_id, chunk_index, chunk_hash, pipeline_version, summary, tags, entities, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, now(), now())
             ON CONFLICT (bookmark_id, user_id, chunk_index)
             DO UPDATE SET
                 chunk_hash = EXCLUDED.chunk_hash,
                 pipeline_version = EXCLUDED.pipeline_version,
                 summary = EXCLUDED.summary,
                 tags = EXCLUDED.tags,
+                entities = EXCLUDED.entities,
                 updated_at = now()
             "#,
             &[
@@ -275,6 +332,7 @@ pub async fn upsert_bookmark_ai_chunk(pool: &PgPool, chunk: &BookmarkAiChunk) ->
                 &chunk.pipeline_version,
                 &chunk.summary,
                 &chunk.tags,
+                &Json(&chunk.entities),
             ],
         )
         .await?;
@@ -282,10 +340,15 @@ pub async fn upsert_bookmark_ai_chunk(pool: &PgPool, chunk: &BookmarkAiChunk) ->
 }
 
 pub async fn delete_bookmark_ai_chunks(
-    pool: &PgPool,
+    pool: &Database,
     user_id: Uuid,
     bookmark_id: &str,
 ) -> Result<()> {
+    let pool = dispatch!(pool, |db| sqlite::ai::delete_bookmark_ai_chunks(
+        db,
+        user_id,
+        bookmark_id
+    ));
     let rows_affected = pool
         .get()
         .await?
@@ -304,7 +367,7 @@ pub async fn delete_bookmark_ai_chunks(
 }
 
 pub async fn mark_text_ai_failure(
-    pool: &PgPool,
+    pool: &Database,
     user_id: Uuid,
     bookmark_id: &str,
     status: AiGenerationStatus,
@@ -312,6 +375,15 @@ pub async fn mark_text_ai_failure(
     next_attempt_at: DateTime<Utc>,
     fail_reason: &str,
 ) -> Result<()> {
+    let pool = dispatch!(pool, |db| sqlite::ai::mark_text_ai_failure(
+        db,
+        user_id,
+        bookmark_id,
+        status,
+        attempts,
+        next_attempt_at,
+        fail_reason,
+    ));
     let fail_reason = truncate_fail_reason(fail_reason);
     pool.get()
         .await?
@@ -336,22 +408,42 @@ pub async fn mark_text_ai_failure(
     Ok(())
 }
 
+/// Stores the outputs of the text AI pipeline. `summary_status` and
+/// `tag_status` are [`AiGenerationStatus::Skipped`] for outputs the user
+/// turned off, so [`requeue_skipped`] can generate them later.
+#[allow(clippy::too_many_arguments)]
 pub async fn complete_text_ai_outputs(
-    pool: &PgPool,
+    pool: &Database,
     user_id: Uuid,
     bookmark_id: &str,
     summary: Option<&str>,
     tags: Option<&[String]>,
+    kind: Option<BookmarkKind>,
+    summary_status: AiGenerationStatus,
+    tag_status: AiGenerationStatus,
     pipeline_version: i32,
 ) -> Result<()> {
+    let pool = dispatch!(pool, |db| sqlite::ai::complete_text_ai_outputs(
+        db,
+        user_id,
+        bookmark_id,
+        summary,
+        tags,
+        kind,
+        summary_status,
+        tag_status,
+        pipeline_version,
+    ));
     pool.get()
         .await?
         .execute(
             "UPDATE bookmark
              SET summary = $1,
                  tags = $2,
-                 summary_status = 'done',
-                 tag_status = 'done',
+                 kind = $6,
+                 summary_status = $7,
+                 summary_style = NULL,
+                 tag_status = $8,
                  text_ai_status = 'done',
                  text_ai_attempts = 0,
                  text_ai_next_attempt_at = now(),
@@ -359,14 +451,56 @@ pub async fn complete_text_ai_outputs(
                  text_ai_pipeline_version = $3,
                  updated_at = now()
              WHERE bookmark_id = $4 AND user_id = $5",
-            &[&summary, &tags, &pipeline_version, &bookmark_id, &user_id],
+            &[
+                &summary,
+                &tags,
+                &pipeline_version,
+                &bookmark_id,
+                &user_id,
+                &kind.as_ref().map(AsRef::<str>::as_ref),
+                &summary_status,
+                &tag_status,
+            ],
         )
         .await?;
     Ok(())
 }
 
+/// Queues the summaries and/or tags skipped while the user had them turned
+/// off, returning how many bookmarks the text AI daemon has to go over.
+pub async fn requeue_skipped(
+    pool: &Database,
+    user_id: Uuid,
+    summaries: bool,
+    tags: bool,
+) -> Result<u64> {
+    let pool = dispatch!(pool, |db| sqlite::ai::requeue_skipped(
+        db, user_id, summaries, tags
+    ));
+    let queued = pool
+        .get()
+        .await?
+        .execute(
+            "UPDATE bookmark
+             SET summary_status = CASE WHEN $2 AND summary_status = 'skipped'
+                     THEN 'pending' ELSE summary_status END,
+                 tag_status = CASE WHEN $3 AND tag_status = 'skipped'
+                     THEN 'pending' ELSE tag_status END,
+                 text_ai_status = 'pending',
+                 text_ai_attempts = 0,
+                 text_ai_next_attempt_at = now(),
+                 text_ai_fail_reason = NULL
+             WHERE user_id = $1 AND deleted_at IS NULL
+               AND (($2 AND summary_status = 'skipped') OR ($3 AND tag_status = 'skipped'))",
+            &[&user_id, &summaries, &tags],
+        )
+        .await?;
+    debug!(%user_id, summaries, tags, queued, "Skipped text AI outputs queued");
+    Ok(queued)
+}
+
 pub async fn mark_embedding_failure(
-    pool: &PgPool,
+    pool: &Database,
     user_id: Uuid,
     bookmark_id: &str,
     status: AiGenerationStatus,
@@ -374,6 +508,15 @@ pub async fn mark_embedding_failure(
     next_attempt_at: DateTime<Utc>,
     fail_reason: &str,
 ) -> Result<()> {
+    let pool = dispatch!(pool, |db| sqlite::ai::mark_embedding_failure(
+        db,
+        user_id,
+        bookmark_id,
+        status,
+        attempts,
+        next_attempt_at,
+        fail_reason,
+    ));
     let fail_reason = truncate_fail_reason(fail_reason);
     pool.get()
         .await?
@@ -399,11 +542,17 @@ pub async fn mark_embedding_failure(
 }
 
 pub async fn mark_embedding_success(
-    pool: &PgPool,
+    pool: &Database,
     user_id: Uuid,
     bookmark_id: &str,
     pipeline_version: i32,
 ) -> Result<()> {
+    let pool = dispatch!(pool, |db| sqlite::ai::mark_embedding_success(
+        db,
+        user_id,
+        bookmark_id,
+        pipeline_version
+    ));
     pool.get()
         .await?
         .execute(
@@ -422,7 +571,7 @@ pub async fn mark_embedding_success(
 }
 
 pub async fn mark_embedding_done_without_chunks(
-    pool: &PgPool,
+    pool: &Database,
     user_id: Uuid,
     bookmark_id: &str,
     pipeline_version: i32,
@@ -430,7 +579,164 @@ pub async fn mark_embedding_done_without_chunks(
     mark_embedding_success(pool, user_id, bookmark_id, pipeline_version).await
 }
 
-pub async fn reset_embedding_generation_state(pool: &PgPool, pipeline_version: i32) -> Result<()> {
+/// Replaces the summary with one the user wrote, `None` when the user has no
+/// such bookmark outside the trash.
+pub async fn set_summary(
+    pool: &Database,
+    user_id: Uuid,
+    bookmark_id: &str,
+    summary: &str,
+) -> Result<Option<Bookmark>> {
+    let pool = dispatch!(pool, |db| sqlite::ai::set_summary(
+        db,
+        user_id,
+        bookmark_id,
+        summary
+    ));
+    let row = pool
+        .get()
+        .await?
+        .query_opt(
+            "UPDATE bookmark
+             SET summary = $3,
+                 summary_status = 'done',
+                 summary_style = NULL,
+                 updated_at = now()
+             WHERE bookmark_id = $1 AND user_id = $2 AND deleted_at IS NULL
+             RETURNING *",
+            &[&bookmark_id, &user_id, &summary],
+        )
+        .await?;
+    row.map(|row| {
+        RowBookmark::try_from_row(&row)
+            .map(Bookmark::from)
+            .map_err(Error::from)
+    })
+    .transpose()
+}
+
+/// Has the text AI daemon summarize the bookmark again, in `style`. False
+/// when the user has no such bookmark outside the trash.
+pub async fn request_summary(
+    pool: &Database,
+    user_id: Uuid,
+    bookmark_id: &str,
+    style: Option<SummaryStyle>,
+) -> Result<bool> {
+    let pool = dispatch!(pool, |db| sqlite::ai::request_summary(
+        db,
+        user_id,
+        bookmark_id,
+        style
+    ));
+    let updated = pool
+        .get()
+        .await?
+        .execute(
+            "UPDATE bookmark
+             SET summary_status = 'pending',
+                 summary_style = $3,
+                 text_ai_status = 'pending',
+                 text_ai_attempts = 0,
+                 text_ai_next_attempt_at = now(),
+                 text_ai_fail_reason = NULL
+             WHERE bookmark_id = $1 AND user_id = $2 AND deleted_at IS NULL",
+            &[
+                &bookmark_id,
+                &user_id,
+                &style.as_ref().map(AsRef::<str>::as_ref),
+            ],
+        )
+        .await?;
+    debug!(%user_id, %bookmark_id, ?style, "Summary requested");
+    Ok(updated > 0)
+}
+
+/// Has the AI daemons generate tags, summary and/or chunks of the bookmark
+/// again. Redoing tags or summary drops the analyses of the slices of its
+/// article, so the LLM reads it anew. False when the user has no such
+/// bookmark outside the trash.
+pub async fn request_reprocessing(
+    pool: &Database,
+    user_id: Uuid,
+    bookmark_id: &str,
+    tags: bool,
+    summary: bool,
+    chunks: bool,
+) -> Result<bool> {
+    let pool = dispatch!(pool, |db| sqlite::ai::request_reprocessing(
+        db,
+        user_id,
+        bookmark_id,
+        tags,
+        summary,
+        chunks,
+    ));
+    let mut client = pool.get().await?;
+    let tx = client.transaction().await?;
+    let updated = tx
+        .execute(
+            "UPDATE bookmark
+             SET tag_status = CASE WHEN $3 THEN 'pending' ELSE tag_status END,
+                 summary_status = CASE WHEN $4 THEN 'pending' ELSE summary_status END,
+                 summary_style = CASE WHEN $4 THEN NULL ELSE summary_style END,
+                 text_ai_status = CASE WHEN $3 OR $4 THEN 'pending' ELSE text_ai_status END,
+                 text_ai_attempts = CASE WHEN $3 OR $4 THEN 0 ELSE text_ai_attempts END,
+                 text_ai_next_attempt_at =
+                     CASE WHEN $3 OR $4 THEN now() ELSE text_ai_next_attempt_at END,
+                 text_ai_fail_reason = CASE WHEN $3 OR $4 THEN NULL ELSE text_ai_fail_reason END,
+                 embedding_status = CASE WHEN $5 THEN 'pending' ELSE embedding_status END,
+                 embedding_attempts = CASE WHEN $5 THEN 0 ELSE embedding_attempts END,
+                 embedding_next_attempt_at =
+                     CASE WHEN $5 THEN now() ELSE embedding_next_attempt_at END,
+                 embedding_fail_reason = CASE WHEN $5 THEN NULL ELSE embedding_fail_reason END
+             WHERE bookmark_id = $1 AND user_id = $2 AND deleted_at IS NULL",
+            &[&bookmark_id, &user_id, &tags, &summary, &chunks],
+        )
+        .await?;
+    if updated > 0 && (tags || summary) {
+        tx.execute(
+            "DELETE FROM bookmark_ai_chunk WHERE bookmark_id = $1 AND user_id = $2",
+            &[&bookmark_id, &user_id],
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    debug!(%user_id, %bookmark_id, tags, summary, chunks, "Reprocessing requested");
+    Ok(updated > 0)
+}
+
+/// Leaves the bookmark out of summaries, tagging and embeddings, as if the
+/// pipeline was done with it.
+pub async fn skip_processing(pool: &Database, user_id: Uuid, bookmark_id: &str) -> Result<()> {
+    let pool = dispatch!(pool, |db| sqlite::ai::skip_processing(
+        db,
+        user_id,
+        bookmark_id
+    ));
+    pool.get()
+        .await?
+        .execute(
+            "UPDATE bookmark
+             SET summary_status = 'done',
+                 tag_status = 'done',
+                 text_ai_status = 'done',
+                 embedding_status = 'done'
+             WHERE bookmark_id = $1 AND user_id = $2",
+            &[&bookmark_id, &user_id],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn reset_embedding_generation_state(
+    pool: &Database,
+    pipeline_version: i32,
+) -> Result<()> {
+    let pool = dispatch!(pool, |db| sqlite::ai::reset_embedding_generation_state(
+        db,
+        pipeline_version
+    ));
     pool.get()
         .await?
         .execute(
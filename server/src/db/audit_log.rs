@@ -0,0 +1,126 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use shared::{AuditAction, AuditEvent};
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+use super::{dispatch, sqlite, Database};
+use crate::error::{Error, Result};
+
+fn from_row(row: &Row) -> Result<AuditEvent> {
+    let action: String = row.get("action");
+    Ok(AuditEvent {
+        event_id: row.get("event_id"),
+        action: AuditAction::from_str(&action)
+            .map_err(|_| Error::from(anyhow::anyhow!("unknown audit action: {action}")))?,
+        target: row.get("target"),
+        ip: row.get("ip"),
+        created_at: row.get("created_at"),
+    })
+}
+
+/// Records `action` of `user_id`, along with their current username.
+pub async fn insert(
+    pool: &Database,
+    user_id: Uuid,
+    action: AuditAction,
+    target: Option<&str>,
+    ip: Option<IpAddr>,
+) -> Result<()> {
+    const SQL: &str = r#"
+        INSERT INTO audit_log (user_id, username, action, target, ip)
+        VALUES ($1, (SELECT username FROM "user" WHERE user_id = $1), $2, $3, $4);"#;
+    let pool = dispatch!(pool, |db| sqlite::audit_log::insert(
+        db, user_id, action, target, ip
+    ));
+    let client = pool.get().await?;
+    client
+        .execute(
+            SQL,
+            &[
+                &user_id,
+                &action.as_ref(),
+                &target,
+                &ip.map(|ip| ip.to_string()),
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+/// Records `action` under `username` alone, for a name no account has.
+pub async fn insert_for_username(
+    pool: &Database,
+    username: &str,
+    action: AuditAction,
+    target: Option<&str>,
+    ip: Option<IpAddr>,
+) -> Result<()> {
+    const SQL: &str =
+        "INSERT INTO audit_log (username, action, target, ip) VALUES ($1, $2, $3, $4);";
+    let pool = dispatch!(pool, |db| sqlite::audit_log::insert_for_username(
+        db, username, action, target, ip
+    ));
+    let client = pool.get().await?;
+    client
+        .execute(
+            SQL,
+            &[
+                &username,
+                &action.as_ref(),
+                &target,
+                &ip.map(|ip| ip.to_string()),
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+/// Events of `user_id` older than `before`, newest first.
+pub async fn list(
+    pool: &Database,
+    user_id: Uuid,
+    before: Option<DateTime<Utc>>,
+    limit: u32,
+) -> Result<Vec<AuditEvent>> {
+    const SQL: &str = r#"
+        SELECT event_id, action, target, ip, created_at
+        FROM audit_log
+        WHERE user_id = $1 AND ($2::TIMESTAMPTZ IS NULL OR created_at < $2)
+        ORDER BY created_at DESC, event_id
+        LIMIT $3;"#;
+    let pool = dispatch!(pool, |db| sqlite::audit_log::list(
+        db, user_id, before, limit
+    ));
+    let client = pool.get().await?;
+    let rows = client
+        .query(SQL, &[&user_id, &before, &i64::from(limit)])
+        .await?;
+    rows.iter().map(from_row).collect()
+}
+
+/// Events recorded under `username` older than `before`, newest first,
+/// including those of a deleted account and of sign-ins to no account.
+pub async fn list_by_username(
+    pool: &Database,
+    username: &str,
+    before: Option<DateTime<Utc>>,
+    limit: u32,
+) -> Result<Vec<AuditEvent>> {
+    const SQL: &str = r#"
+        SELECT event_id, action, target, ip, created_at
+        FROM audit_log
+        WHERE username = $1 AND ($2::TIMESTAMPTZ IS NULL OR created_at < $2)
+        ORDER BY created_at DESC, event_id
+        LIMIT $3;"#;
+    let pool = dispatch!(pool, |db| sqlite::audit_log::list_by_username(
+        db, username, before, limit
+    ));
+    let client = pool.get().await?;
+    let rows = client
+        .query(SQL, &[&username, &before, &i64::from(limit)])
+        .await?;
+    rows.iter().map(from_row).collect()
+}
@@ -0,0 +1,681 @@
+use std::collections::HashMap;
+
+use pgvector::Vector;
+use shared::Entity;
+use tokio_postgres::types::Json;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use super::bookmark::{parse_visibility, AiGenerationStatus};
+use super::user::User;
+use super::{dispatch, extraction_rule, rule, sqlite, user_settings, Database, ResultExt};
+use crate::backup::{
+    BookmarkRecord, BookmarkVersionRecord, ChunkRecord, CollectionRecord, DigestRecord,
+    FetchCredentialRecord, UserDump, WorkspaceBookmarkRecord, WorkspaceMembershipRecord,
+    WorkspaceRecord,
+};
+use crate::error::{Error, Result};
+use crate::{reading_time, EMBEDDING_PIPELINE_VERSION, TEXT_AI_PIPELINE_VERSION};
+
+pub async fn user_ids(pool: &Database) -> Result<Vec<Uuid>> {
+    let pool = dispatch!(pool, |db| sqlite::backup::user_ids(db));
+    let client = pool.get().await?;
+    let rows = client
+        .query(r#"SELECT user_id FROM "user" ORDER BY created_at"#, &[])
+        .await?;
+    Ok(rows.iter().map(|row| row.get("user_id")).collect())
+}
+
+/// The user with their settings, bookmarks, chunks, versions, digests,
+/// collections, rules, fetch credentials and workspaces. Tasks, RAG sessions,
+/// usage records and tokens are left out, and so is what refers to bookmarks
+/// in the trash.
+pub async fn export_user(pool: &Database, user_id: Uuid) -> Result<UserDump> {
+    let pool = dispatch!(pool, |db| sqlite::backup::export_user(db, user_id));
+    let client = pool.get().await?;
+    let row = client
+        .query_one(
+            r#"SELECT user_id, username, password_hash, email, email_verified_at, created_at,
+                      updated_at
+               FROM "user" WHERE user_id = $1"#,
+            &[&user_id],
+        )
+        .await?;
+    let user = User {
+        user_id: row.get("user_id"),
+        username: row.get("username"),
+        password_hash: row.get("password_hash"),
+        email: row.get("email"),
+        email_verified_at: row.get("email_verified_at"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    };
+
+    let settings = client
+        .query_opt(
+            "SELECT auto_tagging_enabled, summaries_enabled, summary_language, preferred_text_model,
+                    digest_enabled, reader_font_family, reader_font_size, reader_line_width,
+                    reader_justify
+             FROM user_settings WHERE user_id = $1",
+            &[&user_id],
+        )
+        .await?
+        .as_ref()
+        .map(user_settings::from_row);
+
+    let mut chunks: HashMap<String, Vec<ChunkRecord>> = HashMap::new();
+    for row in client
+        .query(
+            "SELECT bookmark_id, chunk_index, chunk_text, embedding
+             FROM bookmark_chunk WHERE user_id = $1
+             ORDER BY bookmark_id, chunk_index",
+            &[&user_id],
+        )
+        .await?
+    {
+        let embedding: Option<Vector> = row.get("embedding");
+        chunks
+            .entry(row.get("bookmark_id"))
+            .or_default()
+            .push(ChunkRecord {
+                chunk_index: row.get("chunk_index"),
+                chunk_text: row.get("chunk_text"),
+                embedding: embedding.as_ref().map(Vector::to_vec),
+            });
+    }
+
+    let mut entities: HashMap<String, Vec<Entity>> = HashMap::new();
+    for row in client
+        .query(
+            "SELECT bookmark_id, kind, name FROM entity WHERE user_id = $1
+             ORDER BY bookmark_id, kind, name",
+            &[&user_id],
+        )
+        .await?
+    {
+        let kind: String = row.get("kind");
+        if let Ok(kind) = kind.parse() {
+            entities
+                .entry(row.get("bookmark_id"))
+                .or_default()
+                .push(Entity {
+                    kind,
+                    name: row.get("name"),
+                });
+        }
+    }
+
+    let mut versions: HashMap<String, Vec<BookmarkVersionRecord>> = HashMap::new();
+    for row in client
+        .query(
+            "SELECT bookmark_id, version_id, title, text_content, html, word_count, captured_at,
+                    created_at
+             FROM bookmark_version WHERE user_id = $1
+             ORDER BY bookmark_id, captured_at",
+            &[&user_id],
+        )
+        .await?
+    {
+        versions
+            .entry(row.get("bookmark_id"))
+            .or_default()
+            .push(BookmarkVersionRecord {
+                version_id: row.get("version_id"),
+                title: row.get("title"),
+                text_content: row.get("text_content"),
+                html: row.get("html"),
+                word_count: row.get("word_count"),
+                captured_at: row.get("captured_at"),
+                created_at: row.get("created_at"),
+            });
+    }
+
+    let bookmarks = client
+        .query(
+            "SELECT bookmark_id, url, canonical_url, domain, title, text_content, tags, summary,
+                    kind, favorite, original_title, visibility, summary_status, tag_status, text_ai_status,
+                    language, text_search_config, static_bytes, created_at, updated_at, read_at
+             FROM bookmark WHERE user_id = $1 AND deleted_at IS NULL
+             ORDER BY created_at",
+            &[&user_id],
+        )
+        .await?
+        .iter()
+        .map(|row| {
+            let bookmark_id: String = row.get("bookmark_id");
+            BookmarkRecord {
+                chunks: chunks.remove(&bookmark_id).unwrap_or_default(),
+                entities: entities.remove(&bookmark_id).unwrap_or_default(),
+                versions: versions.remove(&bookmark_id).unwrap_or_default(),
+                bookmark_id,
+                url: row.get("url"),
+                canonical_url: row.get("canonical_url"),
+                domain: row.get("domain"),
+                title: row.get("title"),
+                text_content: row.get("text_content"),
+                tags: row.get("tags"),
+                summary: row.get("summary"),
+                kind: row.get("kind"),
+                favorite: row.get("favorite"),
+                original_title: row.get("original_title"),
+                visibility: parse_visibility(row.get("visibility")),
+                summary_status: row.get("summary_status"),
+                tag_status: row.get("tag_status"),
+                text_ai_status: row.get("text_ai_status"),
+                language: row.get("language"),
+                text_search_config: row.get("text_search_config"),
+                static_bytes: row.get("static_bytes"),
+                created_at: row.get("created_at"),
+                updated_at: row.get("updated_at"),
+                read_at: row.get("read_at"),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let digests = client
+        .query(
+            "SELECT period_start, period_end, bookmark_ids, content, emailed_at, created_at
+             FROM digest WHERE user_id = $1
+             ORDER BY period_start",
+            &[&user_id],
+        )
+        .await?
+        .iter()
+        .map(|row| DigestRecord {
+            period_start: row.get("period_start"),
+            period_end: row.get("period_end"),
+            bookmark_ids: row.get("bookmark_ids"),
+            content: row.get("content"),
+            emailed_at: row.get("emailed_at"),
+            created_at: row.get("created_at"),
+        })
+        .collect();
+
+    let collections = client
+        .query(
+            "SELECT c.collection_id, c.name, c.description, c.created_at, c.updated_at,
+                    COALESCE(
+                        array_agg(cb.bookmark_id ORDER BY cb.position)
+                            FILTER (WHERE cb.bookmark_id IS NOT NULL),
+                        '{}'
+                    ) AS bookmark_ids
+             FROM collection c
+             LEFT JOIN collection_bookmark cb ON cb.collection_id = c.collection_id
+                 AND EXISTS (
+                     SELECT 1 FROM bookmark b
+                     WHERE b.bookmark_id = cb.bookmark_id AND b.user_id = cb.user_id
+                       AND b.deleted_at IS NULL
+                 )
+             WHERE c.user_id = $1
+             GROUP BY c.collection_id
+             ORDER BY c.created_at",
+            &[&user_id],
+        )
+        .await?
+        .iter()
+        .map(|row| CollectionRecord {
+            collection_id: row.get("collection_id"),
+            name: row.get("name"),
+            description: row.get("description"),
+            bookmark_ids: row.get("bookmark_ids"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        })
+        .collect();
+
+    let rules = client
+        .query(
+            "SELECT rule_id, name, enabled, conditions, actions, created_at, updated_at
+             FROM rule WHERE user_id = $1
+             ORDER BY created_at",
+            &[&user_id],
+        )
+        .await?
+        .iter()
+        .map(rule::from_row)
+        .collect();
+
+    let extraction_rules = client
+        .query(
+            "SELECT rule_id, domain, keep_selectors, drop_selectors, title_selector, created_at,
+                    updated_at
+             FROM extraction_rule WHERE user_id = $1
+             ORDER BY created_at",
+            &[&user_id],
+        )
+        .await?
+        .iter()
+        .map(extraction_rule::from_row)
+        .collect();
+
+    let fetch_credentials = client
+        .query(
+            "SELECT domain, sealed_headers, updated_at FROM fetch_credential
+             WHERE user_id = $1
+             ORDER BY domain",
+            &[&user_id],
+        )
+        .await?
+        .iter()
+        .map(|row| FetchCredentialRecord {
+            domain: row.get("domain"),
+            sealed_headers: row.get("sealed_headers"),
+            updated_at: row.get("updated_at"),
+        })
+        .collect();
+
+    let workspaces = client
+        .query(
+            "SELECT workspace_id, name, created_at FROM workspace
+             WHERE owner_id = $1
+             ORDER BY created_at",
+            &[&user_id],
+        )
+        .await?
+        .iter()
+        .map(|row| WorkspaceRecord {
+            workspace_id: row.get("workspace_id"),
+            name: row.get("name"),
+            created_at: row.get("created_at"),
+        })
+        .collect();
+
+    let workspace_memberships = client
+        .query(
+            "SELECT workspace_id, role, joined_at FROM workspace_member
+             WHERE user_id = $1
+             ORDER BY joined_at",
+            &[&user_id],
+        )
+        .await?
+        .iter()
+        .map(|row| WorkspaceMembershipRecord {
+            workspace_id: row.get("workspace_id"),
+            role: row.get("role"),
+            joined_at: row.get("joined_at"),
+        })
+        .collect();
+
+    let workspace_bookmarks = client
+        .query(
+            "SELECT wb.workspace_id, wb.bookmark_id, wb.added_at FROM workspace_bookmark wb
+             JOIN bookmark b ON b.bookmark_id = wb.bookmark_id AND b.user_id = wb.user_id
+             WHERE wb.user_id = $1 AND b.deleted_at IS NULL
+             ORDER BY wb.added_at",
+            &[&user_id],
+        )
+        .await?
+        .iter()
+        .map(|row| WorkspaceBookmarkRecord {
+            workspace_id: row.get("workspace_id"),
+            bookmark_id: row.get("bookmark_id"),
+            added_at: row.get("added_at"),
+        })
+        .collect();
+
+    debug!(user_id = %user_id, bookmark_count = bookmarks.len(), "Exported user");
+    Ok(UserDump {
+        user,
+        settings,
+        bookmarks,
+        digests,
+        collections,
+        rules,
+        extraction_rules,
+        fetch_credentials,
+        workspaces,
+        workspace_memberships,
+        workspace_bookmarks,
+    })
+}
+
+/// Inserts everything in `dump` in one transaction, the user must not exist
+/// yet. Chunks are only inserted with `with_chunks`, otherwise the embedding
+/// daemon regenerates them.
+pub async fn import_user(pool: &Database, dump: &UserDump, with_chunks: bool) -> Result<()> {
+    let user_id = dump.user.user_id;
+    let pool = dispatch!(pool, |db| sqlite::backup::import_user(
+        db,
+        dump,
+        with_chunks
+    ));
+    let mut client = pool.get().await?;
+    let tx = client.transaction().await?;
+
+    tx.execute(
+        r#"INSERT INTO "user"
+               (user_id, username, password_hash, email, email_verified_at, created_at, updated_at)
+           VALUES ($1, $2, $3, $4, $5, $6, $7)"#,
+        &[
+            &user_id,
+            &dump.user.username,
+            &dump.user.password_hash,
+            &dump.user.email,
+            &dump.user.email_verified_at,
+            &dump.user.created_at,
+            &dump.user.updated_at,
+        ],
+    )
+    .await
+    .on_constraint("user_pkey", |_| {
+        Error::constraint_violation("user_pkey", "user already exists")
+    })
+    .on_constraint("user_username_unique", |_| {
+        Error::constraint_violation("unique_username", "username already used")
+    })
+    .on_constraint("user_email_unique", |_| {
+        Error::constraint_violation("unique_email", "email already used")
+    })?;
+
+    if let Some(settings) = &dump.settings {
+        tx.execute(
+            "INSERT INTO user_settings
+                 (user_id, auto_tagging_enabled, summaries_enabled, summary_language,
+                  preferred_text_model, digest_enabled, reader_font_family, reader_font_size,
+                  reader_line_width, reader_justify)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+            &[
+                &user_id,
+                &settings.auto_tagging_enabled,
+                &settings.summaries_enabled,
+                &settings.summary_language,
+                &settings.preferred_text_model,
+                &settings.digest_enabled,
+                &settings.reader.font_family.as_ref(),
+                &settings.reader.font_size,
+                &settings.reader.line_width,
+                &settings.reader.justify,
+            ],
+        )
+        .await?;
+    }
+
+    for bookmark in &dump.bookmarks {
+        let with_chunks = with_chunks && !bookmark.chunks.is_empty();
+        let embedding_status = if with_chunks || bookmark.text_content.len() < 200 {
+            AiGenerationStatus::Done
+        } else {
+            AiGenerationStatus::Pending
+        };
+        let word_count = reading_time::word_count(&bookmark.text_content);
+        tx.execute(
+            "INSERT INTO bookmark
+                 (bookmark_id, user_id, url, canonical_url, domain, title, text_content, tags,
+                  summary, summary_status, tag_status, text_ai_status, text_ai_pipeline_version,
+                  embedding_status, embedding_pipeline_version, language, text_search_config,
+                  static_bytes, created_at, updated_at, word_count, reading_time_minutes, kind,
+                  favorite, original_title, visibility, read_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17,
+                     $18, $19, $20, $21, $22, $23, $24, $25, $26, $27)",
+            &[
+                &bookmark.bookmark_id,
+                &user_id,
+                &bookmark.url,
+                &bookmark.canonical_url,
+                &bookmark.domain,
+                &bookmark.title,
+                &bookmark.text_content,
+                &bookmark.tags,
+                &bookmark.summary,
+                &bookmark.summary_status,
+                &bookmark.tag_status,
+                &bookmark.text_ai_status,
+                &TEXT_AI_PIPELINE_VERSION,
+                &embedding_status,
+                &EMBEDDING_PIPELINE_VERSION,
+                &bookmark.language,
+                &bookmark.text_search_config,
+                &bookmark.static_bytes,
+                &bookmark.created_at,
+                &bookmark.updated_at,
+                &word_count,
+                &reading_time::minutes(word_count),
+                &bookmark.kind,
+                &bookmark.favorite,
+                &bookmark.original_title,
+                &bookmark.visibility.as_ref(),
+                &bookmark.read_at,
+            ],
+        )
+        .await?;
+        for version in &bookmark.versions {
+            tx.execute(
+                "INSERT INTO bookmark_version
+                     (version_id, bookmark_id, user_id, title, text_content, html, word_count,
+                      captured_at, created_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+                &[
+                    &version.version_id,
+                    &bookmark.bookmark_id,
+                    &user_id,
+                    &version.title,
+                    &version.text_content,
+                    &version.html,
+                    &version.word_count,
+                    &version.captured_at,
+                    &version.created_at,
+                ],
+            )
+            .await?;
+        }
+        for entity in &bookmark.entities {
+            tx.execute(
+                "INSERT INTO entity (bookmark_id, user_id, kind, name) VALUES ($1, $2, $3, $4)
+                 ON CONFLICT DO NOTHING",
+                &[
+                    &bookmark.bookmark_id,
+                    &user_id,
+                    &entity.kind.as_ref(),
+                    &entity.name,
+                ],
+            )
+            .await?;
+        }
+        if !with_chunks {
+            continue;
+        }
+        for chunk in &bookmark.chunks {
+            let embedding = chunk.embedding.clone().map(Vector::from);
+            tx.execute(
+                "INSERT INTO bookmark_chunk
+                     (bookmark_id, user_id, chunk_text, chunk_index, embedding)
+                 VALUES ($1, $2, $3, $4, $5)",
+                &[
+                    &bookmark.bookmark_id,
+                    &user_id,
+                    &chunk.chunk_text,
+                    &chunk.chunk_index,
+                    &embedding,
+                ],
+            )
+            .await?;
+        }
+        tx.execute(
+            "UPDATE bookmark
+             SET embedding = (
+                 SELECT AVG(embedding) FROM bookmark_chunk
+                 WHERE bookmark_id = $1 AND user_id = $2
+             )
+             WHERE bookmark_id = $1 AND user_id = $2",
+            &[&bookmark.bookmark_id, &user_id],
+        )
+        .await?;
+    }
+
+    for digest in &dump.digests {
+        tx.execute(
+            "INSERT INTO digest
+                 (user_id, period_start, period_end, bookmark_ids, content, emailed_at, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            &[
+                &user_id,
+                &digest.period_start,
+                &digest.period_end,
+                &digest.bookmark_ids,
+                &digest.content,
+                &digest.emailed_at,
+                &digest.created_at,
+            ],
+        )
+        .await?;
+    }
+
+    for collection in &dump.collections {
+        tx.execute(
+            "INSERT INTO collection
+                 (collection_id, user_id, name, description, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6)",
+            &[
+                &collection.collection_id,
+                &user_id,
+                &collection.name,
+                &collection.description,
+                &collection.created_at,
+                &collection.updated_at,
+            ],
+        )
+        .await?;
+        for (position, bookmark_id) in collection.bookmark_ids.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO collection_bookmark (collection_id, user_id, bookmark_id, position)
+                 VALUES ($1, $2, $3, $4)",
+                &[
+                    &collection.collection_id,
+                    &user_id,
+                    bookmark_id,
+                    &(position as i32),
+                ],
+            )
+            .await?;
+        }
+    }
+
+    for rule in &dump.rules {
+        tx.execute(
+            "INSERT INTO rule
+                 (rule_id, user_id, name, enabled, conditions, actions, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            &[
+                &rule.rule_id,
+                &user_id,
+                &rule.name,
+                &rule.enabled,
+                &Json(&rule.conditions),
+                &Json(&rule.actions),
+                &rule.created_at,
+                &rule.updated_at,
+            ],
+        )
+        .await?;
+    }
+
+    for rule in &dump.extraction_rules {
+        tx.execute(
+            "INSERT INTO extraction_rule
+                 (rule_id, user_id, domain, keep_selectors, drop_selectors, title_selector,
+                  created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            &[
+                &rule.rule_id,
+                &user_id,
+                &rule.domain,
+                &rule.keep_selectors,
+                &rule.drop_selectors,
+                &rule.title_selector,
+                &rule.created_at,
+                &rule.updated_at,
+            ],
+        )
+        .await?;
+    }
+
+    for credential in &dump.fetch_credentials {
+        tx.execute(
+            "INSERT INTO fetch_credential (user_id, domain, sealed_headers, updated_at)
+             VALUES ($1, $2, $3, $4)",
+            &[
+                &user_id,
+                &credential.domain,
+                &credential.sealed_headers,
+                &credential.updated_at,
+            ],
+        )
+        .await?;
+    }
+
+    for workspace in &dump.workspaces {
+        tx.execute(
+            "INSERT INTO workspace (workspace_id, owner_id, name, created_at)
+             VALUES ($1, $2, $3, $4)",
+            &[
+                &workspace.workspace_id,
+                &user_id,
+                &workspace.name,
+                &workspace.created_at,
+            ],
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+    info!(
+        user_id = %user_id,
+        bookmark_count = dump.bookmarks.len(),
+        with_chunks,
+        "Imported user"
+    );
+    Ok(())
+}
+
+/// Second pass of a restore, once every user and their workspaces are in.
+/// Memberships of workspaces that weren't restored are skipped.
+pub async fn import_workspace_memberships(
+    pool: &Database,
+    user_id: Uuid,
+    memberships: &[WorkspaceMembershipRecord],
+    bookmarks: &[WorkspaceBookmarkRecord],
+) -> Result<()> {
+    let pool = dispatch!(pool, |db| sqlite::backup::import_workspace_memberships(
+        db,
+        user_id,
+        memberships,
+        bookmarks,
+    ));
+    let mut client = pool.get().await?;
+    let tx = client.transaction().await?;
+    for membership in memberships {
+        tx.execute(
+            "INSERT INTO workspace_member (workspace_id, user_id, role, joined_at)
+             SELECT workspace_id, $2, $3, $4 FROM workspace WHERE workspace_id = $1
+             ON CONFLICT DO NOTHING",
+            &[
+                &membership.workspace_id,
+                &user_id,
+                &membership.role,
+                &membership.joined_at,
+            ],
+        )
+        .await?;
+    }
+    for bookmark in bookmarks {
+        tx.execute(
+            "INSERT INTO workspace_bookmark (workspace_id, user_id, bookmark_id, added_at)
+             SELECT workspace_id, $2, $3, $4 FROM workspace WHERE workspace_id = $1
+             ON CONFLICT DO NOTHING",
+            &[
+                &bookmark.workspace_id,
+                &user_id,
+                &bookmark.bookmark_id,
+                &bookmark.added_at,
+            ],
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    debug!(
+        user_id = %user_id,
+        membership_count = memberships.len(),
+        "Imported workspace memberships"
+    );
+    Ok(())
+}
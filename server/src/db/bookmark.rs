@@ -1,19 +1,30 @@
 use std::collections::HashSet;
 
 use anyhow::anyhow;
-use chrono::{DateTime, Utc};
-use deadpool_postgres::GenericClient;
+use chrono::{DateTime, NaiveDate, Utc};
 use postgres_from_row::FromRow;
 use postgres_types::{FromSql, ToSql};
 use serde::{Deserialize, Serialize};
-use shared::{Bookmark, TagOperation};
+use sha2::{Digest, Sha256};
+use shared::{Bookmark, BookmarkKind, BulkBookmarkAction, SummaryStyle, TagOperation, Visibility};
 use tracing::{debug, info};
+use url::Url;
 use uuid::Uuid;
 
-use super::{PgPool, ResultExt};
-use crate::bookmark_identity::canonicalize_url_str;
+use super::{dispatch, sqlite, Database, GenericClient, PgPool, ResultExt};
+use crate::bookmark_identity::{canonicalize_url, canonicalize_url_str, domain_from_url};
 use crate::error::{Error, Result};
-use crate::{EMBEDDING_PIPELINE_VERSION, TEXT_AI_PIPELINE_VERSION};
+use crate::{language, reading_time, EMBEDDING_PIPELINE_VERSION, TEXT_AI_PIPELINE_VERSION};
+
+/// Kinds are stored as text, one no longer known reads as unclassified.
+pub(super) fn parse_kind(kind: Option<String>) -> Option<BookmarkKind> {
+    kind.and_then(|kind| kind.parse().ok())
+}
+
+/// Unknown values read as private, the narrowest audience.
+pub(super) fn parse_visibility(visibility: String) -> Visibility {
+    visibility.parse().unwrap_or_default()
+}
 
 fn normalize_tags(tags: &[String]) -> Vec<String> {
     let mut seen = HashSet::new();
@@ -23,7 +34,7 @@ fn normalize_tags(tags: &[String]) -> Vec<String> {
         .collect()
 }
 
-fn normalized_tag_option(tags: &[String]) -> Option<Vec<String>> {
+pub(super) fn normalized_tag_option(tags: &[String]) -> Option<Vec<String>> {
     let tags = normalize_tags(tags);
     if tags.is_empty() {
         None
@@ -71,6 +82,8 @@ pub enum AiGenerationStatus {
     Pending,
     Done,
     Fail,
+    /// Not generated because the user turned the feature off
+    Skipped,
 }
 
 #[derive(Debug, Clone)]
@@ -79,6 +92,8 @@ pub struct BookmarkGenerationCandidate {
     pub attempts: i16,
     pub needs_summary: bool,
     pub needs_tags: bool,
+    /// Asked for by the user regenerating the summary
+    pub summary_style: Option<SummaryStyle>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -92,6 +107,12 @@ struct RowBookmark {
     summary: Option<String>,
     created_at: DateTime<Utc>,
     updated_at: Option<DateTime<Utc>>,
+    word_count: i32,
+    reading_time_minutes: i32,
+    kind: Option<String>,
+    favorite: bool,
+    original_title: Option<String>,
+    visibility: String,
 }
 
 impl From<RowBookmark> for Bookmark {
@@ -106,20 +127,29 @@ impl From<RowBookmark> for Bookmark {
             summary: value.summary,
             created_at: value.created_at,
             updated_at: value.updated_at,
+            word_count: value.word_count,
+            reading_time_minutes: value.reading_time_minutes,
+            kind: parse_kind(value.kind),
+            favorite: value.favorite,
+            original_title: value.original_title,
+            visibility: parse_visibility(value.visibility),
         }
     }
 }
 
-pub async fn get_tag_count_by_user(pool: &PgPool, user_id: Uuid) -> Result<Vec<(String, i64)>> {
+pub async fn get_tag_count_by_user(pool: &Database, user_id: Uuid) -> Result<Vec<(String, i64)>> {
     const SQL: &str = r#"
     WITH tags AS (
         SELECT unnest(tags) AS tag
         FROM bookmark
-        WHERE user_id = $1
+        WHERE user_id = $1 AND deleted_at IS NULL
     )
     SELECT tag, count(1) AS counter FROM tags GROUP BY tag;
     "#;
     debug!(user_id = %user_id, "Fetching tag counts");
+    let pool = dispatch!(pool, |db| sqlite::bookmark::get_tag_count_by_user(
+        db, user_id
+    ));
     let client = pool.get().await?;
     let rows = client.query(SQL, &[&user_id]).await?;
     let result = rows
@@ -135,9 +165,11 @@ pub async fn get_tag_count_by_user(pool: &PgPool, user_id: Uuid) -> Result<Vec<(
     Ok(result)
 }
 
-pub async fn get_by_user(pool: &PgPool, user_id: Uuid) -> Result<Vec<Bookmark>> {
-    const SQL: &str = "SELECT * FROM bookmark b WHERE b.user_id = $1 ORDER BY b.created_at ASC;";
+pub async fn get_by_user(pool: &Database, user_id: Uuid) -> Result<Vec<Bookmark>> {
+    const SQL: &str = "SELECT * FROM bookmark b WHERE b.user_id = $1 AND b.deleted_at IS NULL \
+                       ORDER BY b.created_at ASC;";
     debug!(user_id = %user_id, "Fetching all bookmarks");
+    let pool = dispatch!(pool, |db| sqlite::bookmark::get_by_user(db, user_id));
     let client = pool.get().await?;
     let results = client
         .query(SQL, &[&user_id])
@@ -153,10 +185,44 @@ pub async fn get_by_user(pool: &PgPool, user_id: Uuid) -> Result<Vec<Bookmark>>
     Ok(results)
 }
 
-pub async fn get_by_tag(pool: &PgPool, user_id: Uuid, tag: &str) -> Result<Vec<Bookmark>> {
-    const SQL: &str =
-        "SELECT * FROM bookmark b WHERE b.user_id = $1 AND b.tags @> $2 ORDER BY b.created_at ASC;";
+/// The integer id wallabag clients know a bookmark by, derived from its id,
+/// itself a hash of the URL. Computed by the `wallabag_entry_id` SQL function
+/// as well.
+pub fn wallabag_entry_id(bookmark_id: &str) -> i64 {
+    let hash = Sha256::digest(bookmark_id.as_bytes());
+    i64::from(u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]]) & 0x7fff_ffff)
+}
+
+/// The bookmark with the wallabag `entry_id`, the oldest one should two
+/// share it.
+pub async fn get_by_wallabag_entry_id(
+    pool: &Database,
+    user_id: Uuid,
+    entry_id: i64,
+) -> Result<Option<Bookmark>> {
+    const SQL: &str = "SELECT * FROM bookmark b \
+                       WHERE b.user_id = $1 AND wallabag_entry_id(b.bookmark_id) = $2 \
+                       AND b.deleted_at IS NULL ORDER BY b.created_at ASC LIMIT 1;";
+    let pool = dispatch!(pool, |db| sqlite::bookmark::get_by_wallabag_entry_id(
+        db, user_id, entry_id
+    ));
+    let client = pool.get().await?;
+    client
+        .query_opt(SQL, &[&user_id, &entry_id])
+        .await?
+        .map(|row| {
+            RowBookmark::try_from_row(&row)
+                .map(Bookmark::from)
+                .map_err(Error::from)
+        })
+        .transpose()
+}
+
+pub async fn get_by_tag(pool: &Database, user_id: Uuid, tag: &str) -> Result<Vec<Bookmark>> {
+    const SQL: &str = "SELECT * FROM bookmark b WHERE b.user_id = $1 AND b.tags @> $2 \
+                       AND b.deleted_at IS NULL ORDER BY b.created_at ASC;";
     debug!(user_id = %user_id, tag = %tag, "Fetching bookmarks with tag");
+    let pool = dispatch!(pool, |db| sqlite::bookmark::get_by_tag(db, user_id, tag));
     let client = pool.get().await?;
     let results = client
         .query(SQL, &[&user_id, &[&tag]])
@@ -177,12 +243,159 @@ pub async fn get_by_tag(pool: &PgPool, user_id: Uuid, tag: &str) -> Result<Vec<B
     Ok(results)
 }
 
+/// A random bookmark of the user, among the ones never opened in the reader
+/// when `unread` is set.
+pub async fn get_random(
+    pool: &Database,
+    user_id: Uuid,
+    tag: Option<&str>,
+    unread: bool,
+) -> Result<Option<Bookmark>> {
+    const SQL: &str = "SELECT * FROM bookmark b WHERE b.user_id = $1 AND b.deleted_at IS NULL \
+                       AND ($2::text IS NULL OR $2 = ANY(b.tags)) \
+                       AND (NOT $3 OR b.read_at IS NULL) ORDER BY random() LIMIT 1;";
+    debug!(user_id = %user_id, tag = ?tag, unread, "Picking a random bookmark");
+    let pool = dispatch!(pool, |db| sqlite::bookmark::get_random(
+        db, user_id, tag, unread
+    ));
+    let client = pool.get().await?;
+    client
+        .query_opt(SQL, &[&user_id, &tag, &unread])
+        .await?
+        .map(|row| {
+            RowBookmark::try_from_row(&row)
+                .map(Bookmark::from)
+                .map_err(Error::from)
+        })
+        .transpose()
+}
+
+/// Other bookmarks of the user whose articles link to the canonical URL of
+/// `bookmark_id`, newest first.
+pub async fn get_backlinks(
+    pool: &Database,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<Vec<Bookmark>> {
+    const SQL: &str = "SELECT b.* FROM bookmark b
+        WHERE b.user_id = $2 AND b.bookmark_id <> $1 AND b.deleted_at IS NULL
+          AND EXISTS (
+            SELECT 1 FROM bookmark_link l
+            JOIN bookmark target ON target.user_id = l.user_id AND target.canonical_url = l.url
+            WHERE l.bookmark_id = b.bookmark_id AND l.user_id = b.user_id
+              AND target.bookmark_id = $1
+          )
+        ORDER BY b.created_at DESC;";
+    let pool = dispatch!(pool, |db| sqlite::bookmark::get_backlinks(
+        db,
+        user_id,
+        bookmark_id
+    ));
+    let client = pool.get().await?;
+    let results = client
+        .query(SQL, &[&bookmark_id, &user_id])
+        .await?
+        .iter()
+        .map(|row| {
+            RowBookmark::try_from_row(row)
+                .map(Bookmark::from)
+                .map_err(Error::from)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    debug!(bookmark_id = %bookmark_id, backlink_count = %results.len(), "Found backlinks");
+    Ok(results)
+}
+
+/// Bookmarks of a collection of the user, in its order.
+pub async fn get_by_collection(
+    pool: &Database,
+    user_id: Uuid,
+    collection_id: Uuid,
+) -> Result<Vec<Bookmark>> {
+    const SQL: &str = "SELECT b.* FROM collection_bookmark cb
+        JOIN bookmark b ON b.bookmark_id = cb.bookmark_id AND b.user_id = cb.user_id
+        WHERE cb.collection_id = $1 AND cb.user_id = $2 AND b.deleted_at IS NULL
+        ORDER BY cb.position;";
+    let pool = dispatch!(pool, |db| sqlite::bookmark::get_by_collection(
+        db,
+        user_id,
+        collection_id
+    ));
+    let client = pool.get().await?;
+    client
+        .query(SQL, &[&collection_id, &user_id])
+        .await?
+        .iter()
+        .map(|row| {
+            RowBookmark::try_from_row(row)
+                .map(Bookmark::from)
+                .map_err(Error::from)
+        })
+        .collect()
+}
+
+/// Bookmarks saved on the same month and day as `today` in earlier years,
+/// newest first. Dates are in UTC.
+pub async fn get_on_this_day(
+    pool: &Database,
+    user_id: Uuid,
+    today: NaiveDate,
+) -> Result<Vec<Bookmark>> {
+    const SQL: &str = r#"
+    SELECT * FROM bookmark b
+    WHERE b.user_id = $1
+        AND b.deleted_at IS NULL
+        AND to_char(b.created_at AT TIME ZONE 'UTC', 'MM-DD') = to_char($2::date, 'MM-DD')
+        AND (b.created_at AT TIME ZONE 'UTC')::date < $2::date
+    ORDER BY b.created_at DESC;"#;
+    debug!(user_id = %user_id, today = %today, "Fetching bookmarks saved on this day");
+    let pool = dispatch!(pool, |db| sqlite::bookmark::get_on_this_day(
+        db, user_id, today
+    ));
+    let client = pool.get().await?;
+    let results = client
+        .query(SQL, &[&user_id, &today])
+        .await?
+        .iter()
+        .map(|row| {
+            RowBookmark::try_from_row(row)
+                .map(Bookmark::from)
+                .map_err(Error::from)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    info!(
+        user_id = %user_id,
+        bookmark_count = %results.len(),
+        "Found bookmarks saved on this day"
+    );
+    Ok(results)
+}
+
+/// Which of `canonical_urls` the user already saved, outside the trash.
+pub async fn existing_canonical_urls(
+    pool: &Database,
+    user_id: Uuid,
+    canonical_urls: &[String],
+) -> Result<HashSet<String>> {
+    const SQL: &str = "SELECT canonical_url FROM bookmark \
+                       WHERE user_id = $1 AND canonical_url = ANY($2) AND deleted_at IS NULL;";
+    let pool = dispatch!(pool, |db| sqlite::bookmark::existing_canonical_urls(
+        db,
+        user_id,
+        canonical_urls
+    ));
+    let client = pool.get().await?;
+    let rows = client.query(SQL, &[&user_id, &canonical_urls]).await?;
+    Ok(rows.iter().map(|row| row.get("canonical_url")).collect())
+}
+
 pub async fn get_by_canonical_url_and_user_id(
-    pool: &PgPool,
+    pool: &Database,
     url: &str,
     user_id: Uuid,
 ) -> Result<Option<Bookmark>> {
-    const SQL: &str = "SELECT * FROM bookmark WHERE canonical_url = $1 AND user_id = $2;";
+    const SQL: &str = "SELECT * FROM bookmark \
+                       WHERE canonical_url = $1 AND user_id = $2 AND deleted_at IS NULL;";
     let canonical_url = canonicalize_url_str(url)?;
     debug!(
         url = %url,
@@ -190,6 +403,9 @@ pub async fn get_by_canonical_url_and_user_id(
         user_id = %user_id,
         "Checking for existing bookmark"
     );
+    let pool = dispatch!(pool, |db| {
+        sqlite::bookmark::get_by_canonical_url_and_user_id(db, canonical_url, user_id)
+    });
     let client = pool.get().await?;
     let result = client
         .query_opt(SQL, &[&canonical_url, &user_id])
@@ -214,12 +430,18 @@ pub async fn get_by_canonical_url_and_user_id(
 }
 
 pub async fn get_with_user_data(
-    pool: &PgPool,
+    pool: &Database,
     user_id: Uuid,
     bookmark_id: &str,
 ) -> Result<Option<Bookmark>> {
-    const SQL: &str = "SELECT * FROM bookmark b WHERE b.user_id = $1 AND b.bookmark_id = $2;";
+    const SQL: &str = "SELECT * FROM bookmark b \
+                       WHERE b.user_id = $1 AND b.bookmark_id = $2 AND b.deleted_at IS NULL;";
     debug!(bookmark_id = %bookmark_id, user_id = %user_id, "Fetching bookmark");
+    let pool = dispatch!(pool, |db| sqlite::bookmark::get_with_user_data(
+        db,
+        user_id,
+        bookmark_id
+    ));
     let client = pool.get().await?;
     let result = client
         .query_opt(SQL, &[&user_id, &bookmark_id])
@@ -237,36 +459,81 @@ pub async fn get_with_user_data(
     Ok(result)
 }
 
+/// A bookmark saved into the workspace, the earliest saved when several
+/// members saved one with the same id. Trashed and private ones are not
+/// shared.
+pub async fn get_in_workspace(
+    pool: &Database,
+    workspace_id: Uuid,
+    bookmark_id: &str,
+) -> Result<Option<Bookmark>> {
+    let pool = dispatch!(pool, |db| sqlite::bookmark::get_in_workspace(
+        db,
+        workspace_id,
+        bookmark_id
+    ));
+    let client = pool.get().await?;
+    client
+        .query_opt(
+            "SELECT b.* FROM bookmark b
+             JOIN workspace_bookmark wb ON wb.bookmark_id = b.bookmark_id AND wb.user_id = b.user_id
+             WHERE wb.workspace_id = $1 AND b.bookmark_id = $2
+             AND b.deleted_at IS NULL AND b.visibility <> 'private'
+             ORDER BY wb.added_at
+             LIMIT 1",
+            &[&workspace_id, &bookmark_id],
+        )
+        .await?
+        .map(|row| {
+            RowBookmark::try_from_row(&row)
+                .map(Bookmark::from)
+                .map_err(Error::from)
+        })
+        .transpose()
+}
+
+/// Sets the tags of a bookmark to its own with the ones of `${idx}` added.
+fn tags_appended(idx: usize) -> String {
+    format!(
+        "tags=(SELECT NULLIF(ARRAY(
+            SELECT DISTINCT unnest(array_cat(COALESCE(tags, ARRAY[]::text[]), COALESCE(${idx}, ARRAY[]::text[])))
+        ), ARRAY[]::text[]))"
+    )
+}
+
+/// Tags set by hand are final, the text AI only has the summary left to do.
+const TAGS_DONE: &str = "tag_status='done',
+    text_ai_status=CASE
+        WHEN summary_status='done' THEN 'done'::task_status
+        ELSE 'pending'::task_status
+    END,
+    text_ai_attempts=0,
+    text_ai_next_attempt_at=now(),
+    text_ai_fail_reason=NULL,
+    updated_at=now()";
+
 pub async fn update_tags(
-    pool: &PgPool,
+    pool: &Database,
     user_id: Uuid,
     bookmark_id: &str,
     operation: &TagOperation,
 ) -> Result<Bookmark> {
     let (update_tag_sql, tags) = match operation {
-        TagOperation::Set(tags) => ("tags=$1", normalized_tag_option(tags)),
-        TagOperation::Append(tags) => (
-            "tags=(SELECT NULLIF(ARRAY(
-                SELECT DISTINCT unnest(array_cat(COALESCE(tags, ARRAY[]::text[]), COALESCE($1, ARRAY[]::text[])))
-            ), ARRAY[]::text[]))",
-            normalized_tag_option(tags),
-        ),
+        TagOperation::Set(tags) => ("tags=$1".to_string(), normalized_tag_option(tags)),
+        TagOperation::Append(tags) => (tags_appended(1), normalized_tag_option(tags)),
     };
     let sql = format!(
         "UPDATE bookmark
-         SET {update_tag_sql},
-             tag_status='done',
-             text_ai_status=CASE
-                 WHEN summary_status='done' THEN 'done'::task_status
-                 ELSE 'pending'::task_status
-             END,
-             text_ai_attempts=0,
-             text_ai_next_attempt_at=now(),
-             text_ai_fail_reason=NULL,
-             updated_at=now()
+         SET {update_tag_sql}, {TAGS_DONE}
          WHERE bookmark_id=$2 AND user_id=$3
          RETURNING *;"
     );
+    let pool = dispatch!(pool, |db| sqlite::bookmark::update_tags(
+        db,
+        user_id,
+        bookmark_id,
+        operation
+    ));
     let client = pool.get().await?;
     let row = client
         .query_one(&sql, &[&tags, &bookmark_id, &user_id])
@@ -284,31 +551,142 @@ pub async fn update_tags(
     Ok(result)
 }
 
-pub async fn save(pool: &PgPool, bookmark: &Bookmark, text_content: &str) -> Result<Bookmark> {
-    let canonical_url = canonicalize_url_str(&bookmark.url)?;
-    let normalized_tags: Option<Vec<String>> = bookmark
-        .tags
-        .as_ref()
-        .and_then(|tags| normalized_tag_option(tags));
-    let summary = bookmark
-        .summary
-        .as_ref()
-        .map(|summary| summary.trim().to_string())
-        .filter(|summary| !summary.is_empty());
-    let summary_status = status_for_initial_summary(summary.as_deref());
-    let tag_status = status_for_initial_tags(normalized_tags.as_deref());
-    let text_ai_status = status_for_initial_text_ai(summary_status, tag_status);
-    let embedding_status = status_for_initial_embeddings(text_content);
+/// Applies `action` to the bookmarks of `bookmark_ids` the user has outside
+/// the trash, all of them or none should it fail. Returns how many it was
+/// applied to.
+pub async fn bulk_update(
+    pool: &Database,
+    user_id: Uuid,
+    bookmark_ids: &[String],
+    action: &BulkBookmarkAction,
+) -> Result<u64> {
+    const MATCHING: &str = "user_id=$1 AND bookmark_id=ANY($2) AND deleted_at IS NULL";
+    let pool = dispatch!(pool, |db| sqlite::bookmark::bulk_update(
+        db,
+        user_id,
+        bookmark_ids,
+        action
+    ));
+    let client = pool.get().await?;
+    let updated = match action {
+        BulkBookmarkAction::AddTags { tags } => {
+            let sql = format!(
+                "UPDATE bookmark SET {}, {TAGS_DONE} WHERE {MATCHING}",
+                tags_appended(3)
+            );
+            client
+                .execute(
+                    &sql,
+                    &[&user_id, &bookmark_ids, &normalized_tag_option(tags)],
+                )
+                .await?
+        }
+        BulkBookmarkAction::Trash => {
+            let sql = format!("UPDATE bookmark SET deleted_at=now() WHERE {MATCHING}");
+            client.execute(&sql, &[&user_id, &bookmark_ids]).await?
+        }
+        BulkBookmarkAction::Favorite | BulkBookmarkAction::Unfavorite => {
+            let favorite = matches!(action, BulkBookmarkAction::Favorite);
+            let sql = format!("UPDATE bookmark SET favorite=$3 WHERE {MATCHING}");
+            client
+                .execute(&sql, &[&user_id, &bookmark_ids, &favorite])
+                .await?
+        }
+    };
+    info!(
+        user_id = %user_id,
+        action = ?action,
+        requested = bookmark_ids.len(),
+        updated,
+        "Bulk updated bookmarks"
+    );
+    Ok(updated)
+}
+
+/// Replaces the tag `from` with `to` on every bookmark of the user, merging it
+/// into `to` where both were present. Returns the number of bookmarks changed.
+pub async fn rename_tag(pool: &Database, user_id: Uuid, from: &str, to: &str) -> Result<u64> {
+    const SQL: &str = "UPDATE bookmark
+         SET tags=ARRAY(SELECT DISTINCT unnest(array_replace(tags, $2, $3))),
+             updated_at=now()
+         WHERE user_id=$1 AND tags @> ARRAY[$2]";
+    debug!(user_id = %user_id, from = %from, to = %to, "Renaming tag");
+    let pool = dispatch!(pool, |db| sqlite::bookmark::rename_tag(
+        db, user_id, from, to
+    ));
+    let client = pool.get().await?;
+    let renamed = client.execute(SQL, &[&user_id, &from, &to]).await?;
+    info!(user_id = %user_id, from = %from, to = %to, bookmark_count = %renamed, "Renamed tag");
+    Ok(renamed)
+}
+
+/// Values derived from a bookmark and its text when it is first saved.
+pub(super) struct NewBookmark {
+    pub(super) canonical_url: String,
+    pub(super) tags: Option<Vec<String>>,
+    pub(super) summary: Option<String>,
+    pub(super) summary_status: AiGenerationStatus,
+    pub(super) tag_status: AiGenerationStatus,
+    pub(super) text_ai_status: AiGenerationStatus,
+    pub(super) embedding_status: AiGenerationStatus,
+    pub(super) language: Option<&'static str>,
+    pub(super) text_search_config: &'static str,
+    pub(super) word_count: i32,
+    pub(super) reading_time_minutes: i32,
+}
+
+impl NewBookmark {
+    fn new(bookmark: &Bookmark, text_content: &str) -> Result<Self> {
+        let canonical_url = canonicalize_url_str(&bookmark.url)?;
+        let tags: Option<Vec<String>> = bookmark
+            .tags
+            .as_ref()
+            .and_then(|tags| normalized_tag_option(tags));
+        let summary = bookmark
+            .summary
+            .as_ref()
+            .map(|summary| summary.trim().to_string())
+            .filter(|summary| !summary.is_empty());
+        let summary_status = status_for_initial_summary(summary.as_deref());
+        let tag_status = status_for_initial_tags(tags.as_deref());
+        let detected = language::detect(&format!("{}\n{}", bookmark.title, text_content));
+        let word_count = reading_time::word_count(text_content);
+        Ok(Self {
+            canonical_url,
+            tags,
+            summary,
+            summary_status,
+            tag_status,
+            text_ai_status: status_for_initial_text_ai(summary_status, tag_status),
+            embedding_status: status_for_initial_embeddings(text_content),
+            language: detected.map(|detected| detected.code),
+            text_search_config: language::text_search_config(detected),
+            word_count,
+            reading_time_minutes: reading_time::minutes(word_count),
+        })
+    }
+}
+
+pub async fn save(pool: &Database, bookmark: &Bookmark, text_content: &str) -> Result<Bookmark> {
+    let new = NewBookmark::new(bookmark, text_content)?;
+    let pool = dispatch!(pool, |db| sqlite::bookmark::save(
+        db,
+        bookmark,
+        text_content,
+        new
+    ));
 
     const SQL: &str = r#"
     INSERT INTO bookmark
         (bookmark_id, user_id, url, canonical_url, domain, title, text_content, tags, summary,
          summary_status, tag_status, text_ai_status, text_ai_attempts, text_ai_next_attempt_at,
          text_ai_fail_reason, text_ai_pipeline_version, embedding_status, embedding_attempts,
-         embedding_next_attempt_at, embedding_fail_reason, embedding_pipeline_version, created_at, updated_at)
+         embedding_next_attempt_at, embedding_fail_reason, embedding_pipeline_version, language,
+         text_search_config, word_count, reading_time_minutes, favorite, created_at, updated_at)
     VALUES
         ($1, $2, $3, $4, $5, $6, $7, $8, $9,
-         $10, $11, $12, 0, now(), NULL, $13, $14, 0, now(), NULL, $15, now(), now())
+         $10, $11, $12, 0, now(), NULL, $13, $14, 0, now(), NULL, $15, $16, $17, $18, $19, $20,
+         now(), now())
     RETURNING *;"#;
 
     let client = pool.get().await?;
@@ -319,23 +697,32 @@ pub async fn save(pool: &PgPool, bookmark: &Bookmark, text_content: &str) -> Res
                 &bookmark.bookmark_id,
                 &bookmark.user_id,
                 &bookmark.url,
-                &canonical_url,
+                &new.canonical_url,
                 &bookmark.domain,
                 &bookmark.title,
                 &text_content,
-                &normalized_tags,
-                &summary,
-                &summary_status,
-                &tag_status,
-                &text_ai_status,
+                &new.tags,
+                &new.summary,
+                &new.summary_status,
+                &new.tag_status,
+                &new.text_ai_status,
                 &TEXT_AI_PIPELINE_VERSION,
-                &embedding_status,
+                &new.embedding_status,
                 &EMBEDDING_PIPELINE_VERSION,
+                &new.language,
+                &new.text_search_config,
+                &new.word_count,
+                &new.reading_time_minutes,
+                &bookmark.favorite,
             ],
         )
         .await
         .on_constraint("bookmark_canonical_url_user_unique", |_| {
             Error::constraint_violation("duplicate_bookmark", "bookmark already exists for user")
+        })
+        // The id of a bookmark whose URL was edited away is still taken
+        .on_constraint("bookmark_pkey", |_| {
+            Error::constraint_violation("duplicate_bookmark", "bookmark already exists for user")
         })?;
     let result = RowBookmark::try_from_row(&row)
         .map(Bookmark::from)
@@ -350,6 +737,88 @@ pub async fn save(pool: &PgPool, bookmark: &Bookmark, text_content: &str) -> Res
     Ok(result)
 }
 
+/// Keeps the current capture of the bookmark as a version, `html` being its
+/// page, and replaces it with the title of `bookmark` and `text_content`.
+/// Summary and embeddings are generated again, tags are kept. A title the
+/// user edited is kept too, the new one becoming its `original_title`.
+pub async fn refresh_capture(
+    pool: &Database,
+    bookmark: &Bookmark,
+    text_content: &str,
+    html: Option<&str>,
+) -> Result<Bookmark> {
+    let new = NewBookmark::new(bookmark, text_content)?;
+    let pool = dispatch!(pool, |db| sqlite::bookmark::refresh_capture(
+        db,
+        bookmark,
+        text_content,
+        html,
+        new
+    ));
+
+    let mut client = pool.get().await?;
+    let tx = client.transaction().await?;
+    let kept = tx
+        .execute(
+            "INSERT INTO bookmark_version
+                (bookmark_id, user_id, title, text_content, html, word_count, captured_at)
+             SELECT bookmark_id, user_id, title, text_content, $3, word_count,
+                coalesce(captured_at, created_at)
+             FROM bookmark WHERE bookmark_id = $1 AND user_id = $2",
+            &[&bookmark.bookmark_id, &bookmark.user_id, &html],
+        )
+        .await?;
+    if kept == 0 {
+        return Err(Error::NotFound);
+    }
+    let row = tx
+        .query_one(
+            "UPDATE bookmark
+             SET title = CASE WHEN original_title IS NULL THEN $3 ELSE title END,
+                 original_title = CASE WHEN original_title IS NULL THEN NULL ELSE $3 END,
+                 text_content = $4,
+                 language = $5,
+                 text_search_config = $6,
+                 word_count = $7,
+                 reading_time_minutes = $8,
+                 summary_status = 'pending',
+                 text_ai_status = 'pending',
+                 text_ai_attempts = 0,
+                 text_ai_next_attempt_at = now(),
+                 text_ai_fail_reason = NULL,
+                 embedding_status = $9,
+                 embedding_attempts = 0,
+                 embedding_next_attempt_at = now(),
+                 embedding_fail_reason = NULL,
+                 captured_at = now(),
+                 updated_at = now()
+             WHERE bookmark_id = $1 AND user_id = $2
+             RETURNING *",
+            &[
+                &bookmark.bookmark_id,
+                &bookmark.user_id,
+                &bookmark.title,
+                &text_content,
+                &new.language,
+                &new.text_search_config,
+                &new.word_count,
+                &new.reading_time_minutes,
+                &new.embedding_status,
+            ],
+        )
+        .await?;
+    tx.commit().await?;
+    let result = RowBookmark::try_from_row(&row)
+        .map(Bookmark::from)
+        .map_err(Error::from)?;
+    info!(
+        bookmark_id = %result.bookmark_id,
+        user_id = %result.user_id,
+        "Bookmark capture refreshed"
+    );
+    Ok(result)
+}
+
 async fn bookmark_has_canonical_url_column(client: &impl GenericClient) -> Result<bool> {
     let exists = client
         .query_one(
@@ -445,7 +914,7 @@ pub async fn ensure_canonical_url_support(pool: &PgPool) -> Result<()> {
 }
 
 pub async fn update_summary(
-    pool: &PgPool,
+    pool: &Database,
     user_id: Uuid,
     bookmark_id: &str,
     summary: &str,
@@ -456,6 +925,12 @@ pub async fn update_summary(
     } else {
         Some(summary)
     };
+    let pool = dispatch!(pool, |db| sqlite::bookmark::update_summary(
+        db,
+        user_id,
+        bookmark_id,
+        summary
+    ));
     let client = pool.get().await?;
     let row = client
         .query_one(
@@ -488,12 +963,18 @@ pub async fn update_summary(
 }
 
 pub async fn get_text_content(
-    pool: &PgPool,
+    pool: &Database,
     user_id: Uuid,
     bookmark_id: &str,
 ) -> Result<Option<String>> {
-    const SQL: &str = "SELECT text_content FROM bookmark WHERE bookmark_id = $1 AND user_id = $2";
+    const SQL: &str = "SELECT text_content FROM bookmark \
+                       WHERE bookmark_id = $1 AND user_id = $2 AND deleted_at IS NULL";
     debug!(bookmark_id = %bookmark_id, user_id = %user_id, "Fetching text content");
+    let pool = dispatch!(pool, |db| sqlite::bookmark::get_text_content(
+        db,
+        user_id,
+        bookmark_id
+    ));
     let client = pool.get().await?;
     let result: Option<String> = client
         .query_opt(SQL, &[&bookmark_id, &user_id])
@@ -513,9 +994,278 @@ pub async fn get_text_content(
     Ok(result)
 }
 
-pub async fn delete(pool: &PgPool, user_id: Uuid, bookmark_id: &str) -> Result<bool> {
+/// Moves a bookmark to the trash, where it is hidden from every other query
+/// until restored or purged.
+/// Records the first time the bookmark is opened in the reader. False when
+/// the bookmark doesn't exist.
+pub async fn mark_read(pool: &Database, user_id: Uuid, bookmark_id: &str) -> Result<bool> {
+    const SQL: &str = "UPDATE bookmark SET read_at = COALESCE(read_at, now()) \
+                       WHERE bookmark_id = $1 AND user_id = $2 AND deleted_at IS NULL";
+    let pool = dispatch!(pool, |db| sqlite::bookmark::mark_read(
+        db,
+        user_id,
+        bookmark_id
+    ));
+    let client = pool.get().await?;
+    let rows_affected = client.execute(SQL, &[&bookmark_id, &user_id]).await?;
+    debug!(bookmark_id = %bookmark_id, user_id = %user_id, rows_affected, "Marked bookmark read");
+    Ok(rows_affected > 0)
+}
+
+pub async fn move_to_trash(pool: &Database, user_id: Uuid, bookmark_id: &str) -> Result<bool> {
+    const SQL: &str = "UPDATE bookmark SET deleted_at = now() \
+                       WHERE bookmark_id = $1 AND user_id = $2 AND deleted_at IS NULL";
+    let pool = dispatch!(pool, |db| sqlite::bookmark::move_to_trash(
+        db,
+        user_id,
+        bookmark_id
+    ));
+    let client = pool.get().await?;
+    let rows_affected = client.execute(SQL, &[&bookmark_id, &user_id]).await?;
+    debug!(bookmark_id = %bookmark_id, user_id = %user_id, rows_affected, "Moved bookmark to trash");
+    Ok(rows_affected > 0)
+}
+
+/// Bookmarks in the trash with when they were deleted, the most recent first.
+pub async fn get_trash(pool: &Database, user_id: Uuid) -> Result<Vec<(Bookmark, DateTime<Utc>)>> {
+    const SQL: &str = "SELECT * FROM bookmark b WHERE b.user_id = $1 AND b.deleted_at IS NOT NULL \
+                       ORDER BY b.deleted_at DESC;";
+    let pool = dispatch!(pool, |db| sqlite::bookmark::get_trash(db, user_id));
+    let client = pool.get().await?;
+    client
+        .query(SQL, &[&user_id])
+        .await?
+        .iter()
+        .map(|row| {
+            let bookmark = RowBookmark::try_from_row(row).map(Bookmark::from)?;
+            Ok((bookmark, row.try_get("deleted_at")?))
+        })
+        .collect()
+}
+
+/// Changes the title and/or the URL of the bookmark, keeping the extracted
+/// title as `original_title` the first time the title changes. `None` when
+/// the user has no such bookmark outside the trash.
+pub async fn edit(
+    pool: &Database,
+    user_id: Uuid,
+    bookmark_id: &str,
+    title: Option<&str>,
+    url: Option<&Url>,
+) -> Result<Option<Bookmark>> {
+    const SQL: &str = "UPDATE bookmark
+                       SET original_title = CASE
+                               WHEN $3::text IS NULL OR $3 = title THEN original_title
+                               ELSE coalesce(original_title, title)
+                           END,
+                           title = coalesce($3, title),
+                           url = coalesce($4, url),
+                           canonical_url = coalesce($5, canonical_url),
+                           domain = coalesce($6, domain),
+                           updated_at = now()
+                       WHERE bookmark_id = $1 AND user_id = $2 AND deleted_at IS NULL
+                       RETURNING *;";
+    let canonical_url = url
+        .map(|url| canonicalize_url(url.clone()))
+        .transpose()?
+        .map(String::from);
+    let domain = url.map(domain_from_url).transpose()?;
+    let url = url.map(Url::to_string);
+    let pool = match pool {
+        Database::Postgres(pool) => pool,
+        Database::Sqlite(db) => {
+            let title = title.map(str::to_string);
+            return sqlite::bookmark::edit(
+                db,
+                user_id,
+                bookmark_id,
+                title,
+                url,
+                canonical_url,
+                domain,
+            )
+            .await;
+        }
+    };
+    let client = pool.get().await?;
+    let result = client
+        .query_opt(
+            SQL,
+            &[
+                &bookmark_id,
+                &user_id,
+                &title,
+                &url,
+                &canonical_url,
+                &domain,
+            ],
+        )
+        .await
+        .on_constraint("bookmark_canonical_url_user_unique", |_| {
+            Error::constraint_violation("duplicate_bookmark", "bookmark already exists for user")
+        })?
+        .map(|row| {
+            RowBookmark::try_from_row(&row)
+                .map(Bookmark::from)
+                .map_err(Error::from)
+        })
+        .transpose()?;
+    if let Some(edited) = &result {
+        info!(bookmark_id = %edited.bookmark_id, %user_id, "Bookmark edited");
+    }
+    Ok(result)
+}
+
+/// Marks or unmarks the bookmark as favorite, `None` when the user has no
+/// such bookmark outside the trash.
+pub async fn set_favorite(
+    pool: &Database,
+    user_id: Uuid,
+    bookmark_id: &str,
+    favorite: bool,
+) -> Result<Option<Bookmark>> {
+    const SQL: &str = "UPDATE bookmark SET favorite = $3 \
+                       WHERE bookmark_id = $1 AND user_id = $2 AND deleted_at IS NULL \
+                       RETURNING *;";
+    let pool = dispatch!(pool, |db| sqlite::bookmark::set_favorite(
+        db,
+        user_id,
+        bookmark_id,
+        favorite
+    ));
+    let client = pool.get().await?;
+    client
+        .query_opt(SQL, &[&bookmark_id, &user_id, &favorite])
+        .await?
+        .map(|row| {
+            RowBookmark::try_from_row(&row)
+                .map(Bookmark::from)
+                .map_err(Error::from)
+        })
+        .transpose()
+}
+
+/// Changes who may see the bookmark, `None` when the user has no such
+/// bookmark outside the trash.
+pub async fn set_visibility(
+    pool: &Database,
+    user_id: Uuid,
+    bookmark_id: &str,
+    visibility: Visibility,
+) -> Result<Option<Bookmark>> {
+    const SQL: &str = "UPDATE bookmark SET visibility = $3 \
+                       WHERE bookmark_id = $1 AND user_id = $2 AND deleted_at IS NULL \
+                       RETURNING *;";
+    let pool = dispatch!(pool, |db| sqlite::bookmark::set_visibility(
+        db,
+        user_id,
+        bookmark_id,
+        visibility
+    ));
+    let client = pool.get().await?;
+    client
+        .query_opt(SQL, &[&bookmark_id, &user_id, &visibility.as_ref()])
+        .await?
+        .map(|row| {
+            RowBookmark::try_from_row(&row)
+                .map(Bookmark::from)
+                .map_err(Error::from)
+        })
+        .transpose()
+}
+
+/// Takes a bookmark out of the trash, `None` when it isn't there.
+pub async fn restore(
+    pool: &Database,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<Option<Bookmark>> {
+    const SQL: &str = "UPDATE bookmark SET deleted_at = NULL \
+                       WHERE bookmark_id = $1 AND user_id = $2 AND deleted_at IS NOT NULL \
+                       RETURNING *;";
+    let pool = dispatch!(pool, |db| sqlite::bookmark::restore(
+        db,
+        user_id,
+        bookmark_id
+    ));
+    let client = pool.get().await?;
+    let result = client
+        .query_opt(SQL, &[&bookmark_id, &user_id])
+        .await?
+        .map(|row| {
+            RowBookmark::try_from_row(&row)
+                .map(Bookmark::from)
+                .map_err(Error::from)
+        })
+        .transpose()?;
+    if result.is_some() {
+        info!(bookmark_id = %bookmark_id, user_id = %user_id, "Bookmark restored from trash");
+    }
+    Ok(result)
+}
+
+/// Takes the bookmark saved from `url` out of the trash, if it is there, so
+/// saving it again brings it back instead of duplicating it.
+pub async fn restore_by_canonical_url(pool: &Database, user_id: Uuid, url: &str) -> Result<bool> {
+    const SQL: &str = "UPDATE bookmark SET deleted_at = NULL \
+                       WHERE canonical_url = $1 AND user_id = $2 AND deleted_at IS NOT NULL";
+    let canonical_url = canonicalize_url_str(url)?;
+    let pool = dispatch!(pool, |db| sqlite::bookmark::restore_by_canonical_url(
+        db,
+        user_id,
+        canonical_url
+    ));
+    let client = pool.get().await?;
+    let rows_affected = client.execute(SQL, &[&canonical_url, &user_id]).await?;
+    Ok(rows_affected > 0)
+}
+
+/// Bookmarks of any user moved to the trash before `deleted_before`, as
+/// `(user_id, bookmark_id)`, the oldest first.
+pub async fn get_expired_trash(
+    pool: &Database,
+    deleted_before: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<(Uuid, String)>> {
+    const SQL: &str = "SELECT user_id, bookmark_id FROM bookmark \
+                       WHERE deleted_at < $1 ORDER BY deleted_at LIMIT $2;";
+    let pool = dispatch!(pool, |db| sqlite::bookmark::get_expired_trash(
+        db,
+        deleted_before,
+        limit
+    ));
+    let client = pool.get().await?;
+    client
+        .query(SQL, &[&deleted_before, &limit])
+        .await?
+        .iter()
+        .map(|row| Ok((row.try_get(0)?, row.try_get(1)?)))
+        .collect()
+}
+
+/// Removes a bookmark from the trash for good, its chunks, entities and
+/// versions with it. Restored bookmarks are left alone.
+pub async fn purge(pool: &Database, user_id: Uuid, bookmark_id: &str) -> Result<bool> {
+    const SQL: &str =
+        "DELETE FROM bookmark WHERE bookmark_id = $1 AND user_id = $2 AND deleted_at IS NOT NULL";
+    let pool = dispatch!(pool, |db| sqlite::bookmark::purge(db, user_id, bookmark_id));
+    let client = pool.get().await?;
+    let rows_affected = client.execute(SQL, &[&bookmark_id, &user_id]).await?;
+    if rows_affected > 0 {
+        info!(bookmark_id = %bookmark_id, user_id = %user_id, "Bookmark purged from trash");
+    }
+    Ok(rows_affected > 0)
+}
+
+/// Removes a bookmark for good, whether it is in the trash or not.
+pub async fn delete(pool: &Database, user_id: Uuid, bookmark_id: &str) -> Result<bool> {
     const SQL: &str = "DELETE FROM bookmark WHERE bookmark_id = $1 AND user_id = $2";
     debug!(bookmark_id = %bookmark_id, user_id = %user_id, "Deleting bookmark");
+    let pool = dispatch!(pool, |db| sqlite::bookmark::delete(
+        db,
+        user_id,
+        bookmark_id
+    ));
     let client = pool.get().await?;
     let rows_affected = client.execute(SQL, &[&bookmark_id, &user_id]).await?;
     if rows_affected > 0 {
@@ -1,19 +1,20 @@
 use chrono::{DateTime, Duration, Utc};
-use deadpool_postgres::GenericClient;
 use postgres_from_row::FromRow;
 use postgres_types::{FromSql, ToSql};
 use serde::{Deserialize, Serialize};
 use shared::{
     BookmarkTask, BookmarkTaskSearchRequest, BookmarkTaskSearchResponse, BookmarkTaskStatus,
+    BulkRetryTasksRequest, DeadLetterGroup, DeadLetterResponse, TaskStatusCount,
 };
 use tracing::{debug, info};
 use url::Url;
 use uuid::Uuid;
 
-use super::PgPool;
+use super::{dispatch, sqlite, Database};
 use crate::error::{self, Error, Result};
 
-const NEXT_DELIVERY_WINDOW: Duration = Duration::minutes(5);
+/// Most tasks leased by an instance at once.
+pub const LEASE_BATCH_SIZE: i64 = 10;
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromSql, ToSql)]
 #[postgres(name = "task_status", rename_all = "snake_case")]
@@ -21,6 +22,7 @@ enum ColumnBookmarkTaskStatus {
     Pending,
     Done,
     Fail,
+    Cancelled,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -35,6 +37,8 @@ struct RowBookmarkTask {
     pub next_delivery: DateTime<Utc>,
     pub retries: Option<i16>,
     pub fail_reason: Option<String>,
+    pub ignore_robots: bool,
+    pub refresh: bool,
 }
 
 impl From<ColumnBookmarkTaskStatus> for BookmarkTaskStatus {
@@ -43,6 +47,7 @@ impl From<ColumnBookmarkTaskStatus> for BookmarkTaskStatus {
             ColumnBookmarkTaskStatus::Pending => BookmarkTaskStatus::Pending,
             ColumnBookmarkTaskStatus::Done => BookmarkTaskStatus::Done,
             ColumnBookmarkTaskStatus::Fail => BookmarkTaskStatus::Fail,
+            ColumnBookmarkTaskStatus::Cancelled => BookmarkTaskStatus::Cancelled,
         }
     }
 }
@@ -53,6 +58,7 @@ impl From<BookmarkTaskStatus> for ColumnBookmarkTaskStatus {
             BookmarkTaskStatus::Pending => ColumnBookmarkTaskStatus::Pending,
             BookmarkTaskStatus::Done => ColumnBookmarkTaskStatus::Done,
             BookmarkTaskStatus::Fail => ColumnBookmarkTaskStatus::Fail,
+            BookmarkTaskStatus::Cancelled => ColumnBookmarkTaskStatus::Cancelled,
         }
     }
 }
@@ -70,18 +76,65 @@ impl From<RowBookmarkTask> for BookmarkTask {
             next_delivery: value.next_delivery,
             retries: value.retries,
             fail_reason: value.fail_reason,
+            ignore_robots: value.ignore_robots,
+            refresh: value.refresh,
         }
     }
 }
 
+/// Queues capturing the page of an existing bookmark again.
+pub async fn create_refresh(pool: &Database, user_id: Uuid, url: Url) -> Result<BookmarkTask> {
+    const SQL: &str = r#"INSERT INTO "bookmark_task" (user_id, url, status, refresh)
+    VALUES ($1, $2, $3, TRUE) RETURNING "bookmark_task".*;"#;
+    let pool = dispatch!(pool, |db| sqlite::bookmark_task::create_refresh(
+        db, user_id, url
+    ));
+    let client = pool.get().await?;
+    let row = client
+        .query_one(
+            SQL,
+            &[
+                &user_id,
+                &url.to_string(),
+                &ColumnBookmarkTaskStatus::Pending,
+            ],
+        )
+        .await?;
+    let task = RowBookmarkTask::try_from_row(&row)
+        .map(BookmarkTask::from)
+        .map_err(anyhow::Error::from)?;
+    info!(
+        task_id = %task.task_id,
+        user_id = %task.user_id,
+        url = %task.url,
+        "Bookmark refresh task created"
+    );
+    Ok(task)
+}
+
 pub async fn create(
-    pool: &PgPool,
+    pool: &Database,
+    user_id: Uuid,
+    url: Url,
+    tags: Vec<String>,
+) -> Result<BookmarkTask> {
+    create_with_robots_override(pool, user_id, url, tags, false).await
+}
+
+/// Same as [`create`], `ignore_robots` skips the robots.txt and noindex checks
+/// for this task.
+pub async fn create_with_robots_override(
+    pool: &Database,
     user_id: Uuid,
     url: Url,
     tags: Vec<String>,
+    ignore_robots: bool,
 ) -> Result<BookmarkTask> {
-    const SQL: &str = r#"INSERT INTO "bookmark_task" (user_id, url, status, tags)
-    VALUES ($1, $2, $3, $4) RETURNING "bookmark_task".*;"#;
+    const SQL: &str = r#"INSERT INTO "bookmark_task" (user_id, url, status, tags, ignore_robots)
+    VALUES ($1, $2, $3, $4, $5) RETURNING "bookmark_task".*;"#;
+    let pool = dispatch!(pool, |db| {
+        sqlite::bookmark_task::create_with_robots_override(db, user_id, url, tags, ignore_robots)
+    });
     let client = pool.get().await?;
     let row = client
         .query_one(
@@ -91,6 +144,7 @@ pub async fn create(
                 &url.to_string(),
                 &ColumnBookmarkTaskStatus::Pending,
                 &tags,
+                &ignore_robots,
             ],
         )
         .await?;
@@ -107,16 +161,84 @@ pub async fn create(
     Ok(task)
 }
 
-pub async fn peek(pool: &PgPool, now: DateTime<Utc>) -> Result<Vec<BookmarkTask>> {
-    const QUERY: &str = r#"SELECT * FROM bookmark_task WHERE next_delivery <= $1
-    AND status = 'pending' FOR UPDATE SKIP LOCKED LIMIT 10;"#;
-
-    debug!(next_delivery = %now, "Peeking for pending tasks");
+/// Queues a task for each URL in one transaction, all or none of them.
+pub async fn create_batch(
+    pool: &Database,
+    user_id: Uuid,
+    urls: Vec<Url>,
+    tags: Vec<String>,
+    ignore_robots: bool,
+) -> Result<Vec<BookmarkTask>> {
+    const SQL: &str = r#"INSERT INTO "bookmark_task" (user_id, url, status, tags, ignore_robots)
+    VALUES ($1, $2, $3, $4, $5) RETURNING "bookmark_task".*;"#;
+    let pool = dispatch!(pool, |db| sqlite::bookmark_task::create_batch(
+        db,
+        user_id,
+        urls,
+        tags,
+        ignore_robots
+    ));
     let mut client = pool.get().await?;
     let tx = client.transaction().await?;
+    let statement = tx.prepare(SQL).await?;
+    let mut tasks = Vec::with_capacity(urls.len());
+    for url in urls {
+        let row = tx
+            .query_one(
+                &statement,
+                &[
+                    &user_id,
+                    &url.to_string(),
+                    &ColumnBookmarkTaskStatus::Pending,
+                    &tags,
+                    &ignore_robots,
+                ],
+            )
+            .await?;
+        tasks.push(
+            RowBookmarkTask::try_from_row(&row)
+                .map(BookmarkTask::from)
+                .map_err(anyhow::Error::from)?,
+        );
+    }
+    tx.commit().await?;
+    info!(%user_id, task_count = tasks.len(), "Bookmark tasks created");
+    Ok(tasks)
+}
+
+/// Leases up to [`LEASE_BATCH_SIZE`] pending tasks due at `now` to `owner`
+/// until `now + duration`. Tasks leased by another instance are skipped until
+/// their lease expires, so replicas sharing the database split the queue.
+pub async fn lease(
+    pool: &Database,
+    owner: &str,
+    now: DateTime<Utc>,
+    duration: Duration,
+) -> Result<Vec<BookmarkTask>> {
+    const SQL: &str = r#"UPDATE bookmark_task
+    SET lease_owner = $2, lease_expires_at = $3
+    WHERE task_id IN (
+        SELECT task_id FROM bookmark_task
+        WHERE status = 'pending' AND next_delivery <= $1
+          AND (lease_expires_at IS NULL OR lease_expires_at <= $1)
+        ORDER BY next_delivery
+        LIMIT $4
+        FOR UPDATE SKIP LOCKED
+    )
+    RETURNING *;"#;
 
-    let tasks = tx
-        .query(QUERY, &[&now])
+    debug!(next_delivery = %now, owner, "Leasing pending tasks");
+    let expires_at = now + duration;
+    let pool = dispatch!(pool, |db| sqlite::bookmark_task::lease(
+        db,
+        owner,
+        now,
+        expires_at,
+        LEASE_BATCH_SIZE
+    ));
+    let client = pool.get().await?;
+    let tasks = client
+        .query(SQL, &[&now, &owner, &expires_at, &LEASE_BATCH_SIZE])
         .await?
         .iter()
         .map(|row| {
@@ -126,39 +248,73 @@ pub async fn peek(pool: &PgPool, now: DateTime<Utc>) -> Result<Vec<BookmarkTask>
         })
         .collect::<Result<Vec<_>>>()?;
 
-    const UPDATE: &str = "UPDATE bookmark_task SET next_delivery = $1 WHERE task_id = ANY ($2);";
-    let next_delivery = now + NEXT_DELIVERY_WINDOW;
-    let ids: Vec<Uuid> = tasks.iter().map(|t| t.task_id).collect();
-    let rows_affected = tx.execute(UPDATE, &[&next_delivery, &ids]).await?;
-    tx.commit().await?;
-
     if !tasks.is_empty() {
         info!(
             task_count = %tasks.len(),
-            next_delivery = %next_delivery,
-            rows_affected = %rows_affected,
-            "Found pending tasks, rescheduled"
+            owner,
+            lease_expires_at = %expires_at,
+            "Found pending tasks, leased"
         );
-        debug!(task_ids = ?ids, "Task IDs processed");
+        debug!(task_ids = ?tasks.iter().map(|t| t.task_id).collect::<Vec<_>>(), "Task IDs leased");
     } else {
         debug!("No pending tasks found");
     }
     Ok(tasks)
 }
 
+/// Extends the leases `owner` still holds on `task_ids` until `expires_at`,
+/// returning how many it still holds.
+pub async fn renew_leases(
+    pool: &Database,
+    owner: &str,
+    task_ids: &[Uuid],
+    expires_at: DateTime<Utc>,
+) -> Result<u64> {
+    const SQL: &str = r#"UPDATE bookmark_task SET lease_expires_at = $1
+    WHERE task_id = ANY ($2) AND lease_owner = $3 AND status = 'pending'"#;
+    let pool = dispatch!(pool, |db| sqlite::bookmark_task::renew_leases(
+        db, owner, task_ids, expires_at
+    ));
+    let client = pool.get().await?;
+    let renewed = client
+        .execute(SQL, &[&expires_at, &task_ids, &owner])
+        .await?;
+    debug!(owner, renewed, lease_expires_at = %expires_at, "Task leases renewed");
+    Ok(renewed)
+}
+
+/// Records the outcome of a task and releases its lease. False when `owner`
+/// no longer holds the lease of the pending task, and nothing changed: the
+/// task was cancelled meanwhile, or another instance took it over once the
+/// lease expired, and may have finished it already.
 pub async fn update(
-    pool: &PgPool,
+    pool: &Database,
+    owner: &str,
     task: BookmarkTask,
     status: BookmarkTaskStatus,
     retries: Option<i16>,
     fail_reason: Option<String>,
-) -> Result<()> {
-    const SQL: &str =
-        "UPDATE bookmark_task SET status = $1, retries = $2, fail_reason = $3 WHERE task_id = $4";
+) -> Result<bool> {
+    // A task cancelled while the daemon was processing it stays cancelled
+    const SQL: &str = r#"UPDATE bookmark_task
+    SET status = $1, retries = $2, fail_reason = $3, updated_at = now(),
+        lease_owner = NULL, lease_expires_at = NULL
+    WHERE task_id = $4 AND status = 'pending' AND lease_owner = $5"#;
+    let pool = dispatch!(pool, |db| sqlite::bookmark_task::update(
+        db,
+        owner,
+        task.task_id,
+        status,
+        retries,
+        fail_reason,
+    ));
     let client = pool.get().await?;
     let status: ColumnBookmarkTaskStatus = status.into();
     let row_count = client
-        .execute(SQL, &[&status, &retries, &fail_reason, &task.task_id])
+        .execute(
+            SQL,
+            &[&status, &retries, &fail_reason, &task.task_id, &owner],
+        )
         .await?;
     info!(
         task_id = %task.task_id,
@@ -170,11 +326,253 @@ pub async fn update(
     if let Some(reason) = &fail_reason {
         debug!(task_id = %task.task_id, reason = %reason, "Task failure reason");
     }
+    Ok(row_count > 0)
+}
+
+/// Keeps a task pending after a failed attempt, delays its next delivery and
+/// releases its lease. False when `owner` lost the lease meanwhile.
+pub async fn schedule_retry(
+    pool: &Database,
+    owner: &str,
+    task: &BookmarkTask,
+    retries: i16,
+    next_delivery: DateTime<Utc>,
+) -> Result<bool> {
+    const SQL: &str = r#"UPDATE bookmark_task
+    SET retries = $1, next_delivery = $2, updated_at = now(),
+        lease_owner = NULL, lease_expires_at = NULL
+    WHERE task_id = $3 AND status = 'pending' AND lease_owner = $4"#;
+    let pool = dispatch!(pool, |db| sqlite::bookmark_task::schedule_retry(
+        db,
+        owner,
+        task.task_id,
+        retries,
+        next_delivery,
+    ));
+    let client = pool.get().await?;
+    let row_count = client
+        .execute(SQL, &[&retries, &next_delivery, &task.task_id, &owner])
+        .await?;
+    info!(
+        task_id = %task.task_id,
+        retries = %retries,
+        next_delivery = %next_delivery,
+        rows_affected = %row_count,
+        "Task retry scheduled"
+    );
+    Ok(row_count > 0)
+}
+
+/// Links the task to the bookmark it produced, the AI daemons add their
+/// stages to the task timeline through it.
+pub async fn set_bookmark_id(pool: &Database, task_id: Uuid, bookmark_id: &str) -> Result<()> {
+    const SQL: &str = "UPDATE bookmark_task SET bookmark_id = $1 WHERE task_id = $2;";
+    let pool = dispatch!(pool, |db| sqlite::bookmark_task::set_bookmark_id(
+        db,
+        task_id,
+        bookmark_id
+    ));
+    let client = pool.get().await?;
+    client.execute(SQL, &[&bookmark_id, &task_id]).await?;
     Ok(())
 }
 
+pub async fn get_by_id(
+    pool: &Database,
+    user_id: Uuid,
+    task_id: Uuid,
+) -> Result<Option<BookmarkTask>> {
+    const SQL: &str = "SELECT * FROM bookmark_task WHERE task_id = $1 AND user_id = $2;";
+    let pool = dispatch!(pool, |db| sqlite::bookmark_task::get_by_id(
+        db, user_id, task_id
+    ));
+    let client = pool.get().await?;
+    let row = client.query_opt(SQL, &[&task_id, &user_id]).await?;
+    row.map(|row| {
+        RowBookmarkTask::try_from_row(&row)
+            .map(BookmarkTask::from)
+            .map_err(Error::from)
+    })
+    .transpose()
+}
+
+/// Moves a failed task back to pending with its retry state cleared, `None`
+/// when the task doesn't exist or isn't failed.
+pub async fn retry(pool: &Database, user_id: Uuid, task_id: Uuid) -> Result<Option<BookmarkTask>> {
+    const SQL: &str = r#"UPDATE bookmark_task
+    SET status = 'pending', retries = NULL, fail_reason = NULL,
+        next_delivery = now(), updated_at = now(),
+        lease_owner = NULL, lease_expires_at = NULL
+    WHERE task_id = $1 AND user_id = $2 AND status = 'fail'
+    RETURNING *;"#;
+    let pool = dispatch!(pool, |db| sqlite::bookmark_task::retry(
+        db, user_id, task_id
+    ));
+    let client = pool.get().await?;
+    let row = client.query_opt(SQL, &[&task_id, &user_id]).await?;
+    let task = row
+        .map(|row| {
+            RowBookmarkTask::try_from_row(&row)
+                .map(BookmarkTask::from)
+                .map_err(Error::from)
+        })
+        .transpose()?;
+    info!(task_id = %task_id, requeued = task.is_some(), "Task retry requested");
+    Ok(task)
+}
+
+/// Cancels a pending task, `None` when the task doesn't exist or isn't
+/// pending.
+pub async fn cancel(pool: &Database, user_id: Uuid, task_id: Uuid) -> Result<Option<BookmarkTask>> {
+    const SQL: &str = r#"UPDATE bookmark_task
+    SET status = 'cancelled', updated_at = now(), lease_owner = NULL, lease_expires_at = NULL
+    WHERE task_id = $1 AND user_id = $2 AND status = 'pending'
+    RETURNING *;"#;
+    let pool = dispatch!(pool, |db| sqlite::bookmark_task::cancel(
+        db, user_id, task_id
+    ));
+    let client = pool.get().await?;
+    let row = client.query_opt(SQL, &[&task_id, &user_id]).await?;
+    let task = row
+        .map(|row| {
+            RowBookmarkTask::try_from_row(&row)
+                .map(BookmarkTask::from)
+                .map_err(Error::from)
+        })
+        .transpose()?;
+    info!(task_id = %task_id, cancelled = task.is_some(), "Task cancel requested");
+    Ok(task)
+}
+
+/// Deletes up to `limit` done, failed or cancelled tasks last updated before
+/// `updated_before`, their timelines with them. Returns how many went.
+pub async fn delete_finished(
+    pool: &Database,
+    updated_before: DateTime<Utc>,
+    limit: i64,
+) -> Result<u64> {
+    const SQL: &str = r#"
+        DELETE FROM bookmark_task WHERE task_id IN (
+            SELECT task_id FROM bookmark_task
+            WHERE status IN ('done', 'fail', 'cancelled') AND updated_at < $1
+            LIMIT $2
+        );"#;
+    let pool = dispatch!(pool, |db| sqlite::bookmark_task::delete_finished(
+        db,
+        updated_before,
+        limit
+    ));
+    let client = pool.get().await?;
+    Ok(client.execute(SQL, &[&updated_before, &limit]).await?)
+}
+
+/// Failed tasks of `user_id` grouped by `fail_reason`, largest groups first.
+pub async fn dead_letter(pool: &Database, user_id: Uuid) -> Result<DeadLetterResponse> {
+    const SQL: &str = r#"SELECT coalesce(fail_reason, '') AS fail_reason,
+        COUNT(*) AS task_count,
+        MIN(updated_at) AS first_failed_at,
+        MAX(updated_at) AS last_failed_at
+    FROM bookmark_task
+    WHERE user_id = $1 AND status = 'fail'
+    GROUP BY 1
+    ORDER BY task_count DESC, last_failed_at DESC;"#;
+    let pool = dispatch!(pool, |db| sqlite::bookmark_task::dead_letter(db, user_id));
+    let client = pool.get().await?;
+    let groups: Vec<DeadLetterGroup> = client
+        .query(SQL, &[&user_id])
+        .await?
+        .iter()
+        .map(|row| DeadLetterGroup {
+            fail_reason: row.get("fail_reason"),
+            task_count: row.get("task_count"),
+            first_failed_at: row.get("first_failed_at"),
+            last_failed_at: row.get("last_failed_at"),
+        })
+        .collect();
+    let total_count = groups.iter().map(|group| group.task_count).sum();
+    debug!(
+        user_id = %user_id,
+        group_count = groups.len(),
+        total_count = %total_count,
+        "Dead letter tasks aggregated"
+    );
+    Ok(DeadLetterResponse {
+        groups,
+        total_count,
+    })
+}
+
+/// Requeues every failed task of `user_id` matching `request`, same reset as
+/// [`retry`].
+pub async fn retry_matching(
+    pool: &Database,
+    user_id: Uuid,
+    request: &BulkRetryTasksRequest,
+) -> Result<Vec<BookmarkTask>> {
+    let mut filters: Vec<String> = vec!["user_id = $1".into(), "status = 'fail'".into()];
+    let mut params: Vec<&(dyn ToSql + Sync)> = vec![&user_id];
+
+    if let Some(fail_reason) = &request.fail_reason {
+        params.push(fail_reason);
+        filters.push(format!("coalesce(fail_reason, '') = ${}", params.len()));
+    }
+
+    let url_pattern = request.url.as_ref().map(|e| format!("%{e}%"));
+    if let Some(url_pattern) = &url_pattern {
+        params.push(url_pattern);
+        filters.push(format!("url LIKE ${}", params.len()));
+    }
+
+    if let Some(tags) = &request.tags {
+        if !tags.is_empty() {
+            params.push(tags);
+            filters.push(format!("tags @> ${}", params.len()));
+        }
+    }
+
+    if let Some(from_created_at) = &request.from_created_at {
+        params.push(from_created_at);
+        filters.push(format!("created_at >= ${}", params.len()));
+    }
+
+    if let Some(to_created_at) = &request.to_created_at {
+        params.push(to_created_at);
+        filters.push(format!("created_at <= ${}", params.len()));
+    }
+
+    let sql = format!(
+        r#"UPDATE bookmark_task
+        SET status = 'pending', retries = NULL, fail_reason = NULL,
+            next_delivery = now(), updated_at = now(),
+            lease_owner = NULL, lease_expires_at = NULL
+        WHERE {}
+        RETURNING *;"#,
+        filters.join(" AND ")
+    );
+    let pool = dispatch!(pool, |db| sqlite::bookmark_task::retry_matching(
+        db, user_id, request
+    ));
+    let client = pool.get().await?;
+    let tasks = client
+        .query(&sql, &params)
+        .await?
+        .iter()
+        .map(|row| {
+            RowBookmarkTask::try_from_row(row)
+                .map(BookmarkTask::from)
+                .map_err(Error::from)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    info!(
+        user_id = %user_id,
+        requeued = tasks.len(),
+        "Failed tasks requeued in bulk"
+    );
+    Ok(tasks)
+}
+
 pub async fn search(
-    pool: &PgPool,
+    pool: &Database,
     user_id: Uuid,
     request: &BookmarkTaskSearchRequest,
 ) -> Result<BookmarkTaskSearchResponse> {
@@ -198,6 +596,24 @@ pub async fn search(
         }
     }
 
+    if let Some(to_created_at) = &request.to_created_at {
+        params.push(to_created_at);
+        filters.push(format!("bt.created_at <= ${}", params.len()));
+    }
+
+    if let Some(from_created_at) = &request.from_created_at {
+        params.push(from_created_at);
+        filters.push(format!("bt.created_at >= ${}", params.len()));
+    }
+
+    // Counted per status across all pages, so without the status and page
+    let count_sql = format!(
+        "SELECT bt.status, count(1) AS task_count FROM bookmark_task bt WHERE {} \
+         GROUP BY bt.status",
+        filters.join(" AND ")
+    );
+    let count_params = params.clone();
+
     let bookmark_task_status = request.status.clone().map(ColumnBookmarkTaskStatus::from);
     if let Some(status) = &bookmark_task_status {
         params.push(status);
@@ -210,16 +626,6 @@ pub async fn search(
         filters.push(format!("bt.task_id > ${}", params.len()));
     }
 
-    if let Some(to_created_at) = &request.to_created_at {
-        params.push(to_created_at);
-        filters.push(format!("bt.created_at <= ${}", params.len()));
-    }
-
-    if let Some(from_created_at) = &request.from_created_at {
-        params.push(from_created_at);
-        filters.push(format!("bt.created_at >= ${}", params.len()));
-    }
-
     let filter_clause = if !filters.is_empty() {
         format!("WHERE {}", filters.join(" AND "))
     } else {
@@ -234,6 +640,9 @@ pub async fn search(
         page_size + 1
     );
 
+    let pool = dispatch!(pool, |db| sqlite::bookmark_task::search(
+        db, user_id, request
+    ));
     let client = pool.get().await?;
     let rows = client.query(&sql, &params).await?;
 
@@ -260,9 +669,56 @@ pub async fn search(
         "Task search completed"
     );
 
-    Ok(BookmarkTaskSearchResponse {
+    let status_counts = if request.include_total {
+        client
+            .query(&count_sql, &count_params)
+            .await?
+            .iter()
+            .map(|row| {
+                let count: i64 = row.get("task_count");
+                TaskStatusCount {
+                    status: row.get::<_, ColumnBookmarkTaskStatus>("status").into(),
+                    count: count as usize,
+                }
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    Ok(search_response(request, tasks, has_more, status_counts))
+}
+
+/// Response of a search, with its total out of the counts per status when
+/// the request asked for them.
+pub(in crate::db) fn search_response(
+    request: &BookmarkTaskSearchRequest,
+    tasks: Vec<BookmarkTask>,
+    has_more: bool,
+    mut status_counts: Vec<TaskStatusCount>,
+) -> BookmarkTaskSearchResponse {
+    const ORDER: [BookmarkTaskStatus; 4] = [
+        BookmarkTaskStatus::Pending,
+        BookmarkTaskStatus::Fail,
+        BookmarkTaskStatus::Done,
+        BookmarkTaskStatus::Cancelled,
+    ];
+    status_counts.sort_by_key(|counted| ORDER.iter().position(|status| *status == counted.status));
+    let total_count = request.include_total.then(|| {
+        status_counts
+            .iter()
+            .filter(|counted| {
+                request
+                    .status
+                    .as_ref()
+                    .is_none_or(|status| *status == counted.status)
+            })
+            .map(|counted| counted.count)
+            .sum()
+    });
+    BookmarkTaskSearchResponse {
         tasks,
         has_more,
-        total_count: None, // Could add a COUNT query if needed
-    })
+        total_count,
+        status_counts,
+    }
 }
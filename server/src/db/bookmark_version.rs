@@ -0,0 +1,66 @@
+use shared::{BookmarkVersion, BookmarkVersionContent};
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+use super::{dispatch, sqlite, Database};
+use crate::error::Result;
+
+fn from_row(row: &Row) -> BookmarkVersion {
+    BookmarkVersion {
+        version_id: row.get("version_id"),
+        title: row.get("title"),
+        word_count: row.get("word_count"),
+        captured_at: row.get("captured_at"),
+    }
+}
+
+/// Earlier captures of the bookmark, the most recent first.
+pub async fn list(
+    pool: &Database,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<Vec<BookmarkVersion>> {
+    let pool = dispatch!(pool, |db| sqlite::bookmark_version::list(
+        db,
+        user_id,
+        bookmark_id
+    ));
+    let client = pool.get().await?;
+    let rows = client
+        .query(
+            "SELECT version_id, title, word_count, captured_at FROM bookmark_version
+             WHERE bookmark_id = $1 AND user_id = $2
+             ORDER BY captured_at DESC",
+            &[&bookmark_id, &user_id],
+        )
+        .await?;
+    Ok(rows.iter().map(from_row).collect())
+}
+
+pub async fn get(
+    pool: &Database,
+    user_id: Uuid,
+    bookmark_id: &str,
+    version_id: Uuid,
+) -> Result<Option<BookmarkVersionContent>> {
+    let pool = dispatch!(pool, |db| sqlite::bookmark_version::get(
+        db,
+        user_id,
+        bookmark_id,
+        version_id
+    ));
+    let client = pool.get().await?;
+    let row = client
+        .query_opt(
+            "SELECT version_id, title, word_count, captured_at, text_content, html
+             FROM bookmark_version
+             WHERE version_id = $1 AND bookmark_id = $2 AND user_id = $3",
+            &[&version_id, &bookmark_id, &user_id],
+        )
+        .await?;
+    Ok(row.map(|row| BookmarkVersionContent {
+        version: from_row(&row),
+        text_content: row.get("text_content"),
+        html: row.get("html"),
+    }))
+}
@@ -1,23 +1,89 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
-use deadpool_postgres::GenericClient;
 use pgvector::Vector;
 use postgres_from_row::FromRow;
-use shared::{Bookmark, BookmarkChunk, RagChunkMatch};
+use postgres_types::ToSql;
+use shared::{
+    Bookmark, BookmarkChunk, ChunkDetail, ChunkEmbedding, RagChunkMatch, RelatedBookmark, TagFilter,
+};
 use tracing::debug;
 use uuid::Uuid;
 
-use super::PgPool;
+use super::{bookmark, dispatch, search, sqlite, Database, EmbeddingProfile, PgTransaction};
 use crate::error::{Error, Result};
 
-/// Result from hybrid search combining vector and FTS scores
+/// Result from hybrid search combining vector and FTS scores. A chunk found by
+/// only one of the two searches has no rank (and a zero score) in the other.
 #[derive(Debug, Clone)]
 pub struct HybridChunkMatch {
     pub chunk: BookmarkChunk,
     pub bookmark: Bookmark,
     pub vector_score: f64,
-    pub vector_rank: usize,
+    pub vector_rank: Option<usize>,
     pub fts_score: f64,
-    pub fts_rank: usize,
+    pub fts_rank: Option<usize>,
+}
+
+/// Restricts a chunk search to a subset of the user's bookmarks. The default
+/// scope covers the whole library.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChunkScope<'a> {
+    pub tags_filter: Option<&'a TagFilter>,
+    pub bookmark_ids: Option<&'a Vec<String>>,
+    /// Chunks of the bookmarks saved into this workspace rather than of the
+    /// user's own, none unless the user is one of its members
+    pub workspace_id: Option<&'a Uuid>,
+}
+
+impl<'a> ChunkScope<'a> {
+    /// Whose chunks the alias `c` covers, the user being `$1`.
+    fn owner_condition<'p>(&self, params: &mut Vec<&'p (dyn ToSql + Sync)>) -> String
+    where
+        'a: 'p,
+    {
+        match self.workspace_id {
+            Some(workspace_id) => {
+                params.push(workspace_id);
+                format!(
+                    "EXISTS (SELECT 1 FROM workspace_bookmark wb \
+                 JOIN workspace_member wm ON wm.workspace_id = wb.workspace_id AND wm.user_id = $1 \
+                 JOIN bookmark vb ON vb.bookmark_id = wb.bookmark_id AND vb.user_id = wb.user_id \
+                 WHERE wb.workspace_id = ${} AND wb.bookmark_id = c.bookmark_id \
+                 AND vb.visibility <> 'private' \
+                 AND wb.user_id = c.user_id)",
+                    params.len()
+                )
+            }
+            None => "c.user_id = $1".to_string(),
+        }
+    }
+
+    /// Appends conditions on the bookmark alias `b` as ` AND ...` clauses.
+    fn push_filters<'p>(&self, clause: &mut String, params: &mut Vec<&'p (dyn ToSql + Sync)>)
+    where
+        'a: 'p,
+    {
+        if let Some(bookmark_ids) = self.bookmark_ids {
+            params.push(bookmark_ids);
+            clause.push_str(&format!(" AND b.bookmark_id = ANY(${})", params.len()));
+        }
+        match self.tags_filter {
+            Some(TagFilter::And(tags)) => {
+                params.push(tags);
+                clause.push_str(&format!(" AND b.tags @> ${}", params.len()));
+            }
+            Some(TagFilter::Or(tags)) => {
+                params.push(tags);
+                clause.push_str(&format!(" AND b.tags && ${}", params.len()));
+            }
+            Some(TagFilter::Untagged) => {
+                clause
+                    .push_str(" AND (b.tags IS NULL OR coalesce(array_length(b.tags, 1), 0) = 0)");
+            }
+            Some(TagFilter::Any) | None => {}
+        }
+    }
 }
 
 #[derive(Debug, FromRow)]
@@ -46,7 +112,7 @@ impl From<RowBookmarkChunk> for BookmarkChunk {
 }
 
 pub async fn store_chunks_with_embeddings(
-    pool: &PgPool,
+    pool: &Database,
     bookmark_id: &str,
     user_id: Uuid,
     chunks: Vec<String>,
@@ -70,6 +136,13 @@ pub async fn store_chunks_with_embeddings(
         )]));
     }
 
+    let pool = dispatch!(pool, |db| sqlite::chunks::store_chunks_with_embeddings(
+        db,
+        bookmark_id,
+        user_id,
+        chunks,
+        embeddings,
+    ));
     let client = pool.get().await?;
 
     // Delete existing chunks for this bookmark
@@ -105,6 +178,20 @@ pub async fn store_chunks_with_embeddings(
         stored_chunks.push(BookmarkChunk::from(chunk_row));
     }
 
+    client
+        .execute(
+            r#"
+            UPDATE bookmark
+            SET embedding = (
+                SELECT AVG(embedding) FROM bookmark_chunk
+                WHERE bookmark_id = $1 AND user_id = $2
+            )
+            WHERE bookmark_id = $1 AND user_id = $2
+            "#,
+            &[&bookmark_id, &user_id],
+        )
+        .await?;
+
     debug!(
         bookmark_id,
         user_id = %user_id,
@@ -115,43 +202,156 @@ pub async fn store_chunks_with_embeddings(
     Ok(stored_chunks)
 }
 
+/// Embeddings of `profile` the user has cached for the given content hashes,
+/// by hash. Hashes never embedded with this profile are missing from the
+/// map, the ones found count as used now.
+pub async fn get_cached_embeddings(
+    pool: &Database,
+    user_id: Uuid,
+    profile: &EmbeddingProfile,
+    content_hashes: &[String],
+) -> Result<HashMap<String, Vec<f32>>> {
+    let pool = dispatch!(pool, |db| sqlite::chunks::get_cached_embeddings(
+        db,
+        user_id,
+        profile,
+        content_hashes
+    ));
+    let client = pool.get().await?;
+    let rows = client
+        .query(
+            "UPDATE embedding_cache SET last_used_at = now()
+             WHERE user_id = $1 AND provider = $2 AND model = $3 AND dimensions = $4
+               AND content_hash = ANY($5)
+             RETURNING content_hash, embedding",
+            &[
+                &user_id,
+                &profile.provider,
+                &profile.model,
+                &(profile.dimensions as i32),
+                &content_hashes,
+            ],
+        )
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let embedding: Vector = row.get("embedding");
+            (row.get("content_hash"), embedding.to_vec())
+        })
+        .collect())
+}
+
+/// Caches embeddings of `profile` for the user by content hash, entries
+/// already cached are kept.
+pub async fn cache_embeddings(
+    pool: &Database,
+    user_id: Uuid,
+    profile: &EmbeddingProfile,
+    entries: Vec<(String, Vec<f32>)>,
+) -> Result<()> {
+    let pool = dispatch!(pool, |db| sqlite::chunks::cache_embeddings(
+        db, user_id, profile, entries
+    ));
+    let mut client = pool.get().await?;
+    let tx = client.transaction().await?;
+    let insert = tx
+        .prepare(
+            "INSERT INTO embedding_cache
+                (user_id, provider, model, dimensions, content_hash, embedding)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             ON CONFLICT DO NOTHING",
+        )
+        .await?;
+    for (content_hash, embedding) in entries {
+        tx.execute(
+            &insert,
+            &[
+                &user_id,
+                &profile.provider,
+                &profile.model,
+                &(profile.dimensions as i32),
+                &content_hash,
+                &Vector::from(embedding),
+            ],
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Deletes up to `limit` cached embeddings of any user last used before
+/// `used_before`. Returns how many went.
+pub async fn delete_cached_embeddings_before(
+    pool: &Database,
+    used_before: DateTime<Utc>,
+    limit: i64,
+) -> Result<u64> {
+    const SQL: &str = r#"
+        DELETE FROM embedding_cache WHERE ctid IN (
+            SELECT ctid FROM embedding_cache WHERE last_used_at < $1 LIMIT $2
+        );"#;
+    let pool = dispatch!(pool, |db| sqlite::chunks::delete_cached_embeddings_before(
+        db,
+        used_before,
+        limit
+    ));
+    let client = pool.get().await?;
+    Ok(client.execute(SQL, &[&used_before, &limit]).await?)
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn search_similar_chunks(
-    pool: &PgPool,
+    pool: &Database,
     user_id: Uuid,
     query_embedding: Vec<f32>,
     embedding_dimensions: usize,
+    hnsw_ef_search: u32,
     limit: usize,
     similarity_threshold: f64,
+    scope: ChunkScope<'_>,
 ) -> Result<Vec<RagChunkMatch>> {
-    let client = pool.get().await?;
+    let pool = dispatch!(pool, |db| sqlite::chunks::search_similar_chunks(
+        db,
+        user_id,
+        query_embedding,
+        limit,
+        similarity_threshold,
+        scope,
+    ));
+    let mut client = pool.get().await?;
+    let query_embedding = Vector::from(query_embedding);
+    let limit = limit as i64;
+    let mut params: Vec<&(dyn ToSql + Sync)> =
+        vec![&user_id, &query_embedding, &similarity_threshold, &limit];
+    let owner = scope.owner_condition(&mut params);
+    let mut scope_clause = String::new();
+    scope.push_filters(&mut scope_clause, &mut params);
+
     let statement = format!(
         r#"
             SELECT 
                 c.chunk_id, c.bookmark_id, c.user_id, c.chunk_text, 
                 c.chunk_index, c.created_at, c.updated_at,
-                b.url, b.domain, b.title, b.tags, b.summary, 
+                b.url, b.domain, b.title, b.tags, b.summary,
+                b.word_count, b.reading_time_minutes, b.kind, b.favorite, b.original_title,
+                b.visibility,
                 b.created_at as bookmark_created_at, b.updated_at as bookmark_updated_at,
                 1 - ((c.embedding::vector({embedding_dimensions})) <=> $2) as similarity_score
             FROM bookmark_chunk c
             INNER JOIN bookmark b ON c.bookmark_id = b.bookmark_id AND c.user_id = b.user_id
-            WHERE c.user_id = $1 
-            AND 1 - ((c.embedding::vector({embedding_dimensions})) <=> $2) >= $3
+            WHERE {owner} AND b.deleted_at IS NULL
+            AND 1 - ((c.embedding::vector({embedding_dimensions})) <=> $2) >= $3{scope_clause}
             ORDER BY (c.embedding::vector({embedding_dimensions})) <=> $2
             LIMIT $4
         "#
     );
 
-    let rows = client
-        .query(
-            &statement,
-            &[
-                &user_id,
-                &Vector::from(query_embedding),
-                &similarity_threshold,
-                &(limit as i64),
-            ],
-        )
-        .await?;
+    let tx = client.transaction().await?;
+    set_hnsw_ef_search(&tx, hnsw_ef_search).await?;
+    let rows = tx.query(&statement, &params).await?;
+    tx.commit().await?;
 
     let mut matches = Vec::new();
     for row in rows {
@@ -175,6 +375,12 @@ pub async fn search_similar_chunks(
             summary: row.get("summary"),
             created_at: row.get("bookmark_created_at"),
             updated_at: row.get("bookmark_updated_at"),
+            word_count: row.get("word_count"),
+            reading_time_minutes: row.get("reading_time_minutes"),
+            kind: bookmark::parse_kind(row.get("kind")),
+            favorite: row.get("favorite"),
+            original_title: row.get("original_title"),
+            visibility: bookmark::parse_visibility(row.get("visibility")),
         };
 
         matches.push(RagChunkMatch {
@@ -185,6 +391,7 @@ pub async fn search_similar_chunks(
             vector_score: None,          // Only set in hybrid search mode
             fts_score: None,             // Only set in hybrid search mode
             combined_score: None,        // Only set in hybrid search mode
+            rerank_score: None,
         });
     }
 
@@ -198,8 +405,85 @@ pub async fn search_similar_chunks(
     Ok(matches)
 }
 
+/// Bookmarks closest to `bookmark_id` by their averaged chunk embeddings,
+/// most similar first. Empty when the bookmark has not been embedded yet.
+pub async fn find_related_bookmarks(
+    pool: &Database,
+    user_id: Uuid,
+    bookmark_id: &str,
+    limit: usize,
+) -> Result<Vec<RelatedBookmark>> {
+    let pool = dispatch!(pool, |db| sqlite::chunks::find_related_bookmarks(
+        db,
+        user_id,
+        bookmark_id,
+        limit
+    ));
+    let client = pool.get().await?;
+    let limit = limit as i64;
+    let rows = client
+        .query(
+            r#"
+            WITH source AS (
+                SELECT embedding FROM bookmark
+                WHERE bookmark_id = $1 AND user_id = $2 AND embedding IS NOT NULL
+            )
+            SELECT
+                b.bookmark_id, b.user_id, b.url, b.domain, b.title, b.tags, b.summary,
+                b.word_count, b.reading_time_minutes, b.kind, b.favorite, b.original_title,
+                b.visibility,
+                b.created_at, b.updated_at,
+                1 - (b.embedding <=> s.embedding) as similarity_score
+            FROM bookmark b
+            CROSS JOIN source s
+            WHERE b.user_id = $2
+            AND b.bookmark_id <> $1
+            AND b.deleted_at IS NULL
+            AND b.embedding IS NOT NULL
+            AND vector_dims(b.embedding) = vector_dims(s.embedding)
+            ORDER BY b.embedding <=> s.embedding
+            LIMIT $3
+            "#,
+            &[&bookmark_id, &user_id, &limit],
+        )
+        .await?;
+
+    let related: Vec<RelatedBookmark> = rows
+        .iter()
+        .map(|row| RelatedBookmark {
+            bookmark: Bookmark {
+                bookmark_id: row.get("bookmark_id"),
+                user_id: row.get("user_id"),
+                url: row.get("url"),
+                domain: row.get("domain"),
+                title: row.get("title"),
+                tags: row.get("tags"),
+                summary: row.get("summary"),
+                created_at: row.get("created_at"),
+                updated_at: row.get("updated_at"),
+                word_count: row.get("word_count"),
+                reading_time_minutes: row.get("reading_time_minutes"),
+                kind: bookmark::parse_kind(row.get("kind")),
+                favorite: row.get("favorite"),
+                original_title: row.get("original_title"),
+                visibility: bookmark::parse_visibility(row.get("visibility")),
+            },
+            similarity_score: row.get("similarity_score"),
+        })
+        .collect();
+
+    debug!(
+        user_id = %user_id,
+        bookmark_id,
+        related_found = related.len(),
+        "Found related bookmarks"
+    );
+
+    Ok(related)
+}
+
 pub async fn get_chunks_by_ids(
-    pool: &PgPool,
+    pool: &Database,
     user_id: Uuid,
     chunk_ids: &[Uuid],
 ) -> Result<Vec<BookmarkChunk>> {
@@ -207,6 +491,9 @@ pub async fn get_chunks_by_ids(
         return Ok(Vec::new());
     }
 
+    let pool = dispatch!(pool, |db| sqlite::chunks::get_chunks_by_ids(
+        db, user_id, chunk_ids
+    ));
     let client = pool.get().await?;
 
     let rows = client
@@ -234,10 +521,15 @@ pub async fn get_chunks_by_ids(
 }
 
 pub async fn has_chunks_for_bookmark(
-    pool: &PgPool,
+    pool: &Database,
     bookmark_id: &str,
     user_id: Uuid,
 ) -> Result<bool> {
+    let pool = dispatch!(pool, |db| sqlite::chunks::has_chunks_for_bookmark(
+        db,
+        bookmark_id,
+        user_id
+    ));
     let client = pool.get().await?;
 
     let count: i64 = client
@@ -251,8 +543,67 @@ pub async fn has_chunks_for_bookmark(
     Ok(count > 0)
 }
 
+/// Chunks of a bookmark of `user_id` in order, with the embedding each one
+/// holds under the current embedding profile.
+pub async fn chunk_details(
+    pool: &Database,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<Vec<ChunkDetail>> {
+    let pool = dispatch!(pool, |db| sqlite::chunks::chunk_details(
+        db,
+        user_id,
+        bookmark_id
+    ));
+    let client = pool.get().await?;
+
+    let rows = client
+        .query(
+            r#"
+            SELECT c.chunk_id, c.bookmark_id, c.user_id, c.chunk_text,
+                   c.chunk_index, c.created_at, c.updated_at,
+                   vector_dims(c.embedding) AS dimensions, ec.provider, ec.model
+            FROM bookmark_chunk c
+            LEFT JOIN embedding_config ec ON ec.embedding_config_id
+            WHERE c.user_id = $1 AND c.bookmark_id = $2
+            ORDER BY c.chunk_index
+            "#,
+            &[&user_id, &bookmark_id],
+        )
+        .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            let chunk = RowBookmarkChunk::try_from_row(&row).map_err(Error::from)?;
+            let dimensions: Option<i32> = row.get("dimensions");
+            let provider: Option<String> = row.get("provider");
+            let model: Option<String> = row.get("model");
+            Ok(ChunkDetail {
+                chunk: chunk.into(),
+                embedding: chunk_embedding(provider, model, dimensions.map(|d| d as usize)),
+            })
+        })
+        .collect()
+}
+
+/// Embedding of a chunk, known only when both its vector and the profile it
+/// was made with are stored.
+pub(super) fn chunk_embedding(
+    provider: Option<String>,
+    model: Option<String>,
+    dimensions: Option<usize>,
+) -> Option<ChunkEmbedding> {
+    Some(ChunkEmbedding {
+        provider: provider?,
+        model: model?,
+        dimensions: dimensions?,
+    })
+}
+
+/// Chunks of the user's bookmarks or of the ones saved into a workspace the
+/// user is a member of, such as the sources of a workspace answer.
 pub async fn get_chunks_with_bookmarks_by_ids(
-    pool: &PgPool,
+    pool: &Database,
     user_id: Uuid,
     chunk_ids: &[Uuid],
 ) -> Result<Vec<(BookmarkChunk, Bookmark)>> {
@@ -260,6 +611,9 @@ pub async fn get_chunks_with_bookmarks_by_ids(
         return Ok(Vec::new());
     }
 
+    let pool = dispatch!(pool, |db| sqlite::chunks::get_chunks_with_bookmarks_by_ids(
+        db, user_id, chunk_ids
+    ));
     let client = pool.get().await?;
 
     let rows = client
@@ -269,10 +623,18 @@ pub async fn get_chunks_with_bookmarks_by_ids(
                 c.chunk_id, c.bookmark_id, c.user_id, c.chunk_text,
                 c.chunk_index, c.created_at, c.updated_at,
                 b.url, b.domain, b.title, b.tags, b.summary,
+                b.word_count, b.reading_time_minutes, b.kind, b.favorite, b.original_title,
+                b.visibility,
                 b.created_at as bookmark_created_at, b.updated_at as bookmark_updated_at
             FROM bookmark_chunk c
             INNER JOIN bookmark b ON c.bookmark_id = b.bookmark_id AND c.user_id = b.user_id
-            WHERE c.user_id = $1 AND c.chunk_id = ANY($2)
+            WHERE c.chunk_id = ANY($2) AND b.deleted_at IS NULL
+            AND (c.user_id = $1 OR EXISTS (
+                SELECT 1 FROM workspace_bookmark wb
+                JOIN workspace_member wm ON wm.workspace_id = wb.workspace_id AND wm.user_id = $1
+                WHERE wb.bookmark_id = c.bookmark_id AND wb.user_id = c.user_id
+                AND b.visibility <> 'private'
+            ))
             ORDER BY c.chunk_index
             "#,
             &[&user_id, &chunk_ids],
@@ -301,6 +663,12 @@ pub async fn get_chunks_with_bookmarks_by_ids(
             summary: row.get("summary"),
             created_at: row.get("bookmark_created_at"),
             updated_at: row.get("bookmark_updated_at"),
+            word_count: row.get("word_count"),
+            reading_time_minutes: row.get("reading_time_minutes"),
+            kind: bookmark::parse_kind(row.get("kind")),
+            favorite: row.get("favorite"),
+            original_title: row.get("original_title"),
+            visibility: bookmark::parse_visibility(row.get("visibility")),
         };
 
         results.push((chunk, bookmark));
@@ -316,9 +684,12 @@ pub async fn get_chunks_with_bookmarks_by_ids(
 }
 
 pub async fn get_bookmarks_without_chunks(
-    pool: &PgPool,
+    pool: &Database,
     limit: usize,
 ) -> Result<Vec<(String, Uuid, String)>> {
+    let pool = dispatch!(pool, |db| sqlite::chunks::get_bookmarks_without_chunks(
+        db, limit
+    ));
     let client = pool.get().await?;
 
     let rows = client
@@ -328,6 +699,7 @@ pub async fn get_bookmarks_without_chunks(
             FROM bookmark b
             LEFT JOIN bookmark_chunk c ON b.bookmark_id = c.bookmark_id AND b.user_id = c.user_id
             WHERE c.bookmark_id IS NULL
+            AND b.deleted_at IS NULL
             AND LENGTH(b.text_content) >= 200
             ORDER BY RANDOM()
             LIMIT $1
@@ -350,71 +722,120 @@ pub async fn get_bookmarks_without_chunks(
     results
 }
 
-/// Search chunks using hybrid approach: vector similarity + full-text search
-/// Returns chunks with both vector and FTS scores for RRF/weighted combination
+/// Sets how many candidates the HNSW embedding index visits for the rest of
+/// `tx`. Filters on the user, tags or bookmarks apply to these candidates, so
+/// too few of them can leave a search with fewer chunks than asked for.
+async fn set_hnsw_ef_search(tx: &PgTransaction<'_>, ef_search: u32) -> Result<()> {
+    tx.execute(
+        "SELECT set_config('hnsw.ef_search', $1, true)",
+        &[&ef_search.to_string()],
+    )
+    .await?;
+    Ok(())
+}
+
+/// Search chunks using hybrid approach: vector similarity + chunk full-text
+/// search. Returns the union of both candidate lists with per-list scores and
+/// ranks for RRF/weighted combination
+#[allow(clippy::too_many_arguments)]
 pub async fn search_chunks_hybrid(
-    pool: &PgPool,
+    pool: &Database,
     user_id: Uuid,
     query_text: &str,
     query_embedding: Vec<f32>,
     embedding_dimensions: usize,
+    hnsw_ef_search: u32,
     limit: usize,
     similarity_threshold: f64,
+    scope: ChunkScope<'_>,
 ) -> Result<Vec<HybridChunkMatch>> {
-    let client = pool.get().await?;
+    let pool = dispatch!(pool, |db| sqlite::chunks::search_chunks_hybrid(
+        db,
+        user_id,
+        query_text,
+        query_embedding,
+        limit,
+        similarity_threshold,
+        scope,
+    ));
+    let mut client = pool.get().await?;
+    let configs = {
+        let mut params: Vec<&(dyn ToSql + Sync)> = vec![&user_id];
+        // The owner condition reads the chunk alias, whose keys the bookmark shares
+        let owner = scope.owner_condition(&mut params);
+        search::text_search_configs(&client, "c", &owner, &params).await?
+    };
+    let fts_condition = search::full_text_condition("c.search_tokens", &configs, 5);
+    let tsquery = search::full_text_query(&configs, 5);
+    let query_embedding = Vector::from(query_embedding);
+    let limit = limit as i64;
+    let mut params: Vec<&(dyn ToSql + Sync)> = vec![
+        &user_id,
+        &query_embedding,
+        &similarity_threshold,
+        &limit,
+        &query_text,
+    ];
+    let owner = scope.owner_condition(&mut params);
+    let mut scope_clause = String::new();
+    scope.push_filters(&mut scope_clause, &mut params);
+
     let statement = format!(
         r#"
             WITH vector_matches AS (
                 SELECT
-                    c.chunk_id, c.bookmark_id, c.user_id, c.chunk_text,
-                    c.chunk_index, c.created_at, c.updated_at,
+                    c.chunk_id,
                     1 - ((c.embedding::vector({embedding_dimensions})) <=> $2) as vector_score,
                     ROW_NUMBER() OVER (ORDER BY (c.embedding::vector({embedding_dimensions})) <=> $2) as vector_rank
                 FROM bookmark_chunk c
-                WHERE c.user_id = $1
-                AND 1 - ((c.embedding::vector({embedding_dimensions})) <=> $2) >= $3
+                INNER JOIN bookmark b ON c.bookmark_id = b.bookmark_id AND c.user_id = b.user_id
+                WHERE {owner} AND b.deleted_at IS NULL
+                AND 1 - ((c.embedding::vector({embedding_dimensions})) <=> $2) >= $3{scope_clause}
                 ORDER BY (c.embedding::vector({embedding_dimensions})) <=> $2
                 LIMIT $4
             ),
-            fts_scores AS (
+            fts_matches AS (
                 SELECT
-                    b.bookmark_id,
-                    ts_rank(b.search_tokens, websearch_to_tsquery('english', $5)) as fts_score,
+                    c.chunk_id,
+                    ts_rank(c.search_tokens, {tsquery})::float8 as fts_score,
                     ROW_NUMBER() OVER (
-                        ORDER BY ts_rank(b.search_tokens, websearch_to_tsquery('english', $5)) DESC
+                        ORDER BY ts_rank(c.search_tokens, {tsquery}) DESC
                     ) as fts_rank
-                FROM bookmark b
-                WHERE b.user_id = $1
-                AND b.search_tokens @@ websearch_to_tsquery('english', $5)
+                FROM bookmark_chunk c
+                INNER JOIN bookmark b ON c.bookmark_id = b.bookmark_id AND c.user_id = b.user_id
+                WHERE {owner} AND b.deleted_at IS NULL
+                AND {fts_condition}{scope_clause}
+                ORDER BY fts_score DESC
+                LIMIT $4
+            ),
+            candidates AS (
+                SELECT chunk_id FROM vector_matches
+                UNION
+                SELECT chunk_id FROM fts_matches
             )
             SELECT
-                vm.chunk_id, vm.bookmark_id, vm.user_id, vm.chunk_text,
-                vm.chunk_index, vm.created_at, vm.updated_at,
-                vm.vector_score, vm.vector_rank,
-                COALESCE(fs.fts_score, 0.0) as fts_score,
-                COALESCE(fs.fts_rank, $4 + 1) as fts_rank,
+                c.chunk_id, c.bookmark_id, c.user_id, c.chunk_text,
+                c.chunk_index, c.created_at, c.updated_at,
+                COALESCE(vm.vector_score, 0.0) as vector_score, vm.vector_rank,
+                COALESCE(fm.fts_score, 0.0) as fts_score, fm.fts_rank,
                 b.url, b.domain, b.title, b.tags, b.summary,
+                b.word_count, b.reading_time_minutes, b.kind, b.favorite, b.original_title,
+                b.visibility,
                 b.created_at as bookmark_created_at, b.updated_at as bookmark_updated_at
-            FROM vector_matches vm
-            INNER JOIN bookmark b ON vm.bookmark_id = b.bookmark_id AND vm.user_id = b.user_id
-            LEFT JOIN fts_scores fs ON vm.bookmark_id = fs.bookmark_id
+            FROM candidates
+            INNER JOIN bookmark_chunk c ON c.chunk_id = candidates.chunk_id
+            INNER JOIN bookmark b ON c.bookmark_id = b.bookmark_id AND c.user_id = b.user_id
+            LEFT JOIN vector_matches vm ON vm.chunk_id = c.chunk_id
+            LEFT JOIN fts_matches fm ON fm.chunk_id = c.chunk_id
         "#
     );
 
-    // Use CTE to get vector matches with ranks, then join with FTS scores from
-    // bookmarks
-    let rows = client
-        .query(
-            &statement,
-            &[
-                &user_id,
-                &Vector::from(query_embedding),
-                &similarity_threshold,
-                &(limit as i64),
-                &query_text,
-            ],
-        )
-        .await?;
+    // Vector and full-text candidates are ranked independently, then unioned
+    // so either search alone can contribute a chunk to the fusion step
+    let tx = client.transaction().await?;
+    set_hnsw_ef_search(&tx, hnsw_ef_search).await?;
+    let rows = tx.query(&statement, &params).await?;
+    tx.commit().await?;
 
     let mut matches = Vec::new();
     for row in rows {
@@ -438,18 +859,24 @@ pub async fn search_chunks_hybrid(
             summary: row.get("summary"),
             created_at: row.get("bookmark_created_at"),
             updated_at: row.get("bookmark_updated_at"),
+            word_count: row.get("word_count"),
+            reading_time_minutes: row.get("reading_time_minutes"),
+            kind: bookmark::parse_kind(row.get("kind")),
+            favorite: row.get("favorite"),
+            original_title: row.get("original_title"),
+            visibility: bookmark::parse_visibility(row.get("visibility")),
         };
 
-        let vector_rank: i64 = row.get("vector_rank");
-        let fts_rank: i64 = row.get("fts_rank");
+        let vector_rank: Option<i64> = row.get("vector_rank");
+        let fts_rank: Option<i64> = row.get("fts_rank");
 
         matches.push(HybridChunkMatch {
             chunk,
             bookmark,
             vector_score: row.get("vector_score"),
-            vector_rank: vector_rank as usize,
+            vector_rank: vector_rank.map(|rank| rank as usize),
             fts_score: row.get("fts_score"),
-            fts_rank: fts_rank as usize,
+            fts_rank: fts_rank.map(|rank| rank as usize),
         });
     }
 
@@ -0,0 +1,180 @@
+use shared::{Collection, CollectionRequest};
+use tokio_postgres::Row;
+use tracing::debug;
+use uuid::Uuid;
+
+use super::{dispatch, sqlite, Database, PgTransaction};
+use crate::error::{Error, Result};
+
+const SELECT: &str = "SELECT c.collection_id, c.name, c.description, c.created_at, c.updated_at,
+        ARRAY(
+            SELECT cb.bookmark_id FROM collection_bookmark cb
+            WHERE cb.collection_id = c.collection_id
+            ORDER BY cb.position
+        ) AS bookmark_ids
+    FROM collection c";
+
+fn from_row(row: &Row) -> Collection {
+    Collection {
+        collection_id: row.get("collection_id"),
+        name: row.get("name"),
+        description: row.get("description"),
+        bookmark_ids: row.get("bookmark_ids"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+pub(super) fn unknown_bookmarks() -> Error {
+    Error::unprocessable_entity([("bookmark_ids", "must all be bookmarks of the user")])
+}
+
+/// Collections of the user by name.
+pub async fn list(pool: &Database, user_id: Uuid) -> Result<Vec<Collection>> {
+    let pool = dispatch!(pool, |db| sqlite::collection::list(db, user_id));
+    let client = pool.get().await?;
+    let rows = client
+        .query(
+            &format!("{SELECT} WHERE c.user_id = $1 ORDER BY lower(c.name), c.created_at"),
+            &[&user_id],
+        )
+        .await?;
+    Ok(rows.iter().map(from_row).collect())
+}
+
+pub async fn get(
+    pool: &Database,
+    user_id: Uuid,
+    collection_id: Uuid,
+) -> Result<Option<Collection>> {
+    let pool = dispatch!(pool, |db| sqlite::collection::get(
+        db,
+        user_id,
+        collection_id
+    ));
+    let client = pool.get().await?;
+    let row = client
+        .query_opt(
+            &format!("{SELECT} WHERE c.collection_id = $1 AND c.user_id = $2"),
+            &[&collection_id, &user_id],
+        )
+        .await?;
+    Ok(row.as_ref().map(from_row))
+}
+
+/// Fails with unprocessable entity when some of `bookmark_ids` aren't
+/// bookmarks of the user.
+async fn replace_bookmarks(
+    tx: &PgTransaction<'_>,
+    user_id: Uuid,
+    collection_id: Uuid,
+    bookmark_ids: &[String],
+) -> Result<()> {
+    tx.execute(
+        "DELETE FROM collection_bookmark WHERE collection_id = $1",
+        &[&collection_id],
+    )
+    .await?;
+    let inserted = tx
+        .execute(
+            "INSERT INTO collection_bookmark (collection_id, user_id, bookmark_id, position)
+             SELECT $1, $2, b.bookmark_id, ids.position::INTEGER
+             FROM unnest($3::TEXT[]) WITH ORDINALITY AS ids(bookmark_id, position)
+             JOIN bookmark b ON b.bookmark_id = ids.bookmark_id AND b.user_id = $2",
+            &[&collection_id, &user_id, &bookmark_ids],
+        )
+        .await?;
+    if inserted as usize != bookmark_ids.len() {
+        return Err(unknown_bookmarks());
+    }
+    Ok(())
+}
+
+pub async fn create(
+    pool: &Database,
+    user_id: Uuid,
+    request: &CollectionRequest,
+) -> Result<Collection> {
+    let pool = dispatch!(pool, |db| sqlite::collection::create(db, user_id, request));
+    let mut client = pool.get().await?;
+    let tx = client.transaction().await?;
+    let collection_id: Uuid = tx
+        .query_one(
+            "INSERT INTO collection (user_id, name, description) VALUES ($1, $2, $3)
+             RETURNING collection_id",
+            &[&user_id, &request.name, &request.description],
+        )
+        .await?
+        .get("collection_id");
+    replace_bookmarks(&tx, user_id, collection_id, &request.bookmark_ids).await?;
+    let row = tx
+        .query_one(
+            &format!("{SELECT} WHERE c.collection_id = $1"),
+            &[&collection_id],
+        )
+        .await?;
+    tx.commit().await?;
+    debug!(%user_id, %collection_id, "Collection created");
+    Ok(from_row(&row))
+}
+
+/// Replaces name, description and bookmarks, `None` when the user has no
+/// such collection.
+pub async fn update(
+    pool: &Database,
+    user_id: Uuid,
+    collection_id: Uuid,
+    request: &CollectionRequest,
+) -> Result<Option<Collection>> {
+    let pool = dispatch!(pool, |db| sqlite::collection::update(
+        db,
+        user_id,
+        collection_id,
+        request
+    ));
+    let mut client = pool.get().await?;
+    let tx = client.transaction().await?;
+    let updated = tx
+        .execute(
+            "UPDATE collection SET name = $3, description = $4, updated_at = now()
+             WHERE collection_id = $1 AND user_id = $2",
+            &[
+                &collection_id,
+                &user_id,
+                &request.name,
+                &request.description,
+            ],
+        )
+        .await?;
+    if updated == 0 {
+        return Ok(None);
+    }
+    replace_bookmarks(&tx, user_id, collection_id, &request.bookmark_ids).await?;
+    let row = tx
+        .query_one(
+            &format!("{SELECT} WHERE c.collection_id = $1"),
+            &[&collection_id],
+        )
+        .await?;
+    tx.commit().await?;
+    debug!(%user_id, %collection_id, "Collection updated");
+    Ok(Some(from_row(&row)))
+}
+
+/// Deletes the collection, not its bookmarks. False when the user has no
+/// such collection.
+pub async fn delete(pool: &Database, user_id: Uuid, collection_id: Uuid) -> Result<bool> {
+    let pool = dispatch!(pool, |db| sqlite::collection::delete(
+        db,
+        user_id,
+        collection_id
+    ));
+    let client = pool.get().await?;
+    let deleted = client
+        .execute(
+            "DELETE FROM collection WHERE collection_id = $1 AND user_id = $2",
+            &[&collection_id, &user_id],
+        )
+        .await?;
+    Ok(deleted > 0)
+}
@@ -0,0 +1,199 @@
+use chrono::{DateTime, Utc};
+use shared::{Bookmark, Digest};
+use tokio_postgres::Row;
+use tracing::debug;
+use uuid::Uuid;
+
+use super::user::hash_token;
+use super::{bookmark, dispatch, sqlite, Database};
+use crate::error::Result;
+
+fn from_row(row: &Row) -> Digest {
+    Digest {
+        digest_id: row.get("digest_id"),
+        period_start: row.get("period_start"),
+        period_end: row.get("period_end"),
+        bookmark_ids: row.get("bookmark_ids"),
+        content: row.get("content"),
+        created_at: row.get("created_at"),
+    }
+}
+
+/// Users who saved bookmarks in `[period_start, period_end)`, still want a
+/// digest and don't have one for that period yet.
+pub async fn users_due(
+    pool: &Database,
+    period_start: DateTime<Utc>,
+    period_end: DateTime<Utc>,
+) -> Result<Vec<Uuid>> {
+    const SQL: &str = r#"
+        SELECT DISTINCT b.user_id
+        FROM bookmark b
+        LEFT JOIN user_settings s ON s.user_id = b.user_id
+        WHERE b.created_at >= $1 AND b.created_at < $2 AND b.deleted_at IS NULL
+        AND coalesce(s.digest_enabled, TRUE)
+        AND NOT EXISTS (
+            SELECT 1 FROM digest d WHERE d.user_id = b.user_id AND d.period_start = $1
+        );"#;
+    let pool = dispatch!(pool, |db| sqlite::digest::users_due(
+        db,
+        period_start,
+        period_end
+    ));
+    let client = pool.get().await?;
+    let rows = client.query(SQL, &[&period_start, &period_end]).await?;
+    Ok(rows.iter().map(|row| row.get("user_id")).collect())
+}
+
+/// Bookmarks saved by `user_id` in `[period_start, period_end)`, oldest first.
+pub async fn bookmarks_in_period(
+    pool: &Database,
+    user_id: Uuid,
+    period_start: DateTime<Utc>,
+    period_end: DateTime<Utc>,
+    limit: usize,
+) -> Result<Vec<Bookmark>> {
+    const SQL: &str = r#"
+        SELECT bookmark_id, user_id, url, domain, title, tags, summary, created_at, updated_at,
+            word_count, reading_time_minutes, kind, favorite, original_title, visibility
+        FROM bookmark
+        WHERE user_id = $1 AND created_at >= $2 AND created_at < $3 AND deleted_at IS NULL
+        ORDER BY created_at
+        LIMIT $4;"#;
+    let pool = dispatch!(pool, |db| sqlite::digest::bookmarks_in_period(
+        db,
+        user_id,
+        period_start,
+        period_end,
+        limit,
+    ));
+    let client = pool.get().await?;
+    let limit = limit as i64;
+    let rows = client
+        .query(SQL, &[&user_id, &period_start, &period_end, &limit])
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| Bookmark {
+            bookmark_id: row.get("bookmark_id"),
+            user_id: row.get("user_id"),
+            url: row.get("url"),
+            domain: row.get("domain"),
+            title: row.get("title"),
+            tags: row.get("tags"),
+            summary: row.get("summary"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+            word_count: row.get("word_count"),
+            reading_time_minutes: row.get("reading_time_minutes"),
+            kind: bookmark::parse_kind(row.get("kind")),
+            favorite: row.get("favorite"),
+            original_title: row.get("original_title"),
+            visibility: bookmark::parse_visibility(row.get("visibility")),
+        })
+        .collect())
+}
+
+/// Stores a digest, `None` when one already exists for the period.
+pub async fn insert(
+    pool: &Database,
+    user_id: Uuid,
+    period_start: DateTime<Utc>,
+    period_end: DateTime<Utc>,
+    bookmark_ids: &[String],
+    content: &str,
+) -> Result<Option<Digest>> {
+    const SQL: &str = r#"
+        INSERT INTO digest (user_id, period_start, period_end, bookmark_ids, content)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (user_id, period_start) DO NOTHING
+        RETURNING digest_id, period_start, period_end, bookmark_ids, content, created_at;"#;
+    let pool = dispatch!(pool, |db| sqlite::digest::insert(
+        db,
+        user_id,
+        period_start,
+        period_end,
+        bookmark_ids,
+        content,
+    ));
+    let client = pool.get().await?;
+    let row = client
+        .query_opt(
+            SQL,
+            &[
+                &user_id,
+                &period_start,
+                &period_end,
+                &bookmark_ids,
+                &content,
+            ],
+        )
+        .await?;
+    debug!(user_id = %user_id, stored = row.is_some(), "Inserted digest");
+    Ok(row.as_ref().map(from_row))
+}
+
+pub async fn mark_emailed(pool: &Database, user_id: Uuid, digest_id: Uuid) -> Result<()> {
+    const SQL: &str = "UPDATE digest SET emailed_at = now() WHERE digest_id = $1 AND user_id = $2;";
+    let pool = dispatch!(pool, |db| sqlite::digest::mark_emailed(
+        db, user_id, digest_id
+    ));
+    let client = pool.get().await?;
+    client.execute(SQL, &[&digest_id, &user_id]).await?;
+    Ok(())
+}
+
+/// Most recent digests for `user_id`, newest period first.
+pub async fn list(pool: &Database, user_id: Uuid, limit: usize) -> Result<Vec<Digest>> {
+    const SQL: &str = r#"
+        SELECT digest_id, period_start, period_end, bookmark_ids, content, created_at
+        FROM digest
+        WHERE user_id = $1
+        ORDER BY period_start DESC
+        LIMIT $2;"#;
+    let pool = dispatch!(pool, |db| sqlite::digest::list(db, user_id, limit));
+    let client = pool.get().await?;
+    let limit = limit as i64;
+    let rows = client.query(SQL, &[&user_id, &limit]).await?;
+    Ok(rows.iter().map(from_row).collect())
+}
+
+pub async fn get(pool: &Database, user_id: Uuid, digest_id: Uuid) -> Result<Option<Digest>> {
+    const SQL: &str = r#"
+        SELECT digest_id, period_start, period_end, bookmark_ids, content, created_at
+        FROM digest
+        WHERE digest_id = $1 AND user_id = $2;"#;
+    let pool = dispatch!(pool, |db| sqlite::digest::get(db, user_id, digest_id));
+    let client = pool.get().await?;
+    let row = client.query_opt(SQL, &[&digest_id, &user_id]).await?;
+    Ok(row.as_ref().map(from_row))
+}
+
+/// Issues the secret of the user's digest feed URL, revoking the one issued
+/// before. Only its hash is stored.
+pub async fn issue_feed_token(pool: &Database, user_id: Uuid) -> Result<String> {
+    const SQL: &str = r#"
+        INSERT INTO digest_feed_token (user_id, token_hash) VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE SET token_hash = EXCLUDED.token_hash, created_at = now();"#;
+    let token = hex::encode(rand::random::<[u8; 32]>());
+    match pool {
+        Database::Postgres(pool) => {
+            let client = pool.get().await?;
+            client
+                .execute(SQL, &[&user_id, &hash_token(&token)])
+                .await?;
+        }
+        Database::Sqlite(db) => sqlite::digest::issue_feed_token(db, user_id, &token).await?,
+    }
+    debug!(user_id = %user_id, "Digest feed token issued");
+    Ok(token)
+}
+
+/// Owner of the digest feed `token`.
+pub async fn feed_token_user(pool: &Database, token: &str) -> Result<Option<Uuid>> {
+    const SQL: &str = "SELECT user_id FROM digest_feed_token WHERE token_hash = $1;";
+    let pool = dispatch!(pool, |db| sqlite::digest::feed_token_user(db, token));
+    let client = pool.get().await?;
+    let row = client.query_opt(SQL, &[&hash_token(token)]).await?;
+    Ok(row.map(|row| row.get("user_id")))
+}
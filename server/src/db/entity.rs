@@ -0,0 +1,81 @@
+use std::str::FromStr;
+
+use shared::{Entity, EntityCount, EntityKind};
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+use super::{dispatch, sqlite, Database};
+use crate::error::{Error, Result};
+
+fn from_row(row: &Row) -> Result<EntityCount> {
+    let kind: String = row.get("kind");
+    Ok(EntityCount {
+        kind: EntityKind::from_str(&kind)
+            .map_err(|_| Error::from(anyhow::anyhow!("unknown entity kind: {kind}")))?,
+        name: row.get("name"),
+        count: row.get("count"),
+    })
+}
+
+/// Replaces the entities of the bookmark with `entities`.
+pub async fn replace_for_bookmark(
+    pool: &Database,
+    user_id: Uuid,
+    bookmark_id: &str,
+    entities: &[Entity],
+) -> Result<()> {
+    let pool = dispatch!(pool, |db| sqlite::entity::replace_for_bookmark(
+        db,
+        user_id,
+        bookmark_id,
+        entities
+    ));
+    let mut client = pool.get().await?;
+    let tx = client.transaction().await?;
+    tx.execute(
+        "DELETE FROM entity WHERE bookmark_id = $1 AND user_id = $2",
+        &[&bookmark_id, &user_id],
+    )
+    .await?;
+    for entity in entities {
+        tx.execute(
+            "INSERT INTO entity (bookmark_id, user_id, kind, name) VALUES ($1, $2, $3, $4)
+             ON CONFLICT DO NOTHING",
+            &[&bookmark_id, &user_id, &entity.kind.as_ref(), &entity.name],
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Entities of the user's bookmarks with how many mention each, the most
+/// mentioned first.
+pub async fn list(
+    pool: &Database,
+    user_id: Uuid,
+    kind: Option<EntityKind>,
+    query: Option<&str>,
+    limit: i64,
+) -> Result<Vec<EntityCount>> {
+    const SQL: &str = r#"
+        SELECT kind, name, count(1) AS count
+        FROM entity
+        WHERE user_id = $1
+          AND ($2::text IS NULL OR kind = $2)
+          AND ($3::text IS NULL OR strpos(lower(name), lower($3)) > 0)
+        GROUP BY kind, name
+        ORDER BY count DESC, lower(name)
+        LIMIT $4;"#;
+    let pool = dispatch!(pool, |db| sqlite::entity::list(
+        db, user_id, kind, query, limit
+    ));
+    let kind = kind.as_ref().map(AsRef::<str>::as_ref);
+    pool.get()
+        .await?
+        .query(SQL, &[&user_id, &kind, &query, &limit])
+        .await?
+        .iter()
+        .map(from_row)
+        .collect()
+}
@@ -0,0 +1,131 @@
+use shared::{ExtractionRule, ExtractionRuleRequest};
+use tokio_postgres::Row;
+use tracing::debug;
+use uuid::Uuid;
+
+use super::{dispatch, sqlite, Database};
+use crate::error::Result;
+
+const COLUMNS: &str =
+    "rule_id, domain, keep_selectors, drop_selectors, title_selector, created_at, updated_at";
+
+pub(super) fn from_row(row: &Row) -> ExtractionRule {
+    ExtractionRule {
+        rule_id: row.get("rule_id"),
+        domain: row.get("domain"),
+        keep_selectors: row.get("keep_selectors"),
+        drop_selectors: row.get("drop_selectors"),
+        title_selector: row.get("title_selector"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+/// Extraction rules of the user, by domain.
+pub async fn list(pool: &Database, user_id: Uuid) -> Result<Vec<ExtractionRule>> {
+    let pool = dispatch!(pool, |db| sqlite::extraction_rule::list(db, user_id));
+    let client = pool.get().await?;
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {COLUMNS} FROM extraction_rule WHERE user_id = $1
+                 ORDER BY domain, created_at"
+            ),
+            &[&user_id],
+        )
+        .await?;
+    Ok(rows.iter().map(from_row).collect())
+}
+
+pub async fn get(pool: &Database, user_id: Uuid, rule_id: Uuid) -> Result<Option<ExtractionRule>> {
+    let pool = dispatch!(pool, |db| sqlite::extraction_rule::get(
+        db, user_id, rule_id
+    ));
+    let client = pool.get().await?;
+    let row = client
+        .query_opt(
+            &format!("SELECT {COLUMNS} FROM extraction_rule WHERE rule_id = $1 AND user_id = $2"),
+            &[&rule_id, &user_id],
+        )
+        .await?;
+    Ok(row.as_ref().map(from_row))
+}
+
+pub async fn create(
+    pool: &Database,
+    user_id: Uuid,
+    request: &ExtractionRuleRequest,
+) -> Result<ExtractionRule> {
+    let pool = dispatch!(pool, |db| sqlite::extraction_rule::create(
+        db, user_id, request
+    ));
+    let client = pool.get().await?;
+    let row = client
+        .query_one(
+            &format!(
+                "INSERT INTO extraction_rule
+                    (user_id, domain, keep_selectors, drop_selectors, title_selector)
+                 VALUES ($1, $2, $3, $4, $5)
+                 RETURNING {COLUMNS}"
+            ),
+            &[
+                &user_id,
+                &request.domain,
+                &request.keep_selectors,
+                &request.drop_selectors,
+                &request.title_selector,
+            ],
+        )
+        .await?;
+    let rule = from_row(&row);
+    debug!(%user_id, rule_id = %rule.rule_id, "Extraction rule created");
+    Ok(rule)
+}
+
+/// Replaces all of the rule, `None` when the user has no such rule.
+pub async fn update(
+    pool: &Database,
+    user_id: Uuid,
+    rule_id: Uuid,
+    request: &ExtractionRuleRequest,
+) -> Result<Option<ExtractionRule>> {
+    let pool = dispatch!(pool, |db| sqlite::extraction_rule::update(
+        db, user_id, rule_id, request
+    ));
+    let client = pool.get().await?;
+    let row = client
+        .query_opt(
+            &format!(
+                "UPDATE extraction_rule
+                 SET domain = $3, keep_selectors = $4, drop_selectors = $5,
+                     title_selector = $6, updated_at = now()
+                 WHERE rule_id = $1 AND user_id = $2
+                 RETURNING {COLUMNS}"
+            ),
+            &[
+                &rule_id,
+                &user_id,
+                &request.domain,
+                &request.keep_selectors,
+                &request.drop_selectors,
+                &request.title_selector,
+            ],
+        )
+        .await?;
+    Ok(row.as_ref().map(from_row))
+}
+
+/// False when the user has no such rule.
+pub async fn delete(pool: &Database, user_id: Uuid, rule_id: Uuid) -> Result<bool> {
+    let pool = dispatch!(pool, |db| sqlite::extraction_rule::delete(
+        db, user_id, rule_id
+    ));
+    let client = pool.get().await?;
+    let deleted = client
+        .execute(
+            "DELETE FROM extraction_rule WHERE rule_id = $1 AND user_id = $2",
+            &[&rule_id, &user_id],
+        )
+        .await?;
+    Ok(deleted > 0)
+}
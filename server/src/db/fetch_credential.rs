@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+use tracing::debug;
+use uuid::Uuid;
+
+use super::{dispatch, sqlite, Database};
+use crate::error::Result;
+
+/// Headers of a domain as stored, sealed by
+/// [`crate::fetch_credentials::CredentialCipher`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SealedFetchCredential {
+    pub domain: String,
+    pub sealed_headers: Vec<u8>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Fetch credentials of the user, by domain.
+pub async fn list(pool: &Database, user_id: Uuid) -> Result<Vec<SealedFetchCredential>> {
+    let pool = dispatch!(pool, |db| sqlite::fetch_credential::list(db, user_id));
+    let client = pool.get().await?;
+    let rows = client
+        .query(
+            "SELECT domain, sealed_headers, updated_at FROM fetch_credential
+             WHERE user_id = $1 ORDER BY domain",
+            &[&user_id],
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| SealedFetchCredential {
+            domain: row.get("domain"),
+            sealed_headers: row.get("sealed_headers"),
+            updated_at: row.get("updated_at"),
+        })
+        .collect())
+}
+
+/// Replaces all of the user's fetch credentials with `credentials`, pairs of
+/// domain and sealed headers.
+pub async fn replace(
+    pool: &Database,
+    user_id: Uuid,
+    credentials: Vec<(String, Vec<u8>)>,
+) -> Result<()> {
+    let pool = dispatch!(pool, |db| sqlite::fetch_credential::replace(
+        db,
+        user_id,
+        credentials
+    ));
+    let mut client = pool.get().await?;
+    let tx = client.transaction().await?;
+    tx.execute(
+        "DELETE FROM fetch_credential WHERE user_id = $1",
+        &[&user_id],
+    )
+    .await?;
+    for (domain, sealed_headers) in &credentials {
+        tx.execute(
+            "INSERT INTO fetch_credential (user_id, domain, sealed_headers)
+             VALUES ($1, $2, $3)",
+            &[&user_id, domain, sealed_headers],
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    debug!(%user_id, credential_count = credentials.len(), "Fetch credentials replaced");
+    Ok(())
+}
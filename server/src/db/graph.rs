@@ -0,0 +1,94 @@
+use shared::Entity;
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+use super::{dispatch, sqlite, Database};
+use crate::error::Result;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphBookmark {
+    pub bookmark_id: String,
+    pub title: String,
+    pub domain: String,
+    pub canonical_url: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphLink {
+    pub bookmark_id: String,
+    pub url: String,
+    pub domain: String,
+}
+
+/// The most recent bookmarks of a user with their entities and outbound
+/// links, what the library graph is built from.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GraphData {
+    pub bookmarks: Vec<GraphBookmark>,
+    pub entities: Vec<(String, Entity)>,
+    pub links: Vec<GraphLink>,
+}
+
+fn entity_from_row(row: &Row) -> Option<(String, Entity)> {
+    let kind: String = row.get("kind");
+    let entity = Entity {
+        kind: kind.parse().ok()?,
+        name: row.get("name"),
+    };
+    Some((row.get("bookmark_id"), entity))
+}
+
+pub async fn load(pool: &Database, user_id: Uuid, limit: i64) -> Result<GraphData> {
+    let pool = dispatch!(pool, |db| sqlite::graph::load(db, user_id, limit));
+    let client = pool.get().await?;
+    let bookmarks: Vec<GraphBookmark> = client
+        .query(
+            "SELECT bookmark_id, title, domain, canonical_url FROM bookmark
+             WHERE user_id = $1 AND deleted_at IS NULL ORDER BY created_at DESC LIMIT $2",
+            &[&user_id, &limit],
+        )
+        .await?
+        .iter()
+        .map(|row| GraphBookmark {
+            bookmark_id: row.get("bookmark_id"),
+            title: row.get("title"),
+            domain: row.get("domain"),
+            canonical_url: row.get("canonical_url"),
+        })
+        .collect();
+    let bookmark_ids: Vec<&str> = bookmarks
+        .iter()
+        .map(|bookmark| bookmark.bookmark_id.as_str())
+        .collect();
+
+    let entities = client
+        .query(
+            "SELECT bookmark_id, kind, name FROM entity
+             WHERE user_id = $1 AND bookmark_id = ANY($2)",
+            &[&user_id, &bookmark_ids],
+        )
+        .await?
+        .iter()
+        .filter_map(entity_from_row)
+        .collect();
+    let links = client
+        .query(
+            "SELECT bookmark_id, url, domain FROM bookmark_link
+             WHERE user_id = $1 AND bookmark_id = ANY($2)",
+            &[&user_id, &bookmark_ids],
+        )
+        .await?
+        .iter()
+        .map(|row| GraphLink {
+            bookmark_id: row.get("bookmark_id"),
+            url: row.get("url"),
+            domain: row.get("domain"),
+        })
+        .collect();
+
+    Ok(GraphData {
+        bookmarks,
+        entities,
+        links,
+    })
+}
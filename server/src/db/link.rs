@@ -0,0 +1,77 @@
+use shared::BookmarkLink;
+use url::Url;
+use uuid::Uuid;
+
+use super::{dispatch, sqlite, Database};
+use crate::bookmark_identity::domain_from_url;
+use crate::error::Result;
+
+/// Replaces the outbound links of the bookmark with `links`, which are
+/// expected canonical.
+pub async fn replace_for_bookmark(
+    pool: &Database,
+    user_id: Uuid,
+    bookmark_id: &str,
+    links: &[Url],
+) -> Result<()> {
+    let links: Vec<(String, String)> = links
+        .iter()
+        .filter_map(|link| Some((link.to_string(), domain_from_url(link).ok()?)))
+        .collect();
+    let pool = dispatch!(pool, |db| sqlite::link::replace_for_bookmark(
+        db,
+        user_id,
+        bookmark_id,
+        links
+    ));
+    let mut client = pool.get().await?;
+    let tx = client.transaction().await?;
+    tx.execute(
+        "DELETE FROM bookmark_link WHERE bookmark_id = $1 AND user_id = $2",
+        &[&bookmark_id, &user_id],
+    )
+    .await?;
+    for (url, domain) in &links {
+        tx.execute(
+            "INSERT INTO bookmark_link (bookmark_id, user_id, url, domain) VALUES ($1, $2, $3, $4)
+             ON CONFLICT DO NOTHING",
+            &[&bookmark_id, &user_id, url, domain],
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Outbound links of the bookmark, each with the saved bookmark of the user
+/// it points to when there is one.
+pub async fn list_outbound(
+    pool: &Database,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<Vec<BookmarkLink>> {
+    let pool = dispatch!(pool, |db| sqlite::link::list_outbound(
+        db,
+        user_id,
+        bookmark_id
+    ));
+    let client = pool.get().await?;
+    let links = client
+        .query(
+            "SELECT l.url, l.domain, b.bookmark_id FROM bookmark_link l
+             LEFT JOIN bookmark b ON b.user_id = l.user_id AND b.canonical_url = l.url
+                 AND b.deleted_at IS NULL
+             WHERE l.bookmark_id = $1 AND l.user_id = $2
+             ORDER BY l.url",
+            &[&bookmark_id, &user_id],
+        )
+        .await?
+        .iter()
+        .map(|row| BookmarkLink {
+            url: row.get("url"),
+            domain: row.get("domain"),
+            bookmark_id: row.get("bookmark_id"),
+        })
+        .collect();
+    Ok(links)
+}
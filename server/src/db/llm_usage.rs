@@ -0,0 +1,93 @@
+use chrono::NaiveDate;
+use shared::LlmUsageDay;
+use uuid::Uuid;
+
+use super::{dispatch, sqlite, Database};
+use crate::error::Result;
+
+/// A single LLM request attempt, as recorded by the LLM client.
+#[derive(Debug, Clone)]
+pub struct NewLlmUsage {
+    pub user_id: Option<Uuid>,
+    pub bookmark_id: Option<String>,
+    pub session_id: Option<Uuid>,
+    pub purpose: String,
+    pub request_kind: &'static str,
+    pub provider: String,
+    pub model: String,
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+    pub duration_ms: i64,
+    pub success: bool,
+}
+
+pub async fn insert(pool: &Database, usage: &NewLlmUsage) -> Result<()> {
+    const SQL: &str = r#"
+        INSERT INTO llm_usage (
+            user_id, bookmark_id, session_id, purpose, request_kind, provider, model,
+            input_tokens, output_tokens, duration_ms, success
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11);"#;
+    let pool = dispatch!(pool, |db| sqlite::llm_usage::insert(db, usage));
+    let client = pool.get().await?;
+    client
+        .execute(
+            SQL,
+            &[
+                &usage.user_id,
+                &usage.bookmark_id,
+                &usage.session_id,
+                &usage.purpose,
+                &usage.request_kind,
+                &usage.provider,
+                &usage.model,
+                &usage.input_tokens,
+                &usage.output_tokens,
+                &usage.duration_ms,
+                &usage.success,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+/// Daily totals per purpose and model for `user_id` since `since` (UTC days),
+/// newest day first.
+pub async fn summarize_by_day(
+    pool: &Database,
+    user_id: Uuid,
+    since: NaiveDate,
+) -> Result<Vec<LlmUsageDay>> {
+    const SQL: &str = r#"
+        SELECT
+            (created_at AT TIME ZONE 'UTC')::date AS day,
+            purpose,
+            model,
+            COUNT(*) AS calls,
+            COUNT(*) FILTER (WHERE NOT success) AS failed_calls,
+            COALESCE(SUM(input_tokens), 0)::BIGINT AS input_tokens,
+            COALESCE(SUM(output_tokens), 0)::BIGINT AS output_tokens,
+            COALESCE(SUM(duration_ms), 0)::BIGINT AS duration_ms
+        FROM llm_usage
+        WHERE user_id = $1 AND (created_at AT TIME ZONE 'UTC')::date >= $2
+        GROUP BY day, purpose, model
+        ORDER BY day DESC, purpose, model;"#;
+    let pool = dispatch!(pool, |db| sqlite::llm_usage::summarize_by_day(
+        db, user_id, since
+    ));
+    let client = pool.get().await?;
+    let rows = client.query(SQL, &[&user_id, &since]).await?;
+    Ok(rows
+        .iter()
+        .map(|row| LlmUsageDay {
+            day: row.get("day"),
+            purpose: row.get("purpose"),
+            model: row.get("model"),
+            calls: row.get("calls"),
+            failed_calls: row.get("failed_calls"),
+            input_tokens: row.get("input_tokens"),
+            output_tokens: row.get("output_tokens"),
+            duration_ms: row.get("duration_ms"),
+        })
+        .collect())
+}
@@ -0,0 +1,133 @@
+//! Schema migrations embedded in the binary, shared by both database
+//! backends. Each one is applied once, in version order and in its own
+//! transaction, and its checksum is recorded so a migration edited after it
+//! ran is caught at startup instead of leaving instances with diverging
+//! schemas.
+
+use std::collections::BTreeMap;
+
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Migration {
+    pub version: i32,
+    /// File name under `schema/`
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+impl Migration {
+    /// Hex SHA-256 of the statements.
+    pub fn checksum(&self) -> String {
+        hex::encode(Sha256::digest(self.sql.as_bytes()))
+    }
+}
+
+/// A [`Migration`] for the file `schema/$name`.
+macro_rules! migration {
+    ($version:literal, $name:literal) => {
+        $crate::db::migration::Migration {
+            version: $version,
+            name: $name,
+            sql: include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/schema/", $name)),
+        }
+    };
+}
+pub(super) use migration;
+
+/// What brings a database up to date with the migrations of this build.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(super) struct Plan {
+    /// Applied before checksums were recorded, their checksum is stored as is
+    pub backfill: Vec<Migration>,
+    pub pending: Vec<Migration>,
+}
+
+/// Compares `migrations`, sorted by version, with the `applied` versions and
+/// their recorded checksums. Fails when an applied migration no longer
+/// matches its checksum.
+pub(super) fn plan(
+    migrations: &[Migration],
+    applied: &BTreeMap<i32, Option<String>>,
+) -> Result<Plan> {
+    debug_assert!(migrations.is_sorted_by(|a, b| a.version < b.version));
+    let mut plan = Plan::default();
+    for migration in migrations {
+        match applied.get(&migration.version) {
+            None => plan.pending.push(*migration),
+            Some(None) => plan.backfill.push(*migration),
+            Some(Some(checksum)) if *checksum == migration.checksum() => {}
+            Some(Some(_)) => {
+                return Err(Error::Anyhow(anyhow::anyhow!(
+                    "Migration {} ({}) was changed after it was applied",
+                    migration.version,
+                    migration.name
+                )));
+            }
+        }
+    }
+
+    let latest = migrations.last().map_or(0, |migration| migration.version);
+    if let Some((&version, _)) = applied.range(latest + 1..).next_back() {
+        warn!(
+            schema_version = version,
+            latest_known = latest,
+            "Database schema is newer than this build, unknown migrations are left as they are"
+        );
+    }
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIGRATIONS: [Migration; 3] = [
+        Migration {
+            version: 1,
+            name: "1_first.sql",
+            sql: "CREATE TABLE a (id INTEGER);",
+        },
+        Migration {
+            version: 2,
+            name: "2_second.sql",
+            sql: "CREATE TABLE b (id INTEGER);",
+        },
+        Migration {
+            version: 3,
+            name: "3_third.sql",
+            sql: "CREATE TABLE c (id INTEGER);",
+        },
+    ];
+
+    #[test]
+    fn pending_and_unrecorded_migrations_are_planned() {
+        let applied = BTreeMap::from([(1, Some(MIGRATIONS[0].checksum())), (2, None)]);
+        let planned = plan(&MIGRATIONS, &applied).unwrap();
+        assert_eq!(planned.backfill, [MIGRATIONS[1]]);
+        assert_eq!(planned.pending, [MIGRATIONS[2]]);
+
+        // Versions of a newer build are left alone
+        let applied = MIGRATIONS
+            .iter()
+            .map(|migration| (migration.version, Some(migration.checksum())))
+            .chain([(4, Some("unknown".to_string()))])
+            .collect();
+        assert_eq!(plan(&MIGRATIONS, &applied).unwrap(), Plan::default());
+    }
+
+    #[test]
+    fn changed_migrations_are_rejected() {
+        let applied = BTreeMap::from([(1, Some(MIGRATIONS[1].checksum()))]);
+        let Err(Error::Anyhow(error)) = plan(&MIGRATIONS, &applied) else {
+            panic!("a changed migration must be rejected");
+        };
+        assert_eq!(
+            error.to_string(),
+            "Migration 1 (1_first.sql) was changed after it was applied"
+        );
+    }
+}
@@ -1,116 +1,173 @@
+use std::collections::BTreeMap;
+
 use anyhow::Context;
-use deadpool_postgres::{
-    Config, GenericClient, ManagerConfig, PoolConfig, RecyclingMethod, Runtime,
-};
+use deadpool_postgres::{Config, ManagerConfig, PoolConfig, RecyclingMethod, Runtime};
 use secrecy::ExposeSecret;
 use tracing::{debug, info, warn};
 
+use self::migration::{migration, Migration};
+pub use self::pg::{GenericClient, PgConnection, PgPool, PgTransaction};
+pub use self::sqlite::SqlitePool;
 use crate::error::{Error, Result};
-use crate::{PgParams, EMBEDDING_PIPELINE_VERSION};
+use crate::{DatabaseParams, PgParams, EMBEDDING_PIPELINE_VERSION};
 
+pub mod activity;
 pub mod ai;
+pub mod audit_log;
+pub mod backup;
 pub mod bookmark;
 pub mod bookmark_task;
+pub mod bookmark_version;
 pub mod chunks;
+pub mod collection;
+pub mod digest;
+pub mod entity;
+pub mod extraction_rule;
+pub mod fetch_credential;
+pub mod graph;
+pub mod link;
+pub mod llm_usage;
+mod migration;
+pub mod notify;
+mod pg;
 pub mod rag;
+pub mod reindex;
+pub mod rule;
+pub mod scheduled_job;
 pub mod search;
+mod sqlite;
+pub mod storage;
+pub mod task_event;
 pub mod user;
+pub mod user_session;
+pub mod user_settings;
+pub mod workspace;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum DatabaseBackendKind {
+    /// The Postgres server of PG_HOST
+    #[default]
+    Postgres,
+    /// A single SQLite file at SQLITE_PATH, for single-user deployments
+    Sqlite,
+}
+
+/// The database every query function runs against.
+#[derive(Debug, Clone)]
+pub enum Database {
+    Postgres(PgPool),
+    Sqlite(SqlitePool),
+}
 
-pub type PgPool = deadpool_postgres::Pool;
-pub type PgConnection = deadpool_postgres::Object;
+/// The Postgres pool of a [`Database`], or returns from the calling function
+/// with the awaited `$sqlite` call, `$db` bound to the SQLite pool.
+macro_rules! dispatch {
+    ($pool:expr, |$db:ident| $sqlite:expr) => {
+        match $pool {
+            $crate::db::Database::Postgres(pool) => pool,
+            $crate::db::Database::Sqlite($db) => return $sqlite.await,
+        }
+    };
+}
+use dispatch;
 
 const EMBEDDING_INDEX_NAME: &str = "idx_bookmark_chunk_embedding";
 
-const CREATE_GET_SCHEMA_FUNCTION: &str = "
-CREATE OR REPLACE FUNCTION get_schema_version() RETURNS INTEGER AS $$
-DECLARE
-    max_version INTEGER;
-BEGIN
-    IF EXISTS (
-        SELECT 1
-        FROM pg_catalog.pg_tables
-        WHERE schemaname = current_schema()
-        AND tablename = 'schema_version'
-    ) THEN
-        SELECT COALESCE(MAX(version), 0) INTO max_version
-        FROM schema_version;
-    ELSE
-        max_version := 0;
-    END IF;
-    RETURN max_version;
-END;
-$$ LANGUAGE plpgsql;";
-
-const SCHEMAS: [(i32, &str); 9] = [
-    (
-        1,
-        include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/schema/1_unified.sql")),
-    ),
-    (
-        2,
-        include_str!(concat!(
-            env!("CARGO_MANIFEST_DIR"),
-            "/schema/2_embedding_dimension.sql"
-        )),
-    ),
-    (
-        3,
-        include_str!(concat!(
-            env!("CARGO_MANIFEST_DIR"),
-            "/schema/3_lowercase_tags.sql"
-        )),
-    ),
-    (
-        4,
-        include_str!(concat!(
-            env!("CARGO_MANIFEST_DIR"),
-            "/schema/4_trim_tags.sql"
-        )),
-    ),
-    (
-        5,
-        include_str!(concat!(
-            env!("CARGO_MANIFEST_DIR"),
-            "/schema/5_reembed_qwen3.sql"
-        )),
-    ),
-    (
-        6,
-        include_str!(concat!(
-            env!("CARGO_MANIFEST_DIR"),
-            "/schema/6_ai_generation_retry_state.sql"
-        )),
-    ),
-    (
-        7,
-        include_str!(concat!(
-            env!("CARGO_MANIFEST_DIR"),
-            "/schema/7_bookmark_identity.sql"
-        )),
-    ),
-    (
-        8,
-        include_str!(concat!(
-            env!("CARGO_MANIFEST_DIR"),
-            "/schema/8_flexible_embeddings.sql"
-        )),
-    ),
-    (
-        9,
-        include_str!(concat!(
-            env!("CARGO_MANIFEST_DIR"),
-            "/schema/9_unified_ai_pipeline.sql"
-        )),
-    ),
+/// Migration 1 creates `schema_version`, the name and checksum columns came
+/// with the migration runner, which adds them before recording any version.
+const ADD_SCHEMA_VERSION_CHECKSUM: &str = "
+ALTER TABLE schema_version
+    ADD COLUMN IF NOT EXISTS name TEXT,
+    ADD COLUMN IF NOT EXISTS checksum TEXT;";
+
+/// Older migrations insert their own version, so recording one updates it.
+const RECORD_MIGRATION: &str = "
+INSERT INTO schema_version (version, name, checksum) VALUES ($1, $2, $3)
+ON CONFLICT (version) DO UPDATE SET name = EXCLUDED.name, checksum = EXCLUDED.checksum;";
+
+/// Advisory lock key held while migrating, so instances starting together
+/// don't apply the same migration twice.
+const MIGRATION_LOCK_KEY: i64 = 0x626f_6f6b_6d61_726b;
+
+const MIGRATIONS: [Migration; 51] = [
+    migration!(1, "1_unified.sql"),
+    migration!(2, "2_embedding_dimension.sql"),
+    migration!(3, "3_lowercase_tags.sql"),
+    migration!(4, "4_trim_tags.sql"),
+    migration!(5, "5_reembed_qwen3.sql"),
+    migration!(6, "6_ai_generation_retry_state.sql"),
+    migration!(7, "7_bookmark_identity.sql"),
+    migration!(8, "8_flexible_embeddings.sql"),
+    migration!(9, "9_unified_ai_pipeline.sql"),
+    migration!(10, "10_chunk_full_text_search.sql"),
+    migration!(11, "11_user_settings.sql"),
+    migration!(12, "12_llm_usage.sql"),
+    migration!(13, "13_bookmark_language.sql"),
+    migration!(14, "14_bookmark_embedding.sql"),
+    migration!(15, "15_digest.sql"),
+    migration!(16, "16_user_email.sql"),
+    migration!(17, "17_task_cancelled.sql"),
+    migration!(18, "18_task_ignore_robots.sql"),
+    migration!(19, "19_task_event.sql"),
+    migration!(20, "20_bookmark_static_bytes.sql"),
+    migration!(21, "21_bookmark_reading_time.sql"),
+    migration!(22, "22_bookmark_kind.sql"),
+    migration!(23, "23_entity.sql"),
+    migration!(24, "24_bookmark_link.sql"),
+    migration!(25, "25_collection.sql"),
+    migration!(26, "26_bookmark_version.sql"),
+    migration!(27, "27_bookmark_trash.sql"),
+    migration!(28, "28_trigram.sql"),
+    migration!(29, "29_rule.sql"),
+    migration!(30, "30_original_title.sql"),
+    migration!(31, "31_summary_style.sql"),
+    migration!(32, "32_reader_settings.sql"),
+    migration!(33, "33_rag_feedback.sql"),
+    migration!(34, "34_rag_session_title.sql"),
+    migration!(35, "35_embedding_cache.sql"),
+    migration!(36, "36_extraction_rule.sql"),
+    migration!(37, "37_fetch_credential.sql"),
+    migration!(38, "38_audit_log.sql"),
+    migration!(39, "39_user_session.sql"),
+    migration!(40, "40_workspace.sql"),
+    migration!(41, "41_bookmark_visibility.sql"),
+    migration!(42, "42_scheduled_job.sql"),
+    migration!(43, "43_task_lease.sql"),
+    migration!(44, "44_queue_notify.sql"),
+    migration!(45, "45_ai_output_skipped.sql"),
+    migration!(46, "46_search_tokens_backfill.sql"),
+    migration!(47, "47_digest_feed_token.sql"),
+    migration!(48, "48_bookmark_read_at.sql"),
+    migration!(49, "49_embedding_cache_owner.sql"),
+    migration!(50, "50_audit_log_keep_events.sql"),
+    migration!(51, "51_wallabag_entry_id.sql"),
 ];
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct EmbeddingProfile {
     pub provider: String,
     pub model: String,
     pub dimensions: usize,
 }
 
+fn pg_config(pg: &PgParams) -> Config {
+    let mut cfg = Config::new();
+    cfg.host = Some(pg.pg_host.clone());
+    cfg.port = Some(pg.pg_port);
+    cfg.user = Some(pg.pg_user.expose_secret().to_owned());
+    cfg.password = Some(pg.pg_password.expose_secret().to_owned());
+    cfg.dbname = Some(pg.pg_database.expose_secret().to_owned());
+    cfg
+}
+
+/// Settings of a standalone connection, outside the pool, to the database
+/// of `pg`.
+pub fn connection_config(pg: &PgParams) -> anyhow::Result<tokio_postgres::Config> {
+    pg_config(pg)
+        .get_pg_config()
+        .context("Invalid postgres settings")
+}
+
 pub async fn get_pool(pg: PgParams) -> anyhow::Result<PgPool> {
     info!(
         pg_host = %pg.pg_host,
@@ -120,12 +177,7 @@ pub async fn get_pool(pg: PgParams) -> anyhow::Result<PgPool> {
     );
     debug!(user = "[REDACTED]", database = "[REDACTED]", "Pool config");
 
-    let mut cfg = Config::new();
-    cfg.host = Some(pg.pg_host.clone());
-    cfg.port = Some(pg.pg_port);
-    cfg.user = Some(pg.pg_user.expose_secret().to_owned());
-    cfg.password = Some(pg.pg_password.expose_secret().to_owned());
-    cfg.dbname = Some(pg.pg_database.expose_secret().to_owned());
+    let mut cfg = pg_config(&pg);
     cfg.manager = Some(ManagerConfig {
         recycling_method: RecyclingMethod::Fast,
     });
@@ -139,62 +191,124 @@ pub async fn get_pool(pg: PgParams) -> anyhow::Result<PgPool> {
         .with_context(|| format!("Failure creating postgres pool with params: {pg:?}"))?;
 
     info!(max_connections = %pg.pg_max_connections, "Postgres pool created successfully");
-    Ok(pool)
+    Ok(pool.into())
 }
 
-async fn get_schema_version(pool: &PgPool) -> Result<i32> {
-    debug!("Fetching current database schema version");
-    let client = pool.get().await?;
-    client.execute(CREATE_GET_SCHEMA_FUNCTION, &[]).await?;
-    let schema_version = client.query_one("SELECT get_schema_version()", &[]).await?;
-    let schema_version: i32 = schema_version.get(0);
-    debug!(schema_version = %schema_version, "Current schema version retrieved");
-    Ok(schema_version)
+pub async fn connect(params: &DatabaseParams, pg: Option<&PgParams>) -> anyhow::Result<Database> {
+    match params.database_backend {
+        DatabaseBackendKind::Postgres => {
+            let Some(pg) = pg else {
+                anyhow::bail!("PG_* settings are required with the postgres database backend");
+            };
+            Ok(Database::Postgres(get_pool(pg.clone()).await?))
+        }
+        DatabaseBackendKind::Sqlite => {
+            let Some(path) = &params.sqlite_path else {
+                anyhow::bail!("SQLITE_PATH is required with the sqlite database backend");
+            };
+            Ok(Database::Sqlite(SqlitePool::open(path)?))
+        }
+    }
 }
 
-pub async fn run_migrations(pool: &PgPool) -> Result<()> {
+pub async fn run_migrations(pool: &Database) -> Result<()> {
+    let pool = dispatch!(pool, |db| sqlite::run_migrations(db));
     info!("Starting database migrations check");
-    let mut migrations_applied = 0;
+    let mut client = pool.get().await?;
+    client
+        .execute("SELECT pg_advisory_lock($1)", &[&MIGRATION_LOCK_KEY])
+        .await?;
+    let migrated = apply_migrations(&mut client).await;
+    client
+        .execute("SELECT pg_advisory_unlock($1)", &[&MIGRATION_LOCK_KEY])
+        .await?;
+    migrated?;
 
-    for (version, statement) in SCHEMAS {
-        let schema_version: i32 = get_schema_version(pool).await?;
-        if version > schema_version {
-            info!(
-                from_version = %schema_version,
-                to_version = %version,
-                "Applying migration"
-            );
-            let start = std::time::Instant::now();
-            pool.get().await?.batch_execute(statement).await?;
-            let elapsed = start.elapsed();
-            info!(
-                version = %version,
-                elapsed = ?elapsed,
-                "Migration applied successfully"
-            );
-            migrations_applied += 1;
-        } else {
-            debug!(version = %version, "Migration already applied, skipping");
-        }
+    let schema_version: i32 = client
+        .query_one("SELECT COALESCE(MAX(version), 0) FROM schema_version", &[])
+        .await?
+        .get(0);
+    drop(client);
+    if schema_version >= 7 {
+        bookmark::ensure_canonical_url_support(pool).await?;
     }
 
-    if migrations_applied > 0 {
-        info!(migrations_applied = %migrations_applied, "Applied migrations successfully");
+    Ok(())
+}
+
+async fn apply_migrations(client: &mut PgConnection) -> Result<()> {
+    // A new database gets the table from migration 1
+    let mut has_schema_version: bool = client
+        .query_one("SELECT to_regclass('schema_version') IS NOT NULL", &[])
+        .await?
+        .get(0);
+    let applied = if has_schema_version {
+        client.batch_execute(ADD_SCHEMA_VERSION_CHECKSUM).await?;
+        client
+            .query("SELECT version, checksum FROM schema_version", &[])
+            .await?
+            .iter()
+            .map(|row| (row.get("version"), row.get("checksum")))
+            .collect()
     } else {
-        info!("Database schema is up to date");
-    }
+        BTreeMap::new()
+    };
+    let plan = migration::plan(&MIGRATIONS, &applied)?;
 
-    if get_schema_version(pool).await? >= 7 {
-        bookmark::ensure_canonical_url_support(pool).await?;
+    for migration in &plan.backfill {
+        debug!(
+            version = migration.version,
+            "Recording checksum of applied migration"
+        );
+        client
+            .execute(
+                RECORD_MIGRATION,
+                &[&migration.version, &migration.name, &migration.checksum()],
+            )
+            .await?;
     }
 
+    if plan.pending.is_empty() {
+        info!("Database schema is up to date");
+        return Ok(());
+    }
+    for migration in &plan.pending {
+        info!(
+            version = migration.version,
+            name = migration.name,
+            "Applying migration"
+        );
+        let start = std::time::Instant::now();
+        let tx = client.transaction().await?;
+        tx.batch_execute(migration.sql).await?;
+        if !has_schema_version {
+            tx.batch_execute(ADD_SCHEMA_VERSION_CHECKSUM).await?;
+            has_schema_version = true;
+        }
+        tx.execute(
+            RECORD_MIGRATION,
+            &[&migration.version, &migration.name, &migration.checksum()],
+        )
+        .await?;
+        tx.commit().await?;
+        info!(
+            version = migration.version,
+            elapsed = ?start.elapsed(),
+            "Migration applied successfully"
+        );
+    }
+    info!(
+        migrations_applied = plan.pending.len(),
+        "Applied migrations successfully"
+    );
     Ok(())
 }
 
 pub async fn reconcile_embedding_profile(
-    pool: &PgPool,
+    db: &Database,
     target: &EmbeddingProfile,
 ) -> anyhow::Result<()> {
+    let pool = dispatch!(db, |db| sqlite::reconcile_embedding_profile(db, target));
     let client = pool.get().await?;
     let stored = get_embedding_profile(&client).await?;
     let chunk_count = bookmark_chunk_count(&client).await?;
@@ -218,10 +332,12 @@ pub async fn reconcile_embedding_profile(
                 new_dimensions = target.dimensions,
                 "Embedding profile changed, clearing chunk embeddings"
             );
-            client.execute("TRUNCATE bookmark_chunk", &[]).await?;
+            client
+                .batch_execute("TRUNCATE bookmark_chunk; UPDATE bookmark SET embedding = NULL;")
+                .await?;
             upsert_embedding_profile(&client, target).await?;
             drop(client);
-            ai::reset_embedding_generation_state(pool, EMBEDDING_PIPELINE_VERSION).await?;
+            ai::reset_embedding_generation_state(db, EMBEDDING_PIPELINE_VERSION).await?;
             let client = pool.get().await?;
             ensure_embedding_index(&client, target.dimensions).await?;
             return Ok(());
@@ -234,10 +350,12 @@ pub async fn reconcile_embedding_profile(
                 dimensions = target.dimensions,
                 "Embedding profile metadata missing for existing chunks, clearing chunk embeddings"
             );
-            client.execute("TRUNCATE bookmark_chunk", &[]).await?;
+            client
+                .batch_execute("TRUNCATE bookmark_chunk; UPDATE bookmark SET embedding = NULL;")
+                .await?;
             upsert_embedding_profile(&client, target).await?;
             drop(client);
-            ai::reset_embedding_generation_state(pool, EMBEDDING_PIPELINE_VERSION).await?;
+            ai::reset_embedding_generation_state(db, EMBEDDING_PIPELINE_VERSION).await?;
             let client = pool.get().await?;
             ensure_embedding_index(&client, target.dimensions).await?;
             return Ok(());
@@ -257,6 +375,68 @@ pub async fn reconcile_embedding_profile(
     Ok(())
 }
 
+/// Fails when the stored chunk embeddings were not produced by `expected`, so
+/// vectors from different models are never written or compared together.
+/// Reconciliation at startup re-embeds after a model change; this guards
+/// against a second instance running with another configuration.
+pub async fn ensure_embedding_profile(
+    pool: &Database,
+    expected: &EmbeddingProfile,
+) -> anyhow::Result<()> {
+    match stored_embedding_profile(pool).await? {
+        Some(stored) if stored == *expected => Ok(()),
+        Some(stored) => anyhow::bail!(
+            "Stored embeddings use {}/{} ({} dimensions) but this instance is configured for {}/{} ({} dimensions)",
+            stored.provider,
+            stored.model,
+            stored.dimensions,
+            expected.provider,
+            expected.model,
+            expected.dimensions
+        ),
+        None => anyhow::bail!("Embedding profile has not been initialized"),
+    }
+}
+
+/// Provider, model and dimensions of the stored chunk embeddings, `None`
+/// before any embedding profile was recorded.
+pub async fn stored_embedding_profile(pool: &Database) -> anyhow::Result<Option<EmbeddingProfile>> {
+    let pool = dispatch!(pool, |db| sqlite::stored_embedding_profile(db));
+    let client = pool.get().await?;
+    get_embedding_profile(&client).await
+}
+
+/// Records `profile` for embeddings copied from another instance, e.g. a
+/// restored backup. Startup reconciliation re-embeds them if this instance
+/// is configured for another model.
+pub async fn adopt_embedding_profile(
+    pool: &Database,
+    profile: &EmbeddingProfile,
+) -> anyhow::Result<()> {
+    let pool = dispatch!(pool, |db| sqlite::adopt_embedding_profile(db, profile));
+    let client = pool.get().await?;
+    upsert_embedding_profile(&client, profile).await?;
+    ensure_embedding_index(&client, profile.dimensions).await
+}
+
+/// Rebuilds the ANN index of the chunk embeddings, creating it for the
+/// stored profile if it's missing. False when there's nothing to rebuild:
+/// no profile yet, or SQLite, which compares every vector without an index.
+pub async fn rebuild_embedding_index(pool: &Database) -> anyhow::Result<bool> {
+    let Database::Postgres(pool) = pool else {
+        return Ok(false);
+    };
+    let client = pool.get().await?;
+    let Some(profile) = get_embedding_profile(&client).await? else {
+        return Ok(false);
+    };
+    ensure_embedding_index(&client, profile.dimensions).await?;
+    client
+        .batch_execute(&format!("REINDEX INDEX {EMBEDDING_INDEX_NAME}"))
+        .await?;
+    Ok(true)
+}
+
 async fn get_embedding_profile(
     client: &impl GenericClient,
 ) -> anyhow::Result<Option<EmbeddingProfile>> {
@@ -348,9 +528,10 @@ async fn ensure_embedding_index(
     Ok(())
 }
 
-pub async fn run_health_check(pool: &PgPool) -> Result<()> {
+pub async fn run_health_check(pool: &Database) -> Result<()> {
     debug!("Running database health check");
     let start = std::time::Instant::now();
+    let pool = dispatch!(pool, |db| sqlite::run_health_check(db));
     let client = pool.get().await?;
     let _ = client.query_one("SELECT 1", &[]).await?;
     let elapsed = start.elapsed();
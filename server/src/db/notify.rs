@@ -0,0 +1,84 @@
+//! Postgres notifications waking the daemons of every replica. Triggers
+//! NOTIFY the channels below when work is queued, a connection dedicated to
+//! the listener LISTENs to them. Replicas also relay the events of their
+//! event bus to each other through them.
+
+use anyhow::{bail, Result};
+use futures::StreamExt;
+use tokio::sync::mpsc;
+use tokio_postgres::{AsyncMessage, NoTls};
+use tracing::{debug, info, warn};
+
+use super::Database;
+
+/// A bookmark task is pending and due.
+pub const TASK_QUEUED: &str = "bookmark_task_queued";
+/// Bookmarks were saved, their AI stages are pending.
+pub const BOOKMARK_SAVED: &str = "bookmark_saved";
+/// An event of the event bus of a replica, as its payload.
+pub const SERVER_EVENT: &str = "server_event";
+
+/// Sends `payload` to the connections listening on `channel`. SQLite serves a
+/// single replica, there is nobody to notify.
+pub async fn notify(pool: &Database, channel: &str, payload: &str) -> Result<()> {
+    let Database::Postgres(pool) = pool else {
+        return Ok(());
+    };
+    let client = pool.get().await?;
+    client
+        .execute("SELECT pg_notify($1, $2)", &[&channel, &payload])
+        .await?;
+    Ok(())
+}
+
+/// Calls `on_notify` with the channel and payload of each notification
+/// received on `channels`, and once per channel without a payload as soon as
+/// it listens, to catch up with what was sent while not connected. Returns
+/// when the connection drops.
+pub async fn listen(
+    config: &tokio_postgres::Config,
+    channels: &[&str],
+    mut on_notify: impl FnMut(&str, Option<&str>),
+) -> Result<()> {
+    let (client, mut connection) = config.connect(NoTls).await?;
+    let (notifications_tx, mut notifications_rx) = mpsc::unbounded_channel();
+    // Notifications only come through the connection, which must be polled
+    // for the client's statements to complete too
+    let connection = tokio::spawn(async move {
+        let mut messages = futures::stream::poll_fn(move |cx| connection.poll_message(cx));
+        while let Some(message) = messages.next().await {
+            match message {
+                Ok(AsyncMessage::Notification(notification)) => {
+                    if notifications_tx.send(notification).is_err() {
+                        break;
+                    }
+                }
+                Ok(message) => debug!(?message, "Ignoring postgres message"),
+                Err(error) => {
+                    warn!(?error, "Notification connection failed");
+                    break;
+                }
+            }
+        }
+    });
+
+    let statements: String = channels
+        .iter()
+        .map(|channel| format!("LISTEN {channel};"))
+        .collect();
+    client.batch_execute(&statements).await?;
+    info!(?channels, "Listening for postgres notifications");
+    for channel in channels {
+        on_notify(channel, None);
+    }
+
+    while let Some(notification) = notifications_rx.recv().await {
+        debug!(
+            channel = notification.channel(),
+            "Postgres notification received"
+        );
+        on_notify(notification.channel(), Some(notification.payload()));
+    }
+    connection.abort();
+    bail!("Notification connection closed")
+}
@@ -0,0 +1,244 @@
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+
+use deadpool_postgres::{Object, Pool, PoolError, Transaction};
+use tokio_postgres::types::ToSql;
+use tokio_postgres::{Error, Row, Statement, ToStatement};
+use tracing::{info_span, Instrument, Span};
+
+/// The Postgres pool. Its connections and transactions trace every
+/// statement they run, so each query gets a span without marking the query
+/// functions one by one.
+#[derive(Debug, Clone)]
+pub struct PgPool(Pool);
+
+impl From<Pool> for PgPool {
+    fn from(pool: Pool) -> Self {
+        Self(pool)
+    }
+}
+
+impl PgPool {
+    pub async fn get(&self) -> Result<PgConnection, PoolError> {
+        self.0
+            .get()
+            .instrument(info_span!("db.pool.get", db.system = "postgresql"))
+            .await
+            .map(PgConnection)
+    }
+}
+
+/// A pooled connection, back to the pool when dropped.
+#[derive(Debug)]
+pub struct PgConnection(Object);
+
+impl Deref for PgConnection {
+    type Target = Object;
+
+    fn deref(&self) -> &Object {
+        &self.0
+    }
+}
+
+impl DerefMut for PgConnection {
+    fn deref_mut(&mut self) -> &mut Object {
+        &mut self.0
+    }
+}
+
+/// Rolled back when dropped without a commit.
+pub struct PgTransaction<'a>(Transaction<'a>);
+
+impl<'a> Deref for PgTransaction<'a> {
+    type Target = Transaction<'a>;
+
+    fn deref(&self) -> &Transaction<'a> {
+        &self.0
+    }
+}
+
+impl PgConnection {
+    pub async fn transaction(&mut self) -> Result<PgTransaction<'_>, Error> {
+        self.0
+            .transaction()
+            .instrument(statement_span(Some("BEGIN")))
+            .await
+            .map(PgTransaction)
+    }
+}
+
+impl PgTransaction<'_> {
+    pub async fn commit(self) -> Result<(), Error> {
+        self.0
+            .commit()
+            .instrument(statement_span(Some("COMMIT")))
+            .await
+    }
+}
+
+/// Statement argument of the traced methods, the SQL text goes in the span
+/// unless the statement was prepared.
+pub trait Query: ToStatement + Sync {
+    fn sql(&self) -> Option<&str>;
+}
+
+impl Query for str {
+    fn sql(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl Query for String {
+    fn sql(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl Query for Statement {
+    fn sql(&self) -> Option<&str> {
+        None
+    }
+}
+
+fn statement_span(sql: Option<&str>) -> Span {
+    info_span!(
+        "db.query",
+        db.system = "postgresql",
+        db.statement = sql.map(str::trim)
+    )
+}
+
+/// Like deadpool's `GenericClient`, which can't be implemented outside of
+/// it, for the traced connection and transaction.
+pub trait GenericClient: Sync {
+    fn query<T: Query + ?Sized>(
+        &self,
+        statement: &T,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> impl Future<Output = Result<Vec<Row>, Error>> + Send;
+
+    fn query_one<T: Query + ?Sized>(
+        &self,
+        statement: &T,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> impl Future<Output = Result<Row, Error>> + Send;
+
+    fn query_opt<T: Query + ?Sized>(
+        &self,
+        statement: &T,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> impl Future<Output = Result<Option<Row>, Error>> + Send;
+
+    fn execute<T: Query + ?Sized>(
+        &self,
+        statement: &T,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> impl Future<Output = Result<u64, Error>> + Send;
+
+    fn batch_execute(&self, sql: &str) -> impl Future<Output = Result<(), Error>> + Send;
+}
+
+macro_rules! traced_client {
+    ($client:ty) => {
+        impl $client {
+            pub async fn query<T: Query + ?Sized>(
+                &self,
+                statement: &T,
+                params: &[&(dyn ToSql + Sync)],
+            ) -> Result<Vec<Row>, Error> {
+                self.0
+                    .query(statement, params)
+                    .instrument(statement_span(statement.sql()))
+                    .await
+            }
+
+            pub async fn query_one<T: Query + ?Sized>(
+                &self,
+                statement: &T,
+                params: &[&(dyn ToSql + Sync)],
+            ) -> Result<Row, Error> {
+                self.0
+                    .query_one(statement, params)
+                    .instrument(statement_span(statement.sql()))
+                    .await
+            }
+
+            pub async fn query_opt<T: Query + ?Sized>(
+                &self,
+                statement: &T,
+                params: &[&(dyn ToSql + Sync)],
+            ) -> Result<Option<Row>, Error> {
+                self.0
+                    .query_opt(statement, params)
+                    .instrument(statement_span(statement.sql()))
+                    .await
+            }
+
+            pub async fn execute<T: Query + ?Sized>(
+                &self,
+                statement: &T,
+                params: &[&(dyn ToSql + Sync)],
+            ) -> Result<u64, Error> {
+                self.0
+                    .execute(statement, params)
+                    .instrument(statement_span(statement.sql()))
+                    .await
+            }
+
+            pub async fn batch_execute(&self, sql: &str) -> Result<(), Error> {
+                self.0
+                    .batch_execute(sql)
+                    .instrument(statement_span(Some(sql)))
+                    .await
+            }
+
+            pub async fn prepare(&self, sql: &str) -> Result<Statement, Error> {
+                self.0
+                    .prepare(sql)
+                    .instrument(statement_span(Some(sql)))
+                    .await
+            }
+        }
+
+        impl GenericClient for $client {
+            fn query<T: Query + ?Sized>(
+                &self,
+                statement: &T,
+                params: &[&(dyn ToSql + Sync)],
+            ) -> impl Future<Output = Result<Vec<Row>, Error>> + Send {
+                <$client>::query(self, statement, params)
+            }
+
+            fn query_one<T: Query + ?Sized>(
+                &self,
+                statement: &T,
+                params: &[&(dyn ToSql + Sync)],
+            ) -> impl Future<Output = Result<Row, Error>> + Send {
+                <$client>::query_one(self, statement, params)
+            }
+
+            fn query_opt<T: Query + ?Sized>(
+                &self,
+                statement: &T,
+                params: &[&(dyn ToSql + Sync)],
+            ) -> impl Future<Output = Result<Option<Row>, Error>> + Send {
+                <$client>::query_opt(self, statement, params)
+            }
+
+            fn execute<T: Query + ?Sized>(
+                &self,
+                statement: &T,
+                params: &[&(dyn ToSql + Sync)],
+            ) -> impl Future<Output = Result<u64, Error>> + Send {
+                <$client>::execute(self, statement, params)
+            }
+
+            fn batch_execute(&self, sql: &str) -> impl Future<Output = Result<(), Error>> + Send {
+                <$client>::batch_execute(self, sql)
+            }
+        }
+    };
+}
+
+traced_client!(PgConnection);
+traced_client!(PgTransaction<'_>);
@@ -1,27 +1,44 @@
 use std::collections::HashMap;
 
-use chrono::{DateTime, Utc};
-use deadpool_postgres::GenericClient;
+use chrono::{DateTime, NaiveDate, Utc};
 use postgres_from_row::FromRow;
 use shared::{
-    RagChunkInfo, RagHistoryRequest, RagHistoryResponse, RagSession, RagSessionWithSources,
+    RagChunkInfo, RagFeedback, RagFeedbackComment, RagFeedbackRequest, RagHistoryRequest,
+    RagHistoryResponse, RagQualityDay, RagQualityReport, RagSession, RagSessionWithSources,
 };
 use tracing::debug;
 use uuid::Uuid;
 
 use super::chunks::get_chunks_with_bookmarks_by_ids;
-use super::PgPool;
+use super::{dispatch, sqlite, Database, PgPool};
 use crate::error::{Error, Result};
 
 #[derive(Debug, FromRow)]
 struct RowRagSession {
     session_id: Uuid,
     user_id: Uuid,
+    title: Option<String>,
     question: String,
     answer: Option<String>,
     relevant_chunks: Vec<Uuid>,
     created_at: DateTime<Utc>,
     updated_at: Option<DateTime<Utc>>,
+    feedback_rating: Option<String>,
+    feedback_comment: Option<String>,
+    feedback_at: Option<DateTime<Utc>>,
+}
+
+/// The feedback stored with a session, when it was rated.
+pub(in crate::db) fn feedback(
+    rating: Option<String>,
+    comment: Option<String>,
+    rated_at: Option<DateTime<Utc>>,
+) -> Option<RagFeedback> {
+    Some(RagFeedback {
+        rating: rating?.parse().ok()?,
+        comment,
+        rated_at: rated_at?,
+    })
 }
 
 impl From<RowRagSession> for RagSession {
@@ -29,20 +46,25 @@ impl From<RowRagSession> for RagSession {
         Self {
             session_id: row.session_id,
             user_id: row.user_id,
+            title: row.title,
             question: row.question,
             answer: row.answer,
             relevant_chunks: row.relevant_chunks,
             created_at: row.created_at,
             updated_at: row.updated_at,
+            feedback: feedback(row.feedback_rating, row.feedback_comment, row.feedback_at),
         }
     }
 }
 
 pub async fn create_rag_session(
-    pool: &PgPool,
+    pool: &Database,
     user_id: Uuid,
     question: &str,
 ) -> Result<RagSession> {
+    let pool = dispatch!(pool, |db| sqlite::rag::create_rag_session(
+        db, user_id, question
+    ));
     let client = pool.get().await?;
 
     let row = client
@@ -50,7 +72,8 @@ pub async fn create_rag_session(
             r#"
             INSERT INTO rag_session (user_id, question)
             VALUES ($1, $2)
-            RETURNING session_id, user_id, question, answer, relevant_chunks, created_at, updated_at
+            RETURNING session_id, user_id, title, question, answer, relevant_chunks, created_at, updated_at,
+                feedback_rating, feedback_comment, feedback_at
             "#,
             &[&user_id, &question],
         )
@@ -67,12 +90,19 @@ pub async fn create_rag_session(
 }
 
 pub async fn update_rag_session(
-    pool: &PgPool,
+    pool: &Database,
     session_id: Uuid,
     user_id: Uuid,
     answer: &str,
     relevant_chunk_ids: &[Uuid],
 ) -> Result<RagSession> {
+    let pool = dispatch!(pool, |db| sqlite::rag::update_rag_session(
+        db,
+        session_id,
+        user_id,
+        answer,
+        relevant_chunk_ids,
+    ));
     let client = pool.get().await?;
 
     let row = client
@@ -81,7 +111,8 @@ pub async fn update_rag_session(
             UPDATE rag_session 
             SET answer = $3, relevant_chunks = $4, updated_at = NOW()
             WHERE session_id = $1 AND user_id = $2
-            RETURNING session_id, user_id, question, answer, relevant_chunks, created_at, updated_at
+            RETURNING session_id, user_id, title, question, answer, relevant_chunks, created_at, updated_at,
+                feedback_rating, feedback_comment, feedback_at
             "#,
             &[&session_id, &user_id, &answer, &relevant_chunk_ids],
         )
@@ -99,16 +130,20 @@ pub async fn update_rag_session(
 }
 
 pub async fn get_rag_session(
-    pool: &PgPool,
+    pool: &Database,
     session_id: Uuid,
     user_id: Uuid,
 ) -> Result<Option<RagSession>> {
+    let pool = dispatch!(pool, |db| sqlite::rag::get_rag_session(
+        db, session_id, user_id
+    ));
     let client = pool.get().await?;
 
     let rows = client
         .query(
             r#"
-            SELECT session_id, user_id, question, answer, relevant_chunks, created_at, updated_at
+            SELECT session_id, user_id, title, question, answer, relevant_chunks, created_at, updated_at,
+                feedback_rating, feedback_comment, feedback_at
             FROM rag_session 
             WHERE session_id = $1 AND user_id = $2
             "#,
@@ -124,21 +159,200 @@ pub async fn get_rag_session(
     }
 }
 
-pub async fn get_rag_history(
-    pool: &PgPool,
+/// Names a session, once its title is generated.
+pub async fn set_rag_session_title(
+    pool: &Database,
+    session_id: Uuid,
     user_id: Uuid,
-    request: &RagHistoryRequest,
-) -> Result<RagHistoryResponse> {
+    title: &str,
+) -> Result<()> {
+    let pool = dispatch!(pool, |db| sqlite::rag::set_rag_session_title(
+        db, session_id, user_id, title
+    ));
     let client = pool.get().await?;
+    client
+        .execute(
+            "UPDATE rag_session SET title = $3 WHERE session_id = $1 AND user_id = $2",
+            &[&session_id, &user_id, &title],
+        )
+        .await?;
+    Ok(())
+}
 
-    let limit = request.limit.unwrap_or(20).min(100) as i64;
-    let offset = request.offset.unwrap_or(0) as i64;
+/// Deletes a session, returning whether the user had it.
+pub async fn delete_rag_session(pool: &Database, session_id: Uuid, user_id: Uuid) -> Result<bool> {
+    let pool = dispatch!(pool, |db| sqlite::rag::delete_rag_session(
+        db, session_id, user_id
+    ));
+    let client = pool.get().await?;
+    let deleted = client
+        .execute(
+            "DELETE FROM rag_session WHERE session_id = $1 AND user_id = $2",
+            &[&session_id, &user_id],
+        )
+        .await?;
+    debug!(session_id = %session_id, user_id = %user_id, deleted, "Deleted RAG session");
+    Ok(deleted > 0)
+}
+
+/// Deletes up to `limit` sessions of any user asked before `created_before`.
+/// Returns how many went.
+pub async fn delete_sessions_before(
+    pool: &Database,
+    created_before: DateTime<Utc>,
+    limit: i64,
+) -> Result<u64> {
+    const SQL: &str = r#"
+        DELETE FROM rag_session WHERE session_id IN (
+            SELECT session_id FROM rag_session WHERE created_at < $1 LIMIT $2
+        );"#;
+    let pool = dispatch!(pool, |db| sqlite::rag::delete_sessions_before(
+        db,
+        created_before,
+        limit
+    ));
+    let client = pool.get().await?;
+    Ok(client.execute(SQL, &[&created_before, &limit]).await?)
+}
+
+/// Rates the answer of a session, replacing an earlier rating, `None` when
+/// the user has no such session.
+pub async fn set_feedback(
+    pool: &Database,
+    session_id: Uuid,
+    user_id: Uuid,
+    request: &RagFeedbackRequest,
+) -> Result<Option<RagSession>> {
+    let pool = dispatch!(pool, |db| sqlite::rag::set_feedback(
+        db, session_id, user_id, request
+    ));
+    let client = pool.get().await?;
+
+    let row = client
+        .query_opt(
+            r#"
+            UPDATE rag_session
+            SET feedback_rating = $3, feedback_comment = $4, feedback_at = NOW()
+            WHERE session_id = $1 AND user_id = $2
+            RETURNING session_id, user_id, title, question, answer, relevant_chunks, created_at, updated_at,
+                feedback_rating, feedback_comment, feedback_at
+            "#,
+            &[
+                &session_id,
+                &user_id,
+                &request.rating.as_ref(),
+                &request.comment,
+            ],
+        )
+        .await?;
+
+    row.map(|row| {
+        let session_row = RowRagSession::try_from_row(&row).map_err(Error::from)?;
+        Ok(RagSession::from(session_row))
+    })
+    .transpose()
+}
+
+/// Answers and ratings of the sessions started since `since`, by day, with the
+/// latest `comment_limit` comments.
+pub async fn quality_report(
+    pool: &Database,
+    user_id: Uuid,
+    since: NaiveDate,
+    comment_limit: i64,
+) -> Result<RagQualityReport> {
+    const DAYS_SQL: &str = r#"
+        SELECT
+            (created_at AT TIME ZONE 'UTC')::date AS day,
+            COUNT(*) FILTER (WHERE answer IS NOT NULL) AS answered,
+            COUNT(*) FILTER (WHERE feedback_rating = 'up') AS rated_up,
+            COUNT(*) FILTER (WHERE feedback_rating = 'down') AS rated_down
+        FROM rag_session
+        WHERE user_id = $1 AND (created_at AT TIME ZONE 'UTC')::date >= $2
+        GROUP BY day
+        ORDER BY day DESC;"#;
+    const COMMENTS_SQL: &str = r#"
+        SELECT session_id, question, feedback_rating, feedback_comment, feedback_at
+        FROM rag_session
+        WHERE user_id = $1 AND (created_at AT TIME ZONE 'UTC')::date >= $2
+            AND feedback_comment IS NOT NULL
+        ORDER BY feedback_at DESC
+        LIMIT $3;"#;
+    let pool = match pool {
+        Database::Postgres(pool) => pool,
+        Database::Sqlite(db) => {
+            let (days, comments) =
+                sqlite::rag::quality_report(db, user_id, since, comment_limit).await?;
+            return Ok(summarize_quality(days, comments));
+        }
+    };
+    let client = pool.get().await?;
+    let days = client
+        .query(DAYS_SQL, &[&user_id, &since])
+        .await?
+        .iter()
+        .map(|row| RagQualityDay {
+            day: row.get("day"),
+            answered: row.get("answered"),
+            rated_up: row.get("rated_up"),
+            rated_down: row.get("rated_down"),
+        })
+        .collect();
+    let comments = client
+        .query(COMMENTS_SQL, &[&user_id, &since, &comment_limit])
+        .await?
+        .iter()
+        .filter_map(|row| {
+            Some(RagFeedbackComment {
+                session_id: row.get("session_id"),
+                question: row.get("question"),
+                rating: row.get::<_, String>("feedback_rating").parse().ok()?,
+                comment: row.get("feedback_comment"),
+                rated_at: row.get("feedback_at"),
+            })
+        })
+        .collect();
+    Ok(summarize_quality(days, comments))
+}
+
+fn summarize_quality(
+    days: Vec<RagQualityDay>,
+    comments: Vec<RagFeedbackComment>,
+) -> RagQualityReport {
+    let answered = days.iter().map(|day| day.answered).sum();
+    let rated_up: i64 = days.iter().map(|day| day.rated_up).sum();
+    let rated_down: i64 = days.iter().map(|day| day.rated_down).sum();
+    let rated = rated_up + rated_down;
+    RagQualityReport {
+        days,
+        answered,
+        rated_up,
+        rated_down,
+        approval_rate: (rated > 0).then(|| rated_up as f64 / rated as f64),
+        comments,
+    }
+}
+
+/// A page of sessions, newest first, with the total count of the user,
+/// only those matching `query` when given.
+async fn get_rag_sessions(
+    pool: &PgPool,
+    user_id: Uuid,
+    query: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<RagSession>, i64)> {
+    let client = pool.get().await?;
 
     // Get total count
     let count_row = client
         .query_one(
-            "SELECT COUNT(*) FROM rag_session WHERE user_id = $1",
-            &[&user_id],
+            r#"
+            SELECT COUNT(*) FROM rag_session
+            WHERE user_id = $1
+                AND ($2::TEXT IS NULL OR search_tokens @@ websearch_to_tsquery('simple', $2))
+            "#,
+            &[&user_id, &query],
         )
         .await?;
     let total_count: i64 = count_row.get(0);
@@ -147,13 +361,15 @@ pub async fn get_rag_history(
     let rows = client
         .query(
             r#"
-            SELECT session_id, user_id, question, answer, relevant_chunks, created_at, updated_at
+            SELECT session_id, user_id, title, question, answer, relevant_chunks, created_at, updated_at,
+                feedback_rating, feedback_comment, feedback_at
             FROM rag_session
             WHERE user_id = $1
+                AND ($2::TEXT IS NULL OR search_tokens @@ websearch_to_tsquery('simple', $2))
             ORDER BY created_at DESC
-            LIMIT $2 OFFSET $3
+            LIMIT $3 OFFSET $4
             "#,
-            &[&user_id, &limit, &offset],
+            &[&user_id, &query, &limit, &offset],
         )
         .await?;
 
@@ -165,7 +381,28 @@ pub async fn get_rag_history(
         })
         .collect();
 
-    let sessions = sessions?;
+    Ok((sessions?, total_count))
+}
+
+pub async fn get_rag_history(
+    pool: &Database,
+    user_id: Uuid,
+    request: &RagHistoryRequest,
+) -> Result<RagHistoryResponse> {
+    let limit = request.limit.unwrap_or(20).min(100) as i64;
+    let offset = request.offset.unwrap_or(0) as i64;
+    let query = request
+        .query
+        .as_deref()
+        .map(str::trim)
+        .filter(|query| !query.is_empty());
+
+    let (sessions, total_count) = match pool {
+        Database::Postgres(pg) => get_rag_sessions(pg, user_id, query, limit, offset).await?,
+        Database::Sqlite(db) => {
+            sqlite::rag::get_rag_sessions(db, user_id, query, limit, offset).await?
+        }
+    };
 
     // Collect all chunk IDs across all sessions
     let all_chunk_ids: Vec<Uuid> = sessions
@@ -203,11 +440,13 @@ pub async fn get_rag_history(
             RagSessionWithSources {
                 session_id: session.session_id,
                 user_id: session.user_id,
+                title: session.title,
                 question: session.question,
                 answer: session.answer,
                 sources,
                 created_at: session.created_at,
                 updated_at: session.updated_at,
+                feedback: session.feedback,
             }
         })
         .collect();
@@ -0,0 +1,57 @@
+use uuid::Uuid;
+
+use super::{dispatch, sqlite, Database};
+use crate::error::Result;
+
+/// Recomputes the full-text search tokens of the bookmarks of a user and of
+/// their chunks, returning how many bookmarks were indexed again.
+pub async fn rebuild_search_tokens(pool: &Database, user_id: Uuid) -> Result<u64> {
+    let pool = dispatch!(pool, |db| sqlite::reindex::rebuild_search_tokens(
+        db, user_id
+    ));
+    let mut client = pool.get().await?;
+    let tx = client.transaction().await?;
+    // The tokens are set by triggers on update
+    let bookmarks = tx
+        .execute(
+            "UPDATE bookmark SET text_search_config = text_search_config WHERE user_id = $1",
+            &[&user_id],
+        )
+        .await?;
+    tx.execute(
+        "UPDATE bookmark_chunk SET chunk_text = chunk_text WHERE user_id = $1",
+        &[&user_id],
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(bookmarks)
+}
+
+/// Queues for the embedding daemon the bookmarks of a user whose embedding
+/// failed or that have chunks without a vector, returning how many.
+pub async fn queue_missing_embeddings(pool: &Database, user_id: Uuid) -> Result<u64> {
+    let pool = dispatch!(pool, |db| sqlite::reindex::queue_missing_embeddings(
+        db, user_id
+    ));
+    Ok(pool
+        .get()
+        .await?
+        .execute(
+            "UPDATE bookmark b
+             SET embedding_status = 'pending',
+                 embedding_attempts = 0,
+                 embedding_next_attempt_at = now(),
+                 embedding_fail_reason = NULL
+             WHERE b.user_id = $1
+               AND b.deleted_at IS NULL
+               AND (b.embedding_status = 'fail' OR (
+                   b.embedding_status = 'done' AND EXISTS (
+                       SELECT 1 FROM bookmark_chunk c
+                       WHERE c.bookmark_id = b.bookmark_id AND c.user_id = b.user_id
+                         AND (c.embedding IS NULL OR b.embedding IS NULL)
+                   )
+               ))",
+            &[&user_id],
+        )
+        .await?)
+}
@@ -0,0 +1,113 @@
+use shared::{Rule, RuleActions, RuleConditions, RuleRequest};
+use tokio_postgres::types::Json;
+use tokio_postgres::Row;
+use tracing::debug;
+use uuid::Uuid;
+
+use super::{dispatch, sqlite, Database};
+use crate::error::Result;
+
+const SELECT: &str =
+    "SELECT rule_id, name, enabled, conditions, actions, created_at, updated_at FROM rule";
+
+pub(super) fn from_row(row: &Row) -> Rule {
+    Rule {
+        rule_id: row.get("rule_id"),
+        name: row.get("name"),
+        enabled: row.get("enabled"),
+        conditions: row.get::<_, Json<RuleConditions>>("conditions").0,
+        actions: row.get::<_, Json<RuleActions>>("actions").0,
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+/// Rules of the user, oldest first.
+pub async fn list(pool: &Database, user_id: Uuid) -> Result<Vec<Rule>> {
+    let pool = dispatch!(pool, |db| sqlite::rule::list(db, user_id));
+    let client = pool.get().await?;
+    let rows = client
+        .query(
+            &format!("{SELECT} WHERE user_id = $1 ORDER BY created_at"),
+            &[&user_id],
+        )
+        .await?;
+    Ok(rows.iter().map(from_row).collect())
+}
+
+pub async fn get(pool: &Database, user_id: Uuid, rule_id: Uuid) -> Result<Option<Rule>> {
+    let pool = dispatch!(pool, |db| sqlite::rule::get(db, user_id, rule_id));
+    let client = pool.get().await?;
+    let row = client
+        .query_opt(
+            &format!("{SELECT} WHERE rule_id = $1 AND user_id = $2"),
+            &[&rule_id, &user_id],
+        )
+        .await?;
+    Ok(row.as_ref().map(from_row))
+}
+
+pub async fn create(pool: &Database, user_id: Uuid, request: &RuleRequest) -> Result<Rule> {
+    let pool = dispatch!(pool, |db| sqlite::rule::create(db, user_id, request));
+    let client = pool.get().await?;
+    let row = client
+        .query_one(
+            "INSERT INTO rule (user_id, name, enabled, conditions, actions)
+             VALUES ($1, $2, $3, $4, $5)
+             RETURNING rule_id, name, enabled, conditions, actions, created_at, updated_at",
+            &[
+                &user_id,
+                &request.name,
+                &request.enabled.unwrap_or(true),
+                &Json(&request.conditions),
+                &Json(&request.actions),
+            ],
+        )
+        .await?;
+    let rule = from_row(&row);
+    debug!(%user_id, rule_id = %rule.rule_id, "Rule created");
+    Ok(rule)
+}
+
+/// Replaces all of the rule, `None` when the user has no such rule.
+pub async fn update(
+    pool: &Database,
+    user_id: Uuid,
+    rule_id: Uuid,
+    request: &RuleRequest,
+) -> Result<Option<Rule>> {
+    let pool = dispatch!(pool, |db| sqlite::rule::update(
+        db, user_id, rule_id, request
+    ));
+    let client = pool.get().await?;
+    let row = client
+        .query_opt(
+            "UPDATE rule
+             SET name = $3, enabled = $4, conditions = $5, actions = $6, updated_at = now()
+             WHERE rule_id = $1 AND user_id = $2
+             RETURNING rule_id, name, enabled, conditions, actions, created_at, updated_at",
+            &[
+                &rule_id,
+                &user_id,
+                &request.name,
+                &request.enabled.unwrap_or(true),
+                &Json(&request.conditions),
+                &Json(&request.actions),
+            ],
+        )
+        .await?;
+    Ok(row.as_ref().map(from_row))
+}
+
+/// False when the user has no such rule.
+pub async fn delete(pool: &Database, user_id: Uuid, rule_id: Uuid) -> Result<bool> {
+    let pool = dispatch!(pool, |db| sqlite::rule::delete(db, user_id, rule_id));
+    let client = pool.get().await?;
+    let deleted = client
+        .execute(
+            "DELETE FROM rule WHERE rule_id = $1 AND user_id = $2",
+            &[&rule_id, &user_id],
+        )
+        .await?;
+    Ok(deleted > 0)
+}
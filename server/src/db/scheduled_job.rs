@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+
+use super::{dispatch, sqlite, Database};
+use crate::error::Result;
+
+/// Last run of a periodic job.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobRun {
+    pub last_started_at: DateTime<Utc>,
+    /// None while it runs, or when it was interrupted
+    pub last_finished_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+pub async fn last_run(pool: &Database, name: &str) -> Result<Option<JobRun>> {
+    let pool = dispatch!(pool, |db| sqlite::scheduled_job::last_run(db, name));
+    let row = pool
+        .get()
+        .await?
+        .query_opt(
+            "SELECT last_started_at, last_finished_at, last_error
+             FROM scheduled_job WHERE name = $1",
+            &[&name],
+        )
+        .await?;
+    Ok(row.map(|row| JobRun {
+        last_started_at: row.get("last_started_at"),
+        last_finished_at: row.get("last_finished_at"),
+        last_error: row.get("last_error"),
+    }))
+}
+
+/// Records that the job starts at `now`, unless a run already started at or
+/// after `due`, such as on another instance. True when the caller runs it.
+pub async fn claim(
+    pool: &Database,
+    name: &str,
+    due: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<bool> {
+    let pool = dispatch!(pool, |db| sqlite::scheduled_job::claim(db, name, due, now));
+    let claimed = pool
+        .get()
+        .await?
+        .execute(
+            "INSERT INTO scheduled_job (name, last_started_at) VALUES ($1, $3)
+             ON CONFLICT (name) DO UPDATE
+             SET last_started_at = EXCLUDED.last_started_at,
+                 last_finished_at = NULL,
+                 last_error = NULL
+             WHERE scheduled_job.last_started_at < $2",
+            &[&name, &due, &now],
+        )
+        .await?;
+    Ok(claimed > 0)
+}
+
+pub async fn finish(
+    pool: &Database,
+    name: &str,
+    now: DateTime<Utc>,
+    error: Option<&str>,
+) -> Result<()> {
+    let pool = dispatch!(pool, |db| sqlite::scheduled_job::finish(
+        db, name, now, error
+    ));
+    pool.get()
+        .await?
+        .execute(
+            "UPDATE scheduled_job SET last_finished_at = $2, last_error = $3 WHERE name = $1",
+            &[&name, &now, &error],
+        )
+        .await?;
+    Ok(())
+}
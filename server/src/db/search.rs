@@ -1,16 +1,30 @@
-use chrono::{DateTime, Utc};
-use deadpool_postgres::GenericClient;
+use chrono::{DateTime, NaiveDate, Utc};
 use futures::TryFutureExt;
 use postgres_from_row::FromRow;
 use postgres_types::ToSql;
 use serde::{Deserialize, Serialize};
-use shared::{Bookmark, SearchRequest, SearchResponse, SearchResultItem, TagCount, TagFilter};
+use shared::{
+    Bookmark, BookmarkKind, DomainCount, MonthCount, ReadingTime, SearchRequest, SearchResponse,
+    SearchResultItem, SearchSuggestions, TagCount, TagFilter, TitleCount,
+};
 use tokio::try_join;
 use tracing::{debug, warn};
 use uuid::Uuid;
 
-use super::PgPool;
+use super::bookmark::{parse_kind, parse_visibility};
+use super::{dispatch, sqlite, Database, GenericClient};
 use crate::error::{Error, Result};
+use crate::search_query::{self, Field, ParsedQuery};
+
+/// How many domains the facet of a search lists.
+pub(super) const DOMAIN_FACET_SIZE: i64 = 20;
+
+pub const DEFAULT_SNIPPET_COUNT: u32 = 1;
+pub const DEFAULT_SNIPPET_WORDS: u32 = 35;
+
+/// Separates the fragments `ts_headline` returns, a control character that
+/// captured text doesn't contain.
+const FRAGMENT_DELIMITER: char = '\u{1f}';
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 struct RowTagCount {
@@ -18,6 +32,51 @@ struct RowTagCount {
     count: i64,
 }
 
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+struct RowDomainCount {
+    domain: String,
+    count: i64,
+}
+
+impl From<RowDomainCount> for DomainCount {
+    fn from(value: RowDomainCount) -> Self {
+        Self {
+            domain: value.domain,
+            count: value.count,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+struct RowTitleCount {
+    title: String,
+    count: i64,
+}
+
+impl From<RowTitleCount> for TitleCount {
+    fn from(value: RowTitleCount) -> Self {
+        Self {
+            title: value.title,
+            count: value.count,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+struct RowMonthCount {
+    month: NaiveDate,
+    count: i64,
+}
+
+impl From<RowMonthCount> for MonthCount {
+    fn from(value: RowMonthCount) -> Self {
+        Self {
+            month: value.month,
+            count: value.count,
+        }
+    }
+}
+
 impl From<RowTagCount> for TagCount {
     fn from(value: RowTagCount) -> Self {
         Self {
@@ -35,10 +94,18 @@ struct RowSearchResultItem {
     domain: String,
     title: String,
     search_match: Option<String>,
+    title_highlight: Option<String>,
+    summary_highlight: Option<String>,
     tags: Option<Vec<String>>,
     summary: Option<String>,
     created_at: DateTime<Utc>,
     updated_at: Option<DateTime<Utc>>,
+    word_count: i32,
+    reading_time_minutes: i32,
+    kind: Option<String>,
+    favorite: bool,
+    original_title: Option<String>,
+    visibility: String,
 }
 
 impl From<RowSearchResultItem> for Bookmark {
@@ -53,6 +120,12 @@ impl From<RowSearchResultItem> for Bookmark {
             summary: value.summary,
             created_at: value.created_at,
             updated_at: value.updated_at,
+            word_count: value.word_count,
+            reading_time_minutes: value.reading_time_minutes,
+            kind: parse_kind(value.kind),
+            favorite: value.favorite,
+            original_title: value.original_title,
+            visibility: parse_visibility(value.visibility),
         }
     }
 }
@@ -60,58 +133,330 @@ impl From<RowSearchResultItem> for Bookmark {
 impl From<RowSearchResultItem> for SearchResultItem {
     fn from(value: RowSearchResultItem) -> Self {
         Self {
-            search_match: value.search_match.clone(),
+            snippets: value
+                .search_match
+                .as_deref()
+                .map(split_fragments)
+                .unwrap_or_default(),
+            title_highlight: value.title_highlight.clone(),
+            summary_highlight: value.summary_highlight.clone(),
             bookmark: value.into(),
         }
     }
 }
 
+fn split_fragments(headline: &str) -> Vec<String> {
+    headline
+        .split(FRAGMENT_DELIMITER)
+        .map(str::trim)
+        .filter(|fragment| !fragment.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// `ts_headline` options for the text fragments of a search. `MinWords` has
+/// to stay below `MaxWords`.
+fn headline_options(request: &SearchRequest) -> String {
+    let count = request.snippet_count.unwrap_or(DEFAULT_SNIPPET_COUNT);
+    let words = request
+        .snippet_words
+        .unwrap_or(DEFAULT_SNIPPET_WORDS)
+        .max(2);
+    format!(
+        "StartSel=<mark>, StopSel=</mark>, MaxFragments={count}, MaxWords={words}, \
+         MinWords={}, FragmentDelimiter={FRAGMENT_DELIMITER}",
+        words / 2
+    )
+}
+
 pub async fn search(
-    pool: &PgPool,
+    pool: &Database,
     user_id: Uuid,
     request: &SearchRequest,
 ) -> Result<SearchResponse> {
+    let query = request
+        .query
+        .as_deref()
+        .map(search_query::parse)
+        .unwrap_or_default();
+    let pool = dispatch!(pool, |db| sqlite::search::search(
+        db, user_id, request, query
+    ));
     let mut client = pool.get().await?;
     let transaction = client.transaction().await?;
 
-    let f_search = run_search(&transaction, user_id, request).map_err(|e| {
+    let configs = match &query.text {
+        Some(_) => {
+            let mut params: Vec<&(dyn ToSql + Sync)> = vec![];
+            let mut filters = vec![];
+            push_owner(&user_id, request, &mut params, &mut filters);
+            text_search_configs(&transaction, "b", &filters.join(" AND "), &params).await?
+        }
+        None => vec![],
+    };
+    let text_match = query
+        .text
+        .as_ref()
+        .map(|text| TextMatch::FullText(text, &configs));
+    let mut response = run_all(&transaction, user_id, request, &query, text_match).await?;
+    if response.total == 0 {
+        if let Some(words) = query.text.as_deref().and_then(search_query::fuzzy_words) {
+            debug!(%words, "No full-text match, falling back to trigrams");
+            let text_match = Some(TextMatch::Fuzzy(&words));
+            response = run_all(&transaction, user_id, request, &query, text_match).await?;
+            response.fuzzy = response.total > 0;
+        }
+    }
+
+    transaction.commit().await?;
+    Ok(response)
+}
+
+/// `LIKE` pattern of the values starting with `text`, backslash escaped.
+pub(in crate::db) fn like_prefix(text: &str) -> String {
+    let mut pattern = String::with_capacity(text.len() + 1);
+    for c in text.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// Tags, titles and domains completing `text`, which is expected lowercase.
+/// Prefix matches come first, then the ones `<%` finds similar, which the
+/// trigram indexes on the title and domain keep cheap.
+pub async fn suggest(
+    pool: &Database,
+    user_id: Uuid,
+    text: &str,
+    limit: i64,
+) -> Result<SearchSuggestions> {
+    const TAGS_SQL: &str = r#"
+        SELECT tag, count(1) AS count
+        FROM bookmark b, unnest(b.tags) AS tag
+        WHERE b.user_id = $1 AND b.deleted_at IS NULL
+          AND (tag LIKE $3 OR $2 <% tag)
+        GROUP BY tag
+        ORDER BY tag LIKE $3 DESC, word_similarity($2, tag) DESC, count DESC, tag
+        LIMIT $4;"#;
+    const TITLES_SQL: &str = r#"
+        SELECT b.title, count(1) AS count
+        FROM bookmark b
+        WHERE b.user_id = $1 AND b.deleted_at IS NULL
+          AND (b.title ILIKE $3 OR $2 <% b.title)
+        GROUP BY b.title
+        ORDER BY b.title ILIKE $3 DESC, word_similarity($2, b.title) DESC, count DESC
+        LIMIT $4;"#;
+    const DOMAINS_SQL: &str = r#"
+        SELECT b.domain, count(1) AS count
+        FROM bookmark b
+        WHERE b.user_id = $1 AND b.deleted_at IS NULL
+          AND (b.domain LIKE $3 OR $2 <% b.domain)
+        GROUP BY b.domain
+        ORDER BY b.domain LIKE $3 DESC, word_similarity($2, b.domain) DESC, count DESC
+        LIMIT $4;"#;
+    let pool = dispatch!(pool, |db| sqlite::search::suggest(db, user_id, text, limit));
+    let prefix = like_prefix(text);
+    let params: [&(dyn ToSql + Sync); 4] = [&user_id, &text, &prefix, &limit];
+    let client = pool.get().await?;
+    let (tags, titles, domains) = try_join!(
+        client.query(TAGS_SQL, &params),
+        client.query(TITLES_SQL, &params),
+        client.query(DOMAINS_SQL, &params),
+    )?;
+    Ok(SearchSuggestions {
+        tags: tags
+            .iter()
+            .map(|row| RowTagCount::try_from_row(row).map(TagCount::from))
+            .collect::<std::result::Result<_, _>>()?,
+        titles: titles
+            .iter()
+            .map(|row| RowTitleCount::try_from_row(row).map(TitleCount::from))
+            .collect::<std::result::Result<_, _>>()?,
+        domains: domains
+            .iter()
+            .map(|row| RowDomainCount::try_from_row(row).map(DomainCount::from))
+            .collect::<std::result::Result<_, _>>()?,
+    })
+}
+
+/// How the free text of a query selects bookmarks.
+#[derive(Debug, Clone, Copy)]
+enum TextMatch<'a> {
+    /// `websearch_to_tsquery` syntax against the search tokens, parsed with
+    /// each of the text search configs of the bookmarks searched
+    FullText(&'a String, &'a [String]),
+    /// Words close to the title or domain by trigram similarity, typos
+    /// included
+    Fuzzy(&'a String),
+}
+
+/// Text search configs of the bookmarks matching `owner`, a condition on
+/// the bookmark `alias`. They are spliced into statements as literals, so
+/// names other than plain identifiers are left out.
+pub(super) async fn text_search_configs(
+    client: &impl GenericClient,
+    alias: &str,
+    owner: &str,
+    params: &[&(dyn ToSql + Sync)],
+) -> Result<Vec<String>> {
+    let sql = format!(
+        "SELECT DISTINCT {alias}.text_search_config FROM bookmark {alias} \
+         WHERE {owner} AND {alias}.deleted_at IS NULL"
+    );
+    let configs = client
+        .query(&sql, params)
+        .await?
+        .iter()
+        .map(|row| row.get::<_, String>(0))
+        .filter(|config| {
+            !config.is_empty()
+                && config
+                    .bytes()
+                    .all(|byte| byte.is_ascii_lowercase() || byte == b'_')
+        })
+        .collect();
+    Ok(configs)
+}
+
+/// `tokens` matching the query `$idx`, one branch per config so each parses
+/// the query once and the GIN index on the tokens can be used.
+pub(super) fn full_text_condition(tokens: &str, configs: &[String], idx: usize) -> String {
+    if configs.is_empty() {
+        return "FALSE".to_string();
+    }
+    let branches: Vec<String> = configs
+        .iter()
+        .map(|config| {
+            format!(
+                "(b.text_search_config = '{config}' \
+                 AND {tokens} @@ websearch_to_tsquery('{config}'::regconfig, ${idx}))"
+            )
+        })
+        .collect();
+    format!("({})", branches.join(" OR "))
+}
+
+/// The query `$idx` parsed with the config of the bookmark, for ranking and
+/// highlighting the rows [`full_text_condition`] matched.
+pub(super) fn full_text_query(configs: &[String], idx: usize) -> String {
+    let branches: String = configs
+        .iter()
+        .map(|config| {
+            format!(" WHEN '{config}' THEN websearch_to_tsquery('{config}'::regconfig, ${idx})")
+        })
+        .collect();
+    format!("(CASE b.text_search_config{branches} END)")
+}
+
+/// `<%` is true when `word_similarity` passes
+/// `pg_trgm.word_similarity_threshold` (0.6 by default), and can use the
+/// trigram indexes.
+fn fuzzy_condition(idx: usize) -> String {
+    format!("(${idx} <% b.title OR ${idx} <% b.domain)")
+}
+
+fn push_text_match<'a>(
+    text_match: Option<TextMatch<'a>>,
+    params: &mut Vec<&'a (dyn ToSql + Sync)>,
+    filters: &mut Vec<String>,
+) {
+    match text_match {
+        Some(TextMatch::FullText(text, configs)) => {
+            params.push(text);
+            filters.push(full_text_condition(
+                "b.search_tokens",
+                configs,
+                params.len(),
+            ));
+        }
+        Some(TextMatch::Fuzzy(words)) => {
+            params.push(words);
+            filters.push(fuzzy_condition(params.len()));
+        }
+        None => {}
+    }
+}
+
+async fn run_all(
+    client: &impl GenericClient,
+    user_id: Uuid,
+    request: &SearchRequest,
+    query: &ParsedQuery,
+    text_match: Option<TextMatch<'_>>,
+) -> Result<SearchResponse> {
+    let f_search = run_search(client, user_id, request, query, text_match).map_err(|e| {
         warn!("Search query fail");
         e
     });
-    let f_aggregation = run_aggregation(&transaction, user_id, request).map_err(|e| {
+    let f_aggregation = run_aggregation(client, user_id, request, query, text_match).map_err(|e| {
         warn!("Aggregation query fail");
         e
     });
-    let f_total = run_total(&transaction, user_id, request).map_err(|e| {
+    let f_domains =
+        run_domain_aggregation(client, user_id, request, query, text_match).map_err(|e| {
+            warn!("Domain aggregation query fail");
+            e
+        });
+    let f_months = run_month_histogram(client, user_id, request, query, text_match).map_err(|e| {
+        warn!("Month histogram query fail");
+        e
+    });
+    let f_total = run_total(client, user_id, request, query, text_match).map_err(|e| {
         warn!("Total query fail");
         e
     });
-    let (items, tags, total) = try_join!(f_search, f_aggregation, f_total)?;
-
-    transaction.commit().await?;
-
-    Ok(SearchResponse { items, tags, total })
+    let (items, tags, domains, months, total) =
+        try_join!(f_search, f_aggregation, f_domains, f_months, f_total)?;
+
+    Ok(SearchResponse {
+        items,
+        tags,
+        domains,
+        months,
+        total,
+        fuzzy: false,
+    })
 }
 
-async fn run_total(
-    client: &impl GenericClient,
-    user_id: Uuid,
-    request: &SearchRequest,
-) -> Result<u64> {
-    let mut filters: Vec<String> = vec![];
-    let mut params: Vec<&(dyn ToSql + Sync)> = vec![];
-
-    params.push(&user_id);
-    filters.push(format!("b.user_id = ${}", params.len()));
-
-    if let Some(query) = &request.query {
-        params.push(query);
-        filters.push(format!(
-            "b.search_tokens @@ websearch_to_tsquery('english', ${})",
-            params.len()
-        ));
+/// The bookmarks of the user, or the ones saved into the workspace of the
+/// request when the user is one of its members.
+fn push_owner<'a>(
+    user_id: &'a Uuid,
+    request: &'a SearchRequest,
+    params: &mut Vec<&'a (dyn ToSql + Sync)>,
+    filters: &mut Vec<String>,
+) {
+    params.push(user_id);
+    let user_idx = params.len();
+    match &request.workspace {
+        Some(workspace_id) => {
+            params.push(workspace_id);
+            filters.push(format!(
+                "EXISTS (SELECT 1 FROM workspace_bookmark wb \
+                 JOIN workspace_member wm ON wm.workspace_id = wb.workspace_id \
+                 AND wm.user_id = ${user_idx} \
+                 WHERE wb.workspace_id = ${} AND wb.bookmark_id = b.bookmark_id \
+                 AND wb.user_id = b.user_id) AND b.visibility <> 'private'",
+                params.len()
+            ));
+        }
+        None => filters.push(format!("b.user_id = ${user_idx}")),
     }
+}
 
+/// Tag, domain, date and query field filters, shared by the search and its
+/// aggregations.
+fn push_filters<'a>(
+    request: &'a SearchRequest,
+    query: &'a ParsedQuery,
+    params: &mut Vec<&'a (dyn ToSql + Sync)>,
+    filters: &mut Vec<String>,
+) {
+    filters.push("b.deleted_at IS NULL".to_string());
     if let Some(tag_filter) = &request.tags_filter {
         match tag_filter {
             TagFilter::And(tags) => {
@@ -131,6 +476,111 @@ async fn run_total(
         }
     }
 
+    if !request.domains.is_empty() {
+        params.push(&request.domains);
+        filters.push(format!("b.domain = ANY(${})", params.len()));
+    }
+
+    if let Some(from_date) = &request.from_date {
+        params.push(from_date);
+        filters.push(format!("b.created_at >= ${}", params.len()));
+    }
+
+    if let Some(to_date) = &request.to_date {
+        params.push(to_date);
+        filters.push(format!("b.created_at <= ${}", params.len()));
+    }
+
+    if let Some(condition) = reading_time_condition(&request.reading_times) {
+        filters.push(condition);
+    }
+
+    if let Some(condition) = kind_condition(&request.kinds) {
+        filters.push(condition);
+    }
+
+    for entity in &request.entities {
+        params.push(entity);
+        filters.push(format!(
+            "EXISTS (SELECT 1 FROM entity e WHERE e.bookmark_id = b.bookmark_id \
+             AND e.user_id = b.user_id AND lower(e.name) = lower(${}))",
+            params.len()
+        ));
+    }
+
+    if let Some(collection_id) = &request.collection {
+        params.push(collection_id);
+        filters.push(format!(
+            "EXISTS (SELECT 1 FROM collection_bookmark cb WHERE cb.bookmark_id = b.bookmark_id \
+             AND cb.user_id = b.user_id AND cb.collection_id = ${})",
+            params.len()
+        ));
+    }
+
+    for filter in &query.filters {
+        params.push(&filter.value);
+        let idx = params.len();
+        let condition = match filter.field {
+            Field::Title => format!("strpos(lower(b.title), lower(${idx})) > 0"),
+            Field::Domain => format!("b.domain = ${idx}"),
+            Field::Tag => format!("coalesce(${idx} = ANY(b.tags), false)"),
+        };
+        if filter.negated {
+            filters.push(format!("NOT {condition}"));
+        } else {
+            filters.push(condition);
+        }
+    }
+}
+
+/// Matches bookmarks whose reading time falls in any of the buckets, the
+/// bounds are constants so both backends inline them in the SQL.
+pub(super) fn reading_time_condition(reading_times: &[ReadingTime]) -> Option<String> {
+    if reading_times.is_empty() {
+        return None;
+    }
+    let buckets: Vec<String> = reading_times
+        .iter()
+        .map(|reading_time| match reading_time.minutes() {
+            (Some(from), Some(to)) => {
+                format!("(b.reading_time_minutes >= {from} AND b.reading_time_minutes < {to})")
+            }
+            (Some(from), None) => format!("b.reading_time_minutes >= {from}"),
+            (None, Some(to)) => format!("b.reading_time_minutes < {to}"),
+            (None, None) => "TRUE".to_string(),
+        })
+        .collect();
+    Some(format!("({})", buckets.join(" OR ")))
+}
+
+/// Matches bookmarks classified as any of the kinds, inlined like
+/// [`reading_time_condition`] as they are a closed set of names.
+pub(super) fn kind_condition(kinds: &[BookmarkKind]) -> Option<String> {
+    if kinds.is_empty() {
+        return None;
+    }
+    let kinds: Vec<String> = kinds
+        .iter()
+        .map(|kind| format!("'{}'", kind.as_ref()))
+        .collect();
+    Some(format!("b.kind IN ({})", kinds.join(", ")))
+}
+
+async fn run_total(
+    client: &impl GenericClient,
+    user_id: Uuid,
+    request: &SearchRequest,
+    query: &ParsedQuery,
+    text_match: Option<TextMatch<'_>>,
+) -> Result<u64> {
+    let mut filters: Vec<String> = vec![];
+    let mut params: Vec<&(dyn ToSql + Sync)> = vec![];
+
+    push_owner(&user_id, request, &mut params, &mut filters);
+    push_text_match(text_match, &mut params, &mut filters);
+
+    push_filters(request, query, &mut params, &mut filters);
+
     let filter_clause = format!("WHERE {}", filters.join(" AND "));
     let sql = format!("SELECT COUNT(1) FROM bookmark b {filter_clause}");
 
@@ -144,39 +594,16 @@ async fn run_aggregation(
     client: &impl GenericClient,
     user_id: Uuid,
     request: &SearchRequest,
+    query: &ParsedQuery,
+    text_match: Option<TextMatch<'_>>,
 ) -> Result<Vec<TagCount>> {
     let mut filters: Vec<String> = vec![];
     let mut params: Vec<&(dyn ToSql + Sync)> = vec![];
 
-    params.push(&user_id);
-    filters.push(format!("b.user_id = ${}", params.len()));
-
-    if let Some(query) = &request.query {
-        params.push(query);
-        filters.push(format!(
-            "b.search_tokens @@ websearch_to_tsquery('english', ${})",
-            params.len()
-        ));
-    }
+    push_owner(&user_id, request, &mut params, &mut filters);
+    push_text_match(text_match, &mut params, &mut filters);
 
-    if let Some(tag_filter) = &request.tags_filter {
-        match tag_filter {
-            TagFilter::And(tags) => {
-                params.push(tags);
-                filters.push(format!("b.tags @> ${}", params.len()));
-            }
-            TagFilter::Or(tags) => {
-                params.push(tags);
-                filters.push(format!("b.tags && ${}", params.len()));
-            }
-            TagFilter::Untagged => {
-                filters.push(
-                    "(b.tags IS NULL OR coalesce(array_length(b.tags, 1), 0) = 0)".to_string(),
-                );
-            }
-            TagFilter::Any => { /* No filter */ }
-        }
-    }
+    push_filters(request, query, &mut params, &mut filters);
 
     let filter_clause = format!("WHERE {}", filters.join(" AND "));
     let sql = format!(
@@ -198,61 +625,135 @@ async fn run_aggregation(
         .collect::<Result<Vec<_>>>()
 }
 
+async fn run_domain_aggregation(
+    client: &impl GenericClient,
+    user_id: Uuid,
+    request: &SearchRequest,
+    query: &ParsedQuery,
+    text_match: Option<TextMatch<'_>>,
+) -> Result<Vec<DomainCount>> {
+    let mut filters: Vec<String> = vec![];
+    let mut params: Vec<&(dyn ToSql + Sync)> = vec![];
+
+    push_owner(&user_id, request, &mut params, &mut filters);
+    push_text_match(text_match, &mut params, &mut filters);
+
+    push_filters(request, query, &mut params, &mut filters);
+
+    params.push(&DOMAIN_FACET_SIZE);
+    let limit_idx = params.len();
+    let filter_clause = format!("WHERE {}", filters.join(" AND "));
+    let sql = format!(
+        "SELECT b.domain, count(1) AS count FROM bookmark b {filter_clause} \
+         GROUP BY b.domain ORDER BY count DESC, b.domain LIMIT ${limit_idx}",
+    );
+
+    debug!(?sql, "Domain aggregation query");
+
+    client
+        .query(&sql, &params)
+        .await?
+        .iter()
+        .map(|row| {
+            RowDomainCount::try_from_row(row)
+                .map(DomainCount::from)
+                .map_err(Error::from)
+        })
+        .collect::<Result<Vec<_>>>()
+}
+
+async fn run_month_histogram(
+    client: &impl GenericClient,
+    user_id: Uuid,
+    request: &SearchRequest,
+    query: &ParsedQuery,
+    text_match: Option<TextMatch<'_>>,
+) -> Result<Vec<MonthCount>> {
+    let mut filters: Vec<String> = vec![];
+    let mut params: Vec<&(dyn ToSql + Sync)> = vec![];
+
+    push_owner(&user_id, request, &mut params, &mut filters);
+    push_text_match(text_match, &mut params, &mut filters);
+
+    push_filters(request, query, &mut params, &mut filters);
+
+    let filter_clause = format!("WHERE {}", filters.join(" AND "));
+    let sql = format!(
+        "SELECT date_trunc('month', b.created_at AT TIME ZONE 'UTC')::date AS month, \
+         count(1) AS count FROM bookmark b {filter_clause} GROUP BY month ORDER BY month",
+    );
+
+    debug!(?sql, "Month histogram query");
+
+    client
+        .query(&sql, &params)
+        .await?
+        .iter()
+        .map(|row| {
+            RowMonthCount::try_from_row(row)
+                .map(MonthCount::from)
+                .map_err(Error::from)
+        })
+        .collect::<Result<Vec<_>>>()
+}
+
 async fn run_search(
     client: &impl GenericClient,
     user_id: Uuid,
     request: &SearchRequest,
+    query: &ParsedQuery,
+    text_match: Option<TextMatch<'_>>,
 ) -> Result<Vec<SearchResultItem>> {
     let mut params: Vec<&(dyn ToSql + Sync)> = vec![];
     let mut filters: Vec<String> = vec![];
     let mut order_by_clause = "ORDER BY b.created_at DESC".to_string();
 
     let select_clause;
-    let query_param_idx: Option<usize>;
+    let text_filter: Option<String>;
     // Necessary because we need a stable memory location for the param borrow
     let none_query_param = None::<String>;
 
-    if let Some(query) = &request.query {
-        params.push(query);
+    if let Some(TextMatch::FullText(text, configs)) = text_match {
+        params.push(text);
         let idx = params.len();
-        query_param_idx = Some(idx);
-        select_clause = format!("ts_headline('english', b.text_content, websearch_to_tsquery('english', ${idx}), 'StartSel=<mark>, StopSel=</mark>') AS search_match, b.*");
+        text_filter = Some(full_text_condition("b.search_tokens", configs, idx));
+        let tsquery = full_text_query(configs, idx);
+        let options = headline_options(request);
+        let highlight_clause = if request.highlight_title_summary {
+            format!(
+                "ts_headline(b.text_search_config::regconfig, b.title, {tsquery}, 'StartSel=<mark>, StopSel=</mark>, HighlightAll=true') AS title_highlight, \
+                 ts_headline(b.text_search_config::regconfig, b.summary, {tsquery}, 'StartSel=<mark>, StopSel=</mark>, HighlightAll=true') AS summary_highlight"
+            )
+        } else {
+            "NULL::text AS title_highlight, NULL::text AS summary_highlight".to_string()
+        };
+        select_clause = format!("ts_headline(b.text_search_config::regconfig, b.text_content, {tsquery}, '{options}') AS search_match, {highlight_clause}, b.*");
+        order_by_clause = format!("ORDER BY ts_rank(b.search_tokens, {tsquery}) DESC");
+    } else if let Some(TextMatch::Fuzzy(words)) = text_match {
+        params.push(words);
+        let idx = params.len();
+        text_filter = Some(fuzzy_condition(idx));
+        // Typos have no match to highlight in the text
+        select_clause = "NULL::text AS search_match, NULL::text AS title_highlight, \
+                         NULL::text AS summary_highlight, b.*"
+            .to_string();
         order_by_clause = format!(
-            "ORDER BY ts_rank(b.search_tokens, websearch_to_tsquery('english', ${idx})) DESC",
+            "ORDER BY greatest(word_similarity(${idx}, b.title), word_similarity(${idx}, b.domain)) DESC",
         );
     } else {
         params.push(&none_query_param);
-        query_param_idx = None;
-        select_clause = format!("${}::text AS search_match, b.*", params.len());
+        text_filter = None;
+        select_clause = format!(
+            "${}::text AS search_match, NULL::text AS title_highlight, \
+             NULL::text AS summary_highlight, b.*",
+            params.len()
+        );
     }
 
-    params.push(&user_id);
-    filters.push(format!("b.user_id = ${}", params.len()));
+    push_owner(&user_id, request, &mut params, &mut filters);
+    filters.extend(text_filter);
 
-    if let Some(idx) = query_param_idx {
-        filters.push(format!(
-            "b.search_tokens @@ websearch_to_tsquery('english', ${idx})",
-        ));
-    }
-
-    if let Some(tag_filter) = &request.tags_filter {
-        match tag_filter {
-            TagFilter::And(tags) => {
-                params.push(tags);
-                filters.push(format!("b.tags @> ${}", params.len()));
-            }
-            TagFilter::Or(tags) => {
-                params.push(tags);
-                filters.push(format!("b.tags && ${}", params.len()));
-            }
-            TagFilter::Untagged => {
-                filters.push(
-                    "(b.tags IS NULL OR coalesce(array_length(b.tags, 1), 0) = 0)".to_string(),
-                );
-            }
-            TagFilter::Any => {}
-        }
-    }
+    push_filters(request, query, &mut params, &mut filters);
 
     let filter_clause = format!("WHERE {}", filters.join(" AND "));
     let limit_clause = format!("LIMIT {}", request.limit.unwrap_or(20));
@@ -278,3 +779,42 @@ async fn run_search(
         })
         .collect::<Result<Vec<_>>>()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn like_prefix_escapes_wildcards() {
+        assert_eq!(like_prefix("rust"), "rust%");
+        assert_eq!(like_prefix(r"50%_off\"), r"50\%\_off\\%");
+    }
+
+    #[test]
+    fn full_text_conditions_name_each_config() {
+        let configs = ["english".to_string(), "french".to_string()];
+        assert_eq!(
+            full_text_condition("b.search_tokens", &configs, 2),
+            "((b.text_search_config = 'english' \
+             AND b.search_tokens @@ websearch_to_tsquery('english'::regconfig, $2)) OR \
+             (b.text_search_config = 'french' \
+             AND b.search_tokens @@ websearch_to_tsquery('french'::regconfig, $2)))"
+        );
+        assert_eq!(full_text_condition("b.search_tokens", &[], 2), "FALSE");
+        assert_eq!(
+            full_text_query(&configs[..1], 3),
+            "(CASE b.text_search_config WHEN 'english' \
+             THEN websearch_to_tsquery('english'::regconfig, $3) END)"
+        );
+    }
+
+    #[test]
+    fn headline_is_split_into_trimmed_fragments() {
+        let headline = format!("first <mark>match</mark> {FRAGMENT_DELIMITER} second one ");
+        assert_eq!(
+            split_fragments(&headline),
+            vec!["first <mark>match</mark>", "second one"]
+        );
+        assert!(split_fragments("").is_empty());
+    }
+}
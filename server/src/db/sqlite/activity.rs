@@ -0,0 +1,74 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use rusqlite::types::Type;
+use rusqlite::{params, Row};
+use shared::{ActivityItem, ActivityKind};
+use uuid::Uuid;
+
+use super::{timestamp, SqlitePool};
+use crate::error::Result;
+
+fn from_row(row: &Row) -> rusqlite::Result<ActivityItem> {
+    let kind: String = row.get("kind")?;
+    Ok(ActivityItem {
+        kind: ActivityKind::from_str(&kind)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(e)))?,
+        bookmark_id: row.get("bookmark_id")?,
+        title: row.get("title")?,
+        detail: row.get("detail")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+pub(in crate::db) async fn list(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    before: Option<DateTime<Utc>>,
+    limit: u32,
+) -> Result<Vec<ActivityItem>> {
+    let before = before.map(|before| timestamp(&before));
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(
+                "SELECT kind, bookmark_id, title, detail, created_at
+                 FROM (
+                     SELECT 'bookmark_added' AS kind, b.bookmark_id, b.title,
+                            NULL AS detail, b.created_at
+                     FROM bookmark b
+                     WHERE b.user_id = ?1 AND b.deleted_at IS NULL
+                     UNION ALL
+                     SELECT CASE e.stage WHEN 'summarized' THEN 'summary_generated'
+                                ELSE 'tags_applied' END,
+                            b.bookmark_id, b.title,
+                            CASE e.stage WHEN 'tagged' THEN e.detail END, e.created_at
+                     FROM task_event e
+                     JOIN bookmark_task t ON t.task_id = e.task_id
+                     JOIN bookmark b ON b.bookmark_id = t.bookmark_id AND b.user_id = t.user_id
+                     WHERE t.user_id = ?1 AND e.stage IN ('summarized', 'tagged')
+                       AND b.deleted_at IS NULL
+                       AND t.task_id = (
+                           SELECT oldest.task_id FROM bookmark_task oldest
+                           WHERE oldest.user_id = t.user_id
+                             AND oldest.bookmark_id = t.bookmark_id
+                           ORDER BY oldest.created_at, oldest.task_id
+                           LIMIT 1
+                       )
+                     UNION ALL
+                     SELECT 'question_asked', NULL, NULL, question, created_at
+                     FROM rag_session
+                     WHERE user_id = ?1
+                     UNION ALL
+                     SELECT 'import_completed', NULL, NULL, target, created_at
+                     FROM audit_log
+                     WHERE user_id = ?1 AND action = 'backup_restored'
+                 )
+                 WHERE ?2 IS NULL OR created_at < ?2
+                 ORDER BY created_at DESC, kind
+                 LIMIT ?3",
+            )?
+            .query_map(params![user_id, before, limit], from_row)?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
@@ -0,0 +1,550 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use shared::{Bookmark, BookmarkKind, Entity, SummaryStyle};
+use uuid::Uuid;
+
+use super::{bookmark, timestamp, Json, SqlitePool};
+use crate::db::ai::{truncate_fail_reason, BookmarkAiChunk, EmbeddingGenerationCandidate};
+use crate::db::bookmark::{AiGenerationStatus, BookmarkGenerationCandidate};
+use crate::error::Result;
+
+// A single connection serves the whole process, so selecting and pushing the
+// next attempt forward in one transaction is enough to claim the rows.
+
+pub(in crate::db) async fn claim_bookmarks_pending_text_ai(
+    pool: &SqlitePool,
+    limit: usize,
+    now: DateTime<Utc>,
+    claim_window: ChronoDuration,
+) -> Result<Vec<BookmarkGenerationCandidate>> {
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        let candidates = tx
+            .prepare(
+                "SELECT *
+                 FROM bookmark
+                 WHERE text_ai_status = 'pending'
+                   AND text_ai_next_attempt_at <= ?1
+                   AND (summary_status = 'pending' OR tag_status = 'pending')
+                   AND deleted_at IS NULL
+                 ORDER BY text_ai_next_attempt_at ASC, created_at ASC
+                 LIMIT ?2",
+            )?
+            .query_map(params![timestamp(&now), limit as i64], |row| {
+                let summary_status: AiGenerationStatus = row.get("summary_status")?;
+                let tag_status: AiGenerationStatus = row.get("tag_status")?;
+                let summary_style: Option<String> = row.get("summary_style")?;
+                Ok(BookmarkGenerationCandidate {
+                    bookmark: bookmark::from_row(row)?,
+                    attempts: row.get("text_ai_attempts")?,
+                    needs_summary: summary_status == AiGenerationStatus::Pending,
+                    needs_tags: tag_status == AiGenerationStatus::Pending,
+                    summary_style: summary_style.and_then(|style| style.parse().ok()),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let claimed_until = timestamp(&(now + claim_window));
+        for candidate in &candidates {
+            tx.execute(
+                "UPDATE bookmark SET text_ai_next_attempt_at = ?1
+                 WHERE bookmark_id = ?2 AND user_id = ?3",
+                params![
+                    claimed_until,
+                    candidate.bookmark.bookmark_id,
+                    candidate.bookmark.user_id
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(candidates)
+    })
+    .await
+}
+
+pub(in crate::db) async fn claim_bookmarks_pending_embeddings(
+    pool: &SqlitePool,
+    limit: usize,
+    now: DateTime<Utc>,
+    claim_window: ChronoDuration,
+) -> Result<Vec<EmbeddingGenerationCandidate>> {
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        let candidates = tx
+            .prepare(
+                "SELECT bookmark_id, user_id, text_content, embedding_attempts
+                 FROM bookmark
+                 WHERE embedding_status = 'pending'
+                   AND embedding_next_attempt_at <= ?1
+                   AND deleted_at IS NULL
+                 ORDER BY embedding_next_attempt_at ASC, created_at ASC
+                 LIMIT ?2",
+            )?
+            .query_map(params![timestamp(&now), limit as i64], |row| {
+                Ok(EmbeddingGenerationCandidate {
+                    bookmark_id: row.get("bookmark_id")?,
+                    user_id: row.get("user_id")?,
+                    text_content: row.get("text_content")?,
+                    attempts: row.get("embedding_attempts")?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let claimed_until = timestamp(&(now + claim_window));
+        for candidate in &candidates {
+            tx.execute(
+                "UPDATE bookmark SET embedding_next_attempt_at = ?1
+                 WHERE bookmark_id = ?2 AND user_id = ?3",
+                params![claimed_until, candidate.bookmark_id, candidate.user_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(candidates)
+    })
+    .await
+}
+
+pub(in crate::db) async fn refresh_text_ai_claim(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+    next_attempt_at: DateTime<Utc>,
+) -> Result<()> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        connection.execute(
+            "UPDATE bookmark SET text_ai_next_attempt_at = ?1
+             WHERE bookmark_id = ?2 AND user_id = ?3 AND text_ai_status = 'pending'",
+            params![timestamp(&next_attempt_at), bookmark_id, user_id],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+pub(in crate::db) async fn refresh_embedding_claim(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+    next_attempt_at: DateTime<Utc>,
+) -> Result<()> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        connection.execute(
+            "UPDATE bookmark SET embedding_next_attempt_at = ?1
+             WHERE bookmark_id = ?2 AND user_id = ?3 AND embedding_status = 'pending'",
+            params![timestamp(&next_attempt_at), bookmark_id, user_id],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+pub(in crate::db) async fn list_bookmark_ai_chunks(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<Vec<BookmarkAiChunk>> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(
+                "SELECT bookmark_id, user_id, chunk_index, chunk_hash, pipeline_version, summary,
+                        tags, entities
+                 FROM bookmark_ai_chunk
+                 WHERE bookmark_id = ?1 AND user_id = ?2
+                 ORDER BY chunk_index ASC",
+            )?
+            .query_map(params![bookmark_id, user_id], |row| {
+                Ok(BookmarkAiChunk {
+                    bookmark_id: row.get("bookmark_id")?,
+                    user_id: row.get("user_id")?,
+                    chunk_index: row.get("chunk_index")?,
+                    chunk_hash: row.get("chunk_hash")?,
+                    pipeline_version: row.get("pipeline_version")?,
+                    summary: row.get("summary")?,
+                    tags: row.get::<_, Json<Vec<String>>>("tags")?.0,
+                    entities: row.get::<_, Json<Vec<Entity>>>("entities")?.0,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn upsert_bookmark_ai_chunk(
+    pool: &SqlitePool,
+    chunk: &BookmarkAiChunk,
+) -> Result<()> {
+    let chunk = chunk.clone();
+    pool.run(move |connection| {
+        connection.execute(
+            "INSERT INTO bookmark_ai_chunk
+                (bookmark_id, user_id, chunk_index, chunk_hash, pipeline_version, summary, tags,
+                 entities, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?9, ?8, ?8)
+             ON CONFLICT (bookmark_id, user_id, chunk_index)
+             DO UPDATE SET
+                chunk_hash = excluded.chunk_hash,
+                pipeline_version = excluded.pipeline_version,
+                summary = excluded.summary,
+                tags = excluded.tags,
+                entities = excluded.entities,
+                updated_at = excluded.updated_at",
+            params![
+                chunk.bookmark_id,
+                chunk.user_id,
+                chunk.chunk_index,
+                chunk.chunk_hash,
+                chunk.pipeline_version,
+                chunk.summary,
+                Json(&chunk.tags),
+                timestamp(&Utc::now()),
+                Json(&chunk.entities)
+            ],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+pub(in crate::db) async fn delete_bookmark_ai_chunks(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<()> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        connection.execute(
+            "DELETE FROM bookmark_ai_chunk WHERE bookmark_id = ?1 AND user_id = ?2",
+            params![bookmark_id, user_id],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+pub(in crate::db) async fn mark_text_ai_failure(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+    status: AiGenerationStatus,
+    attempts: i16,
+    next_attempt_at: DateTime<Utc>,
+    fail_reason: &str,
+) -> Result<()> {
+    let bookmark_id = bookmark_id.to_string();
+    let fail_reason = truncate_fail_reason(fail_reason);
+    pool.run(move |connection| {
+        connection.execute(
+            "UPDATE bookmark
+             SET text_ai_status = ?1,
+                 text_ai_attempts = ?2,
+                 text_ai_next_attempt_at = ?3,
+                 text_ai_fail_reason = ?4,
+                 updated_at = ?5
+             WHERE bookmark_id = ?6 AND user_id = ?7",
+            params![
+                status,
+                attempts,
+                timestamp(&next_attempt_at),
+                fail_reason,
+                timestamp(&Utc::now()),
+                bookmark_id,
+                user_id
+            ],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(in crate::db) async fn complete_text_ai_outputs(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+    summary: Option<&str>,
+    tags: Option<&[String]>,
+    kind: Option<BookmarkKind>,
+    summary_status: AiGenerationStatus,
+    tag_status: AiGenerationStatus,
+    pipeline_version: i32,
+) -> Result<()> {
+    let bookmark_id = bookmark_id.to_string();
+    let summary = summary.map(str::to_string);
+    let tags = tags.map(|tags| Json(tags.to_vec()));
+    pool.run(move |connection| {
+        connection.execute(
+            "UPDATE bookmark
+             SET summary = ?1,
+                 tags = ?2,
+                 kind = ?7,
+                 summary_status = ?8,
+                 summary_style = NULL,
+                 tag_status = ?9,
+                 text_ai_status = 'done',
+                 text_ai_attempts = 0,
+                 text_ai_next_attempt_at = ?4,
+                 text_ai_fail_reason = NULL,
+                 text_ai_pipeline_version = ?3,
+                 updated_at = ?4
+             WHERE bookmark_id = ?5 AND user_id = ?6",
+            params![
+                summary,
+                tags,
+                pipeline_version,
+                timestamp(&Utc::now()),
+                bookmark_id,
+                user_id,
+                kind.as_ref().map(AsRef::<str>::as_ref),
+                summary_status,
+                tag_status
+            ],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+pub(in crate::db) async fn requeue_skipped(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    summaries: bool,
+    tags: bool,
+) -> Result<u64> {
+    pool.run(move |connection| {
+        let queued = connection.execute(
+            "UPDATE bookmark
+             SET summary_status = CASE WHEN ?2 AND summary_status = 'skipped'
+                     THEN 'pending' ELSE summary_status END,
+                 tag_status = CASE WHEN ?3 AND tag_status = 'skipped'
+                     THEN 'pending' ELSE tag_status END,
+                 text_ai_status = 'pending',
+                 text_ai_attempts = 0,
+                 text_ai_next_attempt_at = ?4,
+                 text_ai_fail_reason = NULL
+             WHERE user_id = ?1 AND deleted_at IS NULL
+               AND ((?2 AND summary_status = 'skipped') OR (?3 AND tag_status = 'skipped'))",
+            params![user_id, summaries, tags, timestamp(&Utc::now())],
+        )?;
+        Ok(queued as u64)
+    })
+    .await
+}
+
+pub(in crate::db) async fn mark_embedding_failure(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+    status: AiGenerationStatus,
+    attempts: i16,
+    next_attempt_at: DateTime<Utc>,
+    fail_reason: &str,
+) -> Result<()> {
+    let bookmark_id = bookmark_id.to_string();
+    let fail_reason = truncate_fail_reason(fail_reason);
+    pool.run(move |connection| {
+        connection.execute(
+            "UPDATE bookmark
+             SET embedding_status = ?1,
+                 embedding_attempts = ?2,
+                 embedding_next_attempt_at = ?3,
+                 embedding_fail_reason = ?4,
+                 updated_at = ?5
+             WHERE bookmark_id = ?6 AND user_id = ?7",
+            params![
+                status,
+                attempts,
+                timestamp(&next_attempt_at),
+                fail_reason,
+                timestamp(&Utc::now()),
+                bookmark_id,
+                user_id
+            ],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+pub(in crate::db) async fn mark_embedding_success(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+    pipeline_version: i32,
+) -> Result<()> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        connection.execute(
+            "UPDATE bookmark
+             SET embedding_status = 'done',
+                 embedding_attempts = 0,
+                 embedding_next_attempt_at = ?2,
+                 embedding_fail_reason = NULL,
+                 embedding_pipeline_version = ?1,
+                 updated_at = ?2
+             WHERE bookmark_id = ?3 AND user_id = ?4",
+            params![
+                pipeline_version,
+                timestamp(&Utc::now()),
+                bookmark_id,
+                user_id
+            ],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+pub(super) fn reset_embedding_generation_state_in(
+    connection: &Connection,
+    pipeline_version: i32,
+) -> rusqlite::Result<()> {
+    connection.execute(
+        "UPDATE bookmark
+         SET embedding_status = CASE WHEN length(text_content) >= 200 THEN 'pending' ELSE 'done' END,
+             embedding_attempts = 0,
+             embedding_next_attempt_at = ?2,
+             embedding_fail_reason = NULL,
+             embedding_pipeline_version = ?1,
+             updated_at = ?2",
+        params![pipeline_version, timestamp(&Utc::now())],
+    )?;
+    Ok(())
+}
+
+pub(in crate::db) async fn set_summary(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+    summary: &str,
+) -> Result<Option<Bookmark>> {
+    let bookmark_id = bookmark_id.to_string();
+    let summary = summary.to_string();
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                "UPDATE bookmark
+                 SET summary = ?3,
+                     summary_status = 'done',
+                     summary_style = NULL,
+                     updated_at = ?4
+                 WHERE bookmark_id = ?1 AND user_id = ?2 AND deleted_at IS NULL
+                 RETURNING *",
+                params![bookmark_id, user_id, summary, timestamp(&Utc::now())],
+                bookmark::from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn request_summary(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+    style: Option<SummaryStyle>,
+) -> Result<bool> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        let updated = connection.execute(
+            "UPDATE bookmark
+             SET summary_status = 'pending',
+                 summary_style = ?3,
+                 text_ai_status = 'pending',
+                 text_ai_attempts = 0,
+                 text_ai_next_attempt_at = ?4,
+                 text_ai_fail_reason = NULL
+             WHERE bookmark_id = ?1 AND user_id = ?2 AND deleted_at IS NULL",
+            params![
+                bookmark_id,
+                user_id,
+                style.as_ref().map(AsRef::<str>::as_ref),
+                timestamp(&Utc::now())
+            ],
+        )?;
+        Ok(updated > 0)
+    })
+    .await
+}
+
+pub(in crate::db) async fn request_reprocessing(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+    tags: bool,
+    summary: bool,
+    chunks: bool,
+) -> Result<bool> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        let updated = tx.execute(
+            "UPDATE bookmark
+             SET tag_status = CASE WHEN ?3 THEN 'pending' ELSE tag_status END,
+                 summary_status = CASE WHEN ?4 THEN 'pending' ELSE summary_status END,
+                 summary_style = CASE WHEN ?4 THEN NULL ELSE summary_style END,
+                 text_ai_status = CASE WHEN ?3 OR ?4 THEN 'pending' ELSE text_ai_status END,
+                 text_ai_attempts = CASE WHEN ?3 OR ?4 THEN 0 ELSE text_ai_attempts END,
+                 text_ai_next_attempt_at =
+                     CASE WHEN ?3 OR ?4 THEN ?6 ELSE text_ai_next_attempt_at END,
+                 text_ai_fail_reason = CASE WHEN ?3 OR ?4 THEN NULL ELSE text_ai_fail_reason END,
+                 embedding_status = CASE WHEN ?5 THEN 'pending' ELSE embedding_status END,
+                 embedding_attempts = CASE WHEN ?5 THEN 0 ELSE embedding_attempts END,
+                 embedding_next_attempt_at =
+                     CASE WHEN ?5 THEN ?6 ELSE embedding_next_attempt_at END,
+                 embedding_fail_reason = CASE WHEN ?5 THEN NULL ELSE embedding_fail_reason END
+             WHERE bookmark_id = ?1 AND user_id = ?2 AND deleted_at IS NULL",
+            params![
+                bookmark_id,
+                user_id,
+                tags,
+                summary,
+                chunks,
+                timestamp(&Utc::now())
+            ],
+        )?;
+        if updated > 0 && (tags || summary) {
+            tx.execute(
+                "DELETE FROM bookmark_ai_chunk WHERE bookmark_id = ?1 AND user_id = ?2",
+                params![bookmark_id, user_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(updated > 0)
+    })
+    .await
+}
+
+pub(in crate::db) async fn skip_processing(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<()> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        connection.execute(
+            "UPDATE bookmark
+             SET summary_status = 'done',
+                 tag_status = 'done',
+                 text_ai_status = 'done',
+                 embedding_status = 'done'
+             WHERE bookmark_id = ?1 AND user_id = ?2",
+            params![bookmark_id, user_id],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+pub(in crate::db) async fn reset_embedding_generation_state(
+    pool: &SqlitePool,
+    pipeline_version: i32,
+) -> Result<()> {
+    pool.run(move |connection| {
+        Ok(reset_embedding_generation_state_in(
+            connection,
+            pipeline_version,
+        )?)
+    })
+    .await
+}
@@ -0,0 +1,121 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use rusqlite::types::Type;
+use rusqlite::{params, Row};
+use shared::{AuditAction, AuditEvent};
+use uuid::Uuid;
+
+use super::{timestamp, SqlitePool};
+use crate::error::Result;
+
+fn from_row(row: &Row) -> rusqlite::Result<AuditEvent> {
+    let action: String = row.get("action")?;
+    Ok(AuditEvent {
+        event_id: row.get("event_id")?,
+        action: AuditAction::from_str(&action)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, Type::Text, Box::new(e)))?,
+        target: row.get("target")?,
+        ip: row.get("ip")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+pub(in crate::db) async fn insert(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    action: AuditAction,
+    target: Option<&str>,
+    ip: Option<IpAddr>,
+) -> Result<()> {
+    let target = target.map(str::to_string);
+    pool.run(move |connection| {
+        connection.execute(
+            r#"INSERT INTO audit_log (event_id, user_id, username, action, target, ip, created_at)
+               VALUES (?1, ?2, (SELECT username FROM "user" WHERE user_id = ?2), ?3, ?4, ?5, ?6)"#,
+            params![
+                Uuid::new_v4(),
+                user_id,
+                action.as_ref(),
+                target,
+                ip.map(|ip| ip.to_string()),
+                timestamp(&Utc::now())
+            ],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+pub(in crate::db) async fn insert_for_username(
+    pool: &SqlitePool,
+    username: &str,
+    action: AuditAction,
+    target: Option<&str>,
+    ip: Option<IpAddr>,
+) -> Result<()> {
+    let username = username.to_string();
+    let target = target.map(str::to_string);
+    pool.run(move |connection| {
+        connection.execute(
+            "INSERT INTO audit_log (event_id, username, action, target, ip, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                Uuid::new_v4(),
+                username,
+                action.as_ref(),
+                target,
+                ip.map(|ip| ip.to_string()),
+                timestamp(&Utc::now())
+            ],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+pub(in crate::db) async fn list(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    before: Option<DateTime<Utc>>,
+    limit: u32,
+) -> Result<Vec<AuditEvent>> {
+    let before = before.map(|before| timestamp(&before));
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(
+                "SELECT event_id, action, target, ip, created_at
+                 FROM audit_log
+                 WHERE user_id = ?1 AND (?2 IS NULL OR created_at < ?2)
+                 ORDER BY created_at DESC, event_id
+                 LIMIT ?3",
+            )?
+            .query_map(params![user_id, before, limit], from_row)?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn list_by_username(
+    pool: &SqlitePool,
+    username: &str,
+    before: Option<DateTime<Utc>>,
+    limit: u32,
+) -> Result<Vec<AuditEvent>> {
+    let username = username.to_string();
+    let before = before.map(|before| timestamp(&before));
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(
+                "SELECT event_id, action, target, ip, created_at
+                 FROM audit_log
+                 WHERE username = ?1 AND (?2 IS NULL OR created_at < ?2)
+                 ORDER BY created_at DESC, event_id
+                 LIMIT ?3",
+            )?
+            .query_map(params![username, before, limit], from_row)?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
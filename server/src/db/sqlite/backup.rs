@@ -0,0 +1,629 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension};
+use shared::Entity;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use super::{
+    blob_to_vector, extraction_rule, mean_vector, rule, string_list, timestamp, user,
+    user_settings, vector_to_blob, Json, SqlitePool, SqliteResultExt,
+};
+use crate::backup::{
+    BookmarkRecord, BookmarkVersionRecord, ChunkRecord, CollectionRecord, DigestRecord,
+    FetchCredentialRecord, UserDump, WorkspaceBookmarkRecord, WorkspaceMembershipRecord,
+    WorkspaceRecord,
+};
+use crate::db::bookmark::{parse_visibility, AiGenerationStatus};
+use crate::error::{Error, Result};
+use crate::{reading_time, EMBEDDING_PIPELINE_VERSION, TEXT_AI_PIPELINE_VERSION};
+
+pub(in crate::db) async fn user_ids(pool: &SqlitePool) -> Result<Vec<Uuid>> {
+    pool.run(|connection| {
+        Ok(connection
+            .prepare(r#"SELECT user_id FROM "user" ORDER BY created_at"#)?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn export_user(pool: &SqlitePool, user_id: Uuid) -> Result<UserDump> {
+    let dump = pool
+        .run(move |connection| {
+            let user = connection.query_row(
+                r#"SELECT * FROM "user" WHERE user_id = ?1"#,
+                [user_id],
+                user::from_row,
+            )?;
+
+            let settings = connection
+                .query_row(
+                    "SELECT auto_tagging_enabled, summaries_enabled, summary_language,
+                            preferred_text_model, digest_enabled, reader_font_family,
+                            reader_font_size, reader_line_width, reader_justify
+                     FROM user_settings WHERE user_id = ?1",
+                    [user_id],
+                    user_settings::from_row,
+                )
+                .optional()?;
+
+            let mut chunks: HashMap<String, Vec<ChunkRecord>> = HashMap::new();
+            let mut statement = connection.prepare(
+                "SELECT bookmark_id, chunk_index, chunk_text, embedding
+                 FROM bookmark_chunk WHERE user_id = ?1
+                 ORDER BY bookmark_id, chunk_index",
+            )?;
+            let mut rows = statement.query([user_id])?;
+            while let Some(row) = rows.next()? {
+                let embedding: Option<Vec<u8>> = row.get("embedding")?;
+                chunks
+                    .entry(row.get("bookmark_id")?)
+                    .or_default()
+                    .push(ChunkRecord {
+                        chunk_index: row.get("chunk_index")?,
+                        chunk_text: row.get("chunk_text")?,
+                        embedding: embedding.as_deref().map(blob_to_vector),
+                    });
+            }
+
+            let mut entities: HashMap<String, Vec<Entity>> = HashMap::new();
+            let mut statement = connection.prepare(
+                "SELECT bookmark_id, kind, name FROM entity WHERE user_id = ?1
+                 ORDER BY bookmark_id, kind, name",
+            )?;
+            let mut rows = statement.query([user_id])?;
+            while let Some(row) = rows.next()? {
+                let kind: String = row.get("kind")?;
+                if let Ok(kind) = kind.parse() {
+                    entities
+                        .entry(row.get("bookmark_id")?)
+                        .or_default()
+                        .push(Entity {
+                            kind,
+                            name: row.get("name")?,
+                        });
+                }
+            }
+
+            let mut versions: HashMap<String, Vec<BookmarkVersionRecord>> = HashMap::new();
+            let mut statement = connection.prepare(
+                "SELECT bookmark_id, version_id, title, text_content, html, word_count,
+                        captured_at, created_at
+                 FROM bookmark_version WHERE user_id = ?1
+                 ORDER BY bookmark_id, captured_at",
+            )?;
+            let mut rows = statement.query([user_id])?;
+            while let Some(row) = rows.next()? {
+                versions
+                    .entry(row.get("bookmark_id")?)
+                    .or_default()
+                    .push(BookmarkVersionRecord {
+                        version_id: row.get("version_id")?,
+                        title: row.get("title")?,
+                        text_content: row.get("text_content")?,
+                        html: row.get("html")?,
+                        word_count: row.get("word_count")?,
+                        captured_at: row.get("captured_at")?,
+                        created_at: row.get("created_at")?,
+                    });
+            }
+
+            let bookmarks = connection
+                .prepare(
+                    "SELECT bookmark_id, url, canonical_url, domain, title, text_content, tags,
+                            summary, kind, favorite, original_title, visibility, summary_status,
+                            tag_status, text_ai_status, language, text_search_config,
+                            static_bytes, created_at, updated_at, read_at
+                     FROM bookmark WHERE user_id = ?1 AND deleted_at IS NULL
+                     ORDER BY created_at",
+                )?
+                .query_map([user_id], |row| {
+                    let bookmark_id: String = row.get("bookmark_id")?;
+                    Ok(BookmarkRecord {
+                        chunks: chunks.remove(&bookmark_id).unwrap_or_default(),
+                        entities: entities.remove(&bookmark_id).unwrap_or_default(),
+                        versions: versions.remove(&bookmark_id).unwrap_or_default(),
+                        bookmark_id,
+                        url: row.get("url")?,
+                        canonical_url: row.get("canonical_url")?,
+                        domain: row.get("domain")?,
+                        title: row.get("title")?,
+                        text_content: row.get("text_content")?,
+                        tags: string_list(row, "tags")?,
+                        summary: row.get("summary")?,
+                        kind: row.get("kind")?,
+                        favorite: row.get("favorite")?,
+                        original_title: row.get("original_title")?,
+                        visibility: parse_visibility(row.get("visibility")?),
+                        summary_status: row.get("summary_status")?,
+                        tag_status: row.get("tag_status")?,
+                        text_ai_status: row.get("text_ai_status")?,
+                        language: row.get("language")?,
+                        text_search_config: row.get("text_search_config")?,
+                        static_bytes: row.get("static_bytes")?,
+                        created_at: row.get("created_at")?,
+                        updated_at: row.get("updated_at")?,
+                        read_at: row.get("read_at")?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+
+            let digests = connection
+                .prepare(
+                    "SELECT period_start, period_end, bookmark_ids, content, emailed_at,
+                            created_at
+                     FROM digest WHERE user_id = ?1
+                     ORDER BY period_start",
+                )?
+                .query_map([user_id], |row| {
+                    Ok(DigestRecord {
+                        period_start: row.get("period_start")?,
+                        period_end: row.get("period_end")?,
+                        bookmark_ids: row.get::<_, Json<Vec<String>>>("bookmark_ids")?.0,
+                        content: row.get("content")?,
+                        emailed_at: row.get("emailed_at")?,
+                        created_at: row.get("created_at")?,
+                    })
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+
+            let collections = connection
+                .prepare(
+                    "SELECT c.collection_id, c.name, c.description, c.created_at, c.updated_at,
+                            (SELECT json_group_array(bookmark_id) FROM (
+                                SELECT cb.bookmark_id FROM collection_bookmark cb
+                                JOIN bookmark b ON b.bookmark_id = cb.bookmark_id
+                                    AND b.user_id = cb.user_id
+                                WHERE cb.collection_id = c.collection_id
+                                  AND b.deleted_at IS NULL
+                                ORDER BY cb.position
+                            )) AS bookmark_ids
+                     FROM collection c WHERE c.user_id = ?1
+                     ORDER BY c.created_at",
+                )?
+                .query_map([user_id], |row| {
+                    Ok(CollectionRecord {
+                        collection_id: row.get("collection_id")?,
+                        name: row.get("name")?,
+                        description: row.get("description")?,
+                        bookmark_ids: row.get::<_, Json<Vec<String>>>("bookmark_ids")?.0,
+                        created_at: row.get("created_at")?,
+                        updated_at: row.get("updated_at")?,
+                    })
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+
+            let rules = connection
+                .prepare(
+                    "SELECT rule_id, name, enabled, conditions, actions, created_at, updated_at
+                     FROM rule WHERE user_id = ?1
+                     ORDER BY created_at",
+                )?
+                .query_map([user_id], rule::from_row)?
+                .collect::<rusqlite::Result<_>>()?;
+
+            let extraction_rules = connection
+                .prepare(
+                    "SELECT rule_id, domain, keep_selectors, drop_selectors, title_selector,
+                            created_at, updated_at
+                     FROM extraction_rule WHERE user_id = ?1
+                     ORDER BY created_at",
+                )?
+                .query_map([user_id], extraction_rule::from_row)?
+                .collect::<rusqlite::Result<_>>()?;
+
+            let fetch_credentials = connection
+                .prepare(
+                    "SELECT domain, sealed_headers, updated_at FROM fetch_credential
+                     WHERE user_id = ?1
+                     ORDER BY domain",
+                )?
+                .query_map([user_id], |row| {
+                    Ok(FetchCredentialRecord {
+                        domain: row.get("domain")?,
+                        sealed_headers: row.get("sealed_headers")?,
+                        updated_at: row.get("updated_at")?,
+                    })
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+
+            let workspaces = connection
+                .prepare(
+                    "SELECT workspace_id, name, created_at FROM workspace
+                     WHERE owner_id = ?1
+                     ORDER BY created_at",
+                )?
+                .query_map([user_id], |row| {
+                    Ok(WorkspaceRecord {
+                        workspace_id: row.get("workspace_id")?,
+                        name: row.get("name")?,
+                        created_at: row.get("created_at")?,
+                    })
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+
+            let workspace_memberships = connection
+                .prepare(
+                    "SELECT workspace_id, role, joined_at FROM workspace_member
+                     WHERE user_id = ?1
+                     ORDER BY joined_at",
+                )?
+                .query_map([user_id], |row| {
+                    Ok(WorkspaceMembershipRecord {
+                        workspace_id: row.get("workspace_id")?,
+                        role: row.get("role")?,
+                        joined_at: row.get("joined_at")?,
+                    })
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+
+            let workspace_bookmarks = connection
+                .prepare(
+                    "SELECT wb.workspace_id, wb.bookmark_id, wb.added_at
+                     FROM workspace_bookmark wb
+                     JOIN bookmark b ON b.bookmark_id = wb.bookmark_id AND b.user_id = wb.user_id
+                     WHERE wb.user_id = ?1 AND b.deleted_at IS NULL
+                     ORDER BY wb.added_at",
+                )?
+                .query_map([user_id], |row| {
+                    Ok(WorkspaceBookmarkRecord {
+                        workspace_id: row.get("workspace_id")?,
+                        bookmark_id: row.get("bookmark_id")?,
+                        added_at: row.get("added_at")?,
+                    })
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+
+            Ok(UserDump {
+                user,
+                settings,
+                bookmarks,
+                digests,
+                collections,
+                rules,
+                extraction_rules,
+                fetch_credentials,
+                workspaces,
+                workspace_memberships,
+                workspace_bookmarks,
+            })
+        })
+        .await?;
+    debug!(user_id = %user_id, bookmark_count = dump.bookmarks.len(), "Exported user");
+    Ok(dump)
+}
+
+pub(in crate::db) async fn import_user(
+    pool: &SqlitePool,
+    dump: &UserDump,
+    with_chunks: bool,
+) -> Result<()> {
+    let user_id = dump.user.user_id;
+    let bookmark_count = dump.bookmarks.len();
+    let dump = dump.clone();
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        let now = timestamp(&Utc::now());
+
+        tx.execute(
+            r#"INSERT INTO "user"
+                   (user_id, username, password_hash, email, email_verified_at, created_at,
+                    updated_at)
+               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"#,
+            params![
+                user_id,
+                dump.user.username,
+                dump.user.password_hash,
+                dump.user.email,
+                dump.user.email_verified_at.as_ref().map(timestamp),
+                timestamp(&dump.user.created_at),
+                timestamp(&dump.user.updated_at)
+            ],
+        )
+        .on_unique("user.user_id", || {
+            Error::constraint_violation("user_pkey", "user already exists")
+        })
+        .on_unique("index 'user_username_unique'", || {
+            Error::constraint_violation("unique_username", "username already used")
+        })
+        .on_unique("index 'user_email_unique'", || {
+            Error::constraint_violation("unique_email", "email already used")
+        })?;
+
+        if let Some(settings) = &dump.settings {
+            tx.execute(
+                "INSERT INTO user_settings
+                     (user_id, auto_tagging_enabled, summaries_enabled, summary_language,
+                      preferred_text_model, digest_enabled, reader_font_family, reader_font_size,
+                      reader_line_width, reader_justify, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?11)",
+                params![
+                    user_id,
+                    settings.auto_tagging_enabled,
+                    settings.summaries_enabled,
+                    settings.summary_language,
+                    settings.preferred_text_model,
+                    settings.digest_enabled,
+                    settings.reader.font_family.as_ref(),
+                    settings.reader.font_size,
+                    settings.reader.line_width,
+                    settings.reader.justify,
+                    now
+                ],
+            )?;
+        }
+
+        for bookmark in &dump.bookmarks {
+            let with_chunks = with_chunks && !bookmark.chunks.is_empty();
+            let embedding_status = if with_chunks || bookmark.text_content.len() < 200 {
+                AiGenerationStatus::Done
+            } else {
+                AiGenerationStatus::Pending
+            };
+            let word_count = reading_time::word_count(&bookmark.text_content);
+            tx.execute(
+                "INSERT INTO bookmark
+                     (bookmark_id, user_id, url, canonical_url, domain, title, text_content, tags,
+                      summary, summary_status, tag_status, text_ai_status,
+                      text_ai_next_attempt_at, text_ai_pipeline_version, embedding_status,
+                      embedding_next_attempt_at, embedding_pipeline_version, language,
+                      text_search_config, static_bytes, created_at, updated_at, word_count,
+                      reading_time_minutes, kind, favorite, original_title, visibility,
+                      read_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?13,
+                         ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28)",
+                params![
+                    bookmark.bookmark_id,
+                    user_id,
+                    bookmark.url,
+                    bookmark.canonical_url,
+                    bookmark.domain,
+                    bookmark.title,
+                    bookmark.text_content,
+                    bookmark.tags.as_ref().map(Json),
+                    bookmark.summary,
+                    bookmark.summary_status,
+                    bookmark.tag_status,
+                    bookmark.text_ai_status,
+                    now,
+                    TEXT_AI_PIPELINE_VERSION,
+                    embedding_status,
+                    EMBEDDING_PIPELINE_VERSION,
+                    bookmark.language,
+                    bookmark.text_search_config,
+                    bookmark.static_bytes,
+                    timestamp(&bookmark.created_at),
+                    timestamp(&bookmark.updated_at),
+                    word_count,
+                    reading_time::minutes(word_count),
+                    bookmark.kind,
+                    bookmark.favorite,
+                    bookmark.original_title,
+                    bookmark.visibility.as_ref(),
+                    bookmark.read_at.as_ref().map(timestamp)
+                ],
+            )?;
+            for version in &bookmark.versions {
+                tx.execute(
+                    "INSERT INTO bookmark_version
+                         (version_id, bookmark_id, user_id, title, text_content, html, word_count,
+                          captured_at, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    params![
+                        version.version_id,
+                        bookmark.bookmark_id,
+                        user_id,
+                        version.title,
+                        version.text_content,
+                        version.html,
+                        version.word_count,
+                        timestamp(&version.captured_at),
+                        timestamp(&version.created_at)
+                    ],
+                )?;
+            }
+            for entity in &bookmark.entities {
+                tx.execute(
+                    "INSERT INTO entity (bookmark_id, user_id, kind, name)
+                     VALUES (?1, ?2, ?3, ?4)
+                     ON CONFLICT DO NOTHING",
+                    params![
+                        bookmark.bookmark_id,
+                        user_id,
+                        entity.kind.as_ref(),
+                        entity.name
+                    ],
+                )?;
+            }
+            if !with_chunks {
+                continue;
+            }
+            for chunk in &bookmark.chunks {
+                tx.execute(
+                    "INSERT INTO bookmark_chunk
+                         (chunk_id, bookmark_id, user_id, chunk_text, chunk_index, embedding,
+                          created_at, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)",
+                    params![
+                        Uuid::new_v4(),
+                        bookmark.bookmark_id,
+                        user_id,
+                        chunk.chunk_text,
+                        chunk.chunk_index,
+                        chunk.embedding.as_deref().map(vector_to_blob),
+                        now
+                    ],
+                )?;
+            }
+            let embedding = mean_vector(
+                bookmark
+                    .chunks
+                    .iter()
+                    .filter_map(|chunk| chunk.embedding.as_deref()),
+            );
+            tx.execute(
+                "UPDATE bookmark SET embedding = ?1 WHERE bookmark_id = ?2 AND user_id = ?3",
+                params![
+                    embedding.as_deref().map(vector_to_blob),
+                    bookmark.bookmark_id,
+                    user_id
+                ],
+            )?;
+        }
+
+        for digest in &dump.digests {
+            tx.execute(
+                "INSERT INTO digest
+                     (digest_id, user_id, period_start, period_end, bookmark_ids, content,
+                      emailed_at, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    Uuid::new_v4(),
+                    user_id,
+                    timestamp(&digest.period_start),
+                    timestamp(&digest.period_end),
+                    Json(&digest.bookmark_ids),
+                    digest.content,
+                    digest.emailed_at.as_ref().map(timestamp),
+                    timestamp(&digest.created_at)
+                ],
+            )?;
+        }
+
+        for collection in &dump.collections {
+            tx.execute(
+                "INSERT INTO collection
+                     (collection_id, user_id, name, description, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    collection.collection_id,
+                    user_id,
+                    collection.name,
+                    collection.description,
+                    timestamp(&collection.created_at),
+                    collection.updated_at.as_ref().map(timestamp)
+                ],
+            )?;
+            for (position, bookmark_id) in collection.bookmark_ids.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO collection_bookmark (collection_id, user_id, bookmark_id, position)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![collection.collection_id, user_id, bookmark_id, position as i64],
+                )?;
+            }
+        }
+
+        for rule in &dump.rules {
+            tx.execute(
+                "INSERT INTO rule
+                     (rule_id, user_id, name, enabled, conditions, actions, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    rule.rule_id,
+                    user_id,
+                    rule.name,
+                    rule.enabled,
+                    Json(&rule.conditions),
+                    Json(&rule.actions),
+                    timestamp(&rule.created_at),
+                    rule.updated_at.as_ref().map(timestamp)
+                ],
+            )?;
+        }
+
+        for rule in &dump.extraction_rules {
+            tx.execute(
+                "INSERT INTO extraction_rule
+                     (rule_id, user_id, domain, keep_selectors, drop_selectors, title_selector,
+                      created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    rule.rule_id,
+                    user_id,
+                    rule.domain,
+                    Json(&rule.keep_selectors),
+                    Json(&rule.drop_selectors),
+                    rule.title_selector,
+                    timestamp(&rule.created_at),
+                    rule.updated_at.as_ref().map(timestamp)
+                ],
+            )?;
+        }
+
+        for credential in &dump.fetch_credentials {
+            tx.execute(
+                "INSERT INTO fetch_credential (user_id, domain, sealed_headers, updated_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    user_id,
+                    credential.domain,
+                    credential.sealed_headers,
+                    timestamp(&credential.updated_at)
+                ],
+            )?;
+        }
+
+        for workspace in &dump.workspaces {
+            tx.execute(
+                "INSERT INTO workspace (workspace_id, owner_id, name, created_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    workspace.workspace_id,
+                    user_id,
+                    workspace.name,
+                    timestamp(&workspace.created_at)
+                ],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    })
+    .await?;
+    info!(user_id = %user_id, bookmark_count, with_chunks, "Imported user");
+    Ok(())
+}
+
+pub(in crate::db) async fn import_workspace_memberships(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    memberships: &[WorkspaceMembershipRecord],
+    bookmarks: &[WorkspaceBookmarkRecord],
+) -> Result<()> {
+    let memberships = memberships.to_vec();
+    let bookmarks = bookmarks.to_vec();
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        for membership in &memberships {
+            tx.execute(
+                "INSERT INTO workspace_member (workspace_id, user_id, role, joined_at)
+                 SELECT workspace_id, ?2, ?3, ?4 FROM workspace WHERE workspace_id = ?1
+                 ON CONFLICT DO NOTHING",
+                params![
+                    membership.workspace_id,
+                    user_id,
+                    membership.role,
+                    timestamp(&membership.joined_at)
+                ],
+            )?;
+        }
+        for bookmark in &bookmarks {
+            tx.execute(
+                "INSERT INTO workspace_bookmark (workspace_id, user_id, bookmark_id, added_at)
+                 SELECT workspace_id, ?2, ?3, ?4 FROM workspace WHERE workspace_id = ?1
+                 ON CONFLICT DO NOTHING",
+                params![
+                    bookmark.workspace_id,
+                    user_id,
+                    bookmark.bookmark_id,
+                    timestamp(&bookmark.added_at)
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    })
+    .await
+}
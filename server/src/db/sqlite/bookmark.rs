@@ -0,0 +1,821 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{params, OptionalExtension, Row};
+use shared::{Bookmark, BulkBookmarkAction, TagOperation, Visibility};
+use uuid::Uuid;
+
+use super::{string_list, timestamp, Json, SqlitePool, SqliteResultExt};
+use crate::db::bookmark::{normalized_tag_option, NewBookmark};
+use crate::error::{Error, Result};
+use crate::{EMBEDDING_PIPELINE_VERSION, TEXT_AI_PIPELINE_VERSION};
+
+pub(super) fn from_row(row: &Row) -> rusqlite::Result<Bookmark> {
+    Ok(Bookmark {
+        bookmark_id: row.get("bookmark_id")?,
+        user_id: row.get("user_id")?,
+        url: row.get("url")?,
+        domain: row.get("domain")?,
+        title: row.get("title")?,
+        tags: string_list(row, "tags")?,
+        summary: row.get("summary")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+        word_count: row.get("word_count")?,
+        reading_time_minutes: row.get("reading_time_minutes")?,
+        kind: crate::db::bookmark::parse_kind(row.get("kind")?),
+        favorite: row.get("favorite")?,
+        original_title: row.get("original_title")?,
+        visibility: crate::db::bookmark::parse_visibility(row.get("visibility")?),
+    })
+}
+
+pub(in crate::db) async fn get_tag_count_by_user(
+    pool: &SqlitePool,
+    user_id: Uuid,
+) -> Result<Vec<(String, i64)>> {
+    pool.run(move |connection| {
+        let mut statement = connection.prepare(
+            "SELECT t.value AS tag, count(1) AS counter
+             FROM bookmark b, json_each(b.tags) t
+             WHERE b.user_id = ?1 AND b.deleted_at IS NULL
+             GROUP BY t.value",
+        )?;
+        let rows = statement.query_map([user_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get_by_user(pool: &SqlitePool, user_id: Uuid) -> Result<Vec<Bookmark>> {
+    pool.run(move |connection| {
+        let mut statement = connection.prepare(
+            "SELECT * FROM bookmark b WHERE b.user_id = ?1 AND b.deleted_at IS NULL
+             ORDER BY b.created_at ASC",
+        )?;
+        let rows = statement.query_map([user_id], from_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get_by_wallabag_entry_id(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    entry_id: i64,
+) -> Result<Option<Bookmark>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                "SELECT * FROM bookmark b
+                 WHERE b.user_id = ?1 AND wallabag_entry_id(b.bookmark_id) = ?2
+                 AND b.deleted_at IS NULL ORDER BY b.created_at ASC LIMIT 1",
+                params![user_id, entry_id],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get_by_tag(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    tag: &str,
+) -> Result<Vec<Bookmark>> {
+    let tag = tag.to_string();
+    pool.run(move |connection| {
+        let mut statement = connection.prepare(
+            "SELECT * FROM bookmark b
+             WHERE b.user_id = ?1 AND b.deleted_at IS NULL
+               AND EXISTS (SELECT 1 FROM json_each(b.tags) WHERE value = ?2)
+             ORDER BY b.created_at ASC",
+        )?;
+        let rows = statement.query_map(params![user_id, tag], from_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get_random(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    tag: Option<&str>,
+    unread: bool,
+) -> Result<Option<Bookmark>> {
+    let tag = tag.map(str::to_string);
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                "SELECT * FROM bookmark b
+                 WHERE b.user_id = ?1 AND b.deleted_at IS NULL
+                   AND (?2 IS NULL OR EXISTS (SELECT 1 FROM json_each(b.tags) WHERE value = ?2))
+                   AND (NOT ?3 OR b.read_at IS NULL)
+                 ORDER BY random() LIMIT 1",
+                params![user_id, tag, unread],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get_backlinks(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<Vec<Bookmark>> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        let mut statement = connection.prepare(
+            "SELECT b.* FROM bookmark b
+             WHERE b.user_id = ?2 AND b.bookmark_id <> ?1 AND b.deleted_at IS NULL
+               AND EXISTS (
+                 SELECT 1 FROM bookmark_link l
+                 JOIN bookmark target
+                   ON target.user_id = l.user_id AND target.canonical_url = l.url
+                 WHERE l.bookmark_id = b.bookmark_id AND l.user_id = b.user_id
+                   AND target.bookmark_id = ?1
+               )
+             ORDER BY b.created_at DESC",
+        )?;
+        let rows = statement.query_map(params![bookmark_id, user_id], from_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get_by_collection(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    collection_id: Uuid,
+) -> Result<Vec<Bookmark>> {
+    pool.run(move |connection| {
+        let mut statement = connection.prepare(
+            "SELECT b.* FROM collection_bookmark cb
+             JOIN bookmark b ON b.bookmark_id = cb.bookmark_id AND b.user_id = cb.user_id
+             WHERE cb.collection_id = ?1 AND cb.user_id = ?2 AND b.deleted_at IS NULL
+             ORDER BY cb.position",
+        )?;
+        let rows = statement.query_map(params![collection_id, user_id], from_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get_on_this_day(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    today: NaiveDate,
+) -> Result<Vec<Bookmark>> {
+    pool.run(move |connection| {
+        let mut statement = connection.prepare(
+            "SELECT * FROM bookmark b
+             WHERE b.user_id = ?1
+               AND b.deleted_at IS NULL
+               AND strftime('%m-%d', b.created_at) = strftime('%m-%d', ?2)
+               AND date(b.created_at) < ?2
+             ORDER BY b.created_at DESC",
+        )?;
+        let rows = statement.query_map(params![user_id, today.to_string()], from_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn existing_canonical_urls(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    canonical_urls: &[String],
+) -> Result<HashSet<String>> {
+    let canonical_urls = canonical_urls.to_vec();
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(
+                "SELECT canonical_url FROM bookmark
+                 WHERE user_id = ?1 AND deleted_at IS NULL
+                   AND canonical_url IN (SELECT value FROM json_each(?2))",
+            )?
+            .query_map(params![user_id, Json(canonical_urls)], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get_by_canonical_url_and_user_id(
+    pool: &SqlitePool,
+    canonical_url: String,
+    user_id: Uuid,
+) -> Result<Option<Bookmark>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                "SELECT * FROM bookmark
+                 WHERE canonical_url = ?1 AND user_id = ?2 AND deleted_at IS NULL",
+                params![canonical_url, user_id],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get_with_user_data(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<Option<Bookmark>> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                "SELECT * FROM bookmark b
+                 WHERE b.user_id = ?1 AND b.bookmark_id = ?2 AND b.deleted_at IS NULL",
+                params![user_id, bookmark_id],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get_in_workspace(
+    pool: &SqlitePool,
+    workspace_id: Uuid,
+    bookmark_id: &str,
+) -> Result<Option<Bookmark>> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                "SELECT b.* FROM bookmark b
+                 JOIN workspace_bookmark wb ON wb.bookmark_id = b.bookmark_id AND wb.user_id = b.user_id
+                 WHERE wb.workspace_id = ?1 AND b.bookmark_id = ?2
+                 AND b.deleted_at IS NULL AND b.visibility <> 'private'
+                 ORDER BY wb.added_at
+                 LIMIT 1",
+                params![workspace_id, bookmark_id],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn update_tags(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+    operation: &TagOperation,
+) -> Result<Bookmark> {
+    let bookmark_id = bookmark_id.to_string();
+    let operation = operation.clone();
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        let tags = match operation {
+            TagOperation::Set(tags) => normalized_tag_option(&tags),
+            TagOperation::Append(tags) => {
+                let current: Option<Json<Vec<String>>> = tx.query_row(
+                    "SELECT tags FROM bookmark WHERE bookmark_id = ?1 AND user_id = ?2",
+                    params![bookmark_id, user_id],
+                    |row| row.get(0),
+                )?;
+                let mut merged = current.map(|Json(tags)| tags).unwrap_or_default();
+                merged.extend(normalized_tag_option(&tags).unwrap_or_default());
+                normalized_tag_option(&merged)
+            }
+        };
+        let bookmark = tx.query_row(
+            "UPDATE bookmark
+             SET tags = ?1,
+                 tag_status = 'done',
+                 text_ai_status = CASE WHEN summary_status = 'done' THEN 'done' ELSE 'pending' END,
+                 text_ai_attempts = 0,
+                 text_ai_next_attempt_at = ?2,
+                 text_ai_fail_reason = NULL,
+                 updated_at = ?2
+             WHERE bookmark_id = ?3 AND user_id = ?4
+             RETURNING *",
+            params![tags.map(Json), timestamp(&Utc::now()), bookmark_id, user_id],
+            from_row,
+        )?;
+        tx.commit()?;
+        Ok(bookmark)
+    })
+    .await
+}
+
+pub(in crate::db) async fn bulk_update(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_ids: &[String],
+    action: &BulkBookmarkAction,
+) -> Result<u64> {
+    let bookmark_ids = Json(bookmark_ids.to_vec());
+    let action = action.clone();
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        let now = timestamp(&Utc::now());
+        let updated = match action {
+            BulkBookmarkAction::AddTags { tags } => {
+                let tagged = {
+                    let mut statement = tx.prepare(
+                        "SELECT bookmark_id, tags FROM bookmark
+                         WHERE user_id = ?1 AND deleted_at IS NULL
+                           AND bookmark_id IN (SELECT value FROM json_each(?2))",
+                    )?;
+                    let rows = statement.query_map(params![user_id, bookmark_ids], |row| {
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            row.get::<_, Option<Json<Vec<String>>>>(1)?,
+                        ))
+                    })?;
+                    rows.collect::<rusqlite::Result<Vec<_>>>()?
+                };
+                let updated = tagged.len();
+                for (bookmark_id, current) in tagged {
+                    let mut merged = current.map(|Json(tags)| tags).unwrap_or_default();
+                    merged.extend(tags.iter().cloned());
+                    tx.execute(
+                        "UPDATE bookmark
+                         SET tags = ?1,
+                             tag_status = 'done',
+                             text_ai_status = CASE WHEN summary_status = 'done' THEN 'done' ELSE 'pending' END,
+                             text_ai_attempts = 0,
+                             text_ai_next_attempt_at = ?2,
+                             text_ai_fail_reason = NULL,
+                             updated_at = ?2
+                         WHERE bookmark_id = ?3 AND user_id = ?4",
+                        params![normalized_tag_option(&merged).map(Json), now, bookmark_id, user_id],
+                    )?;
+                }
+                updated
+            }
+            BulkBookmarkAction::Trash => tx.execute(
+                "UPDATE bookmark SET deleted_at = ?1
+                 WHERE user_id = ?2 AND deleted_at IS NULL
+                   AND bookmark_id IN (SELECT value FROM json_each(?3))",
+                params![now, user_id, bookmark_ids],
+            )?,
+            BulkBookmarkAction::Favorite | BulkBookmarkAction::Unfavorite => tx.execute(
+                "UPDATE bookmark SET favorite = ?1
+                 WHERE user_id = ?2 AND deleted_at IS NULL
+                   AND bookmark_id IN (SELECT value FROM json_each(?3))",
+                params![
+                    matches!(action, BulkBookmarkAction::Favorite),
+                    user_id,
+                    bookmark_ids
+                ],
+            )?,
+        };
+        tx.commit()?;
+        Ok(updated as u64)
+    })
+    .await
+}
+
+pub(in crate::db) async fn rename_tag(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    from: &str,
+    to: &str,
+) -> Result<u64> {
+    let (from, to) = (from.to_string(), to.to_string());
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        let tagged = {
+            let mut statement = tx.prepare(
+                "SELECT b.bookmark_id, b.tags FROM bookmark b
+                 WHERE b.user_id = ?1 AND EXISTS (SELECT 1 FROM json_each(b.tags) t WHERE t.value = ?2)",
+            )?;
+            let rows = statement.query_map(params![user_id, from], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, Json<Vec<String>>>(1)?))
+            })?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()?
+        };
+        let now = timestamp(&Utc::now());
+        for (bookmark_id, Json(tags)) in &tagged {
+            let tags: Vec<String> = tags
+                .iter()
+                .map(|tag| if *tag == from { to.clone() } else { tag.clone() })
+                .collect();
+            tx.execute(
+                "UPDATE bookmark SET tags = ?1, updated_at = ?2
+                 WHERE bookmark_id = ?3 AND user_id = ?4",
+                params![normalized_tag_option(&tags).map(Json), now, bookmark_id, user_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(tagged.len() as u64)
+    })
+    .await
+}
+
+pub(in crate::db) async fn save(
+    pool: &SqlitePool,
+    bookmark: &Bookmark,
+    text_content: &str,
+    new: NewBookmark,
+) -> Result<Bookmark> {
+    let bookmark = bookmark.clone();
+    let text_content = text_content.to_string();
+    pool.run(move |connection| {
+        connection
+            .query_row(
+                "INSERT INTO bookmark
+                    (bookmark_id, user_id, url, canonical_url, domain, title, text_content, tags,
+                     summary, summary_status, tag_status, text_ai_status, text_ai_attempts,
+                     text_ai_next_attempt_at, text_ai_pipeline_version, embedding_status,
+                     embedding_attempts, embedding_next_attempt_at, embedding_pipeline_version,
+                     language, text_search_config, word_count, reading_time_minutes, favorite,
+                     created_at, updated_at)
+                 VALUES
+                    (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, 0, ?13, ?14, ?15, 0, ?13,
+                     ?16, ?17, ?18, ?19, ?20, ?21, ?13, ?13)
+                 RETURNING *",
+                params![
+                    bookmark.bookmark_id,
+                    bookmark.user_id,
+                    bookmark.url,
+                    new.canonical_url,
+                    bookmark.domain,
+                    bookmark.title,
+                    text_content,
+                    new.tags.map(Json),
+                    new.summary,
+                    new.summary_status,
+                    new.tag_status,
+                    new.text_ai_status,
+                    timestamp(&Utc::now()),
+                    TEXT_AI_PIPELINE_VERSION,
+                    new.embedding_status,
+                    EMBEDDING_PIPELINE_VERSION,
+                    new.language,
+                    new.text_search_config,
+                    new.word_count,
+                    new.reading_time_minutes,
+                    bookmark.favorite,
+                ],
+                from_row,
+            )
+            .on_unique("bookmark.user_id, bookmark.canonical_url", || {
+                Error::constraint_violation(
+                    "duplicate_bookmark",
+                    "bookmark already exists for user",
+                )
+            })
+            .on_unique("bookmark.bookmark_id, bookmark.user_id", || {
+                Error::constraint_violation(
+                    "duplicate_bookmark",
+                    "bookmark already exists for user",
+                )
+            })
+    })
+    .await
+}
+
+pub(in crate::db) async fn refresh_capture(
+    pool: &SqlitePool,
+    bookmark: &Bookmark,
+    text_content: &str,
+    html: Option<&str>,
+    new: NewBookmark,
+) -> Result<Bookmark> {
+    let bookmark = bookmark.clone();
+    let text_content = text_content.to_string();
+    let html = html.map(str::to_string);
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        let now = timestamp(&Utc::now());
+        let kept = tx.execute(
+            "INSERT INTO bookmark_version
+                (version_id, bookmark_id, user_id, title, text_content, html, word_count,
+                 captured_at, created_at)
+             SELECT ?1, bookmark_id, user_id, title, text_content, ?4, word_count,
+                coalesce(captured_at, created_at), ?5
+             FROM bookmark WHERE bookmark_id = ?2 AND user_id = ?3",
+            params![
+                Uuid::new_v4(),
+                bookmark.bookmark_id,
+                bookmark.user_id,
+                html,
+                now
+            ],
+        )?;
+        if kept == 0 {
+            return Err(Error::NotFound);
+        }
+        let refreshed = tx.query_row(
+            "UPDATE bookmark
+             SET title = CASE WHEN original_title IS NULL THEN ?3 ELSE title END,
+                 original_title = CASE WHEN original_title IS NULL THEN NULL ELSE ?3 END,
+                 text_content = ?4,
+                 language = ?5,
+                 text_search_config = ?6,
+                 word_count = ?7,
+                 reading_time_minutes = ?8,
+                 summary_status = 'pending',
+                 text_ai_status = 'pending',
+                 text_ai_attempts = 0,
+                 text_ai_next_attempt_at = ?10,
+                 text_ai_fail_reason = NULL,
+                 embedding_status = ?9,
+                 embedding_attempts = 0,
+                 embedding_next_attempt_at = ?10,
+                 embedding_fail_reason = NULL,
+                 captured_at = ?10,
+                 updated_at = ?10
+             WHERE bookmark_id = ?1 AND user_id = ?2
+             RETURNING *",
+            params![
+                bookmark.bookmark_id,
+                bookmark.user_id,
+                bookmark.title,
+                text_content,
+                new.language,
+                new.text_search_config,
+                new.word_count,
+                new.reading_time_minutes,
+                new.embedding_status,
+                now
+            ],
+            from_row,
+        )?;
+        tx.commit()?;
+        Ok(refreshed)
+    })
+    .await
+}
+
+pub(in crate::db) async fn update_summary(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+    summary: Option<&str>,
+) -> Result<Bookmark> {
+    let bookmark_id = bookmark_id.to_string();
+    let summary = summary.map(str::to_string);
+    pool.run(move |connection| {
+        Ok(connection.query_row(
+            "UPDATE bookmark
+             SET summary = ?1,
+                 summary_status = 'done',
+                 text_ai_status = CASE WHEN tag_status = 'done' THEN 'done' ELSE 'pending' END,
+                 text_ai_attempts = 0,
+                 text_ai_next_attempt_at = ?2,
+                 text_ai_fail_reason = NULL,
+                 updated_at = ?2
+             WHERE bookmark_id = ?3 AND user_id = ?4
+             RETURNING *",
+            params![summary, timestamp(&Utc::now()), bookmark_id, user_id],
+            from_row,
+        )?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get_text_content(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<Option<String>> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                "SELECT text_content FROM bookmark
+                 WHERE bookmark_id = ?1 AND user_id = ?2 AND deleted_at IS NULL",
+                params![bookmark_id, user_id],
+                |row| row.get(0),
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn mark_read(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<bool> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        let rows_affected = connection.execute(
+            "UPDATE bookmark SET read_at = COALESCE(read_at, ?1)
+             WHERE bookmark_id = ?2 AND user_id = ?3 AND deleted_at IS NULL",
+            params![timestamp(&Utc::now()), bookmark_id, user_id],
+        )?;
+        Ok(rows_affected > 0)
+    })
+    .await
+}
+
+pub(in crate::db) async fn move_to_trash(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<bool> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        let rows_affected = connection.execute(
+            "UPDATE bookmark SET deleted_at = ?1
+             WHERE bookmark_id = ?2 AND user_id = ?3 AND deleted_at IS NULL",
+            params![timestamp(&Utc::now()), bookmark_id, user_id],
+        )?;
+        Ok(rows_affected > 0)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get_trash(
+    pool: &SqlitePool,
+    user_id: Uuid,
+) -> Result<Vec<(Bookmark, DateTime<Utc>)>> {
+    pool.run(move |connection| {
+        let mut statement = connection.prepare(
+            "SELECT * FROM bookmark b WHERE b.user_id = ?1 AND b.deleted_at IS NOT NULL
+             ORDER BY b.deleted_at DESC",
+        )?;
+        let rows = statement.query_map([user_id], |row| {
+            Ok((from_row(row)?, row.get("deleted_at")?))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn restore(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<Option<Bookmark>> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                "UPDATE bookmark SET deleted_at = NULL
+                 WHERE bookmark_id = ?1 AND user_id = ?2 AND deleted_at IS NOT NULL
+                 RETURNING *",
+                params![bookmark_id, user_id],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn edit(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+    title: Option<String>,
+    url: Option<String>,
+    canonical_url: Option<String>,
+    domain: Option<String>,
+) -> Result<Option<Bookmark>> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        connection
+            .query_row(
+                "UPDATE bookmark
+                 SET original_title = CASE
+                         WHEN ?3 IS NULL OR ?3 = title THEN original_title
+                         ELSE coalesce(original_title, title)
+                     END,
+                     title = coalesce(?3, title),
+                     url = coalesce(?4, url),
+                     canonical_url = coalesce(?5, canonical_url),
+                     domain = coalesce(?6, domain),
+                     updated_at = ?7
+                 WHERE bookmark_id = ?1 AND user_id = ?2 AND deleted_at IS NULL
+                 RETURNING *",
+                params![
+                    bookmark_id,
+                    user_id,
+                    title,
+                    url,
+                    canonical_url,
+                    domain,
+                    timestamp(&Utc::now())
+                ],
+                from_row,
+            )
+            .optional()
+            .on_unique("bookmark.user_id, bookmark.canonical_url", || {
+                Error::constraint_violation(
+                    "duplicate_bookmark",
+                    "bookmark already exists for user",
+                )
+            })
+    })
+    .await
+}
+
+pub(in crate::db) async fn set_favorite(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+    favorite: bool,
+) -> Result<Option<Bookmark>> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                "UPDATE bookmark SET favorite = ?3
+                 WHERE bookmark_id = ?1 AND user_id = ?2 AND deleted_at IS NULL
+                 RETURNING *",
+                params![bookmark_id, user_id, favorite],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn set_visibility(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+    visibility: Visibility,
+) -> Result<Option<Bookmark>> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                "UPDATE bookmark SET visibility = ?3
+                 WHERE bookmark_id = ?1 AND user_id = ?2 AND deleted_at IS NULL
+                 RETURNING *",
+                params![bookmark_id, user_id, visibility.as_ref()],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn restore_by_canonical_url(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    canonical_url: String,
+) -> Result<bool> {
+    pool.run(move |connection| {
+        let rows_affected = connection.execute(
+            "UPDATE bookmark SET deleted_at = NULL
+             WHERE canonical_url = ?1 AND user_id = ?2 AND deleted_at IS NOT NULL",
+            params![canonical_url, user_id],
+        )?;
+        Ok(rows_affected > 0)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get_expired_trash(
+    pool: &SqlitePool,
+    deleted_before: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<(Uuid, String)>> {
+    pool.run(move |connection| {
+        let mut statement = connection.prepare(
+            "SELECT user_id, bookmark_id FROM bookmark
+             WHERE deleted_at < ?1 ORDER BY deleted_at LIMIT ?2",
+        )?;
+        let rows = statement.query_map(params![timestamp(&deleted_before), limit], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn purge(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<bool> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        let rows_affected = connection.execute(
+            "DELETE FROM bookmark
+             WHERE bookmark_id = ?1 AND user_id = ?2 AND deleted_at IS NOT NULL",
+            params![bookmark_id, user_id],
+        )?;
+        Ok(rows_affected > 0)
+    })
+    .await
+}
+
+pub(in crate::db) async fn delete(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<bool> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        let rows_affected = connection.execute(
+            "DELETE FROM bookmark WHERE bookmark_id = ?1 AND user_id = ?2",
+            params![bookmark_id, user_id],
+        )?;
+        Ok(rows_affected > 0)
+    })
+    .await
+}
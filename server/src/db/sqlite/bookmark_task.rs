@@ -0,0 +1,520 @@
+use chrono::{DateTime, Utc};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef};
+use rusqlite::{params, params_from_iter, OptionalExtension, Row, ToSql};
+use shared::{
+    BookmarkTask, BookmarkTaskSearchRequest, BookmarkTaskSearchResponse, BookmarkTaskStatus,
+    BulkRetryTasksRequest, DeadLetterGroup, DeadLetterResponse, TaskStatusCount,
+};
+use url::Url;
+use uuid::Uuid;
+
+use super::{string_list, timestamp, Json, Params, SqlitePool};
+use crate::db::bookmark_task::search_response;
+use crate::error::Result;
+
+struct Status(BookmarkTaskStatus);
+
+impl ToSql for Status {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(match self.0 {
+            BookmarkTaskStatus::Pending => "pending",
+            BookmarkTaskStatus::Done => "done",
+            BookmarkTaskStatus::Fail => "fail",
+            BookmarkTaskStatus::Cancelled => "cancelled",
+        }))
+    }
+}
+
+impl FromSql for Status {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "pending" => Ok(Status(BookmarkTaskStatus::Pending)),
+            "done" => Ok(Status(BookmarkTaskStatus::Done)),
+            "fail" => Ok(Status(BookmarkTaskStatus::Fail)),
+            "cancelled" => Ok(Status(BookmarkTaskStatus::Cancelled)),
+            other => Err(FromSqlError::Other(
+                format!("unknown task status: {other}").into(),
+            )),
+        }
+    }
+}
+
+fn from_row(row: &Row) -> rusqlite::Result<BookmarkTask> {
+    Ok(BookmarkTask {
+        task_id: row.get("task_id")?,
+        user_id: row.get("user_id")?,
+        url: row.get("url")?,
+        status: row.get::<_, Status>("status")?.0,
+        tags: string_list(row, "tags")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+        next_delivery: row.get("next_delivery")?,
+        retries: row.get("retries")?,
+        fail_reason: row.get("fail_reason")?,
+        ignore_robots: row.get("ignore_robots")?,
+        refresh: row.get("refresh")?,
+    })
+}
+
+pub(in crate::db) async fn create_with_robots_override(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    url: Url,
+    tags: Vec<String>,
+    ignore_robots: bool,
+) -> Result<BookmarkTask> {
+    pool.run(move |connection| {
+        Ok(connection.query_row(
+            "INSERT INTO bookmark_task
+                (task_id, user_id, url, status, tags, ignore_robots, created_at, updated_at,
+                 next_delivery)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7, ?7)
+             RETURNING *",
+            params![
+                Uuid::new_v4(),
+                user_id,
+                url.to_string(),
+                Status(BookmarkTaskStatus::Pending),
+                Json(tags),
+                ignore_robots,
+                timestamp(&Utc::now())
+            ],
+            from_row,
+        )?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn create_batch(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    urls: Vec<Url>,
+    tags: Vec<String>,
+    ignore_robots: bool,
+) -> Result<Vec<BookmarkTask>> {
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        let now = timestamp(&Utc::now());
+        let mut tasks = Vec::with_capacity(urls.len());
+        {
+            let mut statement = tx.prepare(
+                "INSERT INTO bookmark_task
+                    (task_id, user_id, url, status, tags, ignore_robots, created_at, updated_at,
+                     next_delivery)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7, ?7)
+                 RETURNING *",
+            )?;
+            for url in urls {
+                tasks.push(statement.query_row(
+                    params![
+                        Uuid::new_v4(),
+                        user_id,
+                        url.to_string(),
+                        Status(BookmarkTaskStatus::Pending),
+                        Json(&tags),
+                        ignore_robots,
+                        now
+                    ],
+                    from_row,
+                )?);
+            }
+        }
+        tx.commit()?;
+        Ok(tasks)
+    })
+    .await
+}
+
+pub(in crate::db) async fn create_refresh(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    url: Url,
+) -> Result<BookmarkTask> {
+    pool.run(move |connection| {
+        Ok(connection.query_row(
+            "INSERT INTO bookmark_task
+                (task_id, user_id, url, status, refresh, created_at, updated_at, next_delivery)
+             VALUES (?1, ?2, ?3, ?4, TRUE, ?5, ?5, ?5)
+             RETURNING *",
+            params![
+                Uuid::new_v4(),
+                user_id,
+                url.to_string(),
+                Status(BookmarkTaskStatus::Pending),
+                timestamp(&Utc::now())
+            ],
+            from_row,
+        )?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn lease(
+    pool: &SqlitePool,
+    owner: &str,
+    now: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<BookmarkTask>> {
+    let owner = owner.to_string();
+    pool.run(move |connection| {
+        let now = timestamp(&now);
+        Ok(connection
+            .prepare(
+                "UPDATE bookmark_task SET lease_owner = ?2, lease_expires_at = ?3
+                 WHERE task_id IN (
+                     SELECT task_id FROM bookmark_task
+                     WHERE status = 'pending' AND next_delivery <= ?1
+                       AND (lease_expires_at IS NULL OR lease_expires_at <= ?1)
+                     ORDER BY next_delivery
+                     LIMIT ?4
+                 )
+                 RETURNING *",
+            )?
+            .query_map(params![now, owner, timestamp(&expires_at), limit], from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn renew_leases(
+    pool: &SqlitePool,
+    owner: &str,
+    task_ids: &[Uuid],
+    expires_at: DateTime<Utc>,
+) -> Result<u64> {
+    let owner = owner.to_string();
+    let task_ids = task_ids.to_vec();
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        let mut renewed = 0;
+        {
+            let mut statement = tx.prepare(
+                "UPDATE bookmark_task SET lease_expires_at = ?1
+                 WHERE task_id = ?2 AND lease_owner = ?3 AND status = 'pending'",
+            )?;
+            let expires_at = timestamp(&expires_at);
+            for task_id in task_ids {
+                renewed += statement.execute(params![expires_at, task_id, owner])? as u64;
+            }
+        }
+        tx.commit()?;
+        Ok(renewed)
+    })
+    .await
+}
+
+pub(in crate::db) async fn update(
+    pool: &SqlitePool,
+    owner: &str,
+    task_id: Uuid,
+    status: BookmarkTaskStatus,
+    retries: Option<i16>,
+    fail_reason: Option<String>,
+) -> Result<bool> {
+    let owner = owner.to_string();
+    pool.run(move |connection| {
+        let updated = connection.execute(
+            "UPDATE bookmark_task
+             SET status = ?1, retries = ?2, fail_reason = ?3, updated_at = ?4,
+                 lease_owner = NULL, lease_expires_at = NULL
+             WHERE task_id = ?5 AND status = 'pending' AND lease_owner = ?6",
+            params![
+                Status(status),
+                retries,
+                fail_reason,
+                timestamp(&Utc::now()),
+                task_id,
+                owner
+            ],
+        )?;
+        Ok(updated > 0)
+    })
+    .await
+}
+
+pub(in crate::db) async fn schedule_retry(
+    pool: &SqlitePool,
+    owner: &str,
+    task_id: Uuid,
+    retries: i16,
+    next_delivery: DateTime<Utc>,
+) -> Result<bool> {
+    let owner = owner.to_string();
+    pool.run(move |connection| {
+        let updated = connection.execute(
+            "UPDATE bookmark_task SET retries = ?1, next_delivery = ?2, updated_at = ?3,
+                 lease_owner = NULL, lease_expires_at = NULL
+             WHERE task_id = ?4 AND status = 'pending' AND lease_owner = ?5",
+            params![
+                retries,
+                timestamp(&next_delivery),
+                timestamp(&Utc::now()),
+                task_id,
+                owner
+            ],
+        )?;
+        Ok(updated > 0)
+    })
+    .await
+}
+
+pub(in crate::db) async fn set_bookmark_id(
+    pool: &SqlitePool,
+    task_id: Uuid,
+    bookmark_id: &str,
+) -> Result<()> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        connection.execute(
+            "UPDATE bookmark_task SET bookmark_id = ?1 WHERE task_id = ?2",
+            params![bookmark_id, task_id],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+pub(in crate::db) async fn get_by_id(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    task_id: Uuid,
+) -> Result<Option<BookmarkTask>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                "SELECT * FROM bookmark_task WHERE task_id = ?1 AND user_id = ?2",
+                params![task_id, user_id],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn retry(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    task_id: Uuid,
+) -> Result<Option<BookmarkTask>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                "UPDATE bookmark_task
+                 SET status = 'pending', retries = NULL, fail_reason = NULL,
+                     next_delivery = ?3, updated_at = ?3,
+                     lease_owner = NULL, lease_expires_at = NULL
+                 WHERE task_id = ?1 AND user_id = ?2 AND status = 'fail'
+                 RETURNING *",
+                params![task_id, user_id, timestamp(&Utc::now())],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn cancel(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    task_id: Uuid,
+) -> Result<Option<BookmarkTask>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                "UPDATE bookmark_task
+                 SET status = 'cancelled', updated_at = ?3,
+                     lease_owner = NULL, lease_expires_at = NULL
+                 WHERE task_id = ?1 AND user_id = ?2 AND status = 'pending'
+                 RETURNING *",
+                params![task_id, user_id, timestamp(&Utc::now())],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn delete_finished(
+    pool: &SqlitePool,
+    updated_before: DateTime<Utc>,
+    limit: i64,
+) -> Result<u64> {
+    pool.run(move |connection| {
+        let deleted = connection.execute(
+            "DELETE FROM bookmark_task WHERE task_id IN (
+                 SELECT task_id FROM bookmark_task
+                 WHERE status IN ('done', 'fail', 'cancelled') AND updated_at < ?1
+                 LIMIT ?2
+             )",
+            params![timestamp(&updated_before), limit],
+        )?;
+        Ok(deleted as u64)
+    })
+    .await
+}
+
+pub(in crate::db) async fn dead_letter(
+    pool: &SqlitePool,
+    user_id: Uuid,
+) -> Result<DeadLetterResponse> {
+    pool.run(move |connection| {
+        let groups = connection
+            .prepare(
+                "SELECT coalesce(fail_reason, '') AS fail_reason,
+                    COUNT(*) AS task_count,
+                    MIN(updated_at) AS first_failed_at,
+                    MAX(updated_at) AS last_failed_at
+                 FROM bookmark_task
+                 WHERE user_id = ?1 AND status = 'fail'
+                 GROUP BY 1
+                 ORDER BY task_count DESC, last_failed_at DESC",
+            )?
+            .query_map([user_id], |row| {
+                Ok(DeadLetterGroup {
+                    fail_reason: row.get("fail_reason")?,
+                    task_count: row.get("task_count")?,
+                    first_failed_at: row.get("first_failed_at")?,
+                    last_failed_at: row.get("last_failed_at")?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let total_count = groups.iter().map(|group| group.task_count).sum();
+        Ok(DeadLetterResponse {
+            groups,
+            total_count,
+        })
+    })
+    .await
+}
+
+/// Filters shared by [`retry_matching`] and [`search`].
+fn push_filters(
+    filters: &mut Vec<String>,
+    params: &mut Params,
+    url: &Option<String>,
+    tags: &Option<Vec<String>>,
+    from_created_at: &Option<DateTime<Utc>>,
+    to_created_at: &Option<DateTime<Utc>>,
+) {
+    if let Some(url) = url {
+        params.push(Box::new(url.clone()));
+        filters.push(format!("instr(url, ?{}) > 0", params.len()));
+    }
+    if let Some(tags) = tags.as_ref().filter(|tags| !tags.is_empty()) {
+        params.push(Box::new(Json(tags.clone())));
+        filters.push(format!(
+            "NOT EXISTS (SELECT 1 FROM json_each(?{}) wanted
+                WHERE wanted.value NOT IN (SELECT value FROM json_each(tags)))",
+            params.len()
+        ));
+    }
+    if let Some(from_created_at) = from_created_at {
+        params.push(Box::new(timestamp(from_created_at)));
+        filters.push(format!("created_at >= ?{}", params.len()));
+    }
+    if let Some(to_created_at) = to_created_at {
+        params.push(Box::new(timestamp(to_created_at)));
+        filters.push(format!("created_at <= ?{}", params.len()));
+    }
+}
+
+pub(in crate::db) async fn retry_matching(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    request: &BulkRetryTasksRequest,
+) -> Result<Vec<BookmarkTask>> {
+    let mut filters: Vec<String> = vec!["user_id = ?1".into(), "status = 'fail'".into()];
+    let mut params: Params = vec![Box::new(user_id), Box::new(timestamp(&Utc::now()))];
+    if let Some(fail_reason) = &request.fail_reason {
+        params.push(Box::new(fail_reason.clone()));
+        filters.push(format!("coalesce(fail_reason, '') = ?{}", params.len()));
+    }
+    push_filters(
+        &mut filters,
+        &mut params,
+        &request.url,
+        &request.tags,
+        &request.from_created_at,
+        &request.to_created_at,
+    );
+    let sql = format!(
+        "UPDATE bookmark_task
+         SET status = 'pending', retries = NULL, fail_reason = NULL,
+             next_delivery = ?2, updated_at = ?2,
+             lease_owner = NULL, lease_expires_at = NULL
+         WHERE {}
+         RETURNING *",
+        filters.join(" AND ")
+    );
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(&sql)?
+            .query_map(params_from_iter(params), from_row)?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn search(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    request: &BookmarkTaskSearchRequest,
+) -> Result<BookmarkTaskSearchResponse> {
+    let mut filters: Vec<String> = vec!["user_id = ?1".into()];
+    let mut params: Params = vec![Box::new(user_id)];
+    push_filters(
+        &mut filters,
+        &mut params,
+        &request.url,
+        &request.tags,
+        &request.from_created_at,
+        &request.to_created_at,
+    );
+    let count_sql = format!(
+        "SELECT status, count(1) AS task_count FROM bookmark_task WHERE {} GROUP BY status",
+        filters.join(" AND ")
+    );
+    let count_params_len = params.len();
+    if let Some(status) = &request.status {
+        params.push(Box::new(Status(status.clone())));
+        filters.push(format!("status = ?{}", params.len()));
+    }
+    if let Some(last_task_id) = request.last_task_id {
+        params.push(Box::new(last_task_id));
+        filters.push(format!("task_id > ?{}", params.len()));
+    }
+
+    let page_size = request.page_size.unwrap_or(25) as usize;
+    let sql = format!(
+        "SELECT * FROM bookmark_task WHERE {} ORDER BY task_id ASC LIMIT {}",
+        filters.join(" AND "),
+        page_size + 1
+    );
+    let include_total = request.include_total;
+    let (mut tasks, status_counts): (Vec<BookmarkTask>, Vec<TaskStatusCount>) = pool
+        .run(move |connection| {
+            let tasks = connection
+                .prepare(&sql)?
+                .query_map(params_from_iter(params.iter()), from_row)?
+                .collect::<rusqlite::Result<_>>()?;
+            let status_counts = if include_total {
+                connection
+                    .prepare(&count_sql)?
+                    .query_map(params_from_iter(&params[..count_params_len]), |row| {
+                        Ok(TaskStatusCount {
+                            status: row.get::<_, Status>("status")?.0,
+                            count: row.get("task_count")?,
+                        })
+                    })?
+                    .collect::<rusqlite::Result<_>>()?
+            } else {
+                Vec::new()
+            };
+            Ok((tasks, status_counts))
+        })
+        .await?;
+    let has_more = tasks.len() > page_size;
+    tasks.truncate(page_size);
+    Ok(search_response(request, tasks, has_more, status_counts))
+}
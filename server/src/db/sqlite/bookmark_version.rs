@@ -0,0 +1,61 @@
+use rusqlite::{params, OptionalExtension, Row};
+use shared::{BookmarkVersion, BookmarkVersionContent};
+use uuid::Uuid;
+
+use super::SqlitePool;
+use crate::error::Result;
+
+fn from_row(row: &Row) -> rusqlite::Result<BookmarkVersion> {
+    Ok(BookmarkVersion {
+        version_id: row.get("version_id")?,
+        title: row.get("title")?,
+        word_count: row.get("word_count")?,
+        captured_at: row.get("captured_at")?,
+    })
+}
+
+pub(in crate::db) async fn list(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<Vec<BookmarkVersion>> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(
+                "SELECT version_id, title, word_count, captured_at FROM bookmark_version
+                 WHERE bookmark_id = ?1 AND user_id = ?2
+                 ORDER BY captured_at DESC",
+            )?
+            .query_map(params![bookmark_id, user_id], from_row)?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+    version_id: Uuid,
+) -> Result<Option<BookmarkVersionContent>> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                "SELECT version_id, title, word_count, captured_at, text_content, html
+                 FROM bookmark_version
+                 WHERE version_id = ?1 AND bookmark_id = ?2 AND user_id = ?3",
+                params![version_id, bookmark_id, user_id],
+                |row| {
+                    Ok(BookmarkVersionContent {
+                        version: from_row(row)?,
+                        text_content: row.get("text_content")?,
+                        html: row.get("html")?,
+                    })
+                },
+            )
+            .optional()?)
+    })
+    .await
+}
@@ -0,0 +1,556 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, params_from_iter, Row};
+use shared::{Bookmark, BookmarkChunk, ChunkDetail, RagChunkMatch, RelatedBookmark};
+use uuid::Uuid;
+
+use super::{
+    blob_to_vector, fts_query, mean_vector, string_list, tag_condition, timestamp, vector_to_blob,
+    FtsQuery, Json, Params, SqlitePool,
+};
+use crate::db::chunks::{chunk_embedding, ChunkScope, HybridChunkMatch};
+use crate::db::EmbeddingProfile;
+use crate::error::Result;
+
+/// Columns read by [`chunk_from_row`] and [`bookmark_from_row`], for the
+/// chunk alias `c` joined with its bookmark `b`.
+const CHUNK_WITH_BOOKMARK_COLUMNS: &str = "
+    c.chunk_id, c.bookmark_id, c.user_id, c.chunk_text, c.chunk_index, c.created_at,
+    c.updated_at, b.url, b.domain, b.title, b.tags, b.summary, b.word_count,
+    b.reading_time_minutes, b.kind, b.favorite, b.original_title, b.visibility,
+    b.created_at AS bookmark_created_at, b.updated_at AS bookmark_updated_at";
+
+fn chunk_from_row(row: &Row) -> rusqlite::Result<BookmarkChunk> {
+    Ok(BookmarkChunk {
+        chunk_id: row.get("chunk_id")?,
+        bookmark_id: row.get("bookmark_id")?,
+        user_id: row.get("user_id")?,
+        chunk_text: row.get("chunk_text")?,
+        chunk_index: row.get("chunk_index")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn bookmark_from_row(row: &Row) -> rusqlite::Result<Bookmark> {
+    Ok(Bookmark {
+        bookmark_id: row.get("bookmark_id")?,
+        user_id: row.get("user_id")?,
+        url: row.get("url")?,
+        domain: row.get("domain")?,
+        title: row.get("title")?,
+        tags: string_list(row, "tags")?,
+        summary: row.get("summary")?,
+        created_at: row.get("bookmark_created_at")?,
+        updated_at: row.get("bookmark_updated_at")?,
+        word_count: row.get("word_count")?,
+        reading_time_minutes: row.get("reading_time_minutes")?,
+        kind: crate::db::bookmark::parse_kind(row.get("kind")?),
+        favorite: row.get("favorite")?,
+        original_title: row.get("original_title")?,
+        visibility: crate::db::bookmark::parse_visibility(row.get("visibility")?),
+    })
+}
+
+impl ChunkScope<'_> {
+    /// Same as `owner_condition`, for SQLite.
+    fn sqlite_owner_condition(&self, params: &mut Params) -> String {
+        match self.workspace_id {
+            Some(workspace_id) => {
+                params.push(Box::new(*workspace_id));
+                format!(
+                    "EXISTS (SELECT 1 FROM workspace_bookmark wb \
+                 JOIN workspace_member wm ON wm.workspace_id = wb.workspace_id AND wm.user_id = ?1 \
+                 JOIN bookmark vb ON vb.bookmark_id = wb.bookmark_id AND vb.user_id = wb.user_id \
+                 WHERE wb.workspace_id = ?{} AND wb.bookmark_id = c.bookmark_id \
+                 AND vb.visibility <> 'private' \
+                 AND wb.user_id = c.user_id)",
+                    params.len()
+                )
+            }
+            None => "c.user_id = ?1".to_string(),
+        }
+    }
+
+    /// Same as `push_filters`, for SQLite.
+    fn push_sqlite_filters(&self, clause: &mut String, params: &mut Params) {
+        if let Some(bookmark_ids) = self.bookmark_ids {
+            params.push(Box::new(Json(bookmark_ids.clone())));
+            clause.push_str(&format!(
+                " AND b.bookmark_id IN (SELECT value FROM json_each(?{}))",
+                params.len()
+            ));
+        }
+        if let Some(condition) = self
+            .tags_filter
+            .and_then(|filter| tag_condition(filter, params))
+        {
+            clause.push_str(" AND ");
+            clause.push_str(&condition);
+        }
+    }
+}
+
+/// `user_id` as `?1` followed by one placeholder per chunk id.
+fn chunk_id_params(user_id: Uuid, chunk_ids: &[Uuid]) -> (Params, String) {
+    let mut params: Params = vec![Box::new(user_id)];
+    params.extend(
+        chunk_ids
+            .iter()
+            .map(|chunk_id| Box::new(*chunk_id) as Box<dyn rusqlite::ToSql + Send>),
+    );
+    let placeholders = (2..=params.len())
+        .map(|idx| format!("?{idx}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    (params, placeholders)
+}
+
+pub(in crate::db) async fn get_cached_embeddings(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    profile: &EmbeddingProfile,
+    content_hashes: &[String],
+) -> Result<HashMap<String, Vec<f32>>> {
+    let profile = profile.clone();
+    let content_hashes = content_hashes.to_vec();
+    pool.run(move |connection| {
+        let mut statement = connection.prepare(
+            "UPDATE embedding_cache SET last_used_at = ?1
+             WHERE user_id = ?2 AND provider = ?3 AND model = ?4 AND dimensions = ?5
+               AND content_hash IN (SELECT value FROM json_each(?6))
+             RETURNING content_hash, embedding",
+        )?;
+        let cached = statement
+            .query_map(
+                params![
+                    timestamp(&Utc::now()),
+                    user_id,
+                    profile.provider,
+                    profile.model,
+                    profile.dimensions as i64,
+                    Json(content_hashes)
+                ],
+                |row| {
+                    let embedding: Vec<u8> = row.get("embedding")?;
+                    Ok((row.get("content_hash")?, blob_to_vector(&embedding)))
+                },
+            )?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(cached)
+    })
+    .await
+}
+
+pub(in crate::db) async fn cache_embeddings(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    profile: &EmbeddingProfile,
+    entries: Vec<(String, Vec<f32>)>,
+) -> Result<()> {
+    let profile = profile.clone();
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        let now = timestamp(&Utc::now());
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO embedding_cache
+                    (user_id, provider, model, dimensions, content_hash, embedding,
+                     created_at, last_used_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)
+                 ON CONFLICT DO NOTHING",
+            )?;
+            for (content_hash, embedding) in &entries {
+                insert.execute(params![
+                    user_id,
+                    profile.provider,
+                    profile.model,
+                    profile.dimensions as i64,
+                    content_hash,
+                    vector_to_blob(embedding),
+                    now
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    })
+    .await
+}
+
+pub(in crate::db) async fn delete_cached_embeddings_before(
+    pool: &SqlitePool,
+    used_before: DateTime<Utc>,
+    limit: i64,
+) -> Result<u64> {
+    pool.run(move |connection| {
+        let deleted = connection.execute(
+            "DELETE FROM embedding_cache WHERE rowid IN (
+                 SELECT rowid FROM embedding_cache WHERE last_used_at < ?1 LIMIT ?2
+             )",
+            params![timestamp(&used_before), limit],
+        )?;
+        Ok(deleted as u64)
+    })
+    .await
+}
+
+pub(in crate::db) async fn store_chunks_with_embeddings(
+    pool: &SqlitePool,
+    bookmark_id: &str,
+    user_id: Uuid,
+    chunks: Vec<String>,
+    embeddings: Vec<Vec<f32>>,
+) -> Result<Vec<BookmarkChunk>> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        tx.execute(
+            "DELETE FROM bookmark_chunk WHERE bookmark_id = ?1 AND user_id = ?2",
+            params![bookmark_id, user_id],
+        )?;
+        let now = timestamp(&Utc::now());
+        let mut stored_chunks = Vec::with_capacity(chunks.len());
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO bookmark_chunk
+                    (chunk_id, bookmark_id, user_id, chunk_text, chunk_index, embedding,
+                     created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)
+                 RETURNING chunk_id, bookmark_id, user_id, chunk_text, chunk_index, created_at,
+                           updated_at",
+            )?;
+            for (index, (chunk_text, embedding)) in chunks.iter().zip(&embeddings).enumerate() {
+                stored_chunks.push(insert.query_row(
+                    params![
+                        Uuid::new_v4(),
+                        bookmark_id,
+                        user_id,
+                        chunk_text,
+                        index as i32,
+                        vector_to_blob(embedding),
+                        now
+                    ],
+                    chunk_from_row,
+                )?);
+            }
+        }
+        let embedding = mean_vector(embeddings.iter().map(Vec::as_slice));
+        tx.execute(
+            "UPDATE bookmark SET embedding = ?1 WHERE bookmark_id = ?2 AND user_id = ?3",
+            params![
+                embedding.as_deref().map(vector_to_blob),
+                bookmark_id,
+                user_id
+            ],
+        )?;
+        tx.commit()?;
+        Ok(stored_chunks)
+    })
+    .await
+}
+
+pub(in crate::db) async fn search_similar_chunks(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    query_embedding: Vec<f32>,
+    limit: usize,
+    similarity_threshold: f64,
+    scope: ChunkScope<'_>,
+) -> Result<Vec<RagChunkMatch>> {
+    let mut params: Params = vec![
+        Box::new(user_id),
+        Box::new(vector_to_blob(&query_embedding)),
+        Box::new(similarity_threshold),
+        Box::new(limit as i64),
+    ];
+    let owner = scope.sqlite_owner_condition(&mut params);
+    let mut scope_clause = String::new();
+    scope.push_sqlite_filters(&mut scope_clause, &mut params);
+    let statement = format!(
+        "SELECT * FROM (
+            SELECT {CHUNK_WITH_BOOKMARK_COLUMNS},
+                1 - cosine_distance(c.embedding, ?2) AS similarity_score
+            FROM bookmark_chunk c
+            INNER JOIN bookmark b ON c.bookmark_id = b.bookmark_id AND c.user_id = b.user_id
+            WHERE {owner} AND b.deleted_at IS NULL{scope_clause}
+         )
+         WHERE similarity_score >= ?3
+         ORDER BY similarity_score DESC
+         LIMIT ?4"
+    );
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(&statement)?
+            .query_map(params_from_iter(params), |row| {
+                Ok(RagChunkMatch {
+                    chunk: chunk_from_row(row)?,
+                    bookmark: bookmark_from_row(row)?,
+                    similarity_score: row.get("similarity_score")?,
+                    relevance_explanation: None,
+                    vector_score: None,
+                    fts_score: None,
+                    combined_score: None,
+                    rerank_score: None,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn find_related_bookmarks(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+    limit: usize,
+) -> Result<Vec<RelatedBookmark>> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(
+                "SELECT * FROM (
+                    SELECT b.bookmark_id, b.user_id, b.url, b.domain, b.title, b.tags, b.summary,
+                        b.word_count, b.reading_time_minutes, b.kind, b.favorite, b.original_title,
+                        b.visibility,
+                        b.created_at AS bookmark_created_at,
+                        b.updated_at AS bookmark_updated_at,
+                        1 - cosine_distance(b.embedding, s.embedding) AS similarity_score
+                    FROM bookmark b
+                    CROSS JOIN (
+                        SELECT embedding FROM bookmark
+                        WHERE bookmark_id = ?1 AND user_id = ?2 AND embedding IS NOT NULL
+                    ) s
+                    WHERE b.user_id = ?2 AND b.bookmark_id <> ?1 AND b.deleted_at IS NULL
+                 )
+                 WHERE similarity_score IS NOT NULL
+                 ORDER BY similarity_score DESC
+                 LIMIT ?3",
+            )?
+            .query_map(params![bookmark_id, user_id, limit as i64], |row| {
+                Ok(RelatedBookmark {
+                    bookmark: bookmark_from_row(row)?,
+                    similarity_score: row.get("similarity_score")?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get_chunks_by_ids(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    chunk_ids: &[Uuid],
+) -> Result<Vec<BookmarkChunk>> {
+    let (params, placeholders) = chunk_id_params(user_id, chunk_ids);
+    let statement = format!(
+        "SELECT chunk_id, bookmark_id, user_id, chunk_text, chunk_index, created_at, updated_at
+         FROM bookmark_chunk
+         WHERE user_id = ?1 AND chunk_id IN ({placeholders})
+         ORDER BY chunk_index"
+    );
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(&statement)?
+            .query_map(params_from_iter(params), chunk_from_row)?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn has_chunks_for_bookmark(
+    pool: &SqlitePool,
+    bookmark_id: &str,
+    user_id: Uuid,
+) -> Result<bool> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        Ok(connection.query_row(
+            "SELECT EXISTS (
+                SELECT 1 FROM bookmark_chunk WHERE bookmark_id = ?1 AND user_id = ?2
+             )",
+            params![bookmark_id, user_id],
+            |row| row.get(0),
+        )?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn chunk_details(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<Vec<ChunkDetail>> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(
+                "SELECT c.chunk_id, c.bookmark_id, c.user_id, c.chunk_text, c.chunk_index,
+                        c.created_at, c.updated_at,
+                        length(c.embedding) / 4 AS dimensions, ec.provider, ec.model
+                 FROM bookmark_chunk c
+                 LEFT JOIN embedding_config ec ON ec.embedding_config_id = 1
+                 WHERE c.user_id = ?1 AND c.bookmark_id = ?2
+                 ORDER BY c.chunk_index",
+            )?
+            .query_map(params![user_id, bookmark_id], |row| {
+                Ok(ChunkDetail {
+                    chunk: chunk_from_row(row)?,
+                    embedding: chunk_embedding(
+                        row.get("provider")?,
+                        row.get("model")?,
+                        row.get("dimensions")?,
+                    ),
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get_chunks_with_bookmarks_by_ids(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    chunk_ids: &[Uuid],
+) -> Result<Vec<(BookmarkChunk, Bookmark)>> {
+    let (params, placeholders) = chunk_id_params(user_id, chunk_ids);
+    let statement = format!(
+        "SELECT {CHUNK_WITH_BOOKMARK_COLUMNS}
+         FROM bookmark_chunk c
+         INNER JOIN bookmark b ON c.bookmark_id = b.bookmark_id AND c.user_id = b.user_id
+         WHERE c.chunk_id IN ({placeholders}) AND b.deleted_at IS NULL
+         AND (c.user_id = ?1 OR EXISTS (
+            SELECT 1 FROM workspace_bookmark wb
+            JOIN workspace_member wm ON wm.workspace_id = wb.workspace_id AND wm.user_id = ?1
+            WHERE wb.bookmark_id = c.bookmark_id AND wb.user_id = c.user_id
+            AND b.visibility <> 'private'
+         ))
+         ORDER BY c.chunk_index"
+    );
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(&statement)?
+            .query_map(params_from_iter(params), |row| {
+                Ok((chunk_from_row(row)?, bookmark_from_row(row)?))
+            })?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get_bookmarks_without_chunks(
+    pool: &SqlitePool,
+    limit: usize,
+) -> Result<Vec<(String, Uuid, String)>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(
+                "SELECT b.bookmark_id, b.user_id, b.text_content
+                 FROM bookmark b
+                 WHERE NOT EXISTS (
+                     SELECT 1 FROM bookmark_chunk c
+                     WHERE c.bookmark_id = b.bookmark_id AND c.user_id = b.user_id
+                 )
+                 AND b.deleted_at IS NULL
+                 AND length(b.text_content) >= 200
+                 ORDER BY random()
+                 LIMIT ?1",
+            )?
+            .query_map([limit as i64], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(in crate::db) async fn search_chunks_hybrid(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    query_text: &str,
+    query_embedding: Vec<f32>,
+    limit: usize,
+    similarity_threshold: f64,
+    scope: ChunkScope<'_>,
+) -> Result<Vec<HybridChunkMatch>> {
+    let mut params: Params = vec![
+        Box::new(user_id),
+        Box::new(vector_to_blob(&query_embedding)),
+        Box::new(similarity_threshold),
+        Box::new(limit as i64),
+    ];
+    // Chunks only match the full-text side when the query has searchable
+    // terms, FTS5 can't rank the rows an exclusion-only query leaves
+    let fts_condition = match fts_query(query_text) {
+        Some(FtsQuery::Match(expression)) => {
+            params.push(Box::new(expression));
+            format!("bookmark_chunk_fts MATCH ?{}", params.len())
+        }
+        Some(FtsQuery::Exclude(_)) | None => "FALSE".to_string(),
+    };
+    let owner = scope.sqlite_owner_condition(&mut params);
+    let mut scope_clause = String::new();
+    scope.push_sqlite_filters(&mut scope_clause, &mut params);
+    let statement = format!(
+        "WITH vector_matches AS (
+            SELECT chunk_id, vector_score,
+                ROW_NUMBER() OVER (ORDER BY vector_score DESC) AS vector_rank
+            FROM (
+                SELECT c.chunk_id, 1 - cosine_distance(c.embedding, ?2) AS vector_score
+                FROM bookmark_chunk c
+                INNER JOIN bookmark b ON c.bookmark_id = b.bookmark_id AND c.user_id = b.user_id
+                WHERE {owner} AND b.deleted_at IS NULL{scope_clause}
+            )
+            WHERE vector_score >= ?3
+            ORDER BY vector_score DESC
+            LIMIT ?4
+         ),
+         fts_matches AS (
+            SELECT chunk_id, fts_score,
+                ROW_NUMBER() OVER (ORDER BY fts_score DESC) AS fts_rank
+            FROM (
+                SELECT c.chunk_id, -bm25(bookmark_chunk_fts) AS fts_score
+                FROM bookmark_chunk_fts
+                INNER JOIN bookmark_chunk c ON c.id = bookmark_chunk_fts.rowid
+                INNER JOIN bookmark b ON c.bookmark_id = b.bookmark_id AND c.user_id = b.user_id
+                WHERE {fts_condition} AND {owner} AND b.deleted_at IS NULL{scope_clause}
+            )
+            ORDER BY fts_score DESC
+            LIMIT ?4
+         ),
+         candidates AS (
+            SELECT chunk_id FROM vector_matches
+            UNION
+            SELECT chunk_id FROM fts_matches
+         )
+         SELECT {CHUNK_WITH_BOOKMARK_COLUMNS},
+            coalesce(vm.vector_score, 0.0) AS vector_score, vm.vector_rank,
+            coalesce(fm.fts_score, 0.0) AS fts_score, fm.fts_rank
+         FROM candidates
+         INNER JOIN bookmark_chunk c ON c.chunk_id = candidates.chunk_id
+         INNER JOIN bookmark b ON c.bookmark_id = b.bookmark_id AND c.user_id = b.user_id
+         LEFT JOIN vector_matches vm ON vm.chunk_id = c.chunk_id
+         LEFT JOIN fts_matches fm ON fm.chunk_id = c.chunk_id"
+    );
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(&statement)?
+            .query_map(params_from_iter(params), |row| {
+                let vector_rank: Option<i64> = row.get("vector_rank")?;
+                let fts_rank: Option<i64> = row.get("fts_rank")?;
+                Ok(HybridChunkMatch {
+                    chunk: chunk_from_row(row)?,
+                    bookmark: bookmark_from_row(row)?,
+                    vector_score: row.get("vector_score")?,
+                    vector_rank: vector_rank.map(|rank| rank as usize),
+                    fts_score: row.get("fts_score")?,
+                    fts_rank: fts_rank.map(|rank| rank as usize),
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
@@ -0,0 +1,163 @@
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use shared::{Collection, CollectionRequest};
+use uuid::Uuid;
+
+use super::{timestamp, Json, SqlitePool};
+use crate::db::collection::unknown_bookmarks;
+use crate::error::Result;
+
+const SELECT: &str = "SELECT c.collection_id, c.name, c.description, c.created_at, c.updated_at,
+        (
+            SELECT json_group_array(bookmark_id) FROM (
+                SELECT cb.bookmark_id FROM collection_bookmark cb
+                WHERE cb.collection_id = c.collection_id
+                ORDER BY cb.position
+            )
+        ) AS bookmark_ids
+    FROM collection c";
+
+fn from_row(row: &Row) -> rusqlite::Result<Collection> {
+    Ok(Collection {
+        collection_id: row.get("collection_id")?,
+        name: row.get("name")?,
+        description: row.get("description")?,
+        bookmark_ids: row.get::<_, Json<Vec<String>>>("bookmark_ids")?.0,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+pub(in crate::db) async fn list(pool: &SqlitePool, user_id: Uuid) -> Result<Vec<Collection>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(&format!(
+                "{SELECT} WHERE c.user_id = ?1 ORDER BY lower(c.name), c.created_at"
+            ))?
+            .query_map([user_id], from_row)?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    collection_id: Uuid,
+) -> Result<Option<Collection>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                &format!("{SELECT} WHERE c.collection_id = ?1 AND c.user_id = ?2"),
+                params![collection_id, user_id],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+fn replace_bookmarks(
+    connection: &Connection,
+    user_id: Uuid,
+    collection_id: Uuid,
+    bookmark_ids: &[String],
+) -> Result<()> {
+    connection.execute(
+        "DELETE FROM collection_bookmark WHERE collection_id = ?1",
+        [collection_id],
+    )?;
+    let inserted = connection.execute(
+        "INSERT INTO collection_bookmark (collection_id, user_id, bookmark_id, position)
+         SELECT ?1, ?2, b.bookmark_id, ids.key
+         FROM json_each(?3) ids
+         JOIN bookmark b ON b.bookmark_id = ids.value AND b.user_id = ?2",
+        params![collection_id, user_id, Json(bookmark_ids)],
+    )?;
+    if inserted != bookmark_ids.len() {
+        return Err(unknown_bookmarks());
+    }
+    Ok(())
+}
+
+pub(in crate::db) async fn create(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    request: &CollectionRequest,
+) -> Result<Collection> {
+    let request = request.clone();
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        let collection_id = Uuid::new_v4();
+        tx.execute(
+            "INSERT INTO collection (collection_id, user_id, name, description, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                collection_id,
+                user_id,
+                request.name,
+                request.description,
+                timestamp(&Utc::now())
+            ],
+        )?;
+        replace_bookmarks(&tx, user_id, collection_id, &request.bookmark_ids)?;
+        let collection = tx.query_row(
+            &format!("{SELECT} WHERE c.collection_id = ?1"),
+            [collection_id],
+            from_row,
+        )?;
+        tx.commit()?;
+        Ok(collection)
+    })
+    .await
+}
+
+pub(in crate::db) async fn update(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    collection_id: Uuid,
+    request: &CollectionRequest,
+) -> Result<Option<Collection>> {
+    let request = request.clone();
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        let updated = tx.execute(
+            "UPDATE collection SET name = ?3, description = ?4, updated_at = ?5
+             WHERE collection_id = ?1 AND user_id = ?2",
+            params![
+                collection_id,
+                user_id,
+                request.name,
+                request.description,
+                timestamp(&Utc::now())
+            ],
+        )?;
+        if updated == 0 {
+            return Ok(None);
+        }
+        replace_bookmarks(&tx, user_id, collection_id, &request.bookmark_ids)?;
+        let collection = tx.query_row(
+            &format!("{SELECT} WHERE c.collection_id = ?1"),
+            [collection_id],
+            from_row,
+        )?;
+        tx.commit()?;
+        Ok(Some(collection))
+    })
+    .await
+}
+
+pub(in crate::db) async fn delete(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    collection_id: Uuid,
+) -> Result<bool> {
+    pool.run(move |connection| {
+        let deleted = connection.execute(
+            "DELETE FROM collection WHERE collection_id = ?1 AND user_id = ?2",
+            params![collection_id, user_id],
+        )?;
+        Ok(deleted > 0)
+    })
+    .await
+}
@@ -0,0 +1,197 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, OptionalExtension, Row};
+use shared::{Bookmark, Digest};
+use uuid::Uuid;
+
+use super::{bookmark, timestamp, Json, SqlitePool};
+use crate::db::user::hash_token;
+use crate::error::Result;
+
+fn from_row(row: &Row) -> rusqlite::Result<Digest> {
+    Ok(Digest {
+        digest_id: row.get("digest_id")?,
+        period_start: row.get("period_start")?,
+        period_end: row.get("period_end")?,
+        bookmark_ids: row.get::<_, Json<Vec<String>>>("bookmark_ids")?.0,
+        content: row.get("content")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+pub(in crate::db) async fn users_due(
+    pool: &SqlitePool,
+    period_start: DateTime<Utc>,
+    period_end: DateTime<Utc>,
+) -> Result<Vec<Uuid>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(
+                "SELECT DISTINCT b.user_id
+                 FROM bookmark b
+                 LEFT JOIN user_settings s ON s.user_id = b.user_id
+                 WHERE b.created_at >= ?1 AND b.created_at < ?2 AND b.deleted_at IS NULL
+                 AND coalesce(s.digest_enabled, TRUE)
+                 AND NOT EXISTS (
+                     SELECT 1 FROM digest d WHERE d.user_id = b.user_id AND d.period_start = ?1
+                 )",
+            )?
+            .query_map(
+                params![timestamp(&period_start), timestamp(&period_end)],
+                |row| row.get(0),
+            )?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn bookmarks_in_period(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    period_start: DateTime<Utc>,
+    period_end: DateTime<Utc>,
+    limit: usize,
+) -> Result<Vec<Bookmark>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(
+                "SELECT * FROM bookmark
+                 WHERE user_id = ?1 AND created_at >= ?2 AND created_at < ?3
+                   AND deleted_at IS NULL
+                 ORDER BY created_at
+                 LIMIT ?4",
+            )?
+            .query_map(
+                params![
+                    user_id,
+                    timestamp(&period_start),
+                    timestamp(&period_end),
+                    limit as i64
+                ],
+                bookmark::from_row,
+            )?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn insert(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    period_start: DateTime<Utc>,
+    period_end: DateTime<Utc>,
+    bookmark_ids: &[String],
+    content: &str,
+) -> Result<Option<Digest>> {
+    let bookmark_ids = Json(bookmark_ids.to_vec());
+    let content = content.to_string();
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                "INSERT INTO digest
+                    (digest_id, user_id, period_start, period_end, bookmark_ids, content,
+                     created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                 ON CONFLICT (user_id, period_start) DO NOTHING
+                 RETURNING digest_id, period_start, period_end, bookmark_ids, content, created_at",
+                params![
+                    Uuid::new_v4(),
+                    user_id,
+                    timestamp(&period_start),
+                    timestamp(&period_end),
+                    bookmark_ids,
+                    content,
+                    timestamp(&Utc::now())
+                ],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn mark_emailed(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    digest_id: Uuid,
+) -> Result<()> {
+    pool.run(move |connection| {
+        connection.execute(
+            "UPDATE digest SET emailed_at = ?1 WHERE digest_id = ?2 AND user_id = ?3",
+            params![timestamp(&Utc::now()), digest_id, user_id],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+pub(in crate::db) async fn list(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    limit: usize,
+) -> Result<Vec<Digest>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(
+                "SELECT digest_id, period_start, period_end, bookmark_ids, content, created_at
+                 FROM digest
+                 WHERE user_id = ?1
+                 ORDER BY period_start DESC
+                 LIMIT ?2",
+            )?
+            .query_map(params![user_id, limit as i64], from_row)?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    digest_id: Uuid,
+) -> Result<Option<Digest>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                "SELECT digest_id, period_start, period_end, bookmark_ids, content, created_at
+                 FROM digest
+                 WHERE digest_id = ?1 AND user_id = ?2",
+                params![digest_id, user_id],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn issue_feed_token(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    token: &str,
+) -> Result<()> {
+    let token_hash = hash_token(token);
+    pool.run(move |connection| {
+        connection.execute(
+            "INSERT INTO digest_feed_token (user_id, token_hash, created_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT (user_id) DO UPDATE SET
+                token_hash = excluded.token_hash,
+                created_at = excluded.created_at",
+            params![user_id, token_hash, timestamp(&Utc::now())],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+pub(in crate::db) async fn feed_token_user(pool: &SqlitePool, token: &str) -> Result<Option<Uuid>> {
+    let token_hash = hash_token(token);
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                "SELECT user_id FROM digest_feed_token WHERE token_hash = ?1",
+                [token_hash],
+                |row| row.get(0),
+            )
+            .optional()?)
+    })
+    .await
+}
@@ -0,0 +1,73 @@
+use std::str::FromStr;
+
+use rusqlite::types::Type;
+use rusqlite::{params, Row};
+use shared::{Entity, EntityCount, EntityKind};
+use uuid::Uuid;
+
+use super::SqlitePool;
+use crate::error::Result;
+
+fn from_row(row: &Row) -> rusqlite::Result<EntityCount> {
+    let kind: String = row.get("kind")?;
+    Ok(EntityCount {
+        kind: EntityKind::from_str(&kind)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(e)))?,
+        name: row.get("name")?,
+        count: row.get("count")?,
+    })
+}
+
+pub(in crate::db) async fn replace_for_bookmark(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+    entities: &[Entity],
+) -> Result<()> {
+    let bookmark_id = bookmark_id.to_string();
+    let entities = entities.to_vec();
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        tx.execute(
+            "DELETE FROM entity WHERE bookmark_id = ?1 AND user_id = ?2",
+            params![bookmark_id, user_id],
+        )?;
+        for entity in &entities {
+            tx.execute(
+                "INSERT INTO entity (bookmark_id, user_id, kind, name) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT DO NOTHING",
+                params![bookmark_id, user_id, entity.kind.as_ref(), entity.name],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    })
+    .await
+}
+
+pub(in crate::db) async fn list(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    kind: Option<EntityKind>,
+    query: Option<&str>,
+    limit: i64,
+) -> Result<Vec<EntityCount>> {
+    let kind = kind.map(|kind| kind.as_ref().to_string());
+    let query = query.map(str::to_lowercase);
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(
+                "SELECT kind, name, count(1) AS count
+                 FROM entity
+                 WHERE user_id = ?1
+                   AND (?2 IS NULL OR kind = ?2)
+                   AND (?3 IS NULL OR instr(lower(name), ?3) > 0)
+                 GROUP BY kind, name
+                 ORDER BY count DESC, lower(name)
+                 LIMIT ?4",
+            )?
+            .query_map(params![user_id, kind, query, limit], from_row)?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
@@ -0,0 +1,128 @@
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension, Row};
+use shared::{ExtractionRule, ExtractionRuleRequest};
+use uuid::Uuid;
+
+use super::{timestamp, Json, SqlitePool};
+use crate::error::Result;
+
+const COLUMNS: &str =
+    "rule_id, domain, keep_selectors, drop_selectors, title_selector, created_at, updated_at";
+
+pub(super) fn from_row(row: &Row) -> rusqlite::Result<ExtractionRule> {
+    Ok(ExtractionRule {
+        rule_id: row.get("rule_id")?,
+        domain: row.get("domain")?,
+        keep_selectors: row.get::<_, Json<_>>("keep_selectors")?.0,
+        drop_selectors: row.get::<_, Json<_>>("drop_selectors")?.0,
+        title_selector: row.get("title_selector")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+pub(in crate::db) async fn list(pool: &SqlitePool, user_id: Uuid) -> Result<Vec<ExtractionRule>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(&format!(
+                "SELECT {COLUMNS} FROM extraction_rule WHERE user_id = ?1
+                 ORDER BY domain, created_at"
+            ))?
+            .query_map([user_id], from_row)?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    rule_id: Uuid,
+) -> Result<Option<ExtractionRule>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                &format!(
+                    "SELECT {COLUMNS} FROM extraction_rule WHERE rule_id = ?1 AND user_id = ?2"
+                ),
+                params![rule_id, user_id],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn create(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    request: &ExtractionRuleRequest,
+) -> Result<ExtractionRule> {
+    let request = request.clone();
+    pool.run(move |connection| {
+        Ok(connection.query_row(
+            &format!(
+                "INSERT INTO extraction_rule
+                    (rule_id, user_id, domain, keep_selectors, drop_selectors, title_selector,
+                     created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                 RETURNING {COLUMNS}"
+            ),
+            params![
+                Uuid::new_v4(),
+                user_id,
+                request.domain,
+                Json(&request.keep_selectors),
+                Json(&request.drop_selectors),
+                request.title_selector,
+                timestamp(&Utc::now())
+            ],
+            from_row,
+        )?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn update(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    rule_id: Uuid,
+    request: &ExtractionRuleRequest,
+) -> Result<Option<ExtractionRule>> {
+    let request = request.clone();
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                &format!(
+                    "UPDATE extraction_rule
+                     SET domain = ?3, keep_selectors = ?4, drop_selectors = ?5,
+                         title_selector = ?6, updated_at = ?7
+                     WHERE rule_id = ?1 AND user_id = ?2
+                     RETURNING {COLUMNS}"
+                ),
+                params![
+                    rule_id,
+                    user_id,
+                    request.domain,
+                    Json(&request.keep_selectors),
+                    Json(&request.drop_selectors),
+                    request.title_selector,
+                    timestamp(&Utc::now())
+                ],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn delete(pool: &SqlitePool, user_id: Uuid, rule_id: Uuid) -> Result<bool> {
+    pool.run(move |connection| {
+        let deleted = connection.execute(
+            "DELETE FROM extraction_rule WHERE rule_id = ?1 AND user_id = ?2",
+            params![rule_id, user_id],
+        )?;
+        Ok(deleted > 0)
+    })
+    .await
+}
@@ -0,0 +1,53 @@
+use chrono::Utc;
+use rusqlite::params;
+use uuid::Uuid;
+
+use super::{timestamp, SqlitePool};
+use crate::db::fetch_credential::SealedFetchCredential;
+use crate::error::Result;
+
+pub(in crate::db) async fn list(
+    pool: &SqlitePool,
+    user_id: Uuid,
+) -> Result<Vec<SealedFetchCredential>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(
+                "SELECT domain, sealed_headers, updated_at FROM fetch_credential
+                 WHERE user_id = ?1 ORDER BY domain",
+            )?
+            .query_map([user_id], |row| {
+                Ok(SealedFetchCredential {
+                    domain: row.get("domain")?,
+                    sealed_headers: row.get("sealed_headers")?,
+                    updated_at: row.get("updated_at")?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn replace(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    credentials: Vec<(String, Vec<u8>)>,
+) -> Result<()> {
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        tx.execute("DELETE FROM fetch_credential WHERE user_id = ?1", [user_id])?;
+        let now = timestamp(&Utc::now());
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO fetch_credential (user_id, domain, sealed_headers, updated_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for (domain, sealed_headers) in &credentials {
+                insert.execute(params![user_id, domain, sealed_headers, now])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    })
+    .await
+}
@@ -0,0 +1,70 @@
+use rusqlite::params;
+use shared::Entity;
+use uuid::Uuid;
+
+use super::{Json, SqlitePool};
+use crate::db::graph::{GraphBookmark, GraphData, GraphLink};
+use crate::error::Result;
+
+pub(in crate::db) async fn load(pool: &SqlitePool, user_id: Uuid, limit: i64) -> Result<GraphData> {
+    pool.run(move |connection| {
+        let bookmarks = connection
+            .prepare(
+                "SELECT bookmark_id, title, domain, canonical_url FROM bookmark
+                 WHERE user_id = ?1 AND deleted_at IS NULL
+                 ORDER BY created_at DESC LIMIT ?2",
+            )?
+            .query_map(params![user_id, limit], |row| {
+                Ok(GraphBookmark {
+                    bookmark_id: row.get("bookmark_id")?,
+                    title: row.get("title")?,
+                    domain: row.get("domain")?,
+                    canonical_url: row.get("canonical_url")?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let bookmark_ids = Json(
+            bookmarks
+                .iter()
+                .map(|bookmark| bookmark.bookmark_id.clone())
+                .collect::<Vec<_>>(),
+        );
+
+        let entities = connection
+            .prepare(
+                "SELECT bookmark_id, kind, name FROM entity
+                 WHERE user_id = ?1 AND bookmark_id IN (SELECT value FROM json_each(?2))",
+            )?
+            .query_map(params![user_id, bookmark_ids], |row| {
+                let kind: String = row.get("kind")?;
+                let bookmark_id: String = row.get("bookmark_id")?;
+                let name: String = row.get("name")?;
+                Ok(kind
+                    .parse()
+                    .ok()
+                    .map(|kind| (bookmark_id, Entity { kind, name })))
+            })?
+            .filter_map(|entity| entity.transpose())
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let links = connection
+            .prepare(
+                "SELECT bookmark_id, url, domain FROM bookmark_link
+                 WHERE user_id = ?1 AND bookmark_id IN (SELECT value FROM json_each(?2))",
+            )?
+            .query_map(params![user_id, bookmark_ids], |row| {
+                Ok(GraphLink {
+                    bookmark_id: row.get("bookmark_id")?,
+                    url: row.get("url")?,
+                    domain: row.get("domain")?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(GraphData {
+            bookmarks,
+            entities,
+            links,
+        })
+    })
+    .await
+}
@@ -0,0 +1,60 @@
+use rusqlite::params;
+use shared::BookmarkLink;
+use uuid::Uuid;
+
+use super::SqlitePool;
+use crate::error::Result;
+
+pub(in crate::db) async fn replace_for_bookmark(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+    links: Vec<(String, String)>,
+) -> Result<()> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        tx.execute(
+            "DELETE FROM bookmark_link WHERE bookmark_id = ?1 AND user_id = ?2",
+            params![bookmark_id, user_id],
+        )?;
+        for (url, domain) in &links {
+            tx.execute(
+                "INSERT INTO bookmark_link (bookmark_id, user_id, url, domain)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT DO NOTHING",
+                params![bookmark_id, user_id, url, domain],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    })
+    .await
+}
+
+pub(in crate::db) async fn list_outbound(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<Vec<BookmarkLink>> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(
+                "SELECT l.url, l.domain, b.bookmark_id FROM bookmark_link l
+                 LEFT JOIN bookmark b ON b.user_id = l.user_id AND b.canonical_url = l.url
+                   AND b.deleted_at IS NULL
+                 WHERE l.bookmark_id = ?1 AND l.user_id = ?2
+                 ORDER BY l.url",
+            )?
+            .query_map(params![bookmark_id, user_id], |row| {
+                Ok(BookmarkLink {
+                    url: row.get("url")?,
+                    domain: row.get("domain")?,
+                    bookmark_id: row.get("bookmark_id")?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
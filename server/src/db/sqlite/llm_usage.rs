@@ -0,0 +1,77 @@
+use chrono::{NaiveDate, Utc};
+use rusqlite::params;
+use shared::LlmUsageDay;
+use uuid::Uuid;
+
+use super::{timestamp, SqlitePool};
+use crate::db::llm_usage::NewLlmUsage;
+use crate::error::Result;
+
+pub(in crate::db) async fn insert(pool: &SqlitePool, usage: &NewLlmUsage) -> Result<()> {
+    let usage = usage.clone();
+    pool.run(move |connection| {
+        connection.execute(
+            "INSERT INTO llm_usage (
+                usage_id, user_id, bookmark_id, session_id, purpose, request_kind, provider,
+                model, input_tokens, output_tokens, duration_ms, success, created_at
+             )
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                Uuid::new_v4(),
+                usage.user_id,
+                usage.bookmark_id,
+                usage.session_id,
+                usage.purpose,
+                usage.request_kind,
+                usage.provider,
+                usage.model,
+                usage.input_tokens,
+                usage.output_tokens,
+                usage.duration_ms,
+                usage.success,
+                timestamp(&Utc::now())
+            ],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+pub(in crate::db) async fn summarize_by_day(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    since: NaiveDate,
+) -> Result<Vec<LlmUsageDay>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(
+                "SELECT
+                    date(created_at) AS day,
+                    purpose,
+                    model,
+                    COUNT(*) AS calls,
+                    COUNT(*) FILTER (WHERE NOT success) AS failed_calls,
+                    coalesce(SUM(input_tokens), 0) AS input_tokens,
+                    coalesce(SUM(output_tokens), 0) AS output_tokens,
+                    coalesce(SUM(duration_ms), 0) AS duration_ms
+                 FROM llm_usage
+                 WHERE user_id = ?1 AND date(created_at) >= ?2
+                 GROUP BY day, purpose, model
+                 ORDER BY day DESC, purpose, model",
+            )?
+            .query_map(params![user_id, since.to_string()], |row| {
+                Ok(LlmUsageDay {
+                    day: row.get("day")?,
+                    purpose: row.get("purpose")?,
+                    model: row.get("model")?,
+                    calls: row.get("calls")?,
+                    failed_calls: row.get("failed_calls")?,
+                    input_tokens: row.get("input_tokens")?,
+                    output_tokens: row.get("output_tokens")?,
+                    duration_ms: row.get("duration_ms")?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
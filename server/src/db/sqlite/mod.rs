@@ -0,0 +1,687 @@
+//! Embedded SQLite database for single-user deployments. Every function in
+//! `db` dispatches to its counterpart here when the instance runs with
+//! `DATABASE_BACKEND=sqlite`. Full-text search uses FTS5 with the English
+//! Porter stemmer for every language, vector search compares the query with
+//! every stored embedding.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use anyhow::Context;
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef};
+use rusqlite::{params, Connection, OptionalExtension, Row, ToSql};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use shared::TagFilter;
+use tracing::{info, warn};
+
+use super::bookmark::AiGenerationStatus;
+use super::migration::{self, migration, Migration};
+use super::EmbeddingProfile;
+use crate::error::{Error, Result};
+use crate::EMBEDDING_PIPELINE_VERSION;
+
+pub(super) mod activity;
+pub(super) mod ai;
+pub(super) mod audit_log;
+pub(super) mod backup;
+pub(super) mod bookmark;
+pub(super) mod bookmark_task;
+pub(super) mod bookmark_version;
+pub(super) mod chunks;
+pub(super) mod collection;
+pub(super) mod digest;
+pub(super) mod entity;
+pub(super) mod extraction_rule;
+pub(super) mod fetch_credential;
+pub(super) mod graph;
+pub(super) mod link;
+pub(super) mod llm_usage;
+pub(super) mod rag;
+pub(super) mod reindex;
+pub(super) mod rule;
+pub(super) mod scheduled_job;
+pub(super) mod search;
+pub(super) mod storage;
+pub(super) mod task_event;
+pub(super) mod user;
+pub(super) mod user_session;
+pub(super) mod user_settings;
+pub(super) mod workspace;
+
+const CREATE_SCHEMA_VERSION_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS schema_version (
+    version INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    checksum TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (version)
+);";
+
+const MIGRATIONS: [Migration; 27] = [
+    migration!(1, "sqlite/1_initial.sql"),
+    migration!(2, "sqlite/2_reading_time.sql"),
+    migration!(3, "sqlite/3_kind.sql"),
+    migration!(4, "sqlite/4_entity.sql"),
+    migration!(5, "sqlite/5_bookmark_link.sql"),
+    migration!(6, "sqlite/6_collection.sql"),
+    migration!(7, "sqlite/7_bookmark_version.sql"),
+    migration!(8, "sqlite/8_bookmark_trash.sql"),
+    migration!(9, "sqlite/9_rule.sql"),
+    migration!(10, "sqlite/10_original_title.sql"),
+    migration!(11, "sqlite/11_summary_style.sql"),
+    migration!(12, "sqlite/12_reader_settings.sql"),
+    migration!(13, "sqlite/13_rag_feedback.sql"),
+    migration!(14, "sqlite/14_rag_session_title.sql"),
+    migration!(15, "sqlite/15_embedding_cache.sql"),
+    migration!(16, "sqlite/16_extraction_rule.sql"),
+    migration!(17, "sqlite/17_fetch_credential.sql"),
+    migration!(18, "sqlite/18_audit_log.sql"),
+    migration!(19, "sqlite/19_user_session.sql"),
+    migration!(20, "sqlite/20_workspace.sql"),
+    migration!(21, "sqlite/21_bookmark_visibility.sql"),
+    migration!(22, "sqlite/22_scheduled_job.sql"),
+    migration!(23, "sqlite/23_task_lease.sql"),
+    migration!(24, "sqlite/24_digest_feed_token.sql"),
+    migration!(25, "sqlite/25_bookmark_read_at.sql"),
+    migration!(26, "sqlite/26_embedding_cache_owner.sql"),
+    migration!(27, "sqlite/27_audit_log_keep_events.sql"),
+];
+
+/// A single connection shared by the whole server, statements run one at a
+/// time on the blocking thread pool.
+#[derive(Debug, Clone)]
+pub struct SqlitePool {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqlitePool {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        info!(path = ?path, "Opening SQLite database");
+        let connection =
+            Connection::open(path).with_context(|| format!("Failed to open {path:?}"))?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.pragma_update(None, "synchronous", "NORMAL")?;
+        Self::new(connection)
+    }
+
+    /// A private database that is gone once the pool is dropped.
+    pub fn open_in_memory() -> anyhow::Result<Self> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    fn new(connection: Connection) -> anyhow::Result<Self> {
+        connection.pragma_update(None, "foreign_keys", true)?;
+        connection.busy_timeout(std::time::Duration::from_secs(5))?;
+        connection.create_scalar_function(
+            "cosine_distance",
+            2,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| {
+                let a = ctx.get_raw(0).as_blob_or_null()?;
+                let b = ctx.get_raw(1).as_blob_or_null()?;
+                Ok(a.zip(b).and_then(|(a, b)| cosine_distance(a, b)))
+            },
+        )?;
+        connection.create_scalar_function(
+            "word_similarity",
+            2,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| {
+                let query = ctx.get_raw(0).as_str_or_null()?;
+                let text = ctx.get_raw(1).as_str_or_null()?;
+                Ok(query
+                    .zip(text)
+                    .map(|(query, text)| word_similarity(query, text)))
+            },
+        )?;
+        connection.create_scalar_function(
+            "wallabag_entry_id",
+            1,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| {
+                let bookmark_id = ctx.get_raw(0).as_str_or_null()?;
+                Ok(bookmark_id.map(super::bookmark::wallabag_entry_id))
+            },
+        )?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Runs `f` with the connection on the blocking thread pool.
+    pub(crate) async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || {
+            // A panic can't leave the database in a bad state, SQLite rolls
+            // back whatever statement or transaction was interrupted
+            let mut connection = connection.lock().unwrap_or_else(PoisonError::into_inner);
+            f(&mut connection)
+        })
+        .await
+        .map_err(anyhow::Error::from)?
+    }
+}
+
+pub(super) async fn run_migrations(pool: &SqlitePool) -> Result<()> {
+    info!("Starting SQLite migrations check");
+    pool.run(|connection| {
+        connection.execute_batch(CREATE_SCHEMA_VERSION_TABLE)?;
+        let applied = connection
+            .prepare("SELECT version, checksum FROM schema_version")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        let plan = migration::plan(&MIGRATIONS, &applied)?;
+        if plan.pending.is_empty() {
+            info!("Database schema is up to date");
+            return Ok(());
+        }
+        for migration in &plan.pending {
+            info!(
+                version = migration.version,
+                name = migration.name,
+                "Applying migration"
+            );
+            let tx = connection.transaction()?;
+            tx.execute_batch(migration.sql)?;
+            tx.execute(
+                "INSERT INTO schema_version (version, name, checksum, updated_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    migration.version,
+                    migration.name,
+                    migration.checksum(),
+                    timestamp(&Utc::now())
+                ],
+            )?;
+            tx.commit()?;
+        }
+        info!(
+            migrations_applied = plan.pending.len(),
+            "Applied migrations successfully"
+        );
+        Ok(())
+    })
+    .await
+}
+
+pub(super) async fn run_health_check(pool: &SqlitePool) -> Result<()> {
+    pool.run(|connection| {
+        connection.query_row("SELECT 1", [], |_| Ok(()))?;
+        Ok(())
+    })
+    .await
+}
+
+fn get_embedding_profile(connection: &Connection) -> rusqlite::Result<Option<EmbeddingProfile>> {
+    connection
+        .query_row(
+            "SELECT provider, model, dimensions FROM embedding_config WHERE embedding_config_id = 1",
+            [],
+            |row| {
+                Ok(EmbeddingProfile {
+                    provider: row.get("provider")?,
+                    model: row.get("model")?,
+                    dimensions: row.get("dimensions")?,
+                })
+            },
+        )
+        .optional()
+}
+
+fn upsert_embedding_profile(
+    connection: &Connection,
+    profile: &EmbeddingProfile,
+) -> rusqlite::Result<()> {
+    connection.execute(
+        "INSERT INTO embedding_config (embedding_config_id, provider, model, dimensions, updated_at)
+         VALUES (1, ?1, ?2, ?3, ?4)
+         ON CONFLICT (embedding_config_id)
+         DO UPDATE SET
+            provider = excluded.provider,
+            model = excluded.model,
+            dimensions = excluded.dimensions,
+            updated_at = excluded.updated_at",
+        params![
+            profile.provider,
+            profile.model,
+            profile.dimensions,
+            timestamp(&Utc::now())
+        ],
+    )?;
+    Ok(())
+}
+
+/// Drops every chunk so the embedding daemon regenerates them with the new
+/// profile, same as the Postgres `TRUNCATE`.
+fn clear_embeddings(connection: &mut Connection, profile: &EmbeddingProfile) -> Result<()> {
+    let tx = connection.transaction()?;
+    tx.execute_batch("DELETE FROM bookmark_chunk; UPDATE bookmark SET embedding = NULL;")?;
+    upsert_embedding_profile(&tx, profile)?;
+    ai::reset_embedding_generation_state_in(&tx, EMBEDDING_PIPELINE_VERSION)?;
+    tx.commit()?;
+    Ok(())
+}
+
+pub(super) async fn reconcile_embedding_profile(
+    pool: &SqlitePool,
+    target: &EmbeddingProfile,
+) -> anyhow::Result<()> {
+    let target = target.clone();
+    pool.run(move |connection| {
+        let stored = get_embedding_profile(connection)?;
+        let chunk_count: i64 =
+            connection.query_row("SELECT COUNT(*) FROM bookmark_chunk", [], |row| row.get(0))?;
+        match stored {
+            Some(current) if current == target => {
+                info!(
+                    provider = %target.provider,
+                    model = %target.model,
+                    dimensions = target.dimensions,
+                    "Embedding profile matches current configuration"
+                );
+            }
+            Some(current) => {
+                warn!(
+                    old_provider = %current.provider,
+                    old_model = %current.model,
+                    old_dimensions = current.dimensions,
+                    new_provider = %target.provider,
+                    new_model = %target.model,
+                    new_dimensions = target.dimensions,
+                    "Embedding profile changed, clearing chunk embeddings"
+                );
+                clear_embeddings(connection, &target)?;
+            }
+            None if chunk_count > 0 => {
+                warn!(
+                    chunk_count,
+                    provider = %target.provider,
+                    model = %target.model,
+                    dimensions = target.dimensions,
+                    "Embedding profile metadata missing for existing chunks, clearing chunk embeddings"
+                );
+                clear_embeddings(connection, &target)?;
+            }
+            None => {
+                info!(
+                    provider = %target.provider,
+                    model = %target.model,
+                    dimensions = target.dimensions,
+                    "Initializing embedding profile metadata"
+                );
+                upsert_embedding_profile(connection, &target)?;
+            }
+        }
+        Ok(())
+    })
+    .await
+    .map_err(anyhow::Error::from)
+}
+
+pub(super) async fn stored_embedding_profile(
+    pool: &SqlitePool,
+) -> anyhow::Result<Option<EmbeddingProfile>> {
+    pool.run(|connection| Ok(get_embedding_profile(connection)?))
+        .await
+        .map_err(anyhow::Error::from)
+}
+
+pub(super) async fn adopt_embedding_profile(
+    pool: &SqlitePool,
+    profile: &EmbeddingProfile,
+) -> anyhow::Result<()> {
+    let profile = profile.clone();
+    pool.run(move |connection| Ok(upsert_embedding_profile(connection, &profile)?))
+        .await
+        .map_err(anyhow::Error::from)
+}
+
+/// Timestamps are stored as RFC 3339 text with a fixed precision, so they
+/// compare and sort as strings.
+pub(crate) fn timestamp(value: &DateTime<Utc>) -> String {
+    value.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+/// Arrays stored as JSON text, e.g. tags.
+#[derive(Debug)]
+pub(crate) struct Json<T>(pub T);
+
+impl<T: Serialize> ToSql for Json<T> {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        serde_json::to_string(&self.0)
+            .map(ToSqlOutput::from)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+    }
+}
+
+impl<T: DeserializeOwned> FromSql for Json<T> {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        serde_json::from_slice(value.as_bytes()?)
+            .map(Json)
+            .map_err(FromSqlError::other)
+    }
+}
+
+pub(crate) fn string_list(row: &Row, column: &str) -> rusqlite::Result<Option<Vec<String>>> {
+    Ok(row
+        .get::<_, Option<Json<Vec<String>>>>(column)?
+        .map(|Json(values)| values))
+}
+
+impl ToSql for AiGenerationStatus {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(match self {
+            AiGenerationStatus::Pending => "pending",
+            AiGenerationStatus::Done => "done",
+            AiGenerationStatus::Fail => "fail",
+            AiGenerationStatus::Skipped => "skipped",
+        }))
+    }
+}
+
+impl FromSql for AiGenerationStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "pending" => Ok(AiGenerationStatus::Pending),
+            "done" => Ok(AiGenerationStatus::Done),
+            "fail" => Ok(AiGenerationStatus::Fail),
+            "skipped" => Ok(AiGenerationStatus::Skipped),
+            other => Err(FromSqlError::Other(
+                format!("unknown generation status: {other}").into(),
+            )),
+        }
+    }
+}
+
+pub(crate) fn vector_to_blob(vector: &[f32]) -> Vec<u8> {
+    vector
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+pub(crate) fn blob_to_vector(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect()
+}
+
+/// Element-wise mean, what pgvector's `AVG` computes for the bookmark
+/// embedding. `None` without vectors or when their dimensions differ.
+pub(crate) fn mean_vector<'a>(vectors: impl IntoIterator<Item = &'a [f32]>) -> Option<Vec<f32>> {
+    let mut vectors = vectors.into_iter();
+    let mut sum: Vec<f64> = vectors.next()?.iter().map(|&v| f64::from(v)).collect();
+    let mut count = 1.0;
+    for vector in vectors {
+        if vector.len() != sum.len() {
+            return None;
+        }
+        for (total, &value) in sum.iter_mut().zip(vector) {
+            *total += f64::from(value);
+        }
+        count += 1.0;
+    }
+    Some(
+        sum.into_iter()
+            .map(|total| (total / count) as f32)
+            .collect(),
+    )
+}
+
+/// pgvector's `<=>` on two vectors stored with [`vector_to_blob`], `None`
+/// when the dimensions differ or either vector is all zeros.
+fn cosine_distance(a: &[u8], b: &[u8]) -> Option<f64> {
+    if a.len() != b.len() || !a.len().is_multiple_of(4) {
+        return None;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f64, 0.0f64, 0.0f64);
+    for (a, b) in blob_to_vector(a).into_iter().zip(blob_to_vector(b)) {
+        let (a, b) = (f64::from(a), f64::from(b));
+        dot += a * b;
+        norm_a += a * a;
+        norm_b += b * b;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return None;
+    }
+    Some(1.0 - dot / (norm_a.sqrt() * norm_b.sqrt()))
+}
+
+/// The trigrams of each word like `pg_trgm` extracts them: lowercased, with
+/// two spaces before and one after.
+fn trigrams(text: &str) -> HashSet<[char; 3]> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .flat_map(|word| {
+            let padded: Vec<char> = format!("  {} ", word.to_lowercase()).chars().collect();
+            padded
+                .windows(3)
+                .map(|window| [window[0], window[1], window[2]])
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Share of the trigrams of `query` found in `text`, a close stand-in for
+/// `pg_trgm`'s `word_similarity` that doesn't require the shared trigrams to
+/// be contiguous in `text`.
+fn word_similarity(query: &str, text: &str) -> f64 {
+    let query = trigrams(query);
+    if query.is_empty() {
+        return 0.0;
+    }
+    let text = trigrams(text);
+    query.intersection(&text).count() as f64 / query.len() as f64
+}
+
+/// A `websearch_to_tsquery` style query translated to FTS5.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum FtsQuery {
+    /// Rows matching the expression
+    Match(String),
+    /// Rows that don't match the expression, for queries made only of
+    /// `-excluded` terms, which FTS5 can't express on their own
+    Exclude(String),
+}
+
+/// Unquoted words and `"quoted phrases"` must all match, `or` between them
+/// matches either side and `-term` excludes it. `None` when nothing in
+/// `text` can be searched.
+pub(crate) fn fts_query(text: &str) -> Option<FtsQuery> {
+    struct Group {
+        included: Vec<String>,
+        excluded: Vec<String>,
+    }
+    let new_group = || Group {
+        included: vec![],
+        excluded: vec![],
+    };
+
+    let mut groups = vec![new_group()];
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let negated = c == '-';
+        if negated {
+            chars.next();
+        }
+        let quoted = chars.peek() == Some(&'"');
+        let term: String = if quoted {
+            chars.next();
+            let term = chars.by_ref().take_while(|&c| c != '"').collect();
+            term
+        } else {
+            let mut term = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' {
+                    break;
+                }
+                term.push(c);
+                chars.next();
+            }
+            term
+        };
+        if !quoted && !negated && term.eq_ignore_ascii_case("or") {
+            if groups
+                .last()
+                .is_some_and(|group| !group.included.is_empty())
+            {
+                groups.push(new_group());
+            }
+            continue;
+        }
+        if !term.chars().any(char::is_alphanumeric) {
+            continue;
+        }
+        let phrase = format!("\"{}\"", term.replace('"', "\"\""));
+        let group = groups.last_mut().expect("there is always a group");
+        if negated {
+            group.excluded.push(phrase);
+        } else {
+            group.included.push(phrase);
+        }
+    }
+
+    let matching: Vec<String> = groups
+        .iter()
+        .filter(|group| !group.included.is_empty())
+        .map(|group| {
+            let mut expression = group.included.join(" AND ");
+            for excluded in &group.excluded {
+                expression.push_str(" NOT ");
+                expression.push_str(excluded);
+            }
+            format!("({expression})")
+        })
+        .collect();
+    if !matching.is_empty() {
+        return Some(FtsQuery::Match(matching.join(" OR ")));
+    }
+    let excluded: Vec<&str> = groups
+        .iter()
+        .flat_map(|group| group.excluded.iter().map(String::as_str))
+        .collect();
+    (!excluded.is_empty()).then(|| FtsQuery::Exclude(excluded.join(" OR ")))
+}
+
+/// Parameters of a statement built at runtime, bound to `?N` placeholders
+/// numbered by their position.
+pub(crate) type Params = Vec<Box<dyn ToSql + Send>>;
+
+/// Condition on the tags of the bookmark alias `b`, `None` when `filter`
+/// doesn't restrict them.
+pub(crate) fn tag_condition(filter: &TagFilter, params: &mut Params) -> Option<String> {
+    match filter {
+        TagFilter::And(tags) => {
+            params.push(Box::new(Json(tags.clone())));
+            Some(format!(
+                "NOT EXISTS (SELECT 1 FROM json_each(?{}) wanted \
+                 WHERE wanted.value NOT IN (SELECT value FROM json_each(b.tags)))",
+                params.len()
+            ))
+        }
+        TagFilter::Or(tags) => {
+            params.push(Box::new(Json(tags.clone())));
+            Some(format!(
+                "EXISTS (SELECT 1 FROM json_each(b.tags) \
+                 WHERE value IN (SELECT value FROM json_each(?{})))",
+                params.len()
+            ))
+        }
+        TagFilter::Untagged => {
+            Some("(b.tags IS NULL OR json_array_length(b.tags) = 0)".to_string())
+        }
+        TagFilter::Any => None,
+    }
+}
+
+pub(crate) trait SqliteResultExt<T> {
+    /// Maps a UNIQUE violation on `target`, which SQLite reports as
+    /// `index 'name'` for expression indexes and `table.column, ...`
+    /// otherwise.
+    fn on_unique(self, target: &str, f: impl FnOnce() -> Error) -> Result<T>;
+}
+
+impl<T, E> SqliteResultExt<T> for Result<T, E>
+where
+    E: Into<Error>,
+{
+    fn on_unique(self, target: &str, f: impl FnOnce() -> Error) -> Result<T> {
+        self.map_err(|e| match e.into() {
+            Error::Sqlite(rusqlite::Error::SqliteFailure(failure, Some(message)))
+                if failure.code == rusqlite::ErrorCode::ConstraintViolation
+                    && message.strip_prefix("UNIQUE constraint failed: ") == Some(target) =>
+            {
+                f()
+            }
+            other => other,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_similarity_tolerates_typos() {
+        assert_eq!(word_similarity("Kubernetes", "kubernetes docs"), 1.0);
+        assert!(word_similarity("kuberntes", "Kubernetes in Action") >= 0.6);
+        assert!(word_similarity("kuberntes", "Learning Go") < 0.6);
+        assert_eq!(word_similarity("--", "anything"), 0.0);
+    }
+
+    #[test]
+    fn websearch_queries_translate_to_fts5() {
+        let query = |text: &str| {
+            fts_query(text).map(|query| match query {
+                FtsQuery::Match(expression) => format!("match {expression}"),
+                FtsQuery::Exclude(expression) => format!("exclude {expression}"),
+            })
+        };
+        assert_eq!(
+            query("rust async").as_deref(),
+            Some(r#"match ("rust" AND "async")"#)
+        );
+        assert_eq!(
+            query(r#""borrow checker" -unsafe"#).as_deref(),
+            Some(r#"match ("borrow checker" NOT "unsafe")"#)
+        );
+        assert_eq!(
+            query("rust or go").as_deref(),
+            Some(r#"match ("rust") OR ("go")"#)
+        );
+        assert_eq!(query("-java").as_deref(), Some(r#"exclude "java""#));
+        assert_eq!(
+            query(r#"say "hi there"#).as_deref(),
+            Some(r#"match ("say" AND "hi there")"#)
+        );
+        assert_eq!(query("  -- ? "), None);
+    }
+
+    #[test]
+    fn vectors_round_trip_and_compare() {
+        let a = vector_to_blob(&[1.0, 0.0]);
+        let b = vector_to_blob(&[0.0, 2.0]);
+        assert_eq!(blob_to_vector(&a), [1.0, 0.0]);
+        assert_eq!(cosine_distance(&a, &a), Some(0.0));
+        assert_eq!(cosine_distance(&a, &b), Some(1.0));
+        assert_eq!(cosine_distance(&a, &vector_to_blob(&[1.0])), None);
+        assert_eq!(
+            mean_vector([[1.0, 2.0].as_slice(), [3.0, 4.0].as_slice()]),
+            Some(vec![2.0, 3.0])
+        );
+        assert_eq!(mean_vector([[1.0].as_slice(), [1.0, 2.0].as_slice()]), None);
+    }
+}
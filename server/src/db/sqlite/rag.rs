@@ -0,0 +1,282 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{params, params_from_iter, OptionalExtension, Row};
+use shared::{RagFeedbackComment, RagFeedbackRequest, RagQualityDay, RagSession};
+use uuid::Uuid;
+
+use super::{fts_query, timestamp, FtsQuery, Json, Params, SqlitePool};
+use crate::db::rag::feedback;
+use crate::error::Result;
+
+const COLUMNS: &str = "session_id, user_id, title, question, answer, relevant_chunks,
+    created_at, updated_at, feedback_rating, feedback_comment, feedback_at";
+
+fn from_row(row: &Row) -> rusqlite::Result<RagSession> {
+    Ok(RagSession {
+        session_id: row.get("session_id")?,
+        user_id: row.get("user_id")?,
+        title: row.get("title")?,
+        question: row.get("question")?,
+        answer: row.get("answer")?,
+        relevant_chunks: row.get::<_, Json<Vec<Uuid>>>("relevant_chunks")?.0,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+        feedback: feedback(
+            row.get("feedback_rating")?,
+            row.get("feedback_comment")?,
+            row.get("feedback_at")?,
+        ),
+    })
+}
+
+pub(in crate::db) async fn create_rag_session(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    question: &str,
+) -> Result<RagSession> {
+    let question = question.to_string();
+    pool.run(move |connection| {
+        Ok(connection.query_row(
+            &format!(
+                "INSERT INTO rag_session (session_id, user_id, question, created_at)
+                 VALUES (?1, ?2, ?3, ?4)
+                 RETURNING {COLUMNS}"
+            ),
+            params![Uuid::new_v4(), user_id, question, timestamp(&Utc::now())],
+            from_row,
+        )?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn update_rag_session(
+    pool: &SqlitePool,
+    session_id: Uuid,
+    user_id: Uuid,
+    answer: &str,
+    relevant_chunk_ids: &[Uuid],
+) -> Result<RagSession> {
+    let answer = answer.to_string();
+    let relevant_chunk_ids = Json(relevant_chunk_ids.to_vec());
+    pool.run(move |connection| {
+        Ok(connection.query_row(
+            &format!(
+                "UPDATE rag_session SET answer = ?3, relevant_chunks = ?4, updated_at = ?5
+                 WHERE session_id = ?1 AND user_id = ?2
+                 RETURNING {COLUMNS}"
+            ),
+            params![
+                session_id,
+                user_id,
+                answer,
+                relevant_chunk_ids,
+                timestamp(&Utc::now())
+            ],
+            from_row,
+        )?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get_rag_session(
+    pool: &SqlitePool,
+    session_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<RagSession>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                &format!(
+                    "SELECT {COLUMNS} FROM rag_session WHERE session_id = ?1 AND user_id = ?2"
+                ),
+                params![session_id, user_id],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn set_rag_session_title(
+    pool: &SqlitePool,
+    session_id: Uuid,
+    user_id: Uuid,
+    title: &str,
+) -> Result<()> {
+    let title = title.to_string();
+    pool.run(move |connection| {
+        connection.execute(
+            "UPDATE rag_session SET title = ?3 WHERE session_id = ?1 AND user_id = ?2",
+            params![session_id, user_id, title],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+pub(in crate::db) async fn delete_rag_session(
+    pool: &SqlitePool,
+    session_id: Uuid,
+    user_id: Uuid,
+) -> Result<bool> {
+    pool.run(move |connection| {
+        let deleted = connection.execute(
+            "DELETE FROM rag_session WHERE session_id = ?1 AND user_id = ?2",
+            params![session_id, user_id],
+        )?;
+        Ok(deleted > 0)
+    })
+    .await
+}
+
+pub(in crate::db) async fn delete_sessions_before(
+    pool: &SqlitePool,
+    created_before: DateTime<Utc>,
+    limit: i64,
+) -> Result<u64> {
+    pool.run(move |connection| {
+        let deleted = connection.execute(
+            "DELETE FROM rag_session WHERE session_id IN (
+                 SELECT session_id FROM rag_session WHERE created_at < ?1 LIMIT ?2
+             )",
+            params![timestamp(&created_before), limit],
+        )?;
+        Ok(deleted as u64)
+    })
+    .await
+}
+
+pub(in crate::db) async fn set_feedback(
+    pool: &SqlitePool,
+    session_id: Uuid,
+    user_id: Uuid,
+    request: &RagFeedbackRequest,
+) -> Result<Option<RagSession>> {
+    let rating = request.rating.as_ref().to_string();
+    let comment = request.comment.clone();
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                &format!(
+                    "UPDATE rag_session
+                     SET feedback_rating = ?3, feedback_comment = ?4, feedback_at = ?5
+                     WHERE session_id = ?1 AND user_id = ?2
+                     RETURNING {COLUMNS}"
+                ),
+                params![session_id, user_id, rating, comment, timestamp(&Utc::now())],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+/// Answers and ratings by day, and the latest comments, of the sessions
+/// started since `since`.
+pub(in crate::db) async fn quality_report(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    since: NaiveDate,
+    comment_limit: i64,
+) -> Result<(Vec<RagQualityDay>, Vec<RagFeedbackComment>)> {
+    pool.run(move |connection| {
+        let since = since.to_string();
+        let days = connection
+            .prepare(
+                "SELECT
+                    date(created_at) AS day,
+                    COUNT(*) FILTER (WHERE answer IS NOT NULL) AS answered,
+                    COUNT(*) FILTER (WHERE feedback_rating = 'up') AS rated_up,
+                    COUNT(*) FILTER (WHERE feedback_rating = 'down') AS rated_down
+                 FROM rag_session
+                 WHERE user_id = ?1 AND date(created_at) >= ?2
+                 GROUP BY day
+                 ORDER BY day DESC",
+            )?
+            .query_map(params![user_id, since], |row| {
+                Ok(RagQualityDay {
+                    day: row.get("day")?,
+                    answered: row.get("answered")?,
+                    rated_up: row.get("rated_up")?,
+                    rated_down: row.get("rated_down")?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        let comments = connection
+            .prepare(
+                "SELECT session_id, question, feedback_rating, feedback_comment, feedback_at
+                 FROM rag_session
+                 WHERE user_id = ?1 AND date(created_at) >= ?2
+                     AND feedback_comment IS NOT NULL
+                 ORDER BY feedback_at DESC
+                 LIMIT ?3",
+            )?
+            .query_map(params![user_id, since, comment_limit], |row| {
+                let Ok(rating) = row.get::<_, String>("feedback_rating")?.parse() else {
+                    return Ok(None);
+                };
+                Ok(Some(RagFeedbackComment {
+                    session_id: row.get("session_id")?,
+                    question: row.get("question")?,
+                    rating,
+                    comment: row.get("feedback_comment")?,
+                    rated_at: row.get("feedback_at")?,
+                }))
+            })?
+            .filter_map(Result::transpose)
+            .collect::<rusqlite::Result<_>>()?;
+        Ok((days, comments))
+    })
+    .await
+}
+
+/// A page of sessions, newest first, with the total count of the user,
+/// only those matching `query` when given.
+pub(in crate::db) async fn get_rag_sessions(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    query: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<RagSession>, i64)> {
+    let mut params: Params = vec![Box::new(user_id)];
+    let mut condition = String::new();
+    match query.map(fts_query) {
+        None => {}
+        Some(Some(FtsQuery::Match(expression))) => {
+            params.push(Box::new(expression));
+            condition = " AND session_id IN (SELECT session_id FROM rag_session_fts \
+                 WHERE rag_session_fts MATCH ?2)"
+                .to_string();
+        }
+        Some(Some(FtsQuery::Exclude(expression))) => {
+            params.push(Box::new(expression));
+            condition = " AND session_id NOT IN (SELECT session_id FROM rag_session_fts \
+                 WHERE rag_session_fts MATCH ?2)"
+                .to_string();
+        }
+        // Nothing searchable, like `websearch_to_tsquery` nothing matches
+        Some(None) => condition = " AND FALSE".to_string(),
+    }
+    pool.run(move |connection| {
+        let total_count = connection.query_row(
+            &format!("SELECT COUNT(*) FROM rag_session WHERE user_id = ?1{condition}"),
+            params_from_iter(params.iter()),
+            |row| row.get(0),
+        )?;
+        let limit_index = params.len() + 1;
+        params.push(Box::new(limit));
+        params.push(Box::new(offset));
+        let sessions = connection
+            .prepare(&format!(
+                "SELECT {COLUMNS} FROM rag_session
+                 WHERE user_id = ?1{condition}
+                 ORDER BY created_at DESC
+                 LIMIT ?{limit_index} OFFSET ?{}",
+                limit_index + 1
+            ))?
+            .query_map(params_from_iter(params.iter()), from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok((sessions, total_count))
+    })
+    .await
+}
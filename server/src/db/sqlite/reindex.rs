@@ -0,0 +1,50 @@
+use chrono::Utc;
+use rusqlite::params;
+use uuid::Uuid;
+
+use super::{timestamp, SqlitePool};
+use crate::error::Result;
+
+pub(in crate::db) async fn rebuild_search_tokens(pool: &SqlitePool, user_id: Uuid) -> Result<u64> {
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        // The FTS rows are replaced by the update triggers
+        let bookmarks = tx.execute(
+            "UPDATE bookmark SET title = title WHERE user_id = ?1",
+            params![user_id],
+        )?;
+        tx.execute(
+            "UPDATE bookmark_chunk SET chunk_text = chunk_text WHERE user_id = ?1",
+            params![user_id],
+        )?;
+        tx.commit()?;
+        Ok(bookmarks as u64)
+    })
+    .await
+}
+
+pub(in crate::db) async fn queue_missing_embeddings(
+    pool: &SqlitePool,
+    user_id: Uuid,
+) -> Result<u64> {
+    pool.run(move |connection| {
+        Ok(connection.execute(
+            "UPDATE bookmark
+             SET embedding_status = 'pending',
+                 embedding_attempts = 0,
+                 embedding_next_attempt_at = ?2,
+                 embedding_fail_reason = NULL
+             WHERE user_id = ?1
+               AND deleted_at IS NULL
+               AND (embedding_status = 'fail' OR (
+                   embedding_status = 'done' AND EXISTS (
+                       SELECT 1 FROM bookmark_chunk c
+                       WHERE c.bookmark_id = bookmark.bookmark_id AND c.user_id = bookmark.user_id
+                         AND (c.embedding IS NULL OR bookmark.embedding IS NULL)
+                   )
+               ))",
+            params![user_id, timestamp(&Utc::now())],
+        )? as u64)
+    })
+    .await
+}
@@ -0,0 +1,122 @@
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension, Row};
+use shared::{Rule, RuleRequest};
+use uuid::Uuid;
+
+use super::{timestamp, Json, SqlitePool};
+use crate::error::Result;
+
+const COLUMNS: &str = "rule_id, name, enabled, conditions, actions, created_at, updated_at";
+
+pub(super) fn from_row(row: &Row) -> rusqlite::Result<Rule> {
+    Ok(Rule {
+        rule_id: row.get("rule_id")?,
+        name: row.get("name")?,
+        enabled: row.get("enabled")?,
+        conditions: row.get::<_, Json<_>>("conditions")?.0,
+        actions: row.get::<_, Json<_>>("actions")?.0,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+pub(in crate::db) async fn list(pool: &SqlitePool, user_id: Uuid) -> Result<Vec<Rule>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(&format!(
+                "SELECT {COLUMNS} FROM rule WHERE user_id = ?1 ORDER BY created_at"
+            ))?
+            .query_map([user_id], from_row)?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    rule_id: Uuid,
+) -> Result<Option<Rule>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                &format!("SELECT {COLUMNS} FROM rule WHERE rule_id = ?1 AND user_id = ?2"),
+                params![rule_id, user_id],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn create(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    request: &RuleRequest,
+) -> Result<Rule> {
+    let request = request.clone();
+    pool.run(move |connection| {
+        Ok(connection.query_row(
+            &format!(
+                "INSERT INTO rule
+                    (rule_id, user_id, name, enabled, conditions, actions, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                 RETURNING {COLUMNS}"
+            ),
+            params![
+                Uuid::new_v4(),
+                user_id,
+                request.name,
+                request.enabled.unwrap_or(true),
+                Json(&request.conditions),
+                Json(&request.actions),
+                timestamp(&Utc::now())
+            ],
+            from_row,
+        )?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn update(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    rule_id: Uuid,
+    request: &RuleRequest,
+) -> Result<Option<Rule>> {
+    let request = request.clone();
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                &format!(
+                    "UPDATE rule
+                     SET name = ?3, enabled = ?4, conditions = ?5, actions = ?6, updated_at = ?7
+                     WHERE rule_id = ?1 AND user_id = ?2
+                     RETURNING {COLUMNS}"
+                ),
+                params![
+                    rule_id,
+                    user_id,
+                    request.name,
+                    request.enabled.unwrap_or(true),
+                    Json(&request.conditions),
+                    Json(&request.actions),
+                    timestamp(&Utc::now())
+                ],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn delete(pool: &SqlitePool, user_id: Uuid, rule_id: Uuid) -> Result<bool> {
+    pool.run(move |connection| {
+        let deleted = connection.execute(
+            "DELETE FROM rule WHERE rule_id = ?1 AND user_id = ?2",
+            params![rule_id, user_id],
+        )?;
+        Ok(deleted > 0)
+    })
+    .await
+}
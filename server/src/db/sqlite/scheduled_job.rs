@@ -0,0 +1,67 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, OptionalExtension};
+
+use super::{timestamp, SqlitePool};
+use crate::db::scheduled_job::JobRun;
+use crate::error::Result;
+
+pub(in crate::db) async fn last_run(pool: &SqlitePool, name: &str) -> Result<Option<JobRun>> {
+    let name = name.to_string();
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                "SELECT last_started_at, last_finished_at, last_error
+                 FROM scheduled_job WHERE name = ?1",
+                params![name],
+                |row| {
+                    Ok(JobRun {
+                        last_started_at: row.get("last_started_at")?,
+                        last_finished_at: row.get("last_finished_at")?,
+                        last_error: row.get("last_error")?,
+                    })
+                },
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn claim(
+    pool: &SqlitePool,
+    name: &str,
+    due: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<bool> {
+    let name = name.to_string();
+    pool.run(move |connection| {
+        let claimed = connection.execute(
+            "INSERT INTO scheduled_job (name, last_started_at) VALUES (?1, ?3)
+             ON CONFLICT (name) DO UPDATE
+             SET last_started_at = excluded.last_started_at,
+                 last_finished_at = NULL,
+                 last_error = NULL
+             WHERE scheduled_job.last_started_at < ?2",
+            params![name, timestamp(&due), timestamp(&now)],
+        )?;
+        Ok(claimed > 0)
+    })
+    .await
+}
+
+pub(in crate::db) async fn finish(
+    pool: &SqlitePool,
+    name: &str,
+    now: DateTime<Utc>,
+    error: Option<&str>,
+) -> Result<()> {
+    let name = name.to_string();
+    let error = error.map(str::to_string);
+    pool.run(move |connection| {
+        connection.execute(
+            "UPDATE scheduled_job SET last_finished_at = ?2, last_error = ?3 WHERE name = ?1",
+            params![name, timestamp(&now), error],
+        )?;
+        Ok(())
+    })
+    .await
+}
@@ -0,0 +1,499 @@
+use rusqlite::{params, params_from_iter, Connection};
+use shared::{
+    DomainCount, MonthCount, SearchRequest, SearchResponse, SearchResultItem, SearchSuggestions,
+    TagCount, TitleCount,
+};
+use tracing::debug;
+use uuid::Uuid;
+
+use super::{bookmark, fts_query, tag_condition, timestamp, FtsQuery, Json, Params, SqlitePool};
+use crate::db::search::{
+    kind_condition, like_prefix, reading_time_condition, DEFAULT_SNIPPET_COUNT,
+    DEFAULT_SNIPPET_WORDS, DOMAIN_FACET_SIZE,
+};
+use crate::error::Result;
+use crate::search_query::{self, Field, ParsedQuery};
+
+/// The `FROM` and `WHERE` clauses shared by the search and its aggregations,
+/// the full-text index is joined as `bookmark_fts` when the query matches on
+/// it.
+struct Selection {
+    from: &'static str,
+    filters: Vec<String>,
+    params: Params,
+    ranked: bool,
+    /// Trigram similarity of the query with the title or domain, ordering
+    /// the results of the fuzzy fallback
+    similarity: Option<String>,
+}
+
+impl Selection {
+    fn new(user_id: Uuid, request: &SearchRequest, query: &ParsedQuery) -> Self {
+        let mut selection = Self {
+            from: "bookmark b",
+            filters: vec![],
+            params: vec![],
+            ranked: false,
+            similarity: None,
+        };
+        match request.workspace {
+            Some(workspace_id) => {
+                selection.params.push(Box::new(user_id));
+                selection.params.push(Box::new(workspace_id));
+                selection.filters.push(
+                    "EXISTS (SELECT 1 FROM workspace_bookmark wb \
+                     JOIN workspace_member wm ON wm.workspace_id = wb.workspace_id \
+                     AND wm.user_id = ?1 \
+                     WHERE wb.workspace_id = ?2 AND wb.bookmark_id = b.bookmark_id \
+                     AND wb.user_id = b.user_id) AND b.visibility <> 'private'"
+                        .to_string(),
+                );
+            }
+            None => selection.push(user_id, "b.user_id = ?"),
+        }
+        selection.filters.push("b.deleted_at IS NULL".to_string());
+
+        if let Some(text) = &query.text {
+            match fts_query(text) {
+                Some(FtsQuery::Match(expression)) => {
+                    selection.from = "bookmark b JOIN bookmark_fts ON bookmark_fts.rowid = b.id";
+                    selection.ranked = true;
+                    selection.push(expression, "bookmark_fts MATCH ?");
+                }
+                Some(FtsQuery::Exclude(expression)) => selection.push(
+                    expression,
+                    "b.id NOT IN (SELECT rowid FROM bookmark_fts WHERE bookmark_fts MATCH ?)",
+                ),
+                // Like an empty tsquery, nothing to search matches nothing
+                None => selection.filters.push("FALSE".to_string()),
+            }
+        }
+
+        if let Some(condition) = request
+            .tags_filter
+            .as_ref()
+            .and_then(|filter| tag_condition(filter, &mut selection.params))
+        {
+            selection.filters.push(condition);
+        }
+
+        if !request.domains.is_empty() {
+            selection.push(
+                Json(request.domains.clone()),
+                "b.domain IN (SELECT value FROM json_each(?))",
+            );
+        }
+
+        if let Some(from_date) = &request.from_date {
+            selection.push(timestamp(from_date), "b.created_at >= ?");
+        }
+
+        if let Some(to_date) = &request.to_date {
+            selection.push(timestamp(to_date), "b.created_at <= ?");
+        }
+
+        if let Some(condition) = reading_time_condition(&request.reading_times) {
+            selection.filters.push(condition);
+        }
+
+        if let Some(condition) = kind_condition(&request.kinds) {
+            selection.filters.push(condition);
+        }
+
+        for entity in &request.entities {
+            selection.push(
+                entity.clone(),
+                "EXISTS (SELECT 1 FROM entity e WHERE e.bookmark_id = b.bookmark_id \
+                 AND e.user_id = b.user_id AND lower(e.name) = lower(?))",
+            );
+        }
+
+        if let Some(collection_id) = request.collection {
+            selection.push(
+                collection_id,
+                "EXISTS (SELECT 1 FROM collection_bookmark cb \
+                 WHERE cb.bookmark_id = b.bookmark_id AND cb.user_id = b.user_id \
+                 AND cb.collection_id = ?)",
+            );
+        }
+
+        for filter in &query.filters {
+            let condition = match filter.field {
+                Field::Title => "instr(lower(b.title), lower(?)) > 0",
+                Field::Domain => "b.domain = ?",
+                Field::Tag => "EXISTS (SELECT 1 FROM json_each(b.tags) WHERE value = ?)",
+            };
+            if filter.negated {
+                selection.push(filter.value.clone(), &format!("NOT {condition}"));
+            } else {
+                selection.push(filter.value.clone(), condition);
+            }
+        }
+
+        selection
+    }
+
+    /// The fallback for a full-text query that found nothing: bookmarks whose
+    /// title or domain are close to `words`, like `<%` in `pg_trgm`.
+    fn fuzzy(user_id: Uuid, request: &SearchRequest, query: &ParsedQuery, words: String) -> Self {
+        let query = ParsedQuery {
+            text: None,
+            filters: query.filters.clone(),
+        };
+        let mut selection = Self::new(user_id, request, &query);
+        selection.params.push(Box::new(words));
+        let idx = selection.params.len();
+        let similarity =
+            format!("max(word_similarity(?{idx}, b.title), word_similarity(?{idx}, b.domain))");
+        selection
+            .filters
+            .push(format!("{similarity} >= {FUZZY_THRESHOLD}"));
+        selection.similarity = Some(similarity);
+        selection
+    }
+
+    /// Binds `value` to the single `?` of `condition`.
+    fn push(&mut self, value: impl rusqlite::ToSql + Send + 'static, condition: &str) {
+        self.params.push(Box::new(value));
+        self.filters
+            .push(condition.replace('?', &format!("?{}", self.params.len())));
+    }
+
+    fn where_clause(&self) -> String {
+        format!("WHERE {}", self.filters.join(" AND "))
+    }
+
+    fn query<T>(
+        &self,
+        connection: &Connection,
+        sql: &str,
+        f: impl FnMut(&rusqlite::Row) -> rusqlite::Result<T>,
+    ) -> rusqlite::Result<Vec<T>> {
+        debug!(?sql, "Search query");
+        connection
+            .prepare(sql)?
+            .query_map(params_from_iter(self.params.iter()), f)?
+            .collect()
+    }
+}
+
+/// The default `pg_trgm.word_similarity_threshold` behind `<%`.
+const FUZZY_THRESHOLD: f64 = 0.6;
+
+/// Put around matches by `highlight()`, so fragments can be cut from the
+/// text before they are turned into `<mark>` tags.
+const MATCH_START: char = '\u{2}';
+const MATCH_END: char = '\u{3}';
+
+/// Up to `count` fragments of `words` words around the matches of a text
+/// highlighted with [`MATCH_START`] and [`MATCH_END`], in text order. FTS5's
+/// `snippet()` only returns one. Without a match in the text, e.g. when only
+/// the title matched, its start is the only fragment.
+fn fragments(highlighted: &str, count: usize, words: usize) -> Vec<String> {
+    let tokens: Vec<&str> = highlighted.split_whitespace().collect();
+    // Whether a match is open before each token, and after the last one
+    let mut open = Vec::with_capacity(tokens.len() + 1);
+    let mut depth = 0;
+    for token in &tokens {
+        open.push(depth > 0);
+        depth += token.matches(MATCH_START).count();
+        depth = depth.saturating_sub(token.matches(MATCH_END).count());
+    }
+    open.push(depth > 0);
+
+    let mut windows: Vec<(usize, usize)> = vec![];
+    for (index, token) in tokens.iter().enumerate() {
+        if windows.len() == count {
+            break;
+        }
+        let previous_end = windows.last().map_or(0, |&(_, end)| end);
+        if !token.contains(MATCH_START) || index < previous_end {
+            continue;
+        }
+        let start = index.saturating_sub(words / 2).max(previous_end);
+        windows.push((start, (start + words).min(tokens.len())));
+    }
+    if windows.is_empty() && !tokens.is_empty() {
+        windows.push((0, words.min(tokens.len())));
+    }
+
+    windows
+        .into_iter()
+        .map(|(start, end)| {
+            let mut fragment = String::new();
+            if start > 0 {
+                fragment.push('…');
+            }
+            if open[start] {
+                fragment.push(MATCH_START);
+            }
+            fragment.push_str(&tokens[start..end].join(" "));
+            if open[end] {
+                fragment.push(MATCH_END);
+            }
+            if end < tokens.len() {
+                fragment.push('…');
+            }
+            fragment
+                .replace(MATCH_START, "<mark>")
+                .replace(MATCH_END, "</mark>")
+        })
+        .collect()
+}
+
+pub(in crate::db) async fn search(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    request: &SearchRequest,
+    query: ParsedQuery,
+) -> Result<SearchResponse> {
+    let selection = Selection::new(user_id, request, &query);
+    let fuzzy = query
+        .text
+        .as_deref()
+        .and_then(search_query::fuzzy_words)
+        .map(|words| Selection::fuzzy(user_id, request, &query, words));
+    let page = Page {
+        limit: request.limit.unwrap_or(20),
+        offset: request.offset.unwrap_or(0),
+        snippet_count: request.snippet_count.unwrap_or(DEFAULT_SNIPPET_COUNT) as usize,
+        snippet_words: request.snippet_words.unwrap_or(DEFAULT_SNIPPET_WORDS) as usize,
+        highlight_title: request.highlight_title_summary,
+    };
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        let mut response = run_all(&tx, &selection, &page)?;
+        if let (0, Some(fuzzy)) = (response.total, fuzzy) {
+            debug!("No full-text match, falling back to trigrams");
+            response = run_all(&tx, &fuzzy, &page)?;
+            response.fuzzy = response.total > 0;
+        }
+        tx.commit()?;
+        Ok(response)
+    })
+    .await
+}
+
+struct Page {
+    limit: i32,
+    offset: i32,
+    snippet_count: usize,
+    snippet_words: usize,
+    highlight_title: bool,
+}
+
+fn run_all(
+    tx: &Connection,
+    selection: &Selection,
+    page: &Page,
+) -> rusqlite::Result<SearchResponse> {
+    let Page {
+        limit,
+        offset,
+        snippet_count,
+        snippet_words,
+        highlight_title,
+    } = *page;
+    let (from, where_clause) = (selection.from, selection.where_clause());
+
+    let (search_match, order_by) = if selection.ranked {
+        (
+            "highlight(bookmark_fts, 1, char(2), char(3))".to_string(),
+            // bm25() is lower for better matches, weighted like the title,
+            // text and tags weights of the Postgres search tokens
+            "bm25(bookmark_fts, 10.0, 4.0, 2.0)".to_string(),
+        )
+    } else if let Some(similarity) = &selection.similarity {
+        ("NULL".to_string(), format!("{similarity} DESC"))
+    } else {
+        ("NULL".to_string(), "b.created_at DESC".to_string())
+    };
+    // The summary is not indexed, so only the title gets highlighted
+    let title_highlight = if selection.ranked && highlight_title {
+        "highlight(bookmark_fts, 0, '<mark>', '</mark>')"
+    } else {
+        "NULL"
+    };
+    let items = selection.query(
+        tx,
+        &format!(
+            "SELECT b.*, {search_match} AS search_match, {title_highlight} AS title_highlight \
+             FROM {from} {where_clause} ORDER BY {order_by} LIMIT {limit} OFFSET {offset}"
+        ),
+        |row| {
+            let search_match: Option<String> = row.get("search_match")?;
+            Ok(SearchResultItem {
+                snippets: search_match
+                    .map(|text| fragments(&text, snippet_count, snippet_words))
+                    .unwrap_or_default(),
+                title_highlight: row.get("title_highlight")?,
+                summary_highlight: None,
+                bookmark: bookmark::from_row(row)?,
+            })
+        },
+    )?;
+
+    let tags = selection.query(
+        tx,
+        &format!(
+            "SELECT t.value AS tag, count(1) AS count \
+             FROM {from} JOIN json_each(b.tags) t {where_clause} GROUP BY t.value"
+        ),
+        |row| {
+            Ok(TagCount {
+                tag: row.get("tag")?,
+                count: row.get("count")?,
+            })
+        },
+    )?;
+
+    let domains = selection.query(
+        tx,
+        &format!(
+            "SELECT b.domain, count(1) AS count FROM {from} {where_clause} \
+             GROUP BY b.domain ORDER BY count DESC, b.domain LIMIT {DOMAIN_FACET_SIZE}"
+        ),
+        |row| {
+            Ok(DomainCount {
+                domain: row.get("domain")?,
+                count: row.get("count")?,
+            })
+        },
+    )?;
+
+    let months = selection.query(
+        tx,
+        &format!(
+            "SELECT strftime('%Y-%m-01', b.created_at) AS month, count(1) AS count \
+             FROM {from} {where_clause} GROUP BY month ORDER BY month"
+        ),
+        |row| {
+            Ok(MonthCount {
+                month: row.get("month")?,
+                count: row.get("count")?,
+            })
+        },
+    )?;
+
+    let total = selection.query(
+        tx,
+        &format!("SELECT COUNT(1) FROM {from} {where_clause}"),
+        |row| row.get::<_, i64>(0),
+    )?;
+
+    Ok(SearchResponse {
+        items,
+        tags,
+        domains,
+        months,
+        total: total.first().copied().unwrap_or_default() as u64,
+        fuzzy: false,
+    })
+}
+
+pub(in crate::db) async fn suggest(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    text: &str,
+    limit: i64,
+) -> Result<SearchSuggestions> {
+    let text = text.to_string();
+    let prefix = like_prefix(&text);
+    pool.run(move |connection| {
+        let params = params![user_id, text, prefix, limit, FUZZY_THRESHOLD];
+        let tags = connection
+            .prepare(
+                r"SELECT t.value AS tag, count(1) AS count
+                  FROM bookmark b JOIN json_each(b.tags) t
+                  WHERE b.user_id = ?1 AND b.deleted_at IS NULL
+                    AND (t.value LIKE ?3 ESCAPE '\' OR word_similarity(?2, t.value) >= ?5)
+                  GROUP BY t.value
+                  ORDER BY t.value LIKE ?3 ESCAPE '\' DESC, word_similarity(?2, t.value) DESC,
+                    count DESC, t.value
+                  LIMIT ?4",
+            )?
+            .query_map(params, |row| {
+                Ok(TagCount {
+                    tag: row.get("tag")?,
+                    count: row.get("count")?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        let titles = connection
+            .prepare(
+                r"SELECT b.title, count(1) AS count
+                  FROM bookmark b
+                  WHERE b.user_id = ?1 AND b.deleted_at IS NULL
+                    AND (b.title LIKE ?3 ESCAPE '\' OR word_similarity(?2, b.title) >= ?5)
+                  GROUP BY b.title
+                  ORDER BY b.title LIKE ?3 ESCAPE '\' DESC, word_similarity(?2, b.title) DESC,
+                    count DESC
+                  LIMIT ?4",
+            )?
+            .query_map(params, |row| {
+                Ok(TitleCount {
+                    title: row.get("title")?,
+                    count: row.get("count")?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        let domains = connection
+            .prepare(
+                r"SELECT b.domain, count(1) AS count
+                  FROM bookmark b
+                  WHERE b.user_id = ?1 AND b.deleted_at IS NULL
+                    AND (b.domain LIKE ?3 ESCAPE '\' OR word_similarity(?2, b.domain) >= ?5)
+                  GROUP BY b.domain
+                  ORDER BY b.domain LIKE ?3 ESCAPE '\' DESC, word_similarity(?2, b.domain) DESC,
+                    count DESC
+                  LIMIT ?4",
+            )?
+            .query_map(params, |row| {
+                Ok(DomainCount {
+                    domain: row.get("domain")?,
+                    count: row.get("count")?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(SearchSuggestions {
+            tags,
+            titles,
+            domains,
+        })
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlighted(text: &str) -> String {
+        text.replace("<mark>", &MATCH_START.to_string())
+            .replace("</mark>", &MATCH_END.to_string())
+    }
+
+    #[test]
+    fn fragments_surround_matches_in_text_order() {
+        let text = highlighted("a b <mark>c</mark> d e f g h i <mark>j</mark> k l");
+
+        assert_eq!(
+            fragments(&text, 2, 4),
+            vec!["a b <mark>c</mark> d…", "…h i <mark>j</mark> k…"]
+        );
+        assert_eq!(fragments(&text, 1, 4), vec!["a b <mark>c</mark> d…"]);
+    }
+
+    #[test]
+    fn fragments_keep_a_match_spanning_their_edge_marked() {
+        let text = highlighted("a <mark>b c d</mark> e");
+
+        assert_eq!(fragments(&text, 1, 2), vec!["a <mark>b</mark>…"]);
+    }
+
+    #[test]
+    fn text_without_match_starts_the_only_fragment() {
+        assert_eq!(fragments("a b c d", 3, 2), vec!["a b…"]);
+        assert!(fragments("", 3, 2).is_empty());
+    }
+}
@@ -0,0 +1,44 @@
+use rusqlite::params;
+use shared::StorageUsage;
+use uuid::Uuid;
+
+use super::SqlitePool;
+use crate::error::Result;
+
+pub(in crate::db) async fn usage(pool: &SqlitePool, user_id: Uuid) -> Result<StorageUsage> {
+    pool.run(move |connection| {
+        let (static_bytes, text_bytes): (i64, i64) = connection.query_row(
+            "SELECT
+                coalesce(SUM(static_bytes), 0),
+                coalesce(SUM(length(CAST(text_content AS BLOB))), 0)
+             FROM bookmark
+             WHERE user_id = ?1",
+            [user_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(StorageUsage {
+            static_bytes,
+            text_bytes,
+            total_bytes: static_bytes + text_bytes,
+            quota_bytes: None,
+        })
+    })
+    .await
+}
+
+pub(in crate::db) async fn set_static_bytes(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+    static_bytes: i64,
+) -> Result<()> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        connection.execute(
+            "UPDATE bookmark SET static_bytes = ?1 WHERE bookmark_id = ?2 AND user_id = ?3",
+            params![static_bytes, bookmark_id, user_id],
+        )?;
+        Ok(())
+    })
+    .await
+}
@@ -0,0 +1,95 @@
+use std::str::FromStr;
+
+use chrono::Utc;
+use rusqlite::types::Type;
+use rusqlite::{params, Row};
+use shared::{TaskEvent, TaskStage};
+use uuid::Uuid;
+
+use super::{timestamp, SqlitePool};
+use crate::error::Result;
+
+fn from_row(row: &Row) -> rusqlite::Result<TaskEvent> {
+    let stage: String = row.get("stage")?;
+    Ok(TaskEvent {
+        event_id: row.get("event_id")?,
+        task_id: row.get("task_id")?,
+        stage: TaskStage::from_str(&stage)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, Type::Text, Box::new(e)))?,
+        detail: row.get("detail")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+pub(in crate::db) async fn insert(
+    pool: &SqlitePool,
+    task_id: Uuid,
+    stage: TaskStage,
+    detail: Option<&str>,
+) -> Result<()> {
+    let detail = detail.map(str::to_string);
+    pool.run(move |connection| {
+        connection.execute(
+            "INSERT INTO task_event (event_id, task_id, stage, detail, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                Uuid::new_v4(),
+                task_id,
+                stage.as_ref(),
+                detail,
+                timestamp(&Utc::now())
+            ],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+pub(in crate::db) async fn insert_for_bookmark(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    bookmark_id: &str,
+    stage: TaskStage,
+    detail: Option<&str>,
+) -> Result<u64> {
+    let bookmark_id = bookmark_id.to_string();
+    let detail = detail.map(str::to_string);
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        let task_ids = tx
+            .prepare("SELECT task_id FROM bookmark_task WHERE user_id = ?1 AND bookmark_id = ?2")?
+            .query_map(params![user_id, bookmark_id], |row| row.get::<_, Uuid>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let now = timestamp(&Utc::now());
+        for task_id in &task_ids {
+            tx.execute(
+                "INSERT INTO task_event (event_id, task_id, stage, detail, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![Uuid::new_v4(), task_id, stage.as_ref(), detail, now],
+            )?;
+        }
+        tx.commit()?;
+        Ok(task_ids.len() as u64)
+    })
+    .await
+}
+
+pub(in crate::db) async fn list(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    task_id: Uuid,
+) -> Result<Vec<TaskEvent>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(
+                "SELECT e.event_id, e.task_id, e.stage, e.detail, e.created_at
+                 FROM task_event e
+                 JOIN bookmark_task t ON t.task_id = e.task_id
+                 WHERE e.task_id = ?1 AND t.user_id = ?2
+                 ORDER BY e.created_at, e.rowid",
+            )?
+            .query_map(params![task_id, user_id], from_row)?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
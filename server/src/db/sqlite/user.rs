@@ -0,0 +1,198 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, OptionalExtension, Row};
+use tracing::info;
+use uuid::Uuid;
+
+use super::{timestamp, SqlitePool, SqliteResultExt};
+use crate::db::user::{hash_token, TokenPurpose, User};
+use crate::error::{Error, Result};
+
+pub(super) fn from_row(row: &Row) -> rusqlite::Result<User> {
+    Ok(User {
+        user_id: row.get("user_id")?,
+        username: row.get("username")?,
+        password_hash: row.get("password_hash")?,
+        email: row.get("email")?,
+        email_verified_at: row.get("email_verified_at")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+pub(in crate::db) async fn get_by_id(pool: &SqlitePool, id: &Uuid) -> Result<Option<User>> {
+    let id = *id;
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(r#"SELECT * FROM "user" WHERE user_id = ?1"#, [id], from_row)
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get_by_username(
+    pool: &SqlitePool,
+    username: String,
+) -> Result<Option<User>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                r#"SELECT * FROM "user" WHERE lower(username) = ?1"#,
+                [username],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn create(
+    pool: &SqlitePool,
+    username: String,
+    email: Option<String>,
+    password_hash: String,
+) -> Result<User> {
+    let user = pool
+        .run(move |connection| {
+            let now = timestamp(&Utc::now());
+            connection
+                .query_row(
+                    r#"INSERT INTO "user" (user_id, username, email, password_hash, created_at, updated_at)
+                       VALUES (?1, ?2, ?3, ?4, ?5, ?5) RETURNING *"#,
+                    params![Uuid::new_v4(), username, email, password_hash, now],
+                    from_row,
+                )
+                .on_unique("index 'user_username_unique'", || {
+                    Error::constraint_violation("unique_username", "username already used")
+                })
+                .on_unique("index 'user_email_unique'", || {
+                    Error::constraint_violation("unique_email", "email already used")
+                })
+        })
+        .await?;
+    info!(
+        user_id = %user.user_id,
+        username = %user.username,
+        "User created successfully"
+    );
+    Ok(user)
+}
+
+pub(in crate::db) async fn update_password(
+    pool: &SqlitePool,
+    user_id: &Uuid,
+    password_hash: String,
+) -> Result<bool> {
+    let user_id = *user_id;
+    pool.run(move |connection| {
+        let rows_affected = connection.execute(
+            r#"UPDATE "user" SET password_hash = ?1, updated_at = ?2 WHERE user_id = ?3"#,
+            params![password_hash, timestamp(&Utc::now()), user_id],
+        )?;
+        Ok(rows_affected > 0)
+    })
+    .await
+}
+
+pub(in crate::db) async fn update_email(
+    pool: &SqlitePool,
+    user_id: &Uuid,
+    email: &str,
+) -> Result<bool> {
+    let user_id = *user_id;
+    let email = email.to_string();
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        let rows_affected = tx
+            .execute(
+                r#"UPDATE "user" SET email = ?1, email_verified_at = NULL, updated_at = ?2
+                   WHERE user_id = ?3"#,
+                params![email, timestamp(&Utc::now()), user_id],
+            )
+            .on_unique("index 'user_email_unique'", || {
+                Error::constraint_violation("unique_email", "email already used")
+            })?;
+        tx.execute(
+            "DELETE FROM user_token WHERE user_id = ?1 AND purpose = ?2",
+            params![user_id, TokenPurpose::VerifyEmail.as_str()],
+        )?;
+        tx.commit()?;
+        Ok(rows_affected > 0)
+    })
+    .await
+}
+
+pub(in crate::db) async fn mark_email_verified(pool: &SqlitePool, user_id: &Uuid) -> Result<bool> {
+    let user_id = *user_id;
+    pool.run(move |connection| {
+        let now = timestamp(&Utc::now());
+        let rows_affected = connection.execute(
+            r#"UPDATE "user" SET email_verified_at = ?1, updated_at = ?1
+               WHERE user_id = ?2 AND email IS NOT NULL"#,
+            params![now, user_id],
+        )?;
+        Ok(rows_affected > 0)
+    })
+    .await
+}
+
+pub(in crate::db) async fn create_token(
+    pool: &SqlitePool,
+    user_id: &Uuid,
+    purpose: TokenPurpose,
+    token: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<()> {
+    let user_id = *user_id;
+    let token_hash = hash_token(token);
+    pool.run(move |connection| {
+        connection.execute(
+            "INSERT INTO user_token (token_hash, user_id, purpose, expires_at, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                token_hash,
+                user_id,
+                purpose.as_str(),
+                timestamp(&expires_at),
+                timestamp(&Utc::now())
+            ],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+pub(in crate::db) async fn consume_token(
+    pool: &SqlitePool,
+    token: &str,
+    purpose: TokenPurpose,
+) -> Result<Option<Uuid>> {
+    let token_hash = hash_token(token);
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(
+                "UPDATE user_token SET used_at = ?1
+                 WHERE purpose = ?3 AND used_at IS NULL AND user_id = (
+                     SELECT user_id FROM user_token
+                     WHERE token_hash = ?2 AND purpose = ?3 AND used_at IS NULL
+                       AND expires_at > ?1
+                 )
+                 RETURNING user_id",
+            )?
+            .query_row(
+                params![timestamp(&Utc::now()), token_hash, purpose.as_str()],
+                |row| row.get(0),
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn delete(pool: &SqlitePool, user_id: &Uuid) -> Result<bool> {
+    let user_id = *user_id;
+    pool.run(move |connection| {
+        let rows_affected =
+            connection.execute(r#"DELETE FROM "user" WHERE user_id = ?1"#, [user_id])?;
+        Ok(rows_affected > 0)
+    })
+    .await
+}
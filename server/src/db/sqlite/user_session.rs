@@ -0,0 +1,132 @@
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Row};
+use uuid::Uuid;
+
+use super::{timestamp, SqlitePool};
+use crate::db::user_session::{UserSession, TOUCH_INTERVAL};
+use crate::error::Result;
+
+fn from_row(row: &Row) -> rusqlite::Result<UserSession> {
+    Ok(UserSession {
+        session_id: row.get("session_id")?,
+        device_label: row.get("device_label")?,
+        ip: row.get("ip")?,
+        created_at: row.get("created_at")?,
+        last_seen_at: row.get("last_seen_at")?,
+        expires_at: row.get("expires_at")?,
+    })
+}
+
+pub(in crate::db) async fn create(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    device_label: Option<&str>,
+    ip: Option<IpAddr>,
+    expires_at: DateTime<Utc>,
+) -> Result<Uuid> {
+    let device_label = device_label.map(str::to_string);
+    pool.run(move |connection| {
+        let now = timestamp(&Utc::now());
+        connection.execute(
+            "DELETE FROM user_session WHERE user_id = ?1 AND expires_at <= ?2",
+            params![user_id, now],
+        )?;
+        let session_id = Uuid::new_v4();
+        connection.execute(
+            "INSERT INTO user_session
+                 (session_id, user_id, device_label, ip, created_at, last_seen_at, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?5, ?6)",
+            params![
+                session_id,
+                user_id,
+                device_label,
+                ip.map(|ip| ip.to_string()),
+                now,
+                timestamp(&expires_at)
+            ],
+        )?;
+        Ok(session_id)
+    })
+    .await
+}
+
+pub(in crate::db) async fn touch(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    session_id: Uuid,
+    ip: Option<IpAddr>,
+) -> Result<bool> {
+    pool.run(move |connection| {
+        let now = Utc::now();
+        let touched = connection.execute(
+            "UPDATE user_session SET last_seen_at = ?3, ip = COALESCE(?4, ip)
+             WHERE session_id = ?1 AND user_id = ?2 AND expires_at > ?3 AND last_seen_at < ?5",
+            params![
+                session_id,
+                user_id,
+                timestamp(&now),
+                ip.map(|ip| ip.to_string()),
+                timestamp(&(now - TOUCH_INTERVAL))
+            ],
+        )?;
+        if touched > 0 {
+            return Ok(true);
+        }
+        Ok(connection.query_row(
+            "SELECT EXISTS (
+                 SELECT 1 FROM user_session
+                 WHERE session_id = ?1 AND user_id = ?2 AND expires_at > ?3
+             )",
+            params![session_id, user_id, timestamp(&now)],
+            |row| row.get(0),
+        )?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn list(pool: &SqlitePool, user_id: Uuid) -> Result<Vec<UserSession>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(
+                "SELECT session_id, device_label, ip, created_at, last_seen_at, expires_at
+                 FROM user_session
+                 WHERE user_id = ?1 AND expires_at > ?2
+                 ORDER BY last_seen_at DESC, session_id",
+            )?
+            .query_map(params![user_id, timestamp(&Utc::now())], from_row)?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn revoke_others(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    keep: Option<Uuid>,
+) -> Result<u64> {
+    pool.run(move |connection| {
+        let deleted = connection.execute(
+            "DELETE FROM user_session WHERE user_id = ?1 AND (?2 IS NULL OR session_id <> ?2)",
+            params![user_id, keep],
+        )?;
+        Ok(deleted as u64)
+    })
+    .await
+}
+
+pub(in crate::db) async fn revoke(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    session_id: Uuid,
+) -> Result<bool> {
+    pool.run(move |connection| {
+        let deleted = connection.execute(
+            "DELETE FROM user_session WHERE session_id = ?1 AND user_id = ?2",
+            params![session_id, user_id],
+        )?;
+        Ok(deleted > 0)
+    })
+    .await
+}
@@ -0,0 +1,90 @@
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension, Row};
+use shared::{ReaderSettings, UserSettings};
+use uuid::Uuid;
+
+use super::{timestamp, SqlitePool};
+use crate::error::Result;
+
+pub(super) fn from_row(row: &Row) -> rusqlite::Result<UserSettings> {
+    Ok(UserSettings {
+        auto_tagging_enabled: row.get("auto_tagging_enabled")?,
+        summaries_enabled: row.get("summaries_enabled")?,
+        summary_language: row.get("summary_language")?,
+        preferred_text_model: row.get("preferred_text_model")?,
+        digest_enabled: row.get("digest_enabled")?,
+        reader: ReaderSettings {
+            font_family: row
+                .get::<_, String>("reader_font_family")?
+                .parse()
+                .unwrap_or_default(),
+            font_size: row.get("reader_font_size")?,
+            line_width: row.get("reader_line_width")?,
+            justify: row.get("reader_justify")?,
+        },
+    })
+}
+
+pub(in crate::db) async fn get(pool: &SqlitePool, user_id: Uuid) -> Result<UserSettings> {
+    pool.run(move |connection| {
+        let settings = connection
+            .query_row(
+                "SELECT auto_tagging_enabled, summaries_enabled, summary_language,
+                        preferred_text_model, digest_enabled, reader_font_family,
+                        reader_font_size, reader_line_width, reader_justify
+                 FROM user_settings
+                 WHERE user_id = ?1",
+                [user_id],
+                from_row,
+            )
+            .optional()?;
+        Ok(settings.unwrap_or_default())
+    })
+    .await
+}
+
+pub(in crate::db) async fn upsert(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    settings: &UserSettings,
+) -> Result<UserSettings> {
+    let settings = settings.clone();
+    pool.run(move |connection| {
+        Ok(connection.query_row(
+            "INSERT INTO user_settings
+                (user_id, auto_tagging_enabled, summaries_enabled, summary_language,
+                 preferred_text_model, digest_enabled, reader_font_family, reader_font_size,
+                 reader_line_width, reader_justify, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?11)
+             ON CONFLICT (user_id) DO UPDATE SET
+                auto_tagging_enabled = excluded.auto_tagging_enabled,
+                summaries_enabled = excluded.summaries_enabled,
+                summary_language = excluded.summary_language,
+                preferred_text_model = excluded.preferred_text_model,
+                digest_enabled = excluded.digest_enabled,
+                reader_font_family = excluded.reader_font_family,
+                reader_font_size = excluded.reader_font_size,
+                reader_line_width = excluded.reader_line_width,
+                reader_justify = excluded.reader_justify,
+                updated_at = excluded.updated_at
+             RETURNING auto_tagging_enabled, summaries_enabled, summary_language,
+                       preferred_text_model, digest_enabled, reader_font_family,
+                       reader_font_size, reader_line_width, reader_justify",
+            params![
+                user_id,
+                settings.auto_tagging_enabled,
+                settings.summaries_enabled,
+                settings.summary_language,
+                settings.preferred_text_model,
+                settings.digest_enabled,
+                settings.reader.font_family.as_ref(),
+                settings.reader.font_size,
+                settings.reader.line_width,
+                settings.reader.justify,
+                timestamp(&Utc::now())
+            ],
+            from_row,
+        )?)
+    })
+    .await
+}
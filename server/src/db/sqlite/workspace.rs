@@ -0,0 +1,248 @@
+use std::str::FromStr;
+
+use chrono::Utc;
+use rusqlite::types::Type;
+use rusqlite::{params, OptionalExtension, Row};
+use shared::{Workspace, WorkspaceMember, WorkspaceRole};
+use uuid::Uuid;
+
+use super::{timestamp, SqlitePool};
+use crate::error::Result;
+
+const SELECT: &str = "SELECT w.workspace_id, w.name, w.created_at, m.role,
+        (SELECT count(1) FROM workspace_member wm
+         WHERE wm.workspace_id = w.workspace_id) AS member_count,
+        (SELECT count(1) FROM workspace_bookmark wb
+         JOIN bookmark b ON b.bookmark_id = wb.bookmark_id AND b.user_id = wb.user_id
+         WHERE wb.workspace_id = w.workspace_id AND b.deleted_at IS NULL
+         AND b.visibility <> 'private') AS bookmark_count
+    FROM workspace w
+    JOIN workspace_member m ON m.workspace_id = w.workspace_id AND m.user_id = ?1";
+
+fn role(row: &Row) -> rusqlite::Result<WorkspaceRole> {
+    let role: String = row.get("role")?;
+    WorkspaceRole::from_str(&role)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, Type::Text, Box::new(e)))
+}
+
+fn from_row(row: &Row) -> rusqlite::Result<Workspace> {
+    Ok(Workspace {
+        workspace_id: row.get("workspace_id")?,
+        name: row.get("name")?,
+        role: role(row)?,
+        member_count: row.get("member_count")?,
+        bookmark_count: row.get("bookmark_count")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+fn member_from_row(row: &Row) -> rusqlite::Result<WorkspaceMember> {
+    Ok(WorkspaceMember {
+        user_id: row.get("user_id")?,
+        username: row.get("username")?,
+        role: role(row)?,
+        joined_at: row.get("joined_at")?,
+    })
+}
+
+pub(in crate::db) async fn list(pool: &SqlitePool, user_id: Uuid) -> Result<Vec<Workspace>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(&format!("{SELECT} ORDER BY lower(w.name), w.created_at"))?
+            .query_map([user_id], from_row)?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn get(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    workspace_id: Uuid,
+) -> Result<Option<Workspace>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .query_row(
+                &format!("{SELECT} WHERE w.workspace_id = ?2"),
+                params![user_id, workspace_id],
+                from_row,
+            )
+            .optional()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn create(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    name: &str,
+) -> Result<Workspace> {
+    let name = name.to_string();
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        let workspace_id = Uuid::new_v4();
+        let now = timestamp(&Utc::now());
+        tx.execute(
+            "INSERT INTO workspace (workspace_id, owner_id, name, created_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![workspace_id, user_id, name, now],
+        )?;
+        tx.execute(
+            "INSERT INTO workspace_member (workspace_id, user_id, role, joined_at)
+             VALUES (?1, ?2, 'owner', ?3)",
+            params![workspace_id, user_id, now],
+        )?;
+        let workspace = tx.query_row(
+            &format!("{SELECT} WHERE w.workspace_id = ?2"),
+            params![user_id, workspace_id],
+            from_row,
+        )?;
+        tx.commit()?;
+        Ok(workspace)
+    })
+    .await
+}
+
+pub(in crate::db) async fn delete(
+    pool: &SqlitePool,
+    user_id: Uuid,
+    workspace_id: Uuid,
+) -> Result<bool> {
+    pool.run(move |connection| {
+        Ok(connection.execute(
+            "DELETE FROM workspace WHERE workspace_id = ?1 AND owner_id = ?2",
+            params![workspace_id, user_id],
+        )? > 0)
+    })
+    .await
+}
+
+pub(in crate::db) async fn members(
+    pool: &SqlitePool,
+    workspace_id: Uuid,
+) -> Result<Vec<WorkspaceMember>> {
+    pool.run(move |connection| {
+        Ok(connection
+            .prepare(
+                r#"SELECT m.user_id, u.username, m.role, m.joined_at
+                   FROM workspace_member m
+                   JOIN "user" u ON u.user_id = m.user_id
+                   WHERE m.workspace_id = ?1
+                   ORDER BY m.role = 'owner' DESC, lower(u.username)"#,
+            )?
+            .query_map([workspace_id], member_from_row)?
+            .collect::<rusqlite::Result<_>>()?)
+    })
+    .await
+}
+
+pub(in crate::db) async fn set_member(
+    pool: &SqlitePool,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    role: WorkspaceRole,
+) -> Result<bool> {
+    pool.run(move |connection| {
+        Ok(connection.execute(
+            "INSERT INTO workspace_member (workspace_id, user_id, role, joined_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (workspace_id, user_id) DO UPDATE SET role = excluded.role
+             WHERE workspace_member.role <> 'owner'",
+            params![workspace_id, user_id, role.as_ref(), timestamp(&Utc::now())],
+        )? > 0)
+    })
+    .await
+}
+
+pub(in crate::db) async fn remove_member(
+    pool: &SqlitePool,
+    workspace_id: Uuid,
+    user_id: Uuid,
+) -> Result<bool> {
+    pool.run(move |connection| {
+        let tx = connection.transaction()?;
+        let deleted = tx.execute(
+            "DELETE FROM workspace_member
+             WHERE workspace_id = ?1 AND user_id = ?2 AND role <> 'owner'",
+            params![workspace_id, user_id],
+        )?;
+        if deleted == 0 {
+            return Ok(false);
+        }
+        tx.execute(
+            "DELETE FROM workspace_bookmark WHERE workspace_id = ?1 AND user_id = ?2",
+            params![workspace_id, user_id],
+        )?;
+        tx.commit()?;
+        Ok(true)
+    })
+    .await
+}
+
+pub(in crate::db) async fn add_bookmark(
+    pool: &SqlitePool,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<bool> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        let found = connection
+            .query_row(
+                "SELECT 1 FROM bookmark
+                 WHERE bookmark_id = ?1 AND user_id = ?2 AND deleted_at IS NULL
+                 AND visibility <> 'private'",
+                params![bookmark_id, user_id],
+                |_| Ok(()),
+            )
+            .optional()?;
+        if found.is_none() {
+            return Ok(false);
+        }
+        connection.execute(
+            "INSERT INTO workspace_bookmark (workspace_id, user_id, bookmark_id, added_at)
+             VALUES (?1, ?2, ?3, ?4) ON CONFLICT DO NOTHING",
+            params![workspace_id, user_id, bookmark_id, timestamp(&Utc::now())],
+        )?;
+        Ok(true)
+    })
+    .await
+}
+
+pub(in crate::db) async fn remove_bookmark(
+    pool: &SqlitePool,
+    workspace_id: Uuid,
+    bookmark_id: &str,
+) -> Result<bool> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        Ok(connection.execute(
+            "DELETE FROM workspace_bookmark WHERE workspace_id = ?1 AND bookmark_id = ?2",
+            params![workspace_id, bookmark_id],
+        )? > 0)
+    })
+    .await
+}
+
+pub(in crate::db) async fn shares_bookmark(
+    pool: &SqlitePool,
+    member_id: Uuid,
+    owner_id: Uuid,
+    bookmark_id: &str,
+) -> Result<bool> {
+    let bookmark_id = bookmark_id.to_string();
+    pool.run(move |connection| {
+        Ok(connection.query_row(
+            "SELECT EXISTS (
+                 SELECT 1 FROM workspace_bookmark wb
+                 JOIN workspace_member wm ON wm.workspace_id = wb.workspace_id AND wm.user_id = ?1
+                 JOIN bookmark b ON b.bookmark_id = wb.bookmark_id AND b.user_id = wb.user_id
+                 WHERE wb.user_id = ?2 AND wb.bookmark_id = ?3
+                 AND b.deleted_at IS NULL AND b.visibility <> 'private'
+             )",
+            params![member_id, owner_id, bookmark_id],
+            |row| row.get(0),
+        )?)
+    })
+    .await
+}
@@ -0,0 +1,70 @@
+use shared::StorageUsage;
+use tracing::debug;
+use uuid::Uuid;
+
+use super::{dispatch, sqlite, Database};
+use crate::error::{Error, Result};
+
+pub async fn usage(pool: &Database, user_id: Uuid) -> Result<StorageUsage> {
+    const SQL: &str = r#"
+        SELECT
+            COALESCE(SUM(static_bytes), 0)::BIGINT AS static_bytes,
+            COALESCE(SUM(octet_length(text_content)), 0)::BIGINT AS text_bytes
+        FROM bookmark
+        WHERE user_id = $1;"#;
+    let pool = dispatch!(pool, |db| sqlite::storage::usage(db, user_id));
+    let client = pool.get().await?;
+    let row = client.query_one(SQL, &[&user_id]).await?;
+    let static_bytes: i64 = row.get("static_bytes");
+    let text_bytes: i64 = row.get("text_bytes");
+    Ok(StorageUsage {
+        static_bytes,
+        text_bytes,
+        total_bytes: static_bytes + text_bytes,
+        quota_bytes: None,
+    })
+}
+
+/// Records the size of the static content written for a bookmark.
+pub async fn set_static_bytes(
+    pool: &Database,
+    user_id: Uuid,
+    bookmark_id: &str,
+    static_bytes: i64,
+) -> Result<()> {
+    const SQL: &str =
+        "UPDATE bookmark SET static_bytes = $1 WHERE bookmark_id = $2 AND user_id = $3;";
+    let pool = dispatch!(pool, |db| sqlite::storage::set_static_bytes(
+        db,
+        user_id,
+        bookmark_id,
+        static_bytes
+    ));
+    let client = pool.get().await?;
+    client
+        .execute(SQL, &[&static_bytes, &bookmark_id, &user_id])
+        .await?;
+    debug!(bookmark_id, static_bytes, "Static content size recorded");
+    Ok(())
+}
+
+/// Fails with [`Error::StorageQuotaExceeded`] once `user_id` stores
+/// `quota_bytes` or more, a `None` quota never fails.
+pub async fn ensure_within_quota(
+    pool: &Database,
+    user_id: Uuid,
+    quota_bytes: Option<u64>,
+) -> Result<()> {
+    let Some(quota_bytes) = quota_bytes else {
+        return Ok(());
+    };
+    let quota_bytes = i64::try_from(quota_bytes).unwrap_or(i64::MAX);
+    let used_bytes = usage(pool, user_id).await?.total_bytes;
+    if used_bytes >= quota_bytes {
+        return Err(Error::StorageQuotaExceeded {
+            used_bytes,
+            quota_bytes,
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use std::str::FromStr;
+
+use shared::{TaskEvent, TaskStage};
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+use super::{dispatch, sqlite, Database};
+use crate::error::{Error, Result};
+
+fn from_row(row: &Row) -> Result<TaskEvent> {
+    let stage: String = row.get("stage");
+    Ok(TaskEvent {
+        event_id: row.get("event_id"),
+        task_id: row.get("task_id"),
+        stage: TaskStage::from_str(&stage)
+            .map_err(|_| Error::from(anyhow::anyhow!("unknown task stage: {stage}")))?,
+        detail: row.get("detail"),
+        created_at: row.get("created_at"),
+    })
+}
+
+pub async fn insert(
+    pool: &Database,
+    task_id: Uuid,
+    stage: TaskStage,
+    detail: Option<&str>,
+) -> Result<()> {
+    const SQL: &str = "INSERT INTO task_event (task_id, stage, detail) VALUES ($1, $2, $3);";
+    let pool = dispatch!(pool, |db| sqlite::task_event::insert(
+        db, task_id, stage, detail
+    ));
+    let client = pool.get().await?;
+    client
+        .execute(SQL, &[&task_id, &stage.as_ref(), &detail])
+        .await?;
+    Ok(())
+}
+
+/// Adds the stage to the timeline of every task that produced the bookmark,
+/// bookmarks without a task are skipped.
+pub async fn insert_for_bookmark(
+    pool: &Database,
+    user_id: Uuid,
+    bookmark_id: &str,
+    stage: TaskStage,
+    detail: Option<&str>,
+) -> Result<u64> {
+    const SQL: &str = r#"
+        INSERT INTO task_event (task_id, stage, detail)
+        SELECT task_id, $3, $4
+        FROM bookmark_task
+        WHERE user_id = $1 AND bookmark_id = $2;"#;
+    let pool = dispatch!(pool, |db| sqlite::task_event::insert_for_bookmark(
+        db,
+        user_id,
+        bookmark_id,
+        stage,
+        detail
+    ));
+    let client = pool.get().await?;
+    let rows_affected = client
+        .execute(SQL, &[&user_id, &bookmark_id, &stage.as_ref(), &detail])
+        .await?;
+    Ok(rows_affected)
+}
+
+/// Timeline of `task_id`, oldest stage first.
+pub async fn list(pool: &Database, user_id: Uuid, task_id: Uuid) -> Result<Vec<TaskEvent>> {
+    const SQL: &str = r#"
+        SELECT e.event_id, e.task_id, e.stage, e.detail, e.created_at
+        FROM task_event e
+        JOIN bookmark_task t ON t.task_id = e.task_id
+        WHERE e.task_id = $1 AND t.user_id = $2
+        ORDER BY e.created_at, e.event_id;"#;
+    let pool = dispatch!(pool, |db| sqlite::task_event::list(db, user_id, task_id));
+    let client = pool.get().await?;
+    let rows = client.query(SQL, &[&task_id, &user_id]).await?;
+    rows.iter().map(from_row).collect()
+}
@@ -1,29 +1,56 @@
-use chrono::{DateTime, Utc};
-use deadpool_postgres::GenericClient;
+use chrono::{DateTime, Duration, Utc};
 use postgres_from_row::FromRow;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tracing::{debug, info};
 use uuid::Uuid;
 
-use super::PgPool;
+use super::{dispatch, sqlite, Database};
 use crate::db::{Error, Result, ResultExt};
 
 pub(crate) fn normalize_username(username: &str) -> String {
     username.trim().to_lowercase()
 }
 
+pub(crate) fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// What a token sent by email can be redeemed for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenPurpose {
+    VerifyEmail,
+    PasswordReset,
+}
+
+impl TokenPurpose {
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            TokenPurpose::VerifyEmail => "verify_email",
+            TokenPurpose::PasswordReset => "password_reset",
+        }
+    }
+}
+
+pub(super) fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct User {
     pub user_id: Uuid,
     pub username: String,
     pub password_hash: String,
+    pub email: Option<String>,
+    pub email_verified_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-pub async fn get_by_id(pool: &PgPool, id: &Uuid) -> Result<Option<User>> {
+pub async fn get_by_id(pool: &Database, id: &Uuid) -> Result<Option<User>> {
     const SQL: &str = r#"SELECT * FROM "user" WHERE user_id = $1;"#;
     debug!(user_id = %id, "Fetching user by id");
+    let pool = dispatch!(pool, |db| sqlite::user::get_by_id(db, id));
     let client = pool.get().await?;
     let result = client.query_opt(SQL, &[id]).await?;
     let user = result.map(|row| User::try_from_row(&row)).transpose()?;
@@ -34,10 +61,11 @@ pub async fn get_by_id(pool: &PgPool, id: &Uuid) -> Result<Option<User>> {
     Ok(user)
 }
 
-pub async fn get_by_username(pool: &PgPool, username: String) -> Result<Option<User>> {
+pub async fn get_by_username(pool: &Database, username: String) -> Result<Option<User>> {
     const SQL: &str = r#"SELECT * from "user" WHERE LOWER(username) = $1;"#;
     let username = normalize_username(&username);
     debug!(username = %username, "Fetching user by username");
+    let pool = dispatch!(pool, |db| sqlite::user::get_by_username(db, username));
     let client = pool.get().await?;
     let result = client.query_opt(SQL, &[&username]).await?;
     let user = result.map(|row| User::try_from_row(&row)).transpose()?;
@@ -48,16 +76,30 @@ pub async fn get_by_username(pool: &PgPool, username: String) -> Result<Option<U
     Ok(user)
 }
 
-pub async fn create(pool: &PgPool, username: String, password_hash: String) -> Result<User> {
+pub async fn create(
+    pool: &Database,
+    username: String,
+    email: Option<String>,
+    password_hash: String,
+) -> Result<User> {
     let username = normalize_username(&username);
-    const SQL: &str =
-        r#"INSERT INTO "user" (username, password_hash) VALUES ($1, $2) RETURNING "user".*;"#;
+    let email = email.as_deref().map(normalize_email);
+    const SQL: &str = r#"INSERT INTO "user" (username, email, password_hash) VALUES ($1, $2, $3) RETURNING "user".*;"#;
+    let pool = dispatch!(pool, |db| sqlite::user::create(
+        db,
+        username,
+        email,
+        password_hash
+    ));
     let client = pool.get().await?;
     let row = client
-        .query_one(SQL, &[&username, &password_hash])
+        .query_one(SQL, &[&username, &email, &password_hash])
         .await
         .on_constraint("user_username_unique", |_| {
             Error::constraint_violation("unique_username", "username already used")
+        })
+        .on_constraint("user_email_unique", |_| {
+            Error::constraint_violation("unique_email", "email already used")
         })?;
     let user = User::try_from_row(&row)?;
     info!(
@@ -67,3 +109,130 @@ pub async fn create(pool: &PgPool, username: String, password_hash: String) -> R
     );
     Ok(user)
 }
+
+pub async fn update_password(
+    pool: &Database,
+    user_id: &Uuid,
+    password_hash: String,
+) -> Result<bool> {
+    const SQL: &str =
+        r#"UPDATE "user" SET password_hash = $1, updated_at = now() WHERE user_id = $2;"#;
+    let pool = dispatch!(pool, |db| sqlite::user::update_password(
+        db,
+        user_id,
+        password_hash
+    ));
+    let client = pool.get().await?;
+    let rows_affected = client.execute(SQL, &[&password_hash, user_id]).await?;
+    if rows_affected > 0 {
+        info!(user_id = %user_id, "User password updated");
+    } else {
+        debug!(user_id = %user_id, "User not found for password update");
+    }
+    Ok(rows_affected > 0)
+}
+
+/// Sets a new, unverified email address and drops verification tokens issued
+/// for the previous one.
+pub async fn update_email(pool: &Database, user_id: &Uuid, email: &str) -> Result<bool> {
+    const SQL: &str = r#"
+        UPDATE "user" SET email = $1, email_verified_at = NULL, updated_at = now()
+        WHERE user_id = $2;"#;
+    let email = normalize_email(email);
+    let pool = dispatch!(pool, |db| sqlite::user::update_email(db, user_id, &email));
+    let client = pool.get().await?;
+    let rows_affected = client
+        .execute(SQL, &[&email, user_id])
+        .await
+        .on_constraint("user_email_unique", |_| {
+            Error::constraint_violation("unique_email", "email already used")
+        })?;
+    client
+        .execute(
+            "DELETE FROM user_token WHERE user_id = $1 AND purpose = $2",
+            &[user_id, &TokenPurpose::VerifyEmail.as_str()],
+        )
+        .await?;
+    debug!(user_id = %user_id, updated = rows_affected > 0, "User email updated");
+    Ok(rows_affected > 0)
+}
+
+pub async fn mark_email_verified(pool: &Database, user_id: &Uuid) -> Result<bool> {
+    const SQL: &str = r#"
+        UPDATE "user" SET email_verified_at = now(), updated_at = now()
+        WHERE user_id = $1 AND email IS NOT NULL;"#;
+    let pool = dispatch!(pool, |db| sqlite::user::mark_email_verified(db, user_id));
+    let client = pool.get().await?;
+    let rows_affected = client.execute(SQL, &[user_id]).await?;
+    Ok(rows_affected > 0)
+}
+
+/// Issues a random single-use token for `purpose`. Only its hash is stored,
+/// the returned value is what gets emailed to the user.
+pub async fn create_token(
+    pool: &Database,
+    user_id: &Uuid,
+    purpose: TokenPurpose,
+    ttl: Duration,
+) -> Result<String> {
+    const SQL: &str = r#"
+        INSERT INTO user_token (token_hash, user_id, purpose, expires_at)
+        VALUES ($1, $2, $3, $4);"#;
+    let token = hex::encode(rand::random::<[u8; 32]>());
+    let expires_at = Utc::now() + ttl;
+    match pool {
+        Database::Postgres(pool) => {
+            let client = pool.get().await?;
+            client
+                .execute(
+                    SQL,
+                    &[&hash_token(&token), user_id, &purpose.as_str(), &expires_at],
+                )
+                .await?;
+        }
+        Database::Sqlite(db) => {
+            sqlite::user::create_token(db, user_id, purpose, &token, expires_at).await?
+        }
+    }
+    debug!(user_id = %user_id, purpose = purpose.as_str(), "User token issued");
+    Ok(token)
+}
+
+/// Marks `token` as used and returns its user, `None` when the token is
+/// unknown, expired, already used or issued for another purpose. The other
+/// unused tokens the user has for `purpose` are used up along with it, so an
+/// older reset email can't be redeemed after the password was reset.
+pub async fn consume_token(
+    pool: &Database,
+    token: &str,
+    purpose: TokenPurpose,
+) -> Result<Option<Uuid>> {
+    const SQL: &str = r#"
+        UPDATE user_token SET used_at = now()
+        WHERE purpose = $2 AND used_at IS NULL AND user_id = (
+            SELECT user_id FROM user_token
+            WHERE token_hash = $1 AND purpose = $2 AND used_at IS NULL AND expires_at > now()
+        )
+        RETURNING user_id;"#;
+    let pool = dispatch!(pool, |db| sqlite::user::consume_token(db, token, purpose));
+    let client = pool.get().await?;
+    let rows = client
+        .query(SQL, &[&hash_token(token), &purpose.as_str()])
+        .await?;
+    Ok(rows.first().map(|row| row.get("user_id")))
+}
+
+/// Deletes the user row. Bookmarks, chunks, RAG sessions and tasks are removed
+/// through `ON DELETE CASCADE` foreign keys.
+pub async fn delete(pool: &Database, user_id: &Uuid) -> Result<bool> {
+    const SQL: &str = r#"DELETE FROM "user" WHERE user_id = $1;"#;
+    let pool = dispatch!(pool, |db| sqlite::user::delete(db, user_id));
+    let client = pool.get().await?;
+    let rows_affected = client.execute(SQL, &[user_id]).await?;
+    if rows_affected > 0 {
+        info!(user_id = %user_id, "User deleted");
+    } else {
+        debug!(user_id = %user_id, "User not found for deletion");
+    }
+    Ok(rows_affected > 0)
+}
@@ -0,0 +1,161 @@
+use std::net::IpAddr;
+
+use chrono::{DateTime, Duration, Utc};
+use tokio_postgres::Row;
+use tracing::debug;
+use uuid::Uuid;
+
+use super::{dispatch, sqlite, Database};
+use crate::error::Result;
+
+/// A session seen again within this long isn't written to, so each request
+/// doesn't update its row.
+pub(in crate::db) const TOUCH_INTERVAL: Duration = Duration::minutes(1);
+
+/// A session as stored, [`shared::Session`] tells which one is current.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserSession {
+    pub session_id: Uuid,
+    pub device_label: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+fn from_row(row: &Row) -> UserSession {
+    UserSession {
+        session_id: row.get("session_id"),
+        device_label: row.get("device_label"),
+        ip: row.get("ip"),
+        created_at: row.get("created_at"),
+        last_seen_at: row.get("last_seen_at"),
+        expires_at: row.get("expires_at"),
+    }
+}
+
+/// Starts a session of `user_id`, dropping the ones that expired.
+pub async fn create(
+    pool: &Database,
+    user_id: Uuid,
+    device_label: Option<&str>,
+    ip: Option<IpAddr>,
+    expires_at: DateTime<Utc>,
+) -> Result<Uuid> {
+    let pool = dispatch!(pool, |db| sqlite::user_session::create(
+        db,
+        user_id,
+        device_label,
+        ip,
+        expires_at
+    ));
+    let client = pool.get().await?;
+    client
+        .execute(
+            "DELETE FROM user_session WHERE user_id = $1 AND expires_at <= now()",
+            &[&user_id],
+        )
+        .await?;
+    let row = client
+        .query_one(
+            "INSERT INTO user_session (user_id, device_label, ip, expires_at)
+             VALUES ($1, $2, $3, $4) RETURNING session_id",
+            &[
+                &user_id,
+                &device_label,
+                &ip.map(|ip| ip.to_string()),
+                &expires_at,
+            ],
+        )
+        .await?;
+    let session_id = row.get("session_id");
+    debug!(%user_id, %session_id, "Session created");
+    Ok(session_id)
+}
+
+/// Marks the session as seen from `ip` now, unless it already was less than
+/// [`TOUCH_INTERVAL`] ago. False when it was revoked or expired.
+pub async fn touch(
+    pool: &Database,
+    user_id: Uuid,
+    session_id: Uuid,
+    ip: Option<IpAddr>,
+) -> Result<bool> {
+    const SQL: &str = r#"
+        WITH session AS (
+            SELECT session_id, last_seen_at FROM user_session
+            WHERE session_id = $1 AND user_id = $2 AND expires_at > now()
+        ), touched AS (
+            UPDATE user_session SET last_seen_at = now(), ip = COALESCE($3, user_session.ip)
+            FROM session
+            WHERE user_session.session_id = session.session_id
+              AND session.last_seen_at < $4
+        )
+        SELECT EXISTS (SELECT 1 FROM session) AS valid;"#;
+    let pool = dispatch!(pool, |db| sqlite::user_session::touch(
+        db, user_id, session_id, ip
+    ));
+    let client = pool.get().await?;
+    let seen_before = Utc::now() - TOUCH_INTERVAL;
+    let row = client
+        .query_one(
+            SQL,
+            &[
+                &session_id,
+                &user_id,
+                &ip.map(|ip| ip.to_string()),
+                &seen_before,
+            ],
+        )
+        .await?;
+    Ok(row.get("valid"))
+}
+
+/// Sessions of `user_id` that haven't expired, last seen first.
+pub async fn list(pool: &Database, user_id: Uuid) -> Result<Vec<UserSession>> {
+    let pool = dispatch!(pool, |db| sqlite::user_session::list(db, user_id));
+    let client = pool.get().await?;
+    let rows = client
+        .query(
+            "SELECT session_id, device_label, ip, created_at, last_seen_at, expires_at
+             FROM user_session
+             WHERE user_id = $1 AND expires_at > now()
+             ORDER BY last_seen_at DESC, session_id",
+            &[&user_id],
+        )
+        .await?;
+    Ok(rows.iter().map(from_row).collect())
+}
+
+/// Ends every session of `user_id` but `keep`, their access tokens are
+/// refused from then on. Returns how many ended.
+pub async fn revoke_others(pool: &Database, user_id: Uuid, keep: Option<Uuid>) -> Result<u64> {
+    let pool = dispatch!(pool, |db| sqlite::user_session::revoke_others(
+        db, user_id, keep
+    ));
+    let client = pool.get().await?;
+    let deleted = client
+        .execute(
+            "DELETE FROM user_session
+             WHERE user_id = $1 AND ($2::UUID IS NULL OR session_id <> $2)",
+            &[&user_id, &keep],
+        )
+        .await?;
+    debug!(%user_id, ?keep, deleted, "Other sessions revoked");
+    Ok(deleted)
+}
+
+/// Ends the session, its access token is refused from then on.
+pub async fn revoke(pool: &Database, user_id: Uuid, session_id: Uuid) -> Result<bool> {
+    let pool = dispatch!(pool, |db| sqlite::user_session::revoke(
+        db, user_id, session_id
+    ));
+    let client = pool.get().await?;
+    let deleted = client
+        .execute(
+            "DELETE FROM user_session WHERE session_id = $1 AND user_id = $2",
+            &[&session_id, &user_id],
+        )
+        .await?;
+    Ok(deleted > 0)
+}
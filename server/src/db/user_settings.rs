@@ -0,0 +1,92 @@
+use shared::{ReaderSettings, UserSettings};
+use tokio_postgres::Row;
+use tracing::debug;
+use uuid::Uuid;
+
+use super::{dispatch, sqlite, Database};
+use crate::error::Result;
+
+pub(super) fn from_row(row: &Row) -> UserSettings {
+    UserSettings {
+        auto_tagging_enabled: row.get("auto_tagging_enabled"),
+        summaries_enabled: row.get("summaries_enabled"),
+        summary_language: row.get("summary_language"),
+        preferred_text_model: row.get("preferred_text_model"),
+        digest_enabled: row.get("digest_enabled"),
+        reader: ReaderSettings {
+            font_family: row
+                .get::<_, String>("reader_font_family")
+                .parse()
+                .unwrap_or_default(),
+            font_size: row.get("reader_font_size"),
+            line_width: row.get("reader_line_width"),
+            justify: row.get("reader_justify"),
+        },
+    }
+}
+
+/// Settings for `user_id`, falling back to the defaults when the user never
+/// saved any.
+pub async fn get(pool: &Database, user_id: Uuid) -> Result<UserSettings> {
+    const SQL: &str = r#"
+        SELECT auto_tagging_enabled, summaries_enabled, summary_language, preferred_text_model,
+               digest_enabled, reader_font_family, reader_font_size, reader_line_width,
+               reader_justify
+        FROM user_settings
+        WHERE user_id = $1;"#;
+    let pool = dispatch!(pool, |db| sqlite::user_settings::get(db, user_id));
+    let client = pool.get().await?;
+    let row = client.query_opt(SQL, &[&user_id]).await?;
+    debug!(user_id = %user_id, stored = row.is_some(), "Fetched user settings");
+    Ok(row.as_ref().map(from_row).unwrap_or_default())
+}
+
+pub async fn upsert(
+    pool: &Database,
+    user_id: Uuid,
+    settings: &UserSettings,
+) -> Result<UserSettings> {
+    const SQL: &str = r#"
+        INSERT INTO user_settings
+            (user_id, auto_tagging_enabled, summaries_enabled, summary_language, preferred_text_model,
+             digest_enabled, reader_font_family, reader_font_size, reader_line_width,
+             reader_justify)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        ON CONFLICT (user_id) DO UPDATE SET
+            auto_tagging_enabled = EXCLUDED.auto_tagging_enabled,
+            summaries_enabled = EXCLUDED.summaries_enabled,
+            summary_language = EXCLUDED.summary_language,
+            preferred_text_model = EXCLUDED.preferred_text_model,
+            digest_enabled = EXCLUDED.digest_enabled,
+            reader_font_family = EXCLUDED.reader_font_family,
+            reader_font_size = EXCLUDED.reader_font_size,
+            reader_line_width = EXCLUDED.reader_line_width,
+            reader_justify = EXCLUDED.reader_justify,
+            updated_at = now()
+        RETURNING auto_tagging_enabled, summaries_enabled, summary_language, preferred_text_model,
+                  digest_enabled, reader_font_family, reader_font_size, reader_line_width,
+                  reader_justify;"#;
+    let pool = dispatch!(pool, |db| sqlite::user_settings::upsert(
+        db, user_id, settings
+    ));
+    let client = pool.get().await?;
+    let row = client
+        .query_one(
+            SQL,
+            &[
+                &user_id,
+                &settings.auto_tagging_enabled,
+                &settings.summaries_enabled,
+                &settings.summary_language,
+                &settings.preferred_text_model,
+                &settings.digest_enabled,
+                &settings.reader.font_family.as_ref(),
+                &settings.reader.font_size,
+                &settings.reader.line_width,
+                &settings.reader.justify,
+            ],
+        )
+        .await?;
+    debug!(user_id = %user_id, "Saved user settings");
+    Ok(from_row(&row))
+}
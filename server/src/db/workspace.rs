@@ -0,0 +1,276 @@
+use std::str::FromStr;
+
+use shared::{Workspace, WorkspaceMember, WorkspaceRole};
+use tokio_postgres::Row;
+use tracing::debug;
+use uuid::Uuid;
+
+use super::{dispatch, sqlite, Database};
+use crate::error::{Error, Result};
+
+/// Workspaces joined with the membership of `$1`, the requesting user.
+const SELECT: &str = "SELECT w.workspace_id, w.name, w.created_at, m.role,
+        (SELECT count(1) FROM workspace_member wm
+         WHERE wm.workspace_id = w.workspace_id) AS member_count,
+        (SELECT count(1) FROM workspace_bookmark wb
+         JOIN bookmark b ON b.bookmark_id = wb.bookmark_id AND b.user_id = wb.user_id
+         WHERE wb.workspace_id = w.workspace_id AND b.deleted_at IS NULL
+         AND b.visibility <> 'private') AS bookmark_count
+    FROM workspace w
+    JOIN workspace_member m ON m.workspace_id = w.workspace_id AND m.user_id = $1";
+
+fn parse_role(role: &str) -> Result<WorkspaceRole> {
+    WorkspaceRole::from_str(role)
+        .map_err(|_| Error::from(anyhow::anyhow!("unknown workspace role: {role}")))
+}
+
+fn from_row(row: &Row) -> Result<Workspace> {
+    Ok(Workspace {
+        workspace_id: row.get("workspace_id"),
+        name: row.get("name"),
+        role: parse_role(row.get("role"))?,
+        member_count: row.get("member_count"),
+        bookmark_count: row.get("bookmark_count"),
+        created_at: row.get("created_at"),
+    })
+}
+
+fn member_from_row(row: &Row) -> Result<WorkspaceMember> {
+    Ok(WorkspaceMember {
+        user_id: row.get("user_id"),
+        username: row.get("username"),
+        role: parse_role(row.get("role"))?,
+        joined_at: row.get("joined_at"),
+    })
+}
+
+/// Workspaces the user is a member of, by name.
+pub async fn list(pool: &Database, user_id: Uuid) -> Result<Vec<Workspace>> {
+    let pool = dispatch!(pool, |db| sqlite::workspace::list(db, user_id));
+    let client = pool.get().await?;
+    let rows = client
+        .query(
+            &format!("{SELECT} ORDER BY lower(w.name), w.created_at"),
+            &[&user_id],
+        )
+        .await?;
+    rows.iter().map(from_row).collect()
+}
+
+/// `None` unless the user is a member of the workspace.
+pub async fn get(pool: &Database, user_id: Uuid, workspace_id: Uuid) -> Result<Option<Workspace>> {
+    let pool = dispatch!(pool, |db| sqlite::workspace::get(db, user_id, workspace_id));
+    let client = pool.get().await?;
+    let row = client
+        .query_opt(
+            &format!("{SELECT} WHERE w.workspace_id = $2"),
+            &[&user_id, &workspace_id],
+        )
+        .await?;
+    row.as_ref().map(from_row).transpose()
+}
+
+/// Creates a workspace owned by the user.
+pub async fn create(pool: &Database, user_id: Uuid, name: &str) -> Result<Workspace> {
+    let pool = dispatch!(pool, |db| sqlite::workspace::create(db, user_id, name));
+    let mut client = pool.get().await?;
+    let tx = client.transaction().await?;
+    let workspace_id: Uuid = tx
+        .query_one(
+            "INSERT INTO workspace (owner_id, name) VALUES ($1, $2) RETURNING workspace_id",
+            &[&user_id, &name],
+        )
+        .await?
+        .get("workspace_id");
+    tx.execute(
+        "INSERT INTO workspace_member (workspace_id, user_id, role) VALUES ($1, $2, 'owner')",
+        &[&workspace_id, &user_id],
+    )
+    .await?;
+    let row = tx
+        .query_one(
+            &format!("{SELECT} WHERE w.workspace_id = $2"),
+            &[&user_id, &workspace_id],
+        )
+        .await?;
+    tx.commit().await?;
+    debug!(%user_id, %workspace_id, "Workspace created");
+    from_row(&row)
+}
+
+/// Deletes the workspace, not the bookmarks saved into it. False unless the
+/// user owns it.
+pub async fn delete(pool: &Database, user_id: Uuid, workspace_id: Uuid) -> Result<bool> {
+    let pool = dispatch!(pool, |db| sqlite::workspace::delete(
+        db,
+        user_id,
+        workspace_id
+    ));
+    let client = pool.get().await?;
+    let deleted = client
+        .execute(
+            "DELETE FROM workspace WHERE workspace_id = $1 AND owner_id = $2",
+            &[&workspace_id, &user_id],
+        )
+        .await?;
+    Ok(deleted > 0)
+}
+
+/// Members of the workspace, the owner first.
+pub async fn members(pool: &Database, workspace_id: Uuid) -> Result<Vec<WorkspaceMember>> {
+    let pool = dispatch!(pool, |db| sqlite::workspace::members(db, workspace_id));
+    let client = pool.get().await?;
+    let rows = client
+        .query(
+            r#"SELECT m.user_id, u.username, m.role, m.joined_at
+               FROM workspace_member m
+               JOIN "user" u ON u.user_id = m.user_id
+               WHERE m.workspace_id = $1
+               ORDER BY m.role = 'owner' DESC, lower(u.username)"#,
+            &[&workspace_id],
+        )
+        .await?;
+    rows.iter().map(member_from_row).collect()
+}
+
+/// Adds the user to the workspace, or changes their role. The owner keeps
+/// theirs, false when `user_id` is the owner.
+pub async fn set_member(
+    pool: &Database,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    role: WorkspaceRole,
+) -> Result<bool> {
+    let pool = dispatch!(pool, |db| sqlite::workspace::set_member(
+        db,
+        workspace_id,
+        user_id,
+        role
+    ));
+    let client = pool.get().await?;
+    let updated = client
+        .execute(
+            "INSERT INTO workspace_member (workspace_id, user_id, role) VALUES ($1, $2, $3)
+             ON CONFLICT (workspace_id, user_id) DO UPDATE SET role = excluded.role
+             WHERE workspace_member.role <> 'owner'",
+            &[&workspace_id, &user_id, &role.as_ref()],
+        )
+        .await?;
+    Ok(updated > 0)
+}
+
+/// Removes a member other than the owner along with the bookmarks they
+/// saved into the workspace, false when there is no such member.
+pub async fn remove_member(pool: &Database, workspace_id: Uuid, user_id: Uuid) -> Result<bool> {
+    let pool = dispatch!(pool, |db| sqlite::workspace::remove_member(
+        db,
+        workspace_id,
+        user_id
+    ));
+    let mut client = pool.get().await?;
+    let tx = client.transaction().await?;
+    let deleted = tx
+        .execute(
+            "DELETE FROM workspace_member
+             WHERE workspace_id = $1 AND user_id = $2 AND role <> 'owner'",
+            &[&workspace_id, &user_id],
+        )
+        .await?;
+    if deleted == 0 {
+        return Ok(false);
+    }
+    tx.execute(
+        "DELETE FROM workspace_bookmark WHERE workspace_id = $1 AND user_id = $2",
+        &[&workspace_id, &user_id],
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Saves a bookmark of the user into the workspace, false when the user has
+/// no such bookmark or keeps it private. Saving it again changes nothing.
+pub async fn add_bookmark(
+    pool: &Database,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<bool> {
+    let pool = dispatch!(pool, |db| sqlite::workspace::add_bookmark(
+        db,
+        workspace_id,
+        user_id,
+        bookmark_id
+    ));
+    let client = pool.get().await?;
+    let found = client
+        .query_opt(
+            "SELECT 1 FROM bookmark WHERE bookmark_id = $1 AND user_id = $2 AND deleted_at IS NULL
+             AND visibility <> 'private'",
+            &[&bookmark_id, &user_id],
+        )
+        .await?;
+    if found.is_none() {
+        return Ok(false);
+    }
+    client
+        .execute(
+            "INSERT INTO workspace_bookmark (workspace_id, user_id, bookmark_id)
+             VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+            &[&workspace_id, &user_id, &bookmark_id],
+        )
+        .await?;
+    Ok(true)
+}
+
+/// Removes the bookmark from the workspace whichever members saved it,
+/// false when none did.
+pub async fn remove_bookmark(
+    pool: &Database,
+    workspace_id: Uuid,
+    bookmark_id: &str,
+) -> Result<bool> {
+    let pool = dispatch!(pool, |db| sqlite::workspace::remove_bookmark(
+        db,
+        workspace_id,
+        bookmark_id
+    ));
+    let client = pool.get().await?;
+    let deleted = client
+        .execute(
+            "DELETE FROM workspace_bookmark WHERE workspace_id = $1 AND bookmark_id = $2",
+            &[&workspace_id, &bookmark_id],
+        )
+        .await?;
+    Ok(deleted > 0)
+}
+
+/// Whether the owner saved the bookmark into a workspace the member belongs
+/// to, and still shares it.
+pub async fn shares_bookmark(
+    pool: &Database,
+    member_id: Uuid,
+    owner_id: Uuid,
+    bookmark_id: &str,
+) -> Result<bool> {
+    let pool = dispatch!(pool, |db| sqlite::workspace::shares_bookmark(
+        db,
+        member_id,
+        owner_id,
+        bookmark_id
+    ));
+    let client = pool.get().await?;
+    let row = client
+        .query_one(
+            "SELECT EXISTS (
+                 SELECT 1 FROM workspace_bookmark wb
+                 JOIN workspace_member wm ON wm.workspace_id = wb.workspace_id AND wm.user_id = $1
+                 JOIN bookmark b ON b.bookmark_id = wb.bookmark_id AND b.user_id = wb.user_id
+                 WHERE wb.user_id = $2 AND wb.bookmark_id = $3
+                 AND b.deleted_at IS NULL AND b.visibility <> 'private'
+             )",
+            &[&member_id, &owner_id, &bookmark_id],
+        )
+        .await?;
+    Ok(row.get(0))
+}
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+use url::Url;
+
+use crate::FetchParams;
+
+/// Longest `Retry-After` honored, servers asking for more are retried after
+/// this instead.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// Assumed when a 429 comes without a usable `Retry-After` header.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// A site answered `429 Too Many Requests`.
+#[derive(Debug, thiserror::Error)]
+#[error("{host} answered 429 Too Many Requests, retry after {retry_after:?}")]
+pub struct TooManyRequests {
+    pub host: String,
+    pub retry_after: Duration,
+}
+
+impl TooManyRequests {
+    pub fn new(url: &Url, retry_after: Option<&str>) -> Self {
+        Self {
+            host: url.host_str().unwrap_or_default().to_string(),
+            retry_after: retry_after
+                .and_then(|value| parse_retry_after(value, Utc::now()))
+                .unwrap_or(DEFAULT_RETRY_AFTER)
+                .min(MAX_RETRY_AFTER),
+        }
+    }
+}
+
+/// Parses a `Retry-After` header, either delay seconds or an HTTP date.
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+struct DomainState {
+    permits: Arc<Semaphore>,
+    /// Earliest start of the next request, keeps requests `min_delay` apart.
+    next_request_at: Instant,
+    /// Set after a 429 until the server's `Retry-After` has elapsed.
+    blocked_until: Option<Instant>,
+}
+
+/// Politeness towards the sites we fetch from: at most `max_concurrency`
+/// requests in flight per host, spaced by at least `min_delay`, and no
+/// requests at all while a host asked us to back off.
+pub struct DomainLimiter {
+    min_delay: Duration,
+    max_concurrency: usize,
+    domains: Mutex<HashMap<String, DomainState>>,
+}
+
+impl DomainLimiter {
+    pub fn new(params: &FetchParams) -> Self {
+        Self {
+            min_delay: Duration::from_millis(params.fetch_domain_delay_ms),
+            max_concurrency: params.fetch_domain_concurrency.max(1) as usize,
+            domains: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Waits for a request slot on the host of `url`, the request must be
+    /// done before the returned permit is dropped.
+    pub async fn acquire(&self, url: &Url) -> OwnedSemaphorePermit {
+        let host = host_key(url);
+        let permits = {
+            let mut domains = self.domains.lock().expect("domain limiter mutex poisoned");
+            let now = Instant::now();
+            // Idle hosts with no pending delay carry no state worth keeping
+            domains.retain(|_, state| {
+                Arc::strong_count(&state.permits) > 1
+                    || state.next_request_at > now
+                    || state.blocked_until.is_some_and(|until| until > now)
+            });
+            domains
+                .entry(host.clone())
+                .or_insert_with(|| self.new_state(now))
+                .permits
+                .clone()
+        };
+        let permit = permits
+            .acquire_owned()
+            .await
+            .expect("domain semaphore is never closed");
+
+        loop {
+            let start_at = {
+                let mut domains = self.domains.lock().expect("domain limiter mutex poisoned");
+                let now = Instant::now();
+                let state = domains
+                    .entry(host.clone())
+                    .or_insert_with(|| self.new_state(now));
+                let start_at = state.blocked_until.map_or(state.next_request_at, |until| {
+                    until.max(state.next_request_at)
+                });
+                if start_at <= now {
+                    state.next_request_at = now + self.min_delay;
+                    return permit;
+                }
+                start_at
+            };
+            tokio::time::sleep_until(start_at).await;
+        }
+    }
+
+    /// Stops requests to the host of `url` for `retry_after`.
+    pub fn back_off(&self, url: &Url, retry_after: Duration) {
+        let mut domains = self.domains.lock().expect("domain limiter mutex poisoned");
+        let now = Instant::now();
+        let state = domains
+            .entry(host_key(url))
+            .or_insert_with(|| self.new_state(now));
+        let until = now + retry_after;
+        state.blocked_until = Some(
+            state
+                .blocked_until
+                .map_or(until, |current| current.max(until)),
+        );
+    }
+
+    /// How much longer the host of `url` is backed off, `None` when requests
+    /// are allowed.
+    pub fn blocked_for(&self, url: &Url) -> Option<Duration> {
+        let domains = self.domains.lock().expect("domain limiter mutex poisoned");
+        let until = domains.get(&host_key(url))?.blocked_until?;
+        let remaining = until.saturating_duration_since(Instant::now());
+        (!remaining.is_zero()).then_some(remaining)
+    }
+
+    fn new_state(&self, now: Instant) -> DomainState {
+        DomainState {
+            permits: Arc::new(Semaphore::new(self.max_concurrency)),
+            next_request_at: now,
+            blocked_until: None,
+        }
+    }
+}
+
+fn host_key(url: &Url) -> String {
+    url.host_str().unwrap_or_default().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{TimeZone, Utc};
+    use url::Url;
+
+    use super::{parse_retry_after, DomainLimiter, TooManyRequests, MAX_RETRY_AFTER};
+    use crate::FetchParams;
+
+    fn limiter(delay_ms: u64, concurrency: u32) -> DomainLimiter {
+        DomainLimiter::new(&FetchParams {
+            fetch_domain_delay_ms: delay_ms,
+            fetch_domain_concurrency: concurrency,
+            fetch_respect_robots: false,
+            fetch_proxy: None,
+            fetch_user_agent: None,
+            fetch_user_agent_overrides: Vec::new(),
+            fetch_allowed_networks: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn retry_after_accepts_seconds_and_http_dates() {
+        let now = Utc.with_ymd_and_hms(2015, 10, 21, 7, 27, 0).unwrap();
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn too_many_requests_caps_retry_after() {
+        let url = Url::parse("https://example.com/a").unwrap();
+        let error = TooManyRequests::new(&url, Some("86400"));
+        assert_eq!(error.host, "example.com");
+        assert_eq!(error.retry_after, MAX_RETRY_AFTER);
+    }
+
+    #[tokio::test]
+    async fn requests_to_the_same_host_are_spaced() {
+        let limiter = limiter(200, 4);
+        let url = Url::parse("https://example.com/a").unwrap();
+        let other = Url::parse("https://other.example.org/b").unwrap();
+        let start = tokio::time::Instant::now();
+
+        drop(limiter.acquire(&url).await);
+        drop(limiter.acquire(&other).await);
+        assert!(start.elapsed() < Duration::from_millis(200));
+
+        drop(limiter.acquire(&url).await);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn back_off_blocks_the_host() {
+        let limiter = limiter(0, 1);
+        let url = Url::parse("https://example.com/a").unwrap();
+        let start = tokio::time::Instant::now();
+
+        limiter.back_off(&url, Duration::from_millis(200));
+        assert!(limiter.blocked_for(&url).is_some());
+
+        drop(limiter.acquire(&url).await);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert_eq!(limiter.blocked_for(&url), None);
+    }
+}
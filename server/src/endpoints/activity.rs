@@ -0,0 +1,37 @@
+use axum::extract::Query;
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use axum_macros::debug_handler;
+use shared::{ActivityFeed, ActivityRequest};
+use tracing::debug;
+
+use super::{bounded_limit, Claim};
+use crate::db::activity;
+use crate::error::Result;
+use crate::AppContext;
+
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 200;
+
+pub fn routes() -> Router {
+    Router::new().route("/activity", get(get_activity))
+}
+
+#[utoipa::path(
+    get,
+    path = "/activity",
+    tag = "activity",
+    params(ActivityRequest),
+    responses((status = 200, body = ActivityFeed), (status = 422))
+)]
+#[debug_handler]
+async fn get_activity(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Query(request): Query<ActivityRequest>,
+) -> Result<Json<ActivityFeed>> {
+    let limit = bounded_limit(request.limit, DEFAULT_LIMIT, MAX_LIMIT, "limit")?;
+    let items = activity::list(&app_context.pool, claims.user_id, request.before, limit).await?;
+    debug!(user_id = %claims.user_id, item_count = items.len(), "Listed activity");
+    Ok(Json(ActivityFeed { items }))
+}
@@ -0,0 +1,33 @@
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use axum_macros::debug_handler;
+use shared::AiStatus;
+
+use super::Claim;
+use crate::error::{Error, Result};
+use crate::AppContext;
+
+pub fn routes() -> Router {
+    Router::new().route("/ai/status", get(ai_status))
+}
+
+/// Which AI features work, as of the last startup or readiness check, so
+/// clients can tell why summaries or semantic search are missing.
+#[utoipa::path(
+    get,
+    path = "/ai/status",
+    tag = "ai",
+    responses((status = 200, body = AiStatus))
+)]
+#[debug_handler]
+async fn ai_status(
+    _claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+) -> Result<Json<AiStatus>> {
+    let status = app_context
+        .ai_status
+        .read()
+        .map_err(|_| Error::Anyhow(anyhow::anyhow!("AI status lock poisoned")))?
+        .clone();
+    Ok(Json(status))
+}
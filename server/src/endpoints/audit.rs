@@ -0,0 +1,79 @@
+use std::net::IpAddr;
+
+use axum::extract::Query;
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use axum_macros::debug_handler;
+use shared::{AuditAction, AuditLog, AuditLogRequest};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use super::{bounded_limit, Claim};
+use crate::db::{audit_log, Database};
+use crate::error::Result;
+use crate::AppContext;
+
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 200;
+
+pub fn routes() -> Router {
+    Router::new().route("/audit", get(get_audit_log))
+}
+
+/// Adds `action` to the audit log of `user_id`. A failure is logged only, the
+/// action it describes already happened.
+pub(crate) async fn record(
+    pool: &Database,
+    user_id: Uuid,
+    action: AuditAction,
+    target: Option<&str>,
+    ip: Option<IpAddr>,
+) {
+    if let Err(error) = audit_log::insert(pool, user_id, action, target, ip).await {
+        warn!(?error, %user_id, ?action, "Failed to record audit event");
+    }
+}
+
+/// Adds a failed sign-in as `username` to the audit log of the user it
+/// names, or under the name alone when no account has it. `client` names the
+/// API the attempt came through, `None` for the web sign-in.
+pub(crate) async fn record_failed_sign_in(
+    pool: &Database,
+    user_id: Option<Uuid>,
+    username: &str,
+    client: Option<&str>,
+    ip: Option<IpAddr>,
+) {
+    let action = AuditAction::SignInFailed;
+    let recorded = match user_id {
+        Some(user_id) => audit_log::insert(pool, user_id, action, client, ip).await,
+        None => audit_log::insert_for_username(pool, username, action, client, ip).await,
+    };
+    if let Err(error) = recorded {
+        warn!(
+            ?error,
+            ?user_id,
+            username,
+            "Failed to record failed sign-in"
+        );
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/audit",
+    tag = "auth",
+    params(AuditLogRequest),
+    responses((status = 200, body = AuditLog), (status = 422))
+)]
+#[debug_handler]
+async fn get_audit_log(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Query(request): Query<AuditLogRequest>,
+) -> Result<Json<AuditLog>> {
+    let limit = bounded_limit(request.limit, DEFAULT_LIMIT, MAX_LIMIT, "limit")?;
+    let events = audit_log::list(&app_context.pool, claims.user_id, request.before, limit).await?;
+    debug!(user_id = %claims.user_id, event_count = events.len(), "Listed audit log");
+    Ok(Json(AuditLog { events }))
+}
@@ -1,21 +1,45 @@
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 
-use axum::extract::ConnectInfo;
-use axum::routing::{get, post};
+use axum::extract::{ConnectInfo, Path};
+use axum::http::StatusCode;
+use axum::middleware::from_fn_with_state;
+use axum::routing::{delete, get, post};
 use axum::{Extension, Json, Router};
+use axum_extra::headers::UserAgent;
+use axum_extra::TypedHeader;
 use axum_macros::debug_handler;
 use chrono::{Duration, Utc};
-use secrecy::ExposeSecret;
-use shared::{SignInRequest, SignInResponse, SignUpRequest, SignUpResponse, UserProfile};
+use hmac::{Hmac, KeyInit, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::Sha256;
+use shared::{
+    ApiToken, AuditAction, ChangeEmailRequest, ChangePasswordRequest, DeleteAccountRequest,
+    ErrorCode, PasswordResetConfirmRequest, PasswordResetRequest, Session, Sessions, SignInRequest,
+    SignInResponse, SignUpRequest, SignUpResponse, UserProfile, VerifyEmailRequest,
+};
 use tracing::{debug, error, info, warn};
+use uuid::Uuid;
 
-use super::Claim;
+use super::{audit, Claim};
 use crate::auth_rate_limit::AuthRateLimitKey;
-use crate::db::user;
+use crate::db::user::{self, TokenPurpose, User};
+use crate::db::{user_session, Database};
 use crate::error::{Error, Result};
+use crate::mailer::Mailer;
 use crate::AppContext;
 
 const MIN_PASSWORD_LENGTH: usize = 8;
+const EMAIL_VERIFICATION_TTL: Duration = Duration::hours(24);
+const PASSWORD_RESET_TTL: Duration = Duration::hours(1);
+pub(super) const ACCESS_TOKEN_TTL: Duration = Duration::weeks(2);
+/// Longer User-Agents are cut to label the session.
+const MAX_DEVICE_LABEL_CHARS: usize = 200;
+/// Pinboard's tokens are 20 hex digits.
+const API_TOKEN_BYTES: usize = 10;
+
+fn is_valid_email(email: &str) -> bool {
+    email.trim().parse::<lettre::Address>().is_ok()
+}
 
 fn validate_signup(payload: &SignUpRequest) -> Result<()> {
     let mut errors: Vec<(&'static str, &'static str)> = Vec::new();
@@ -34,6 +58,13 @@ fn validate_signup(payload: &SignUpRequest) -> Result<()> {
     {
         errors.push(("password", "password confirmation should match"));
     }
+    if payload
+        .email
+        .as_deref()
+        .is_some_and(|email| !is_valid_email(email))
+    {
+        errors.push(("email", "email must be a valid address"));
+    }
     if errors.is_empty() {
         Ok(())
     } else {
@@ -56,6 +87,54 @@ fn validate_signin(payload: &SignInRequest) -> Result<()> {
     }
 }
 
+fn validate_change_password(payload: &ChangePasswordRequest) -> Result<()> {
+    let mut errors: Vec<(&'static str, &'static str)> = Vec::new();
+    if payload.old_password.expose_secret().trim().is_empty() {
+        errors.push(("old_password", "old password must not be empty"));
+    }
+    if payload.new_password.expose_secret().trim().is_empty() {
+        errors.push(("new_password", "new password must not be empty"));
+    } else if payload.new_password.expose_secret().chars().count() < MIN_PASSWORD_LENGTH {
+        errors.push(("new_password", "new password must be at least 8 characters"));
+    }
+    if payload
+        .new_password
+        .expose_secret()
+        .ne(payload.new_password_confirmation.expose_secret())
+    {
+        errors.push(("new_password", "new password confirmation should match"));
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::unprocessable_entity(errors))
+    }
+}
+
+fn validate_password_reset(payload: &PasswordResetConfirmRequest) -> Result<()> {
+    let mut errors: Vec<(&'static str, &'static str)> = Vec::new();
+    if payload.token.expose_secret().trim().is_empty() {
+        errors.push(("token", "token must not be empty"));
+    }
+    if payload.new_password.expose_secret().trim().is_empty() {
+        errors.push(("new_password", "new password must not be empty"));
+    } else if payload.new_password.expose_secret().chars().count() < MIN_PASSWORD_LENGTH {
+        errors.push(("new_password", "new password must be at least 8 characters"));
+    }
+    if payload
+        .new_password
+        .expose_secret()
+        .ne(payload.new_password_confirmation.expose_secret())
+    {
+        errors.push(("new_password", "new password confirmation should match"));
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::unprocessable_entity(errors))
+    }
+}
+
 fn auth_rate_limit_key(
     action: &'static str,
     username: &str,
@@ -70,11 +149,101 @@ fn auth_rate_limit_key(
 
 pub fn router() -> Router {
     Router::new()
-        .route("/auth/sign-up", post(sign_up))
-        .route("/auth/sign-in", post(sign_in))
+        .route(
+            "/auth/sign-up",
+            post(sign_up).route_layer(from_fn_with_state("auth", super::rate_limit)),
+        )
+        .route(
+            "/auth/sign-in",
+            post(sign_in).route_layer(from_fn_with_state("auth", super::rate_limit)),
+        )
+        .route(
+            "/auth/password-reset",
+            post(request_password_reset).route_layer(from_fn_with_state("auth", super::rate_limit)),
+        )
+        .route(
+            "/auth/password-reset/confirm",
+            post(confirm_password_reset).route_layer(from_fn_with_state("auth", super::rate_limit)),
+        )
+        .route(
+            "/auth/verify-email",
+            post(verify_email).route_layer(from_fn_with_state("auth", super::rate_limit)),
+        )
         .route("/auth/user-profile", get(get_user_profile))
+        .route("/auth/change-password", post(change_password))
+        .route("/auth/email", post(change_email))
+        .route("/auth/api-token", get(get_api_token))
+        .route("/auth/sessions", get(list_sessions))
+        .route("/auth/sessions/{id}", delete(revoke_session))
+        .route("/users/me", delete(delete_account))
+}
+
+/// Starts a session of `user` on the device `user_agent` names and returns
+/// its access token.
+pub(super) async fn start_session(
+    app_context: &AppContext,
+    user: &User,
+    user_agent: Option<&str>,
+    ip: Option<IpAddr>,
+) -> Result<String> {
+    let expires_at = Utc::now()
+        .checked_add_signed(ACCESS_TOKEN_TTL)
+        .expect("Not overflow");
+    let device_label = user_agent
+        .map(|user_agent| {
+            user_agent
+                .trim()
+                .chars()
+                .take(MAX_DEVICE_LABEL_CHARS)
+                .collect::<String>()
+        })
+        .filter(|device_label| !device_label.is_empty());
+    let session_id = user_session::create(
+        &app_context.pool,
+        user.user_id,
+        device_label.as_deref(),
+        ip,
+        expires_at,
+    )
+    .await?;
+    let claims = Claim {
+        user_id: user.user_id,
+        sub: user.username.clone(),
+        exp: expires_at.timestamp(),
+        sid: Some(session_id),
+    };
+    super::encode_token(&app_context.config, &claims)
+}
+
+/// Derived from the password hash, so changing the password revokes it.
+fn api_token_mac(hmac_key: &SecretString, user: &User) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(hmac_key.expose_secret().as_bytes())
+        .expect("HMAC accepts keys of any size");
+    mac.update(format!("api-token:{}:{}", user.user_id, user.password_hash).as_bytes());
+    mac
+}
+
+/// The token of the Pinboard-compatible API for `user`, without a JWT.
+pub(super) fn api_token(hmac_key: &SecretString, user: &User) -> String {
+    let signature = api_token_mac(hmac_key, user).finalize().into_bytes();
+    hex::encode_upper(&signature[..API_TOKEN_BYTES])
+}
+
+pub(super) fn is_valid_api_token(hmac_key: &SecretString, user: &User, token: &str) -> bool {
+    match hex::decode(token) {
+        Ok(signature) if signature.len() == API_TOKEN_BYTES => api_token_mac(hmac_key, user)
+            .verify_truncated_left(&signature)
+            .is_ok(),
+        _ => false,
+    }
 }
 
+#[utoipa::path(
+    get,
+    path = "/auth/user-profile",
+    tag = "auth",
+    responses((status = 200, body = UserProfile))
+)]
 #[debug_handler]
 async fn get_user_profile(
     claims: Claim,
@@ -94,6 +263,8 @@ async fn get_user_profile(
             Ok(Json(UserProfile {
                 user_id: user.user_id,
                 username: user.username,
+                email_verified: user.email_verified_at.is_some(),
+                email: user.email,
                 created_at: user.created_at,
             }))
         }
@@ -116,6 +287,18 @@ async fn get_user_profile(
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/auth/sign-up",
+    tag = "auth",
+    security(()),
+    request_body = SignUpRequest,
+    responses(
+        (status = 200, body = SignUpResponse),
+        (status = 422, description = "Invalid username, password or email"),
+        (status = 429, description = "Too many attempts"),
+    )
+)]
 #[debug_handler]
 async fn sign_up(
     ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
@@ -134,7 +317,13 @@ async fn sign_up(
 
     let hashed_password = super::hash_password(payload.password).await?;
     let normalized_username = user::normalize_username(&payload.username);
-    let try_user = user::create(&app_context.pool, normalized_username, hashed_password).await;
+    let try_user = user::create(
+        &app_context.pool,
+        normalized_username,
+        payload.email,
+        hashed_password,
+    )
+    .await;
     match try_user {
         Ok(user) => {
             app_context.auth_rate_limiter.reset(&rate_limit_key);
@@ -143,6 +332,9 @@ async fn sign_up(
                 username = %user.username,
                 "User successfully created"
             );
+            if let Some(email) = &user.email {
+                send_email_verification(&app_context, &user.user_id, email).await;
+            }
             Ok(Json(SignUpResponse {
                 id: user.user_id,
                 username: user.username,
@@ -156,10 +348,21 @@ async fn sign_up(
                 username = %payload.username,
                 "Signup failed - username already exists"
             );
-            Err(Error::bad_request([(
-                "username",
-                "username already created",
-            )]))
+            Err(
+                Error::bad_request([("username", "username already created")])
+                    .with_code(ErrorCode::UsernameTaken),
+            )
+        }
+        Err(Error::ConstraintViolation {
+            constraint,
+            message: _,
+        }) if constraint.eq("unique_email") => {
+            warn!(
+                username = %payload.username,
+                "Signup failed - email already used"
+            );
+            Err(Error::bad_request([("email", "email already used")])
+                .with_code(ErrorCode::EmailTaken))
         }
         Err(error) => {
             error!(username = %payload.username, error = %error, "Signup failed");
@@ -168,9 +371,22 @@ async fn sign_up(
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/auth/sign-in",
+    tag = "auth",
+    security(()),
+    request_body = SignInRequest,
+    responses(
+        (status = 200, body = SignInResponse),
+        (status = 401, description = "Wrong username or password"),
+        (status = 429, description = "Too many attempts"),
+    )
+)]
 #[debug_handler()]
 async fn sign_in(
     ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    user_agent: Option<TypedHeader<UserAgent>>,
     Extension(app_context): Extension<AppContext>,
     Json(payload): Json<SignInRequest>,
 ) -> Result<Json<SignInResponse>> {
@@ -185,28 +401,39 @@ async fn sign_in(
     debug!(username = %payload.username, "Signin validation passed");
 
     let normalized_username = user::normalize_username(&payload.username);
-    let maybe_user = user::get_by_username(&app_context.pool, normalized_username).await?;
+    let maybe_user = user::get_by_username(&app_context.pool, normalized_username.clone()).await?;
     if let Some(user) = maybe_user {
         debug!(username = %user.username, "User found for signin");
-        super::verify_password(payload.password, user.password_hash.clone()).await?;
+        if let Err(error) =
+            super::verify_password(payload.password, user.password_hash.clone()).await
+        {
+            audit::record_failed_sign_in(
+                &app_context.pool,
+                Some(user.user_id),
+                &user.username,
+                None,
+                Some(client_addr.ip()),
+            )
+            .await;
+            return Err(error);
+        }
         debug!(
             username = %user.username,
             "Password verification successful"
         );
 
-        let expiration = Utc::now()
-            .checked_add_signed(Duration::weeks(2))
-            .expect("Not overflow")
-            .timestamp();
-        let claims = Claim {
-            user_id: user.user_id,
-            sub: user.username.clone(),
-            exp: expiration,
-        };
-        let token = super::encode_token(&app_context.config, &claims)?;
+        let token = start_session(
+            &app_context,
+            &user,
+            user_agent
+                .as_ref()
+                .map(|TypedHeader(user_agent)| user_agent.as_str()),
+            Some(client_addr.ip()),
+        )
+        .await?;
         info!(
             user_id = %user.user_id,
-            username = %claims.sub,
+            username = %user.username,
             "User successfully authenticated"
         );
 
@@ -217,6 +444,14 @@ async fn sign_in(
             token_type: "Bearer".to_owned(),
         };
         app_context.auth_rate_limiter.reset(&rate_limit_key);
+        audit::record(
+            &app_context.pool,
+            login_response.user_id,
+            AuditAction::SignIn,
+            None,
+            Some(client_addr.ip()),
+        )
+        .await;
         return Ok(Json(login_response));
     }
 
@@ -224,21 +459,444 @@ async fn sign_in(
         username = %payload.username,
         "Signin failed - user not found or wrong credentials"
     );
+    audit::record_failed_sign_in(
+        &app_context.pool,
+        None,
+        &normalized_username,
+        None,
+        Some(client_addr.ip()),
+    )
+    .await;
     Err(Error::WrongCredentials)
 }
 
+#[utoipa::path(
+    post,
+    path = "/auth/change-password",
+    tag = "auth",
+    request_body = ChangePasswordRequest,
+    responses(
+        (status = 204, description = "Password changed"),
+        (status = 401, description = "Wrong old password"),
+        (status = 422, description = "Invalid new password"),
+    )
+)]
+#[debug_handler]
+async fn change_password(
+    claims: Claim,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    Extension(app_context): Extension<AppContext>,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> Result<StatusCode> {
+    info!(user_id = %claims.user_id, "Password change attempt");
+    let rate_limit_key = auth_rate_limit_key("change-password", &claims.sub, client_addr);
+    let decision = app_context.auth_rate_limiter.check(rate_limit_key.clone());
+    if !decision.allowed {
+        return Err(Error::too_many_requests(decision.retry_after_secs));
+    }
+
+    validate_change_password(&payload)?;
+
+    let user = user::get_by_id(&app_context.pool, &claims.user_id)
+        .await?
+        .ok_or(Error::Unauthorized)?;
+    super::verify_password(payload.old_password, user.password_hash).await?;
+    debug!(user_id = %claims.user_id, "Old password verification successful");
+
+    let hashed_password = super::hash_password(payload.new_password).await?;
+    if !user::update_password(&app_context.pool, &claims.user_id, hashed_password).await? {
+        return Err(Error::Unauthorized);
+    }
+    // Sign out the other sessions. The session that changed the password stays
+    // valid.
+    user_session::revoke_others(&app_context.pool, claims.user_id, claims.sid).await?;
+    app_context.auth_rate_limiter.reset(&rate_limit_key);
+    audit::record(
+        &app_context.pool,
+        claims.user_id,
+        AuditAction::PasswordChanged,
+        None,
+        Some(client_addr.ip()),
+    )
+    .await;
+    info!(user_id = %claims.user_id, "Password changed successfully");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Emails a verification code for `email` when SMTP is configured. Failures are
+/// logged only, the caller can ask for a new code by setting the email again.
+async fn send_email_verification(app_context: &AppContext, user_id: &uuid::Uuid, email: &str) {
+    let Some(mailer) = &app_context.mailer else {
+        debug!(user_id = %user_id, "SMTP not configured, skipping email verification");
+        return;
+    };
+    let result = async {
+        let token = user::create_token(
+            &app_context.pool,
+            user_id,
+            TokenPurpose::VerifyEmail,
+            EMAIL_VERIFICATION_TTL,
+        )
+        .await?;
+        mailer.send_email_verification(email, &token).await?;
+        anyhow::Ok(())
+    }
+    .await;
+    if let Err(error) = result {
+        error!(user_id = %user_id, ?error, "Failed to send email verification");
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/email",
+    tag = "auth",
+    request_body = ChangeEmailRequest,
+    responses(
+        (status = 204, description = "Email changed, a verification email is sent"),
+        (status = 422, description = "Invalid email"),
+    )
+)]
+#[debug_handler]
+async fn change_email(
+    claims: Claim,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    Extension(app_context): Extension<AppContext>,
+    Json(payload): Json<ChangeEmailRequest>,
+) -> Result<StatusCode> {
+    if !is_valid_email(&payload.email) {
+        return Err(Error::unprocessable_entity([(
+            "email",
+            "email must be a valid address",
+        )]));
+    }
+    let updated = user::update_email(&app_context.pool, &claims.user_id, &payload.email)
+        .await
+        .map_err(|error| match error {
+            Error::ConstraintViolation { constraint, .. } if constraint == "unique_email" => {
+                Error::bad_request([("email", "email already used")])
+                    .with_code(ErrorCode::EmailTaken)
+            }
+            error => error,
+        })?;
+    if !updated {
+        return Err(Error::Unauthorized);
+    }
+    audit::record(
+        &app_context.pool,
+        claims.user_id,
+        AuditAction::EmailChanged,
+        None,
+        Some(client_addr.ip()),
+    )
+    .await;
+    info!(user_id = %claims.user_id, "User email changed");
+    send_email_verification(
+        &app_context,
+        &claims.user_id,
+        &user::normalize_email(&payload.email),
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/verify-email",
+    tag = "auth",
+    security(()),
+    request_body = VerifyEmailRequest,
+    responses(
+        (status = 204, description = "Email verified"),
+        (status = 400, description = "Invalid or expired token"),
+    )
+)]
+#[debug_handler]
+async fn verify_email(
+    Extension(app_context): Extension<AppContext>,
+    Json(payload): Json<VerifyEmailRequest>,
+) -> Result<StatusCode> {
+    let user_id = user::consume_token(
+        &app_context.pool,
+        payload.token.expose_secret().trim(),
+        TokenPurpose::VerifyEmail,
+    )
+    .await?
+    .ok_or(Error::InvalidToken)?;
+    if !user::mark_email_verified(&app_context.pool, &user_id).await? {
+        return Err(Error::InvalidToken);
+    }
+    info!(user_id = %user_id, "User email verified");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Always accepted when SMTP is configured so the response doesn't reveal
+/// which usernames exist or have a verified email. The lookup and the email
+/// run after the response, so both cases answer in the same time.
+#[utoipa::path(
+    post,
+    path = "/auth/password-reset",
+    tag = "auth",
+    security(()),
+    request_body = PasswordResetRequest,
+    responses((status = 202, description = "A reset email is sent if the account has a verified email"))
+)]
+#[debug_handler]
+async fn request_password_reset(
+    Extension(app_context): Extension<AppContext>,
+    Json(payload): Json<PasswordResetRequest>,
+) -> Result<StatusCode> {
+    let Some(mailer) = app_context.mailer.clone() else {
+        return Err(Error::bad_request([(
+            "email",
+            "email delivery is not configured",
+        )]));
+    };
+    let pool = app_context.pool.clone();
+    tokio::spawn(async move {
+        if let Err(error) = send_password_reset(&pool, &mailer, &payload.username).await {
+            error!(?error, "Failed to process password reset request");
+        }
+    });
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn send_password_reset(pool: &Database, mailer: &Mailer, username: &str) -> Result<()> {
+    let normalized_username = user::normalize_username(username);
+    let maybe_user = user::get_by_username(pool, normalized_username).await?;
+    let Some(user) = maybe_user.filter(|user| user.email_verified_at.is_some()) else {
+        debug!(username = %username, "No verified email for password reset");
+        return Ok(());
+    };
+    let Some(email) = &user.email else {
+        return Ok(());
+    };
+    let token = user::create_token(
+        pool,
+        &user.user_id,
+        TokenPurpose::PasswordReset,
+        PASSWORD_RESET_TTL,
+    )
+    .await?;
+    if let Err(error) = mailer.send_password_reset(email, &token).await {
+        error!(user_id = %user.user_id, ?error, "Failed to send password reset email");
+    } else {
+        info!(user_id = %user.user_id, "Password reset email sent");
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/password-reset/confirm",
+    tag = "auth",
+    security(()),
+    request_body = PasswordResetConfirmRequest,
+    responses(
+        (status = 204, description = "Password changed"),
+        (status = 400, description = "Invalid or expired token"),
+        (status = 422, description = "Invalid new password"),
+    )
+)]
+#[debug_handler]
+async fn confirm_password_reset(
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    Extension(app_context): Extension<AppContext>,
+    Json(payload): Json<PasswordResetConfirmRequest>,
+) -> Result<StatusCode> {
+    validate_password_reset(&payload)?;
+    let user_id = user::consume_token(
+        &app_context.pool,
+        payload.token.expose_secret().trim(),
+        TokenPurpose::PasswordReset,
+    )
+    .await?
+    .ok_or(Error::InvalidToken)?;
+    let hashed_password = super::hash_password(payload.new_password).await?;
+    if !user::update_password(&app_context.pool, &user_id, hashed_password).await? {
+        return Err(Error::InvalidToken);
+    }
+    user_session::revoke_others(&app_context.pool, user_id, None).await?;
+    audit::record(
+        &app_context.pool,
+        user_id,
+        AuditAction::PasswordReset,
+        None,
+        Some(client_addr.ip()),
+    )
+    .await;
+    info!(user_id = %user_id, "Password reset completed");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Deletion is irreversible, so the current password is required in addition
+/// to the session token.
+async fn confirm_account_deletion(user: User, payload: DeleteAccountRequest) -> Result<()> {
+    super::verify_password(payload.password, user.password_hash).await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/me",
+    tag = "auth",
+    request_body = DeleteAccountRequest,
+    responses(
+        (status = 204, description = "Account and all its data deleted"),
+        (status = 401, description = "Wrong password"),
+        (status = 429)
+    )
+)]
+#[debug_handler]
+async fn delete_account(
+    claims: Claim,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    Extension(app_context): Extension<AppContext>,
+    Json(payload): Json<DeleteAccountRequest>,
+) -> Result<StatusCode> {
+    let rate_limit_key = auth_rate_limit_key("delete-account", &claims.sub, client_addr);
+    let decision = app_context.auth_rate_limiter.check(rate_limit_key);
+    if !decision.allowed {
+        return Err(Error::too_many_requests(decision.retry_after_secs));
+    }
+    let user = user::get_by_id(&app_context.pool, &claims.user_id)
+        .await?
+        .ok_or(Error::Unauthorized)?;
+    confirm_account_deletion(user, payload).await?;
+
+    info!(user_id = %claims.user_id, username = %claims.sub, "Deleting user account");
+    // Record the deletion before the user row is gone, so the entry keeps the
+    // username.
+    audit::record(
+        &app_context.pool,
+        claims.user_id,
+        AuditAction::AccountDeleted,
+        None,
+        Some(client_addr.ip()),
+    )
+    .await;
+    let deleted = user::delete(&app_context.pool, &claims.user_id).await?;
+    if !deleted {
+        return Err(Error::NotFound);
+    }
+    let user_prefix = format!("{}/", claims.user_id);
+    if let Err(err) = app_context.storage.delete_prefix(&user_prefix).await {
+        error!(
+            user_id = %claims.user_id,
+            prefix = %user_prefix,
+            error = ?err,
+            "Failed to remove static files for deleted user"
+        );
+    }
+    info!(user_id = %claims.user_id, "User account deleted");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/api-token",
+    tag = "auth",
+    responses((status = 200, body = ApiToken))
+)]
+#[debug_handler]
+async fn get_api_token(
+    claims: Claim,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    Extension(app_context): Extension<AppContext>,
+) -> Result<Json<ApiToken>> {
+    let user = user::get_by_id(&app_context.pool, &claims.user_id)
+        .await?
+        .ok_or(Error::Unauthorized)?;
+    let token = api_token(&app_context.config.hmac_key, &user);
+    audit::record(
+        &app_context.pool,
+        claims.user_id,
+        AuditAction::ApiTokenIssued,
+        None,
+        Some(client_addr.ip()),
+    )
+    .await;
+    Ok(Json(ApiToken {
+        auth_token: format!("{}:{token}", user.username),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/sessions",
+    tag = "auth",
+    responses((status = 200, body = Sessions))
+)]
+#[debug_handler]
+async fn list_sessions(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+) -> Result<Json<Sessions>> {
+    let sessions = user_session::list(&app_context.pool, claims.user_id)
+        .await?
+        .into_iter()
+        .map(|session| Session {
+            current: claims.sid == Some(session.session_id),
+            session_id: session.session_id,
+            device_label: session.device_label,
+            ip: session.ip,
+            created_at: session.created_at,
+            last_seen_at: session.last_seen_at,
+            expires_at: session.expires_at,
+        })
+        .collect();
+    Ok(Json(Sessions { sessions }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/auth/sessions/{id}",
+    tag = "auth",
+    params(("id" = Uuid, Path)),
+    responses((status = 204, description = "Session revoked"), (status = 404))
+)]
+#[debug_handler]
+async fn revoke_session(
+    claims: Claim,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    if !user_session::revoke(&app_context.pool, claims.user_id, id).await? {
+        return Err(Error::NotFound);
+    }
+    audit::record(
+        &app_context.pool,
+        claims.user_id,
+        AuditAction::SessionRevoked,
+        Some(&id.to_string()),
+        Some(client_addr.ip()),
+    )
+    .await;
+    info!(user_id = %claims.user_id, session_id = %id, "Session revoked");
+    Ok(StatusCode::NO_CONTENT)
+}
+
 #[cfg(test)]
 mod tests {
     use secrecy::SecretString;
-    use shared::{SignInRequest, SignUpRequest};
+    use shared::{
+        ChangePasswordRequest, DeleteAccountRequest, PasswordResetConfirmRequest, SignInRequest,
+        SignUpRequest,
+    };
 
-    use super::{validate_signin, validate_signup};
-    use crate::db::user;
+    use super::{
+        api_token, confirm_account_deletion, is_valid_api_token, validate_change_password,
+        validate_password_reset, validate_signin, validate_signup,
+    };
+    use crate::db::user::{self, User};
+    use crate::endpoints::hash_password;
+    use crate::error::Error;
 
     #[test]
     fn signup_requires_minimum_password_length() {
         let result = validate_signup(&SignUpRequest {
             username: "alice".into(),
+            email: None,
             password: SecretString::from("short"),
             password_confirmation: SecretString::from("short"),
         });
@@ -246,6 +904,35 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn signup_rejects_invalid_email() {
+        let result = validate_signup(&SignUpRequest {
+            username: "alice".into(),
+            email: Some("alice-at-example".into()),
+            password: SecretString::from("password123"),
+            password_confirmation: SecretString::from("password123"),
+        });
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn password_reset_requires_token_and_matching_confirmation() {
+        let result = validate_password_reset(&PasswordResetConfirmRequest {
+            token: SecretString::from(" "),
+            new_password: SecretString::from("new-password"),
+            new_password_confirmation: SecretString::from("new-password"),
+        });
+        assert!(result.is_err());
+
+        let result = validate_password_reset(&PasswordResetConfirmRequest {
+            token: SecretString::from("abc"),
+            new_password: SecretString::from("new-password"),
+            new_password_confirmation: SecretString::from("other-password"),
+        });
+        assert!(result.is_err());
+    }
+
     #[test]
     fn signup_normalizes_username_before_persistence() {
         assert_eq!(user::normalize_username(" Alice "), "alice");
@@ -261,4 +948,72 @@ mod tests {
 
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn change_password_requires_matching_confirmation() {
+        let result = validate_change_password(&ChangePasswordRequest {
+            old_password: SecretString::from("password123"),
+            new_password: SecretString::from("new-password"),
+            new_password_confirmation: SecretString::from("other-password"),
+        });
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn change_password_requires_minimum_length() {
+        let result = validate_change_password(&ChangePasswordRequest {
+            old_password: SecretString::from("password123"),
+            new_password: SecretString::from("short"),
+            new_password_confirmation: SecretString::from("short"),
+        });
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn account_deletion_requires_the_current_password() {
+        let user = User {
+            user_id: uuid::Uuid::new_v4(),
+            username: "alice".into(),
+            password_hash: hash_password(SecretString::from("password123"))
+                .await
+                .unwrap(),
+            email: None,
+            email_verified_at: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        };
+        let request = |password: &str| DeleteAccountRequest {
+            password: SecretString::from(password),
+        };
+
+        let result = confirm_account_deletion(user.clone(), request("wrong-password")).await;
+        assert!(matches!(result, Err(Error::WrongCredentials)));
+        assert!(confirm_account_deletion(user, request("password123"))
+            .await
+            .is_ok());
+    }
+
+    #[test]
+    fn api_tokens_are_revoked_by_a_password_change() {
+        let hmac_key = SecretString::from("secret");
+        let mut user = User {
+            user_id: uuid::Uuid::new_v4(),
+            username: "alice".into(),
+            password_hash: "hash".into(),
+            email: None,
+            email_verified_at: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        };
+        let token = api_token(&hmac_key, &user);
+        assert_eq!(token.len(), 20);
+        assert!(is_valid_api_token(&hmac_key, &user, &token));
+        assert!(is_valid_api_token(&hmac_key, &user, &token.to_lowercase()));
+        assert!(!is_valid_api_token(&hmac_key, &user, &token[..10]));
+
+        user.password_hash = "new-hash".into();
+        assert!(!is_valid_api_token(&hmac_key, &user, &token));
+    }
 }
@@ -1,33 +1,237 @@
-use axum::extract::Path;
-use axum::http::StatusCode;
-use axum::routing::{get, post};
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
+
+use axum::extract::{ConnectInfo, Path, Query};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::from_fn_with_state;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
 use axum::{Extension, Json, Router};
 use axum_macros::debug_handler;
+use chrono::{Duration, Utc};
 use shared::{
-    Bookmark, BookmarkTask, Bookmarks, NewBookmark, TagCount, TagOperation, Tags, TagsWithCounters,
+    AskBookmarkRequest, AuditAction, BatchBookmarkRequest, BatchBookmarkResponse,
+    BatchBookmarkResult, BatchBookmarkStatus, Bookmark, BookmarkLinks, BookmarkShareLink,
+    BookmarkTask, Bookmarks, BookmarksRequest, BulkBookmarkAction, BulkBookmarkRequest,
+    BulkBookmarkResponse, NewBookmark, RagQueryResponse, RandomBookmarkRequest,
+    RegenerateSummaryRequest, RelatedBookmarks, RelatedBookmarksRequest, RenameTagRequest,
+    RenameTagResponse, ReprocessRequest, ReprocessResponse, ReprocessStage, SearchRequest,
+    ServerEvent, SummaryRequest, TagCount, TagOperation, TagSuggestions, TagSuggestionsRequest,
+    Tags, TagsWithCounters, UpdateBookmarkRequest, Visibility, VisibilityRequest,
 };
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
+use url::Url;
+use uuid::Uuid;
 
-use super::Claim;
-use crate::db::{bookmark, bookmark_task};
+use super::rag::require_llm_client;
+use super::static_content::{self, PAGE_NAME};
+use super::{audit, bounded_limit, Claim};
+use crate::bookmark_identity::canonicalize_url;
+use crate::db::{ai, bookmark, bookmark_task, chunks, link, search, storage, user_settings};
 use crate::endpoints::Error;
 use crate::error::Result;
-use crate::AppContext;
+use crate::rag::RagEngine;
+use crate::url_guard::UrlGuard;
+use crate::{markdown, static_storage, tag_suggestions, AppContext};
 
 pub fn routes() -> Router {
     Router::new()
         .route("/tags", get(get_all_tags))
         .route("/tags/{tag}", get(get_bookmarks_by_tag))
-        .route("/bookmarks", get(get_bookmarks).post(new_bookmark))
-        .route("/bookmarks/{id}", get(get_bookmark).delete(delete_bookmark))
+        .route("/tags/{tag}/rename", post(rename_tag))
+        .route(
+            "/bookmarks",
+            get(get_bookmarks).merge(
+                post(new_bookmark).route_layer(from_fn_with_state("bookmarks", super::rate_limit)),
+            ),
+        )
+        .route("/bookmarks/batch", post(new_bookmarks))
+        .route("/bookmarks/bulk", post(bulk_update_bookmarks))
+        .route("/bookmarks/random", get(get_random_bookmark))
+        .route("/bookmarks/on-this-day", get(get_on_this_day))
+        .route(
+            "/bookmarks/{id}",
+            get(get_bookmark)
+                .patch(update_bookmark)
+                .delete(delete_bookmark),
+        )
         .route("/bookmarks/{id}/tags", post(set_tags).patch(append_tags))
+        .route(
+            "/bookmarks/{id}/favorite",
+            put(set_favorite).delete(unset_favorite),
+        )
+        .route("/bookmarks/{id}/visibility", put(set_visibility))
+        .route("/bookmarks/{id}/summary", put(set_summary))
+        .route("/bookmarks/{id}/read", post(mark_read))
+        .route(
+            "/bookmarks/{id}/summary/regenerate",
+            post(regenerate_summary),
+        )
+        .route("/bookmarks/{id}/related", get(get_related_bookmarks))
+        .route("/bookmarks/{id}/ask", post(ask_bookmark))
+        .route("/bookmarks/{id}/tag-suggestions", get(get_tag_suggestions))
+        .route("/bookmarks/{id}/links", get(get_bookmark_links))
+        .route("/bookmarks/{id}/refresh", post(refresh_bookmark))
+        .route("/bookmarks/{id}/reprocess", post(reprocess_bookmark))
+        .route("/bookmarks/{id}/share", post(share_bookmark))
+        .route("/bookmarks/{id}/markdown", get(get_bookmark_markdown))
+}
+
+pub(super) const SHARE_LINK_TTL: Duration = Duration::days(7);
+
+const MAX_TITLE_CHARS: usize = 500;
+const MAX_SUMMARY_CHARS: usize = 5000;
+
+const DEFAULT_RELATED_LIMIT: u32 = 5;
+const MAX_RELATED_LIMIT: u32 = 50;
+
+const DEFAULT_TAG_SUGGESTION_LIMIT: u32 = 10;
+const MAX_TAG_SUGGESTION_LIMIT: u32 = 50;
+/// Similar bookmarks whose tags are considered for suggestions.
+const TAG_SUGGESTION_RELATED_BOOKMARKS: usize = 10;
+
+/// Most URLs a batch request saves.
+const MAX_BATCH_URLS: usize = 500;
+
+/// Most bookmarks a bulk request acts on, given by id or matching a search.
+const MAX_BULK_BOOKMARKS: usize = 5000;
+/// Matches of a bulk request's search fetched at a time.
+const BULK_SEARCH_PAGE_SIZE: usize = 500;
+
+/// Trims the title, which can't be blank, and keeps to web URLs.
+fn validate_update_request(request: UpdateBookmarkRequest) -> Result<UpdateBookmarkRequest> {
+    let title = request.title.map(|title| title.trim().to_string());
+    if title
+        .as_ref()
+        .is_some_and(|title| title.is_empty() || title.chars().count() > MAX_TITLE_CHARS)
+    {
+        return Err(Error::unprocessable_entity([(
+            "title",
+            "must have between 1 and 500 characters",
+        )]));
+    }
+    if request
+        .url
+        .as_ref()
+        .is_some_and(|url| !matches!(url.scheme(), "http" | "https") || url.host().is_none())
+    {
+        return Err(Error::unprocessable_entity([(
+            "url",
+            "must be an http or https URL",
+        )]));
+    }
+    if title.is_none() && request.url.is_none() {
+        return Err(Error::unprocessable_entity([(
+            "title",
+            "must be set when the URL isn't",
+        )]));
+    }
+    Ok(UpdateBookmarkRequest {
+        title,
+        url: request.url,
+    })
+}
+
+/// Stages in the order asked for, each once.
+fn validate_reprocess_request(request: ReprocessRequest) -> Result<Vec<ReprocessStage>> {
+    let mut stages = Vec::new();
+    for stage in request.stages {
+        if !stages.contains(&stage) {
+            stages.push(stage);
+        }
+    }
+    if stages.is_empty() {
+        return Err(Error::unprocessable_entity([(
+            "stages",
+            "must have at least one of tags, summary, chunks or content",
+        )]));
+    }
+    Ok(stages)
+}
+
+/// Either ids or a search, at most `MAX_BULK_BOOKMARKS` ids each once, and
+/// tags to add trimmed and lowercased.
+fn validate_bulk_request(request: BulkBookmarkRequest) -> Result<BulkBookmarkRequest> {
+    let mut bookmark_ids: Vec<String> = Vec::new();
+    for bookmark_id in request.bookmark_ids {
+        if !bookmark_ids.contains(&bookmark_id) {
+            bookmark_ids.push(bookmark_id);
+        }
+    }
+    match (&request.search, bookmark_ids.len()) {
+        (Some(_), 0) => {}
+        (Some(_), _) => {
+            return Err(Error::unprocessable_entity([(
+                "bookmark_ids",
+                "must be empty when a search is given",
+            )]))
+        }
+        (None, 0) => {
+            return Err(Error::unprocessable_entity([(
+                "bookmark_ids",
+                "must have at least one bookmark when no search is given",
+            )]))
+        }
+        (None, count) if count > MAX_BULK_BOOKMARKS => {
+            return Err(Error::unprocessable_entity([(
+                "bookmark_ids",
+                "must have at most 5000 bookmarks",
+            )]))
+        }
+        (None, _) => {}
+    }
+    let action = match request.action {
+        BulkBookmarkAction::AddTags { tags } => {
+            let mut normalized: Vec<String> = Vec::new();
+            for tag in tags.iter().map(|tag| tag.trim().to_lowercase()) {
+                if !tag.is_empty() && !normalized.contains(&tag) {
+                    normalized.push(tag);
+                }
+            }
+            if normalized.is_empty() {
+                return Err(Error::unprocessable_entity([(
+                    "action.tags",
+                    "must have at least one tag",
+                )]));
+            }
+            BulkBookmarkAction::AddTags { tags: normalized }
+        }
+        action => action,
+    };
+    Ok(BulkBookmarkRequest {
+        bookmark_ids,
+        search: request.search,
+        action,
+    })
 }
 
+#[utoipa::path(
+    get,
+    path = "/bookmarks",
+    tag = "bookmarks",
+    params(BookmarksRequest),
+    responses((status = 200, body = Bookmarks), (status = 422))
+)]
 #[debug_handler]
 async fn get_bookmarks(
     claims: Claim,
     Extension(app_context): Extension<AppContext>,
+    Query(request): Query<BookmarksRequest>,
 ) -> Result<Json<Bookmarks>> {
+    if let Some(url) = request.url {
+        if Url::parse(&url).is_err() {
+            return Err(Error::unprocessable_entity([(
+                "url",
+                "must be a valid URL",
+            )]));
+        }
+        let found =
+            bookmark::get_by_canonical_url_and_user_id(&app_context.pool, &url, claims.user_id)
+                .await?;
+        return Ok(Json(Bookmarks {
+            bookmarks: found.into_iter().collect(),
+        }));
+    }
     debug!(user_id = %claims.user_id, "Fetching all bookmarks");
     let bookmarks = bookmark::get_by_user(&app_context.pool, claims.user_id).await?;
     info!(
@@ -38,6 +242,12 @@ async fn get_bookmarks(
     Ok(Json(Bookmarks { bookmarks }))
 }
 
+#[utoipa::path(
+    get,
+    path = "/tags",
+    tag = "bookmarks",
+    responses((status = 200, body = TagsWithCounters))
+)]
 #[debug_handler]
 async fn get_all_tags(
     claims: Claim,
@@ -57,6 +267,13 @@ async fn get_all_tags(
     Ok(Json(TagsWithCounters { tags }))
 }
 
+#[utoipa::path(
+    get,
+    path = "/tags/{tag}",
+    tag = "bookmarks",
+    params(("tag" = String, Path)),
+    responses((status = 200, body = Bookmarks))
+)]
 #[debug_handler]
 async fn get_bookmarks_by_tag(
     claims: Claim,
@@ -79,6 +296,95 @@ async fn get_bookmarks_by_tag(
     Ok(Json(Bookmarks { bookmarks }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/tags/{tag}/rename",
+    tag = "bookmarks",
+    params(("tag" = String, Path)),
+    request_body = RenameTagRequest,
+    responses((status = 200, body = RenameTagResponse), (status = 422))
+)]
+#[debug_handler]
+async fn rename_tag(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(tag): Path<String>,
+    Json(request): Json<RenameTagRequest>,
+) -> Result<Json<RenameTagResponse>> {
+    let from = tag.trim().to_lowercase();
+    let to = request.new_name.trim().to_lowercase();
+    if to.is_empty() {
+        return Err(Error::unprocessable_entity([(
+            "new_name",
+            "must not be empty",
+        )]));
+    }
+    info!(user_id = %claims.user_id, from = %from, to = %to, "Renaming tag");
+    let bookmark_count = if from == to {
+        0
+    } else {
+        bookmark::rename_tag(&app_context.pool, claims.user_id, &from, &to).await?
+    };
+    Ok(Json(RenameTagResponse {
+        tag: to,
+        bookmark_count,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/bookmarks/random",
+    tag = "bookmarks",
+    params(RandomBookmarkRequest),
+    responses((status = 200, body = Bookmark), (status = 404, description = "No bookmark to pick"))
+)]
+#[debug_handler]
+async fn get_random_bookmark(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Query(request): Query<RandomBookmarkRequest>,
+) -> Result<Json<Bookmark>> {
+    let tag = request.tag.map(|tag| tag.to_lowercase());
+    let picked = bookmark::get_random(
+        &app_context.pool,
+        claims.user_id,
+        tag.as_deref(),
+        request.unread,
+    )
+    .await?
+    .ok_or(Error::NotFound)?;
+    info!(
+        user_id = %claims.user_id,
+        bookmark_id = %picked.bookmark_id,
+        "Picked a random bookmark"
+    );
+    Ok(Json(picked))
+}
+
+#[utoipa::path(
+    get,
+    path = "/bookmarks/on-this-day",
+    tag = "bookmarks",
+    responses((status = 200, body = Bookmarks))
+)]
+#[debug_handler]
+async fn get_on_this_day(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+) -> Result<Json<Bookmarks>> {
+    let bookmarks =
+        bookmark::get_on_this_day(&app_context.pool, claims.user_id, Utc::now().date_naive())
+            .await?;
+    Ok(Json(Bookmarks { bookmarks }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/bookmarks/{id}",
+    tag = "bookmarks",
+    params(("id" = String, Path)),
+    responses((status = 200, body = Bookmark), (status = 404))
+)]
 #[debug_handler]
 async fn get_bookmark(
     claims: Claim,
@@ -108,35 +414,113 @@ async fn get_bookmark(
     }
 }
 
+/// Corrects the title and/or the URL of a bookmark. The extracted title is
+/// kept as `original_title` the first time the title changes.
+#[utoipa::path(
+    patch,
+    path = "/bookmarks/{id}",
+    tag = "bookmarks",
+    params(("id" = String, Path)),
+    request_body = UpdateBookmarkRequest,
+    responses(
+        (status = 200, body = Bookmark),
+        (status = 400, description = "Another bookmark has the URL"),
+        (status = 404),
+        (status = 422),
+    )
+)]
+#[debug_handler]
+async fn update_bookmark(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<String>,
+    Json(request): Json<UpdateBookmarkRequest>,
+) -> Result<Json<Bookmark>> {
+    let request = validate_update_request(request)?;
+    bookmark::edit(
+        &app_context.pool,
+        claims.user_id,
+        &id,
+        request.title.as_deref(),
+        request.url.as_ref(),
+    )
+    .await?
+    .map(Json)
+    .ok_or(Error::NotFound)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/bookmarks/{id}",
+    tag = "bookmarks",
+    params(("id" = String, Path)),
+    responses((status = 204, description = "Bookmark moved to the trash"), (status = 404))
+)]
 #[debug_handler]
 async fn delete_bookmark(
     claims: Claim,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
     Extension(app_context): Extension<AppContext>,
     Path(id): Path<String>,
 ) -> Result<StatusCode> {
     info!(bookmark_id = %id, user_id = %claims.user_id, "Deleting bookmark");
-    let deleted = bookmark::delete(&app_context.pool, claims.user_id, &id).await?;
-    if !deleted {
+    remove(&app_context, claims.user_id, &id, Some(client_addr.ip())).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Called by the reader when the bookmark is opened.
+#[utoipa::path(
+    post,
+    path = "/bookmarks/{id}/read",
+    tag = "bookmarks",
+    params(("id" = String, Path)),
+    responses((status = 204, description = "Bookmark marked read"), (status = 404))
+)]
+#[debug_handler]
+async fn mark_read(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    if !bookmark::mark_read(&app_context.pool, claims.user_id, &id).await? {
         return Err(Error::NotFound);
     }
-    let static_dir = app_context
-        .config
-        .data_dir
-        .join(claims.user_id.to_string())
-        .join(&id);
-    if static_dir.exists() {
-        if let Err(err) = tokio::fs::remove_dir_all(&static_dir).await {
-            error!(
-                bookmark_id = %id,
-                path = ?static_dir,
-                error = %err,
-                "Failed to remove static files for deleted bookmark"
-            );
-        }
-    }
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Moves a bookmark of `user_id` to the trash. Its static files stay until
+/// it is purged.
+pub(super) async fn remove(
+    app_context: &AppContext,
+    user_id: Uuid,
+    id: &str,
+    ip: Option<IpAddr>,
+) -> Result<()> {
+    if !bookmark::move_to_trash(&app_context.pool, user_id, id).await? {
+        return Err(Error::NotFound);
+    }
+    audit::record(
+        &app_context.pool,
+        user_id,
+        AuditAction::BookmarkDeleted,
+        Some(id),
+        ip,
+    )
+    .await;
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/bookmarks",
+    tag = "bookmarks",
+    request_body = NewBookmark,
+    responses(
+        (status = 201, body = BookmarkTask, description = "Task queued to capture the page"),
+        (status = 403, description = "Storage quota reached"),
+        (status = 429, description = "Too many bookmarks added"),
+    )
+)]
 #[debug_handler]
 async fn new_bookmark(
     claims: Claim,
@@ -154,23 +538,354 @@ async fn new_bookmark(
     tags.retain(|t| !t.trim().is_empty());
     debug!(tags = ?tags, "Filtered tags");
 
-    let response =
-        bookmark_task::create(&app_context.pool, claims.user_id, input.url.clone(), tags).await?;
+    let response = enqueue(
+        &app_context,
+        claims.user_id,
+        input.url.clone(),
+        tags,
+        input.ignore_robots,
+    )
+    .await?;
+    info!(
+        task_id = %response.task_id,
+        url = %input.url,
+        "Bookmark task created"
+    );
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// What becomes of each URL of a batch before looking at the saved
+/// bookmarks: a canonical URL to save, or already a result.
+fn classify_batch(
+    urls: &[String],
+    guard: &UrlGuard,
+) -> Vec<std::result::Result<(Url, String), BatchBookmarkResult>> {
+    let mut seen = HashSet::new();
+    urls.iter()
+        .map(|raw| {
+            let result = |status, reason: Option<String>| BatchBookmarkResult {
+                url: raw.clone(),
+                status,
+                task_id: None,
+                reason,
+            };
+            let invalid = |reason: String| result(BatchBookmarkStatus::Invalid, Some(reason));
+            let url = Url::parse(raw.trim()).map_err(|_| invalid("Not a valid URL".into()))?;
+            guard
+                .check(&url)
+                .map_err(|blocked| invalid(blocked.to_string()))?;
+            let canonical_url = canonicalize_url(url.clone())
+                .map_err(|_| invalid("Not a valid URL".into()))?
+                .to_string();
+            if !seen.insert(canonical_url.clone()) {
+                return Err(result(BatchBookmarkStatus::Duplicate, None));
+            }
+            Ok((url, canonical_url))
+        })
+        .collect()
+}
+
+#[utoipa::path(
+    post,
+    path = "/bookmarks/batch",
+    tag = "bookmarks",
+    request_body = BatchBookmarkRequest,
+    responses(
+        (status = 200, body = BatchBookmarkResponse, description = "Result of each URL, in order"),
+        (status = 403, description = "Storage quota reached"),
+        (status = 422),
+        (status = 429, description = "Too many bookmarks added"),
+    )
+)]
+#[debug_handler]
+async fn new_bookmarks(
+    claims: Claim,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    Extension(app_context): Extension<AppContext>,
+    Json(input): Json<BatchBookmarkRequest>,
+) -> Result<Json<BatchBookmarkResponse>> {
+    if input.urls.is_empty() || input.urls.len() > MAX_BATCH_URLS {
+        return Err(Error::unprocessable_entity([(
+            "urls",
+            "must have between 1 and 500 URLs",
+        )]));
+    }
+    let mut tags = input.tags.unwrap_or_default();
+    tags.retain(|t| !t.trim().is_empty());
+
+    let guard = UrlGuard::new(&app_context.config.fetch.fetch_allowed_networks);
+    let mut classified = classify_batch(&input.urls, &guard);
+    let canonical_urls = classified
+        .iter()
+        .filter_map(|candidate| candidate.as_ref().ok())
+        .map(|(_, canonical_url)| canonical_url.clone())
+        .collect::<Vec<_>>();
+    let existing =
+        bookmark::existing_canonical_urls(&app_context.pool, claims.user_id, &canonical_urls)
+            .await?;
+    for (candidate, raw) in classified.iter_mut().zip(&input.urls) {
+        if matches!(candidate, Ok((_, canonical_url)) if existing.contains(canonical_url)) {
+            *candidate = Err(BatchBookmarkResult {
+                url: raw.clone(),
+                status: BatchBookmarkStatus::Duplicate,
+                task_id: None,
+                reason: None,
+            });
+        }
+    }
+
+    // Each accepted URL costs what adding it alone would, a batch with none
+    // costs one request
+    let wanted = classified
+        .iter()
+        .filter(|candidate| candidate.is_ok())
+        .count();
+    // Before spending tokens on URLs the quota won't let be queued
+    if wanted > 0 {
+        storage::ensure_within_quota(
+            &app_context.pool,
+            claims.user_id,
+            app_context.config.storage_quota_bytes,
+        )
+        .await?;
+    }
+    let grant = app_context.rate_limiter.take(
+        "bookmarks",
+        client_addr.ip(),
+        claims.user_id,
+        wanted.max(1) as u32,
+    );
+    if grant.granted == 0 {
+        warn!(
+            user_id = %claims.user_id,
+            client_ip = %client_addr.ip(),
+            "Bookmark batch rejected by rate limiter"
+        );
+        return Err(Error::too_many_requests(grant.retry_after_secs));
+    }
+    let mut granted = grant.granted as usize;
+    for (candidate, raw) in classified.iter_mut().zip(&input.urls) {
+        if candidate.is_ok() {
+            if granted == 0 {
+                *candidate = Err(BatchBookmarkResult {
+                    url: raw.clone(),
+                    status: BatchBookmarkStatus::RateLimited,
+                    task_id: None,
+                    reason: Some(format!("Try again in {} seconds", grant.retry_after_secs)),
+                });
+            } else {
+                granted -= 1;
+            }
+        }
+    }
+
+    let accepted = classified
+        .iter()
+        .filter_map(|candidate| candidate.as_ref().ok())
+        .map(|(url, _)| url.clone())
+        .collect::<Vec<_>>();
+    let accepted_count = accepted.len();
+    let mut tasks = if accepted.is_empty() {
+        Vec::new()
+    } else {
+        bookmark_task::create_batch(
+            &app_context.pool,
+            claims.user_id,
+            accepted,
+            tags,
+            input.ignore_robots,
+        )
+        .await?
+    }
+    .into_iter();
+    let results = classified
+        .into_iter()
+        .zip(input.urls)
+        .map(|(candidate, raw)| match candidate {
+            Err(result) => result,
+            Ok(_) => {
+                let task = tasks.next().expect("a task per accepted URL");
+                announce(&app_context, &task);
+                BatchBookmarkResult {
+                    url: raw,
+                    status: BatchBookmarkStatus::Accepted,
+                    task_id: Some(task.task_id),
+                    reason: None,
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+    info!(
+        user_id = %claims.user_id,
+        url_count = results.len(),
+        accepted = accepted_count,
+        "Bookmark batch queued"
+    );
+    audit::record(
+        &app_context.pool,
+        claims.user_id,
+        AuditAction::BookmarksBatchAdded,
+        Some(&format!(
+            "{accepted_count} of {} URLs queued",
+            results.len()
+        )),
+        Some(client_addr.ip()),
+    )
+    .await;
+    Ok(Json(BatchBookmarkResponse { results }))
+}
+
+/// Queues a task capturing `url` for `user_id` and wakes the task daemon up.
+pub(super) async fn enqueue(
+    app_context: &AppContext,
+    user_id: Uuid,
+    url: Url,
+    tags: Vec<String>,
+    ignore_robots: bool,
+) -> Result<BookmarkTask> {
+    UrlGuard::new(&app_context.config.fetch.fetch_allowed_networks)
+        .check(&url)
+        .map_err(|blocked| Error::unprocessable_entity([("url", blocked.to_string())]))?;
+    storage::ensure_within_quota(
+        &app_context.pool,
+        user_id,
+        app_context.config.storage_quota_bytes,
+    )
+    .await?;
+    let task = bookmark_task::create_with_robots_override(
+        &app_context.pool,
+        user_id,
+        url,
+        tags,
+        ignore_robots,
+    )
+    .await?;
+    announce(app_context, &task);
+    Ok(task)
+}
+
+/// Tells the owner about a new task and wakes the task daemon up.
+fn announce(app_context: &AppContext, task: &BookmarkTask) {
+    app_context.events.publish(
+        task.user_id,
+        ServerEvent::TaskUpdated { task: task.clone() },
+    );
 
     if let Err(error) = app_context.tx_new_task.send(()) {
         error!(?error, "Failed to notify new task daemon");
     } else {
         debug!("Successfully notified task daemon of new bookmark task");
     }
+}
 
-    info!(
-        task_id = %response.task_id,
-        url = %input.url,
-        "Bookmark task created"
-    );
-    Ok((StatusCode::CREATED, Json(response)))
+#[utoipa::path(
+    post,
+    path = "/bookmarks/{id}/refresh",
+    tag = "bookmarks",
+    params(("id" = String, Path)),
+    responses((status = 201, body = BookmarkTask), (status = 404))
+)]
+#[debug_handler]
+async fn refresh_bookmark(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<BookmarkTask>)> {
+    let bookmark = bookmark::get_with_user_data(&app_context.pool, claims.user_id, &id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    storage::ensure_within_quota(
+        &app_context.pool,
+        claims.user_id,
+        app_context.config.storage_quota_bytes,
+    )
+    .await?;
+    let url = Url::parse(&bookmark.url).map_err(anyhow::Error::from)?;
+    let task = bookmark_task::create_refresh(&app_context.pool, claims.user_id, url).await?;
+    announce(&app_context, &task);
+    info!(bookmark_id = %id, task_id = %task.task_id, "Bookmark refresh queued");
+    Ok((StatusCode::CREATED, Json(task)))
+}
+
+/// Processes parts of a bookmark again, waking the daemons doing it up.
+#[utoipa::path(
+    post,
+    path = "/bookmarks/{id}/reprocess",
+    tag = "bookmarks",
+    params(("id" = String, Path)),
+    request_body = ReprocessRequest,
+    responses(
+        (status = 202, body = ReprocessResponse),
+        (status = 400, description = "No LLM configured for the AI stages"),
+        (status = 403, description = "Storage quota reached"),
+        (status = 404),
+        (status = 422),
+    )
+)]
+#[debug_handler]
+async fn reprocess_bookmark(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<String>,
+    Json(request): Json<ReprocessRequest>,
+) -> Result<(StatusCode, Json<ReprocessResponse>)> {
+    let stages = validate_reprocess_request(request)?;
+    let bookmark = bookmark::get_with_user_data(&app_context.pool, claims.user_id, &id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let tags = stages.contains(&ReprocessStage::Tags);
+    let summary = stages.contains(&ReprocessStage::Summary);
+    let chunks = stages.contains(&ReprocessStage::Chunks);
+    if (tags || summary || chunks) && app_context.llm_client.is_none() {
+        return Err(Error::bad_request([(
+            "llm",
+            "AI features are not available. LLM provider is not configured.",
+        )]));
+    }
+
+    let task = if stages.contains(&ReprocessStage::Content) {
+        storage::ensure_within_quota(
+            &app_context.pool,
+            claims.user_id,
+            app_context.config.storage_quota_bytes,
+        )
+        .await?;
+        let url = Url::parse(&bookmark.url).map_err(anyhow::Error::from)?;
+        let task = bookmark_task::create_refresh(&app_context.pool, claims.user_id, url).await?;
+        announce(&app_context, &task);
+        Some(task)
+    } else {
+        None
+    };
+    if tags || summary || chunks {
+        ai::request_reprocessing(
+            &app_context.pool,
+            claims.user_id,
+            &id,
+            tags,
+            summary,
+            chunks,
+        )
+        .await?;
+        if let Err(error) = app_context.tx_new_bookmark.send(()) {
+            error!(?error, "Failed to notify AI daemons");
+        }
+    }
+    info!(bookmark_id = %id, user_id = %claims.user_id, ?stages, "Bookmark reprocessing queued");
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(ReprocessResponse { stages, task }),
+    ))
 }
 
+#[utoipa::path(
+    post,
+    path = "/bookmarks/{id}/tags",
+    tag = "bookmarks",
+    params(("id" = String, Path)),
+    request_body = Tags,
+    responses((status = 200, body = Bookmark), (status = 404))
+)]
 #[debug_handler]
 async fn set_tags(
     claims: Claim,
@@ -195,6 +910,14 @@ async fn set_tags(
     Ok(Json(updated))
 }
 
+#[utoipa::path(
+    patch,
+    path = "/bookmarks/{id}/tags",
+    tag = "bookmarks",
+    params(("id" = String, Path)),
+    request_body = Tags,
+    responses((status = 200, body = Bookmark), (status = 404))
+)]
 #[debug_handler]
 async fn append_tags(
     claims: Claim,
@@ -218,3 +941,619 @@ async fn append_tags(
     info!(bookmark_id = %bookmark_id, "Tags successfully appended");
     Ok(Json(updated))
 }
+
+/// Ids of every bookmark matching `request`. A search falling back to
+/// similar titles matches nothing here, acting on near misses would surprise.
+async fn bulk_search_targets(
+    app_context: &AppContext,
+    user_id: Uuid,
+    request: SearchRequest,
+) -> Result<Vec<String>> {
+    let mut bookmark_ids = Vec::new();
+    loop {
+        let page = SearchRequest {
+            snippet_count: None,
+            snippet_words: None,
+            highlight_title_summary: false,
+            workspace: None,
+            limit: Some(BULK_SEARCH_PAGE_SIZE as i32),
+            offset: Some(bookmark_ids.len() as i32),
+            ..request.clone()
+        };
+        let response = search::search(&app_context.pool, user_id, &page).await?;
+        if response.fuzzy {
+            return Ok(vec![]);
+        }
+        if response.total as usize > MAX_BULK_BOOKMARKS {
+            return Err(Error::unprocessable_entity([(
+                "search",
+                "matches more than 5000 bookmarks, narrow it down",
+            )]));
+        }
+        let fetched = response.items.len();
+        bookmark_ids.extend(
+            response
+                .items
+                .into_iter()
+                .map(|item| item.bookmark.bookmark_id),
+        );
+        if fetched < BULK_SEARCH_PAGE_SIZE {
+            return Ok(bookmark_ids);
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/bookmarks/bulk",
+    tag = "bookmarks",
+    request_body = BulkBookmarkRequest,
+    responses((status = 200, body = BulkBookmarkResponse), (status = 422))
+)]
+#[debug_handler]
+async fn bulk_update_bookmarks(
+    claims: Claim,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    Extension(app_context): Extension<AppContext>,
+    Json(request): Json<BulkBookmarkRequest>,
+) -> Result<Json<BulkBookmarkResponse>> {
+    let request = validate_bulk_request(request)?;
+    let user_id = claims.user_id;
+    let bookmark_ids = match request.search {
+        Some(search) => bulk_search_targets(&app_context, user_id, search).await?,
+        None => request.bookmark_ids,
+    };
+    let pool = &app_context.pool;
+    // Bookmarks of other users or in the trash are left alone
+    let updated = bookmark::bulk_update(pool, user_id, &bookmark_ids, &request.action).await?;
+    let action = match &request.action {
+        BulkBookmarkAction::AddTags { .. } => "add_tags",
+        BulkBookmarkAction::Trash => "trash",
+        BulkBookmarkAction::Favorite => "favorite",
+        BulkBookmarkAction::Unfavorite => "unfavorite",
+    };
+    audit::record(
+        pool,
+        user_id,
+        AuditAction::BookmarksBulkUpdated,
+        Some(&format!("{action} on {updated} bookmarks")),
+        Some(client_addr.ip()),
+    )
+    .await;
+    Ok(Json(BulkBookmarkResponse {
+        updated: updated as usize,
+    }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/bookmarks/{id}/favorite",
+    tag = "bookmarks",
+    params(("id" = String, Path)),
+    responses((status = 200, body = Bookmark), (status = 404))
+)]
+#[debug_handler]
+async fn set_favorite(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(bookmark_id): Path<String>,
+) -> Result<Json<Bookmark>> {
+    bookmark::set_favorite(&app_context.pool, claims.user_id, &bookmark_id, true)
+        .await?
+        .map(Json)
+        .ok_or(Error::NotFound)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/bookmarks/{id}/favorite",
+    tag = "bookmarks",
+    params(("id" = String, Path)),
+    responses((status = 200, body = Bookmark), (status = 404))
+)]
+#[debug_handler]
+async fn unset_favorite(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(bookmark_id): Path<String>,
+) -> Result<Json<Bookmark>> {
+    bookmark::set_favorite(&app_context.pool, claims.user_id, &bookmark_id, false)
+        .await?
+        .map(Json)
+        .ok_or(Error::NotFound)
+}
+
+/// Workspace members only see bookmarks that aren't private, share links
+/// only work for public ones.
+#[utoipa::path(
+    put,
+    path = "/bookmarks/{id}/visibility",
+    tag = "bookmarks",
+    params(("id" = String, Path)),
+    request_body = VisibilityRequest,
+    responses((status = 200, body = Bookmark), (status = 404))
+)]
+#[debug_handler]
+async fn set_visibility(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(bookmark_id): Path<String>,
+    Json(request): Json<VisibilityRequest>,
+) -> Result<Json<Bookmark>> {
+    let updated = bookmark::set_visibility(
+        &app_context.pool,
+        claims.user_id,
+        &bookmark_id,
+        request.visibility,
+    )
+    .await?
+    .ok_or(Error::NotFound)?;
+    info!(
+        %bookmark_id,
+        user_id = %claims.user_id,
+        visibility = request.visibility.as_ref(),
+        "Bookmark visibility set"
+    );
+    Ok(Json(updated))
+}
+
+#[utoipa::path(
+    put,
+    path = "/bookmarks/{id}/summary",
+    tag = "bookmarks",
+    params(("id" = String, Path)),
+    request_body = SummaryRequest,
+    responses((status = 200, body = Bookmark), (status = 404), (status = 422))
+)]
+#[debug_handler]
+async fn set_summary(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(bookmark_id): Path<String>,
+    Json(request): Json<SummaryRequest>,
+) -> Result<Json<Bookmark>> {
+    let summary = request.summary.trim();
+    if summary.is_empty() || summary.chars().count() > MAX_SUMMARY_CHARS {
+        return Err(Error::unprocessable_entity([(
+            "summary",
+            "must have between 1 and 5000 characters",
+        )]));
+    }
+    let updated = ai::set_summary(&app_context.pool, claims.user_id, &bookmark_id, summary)
+        .await?
+        .ok_or(Error::NotFound)?;
+    info!(bookmark_id = %bookmark_id, user_id = %claims.user_id, "Summary written by hand");
+    Ok(Json(updated))
+}
+
+/// Queues the bookmark for the text AI daemon to summarize again from the
+/// slices of its article it already analyzed.
+#[utoipa::path(
+    post,
+    path = "/bookmarks/{id}/summary/regenerate",
+    tag = "bookmarks",
+    params(("id" = String, Path)),
+    request_body = RegenerateSummaryRequest,
+    responses(
+        (status = 202, description = "Summary queued for generation"),
+        (status = 400, description = "No LLM configured"),
+        (status = 404),
+        (status = 422, description = "Summaries are disabled in the settings"),
+    )
+)]
+#[debug_handler]
+async fn regenerate_summary(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(bookmark_id): Path<String>,
+    Json(request): Json<RegenerateSummaryRequest>,
+) -> Result<StatusCode> {
+    if app_context.llm_client.is_none() {
+        return Err(Error::bad_request([(
+            "llm",
+            "AI features are not available. LLM provider is not configured.",
+        )]));
+    }
+    if !user_settings::get(&app_context.pool, claims.user_id)
+        .await?
+        .summaries_enabled
+    {
+        return Err(Error::unprocessable_entity([(
+            "summary",
+            "summaries are disabled in the settings",
+        )]));
+    }
+    if !ai::request_summary(
+        &app_context.pool,
+        claims.user_id,
+        &bookmark_id,
+        request.style,
+    )
+    .await?
+    {
+        return Err(Error::NotFound);
+    }
+    info!(
+        bookmark_id = %bookmark_id,
+        user_id = %claims.user_id,
+        style = ?request.style,
+        "Summary regeneration queued"
+    );
+    if let Err(error) = app_context.tx_new_bookmark.send(()) {
+        error!(?error, "Failed to notify text AI daemon");
+    }
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[utoipa::path(
+    get,
+    path = "/bookmarks/{id}/related",
+    tag = "bookmarks",
+    params(("id" = String, Path), RelatedBookmarksRequest),
+    responses((status = 200, body = RelatedBookmarks), (status = 404))
+)]
+#[debug_handler]
+async fn get_related_bookmarks(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<String>,
+    Query(request): Query<RelatedBookmarksRequest>,
+) -> Result<Json<RelatedBookmarks>> {
+    let limit = bounded_limit(
+        request.limit,
+        DEFAULT_RELATED_LIMIT,
+        MAX_RELATED_LIMIT,
+        "limit",
+    )? as usize;
+    if bookmark::get_with_user_data(&app_context.pool, claims.user_id, &id)
+        .await?
+        .is_none()
+    {
+        return Err(Error::NotFound);
+    }
+    let bookmarks =
+        chunks::find_related_bookmarks(&app_context.pool, claims.user_id, &id, limit).await?;
+    info!(
+        bookmark_id = %id,
+        user_id = %claims.user_id,
+        related_count = %bookmarks.len(),
+        "Retrieved related bookmarks"
+    );
+    Ok(Json(RelatedBookmarks { bookmarks }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/bookmarks/{id}/ask",
+    tag = "bookmarks",
+    params(("id" = String, Path)),
+    request_body = AskBookmarkRequest,
+    responses((status = 200, body = RagQueryResponse), (status = 400), (status = 404), (status = 422))
+)]
+#[debug_handler]
+async fn ask_bookmark(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<String>,
+    Json(request): Json<AskBookmarkRequest>,
+) -> Result<Json<RagQueryResponse>> {
+    if request.question.trim().is_empty() {
+        return Err(Error::unprocessable_entity([(
+            "question",
+            "must not be empty",
+        )]));
+    }
+    let llm_client = require_llm_client(&app_context)?;
+    if bookmark::get_with_user_data(&app_context.pool, claims.user_id, &id)
+        .await?
+        .is_none()
+    {
+        return Err(Error::NotFound);
+    }
+    if !chunks::has_chunks_for_bookmark(&app_context.pool, &id, claims.user_id).await? {
+        return Err(Error::unprocessable_entity([(
+            "bookmark",
+            "has no passages to answer from yet",
+        )]));
+    }
+    let engine = RagEngine::new(
+        app_context.pool.clone(),
+        llm_client,
+        app_context.config.llm.rag_relevance_concurrency,
+        app_context.config.llm.rag_hnsw_ef_search,
+    );
+    let response = engine
+        .ask_bookmark(claims.user_id, &id, &request)
+        .await
+        .map_err(|error| {
+            warn!(bookmark_id = %id, user_id = %claims.user_id, ?error, "Bookmark question failed");
+            Error::from(error)
+        })?;
+    info!(
+        bookmark_id = %id,
+        user_id = %claims.user_id,
+        session_id = %response.session_id,
+        "Answered bookmark question"
+    );
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/bookmarks/{id}/tag-suggestions",
+    tag = "bookmarks",
+    params(("id" = String, Path), TagSuggestionsRequest),
+    responses((status = 200, body = TagSuggestions), (status = 404), (status = 422))
+)]
+#[debug_handler]
+async fn get_tag_suggestions(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<String>,
+    Query(request): Query<TagSuggestionsRequest>,
+) -> Result<Json<TagSuggestions>> {
+    let limit = bounded_limit(
+        request.limit,
+        DEFAULT_TAG_SUGGESTION_LIMIT,
+        MAX_TAG_SUGGESTION_LIMIT,
+        "limit",
+    )? as usize;
+    let pool = &app_context.pool;
+    let bookmark = bookmark::get_with_user_data(pool, claims.user_id, &id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let vocabulary: Vec<String> = bookmark::get_tag_count_by_user(pool, claims.user_id)
+        .await?
+        .into_iter()
+        .map(|(tag, _)| tag)
+        .collect();
+    let proposed: Vec<Vec<String>> = ai::list_bookmark_ai_chunks(pool, claims.user_id, &id)
+        .await?
+        .into_iter()
+        .map(|chunk| chunk.tags)
+        .collect();
+    let related =
+        chunks::find_related_bookmarks(pool, claims.user_id, &id, TAG_SUGGESTION_RELATED_BOOKMARKS)
+            .await?;
+    let suggestions = tag_suggestions::rank(
+        &bookmark.title,
+        bookmark.tags.as_deref().unwrap_or_default(),
+        &vocabulary,
+        &proposed,
+        &related,
+        limit,
+    );
+    info!(
+        bookmark_id = %id,
+        user_id = %claims.user_id,
+        suggestion_count = %suggestions.len(),
+        "Suggested tags"
+    );
+    Ok(Json(TagSuggestions { suggestions }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/bookmarks/{id}/links",
+    tag = "bookmarks",
+    params(("id" = String, Path)),
+    responses((status = 200, body = BookmarkLinks), (status = 404))
+)]
+#[debug_handler]
+async fn get_bookmark_links(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<String>,
+) -> Result<Json<BookmarkLinks>> {
+    if bookmark::get_with_user_data(&app_context.pool, claims.user_id, &id)
+        .await?
+        .is_none()
+    {
+        return Err(Error::NotFound);
+    }
+    let outbound = link::list_outbound(&app_context.pool, claims.user_id, &id).await?;
+    let backlinks = bookmark::get_backlinks(&app_context.pool, claims.user_id, &id).await?;
+    info!(
+        bookmark_id = %id,
+        user_id = %claims.user_id,
+        outbound_count = %outbound.len(),
+        backlink_count = %backlinks.len(),
+        "Retrieved bookmark links"
+    );
+    Ok(Json(BookmarkLinks {
+        outbound,
+        backlinks,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::{
+        BatchBookmarkStatus, BulkBookmarkAction, BulkBookmarkRequest, ReprocessRequest,
+        ReprocessStage, UpdateBookmarkRequest,
+    };
+    use url::Url;
+
+    use super::{
+        classify_batch, validate_bulk_request, validate_reprocess_request, validate_update_request,
+    };
+    use crate::url_guard::UrlGuard;
+
+    #[test]
+    fn batches_flag_invalid_and_repeated_urls() {
+        let urls = [
+            "https://example.com/a",
+            "not a url",
+            "http://127.0.0.1/admin",
+            "https://example.com/a#section",
+            " https://example.com/b ",
+        ]
+        .map(String::from);
+        let classified = classify_batch(&urls, &UrlGuard::new(&[]));
+
+        let statuses = classified
+            .iter()
+            .map(|candidate| candidate.as_ref().err().map(|result| result.status))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                None,
+                Some(BatchBookmarkStatus::Invalid),
+                Some(BatchBookmarkStatus::Invalid),
+                Some(BatchBookmarkStatus::Duplicate),
+                None,
+            ]
+        );
+        let (_, canonical_url) = classified[4].as_ref().unwrap();
+        assert_eq!(canonical_url, "https://example.com/b");
+        assert_eq!(
+            classified[3].as_ref().unwrap_err().url,
+            "https://example.com/a#section"
+        );
+    }
+
+    #[test]
+    fn bulk_requests_target_ids_or_a_search() {
+        let request = |bookmark_ids: &[&str], action: BulkBookmarkAction| BulkBookmarkRequest {
+            bookmark_ids: bookmark_ids.iter().map(|id| id.to_string()).collect(),
+            search: None,
+            action,
+        };
+        let add_tags = |tags: &[&str]| BulkBookmarkAction::AddTags {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        };
+
+        let valid =
+            validate_bulk_request(request(&["a", "b", "a"], add_tags(&[" Rust", "rust", ""])))
+                .unwrap();
+        assert_eq!(valid.bookmark_ids, vec!["a", "b"]);
+        assert_eq!(valid.action, add_tags(&["rust"]));
+
+        assert!(validate_bulk_request(request(&[], BulkBookmarkAction::Trash)).is_err());
+        assert!(validate_bulk_request(request(&["a"], add_tags(&[" "]))).is_err());
+        let with_search = BulkBookmarkRequest {
+            search: Some(serde_json::from_str(r#"{"query": "rust"}"#).unwrap()),
+            ..request(&["a"], BulkBookmarkAction::Favorite)
+        };
+        assert!(validate_bulk_request(with_search.clone()).is_err());
+        assert!(validate_bulk_request(BulkBookmarkRequest {
+            bookmark_ids: vec![],
+            ..with_search
+        })
+        .is_ok());
+    }
+
+    #[test]
+    fn update_requests_are_trimmed_and_validated() {
+        let request = |title: Option<&str>, url: Option<&str>| UpdateBookmarkRequest {
+            title: title.map(str::to_string),
+            url: url.map(|url| Url::parse(url).unwrap()),
+        };
+        let valid = validate_update_request(request(Some(" Better title "), None)).unwrap();
+        assert_eq!(valid.title.as_deref(), Some("Better title"));
+        assert!(validate_update_request(request(None, Some("https://example.com/a"))).is_ok());
+
+        assert!(validate_update_request(request(None, None)).is_err());
+        assert!(validate_update_request(request(Some("  "), None)).is_err());
+        assert!(validate_update_request(request(Some(&"x".repeat(501)), None)).is_err());
+        assert!(validate_update_request(request(None, Some("ftp://example.com/a"))).is_err());
+    }
+
+    #[test]
+    fn reprocess_stages_are_deduplicated() {
+        let stages = validate_reprocess_request(ReprocessRequest {
+            stages: vec![
+                ReprocessStage::Summary,
+                ReprocessStage::Tags,
+                ReprocessStage::Summary,
+            ],
+        })
+        .unwrap();
+        assert_eq!(stages, vec![ReprocessStage::Summary, ReprocessStage::Tags]);
+        assert!(validate_reprocess_request(ReprocessRequest { stages: vec![] }).is_err());
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/bookmarks/{id}/share",
+    tag = "bookmarks",
+    params(("id" = String, Path)),
+    responses((status = 200, body = BookmarkShareLink), (status = 404), (status = 422))
+)]
+#[debug_handler]
+async fn share_bookmark(
+    claims: Claim,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<String>,
+) -> Result<Json<BookmarkShareLink>> {
+    let shared = bookmark::get_with_user_data(&app_context.pool, claims.user_id, &id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    if shared.visibility != Visibility::Public {
+        return Err(Error::unprocessable_entity([(
+            "visibility",
+            "must be public to share the bookmark",
+        )]));
+    }
+    let expires_at = Utc::now() + SHARE_LINK_TTL;
+    let token = static_content::share_token(
+        &app_context.config.hmac_key,
+        claims.user_id,
+        &id,
+        expires_at,
+    );
+    audit::record(
+        &app_context.pool,
+        claims.user_id,
+        AuditAction::BookmarkShared,
+        Some(&id),
+        Some(client_addr.ip()),
+    )
+    .await;
+    info!(bookmark_id = %id, user_id = %claims.user_id, %expires_at, "Bookmark shared");
+    Ok(Json(BookmarkShareLink {
+        url: format!("/static/{}/{id}/index.html?share={token}", claims.user_id),
+        expires_at,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/bookmarks/{id}/markdown",
+    tag = "bookmarks",
+    params(("id" = String, Path)),
+    responses(
+        (status = 200, content_type = "text/markdown", body = String,
+            description = "The saved article, its images linked under /static"),
+        (status = 404)
+    )
+)]
+#[debug_handler]
+async fn get_bookmark_markdown(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<String>,
+) -> Result<Response> {
+    let bookmark = bookmark::get_with_user_data(&app_context.pool, claims.user_id, &id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let page = app_context
+        .storage
+        .get(&format!("{}/{id}/{PAGE_NAME}", claims.user_id))
+        .await?
+        .ok_or(Error::NotFound)?;
+    let page = static_storage::decode(page.bytes, page.content_encoding)?;
+    let markdown = markdown::article(&bookmark, &String::from_utf8_lossy(&page));
+    debug!(bookmark_id = %id, user_id = %claims.user_id, "Converted bookmark to Markdown");
+    Ok((
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/markdown; charset=utf-8"),
+        )],
+        markdown,
+    )
+        .into_response())
+}
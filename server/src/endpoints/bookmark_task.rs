@@ -1,18 +1,37 @@
-use axum::routing::post;
+use axum::extract::Path;
+use axum::routing::{get, post};
 use axum::{Extension, Json, Router};
 use axum_macros::debug_handler;
-use shared::{BookmarkTaskSearchRequest, BookmarkTaskSearchResponse};
-use tracing::info;
+use shared::{
+    BookmarkTask, BookmarkTaskSearchRequest, BookmarkTaskSearchResponse, BulkRetryTasksRequest,
+    BulkRetryTasksResponse, DeadLetterResponse, ServerEvent, TaskEvents,
+};
+use tracing::{debug, error, info};
+use uuid::Uuid;
 
 use super::Claim;
-use crate::db::bookmark_task::search;
-use crate::error::Result;
+use crate::db::bookmark_task::{self, search};
+use crate::db::task_event;
+use crate::error::{Error, Result};
 use crate::AppContext;
 
 pub fn routes() -> Router {
-    Router::new().route("/tasks", post(search_tasks))
+    Router::new()
+        .route("/tasks", post(search_tasks))
+        .route("/tasks/dead-letter", get(dead_letter))
+        .route("/tasks/retry", post(retry_tasks))
+        .route("/tasks/{id}/retry", post(retry_task))
+        .route("/tasks/{id}/cancel", post(cancel_task))
+        .route("/tasks/{id}/events", get(task_events))
 }
 
+#[utoipa::path(
+    post,
+    path = "/tasks",
+    tag = "tasks",
+    request_body = BookmarkTaskSearchRequest,
+    responses((status = 200, body = BookmarkTaskSearchResponse))
+)]
 #[debug_handler]
 async fn search_tasks(
     claims: Claim,
@@ -23,3 +42,147 @@ async fn search_tasks(
     let result = search(&app_context.pool, claims.user_id, &input).await?;
     Ok(Json(result))
 }
+
+/// Tells apart a missing task from one in the wrong state for the transition.
+async fn transition_error(
+    app_context: &AppContext,
+    user_id: Uuid,
+    task_id: Uuid,
+    message: &'static str,
+) -> Error {
+    match bookmark_task::get_by_id(&app_context.pool, user_id, task_id).await {
+        Ok(Some(_)) => Error::bad_request([("status", message)]),
+        Ok(None) => Error::NotFound,
+        Err(error) => error,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/tasks/{id}/retry",
+    tag = "tasks",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = BookmarkTask), (status = 404))
+)]
+#[debug_handler]
+async fn retry_task(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<BookmarkTask>> {
+    let Some(task) = bookmark_task::retry(&app_context.pool, claims.user_id, id).await? else {
+        return Err(transition_error(
+            &app_context,
+            claims.user_id,
+            id,
+            "only failed tasks can be retried",
+        )
+        .await);
+    };
+    app_context.events.publish(
+        claims.user_id,
+        ServerEvent::TaskUpdated { task: task.clone() },
+    );
+    if let Err(error) = app_context.tx_new_task.send(()) {
+        error!(?error, "Failed to notify new task daemon");
+    } else {
+        debug!("Successfully notified task daemon of retried task");
+    }
+    Ok(Json(task))
+}
+
+#[utoipa::path(
+    post,
+    path = "/tasks/{id}/cancel",
+    tag = "tasks",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = BookmarkTask), (status = 404))
+)]
+#[debug_handler]
+async fn cancel_task(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<BookmarkTask>> {
+    let Some(task) = bookmark_task::cancel(&app_context.pool, claims.user_id, id).await? else {
+        return Err(transition_error(
+            &app_context,
+            claims.user_id,
+            id,
+            "only pending tasks can be cancelled",
+        )
+        .await);
+    };
+    app_context.events.publish(
+        claims.user_id,
+        ServerEvent::TaskUpdated { task: task.clone() },
+    );
+    Ok(Json(task))
+}
+
+#[utoipa::path(
+    get,
+    path = "/tasks/dead-letter",
+    tag = "tasks",
+    responses((status = 200, body = DeadLetterResponse))
+)]
+#[debug_handler]
+async fn dead_letter(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+) -> Result<Json<DeadLetterResponse>> {
+    let response = bookmark_task::dead_letter(&app_context.pool, claims.user_id).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/tasks/retry",
+    tag = "tasks",
+    request_body = BulkRetryTasksRequest,
+    responses((status = 200, body = BulkRetryTasksResponse))
+)]
+#[debug_handler]
+async fn retry_tasks(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Json(input): Json<BulkRetryTasksRequest>,
+) -> Result<Json<BulkRetryTasksResponse>> {
+    let tasks = bookmark_task::retry_matching(&app_context.pool, claims.user_id, &input).await?;
+    if !tasks.is_empty() {
+        if let Err(error) = app_context.tx_new_task.send(()) {
+            error!(?error, "Failed to notify new task daemon");
+        }
+    }
+    let requeued = tasks.len();
+    for task in tasks {
+        app_context
+            .events
+            .publish(claims.user_id, ServerEvent::TaskUpdated { task });
+    }
+    Ok(Json(BulkRetryTasksResponse { requeued }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/tasks/{id}/events",
+    tag = "tasks",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = TaskEvents), (status = 404))
+)]
+#[debug_handler]
+async fn task_events(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<TaskEvents>> {
+    if bookmark_task::get_by_id(&app_context.pool, claims.user_id, id)
+        .await?
+        .is_none()
+    {
+        return Err(Error::NotFound);
+    }
+    let events = task_event::list(&app_context.pool, claims.user_id, id).await?;
+    debug!(task_id = %id, event_count = events.len(), "Listed task events");
+    Ok(Json(TaskEvents { events }))
+}
@@ -0,0 +1,192 @@
+use axum::extract::{Path, Query};
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use axum_macros::debug_handler;
+use shared::{
+    BookmarkVersionContent, BookmarkVersionDiff, BookmarkVersionDiffRequest, BookmarkVersions,
+    DiffHunk, DiffLine, DiffOp,
+};
+use similar::{ChangeTag, TextDiff};
+use tracing::info;
+use uuid::Uuid;
+
+use super::Claim;
+use crate::db::{bookmark, bookmark_version};
+use crate::error::{Error, Result};
+use crate::AppContext;
+
+/// Unchanged lines kept around each change.
+const DIFF_CONTEXT_LINES: usize = 3;
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/bookmarks/{id}/versions", get(list_versions))
+        .route("/bookmarks/{id}/versions/diff", get(diff_versions))
+        .route("/bookmarks/{id}/versions/{version_id}", get(get_version))
+}
+
+fn diff_hunks(old: &str, new: &str) -> Vec<DiffHunk> {
+    let diff = TextDiff::from_lines(old, new);
+    diff.grouped_ops(DIFF_CONTEXT_LINES)
+        .iter()
+        .map(|group| DiffHunk {
+            lines: group
+                .iter()
+                .flat_map(|op| diff.iter_changes(op))
+                .map(|change| DiffLine {
+                    op: match change.tag() {
+                        ChangeTag::Equal => DiffOp::Equal,
+                        ChangeTag::Insert => DiffOp::Insert,
+                        ChangeTag::Delete => DiffOp::Delete,
+                    },
+                    text: change.value().trim_end_matches('\n').to_string(),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+#[utoipa::path(
+    get,
+    path = "/bookmarks/{id}/versions",
+    tag = "bookmarks",
+    params(("id" = String, Path)),
+    responses((status = 200, body = BookmarkVersions), (status = 404))
+)]
+#[debug_handler]
+async fn list_versions(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<String>,
+) -> Result<Json<BookmarkVersions>> {
+    if bookmark::get_with_user_data(&app_context.pool, claims.user_id, &id)
+        .await?
+        .is_none()
+    {
+        return Err(Error::NotFound);
+    }
+    let versions = bookmark_version::list(&app_context.pool, claims.user_id, &id).await?;
+    info!(
+        bookmark_id = %id,
+        user_id = %claims.user_id,
+        count = %versions.len(),
+        "Retrieved bookmark versions"
+    );
+    Ok(Json(BookmarkVersions { versions }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/bookmarks/{id}/versions/{version_id}",
+    tag = "bookmarks",
+    params(("id" = String, Path), ("version_id" = Uuid, Path)),
+    responses((status = 200, body = BookmarkVersionContent), (status = 404))
+)]
+#[debug_handler]
+async fn get_version(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path((id, version_id)): Path<(String, Uuid)>,
+) -> Result<Json<BookmarkVersionContent>> {
+    bookmark_version::get(&app_context.pool, claims.user_id, &id, version_id)
+        .await?
+        .map(Json)
+        .ok_or(Error::NotFound)
+}
+
+#[utoipa::path(
+    get,
+    path = "/bookmarks/{id}/versions/diff",
+    tag = "bookmarks",
+    params(("id" = String, Path), BookmarkVersionDiffRequest),
+    responses(
+        (status = 200, body = BookmarkVersionDiff),
+        (status = 404),
+        (status = 422, description = "The bookmark has no earlier capture"),
+    )
+)]
+#[debug_handler]
+async fn diff_versions(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<String>,
+    Query(request): Query<BookmarkVersionDiffRequest>,
+) -> Result<Json<BookmarkVersionDiff>> {
+    let pool = &app_context.pool;
+    let current = bookmark::get_text_content(pool, claims.user_id, &id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let from = match request.from {
+        Some(version_id) => version_id,
+        None => bookmark_version::list(pool, claims.user_id, &id)
+            .await?
+            .first()
+            .map(|version| version.version_id)
+            .ok_or_else(|| {
+                Error::unprocessable_entity([("from", "the bookmark has no earlier capture")])
+            })?,
+    };
+    let old = bookmark_version::get(pool, claims.user_id, &id, from)
+        .await?
+        .ok_or(Error::NotFound)?
+        .text_content;
+    let new = match request.to {
+        Some(version_id) => {
+            bookmark_version::get(pool, claims.user_id, &id, version_id)
+                .await?
+                .ok_or(Error::NotFound)?
+                .text_content
+        }
+        None => current,
+    };
+    let hunks = diff_hunks(&old, &new);
+    info!(
+        bookmark_id = %id,
+        user_id = %claims.user_id,
+        hunk_count = %hunks.len(),
+        "Computed bookmark version diff"
+    );
+    Ok(Json(BookmarkVersionDiff {
+        from: Some(from),
+        to: request.to,
+        hunks,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_keeps_context_around_changes() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\n";
+        let new = "a\nb\nc\nd\nE\nf\ng\nh\ni\n";
+
+        let hunks = diff_hunks(old, new);
+
+        assert_eq!(hunks.len(), 1);
+        let lines: Vec<(DiffOp, &str)> = hunks[0]
+            .lines
+            .iter()
+            .map(|line| (line.op, line.text.as_str()))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                (DiffOp::Equal, "b"),
+                (DiffOp::Equal, "c"),
+                (DiffOp::Equal, "d"),
+                (DiffOp::Delete, "e"),
+                (DiffOp::Insert, "E"),
+                (DiffOp::Equal, "f"),
+                (DiffOp::Equal, "g"),
+                (DiffOp::Equal, "h"),
+            ]
+        );
+    }
+
+    #[test]
+    fn identical_texts_have_no_hunks() {
+        assert!(diff_hunks("same\ntext\n", "same\ntext\n").is_empty());
+    }
+}
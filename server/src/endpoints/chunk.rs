@@ -0,0 +1,90 @@
+use axum::extract::Path;
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use axum_macros::debug_handler;
+use shared::{BookmarkChunks, ChunkContext};
+use tracing::info;
+use uuid::Uuid;
+
+use super::Claim;
+use crate::db::{bookmark, chunks};
+use crate::error::{Error, Result};
+use crate::AppContext;
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/bookmarks/{id}/chunks", get(list_chunks))
+        .route("/chunks/{id}", get(get_chunk))
+}
+
+/// The chunks RAG retrieves from a bookmark, to see why a passage is found or
+/// missed.
+#[utoipa::path(
+    get,
+    path = "/bookmarks/{id}/chunks",
+    tag = "rag",
+    params(("id" = String, Path)),
+    responses((status = 200, body = BookmarkChunks), (status = 404))
+)]
+#[debug_handler]
+async fn list_chunks(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<String>,
+) -> Result<Json<BookmarkChunks>> {
+    if bookmark::get_with_user_data(&app_context.pool, claims.user_id, &id)
+        .await?
+        .is_none()
+    {
+        return Err(Error::NotFound);
+    }
+    let chunks = chunks::chunk_details(&app_context.pool, claims.user_id, &id).await?;
+    info!(
+        bookmark_id = %id,
+        user_id = %claims.user_id,
+        count = %chunks.len(),
+        "Retrieved bookmark chunks"
+    );
+    Ok(Json(BookmarkChunks { chunks }))
+}
+
+/// A chunk, such as a source of an answer, with its bookmark and neighbours.
+#[utoipa::path(
+    get,
+    path = "/chunks/{id}",
+    tag = "rag",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = ChunkContext), (status = 404))
+)]
+#[debug_handler]
+async fn get_chunk(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ChunkContext>> {
+    let Some((_, bookmark)) =
+        chunks::get_chunks_with_bookmarks_by_ids(&app_context.pool, claims.user_id, &[id])
+            .await?
+            .pop()
+    else {
+        return Err(Error::NotFound);
+    };
+    let mut details =
+        chunks::chunk_details(&app_context.pool, bookmark.user_id, &bookmark.bookmark_id).await?;
+    let position = details
+        .iter()
+        .position(|detail| detail.chunk.chunk_id == id)
+        .ok_or(Error::NotFound)?;
+    let chunk_count = details.len();
+    let next = details.get(position + 1).map(|detail| detail.chunk.clone());
+    let previous = position
+        .checked_sub(1)
+        .map(|previous| details[previous].chunk.clone());
+    Ok(Json(ChunkContext {
+        detail: details.swap_remove(position),
+        bookmark,
+        chunk_count,
+        previous,
+        next,
+    }))
+}
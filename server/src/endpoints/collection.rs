@@ -0,0 +1,336 @@
+use std::collections::HashSet;
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, Path, Query};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Extension, Json, Router};
+use axum_macros::debug_handler;
+use chrono::Utc;
+use serde::Deserialize;
+use shared::{
+    AuditAction, Collection, CollectionRequest, CollectionShareLink, Collections, SharedCollection,
+    SharedCollectionBookmark, Visibility,
+};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use super::bookmark::SHARE_LINK_TTL;
+use super::static_content::{self, PAGE_NAME};
+use super::{audit, Claim};
+use crate::db::{bookmark, collection};
+use crate::error::{Error, Result};
+use crate::AppContext;
+
+const MAX_NAME_CHARS: usize = 200;
+const MAX_DESCRIPTION_CHARS: usize = 2000;
+const MAX_COLLECTION_BOOKMARKS: usize = 1000;
+
+pub fn routes() -> Router {
+    Router::new()
+        .route(
+            "/collections",
+            get(list_collections).post(create_collection),
+        )
+        .route(
+            "/collections/{id}",
+            get(get_collection)
+                .put(update_collection)
+                .delete(delete_collection),
+        )
+        .route("/collections/{id}/share", post(share_collection))
+        .route(
+            "/shared/collections/{user_id}/{id}",
+            get(get_shared_collection),
+        )
+}
+
+/// Trims name and description, an empty description being none.
+fn validate_request(request: CollectionRequest) -> Result<CollectionRequest> {
+    let name = request.name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+        return Err(Error::unprocessable_entity([(
+            "name",
+            "must have between 1 and 200 characters",
+        )]));
+    }
+    let description = request
+        .description
+        .map(|description| description.trim().to_string())
+        .filter(|description| !description.is_empty());
+    if description
+        .as_ref()
+        .is_some_and(|description| description.chars().count() > MAX_DESCRIPTION_CHARS)
+    {
+        return Err(Error::unprocessable_entity([(
+            "description",
+            "must have at most 2000 characters",
+        )]));
+    }
+    if request.bookmark_ids.len() > MAX_COLLECTION_BOOKMARKS {
+        return Err(Error::unprocessable_entity([(
+            "bookmark_ids",
+            "must have at most 1000 bookmarks",
+        )]));
+    }
+    let mut seen = HashSet::new();
+    if !request.bookmark_ids.iter().all(|id| seen.insert(id)) {
+        return Err(Error::unprocessable_entity([(
+            "bookmark_ids",
+            "must not repeat bookmarks",
+        )]));
+    }
+    Ok(CollectionRequest {
+        name,
+        description,
+        bookmark_ids: request.bookmark_ids,
+    })
+}
+
+fn collection_scope(user_id: Uuid, collection_id: Uuid) -> String {
+    format!("collection:{user_id}/{collection_id}")
+}
+
+#[utoipa::path(
+    get,
+    path = "/collections",
+    tag = "collections",
+    responses((status = 200, body = Collections))
+)]
+#[debug_handler]
+async fn list_collections(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+) -> Result<Json<Collections>> {
+    let collections = collection::list(&app_context.pool, claims.user_id).await?;
+    debug!(
+        user_id = %claims.user_id,
+        collection_count = collections.len(),
+        "Listed collections"
+    );
+    Ok(Json(Collections { collections }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections",
+    tag = "collections",
+    request_body = CollectionRequest,
+    responses((status = 201, body = Collection), (status = 422))
+)]
+#[debug_handler]
+async fn create_collection(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Json(request): Json<CollectionRequest>,
+) -> Result<(StatusCode, Json<Collection>)> {
+    let request = validate_request(request)?;
+    let created = collection::create(&app_context.pool, claims.user_id, &request).await?;
+    info!(
+        user_id = %claims.user_id,
+        collection_id = %created.collection_id,
+        "Collection created"
+    );
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/collections/{id}",
+    tag = "collections",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = Collection), (status = 404))
+)]
+#[debug_handler]
+async fn get_collection(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Collection>> {
+    collection::get(&app_context.pool, claims.user_id, id)
+        .await?
+        .map(Json)
+        .ok_or(Error::NotFound)
+}
+
+#[utoipa::path(
+    put,
+    path = "/collections/{id}",
+    tag = "collections",
+    params(("id" = Uuid, Path)),
+    request_body = CollectionRequest,
+    responses((status = 200, body = Collection), (status = 404), (status = 422))
+)]
+#[debug_handler]
+async fn update_collection(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<CollectionRequest>,
+) -> Result<Json<Collection>> {
+    let request = validate_request(request)?;
+    let updated = collection::update(&app_context.pool, claims.user_id, id, &request)
+        .await?
+        .ok_or(Error::NotFound)?;
+    info!(user_id = %claims.user_id, collection_id = %id, "Collection updated");
+    Ok(Json(updated))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/collections/{id}",
+    tag = "collections",
+    params(("id" = Uuid, Path)),
+    responses((status = 204, description = "Collection deleted"), (status = 404))
+)]
+#[debug_handler]
+async fn delete_collection(
+    claims: Claim,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    if !collection::delete(&app_context.pool, claims.user_id, id).await? {
+        return Err(Error::NotFound);
+    }
+    audit::record(
+        &app_context.pool,
+        claims.user_id,
+        AuditAction::CollectionDeleted,
+        Some(&id.to_string()),
+        Some(client_addr.ip()),
+    )
+    .await;
+    info!(user_id = %claims.user_id, collection_id = %id, "Collection deleted");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{id}/share",
+    tag = "collections",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = CollectionShareLink), (status = 404))
+)]
+#[debug_handler]
+async fn share_collection(
+    claims: Claim,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<CollectionShareLink>> {
+    if collection::get(&app_context.pool, claims.user_id, id)
+        .await?
+        .is_none()
+    {
+        return Err(Error::NotFound);
+    }
+    let expires_at = Utc::now() + SHARE_LINK_TTL;
+    let token = static_content::scoped_share_token(
+        &app_context.config.hmac_key,
+        &collection_scope(claims.user_id, id),
+        expires_at,
+    );
+    audit::record(
+        &app_context.pool,
+        claims.user_id,
+        AuditAction::CollectionShared,
+        Some(&id.to_string()),
+        Some(client_addr.ip()),
+    )
+    .await;
+    info!(collection_id = %id, user_id = %claims.user_id, %expires_at, "Collection shared");
+    Ok(Json(CollectionShareLink {
+        url: format!(
+            "/api/v1/shared/collections/{}/{id}?share={token}",
+            claims.user_id
+        ),
+        expires_at,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct SharedCollectionQuery {
+    share: String,
+}
+
+/// The collection behind a share link, with links to the captured pages of
+/// its bookmarks valid as long as the share link is.
+#[utoipa::path(
+    get,
+    path = "/shared/collections/{user_id}/{id}",
+    tag = "collections",
+    security(()),
+    params(("user_id" = Uuid, Path), ("id" = Uuid, Path), ("share" = String, Query)),
+    responses((status = 200, body = SharedCollection), (status = 404))
+)]
+#[debug_handler]
+async fn get_shared_collection(
+    Extension(app_context): Extension<AppContext>,
+    Path((user_id, id)): Path<(Uuid, Uuid)>,
+    Query(query): Query<SharedCollectionQuery>,
+) -> Result<Json<SharedCollection>> {
+    let hmac_key = &app_context.config.hmac_key;
+    let scope = collection_scope(user_id, id);
+    if !static_content::is_valid_scoped_share_token(hmac_key, &scope, &query.share, Utc::now()) {
+        debug!(%user_id, collection_id = %id, "Shared collection access denied");
+        return Err(Error::NotFound);
+    }
+    let shared = collection::get(&app_context.pool, user_id, id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    // Pages stay readable as long as the collection link, not longer
+    let expires_at = static_content::share_token_expires_at(&query.share).ok_or(Error::NotFound)?;
+    // Only public bookmarks of the collection are shared along with it
+    let bookmarks = bookmark::get_by_collection(&app_context.pool, user_id, id)
+        .await?
+        .into_iter()
+        .filter(|bookmark| bookmark.visibility == Visibility::Public)
+        .map(|bookmark| {
+            let token =
+                static_content::share_token(hmac_key, user_id, &bookmark.bookmark_id, expires_at);
+            SharedCollectionBookmark {
+                page_url: format!(
+                    "/static/{user_id}/{}/{PAGE_NAME}?share={token}",
+                    bookmark.bookmark_id
+                ),
+                title: bookmark.title,
+                url: bookmark.url,
+                domain: bookmark.domain,
+                summary: bookmark.summary,
+            }
+        })
+        .collect();
+    Ok(Json(SharedCollection {
+        name: shared.name,
+        description: shared.description,
+        bookmarks,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::CollectionRequest;
+
+    use super::validate_request;
+
+    fn request(name: &str, bookmark_ids: &[&str]) -> CollectionRequest {
+        CollectionRequest {
+            name: name.to_string(),
+            description: Some("  ".to_string()),
+            bookmark_ids: bookmark_ids.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn requests_are_trimmed_and_validated() {
+        let valid = validate_request(request("  Rust async ", &["a", "b"])).unwrap();
+        assert_eq!(valid.name, "Rust async");
+        assert_eq!(valid.description, None);
+        assert_eq!(valid.bookmark_ids, vec!["a", "b"]);
+
+        assert!(validate_request(request(" ", &[])).is_err());
+        assert!(validate_request(request(&"x".repeat(201), &[])).is_err());
+        assert!(validate_request(request("Rust", &["a", "a"])).is_err());
+    }
+}
@@ -0,0 +1,204 @@
+use axum::extract::Path;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Extension, Json, Router};
+use axum_macros::debug_handler;
+use shared::{Digest, DigestFeedToken, Digests};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use super::Claim;
+use crate::db::digest;
+use crate::error::{Error, Result};
+use crate::AppContext;
+
+/// About a year of weekly digests.
+const MAX_LISTED_DIGESTS: usize = 52;
+const FEED_DIGESTS: usize = 20;
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/digests", get(list_digests))
+        .route("/digests/feed.atom", get(digest_feed))
+        .route("/digests/feed-token", post(issue_feed_token))
+        .route("/digests/feed/{token}", get(shared_digest_feed))
+        .route("/digests/{id}", get(get_digest))
+}
+
+#[utoipa::path(
+    get,
+    path = "/digests",
+    tag = "digests",
+    responses((status = 200, body = Digests))
+)]
+#[debug_handler]
+async fn list_digests(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+) -> Result<Json<Digests>> {
+    let digests = digest::list(&app_context.pool, claims.user_id, MAX_LISTED_DIGESTS).await?;
+    debug!(user_id = %claims.user_id, digest_count = digests.len(), "Listed digests");
+    Ok(Json(Digests { digests }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/digests/{id}",
+    tag = "digests",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = Digest), (status = 404))
+)]
+#[debug_handler]
+async fn get_digest(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Digest>> {
+    match digest::get(&app_context.pool, claims.user_id, id).await? {
+        Some(digest) => Ok(Json(digest)),
+        None => Err(Error::NotFound),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/digests/feed.atom",
+    tag = "digests",
+    responses((status = 200, content_type = "application/atom+xml", body = String))
+)]
+#[debug_handler]
+async fn digest_feed(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+) -> Result<impl IntoResponse> {
+    feed_response(&app_context, claims.user_id).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/digests/feed-token",
+    tag = "digests",
+    responses((status = 200, body = DigestFeedToken))
+)]
+#[debug_handler]
+async fn issue_feed_token(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+) -> Result<Json<DigestFeedToken>> {
+    let token = digest::issue_feed_token(&app_context.pool, claims.user_id).await?;
+    info!(user_id = %claims.user_id, "Digest feed token issued");
+    Ok(Json(DigestFeedToken {
+        path: format!("/api/v1/digests/feed/{token}"),
+        token,
+    }))
+}
+
+/// The feed of the user owning the token, without signing in.
+#[utoipa::path(
+    get,
+    path = "/digests/feed/{token}",
+    tag = "digests",
+    params(("token" = String, Path)),
+    responses(
+        (status = 200, content_type = "application/atom+xml", body = String),
+        (status = 404)
+    ),
+    security(())
+)]
+#[debug_handler]
+async fn shared_digest_feed(
+    Extension(app_context): Extension<AppContext>,
+    Path(token): Path<String>,
+) -> Result<impl IntoResponse> {
+    let user_id = digest::feed_token_user(&app_context.pool, &token)
+        .await?
+        .ok_or(Error::NotFound)?;
+    feed_response(&app_context, user_id).await
+}
+
+async fn feed_response(app_context: &AppContext, user_id: Uuid) -> Result<impl IntoResponse> {
+    let digests = digest::list(&app_context.pool, user_id, FEED_DIGESTS).await?;
+    Ok((
+        [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        atom_feed(user_id, &digests),
+    ))
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn atom_feed(user_id: Uuid, digests: &[Digest]) -> String {
+    let updated = digests
+        .iter()
+        .map(|digest| digest.created_at)
+        .max()
+        .unwrap_or_default()
+        .to_rfc3339();
+    let entries: String = digests
+        .iter()
+        .map(|digest| {
+            format!(
+                r#"
+  <entry>
+    <id>urn:uuid:{}</id>
+    <title>Week of {}</title>
+    <updated>{}</updated>
+    <content type="text">{}</content>
+  </entry>"#,
+                digest.digest_id,
+                digest.period_start.format("%Y-%m-%d"),
+                digest.created_at.to_rfc3339(),
+                escape_xml(&digest.content),
+            )
+        })
+        .collect();
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>urn:uuid:{user_id}</id>
+  <title>Bookmark Hub weekly digest</title>
+  <updated>{updated}</updated>{entries}
+</feed>
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use shared::Digest;
+    use uuid::Uuid;
+
+    use super::atom_feed;
+
+    #[test]
+    fn atom_feed_escapes_content() {
+        let digest = Digest {
+            digest_id: Uuid::nil(),
+            period_start: Utc.with_ymd_and_hms(2024, 5, 6, 0, 0, 0).unwrap(),
+            period_end: Utc.with_ymd_and_hms(2024, 5, 13, 0, 0, 0).unwrap(),
+            bookmark_ids: vec!["b1".into()],
+            content: "Rust & <async>".into(),
+            created_at: Utc.with_ymd_and_hms(2024, 5, 13, 1, 0, 0).unwrap(),
+        };
+
+        let feed = atom_feed(Uuid::nil(), &[digest]);
+
+        assert!(feed.contains("<title>Week of 2024-05-06</title>"));
+        assert!(feed.contains("<updated>2024-05-13T01:00:00+00:00</updated>"));
+        assert!(feed.contains("Rust &amp; &lt;async&gt;"));
+    }
+}
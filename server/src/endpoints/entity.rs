@@ -0,0 +1,54 @@
+use axum::extract::Query;
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use axum_macros::debug_handler;
+use shared::{Entities, EntitiesRequest};
+use tracing::debug;
+
+use super::{bounded_limit, Claim};
+use crate::db::entity;
+use crate::error::Result;
+use crate::AppContext;
+
+const DEFAULT_ENTITY_LIMIT: u32 = 100;
+const MAX_ENTITY_LIMIT: u32 = 1000;
+
+pub fn routes() -> Router {
+    Router::new().route("/entities", get(list_entities))
+}
+
+#[utoipa::path(
+    get,
+    path = "/entities",
+    tag = "entities",
+    params(EntitiesRequest),
+    responses((status = 200, body = Entities), (status = 422))
+)]
+#[debug_handler]
+async fn list_entities(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Query(request): Query<EntitiesRequest>,
+) -> Result<Json<Entities>> {
+    let limit = bounded_limit(
+        request.limit,
+        DEFAULT_ENTITY_LIMIT,
+        MAX_ENTITY_LIMIT,
+        "limit",
+    )?;
+    let query = request
+        .query
+        .as_deref()
+        .map(str::trim)
+        .filter(|query| !query.is_empty());
+    let entities = entity::list(
+        &app_context.pool,
+        claims.user_id,
+        request.kind,
+        query,
+        i64::from(limit),
+    )
+    .await?;
+    debug!(user_id = %claims.user_id, entity_count = entities.len(), "Listed entities");
+    Ok(Json(Entities { entities }))
+}
@@ -0,0 +1,71 @@
+use std::convert::Infallible;
+
+use axum::extract::Query;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::routing::get;
+use axum::{Extension, Router};
+use axum_macros::debug_handler;
+use futures::{Stream, StreamExt};
+use shared::{ServerEvent, TaskSubscription};
+use tracing::{error, info};
+
+use super::Claim;
+use crate::AppContext;
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/events", get(stream_events))
+        .route("/events/tasks", get(stream_task_events))
+}
+
+fn into_sse(
+    events: impl Stream<Item = ServerEvent> + Send + 'static,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = events.filter_map(|event| async move {
+        match Event::default().json_data(&event) {
+            Ok(event) => Some(Ok(event)),
+            Err(error) => {
+                error!(?error, "Failed to encode server event");
+                None
+            }
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[utoipa::path(
+    get,
+    path = "/events",
+    tag = "events",
+    responses((status = 200, content_type = "text/event-stream", body = shared::ServerEvent))
+)]
+#[debug_handler]
+async fn stream_events(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    info!(user_id = %claims.user_id, "Opening event stream");
+    into_sse(app_context.events.subscribe_user(claims.user_id))
+}
+
+/// Updates of the tasks matching the filter only, to keep a task list live.
+#[utoipa::path(
+    get,
+    path = "/events/tasks",
+    tag = "events",
+    params(TaskSubscription),
+    responses((status = 200, content_type = "text/event-stream", body = shared::ServerEvent))
+)]
+#[debug_handler]
+async fn stream_task_events(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Query(subscription): Query<TaskSubscription>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    info!(user_id = %claims.user_id, ?subscription, "Opening task event stream");
+    into_sse(
+        app_context
+            .events
+            .subscribe_tasks(claims.user_id, subscription),
+    )
+}
@@ -0,0 +1,219 @@
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use axum_macros::debug_handler;
+use shared::{ExtractionRule, ExtractionRuleRequest, ExtractionRules};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use super::Claim;
+use crate::db::extraction_rule;
+use crate::error::{Error, Result};
+use crate::{readability, AppContext};
+
+const MAX_SELECTORS: usize = 20;
+const MAX_SELECTOR_CHARS: usize = 200;
+
+pub fn routes() -> Router {
+    Router::new()
+        .route(
+            "/extraction-rules",
+            get(list_extraction_rules).post(create_extraction_rule),
+        )
+        .route(
+            "/extraction-rules/{id}",
+            get(get_extraction_rule)
+                .put(update_extraction_rule)
+                .delete(delete_extraction_rule),
+        )
+}
+
+/// Trims the domain and the selectors, lowercases the domain and leaves out
+/// blank and repeated selectors. A rule needs a domain, one selector at
+/// least and only selectors that parse.
+fn validate_request(request: ExtractionRuleRequest) -> Result<ExtractionRuleRequest> {
+    let domain = request.domain.trim().trim_matches('.').to_lowercase();
+    if domain.is_empty() || domain.contains(|c: char| c == '/' || c.is_whitespace()) {
+        return Err(Error::unprocessable_entity([(
+            "domain",
+            "must be a domain name such as example.com",
+        )]));
+    }
+    let selectors = |field: &'static str, selectors: Vec<String>| {
+        let mut valid: Vec<String> = Vec::new();
+        for selector in selectors {
+            let selector = selector.trim().to_string();
+            if selector.is_empty() || valid.contains(&selector) {
+                continue;
+            }
+            if selector.chars().count() > MAX_SELECTOR_CHARS
+                || !readability::is_valid_selector(&selector)
+            {
+                return Err(Error::unprocessable_entity([(
+                    field,
+                    "must be valid CSS selectors of up to 200 characters",
+                )]));
+            }
+            valid.push(selector);
+        }
+        if valid.len() > MAX_SELECTORS {
+            return Err(Error::unprocessable_entity([(
+                field,
+                "must have at most 20 selectors",
+            )]));
+        }
+        Ok(valid)
+    };
+    let keep_selectors = selectors("keep_selectors", request.keep_selectors)?;
+    let drop_selectors = selectors("drop_selectors", request.drop_selectors)?;
+    let title_selector = selectors(
+        "title_selector",
+        request.title_selector.into_iter().collect(),
+    )?
+    .pop();
+    if keep_selectors.is_empty() && drop_selectors.is_empty() && title_selector.is_none() {
+        return Err(Error::unprocessable_entity([(
+            "selectors",
+            "must keep, drop or find the title of some elements",
+        )]));
+    }
+    Ok(ExtractionRuleRequest {
+        domain,
+        keep_selectors,
+        drop_selectors,
+        title_selector,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/extraction-rules",
+    tag = "extraction_rules",
+    responses((status = 200, body = ExtractionRules))
+)]
+#[debug_handler]
+async fn list_extraction_rules(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+) -> Result<Json<ExtractionRules>> {
+    let rules = extraction_rule::list(&app_context.pool, claims.user_id).await?;
+    debug!(user_id = %claims.user_id, rule_count = rules.len(), "Listed extraction rules");
+    Ok(Json(ExtractionRules { rules }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/extraction-rules",
+    tag = "extraction_rules",
+    request_body = ExtractionRuleRequest,
+    responses((status = 201, body = ExtractionRule), (status = 422))
+)]
+#[debug_handler]
+async fn create_extraction_rule(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Json(request): Json<ExtractionRuleRequest>,
+) -> Result<(StatusCode, Json<ExtractionRule>)> {
+    let request = validate_request(request)?;
+    let created = extraction_rule::create(&app_context.pool, claims.user_id, &request).await?;
+    info!(
+        user_id = %claims.user_id,
+        rule_id = %created.rule_id,
+        domain = %created.domain,
+        "Extraction rule created"
+    );
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/extraction-rules/{id}",
+    tag = "extraction_rules",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = ExtractionRule), (status = 404))
+)]
+#[debug_handler]
+async fn get_extraction_rule(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ExtractionRule>> {
+    extraction_rule::get(&app_context.pool, claims.user_id, id)
+        .await?
+        .map(Json)
+        .ok_or(Error::NotFound)
+}
+
+#[utoipa::path(
+    put,
+    path = "/extraction-rules/{id}",
+    tag = "extraction_rules",
+    params(("id" = Uuid, Path)),
+    request_body = ExtractionRuleRequest,
+    responses((status = 200, body = ExtractionRule), (status = 404), (status = 422))
+)]
+#[debug_handler]
+async fn update_extraction_rule(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<ExtractionRuleRequest>,
+) -> Result<Json<ExtractionRule>> {
+    let request = validate_request(request)?;
+    let updated = extraction_rule::update(&app_context.pool, claims.user_id, id, &request)
+        .await?
+        .ok_or(Error::NotFound)?;
+    info!(user_id = %claims.user_id, rule_id = %id, "Extraction rule updated");
+    Ok(Json(updated))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/extraction-rules/{id}",
+    tag = "extraction_rules",
+    params(("id" = Uuid, Path)),
+    responses((status = 204, description = "Extraction rule deleted"), (status = 404))
+)]
+#[debug_handler]
+async fn delete_extraction_rule(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    if !extraction_rule::delete(&app_context.pool, claims.user_id, id).await? {
+        return Err(Error::NotFound);
+    }
+    info!(user_id = %claims.user_id, rule_id = %id, "Extraction rule deleted");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::ExtractionRuleRequest;
+
+    use super::validate_request;
+
+    fn request(domain: &str, drop_selectors: &[&str]) -> ExtractionRuleRequest {
+        ExtractionRuleRequest {
+            domain: domain.to_string(),
+            keep_selectors: vec![" ".to_string()],
+            drop_selectors: drop_selectors.iter().map(|s| s.to_string()).collect(),
+            title_selector: Some(" ".to_string()),
+        }
+    }
+
+    #[test]
+    fn requests_are_normalized_and_validated() {
+        let valid =
+            validate_request(request(" Docs.Example.com ", &[".ad", ".ad ", "nav"])).unwrap();
+        assert_eq!(valid.domain, "docs.example.com");
+        assert!(valid.keep_selectors.is_empty());
+        assert_eq!(valid.drop_selectors, vec![".ad", "nav"]);
+        assert_eq!(valid.title_selector, None);
+
+        assert!(validate_request(request("example.com/docs", &[".ad"])).is_err());
+        assert!(validate_request(request("example.com", &[" "])).is_err());
+        assert!(validate_request(request("example.com", &["div[["])).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use axum::http::{HeaderName, HeaderValue};
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use axum_macros::debug_handler;
+use shared::{FetchCredential, FetchCredentialRequest, FetchCredentials, FetchCredentialsRequest};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use super::Claim;
+use crate::db::fetch_credential;
+use crate::error::{Error, Result};
+use crate::fetch_credentials::CredentialCipher;
+use crate::AppContext;
+
+const MAX_DOMAINS: usize = 50;
+const MAX_HEADERS: usize = 20;
+const MAX_HEADER_VALUE_CHARS: usize = 4096;
+
+pub fn routes() -> Router {
+    Router::new().route(
+        "/fetch-credentials",
+        get(list_fetch_credentials).put(replace_fetch_credentials),
+    )
+}
+
+/// Trims and lowercases the domains, trims the header names. Every domain
+/// appears once and has between one and 20 headers the browser can send.
+fn validate_request(request: FetchCredentialsRequest) -> Result<Vec<FetchCredentialRequest>> {
+    if request.credentials.len() > MAX_DOMAINS {
+        return Err(Error::unprocessable_entity([(
+            "credentials",
+            "must have at most 50 domains",
+        )]));
+    }
+    let mut valid: Vec<FetchCredentialRequest> = Vec::new();
+    for credential in request.credentials {
+        let domain = credential.domain.trim().trim_matches('.').to_lowercase();
+        if domain.is_empty() || domain.contains(|c: char| c == '/' || c.is_whitespace()) {
+            return Err(Error::unprocessable_entity([(
+                "domain",
+                "must be a domain name such as example.com",
+            )]));
+        }
+        if valid.iter().any(|other| other.domain == domain) {
+            return Err(Error::unprocessable_entity([(
+                "domain",
+                "must appear once",
+            )]));
+        }
+        if credential.headers.is_empty() || credential.headers.len() > MAX_HEADERS {
+            return Err(Error::unprocessable_entity([(
+                "headers",
+                "must have between 1 and 20 headers",
+            )]));
+        }
+        let mut headers = std::collections::BTreeMap::new();
+        for (name, value) in credential.headers {
+            let name = name.trim().to_string();
+            if HeaderName::from_bytes(name.as_bytes()).is_err() {
+                return Err(Error::unprocessable_entity([(
+                    "headers",
+                    "must have valid header names",
+                )]));
+            }
+            if value.chars().count() > MAX_HEADER_VALUE_CHARS
+                || HeaderValue::from_str(&value).is_err()
+            {
+                return Err(Error::unprocessable_entity([(
+                    "headers",
+                    "must have valid header values of up to 4096 characters",
+                )]));
+            }
+            headers.insert(name, value);
+        }
+        valid.push(FetchCredentialRequest { domain, headers });
+    }
+    Ok(valid)
+}
+
+/// Header names of the stored credentials. A credential that no longer opens,
+/// after HMAC_KEY was rotated, is listed without header names.
+async fn list(
+    app_context: &AppContext,
+    cipher: &CredentialCipher,
+    user_id: Uuid,
+) -> Result<FetchCredentials> {
+    let credentials = fetch_credential::list(&app_context.pool, user_id)
+        .await?
+        .into_iter()
+        .map(|credential| {
+            let header_names = cipher
+                .open(user_id, &credential.domain, &credential.sealed_headers)
+                .map(|headers| headers.into_keys().collect())
+                .unwrap_or_else(|error| {
+                    warn!(%user_id, domain = %credential.domain, ?error, "Unreadable fetch credential");
+                    Vec::new()
+                });
+            FetchCredential {
+                domain: credential.domain,
+                header_names,
+                updated_at: credential.updated_at,
+            }
+        })
+        .collect();
+    Ok(FetchCredentials { credentials })
+}
+
+#[utoipa::path(
+    get,
+    path = "/fetch-credentials",
+    tag = "fetch_credentials",
+    responses((status = 200, body = FetchCredentials))
+)]
+#[debug_handler]
+async fn list_fetch_credentials(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+) -> Result<Json<FetchCredentials>> {
+    let cipher = CredentialCipher::new(&app_context.config.hmac_key);
+    Ok(Json(list(&app_context, &cipher, claims.user_id).await?))
+}
+
+#[utoipa::path(
+    put,
+    path = "/fetch-credentials",
+    tag = "fetch_credentials",
+    request_body = FetchCredentialsRequest,
+    responses((status = 200, body = FetchCredentials), (status = 422))
+)]
+#[debug_handler]
+async fn replace_fetch_credentials(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Json(request): Json<FetchCredentialsRequest>,
+) -> Result<Json<FetchCredentials>> {
+    let credentials = validate_request(request)?;
+    let cipher = CredentialCipher::new(&app_context.config.hmac_key);
+    let sealed = credentials
+        .iter()
+        .map(|credential| {
+            let sealed = cipher.seal(claims.user_id, &credential.domain, &credential.headers)?;
+            Ok((credential.domain.clone(), sealed))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    fetch_credential::replace(&app_context.pool, claims.user_id, sealed).await?;
+    info!(
+        user_id = %claims.user_id,
+        domain_count = credentials.len(),
+        "Fetch credentials replaced"
+    );
+    Ok(Json(list(&app_context, &cipher, claims.user_id).await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use shared::{FetchCredentialRequest, FetchCredentialsRequest};
+
+    use super::validate_request;
+
+    fn request(credentials: &[(&str, &[(&str, &str)])]) -> FetchCredentialsRequest {
+        FetchCredentialsRequest {
+            credentials: credentials
+                .iter()
+                .map(|(domain, headers)| FetchCredentialRequest {
+                    domain: domain.to_string(),
+                    headers: headers
+                        .iter()
+                        .map(|(name, value)| (name.to_string(), value.to_string()))
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn requests_are_normalized_and_validated() {
+        let valid = validate_request(request(&[(
+            " News.Example.com ",
+            &[(" Cookie ", "session=abc")],
+        )]))
+        .unwrap();
+        assert_eq!(valid[0].domain, "news.example.com");
+        assert_eq!(
+            valid[0].headers,
+            BTreeMap::from([("Cookie".to_string(), "session=abc".to_string())])
+        );
+
+        assert!(validate_request(request(&[("example.com", &[])])).is_err());
+        assert!(validate_request(request(&[("example.com/x", &[("Cookie", "a")])])).is_err());
+        assert!(validate_request(request(&[("example.com", &[("Bad Name", "a")])])).is_err());
+        assert!(validate_request(request(&[("example.com", &[("Cookie", "a\nb")])])).is_err());
+        assert!(validate_request(request(&[
+            ("example.com", &[("Cookie", "a")]),
+            ("Example.com", &[("Cookie", "b")]),
+        ]))
+        .is_err());
+    }
+}
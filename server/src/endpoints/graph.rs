@@ -0,0 +1,257 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use axum::extract::Query;
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use axum_macros::debug_handler;
+use shared::{Graph, GraphEdge, GraphEdgeKind, GraphNode, GraphNodeKind, GraphRequest};
+use tracing::debug;
+
+use super::{bounded_limit, Claim};
+use crate::db::graph::{self, GraphData};
+use crate::error::Result;
+use crate::AppContext;
+
+const DEFAULT_GRAPH_LIMIT: u32 = 200;
+const MAX_GRAPH_LIMIT: u32 = 1000;
+
+pub fn routes() -> Router {
+    Router::new().route("/graph", get(get_graph))
+}
+
+fn bookmark_node_id(bookmark_id: &str) -> String {
+    format!("bookmark:{bookmark_id}")
+}
+
+fn domain_node_id(domain: &str) -> String {
+    format!("domain:{domain}")
+}
+
+/// Entities mentioned by a single bookmark connect nothing, so only the
+/// ones shared by two or more become nodes. Links to a saved bookmark
+/// point at it; other links point at the domain when some bookmark was
+/// saved from there.
+fn build(data: GraphData) -> Graph {
+    let mut nodes = Vec::new();
+    let mut edges: BTreeMap<(String, String), (GraphEdgeKind, u32)> = BTreeMap::new();
+
+    let mut domains = HashSet::new();
+    for bookmark in &data.bookmarks {
+        let id = bookmark_node_id(&bookmark.bookmark_id);
+        nodes.push(GraphNode {
+            id: id.clone(),
+            kind: GraphNodeKind::Bookmark,
+            label: bookmark.title.clone(),
+            entity_kind: None,
+        });
+        edges.insert(
+            (id, domain_node_id(&bookmark.domain)),
+            (GraphEdgeKind::SavedFrom, 1),
+        );
+        domains.insert(bookmark.domain.as_str());
+    }
+    let mut domains: Vec<&str> = domains.into_iter().collect();
+    domains.sort_unstable();
+    nodes.extend(domains.iter().map(|domain| GraphNode {
+        id: domain_node_id(domain),
+        kind: GraphNodeKind::Domain,
+        label: domain.to_string(),
+        entity_kind: None,
+    }));
+
+    let mut mentions: BTreeMap<String, (GraphNode, HashSet<&str>)> = BTreeMap::new();
+    for (bookmark_id, entity) in &data.entities {
+        let id = format!(
+            "entity:{}:{}",
+            entity.kind.as_ref(),
+            entity.name.to_lowercase()
+        );
+        mentions
+            .entry(id.clone())
+            .or_insert_with(|| {
+                let node = GraphNode {
+                    id,
+                    kind: GraphNodeKind::Entity,
+                    label: entity.name.clone(),
+                    entity_kind: Some(entity.kind),
+                };
+                (node, HashSet::new())
+            })
+            .1
+            .insert(bookmark_id.as_str());
+    }
+    for (id, (node, bookmark_ids)) in mentions {
+        if bookmark_ids.len() < 2 {
+            continue;
+        }
+        for bookmark_id in bookmark_ids {
+            edges.insert(
+                (bookmark_node_id(bookmark_id), id.clone()),
+                (GraphEdgeKind::Mentions, 1),
+            );
+        }
+        nodes.push(node);
+    }
+
+    let by_url: HashMap<&str, &str> = data
+        .bookmarks
+        .iter()
+        .map(|bookmark| {
+            (
+                bookmark.canonical_url.as_str(),
+                bookmark.bookmark_id.as_str(),
+            )
+        })
+        .collect();
+    let own_domain: HashMap<&str, &str> = data
+        .bookmarks
+        .iter()
+        .map(|bookmark| (bookmark.bookmark_id.as_str(), bookmark.domain.as_str()))
+        .collect();
+    for link in &data.links {
+        let target = match by_url.get(link.url.as_str()) {
+            Some(&target) if target == link.bookmark_id => continue,
+            Some(&target) => bookmark_node_id(target),
+            None if own_domain.get(link.bookmark_id.as_str()) == Some(&link.domain.as_str()) => {
+                continue
+            }
+            None if domains.binary_search(&link.domain.as_str()).is_ok() => {
+                domain_node_id(&link.domain)
+            }
+            None => continue,
+        };
+        edges
+            .entry((bookmark_node_id(&link.bookmark_id), target))
+            .or_insert((GraphEdgeKind::LinksTo, 0))
+            .1 += 1;
+    }
+
+    let edges = edges
+        .into_iter()
+        .map(|((source, target), (kind, weight))| GraphEdge {
+            source,
+            target,
+            kind,
+            weight,
+        })
+        .collect();
+    Graph { nodes, edges }
+}
+
+#[utoipa::path(
+    get,
+    path = "/graph",
+    tag = "graph",
+    params(GraphRequest),
+    responses((status = 200, body = Graph), (status = 422))
+)]
+#[debug_handler]
+async fn get_graph(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Query(request): Query<GraphRequest>,
+) -> Result<Json<Graph>> {
+    let limit = bounded_limit(request.limit, DEFAULT_GRAPH_LIMIT, MAX_GRAPH_LIMIT, "limit")?;
+    let data = graph::load(&app_context.pool, claims.user_id, i64::from(limit)).await?;
+    let graph = build(data);
+    debug!(
+        user_id = %claims.user_id,
+        node_count = graph.nodes.len(),
+        edge_count = graph.edges.len(),
+        "Built graph"
+    );
+    Ok(Json(graph))
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::{Entity, EntityKind, GraphEdgeKind, GraphNodeKind};
+
+    use super::build;
+    use crate::db::graph::{GraphBookmark, GraphData, GraphLink};
+
+    fn bookmark(id: &str, domain: &str) -> GraphBookmark {
+        GraphBookmark {
+            bookmark_id: id.to_string(),
+            title: format!("Title {id}"),
+            domain: domain.to_string(),
+            canonical_url: format!("https://{domain}/{id}"),
+        }
+    }
+
+    fn entity(bookmark_id: &str, name: &str) -> (String, Entity) {
+        let entity = Entity {
+            kind: EntityKind::Project,
+            name: name.to_string(),
+        };
+        (bookmark_id.to_string(), entity)
+    }
+
+    fn link(bookmark_id: &str, url: &str, domain: &str) -> GraphLink {
+        GraphLink {
+            bookmark_id: bookmark_id.to_string(),
+            url: url.to_string(),
+            domain: domain.to_string(),
+        }
+    }
+
+    #[test]
+    fn only_shared_entities_become_nodes() {
+        let graph = build(GraphData {
+            bookmarks: vec![bookmark("a", "one.com"), bookmark("b", "two.com")],
+            entities: vec![
+                entity("a", "Tokio"),
+                entity("b", "tokio"),
+                entity("a", "Mio"),
+            ],
+            links: vec![],
+        });
+        let entities: Vec<_> = graph
+            .nodes
+            .iter()
+            .filter(|node| node.kind == GraphNodeKind::Entity)
+            .collect();
+        assert_eq!(entities.len(), 1);
+        assert_eq!(entities[0].id, "entity:project:tokio");
+        let mentions = graph
+            .edges
+            .iter()
+            .filter(|edge| edge.kind == GraphEdgeKind::Mentions)
+            .count();
+        assert_eq!(mentions, 2);
+        let saved_from = graph
+            .edges
+            .iter()
+            .filter(|edge| edge.kind == GraphEdgeKind::SavedFrom)
+            .count();
+        assert_eq!(saved_from, 2);
+    }
+
+    #[test]
+    fn links_point_at_bookmarks_or_known_domains() {
+        let graph = build(GraphData {
+            bookmarks: vec![bookmark("a", "one.com"), bookmark("b", "two.com")],
+            entities: vec![],
+            links: vec![
+                link("a", "https://two.com/b", "two.com"),
+                link("b", "https://one.com/other", "one.com"),
+                link("b", "https://one.com/more", "one.com"),
+                link("b", "https://two.com/self", "two.com"),
+                link("b", "https://unknown.org/", "unknown.org"),
+            ],
+        });
+        let links: Vec<_> = graph
+            .edges
+            .iter()
+            .filter(|edge| edge.kind == GraphEdgeKind::LinksTo)
+            .map(|edge| (edge.source.as_str(), edge.target.as_str(), edge.weight))
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                ("bookmark:a", "bookmark:b", 1),
+                ("bookmark:b", "domain:one.com", 2),
+            ]
+        );
+    }
+}
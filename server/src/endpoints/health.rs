@@ -0,0 +1,276 @@
+use std::future::Future;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{routing, Extension, Json, Router};
+use serde::Serialize;
+use shared::AiStatus;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::chrome_client::{ChromeClient, ChromeConnection};
+use crate::error::Result;
+use crate::static_storage::StorageBackendKind;
+use crate::url_guard::UrlGuard;
+use crate::{db, llm, AppContext};
+
+/// A dependency slower than this is reported as unavailable.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Status {
+    Ok,
+    Unavailable,
+    /// Not configured, so not needed to serve requests
+    Skipped,
+}
+
+#[derive(Debug, Serialize)]
+struct Check {
+    status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    elapsed_ms: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl Check {
+    fn skipped() -> Self {
+        Self {
+            status: Status::Skipped,
+            elapsed_ms: None,
+            error: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Checks {
+    database: Check,
+    data_dir: Check,
+    ollama: Check,
+    chrome: Check,
+}
+
+impl Checks {
+    fn status(&self) -> Status {
+        let all = [&self.database, &self.data_dir, &self.ollama, &self.chrome];
+        if all.iter().any(|check| check.status == Status::Unavailable) {
+            Status::Unavailable
+        } else {
+            Status::Ok
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Readiness {
+    status: Status,
+    checks: Checks,
+    /// Degraded AI features don't fail readiness, the rest keeps working
+    ai: AiStatus,
+}
+
+impl IntoResponse for Readiness {
+    fn into_response(self) -> Response {
+        let status = match self.status {
+            Status::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Status::Ok | Status::Skipped => StatusCode::OK,
+        };
+        (status, Json(self)).into_response()
+    }
+}
+
+async fn check(name: &str, probe: impl Future<Output = anyhow::Result<()>>) -> Check {
+    let start = Instant::now();
+    let result = match tokio::time::timeout(CHECK_TIMEOUT, probe).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow!("Timed out after {CHECK_TIMEOUT:?}")),
+    };
+    let elapsed_ms = Some(start.elapsed().as_millis());
+    match result {
+        Ok(()) => Check {
+            status: Status::Ok,
+            elapsed_ms,
+            error: None,
+        },
+        Err(error) => {
+            warn!(
+                dependency = name,
+                error = format!("{error:#}"),
+                "Readiness check failed"
+            );
+            Check {
+                status: Status::Unavailable,
+                elapsed_ms,
+                error: Some(format!("{error:#}")),
+            }
+        }
+    }
+}
+
+/// Writes and removes a probe file, the name is unique so concurrent probes
+/// don't trip over each other.
+async fn check_data_dir(data_dir: &Path) -> anyhow::Result<()> {
+    let probe = data_dir.join(format!(".ready-{}", Uuid::new_v4()));
+    tokio::fs::write(&probe, b"ready").await?;
+    tokio::fs::remove_file(&probe).await?;
+    Ok(())
+}
+
+async fn check_ollama(base_url: &str) -> anyhow::Result<()> {
+    reqwest::Client::new()
+        .get(format!("{base_url}/api/version"))
+        .timeout(CHECK_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+async fn liveness_handler() -> Json<String> {
+    Json("OK".to_string())
+}
+
+async fn health_check_handler(
+    Extension(app_context): Extension<AppContext>,
+) -> Result<Json<String>> {
+    db::run_health_check(&app_context.pool).await?;
+    Ok(Json("OK".to_string()))
+}
+
+/// Every dependency needed to serve requests and process bookmarks, checked
+/// concurrently. Answers 503 when any of them is unavailable.
+async fn readiness_handler(Extension(app_context): Extension<AppContext>) -> Readiness {
+    let config = &app_context.config;
+
+    let database = check("database", async {
+        db::run_health_check(&app_context.pool).await?;
+        Ok(())
+    });
+    let data_dir = async {
+        match (&config.storage.storage_backend, &config.data_dir) {
+            (StorageBackendKind::Local, Some(data_dir)) => {
+                check("data_dir", check_data_dir(data_dir)).await
+            }
+            _ => Check::skipped(),
+        }
+    };
+    let uses_ollama = app_context.llm_client.as_ref().is_some_and(|client| {
+        client.text_provider == "ollama" || client.embedding_provider == "ollama"
+    });
+    let ollama = async {
+        if uses_ollama {
+            check("ollama", check_ollama(&llm::ollama_base_url(&config.llm))).await
+        } else {
+            Check::skipped()
+        }
+    };
+    let chrome_client = ChromeClient::new(
+        ChromeConnection::from_params(config.chrome.as_ref(), config.fetch.fetch_proxy.as_ref()),
+        UrlGuard::new(&config.fetch.fetch_allowed_networks),
+    );
+    let chrome = check("chrome", chrome_client.check_available());
+
+    let ai = llm::check_ai_status(&config.llm, false);
+
+    let (database, data_dir, ollama, chrome, ai) =
+        tokio::join!(database, data_dir, ollama, chrome, ai);
+    if let Ok(mut status) = app_context.ai_status.write() {
+        *status = ai.clone();
+    }
+    let checks = Checks {
+        database,
+        data_dir,
+        ollama,
+        chrome,
+    };
+    Readiness {
+        status: checks.status(),
+        checks,
+        ai,
+    }
+}
+
+/// `/health/live` only tells the process is serving, `/health/ready` checks
+/// its dependencies. `/health` keeps checking the database alone.
+pub fn routes() -> Router {
+    Router::new()
+        .route("/health", routing::get(health_check_handler))
+        .route("/health/live", routing::get(liveness_handler))
+        .route("/health/ready", routing::get(readiness_handler))
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::{AiFeatureState, AiFeatureStatus};
+
+    use super::*;
+
+    fn with_status(status: Status) -> Check {
+        Check {
+            status,
+            elapsed_ms: None,
+            error: None,
+        }
+    }
+
+    fn with_feature_state(state: AiFeatureState) -> AiFeatureStatus {
+        AiFeatureStatus {
+            state,
+            provider: None,
+            model: None,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn skipped_dependencies_dont_fail_readiness() {
+        let checks = Checks {
+            database: with_status(Status::Ok),
+            data_dir: with_status(Status::Ok),
+            ollama: with_status(Status::Skipped),
+            chrome: with_status(Status::Skipped),
+        };
+        assert_eq!(checks.status(), Status::Ok);
+    }
+
+    #[test]
+    fn any_unavailable_dependency_fails_readiness() {
+        let checks = Checks {
+            database: with_status(Status::Ok),
+            data_dir: with_status(Status::Ok),
+            ollama: with_status(Status::Unavailable),
+            chrome: with_status(Status::Ok),
+        };
+        assert_eq!(checks.status(), Status::Unavailable);
+        let ai = AiStatus {
+            text: with_feature_state(AiFeatureState::Available),
+            embeddings: with_feature_state(AiFeatureState::Degraded),
+        };
+        let response = Readiness {
+            status: checks.status(),
+            checks,
+            ai,
+        }
+        .into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn data_dir_check_needs_a_writable_directory() {
+        let data_dir = std::env::temp_dir().join(format!("health-{}", Uuid::new_v4()));
+        let missing = check("data_dir", check_data_dir(&data_dir)).await;
+        assert_eq!(missing.status, Status::Unavailable);
+        assert!(missing.error.is_some());
+
+        std::fs::create_dir(&data_dir).unwrap();
+        let writable = check("data_dir", check_data_dir(&data_dir)).await;
+        std::fs::remove_dir(&data_dir).unwrap();
+        assert_eq!(writable.status, Status::Ok);
+    }
+}
@@ -0,0 +1,37 @@
+use axum::extract::Query;
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use axum_macros::debug_handler;
+use chrono::{Days, Utc};
+use shared::{LlmUsageRequest, LlmUsageResponse};
+
+use super::{bounded_limit, Claim};
+use crate::db::llm_usage;
+use crate::error::Result;
+use crate::AppContext;
+
+const DEFAULT_USAGE_DAYS: u32 = 30;
+const MAX_USAGE_DAYS: u32 = 366;
+
+pub fn routes() -> Router {
+    Router::new().route("/usage/llm", get(llm_usage_summary))
+}
+
+#[utoipa::path(
+    get,
+    path = "/usage/llm",
+    tag = "usage",
+    params(LlmUsageRequest),
+    responses((status = 200, body = LlmUsageResponse), (status = 422))
+)]
+#[debug_handler]
+async fn llm_usage_summary(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Query(request): Query<LlmUsageRequest>,
+) -> Result<Json<LlmUsageResponse>> {
+    let days = bounded_limit(request.days, DEFAULT_USAGE_DAYS, MAX_USAGE_DAYS, "days")?;
+    let since = Utc::now().date_naive() - Days::new(u64::from(days - 1));
+    let days = llm_usage::summarize_by_day(&app_context.pool, claims.user_id, since).await?;
+    Ok(Json(LlmUsageResponse { days }))
+}
@@ -1,51 +1,94 @@
+use std::net::{IpAddr, SocketAddr};
+
 use argon2::password_hash::SaltString;
 use argon2::{Argon2, PasswordHash};
-use axum::extract::FromRequestParts;
+use axum::extract::{ConnectInfo, FromRequestParts, Request, State};
 use axum::http::request::Parts;
-use axum::{routing, Extension, Json, RequestPartsExt, Router};
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::{Extension, RequestPartsExt, Router};
 use axum_extra::headers::authorization::Bearer;
 use axum_extra::headers::Authorization;
 use axum_extra::TypedHeader;
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
+use tracing::warn;
 use uuid::Uuid;
 
+use crate::db::user_session;
 use crate::error::{Error, Result};
-use crate::{db, AppContext, Config};
+use crate::{AppContext, Config};
 
+mod activity;
+mod ai_status;
+pub(crate) mod audit;
 mod auth;
 mod bookmark;
 mod bookmark_task;
+mod bookmark_version;
+mod chunk;
+mod collection;
+mod digest;
+mod entity;
+mod events;
+mod extraction_rule;
+mod fetch_credential;
+mod graph;
+mod health;
+mod llm_usage;
+mod openapi;
+mod pinboard;
 mod rag;
+mod rule;
 mod search;
+mod settings;
 mod static_content;
-
+mod storage_usage;
+mod trash;
+mod wallabag;
+mod workspace;
+
+pub use health::routes as health_check;
+pub use openapi::routes as api_docs;
+pub use pinboard::routes as pinboard;
 pub use static_content::routes as static_content;
+pub use wallabag::routes as wallabag;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claim {
     pub sub: String,
     pub exp: i64,
     pub user_id: Uuid,
-}
-
-async fn health_check_handler(
-    Extension(app_context): Extension<AppContext>,
-) -> Result<Json<String>> {
-    db::run_health_check(&app_context.pool).await?;
-    Ok(Json("OK".to_string()))
-}
-
-pub fn health_check() -> Router {
-    Router::new().route("/health", routing::get(health_check_handler))
+    /// Session the token belongs to, tokens issued before sessions existed
+    /// have none and are refused
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<Uuid>,
 }
 
 pub fn routers_v1() -> Router {
     auth::router()
+        .merge(activity::routes())
+        .merge(ai_status::routes())
+        .merge(audit::routes())
         .merge(bookmark::routes())
         .merge(search::routes())
         .merge(bookmark_task::routes())
+        .merge(bookmark_version::routes())
+        .merge(chunk::routes())
+        .merge(collection::routes())
+        .merge(workspace::routes())
+        .merge(digest::routes())
+        .merge(entity::routes())
+        .merge(graph::routes())
+        .merge(events::routes())
+        .merge(settings::routes())
+        .merge(llm_usage::routes())
+        .merge(storage_usage::routes())
+        .merge(trash::routes())
+        .merge(rule::routes())
+        .merge(extraction_rule::routes())
+        .merge(fetch_credential::routes())
         .nest("/rag", rag::routes())
 }
 
@@ -61,18 +104,88 @@ where
                 .await
                 .expect("Bug: AppContext should be added as an Extension");
 
+        // Checked once per request, by the rate limiter or the handler
+        if let Some(claims) = parts.extensions.get::<Claim>() {
+            return Ok(claims.clone());
+        }
+
         let TypedHeader(Authorization(bearer)) = parts
             .extract::<TypedHeader<Authorization<Bearer>>>()
             .await
             .map_err(|_| Error::InvalidToken)?;
+        let ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(client_addr)| client_addr.ip());
+
+        let claims = authenticate(&app_context, bearer.token(), ip).await?;
+        parts.extensions.insert(claims.clone());
+        Ok(claims)
+    }
+}
 
-        let hmac_key = app_context.config.hmac_key.expose_secret();
-        let decoder = DecodingKey::from_secret(hmac_key.as_bytes());
+/// The claims of a valid access token whose session wasn't revoked, marking
+/// the session as seen from `ip`.
+pub(crate) async fn authenticate(
+    app_context: &AppContext,
+    token: &str,
+    ip: Option<IpAddr>,
+) -> Result<Claim> {
+    let hmac_key = app_context.config.hmac_key.expose_secret();
+    let decoder = DecodingKey::from_secret(hmac_key.as_bytes());
+    let claims = decode::<Claim>(token, &decoder, &Validation::default())
+        .map_err(|_| Error::InvalidToken)?
+        .claims;
+    // Tokens without a session can't be revoked. They lived two weeks at
+    // most, so refusing them signs their holders out once instead of keeping
+    // them valid until a guessed date.
+    let Some(session_id) = claims.sid else {
+        return Err(Error::InvalidToken);
+    };
+    if !user_session::touch(&app_context.pool, claims.user_id, session_id, ip).await? {
+        return Err(Error::InvalidToken);
+    }
+    Ok(claims)
+}
 
-        let token_data = decode::<Claim>(bearer.token(), &decoder, &Validation::default())
-            .map_err(|_| Error::InvalidToken)?;
+/// Middleware spending one token from the caller's per-IP bucket, and from the
+/// per-user bucket when the request carries a valid token, for `scope`.
+async fn rate_limit(
+    State(scope): State<&'static str>,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    Extension(app_context): Extension<AppContext>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    let (mut parts, body) = request.into_parts();
+    let user_id = Claim::from_request_parts(&mut parts, &())
+        .await
+        .ok()
+        .map(|claims| claims.user_id);
+    let decision = app_context
+        .rate_limiter
+        .check(scope, client_addr.ip(), user_id);
+    if !decision.allowed {
+        warn!(
+            scope,
+            client_ip = %client_addr.ip(),
+            user_id = ?user_id,
+            "Request rejected by rate limiter"
+        );
+        return Err(Error::too_many_requests(decision.retry_after_secs));
+    }
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
 
-        Ok(token_data.claims)
+/// `value`, or `default` when the request leaves it out, rejected on `field`
+/// unless between 1 and `max`.
+fn bounded_limit(value: Option<u32>, default: u32, max: u32, field: &'static str) -> Result<u32> {
+    match value.unwrap_or(default) {
+        value if (1..=max).contains(&value) => Ok(value),
+        _ => Err(Error::unprocessable_entity([(
+            field,
+            format!("must be between 1 and {max}"),
+        )])),
     }
 }
 
@@ -111,3 +224,26 @@ async fn verify_password(password: SecretString, password_hash: String) -> Resul
     .await
     .map_err(|error| Error::argon2(error.to_string()))?
 }
+
+#[cfg(test)]
+mod tests {
+    use super::bounded_limit;
+
+    #[test]
+    fn bounded_limits_default_and_reject_out_of_range() {
+        let cases = [
+            (None, Some(5)),
+            (Some(1), Some(1)),
+            (Some(50), Some(50)),
+            (Some(0), None),
+            (Some(51), None),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(
+                bounded_limit(value, 5, 50, "limit").ok(),
+                expected,
+                "{value:?}"
+            );
+        }
+    }
+}
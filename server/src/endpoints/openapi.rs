@@ -0,0 +1,188 @@
+use axum::Router;
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
+
+use super::{
+    activity, ai_status, audit, auth, bookmark, bookmark_task, bookmark_version, chunk, collection,
+    digest, entity, events, extraction_rule, fetch_credential, graph, llm_usage, rag, rule, search,
+    settings, storage_usage, trash, workspace,
+};
+
+/// `security` in [`ApiDoc`] repeats it as a literal.
+const SECURITY_SCHEME: &str = "jwt";
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "bookmark-hub"),
+    servers((url = "/api/v1")),
+    security(("jwt" = [])),
+    modifiers(&BearerAuth),
+    // Body of every error response
+    components(schemas(shared::Problem)),
+    paths(
+        auth::get_user_profile,
+        auth::sign_up,
+        auth::sign_in,
+        auth::change_password,
+        auth::change_email,
+        auth::get_api_token,
+        auth::verify_email,
+        auth::request_password_reset,
+        auth::confirm_password_reset,
+        auth::delete_account,
+        auth::list_sessions,
+        auth::revoke_session,
+        audit::get_audit_log,
+        activity::get_activity,
+        bookmark::get_bookmarks,
+        bookmark::get_all_tags,
+        bookmark::get_bookmarks_by_tag,
+        bookmark::rename_tag,
+        bookmark::get_random_bookmark,
+        bookmark::get_on_this_day,
+        bookmark::get_bookmark,
+        bookmark::update_bookmark,
+        bookmark::delete_bookmark,
+        bookmark::mark_read,
+        bookmark::new_bookmark,
+        bookmark::new_bookmarks,
+        bookmark::set_tags,
+        bookmark::append_tags,
+        bookmark::set_favorite,
+        bookmark::unset_favorite,
+        bookmark::set_visibility,
+        bookmark::bulk_update_bookmarks,
+        bookmark::set_summary,
+        bookmark::regenerate_summary,
+        bookmark::get_related_bookmarks,
+        bookmark::ask_bookmark,
+        bookmark::get_tag_suggestions,
+        bookmark::get_bookmark_links,
+        bookmark::refresh_bookmark,
+        bookmark::reprocess_bookmark,
+        bookmark_version::list_versions,
+        bookmark_version::get_version,
+        bookmark_version::diff_versions,
+        chunk::list_chunks,
+        chunk::get_chunk,
+        trash::get_trash,
+        trash::restore_bookmark,
+        trash::purge_bookmark,
+        bookmark::share_bookmark,
+        bookmark::get_bookmark_markdown,
+        bookmark_task::search_tasks,
+        bookmark_task::retry_task,
+        bookmark_task::cancel_task,
+        bookmark_task::dead_letter,
+        bookmark_task::retry_tasks,
+        bookmark_task::task_events,
+        collection::list_collections,
+        collection::create_collection,
+        collection::get_collection,
+        collection::update_collection,
+        collection::delete_collection,
+        collection::share_collection,
+        collection::get_shared_collection,
+        workspace::list_workspaces,
+        workspace::create_workspace,
+        workspace::get_workspace,
+        workspace::delete_workspace,
+        workspace::list_members,
+        workspace::set_member,
+        workspace::remove_member,
+        workspace::add_bookmark,
+        workspace::get_bookmark,
+        workspace::remove_bookmark,
+        digest::list_digests,
+        digest::get_digest,
+        digest::digest_feed,
+        digest::issue_feed_token,
+        digest::shared_digest_feed,
+        entity::list_entities,
+        graph::get_graph,
+        events::stream_events,
+        events::stream_task_events,
+        llm_usage::llm_usage_summary,
+        storage_usage::storage_usage,
+        ai_status::ai_status,
+        rag::rag_query,
+        rag::rag_query_stream,
+        rag::rag_history,
+        rag::delete_rag_session,
+        rag::rag_feedback,
+        rag::rag_quality,
+        rule::list_rules,
+        rule::create_rule,
+        rule::get_rule,
+        rule::update_rule,
+        rule::delete_rule,
+        extraction_rule::list_extraction_rules,
+        extraction_rule::create_extraction_rule,
+        extraction_rule::get_extraction_rule,
+        extraction_rule::update_extraction_rule,
+        extraction_rule::delete_extraction_rule,
+        fetch_credential::list_fetch_credentials,
+        fetch_credential::replace_fetch_credentials,
+        search::search_bookmark,
+        search::suggest_search,
+        settings::get_settings,
+        settings::update_settings,
+    )
+)]
+struct ApiDoc;
+
+/// Every endpoint but sign-up, sign-in, the email flows and shared
+/// collections expects the JWT returned by sign-in as a bearer token.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            SECURITY_SCHEME,
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+        );
+    }
+}
+
+/// `/api/v1/openapi.json` and the Swagger UI browsing it at `/api/v1/docs`.
+pub fn routes() -> Router {
+    SwaggerUi::new("/api/v1/docs")
+        .url("/api/v1/openapi.json", ApiDoc::openapi())
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use utoipa::OpenApi;
+
+    use super::ApiDoc;
+    use crate::endpoints::{api_docs, routers_v1};
+
+    #[test]
+    fn spec_covers_the_api() {
+        let spec = ApiDoc::openapi();
+        for path in [
+            "/auth/sign-in",
+            "/bookmarks",
+            "/bookmarks/{id}/tags",
+            "/tasks/{id}/events",
+            "/rag/query/stream",
+            "/search",
+        ] {
+            assert!(spec.paths.paths.contains_key(path), "{path} missing");
+        }
+        let schemas = spec.components.expect("components").schemas;
+        for schema in ["Bookmark", "SearchRequest", "SignInRequest", "TaskStage"] {
+            assert!(schemas.contains_key(schema), "{schema} schema missing");
+        }
+    }
+
+    #[test]
+    fn docs_routes_dont_clash_with_the_api() {
+        let _ = axum::Router::new()
+            .nest("/api/v1", routers_v1())
+            .merge(api_docs());
+    }
+}
@@ -0,0 +1,550 @@
+//! The core of the [Pinboard v1 API](https://pinboard.in/api) over the
+//! bookmarks of a user, so the apps and browser extensions speaking it can use
+//! this server. Mounted at `/pinboard/v1`, requests authenticate with
+//! `auth_token=username:token` from `GET /api/v1/auth/api-token`. Replies are
+//! XML unless `format=json` is asked for, like Pinboard's.
+
+use std::collections::HashSet;
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, FromRequestParts, Query};
+use axum::http::header;
+use axum::http::request::Parts;
+use axum::middleware::from_fn_with_state;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Extension, Router};
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use shared::{Bookmark, TagOperation};
+use tracing::{debug, info};
+use url::Url;
+use uuid::Uuid;
+
+use super::{audit, auth, bookmark as bookmark_endpoints};
+use crate::db::{bookmark, user};
+use crate::error::{Error, Result};
+use crate::AppContext;
+
+const DEFAULT_RECENT_COUNT: usize = 15;
+const MAX_RECENT_COUNT: usize = 100;
+/// Pinboard filters on at most three tags.
+const MAX_FILTER_TAGS: usize = 3;
+/// Target of the failed sign-ins through this API in the audit log.
+const AUDIT_CLIENT: &str = "pinboard";
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/posts/update", get(posts_update))
+        .route(
+            "/posts/add",
+            get(posts_add).route_layer(from_fn_with_state("bookmarks", super::rate_limit)),
+        )
+        .route("/posts/delete", get(posts_delete))
+        .route("/posts/get", get(posts_get))
+        .route("/posts/recent", get(posts_recent))
+        .route("/posts/all", get(posts_all))
+        .route("/tags/get", get(tags_get))
+        .route("/user/api_token", get(user_api_token))
+}
+
+/// The user of a valid `auth_token` query parameter.
+struct PinboardUser {
+    user_id: Uuid,
+    username: String,
+    json: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct AuthQuery {
+    auth_token: Option<String>,
+    format: Option<String>,
+}
+
+impl<S> FromRequestParts<S> for PinboardUser
+where
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Extension(app_context): Extension<AppContext> =
+            Extension::from_request_parts(parts, state)
+                .await
+                .expect("Bug: AppContext should be added as an Extension");
+        let Query(query) = Query::<AuthQuery>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| Error::InvalidToken)?;
+        let (username, token) = query
+            .auth_token
+            .as_deref()
+            .and_then(|auth_token| auth_token.rsplit_once(':'))
+            .ok_or(Error::InvalidToken)?;
+        let username = user::normalize_username(username);
+        let user = user::get_by_username(&app_context.pool, username.clone()).await?;
+        let Some(user) = user
+            .as_ref()
+            .filter(|user| auth::is_valid_api_token(&app_context.config.hmac_key, user, token))
+        else {
+            let ip = parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(client_addr)| client_addr.ip());
+            audit::record_failed_sign_in(
+                &app_context.pool,
+                user.map(|user| user.user_id),
+                &username,
+                Some(AUDIT_CLIENT),
+                ip,
+            )
+            .await;
+            return Err(Error::InvalidToken);
+        };
+        Ok(PinboardUser {
+            user_id: user.user_id,
+            username: user.username.clone(),
+            json: query.format.as_deref() == Some("json"),
+        })
+    }
+}
+
+/// A bookmark as Pinboard describes posts.
+#[derive(Debug, PartialEq, Serialize)]
+struct Post {
+    href: String,
+    description: String,
+    extended: String,
+    /// Changes whenever the bookmark does
+    meta: String,
+    hash: String,
+    time: String,
+    shared: &'static str,
+    toread: &'static str,
+    tags: String,
+}
+
+impl From<&Bookmark> for Post {
+    fn from(bookmark: &Bookmark) -> Self {
+        let changed_at = bookmark.updated_at.unwrap_or(bookmark.created_at);
+        Post {
+            href: bookmark.url.clone(),
+            description: bookmark.title.clone(),
+            extended: bookmark.summary.clone().unwrap_or_default(),
+            meta: format!("{:x}", changed_at.timestamp_micros()),
+            hash: bookmark.bookmark_id.clone(),
+            time: pinboard_time(bookmark.created_at),
+            shared: "no",
+            toread: "no",
+            tags: bookmark.tags.as_deref().unwrap_or_default().join(" "),
+        }
+    }
+}
+
+impl Post {
+    fn xml(&self) -> String {
+        format!(
+            r#"<post href="{}" description="{}" extended="{}" meta="{}" hash="{}" time="{}" shared="{}" toread="{}" tag="{}" />"#,
+            escape_xml(&self.href),
+            escape_xml(&self.description),
+            escape_xml(&self.extended),
+            self.meta,
+            escape_xml(&self.hash),
+            self.time,
+            self.shared,
+            self.toread,
+            escape_xml(&self.tags),
+        )
+    }
+}
+
+/// Every reply shape of the endpoints, rendered as XML or JSON.
+#[derive(Debug, PartialEq)]
+enum Reply {
+    /// `done`, or what went wrong
+    ResultCode(&'static str),
+    UpdateTime(DateTime<Utc>),
+    /// Posts of a day, or the most recent ones
+    Posts {
+        date: DateTime<Utc>,
+        user: String,
+        posts: Vec<Post>,
+    },
+    AllPosts {
+        user: String,
+        posts: Vec<Post>,
+    },
+    Tags(Vec<(String, i64)>),
+    ApiToken(String),
+}
+
+impl Reply {
+    fn render(self, json: bool) -> Response {
+        if json {
+            let body = match self {
+                Reply::ResultCode(code) => serde_json::json!({ "result_code": code }),
+                Reply::UpdateTime(time) => {
+                    serde_json::json!({ "update_time": pinboard_time(time) })
+                }
+                Reply::Posts { date, user, posts } => {
+                    serde_json::json!({ "date": pinboard_time(date), "user": user, "posts": posts })
+                }
+                Reply::AllPosts { posts, .. } => serde_json::json!(posts),
+                Reply::Tags(tags) => serde_json::Value::Object(
+                    tags.into_iter()
+                        .map(|(tag, count)| (tag, count.into()))
+                        .collect(),
+                ),
+                Reply::ApiToken(token) => serde_json::json!({ "result": token }),
+            };
+            return (
+                [(header::CONTENT_TYPE, "application/json")],
+                body.to_string(),
+            )
+                .into_response();
+        }
+
+        let body = match self {
+            Reply::ResultCode(code) => format!(r#"<result code="{code}" />"#),
+            Reply::UpdateTime(time) => format!(r#"<update time="{}" />"#, pinboard_time(time)),
+            Reply::Posts { date, user, posts } => format!(
+                r#"<posts dt="{}" user="{}">{}</posts>"#,
+                pinboard_time(date),
+                escape_xml(&user),
+                posts.iter().map(Post::xml).collect::<String>()
+            ),
+            Reply::AllPosts { user, posts } => format!(
+                r#"<posts user="{}">{}</posts>"#,
+                escape_xml(&user),
+                posts.iter().map(Post::xml).collect::<String>()
+            ),
+            Reply::Tags(tags) => format!(
+                "<tags>{}</tags>",
+                tags.iter()
+                    .map(|(tag, count)| format!(
+                        r#"<tag count="{count}" tag="{}" />"#,
+                        escape_xml(tag)
+                    ))
+                    .collect::<String>()
+            ),
+            Reply::ApiToken(token) => format!("<result>{}</result>", escape_xml(&token)),
+        };
+        (
+            [(header::CONTENT_TYPE, "text/xml; charset=utf-8")],
+            format!("<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n{body}"),
+        )
+            .into_response()
+    }
+}
+
+fn pinboard_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Pinboard separates tags with spaces, some clients with commas.
+fn parse_tags(tags: Option<&str>) -> Vec<String> {
+    tags.unwrap_or_default()
+        .split([' ', ','])
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+fn has_tags(bookmark: &Bookmark, tags: &[String]) -> bool {
+    let bookmark_tags: HashSet<&str> = bookmark.tags.iter().flatten().map(String::as_str).collect();
+    tags.iter().all(|tag| bookmark_tags.contains(tag.as_str()))
+}
+
+/// Newest first, as Pinboard lists posts.
+async fn bookmarks_with_tags(
+    app_context: &AppContext,
+    user_id: Uuid,
+    tag: Option<&str>,
+) -> Result<Vec<Bookmark>> {
+    let tags: Vec<_> = parse_tags(tag).into_iter().take(MAX_FILTER_TAGS).collect();
+    let mut bookmarks = bookmark::get_by_user(&app_context.pool, user_id).await?;
+    bookmarks.retain(|bookmark| has_tags(bookmark, &tags));
+    bookmarks.reverse();
+    Ok(bookmarks)
+}
+
+async fn find_by_url(
+    app_context: &AppContext,
+    user_id: Uuid,
+    url: &str,
+) -> Result<Option<Bookmark>> {
+    if Url::parse(url).is_err() {
+        return Ok(None);
+    }
+    bookmark::get_by_canonical_url_and_user_id(&app_context.pool, url, user_id).await
+}
+
+async fn posts_update(
+    user: PinboardUser,
+    Extension(app_context): Extension<AppContext>,
+) -> Result<Response> {
+    let bookmarks = bookmark::get_by_user(&app_context.pool, user.user_id).await?;
+    let update_time = bookmarks
+        .iter()
+        .map(|bookmark| bookmark.updated_at.unwrap_or(bookmark.created_at))
+        .max()
+        .unwrap_or_default();
+    Ok(Reply::UpdateTime(update_time).render(user.json))
+}
+
+#[derive(Debug, Deserialize)]
+struct PostsAddQuery {
+    url: Option<String>,
+    tags: Option<String>,
+    replace: Option<String>,
+}
+
+/// The title and description come from the page once it is captured, those
+/// sent are ignored.
+async fn posts_add(
+    user: PinboardUser,
+    Extension(app_context): Extension<AppContext>,
+    Query(query): Query<PostsAddQuery>,
+) -> Result<Response> {
+    let Some(url) = query.url.as_deref().and_then(|url| Url::parse(url).ok()) else {
+        return Ok(Reply::ResultCode("missing url").render(user.json));
+    };
+    let tags = parse_tags(query.tags.as_deref());
+    let existing = find_by_url(&app_context, user.user_id, url.as_str()).await?;
+    if let Some(existing) = existing {
+        if query.replace.as_deref() == Some("no") {
+            return Ok(Reply::ResultCode("item already exists").render(user.json));
+        }
+        bookmark::update_tags(
+            &app_context.pool,
+            user.user_id,
+            &existing.bookmark_id,
+            &TagOperation::Set(tags),
+        )
+        .await?;
+        info!(user_id = %user.user_id, bookmark_id = %existing.bookmark_id, "Pinboard post replaced");
+        return Ok(Reply::ResultCode("done").render(user.json));
+    }
+    let task = bookmark_endpoints::enqueue(&app_context, user.user_id, url, tags, false).await?;
+    info!(user_id = %user.user_id, task_id = %task.task_id, "Pinboard post added");
+    Ok(Reply::ResultCode("done").render(user.json))
+}
+
+#[derive(Debug, Deserialize)]
+struct PostsDeleteQuery {
+    url: Option<String>,
+}
+
+async fn posts_delete(
+    user: PinboardUser,
+    Extension(app_context): Extension<AppContext>,
+    Query(query): Query<PostsDeleteQuery>,
+) -> Result<Response> {
+    let existing = match query.url.as_deref() {
+        Some(url) => find_by_url(&app_context, user.user_id, url).await?,
+        None => None,
+    };
+    let Some(existing) = existing else {
+        return Ok(Reply::ResultCode("item not found").render(user.json));
+    };
+    bookmark_endpoints::remove(&app_context, user.user_id, &existing.bookmark_id, None).await?;
+    Ok(Reply::ResultCode("done").render(user.json))
+}
+
+#[derive(Debug, Deserialize)]
+struct PostsGetQuery {
+    tag: Option<String>,
+    dt: Option<NaiveDate>,
+    url: Option<String>,
+}
+
+/// Posts of the day `dt`, the most recent day with posts by default.
+async fn posts_get(
+    user: PinboardUser,
+    Extension(app_context): Extension<AppContext>,
+    Query(query): Query<PostsGetQuery>,
+) -> Result<Response> {
+    let bookmarks = match query.url.as_deref() {
+        Some(url) => find_by_url(&app_context, user.user_id, url)
+            .await?
+            .into_iter()
+            .collect(),
+        None => bookmarks_with_tags(&app_context, user.user_id, query.tag.as_deref()).await?,
+    };
+    let day = query.dt.or_else(|| {
+        bookmarks
+            .first()
+            .map(|bookmark| bookmark.created_at.date_naive())
+    });
+    let posts = bookmarks
+        .iter()
+        .filter(|bookmark| query.url.is_some() || Some(bookmark.created_at.date_naive()) == day)
+        .map(Post::from)
+        .collect();
+    let date = day
+        .and_then(|day| day.and_hms_opt(0, 0, 0))
+        .map(|date| date.and_utc())
+        .unwrap_or_else(Utc::now);
+    Ok(Reply::Posts {
+        date,
+        user: user.username,
+        posts,
+    }
+    .render(user.json))
+}
+
+#[derive(Debug, Deserialize)]
+struct PostsRecentQuery {
+    tag: Option<String>,
+    count: Option<usize>,
+}
+
+async fn posts_recent(
+    user: PinboardUser,
+    Extension(app_context): Extension<AppContext>,
+    Query(query): Query<PostsRecentQuery>,
+) -> Result<Response> {
+    let count = query
+        .count
+        .unwrap_or(DEFAULT_RECENT_COUNT)
+        .min(MAX_RECENT_COUNT);
+    let bookmarks = bookmarks_with_tags(&app_context, user.user_id, query.tag.as_deref()).await?;
+    let date = bookmarks
+        .first()
+        .map_or_else(Utc::now, |bookmark| bookmark.created_at);
+    Ok(Reply::Posts {
+        date,
+        user: user.username,
+        posts: bookmarks.iter().take(count).map(Post::from).collect(),
+    }
+    .render(user.json))
+}
+
+#[derive(Debug, Deserialize)]
+struct PostsAllQuery {
+    tag: Option<String>,
+    start: Option<usize>,
+    results: Option<usize>,
+    fromdt: Option<DateTime<Utc>>,
+    todt: Option<DateTime<Utc>>,
+}
+
+async fn posts_all(
+    user: PinboardUser,
+    Extension(app_context): Extension<AppContext>,
+    Query(query): Query<PostsAllQuery>,
+) -> Result<Response> {
+    let bookmarks = bookmarks_with_tags(&app_context, user.user_id, query.tag.as_deref()).await?;
+    let posts: Vec<_> = bookmarks
+        .iter()
+        .filter(|bookmark| query.fromdt.is_none_or(|from| bookmark.created_at >= from))
+        .filter(|bookmark| query.todt.is_none_or(|to| bookmark.created_at <= to))
+        .skip(query.start.unwrap_or_default())
+        .take(query.results.unwrap_or(usize::MAX))
+        .map(Post::from)
+        .collect();
+    debug!(user_id = %user.user_id, post_count = posts.len(), "Listing Pinboard posts");
+    Ok(Reply::AllPosts {
+        user: user.username,
+        posts,
+    }
+    .render(user.json))
+}
+
+async fn tags_get(
+    user: PinboardUser,
+    Extension(app_context): Extension<AppContext>,
+) -> Result<Response> {
+    let mut tags = bookmark::get_tag_count_by_user(&app_context.pool, user.user_id).await?;
+    tags.sort();
+    Ok(Reply::Tags(tags).render(user.json))
+}
+
+async fn user_api_token(
+    user: PinboardUser,
+    Extension(app_context): Extension<AppContext>,
+) -> Result<Response> {
+    let user_record = user::get_by_id(&app_context.pool, &user.user_id)
+        .await?
+        .ok_or(Error::InvalidToken)?;
+    let token = auth::api_token(&app_context.config.hmac_key, &user_record);
+    Ok(Reply::ApiToken(token).render(user.json))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use shared::{Bookmark, Visibility};
+    use uuid::Uuid;
+
+    use super::{escape_xml, has_tags, parse_tags, Post};
+
+    fn bookmark(tags: &[&str]) -> Bookmark {
+        Bookmark {
+            bookmark_id: "abc".to_string(),
+            user_id: Uuid::nil(),
+            url: "https://example.com/?a=1&b=2".to_string(),
+            domain: "example.com".to_string(),
+            title: "Rust <3".to_string(),
+            tags: Some(tags.iter().map(ToString::to_string).collect()),
+            summary: None,
+            created_at: Utc.with_ymd_and_hms(2024, 5, 1, 12, 30, 0).unwrap(),
+            updated_at: None,
+            word_count: 0,
+            reading_time_minutes: 0,
+            kind: None,
+            favorite: false,
+            original_title: None,
+            visibility: Visibility::Private,
+        }
+    }
+
+    #[test]
+    fn tags_split_on_spaces_and_commas() {
+        assert_eq!(
+            parse_tags(Some("Rust, web  async")),
+            vec!["rust", "web", "async"]
+        );
+        assert!(parse_tags(None).is_empty());
+    }
+
+    #[test]
+    fn tag_filters_need_every_tag() {
+        let bookmark = bookmark(&["rust", "web"]);
+        assert!(has_tags(&bookmark, &[]));
+        assert!(has_tags(&bookmark, &["rust".to_string()]));
+        assert!(!has_tags(
+            &bookmark,
+            &["rust".to_string(), "async".to_string()]
+        ));
+    }
+
+    #[test]
+    fn posts_render_as_escaped_xml() {
+        assert_eq!(
+            escape_xml(r#"<a href="x">'&'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&apos;&amp;&apos;&lt;/a&gt;"
+        );
+        let xml = Post::from(&bookmark(&["rust", "web"])).xml();
+        assert!(
+            xml.starts_with(r#"<post href="https://example.com/?a=1&amp;b=2" description="Rust &lt;3" extended="""#),
+            "{xml}"
+        );
+        assert!(xml.contains(r#"time="2024-05-01T12:30:00Z""#), "{xml}");
+        assert!(xml.ends_with(r#"tag="rust web" />"#), "{xml}");
+    }
+}
@@ -1,26 +1,47 @@
-use axum::routing::post;
+use std::convert::Infallible;
+
+use axum::extract::{Path, Query};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::routing::{delete, get, post};
 use axum::{Extension, Json, Router};
 use axum_macros::debug_handler;
+use chrono::{Days, Utc};
+use futures::Stream;
 use shared::{
-    HybridSearchConfig, RagHistoryRequest, RagHistoryResponse, RagQueryRequest, RagQueryResponse,
+    HybridSearchConfig, RagFeedbackRequest, RagHistoryRequest, RagHistoryResponse,
+    RagQualityReport, RagQualityRequest, RagQueryRequest, RagQueryResponse, RagSession,
+    RagStreamEvent, TagFilter,
 };
-use tracing::{info, warn};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
 
-use super::Claim;
-use crate::db::rag::get_rag_history;
+use super::{bounded_limit, workspace, Claim};
+use crate::db::rag::{self, get_rag_history};
 use crate::error::{Error, Result};
+use crate::llm::LlmClient;
 use crate::rag::RagEngine;
 use crate::AppContext;
 
 pub fn routes() -> Router {
     Router::new()
         .route("/query", post(rag_query))
+        .route("/query/stream", post(rag_query_stream))
         .route("/history", post(rag_history))
+        .route("/sessions/{session_id}", delete(delete_rag_session))
+        .route("/sessions/{session_id}/feedback", post(rag_feedback))
+        .route("/quality", get(rag_quality))
 }
 
-fn validate_weighted_hybrid_config(config: &HybridSearchConfig) -> Result<()> {
-    let vector_weight = config.vector_weight.unwrap_or(0.5);
-    let fts_weight = config.fts_weight.unwrap_or(0.5);
+const MAX_FEEDBACK_COMMENT_CHARS: usize = 2000;
+const DEFAULT_QUALITY_DAYS: u32 = 30;
+const MAX_QUALITY_DAYS: u32 = 366;
+const QUALITY_REPORT_COMMENTS: i64 = 20;
+
+fn validate_hybrid_weights(config: &HybridSearchConfig) -> Result<()> {
+    let vector_weight = config.vector_weight.unwrap_or(1.0);
+    let fts_weight = config.fts_weight.unwrap_or(1.0);
     let mut errors = Vec::new();
 
     for (field, value) in [("vector_weight", vector_weight), ("fts_weight", fts_weight)] {
@@ -45,23 +66,80 @@ fn validate_weighted_hybrid_config(config: &HybridSearchConfig) -> Result<()> {
     }
 }
 
+fn normalize_rag_query_request(request: &mut RagQueryRequest) {
+    if let Some(TagFilter::And(tags) | TagFilter::Or(tags)) = &mut request.tags_filter {
+        for tag in tags.iter_mut() {
+            *tag = tag.to_lowercase();
+        }
+    }
+}
+
 fn validate_rag_query_request(request: &RagQueryRequest) -> Result<()> {
+    if request.bookmark_ids.as_ref().is_some_and(Vec::is_empty) {
+        return Err(Error::unprocessable_entity([(
+            "bookmark_ids",
+            "must not be empty when provided",
+        )]));
+    }
     let Some(config) = request.hybrid_search.as_ref() else {
         return Ok(());
     };
-    if !config.enabled || config.use_rrf.unwrap_or(true) {
+    if !config.enabled {
         return Ok(());
     }
-    validate_weighted_hybrid_config(config)
+    validate_hybrid_weights(config)
+}
+
+/// Trims the comment, dropping it when blank.
+fn normalize_feedback_request(request: &mut RagFeedbackRequest) -> Result<()> {
+    request.comment = request
+        .comment
+        .as_deref()
+        .map(str::trim)
+        .filter(|comment| !comment.is_empty())
+        .map(str::to_string);
+    if request
+        .comment
+        .as_ref()
+        .is_some_and(|comment| comment.chars().count() > MAX_FEEDBACK_COMMENT_CHARS)
+    {
+        return Err(Error::unprocessable_entity([(
+            "comment",
+            "must be at most 2000 characters",
+        )]));
+    }
+    Ok(())
 }
 
+pub(super) fn require_llm_client(app_context: &AppContext) -> Result<LlmClient> {
+    match &app_context.llm_client {
+        Some(client) => Ok(client.clone()),
+        None => {
+            warn!("RAG query attempted but LLM is not configured");
+            Err(Error::bad_request([(
+                "llm",
+                "AI features are not available. LLM provider is not configured.",
+            )]))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/rag/query",
+    tag = "rag",
+    request_body = RagQueryRequest,
+    responses((status = 200, body = RagQueryResponse))
+)]
 #[debug_handler]
 async fn rag_query(
     claims: Claim,
     Extension(app_context): Extension<AppContext>,
-    Json(request): Json<RagQueryRequest>,
+    Json(mut request): Json<RagQueryRequest>,
 ) -> Result<Json<RagQueryResponse>> {
+    normalize_rag_query_request(&mut request);
     validate_rag_query_request(&request)?;
+    workspace::require_member(&app_context, claims.user_id, request.workspace).await?;
     info!(
         user_id = %claims.user_id,
         question = %request.question,
@@ -69,19 +147,15 @@ async fn rag_query(
     );
 
     // Check if LLM is configured
-    let llm_client = match &app_context.llm_client {
-        Some(client) => client.clone(),
-        None => {
-            warn!("RAG query attempted but LLM is not configured");
-            return Err(Error::bad_request([(
-                "llm",
-                "AI features are not available. LLM provider is not configured.",
-            )]));
-        }
-    };
+    let llm_client = require_llm_client(&app_context)?;
 
     // Create RAG engine
-    let rag_engine = RagEngine::new(app_context.pool.clone(), llm_client);
+    let rag_engine = RagEngine::new(
+        app_context.pool.clone(),
+        llm_client,
+        app_context.config.llm.rag_relevance_concurrency,
+        app_context.config.llm.rag_hnsw_ef_search,
+    );
 
     // Process the query
     match rag_engine.process_query(claims.user_id, &request).await {
@@ -109,6 +183,81 @@ async fn rag_query(
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/rag/query/stream",
+    tag = "rag",
+    request_body = RagQueryRequest,
+    responses((status = 200, content_type = "text/event-stream", body = RagStreamEvent))
+)]
+#[debug_handler]
+async fn rag_query_stream(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Json(mut request): Json<RagQueryRequest>,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, Infallible>>>> {
+    normalize_rag_query_request(&mut request);
+    validate_rag_query_request(&request)?;
+    workspace::require_member(&app_context, claims.user_id, request.workspace).await?;
+    info!(
+        user_id = %claims.user_id,
+        question = %request.question,
+        "Streaming RAG query received"
+    );
+    let llm_client = require_llm_client(&app_context)?;
+    let rag_engine = RagEngine::new(
+        app_context.pool.clone(),
+        llm_client,
+        app_context.config.llm.rag_relevance_concurrency,
+        app_context.config.llm.rag_hnsw_ef_search,
+    );
+
+    // The pipeline runs detached so it still records the session if the
+    // client disconnects halfway through the answer.
+    let (tx, rx) = mpsc::unbounded_channel();
+    let user_id = claims.user_id;
+    tokio::spawn(async move {
+        let event = match rag_engine
+            .process_query_streaming(user_id, &request, &tx)
+            .await
+        {
+            Ok(response) => {
+                info!(
+                    user_id = %user_id,
+                    session_id = %response.session_id,
+                    relevant_chunks = response.relevant_chunks.len(),
+                    "Streaming RAG query processed successfully"
+                );
+                RagStreamEvent::Completed { response }
+            }
+            Err(error) => {
+                warn!(user_id = %user_id, ?error, "Streaming RAG query processing failed");
+                RagStreamEvent::Failed {
+                    message: format!("Failed to process RAG query: {error}"),
+                }
+            }
+        };
+        let _ = tx.send(event);
+    });
+
+    let stream = futures::stream::unfold(rx, |mut rx| async move {
+        let event = rx.recv().await?;
+        let event = Event::default().json_data(&event).unwrap_or_else(|error| {
+            error!(?error, "Failed to encode RAG stream event");
+            Event::default().comment("encoding error")
+        });
+        Some((Ok(event), rx))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rag/history",
+    tag = "rag",
+    request_body = RagHistoryRequest,
+    responses((status = 200, body = RagHistoryResponse))
+)]
 #[debug_handler]
 async fn rag_history(
     claims: Claim,
@@ -119,6 +268,7 @@ async fn rag_history(
         user_id = %claims.user_id,
         limit = request.limit,
         offset = request.offset,
+        query = request.query.as_deref(),
         "RAG history request received"
     );
 
@@ -143,11 +293,85 @@ async fn rag_history(
     }
 }
 
+#[utoipa::path(
+    delete,
+    path = "/rag/sessions/{session_id}",
+    tag = "rag",
+    params(("session_id" = Uuid, Path)),
+    responses((status = 204, description = "Session deleted"), (status = 404))
+)]
+#[debug_handler]
+async fn delete_rag_session(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(session_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    if !rag::delete_rag_session(&app_context.pool, session_id, claims.user_id).await? {
+        return Err(Error::NotFound);
+    }
+    info!(user_id = %claims.user_id, session_id = %session_id, "RAG session deleted");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/rag/sessions/{session_id}/feedback",
+    tag = "rag",
+    params(("session_id" = Uuid, Path)),
+    request_body = RagFeedbackRequest,
+    responses((status = 200, body = RagSession), (status = 404), (status = 422))
+)]
+#[debug_handler]
+async fn rag_feedback(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(session_id): Path<Uuid>,
+    Json(mut request): Json<RagFeedbackRequest>,
+) -> Result<Json<RagSession>> {
+    normalize_feedback_request(&mut request)?;
+    let session = rag::set_feedback(&app_context.pool, session_id, claims.user_id, &request)
+        .await?
+        .ok_or(Error::NotFound)?;
+    info!(
+        user_id = %claims.user_id,
+        session_id = %session_id,
+        rating = request.rating.as_ref(),
+        commented = request.comment.is_some(),
+        "RAG answer rated"
+    );
+    Ok(Json(session))
+}
+
+#[utoipa::path(
+    get,
+    path = "/rag/quality",
+    tag = "rag",
+    params(RagQualityRequest),
+    responses((status = 200, body = RagQualityReport), (status = 422))
+)]
+#[debug_handler]
+async fn rag_quality(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Query(request): Query<RagQualityRequest>,
+) -> Result<Json<RagQualityReport>> {
+    let days = bounded_limit(request.days, DEFAULT_QUALITY_DAYS, MAX_QUALITY_DAYS, "days")?;
+    let since = Utc::now().date_naive() - Days::new(u64::from(days - 1));
+    let report = rag::quality_report(
+        &app_context.pool,
+        claims.user_id,
+        since,
+        QUALITY_REPORT_COMMENTS,
+    )
+    .await?;
+    Ok(Json(report))
+}
+
 #[cfg(test)]
 mod tests {
-    use shared::{HybridSearchConfig, RagQueryRequest};
+    use shared::{HybridSearchConfig, RagFeedbackRequest, RagQueryRequest, RagRating};
 
-    use super::validate_rag_query_request;
+    use super::{normalize_feedback_request, validate_rag_query_request};
 
     #[test]
     fn weighted_hybrid_rejects_zero_total_weight() {
@@ -163,6 +387,11 @@ mod tests {
                 vector_weight: Some(0.0),
                 fts_weight: Some(0.0),
             }),
+            tags_filter: None,
+            bookmark_ids: None,
+            workspace: None,
+            history: Vec::new(),
+            rerank: false,
         };
 
         assert!(validate_rag_query_request(&request).is_err());
@@ -182,6 +411,11 @@ mod tests {
                 vector_weight: Some(-0.1),
                 fts_weight: Some(1.0),
             }),
+            tags_filter: None,
+            bookmark_ids: None,
+            workspace: None,
+            history: Vec::new(),
+            rerank: false,
         };
 
         assert!(validate_rag_query_request(&request).is_err());
@@ -201,8 +435,72 @@ mod tests {
                 vector_weight: Some(0.2),
                 fts_weight: Some(0.8),
             }),
+            tags_filter: None,
+            bookmark_ids: None,
+            workspace: None,
+            history: Vec::new(),
+            rerank: false,
         };
 
         assert!(validate_rag_query_request(&request).is_ok());
     }
+
+    #[test]
+    fn rrf_hybrid_rejects_negative_weight() {
+        let request = RagQueryRequest {
+            question: "test".into(),
+            max_chunks: None,
+            similarity_threshold: None,
+            max_context_tokens: None,
+            hybrid_search: Some(HybridSearchConfig {
+                enabled: true,
+                use_rrf: Some(true),
+                rrf_k: None,
+                vector_weight: Some(1.0),
+                fts_weight: Some(-1.0),
+            }),
+            tags_filter: None,
+            bookmark_ids: None,
+            workspace: None,
+            history: Vec::new(),
+            rerank: false,
+        };
+
+        assert!(validate_rag_query_request(&request).is_err());
+    }
+
+    #[test]
+    fn rejects_empty_bookmark_selection() {
+        let request = RagQueryRequest {
+            question: "test".into(),
+            max_chunks: None,
+            similarity_threshold: None,
+            max_context_tokens: None,
+            hybrid_search: None,
+            tags_filter: None,
+            bookmark_ids: Some(vec![]),
+            workspace: None,
+            history: Vec::new(),
+            rerank: false,
+        };
+
+        assert!(validate_rag_query_request(&request).is_err());
+    }
+
+    #[test]
+    fn feedback_comments_are_trimmed_and_bounded() {
+        let mut request = RagFeedbackRequest {
+            rating: RagRating::Down,
+            comment: Some("  missed the point \n".into()),
+        };
+        normalize_feedback_request(&mut request).unwrap();
+        assert_eq!(request.comment.as_deref(), Some("missed the point"));
+
+        request.comment = Some("   ".into());
+        normalize_feedback_request(&mut request).unwrap();
+        assert_eq!(request.comment, None);
+
+        request.comment = Some("a".repeat(2001));
+        assert!(normalize_feedback_request(&mut request).is_err());
+    }
 }
@@ -0,0 +1,221 @@
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use axum_macros::debug_handler;
+use shared::{Rule, RuleActions, RuleConditions, RuleRequest, Rules};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use super::Claim;
+use crate::db::rule;
+use crate::error::{Error, Result};
+use crate::AppContext;
+
+const MAX_NAME_CHARS: usize = 200;
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/rules", get(list_rules).post(create_rule))
+        .route(
+            "/rules/{id}",
+            get(get_rule).put(update_rule).delete(delete_rule),
+        )
+}
+
+/// Trims the name and the conditions, lowercases domain and tags, empty
+/// values being none. A rule needs at least one condition and one action.
+fn validate_request(request: RuleRequest) -> Result<RuleRequest> {
+    let name = request.name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+        return Err(Error::unprocessable_entity([(
+            "name",
+            "must have between 1 and 200 characters",
+        )]));
+    }
+    let non_blank = |value: String| {
+        let value = value.trim().to_string();
+        (!value.is_empty()).then_some(value)
+    };
+    let conditions = RuleConditions {
+        url_pattern: request.conditions.url_pattern.and_then(non_blank),
+        domain: request
+            .conditions
+            .domain
+            .and_then(non_blank)
+            .map(|domain| domain.to_lowercase()),
+        title_keywords: request
+            .conditions
+            .title_keywords
+            .into_iter()
+            .filter_map(non_blank)
+            .collect(),
+    };
+    if conditions == RuleConditions::default() {
+        return Err(Error::unprocessable_entity([(
+            "conditions",
+            "must have a URL pattern, a domain or title keywords",
+        )]));
+    }
+    let mut add_tags: Vec<String> = Vec::new();
+    for tag in request.actions.add_tags.into_iter().filter_map(non_blank) {
+        let tag = tag.to_lowercase();
+        if !add_tags.contains(&tag) {
+            add_tags.push(tag);
+        }
+    }
+    let actions = RuleActions {
+        add_tags,
+        ..request.actions
+    };
+    if actions == RuleActions::default() {
+        return Err(Error::unprocessable_entity([(
+            "actions",
+            "must add tags, mark as favorite or skip AI processing",
+        )]));
+    }
+    Ok(RuleRequest {
+        name,
+        enabled: request.enabled,
+        conditions,
+        actions,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/rules",
+    tag = "rules",
+    responses((status = 200, body = Rules))
+)]
+#[debug_handler]
+async fn list_rules(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+) -> Result<Json<Rules>> {
+    let rules = rule::list(&app_context.pool, claims.user_id).await?;
+    debug!(user_id = %claims.user_id, rule_count = rules.len(), "Listed rules");
+    Ok(Json(Rules { rules }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rules",
+    tag = "rules",
+    request_body = RuleRequest,
+    responses((status = 201, body = Rule), (status = 422))
+)]
+#[debug_handler]
+async fn create_rule(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Json(request): Json<RuleRequest>,
+) -> Result<(StatusCode, Json<Rule>)> {
+    let request = validate_request(request)?;
+    let created = rule::create(&app_context.pool, claims.user_id, &request).await?;
+    info!(user_id = %claims.user_id, rule_id = %created.rule_id, "Rule created");
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/rules/{id}",
+    tag = "rules",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = Rule), (status = 404))
+)]
+#[debug_handler]
+async fn get_rule(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Rule>> {
+    rule::get(&app_context.pool, claims.user_id, id)
+        .await?
+        .map(Json)
+        .ok_or(Error::NotFound)
+}
+
+#[utoipa::path(
+    put,
+    path = "/rules/{id}",
+    tag = "rules",
+    params(("id" = Uuid, Path)),
+    request_body = RuleRequest,
+    responses((status = 200, body = Rule), (status = 404), (status = 422))
+)]
+#[debug_handler]
+async fn update_rule(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<RuleRequest>,
+) -> Result<Json<Rule>> {
+    let request = validate_request(request)?;
+    let updated = rule::update(&app_context.pool, claims.user_id, id, &request)
+        .await?
+        .ok_or(Error::NotFound)?;
+    info!(user_id = %claims.user_id, rule_id = %id, "Rule updated");
+    Ok(Json(updated))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/rules/{id}",
+    tag = "rules",
+    params(("id" = Uuid, Path)),
+    responses((status = 204, description = "Rule deleted"), (status = 404))
+)]
+#[debug_handler]
+async fn delete_rule(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    if !rule::delete(&app_context.pool, claims.user_id, id).await? {
+        return Err(Error::NotFound);
+    }
+    info!(user_id = %claims.user_id, rule_id = %id, "Rule deleted");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::{RuleActions, RuleConditions, RuleRequest};
+
+    use super::validate_request;
+
+    fn request(domain: &str, add_tags: &[&str]) -> RuleRequest {
+        RuleRequest {
+            name: " GitHub ".to_string(),
+            enabled: None,
+            conditions: RuleConditions {
+                url_pattern: Some(" ".to_string()),
+                domain: Some(domain.to_string()),
+                title_keywords: vec!["".to_string()],
+            },
+            actions: RuleActions {
+                add_tags: add_tags.iter().map(|tag| tag.to_string()).collect(),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn requests_are_normalized_and_validated() {
+        let valid = validate_request(request(" GitHub.com", &["Code", "code ", " "])).unwrap();
+        assert_eq!(valid.name, "GitHub");
+        assert_eq!(
+            valid.conditions,
+            RuleConditions {
+                url_pattern: None,
+                domain: Some("github.com".to_string()),
+                title_keywords: vec![],
+            }
+        );
+        assert_eq!(valid.actions.add_tags, vec!["code"]);
+
+        assert!(validate_request(request(" ", &["code"])).is_err());
+        assert!(validate_request(request("github.com", &[" "])).is_err());
+    }
+}
@@ -1,28 +1,132 @@
-use axum::routing::post;
+use axum::extract::Query;
+use axum::routing::{get, post};
 use axum::{Extension, Json, Router};
 use axum_macros::debug_handler;
-use shared::{SearchRequest, SearchResponse, TagFilter};
+use shared::{SearchRequest, SearchResponse, SearchSuggestRequest, SearchSuggestions, TagFilter};
 
-use super::Claim;
-use crate::db::search::search;
-use crate::error::Result;
+use super::{bounded_limit, workspace, Claim};
+use crate::db::search::{search, suggest};
+use crate::error::{Error, Result};
 use crate::AppContext;
 
+const MAX_SNIPPET_COUNT: u32 = 5;
+const MIN_SNIPPET_WORDS: u32 = 5;
+const MAX_SNIPPET_WORDS: u32 = 100;
+const DEFAULT_SUGGEST_LIMIT: u32 = 5;
+const MAX_SUGGEST_LIMIT: u32 = 20;
+
 pub fn routes() -> Router {
-    Router::new().route("/search", post(search_bookmark))
+    Router::new()
+        .route("/search", post(search_bookmark))
+        .route("/search/suggest", get(suggest_search))
+}
+
+fn validate_snippets(request: &SearchRequest) -> Result<()> {
+    let mut errors = vec![];
+    if request
+        .snippet_count
+        .is_some_and(|count| !(1..=MAX_SNIPPET_COUNT).contains(&count))
+    {
+        errors.push(("snippet_count", "must be between 1 and 5"));
+    }
+    if request
+        .snippet_words
+        .is_some_and(|words| !(MIN_SNIPPET_WORDS..=MAX_SNIPPET_WORDS).contains(&words))
+    {
+        errors.push(("snippet_words", "must be between 5 and 100"));
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::unprocessable_entity(errors))
+    }
 }
 
+#[utoipa::path(
+    post,
+    path = "/search",
+    tag = "search",
+    request_body = SearchRequest,
+    responses((status = 200, body = SearchResponse), (status = 422))
+)]
 #[debug_handler]
 async fn search_bookmark(
     claims: Claim,
     Extension(app_context): Extension<AppContext>,
     Json(mut input): Json<SearchRequest>,
 ) -> Result<Json<SearchResponse>> {
+    validate_snippets(&input)?;
     if let Some(TagFilter::And(tags) | TagFilter::Or(tags)) = &mut input.tags_filter {
         for tag in tags.iter_mut() {
             *tag = tag.to_lowercase();
         }
     }
+    for domain in input.domains.iter_mut() {
+        *domain = domain.trim().to_lowercase();
+    }
+    workspace::require_member(&app_context, claims.user_id, input.workspace).await?;
     let result = search(&app_context.pool, claims.user_id, &input).await?;
     Ok(Json(result))
 }
+
+#[utoipa::path(
+    get,
+    path = "/search/suggest",
+    tag = "search",
+    params(SearchSuggestRequest),
+    responses((status = 200, body = SearchSuggestions), (status = 422))
+)]
+#[debug_handler]
+async fn suggest_search(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Query(request): Query<SearchSuggestRequest>,
+) -> Result<Json<SearchSuggestions>> {
+    let limit = bounded_limit(
+        request.limit,
+        DEFAULT_SUGGEST_LIMIT,
+        MAX_SUGGEST_LIMIT,
+        "limit",
+    )?;
+    let text = request.q.trim().to_lowercase();
+    if text.is_empty() {
+        return Ok(Json(SearchSuggestions::default()));
+    }
+    let suggestions = suggest(&app_context.pool, claims.user_id, &text, i64::from(limit)).await?;
+    Ok(Json(suggestions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(snippet_count: Option<u32>, snippet_words: Option<u32>) -> SearchRequest {
+        SearchRequest {
+            query: Some("rust".to_string()),
+            tags_filter: None,
+            domains: vec![],
+            from_date: None,
+            to_date: None,
+            reading_times: vec![],
+            kinds: vec![],
+            entities: vec![],
+            collection: None,
+            workspace: None,
+            snippet_count,
+            snippet_words,
+            highlight_title_summary: false,
+            limit: None,
+            offset: None,
+        }
+    }
+
+    #[test]
+    fn snippet_options_are_bounded() {
+        assert!(validate_snippets(&request(None, None)).is_ok());
+        assert!(validate_snippets(&request(Some(5), Some(5))).is_ok());
+        assert!(validate_snippets(&request(Some(0), None)).is_err());
+        assert!(validate_snippets(&request(Some(6), None)).is_err());
+        assert!(validate_snippets(&request(None, Some(4))).is_err());
+        assert!(validate_snippets(&request(None, Some(101))).is_err());
+    }
+}
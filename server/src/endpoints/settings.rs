@@ -0,0 +1,155 @@
+use std::ops::RangeInclusive;
+
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use axum_macros::debug_handler;
+use shared::UserSettings;
+use tracing::{error, info};
+
+use super::Claim;
+use crate::db::{ai, user_settings};
+use crate::error::{Error, Result};
+use crate::AppContext;
+
+const MAX_SUMMARY_LANGUAGE_LENGTH: usize = 64;
+const MAX_MODEL_NAME_LENGTH: usize = 256;
+const READER_FONT_SIZES: RangeInclusive<i32> = 12..=32;
+const READER_LINE_WIDTHS: RangeInclusive<i32> = 40..=120;
+
+pub fn routes() -> Router {
+    Router::new().route("/settings", get(get_settings).put(update_settings))
+}
+
+/// Blank optional strings mean "use the server default".
+fn normalize_settings(settings: &mut UserSettings) {
+    for value in [
+        &mut settings.summary_language,
+        &mut settings.preferred_text_model,
+    ] {
+        *value = value
+            .take()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+    }
+}
+
+fn validate_settings(settings: &UserSettings) -> Result<()> {
+    let mut errors = Vec::new();
+    if settings
+        .summary_language
+        .as_ref()
+        .is_some_and(|v| v.chars().count() > MAX_SUMMARY_LANGUAGE_LENGTH)
+    {
+        errors.push(("summary_language", "must be at most 64 characters"));
+    }
+    if settings
+        .preferred_text_model
+        .as_ref()
+        .is_some_and(|v| v.chars().count() > MAX_MODEL_NAME_LENGTH)
+    {
+        errors.push(("preferred_text_model", "must be at most 256 characters"));
+    }
+    if !READER_FONT_SIZES.contains(&settings.reader.font_size) {
+        errors.push(("reader.font_size", "must be between 12 and 32"));
+    }
+    if !READER_LINE_WIDTHS.contains(&settings.reader.line_width) {
+        errors.push(("reader.line_width", "must be between 40 and 120"));
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::unprocessable_entity(errors))
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/settings",
+    tag = "settings",
+    responses((status = 200, body = UserSettings))
+)]
+#[debug_handler]
+async fn get_settings(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+) -> Result<Json<UserSettings>> {
+    let settings = user_settings::get(&app_context.pool, claims.user_id).await?;
+    Ok(Json(settings))
+}
+
+#[utoipa::path(
+    put,
+    path = "/settings",
+    tag = "settings",
+    request_body = UserSettings,
+    responses((status = 200, body = UserSettings), (status = 422))
+)]
+#[debug_handler]
+async fn update_settings(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Json(mut settings): Json<UserSettings>,
+) -> Result<Json<UserSettings>> {
+    normalize_settings(&mut settings);
+    validate_settings(&settings)?;
+    let settings = user_settings::upsert(&app_context.pool, claims.user_id, &settings).await?;
+    let requeued = ai::requeue_skipped(
+        &app_context.pool,
+        claims.user_id,
+        settings.summaries_enabled,
+        settings.auto_tagging_enabled,
+    )
+    .await?;
+    info!(
+        user_id = %claims.user_id,
+        auto_tagging_enabled = settings.auto_tagging_enabled,
+        summaries_enabled = settings.summaries_enabled,
+        requeued,
+        "User settings updated"
+    );
+    if requeued > 0 {
+        if let Err(error) = app_context.tx_new_bookmark.send(()) {
+            error!(?error, "Failed to notify text AI daemon");
+        }
+    }
+    Ok(Json(settings))
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::UserSettings;
+
+    use super::{normalize_settings, validate_settings};
+
+    #[test]
+    fn blank_strings_fall_back_to_defaults() {
+        let mut settings = UserSettings {
+            summary_language: Some("  ".into()),
+            preferred_text_model: Some(" qwen3:8b ".into()),
+            ..UserSettings::default()
+        };
+        normalize_settings(&mut settings);
+        assert_eq!(settings.summary_language, None);
+        assert_eq!(settings.preferred_text_model.as_deref(), Some("qwen3:8b"));
+    }
+
+    #[test]
+    fn rejects_reader_settings_out_of_range() {
+        let mut settings = UserSettings::default();
+        assert!(validate_settings(&settings).is_ok());
+        settings.reader.font_size = 8;
+        assert!(validate_settings(&settings).is_err());
+        settings.reader.font_size = 20;
+        settings.reader.line_width = 200;
+        assert!(validate_settings(&settings).is_err());
+    }
+
+    #[test]
+    fn rejects_overlong_language() {
+        let settings = UserSettings {
+            summary_language: Some("x".repeat(65)),
+            ..UserSettings::default()
+        };
+        assert!(validate_settings(&settings).is_err());
+    }
+}
@@ -1,15 +1,590 @@
-use axum::Router;
-use tower_http::compression::CompressionLayer;
-use tower_http::services::fs::ServeDir;
-
-use crate::Config;
-
-pub fn routes(config: &Config) -> Router {
-    Router::new()
-        .nest_service(
-            "/static",
-            ServeDir::new(&config.data_dir).precompressed_gzip(), // Serve .gz files when available
-        )
-        .layer(CompressionLayer::new()) // Add dynamic compression for
-                                        // non-compressed content
+use axum::extract::{Path, Query};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Extension, Router};
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, KeyInit, Mac};
+use lol_html::{element, rewrite_str, RewriteStrSettings};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use shared::Visibility;
+use tracing::debug;
+use uuid::Uuid;
+
+use super::Claim;
+use crate::db::{bookmark, workspace};
+use crate::error::{Error, Result};
+use crate::static_storage::{self, decode, StoredObject, GZIP_ENCODING};
+use crate::AppContext;
+
+/// Images of a served page are signed until the end of the window after the
+/// current one. Within a window the page doesn't change, so it can be
+/// revalidated, and its images stay readable for at least a window.
+const PAGE_IMAGES_WINDOW: Duration = Duration::hours(1);
+
+/// Pages are revalidated on every open, their signed image URLs change.
+const PAGE_CACHE_CONTROL: &str = "private, no-cache";
+
+/// Images never change once captured, only their signed URL does.
+const IMAGE_CACHE_CONTROL: &str = "private, max-age=86400";
+
+pub(super) const PAGE_NAME: &str = "index.html";
+
+pub fn routes() -> Router {
+    Router::new().route(
+        "/static/{user_id}/{bookmark_id}/{name}",
+        get(static_content),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct StaticContentQuery {
+    share: Option<String>,
+}
+
+fn share_mac(hmac_key: &SecretString, scope: &str, expires_at: i64) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(hmac_key.expose_secret().as_bytes())
+        .expect("HMAC accepts keys of any size");
+    mac.update(format!("{scope}:{expires_at}").as_bytes());
+    mac
+}
+
+fn static_scope(user_id: Uuid, bookmark_id: &str) -> String {
+    format!("static:{user_id}/{bookmark_id}")
+}
+
+/// Narrower than the static scope: it doesn't open the page itself, so an
+/// image URL copied out of a page doesn't share the page.
+fn image_scope(user_id: Uuid, bookmark_id: &str) -> String {
+    format!("image:{user_id}/{bookmark_id}")
+}
+
+/// Grants access to whatever `scope` names until `expires_at`, without a
+/// JWT. Scopes must not be prefixes of one another.
+pub(super) fn scoped_share_token(
+    hmac_key: &SecretString,
+    scope: &str,
+    expires_at: DateTime<Utc>,
+) -> String {
+    let expires_at = expires_at.timestamp();
+    let signature = share_mac(hmac_key, scope, expires_at)
+        .finalize()
+        .into_bytes();
+    format!("{expires_at}.{}", hex::encode(signature))
+}
+
+pub(super) fn share_token_expires_at(token: &str) -> Option<DateTime<Utc>> {
+    let (expires_at, _) = token.split_once('.')?;
+    DateTime::from_timestamp(expires_at.parse().ok()?, 0)
+}
+
+pub(super) fn is_valid_scoped_share_token(
+    hmac_key: &SecretString,
+    scope: &str,
+    token: &str,
+    now: DateTime<Utc>,
+) -> bool {
+    let Some((expires_at, signature)) = token.split_once('.') else {
+        return false;
+    };
+    let (Ok(expires_at), Ok(signature)) = (expires_at.parse::<i64>(), hex::decode(signature))
+    else {
+        return false;
+    };
+    expires_at > now.timestamp()
+        && share_mac(hmac_key, scope, expires_at)
+            .verify_slice(&signature)
+            .is_ok()
+}
+
+/// Grants access to the static content of one bookmark until `expires_at`,
+/// without a JWT.
+pub(super) fn share_token(
+    hmac_key: &SecretString,
+    user_id: Uuid,
+    bookmark_id: &str,
+    expires_at: DateTime<Utc>,
+) -> String {
+    scoped_share_token(hmac_key, &static_scope(user_id, bookmark_id), expires_at)
+}
+
+fn is_valid_share_token(
+    hmac_key: &SecretString,
+    user_id: Uuid,
+    bookmark_id: &str,
+    token: &str,
+    now: DateTime<Utc>,
+) -> bool {
+    is_valid_scoped_share_token(hmac_key, &static_scope(user_id, bookmark_id), token, now)
+}
+
+/// Grants access to the images of one bookmark until `expires_at`, not to
+/// its page.
+fn image_token(
+    hmac_key: &SecretString,
+    user_id: Uuid,
+    bookmark_id: &str,
+    expires_at: DateTime<Utc>,
+) -> String {
+    scoped_share_token(hmac_key, &image_scope(user_id, bookmark_id), expires_at)
+}
+
+fn is_valid_image_token(
+    hmac_key: &SecretString,
+    user_id: Uuid,
+    bookmark_id: &str,
+    name: &str,
+    token: &str,
+    now: DateTime<Utc>,
+) -> bool {
+    name != PAGE_NAME
+        && is_valid_scoped_share_token(hmac_key, &image_scope(user_id, bookmark_id), token, now)
+}
+
+/// What a `share` token opens, without a JWT.
+#[derive(Debug, PartialEq, Eq)]
+enum ShareAccess {
+    /// An image of a page served to someone who could read it, whatever the
+    /// bookmark's visibility
+    PageImage,
+    /// Anything of a bookmark shared by link, while it stays public
+    Bookmark,
+}
+
+fn share_access(
+    hmac_key: &SecretString,
+    user_id: Uuid,
+    bookmark_id: &str,
+    name: &str,
+    token: &str,
+    now: DateTime<Utc>,
+) -> Option<ShareAccess> {
+    if is_valid_image_token(hmac_key, user_id, bookmark_id, name, token, now) {
+        Some(ShareAccess::PageImage)
+    } else if is_valid_share_token(hmac_key, user_id, bookmark_id, token, now) {
+        Some(ShareAccess::Bookmark)
+    } else {
+        None
+    }
+}
+
+/// Links shared before the owner made the bookmark private stop working.
+async fn is_public(app_context: &AppContext, user_id: Uuid, bookmark_id: &str) -> Result<bool> {
+    Ok(
+        bookmark::get_with_user_data(&app_context.pool, user_id, bookmark_id)
+            .await?
+            .is_some_and(|bookmark| bookmark.visibility == Visibility::Public),
+    )
+}
+
+async fn static_content(
+    claims: Result<Claim>,
+    Extension(app_context): Extension<AppContext>,
+    Path((user_id, bookmark_id, name)): Path<(String, String, String)>,
+    Query(query): Query<StaticContentQuery>,
+    request_headers: HeaderMap,
+) -> Result<Response> {
+    let user_id = Uuid::parse_str(&user_id).map_err(|_| Error::NotFound)?;
+    let hmac_key = &app_context.config.hmac_key;
+    let requester_id = claims.ok().map(|claims| claims.user_id);
+    let is_owner = requester_id == Some(user_id);
+    let is_member = match requester_id {
+        Some(member_id) if !is_owner => {
+            workspace::shares_bookmark(&app_context.pool, member_id, user_id, &bookmark_id).await?
+        }
+        _ => false,
+    };
+    let access = query
+        .share
+        .as_deref()
+        .and_then(|token| share_access(hmac_key, user_id, &bookmark_id, &name, token, Utc::now()));
+    let is_shared = !is_owner
+        && !is_member
+        && match access {
+            Some(ShareAccess::PageImage) => true,
+            Some(ShareAccess::Bookmark) => is_public(&app_context, user_id, &bookmark_id).await?,
+            None => false,
+        };
+    // Not telling apart someone else's content from missing content
+    if !is_owner && !is_member && !is_shared {
+        debug!(%user_id, bookmark_id, "Static content access denied");
+        return Err(Error::NotFound);
+    }
+
+    let key = format!("{user_id}/{bookmark_id}/{name}");
+    if static_storage::validate_key(&key).is_err() {
+        return Err(Error::NotFound);
+    }
+    let object = app_context
+        .storage
+        .get(&key)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    if name == PAGE_NAME {
+        return page_response(object, &request_headers, user_id, &bookmark_id, hmac_key);
+    }
+    Ok(object_response(object, &request_headers)?)
+}
+
+fn page_response(
+    object: StoredObject,
+    request_headers: &HeaderMap,
+    user_id: Uuid,
+    bookmark_id: &str,
+    hmac_key: &SecretString,
+) -> Result<Response> {
+    let window_start = page_window_start(Utc::now());
+    let images_expire_at = window_start + PAGE_IMAGES_WINDOW * 2;
+    let etag = format!(
+        "\"{}-{}\"",
+        content_hash(&object.bytes),
+        images_expire_at.timestamp()
+    );
+    // The served page changes when the window does, even if the stored one didn't
+    let last_modified = object
+        .last_modified
+        .map(|last_modified| last_modified.max(window_start));
+    let mut response_headers = cache_headers(&etag, last_modified, PAGE_CACHE_CONTROL)?;
+    if is_not_modified(request_headers, &etag, last_modified) {
+        return Ok((StatusCode::NOT_MODIFIED, response_headers).into_response());
+    }
+
+    let page = signed_page(object, user_id, bookmark_id, hmac_key, images_expire_at)?;
+    response_headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/html; charset=utf-8"),
+    );
+    Ok((response_headers, page).into_response())
+}
+
+/// The stored page of a bookmark with its images readable without a JWT until
+/// `expires_at`.
+pub(super) fn signed_page(
+    object: StoredObject,
+    user_id: Uuid,
+    bookmark_id: &str,
+    hmac_key: &SecretString,
+    expires_at: DateTime<Utc>,
+) -> anyhow::Result<String> {
+    let token = image_token(hmac_key, user_id, bookmark_id, expires_at);
+    let page = decode(object.bytes, object.content_encoding)?;
+    sign_page_images(&page, &format!("/static/{user_id}/{bookmark_id}/"), &token)
+}
+
+fn page_window_start(now: DateTime<Utc>) -> DateTime<Utc> {
+    let window = PAGE_IMAGES_WINDOW.num_seconds();
+    let start = now.timestamp() - now.timestamp().rem_euclid(window);
+    DateTime::from_timestamp(start, 0).expect("window start is in range")
+}
+
+fn content_hash(bytes: &[u8]) -> String {
+    hex::encode(&Sha256::digest(bytes)[..16])
+}
+
+fn http_date(date: DateTime<Utc>) -> String {
+    date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+fn cache_headers(
+    etag: &str,
+    last_modified: Option<DateTime<Utc>>,
+    cache_control: &'static str,
+) -> anyhow::Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::ETAG, HeaderValue::from_str(etag)?);
+    headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(cache_control),
+    );
+    if let Some(last_modified) = last_modified {
+        headers.insert(
+            header::LAST_MODIFIED,
+            HeaderValue::from_str(&http_date(last_modified))?,
+        );
+    }
+    Ok(headers)
+}
+
+/// `If-None-Match` wins over `If-Modified-Since` when both are sent.
+fn is_not_modified(
+    request_headers: &HeaderMap,
+    etag: &str,
+    last_modified: Option<DateTime<Utc>>,
+) -> bool {
+    if let Some(if_none_match) = request_headers.get(header::IF_NONE_MATCH) {
+        return if_none_match.to_str().is_ok_and(|value| {
+            value
+                .split(',')
+                .map(|tag| tag.trim().trim_start_matches("W/"))
+                .any(|tag| tag == "*" || tag == etag)
+        });
+    }
+    let if_modified_since = request_headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok());
+    match (last_modified, if_modified_since) {
+        // HTTP dates have no sub-second precision
+        (Some(last_modified), Some(since)) => last_modified.timestamp() <= since.timestamp(),
+        _ => false,
+    }
+}
+
+/// The browser loads the images of a page without our JWT, their URLs carry
+/// a share token instead.
+fn sign_page_images(page: &[u8], images_prefix: &str, token: &str) -> anyhow::Result<String> {
+    let page = String::from_utf8_lossy(page);
+    let signed = rewrite_str(
+        &page,
+        RewriteStrSettings::new().append_element_content_handler(element!("img[src]", |el| {
+            let src = el.get_attribute("src").expect("img[src] was required");
+            if src.starts_with(images_prefix) && !src.contains('?') {
+                el.set_attribute("src", &format!("{src}?share={token}"))?;
+            }
+            Ok(())
+        })),
+    )?;
+    Ok(signed)
+}
+
+fn object_response(object: StoredObject, request_headers: &HeaderMap) -> anyhow::Result<Response> {
+    let accepts_gzip = request_headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(|value| value.contains(GZIP_ENCODING));
+    let gzipped = object.content_encoding.as_deref() == Some(GZIP_ENCODING);
+    let send_gzipped = gzipped && accepts_gzip;
+    // Each encoding is a different representation
+    let etag = if send_gzipped {
+        format!("\"{}-{GZIP_ENCODING}\"", content_hash(&object.bytes))
+    } else {
+        format!("\"{}\"", content_hash(&object.bytes))
+    };
+    let mut response_headers = cache_headers(&etag, object.last_modified, IMAGE_CACHE_CONTROL)?;
+    response_headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+    if is_not_modified(request_headers, &etag, object.last_modified) {
+        return Ok((StatusCode::NOT_MODIFIED, response_headers).into_response());
+    }
+
+    // Local files carry no metadata
+    let content_type = object
+        .content_type
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+    response_headers.insert(header::CONTENT_TYPE, HeaderValue::from_str(&content_type)?);
+    let bytes = if send_gzipped {
+        response_headers.insert(
+            header::CONTENT_ENCODING,
+            HeaderValue::from_static(GZIP_ENCODING),
+        );
+        object.bytes
+    } else {
+        decode(object.bytes, object.content_encoding)?
+    };
+    Ok((response_headers, bytes).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+    use chrono::{Duration, TimeZone, Utc};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use secrecy::SecretString;
+    use uuid::Uuid;
+
+    use super::{
+        decode, http_date, image_token, is_valid_image_token, is_valid_share_token,
+        object_response, page_window_start, share_access, share_token, sign_page_images,
+        signed_page, ShareAccess, PAGE_NAME,
+    };
+    use crate::static_storage::{StoredObject, GZIP_ENCODING};
+
+    fn gzipped(content: &[u8]) -> StoredObject {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content).unwrap();
+        StoredObject {
+            content_type: Some("image/svg+xml".to_string()),
+            content_encoding: Some(GZIP_ENCODING.to_string()),
+            bytes: encoder.finish().unwrap(),
+            last_modified: Some(Utc.with_ymd_and_hms(2024, 5, 1, 10, 30, 0).unwrap()),
+        }
+    }
+
+    fn request_headers(pairs: &[(header::HeaderName, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn share_tokens_are_scoped_and_expire() {
+        let key = SecretString::from("secret");
+        let user_id = Uuid::new_v4();
+        let now = Utc::now();
+        let token = share_token(&key, user_id, "b1", now + Duration::hours(1));
+
+        assert!(is_valid_share_token(&key, user_id, "b1", &token, now));
+        assert!(!is_valid_share_token(&key, user_id, "b2", &token, now));
+        assert!(!is_valid_share_token(
+            &key,
+            Uuid::new_v4(),
+            "b1",
+            &token,
+            now
+        ));
+        assert!(!is_valid_share_token(
+            &key,
+            user_id,
+            "b1",
+            &token,
+            now + Duration::hours(2)
+        ));
+        assert!(!is_valid_share_token(&key, user_id, "b1", "garbage", now));
+    }
+
+    #[test]
+    fn image_tokens_open_images_only() {
+        let key = SecretString::from("secret");
+        let user_id = Uuid::new_v4();
+        let now = Utc::now();
+        let token = image_token(&key, user_id, "b1", now + Duration::hours(1));
+
+        assert!(is_valid_image_token(
+            &key, user_id, "b1", "img1", &token, now
+        ));
+        assert!(!is_valid_image_token(
+            &key, user_id, "b1", PAGE_NAME, &token, now
+        ));
+        assert!(!is_valid_image_token(
+            &key, user_id, "b2", "img1", &token, now
+        ));
+        assert!(!is_valid_share_token(&key, user_id, "b1", &token, now));
+        let shared = share_token(&key, user_id, "b1", now + Duration::hours(1));
+        assert!(!is_valid_image_token(
+            &key, user_id, "b1", "img1", &shared, now
+        ));
+    }
+
+    #[test]
+    fn images_of_a_served_page_open_without_a_jwt() {
+        let key = SecretString::from("secret");
+        let user_id = Uuid::new_v4();
+        let now = Utc::now();
+        let page = StoredObject {
+            content_type: Some("text/html".to_string()),
+            content_encoding: None,
+            bytes: format!(r#"<img src="/static/{user_id}/b1/img1">"#).into_bytes(),
+            last_modified: None,
+        };
+
+        // Served to the owner of a private bookmark, the visibility isn't checked
+        let signed = signed_page(page, user_id, "b1", &key, now + Duration::hours(2)).unwrap();
+        let token = signed
+            .split_once("?share=")
+            .and_then(|(_, rest)| rest.split_once('"'))
+            .map(|(token, _)| token)
+            .expect("signed image URL");
+
+        assert_eq!(
+            share_access(&key, user_id, "b1", "img1", token, now),
+            Some(ShareAccess::PageImage)
+        );
+        assert_eq!(
+            share_access(&key, user_id, "b1", PAGE_NAME, token, now),
+            None
+        );
+        let shared = share_token(&key, user_id, "b1", now + Duration::hours(1));
+        assert_eq!(
+            share_access(&key, user_id, "b1", PAGE_NAME, &shared, now),
+            Some(ShareAccess::Bookmark)
+        );
+    }
+
+    #[test]
+    fn page_images_get_signed() {
+        let page = br#"<img src="/static/u/b/img1"><img src="https://example.com/a.png">"#;
+
+        let signed = sign_page_images(page, "/static/u/b/", "123.abc").unwrap();
+
+        assert_eq!(
+            signed,
+            r#"<img src="/static/u/b/img1?share=123.abc"><img src="https://example.com/a.png">"#
+        );
+    }
+
+    #[tokio::test]
+    async fn gzipped_objects_are_decoded_for_clients_without_gzip() {
+        let accepts_gzip = request_headers(&[(header::ACCEPT_ENCODING, "gzip, br")]);
+        let response = object_response(gzipped(b"<svg/>"), &accepts_gzip).unwrap();
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], GZIP_ENCODING);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/svg+xml");
+
+        let response = object_response(gzipped(b"<svg/>"), &HeaderMap::new()).unwrap();
+        assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"<svg/>");
+        assert_eq!(decode(b"plain".to_vec(), None).unwrap(), b"plain");
+    }
+
+    #[test]
+    fn conditional_requests_get_not_modified() {
+        let response = object_response(gzipped(b"<svg/>"), &HeaderMap::new()).unwrap();
+        let etag = response.headers()[header::ETAG]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            response.headers()[header::LAST_MODIFIED],
+            "Wed, 01 May 2024 10:30:00 GMT"
+        );
+
+        let revalidate = |pairs: &[(header::HeaderName, &str)]| {
+            object_response(gzipped(b"<svg/>"), &request_headers(pairs))
+                .unwrap()
+                .status()
+        };
+        assert_eq!(
+            revalidate(&[(header::IF_NONE_MATCH, &format!("\"other\", W/{etag}"))]),
+            StatusCode::NOT_MODIFIED
+        );
+        assert_eq!(
+            revalidate(&[(header::IF_NONE_MATCH, "\"other\"")]),
+            StatusCode::OK
+        );
+        // The gzip representation has its own tag
+        assert_eq!(
+            revalidate(&[
+                (header::IF_NONE_MATCH, &etag),
+                (header::ACCEPT_ENCODING, "gzip")
+            ]),
+            StatusCode::OK
+        );
+        assert_eq!(
+            revalidate(&[(header::IF_MODIFIED_SINCE, "Wed, 01 May 2024 10:30:00 GMT")]),
+            StatusCode::NOT_MODIFIED
+        );
+        assert_eq!(
+            revalidate(&[(header::IF_MODIFIED_SINCE, "Wed, 01 May 2024 10:29:59 GMT")]),
+            StatusCode::OK
+        );
+    }
+
+    #[test]
+    fn page_windows_start_on_the_hour() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 10, 30, 15).unwrap();
+        let start = page_window_start(now);
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 5, 1, 10, 0, 0).unwrap());
+        assert_eq!(page_window_start(start), start);
+        assert_eq!(http_date(start), "Wed, 01 May 2024 10:00:00 GMT");
+    }
 }
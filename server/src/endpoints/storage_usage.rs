@@ -0,0 +1,35 @@
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use axum_macros::debug_handler;
+use shared::StorageUsage;
+
+use super::Claim;
+use crate::db::storage;
+use crate::error::Result;
+use crate::AppContext;
+
+pub fn routes() -> Router {
+    Router::new().route("/usage/storage", get(storage_usage))
+}
+
+#[utoipa::path(
+    get,
+    path = "/usage/storage",
+    tag = "usage",
+    responses((status = 200, body = StorageUsage))
+)]
+#[debug_handler]
+async fn storage_usage(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+) -> Result<Json<StorageUsage>> {
+    let usage = storage::usage(&app_context.pool, claims.user_id).await?;
+    let quota_bytes = app_context
+        .config
+        .storage_quota_bytes
+        .map(|quota| i64::try_from(quota).unwrap_or(i64::MAX));
+    Ok(Json(StorageUsage {
+        quota_bytes,
+        ..usage
+    }))
+}
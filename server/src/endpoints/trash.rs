@@ -0,0 +1,106 @@
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, Path};
+use axum::http::StatusCode;
+use axum::routing::{delete, get, post};
+use axum::{Extension, Json, Router};
+use axum_macros::debug_handler;
+use chrono::Duration;
+use shared::{AuditAction, Bookmark, Trash, TrashedBookmark};
+use tracing::{error, info};
+
+use super::{audit, Claim};
+use crate::db::bookmark;
+use crate::error::{Error, Result};
+use crate::AppContext;
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/trash", get(get_trash))
+        .route("/trash/{id}", delete(purge_bookmark))
+        .route("/trash/{id}/restore", post(restore_bookmark))
+}
+
+#[utoipa::path(
+    get,
+    path = "/trash",
+    tag = "bookmarks",
+    responses((status = 200, body = Trash))
+)]
+#[debug_handler]
+async fn get_trash(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+) -> Result<Json<Trash>> {
+    let retention = Duration::days(i64::from(app_context.config.trash_retention_days));
+    let bookmarks: Vec<TrashedBookmark> = bookmark::get_trash(&app_context.pool, claims.user_id)
+        .await?
+        .into_iter()
+        .map(|(bookmark, deleted_at)| TrashedBookmark {
+            bookmark,
+            deleted_at,
+            purge_at: deleted_at + retention,
+        })
+        .collect();
+    info!(user_id = %claims.user_id, bookmark_count = %bookmarks.len(), "Retrieved trash");
+    Ok(Json(Trash { bookmarks }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/trash/{id}/restore",
+    tag = "bookmarks",
+    params(("id" = String, Path)),
+    responses((status = 200, body = Bookmark), (status = 404, description = "Not in the trash"))
+)]
+#[debug_handler]
+async fn restore_bookmark(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<String>,
+) -> Result<Json<Bookmark>> {
+    bookmark::restore(&app_context.pool, claims.user_id, &id)
+        .await?
+        .map(Json)
+        .ok_or(Error::NotFound)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/trash/{id}",
+    tag = "bookmarks",
+    params(("id" = String, Path)),
+    responses(
+        (status = 204, description = "Bookmark removed for good"),
+        (status = 404, description = "Not in the trash"),
+    )
+)]
+#[debug_handler]
+async fn purge_bookmark(
+    claims: Claim,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    if !bookmark::purge(&app_context.pool, claims.user_id, &id).await? {
+        return Err(Error::NotFound);
+    }
+    audit::record(
+        &app_context.pool,
+        claims.user_id,
+        AuditAction::BookmarkPurged,
+        Some(&id),
+        Some(client_addr.ip()),
+    )
+    .await;
+    let static_prefix = format!("{}/{id}/", claims.user_id);
+    if let Err(err) = app_context.storage.delete_prefix(&static_prefix).await {
+        error!(
+            bookmark_id = %id,
+            prefix = %static_prefix,
+            error = ?err,
+            "Failed to remove static files for purged bookmark"
+        );
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
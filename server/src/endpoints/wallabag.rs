@@ -0,0 +1,615 @@
+//! A minimal [wallabag](https://doc.wallabag.org/en/developer/api/readme)
+//! API over the bookmarks of a user, so reading apps speaking it (KOReader,
+//! the wallabag Android and iOS apps) can read saved articles. Mounted at
+//! `/wallabag`, clients sign in with their username and password, any client
+//! id and secret is accepted.
+
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, FromRequest, Path, Query, Request};
+use axum::http::header;
+use axum::middleware::from_fn_with_state;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Extension, Form, Json, Router};
+use axum_extra::headers::UserAgent;
+use axum_extra::TypedHeader;
+use chrono::{DateTime, Utc};
+use secrecy::SecretString;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use shared::{AuditAction, Bookmark, Visibility};
+use tracing::{debug, info, warn};
+use url::Url;
+use uuid::Uuid;
+
+use super::static_content::{self, PAGE_NAME};
+use super::{audit, auth, bookmark as bookmark_endpoints, Claim};
+use crate::bookmark_identity::{domain_from_url, make_bookmark_id};
+use crate::db::bookmark::{self, wallabag_entry_id};
+use crate::db::user;
+use crate::error::{Error, Result};
+use crate::{markdown, static_storage, AppContext};
+
+/// The release whose API this mimics, clients check it.
+const WALLABAG_VERSION: &str = "2.6.10";
+const DEFAULT_PER_PAGE: usize = 30;
+const MAX_PER_PAGE: usize = 100;
+/// Target of the sign-ins through this API in the audit log.
+const AUDIT_CLIENT: &str = "wallabag";
+
+pub fn routes() -> Router {
+    Router::new()
+        .route(
+            "/oauth/v2/token",
+            post(token).route_layer(from_fn_with_state("auth", super::rate_limit)),
+        )
+        .route("/api/version", get(version))
+        .route("/api/version.json", get(version))
+        .route("/api/info", get(info))
+        .route("/api/info.json", get(info))
+        .route(
+            "/api/entries.json",
+            get(list_entries).merge(
+                post(add_entry).route_layer(from_fn_with_state("bookmarks", super::rate_limit)),
+            ),
+        )
+        .route("/api/entries/{entry}", get(get_entry).delete(delete_entry))
+        .route("/api/entries/{entry}/{export}", get(export_entry))
+}
+
+/// A body sent as JSON or as a form, wallabag clients use both.
+struct JsonOrForm<T>(T);
+
+impl<T, S> FromRequest<S> for JsonOrForm<T>
+where
+    T: DeserializeOwned + 'static,
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let is_json = request
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/json"));
+        let payload = if is_json {
+            Json::<T>::from_request(request, state)
+                .await
+                .map(|Json(payload)| payload)
+                .map_err(|rejection| rejection.body_text())
+        } else {
+            Form::<T>::from_request(request, state)
+                .await
+                .map(|Form(payload)| payload)
+                .map_err(|rejection| rejection.body_text())
+        };
+        payload
+            .map(JsonOrForm)
+            .map_err(|error| Error::bad_request([("body", error)]))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenRequest {
+    grant_type: String,
+    username: Option<String>,
+    password: Option<SecretString>,
+    refresh_token: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: i64,
+    token_type: &'static str,
+    scope: Option<String>,
+    /// `username:token`, the token of the Pinboard API
+    refresh_token: String,
+}
+
+/// The `password` and `refresh_token` OAuth grants.
+async fn token(
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    user_agent: Option<TypedHeader<UserAgent>>,
+    Extension(app_context): Extension<AppContext>,
+    JsonOrForm(request): JsonOrForm<TokenRequest>,
+) -> Result<Json<TokenResponse>> {
+    let ip = Some(client_addr.ip());
+    let (username, user, verified) = match request.grant_type.as_str() {
+        "password" => {
+            let (Some(username), Some(password)) = (request.username, request.password) else {
+                return Err(Error::MissingCredentials);
+            };
+            let username = user::normalize_username(&username);
+            let user = user::get_by_username(&app_context.pool, username.clone()).await?;
+            let verified = match &user {
+                Some(user) => super::verify_password(password, user.password_hash.clone()).await,
+                None => Err(Error::WrongCredentials),
+            };
+            (username, user, verified)
+        }
+        "refresh_token" => {
+            let (username, token) = request
+                .refresh_token
+                .as_deref()
+                .and_then(|refresh_token| refresh_token.rsplit_once(':'))
+                .ok_or(Error::InvalidToken)?;
+            let username = user::normalize_username(username);
+            let user = user::get_by_username(&app_context.pool, username.clone()).await?;
+            let verified = user
+                .as_ref()
+                .filter(|user| auth::is_valid_api_token(&app_context.config.hmac_key, user, token))
+                .map(|_| ())
+                .ok_or(Error::InvalidToken);
+            (username, user, verified)
+        }
+        grant_type => {
+            warn!(grant_type, "Unsupported wallabag grant type");
+            return Err(Error::bad_request([(
+                "grant_type",
+                "must be password or refresh_token",
+            )]));
+        }
+    };
+    let user = match (user, verified) {
+        (Some(user), Ok(())) => user,
+        (user, verified) => {
+            audit::record_failed_sign_in(
+                &app_context.pool,
+                user.map(|user| user.user_id),
+                &username,
+                Some(AUDIT_CLIENT),
+                ip,
+            )
+            .await;
+            return Err(verified.err().unwrap_or(Error::WrongCredentials));
+        }
+    };
+    info!(user_id = %user.user_id, "Wallabag client authenticated");
+    let access_token = auth::start_session(
+        &app_context,
+        &user,
+        user_agent
+            .as_ref()
+            .map(|TypedHeader(user_agent)| user_agent.as_str()),
+        ip,
+    )
+    .await?;
+    audit::record(
+        &app_context.pool,
+        user.user_id,
+        AuditAction::SignIn,
+        Some(AUDIT_CLIENT),
+        ip,
+    )
+    .await;
+    Ok(Json(TokenResponse {
+        access_token,
+        expires_in: auth::ACCESS_TOKEN_TTL.num_seconds(),
+        token_type: "bearer",
+        scope: None,
+        refresh_token: format!(
+            "{}:{}",
+            user.username,
+            auth::api_token(&app_context.config.hmac_key, &user)
+        ),
+    }))
+}
+
+async fn version() -> Json<&'static str> {
+    Json(WALLABAG_VERSION)
+}
+
+async fn info() -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "appname": "wallabag",
+        "version": WALLABAG_VERSION,
+        "allowed_registration": false,
+    }))
+}
+
+/// Entries are addressed as `{id}` or `{id}.json`.
+fn parse_entry_id(entry: &str) -> Option<i64> {
+    entry.strip_suffix(".json").unwrap_or(entry).parse().ok()
+}
+
+fn wallabag_time(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S%z").to_string()
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct EntryTag {
+    id: i64,
+    label: String,
+    slug: String,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Link {
+    href: String,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct EntryLinks {
+    #[serde(rename = "self")]
+    self_link: Link,
+}
+
+/// A bookmark as wallabag describes entries. Nothing is archived or starred,
+/// bookmark-hub has no such state.
+#[derive(Debug, PartialEq, Serialize)]
+struct Entry {
+    id: i64,
+    url: String,
+    given_url: String,
+    title: String,
+    content: Option<String>,
+    domain_name: String,
+    created_at: String,
+    updated_at: String,
+    is_archived: u8,
+    is_starred: u8,
+    is_public: bool,
+    tags: Vec<EntryTag>,
+    annotations: Vec<serde_json::Value>,
+    reading_time: i32,
+    mimetype: &'static str,
+    language: Option<String>,
+    preview_picture: Option<String>,
+    user_name: String,
+    #[serde(rename = "_links")]
+    links: EntryLinks,
+}
+
+impl Entry {
+    fn new(bookmark: &Bookmark, user_name: &str, content: Option<String>) -> Self {
+        let id = wallabag_entry_id(&bookmark.bookmark_id);
+        let tags = bookmark
+            .tags
+            .iter()
+            .flatten()
+            .map(|tag| EntryTag {
+                id: wallabag_entry_id(tag),
+                label: tag.clone(),
+                slug: tag.replace(' ', "-"),
+            })
+            .collect();
+        Entry {
+            id,
+            url: bookmark.url.clone(),
+            given_url: bookmark.url.clone(),
+            title: bookmark.title.clone(),
+            content,
+            domain_name: bookmark.domain.clone(),
+            created_at: wallabag_time(bookmark.created_at),
+            updated_at: wallabag_time(bookmark.updated_at.unwrap_or(bookmark.created_at)),
+            is_archived: 0,
+            is_starred: 0,
+            is_public: false,
+            tags,
+            annotations: Vec::new(),
+            reading_time: bookmark.reading_time_minutes,
+            mimetype: "text/html",
+            language: None,
+            preview_picture: None,
+            user_name: user_name.to_string(),
+            links: EntryLinks {
+                self_link: Link {
+                    href: format!("/api/entries/{id}"),
+                },
+            },
+        }
+    }
+}
+
+/// The captured page with its images signed for as long as a share link, so
+/// offline readers have time to download them.
+async fn entry_content(
+    app_context: &AppContext,
+    user_id: Uuid,
+    bookmark_id: &str,
+) -> Result<Option<String>> {
+    let Some(page) = app_context
+        .storage
+        .get(&format!("{user_id}/{bookmark_id}/{PAGE_NAME}"))
+        .await?
+    else {
+        return Ok(None);
+    };
+    let page = static_content::signed_page(
+        page,
+        user_id,
+        bookmark_id,
+        &app_context.config.hmac_key,
+        Utc::now() + bookmark_endpoints::SHARE_LINK_TTL,
+    )?;
+    Ok(Some(page))
+}
+
+async fn find_entry(app_context: &AppContext, user_id: Uuid, entry: &str) -> Result<Bookmark> {
+    let id = parse_entry_id(entry).ok_or(Error::NotFound)?;
+    bookmark::get_by_wallabag_entry_id(&app_context.pool, user_id, id)
+        .await?
+        .ok_or(Error::NotFound)
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntriesQuery {
+    archive: Option<u8>,
+    starred: Option<u8>,
+    /// `created` or `updated`
+    sort: Option<String>,
+    /// `asc` or `desc`
+    order: Option<String>,
+    page: Option<usize>,
+    per_page: Option<usize>,
+    /// Comma-separated, entries must have them all
+    tags: Option<String>,
+    /// Unix timestamp, entries updated since then only
+    since: Option<i64>,
+    /// `metadata` leaves the content out
+    detail: Option<String>,
+}
+
+/// The bookmarks matching `query`, in its order.
+fn filter_entries(mut bookmarks: Vec<Bookmark>, query: &EntriesQuery) -> Vec<Bookmark> {
+    if query.archive == Some(1) || query.starred == Some(1) {
+        return Vec::new();
+    }
+    let tags: Vec<String> = query
+        .tags
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    let since = query
+        .since
+        .and_then(|since| DateTime::from_timestamp(since, 0));
+    let changed_at = |bookmark: &Bookmark| bookmark.updated_at.unwrap_or(bookmark.created_at);
+    bookmarks.retain(|bookmark| {
+        let bookmark_tags = bookmark.tags.as_deref().unwrap_or_default();
+        tags.iter().all(|tag| bookmark_tags.contains(tag))
+            && since.is_none_or(|since| changed_at(bookmark) >= since)
+    });
+    if query.sort.as_deref() == Some("updated") {
+        bookmarks.sort_by_key(changed_at);
+    } else {
+        bookmarks.sort_by_key(|bookmark| bookmark.created_at);
+    }
+    if query.order.as_deref() != Some("asc") {
+        bookmarks.reverse();
+    }
+    bookmarks
+}
+
+async fn list_entries(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Query(query): Query<EntriesQuery>,
+) -> Result<Json<serde_json::Value>> {
+    let bookmarks = bookmark::get_by_user(&app_context.pool, claims.user_id).await?;
+    let bookmarks = filter_entries(bookmarks, &query);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+    let page = query.page.unwrap_or(1).max(1);
+    let total = bookmarks.len();
+    let with_content = query.detail.as_deref() != Some("metadata");
+    let mut items = Vec::new();
+    for bookmark in bookmarks.iter().skip((page - 1) * per_page).take(per_page) {
+        let content = if with_content {
+            entry_content(&app_context, claims.user_id, &bookmark.bookmark_id).await?
+        } else {
+            None
+        };
+        items.push(Entry::new(bookmark, &claims.sub, content));
+    }
+    debug!(user_id = %claims.user_id, page, entry_count = items.len(), "Listing wallabag entries");
+    Ok(Json(serde_json::json!({
+        "page": page,
+        "limit": per_page,
+        "pages": total.div_ceil(per_page).max(1),
+        "total": total,
+        "_links": { "self": { "href": format!("/api/entries?page={page}&perPage={per_page}") } },
+        "_embedded": { "items": items },
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+struct NewEntry {
+    url: String,
+    /// Comma-separated
+    tags: Option<String>,
+}
+
+/// The page is captured in the background, until then the entry has no
+/// content and its URL as title. Its id is already the final one.
+async fn add_entry(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    JsonOrForm(input): JsonOrForm<NewEntry>,
+) -> Result<Json<Entry>> {
+    let url = Url::parse(&input.url)
+        .map_err(|_| Error::unprocessable_entity([("url", "must be a valid URL")]))?;
+    if let Some(existing) =
+        bookmark::get_by_canonical_url_and_user_id(&app_context.pool, url.as_str(), claims.user_id)
+            .await?
+    {
+        let content = entry_content(&app_context, claims.user_id, &existing.bookmark_id).await?;
+        return Ok(Json(Entry::new(&existing, &claims.sub, content)));
+    }
+    let tags = input
+        .tags
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect::<Vec<_>>();
+    let pending = Bookmark {
+        bookmark_id: make_bookmark_id(&url)?,
+        user_id: claims.user_id,
+        url: url.to_string(),
+        domain: domain_from_url(&url)?,
+        title: url.to_string(),
+        tags: Some(tags.clone()),
+        summary: None,
+        created_at: Utc::now(),
+        updated_at: None,
+        word_count: 0,
+        reading_time_minutes: 0,
+        kind: None,
+        favorite: false,
+        original_title: None,
+        visibility: Visibility::Private,
+    };
+    let task = bookmark_endpoints::enqueue(&app_context, claims.user_id, url, tags, false).await?;
+    info!(user_id = %claims.user_id, task_id = %task.task_id, "Wallabag entry added");
+    Ok(Json(Entry::new(&pending, &claims.sub, None)))
+}
+
+async fn get_entry(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(entry): Path<String>,
+) -> Result<Json<Entry>> {
+    let bookmark = find_entry(&app_context, claims.user_id, &entry).await?;
+    let content = entry_content(&app_context, claims.user_id, &bookmark.bookmark_id).await?;
+    Ok(Json(Entry::new(&bookmark, &claims.sub, content)))
+}
+
+/// Answers with the deleted entry, as wallabag does.
+async fn delete_entry(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(entry): Path<String>,
+) -> Result<Json<Entry>> {
+    let bookmark = find_entry(&app_context, claims.user_id, &entry).await?;
+    bookmark_endpoints::remove(&app_context, claims.user_id, &bookmark.bookmark_id, None).await?;
+    info!(user_id = %claims.user_id, bookmark_id = %bookmark.bookmark_id, "Wallabag entry deleted");
+    Ok(Json(Entry::new(&bookmark, &claims.sub, None)))
+}
+
+/// `export.md` and `export.txt`, the formats that need no document rendering.
+async fn export_entry(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path((entry, export)): Path<(String, String)>,
+) -> Result<Response> {
+    let bookmark = find_entry(&app_context, claims.user_id, &entry).await?;
+    match export.as_str() {
+        "export.md" => {
+            let page = app_context
+                .storage
+                .get(&format!(
+                    "{}/{}/{PAGE_NAME}",
+                    claims.user_id, bookmark.bookmark_id
+                ))
+                .await?
+                .ok_or(Error::NotFound)?;
+            let page = static_storage::decode(page.bytes, page.content_encoding)?;
+            let markdown = markdown::article(&bookmark, &String::from_utf8_lossy(&page));
+            Ok((
+                [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+                markdown,
+            )
+                .into_response())
+        }
+        "export.txt" => {
+            let text = bookmark::get_text_content(
+                &app_context.pool,
+                claims.user_id,
+                &bookmark.bookmark_id,
+            )
+            .await?
+            .ok_or(Error::NotFound)?;
+            Ok(([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], text).into_response())
+        }
+        _ => Err(Error::NotFound),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use shared::{Bookmark, Visibility};
+    use uuid::Uuid;
+
+    use super::{filter_entries, parse_entry_id, EntriesQuery, Entry};
+    use crate::db::bookmark::wallabag_entry_id;
+
+    fn bookmark(bookmark_id: &str, tags: &[&str], day: u32) -> Bookmark {
+        Bookmark {
+            bookmark_id: bookmark_id.to_string(),
+            user_id: Uuid::nil(),
+            url: format!("https://example.com/{bookmark_id}"),
+            domain: "example.com".to_string(),
+            title: bookmark_id.to_string(),
+            tags: Some(tags.iter().map(ToString::to_string).collect()),
+            summary: None,
+            created_at: Utc.with_ymd_and_hms(2024, 5, day, 8, 0, 0).unwrap(),
+            updated_at: None,
+            word_count: 0,
+            reading_time_minutes: 0,
+            kind: None,
+            favorite: false,
+            original_title: None,
+            visibility: Visibility::Private,
+        }
+    }
+
+    #[test]
+    fn entry_ids_are_stable_positive_integers() {
+        let id = wallabag_entry_id("AAECAwQFBgcICQoLDA0ODw");
+        assert_eq!(id, wallabag_entry_id("AAECAwQFBgcICQoLDA0ODw"));
+        assert_ne!(id, wallabag_entry_id("DwAOAA0ADAALAAoACQAIBw"));
+        assert!(id >= 0);
+        assert_eq!(parse_entry_id(&format!("{id}.json")), Some(id));
+        assert_eq!(parse_entry_id(&id.to_string()), Some(id));
+        assert_eq!(parse_entry_id("abc.json"), None);
+    }
+
+    #[test]
+    fn entries_are_newest_first_and_filtered_by_tags() {
+        let bookmarks = vec![
+            bookmark("a", &["rust"], 1),
+            bookmark("b", &["rust", "web"], 2),
+            bookmark("c", &["web"], 3),
+        ];
+        let titles = |query: &EntriesQuery| {
+            filter_entries(bookmarks.clone(), query)
+                .into_iter()
+                .map(|bookmark| bookmark.title)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(titles(&EntriesQuery::default()), ["c", "b", "a"]);
+        let rust = EntriesQuery {
+            tags: Some("Rust".to_string()),
+            order: Some("asc".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(titles(&rust), ["a", "b"]);
+        let archived = EntriesQuery {
+            archive: Some(1),
+            ..Default::default()
+        };
+        assert!(titles(&archived).is_empty());
+    }
+
+    #[test]
+    fn entries_use_wallabag_dates_and_tags() {
+        let entry = Entry::new(&bookmark("a", &["rust"], 1), "alice", None);
+        assert_eq!(entry.created_at, "2024-05-01T08:00:00+0000");
+        assert_eq!(entry.tags[0].label, "rust");
+        assert_eq!(
+            entry.links.self_link.href,
+            format!("/api/entries/{}", entry.id)
+        );
+    }
+}
@@ -0,0 +1,367 @@
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, Path};
+use axum::http::StatusCode;
+use axum::routing::{delete, get, post};
+use axum::{Extension, Json, Router};
+use axum_macros::debug_handler;
+use shared::{
+    AuditAction, Bookmark, Workspace, WorkspaceBookmarkRequest, WorkspaceMemberRequest,
+    WorkspaceMembers, WorkspaceRequest, WorkspaceRole, Workspaces,
+};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use super::{audit, Claim};
+use crate::db::{bookmark, user, workspace};
+use crate::error::{Error, Result};
+use crate::AppContext;
+
+const MAX_NAME_CHARS: usize = 200;
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/workspaces", get(list_workspaces).post(create_workspace))
+        .route(
+            "/workspaces/{id}",
+            get(get_workspace).delete(delete_workspace),
+        )
+        .route(
+            "/workspaces/{id}/members",
+            get(list_members).put(set_member),
+        )
+        .route("/workspaces/{id}/members/{user_id}", delete(remove_member))
+        .route("/workspaces/{id}/bookmarks", post(add_bookmark))
+        .route(
+            "/workspaces/{id}/bookmarks/{bookmark_id}",
+            get(get_bookmark).delete(remove_bookmark),
+        )
+}
+
+/// The workspace as `user_id` sees it, not found unless they are a member
+/// with at least `role`.
+async fn require_role(
+    app_context: &AppContext,
+    user_id: Uuid,
+    workspace_id: Uuid,
+    role: WorkspaceRole,
+) -> Result<Workspace> {
+    let workspace = workspace::get(&app_context.pool, user_id, workspace_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    if workspace.role < role {
+        return Err(Error::Forbidden);
+    }
+    Ok(workspace)
+}
+
+/// Not found unless the user is a member of `workspace_id`, for requests
+/// scoped to a workspace.
+pub(super) async fn require_member(
+    app_context: &AppContext,
+    user_id: Uuid,
+    workspace_id: Option<Uuid>,
+) -> Result<()> {
+    if let Some(workspace_id) = workspace_id {
+        require_role(app_context, user_id, workspace_id, WorkspaceRole::Viewer).await?;
+    }
+    Ok(())
+}
+
+fn validate_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+        return Err(Error::unprocessable_entity([(
+            "name",
+            "must have between 1 and 200 characters",
+        )]));
+    }
+    Ok(name.to_string())
+}
+
+#[utoipa::path(
+    get,
+    path = "/workspaces",
+    tag = "workspaces",
+    responses((status = 200, body = Workspaces))
+)]
+#[debug_handler]
+async fn list_workspaces(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+) -> Result<Json<Workspaces>> {
+    let workspaces = workspace::list(&app_context.pool, claims.user_id).await?;
+    debug!(
+        user_id = %claims.user_id,
+        workspace_count = workspaces.len(),
+        "Listed workspaces"
+    );
+    Ok(Json(Workspaces { workspaces }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/workspaces",
+    tag = "workspaces",
+    request_body = WorkspaceRequest,
+    responses((status = 201, body = Workspace), (status = 422))
+)]
+#[debug_handler]
+async fn create_workspace(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Json(request): Json<WorkspaceRequest>,
+) -> Result<(StatusCode, Json<Workspace>)> {
+    let name = validate_name(&request.name)?;
+    let created = workspace::create(&app_context.pool, claims.user_id, &name).await?;
+    info!(
+        user_id = %claims.user_id,
+        workspace_id = %created.workspace_id,
+        "Workspace created"
+    );
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/workspaces/{id}",
+    tag = "workspaces",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = Workspace), (status = 404))
+)]
+#[debug_handler]
+async fn get_workspace(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Workspace>> {
+    workspace::get(&app_context.pool, claims.user_id, id)
+        .await?
+        .map(Json)
+        .ok_or(Error::NotFound)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/workspaces/{id}",
+    tag = "workspaces",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 204, description = "Workspace deleted, its bookmarks stay with their owners"),
+        (status = 403),
+        (status = 404)
+    )
+)]
+#[debug_handler]
+async fn delete_workspace(
+    claims: Claim,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    require_role(&app_context, claims.user_id, id, WorkspaceRole::Owner).await?;
+    if !workspace::delete(&app_context.pool, claims.user_id, id).await? {
+        return Err(Error::NotFound);
+    }
+    audit::record(
+        &app_context.pool,
+        claims.user_id,
+        AuditAction::WorkspaceDeleted,
+        Some(&id.to_string()),
+        Some(client_addr.ip()),
+    )
+    .await;
+    info!(user_id = %claims.user_id, workspace_id = %id, "Workspace deleted");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/workspaces/{id}/members",
+    tag = "workspaces",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = WorkspaceMembers), (status = 404))
+)]
+#[debug_handler]
+async fn list_members(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<WorkspaceMembers>> {
+    require_role(&app_context, claims.user_id, id, WorkspaceRole::Viewer).await?;
+    let members = workspace::members(&app_context.pool, id).await?;
+    Ok(Json(WorkspaceMembers { members }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/workspaces/{id}/members",
+    tag = "workspaces",
+    params(("id" = Uuid, Path)),
+    request_body = WorkspaceMemberRequest,
+    responses((status = 200, body = WorkspaceMembers), (status = 403), (status = 404), (status = 422))
+)]
+#[debug_handler]
+async fn set_member(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<WorkspaceMemberRequest>,
+) -> Result<Json<WorkspaceMembers>> {
+    require_role(&app_context, claims.user_id, id, WorkspaceRole::Owner).await?;
+    if request.role == WorkspaceRole::Owner {
+        return Err(Error::unprocessable_entity([(
+            "role",
+            "must be viewer or editor",
+        )]));
+    }
+    let member = user::get_by_username(&app_context.pool, request.username.trim().to_string())
+        .await?
+        .ok_or_else(|| Error::unprocessable_entity([("username", "is not a user")]))?;
+    if !workspace::set_member(&app_context.pool, id, member.user_id, request.role).await? {
+        return Err(Error::unprocessable_entity([(
+            "username",
+            "is the owner of the workspace",
+        )]));
+    }
+    info!(
+        user_id = %claims.user_id,
+        workspace_id = %id,
+        member_id = %member.user_id,
+        role = request.role.as_ref(),
+        "Workspace member set"
+    );
+    let members = workspace::members(&app_context.pool, id).await?;
+    Ok(Json(WorkspaceMembers { members }))
+}
+
+/// The owner removes members, any other member can leave.
+#[utoipa::path(
+    delete,
+    path = "/workspaces/{id}/members/{user_id}",
+    tag = "workspaces",
+    params(("id" = Uuid, Path), ("user_id" = Uuid, Path)),
+    responses(
+        (status = 204, description = "Member removed"),
+        (status = 403),
+        (status = 404),
+        (status = 422)
+    )
+)]
+#[debug_handler]
+async fn remove_member(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path((id, member_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    let required = if member_id == claims.user_id {
+        WorkspaceRole::Viewer
+    } else {
+        WorkspaceRole::Owner
+    };
+    let workspace = require_role(&app_context, claims.user_id, id, required).await?;
+    if member_id == claims.user_id && workspace.role == WorkspaceRole::Owner {
+        return Err(Error::unprocessable_entity([(
+            "user_id",
+            "is the owner, who deletes the workspace instead",
+        )]));
+    }
+    if !workspace::remove_member(&app_context.pool, id, member_id).await? {
+        return Err(Error::NotFound);
+    }
+    info!(
+        user_id = %claims.user_id,
+        workspace_id = %id,
+        member_id = %member_id,
+        "Workspace member removed"
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/workspaces/{id}/bookmarks",
+    tag = "workspaces",
+    params(("id" = Uuid, Path)),
+    request_body = WorkspaceBookmarkRequest,
+    responses(
+        (status = 204, description = "Bookmark saved into the workspace"),
+        (status = 403),
+        (status = 404),
+        (status = 422)
+    )
+)]
+#[debug_handler]
+async fn add_bookmark(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<WorkspaceBookmarkRequest>,
+) -> Result<StatusCode> {
+    require_role(&app_context, claims.user_id, id, WorkspaceRole::Editor).await?;
+    if !workspace::add_bookmark(&app_context.pool, id, claims.user_id, &request.bookmark_id).await?
+    {
+        return Err(Error::unprocessable_entity([(
+            "bookmark_id",
+            "must be a bookmark of the user that is not private",
+        )]));
+    }
+    debug!(
+        user_id = %claims.user_id,
+        workspace_id = %id,
+        bookmark_id = %request.bookmark_id,
+        "Bookmark saved into workspace"
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// A bookmark another member saved into the workspace, read by any member.
+#[utoipa::path(
+    get,
+    path = "/workspaces/{id}/bookmarks/{bookmark_id}",
+    tag = "workspaces",
+    params(("id" = Uuid, Path), ("bookmark_id" = String, Path)),
+    responses((status = 200, body = Bookmark), (status = 404))
+)]
+#[debug_handler]
+async fn get_bookmark(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path((id, bookmark_id)): Path<(Uuid, String)>,
+) -> Result<Json<Bookmark>> {
+    require_role(&app_context, claims.user_id, id, WorkspaceRole::Viewer).await?;
+    bookmark::get_in_workspace(&app_context.pool, id, &bookmark_id)
+        .await?
+        .map(Json)
+        .ok_or(Error::NotFound)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/workspaces/{id}/bookmarks/{bookmark_id}",
+    tag = "workspaces",
+    params(("id" = Uuid, Path), ("bookmark_id" = String, Path)),
+    responses(
+        (status = 204, description = "Bookmark removed from the workspace, not deleted"),
+        (status = 403),
+        (status = 404)
+    )
+)]
+#[debug_handler]
+async fn remove_bookmark(
+    claims: Claim,
+    Extension(app_context): Extension<AppContext>,
+    Path((id, bookmark_id)): Path<(Uuid, String)>,
+) -> Result<StatusCode> {
+    require_role(&app_context, claims.user_id, id, WorkspaceRole::Editor).await?;
+    if !workspace::remove_bookmark(&app_context.pool, id, &bookmark_id).await? {
+        return Err(Error::NotFound);
+    }
+    debug!(
+        user_id = %claims.user_id,
+        workspace_id = %id,
+        bookmark_id = %bookmark_id,
+        "Bookmark removed from workspace"
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,18 +1,16 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
 
-use axum::http::header::WWW_AUTHENTICATE;
+use axum::http::header::{CONTENT_TYPE, RETRY_AFTER, WWW_AUTHENTICATE};
 use axum::http::{HeaderMap, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
+use shared::{ErrorCode, Problem};
 use tracing::{debug, error, warn};
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
-#[derive(serde::Serialize)]
-struct ErrorsPayload {
-    errors: HashMap<Cow<'static, str>, Vec<Cow<'static, str>>>,
-}
+const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -28,18 +26,23 @@ pub enum Error {
     },
     #[error("invalid_payload")]
     BadRequest {
+        code: ErrorCode,
         errors: HashMap<Cow<'static, str>, Vec<Cow<'static, str>>>,
     },
     #[error("database_error")]
     DatabaseError(#[from] tokio_postgres::Error),
     #[error("database_error")]
     DatabasePool(#[from] deadpool_postgres::PoolError),
+    #[error("database_error")]
+    Sqlite(#[from] rusqlite::Error),
     #[error("constraint_violation")]
     ConstraintViolation { constraint: String, message: String },
     #[error("internal_server_error")]
     Anyhow(#[from] anyhow::Error),
     #[error("too_many_requests")]
     TooManyRequests { retry_after_secs: u64 },
+    #[error("storage quota of {quota_bytes} bytes reached ({used_bytes} bytes used)")]
+    StorageQuotaExceeded { used_bytes: i64, quota_bytes: i64 },
     #[error("wrong_credentials")]
     WrongCredentials,
     #[error("missing_credentials")]
@@ -84,7 +87,18 @@ impl Error {
                 .push(val.into());
         }
 
-        Self::BadRequest { errors: error_map }
+        Self::BadRequest {
+            code: ErrorCode::InvalidPayload,
+            errors: error_map,
+        }
+    }
+
+    /// Replaces the generic `invalid_payload` code of a bad request.
+    pub fn with_code(self, code: ErrorCode) -> Self {
+        match self {
+            Self::BadRequest { errors, .. } => Self::BadRequest { code, errors },
+            other => other,
+        }
     }
 
     pub fn constraint_violation(constraint: &str, message: &str) -> Self {
@@ -110,67 +124,110 @@ impl Error {
             Error::ConstraintViolation { .. } => StatusCode::BAD_REQUEST,
             Error::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::DatabasePool(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::Sqlite(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::Forbidden => StatusCode::FORBIDDEN,
             Error::InvalidToken => StatusCode::BAD_REQUEST,
             Error::Jwt(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::MissingCredentials => StatusCode::BAD_REQUEST,
             Error::NotFound => StatusCode::NOT_FOUND,
             Error::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Error::StorageQuotaExceeded { .. } => StatusCode::FORBIDDEN,
             Error::Unauthorized => StatusCode::UNAUTHORIZED,
             Error::UnprocessableEntity { .. } => StatusCode::UNPROCESSABLE_ENTITY,
             Error::WrongCredentials => StatusCode::UNAUTHORIZED,
         }
     }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::Anyhow(_)
+            | Error::Argon2 { .. }
+            | Error::DatabaseError(_)
+            | Error::DatabasePool(_)
+            | Error::Sqlite(_)
+            | Error::Jwt(_) => ErrorCode::InternalError,
+            Error::BadRequest { code, .. } => *code,
+            Error::ConstraintViolation { constraint, .. } => match constraint.as_str() {
+                "duplicate_bookmark" => ErrorCode::DuplicateBookmark,
+                "unique_username" => ErrorCode::UsernameTaken,
+                "unique_email" => ErrorCode::EmailTaken,
+                _ => ErrorCode::InvalidPayload,
+            },
+            Error::Forbidden => ErrorCode::ActionNotAllowed,
+            Error::InvalidToken => ErrorCode::InvalidToken,
+            Error::MissingCredentials => ErrorCode::MissingCredentials,
+            Error::NotFound => ErrorCode::NotFound,
+            Error::TooManyRequests { .. } => ErrorCode::TooManyRequests,
+            Error::StorageQuotaExceeded { .. } => ErrorCode::QuotaExceeded,
+            Error::Unauthorized => ErrorCode::AuthenticationRequired,
+            Error::UnprocessableEntity { .. } => ErrorCode::InvalidPayload,
+            Error::WrongCredentials => ErrorCode::WrongCredentials,
+        }
+    }
+
+    /// Internal failures carry no detail, their causes are only logged.
+    fn problem(&self) -> Problem {
+        let status = self.status_code();
+        let mut problem = Problem {
+            problem_type: "about:blank".to_string(),
+            title: status.canonical_reason().unwrap_or_default().to_string(),
+            status: status.as_u16(),
+            code: self.code(),
+            detail: None,
+            errors: HashMap::new(),
+        };
+        match self {
+            Self::BadRequest { errors, .. } | Self::UnprocessableEntity { errors } => {
+                problem.errors = errors
+                    .iter()
+                    .map(|(field, messages)| {
+                        let messages = messages.iter().map(|m| m.to_string()).collect();
+                        (field.to_string(), messages)
+                    })
+                    .collect();
+            }
+            Self::ConstraintViolation { message, .. } => problem.detail = Some(message.clone()),
+            Self::TooManyRequests { retry_after_secs } => {
+                problem.detail = Some(format!("retry after {retry_after_secs} seconds"));
+            }
+            Self::StorageQuotaExceeded { .. } => {
+                problem.detail = Some(self.to_string());
+                problem.errors = HashMap::from([("storage".to_string(), vec![self.to_string()])]);
+            }
+            _ => {}
+        }
+        problem
+    }
 }
 
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(PROBLEM_CONTENT_TYPE));
         match self {
-            Self::BadRequest { ref errors } => {
+            Self::BadRequest { ref errors, .. } => {
                 warn!(errors = ?errors, "Bad request");
-                let t = (
-                    StatusCode::BAD_REQUEST,
-                    Json(ErrorsPayload {
-                        errors: errors.clone(),
-                    }),
-                );
-                return t.into_response();
             }
             Self::UnprocessableEntity { ref errors } => {
                 warn!(errors = ?errors, "Unprocessable entity");
-                let t = (
-                    StatusCode::UNPROCESSABLE_ENTITY,
-                    Json(ErrorsPayload {
-                        errors: errors.clone(),
-                    }),
-                );
-                return t.into_response();
             }
             Self::TooManyRequests { retry_after_secs } => {
-                warn!(retry_after_secs, "Auth rate limit exceeded");
-                let t = (
-                    StatusCode::TOO_MANY_REQUESTS,
-                    [(
-                        axum::http::header::RETRY_AFTER,
-                        HeaderValue::from_str(&retry_after_secs.to_string())
-                            .unwrap_or_else(|_| HeaderValue::from_static("60")),
-                    )]
-                    .into_iter()
-                    .collect::<HeaderMap>(),
-                    self.to_string(),
+                warn!(retry_after_secs, "Rate limit exceeded");
+                headers.insert(
+                    RETRY_AFTER,
+                    HeaderValue::from_str(&retry_after_secs.to_string())
+                        .unwrap_or_else(|_| HeaderValue::from_static("60")),
                 );
-                return t.into_response();
+            }
+            Self::StorageQuotaExceeded {
+                used_bytes,
+                quota_bytes,
+            } => {
+                warn!(used_bytes, quota_bytes, "Storage quota exceeded");
             }
             Self::Unauthorized => {
                 warn!("Unauthorized access attempt");
-                let t = (
-                    self.status_code(),
-                    [(WWW_AUTHENTICATE, HeaderValue::from_static("Token"))]
-                        .into_iter()
-                        .collect::<HeaderMap>(),
-                    self.to_string(),
-                );
-                return t.into_response();
+                headers.insert(WWW_AUTHENTICATE, HeaderValue::from_static("Token"));
             }
             Self::Forbidden => {
                 warn!("Forbidden access attempt");
@@ -193,6 +250,9 @@ impl IntoResponse for Error {
             Self::DatabasePool(ref e) => {
                 error!(error = %e, "Database pool error occurred");
             }
+            Self::Sqlite(ref e) => {
+                error!(error = %e, "Database error occurred");
+            }
             Self::ConstraintViolation {
                 ref constraint,
                 ref message,
@@ -214,6 +274,83 @@ impl IntoResponse for Error {
             }
         }
 
-        (self.status_code(), self.to_string()).into_response()
+        (self.status_code(), headers, Json(self.problem())).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::header::{CONTENT_TYPE, RETRY_AFTER};
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+    use shared::{ErrorCode, Problem};
+
+    use super::Error;
+
+    async fn respond(error: Error) -> (StatusCode, Problem) {
+        let response = error.into_response();
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/problem+json");
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn validation_errors_keep_their_fields() {
+        let (status, problem) = respond(Error::unprocessable_entity([(
+            "limit",
+            "must be between 1 and 50",
+        )]))
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(problem.status, 422);
+        assert_eq!(problem.title, "Unprocessable Entity");
+        assert_eq!(problem.code, ErrorCode::InvalidPayload);
+        assert_eq!(problem.errors["limit"], vec!["must be between 1 and 50"]);
+
+        let error = Error::bad_request([("username", "username already created")])
+            .with_code(ErrorCode::UsernameTaken);
+        let (status, problem) = respond(error).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(problem.code, ErrorCode::UsernameTaken);
+    }
+
+    #[tokio::test]
+    async fn constraint_violations_map_to_codes() {
+        let error =
+            Error::constraint_violation("duplicate_bookmark", "bookmark already exists for user");
+        let (_, problem) = respond(error).await;
+        assert_eq!(problem.code, ErrorCode::DuplicateBookmark);
+        assert_eq!(
+            problem.detail.as_deref(),
+            Some("bookmark already exists for user")
+        );
+    }
+
+    #[tokio::test]
+    async fn internal_errors_hide_their_cause() {
+        let (status, problem) = respond(Error::Anyhow(anyhow::anyhow!("disk on fire"))).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(problem.code, ErrorCode::InternalError);
+        assert_eq!(problem.detail, None);
+    }
+
+    #[tokio::test]
+    async fn rate_limits_keep_retry_after() {
+        let response = Error::too_many_requests(30).into_response();
+        assert_eq!(response.headers()[RETRY_AFTER], "30");
+        let (_, problem) = respond(Error::too_many_requests(30)).await;
+        assert_eq!(problem.code, ErrorCode::TooManyRequests);
+    }
+
+    #[test]
+    fn unknown_codes_are_tolerated() {
+        let problem: Problem = serde_json::from_str(
+            r#"{"type":"about:blank","title":"Gone","status":410,"code":"from_the_future"}"#,
+        )
+        .unwrap();
+        assert_eq!(problem.code, ErrorCode::Unknown);
     }
 }
@@ -0,0 +1,303 @@
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use shared::{BookmarkTask, ServerEvent, TaskSubscription};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, error, warn};
+use uuid::Uuid;
+
+const EVENT_BUS_CAPACITY: usize = 256;
+/// Postgres refuses NOTIFY payloads from this size on.
+const RELAY_PAYLOAD_LIMIT: usize = 8000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserEvent {
+    pub user_id: Uuid,
+    pub event: ServerEvent,
+}
+
+/// An event sent to the buses of the other replicas, along with the bus it
+/// comes from, which skips it when it comes back.
+#[derive(Serialize, Deserialize)]
+struct RelayedEvent {
+    origin: Uuid,
+    #[serde(flatten)]
+    event: UserEvent,
+}
+
+/// Fan-out of task and bookmark events to every connected event stream.
+/// Subscribers filter by `user_id`; events published while nobody listens
+/// are dropped. A relayed bus also hands its events over to be sent to the
+/// other replicas, and publishes theirs, so clients of every replica see the
+/// tasks any of them processes.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<UserEvent>,
+    origin: Uuid,
+    relay: Option<mpsc::UnboundedSender<String>>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(EVENT_BUS_CAPACITY);
+        Self {
+            tx,
+            origin: Uuid::new_v4(),
+            relay: None,
+        }
+    }
+
+    /// A bus whose events also come out of the receiver, as payloads to send
+    /// to the other replicas. What they send back goes through
+    /// [`EventBus::publish_relayed`].
+    pub fn relayed() -> (Self, mpsc::UnboundedReceiver<String>) {
+        let (relay_tx, relay_rx) = mpsc::unbounded_channel();
+        let bus = Self {
+            relay: Some(relay_tx),
+            ..Self::new()
+        };
+        (bus, relay_rx)
+    }
+
+    pub fn publish(&self, user_id: Uuid, event: ServerEvent) {
+        let event = UserEvent { user_id, event };
+        if let Some(relay) = self.relay.as_ref().filter(|relay| !relay.is_closed()) {
+            self.relay(relay, event.clone());
+        }
+        self.broadcast(event);
+    }
+
+    /// Publishes an event relayed by another replica. Events this bus sent
+    /// itself are skipped, its subscribers already have them.
+    pub fn publish_relayed(&self, payload: &str) {
+        match serde_json::from_str::<RelayedEvent>(payload) {
+            Ok(relayed) if relayed.origin == self.origin => {}
+            Ok(relayed) => self.broadcast(relayed.event),
+            Err(error) => warn!(?error, "Ignoring malformed relayed event"),
+        }
+    }
+
+    fn broadcast(&self, event: UserEvent) {
+        let user_id = event.user_id;
+        if self.tx.send(event).is_err() {
+            debug!(user_id = %user_id, "No event subscribers, event dropped");
+        }
+    }
+
+    fn relay(&self, relay: &mpsc::UnboundedSender<String>, event: UserEvent) {
+        let user_id = event.user_id;
+        let relayed = RelayedEvent {
+            origin: self.origin,
+            event,
+        };
+        match serde_json::to_string(&relayed) {
+            Ok(payload) if payload.len() < RELAY_PAYLOAD_LIMIT => {
+                // A closed relay means this replica stopped relaying
+                let _ = relay.send(payload);
+            }
+            Ok(payload) => warn!(
+                user_id = %user_id,
+                size = payload.len(),
+                "Event too large to relay to other replicas"
+            ),
+            Err(error) => error!(?error, "Failed to encode relayed event"),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<UserEvent> {
+        self.tx.subscribe()
+    }
+
+    /// Events for a single user. Slow consumers skip what they missed rather
+    /// than ending the stream.
+    pub fn subscribe_user(&self, user_id: Uuid) -> impl Stream<Item = ServerEvent> {
+        futures::stream::unfold(self.subscribe(), move |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) if event.user_id == user_id => return Some((event.event, rx)),
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!(user_id = %user_id, skipped, "Event subscriber lagged behind");
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    /// Updates of the tasks of a user matching `subscription`, so a filtered
+    /// task list follows its rows without receiving every event. Status
+    /// changes are all sent, since the list can't tell which tasks it shows.
+    pub fn subscribe_tasks(
+        &self,
+        user_id: Uuid,
+        subscription: TaskSubscription,
+    ) -> impl Stream<Item = ServerEvent> {
+        let filter = TaskFilter::from(subscription);
+        self.subscribe_user(user_id).filter(move |event| {
+            let matches = match event {
+                ServerEvent::TaskUpdated { task } => filter.matches(task),
+                _ => false,
+            };
+            futures::future::ready(matches)
+        })
+    }
+}
+
+struct TaskFilter {
+    subscription: TaskSubscription,
+    tags: Vec<String>,
+}
+
+impl From<TaskSubscription> for TaskFilter {
+    fn from(subscription: TaskSubscription) -> Self {
+        let tags = subscription
+            .tags
+            .iter()
+            .flat_map(|tags| tags.split(','))
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect();
+        Self { subscription, tags }
+    }
+}
+
+impl TaskFilter {
+    fn matches(&self, task: &BookmarkTask) -> bool {
+        self.subscription
+            .url
+            .as_ref()
+            .is_none_or(|url| task.url.contains(url.as_str()))
+            && self
+                .tags
+                .iter()
+                .all(|tag| task.tags.as_ref().is_some_and(|tags| tags.contains(tag)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use futures::StreamExt;
+    use shared::{BookmarkTask, BookmarkTaskStatus, ServerEvent, TaskSubscription};
+    use uuid::Uuid;
+
+    use super::EventBus;
+
+    fn task_event(user_id: Uuid) -> ServerEvent {
+        ServerEvent::TaskUpdated {
+            task: BookmarkTask {
+                task_id: Uuid::new_v4(),
+                user_id,
+                url: "https://example.com".into(),
+                status: BookmarkTaskStatus::Pending,
+                tags: None,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                next_delivery: Utc::now(),
+                retries: None,
+                fail_reason: None,
+                ignore_robots: false,
+                refresh: false,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn user_stream_only_sees_own_events() {
+        let bus = EventBus::new();
+        let alice = Uuid::new_v4();
+        let bob = Uuid::new_v4();
+        let mut stream = Box::pin(bus.subscribe_user(alice));
+
+        bus.publish(bob, task_event(bob));
+        let expected = task_event(alice);
+        bus.publish(alice, expected.clone());
+
+        assert_eq!(stream.next().await, Some(expected));
+    }
+
+    #[tokio::test]
+    async fn task_stream_only_sees_matching_tasks() {
+        let bus = EventBus::new();
+        let user_id = Uuid::new_v4();
+        let subscription = TaskSubscription {
+            url: Some("example".into()),
+            tags: Some("rust, web".into()),
+        };
+        let mut stream = Box::pin(bus.subscribe_tasks(user_id, subscription));
+
+        let with_tags = |event: ServerEvent, tags: &[&str]| match event {
+            ServerEvent::TaskUpdated { mut task } => {
+                task.tags = Some(tags.iter().map(|tag| tag.to_string()).collect());
+                ServerEvent::TaskUpdated { task }
+            }
+            event => event,
+        };
+        bus.publish(user_id, task_event(user_id));
+        bus.publish(user_id, with_tags(task_event(user_id), &["rust"]));
+        let expected = with_tags(task_event(user_id), &["web", "rust", "db"]);
+        bus.publish(user_id, expected.clone());
+
+        assert_eq!(stream.next().await, Some(expected));
+    }
+
+    #[tokio::test]
+    async fn task_stream_sees_tasks_leaving_a_status() {
+        let bus = EventBus::new();
+        let user_id = Uuid::new_v4();
+        let subscription = TaskSubscription {
+            url: Some("example".into()),
+            tags: None,
+        };
+        let mut stream = Box::pin(bus.subscribe_tasks(user_id, subscription));
+
+        let ServerEvent::TaskUpdated { task } = task_event(user_id) else {
+            unreachable!()
+        };
+        for status in [
+            BookmarkTaskStatus::Done,
+            BookmarkTaskStatus::Fail,
+            BookmarkTaskStatus::Cancelled,
+        ] {
+            let expected = ServerEvent::TaskUpdated {
+                task: BookmarkTask {
+                    status,
+                    ..task.clone()
+                },
+            };
+            bus.publish(user_id, expected.clone());
+            assert_eq!(stream.next().await, Some(expected));
+        }
+    }
+
+    #[tokio::test]
+    async fn relayed_events_reach_other_buses_once() {
+        let (replica_a, mut relay_a) = EventBus::relayed();
+        let (replica_b, _relay_b) = EventBus::relayed();
+        let user_id = Uuid::new_v4();
+        let mut stream_a = Box::pin(replica_a.subscribe_user(user_id));
+        let mut stream_b = Box::pin(replica_b.subscribe_user(user_id));
+
+        let expected = task_event(user_id);
+        replica_a.publish(user_id, expected.clone());
+        let payload = relay_a.recv().await.expect("event relayed");
+        // Every replica listening receives the notification, its sender too
+        replica_a.publish_relayed(&payload);
+        replica_b.publish_relayed(&payload);
+        replica_b.publish_relayed("not an event");
+
+        assert_eq!(stream_b.next().await, Some(expected.clone()));
+        assert_eq!(stream_a.next().await, Some(expected));
+        let second = task_event(user_id);
+        replica_a.publish(user_id, second.clone());
+        assert_eq!(stream_a.next().await, Some(second));
+    }
+}
@@ -0,0 +1,167 @@
+//! The HTTP client fetching robots.txt and images, through `FETCH_PROXY` when
+//! set, with the User-Agent configured for each domain and only to the
+//! addresses [`UrlGuard`] allows.
+
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use reqwest::header::{HeaderValue, USER_AGENT};
+use reqwest::redirect::Policy;
+use reqwest::{Client, Proxy, RequestBuilder};
+use url::Url;
+
+use crate::bookmark_identity::covers_domain;
+use crate::url_guard::{BlockedUrl, UrlGuard};
+use crate::FetchParams;
+
+const MAX_REDIRECTS: usize = 10;
+
+/// User-Agent sent to a domain and its subdomains instead of
+/// `FETCH_USER_AGENT`, written `domain=agent`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserAgentOverride {
+    pub domain: String,
+    pub user_agent: String,
+}
+
+impl FromStr for UserAgentOverride {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (domain, user_agent) = value
+            .split_once('=')
+            .ok_or_else(|| format!("expected domain=agent, got {value:?}"))?;
+        let domain = domain.trim().trim_matches('.').to_lowercase();
+        if domain.is_empty() || domain.contains(|c: char| c == '/' || c.is_whitespace()) {
+            return Err(format!("{domain:?} is not a domain name"));
+        }
+        let user_agent = user_agent.trim().to_string();
+        if user_agent.is_empty() || HeaderValue::from_str(&user_agent).is_err() {
+            return Err(format!(
+                "{user_agent:?} is not a valid User-Agent for {domain}"
+            ));
+        }
+        Ok(Self { domain, user_agent })
+    }
+}
+
+#[derive(Clone)]
+pub struct FetchClient {
+    http: Client,
+    guard: UrlGuard,
+    proxied: bool,
+    user_agent: Option<String>,
+    overrides: Arc<[UserAgentOverride]>,
+}
+
+impl FetchClient {
+    pub fn new(params: &FetchParams, timeout: Duration) -> Result<Self> {
+        let guard = UrlGuard::new(&params.fetch_allowed_networks);
+        let redirect_guard = guard.clone();
+        let mut builder = Client::builder()
+            .timeout(timeout)
+            .dns_resolver(guard.clone())
+            .redirect(Policy::custom(move |attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS {
+                    attempt.error("too many redirects")
+                } else if let Err(blocked) = redirect_guard.check(attempt.url()) {
+                    attempt.error(blocked)
+                } else {
+                    attempt.follow()
+                }
+            }));
+        if let Some(proxy) = &params.fetch_proxy {
+            builder = builder.proxy(
+                Proxy::all(proxy.as_str())
+                    .with_context(|| format!("Invalid FETCH_PROXY {proxy}"))?,
+            );
+        }
+        Ok(Self {
+            http: builder
+                .build()
+                .context("Failed to build fetch HTTP client")?,
+            guard,
+            proxied: params.fetch_proxy.is_some(),
+            user_agent: params.fetch_user_agent.clone(),
+            overrides: params.fetch_user_agent_overrides.clone().into(),
+        })
+    }
+
+    /// The User-Agent of the most specific override covering `host`, or the
+    /// default one.
+    pub fn user_agent_for(&self, host: &str) -> Option<&str> {
+        self.overrides
+            .iter()
+            .filter(|agent| covers_domain(&agent.domain, host))
+            .max_by_key(|agent| agent.domain.len())
+            .map(|agent| agent.user_agent.as_str())
+            .or(self.user_agent.as_deref())
+    }
+
+    /// Whether `url` may be fetched, resolving its host unless a proxy does.
+    pub async fn check(&self, url: &Url) -> Result<()> {
+        if self.proxied {
+            Ok(self.guard.check(url)?)
+        } else {
+            self.guard.check_resolved(url).await
+        }
+    }
+
+    pub fn get(&self, url: &Url) -> Result<RequestBuilder, BlockedUrl> {
+        self.guard.check(url)?;
+        let request = self.http.get(url.as_str());
+        Ok(
+            match url.host_str().and_then(|host| self.user_agent_for(host)) {
+                Some(user_agent) => request.header(USER_AGENT, user_agent),
+                None => request,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{FetchClient, UserAgentOverride};
+    use crate::FetchParams;
+
+    #[test]
+    fn overrides_are_parsed() {
+        let parsed: UserAgentOverride = " News.Example.com = Mozilla/5.0 (X11; Linux x86_64) "
+            .parse()
+            .unwrap();
+        assert_eq!(parsed.domain, "news.example.com");
+        assert_eq!(parsed.user_agent, "Mozilla/5.0 (X11; Linux x86_64)");
+
+        assert!("example.com".parse::<UserAgentOverride>().is_err());
+        assert!("example.com/a=agent".parse::<UserAgentOverride>().is_err());
+        assert!("example.com= ".parse::<UserAgentOverride>().is_err());
+        assert!("example.com=a\nb".parse::<UserAgentOverride>().is_err());
+    }
+
+    #[test]
+    fn the_most_specific_override_wins() {
+        let client = FetchClient::new(
+            &FetchParams {
+                fetch_domain_delay_ms: 0,
+                fetch_domain_concurrency: 1,
+                fetch_respect_robots: false,
+                fetch_proxy: None,
+                fetch_allowed_networks: Vec::new(),
+                fetch_user_agent: Some("default".to_string()),
+                fetch_user_agent_overrides: vec![
+                    "example.com=parent".parse().unwrap(),
+                    "news.example.com=child".parse().unwrap(),
+                ],
+            },
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        assert_eq!(client.user_agent_for("news.example.com"), Some("child"));
+        assert_eq!(client.user_agent_for("www.example.com"), Some("parent"));
+        assert_eq!(client.user_agent_for("example.org"), Some("default"));
+    }
+}
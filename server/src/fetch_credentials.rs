@@ -0,0 +1,167 @@
+//! Request headers a user attaches to the pages of a domain, e.g. the session
+//! cookie of a site behind a login wall. They are sealed with AES-256-GCM
+//! before being stored, bound to the user and the domain they belong to.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Context, Result};
+use hmac::{Hmac, KeyInit, Mac};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::bookmark_identity::covers_domain;
+use crate::db::fetch_credential::{self, SealedFetchCredential};
+use crate::db::Database;
+
+/// Headers to attach to the requests for `domain` and its subdomains.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchHeaders {
+    pub domain: String,
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Seals and opens stored headers with a key derived from HMAC_KEY, so
+/// rotating HMAC_KEY leaves the stored credentials unreadable.
+pub struct CredentialCipher {
+    key: LessSafeKey,
+    random: SystemRandom,
+}
+
+impl CredentialCipher {
+    pub fn new(hmac_key: &SecretString) -> Self {
+        let mut mac = Hmac::<Sha256>::new_from_slice(hmac_key.expose_secret().as_bytes())
+            .expect("HMAC can take key of any size");
+        mac.update(b"fetch-credentials");
+        let key = UnboundKey::new(&AES_256_GCM, &mac.finalize().into_bytes())
+            .expect("a SHA-256 digest is an AES-256 key");
+        Self {
+            key: LessSafeKey::new(key),
+            random: SystemRandom::new(),
+        }
+    }
+
+    /// The nonce followed by the encrypted headers and their tag.
+    pub fn seal(
+        &self,
+        user_id: Uuid,
+        domain: &str,
+        headers: &BTreeMap<String, String>,
+    ) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.random
+            .fill(&mut nonce)
+            .map_err(|_| anyhow!("Failed to generate a nonce"))?;
+        let mut sealed = serde_json::to_vec(headers)?;
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(associated_data(user_id, domain)),
+                &mut sealed,
+            )
+            .map_err(|_| anyhow!("Failed to seal fetch credential"))?;
+        Ok([nonce.as_slice(), &sealed].concat())
+    }
+
+    pub fn open(
+        &self,
+        user_id: Uuid,
+        domain: &str,
+        sealed: &[u8],
+    ) -> Result<BTreeMap<String, String>> {
+        if sealed.len() < NONCE_LEN {
+            return Err(anyhow!("Sealed fetch credential is truncated"));
+        }
+        let (nonce, sealed) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| anyhow!("Invalid fetch credential nonce"))?;
+        let mut sealed = sealed.to_vec();
+        let opened = self
+            .key
+            .open_in_place(
+                nonce,
+                Aad::from(associated_data(user_id, domain)),
+                &mut sealed,
+            )
+            .map_err(|_| anyhow!("Failed to open fetch credential, was HMAC_KEY changed?"))?;
+        serde_json::from_slice(opened).context("Fetch credential is not a header map")
+    }
+}
+
+fn associated_data(user_id: Uuid, domain: &str) -> Vec<u8> {
+    [user_id.as_bytes().as_slice(), domain.as_bytes()].concat()
+}
+
+/// The credential of the most specific domain covering `domain`.
+fn credential_for<'a>(
+    credentials: &'a [SealedFetchCredential],
+    domain: &str,
+) -> Option<&'a SealedFetchCredential> {
+    credentials
+        .iter()
+        .filter(|credential| covers_domain(&credential.domain, domain))
+        .max_by_key(|credential| credential.domain.len())
+}
+
+/// Headers the user set for the pages of `domain`, if any.
+pub async fn headers_for(
+    pool: &Database,
+    cipher: &CredentialCipher,
+    user_id: Uuid,
+    domain: &str,
+) -> Result<Option<FetchHeaders>> {
+    let credentials = fetch_credential::list(pool, user_id).await?;
+    let Some(credential) = credential_for(&credentials, domain) else {
+        return Ok(None);
+    };
+    let headers = cipher
+        .open(user_id, &credential.domain, &credential.sealed_headers)
+        .with_context(|| format!("Fetch credential of {}", credential.domain))?;
+    Ok(Some(FetchHeaders {
+        domain: credential.domain.clone(),
+        headers,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    #[test]
+    fn sealed_headers_open_only_for_their_user_and_domain() {
+        let cipher = CredentialCipher::new(&SecretString::from("secret"));
+        let user_id = Uuid::new_v4();
+        let headers = BTreeMap::from([("Cookie".to_string(), "session=abc".to_string())]);
+        let sealed = cipher.seal(user_id, "example.com", &headers).unwrap();
+        assert!(!sealed
+            .windows(b"session=abc".len())
+            .any(|window| window == b"session=abc"));
+
+        assert_eq!(
+            cipher.open(user_id, "example.com", &sealed).unwrap(),
+            headers
+        );
+        assert!(cipher.open(user_id, "other.com", &sealed).is_err());
+        assert!(cipher.open(Uuid::new_v4(), "example.com", &sealed).is_err());
+        let rotated = CredentialCipher::new(&SecretString::from("rotated"));
+        assert!(rotated.open(user_id, "example.com", &sealed).is_err());
+    }
+
+    #[test]
+    fn the_most_specific_domain_wins() {
+        let credential = |domain: &str| SealedFetchCredential {
+            domain: domain.to_string(),
+            sealed_headers: Vec::new(),
+            updated_at: Utc::now(),
+        };
+        let credentials = vec![credential("example.com"), credential("news.example.com")];
+        let domain_of = |domain| credential_for(&credentials, domain).map(|c| c.domain.as_str());
+        assert_eq!(domain_of("news.example.com"), Some("news.example.com"));
+        assert_eq!(domain_of("www.example.com"), Some("example.com"));
+        assert_eq!(domain_of("badexample.com"), None);
+    }
+}
@@ -0,0 +1,159 @@
+use std::io::Cursor;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use tracing::debug;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptimizedImage {
+    pub content_type: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Quality of the lossy re-encodings, high enough that artifacts don't show
+/// at reading size.
+const LOSSY_QUALITY: u8 = 80;
+
+/// Scales the image down to fit `max_dimension` and keeps the smallest of
+/// the original, the image re-encoded in its own format, as WebP, and, when
+/// it has no transparency, as a lossy JPEG.
+///
+/// Formats we can't decode (SVG, AVIF...) and animated GIFs are kept as they
+/// are. The original only competes when it already fits `max_dimension`.
+pub fn optimize(bytes: Vec<u8>, content_type: &str, max_dimension: u32) -> OptimizedImage {
+    let original = |bytes: Vec<u8>| OptimizedImage {
+        content_type: content_type.to_string(),
+        bytes,
+    };
+    let format = match image::guess_format(&bytes) {
+        Ok(ImageFormat::Gif) | Err(_) => return original(bytes),
+        Ok(format) => format,
+    };
+    let decoded = match image::load_from_memory_with_format(&bytes, format) {
+        Ok(decoded) => decoded,
+        Err(error) => {
+            debug!(?error, ?format, "Image not decodable, keeping original");
+            return original(bytes);
+        }
+    };
+
+    let resized = decoded.width() > max_dimension || decoded.height() > max_dimension;
+    let decoded = if resized {
+        decoded.resize(max_dimension, max_dimension, FilterType::Triangle)
+    } else {
+        decoded
+    };
+    let mut formats = vec![format, ImageFormat::WebP];
+    if !decoded.color().has_alpha() {
+        formats.push(ImageFormat::Jpeg);
+    }
+    formats.dedup();
+    let mut best = (!resized).then(|| original(bytes.clone()));
+    for candidate in formats {
+        let encoded = match encode(&decoded, candidate) {
+            Ok(Some(encoded)) => encoded,
+            Ok(None) => continue,
+            Err(error) => {
+                debug!(?error, format = ?candidate, "Image encoding failed");
+                continue;
+            }
+        };
+        if best
+            .as_ref()
+            .is_none_or(|best| encoded.len() < best.bytes.len())
+        {
+            best = Some(OptimizedImage {
+                content_type: candidate.to_mime_type().to_string(),
+                bytes: encoded,
+            });
+        }
+    }
+    best.unwrap_or_else(|| original(bytes))
+}
+
+/// `None` for the formats we don't encode.
+fn encode(image: &DynamicImage, format: ImageFormat) -> image::ImageResult<Option<Vec<u8>>> {
+    let mut output = Cursor::new(Vec::new());
+    match format {
+        ImageFormat::WebP => {
+            // The encoder takes 8-bit RGB(A) only
+            let image = if image.color().has_alpha() {
+                DynamicImage::ImageRgba8(image.to_rgba8())
+            } else {
+                DynamicImage::ImageRgb8(image.to_rgb8())
+            };
+            image.write_with_encoder(WebPEncoder::new_lossless(&mut output))?;
+        }
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut output, LOSSY_QUALITY))?,
+        ImageFormat::Png => image.write_to(&mut output, ImageFormat::Png)?,
+        _ => return Ok(None),
+    }
+    Ok(Some(output.into_inner()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{DynamicImage, ImageFormat, RgbImage, RgbaImage};
+
+    use super::optimize;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbImage::from_fn(width, height, |x, y| {
+            image::Rgb([(x % 256) as u8, (y % 256) as u8, ((x * y) % 256) as u8])
+        });
+        let mut bytes = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut bytes, ImageFormat::Png)
+            .unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn large_images_are_scaled_down() {
+        let optimized = optimize(png(400, 200), "image/png", 100);
+
+        let decoded = image::load_from_memory(&optimized.bytes).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (100, 50));
+    }
+
+    #[test]
+    fn smallest_encoding_is_kept() {
+        let original = png(300, 300);
+
+        let optimized = optimize(original.clone(), "image/png", 1000);
+
+        assert!(optimized.bytes.len() <= original.len());
+        let format = image::guess_format(&optimized.bytes).unwrap();
+        assert_eq!(optimized.content_type, format.to_mime_type());
+    }
+
+    #[test]
+    fn transparent_images_are_not_turned_into_jpeg() {
+        let image = RgbaImage::from_fn(300, 300, |x, y| {
+            image::Rgba([(x % 256) as u8, (y % 256) as u8, 0, (x % 2 * 255) as u8])
+        });
+        let mut bytes = Cursor::new(Vec::new());
+        DynamicImage::ImageRgba8(image)
+            .write_to(&mut bytes, ImageFormat::Png)
+            .unwrap();
+
+        let optimized = optimize(bytes.into_inner(), "image/png", 100);
+
+        assert_ne!(optimized.content_type, "image/jpeg");
+    }
+
+    #[test]
+    fn undecodable_images_are_kept() {
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg"/>"#.to_vec();
+
+        let optimized = optimize(svg.clone(), "image/svg+xml", 100);
+
+        assert_eq!(optimized.content_type, "image/svg+xml");
+        assert_eq!(optimized.bytes, svg);
+    }
+}
@@ -0,0 +1,186 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Text search config used when the language can't be detected; matches the
+/// config every bookmark was indexed with before detection existed.
+pub const DEFAULT_TEXT_SEARCH_CONFIG: &str = "english";
+
+/// Only the head of long articles is inspected, it is plenty to tell
+/// languages apart and keeps detection cheap.
+const MAX_SAMPLED_WORDS: usize = 2000;
+
+/// Below this many stopword hits the sample is too small or too unusual to
+/// trust, so the bookmark keeps the default config.
+const MIN_STOPWORD_HITS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Language {
+    /// ISO 639-1 code stored on the bookmark.
+    pub code: &'static str,
+    /// Postgres text search configuration used to build and query tsvectors.
+    pub text_search_config: &'static str,
+    stopwords: &'static [&'static str],
+}
+
+const LANGUAGES: &[Language] = &[
+    Language {
+        code: "en",
+        text_search_config: "english",
+        stopwords: &[
+            "the", "and", "of", "to", "is", "in", "that", "it", "was", "for", "with", "as", "on",
+            "are", "this", "be", "by", "have", "from", "which", "not", "but", "they", "you",
+        ],
+    },
+    Language {
+        code: "pt",
+        text_search_config: "portuguese",
+        stopwords: &[
+            "não", "uma", "os", "do", "da", "em", "para", "com", "que", "mais", "como", "dos",
+            "das", "ao", "foi", "são", "também", "pelo", "pela", "isso", "muito", "está", "já",
+            "seu",
+        ],
+    },
+    Language {
+        code: "es",
+        text_search_config: "spanish",
+        stopwords: &[
+            "el", "los", "las", "del", "y", "en", "que", "por", "con", "una", "para", "es", "al",
+            "lo", "como", "más", "pero", "sus", "fue", "este", "ha", "muy", "también", "está",
+        ],
+    },
+    Language {
+        code: "fr",
+        text_search_config: "french",
+        stopwords: &[
+            "le", "les", "des", "et", "est", "une", "du", "dans", "qui", "pour", "pas", "sur",
+            "au", "avec", "ce", "il", "sont", "mais", "nous", "vous", "ou", "leur", "été", "aux",
+        ],
+    },
+    Language {
+        code: "de",
+        text_search_config: "german",
+        stopwords: &[
+            "der", "die", "und", "das", "ist", "nicht", "ein", "eine", "zu", "den", "mit", "sich",
+            "auf", "für", "dem", "auch", "es", "von", "wird", "sie", "werden", "ich", "wir",
+            "oder",
+        ],
+    },
+    Language {
+        code: "it",
+        text_search_config: "italian",
+        stopwords: &[
+            "il", "di", "che", "è", "per", "gli", "della", "non", "una", "sono", "nel", "del",
+            "anche", "alla", "più", "questo", "delle", "dei", "ci", "come", "ma", "si", "hanno",
+            "essere",
+        ],
+    },
+    Language {
+        code: "nl",
+        text_search_config: "dutch",
+        stopwords: &[
+            "de", "het", "een", "van", "en", "is", "dat", "niet", "zijn", "op", "voor", "met",
+            "ook", "aan", "er", "maar", "om", "wordt", "bij", "naar", "nog", "wel", "dit", "kan",
+        ],
+    },
+    Language {
+        code: "sv",
+        text_search_config: "swedish",
+        stopwords: &[
+            "och", "att", "det", "som", "är", "för", "på", "med", "inte", "till", "av", "den",
+            "har", "ett", "jag", "om", "men", "kan", "från", "eller", "var", "vi", "också",
+            "finns",
+        ],
+    },
+    Language {
+        code: "ru",
+        text_search_config: "russian",
+        stopwords: &[
+            "и", "в", "не", "на", "что", "с", "по", "как", "это", "для", "он", "из", "к", "но",
+            "так", "же", "от", "его", "все", "было", "или", "они", "мы", "при",
+        ],
+    },
+];
+
+/// Detect the dominant language of `text` by counting stopwords of each
+/// supported language, `None` when no language is a clear winner.
+pub fn detect(text: &str) -> Option<Language> {
+    let mut hits: HashMap<&'static str, usize> = HashMap::new();
+    for word in text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .take(MAX_SAMPLED_WORDS)
+    {
+        let word = word.to_lowercase();
+        for language in LANGUAGES {
+            if language.stopwords.contains(&word.as_str()) {
+                *hits.entry(language.code).or_default() += 1;
+            }
+        }
+    }
+
+    let mut ranked: Vec<(&Language, usize)> = LANGUAGES
+        .iter()
+        .map(|language| (language, hits.get(language.code).copied().unwrap_or(0)))
+        .collect();
+    ranked.sort_by_key(|(_, hits)| Reverse(*hits));
+
+    match ranked.as_slice() {
+        [(best, best_hits), (_, runner_up_hits), ..]
+            if *best_hits >= MIN_STOPWORD_HITS && best_hits > runner_up_hits =>
+        {
+            Some(**best)
+        }
+        _ => None,
+    }
+}
+
+/// Text search config for a detected language, falling back to the default.
+pub fn text_search_config(language: Option<Language>) -> &'static str {
+    language.map_or(DEFAULT_TEXT_SEARCH_CONFIG, |language| {
+        language.text_search_config
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect, text_search_config, DEFAULT_TEXT_SEARCH_CONFIG};
+
+    #[test]
+    fn detects_english() {
+        let text = "The quick brown fox jumps over the lazy dog, and it was not the first \
+                    time that this happened in the story of the forest.";
+        let language = detect(text).expect("language");
+        assert_eq!(language.code, "en");
+        assert_eq!(language.text_search_config, "english");
+    }
+
+    #[test]
+    fn detects_portuguese() {
+        let text = "O desenvolvimento de software não é uma tarefa simples, e isso também \
+                    vale para os projetos que são mantidos pela comunidade há muito tempo.";
+        let language = detect(text).expect("language");
+        assert_eq!(language.code, "pt");
+        assert_eq!(language.text_search_config, "portuguese");
+    }
+
+    #[test]
+    fn detects_german() {
+        let text = "Die Entwicklung von Software ist nicht einfach, und das gilt auch für \
+                    Projekte, die von der Gemeinschaft mit viel Aufwand gepflegt werden.";
+        assert_eq!(detect(text).map(|language| language.code), Some("de"));
+    }
+
+    #[test]
+    fn detects_russian() {
+        let text = "Это был очень долгий день, и он не знал, что делать с тем, что \
+                    произошло на работе, но все было так же, как и всегда.";
+        assert_eq!(detect(text).map(|language| language.code), Some("ru"));
+    }
+
+    #[test]
+    fn short_or_unknown_text_is_undetected() {
+        assert_eq!(detect(""), None);
+        assert_eq!(detect("Rust 1.80"), None);
+        assert_eq!(text_search_config(detect("")), DEFAULT_TEXT_SEARCH_CONFIG);
+    }
+}
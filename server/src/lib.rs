@@ -3,35 +3,63 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::{ensure, Result as AnyhowResult};
-use clap::{Args, Parser};
+use clap::{ArgAction, Args, Parser, Subcommand};
 use secrecy::SecretString;
 use url::Url;
 
-use self::db::PgPool;
+use self::daemon::scheduler::Schedule;
+use self::db::Database;
 
 pub mod auth_rate_limit;
+pub mod backup;
 pub mod bookmark_identity;
 pub mod chrome_client;
 pub mod daemon;
 pub mod db;
+pub mod domain_limit;
 pub mod endpoints;
 pub mod error;
+pub mod events;
+pub mod fetch_client;
+pub mod fetch_credentials;
+pub mod image_optimizer;
+pub mod language;
 pub mod llm;
+pub mod mailer;
+pub mod markdown;
 pub mod mcp;
 pub mod rag;
+pub mod rate_limit;
 pub mod readability;
+pub mod reading_time;
+pub mod reindex;
+pub mod robots;
+pub mod rules;
+pub mod search_query;
+pub mod static_storage;
+pub mod tag_suggestions;
+pub mod telemetry;
 pub mod tokenizer;
+pub mod url_guard;
 
 pub const TEXT_AI_PIPELINE_VERSION: i32 = 1;
 pub const EMBEDDING_PIPELINE_VERSION: i32 = 1;
 
 #[derive(Clone)]
 pub struct AppContext {
-    pub pool: PgPool,
+    pub pool: Database,
     pub config: Arc<Config>,
     pub auth_rate_limiter: Arc<auth_rate_limit::AuthRateLimiter>,
+    pub rate_limiter: Arc<rate_limit::RateLimiter>,
     pub tx_new_task: tokio::sync::watch::Sender<()>,
+    /// Wakes the AI daemons up, like a bookmark just saved does
+    pub tx_new_bookmark: tokio::sync::watch::Sender<()>,
+    pub events: events::EventBus,
     pub llm_client: Option<llm::LlmClient>,
+    /// Last check of the AI features, refreshed by the readiness probe
+    pub ai_status: Arc<std::sync::RwLock<shared::AiStatus>>,
+    pub mailer: Option<mailer::Mailer>,
+    pub storage: Arc<dyn static_storage::StorageBackend>,
 }
 
 #[derive(Parser, Clone, Debug)]
@@ -41,7 +69,10 @@ pub struct Config {
     pub hmac_key: SecretString,
 
     #[clap(flatten)]
-    pub pg: PgParams,
+    pub database: DatabaseParams,
+
+    #[clap(flatten)]
+    pub pg: Option<PgParams>,
 
     #[clap(flatten)]
     pub llm: LlmParams,
@@ -49,6 +80,33 @@ pub struct Config {
     #[clap(flatten)]
     pub chrome: Option<ChromeParams>,
 
+    #[clap(flatten)]
+    pub smtp: Option<SmtpParams>,
+
+    #[clap(flatten)]
+    pub rate_limit: RateLimitParams,
+
+    #[clap(flatten)]
+    pub task: TaskParams,
+
+    #[clap(flatten)]
+    pub fetch: FetchParams,
+
+    #[clap(flatten)]
+    pub jobs: JobParams,
+
+    #[clap(flatten)]
+    pub image: ImageParams,
+
+    #[clap(flatten)]
+    pub storage: StorageParams,
+
+    #[clap(flatten)]
+    pub http: HttpParams,
+
+    #[clap(flatten)]
+    pub telemetry: TelemetryParams,
+
     #[arg(long, env = "APP_BIND", default_value = "[::]:3000")]
     pub bind: SocketAddr,
 
@@ -62,16 +120,76 @@ pub struct Config {
     #[arg(long, env = "APP_MCP_ALLOWED_HOSTS")]
     pub mcp_allowed_hosts: Option<String>,
 
+    /// Static content directory, required by the local storage backend
     #[arg(long, env = "APP_DATA_DIR")]
-    pub data_dir: PathBuf,
+    pub data_dir: Option<PathBuf>,
+
+    /// Bytes each user may store, new bookmarks are refused past it. Unset
+    /// means no limit
+    #[arg(long, env = "APP_STORAGE_QUOTA_BYTES")]
+    pub storage_quota_bytes: Option<u64>,
+
+    /// Days a deleted bookmark stays in the trash before it is removed for
+    /// good
+    #[arg(long, env = "APP_TRASH_RETENTION_DAYS", default_value = "30")]
+    pub trash_retention_days: u32,
+
+    /// Days done, failed and cancelled tasks are kept, along with their
+    /// timelines. Unset keeps them forever
+    #[arg(long, env = "APP_TASK_RETENTION_DAYS")]
+    pub task_retention_days: Option<u32>,
+
+    /// Days RAG sessions are kept. Unset keeps them forever
+    #[arg(long, env = "APP_RAG_RETENTION_DAYS")]
+    pub rag_retention_days: Option<u32>,
+
+    /// Days a cached chunk embedding is kept after it was last reused
+    #[arg(long, env = "APP_EMBEDDING_CACHE_RETENTION_DAYS", default_value = "90")]
+    pub embedding_cache_retention_days: u32,
 
     #[arg(long, env = "SPA_DIST")]
     pub spa_dir_dir: PathBuf,
+
+    /// Applies pending database migrations and exits, e.g. from a deploy
+    /// step ahead of the new servers
+    #[arg(long)]
+    pub migrate_only: bool,
+
+    /// Runs a maintenance task instead of the server
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// Writes every user's data and static content to a .tar.gz archive
+    Backup {
+        #[arg(long, short)]
+        output: PathBuf,
+    },
+    /// Loads a backup into an instance that has none of its users
+    Restore {
+        #[arg(long, short)]
+        input: PathBuf,
+
+        /// Queues every bookmark for new embeddings instead of keeping the
+        /// ones in the backup
+        #[arg(long)]
+        regenerate_embeddings: bool,
+    },
+    /// Rebuilds the full-text search tokens and the embedding index, and
+    /// queues the bookmarks missing embeddings
+    Reindex {
+        /// Only this user's bookmarks, every user's otherwise
+        #[arg(long)]
+        user: Option<String>,
+    },
 }
 
 #[derive(Debug, Clone, Args)]
 pub struct LlmParams {
-    /// Text completion provider: ollama, openai, anthropic, gemini, openrouter
+    /// Text completion provider: ollama, openai, anthropic, gemini, openrouter,
+    /// openai-compatible
     #[arg(long, env = "LLM_PROVIDER", default_value = "ollama")]
     pub llm_provider: String,
 
@@ -100,6 +218,10 @@ pub struct LlmParams {
     #[arg(long, env = "AI_EMBED_CHUNK_OVERLAP", default_value = "200")]
     pub ai_embed_chunk_overlap: usize,
 
+    /// How embedding chunks are cut: fixed token windows or whole sentences
+    #[arg(long, env = "AI_EMBED_CHUNK_STRATEGY", value_enum, default_value_t)]
+    pub ai_embed_chunk_strategy: tokenizer::ChunkStrategy,
+
     #[arg(long, env = "AI_TEXT_CLAIM_WINDOW_SECS", default_value = "1800")]
     pub ai_text_claim_window_secs: u64,
 
@@ -130,10 +252,23 @@ pub struct LlmParams {
     #[arg(long, env = "LLM_RETRY_MAX_DELAY_MS", default_value = "30000")]
     pub llm_retry_max_delay_ms: u64,
 
+    /// Chunks assessed for relevance at the same time during a RAG query
+    #[arg(long, env = "RAG_RELEVANCE_CONCURRENCY", default_value = "4")]
+    pub rag_relevance_concurrency: usize,
+
+    /// Candidates the Postgres HNSW index visits per similarity search,
+    /// higher finds closer chunks at the cost of slower queries
+    #[arg(long, env = "RAG_HNSW_EF_SEARCH", default_value = "100", value_parser = clap::value_parser!(u32).range(1..=1000))]
+    pub rag_hnsw_ef_search: u32,
+
     // Ollama-specific
     #[arg(long, env = "OLLAMA_URL")]
     pub ollama_url: Option<Url>,
 
+    /// Pull the configured Ollama models missing at startup
+    #[arg(long, env = "OLLAMA_PULL_MODELS")]
+    pub ollama_pull_models: bool,
+
     // Cloud provider API keys
     #[arg(long, env = "OPENAI_API_KEY")]
     pub openai_api_key: Option<SecretString>,
@@ -147,6 +282,13 @@ pub struct LlmParams {
     #[arg(long, env = "OPENROUTER_API_KEY")]
     pub openrouter_api_key: Option<SecretString>,
 
+    /// Base URL of an OpenAI-compatible server, e.g. http://localhost:8000/v1
+    #[arg(long, env = "OPENAI_COMPATIBLE_URL")]
+    pub openai_compatible_url: Option<Url>,
+
+    #[arg(long, env = "OPENAI_COMPATIBLE_API_KEY")]
+    pub openai_compatible_api_key: Option<SecretString>,
+
     /// API key for the embedding provider (if different from text provider)
     #[arg(long, env = "LLM_EMBEDDING_API_KEY")]
     pub llm_embedding_api_key: Option<SecretString>,
@@ -220,10 +362,203 @@ impl LlmParams {
             self.llm_retry_max_delay_ms >= self.llm_retry_base_delay_ms,
             "LLM_RETRY_MAX_DELAY_MS must be greater than or equal to LLM_RETRY_BASE_DELAY_MS"
         );
+        ensure!(
+            self.rag_relevance_concurrency > 0,
+            "RAG_RELEVANCE_CONCURRENCY must be greater than 0"
+        );
         Ok(())
     }
 }
 
+/// Token bucket settings for sign-in, sign-up and bookmark creation.
+#[derive(Debug, Clone, Args)]
+pub struct RateLimitParams {
+    #[arg(long, env = "APP_RATE_LIMIT_IP_BURST", default_value = "20", value_parser = clap::value_parser!(u32).range(1..))]
+    pub rate_limit_ip_burst: u32,
+
+    #[arg(long, env = "APP_RATE_LIMIT_IP_PER_MINUTE", default_value = "10", value_parser = clap::value_parser!(u32).range(1..))]
+    pub rate_limit_ip_per_minute: u32,
+
+    #[arg(long, env = "APP_RATE_LIMIT_USER_BURST", default_value = "30", value_parser = clap::value_parser!(u32).range(1..))]
+    pub rate_limit_user_burst: u32,
+
+    #[arg(long, env = "APP_RATE_LIMIT_USER_PER_MINUTE", default_value = "30", value_parser = clap::value_parser!(u32).range(1..))]
+    pub rate_limit_user_per_minute: u32,
+}
+
+/// Retry policy of the add-bookmark task queue.
+#[derive(Debug, Clone, Args)]
+pub struct TaskParams {
+    /// Attempts after the first one before a task is marked as failed
+    #[arg(long, env = "TASK_MAX_RETRIES", default_value = "5")]
+    pub task_max_retries: i16,
+
+    #[arg(long, env = "TASK_RETRY_BASE_DELAY_SECS", default_value = "30", value_parser = clap::value_parser!(u64).range(1..))]
+    pub task_retry_base_delay_secs: u64,
+
+    #[arg(long, env = "TASK_RETRY_MAX_DELAY_SECS", default_value = "3600")]
+    pub task_retry_max_delay_secs: u64,
+
+    /// Tasks processed at the same time by the add-bookmark daemon
+    #[arg(long, env = "TASK_CONCURRENCY", default_value = "4", value_parser = clap::value_parser!(u32).range(1..))]
+    pub task_concurrency: u32,
+
+    /// Seconds a leased task is held without a heartbeat before another
+    /// instance may take it over
+    #[arg(long, env = "TASK_LEASE_SECS", default_value = "300", value_parser = clap::value_parser!(u64).range(3..))]
+    pub task_lease_secs: u64,
+
+    /// Name this instance leases tasks under, a random one when unset
+    #[arg(long, env = "TASK_INSTANCE_ID")]
+    pub task_instance_id: Option<String>,
+}
+
+impl TaskParams {
+    pub fn validate(&self) -> AnyhowResult<()> {
+        ensure!(
+            self.task_max_retries >= 0,
+            "TASK_MAX_RETRIES must not be negative"
+        );
+        ensure!(
+            self.task_retry_max_delay_secs >= self.task_retry_base_delay_secs,
+            "TASK_RETRY_MAX_DELAY_SECS must be greater than or equal to TASK_RETRY_BASE_DELAY_SECS"
+        );
+        Ok(())
+    }
+}
+
+/// Cron schedules, in UTC, of the periodic jobs.
+#[derive(Debug, Clone, Args)]
+pub struct JobParams {
+    /// Upper bound of the random delay before each run, spreading the load of
+    /// instances sharing a database
+    #[arg(long, env = "JOB_JITTER_SECS", default_value = "60")]
+    pub job_jitter_secs: u64,
+
+    #[arg(long, env = "JOB_DIGEST_SCHEDULE", default_value = "0 * * * *")]
+    pub job_digest_schedule: Schedule,
+
+    #[arg(long, env = "JOB_DIGEST_ENABLED", default_value_t = true, action = ArgAction::Set)]
+    pub job_digest_enabled: bool,
+
+    #[arg(long, env = "JOB_TRASH_SCHEDULE", default_value = "0 * * * *")]
+    pub job_trash_schedule: Schedule,
+
+    #[arg(long, env = "JOB_TRASH_ENABLED", default_value_t = true, action = ArgAction::Set)]
+    pub job_trash_enabled: bool,
+
+    #[arg(long, env = "JOB_RETENTION_SCHEDULE", default_value = "0 * * * *")]
+    pub job_retention_schedule: Schedule,
+
+    #[arg(long, env = "JOB_RETENTION_ENABLED", default_value_t = true, action = ArgAction::Set)]
+    pub job_retention_enabled: bool,
+}
+
+/// Politeness limits, proxy and User-Agent applied when fetching pages and
+/// images.
+#[derive(Debug, Clone, Args)]
+pub struct FetchParams {
+    /// Minimum delay between two requests to the same host
+    #[arg(long, env = "FETCH_DOMAIN_DELAY_MS", default_value = "1000")]
+    pub fetch_domain_delay_ms: u64,
+
+    /// Requests in flight to the same host
+    #[arg(long, env = "FETCH_DOMAIN_CONCURRENCY", default_value = "2", value_parser = clap::value_parser!(u32).range(1..))]
+    pub fetch_domain_concurrency: u32,
+
+    /// Skip pages disallowed by robots.txt or marked noindex, unless the
+    /// bookmark request overrides it
+    #[arg(long, env = "FETCH_RESPECT_ROBOTS")]
+    pub fetch_respect_robots: bool,
+
+    /// http://, https:// or socks5:// proxy pages, robots.txt and images are
+    /// fetched through
+    #[arg(long, env = "FETCH_PROXY")]
+    pub fetch_proxy: Option<Url>,
+
+    /// User-Agent sent when fetching, the client's own when unset
+    #[arg(long, env = "FETCH_USER_AGENT")]
+    pub fetch_user_agent: Option<String>,
+
+    /// `domain=agent` pairs separated by `|`, overriding FETCH_USER_AGENT for
+    /// a domain and its subdomains
+    #[arg(long, env = "FETCH_USER_AGENT_OVERRIDES", value_delimiter = '|')]
+    pub fetch_user_agent_overrides: Vec<fetch_client::UserAgentOverride>,
+
+    /// Comma-separated networks fetched even though they aren't public, e.g.
+    /// 192.168.1.0/24 for a NAS at home. Loopback, private and link-local
+    /// addresses are refused otherwise
+    #[arg(long, env = "FETCH_ALLOWED_NETWORKS", value_delimiter = ',')]
+    pub fetch_allowed_networks: Vec<ipnet::IpNet>,
+}
+
+/// Response compression and request size limits of the HTTP server.
+#[derive(Debug, Clone, Args)]
+pub struct HttpParams {
+    /// Requests with a larger body are refused with 413
+    #[arg(long, env = "APP_MAX_BODY_BYTES", default_value = "2097152")]
+    pub http_max_body_bytes: usize,
+
+    /// Responses smaller than this are sent uncompressed
+    #[arg(long, env = "APP_COMPRESSION_MIN_BYTES", default_value = "1024")]
+    pub http_compression_min_bytes: u64,
+}
+
+/// Export of traces to an OpenTelemetry collector.
+#[derive(Debug, Clone, Args)]
+pub struct TelemetryParams {
+    /// OTLP/HTTP collector endpoint, e.g. http://localhost:4318. Unset
+    /// disables the export
+    #[arg(long, env = "OTEL_EXPORTER_OTLP_ENDPOINT")]
+    pub otlp_endpoint: Option<Url>,
+
+    #[arg(long, env = "OTEL_SERVICE_NAME", default_value = "bookmark-hub")]
+    pub otel_service_name: String,
+}
+
+/// Limits on the images captured with a bookmark.
+#[derive(Debug, Clone, Args)]
+pub struct ImageParams {
+    /// Images larger than this on either side are scaled down
+    #[arg(long, env = "IMAGE_MAX_DIMENSION", default_value = "1600", value_parser = clap::value_parser!(u32).range(1..))]
+    pub image_max_dimension: u32,
+
+    /// Images whose download exceeds this size are skipped
+    #[arg(long, env = "IMAGE_MAX_BYTES", default_value = "5242880")]
+    pub image_max_bytes: u64,
+
+    /// Total size of the images stored for one bookmark, the ones that don't
+    /// fit keep pointing to the original site
+    #[arg(long, env = "IMAGE_MAX_BOOKMARK_BYTES", default_value = "20971520")]
+    pub image_max_bookmark_bytes: u64,
+}
+
+/// Where captured pages and images are stored.
+#[derive(Debug, Clone, Args)]
+pub struct StorageParams {
+    #[arg(long, env = "STORAGE_BACKEND", value_enum, default_value_t)]
+    pub storage_backend: static_storage::StorageBackendKind,
+
+    #[arg(long, env = "S3_BUCKET")]
+    pub s3_bucket: Option<String>,
+
+    /// Custom endpoint for S3-compatible services (MinIO, R2...)
+    #[arg(long, env = "S3_ENDPOINT")]
+    pub s3_endpoint: Option<String>,
+
+    #[arg(long, env = "S3_REGION", default_value = "us-east-1")]
+    pub s3_region: String,
+
+    /// Key prefix, to share a bucket with other data
+    #[arg(long, env = "S3_PREFIX")]
+    pub s3_prefix: Option<String>,
+
+    /// Address the bucket in the path instead of the host name, most
+    /// self-hosted services need it
+    #[arg(long, env = "S3_FORCE_PATH_STYLE")]
+    pub s3_force_path_style: bool,
+}
+
 #[derive(Debug, Clone, Args)]
 pub struct ChromeParams {
     #[arg(long, env = "CHROME_HOST")]
@@ -233,6 +568,37 @@ pub struct ChromeParams {
     pub chrome_port: u16,
 }
 
+/// Outgoing mail server used to email weekly digests.
+#[derive(Debug, Clone, Args)]
+pub struct SmtpParams {
+    #[arg(long, env = "SMTP_HOST")]
+    pub smtp_host: String,
+
+    #[arg(long, env = "SMTP_PORT", default_value = "587")]
+    pub smtp_port: u16,
+
+    #[arg(long, env = "SMTP_USERNAME")]
+    pub smtp_username: Option<String>,
+
+    #[arg(long, env = "SMTP_PASSWORD")]
+    pub smtp_password: Option<SecretString>,
+
+    /// Sender address, e.g. "Bookmark Hub <hub@example.com>"
+    #[arg(long, env = "SMTP_FROM")]
+    pub smtp_from: String,
+}
+
+/// Which database stores users, bookmarks and everything derived from them.
+#[derive(Debug, Clone, Args)]
+pub struct DatabaseParams {
+    #[arg(long, env = "DATABASE_BACKEND", value_enum, default_value_t)]
+    pub database_backend: db::DatabaseBackendKind,
+
+    /// Database file, required by the sqlite backend
+    #[arg(long, env = "SQLITE_PATH")]
+    pub sqlite_path: Option<PathBuf>,
+}
+
 #[derive(Debug, Clone, Args)]
 pub struct PgParams {
     #[clap(long, help = "Postgres host", env = "PG_HOST")]
@@ -1,5 +1,7 @@
 mod operations;
 mod provider;
+mod status;
+mod usage;
 
 use std::future::Future;
 use std::sync::Arc;
@@ -7,13 +9,18 @@ use std::time::Duration;
 
 use anyhow::Result;
 pub use operations::*;
-pub use provider::build_llm_client;
+pub use provider::{build_llm_client, ollama_base_url};
 use rand::RngExt;
 use reqwest::StatusCode;
 use rig::providers::{anthropic, gemini, ollama, openai, openrouter};
+pub use status::{check_ai_status, log_ai_status};
 use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use tokio::time::Instant;
 use tracing::warn;
+pub use usage::UsageContext;
+
+use self::usage::Metered;
+use crate::db::EmbeddingProfile;
 
 /// Text completion client supporting multiple LLM providers.
 #[derive(Clone)]
@@ -23,6 +30,8 @@ pub enum TextClient {
     Anthropic(anthropic::Client),
     Gemini(gemini::Client),
     OpenRouter(openrouter::Client),
+    /// Any server speaking the OpenAI Chat Completions API (vLLM, LM Studio)
+    OpenAICompatible(openai::CompletionsClient),
 }
 
 /// Embedding client supporting providers with embedding APIs.
@@ -156,9 +165,28 @@ pub struct LlmClient {
     pub embedding_ndims: usize,
     limiter: Arc<LlmLimiter>,
     retry: RetrySettings,
+    usage_log: Option<crate::db::Database>,
+    usage_context: UsageContext,
 }
 
 impl LlmClient {
+    /// Provider, model and dimensions of the vectors this client produces.
+    pub fn embedding_profile(&self) -> EmbeddingProfile {
+        EmbeddingProfile {
+            provider: self.embedding_provider.clone(),
+            model: self.embedding_model.clone(),
+            dimensions: self.embedding_ndims,
+        }
+    }
+
+    /// Same client and limits, completing text with `model` instead.
+    pub fn with_text_model(&self, model: &str) -> Self {
+        Self {
+            text_model: model.to_string(),
+            ..self.clone()
+        }
+    }
+
     pub(crate) async fn run_with_retry<T, F, Fut>(
         &self,
         class: LlmWorkClass,
@@ -168,12 +196,19 @@ impl LlmClient {
     ) -> Result<T>
     where
         F: Fn() -> Fut,
-        Fut: Future<Output = Result<T>>,
+        Fut: Future<Output = Result<Metered<T>>>,
     {
         for attempt in 0..=self.retry.max_attempts {
             let _permit = self.limiter.acquire(class, kind).await?;
-            match make_call().await {
-                Ok(value) => return Ok(value),
+            let started_at = Instant::now();
+            let result = make_call().await;
+            let (usage, success) = match &result {
+                Ok(metered) => (metered.usage, true),
+                Err(_) => (None, false),
+            };
+            self.record_usage(kind, operation_name, started_at.elapsed(), usage, success);
+            match result {
+                Ok(metered) => return Ok(metered.value),
                 Err(error)
                     if attempt < self.retry.max_attempts && is_retriable_llm_error(&error) =>
                 {
@@ -196,7 +231,9 @@ impl LlmClient {
     }
 }
 
-fn retry_delay(base: Duration, max: Duration, attempt: u32) -> Duration {
+/// Exponential backoff from `base` capped at `max`, with the delay drawn
+/// uniformly between `base` and the capped value.
+pub(crate) fn retry_delay(base: Duration, max: Duration, attempt: u32) -> Duration {
     let multiplier = 2u32.saturating_pow(attempt);
     let candidate = base.saturating_mul(multiplier);
     let upper = candidate.min(max);
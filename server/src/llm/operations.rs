@@ -1,15 +1,31 @@
 use anyhow::Result;
+use futures::StreamExt;
+use rig::agent::{MultiTurnStreamItem, StreamingResult};
 use rig::client::{CompletionClient, EmbeddingsClient};
 use rig::completion::Prompt;
+use rig::streaming::{StreamedAssistantContent, StreamingPrompt};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use shared::{Bookmark, BookmarkKind, RagTurn, SummaryStyle};
+use tokio::time::Instant;
 
+use super::usage::Metered;
 use super::{EmbeddingClient, LlmClient, LlmRequestKind, LlmWorkClass, TextClient};
 
 #[derive(Debug, Clone, Deserialize, Serialize, schemars::JsonSchema)]
 pub struct CombinedChunkAnalysis {
     pub summary: String,
     pub tags: Vec<String>,
+    #[serde(default)]
+    pub entities: Vec<ChunkEntity>,
+}
+
+/// A named entity as the model reports it, `kind` should be one of
+/// [`shared::EntityKind`].
+#[derive(Debug, Clone, Deserialize, Serialize, schemars::JsonSchema)]
+pub struct ChunkEntity {
+    pub kind: String,
+    pub name: String,
 }
 
 #[derive(Deserialize, Serialize, schemars::JsonSchema)]
@@ -17,6 +33,11 @@ struct TagsModelResponse {
     tags: Vec<String>,
 }
 
+#[derive(Deserialize, Serialize, schemars::JsonSchema)]
+struct KindModelResponse {
+    kind: String,
+}
+
 #[derive(Deserialize, Serialize, schemars::JsonSchema)]
 struct SummaryModelResponse {
     summary: String,
@@ -33,6 +54,19 @@ struct RelevanceResponse {
     explanation: String,
 }
 
+#[derive(Deserialize, Serialize, schemars::JsonSchema)]
+struct PassageScore {
+    /// Number of the passage in the prompt
+    passage: usize,
+    /// From 0, unrelated, to 10, fully answers the question
+    score: f64,
+}
+
+#[derive(Deserialize, Serialize, schemars::JsonSchema)]
+struct RerankResponse {
+    scores: Vec<PassageScore>,
+}
+
 const SYSTEM_PROMPT: &str = r#"You are an expert researcher. Follow these instructions when responding:
   - The user is a highly experienced analyst, no need to simplify it, be as detailed as possible and make sure your response is correct.
   - Be highly organized.
@@ -53,41 +87,66 @@ where
 {
     client
         .run_with_retry(class, LlmRequestKind::Text, operation_name, || async {
-            let result: Result<T> = match &client.text_client {
+            let result: Result<Metered<T>> = match &client.text_client {
                 TextClient::Ollama(c) => {
                     let e = c
                         .extractor::<T>(&client.text_model)
                         .preamble(preamble)
                         .build();
-                    e.extract(prompt).await.map_err(anyhow::Error::from)
+                    e.extract_with_usage(prompt)
+                        .await
+                        .map(Metered::from)
+                        .map_err(anyhow::Error::from)
                 }
                 TextClient::OpenAI(c) => {
                     let e = c
                         .extractor::<T>(&client.text_model)
                         .preamble(preamble)
                         .build();
-                    e.extract(prompt).await.map_err(anyhow::Error::from)
+                    e.extract_with_usage(prompt)
+                        .await
+                        .map(Metered::from)
+                        .map_err(anyhow::Error::from)
                 }
                 TextClient::Anthropic(c) => {
                     let e = c
                         .extractor::<T>(&client.text_model)
                         .preamble(preamble)
                         .build();
-                    e.extract(prompt).await.map_err(anyhow::Error::from)
+                    e.extract_with_usage(prompt)
+                        .await
+                        .map(Metered::from)
+                        .map_err(anyhow::Error::from)
                 }
                 TextClient::Gemini(c) => {
                     let e = c
                         .extractor::<T>(&client.text_model)
                         .preamble(preamble)
                         .build();
-                    e.extract(prompt).await.map_err(anyhow::Error::from)
+                    e.extract_with_usage(prompt)
+                        .await
+                        .map(Metered::from)
+                        .map_err(anyhow::Error::from)
                 }
                 TextClient::OpenRouter(c) => {
                     let e = c
                         .extractor::<T>(&client.text_model)
                         .preamble(preamble)
                         .build();
-                    e.extract(prompt).await.map_err(anyhow::Error::from)
+                    e.extract_with_usage(prompt)
+                        .await
+                        .map(Metered::from)
+                        .map_err(anyhow::Error::from)
+                }
+                TextClient::OpenAICompatible(c) => {
+                    let e = c
+                        .extractor::<T>(&client.text_model)
+                        .preamble(preamble)
+                        .build();
+                    e.extract_with_usage(prompt)
+                        .await
+                        .map(Metered::from)
+                        .map_err(anyhow::Error::from)
                 }
             };
             result
@@ -107,34 +166,141 @@ async fn prompt_text(
             match &client.text_client {
                 TextClient::Ollama(c) => {
                     let a = c.agent(&client.text_model).preamble(preamble).build();
-                    a.prompt(prompt).await.map_err(anyhow::Error::from)
+                    a.prompt(prompt)
+                        .extended_details()
+                        .await
+                        .map(Metered::from)
+                        .map_err(anyhow::Error::from)
                 }
                 TextClient::OpenAI(c) => {
                     let a = c.agent(&client.text_model).preamble(preamble).build();
-                    a.prompt(prompt).await.map_err(anyhow::Error::from)
+                    a.prompt(prompt)
+                        .extended_details()
+                        .await
+                        .map(Metered::from)
+                        .map_err(anyhow::Error::from)
                 }
                 TextClient::Anthropic(c) => {
                     let a = c.agent(&client.text_model).preamble(preamble).build();
-                    a.prompt(prompt).await.map_err(anyhow::Error::from)
+                    a.prompt(prompt)
+                        .extended_details()
+                        .await
+                        .map(Metered::from)
+                        .map_err(anyhow::Error::from)
                 }
                 TextClient::Gemini(c) => {
                     let a = c.agent(&client.text_model).preamble(preamble).build();
-                    a.prompt(prompt).await.map_err(anyhow::Error::from)
+                    a.prompt(prompt)
+                        .extended_details()
+                        .await
+                        .map(Metered::from)
+                        .map_err(anyhow::Error::from)
                 }
                 TextClient::OpenRouter(c) => {
                     let a = c.agent(&client.text_model).preamble(preamble).build();
-                    a.prompt(prompt).await.map_err(anyhow::Error::from)
+                    a.prompt(prompt)
+                        .extended_details()
+                        .await
+                        .map(Metered::from)
+                        .map_err(anyhow::Error::from)
+                }
+                TextClient::OpenAICompatible(c) => {
+                    let a = c.agent(&client.text_model).preamble(preamble).build();
+                    a.prompt(prompt)
+                        .extended_details()
+                        .await
+                        .map(Metered::from)
+                        .map_err(anyhow::Error::from)
                 }
             }
         })
         .await
 }
 
+/// Streams a text completion, handing each text delta to `on_delta` and
+/// returning the full text. Not retried: a partial answer may already have
+/// been delivered when a failure happens.
+async fn stream_text<F>(
+    client: &LlmClient,
+    class: LlmWorkClass,
+    preamble: &str,
+    prompt: &str,
+    operation_name: &str,
+    on_delta: &mut F,
+) -> Result<String>
+where
+    F: FnMut(&str) + Send,
+{
+    let _permit = client.limiter.acquire(class, LlmRequestKind::Text).await?;
+    let started_at = Instant::now();
+    let result = match &client.text_client {
+        TextClient::Ollama(c) => {
+            let a = c.agent(&client.text_model).preamble(preamble).build();
+            drain_text_stream(a.stream_prompt(prompt).await, on_delta).await
+        }
+        TextClient::OpenAI(c) => {
+            let a = c.agent(&client.text_model).preamble(preamble).build();
+            drain_text_stream(a.stream_prompt(prompt).await, on_delta).await
+        }
+        TextClient::Anthropic(c) => {
+            let a = c.agent(&client.text_model).preamble(preamble).build();
+            drain_text_stream(a.stream_prompt(prompt).await, on_delta).await
+        }
+        TextClient::Gemini(c) => {
+            let a = c.agent(&client.text_model).preamble(preamble).build();
+            drain_text_stream(a.stream_prompt(prompt).await, on_delta).await
+        }
+        TextClient::OpenRouter(c) => {
+            let a = c.agent(&client.text_model).preamble(preamble).build();
+            drain_text_stream(a.stream_prompt(prompt).await, on_delta).await
+        }
+        TextClient::OpenAICompatible(c) => {
+            let a = c.agent(&client.text_model).preamble(preamble).build();
+            drain_text_stream(a.stream_prompt(prompt).await, on_delta).await
+        }
+    };
+    let usage = result.as_ref().ok().and_then(|metered| metered.usage);
+    client.record_usage(
+        LlmRequestKind::Text,
+        operation_name,
+        started_at.elapsed(),
+        usage,
+        result.is_ok(),
+    );
+    result.map(|metered| metered.value)
+}
+
+async fn drain_text_stream<R, F>(
+    mut stream: StreamingResult<R>,
+    on_delta: &mut F,
+) -> Result<Metered<String>>
+where
+    F: FnMut(&str) + Send,
+{
+    let mut text = String::new();
+    let mut usage = None;
+    while let Some(item) = stream.next().await {
+        match item? {
+            MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Text(delta)) => {
+                on_delta(&delta.text);
+                text.push_str(&delta.text);
+            }
+            MultiTurnStreamItem::FinalResponse(response) => {
+                usage = Some(response.usage());
+                break;
+            }
+            _ => {}
+        }
+    }
+    Ok(Metered { value: text, usage })
+}
+
 pub async fn analyze_chunk(client: &LlmClient, text: &str) -> Result<CombinedChunkAnalysis> {
     const PROMPT_PREFIX: &str = r#"The following text is a slice of a larger article.
 Return:
 - a short summary that captures what is distinctive in this slice
 - up to 5 specific tags
+- the people, projects and organizations mentioned by name, each with kind "person", "project" (software, libraries, standards, products) or "organization"
 
 Rules:
 - focus on programming and technology topics
@@ -142,6 +308,7 @@ Rules:
 - tags must be 1-2 words
 - the summary should be concise, ideally one sentence
 - it is acceptable to return an empty summary or no tags if nothing specific stands out
+- name entities as they are usually written, e.g. "Tokio" or "Linus Torvalds", and skip passing or generic mentions
 
 Text:"#;
 
@@ -174,26 +341,139 @@ Here are the tags: "#;
     Ok(resp.tags)
 }
 
-pub async fn consolidate_summary(client: &LlmClient, summaries: &[String]) -> Result<String> {
+fn classify_kind_prompt(title: &str, text: &str) -> String {
+    let kinds = BookmarkKind::ALL
+        .iter()
+        .map(|kind| kind.as_ref())
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        r#"Classify the page below into exactly one of these kinds: {kinds}.
+- article: blog posts, news, essays and opinion pieces
+- paper: academic or research papers
+- docs: reference documentation, manuals, API docs and tutorials of a project
+- recipe: cooking recipes
+- video: pages built around a video, such as a talk or a screencast
+Answer with the kind only.
+
+Title: {title}
+
+Text:
+{text}"#
+    )
+}
+
+/// Coarse kind of the page from its title and first slice of text, none when
+/// the model answers with something outside [`BookmarkKind`].
+pub async fn classify_kind(
+    client: &LlmClient,
+    title: &str,
+    text: &str,
+) -> Result<Option<BookmarkKind>> {
+    let resp: KindModelResponse = extract_structured(
+        client,
+        LlmWorkClass::Background,
+        SYSTEM_PROMPT,
+        &classify_kind_prompt(title, text),
+        "classify_kind",
+    )
+    .await?;
+    Ok(resp.kind.trim().parse().ok())
+}
+
+fn consolidate_summary_prompt(
+    summaries: &[String],
+    language: Option<&str>,
+    style: Option<SummaryStyle>,
+) -> String {
     const PROMPT_PREFIX: &str = r#"I'll give you a list of summaries, they come from slices of an article.
 Most of the summaries are related to programming and technology.
 Most of the summaries look duplicated, redundant or ambiguous.
-Try to produce a new consolidated summary that is clearer and more succinct.
-Less is better, give me a maximum of 3 sentences.
-Here are the summaries, separated by new lines: "#;
-
+Try to produce a new consolidated summary that is clearer and more succinct."#;
+
+    let length = match style {
+        None => "Less is better, give me a maximum of 3 sentences.",
+        Some(SummaryStyle::Short) => "Keep it to one or two short sentences.",
+        Some(SummaryStyle::Detailed) => {
+            "Write a single paragraph of up to 8 sentences covering the main points."
+        }
+        Some(SummaryStyle::BulletPoints) => {
+            "Write 3 to 6 bullet points, one per line, each starting with \"- \"."
+        }
+    };
     let text = summaries.join("\n");
+    let language = language
+        .map(|language| format!("Write the summary in {language}.\n"))
+        .unwrap_or_default();
+    format!(
+        "{language}{PROMPT_PREFIX}\n{length}\nHere are the summaries, separated by new lines: \n{text}"
+    )
+}
+
+/// Merges the summaries of the slices of an article into one, in `style`
+/// when the user asked for one.
+pub async fn consolidate_summary(
+    client: &LlmClient,
+    summaries: &[String],
+    language: Option<&str>,
+    style: Option<SummaryStyle>,
+) -> Result<String> {
     let resp: SummaryModelResponse = extract_structured(
         client,
         LlmWorkClass::Background,
         SYSTEM_PROMPT,
-        &format!("{PROMPT_PREFIX}\n{text}"),
+        &consolidate_summary_prompt(summaries, language, style),
         "consolidate_summary",
     )
     .await?;
     Ok(resp.summary)
 }
 
+fn digest_prompt(bookmarks: &[Bookmark], language: Option<&str>) -> String {
+    let entries = bookmarks
+        .iter()
+        .map(|bookmark| {
+            let tags = bookmark
+                .tags
+                .as_ref()
+                .map(|tags| tags.join(", "))
+                .unwrap_or_default();
+            let summary = bookmark.summary.as_deref().unwrap_or("(no summary)");
+            format!(
+                "- {} ({})\n  Tags: {}\n  Summary: {}",
+                bookmark.title, bookmark.url, tags, summary
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let language = language
+        .map(|language| format!("Write the digest in {language}.\n"))
+        .unwrap_or_default();
+    format!(
+        r#"{language}Write a weekly reading digest for the following bookmarks saved this week.
+Group related bookmarks by theme, name each theme in a short heading, and explain in one or two sentences per bookmark why it is worth reading.
+Open with a two-sentence overview of the week. Use plain text, no markdown tables.
+
+Bookmarks:
+{entries}"#
+    )
+}
+
+pub async fn write_digest(
+    client: &LlmClient,
+    bookmarks: &[Bookmark],
+    language: Option<&str>,
+) -> Result<String> {
+    prompt_text(
+        client,
+        LlmWorkClass::Background,
+        SYSTEM_PROMPT,
+        &digest_prompt(bookmarks, language),
+        "write_digest",
+    )
+    .await
+}
+
 pub async fn embeddings_background(client: &LlmClient, text: &str) -> Result<Vec<f32>> {
     embeddings_with_dimensions(
         &client.embedding_client,
@@ -235,31 +515,37 @@ pub async fn embeddings_with_dimensions(
                     .embedding_model_with_ndims(model_name, dimensions)
                     .embed_text(text)
                     .await
+                    .map(Metered::unmetered)
                     .map_err(anyhow::Error::from),
                 (EmbeddingClient::Ollama(c), None) => c
                     .embedding_model(model_name)
                     .embed_text(text)
                     .await
+                    .map(Metered::unmetered)
                     .map_err(anyhow::Error::from),
                 (EmbeddingClient::OpenAI(c), Some(dimensions)) => c
                     .embedding_model_with_ndims(model_name, dimensions)
                     .embed_text(text)
                     .await
+                    .map(Metered::unmetered)
                     .map_err(anyhow::Error::from),
                 (EmbeddingClient::OpenAI(c), None) => c
                     .embedding_model(model_name)
                     .embed_text(text)
                     .await
+                    .map(Metered::unmetered)
                     .map_err(anyhow::Error::from),
                 (EmbeddingClient::Gemini(c), Some(dimensions)) => c
                     .embedding_model_with_ndims(model_name, dimensions)
                     .embed_text(text)
                     .await
+                    .map(Metered::unmetered)
                     .map_err(anyhow::Error::from),
                 (EmbeddingClient::Gemini(c), None) => c
                     .embedding_model(model_name)
                     .embed_text(text)
                     .await
+                    .map(Metered::unmetered)
                     .map_err(anyhow::Error::from),
             }
         })
@@ -313,30 +599,286 @@ Evaluate if this chunk contains information that would help answer the question.
     Ok((resp.relevant, resp.explanation))
 }
 
-pub async fn answer_with_context(
+fn rerank_prompt(question: &str, chunks: &[String]) -> String {
+    let passages = chunks
+        .iter()
+        .enumerate()
+        .map(|(index, chunk)| format!("[{}] {}", index + 1, chunk))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    format!(
+        r#"Score how well each of the following passages answers the question, from 0 (unrelated) to 10 (fully answers it). Judge each passage on its own content, and return a score for every passage with its number.
+
+Question: {}
+
+Passages:
+{}"#,
+        question, passages
+    )
+}
+
+/// Scores each of `chunks` from 0 to 10 against `question` in a single
+/// prompt, `None` for the chunks the model left out.
+pub async fn score_chunks(
     client: &LlmClient,
     question: &str,
-    context_chunks: &[String],
+    chunks: &[String],
+) -> Result<Vec<Option<f64>>> {
+    let resp: RerankResponse = extract_structured(
+        client,
+        LlmWorkClass::Interactive,
+        SYSTEM_PROMPT,
+        &rerank_prompt(question, chunks),
+        "score_chunks",
+    )
+    .await?;
+    let mut scores = vec![None; chunks.len()];
+    for PassageScore { passage, score } in resp.scores {
+        if let Some(slot) = passage
+            .checked_sub(1)
+            .and_then(|index| scores.get_mut(index))
+        {
+            *slot = Some(score.clamp(0.0, 10.0));
+        }
+    }
+    Ok(scores)
+}
+
+/// The earlier turns of a conversation, quoted in the prompts.
+fn conversation(history: &[RagTurn]) -> String {
+    history
+        .iter()
+        .map(|turn| format!("User: {}\nAssistant: {}", turn.question, turn.answer))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn standalone_question_prompt(history: &[RagTurn], question: &str) -> String {
+    format!(
+        r#"Given the following conversation and a follow-up question, rephrase the follow-up question to be a standalone question that can be understood without the conversation. Keep its language, and return it unchanged if it already stands alone. Reply with the question only.
+
+Conversation:
+{}
+
+Follow-up question: {}"#,
+        conversation(history),
+        question
+    )
+}
+
+/// Rewrites `question`, which may refer to the earlier turns of `history`,
+/// into a question searchable on its own.
+pub async fn standalone_question(
+    client: &LlmClient,
+    history: &[RagTurn],
+    question: &str,
 ) -> Result<String> {
-    let context = context_chunks.join("\n\n");
-    let user_prompt = format!(
+    prompt_text(
+        client,
+        LlmWorkClass::Interactive,
+        SYSTEM_PROMPT,
+        &standalone_question_prompt(history, question),
+        "standalone_question",
+    )
+    .await
+}
+
+fn session_title_prompt(question: &str, answer: &str) -> String {
+    format!(
+        r#"Write a short title, of at most 8 words, for the following question and its answer, in the language of the question. Reply with the title only, without quotes or a final period.
+
+Question: {}
+
+Answer: {}"#,
+        question, answer
+    )
+}
+
+/// A title naming a RAG session in the history.
+pub async fn session_title(client: &LlmClient, question: &str, answer: &str) -> Result<String> {
+    prompt_text(
+        client,
+        LlmWorkClass::Background,
+        SYSTEM_PROMPT,
+        &session_title_prompt(question, answer),
+        "session_title",
+    )
+    .await
+}
+
+fn answer_prompt(question: &str, history: &[RagTurn], context_chunks: &[String]) -> String {
+    // Numbered in the order of the response's relevant chunks
+    let context = context_chunks
+        .iter()
+        .enumerate()
+        .map(|(index, chunk)| format!("[{}] {}", index + 1, chunk))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let conversation = if history.is_empty() {
+        String::new()
+    } else {
+        format!("Conversation so far:\n{}\n\n", conversation(history))
+    };
+    format!(
         r#"Given this context information, answer the following question. If the context doesn't contain enough information to answer the question, say so clearly.
 
 Context:
 {}
 
-Question: {}
+{}Question: {}
 
-Provide a clear, accurate answer based on the context provided. If you cannot answer based on the context, explain what information would be needed."#,
-        context, question
-    );
+Provide a clear, accurate answer based on the context provided, citing the numbers of the passages you use like [1]. If you cannot answer based on the context, explain what information would be needed."#,
+        context, conversation, question
+    )
+}
 
+pub async fn answer_with_context(
+    client: &LlmClient,
+    question: &str,
+    history: &[RagTurn],
+    context_chunks: &[String],
+) -> Result<String> {
     prompt_text(
         client,
         LlmWorkClass::Interactive,
         SYSTEM_PROMPT,
-        &user_prompt,
+        &answer_prompt(question, history, context_chunks),
         "answer_with_context",
     )
     .await
 }
+
+pub async fn stream_answer_with_context(
+    client: &LlmClient,
+    question: &str,
+    history: &[RagTurn],
+    context_chunks: &[String],
+    mut on_delta: impl FnMut(&str) + Send,
+) -> Result<String> {
+    stream_text(
+        client,
+        LlmWorkClass::Interactive,
+        SYSTEM_PROMPT,
+        &answer_prompt(question, history, context_chunks),
+        "answer_with_context_stream",
+        &mut on_delta,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+    use rig::agent::{MultiTurnStreamItem, StreamingResult, Text};
+    use rig::streaming::StreamedAssistantContent;
+    use shared::{Bookmark, RagTurn, SummaryStyle, Visibility};
+
+    use super::{
+        answer_prompt, classify_kind_prompt, consolidate_summary_prompt, digest_prompt,
+        drain_text_stream, rerank_prompt, standalone_question_prompt,
+    };
+
+    #[tokio::test]
+    async fn drain_text_stream_forwards_deltas() {
+        let items = ["Hello", ", ", "world"].map(|text| {
+            Ok(MultiTurnStreamItem::StreamAssistantItem(
+                StreamedAssistantContent::Text(Text { text: text.into() }),
+            ))
+        });
+        let stream: StreamingResult<()> = Box::pin(stream::iter(items));
+        let mut deltas = Vec::new();
+
+        let text = drain_text_stream(stream, &mut |delta: &str| deltas.push(delta.to_string()))
+            .await
+            .unwrap();
+
+        assert_eq!(text.value, "Hello, world");
+        assert!(text.usage.is_none());
+        assert_eq!(deltas, vec!["Hello", ", ", "world"]);
+    }
+
+    #[test]
+    fn answer_prompt_quotes_the_conversation_only_when_present() {
+        let context = vec!["Tokio uses a work-stealing scheduler".to_string()];
+        let history = vec![RagTurn {
+            question: "What does Tokio schedule?".into(),
+            answer: "Tasks".into(),
+        }];
+
+        let prompt = answer_prompt("How?", &history, &context);
+        assert!(prompt.contains("[1] Tokio uses a work-stealing scheduler"));
+        assert!(prompt.contains(
+            "Conversation so far:\nUser: What does Tokio schedule?\nAssistant: Tasks\n\nQuestion: How?"
+        ));
+        assert!(!answer_prompt("How?", &[], &context).contains("Conversation so far"));
+
+        let prompt = standalone_question_prompt(&history, "How?");
+        assert!(prompt.contains("User: What does Tokio schedule?"));
+        assert!(prompt.ends_with("Follow-up question: How?"));
+    }
+
+    #[test]
+    fn rerank_prompt_numbers_every_passage() {
+        let chunks = vec!["First passage".to_string(), "Second passage".to_string()];
+
+        let prompt = rerank_prompt("Why?", &chunks);
+
+        assert!(prompt.contains("Question: Why?"));
+        assert!(prompt.ends_with("[1] First passage\n\n[2] Second passage"));
+    }
+
+    #[test]
+    fn classify_kind_prompt_lists_every_kind() {
+        let prompt = classify_kind_prompt("Tokio internals", "The scheduler");
+
+        assert!(prompt.contains("one of these kinds: article, paper, docs, recipe, video."));
+        assert!(prompt.contains("Title: Tokio internals"));
+        assert!(prompt.ends_with("The scheduler"));
+    }
+
+    #[test]
+    fn consolidate_summary_prompt_follows_the_style() {
+        let summaries = vec!["First slice".to_string(), "Second slice".to_string()];
+
+        let prompt = consolidate_summary_prompt(&summaries, None, None);
+        assert!(prompt.contains("a maximum of 3 sentences"));
+        assert!(prompt.ends_with("First slice\nSecond slice"));
+
+        let prompt = consolidate_summary_prompt(
+            &summaries,
+            Some("French"),
+            Some(SummaryStyle::BulletPoints),
+        );
+        assert!(prompt.starts_with("Write the summary in French."));
+        assert!(prompt.contains("bullet points"));
+        assert!(!prompt.contains("3 sentences"));
+    }
+
+    #[test]
+    fn digest_prompt_lists_bookmarks() {
+        let bookmark = Bookmark {
+            bookmark_id: "b1".into(),
+            user_id: uuid::Uuid::nil(),
+            url: "https://example.com/tokio".into(),
+            domain: "example.com".into(),
+            title: "Tokio internals".into(),
+            tags: Some(vec!["tokio".into(), "async".into()]),
+            summary: None,
+            created_at: chrono::Utc::now(),
+            updated_at: None,
+            word_count: 0,
+            reading_time_minutes: 0,
+            kind: None,
+            favorite: false,
+            original_title: None,
+            visibility: Visibility::Private,
+        };
+
+        let prompt = digest_prompt(&[bookmark], Some("Portuguese"));
+
+        assert!(prompt.starts_with("Write the digest in Portuguese."));
+        assert!(prompt.contains("- Tokio internals (https://example.com/tokio)"));
+        assert!(prompt.contains("Tags: tokio, async"));
+        assert!(prompt.contains("Summary: (no summary)"));
+    }
+}
@@ -10,7 +10,7 @@ use crate::LlmParams;
 
 /// Strip trailing slash from URL to avoid double-slash in rig-core's URL
 /// construction.
-fn ollama_base_url(params: &LlmParams) -> String {
+pub fn ollama_base_url(params: &LlmParams) -> String {
     params
         .ollama_url
         .as_ref()
@@ -18,6 +18,24 @@ fn ollama_base_url(params: &LlmParams) -> String {
         .unwrap_or_else(|| "http://localhost:11434".to_string())
 }
 
+/// Build a client for an OpenAI-compatible server. Local servers usually
+/// ignore the key, so it may be left unset.
+fn openai_compatible_client(params: &LlmParams, api_key: Option<&str>) -> Result<openai::Client> {
+    let url = params
+        .openai_compatible_url
+        .as_ref()
+        .map(|u| u.as_str().trim_end_matches('/').to_string())
+        .ok_or_else(|| {
+            anyhow::anyhow!("OPENAI_COMPATIBLE_URL required for openai-compatible provider")
+        })?;
+    let client = openai::Client::builder()
+        .base_url(&url)
+        .api_key(api_key.unwrap_or_default())
+        .http_client(http_client(params)?)
+        .build()?;
+    Ok(client)
+}
+
 const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
 
 fn http_client(params: &LlmParams) -> Result<reqwest::Client> {
@@ -66,6 +84,8 @@ pub async fn build_llm_client(params: &LlmParams) -> Result<Option<LlmClient>> {
             max_delay: Duration::from_millis(params.llm_retry_max_delay_ms),
             max_attempts: 2,
         },
+        usage_log: None,
+        usage_context: super::UsageContext::default(),
     }))
 }
 
@@ -198,6 +218,15 @@ fn build_text_client(params: &LlmParams) -> Result<TextClient> {
                 .build()?;
             Ok(TextClient::OpenRouter(client))
         }
+        "openai-compatible" => {
+            let key = params
+                .openai_compatible_api_key
+                .as_ref()
+                .map(ExposeSecret::expose_secret);
+            // These servers implement Chat Completions, not the Responses API
+            let client = openai_compatible_client(params, key)?;
+            Ok(TextClient::OpenAICompatible(client.completions_api()))
+        }
         other => bail!("Unknown LLM_PROVIDER: {other}"),
     }
 }
@@ -249,8 +278,16 @@ fn build_embedding_client(provider: &str, params: &LlmParams) -> Result<Embeddin
                 .build()?;
             Ok(EmbeddingClient::Gemini(client))
         }
+        "openai-compatible" => {
+            let key = params
+                .llm_embedding_api_key
+                .as_ref()
+                .or(params.openai_compatible_api_key.as_ref())
+                .map(ExposeSecret::expose_secret);
+            Ok(EmbeddingClient::OpenAI(openai_compatible_client(params, key)?))
+        }
         other => bail!(
-            "Provider '{other}' does not support embeddings. Use LLM_EMBEDDING_PROVIDER to select ollama, openai, or gemini for embeddings."
+            "Provider '{other}' does not support embeddings. Use LLM_EMBEDDING_PROVIDER to select ollama, openai, gemini, or openai-compatible for embeddings."
         ),
     }
 }
@@ -0,0 +1,194 @@
+//! What the configured AI features can do, checked at startup and by the
+//! readiness probe so a model missing from Ollama shows up before every task
+//! fails on it.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::json;
+use shared::{AiFeatureState, AiFeatureStatus, AiStatus};
+use tracing::{info, warn};
+
+use super::ollama_base_url;
+use crate::LlmParams;
+
+const TAGS_TIMEOUT: Duration = Duration::from_secs(5);
+/// A pull downloads gigabytes.
+const PULL_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Deserialize)]
+struct Tags {
+    models: Vec<TagModel>,
+}
+
+#[derive(Deserialize)]
+struct TagModel {
+    name: String,
+}
+
+async fn installed_models(base_url: &str) -> Result<Vec<String>> {
+    let tags: Tags = reqwest::Client::new()
+        .get(format!("{base_url}/api/tags"))
+        .timeout(TAGS_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(tags.models.into_iter().map(|model| model.name).collect())
+}
+
+async fn pull_model(base_url: &str, model: &str) -> Result<()> {
+    reqwest::Client::new()
+        .post(format!("{base_url}/api/pull"))
+        .timeout(PULL_TIMEOUT)
+        .json(&json!({ "model": model, "stream": false }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Ollama lists a model pulled without a tag as `name:latest`.
+fn is_installed(installed: &[String], model: &str) -> bool {
+    installed
+        .iter()
+        .any(|name| name == model || (!model.contains(':') && *name == format!("{model}:latest")))
+}
+
+fn disabled(reason: &str) -> AiFeatureStatus {
+    AiFeatureStatus {
+        state: AiFeatureState::Disabled,
+        provider: None,
+        model: None,
+        reason: Some(reason.to_string()),
+    }
+}
+
+/// Checks the text and embedding models configured by `params`. Only Ollama
+/// models are verified, missing ones are pulled when `pull` is set; models of
+/// the other providers are taken as available.
+pub async fn check_ai_status(params: &LlmParams, pull: bool) -> AiStatus {
+    let Some(text_model) = params.llm_text_model.as_deref() else {
+        let status = disabled("LLM_TEXT_MODEL is not set");
+        return AiStatus {
+            text: status.clone(),
+            embeddings: status,
+        };
+    };
+    let embedding_provider = params
+        .llm_embedding_provider
+        .as_deref()
+        .unwrap_or(&params.llm_provider);
+    let embedding_model = params.llm_embedding_model.as_deref().unwrap_or(text_model);
+
+    let mut ollama = Ollama {
+        base_url: ollama_base_url(params),
+        installed: None,
+    };
+    AiStatus {
+        text: ollama
+            .check_model(&params.llm_provider, text_model, pull)
+            .await,
+        embeddings: ollama
+            .check_model(embedding_provider, embedding_model, pull)
+            .await,
+    }
+}
+
+/// The Ollama server, its models listed once per check.
+struct Ollama {
+    base_url: String,
+    installed: Option<Result<Vec<String>, String>>,
+}
+
+impl Ollama {
+    async fn check_model(&mut self, provider: &str, model: &str, pull: bool) -> AiFeatureStatus {
+        let status = |state, reason: Option<String>| AiFeatureStatus {
+            state,
+            provider: Some(provider.to_string()),
+            model: Some(model.to_string()),
+            reason,
+        };
+        if provider != "ollama" {
+            return status(AiFeatureState::Available, None);
+        }
+        let listed = match self.installed.take() {
+            Some(listed) => listed,
+            None => installed_models(&self.base_url)
+                .await
+                .map_err(|error| format!("{error:#}")),
+        };
+        let installed = match self.installed.insert(listed) {
+            Ok(installed) => installed,
+            Err(error) => {
+                let reason = format!("Ollama is unreachable at {}: {error}", self.base_url);
+                return status(AiFeatureState::Degraded, Some(reason));
+            }
+        };
+        if is_installed(installed, model) {
+            return status(AiFeatureState::Available, None);
+        }
+        if !pull {
+            let reason = format!(
+                "Model {model} is not pulled, run `ollama pull {model}` or set OLLAMA_PULL_MODELS"
+            );
+            return status(AiFeatureState::Degraded, Some(reason));
+        }
+        info!(model, "Pulling Ollama model");
+        match pull_model(&self.base_url, model).await {
+            Ok(()) => {
+                info!(model, "Ollama model pulled");
+                installed.push(model.to_string());
+                status(AiFeatureState::Available, None)
+            }
+            Err(error) => {
+                let reason = format!("Failed to pull model {model}: {error:#}");
+                status(AiFeatureState::Degraded, Some(reason))
+            }
+        }
+    }
+}
+
+/// Warns about every AI feature that won't work.
+pub fn log_ai_status(status: &AiStatus) {
+    for (feature, status) in [("text", &status.text), ("embeddings", &status.embeddings)] {
+        match status.state {
+            AiFeatureState::Available => info!(
+                feature,
+                provider = ?status.provider,
+                model = ?status.model,
+                "AI feature available"
+            ),
+            AiFeatureState::Degraded => warn!(
+                feature,
+                provider = ?status.provider,
+                model = ?status.model,
+                reason = ?status.reason,
+                "AI feature degraded"
+            ),
+            AiFeatureState::Disabled => {
+                info!(feature, reason = ?status.reason, "AI feature disabled")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_installed;
+
+    #[test]
+    fn untagged_models_match_their_latest_tag() {
+        let installed = vec![
+            "llama3.2:latest".to_string(),
+            "nomic-embed-text:v1.5".to_string(),
+        ];
+        assert!(is_installed(&installed, "llama3.2"));
+        assert!(is_installed(&installed, "llama3.2:latest"));
+        assert!(is_installed(&installed, "nomic-embed-text:v1.5"));
+        assert!(!is_installed(&installed, "nomic-embed-text"));
+        assert!(!is_installed(&installed, "llama3.2:1b"));
+    }
+}
@@ -0,0 +1,132 @@
+use std::time::Duration;
+
+use rig::agent::PromptResponse;
+use rig::completion::Usage;
+use rig::extractor::ExtractionResponse;
+use tracing::warn;
+use uuid::Uuid;
+
+use super::{LlmClient, LlmRequestKind};
+use crate::db::llm_usage::{self, NewLlmUsage};
+
+/// Who an LLM call is made for, attached to its usage record.
+#[derive(Debug, Clone, Default)]
+pub struct UsageContext {
+    pub user_id: Option<Uuid>,
+    pub bookmark_id: Option<String>,
+    pub session_id: Option<Uuid>,
+}
+
+impl UsageContext {
+    pub fn user(user_id: Uuid) -> Self {
+        Self {
+            user_id: Some(user_id),
+            ..Self::default()
+        }
+    }
+
+    pub fn bookmark(user_id: Uuid, bookmark_id: &str) -> Self {
+        Self {
+            user_id: Some(user_id),
+            bookmark_id: Some(bookmark_id.to_string()),
+            session_id: None,
+        }
+    }
+
+    pub fn session(user_id: Uuid, session_id: Uuid) -> Self {
+        Self {
+            user_id: Some(user_id),
+            bookmark_id: None,
+            session_id: Some(session_id),
+        }
+    }
+}
+
+/// A provider response with the token usage it reported, if any.
+pub(crate) struct Metered<T> {
+    pub(crate) value: T,
+    pub(crate) usage: Option<Usage>,
+}
+
+impl<T> Metered<T> {
+    pub(crate) fn unmetered(value: T) -> Self {
+        Self { value, usage: None }
+    }
+}
+
+impl<T> From<ExtractionResponse<T>> for Metered<T> {
+    fn from(response: ExtractionResponse<T>) -> Self {
+        Self {
+            value: response.data,
+            usage: Some(response.usage),
+        }
+    }
+}
+
+impl From<PromptResponse> for Metered<String> {
+    fn from(response: PromptResponse) -> Self {
+        Self {
+            value: response.output,
+            usage: Some(response.usage),
+        }
+    }
+}
+
+impl LlmClient {
+    /// Record every call made through this client in the `llm_usage` table.
+    pub fn with_usage_log(self, pool: crate::db::Database) -> Self {
+        Self {
+            usage_log: Some(pool),
+            ..self
+        }
+    }
+
+    /// Same client, attributing its calls to `context`.
+    pub fn with_usage_context(&self, context: UsageContext) -> Self {
+        Self {
+            usage_context: context,
+            ..self.clone()
+        }
+    }
+
+    /// Stores the usage record in the background; accounting failures never
+    /// fail the LLM call itself.
+    pub(crate) fn record_usage(
+        &self,
+        kind: LlmRequestKind,
+        purpose: &str,
+        duration: Duration,
+        usage: Option<Usage>,
+        success: bool,
+    ) {
+        let Some(pool) = self.usage_log.clone() else {
+            return;
+        };
+        // Providers that do not report usage leave every counter at zero
+        let usage = usage.filter(|usage| usage.total_tokens + usage.input_tokens > 0);
+        let (request_kind, provider, model) = match kind {
+            LlmRequestKind::Text => ("text", &self.text_provider, &self.text_model),
+            LlmRequestKind::Embedding => {
+                ("embedding", &self.embedding_provider, &self.embedding_model)
+            }
+        };
+        let record = NewLlmUsage {
+            user_id: self.usage_context.user_id,
+            bookmark_id: self.usage_context.bookmark_id.clone(),
+            session_id: self.usage_context.session_id,
+            purpose: purpose.to_string(),
+            request_kind,
+            provider: provider.clone(),
+            model: model.clone(),
+            input_tokens: usage.map(|usage| usage.input_tokens as i64),
+            output_tokens: usage.map(|usage| usage.output_tokens as i64),
+            duration_ms: duration.as_millis() as i64,
+            success,
+        };
+        tokio::spawn(async move {
+            if let Err(error) = llm_usage::insert(&pool, &record).await {
+                warn!(?error, purpose = %record.purpose, "Failed to record LLM usage");
+            }
+        });
+    }
+}
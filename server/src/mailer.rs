@@ -0,0 +1,79 @@
+use anyhow::Context;
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use secrecy::ExposeSecret;
+use tracing::info;
+
+use crate::SmtpParams;
+
+/// Sends plain text mail through the configured SMTP relay (STARTTLS).
+#[derive(Clone)]
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl Mailer {
+    pub fn new(params: &SmtpParams) -> anyhow::Result<Self> {
+        let from = params
+            .smtp_from
+            .parse::<Mailbox>()
+            .context("SMTP_FROM must be a valid mailbox")?;
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&params.smtp_host)
+            .context("invalid SMTP_HOST")?
+            .port(params.smtp_port);
+        if let (Some(username), Some(password)) = (&params.smtp_username, &params.smtp_password) {
+            builder = builder.credentials(Credentials::new(
+                username.clone(),
+                password.expose_secret().to_owned(),
+            ));
+        }
+        Ok(Self {
+            transport: builder.build(),
+            from,
+        })
+    }
+
+    pub async fn send(&self, to: &str, subject: &str, body: String) -> anyhow::Result<()> {
+        let to = to
+            .parse::<Mailbox>()
+            .with_context(|| format!("invalid recipient address {to}"))?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to.clone())
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body)?;
+        self.transport.send(message).await?;
+        info!(to = %to, subject, "Mail sent");
+        Ok(())
+    }
+
+    pub async fn send_email_verification(&self, to: &str, token: &str) -> anyhow::Result<()> {
+        let body = format!(
+            "Confirm this address for your Bookmark Hub account with the code below.\n\n\
+             {token}\n\n\
+             Send it to POST /api/v1/auth/verify-email. The code expires in 24 hours."
+        );
+        self.send(to, "Verify your email address", body).await
+    }
+
+    pub async fn send_password_reset(&self, to: &str, token: &str) -> anyhow::Result<()> {
+        let body = format!(
+            "A password reset was requested for your Bookmark Hub account.\n\n\
+             {token}\n\n\
+             Send this code with your new password to POST /api/v1/auth/password-reset/confirm. \
+             It expires in 1 hour. If you did not ask for a reset, ignore this message."
+        );
+        self.send(to, "Reset your password", body).await
+    }
+
+    pub async fn send_task_failed(&self, to: &str, url: &str, reason: &str) -> anyhow::Result<()> {
+        let body = format!(
+            "Bookmark Hub gave up saving {url} after several attempts.\n\nReason: {reason}"
+        );
+        self.send(to, "A bookmark could not be saved", body).await
+    }
+}
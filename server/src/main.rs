@@ -3,17 +3,27 @@ use std::io;
 use std::sync::Arc;
 use std::time::Duration;
 
-use anyhow::bail;
+use axum::extract::DefaultBodyLimit;
 use axum::http::{header, HeaderValue, Method};
 use axum::{Extension, Router};
 use axum_otel_metrics::HttpMetricsLayerBuilder;
 use clap::Parser;
-use server::db::PgPool;
+use opentelemetry::trace::TracerProvider;
+use server::daemon::retention::RetentionSettings;
+use server::daemon::scheduler::Scheduler;
+use server::db::Database;
+use server::events::EventBus;
 use server::llm::LlmClient;
-use server::{daemon, db, endpoints, mcp, AppContext, Config};
+use server::mailer::Mailer;
+use server::static_storage::{self, StorageBackend};
+use server::{backup, daemon, db, endpoints, mcp, reindex, AppContext, Command, Config};
+use shared::AiStatus;
 use tokio::signal::unix::SignalKind;
+use tower_http::compression::predicate::{NotForContentType, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
 use tower_http::cors::{Any, CorsLayer};
-use tower_http::services::ServeDir;
+use tower_http::limit::RequestBodyLimitLayer;
+use tower_http::services::{ServeDir, ServeFile};
 use tower_http::trace::TraceLayer;
 use tracing::level_filters::LevelFilter;
 use tracing::{debug, error, info, warn};
@@ -21,12 +31,18 @@ use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
 
+const NOTIFY_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let config = Config::parse();
 
+    let tracer_provider = server::telemetry::tracer_provider(&config.telemetry)?;
     tracing_subscriber::registry()
         .with(tracing_subscriber::fmt::layer())
+        .with(tracer_provider.as_ref().map(|provider| {
+            tracing_opentelemetry::layer().with_tracer(provider.tracer("bookmark-hub"))
+        }))
         .with(
             EnvFilter::builder()
                 .with_default_directive(LevelFilter::INFO.into())
@@ -34,7 +50,19 @@ async fn main() -> anyhow::Result<()> {
         )
         .init();
 
+    if let Some(command) = &config.command {
+        return run_command(command, &config).await;
+    }
+
+    if config.migrate_only {
+        let pool = db::connect(&config.database, config.pg.as_ref()).await?;
+        db::run_migrations(&pool).await?;
+        info!("Migrations applied, exiting");
+        return Ok(());
+    }
+
     config.llm.validate_runtime_settings()?;
+    config.task.validate()?;
     let ai_settings = daemon::AiDaemonSettings::from_llm_params(&config.llm)?;
 
     info!(
@@ -45,9 +73,14 @@ async fn main() -> anyhow::Result<()> {
         text_chunk_overlap = ai_settings.text_chunk_overlap,
         embed_chunk_size = ai_settings.embed_chunk_size,
         embed_chunk_overlap = ai_settings.embed_chunk_overlap,
+        embed_chunk_strategy = ?ai_settings.embed_chunk_strategy,
         "Starting Bookmark Hub Server"
     );
 
+    // Ahead of the client, which probes the embedding model, so a pulled model
+    // is there for it
+    let ai_status = server::llm::check_ai_status(&config.llm, config.llm.ollama_pull_models).await;
+    server::llm::log_ai_status(&ai_status);
     let llm_client = server::llm::build_llm_client(&config.llm).await?;
     let llm_enabled = llm_client.is_some();
     info!(llm_enabled = %llm_enabled, provider = %config.llm.llm_provider, "LLM AI features configuration");
@@ -62,31 +95,36 @@ async fn main() -> anyhow::Result<()> {
     }
 
     info!("Initializing database connection pool");
-    let pool = db::get_pool(config.pg.clone()).await?;
+    let pool = db::connect(&config.database, config.pg.as_ref()).await?;
     info!("Running database migrations");
     db::run_migrations(&pool).await?;
 
     if let Some(ref client) = llm_client {
-        let profile = db::EmbeddingProfile {
-            provider: client.embedding_provider.clone(),
-            model: client.embedding_model.clone(),
-            dimensions: client.embedding_ndims,
-        };
-        db::reconcile_embedding_profile(&pool, &profile).await?;
+        db::reconcile_embedding_profile(&pool, &client.embedding_profile()).await?;
     }
+    let llm_client = llm_client.map(|client| client.with_usage_log(pool.clone()));
 
     info!("Database initialization complete");
 
+    let mailer = config.smtp.as_ref().map(Mailer::new).transpose()?;
+    info!(email_enabled = mailer.is_some(), "Email configuration");
+
+    let storage = static_storage::build(&config.storage, config.data_dir.as_deref()).await?;
+
     debug!("Creating inter-daemon communication channels");
     let (new_task_tx, new_task_rx) = tokio::sync::watch::channel(());
     let (new_bookmark_tx, new_bookmark_rx) = tokio::sync::watch::channel(());
+    let (events, relayed_events) = EventBus::relayed();
 
     info!("Spawning background daemons");
     let add_bookmark_daemon = tokio::spawn(setup_add_bookmark_daemon(
         config.clone(),
         pool.clone(),
         new_task_rx,
-        new_bookmark_tx,
+        new_bookmark_tx.clone(),
+        events.clone(),
+        mailer.clone(),
+        storage.clone(),
     ));
     let text_ai_daemon = tokio::spawn(setup_text_ai_daemon(
         llm_client.clone(),
@@ -100,9 +138,35 @@ async fn main() -> anyhow::Result<()> {
         new_bookmark_rx.clone(),
         ai_settings,
     ));
+    let scheduler = tokio::spawn(setup_scheduler(
+        config.clone(),
+        pool.clone(),
+        llm_client.clone(),
+        mailer.clone(),
+        storage.clone(),
+    ));
+
+    let notify_listener = tokio::spawn(setup_notify_listener(
+        config.clone(),
+        pool.clone(),
+        new_task_tx.clone(),
+        new_bookmark_tx.clone(),
+        events.clone(),
+        relayed_events,
+    ));
 
     info!("Setting up HTTP server");
-    let app_server = setup_app(&config, pool.clone(), new_task_tx, llm_client);
+    let app_server = setup_app(
+        &config,
+        pool.clone(),
+        new_task_tx,
+        new_bookmark_tx,
+        events,
+        llm_client,
+        ai_status,
+        mailer,
+        storage,
+    );
 
     info!("All services started successfully");
     tokio::select! {
@@ -158,6 +222,64 @@ async fn main() -> anyhow::Result<()> {
                 }
             }
         }
+        result = notify_listener => {
+            match result {
+                Ok(Err(error)) => {
+                    error!(?error, "Notification listener error");
+                    std::process::exit(1);
+                },
+                Err(error) => {
+                    error!(?error, "Join error in notification listener");
+                    std::process::exit(1);
+                },
+                Ok(Ok(_)) => {
+                    info!("Notification listener stopped");
+                }
+            }
+        }
+        result = scheduler => {
+            match result {
+                Ok(Err(error)) => {
+                    error!(?error, "Scheduler error");
+                    std::process::exit(1);
+                },
+                Err(error) => {
+                    error!(?error, "Join error in scheduler");
+                    std::process::exit(1);
+                },
+                Ok(Ok(_)) => {
+                    info!("Scheduler stopped");
+                }
+            }
+        }
+    }
+    if let Some(provider) = tracer_provider {
+        if let Err(error) = provider.shutdown() {
+            warn!(?error, "Failed to flush traces");
+        }
+    }
+    Ok(())
+}
+
+/// Maintenance tasks only need the database and the static storage, no
+/// daemon or LLM client is started.
+async fn run_command(command: &Command, config: &Config) -> anyhow::Result<()> {
+    let pool = db::connect(&config.database, config.pg.as_ref()).await?;
+    db::run_migrations(&pool).await?;
+    let storage = static_storage::build(&config.storage, config.data_dir.as_deref()).await?;
+    match command {
+        Command::Backup { output } => {
+            backup::backup(&pool, storage.as_ref(), output).await?;
+        }
+        Command::Restore {
+            input,
+            regenerate_embeddings,
+        } => {
+            backup::restore(&pool, storage.as_ref(), input, *regenerate_embeddings).await?;
+        }
+        Command::Reindex { user } => {
+            reindex::reindex(&pool, user.as_deref()).await?;
+        }
     }
     Ok(())
 }
@@ -176,11 +298,17 @@ async fn shutdown_signal() {
     info!("Shutdown signal received, starting graceful shutdown")
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn setup_app(
     config: &Config,
-    pool: PgPool,
+    pool: Database,
     tx: tokio::sync::watch::Sender<()>,
+    tx_new_bookmark: tokio::sync::watch::Sender<()>,
+    events: EventBus,
     llm_client: Option<LlmClient>,
+    ai_status: AiStatus,
+    mailer: Option<Mailer>,
+    storage: Arc<dyn StorageBackend>,
 ) -> anyhow::Result<()> {
     let app_state = AppContext {
         config: Arc::new(config.clone()),
@@ -189,18 +317,40 @@ async fn setup_app(
             5,
             Duration::from_secs(5 * 60),
         )),
+        rate_limiter: Arc::new(server::rate_limit::RateLimiter::new(&config.rate_limit)),
         tx_new_task: tx,
+        tx_new_bookmark,
+        events,
         llm_client,
+        ai_status: Arc::new(std::sync::RwLock::new(ai_status)),
+        mailer,
+        storage,
     };
 
     let metrics = HttpMetricsLayerBuilder::new().build();
+    // Images are already compressed and event streams must be flushed as
+    // they're written
+    let compression = CompressionLayer::new().compress_when(
+        SizeAbove::new(config.http.http_compression_min_bytes)
+            .and(NotForContentType::IMAGES)
+            .and(NotForContentType::SSE)
+            .and(NotForContentType::GRPC),
+    );
     let mut app = Router::new()
         .nest("/api/v1", endpoints::routers_v1())
+        .nest("/pinboard/v1", endpoints::pinboard())
+        .nest("/wallabag", endpoints::wallabag())
         .merge(endpoints::health_check())
-        .merge(endpoints::static_content(config))
+        .merge(endpoints::api_docs())
+        .merge(endpoints::static_content())
         .merge(mcp::router(config.mcp_allowed_hosts.as_deref()))
+        .merge(spa_routes())
         .fallback_service(ServeDir::new(env!("SPA_DIST")))
         .layer(metrics)
+        .layer(compression)
+        // The configured limit replaces axum's fixed one
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(config.http.http_max_body_bytes))
         .layer(Extension(app_state))
         .layer(TraceLayer::new_for_http());
 
@@ -234,36 +384,48 @@ async fn setup_app(
     Ok(())
 }
 
+/// The SPA's own paths, answered with its page so that deep links and
+/// reloads open the app on them rather than a 404.
+fn spa_routes() -> Router {
+    const ROUTES: [&str; 6] = [
+        "/search",
+        "/tasks",
+        "/rag",
+        "/rag/history",
+        "/bookmarks/{bookmark_id}",
+        "/read/{bookmark_id}",
+    ];
+    let index = ServeFile::new(concat!(env!("SPA_DIST"), "/index.html"));
+    ROUTES.into_iter().fold(Router::new(), |router, route| {
+        router.route_service(route, index.clone())
+    })
+}
+
 async fn setup_add_bookmark_daemon(
     config: Config,
-    pool: PgPool,
+    pool: Database,
     new_task_rx: tokio::sync::watch::Receiver<()>,
     new_bookmark_tx: tokio::sync::watch::Sender<()>,
+    events: EventBus,
+    mailer: Option<Mailer>,
+    storage: Arc<dyn StorageBackend>,
 ) -> anyhow::Result<()> {
-    let data_dir = config.data_dir.clone();
-    if !data_dir.exists() || !data_dir.is_dir() {
-        bail!("Data dir is not a directory, {:?}", &config.data_dir);
-    }
-    if data_dir.metadata()?.permissions().readonly() {
-        bail!(
-            "Data dir is readonly, needs write access, {:?}",
-            &config.data_dir
-        );
-    }
-    {
-        let mut test_file = data_dir.clone();
-        test_file.push("test.txt");
-        std::fs::write(&test_file, "test data")?;
-        std::fs::remove_file(&test_file)?;
-        debug!(data_dir = ?data_dir, "Data directory validation successful");
-    }
-    info!(data_dir = ?config.data_dir, "Starting add bookmark daemon");
-    daemon::add_bookmark::run(&pool, &config, new_task_rx, new_bookmark_tx).await
+    info!("Starting add bookmark daemon");
+    daemon::add_bookmark::run(
+        &pool,
+        &config,
+        storage.as_ref(),
+        new_task_rx,
+        new_bookmark_tx,
+        &events,
+        mailer.as_ref(),
+    )
+    .await
 }
 
 async fn setup_text_ai_daemon(
     llm_client: Option<LlmClient>,
-    pool: PgPool,
+    pool: Database,
     new_bookmark_rx: tokio::sync::watch::Receiver<()>,
     settings: daemon::AiDaemonSettings,
 ) -> anyhow::Result<()> {
@@ -281,7 +443,7 @@ async fn setup_text_ai_daemon(
 
 async fn setup_embeddings_daemon(
     llm_client: Option<LlmClient>,
-    pool: PgPool,
+    pool: Database,
     new_bookmark_rx: tokio::sync::watch::Receiver<()>,
     settings: daemon::AiDaemonSettings,
 ) -> anyhow::Result<()> {
@@ -299,3 +461,108 @@ async fn setup_embeddings_daemon(
         }
     }
 }
+
+/// Wakes the daemons on tasks and bookmarks queued through any replica, the
+/// watch channels alone only carry what this process queued. Relays the
+/// events of this replica to the others and publishes theirs.
+async fn setup_notify_listener(
+    config: Config,
+    pool: Database,
+    new_task_tx: tokio::sync::watch::Sender<()>,
+    new_bookmark_tx: tokio::sync::watch::Sender<()>,
+    events: EventBus,
+    mut relayed_events: tokio::sync::mpsc::UnboundedReceiver<String>,
+) -> anyhow::Result<()> {
+    let (Database::Postgres(_), Some(pg)) = (&pool, config.pg) else {
+        info!("Not on postgres, disabling notification listener");
+        return pending::<anyhow::Result<()>>().await;
+    };
+    let connection = db::connection_config(&pg)?;
+    tokio::spawn(async move {
+        while let Some(payload) = relayed_events.recv().await {
+            let sent = db::notify::notify(&pool, db::notify::SERVER_EVENT, &payload).await;
+            if let Err(error) = sent {
+                warn!(?error, "Failed to relay event to other replicas");
+            }
+        }
+    });
+    loop {
+        let result = db::notify::listen(
+            &connection,
+            &[
+                db::notify::TASK_QUEUED,
+                db::notify::BOOKMARK_SAVED,
+                db::notify::SERVER_EVENT,
+            ],
+            |channel, payload| match (channel, payload) {
+                (db::notify::TASK_QUEUED, _) => new_task_tx.send_replace(()),
+                (db::notify::BOOKMARK_SAVED, _) => new_bookmark_tx.send_replace(()),
+                (db::notify::SERVER_EVENT, Some(payload)) => events.publish_relayed(payload),
+                _ => {}
+            },
+        )
+        .await;
+        if let Err(error) = result {
+            warn!(?error, "Notification listener disconnected, reconnecting");
+        }
+        tokio::time::sleep(NOTIFY_RECONNECT_DELAY).await;
+    }
+}
+
+async fn setup_scheduler(
+    config: Config,
+    pool: Database,
+    llm_client: Option<LlmClient>,
+    mailer: Option<Mailer>,
+    storage: Arc<dyn StorageBackend>,
+) -> anyhow::Result<()> {
+    let jobs = config.jobs;
+    let mut scheduler = Scheduler::new(pool.clone(), Duration::from_secs(jobs.job_jitter_secs));
+
+    match llm_client {
+        Some(client) if jobs.job_digest_enabled => {
+            info!(email_enabled = mailer.is_some(), "Enabling digest job");
+            let pool = pool.clone();
+            scheduler.add("digest", jobs.job_digest_schedule, move || {
+                let (pool, client, mailer) = (pool.clone(), client.clone(), mailer.clone());
+                async move { daemon::digest::run(&pool, &client, mailer.as_ref()).await }
+            });
+        }
+        None if jobs.job_digest_enabled => warn!("No LLM configured, disabling digest job"),
+        _ => info!("Digest job disabled"),
+    }
+
+    if jobs.job_trash_enabled {
+        let retention_days = config.trash_retention_days;
+        info!(retention_days, "Enabling trash job");
+        let pool = pool.clone();
+        scheduler.add("trash", jobs.job_trash_schedule, move || {
+            let (pool, storage) = (pool.clone(), storage.clone());
+            async move { daemon::trash::run(&pool, storage.as_ref(), retention_days).await }
+        });
+    } else {
+        info!("Trash job disabled");
+    }
+
+    let settings = RetentionSettings {
+        task_days: config.task_retention_days,
+        rag_days: config.rag_retention_days,
+        embedding_cache_days: config.embedding_cache_retention_days,
+    };
+    if jobs.job_retention_enabled {
+        info!(?settings, "Enabling retention job");
+        scheduler.add("retention", jobs.job_retention_schedule, move || {
+            let pool = pool.clone();
+            async move { daemon::retention::run(&pool, settings).await }
+        });
+    } else {
+        info!("Retention job disabled");
+    }
+
+    if scheduler.is_empty() {
+        info!("No periodic job enabled, disabling scheduler");
+        return pending::<anyhow::Result<()>>().await;
+    }
+    info!("Starting scheduler");
+    scheduler.run().await
+}
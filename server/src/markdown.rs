@@ -0,0 +1,51 @@
+//! Saved articles as Markdown, for note tools and terminals.
+
+use shared::Bookmark;
+
+/// The stored page is only the readable part of the article, the title and
+/// source go on top. Images keep their `/static/...` links, relative to the
+/// server.
+pub fn article(bookmark: &Bookmark, page: &str) -> String {
+    let body = html2md::parse_html(page);
+    format!(
+        "# {}\n\n<{}>\n\n{}\n",
+        bookmark.title.trim(),
+        bookmark.url,
+        body.trim()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use shared::{Bookmark, Visibility};
+    use uuid::Uuid;
+
+    use super::article;
+
+    #[test]
+    fn articles_keep_structure_and_image_links() {
+        let bookmark = Bookmark {
+            bookmark_id: "abc".to_string(),
+            user_id: Uuid::nil(),
+            url: "https://example.com/post".to_string(),
+            domain: "example.com".to_string(),
+            title: "A post ".to_string(),
+            tags: None,
+            summary: None,
+            created_at: Utc::now(),
+            updated_at: None,
+            word_count: 0,
+            reading_time_minutes: 0,
+            kind: None,
+            favorite: false,
+            original_title: None,
+            visibility: Visibility::Private,
+        };
+        let page = r#"<div><h2>Intro</h2><p>Some <strong>bold</strong> and a <a href="https://rust-lang.org">link</a></p><img src="/static/u/abc/1" alt="chart"></div>"#;
+        assert_eq!(
+            article(&bookmark, page),
+            "# A post\n\n<https://example.com/post>\n\nIntro\n----------\n\nSome **bold** and a [link](https://rust-lang.org)\n\n![chart](/static/u/abc/1)\n"
+        );
+    }
+}
@@ -1,17 +1,17 @@
+use std::net::SocketAddr;
+
 use axum::body::Body;
-use axum::extract::Request;
+use axum::extract::{ConnectInfo, Request};
 use axum::http::{HeaderMap, StatusCode};
 use axum::middleware::{self, Next};
 use axum::response::Response;
 use axum::Router;
-use jsonwebtoken::{decode, DecodingKey, Validation};
 use rmcp::transport::streamable_http_server::session::local::LocalSessionManager;
 use rmcp::transport::streamable_http_server::tower::StreamableHttpService as TowerStreamableHttpService;
 use rmcp::transport::streamable_http_server::{StreamableHttpServerConfig, StreamableHttpService};
-use secrecy::ExposeSecret;
 use tracing::warn;
 
-use crate::endpoints::Claim;
+use crate::endpoints::authenticate;
 use crate::AppContext;
 
 mod server;
@@ -20,8 +20,9 @@ mod tools;
 use server::BookmarkMcpServer;
 
 /// Validate the `Authorization: Bearer <jwt>` header using the same HS256
-/// secret the REST API uses, then stash the resulting `Claim` in request
-/// extensions so MCP tool handlers can read it.
+/// secret the REST API uses, refusing tokens of revoked sessions, then stash
+/// the resulting `Claim` in request extensions so MCP tool handlers can read
+/// it.
 ///
 /// `AppContext` is read from request extensions (installed by the outer
 /// `Extension(app_state)` layer in `main.rs`).
@@ -46,11 +47,14 @@ async fn mcp_bearer_auth(
         None => return Err(StatusCode::UNAUTHORIZED),
     };
 
-    let decoder = DecodingKey::from_secret(ctx.config.hmac_key.expose_secret().as_bytes());
-    match decode::<Claim>(bearer, &decoder, &Validation::default()) {
-        Ok(data) => {
+    let ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(client_addr)| client_addr.ip());
+    match authenticate(&ctx, bearer, ip).await {
+        Ok(claims) => {
             let mut request = request;
-            request.extensions_mut().insert(data.claims);
+            request.extensions_mut().insert(claims);
             Ok(next.run(request).await)
         }
         Err(err) => {
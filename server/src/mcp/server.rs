@@ -6,8 +6,9 @@ use rmcp::model::{
 use rmcp::service::RequestContext;
 use rmcp::{tool, tool_handler, tool_router, ErrorData as McpError, RoleServer, ServerHandler};
 use shared::{
-    BookmarkTaskSearchRequest, BookmarkTaskStatus, Bookmarks, RagHistoryRequest, RagQueryRequest,
-    SearchRequest, TagCount, TagFilter, TagOperation, TagsWithCounters,
+    AuditAction, BookmarkTaskSearchRequest, BookmarkTaskStatus, Bookmarks, RagHistoryRequest,
+    RagQueryRequest, SearchRequest, ServerEvent, TagCount, TagFilter, TagOperation,
+    TagsWithCounters,
 };
 use tracing::{error, info, warn};
 use url::Url;
@@ -17,10 +18,11 @@ use super::tools::{
     GetBookmarksByTagParams, ListTasksParams, RagHistoryParams, RagQueryParams,
     SearchBookmarksParams, SetTagsParams,
 };
-use crate::db::{bookmark, bookmark_task, rag as rag_db, search as search_db};
-use crate::endpoints::Claim;
+use crate::db::{bookmark, bookmark_task, rag as rag_db, search as search_db, storage};
+use crate::endpoints::{audit, Claim};
 use crate::error::Error as AppError;
 use crate::rag::RagEngine;
+use crate::url_guard::UrlGuard;
 use crate::AppContext;
 
 #[derive(Clone)]
@@ -35,7 +37,9 @@ fn map_err(error: AppError) -> McpError {
         AppError::Unauthorized | AppError::InvalidToken | AppError::WrongCredentials => {
             McpError::invalid_params("unauthorized", None)
         }
-        AppError::BadRequest { .. } | AppError::UnprocessableEntity { .. } => {
+        AppError::BadRequest { .. }
+        | AppError::UnprocessableEntity { .. }
+        | AppError::StorageQuotaExceeded { .. } => {
             McpError::invalid_params(error.to_string(), None)
         }
         ref other => McpError::internal_error(other.to_string(), None),
@@ -47,6 +51,7 @@ fn parse_task_status(s: &str) -> Result<BookmarkTaskStatus, McpError> {
         "pending" => Ok(BookmarkTaskStatus::Pending),
         "done" => Ok(BookmarkTaskStatus::Done),
         "fail" => Ok(BookmarkTaskStatus::Fail),
+        "cancelled" => Ok(BookmarkTaskStatus::Cancelled),
         other => Err(McpError::invalid_params(
             format!("unknown status '{other}'"),
             None,
@@ -130,12 +135,32 @@ impl BookmarkMcpServer {
         let (claim, app_ctx) = auth_ctx(&ctx)?;
         let url = Url::parse(&params.url)
             .map_err(|e| McpError::invalid_params(format!("invalid url: {e}"), None))?;
+        UrlGuard::new(&app_ctx.config.fetch.fetch_allowed_networks)
+            .check(&url)
+            .map_err(|blocked| McpError::invalid_params(blocked.to_string(), None))?;
         let mut tags = params.tags.unwrap_or_default();
         tags.retain(|t| !t.trim().is_empty());
 
-        let task = bookmark_task::create(&app_ctx.pool, claim.user_id, url.clone(), tags)
-            .await
-            .map_err(map_err)?;
+        storage::ensure_within_quota(
+            &app_ctx.pool,
+            claim.user_id,
+            app_ctx.config.storage_quota_bytes,
+        )
+        .await
+        .map_err(map_err)?;
+        let task = bookmark_task::create_with_robots_override(
+            &app_ctx.pool,
+            claim.user_id,
+            url.clone(),
+            tags,
+            params.ignore_robots,
+        )
+        .await
+        .map_err(map_err)?;
+        app_ctx.events.publish(
+            claim.user_id,
+            ServerEvent::TaskUpdated { task: task.clone() },
+        );
         if let Err(err) = app_ctx.tx_new_task.send(()) {
             error!(?err, "failed to notify ingestion daemon of new task");
         }
@@ -144,7 +169,7 @@ impl BookmarkMcpServer {
     }
 
     #[tool(
-        description = "Delete a bookmark by its identifier. Returns success even if the bookmark's static files could not be removed."
+        description = "Delete a bookmark by its identifier. It is moved to the trash, where it can be restored until it is purged."
     )]
     async fn delete_bookmark(
         &self,
@@ -152,27 +177,20 @@ impl BookmarkMcpServer {
         Parameters(params): Parameters<DeleteBookmarkParams>,
     ) -> Result<CallToolResult, McpError> {
         let (claim, app_ctx) = auth_ctx(&ctx)?;
-        let deleted = bookmark::delete(&app_ctx.pool, claim.user_id, &params.bookmark_id)
+        let deleted = bookmark::move_to_trash(&app_ctx.pool, claim.user_id, &params.bookmark_id)
             .await
             .map_err(map_err)?;
         if !deleted {
             return Err(McpError::resource_not_found("bookmark not found", None));
         }
-        let static_dir = app_ctx
-            .config
-            .data_dir
-            .join(claim.user_id.to_string())
-            .join(&params.bookmark_id);
-        if static_dir.exists() {
-            if let Err(err) = tokio::fs::remove_dir_all(&static_dir).await {
-                error!(
-                    bookmark_id = %params.bookmark_id,
-                    path = ?static_dir,
-                    error = %err,
-                    "failed to remove static files for deleted bookmark"
-                );
-            }
-        }
+        audit::record(
+            &app_ctx.pool,
+            claim.user_id,
+            AuditAction::BookmarkDeleted,
+            Some(&params.bookmark_id),
+            None,
+        )
+        .await;
         ok_text("deleted")
     }
 
@@ -267,6 +285,22 @@ impl BookmarkMcpServer {
         let request = SearchRequest {
             query: params.query,
             tags_filter,
+            domains: params
+                .domains
+                .unwrap_or_default()
+                .iter()
+                .map(|domain| domain.trim().to_lowercase())
+                .collect(),
+            from_date: None,
+            to_date: None,
+            reading_times: vec![],
+            kinds: vec![],
+            entities: vec![],
+            collection: None,
+            workspace: None,
+            snippet_count: None,
+            snippet_words: None,
+            highlight_title_summary: false,
             limit: params.limit,
             offset: params.offset,
         };
@@ -303,6 +337,7 @@ impl BookmarkMcpServer {
             to_created_at: None,
             page_size: params.page_size,
             last_task_id,
+            include_total: false,
         };
         let response = bookmark_task::search(&app_ctx.pool, claim.user_id, &request)
             .await
@@ -335,8 +370,18 @@ impl BookmarkMcpServer {
             similarity_threshold: params.similarity_threshold,
             max_context_tokens: params.max_context_tokens,
             hybrid_search: None,
+            tags_filter: None,
+            bookmark_ids: None,
+            workspace: None,
+            history: Vec::new(),
+            rerank: params.rerank.unwrap_or(false),
         };
-        let engine = RagEngine::new(app_ctx.pool.clone(), llm_client);
+        let engine = RagEngine::new(
+            app_ctx.pool.clone(),
+            llm_client,
+            app_ctx.config.llm.rag_relevance_concurrency,
+            app_ctx.config.llm.rag_hnsw_ef_search,
+        );
         match engine.process_query(claim.user_id, &request).await {
             Ok(response) => ok_json(&response),
             Err(err) => {
@@ -350,7 +395,7 @@ impl BookmarkMcpServer {
     }
 
     #[tool(
-        description = "List past RAG question/answer sessions for the authenticated user, with the source chunks that backed each answer."
+        description = "List past RAG question/answer sessions for the authenticated user, newest first, with the source chunks that backed each answer. Optionally only those matching a full-text query."
     )]
     async fn rag_history(
         &self,
@@ -361,6 +406,7 @@ impl BookmarkMcpServer {
         let request = RagHistoryRequest {
             limit: params.limit,
             offset: params.offset,
+            query: params.query,
         };
         let response = rag_db::get_rag_history(&app_ctx.pool, claim.user_id, &request)
             .await
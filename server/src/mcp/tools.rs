@@ -14,6 +14,9 @@ pub struct CreateBookmarkParams {
     /// Optional tags to attach to the bookmark. Empty strings are ignored.
     #[serde(default)]
     pub tags: Option<Vec<String>>,
+    /// Save the page even if robots.txt or a noindex meta tag opts out.
+    #[serde(default)]
+    pub ignore_robots: bool,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -53,6 +56,9 @@ pub struct SearchBookmarksParams {
     /// Defaults to "or".
     #[serde(default)]
     pub tags_filter_type: Option<String>,
+    /// Only bookmarks from these domains, e.g. "lwn.net".
+    #[serde(default)]
+    pub domains: Option<Vec<String>>,
     /// Max number of results (default 20).
     #[serde(default)]
     pub limit: Option<i32>,
@@ -66,7 +72,7 @@ pub struct ListTasksParams {
     /// Case-insensitive substring to filter task URLs by.
     #[serde(default)]
     pub url: Option<String>,
-    /// Task status to filter by: "pending", "done", or "fail".
+    /// Task status to filter by: "pending", "done", "fail" or "cancelled".
     #[serde(default)]
     pub status: Option<String>,
     /// Tags to filter tasks by (all must be present).
@@ -93,6 +99,10 @@ pub struct RagQueryParams {
     /// Maximum tokens of context to feed the LLM (default 4096).
     #[serde(default)]
     pub max_context_tokens: Option<usize>,
+    /// Re-rank the chunks with an LLM score against the question before
+    /// answering, slower but more precise (default false).
+    #[serde(default)]
+    pub rerank: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -103,4 +113,7 @@ pub struct RagHistoryParams {
     /// Offset for pagination.
     #[serde(default)]
     pub offset: Option<usize>,
+    /// Only sessions whose title, question or answer contain these words.
+    #[serde(default)]
+    pub query: Option<String>,
 }
@@ -1,14 +1,21 @@
 use std::cmp::Ordering;
 
 use anyhow::{Context, Result};
-use shared::{RagChunkMatch, RagQueryRequest, RagQueryResponse};
+use futures::stream::{self, StreamExt};
+use shared::{
+    AskBookmarkRequest, RagChunkMatch, RagCitation, RagQueryRequest, RagQueryResponse,
+    RagStreamEvent, RagTurn,
+};
+use tokio::sync::mpsc::UnboundedSender;
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
-use crate::db::chunks::{search_chunks_hybrid, search_similar_chunks, HybridChunkMatch};
-use crate::db::rag::{create_rag_session, update_rag_session};
-use crate::db::PgPool;
-use crate::llm::{self, LlmClient};
+use crate::db::chunks::{
+    search_chunks_hybrid, search_similar_chunks, ChunkScope, HybridChunkMatch,
+};
+use crate::db::rag::{create_rag_session, set_rag_session_title, update_rag_session};
+use crate::db::{ensure_embedding_profile, Database};
+use crate::llm::{self, LlmClient, UsageContext};
 use crate::tokenizer::count_tokens;
 
 const DEFAULT_MAX_CHUNKS: usize = 6;
@@ -16,31 +23,213 @@ const DEFAULT_SIMILARITY_THRESHOLD: f64 = 0.3;
 const DEFAULT_MAX_CONTEXT_TOKENS: usize = 4096;
 const PROMPT_OVERHEAD_TOKENS: usize = 200;
 const DEFAULT_RRF_K: u32 = 60;
+/// Earlier turns of a conversation kept in the prompts
+const MAX_HISTORY_TURNS: usize = 6;
+/// Passages of a bookmark a question about it is answered from
+const BOOKMARK_MAX_CHUNKS: usize = 8;
+const MAX_TITLE_CHARS: usize = 80;
+
+/// Weighted Reciprocal Rank Fusion score
+/// RRF(d) = w_vector/(k + rank_vector) + w_fts/(k + rank_fts)
+/// A list that did not return the chunk contributes nothing.
+fn rrf_score(
+    vector_rank: Option<usize>,
+    fts_rank: Option<usize>,
+    k: u32,
+    vector_weight: f64,
+    fts_weight: f64,
+) -> f64 {
+    let term = |rank: Option<usize>, weight: f64| {
+        rank.map_or(0.0, |rank| weight / (k as f64 + rank as f64))
+    };
+    term(vector_rank, vector_weight) + term(fts_rank, fts_weight)
+}
+
+/// Positions of the re-ranked chunks, by descending score, the unscored ones
+/// last. Ties keep their order, the similarity one.
+fn rerank_order(scores: &[Option<f64>]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|a, b| {
+        let score = |index: &usize| scores[*index].unwrap_or(-1.0);
+        score(b).total_cmp(&score(a))
+    });
+    order
+}
+
+/// Keeps the citation markers of `answer`, like `[1]` or `[1, 3]`, to the
+/// numbers of its `source_count` sources, dropping the others. Returns the
+/// cleaned answer and the numbers cited, in ascending order.
+fn validate_citations(answer: &str, source_count: usize) -> (String, Vec<usize>) {
+    let mut cleaned = String::with_capacity(answer.len());
+    let mut cited = Vec::new();
+    let mut rest = answer;
+    while let Some(start) = rest.find('[') {
+        cleaned.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let marker = rest.find(']').and_then(|end| {
+            // `[1](...)` is a link, not a citation
+            if rest[end + 1..].starts_with('(') {
+                return None;
+            }
+            let numbers = rest[1..end]
+                .split(',')
+                .map(|number| number.trim().parse::<usize>().ok())
+                .collect::<Option<Vec<_>>>()?;
+            Some((end, numbers))
+        });
+        let Some((end, numbers)) = marker else {
+            cleaned.push('[');
+            rest = &rest[1..];
+            continue;
+        };
+        let valid: Vec<usize> = numbers
+            .into_iter()
+            .filter(|number| (1..=source_count).contains(number))
+            .collect();
+        if valid.is_empty() {
+            cleaned.truncate(cleaned.trim_end().len());
+        } else {
+            let numbers: Vec<String> = valid.iter().map(ToString::to_string).collect();
+            cleaned.push_str(&format!("[{}]", numbers.join(", ")));
+            cited.extend(valid);
+        }
+        rest = &rest[end + 1..];
+    }
+    cleaned.push_str(rest);
+    cited.sort_unstable();
+    cited.dedup();
+    (cleaned, cited)
+}
 
-/// Reciprocal Rank Fusion score
-/// RRF(d) = 1/(k + rank_vector) + 1/(k + rank_fts)
-fn rrf_score(vector_rank: usize, fts_rank: usize, k: u32) -> f64 {
-    1.0 / (k as f64 + vector_rank as f64) + 1.0 / (k as f64 + fts_rank as f64)
+/// The answer with its valid citation markers only, and the sources they
+/// cite among `sources`, the chunks numbered in the prompt.
+fn cite_sources(answer: &str, sources: &[RagChunkMatch]) -> (String, Vec<RagCitation>) {
+    let (answer, cited) = validate_citations(answer, sources.len());
+    let citations = cited
+        .into_iter()
+        .map(|number| {
+            let source = &sources[number - 1];
+            RagCitation {
+                number,
+                bookmark_id: source.bookmark.bookmark_id.clone(),
+                chunk_id: source.chunk.chunk_id,
+            }
+        })
+        .collect();
+    (answer, citations)
+}
+
+/// The title in the reply of the model, without the quotes, markup or label
+/// models tend to add, `None` when there is none.
+fn clean_title(reply: &str) -> Option<String> {
+    fn undecorated(text: &str) -> &str {
+        text.trim_matches(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '*' | '#' | '`'))
+    }
+    let line = reply
+        .lines()
+        .map(undecorated)
+        .find(|line| !line.is_empty())?;
+    let line = line
+        .strip_prefix("Title:")
+        .or_else(|| line.strip_prefix("title:"))
+        .unwrap_or(line);
+    let title = undecorated(line).trim_end_matches('.').trim();
+    if title.is_empty() {
+        return None;
+    }
+    if title.chars().count() <= MAX_TITLE_CHARS {
+        return Some(title.to_string());
+    }
+    let truncated: String = title.chars().take(MAX_TITLE_CHARS).collect();
+    let truncated = truncated
+        .rsplit_once(' ')
+        .map_or(truncated.as_str(), |(words, _)| words);
+    Some(format!("{}…", truncated.trim_end()))
+}
+
+const NO_CONTEXT_ANSWER: &str =
+    "I couldn't find any relevant information in your bookmarks to answer this question.";
+
+/// Optional progress channel for the streaming endpoint. Send errors are
+/// ignored, a closed receiver only means the client went away.
+#[derive(Clone, Copy)]
+struct EventSink<'a>(Option<&'a UnboundedSender<RagStreamEvent>>);
+
+impl EventSink<'_> {
+    fn emit(&self, event: RagStreamEvent) {
+        if let Some(tx) = self.0 {
+            let _ = tx.send(event);
+        }
+    }
+
+    fn is_streaming(&self) -> bool {
+        self.0.is_some()
+    }
 }
 
 pub struct RagEngine {
-    pool: PgPool,
+    pool: Database,
     client: LlmClient,
+    relevance_concurrency: usize,
+    hnsw_ef_search: u32,
 }
 
 impl RagEngine {
-    pub fn new(pool: PgPool, client: LlmClient) -> Self {
-        Self { pool, client }
+    pub fn new(
+        pool: Database,
+        client: LlmClient,
+        relevance_concurrency: usize,
+        hnsw_ef_search: u32,
+    ) -> Self {
+        Self {
+            pool,
+            client,
+            relevance_concurrency: relevance_concurrency.max(1),
+            hnsw_ef_search,
+        }
+    }
+
+    fn for_session(&self, user_id: Uuid, session_id: Uuid) -> Self {
+        Self {
+            pool: self.pool.clone(),
+            client: self
+                .client
+                .with_usage_context(UsageContext::session(user_id, session_id)),
+            relevance_concurrency: self.relevance_concurrency,
+            hnsw_ef_search: self.hnsw_ef_search,
+        }
     }
 
     pub async fn process_query(
         &self,
         user_id: Uuid,
         request: &RagQueryRequest,
+    ) -> Result<RagQueryResponse> {
+        self.run_pipeline(user_id, request, EventSink(None)).await
+    }
+
+    /// Same pipeline as [`Self::process_query`], reporting each stage on
+    /// `events` and streaming the answer as it is generated.
+    pub async fn process_query_streaming(
+        &self,
+        user_id: Uuid,
+        request: &RagQueryRequest,
+        events: &UnboundedSender<RagStreamEvent>,
+    ) -> Result<RagQueryResponse> {
+        self.run_pipeline(user_id, request, EventSink(Some(events)))
+            .await
+    }
+
+    async fn run_pipeline(
+        &self,
+        user_id: Uuid,
+        request: &RagQueryRequest,
+        events: EventSink<'_>,
     ) -> Result<RagQueryResponse> {
         info!(
             user_id = %user_id,
             question = %request.question,
+            streaming = events.is_streaming(),
             "Processing RAG query"
         );
 
@@ -48,30 +237,24 @@ impl RagEngine {
         let session = create_rag_session(&self.pool, user_id, &request.question)
             .await
             .context("Failed to create RAG session")?;
+        // LLM usage from here on is attributed to this session
+        let engine = self.for_session(user_id, session.session_id);
 
-        // Step 1: Question augmentation - generate similar questions
-        let questions = self.generate_query_variations(&request.question).await?;
-
-        // Step 2: Generate embeddings for all questions
-        let mut all_matches = Vec::new();
-        for question in questions {
-            let matches = self
-                .search_for_question(user_id, &question, request)
-                .await?;
-            all_matches.extend(matches);
-        }
+        // A follow-up question is searched once rewritten to stand on its own
+        let history = &request.history[request.history.len().saturating_sub(MAX_HISTORY_TURNS)..];
+        let question = engine.standalone_question(history, &request.question).await;
 
-        // Remove duplicates and sort by similarity
-        all_matches.sort_by(|a, b| {
-            b.similarity_score
-                .partial_cmp(&a.similarity_score)
-                .unwrap_or(Ordering::Equal)
+        // Step 1: Question augmentation - generate similar questions
+        let questions = engine.generate_query_variations(&question).await?;
+        events.emit(RagStreamEvent::VariationsGenerated {
+            questions: questions.clone(),
         });
-        all_matches.dedup_by(|a, b| a.chunk.chunk_id == b.chunk.chunk_id);
 
-        // Limit the number of chunks
-        let max_chunks = request.max_chunks.unwrap_or(DEFAULT_MAX_CHUNKS);
-        all_matches.truncate(max_chunks);
+        // Step 2: Retrieve candidate chunks for every question variation
+        let all_matches = engine.retrieve_chunks(user_id, &questions, request).await?;
+        events.emit(RagStreamEvent::ChunksRetrieved {
+            count: all_matches.len(),
+        });
 
         info!(
             user_id = %user_id,
@@ -81,9 +264,21 @@ impl RagEngine {
         );
 
         // Step 3: Assess relevance of each chunk
-        let relevant_matches = self
-            .assess_chunk_relevance(&request.question, all_matches)
+        let total = all_matches.len();
+        let relevant_matches = engine
+            .assess_chunk_relevance(&question, all_matches)
             .await?;
+        events.emit(RagStreamEvent::RelevanceAssessed {
+            relevant: relevant_matches.len(),
+            total,
+        });
+
+        // Optionally order the relevant chunks by an LLM score instead
+        let relevant_matches = if request.rerank {
+            engine.rerank(&question, relevant_matches).await
+        } else {
+            relevant_matches
+        };
 
         // Step 4: Apply token budget to select chunks that fit within context limits
         let max_tokens = request
@@ -99,27 +294,115 @@ impl RagEngine {
             "Applied token budget to chunks"
         );
 
-        // Step 5: Generate answer using budgeted chunks
+        // Steps 5 and 6: answer from the budgeted chunks and record it
+        engine
+            .answer_session(
+                user_id,
+                session.session_id,
+                &request.question,
+                history,
+                budgeted_matches,
+                events,
+            )
+            .await
+    }
+
+    /// Answers `question` from the passages of one bookmark only, found by
+    /// similarity to the question alone, without the variations and
+    /// relevance assessment of the full pipeline.
+    pub async fn ask_bookmark(
+        &self,
+        user_id: Uuid,
+        bookmark_id: &str,
+        request: &AskBookmarkRequest,
+    ) -> Result<RagQueryResponse> {
+        info!(
+            user_id = %user_id,
+            bookmark_id = %bookmark_id,
+            question = %request.question,
+            "Processing bookmark question"
+        );
+
+        let session = create_rag_session(&self.pool, user_id, &request.question)
+            .await
+            .context("Failed to create RAG session")?;
+        let engine = self.for_session(user_id, session.session_id);
+
+        let history = &request.history[request.history.len().saturating_sub(MAX_HISTORY_TURNS)..];
+        let question = engine.standalone_question(history, &request.question).await;
+
+        ensure_embedding_profile(&engine.pool, &engine.client.embedding_profile())
+            .await
+            .context("Query embeddings are incompatible with stored chunks")?;
+        let query_embedding = llm::embeddings_interactive(&engine.client, &question)
+            .await
+            .context("Failed to generate embedding for question")?;
+        let bookmark_ids = vec![bookmark_id.to_string()];
+        // Every passage of the bookmark is a candidate, the closest first
+        let matches = search_similar_chunks(
+            &engine.pool,
+            user_id,
+            query_embedding,
+            engine.client.embedding_ndims,
+            engine.hnsw_ef_search,
+            BOOKMARK_MAX_CHUNKS,
+            -1.0,
+            ChunkScope {
+                tags_filter: None,
+                bookmark_ids: Some(&bookmark_ids),
+                workspace_id: None,
+            },
+        )
+        .await
+        .context("Failed to search the chunks of the bookmark")?;
+        let budgeted_matches =
+            engine.select_chunks_within_budget(matches, DEFAULT_MAX_CONTEXT_TOKENS)?;
+
+        engine
+            .answer_session(
+                user_id,
+                session.session_id,
+                &request.question,
+                history,
+                budgeted_matches,
+                EventSink(None),
+            )
+            .await
+    }
+
+    /// Answers from `budgeted_matches`, the sources numbered in the prompt,
+    /// and records the answer with the session, titled in the background.
+    async fn answer_session(
+        self,
+        user_id: Uuid,
+        session_id: Uuid,
+        question: &str,
+        history: &[RagTurn],
+        budgeted_matches: Vec<RagChunkMatch>,
+        events: EventSink<'_>,
+    ) -> Result<RagQueryResponse> {
         let answer = if budgeted_matches.is_empty() {
-            "I couldn't find any relevant information in your bookmarks to answer this question."
-                .to_string()
+            events.emit(RagStreamEvent::AnswerDelta {
+                text: NO_CONTEXT_ANSWER.to_string(),
+            });
+            NO_CONTEXT_ANSWER.to_string()
         } else {
             let context_chunks: Vec<String> = budgeted_matches
                 .iter()
                 .map(|m| m.chunk.chunk_text.clone())
                 .collect();
 
-            self.generate_answer(&request.question, &context_chunks)
+            self.generate_answer(question, history, &context_chunks, events)
                 .await?
         };
+        let (answer, citations) = cite_sources(&answer, &budgeted_matches);
 
-        // Step 6: Update session with answer and relevant chunks
         let relevant_chunk_ids: Vec<Uuid> =
             budgeted_matches.iter().map(|m| m.chunk.chunk_id).collect();
 
         let updated_session = update_rag_session(
             &self.pool,
-            session.session_id,
+            session_id,
             user_id,
             &answer,
             &relevant_chunk_ids,
@@ -129,20 +412,98 @@ impl RagEngine {
 
         info!(
             user_id = %user_id,
-            session_id = %session.session_id,
+            session_id = %session_id,
             relevant_chunks = budgeted_matches.len(),
             "RAG query processed successfully"
         );
 
+        // Titled in the background, the answer doesn't wait for it
+        let (title_question, title_answer) = (question.to_string(), answer.clone());
+        tokio::spawn(async move {
+            self.title_session(user_id, session_id, &title_question, &title_answer)
+                .await
+        });
+
         Ok(RagQueryResponse {
             session_id: updated_session.session_id,
-            question: request.question.clone(),
+            question: question.to_string(),
             answer,
             relevant_chunks: budgeted_matches,
+            citations,
             created_at: updated_session.created_at,
         })
     }
 
+    async fn retrieve_chunks(
+        &self,
+        user_id: Uuid,
+        questions: &[String],
+        request: &RagQueryRequest,
+    ) -> Result<Vec<RagChunkMatch>> {
+        ensure_embedding_profile(&self.pool, &self.client.embedding_profile())
+            .await
+            .context("Query embeddings are incompatible with stored chunks")?;
+
+        let mut all_matches = Vec::new();
+        for question in questions {
+            let matches = self.search_for_question(user_id, question, request).await?;
+            all_matches.extend(matches);
+        }
+
+        // Remove duplicates and sort by similarity
+        all_matches.sort_by(|a, b| {
+            b.similarity_score
+                .partial_cmp(&a.similarity_score)
+                .unwrap_or(Ordering::Equal)
+        });
+        all_matches.dedup_by(|a, b| a.chunk.chunk_id == b.chunk.chunk_id);
+
+        // Limit the number of chunks
+        let max_chunks = request.max_chunks.unwrap_or(DEFAULT_MAX_CHUNKS);
+        all_matches.truncate(max_chunks);
+        Ok(all_matches)
+    }
+
+    async fn standalone_question(&self, history: &[RagTurn], question: &str) -> String {
+        if history.is_empty() {
+            return question.to_string();
+        }
+        match llm::standalone_question(&self.client, history, question).await {
+            Ok(standalone) if !standalone.trim().is_empty() => {
+                debug!(
+                    original_question = %question,
+                    standalone_question = %standalone.trim(),
+                    "Rewrote follow-up question"
+                );
+                standalone.trim().to_string()
+            }
+            Ok(_) => question.to_string(),
+            Err(error) => {
+                warn!(
+                    ?error,
+                    "Failed to rewrite follow-up question, using original"
+                );
+                question.to_string()
+            }
+        }
+    }
+
+    async fn title_session(&self, user_id: Uuid, session_id: Uuid, question: &str, answer: &str) {
+        let title = match llm::session_title(&self.client, question, answer).await {
+            Ok(reply) => clean_title(&reply),
+            Err(error) => {
+                warn!(?error, session_id = %session_id, "Failed to generate RAG session title");
+                return;
+            }
+        };
+        let Some(title) = title else {
+            return;
+        };
+        if let Err(error) = set_rag_session_title(&self.pool, session_id, user_id, &title).await {
+            warn!(?error, session_id = %session_id, "Failed to store RAG session title");
+        }
+    }
+
     async fn generate_query_variations(&self, question: &str) -> Result<Vec<String>> {
         let mut questions = vec![question.to_string()];
 
@@ -183,6 +544,12 @@ impl RagEngine {
             .unwrap_or(DEFAULT_SIMILARITY_THRESHOLD);
         let max_chunks = request.max_chunks.unwrap_or(DEFAULT_MAX_CHUNKS);
 
+        let scope = ChunkScope {
+            tags_filter: request.tags_filter.as_ref(),
+            bookmark_ids: request.bookmark_ids.as_ref(),
+            workspace_id: request.workspace.as_ref(),
+        };
+
         // Check if hybrid search is enabled
         let use_hybrid = request
             .hybrid_search
@@ -197,8 +564,10 @@ impl RagEngine {
                 question,
                 query_embedding,
                 self.client.embedding_ndims,
+                self.hnsw_ef_search,
                 max_chunks * 2,
                 similarity_threshold,
+                scope,
             )
             .await
             .context("Failed to search for chunks with hybrid search")?;
@@ -208,7 +577,9 @@ impl RagEngine {
 
             if use_rrf {
                 let k = config.rrf_k.unwrap_or(DEFAULT_RRF_K);
-                Ok(self.combine_with_rrf(hybrid_matches, k))
+                let vector_weight = config.vector_weight.unwrap_or(1.0);
+                let fts_weight = config.fts_weight.unwrap_or(1.0);
+                Ok(self.combine_with_rrf(hybrid_matches, k, vector_weight, fts_weight))
             } else {
                 let vector_weight = config.vector_weight.unwrap_or(0.5);
                 let fts_weight = config.fts_weight.unwrap_or(0.5);
@@ -221,8 +592,10 @@ impl RagEngine {
                 user_id,
                 query_embedding,
                 self.client.embedding_ndims,
+                self.hnsw_ef_search,
                 max_chunks * 2,
                 similarity_threshold,
+                scope,
             )
             .await
             .context("Failed to search for similar chunks")?;
@@ -238,18 +611,24 @@ impl RagEngine {
                     vector_score: None,
                     fts_score: None,
                     combined_score: None,
+                    rerank_score: None,
                 })
                 .collect())
         }
     }
 
-    /// Combine hybrid search results using Reciprocal Rank Fusion
-    /// RRF(d) = 1/(k + rank_vector) + 1/(k + rank_fts)
-    fn combine_with_rrf(&self, matches: Vec<HybridChunkMatch>, k: u32) -> Vec<RagChunkMatch> {
+    /// Combine hybrid search results using weighted Reciprocal Rank Fusion
+    fn combine_with_rrf(
+        &self,
+        matches: Vec<HybridChunkMatch>,
+        k: u32,
+        vector_weight: f64,
+        fts_weight: f64,
+    ) -> Vec<RagChunkMatch> {
         let mut results: Vec<RagChunkMatch> = matches
             .into_iter()
             .map(|m| {
-                let rrf_score = rrf_score(m.vector_rank, m.fts_rank, k);
+                let rrf_score = rrf_score(m.vector_rank, m.fts_rank, k, vector_weight, fts_weight);
                 RagChunkMatch {
                     chunk: m.chunk,
                     bookmark: m.bookmark,
@@ -258,6 +637,7 @@ impl RagEngine {
                     vector_score: Some(m.vector_score),
                     fts_score: Some(m.fts_score),
                     combined_score: Some(rrf_score),
+                    rerank_score: None,
                 }
             })
             .collect();
@@ -271,7 +651,7 @@ impl RagEngine {
 
         debug!(
             results_count = results.len(),
-            k, "Combined hybrid results with RRF"
+            k, vector_weight, fts_weight, "Combined hybrid results with RRF"
         );
 
         results
@@ -300,6 +680,7 @@ impl RagEngine {
                     vector_score: Some(m.vector_score),
                     fts_score: Some(m.fts_score),
                     combined_score: Some(combined),
+                    rerank_score: None,
                 }
             })
             .collect();
@@ -364,18 +745,64 @@ impl RagEngine {
         Ok(selected)
     }
 
+    /// Orders `matches` by the LLM score of each against `question`, keeping
+    /// their order if the scoring fails.
+    async fn rerank(&self, question: &str, matches: Vec<RagChunkMatch>) -> Vec<RagChunkMatch> {
+        if matches.is_empty() {
+            return matches;
+        }
+        let chunks: Vec<String> = matches.iter().map(|m| m.chunk.chunk_text.clone()).collect();
+        let scores = match llm::score_chunks(&self.client, question, &chunks).await {
+            Ok(scores) => scores,
+            Err(error) => {
+                warn!(?error, "Failed to re-rank chunks, keeping similarity order");
+                return matches;
+            }
+        };
+        let order = rerank_order(&scores);
+        let mut matches: Vec<Option<RagChunkMatch>> = matches.into_iter().map(Some).collect();
+        let reranked: Vec<RagChunkMatch> = order
+            .into_iter()
+            .filter_map(|index| {
+                let mut chunk_match = matches[index].take()?;
+                chunk_match.rerank_score = scores[index];
+                Some(chunk_match)
+            })
+            .collect();
+        debug!(
+            chunks = reranked.len(),
+            unscored = scores.iter().filter(|score| score.is_none()).count(),
+            "Re-ranked chunks"
+        );
+        reranked
+    }
+
+    /// Ask the LLM whether each chunk helps answer the question. Up to
+    /// `relevance_concurrency` assessments run at once; the shared LLM limiter
+    /// still caps requests across the whole server.
     async fn assess_chunk_relevance(
         &self,
         question: &str,
         matches: Vec<RagChunkMatch>,
     ) -> Result<Vec<RagChunkMatch>> {
-        let mut relevant_matches = Vec::new();
-
         let match_count = matches.len();
-        for mut chunk_match in matches {
-            match llm::assess_chunk_relevance(&self.client, question, &chunk_match.chunk.chunk_text)
-                .await
-            {
+        let assessments: Vec<_> = stream::iter(matches)
+            .map(|chunk_match| async move {
+                let assessment = llm::assess_chunk_relevance(
+                    &self.client,
+                    question,
+                    &chunk_match.chunk.chunk_text,
+                )
+                .await;
+                (chunk_match, assessment)
+            })
+            .buffer_unordered(self.relevance_concurrency)
+            .collect()
+            .await;
+
+        let mut relevant_matches = Vec::new();
+        for (mut chunk_match, assessment) in assessments {
+            match assessment {
                 Ok((is_relevant, explanation)) => {
                     if is_relevant {
                         let chunk_id = chunk_match.chunk.chunk_id;
@@ -420,15 +847,100 @@ impl RagEngine {
         info!(
             total_matches = match_count,
             relevant_matches = relevant_matches.len(),
+            concurrency = self.relevance_concurrency,
             "Completed chunk relevance assessment"
         );
 
         Ok(relevant_matches)
     }
 
-    async fn generate_answer(&self, question: &str, context_chunks: &[String]) -> Result<String> {
-        llm::answer_with_context(&self.client, question, context_chunks)
-            .await
-            .context("Failed to generate answer with context")
+    async fn generate_answer(
+        &self,
+        question: &str,
+        history: &[RagTurn],
+        context_chunks: &[String],
+        events: EventSink<'_>,
+    ) -> Result<String> {
+        if !events.is_streaming() {
+            return llm::answer_with_context(&self.client, question, history, context_chunks)
+                .await
+                .context("Failed to generate answer with context");
+        }
+        llm::stream_answer_with_context(&self.client, question, history, context_chunks, |delta| {
+            events.emit(RagStreamEvent::AnswerDelta {
+                text: delta.to_string(),
+            })
+        })
+        .await
+        .context("Failed to stream answer with context")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clean_title, rerank_order, rrf_score, validate_citations};
+
+    #[test]
+    fn titles_lose_the_decoration_models_add() {
+        assert_eq!(
+            clean_title("\n\"Tokio's work-stealing scheduler.\"\n").as_deref(),
+            Some("Tokio's work-stealing scheduler")
+        );
+        assert_eq!(
+            clean_title("**Title: Async Rust basics**").as_deref(),
+            Some("Async Rust basics")
+        );
+        assert_eq!(clean_title("  \n\"\""), None);
+        let long = clean_title(&"word ".repeat(40)).unwrap();
+        assert!(long.ends_with("word…"));
+        assert!(long.chars().count() <= 81);
+    }
+
+    #[test]
+    fn rrf_counts_only_lists_that_returned_the_chunk() {
+        let both = rrf_score(Some(1), Some(1), 60, 1.0, 1.0);
+        let vector_only = rrf_score(Some(1), None, 60, 1.0, 1.0);
+
+        assert!((both - 2.0 / 61.0).abs() < f64::EPSILON);
+        assert!((vector_only - 1.0 / 61.0).abs() < f64::EPSILON);
+        assert_eq!(rrf_score(None, None, 60, 1.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn rrf_weights_shift_ranking_towards_full_text() {
+        let vector_first = rrf_score(Some(1), Some(10), 60, 0.2, 0.8);
+        let fts_first = rrf_score(Some(10), Some(1), 60, 0.2, 0.8);
+
+        assert!(fts_first > vector_first);
+    }
+
+    #[test]
+    fn citations_out_of_range_are_dropped() {
+        let (answer, cited) = validate_citations(
+            "Tokio steals work [2]. It has a timer [1, 7] and an [io] driver [9].",
+            3,
+        );
+
+        assert_eq!(
+            answer,
+            "Tokio steals work [2]. It has a timer [1] and an [io] driver."
+        );
+        assert_eq!(cited, vec![1, 2]);
+    }
+
+    #[test]
+    fn links_and_unclosed_brackets_are_not_citations() {
+        let (answer, cited) = validate_citations("See [1](https://tokio.rs) [2] and [3", 2);
+
+        assert_eq!(answer, "See [1](https://tokio.rs) [2] and [3");
+        assert_eq!(cited, vec![2]);
+    }
+
+    #[test]
+    fn rerank_orders_by_score_with_unscored_chunks_last() {
+        let scores = [Some(3.0), None, Some(9.0), Some(3.0), Some(0.0)];
+
+        assert_eq!(rerank_order(&scores), vec![2, 0, 3, 4, 1]);
+        assert!(rerank_order(&[]).is_empty());
     }
 }
@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::auth_rate_limit::RateLimitDecision;
+use crate::RateLimitParams;
+
+/// How often buckets that refilled completely are dropped, rather than on
+/// every request, so a request doesn't cost a scan of every key.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitSubject {
+    Ip(IpAddr),
+    User(Uuid),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RateLimitKey {
+    scope: &'static str,
+    subject: RateLimitSubject,
+}
+
+impl RateLimitKey {
+    pub fn new(scope: &'static str, subject: RateLimitSubject) -> Self {
+        Self { scope, subject }
+    }
+}
+
+/// Tokens taken out of the buckets, fewer than asked for when they ran low.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitGrant {
+    pub granted: u32,
+    /// Until the next token, when fewer were granted than asked for
+    pub retry_after_secs: u64,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+#[derive(Debug)]
+struct Buckets {
+    by_key: HashMap<RateLimitKey, Bucket>,
+    pruned_at: Instant,
+}
+
+/// Token bucket limiter: each key holds up to `capacity` tokens that refill
+/// continuously at `refill_per_sec`, and every request spends one token.
+#[derive(Debug)]
+pub struct TokenBucketLimiter {
+    capacity: f64,
+    refill_per_sec: f64,
+    buckets: Mutex<Buckets>,
+}
+
+impl TokenBucketLimiter {
+    pub fn new(capacity: u32, refill_per_minute: u32) -> Self {
+        Self {
+            capacity: f64::from(capacity.max(1)),
+            refill_per_sec: f64::from(refill_per_minute.max(1)) / 60.0,
+            buckets: Mutex::new(Buckets {
+                by_key: HashMap::new(),
+                pruned_at: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn check(&self, key: RateLimitKey) -> RateLimitDecision {
+        self.check_at(key, Instant::now())
+    }
+
+    fn check_at(&self, key: RateLimitKey, now: Instant) -> RateLimitDecision {
+        let grant = self.take_at(key, 1, now);
+        RateLimitDecision {
+            allowed: grant.granted == 1,
+            retry_after_secs: grant.retry_after_secs,
+        }
+    }
+
+    pub fn take(&self, key: RateLimitKey, wanted: u32) -> RateLimitGrant {
+        self.take_at(key, wanted, Instant::now())
+    }
+
+    /// Spends up to `wanted` tokens, as many as the bucket holds.
+    fn take_at(&self, key: RateLimitKey, wanted: u32, now: Instant) -> RateLimitGrant {
+        let mut buckets = self.buckets.lock().expect("rate limiter mutex poisoned");
+
+        // Buckets that have refilled completely carry no state worth keeping.
+        if now.saturating_duration_since(buckets.pruned_at) >= PRUNE_INTERVAL {
+            buckets
+                .by_key
+                .retain(|_, bucket| self.refilled(bucket, now) < self.capacity);
+            buckets.pruned_at = now;
+        }
+
+        let bucket = buckets.by_key.entry(key).or_insert(Bucket {
+            tokens: self.capacity,
+            updated_at: now,
+        });
+        bucket.tokens = self.refilled(bucket, now);
+        bucket.updated_at = now;
+
+        let granted = (bucket.tokens.floor() as u32).min(wanted);
+        bucket.tokens -= f64::from(granted);
+        let retry_after_secs = if granted < wanted {
+            (((1.0 - bucket.tokens) / self.refill_per_sec).ceil() as u64).max(1)
+        } else {
+            0
+        };
+        RateLimitGrant {
+            granted,
+            retry_after_secs,
+        }
+    }
+
+    /// Returns tokens taken but not used, up to the capacity.
+    pub fn give_back(&self, key: RateLimitKey, tokens: u32) {
+        let mut buckets = self.buckets.lock().expect("rate limiter mutex poisoned");
+        if let Some(bucket) = buckets.by_key.get_mut(&key) {
+            bucket.tokens = (bucket.tokens + f64::from(tokens)).min(self.capacity);
+        }
+    }
+
+    fn refilled(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now
+            .saturating_duration_since(bucket.updated_at)
+            .as_secs_f64();
+        (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity)
+    }
+}
+
+/// Per-IP and per-user limiters shared by the rate limited routes.
+#[derive(Debug)]
+pub struct RateLimiter {
+    per_ip: TokenBucketLimiter,
+    per_user: TokenBucketLimiter,
+}
+
+impl RateLimiter {
+    pub fn new(params: &RateLimitParams) -> Self {
+        Self {
+            per_ip: TokenBucketLimiter::new(
+                params.rate_limit_ip_burst,
+                params.rate_limit_ip_per_minute,
+            ),
+            per_user: TokenBucketLimiter::new(
+                params.rate_limit_user_burst,
+                params.rate_limit_user_per_minute,
+            ),
+        }
+    }
+
+    pub fn check(
+        &self,
+        scope: &'static str,
+        client_ip: IpAddr,
+        user_id: Option<Uuid>,
+    ) -> RateLimitDecision {
+        let decision = self
+            .per_ip
+            .check(RateLimitKey::new(scope, RateLimitSubject::Ip(client_ip)));
+        if !decision.allowed {
+            return decision;
+        }
+        match user_id {
+            Some(user_id) => self
+                .per_user
+                .check(RateLimitKey::new(scope, RateLimitSubject::User(user_id))),
+            None => decision,
+        }
+    }
+
+    /// Spends up to `wanted` tokens from the per-IP bucket and the per-user
+    /// one, as many as both hold, for requests that cost more than one.
+    pub fn take(
+        &self,
+        scope: &'static str,
+        client_ip: IpAddr,
+        user_id: Uuid,
+        wanted: u32,
+    ) -> RateLimitGrant {
+        let ip_key = RateLimitKey::new(scope, RateLimitSubject::Ip(client_ip));
+        let by_ip = self.per_ip.take(ip_key, wanted);
+        let by_user = self.per_user.take(
+            RateLimitKey::new(scope, RateLimitSubject::User(user_id)),
+            by_ip.granted,
+        );
+        if by_user.granted < by_ip.granted {
+            self.per_ip
+                .give_back(ip_key, by_ip.granted - by_user.granted);
+        }
+        RateLimitGrant {
+            granted: by_user.granted,
+            retry_after_secs: by_ip.retry_after_secs.max(by_user.retry_after_secs),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::{Duration, Instant};
+
+    use super::{RateLimitKey, RateLimitSubject, TokenBucketLimiter, PRUNE_INTERVAL};
+
+    fn ip_key() -> RateLimitKey {
+        RateLimitKey::new(
+            "auth",
+            RateLimitSubject::Ip(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+        )
+    }
+
+    #[test]
+    fn blocks_when_burst_is_spent() {
+        let limiter = TokenBucketLimiter::new(2, 60);
+        let now = Instant::now();
+
+        assert!(limiter.check_at(ip_key(), now).allowed);
+        assert!(limiter.check_at(ip_key(), now).allowed);
+
+        let decision = limiter.check_at(ip_key(), now);
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after_secs, 1);
+    }
+
+    #[test]
+    fn refills_over_time() {
+        let limiter = TokenBucketLimiter::new(1, 6);
+        let now = Instant::now();
+
+        assert!(limiter.check_at(ip_key(), now).allowed);
+        let decision = limiter.check_at(ip_key(), now);
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after_secs, 10);
+
+        assert!(
+            !limiter
+                .check_at(ip_key(), now + Duration::from_secs(5))
+                .allowed
+        );
+        assert!(
+            limiter
+                .check_at(ip_key(), now + Duration::from_secs(15))
+                .allowed
+        );
+    }
+
+    #[test]
+    fn takes_what_is_left() {
+        let limiter = TokenBucketLimiter::new(5, 60);
+        let now = Instant::now();
+
+        let grant = limiter.take_at(ip_key(), 3, now);
+        assert_eq!((grant.granted, grant.retry_after_secs), (3, 0));
+        let grant = limiter.take_at(ip_key(), 4, now);
+        assert_eq!((grant.granted, grant.retry_after_secs), (2, 1));
+        assert!(!limiter.check_at(ip_key(), now).allowed);
+
+        limiter.give_back(ip_key(), 1);
+        assert!(limiter.check_at(ip_key(), now).allowed);
+    }
+
+    #[test]
+    fn keys_are_independent() {
+        let limiter = TokenBucketLimiter::new(1, 1);
+        let now = Instant::now();
+        let other = RateLimitKey::new(
+            "bookmarks",
+            RateLimitSubject::Ip(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+        );
+
+        assert!(limiter.check_at(ip_key(), now).allowed);
+        assert!(!limiter.check_at(ip_key(), now).allowed);
+        assert!(limiter.check_at(other, now).allowed);
+    }
+
+    #[test]
+    fn refilled_buckets_are_pruned_periodically() {
+        let limiter = TokenBucketLimiter::new(1, 60);
+        let now = Instant::now();
+        let bucket_count = || limiter.buckets.lock().unwrap().by_key.len();
+
+        assert!(limiter.check_at(ip_key(), now).allowed);
+        let later = now + Duration::from_secs(5);
+        for octet in 1..=3 {
+            let key = RateLimitKey::new(
+                "auth",
+                RateLimitSubject::Ip(IpAddr::V4(Ipv4Addr::new(10, 0, 0, octet))),
+            );
+            assert!(limiter.check_at(key, later).allowed);
+        }
+        // Refilled but not pruned until the interval passes
+        assert_eq!(bucket_count(), 4);
+
+        assert!(limiter.check_at(ip_key(), now + PRUNE_INTERVAL).allowed);
+        assert_eq!(bucket_count(), 1);
+    }
+}
@@ -1,7 +1,12 @@
 use ammonia::Builder;
 use anyhow::Result;
+use dom_query::{Document, Matcher};
 use dom_smoothie::{Article, Config, Readability};
 use serde::{Deserialize, Serialize};
+use shared::ExtractionRule;
+use tracing::{debug, instrument};
+
+use crate::bookmark_identity::covers_domain;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ReadabilityResponse {
@@ -11,9 +16,17 @@ pub struct ReadabilityResponse {
     pub text_content: String,
 }
 
-pub async fn process(raw_content: String) -> Result<ReadabilityResponse> {
-    let mut readability = Readability::new(
-        raw_content,
+/// Extracts the article of a page, with the selectors of `rule` applied to
+/// the page first.
+#[instrument(name = "readability", skip_all)]
+pub async fn process(
+    raw_content: String,
+    rule: Option<&ExtractionRule>,
+) -> Result<ReadabilityResponse> {
+    let document = Document::from(raw_content);
+    let rule_title = rule.and_then(|rule| apply_rule(&document, rule));
+    let mut readability = Readability::with_document(
+        document,
         None,
         Some(Config {
             max_elements_to_parse: usize::MAX,
@@ -24,12 +37,119 @@ pub async fn process(raw_content: String) -> Result<ReadabilityResponse> {
     let sanitized_content = sanitize_html(article.content.as_ref());
 
     Ok(ReadabilityResponse {
-        title: article.title,
+        title: rule_title.unwrap_or(article.title),
         content: sanitized_content,
         text_content: article.text_content.to_string(),
     })
 }
 
+/// Whether `selector` is a CSS selector the extraction rules can apply.
+pub fn is_valid_selector(selector: &str) -> bool {
+    Matcher::new(selector).is_ok()
+}
+
+/// The rule of the most specific domain covering `domain`, a rule for a
+/// domain also covering its subdomains.
+pub fn rule_for<'a>(rules: &'a [ExtractionRule], domain: &str) -> Option<&'a ExtractionRule> {
+    rules
+        .iter()
+        .filter(|rule| covers_domain(&rule.domain, domain))
+        .max_by_key(|rule| rule.domain.len())
+}
+
+/// Reads the title the rule points to, then removes the dropped elements and
+/// narrows the body to the kept ones when any matches.
+fn apply_rule(document: &Document, rule: &ExtractionRule) -> Option<String> {
+    let title = rule
+        .title_selector
+        .as_deref()
+        .and_then(|selector| document.try_select(selector))
+        .map(|selection| selection.first().text().trim().to_string())
+        .filter(|title| !title.is_empty());
+    for selector in &rule.drop_selectors {
+        if let Some(selection) = document.try_select(selector) {
+            selection.remove();
+        }
+    }
+    let kept: String = rule
+        .keep_selectors
+        .iter()
+        .filter_map(|selector| document.try_select(selector))
+        .flat_map(|selection| {
+            selection
+                .nodes()
+                .iter()
+                .map(|node| node.html().to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    if !kept.is_empty() {
+        document.select("body").set_html(kept);
+    }
+    debug!(domain = %rule.domain, has_title = title.is_some(), "Extraction rule applied");
+    title
+}
+
 fn sanitize_html(content: &str) -> String {
     Builder::default().clean(content).to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn rule(domain: &str) -> ExtractionRule {
+        ExtractionRule {
+            rule_id: Uuid::new_v4(),
+            domain: domain.to_string(),
+            keep_selectors: Vec::new(),
+            drop_selectors: Vec::new(),
+            title_selector: None,
+            created_at: Utc::now(),
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn the_most_specific_domain_wins() {
+        let rules = vec![rule("example.com"), rule("docs.example.com")];
+        let domain_of = |domain| rule_for(&rules, domain).map(|rule| rule.domain.as_str());
+        assert_eq!(domain_of("docs.example.com"), Some("docs.example.com"));
+        assert_eq!(domain_of("News.Example.com"), Some("example.com"));
+        assert_eq!(domain_of("example.com"), Some("example.com"));
+        assert_eq!(domain_of("notexample.com"), None);
+    }
+
+    #[test]
+    fn selectors_shape_the_page_before_extraction() {
+        let document = Document::from(
+            "<html><head><title>Site</title></head><body>
+                <nav>Menu</nav>
+                <h1 class=\"headline\"> Real title </h1>
+                <article><p>Kept text</p><div class=\"ad\">Buy now</div></article>
+                <footer>Footer</footer>
+            </body></html>",
+        );
+        let rule = ExtractionRule {
+            keep_selectors: vec!["article".to_string()],
+            drop_selectors: vec![".ad".to_string()],
+            title_selector: Some("h1.headline".to_string()),
+            ..rule("example.com")
+        };
+        assert_eq!(apply_rule(&document, &rule).as_deref(), Some("Real title"));
+        let body = document.select("body").text();
+        assert!(body.contains("Kept text"));
+        assert!(!body.contains("Buy now"));
+        assert!(!body.contains("Menu"));
+        assert!(!body.contains("Footer"));
+    }
+
+    #[test]
+    fn selectors_are_validated() {
+        assert!(is_valid_selector("article .content > p"));
+        assert!(!is_valid_selector("div[["));
+    }
+}
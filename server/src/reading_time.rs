@@ -0,0 +1,34 @@
+//! Word counts and reading time estimates of saved articles.
+
+/// Average silent reading speed of adults for non-fiction, in words per
+/// minute.
+const WORDS_PER_MINUTE: i32 = 238;
+
+pub fn word_count(text: &str) -> i32 {
+    i32::try_from(text.split_whitespace().count()).unwrap_or(i32::MAX)
+}
+
+/// Whole minutes, rounded up so any text takes at least a minute.
+pub fn minutes(word_count: i32) -> i32 {
+    (word_count.max(0) + WORDS_PER_MINUTE - 1) / WORDS_PER_MINUTE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{minutes, word_count};
+
+    #[test]
+    fn words_are_split_on_any_whitespace() {
+        assert_eq!(word_count(""), 0);
+        assert_eq!(word_count("  one\ttwo\n\nthree  "), 3);
+    }
+
+    #[test]
+    fn reading_time_rounds_up() {
+        assert_eq!(minutes(0), 0);
+        assert_eq!(minutes(1), 1);
+        assert_eq!(minutes(238), 1);
+        assert_eq!(minutes(239), 2);
+        assert_eq!(minutes(4_760), 20);
+    }
+}
@@ -0,0 +1,54 @@
+//! `reindex` command, rebuilding what search and RAG derive from the stored
+//! bookmarks to recover after a schema or model change.
+
+use anyhow::{bail, Result};
+use tracing::info;
+
+use crate::db::{self, reindex, user, Database};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    pub users: usize,
+    pub bookmarks: u64,
+    pub queued_embeddings: u64,
+    pub index_rebuilt: bool,
+}
+
+/// Reindexes the user named `username`, or every user, logging the progress
+/// after each one. Missing embeddings are only queued, the embedding daemon
+/// of the running server computes them.
+pub async fn reindex(pool: &Database, username: Option<&str>) -> Result<Summary> {
+    let user_ids = match username {
+        Some(username) => match user::get_by_username(pool, username.to_string()).await? {
+            Some(user) => vec![user.user_id],
+            None => bail!("No user named {username}"),
+        },
+        None => db::backup::user_ids(pool).await?,
+    };
+
+    let mut summary = Summary::default();
+    for user_id in &user_ids {
+        let bookmarks = reindex::rebuild_search_tokens(pool, *user_id).await?;
+        let queued_embeddings = reindex::queue_missing_embeddings(pool, *user_id).await?;
+        summary.users += 1;
+        summary.bookmarks += bookmarks;
+        summary.queued_embeddings += queued_embeddings;
+        info!(
+            user_id = %user_id,
+            bookmarks,
+            queued_embeddings,
+            progress = format!("{}/{}", summary.users, user_ids.len()),
+            "User reindexed"
+        );
+    }
+    summary.index_rebuilt = db::rebuild_embedding_index(pool).await?;
+
+    info!(
+        users = summary.users,
+        bookmarks = summary.bookmarks,
+        queued_embeddings = summary.queued_embeddings,
+        index_rebuilt = summary.index_rebuilt,
+        "Reindex done"
+    );
+    Ok(summary)
+}
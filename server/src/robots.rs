@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use lol_html::{element, rewrite_str, RewriteStrSettings};
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::domain_limit::DomainLimiter;
+use crate::fetch_client::FetchClient;
+
+/// Product token matched against `User-agent` lines.
+pub const ROBOTS_USER_AGENT: &str = "bookmark-hub";
+
+const ROBOTS_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Sites whose robots.txt couldn't be read are asked again sooner.
+const ROBOTS_ERROR_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Robots.txt files past this size are truncated, like crawlers do.
+const MAX_ROBOTS_SIZE: usize = 500 * 1024;
+
+/// The site owner opted out, retrying won't change the outcome.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum RobotsDisallowed {
+    #[error("Disallowed by robots.txt")]
+    RobotsTxt,
+    #[error("Page is marked noindex")]
+    NoIndex,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+/// Rules of the robots.txt group that applies to us.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RobotsRules {
+    rules: Vec<Rule>,
+}
+
+impl RobotsRules {
+    /// Parses `content` keeping the group for [`ROBOTS_USER_AGENT`], or the
+    /// `*` group when none names us.
+    pub fn parse(content: &str) -> Self {
+        let mut own: Option<Vec<Rule>> = None;
+        let mut wildcard: Option<Vec<Rule>> = None;
+
+        let mut agents: Vec<String> = Vec::new();
+        let mut rules: Vec<Rule> = Vec::new();
+        let mut in_rules = false;
+        let mut flush = |agents: &mut Vec<String>, rules: &mut Vec<Rule>| {
+            for agent in agents.iter() {
+                let target = if agent == "*" {
+                    &mut wildcard
+                } else if agent.eq_ignore_ascii_case(ROBOTS_USER_AGENT) {
+                    &mut own
+                } else {
+                    continue;
+                };
+                target.get_or_insert_with(Vec::new).extend(rules.clone());
+            }
+            agents.clear();
+            rules.clear();
+        };
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_lowercase().as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        flush(&mut agents, &mut rules);
+                        in_rules = false;
+                    }
+                    agents.push(value.to_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // An empty Disallow allows everything
+                    if !value.is_empty() {
+                        rules.push(Rule {
+                            allow: key.trim().eq_ignore_ascii_case("allow"),
+                            pattern: value.to_string(),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        flush(&mut agents, &mut rules);
+
+        Self {
+            rules: own.or(wildcard).unwrap_or_default(),
+        }
+    }
+
+    /// The most specific matching rule wins, `Allow` on ties.
+    pub fn is_allowed(&self, url: &Url) -> bool {
+        let path = match url.query() {
+            Some(query) => format!("{}?{query}", url.path()),
+            None => url.path().to_string(),
+        };
+        self.rules
+            .iter()
+            .filter(|rule| pattern_matches(&rule.pattern, &path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+}
+
+/// Robots.txt path matching: a prefix match where `*` matches any sequence
+/// and a trailing `$` anchors the end of the path.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (index, part) in parts.iter().enumerate() {
+        let is_last = index == parts.len() - 1;
+        if is_last && anchored {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(position) => rest = &rest[position + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || parts.is_empty() && rest.is_empty()
+}
+
+/// Whether the page asks robots not to index it through
+/// `<meta name="robots">` or a meta tag naming us.
+pub fn is_noindex(html: &str) -> bool {
+    let mut noindex = false;
+    let _ = rewrite_str(
+        html,
+        RewriteStrSettings::new().append_element_content_handler(element!(
+            "meta[name][content]",
+            |el| {
+                let name = el.get_attribute("name").unwrap_or_default().to_lowercase();
+                if name == "robots" || name == ROBOTS_USER_AGENT {
+                    let content = el.get_attribute("content").unwrap_or_default();
+                    noindex |= content
+                        .split(',')
+                        .map(|directive| directive.trim().to_lowercase())
+                        .any(|directive| directive == "noindex" || directive == "none");
+                }
+                Ok(())
+            }
+        )),
+    );
+    noindex
+}
+
+/// Robots.txt rules per origin, fetched on first use.
+pub struct RobotsCache {
+    http: FetchClient,
+    entries: Mutex<HashMap<String, (Instant, Arc<RobotsRules>)>>,
+}
+
+impl RobotsCache {
+    pub fn new(http: FetchClient) -> Self {
+        Self {
+            http,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn is_allowed(&self, limiter: &DomainLimiter, url: &Url) -> bool {
+        let origin = url.origin().ascii_serialization();
+        let cached = {
+            let mut entries = self.entries.lock().expect("robots cache mutex poisoned");
+            entries.retain(|_, (expires_at, _)| *expires_at > Instant::now());
+            entries.get(&origin).map(|(_, rules)| Arc::clone(rules))
+        };
+        let rules = match cached {
+            Some(rules) => rules,
+            None => {
+                let (rules, ttl) = self.fetch(limiter, url).await;
+                let rules = Arc::new(rules);
+                self.entries
+                    .lock()
+                    .expect("robots cache mutex poisoned")
+                    .insert(origin, (Instant::now() + ttl, Arc::clone(&rules)));
+                rules
+            }
+        };
+        rules.is_allowed(url)
+    }
+
+    async fn fetch(&self, limiter: &DomainLimiter, url: &Url) -> (RobotsRules, Duration) {
+        let Ok(robots_url) = url.join("/robots.txt") else {
+            return (RobotsRules::default(), ROBOTS_CACHE_TTL);
+        };
+        debug!(url = %robots_url, "Fetching robots.txt");
+        let _permit = limiter.acquire(&robots_url).await;
+        let Ok(request) = self.http.get(&robots_url) else {
+            return (RobotsRules::default(), ROBOTS_CACHE_TTL);
+        };
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(error) => {
+                warn!(url = %robots_url, ?error, "Failed to fetch robots.txt, allowing");
+                return (RobotsRules::default(), ROBOTS_ERROR_CACHE_TTL);
+            }
+        };
+        let status = response.status();
+        if !status.is_success() {
+            // A missing robots.txt allows everything
+            let ttl = if status.is_client_error() {
+                ROBOTS_CACHE_TTL
+            } else {
+                ROBOTS_ERROR_CACHE_TTL
+            };
+            debug!(url = %robots_url, %status, "No robots.txt rules");
+            return (RobotsRules::default(), ttl);
+        }
+        match response.text().await {
+            Ok(content) => {
+                let content = match content.char_indices().nth(MAX_ROBOTS_SIZE) {
+                    Some((end, _)) => &content[..end],
+                    None => &content,
+                };
+                let rules = RobotsRules::parse(content);
+                info!(url = %robots_url, rule_count = rules.rules.len(), "robots.txt loaded");
+                (rules, ROBOTS_CACHE_TTL)
+            }
+            Err(error) => {
+                warn!(url = %robots_url, ?error, "Failed to read robots.txt, allowing");
+                (RobotsRules::default(), ROBOTS_ERROR_CACHE_TTL)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use super::{is_noindex, pattern_matches, RobotsRules};
+
+    fn allowed(rules: &RobotsRules, url: &str) -> bool {
+        rules.is_allowed(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn wildcard_group_applies_without_own_group() {
+        let rules = RobotsRules::parse(
+            "User-agent: *\nDisallow: /private/\nAllow: /private/public\n\n\
+             User-agent: OtherBot\nDisallow: /",
+        );
+        assert!(allowed(&rules, "https://example.com/"));
+        assert!(!allowed(&rules, "https://example.com/private/page"));
+        assert!(allowed(&rules, "https://example.com/private/public/page"));
+    }
+
+    #[test]
+    fn own_group_takes_precedence() {
+        let rules = RobotsRules::parse(
+            "User-agent: *\nDisallow: /\n\nUser-agent: bookmark-hub\nDisallow: /drafts",
+        );
+        assert!(allowed(&rules, "https://example.com/posts/1"));
+        assert!(!allowed(&rules, "https://example.com/drafts/1"));
+    }
+
+    #[test]
+    fn own_group_matches_whole_token_ignoring_case() {
+        let rules = RobotsRules::parse(
+            "User-agent: book\nDisallow: /\n\nUser-agent: Bookmark-Hub\nDisallow: /drafts",
+        );
+        assert!(allowed(&rules, "https://example.com/posts/1"));
+        assert!(!allowed(&rules, "https://example.com/drafts/1"));
+    }
+
+    #[test]
+    fn empty_disallow_allows_everything() {
+        let rules = RobotsRules::parse("User-agent: *\nDisallow:\n");
+        assert!(allowed(&rules, "https://example.com/anything"));
+    }
+
+    #[test]
+    fn patterns_support_wildcards_and_anchors() {
+        assert!(pattern_matches("/search", "/search?q=rust"));
+        assert!(pattern_matches("/*.pdf$", "/files/report.pdf"));
+        assert!(!pattern_matches("/*.pdf$", "/files/report.pdf?download=1"));
+        assert!(pattern_matches("/*/edit", "/posts/1/edit"));
+        assert!(pattern_matches("/about$", "/about"));
+        assert!(!pattern_matches("/about$", "/about/team"));
+        assert!(!pattern_matches("/admin", "/blog/admin"));
+    }
+
+    #[test]
+    fn detects_meta_noindex() {
+        assert!(is_noindex(
+            r#"<html><head><meta name="robots" content="noindex, nofollow"></head></html>"#
+        ));
+        assert!(is_noindex(
+            r#"<html><head><meta name="ROBOTS" content="none"></head></html>"#
+        ));
+        assert!(!is_noindex(
+            r#"<html><head><meta name="robots" content="index, follow"></head></html>"#
+        ));
+        assert!(!is_noindex(
+            r#"<html><head><meta name="description" content="noindex"></head></html>"#
+        ));
+    }
+}
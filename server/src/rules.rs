@@ -0,0 +1,188 @@
+//! Evaluation of the rules users define for their new bookmarks.
+
+use shared::{Rule, RuleConditions};
+
+/// Actions of every rule matching a bookmark, put together.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// In the order of the rules, each at most once
+    pub tags: Vec<String>,
+    pub favorite: bool,
+    pub skip_ai: bool,
+    pub matched: usize,
+}
+
+/// `*` matches any run of characters, everything else itself ignoring case.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Last `*` seen and where in the text it started matching
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star, start)) = backtrack {
+            // Let the star take one more character
+            p = star + 1;
+            t = start + 1;
+            backtrack = Some((star, start + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+pub fn matches(conditions: &RuleConditions, url: &str, domain: &str, title: &str) -> bool {
+    let url_matches = conditions
+        .url_pattern
+        .as_deref()
+        .is_none_or(|pattern| glob_matches(pattern, url));
+    let domain_matches = conditions.domain.as_deref().is_none_or(|expected| {
+        let domain = domain.to_lowercase();
+        let expected = expected.to_lowercase();
+        domain == expected || domain.ends_with(&format!(".{expected}"))
+    });
+    let title = title.to_lowercase();
+    let title_matches = conditions.title_keywords.is_empty()
+        || conditions
+            .title_keywords
+            .iter()
+            .any(|keyword| title.contains(&keyword.to_lowercase()));
+    url_matches && domain_matches && title_matches
+}
+
+/// Applies the enabled `rules` to a bookmark of `url`, `domain` and `title`.
+pub fn evaluate(rules: &[Rule], url: &str, domain: &str, title: &str) -> Outcome {
+    let mut outcome = Outcome::default();
+    for rule in rules
+        .iter()
+        .filter(|rule| rule.enabled && matches(&rule.conditions, url, domain, title))
+    {
+        for tag in &rule.actions.add_tags {
+            if !outcome.tags.contains(tag) {
+                outcome.tags.push(tag.clone());
+            }
+        }
+        outcome.favorite |= rule.actions.favorite;
+        outcome.skip_ai |= rule.actions.skip_ai;
+        outcome.matched += 1;
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use shared::RuleActions;
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn rule(conditions: RuleConditions, actions: RuleActions) -> Rule {
+        Rule {
+            rule_id: Uuid::new_v4(),
+            name: "rule".to_string(),
+            enabled: true,
+            conditions,
+            actions,
+            created_at: Utc::now(),
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn globs_match_the_whole_text() {
+        assert!(glob_matches(
+            "https://github.com/*/issues/*",
+            "https://GitHub.com/rust-lang/rust/issues/1"
+        ));
+        assert!(glob_matches("*.pdf", "https://example.com/paper.pdf"));
+        assert!(!glob_matches(
+            "*.pdf",
+            "https://example.com/paper.pdf?download"
+        ));
+        assert!(glob_matches("a*b*c", "abbbc"));
+        assert!(!glob_matches("a*b*c", "acb"));
+        assert!(glob_matches("*", ""));
+    }
+
+    #[test]
+    fn every_condition_set_must_match() {
+        let conditions = RuleConditions {
+            url_pattern: None,
+            domain: Some("github.com".to_string()),
+            title_keywords: vec!["Release".to_string(), "changelog".to_string()],
+        };
+        let url = "https://blog.github.com/x";
+        assert!(matches(
+            &conditions,
+            url,
+            "blog.github.com",
+            "Changelog for May"
+        ));
+        assert!(!matches(&conditions, url, "blog.github.com", "Roadmap"));
+        assert!(!matches(&conditions, url, "notgithub.com", "Release notes"));
+    }
+
+    #[test]
+    fn matching_rules_combine_their_actions() {
+        let rules = [
+            rule(
+                RuleConditions {
+                    domain: Some("arxiv.org".to_string()),
+                    ..Default::default()
+                },
+                RuleActions {
+                    add_tags: vec!["paper".to_string(), "research".to_string()],
+                    ..Default::default()
+                },
+            ),
+            rule(
+                RuleConditions {
+                    url_pattern: Some("*/pdf/*".to_string()),
+                    ..Default::default()
+                },
+                RuleActions {
+                    add_tags: vec!["paper".to_string()],
+                    favorite: true,
+                    skip_ai: true,
+                },
+            ),
+            Rule {
+                enabled: false,
+                ..rule(
+                    RuleConditions {
+                        domain: Some("arxiv.org".to_string()),
+                        ..Default::default()
+                    },
+                    RuleActions {
+                        add_tags: vec!["disabled".to_string()],
+                        ..Default::default()
+                    },
+                )
+            },
+        ];
+
+        let outcome = evaluate(&rules, "https://arxiv.org/pdf/1706.03762", "arxiv.org", "");
+
+        assert_eq!(
+            outcome,
+            Outcome {
+                tags: vec!["paper".to_string(), "research".to_string()],
+                favorite: true,
+                skip_ai: true,
+                matched: 2,
+            }
+        );
+        assert_eq!(
+            evaluate(&rules, "https://lwn.net", "lwn.net", ""),
+            Outcome::default()
+        );
+    }
+}
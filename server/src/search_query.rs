@@ -0,0 +1,167 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    /// Case-insensitive substring of the title
+    Title,
+    /// Exact domain
+    Domain,
+    /// One of the bookmark's tags
+    Tag,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldFilter {
+    pub field: Field,
+    pub value: String,
+    pub negated: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ParsedQuery {
+    /// Full-text part, `None` when the query only had field filters
+    pub text: Option<String>,
+    pub filters: Vec<FieldFilter>,
+}
+
+/// Pulls the field prefixes `title:rust`, `domain:github.com` and `tag:async`,
+/// each negated by a leading `-`, out of a search query. Everything else,
+/// plain words, quoted phrases and `-word`, is left for `websearch_to_tsquery`.
+pub fn parse(query: &str) -> ParsedQuery {
+    let mut text: Vec<String> = Vec::new();
+    let mut filters = Vec::new();
+    for token in tokenize(query) {
+        match parse_filter(&token) {
+            Some(filter) => filters.push(filter),
+            None => text.push(token),
+        }
+    }
+    ParsedQuery {
+        text: (!text.is_empty()).then(|| text.join(" ")),
+        filters,
+    }
+}
+
+/// The words a fuzzy fallback should look for in the full-text part of a
+/// query: negated words are dropped and phrases lose their quotes. `None`
+/// when nothing is left.
+pub fn fuzzy_words(text: &str) -> Option<String> {
+    let tokens = tokenize(text);
+    let words: Vec<&str> = tokens
+        .iter()
+        .filter(|token| !token.starts_with('-'))
+        .flat_map(|token| token.split(|c: char| c == '"' || c.is_whitespace()))
+        .filter(|word| !word.is_empty() && !word.eq_ignore_ascii_case("or"))
+        .collect();
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+/// Splits on whitespace outside double quotes, quotes are kept.
+fn tokenize(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for c in query.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+fn parse_filter(token: &str) -> Option<FieldFilter> {
+    let (negated, token) = match token.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, token),
+    };
+    let (prefix, value) = token.split_once(':')?;
+    let field = match prefix.to_lowercase().as_str() {
+        "title" => Field::Title,
+        "domain" => Field::Domain,
+        "tag" => Field::Tag,
+        _ => return None,
+    };
+    let value = value.trim_matches('"').trim();
+    if value.is_empty() {
+        return None;
+    }
+    let value = match field {
+        Field::Title => value.to_string(),
+        Field::Domain | Field::Tag => value.to_lowercase(),
+    };
+    Some(FieldFilter {
+        field,
+        value,
+        negated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(field: Field, value: &str, negated: bool) -> FieldFilter {
+        FieldFilter {
+            field,
+            value: value.to_string(),
+            negated,
+        }
+    }
+
+    #[test]
+    fn plain_queries_are_left_alone() {
+        let parsed = parse(r#"rust "async runtime" -kubernetes"#);
+        assert_eq!(
+            parsed.text.as_deref(),
+            Some(r#"rust "async runtime" -kubernetes"#)
+        );
+        assert!(parsed.filters.is_empty());
+    }
+
+    #[test]
+    fn field_prefixes_become_filters() {
+        let parsed = parse(r#"title:"Rust Book" domain:GitHub.com -tag:Async tokio"#);
+        assert_eq!(parsed.text.as_deref(), Some("tokio"));
+        assert_eq!(
+            parsed.filters,
+            vec![
+                filter(Field::Title, "Rust Book", false),
+                filter(Field::Domain, "github.com", false),
+                filter(Field::Tag, "async", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_filters_leave_no_text() {
+        let parsed = parse("tag:rust");
+        assert_eq!(parsed.text, None);
+        assert_eq!(parsed.filters, vec![filter(Field::Tag, "rust", false)]);
+    }
+
+    #[test]
+    fn unknown_or_empty_prefixes_stay_text() {
+        let parsed = parse("https://lwn.net c++: tag:");
+        assert_eq!(parsed.text.as_deref(), Some("https://lwn.net c++: tag:"));
+        assert!(parsed.filters.is_empty());
+    }
+
+    #[test]
+    fn fuzzy_words_skip_negations_and_operators() {
+        assert_eq!(
+            fuzzy_words(r#"kuberntes "helm chrat" or -docker"#).as_deref(),
+            Some("kuberntes helm chrat")
+        );
+        assert_eq!(fuzzy_words("-docker"), None);
+    }
+}
@@ -0,0 +1,443 @@
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use aws_sdk_s3::operation::get_object::GetObjectError;
+use aws_sdk_s3::operation::head_object::HeadObjectError;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{Delete, ObjectIdentifier};
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use tracing::{debug, info};
+
+use crate::StorageParams;
+
+pub const GZIP_ENCODING: &str = "gzip";
+
+/// Objects S3 deletes in a single request.
+const S3_DELETE_BATCH: usize = 1000;
+
+/// Bytes of an object as they were before being stored, decompressing the
+/// ones stored gzipped.
+pub fn decode(bytes: Vec<u8>, content_encoding: Option<String>) -> Result<Vec<u8>> {
+    if content_encoding.as_deref() != Some(GZIP_ENCODING) {
+        return Ok(bytes);
+    }
+    let mut decoded = Vec::new();
+    GzDecoder::new(bytes.as_slice()).read_to_end(&mut decoded)?;
+    Ok(decoded)
+}
+
+/// A captured page or image, keyed by `{user_id}/{bookmark_id}/{name}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredObject {
+    /// `None` when the backend doesn't keep it, local files have no metadata.
+    pub content_type: Option<String>,
+    /// `Some("gzip")` for content stored compressed.
+    pub content_encoding: Option<String>,
+    pub bytes: Vec<u8>,
+    /// Set by the backend on `get`, ignored by `put`.
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum StorageBackendKind {
+    /// Files under APP_DATA_DIR
+    #[default]
+    Local,
+    /// An S3-compatible bucket
+    S3,
+}
+
+/// Where the static content of bookmarks lives.
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    async fn put(&self, key: &str, object: StoredObject) -> Result<()>;
+
+    async fn get(&self, key: &str) -> Result<Option<StoredObject>>;
+
+    async fn exists(&self, key: &str) -> Result<bool>;
+
+    /// Removes every object under `prefix`, e.g. a bookmark or a user.
+    async fn delete_prefix(&self, prefix: &str) -> Result<()>;
+
+    /// Keys of every object under `prefix`, in no particular order.
+    async fn list(&self, prefix: &str) -> Result<Vec<String>>;
+}
+
+pub async fn build(
+    params: &StorageParams,
+    data_dir: Option<&Path>,
+) -> Result<Arc<dyn StorageBackend>> {
+    match params.storage_backend {
+        StorageBackendKind::Local => {
+            let Some(data_dir) = data_dir else {
+                bail!("APP_DATA_DIR is required with the local storage backend");
+            };
+            info!(data_dir = ?data_dir, "Using local static storage");
+            Ok(Arc::new(LocalStorage::new(data_dir.to_path_buf())?))
+        }
+        StorageBackendKind::S3 => {
+            let Some(bucket) = params.s3_bucket.clone() else {
+                bail!("S3_BUCKET is required with the s3 storage backend");
+            };
+            info!(bucket = %bucket, endpoint = ?params.s3_endpoint, "Using S3 static storage");
+            Ok(Arc::new(S3Storage::new(params, bucket).await))
+        }
+    }
+}
+
+/// Keys come from request paths, anything that could escape the storage
+/// root is rejected.
+pub fn validate_key(key: &str) -> Result<()> {
+    let valid = !key.is_empty()
+        && Path::new(key)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+    if !valid {
+        bail!("Invalid storage key: {key:?}");
+    }
+    Ok(())
+}
+
+/// Files under the data dir, gzip-encoded objects get a `.gz` suffix as
+/// data dirs always had.
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: PathBuf) -> Result<Self> {
+        if !root.is_dir() {
+            bail!("Data dir is not a directory, {root:?}");
+        }
+        if root.metadata()?.permissions().readonly() {
+            bail!("Data dir is readonly, needs write access, {root:?}");
+        }
+        let test_file = root.join("test.txt");
+        std::fs::write(&test_file, "test data")?;
+        std::fs::remove_file(&test_file)?;
+        debug!(data_dir = ?root, "Data directory validation successful");
+        Ok(Self { root })
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        validate_key(key)?;
+        Ok(self.root.join(key))
+    }
+
+    fn gzip_path(path: &Path) -> PathBuf {
+        let mut gzip = path.as_os_str().to_owned();
+        gzip.push(".gz");
+        PathBuf::from(gzip)
+    }
+}
+
+#[async_trait]
+impl StorageBackend for LocalStorage {
+    async fn put(&self, key: &str, object: StoredObject) -> Result<()> {
+        let mut path = self.path(key)?;
+        if object.content_encoding.as_deref() == Some(GZIP_ENCODING) {
+            path = Self::gzip_path(&path);
+        }
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, object.bytes)
+            .await
+            .with_context(|| format!("Failed to write {path:?}"))
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<StoredObject>> {
+        let path = self.path(key)?;
+        let gzip_path = Self::gzip_path(&path);
+        let (path, content_encoding) = if tokio::fs::try_exists(&gzip_path).await? {
+            (gzip_path, Some(GZIP_ENCODING.to_string()))
+        } else {
+            (path, None)
+        };
+        let bytes = match tokio::fs::read(&path).await {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error).with_context(|| format!("Failed to read {path:?}")),
+        };
+        let last_modified = tokio::fs::metadata(&path)
+            .await
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .map(DateTime::<Utc>::from);
+        Ok(Some(StoredObject {
+            content_type: None,
+            content_encoding,
+            bytes,
+            last_modified,
+        }))
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        let path = self.path(key)?;
+        Ok(tokio::fs::try_exists(&path).await?
+            || tokio::fs::try_exists(Self::gzip_path(&path)).await?)
+    }
+
+    async fn delete_prefix(&self, prefix: &str) -> Result<()> {
+        let path = self.path(prefix.trim_end_matches('/'))?;
+        match tokio::fs::remove_dir_all(&path).await {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+                Err(error).with_context(|| format!("Failed to remove {path:?}"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut keys = Vec::new();
+        let mut dirs = vec![self.path(prefix.trim_end_matches('/'))?];
+        while let Some(dir) = dirs.pop() {
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(error) => return Err(error).with_context(|| format!("Failed to list {dir:?}")),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if entry.file_type().await?.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                let Some(key) = path
+                    .strip_prefix(&self.root)
+                    .ok()
+                    .and_then(|relative| relative.to_str())
+                else {
+                    continue;
+                };
+                // Compressed objects are stored with a `.gz` suffix
+                keys.push(key.strip_suffix(".gz").unwrap_or(key).to_string());
+            }
+        }
+        Ok(keys)
+    }
+}
+
+/// Objects in an S3-compatible bucket (AWS, MinIO, R2...), credentials come
+/// from the usual AWS environment and profile chain.
+pub struct S3Storage {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+    prefix: String,
+}
+
+impl S3Storage {
+    pub async fn new(params: &StorageParams, bucket: String) -> Self {
+        let mut loader = aws_config::defaults(aws_config::BehaviorVersion::latest())
+            .region(aws_config::Region::new(params.s3_region.clone()));
+        if let Some(endpoint) = &params.s3_endpoint {
+            loader = loader.endpoint_url(endpoint);
+        }
+        let shared_config = loader.load().await;
+        let config = aws_sdk_s3::config::Builder::from(&shared_config)
+            .force_path_style(params.s3_force_path_style)
+            .build();
+        let prefix = params
+            .s3_prefix
+            .as_deref()
+            .map(|prefix| format!("{}/", prefix.trim_matches('/')))
+            .unwrap_or_default();
+        Self {
+            client: aws_sdk_s3::Client::from_conf(config),
+            bucket,
+            prefix,
+        }
+    }
+
+    fn object_key(&self, key: &str) -> Result<String> {
+        validate_key(key)?;
+        Ok(format!("{}{key}", self.prefix))
+    }
+
+    /// Full object keys, `S3_PREFIX` included.
+    async fn list_object_keys(&self, object_prefix: &str) -> Result<Vec<String>> {
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(object_prefix)
+            .into_paginator()
+            .send();
+        let mut keys = Vec::new();
+        while let Some(page) = pages.next().await {
+            keys.extend(
+                page.with_context(|| {
+                    format!("Failed to list s3://{}/{object_prefix}", self.bucket)
+                })?
+                .contents
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|object| object.key),
+            );
+        }
+        Ok(keys)
+    }
+}
+
+#[async_trait]
+impl StorageBackend for S3Storage {
+    async fn put(&self, key: &str, object: StoredObject) -> Result<()> {
+        let object_key = self.object_key(key)?;
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(&object_key)
+            .set_content_type(object.content_type)
+            .set_content_encoding(object.content_encoding)
+            .body(ByteStream::from(object.bytes))
+            .send()
+            .await
+            .with_context(|| format!("Failed to put s3://{}/{object_key}", self.bucket))?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<StoredObject>> {
+        let object_key = self.object_key(key)?;
+        let output = match self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(&object_key)
+            .send()
+            .await
+        {
+            Ok(output) => output,
+            Err(error) => {
+                return match error.into_service_error() {
+                    GetObjectError::NoSuchKey(_) => Ok(None),
+                    error => Err(error).with_context(|| {
+                        format!("Failed to get s3://{}/{object_key}", self.bucket)
+                    }),
+                };
+            }
+        };
+        let last_modified = output
+            .last_modified
+            .and_then(|date| DateTime::from_timestamp(date.secs(), date.subsec_nanos()));
+        let bytes = output.body.collect().await?.into_bytes().to_vec();
+        Ok(Some(StoredObject {
+            content_type: output.content_type,
+            content_encoding: output.content_encoding,
+            bytes,
+            last_modified,
+        }))
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        let object_key = self.object_key(key)?;
+        match self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(&object_key)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(error) => match error.into_service_error() {
+                HeadObjectError::NotFound(_) => Ok(false),
+                error => Err(error)
+                    .with_context(|| format!("Failed to head s3://{}/{object_key}", self.bucket)),
+            },
+        }
+    }
+
+    async fn delete_prefix(&self, prefix: &str) -> Result<()> {
+        let object_prefix = format!("{}/", self.object_key(prefix.trim_end_matches('/'))?);
+        let keys = self.list_object_keys(&object_prefix).await?;
+        for batch in keys.chunks(S3_DELETE_BATCH) {
+            let objects = batch
+                .iter()
+                .map(|key| ObjectIdentifier::builder().key(key).build())
+                .collect::<Result<Vec<_>, _>>()?;
+            self.client
+                .delete_objects()
+                .bucket(&self.bucket)
+                .delete(Delete::builder().set_objects(Some(objects)).build()?)
+                .send()
+                .await
+                .with_context(|| {
+                    format!("Failed to delete s3://{}/{object_prefix}", self.bucket)
+                })?;
+        }
+        debug!(prefix = %object_prefix, object_count = keys.len(), "Deleted S3 objects");
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let object_prefix = format!("{}/", self.object_key(prefix.trim_end_matches('/'))?);
+        Ok(self
+            .list_object_keys(&object_prefix)
+            .await?
+            .into_iter()
+            .filter_map(|key| key.strip_prefix(&self.prefix).map(str::to_string))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_key, LocalStorage, StorageBackend, StoredObject, GZIP_ENCODING};
+
+    #[test]
+    fn keys_cannot_escape_the_root() {
+        assert!(validate_key("user/bookmark/index.html").is_ok());
+        assert!(validate_key("").is_err());
+        assert!(validate_key("../etc/passwd").is_err());
+        assert!(validate_key("user/../../etc/passwd").is_err());
+        assert!(validate_key("/etc/passwd").is_err());
+    }
+
+    #[tokio::test]
+    async fn local_storage_round_trips_objects() {
+        let root = std::env::temp_dir().join(format!("static-storage-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let storage = LocalStorage::new(root.clone()).unwrap();
+
+        let page = StoredObject {
+            content_type: None,
+            content_encoding: Some(GZIP_ENCODING.to_string()),
+            bytes: b"compressed".to_vec(),
+            last_modified: None,
+        };
+        storage.put("u/b/index.html", page.clone()).await.unwrap();
+        assert!(root.join("u/b/index.html.gz").exists());
+        let stored = storage.get("u/b/index.html").await.unwrap().unwrap();
+        assert!(stored.last_modified.is_some());
+        assert_eq!(
+            StoredObject {
+                last_modified: None,
+                ..stored
+            },
+            page
+        );
+        assert!(storage.exists("u/b/index.html").await.unwrap());
+        assert_eq!(storage.get("u/b/missing").await.unwrap(), None);
+
+        let image = StoredObject {
+            content_encoding: None,
+            ..page.clone()
+        };
+        storage.put("u/b/images/a.webp", image).await.unwrap();
+        let mut keys = storage.list("u").await.unwrap();
+        keys.sort();
+        assert_eq!(keys, ["u/b/images/a.webp", "u/b/index.html"]);
+        assert!(storage.list("other").await.unwrap().is_empty());
+
+        storage.delete_prefix("u/b/").await.unwrap();
+        assert!(!storage.exists("u/b/index.html").await.unwrap());
+        assert!(storage.list("u").await.unwrap().is_empty());
+        storage.delete_prefix("u/b/").await.unwrap();
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,214 @@
+//! Ranking of the tags suggested for a bookmark while tagging it by hand.
+
+use std::collections::{BTreeMap, HashSet};
+
+use shared::{RelatedBookmark, TagSuggestion, TagSuggestionSource};
+
+/// Share of the analyzed chunks proposing a tag.
+const LLM_WEIGHT: f64 = 1.0;
+/// Share of the similarity of the related bookmarks carrying a tag.
+const SIMILAR_WEIGHT: f64 = 1.0;
+/// Tags of the vocabulary whose words all appear in the title.
+const TITLE_WEIGHT: f64 = 0.5;
+/// Tags already in use are preferred, so the vocabulary doesn't sprawl into
+/// near duplicates.
+const VOCABULARY_BOOST: f64 = 1.5;
+
+#[derive(Default)]
+struct Candidate {
+    score: f64,
+    sources: Vec<TagSuggestionSource>,
+}
+
+impl Candidate {
+    fn add(&mut self, score: f64, source: TagSuggestionSource) {
+        self.score += score;
+        if !self.sources.contains(&source) {
+            self.sources.push(source);
+        }
+    }
+}
+
+fn normalize(tag: &str) -> Option<String> {
+    let tag = tag.trim().to_lowercase();
+    (!tag.is_empty()).then_some(tag)
+}
+
+/// Tags for a bookmark titled `title` and tagged `current`, combining the
+/// tags the LLM proposed for each chunk of its article, the tags of
+/// `related` bookmarks and the user's `vocabulary`. The best `limit` are
+/// returned, leaving out the ones the bookmark already has.
+pub fn rank(
+    title: &str,
+    current: &[String],
+    vocabulary: &[String],
+    proposed: &[Vec<String>],
+    related: &[RelatedBookmark],
+    limit: usize,
+) -> Vec<TagSuggestion> {
+    let mut candidates: BTreeMap<String, Candidate> = BTreeMap::new();
+
+    for chunk_tags in proposed {
+        let chunk_tags: HashSet<String> = chunk_tags.iter().filter_map(|t| normalize(t)).collect();
+        for tag in chunk_tags {
+            candidates
+                .entry(tag)
+                .or_default()
+                .add(LLM_WEIGHT / proposed.len() as f64, TagSuggestionSource::Llm);
+        }
+    }
+
+    let total_similarity: f64 = related
+        .iter()
+        .map(|related| related.similarity_score.max(0.0))
+        .sum();
+    if total_similarity > 0.0 {
+        for related in related {
+            let tags: HashSet<String> = related
+                .bookmark
+                .tags
+                .iter()
+                .flatten()
+                .filter_map(|t| normalize(t))
+                .collect();
+            for tag in tags {
+                candidates.entry(tag).or_default().add(
+                    SIMILAR_WEIGHT * related.similarity_score.max(0.0) / total_similarity,
+                    TagSuggestionSource::Similar,
+                );
+            }
+        }
+    }
+
+    let title_words: HashSet<String> = title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let vocabulary: HashSet<String> = vocabulary.iter().filter_map(|t| normalize(t)).collect();
+    for tag in &vocabulary {
+        let mut words = tag
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .peekable();
+        if words.peek().is_some() && words.all(|word| title_words.contains(word)) {
+            candidates
+                .entry(tag.clone())
+                .or_default()
+                .add(TITLE_WEIGHT, TagSuggestionSource::Vocabulary);
+        }
+    }
+    for (tag, candidate) in candidates.iter_mut() {
+        if vocabulary.contains(tag) {
+            candidate.score *= VOCABULARY_BOOST;
+            if !candidate.sources.contains(&TagSuggestionSource::Vocabulary) {
+                candidate.sources.push(TagSuggestionSource::Vocabulary);
+            }
+        }
+    }
+
+    let current: HashSet<String> = current.iter().filter_map(|t| normalize(t)).collect();
+    let mut suggestions: Vec<TagSuggestion> = candidates
+        .into_iter()
+        .filter(|(tag, _)| !current.contains(tag))
+        .map(|(tag, candidate)| TagSuggestion {
+            tag,
+            score: candidate.score,
+            sources: candidate.sources,
+        })
+        .collect();
+    // Stable, so equal scores stay in alphabetical order
+    suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+    suggestions.truncate(limit);
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use shared::{Bookmark, Visibility};
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn tags(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn related(values: &[&str], similarity_score: f64) -> RelatedBookmark {
+        RelatedBookmark {
+            bookmark: Bookmark {
+                bookmark_id: Uuid::new_v4().to_string(),
+                user_id: Uuid::new_v4(),
+                url: "https://example.com".to_string(),
+                domain: "example.com".to_string(),
+                title: "Related".to_string(),
+                tags: Some(tags(values)),
+                summary: None,
+                created_at: Utc::now(),
+                updated_at: None,
+                word_count: 0,
+                reading_time_minutes: 0,
+                kind: None,
+                favorite: false,
+                original_title: None,
+                visibility: Visibility::Private,
+            },
+            similarity_score,
+        }
+    }
+
+    #[test]
+    fn sources_add_up_and_vocabulary_is_preferred() {
+        let suggestions = rank(
+            "Scaling Kubernetes clusters",
+            &tags(&["devops"]),
+            &tags(&["kubernetes", "devops", "Go"]),
+            &[
+                tags(&["Kubernetes", "k8s"]),
+                tags(&["kubernetes", "scaling"]),
+            ],
+            &[
+                related(&["kubernetes", "go"], 0.9),
+                related(&["devops"], 0.1),
+            ],
+            10,
+        );
+
+        let ranked: Vec<(&str, &[TagSuggestionSource])> = suggestions
+            .iter()
+            .map(|s| (s.tag.as_str(), s.sources.as_slice()))
+            .collect();
+        assert_eq!(
+            ranked,
+            vec![
+                (
+                    "kubernetes",
+                    &[
+                        TagSuggestionSource::Llm,
+                        TagSuggestionSource::Similar,
+                        TagSuggestionSource::Vocabulary
+                    ][..]
+                ),
+                (
+                    "go",
+                    &[
+                        TagSuggestionSource::Similar,
+                        TagSuggestionSource::Vocabulary
+                    ][..]
+                ),
+                ("k8s", &[TagSuggestionSource::Llm][..]),
+                ("scaling", &[TagSuggestionSource::Llm][..]),
+            ]
+        );
+        // 1.0 from the LLM, 0.9 from the related bookmark, 0.5 from the title
+        assert!((suggestions[0].score - 2.4 * VOCABULARY_BOOST).abs() < 1e-9);
+    }
+
+    #[test]
+    fn limit_keeps_the_best() {
+        let suggestions = rank("", &[], &[], &[tags(&["a", "b"]), tags(&["b"])], &[], 1);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].tag, "b");
+    }
+}
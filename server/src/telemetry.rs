@@ -0,0 +1,50 @@
+use anyhow::Context;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+
+use crate::TelemetryParams;
+
+/// Batches spans to the OTLP/HTTP collector, `None` when no endpoint is set.
+/// Shut the provider down before exiting to flush the last batch.
+pub fn tracer_provider(params: &TelemetryParams) -> anyhow::Result<Option<SdkTracerProvider>> {
+    let Some(endpoint) = &params.otlp_endpoint else {
+        return Ok(None);
+    };
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(traces_endpoint(endpoint.as_str()))
+        .build()
+        .context("Failed to build the OTLP span exporter")?;
+    let resource = Resource::builder()
+        .with_service_name(params.otel_service_name.clone())
+        .build();
+    Ok(Some(
+        SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(resource)
+            .build(),
+    ))
+}
+
+/// The collector's base URL gets the `/v1/traces` path of the OTLP/HTTP spec.
+fn traces_endpoint(endpoint: &str) -> String {
+    format!("{}/v1/traces", endpoint.trim_end_matches('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::traces_endpoint;
+
+    #[test]
+    fn appends_the_traces_path() {
+        assert_eq!(
+            traces_endpoint("http://localhost:4318/"),
+            "http://localhost:4318/v1/traces"
+        );
+        assert_eq!(
+            traces_endpoint("https://otel.example.com/collector"),
+            "https://otel.example.com/collector/v1/traces"
+        );
+    }
+}
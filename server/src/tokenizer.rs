@@ -45,3 +45,178 @@ pub fn windowed_chunks(
     }
     Ok(result)
 }
+
+/// How embedding chunks are cut from a bookmark's text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ChunkStrategy {
+    /// Fixed token windows, may cut sentences in half
+    #[default]
+    Window,
+    /// Whole sentences packed up to the token budget
+    Sentence,
+}
+
+pub fn chunk_text(
+    strategy: ChunkStrategy,
+    size: usize,
+    overlap: usize,
+    text: &str,
+) -> anyhow::Result<Vec<String>> {
+    match strategy {
+        ChunkStrategy::Window => windowed_chunks(size, overlap, text),
+        ChunkStrategy::Sentence => sentence_chunks(size, overlap, text),
+    }
+}
+
+/// Packs whole sentences into chunks of at most `max_tokens`, repeating the
+/// trailing sentences of each chunk (up to `overlap_tokens`) at the start of
+/// the next one. Sentences over budget are split on word boundaries.
+pub fn sentence_chunks(
+    max_tokens: usize,
+    overlap_tokens: usize,
+    text: &str,
+) -> anyhow::Result<Vec<String>> {
+    let tokenizer =
+        Tokenizer::from_pretrained(TOKENIZER_MODEL, None).map_err(anyhow::Error::from_boxed)?;
+    let count = |text: &str| -> anyhow::Result<usize> {
+        let encoding = tokenizer
+            .encode(text, false)
+            .map_err(anyhow::Error::from_boxed)?;
+        Ok(encoding.get_ids().len())
+    };
+    pack_sentences(max_tokens, overlap_tokens, text, count)
+}
+
+fn pack_sentences(
+    max_tokens: usize,
+    overlap_tokens: usize,
+    text: &str,
+    count: impl Fn(&str) -> anyhow::Result<usize>,
+) -> anyhow::Result<Vec<String>> {
+    let mut units: Vec<(&str, usize)> = Vec::new();
+    for sentence in split_sentences(text) {
+        let tokens = count(sentence)?;
+        if tokens <= max_tokens {
+            units.push((sentence, tokens));
+            continue;
+        }
+        for word in sentence.split_whitespace() {
+            units.push((word, count(word)?));
+        }
+    }
+
+    let mut chunks = Vec::new();
+    let mut current: Vec<(&str, usize)> = Vec::new();
+    let mut current_tokens = 0;
+    // Units at the front of `current` that were carried over as overlap
+    let mut carried = 0;
+    for unit in units {
+        if current_tokens + unit.1 > max_tokens && current.len() > carried {
+            chunks.push(join_units(&current));
+
+            let mut overlap = Vec::new();
+            let mut overlap_size = 0;
+            for &(text, tokens) in current.iter().rev() {
+                if overlap_size + tokens > overlap_tokens
+                    || overlap_size + tokens + unit.1 > max_tokens
+                {
+                    break;
+                }
+                overlap_size += tokens;
+                overlap.push((text, tokens));
+            }
+            overlap.reverse();
+            carried = overlap.len();
+            current = overlap;
+            current_tokens = overlap_size;
+        }
+        current_tokens += unit.1;
+        current.push(unit);
+    }
+    if current.len() > carried {
+        chunks.push(join_units(&current));
+    }
+    Ok(chunks)
+}
+
+fn join_units(units: &[(&str, usize)]) -> String {
+    units
+        .iter()
+        .map(|(text, _)| *text)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Splits on sentence terminators followed by whitespace and on line breaks,
+/// dropping empty pieces.
+fn split_sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((index, ch)) = chars.next() {
+        let boundary = match ch {
+            '\n' => Some(index),
+            '.' | '!' | '?' => match chars.peek() {
+                Some((_, next)) if next.is_whitespace() => Some(index + ch.len_utf8()),
+                None => Some(index + ch.len_utf8()),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(end) = boundary {
+            let sentence = text[start..end].trim();
+            if !sentence.is_empty() {
+                sentences.push(sentence);
+            }
+            start = end;
+        }
+    }
+    let rest = text[start..].trim();
+    if !rest.is_empty() {
+        sentences.push(rest);
+    }
+    sentences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pack_sentences, split_sentences};
+
+    fn word_count(text: &str) -> anyhow::Result<usize> {
+        Ok(text.split_whitespace().count())
+    }
+
+    #[test]
+    fn splits_on_terminators_and_newlines() {
+        let sentences = split_sentences("First one. Second? Version 1.2 ships!\nNo stop here");
+        assert_eq!(
+            sentences,
+            vec![
+                "First one.",
+                "Second?",
+                "Version 1.2 ships!",
+                "No stop here"
+            ]
+        );
+    }
+
+    #[test]
+    fn packs_whole_sentences_with_overlap() {
+        let text = "One two three. Four five. Six seven eight. Nine ten.";
+        let chunks = pack_sentences(6, 2, text, word_count).unwrap();
+        assert_eq!(
+            chunks,
+            vec![
+                "One two three. Four five.",
+                "Four five. Six seven eight.",
+                "Nine ten.",
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_oversized_sentence_on_words() {
+        let chunks = pack_sentences(2, 0, "a b c d e.", word_count).unwrap();
+        assert_eq!(chunks, vec!["a b", "c d", "e."]);
+    }
+}
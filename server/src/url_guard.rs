@@ -0,0 +1,244 @@
+//! Keeps the fetch pipeline away from loopback, private and link-local
+//! addresses, so a bookmark can't be used to reach the server's own network,
+//! e.g. a cloud metadata endpoint at 169.254.169.254.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, LazyLock};
+
+use anyhow::{Context, Result};
+use ipnet::IpNet;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use url::{Host, Url};
+
+/// Ranges that aren't reachable on the public internet.
+static NON_PUBLIC_NETWORKS: LazyLock<Vec<IpNet>> = LazyLock::new(|| {
+    [
+        "0.0.0.0/8",
+        "10.0.0.0/8",
+        "100.64.0.0/10",
+        "127.0.0.0/8",
+        "169.254.0.0/16",
+        "172.16.0.0/12",
+        "192.0.0.0/24",
+        "192.0.2.0/24",
+        "192.168.0.0/16",
+        "198.18.0.0/15",
+        "198.51.100.0/24",
+        "203.0.113.0/24",
+        "224.0.0.0/4",
+        "240.0.0.0/4",
+        "::/128",
+        "::1/128",
+        "64:ff9b:1::/48",
+        "2001:db8::/32",
+        "fc00::/7",
+        "fe80::/10",
+        "ff00::/8",
+    ]
+    .iter()
+    .map(|network| network.parse().expect("valid network"))
+    .collect()
+});
+
+/// The IPv4 address an IPv6 one stands for, for the prefixes that embed one:
+/// IPv4-mapped `::ffff:0:0/96`, IPv4-compatible `::/96`, NAT64 `64:ff9b::/96`
+/// and 6to4 `2002::/16`.
+fn embedded_ipv4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    let [_, _, c, d, e, f, .., m, n, o, p] = ip.octets();
+    match ip.segments() {
+        [0, 0, 0, 0, 0, 0xffff, _, _]
+        | [0, 0, 0, 0, 0, 0, _, _]
+        | [0x64, 0xff9b, 0, 0, 0, 0, _, _] => Some(Ipv4Addr::new(m, n, o, p)),
+        [0x2002, ..] => Some(Ipv4Addr::new(c, d, e, f)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum BlockedUrl {
+    #[error("Only http and https URLs are fetched")]
+    Scheme,
+    #[error("{0} is not a public address")]
+    Address(IpAddr),
+    #[error("{0} is not a public host")]
+    Host(String),
+}
+
+/// Which addresses may be fetched: the public ones and those in
+/// `FETCH_ALLOWED_NETWORKS`.
+#[derive(Debug, Clone, Default)]
+pub struct UrlGuard {
+    allowed: Arc<[IpNet]>,
+}
+
+impl UrlGuard {
+    pub fn new(allowed: &[IpNet]) -> Self {
+        Self {
+            allowed: allowed.into(),
+        }
+    }
+
+    pub fn is_allowed_ip(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(ip) => embedded_ipv4(ip).map_or(IpAddr::V6(ip), IpAddr::V4),
+            ip => ip,
+        };
+        self.allowed.iter().any(|network| network.contains(&ip))
+            || !NON_PUBLIC_NETWORKS
+                .iter()
+                .any(|network| network.contains(&ip))
+    }
+
+    /// What the URL tells by itself: its scheme, and its host when it is an
+    /// address or a localhost name.
+    pub fn check(&self, url: &Url) -> Result<(), BlockedUrl> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(BlockedUrl::Scheme);
+        }
+        match url.host() {
+            Some(Host::Ipv4(ip)) => self.check_ip(IpAddr::V4(ip)),
+            Some(Host::Ipv6(ip)) => self.check_ip(IpAddr::V6(ip)),
+            Some(Host::Domain(domain)) => {
+                let domain = domain.trim_end_matches('.').to_lowercase();
+                if domain == "localhost" || domain.ends_with(".localhost") {
+                    return Err(BlockedUrl::Host(domain));
+                }
+                Ok(())
+            }
+            None => Err(BlockedUrl::Scheme),
+        }
+    }
+
+    /// [`Self::check`], then every address the host resolves to.
+    pub async fn check_resolved(&self, url: &Url) -> Result<()> {
+        self.check(url)?;
+        if let Some(Host::Domain(domain)) = url.host() {
+            let port = url.port_or_known_default().unwrap_or(80);
+            let addresses = tokio::net::lookup_host((domain, port))
+                .await
+                .with_context(|| format!("Failed to resolve {domain}"))?;
+            for address in addresses {
+                self.check_ip(address.ip())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// [`Self::check_resolved`] for callers off the async runtime, resolving
+    /// on the calling thread.
+    pub fn check_resolved_blocking(&self, url: &Url) -> Result<()> {
+        self.check(url)?;
+        if let Some(Host::Domain(domain)) = url.host() {
+            let port = url.port_or_known_default().unwrap_or(80);
+            let addresses = (domain, port)
+                .to_socket_addrs()
+                .with_context(|| format!("Failed to resolve {domain}"))?;
+            for address in addresses {
+                self.check_ip(address.ip())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_ip(&self, ip: IpAddr) -> Result<(), BlockedUrl> {
+        if self.is_allowed_ip(ip) {
+            Ok(())
+        } else {
+            Err(BlockedUrl::Address(ip))
+        }
+    }
+}
+
+/// Resolves names for reqwest, refusing the ones with an address that
+/// isn't allowed, so redirects and DNS answers changing after
+/// [`UrlGuard::check_resolved`] are covered too.
+impl Resolve for UrlGuard {
+    fn resolve(&self, name: Name) -> Resolving {
+        let guard = self.clone();
+        Box::pin(async move {
+            let addresses: Vec<SocketAddr> =
+                tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+            if let Some(address) = addresses
+                .iter()
+                .find(|address| !guard.is_allowed_ip(address.ip()))
+            {
+                return Err(BlockedUrl::Address(address.ip()).into());
+            }
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use super::{BlockedUrl, UrlGuard};
+
+    fn check(guard: &UrlGuard, url: &str) -> Result<(), BlockedUrl> {
+        guard.check(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn non_public_hosts_are_blocked() {
+        let guard = UrlGuard::default();
+        assert_eq!(check(&guard, "https://example.com/a"), Ok(()));
+        assert_eq!(check(&guard, "http://93.184.215.14/"), Ok(()));
+        assert_eq!(check(&guard, "ftp://example.com/"), Err(BlockedUrl::Scheme));
+        for url in [
+            "http://169.254.169.254/latest/meta-data/",
+            "http://127.0.0.1:8080/",
+            "http://10.1.2.3/",
+            "http://192.168.1.1/",
+            "http://[::1]/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://[fd00::1]/",
+            "http://0.0.0.0/",
+        ] {
+            assert!(
+                matches!(check(&guard, url), Err(BlockedUrl::Address(_))),
+                "{url}"
+            );
+        }
+        assert!(matches!(
+            check(&guard, "http://LocalHost./"),
+            Err(BlockedUrl::Host(_))
+        ));
+        assert!(matches!(
+            check(&guard, "http://app.localhost/"),
+            Err(BlockedUrl::Host(_))
+        ));
+    }
+
+    #[test]
+    fn ipv4_embedded_in_ipv6_is_checked() {
+        let guard = UrlGuard::default();
+        for url in [
+            // NAT64
+            "http://[64:ff9b::7f00:1]/",
+            "http://[64:ff9b::10.1.2.3]/",
+            "http://[64:ff9b:1::a9fe:a9fe]/",
+            // 6to4
+            "http://[2002:7f00:1::]/",
+            "http://[2002:a9fe:a9fe::1]/",
+            // IPv4-compatible
+            "http://[::127.0.0.1]/",
+            "http://[::a00:1]/",
+        ] {
+            assert!(
+                matches!(check(&guard, url), Err(BlockedUrl::Address(_))),
+                "{url}"
+            );
+        }
+        assert_eq!(check(&guard, "http://[64:ff9b::5db8:d70e]/"), Ok(()));
+        assert_eq!(check(&guard, "http://[2002:5db8:d70e::1]/"), Ok(()));
+        assert_eq!(check(&guard, "http://[2606:2800:220:1::]/"), Ok(()));
+    }
+
+    #[test]
+    fn allowed_networks_are_fetched() {
+        let guard = UrlGuard::new(&["192.168.1.0/24".parse().unwrap()]);
+        assert_eq!(check(&guard, "http://192.168.1.20/"), Ok(()));
+        assert!(check(&guard, "http://192.168.2.20/").is_err());
+    }
+}
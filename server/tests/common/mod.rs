@@ -7,6 +7,7 @@ use testcontainers::runners::AsyncRunner;
 use testcontainers::{ContainerAsync, GenericImage, ImageExt};
 use uuid::Uuid;
 
+pub mod sqlite;
 pub mod test_db;
 
 pub type PgPool = deadpool_postgres::Pool;
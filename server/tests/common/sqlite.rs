@@ -0,0 +1,44 @@
+//! The SQLite backend through the same `db::` functions the server calls,
+//! on an in-memory database so no container is needed.
+
+use chrono::Utc;
+use server::db::{self, user, Database, SqlitePool};
+use shared::{Bookmark, Visibility};
+use url::Url;
+use uuid::Uuid;
+
+pub async fn database() -> anyhow::Result<Database> {
+    let db = Database::Sqlite(SqlitePool::open_in_memory()?);
+    db::run_migrations(&db).await?;
+    Ok(db)
+}
+
+pub async fn create_user(db: &Database) -> anyhow::Result<Uuid> {
+    let username = format!("test_user_{}", Uuid::new_v4().simple());
+    Ok(
+        user::create(db, username, None, "password_hash".to_string())
+            .await?
+            .user_id,
+    )
+}
+
+pub fn new_bookmark(user_id: Uuid, url: &str, title: &str, tags: &[&str]) -> Bookmark {
+    let url = Url::parse(url).expect("valid url");
+    Bookmark {
+        bookmark_id: format!("bookmark_{}", Uuid::new_v4().simple()),
+        user_id,
+        domain: url.host_str().unwrap_or_default().to_string(),
+        url: url.to_string(),
+        title: title.to_string(),
+        tags: Some(tags.iter().map(|tag| tag.to_string()).collect()),
+        summary: None,
+        created_at: Utc::now(),
+        updated_at: None,
+        word_count: 0,
+        reading_time_minutes: 0,
+        kind: None,
+        favorite: false,
+        original_title: None,
+        visibility: Visibility::Private,
+    }
+}
@@ -1,8 +1,8 @@
 #![allow(dead_code)]
 
 use anyhow::Context;
-use server::db::{self, user};
-use shared::Bookmark;
+use server::db::{self, user, Database};
+use shared::{Bookmark, Visibility};
 use testcontainers::{ContainerAsync, GenericImage};
 use uuid::Uuid;
 
@@ -22,7 +22,9 @@ const CONTAINER_IMAGE: &str = match option_env!("TEST_DB_CONTAINER_IMAGE") {
 /// automatically handling database creation before a test and cleanup
 /// afterward.
 pub struct TestDatabase {
-    pub pool: PgPool,
+    pub pool: Database,
+    /// The same database as `pool`, for statements the tests run directly
+    pub pg: PgPool,
     pub db_name: String,
 
     // Container reference to keep it alive
@@ -36,7 +38,7 @@ pub struct TestDatabase {
 impl Drop for TestDatabase {
     fn drop(&mut self) {
         // Close the connection pool to prevent any lingering connections
-        self.pool.close();
+        self.pg.close();
         // Container cleanup is handled automatically when _container is dropped
     }
 }
@@ -75,7 +77,8 @@ impl TestDatabase {
             .await?;
 
         // Connect to the new database
-        let pool = create_postgres_pool(&host, port, &db_name, "postgres", None).await?;
+        let pg = create_postgres_pool(&host, port, &db_name, "postgres", None).await?;
+        let pool = Database::Postgres(pg.clone().into());
 
         // Run migrations
         db::run_migrations(&pool)
@@ -84,6 +87,7 @@ impl TestDatabase {
 
         Ok(Self {
             pool,
+            pg,
             db_name,
             _container: container,
             host,
@@ -123,10 +127,11 @@ impl TestDatabase {
             )
             .await?;
 
-        let pool = create_postgres_pool(&host, port, &db_name, "postgres", None).await?;
+        let pg = create_postgres_pool(&host, port, &db_name, "postgres", None).await?;
 
         Ok(Self {
-            pool,
+            pool: Database::Postgres(pg.clone().into()),
+            pg,
             db_name,
             _container: container,
             host,
@@ -143,7 +148,7 @@ impl TestDatabase {
 /// Creates a test user with a unique username.
 pub async fn create_test_user(db: &TestDatabase) -> anyhow::Result<Uuid> {
     let username = format!("test_user_{}", Uuid::new_v4());
-    let user = user::create(&db.pool, username, "password_hash".to_string()).await?;
+    let user = user::create(&db.pool, username, None, "password_hash".to_string()).await?;
     Ok(user.user_id)
 }
 
@@ -167,6 +172,12 @@ pub fn create_test_bookmark(
         summary: None,
         created_at: chrono::Utc::now(),
         updated_at: None,
+        word_count: 0,
+        reading_time_minutes: 0,
+        kind: None,
+        favorite: false,
+        original_title: None,
+        visibility: Visibility::Private,
     }
 }
 
@@ -14,7 +14,7 @@ const TEST_EMBEDDING_DIMENSIONS: usize = 16;
 async fn test_db_database_migrations() -> anyhow::Result<()> {
     // Create an empty database
     let db = TestDatabase::new_empty().await?;
-    let client = db.pool.get().await?;
+    let client = db.pg.get().await?;
 
     // Run migrations
     db::run_migrations(&db.pool)
@@ -117,7 +117,7 @@ async fn test_db_with_migrations_preapplied() -> anyhow::Result<()> {
     let db = TestDatabase::new().await?;
 
     // Verify we can connect and query
-    let client = db.pool.get().await?;
+    let client = db.pg.get().await?;
     let result: i32 = client.query_one("SELECT 1", &[]).await?.get(0);
 
     assert_eq!(result, 1, "Basic query should work");
@@ -134,10 +134,52 @@ async fn test_db_with_migrations_preapplied() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_db_migrations_record_and_verify_checksums() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let client = db.pg.get().await?;
+
+    let unrecorded: i64 = client
+        .query_one(
+            "SELECT COUNT(*) FROM schema_version WHERE checksum IS NULL OR name IS NULL",
+            &[],
+        )
+        .await?
+        .get(0);
+    assert_eq!(unrecorded, 0);
+
+    // Databases migrated before checksums were recorded get them backfilled
+    client
+        .execute(
+            "UPDATE schema_version SET checksum = NULL WHERE version = 1",
+            &[],
+        )
+        .await?;
+    db::run_migrations(&db.pool).await?;
+    let checksum: Option<String> = client
+        .query_one("SELECT checksum FROM schema_version WHERE version = 1", &[])
+        .await?
+        .get(0);
+    assert!(checksum.is_some());
+
+    client
+        .execute(
+            "UPDATE schema_version SET checksum = 'tampered' WHERE version = 2",
+            &[],
+        )
+        .await?;
+    let error = db::run_migrations(&db.pool)
+        .await
+        .expect_err("a changed migration must be rejected");
+    assert!(format!("{error:?}").contains("Migration 2"));
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_db_migration_4_backfills_existing_version_3_data() -> anyhow::Result<()> {
     let db = TestDatabase::new_empty().await?;
-    let client = db.pool.get().await?;
+    let client = db.pg.get().await?;
 
     client
         .batch_execute(include_str!(concat!(
@@ -229,7 +271,7 @@ async fn test_db_migration_4_backfills_existing_version_3_data() -> anyhow::Resu
         .await
         .context("Failed to apply pending migrations after schema version 3")?;
 
-    let client = db.pool.get().await?;
+    let client = db.pg.get().await?;
     let bookmark_tags: Option<Vec<String>> = client
         .query_one(
             "SELECT tags FROM bookmark WHERE bookmark_id = $1 AND user_id = $2",
@@ -257,7 +299,7 @@ async fn test_db_migration_4_backfills_existing_version_3_data() -> anyhow::Resu
         task_tags.into_iter().collect::<BTreeSet<_>>(),
         BTreeSet::from(["ops".to_string()])
     );
-    assert_eq!(schema_version, 9);
+    assert_eq!(schema_version, 20);
 
     Ok(())
 }
@@ -287,7 +329,7 @@ async fn test_db_embedding_profile_reconcile_clears_legacy_chunks() -> anyhow::R
     .await?;
 
     let before_count: i64 = db
-        .pool
+        .pg
         .get()
         .await?
         .query_one("SELECT COUNT(*) FROM bookmark_chunk", &[])
@@ -305,7 +347,7 @@ async fn test_db_embedding_profile_reconcile_clears_legacy_chunks() -> anyhow::R
     )
     .await?;
 
-    let client = db.pool.get().await?;
+    let client = db.pg.get().await?;
     let after_count: i64 = client
         .query_one("SELECT COUNT(*) FROM bookmark_chunk", &[])
         .await?
@@ -341,10 +383,37 @@ async fn test_db_embedding_profile_reconcile_clears_legacy_chunks() -> anyhow::R
     Ok(())
 }
 
+#[tokio::test]
+async fn test_db_ensure_embedding_profile_rejects_other_model() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let profile = EmbeddingProfile {
+        provider: "ollama".to_string(),
+        model: "qwen3-embedding:0.6b".to_string(),
+        dimensions: TEST_EMBEDDING_DIMENSIONS,
+    };
+
+    assert!(db::ensure_embedding_profile(&db.pool, &profile)
+        .await
+        .is_err());
+
+    db::reconcile_embedding_profile(&db.pool, &profile).await?;
+    db::ensure_embedding_profile(&db.pool, &profile).await?;
+
+    let other_model = EmbeddingProfile {
+        model: "nomic-embed-text".to_string(),
+        ..profile
+    };
+    assert!(db::ensure_embedding_profile(&db.pool, &other_model)
+        .await
+        .is_err());
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_db_migration_7_backfills_bookmark_identity() -> anyhow::Result<()> {
     let db = TestDatabase::new_empty().await?;
-    let client = db.pool.get().await?;
+    let client = db.pg.get().await?;
 
     client
         .batch_execute(include_str!(concat!(
@@ -444,7 +513,7 @@ async fn test_db_migration_7_backfills_bookmark_identity() -> anyhow::Result<()>
         .await
         .context("Failed to apply pending migrations after schema version 6")?;
 
-    let client = db.pool.get().await?;
+    let client = db.pg.get().await?;
     let canonical_url: String = client
         .query_one(
             "SELECT canonical_url FROM bookmark WHERE bookmark_id = $1 AND user_id = $2",
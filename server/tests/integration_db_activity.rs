@@ -0,0 +1,86 @@
+#![cfg(feature = "integration-tests")]
+
+mod common;
+
+use common::test_db::{create_test_bookmark, create_test_user, TestDatabase};
+use server::db::{activity, audit_log, bookmark, bookmark_task, rag, task_event};
+use shared::{ActivityKind, AuditAction, TaskStage};
+use url::Url;
+
+#[tokio::test]
+async fn test_activity_merges_events_newest_first() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+    let other_user_id = create_test_user(&db).await?;
+    let saved = create_test_bookmark(user_id, "https://example.com/a", "A", "example.com", None);
+    bookmark::save(&db.pool, &saved, "content").await?;
+    let other = create_test_bookmark(
+        other_user_id,
+        "https://example.com/b",
+        "B",
+        "example.com",
+        None,
+    );
+    bookmark::save(&db.pool, &other, "content").await?;
+    // Saved again later, the stages then go to both tasks
+    for _ in 0..2 {
+        let task =
+            bookmark_task::create(&db.pool, user_id, Url::parse(&saved.url)?, vec![]).await?;
+        bookmark_task::set_bookmark_id(&db.pool, task.task_id, &saved.bookmark_id).await?;
+    }
+    task_event::insert_for_bookmark(
+        &db.pool,
+        user_id,
+        &saved.bookmark_id,
+        TaskStage::Tagged,
+        Some("rust, web"),
+    )
+    .await?;
+    rag::create_rag_session(&db.pool, user_id, "why?").await?;
+    audit_log::insert(
+        &db.pool,
+        user_id,
+        AuditAction::BackupRestored,
+        Some("3 bookmarks"),
+        None,
+    )
+    .await?;
+    // Other audit events aren't activity
+    audit_log::insert(&db.pool, user_id, AuditAction::SignIn, None, None).await?;
+
+    let items = activity::list(&db.pool, user_id, None, 2).await?;
+    assert_eq!(
+        items.iter().map(|item| item.kind).collect::<Vec<_>>(),
+        vec![ActivityKind::ImportCompleted, ActivityKind::QuestionAsked]
+    );
+    assert_eq!(items[0].detail.as_deref(), Some("3 bookmarks"));
+    assert_eq!(items[1].detail.as_deref(), Some("why?"));
+
+    let older = activity::list(&db.pool, user_id, Some(items[1].created_at), 50).await?;
+    assert_eq!(
+        older.iter().map(|item| item.kind).collect::<Vec<_>>(),
+        vec![ActivityKind::TagsApplied, ActivityKind::BookmarkAdded]
+    );
+    assert_eq!(older[0].detail.as_deref(), Some("rust, web"));
+    assert_eq!(
+        older[0].bookmark_id.as_deref(),
+        Some(saved.bookmark_id.as_str())
+    );
+    assert_eq!(older[1].title.as_deref(), Some("A"));
+    assert_eq!(
+        activity::list(&db.pool, other_user_id, None, 50)
+            .await?
+            .len(),
+        1
+    );
+
+    // Trashed bookmarks drop out along with their stages
+    assert!(bookmark::move_to_trash(&db.pool, user_id, &saved.bookmark_id).await?);
+    let items = activity::list(&db.pool, user_id, None, 50).await?;
+    assert_eq!(
+        items.iter().map(|item| item.kind).collect::<Vec<_>>(),
+        vec![ActivityKind::ImportCompleted, ActivityKind::QuestionAsked]
+    );
+
+    Ok(())
+}
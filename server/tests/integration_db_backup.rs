@@ -0,0 +1,223 @@
+#![cfg(feature = "integration-tests")]
+
+mod common;
+
+use common::test_db::{create_test_bookmark, create_test_user, TestDatabase};
+use server::backup;
+use server::db::{
+    self, bookmark, chunks, collection, extraction_rule, fetch_credential, rule, user,
+    user_settings, workspace, EmbeddingProfile,
+};
+use server::static_storage::{LocalStorage, StorageBackend, StoredObject, GZIP_ENCODING};
+use shared::{
+    CollectionRequest, ExtractionRuleRequest, RuleActions, RuleConditions, RuleRequest,
+    UserSettings, WorkspaceRole,
+};
+use uuid::Uuid;
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("{name}-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[tokio::test]
+async fn test_backup_and_restore() -> anyhow::Result<()> {
+    let source = TestDatabase::new().await?;
+    let profile = EmbeddingProfile {
+        provider: "ollama".to_string(),
+        model: "test-embedding".to_string(),
+        dimensions: 3,
+    };
+    db::reconcile_embedding_profile(&source.pool, &profile).await?;
+    let user_id = create_test_user(&source).await?;
+    let settings = UserSettings {
+        summary_language: Some("Portuguese".to_string()),
+        ..UserSettings::default()
+    };
+    user_settings::upsert(&source.pool, user_id, &settings).await?;
+    let saved = bookmark::save(
+        &source.pool,
+        &create_test_bookmark(
+            user_id,
+            "https://example.com/rust",
+            "Rust",
+            "example.com",
+            Some(vec!["rust".to_string()]),
+        ),
+        &"Rust ownership and borrowing. ".repeat(10),
+    )
+    .await?;
+    chunks::store_chunks_with_embeddings(
+        &source.pool,
+        &saved.bookmark_id,
+        user_id,
+        vec!["Rust ownership".to_string()],
+        vec![vec![0.1, 0.2, 0.3]],
+    )
+    .await?;
+
+    let source_dir = temp_dir("backup-source");
+    let source_storage = LocalStorage::new(source_dir.clone())?;
+    let page_key = format!("{user_id}/{}/index.html", saved.bookmark_id);
+    let page = StoredObject {
+        content_type: None,
+        content_encoding: Some(GZIP_ENCODING.to_string()),
+        bytes: b"compressed page".to_vec(),
+        last_modified: None,
+    };
+    source_storage.put(&page_key, page.clone()).await?;
+
+    let archive_dir = temp_dir("backup-archive");
+    let archive = archive_dir.join("backup.tar.gz");
+    let summary = backup::backup(&source.pool, &source_storage, &archive).await?;
+    assert_eq!(
+        summary,
+        backup::Summary {
+            users: 1,
+            bookmarks: 1,
+            objects: 1,
+        }
+    );
+
+    let target = TestDatabase::new().await?;
+    let target_dir = temp_dir("backup-target");
+    let target_storage = LocalStorage::new(target_dir.clone())?;
+    let restored = backup::restore(&target.pool, &target_storage, &archive, false).await?;
+    assert_eq!(restored, summary);
+
+    let source_user = user::get_by_id(&source.pool, &user_id).await?.unwrap();
+    let target_user = user::get_by_id(&target.pool, &user_id).await?.unwrap();
+    assert_eq!(target_user.username, source_user.username);
+    assert_eq!(target_user.password_hash, source_user.password_hash);
+    assert_eq!(user_settings::get(&target.pool, user_id).await?, settings);
+
+    let restored_bookmark = bookmark::get_with_user_data(&target.pool, user_id, &saved.bookmark_id)
+        .await?
+        .unwrap();
+    assert_eq!(restored_bookmark.url, saved.url);
+    assert_eq!(restored_bookmark.tags, saved.tags);
+    assert!(chunks::has_chunks_for_bookmark(&target.pool, &saved.bookmark_id, user_id).await?);
+    assert_eq!(
+        db::stored_embedding_profile(&target.pool).await?,
+        Some(profile)
+    );
+    let restored_page = target_storage.get(&page_key).await?.unwrap();
+    assert_eq!(restored_page.bytes, page.bytes);
+    assert_eq!(restored_page.content_encoding, page.content_encoding);
+
+    // Users are never overwritten
+    assert!(
+        backup::restore(&target.pool, &target_storage, &archive, false)
+            .await
+            .is_err()
+    );
+
+    let regenerated = TestDatabase::new().await?;
+    let regenerated_dir = temp_dir("backup-regenerated");
+    let regenerated_storage = LocalStorage::new(regenerated_dir.clone())?;
+    backup::restore(&regenerated.pool, &regenerated_storage, &archive, true).await?;
+    assert!(
+        !chunks::has_chunks_for_bookmark(&regenerated.pool, &saved.bookmark_id, user_id).await?
+    );
+    let embedding_status: String = regenerated
+        .pg
+        .get()
+        .await?
+        .query_one(
+            "SELECT embedding_status::TEXT FROM bookmark WHERE bookmark_id = $1",
+            &[&saved.bookmark_id],
+        )
+        .await?
+        .get(0);
+    assert_eq!(embedding_status, "pending");
+
+    for dir in [source_dir, archive_dir, target_dir, regenerated_dir] {
+        std::fs::remove_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_backup_round_trips_collections_rules_and_workspaces() -> anyhow::Result<()> {
+    let source = TestDatabase::new().await?;
+    // Created first, so restored before the owner of the workspace they joined
+    let member_id = create_test_user(&source).await?;
+    let owner_id = create_test_user(&source).await?;
+    let page = create_test_bookmark(owner_id, "https://example.com/a", "A", "example.com", None);
+    let saved = bookmark::save(&source.pool, &page, "First capture").await?;
+    bookmark::refresh_capture(&source.pool, &saved, "Second capture", None).await?;
+    collection::create(
+        &source.pool,
+        owner_id,
+        &CollectionRequest {
+            name: "Reading list".to_string(),
+            description: None,
+            bookmark_ids: vec![saved.bookmark_id.clone()],
+        },
+    )
+    .await?;
+    rule::create(
+        &source.pool,
+        owner_id,
+        &RuleRequest {
+            name: "Favorites".to_string(),
+            enabled: None,
+            conditions: RuleConditions {
+                domain: Some("example.com".to_string()),
+                ..Default::default()
+            },
+            actions: RuleActions {
+                favorite: true,
+                ..Default::default()
+            },
+        },
+    )
+    .await?;
+    extraction_rule::create(
+        &source.pool,
+        owner_id,
+        &ExtractionRuleRequest {
+            domain: "example.com".to_string(),
+            keep_selectors: vec!["article".to_string()],
+            drop_selectors: vec![],
+            title_selector: None,
+        },
+    )
+    .await?;
+    fetch_credential::replace(
+        &source.pool,
+        owner_id,
+        vec![("example.com".into(), vec![7])],
+    )
+    .await?;
+    let team = workspace::create(&source.pool, owner_id, "Team").await?;
+    workspace::set_member(
+        &source.pool,
+        team.workspace_id,
+        member_id,
+        WorkspaceRole::Viewer,
+    )
+    .await?;
+
+    let archive_dir = temp_dir("backup-archive");
+    let archive = archive_dir.join("backup.tar.gz");
+    let storage = LocalStorage::new(temp_dir("backup-storage"))?;
+    backup::backup(&source.pool, &storage, &archive).await?;
+    let target = TestDatabase::new().await?;
+    backup::restore(&target.pool, &storage, &archive, false).await?;
+
+    for user_id in [member_id, owner_id] {
+        let before = db::backup::export_user(&source.pool, user_id).await?;
+        let after = db::backup::export_user(&target.pool, user_id).await?;
+        assert_eq!(serde_json::to_value(after)?, serde_json::to_value(before)?);
+    }
+    assert_eq!(
+        workspace::get(&target.pool, member_id, team.workspace_id)
+            .await?
+            .map(|workspace| workspace.role),
+        Some(WorkspaceRole::Viewer)
+    );
+    std::fs::remove_dir_all(archive_dir)?;
+    Ok(())
+}
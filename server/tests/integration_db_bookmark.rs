@@ -6,13 +6,14 @@ use chrono::{Duration, Utc};
 use common::test_db::{create_test_bookmark, create_test_user, TestDatabase};
 use server::db::ai;
 use server::db::bookmark::{self, AiGenerationStatus};
-use shared::TagOperation;
+use shared::{BulkBookmarkAction, TagOperation};
+use uuid::Uuid;
 
 async fn get_processing_state(
     db: &TestDatabase,
     bookmark_id: &str,
 ) -> anyhow::Result<(AiGenerationStatus, i16, Option<String>, AiGenerationStatus)> {
-    let client = db.pool.get().await?;
+    let client = db.pg.get().await?;
     let row = client
         .query_one(
             "SELECT summary_status, text_ai_attempts, text_ai_fail_reason, tag_status
@@ -509,6 +510,43 @@ async fn test_get_tag_count_by_user() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_rename_tag() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user1_id = create_test_user(&db).await?;
+    let user2_id = create_test_user(&db).await?;
+
+    for (user_id, url, tags) in [
+        (user1_id, "https://example.com/1", vec!["rustlang", "web"]),
+        (user1_id, "https://example.com/2", vec!["rustlang", "rust"]),
+        (user1_id, "https://example.com/3", vec!["web"]),
+        (user2_id, "https://example.com/4", vec!["rustlang"]),
+    ] {
+        let tags = tags.into_iter().map(String::from).collect();
+        let new = create_test_bookmark(user_id, url, "Article", "example.com", Some(tags));
+        bookmark::save(&db.pool, &new, "content").await?;
+    }
+
+    let renamed = bookmark::rename_tag(&db.pool, user1_id, "rustlang", "rust").await?;
+    assert_eq!(renamed, 2);
+
+    // The bookmark that had both tags keeps a single one
+    let counts: std::collections::HashMap<String, i64> =
+        bookmark::get_tag_count_by_user(&db.pool, user1_id)
+            .await?
+            .into_iter()
+            .collect();
+    assert_eq!(counts.get("rust"), Some(&2));
+    assert_eq!(counts.get("web"), Some(&2));
+    assert!(!counts.contains_key("rustlang"));
+
+    // Other users keep their tags
+    let user2_bookmarks = bookmark::get_by_tag(&db.pool, user2_id, "rustlang").await?;
+    assert_eq!(user2_bookmarks.len(), 1);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_text_ai_claim_and_backoff() -> anyhow::Result<()> {
     let db = TestDatabase::new().await?;
@@ -852,3 +890,231 @@ async fn test_canonical_url_distinguishes_query_scheme_and_port() -> anyhow::Res
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_get_random() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+
+    assert!(bookmark::get_random(&db.pool, user_id, None, false)
+        .await?
+        .is_none());
+
+    let rust = create_test_bookmark(
+        user_id,
+        "https://example.com/rust",
+        "Rust",
+        "example.com",
+        Some(vec!["rust".to_string()]),
+    );
+    bookmark::save(&db.pool, &rust, "content").await?;
+    let untagged = create_test_bookmark(
+        user_id,
+        "https://example.com/other",
+        "Other",
+        "example.com",
+        None,
+    );
+    bookmark::save(&db.pool, &untagged, "content").await?;
+
+    assert!(bookmark::get_random(&db.pool, user_id, None, false)
+        .await?
+        .is_some());
+    let picked = bookmark::get_random(&db.pool, user_id, Some("rust"), false).await?;
+    assert_eq!(
+        picked.map(|b| b.bookmark_id),
+        Some(rust.bookmark_id.clone())
+    );
+    assert!(
+        bookmark::get_random(&db.pool, user_id, Some("python"), false)
+            .await?
+            .is_none()
+    );
+
+    // Opened in the reader, the bookmark isn't picked among the unread ones
+    assert!(bookmark::mark_read(&db.pool, user_id, &rust.bookmark_id).await?);
+    assert!(bookmark::get_random(&db.pool, user_id, Some("rust"), true)
+        .await?
+        .is_none());
+    let picked = bookmark::get_random(&db.pool, user_id, None, true).await?;
+    assert_eq!(picked.map(|b| b.bookmark_id), Some(untagged.bookmark_id));
+    assert!(!bookmark::mark_read(&db.pool, user_id, "missing").await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_on_this_day() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+
+    let saved_on = [
+        ("https://example.com/2022", "2022-03-14T10:00:00Z"),
+        ("https://example.com/2023", "2023-03-14T23:30:00Z"),
+        ("https://example.com/other-day", "2023-03-15T10:00:00Z"),
+        ("https://example.com/today", "2024-03-14T08:00:00Z"),
+    ];
+    for (url, created_at) in saved_on {
+        let saved = create_test_bookmark(user_id, url, url, "example.com", None);
+        bookmark::save(&db.pool, &saved, "content").await?;
+        let created_at: chrono::DateTime<Utc> = created_at.parse()?;
+        db.pg
+            .get()
+            .await?
+            .execute(
+                "UPDATE bookmark SET created_at = $1 WHERE bookmark_id = $2",
+                &[&created_at, &saved.bookmark_id],
+            )
+            .await?;
+    }
+
+    let today = chrono::NaiveDate::from_ymd_opt(2024, 3, 14).unwrap();
+    let urls: Vec<String> = bookmark::get_on_this_day(&db.pool, user_id, today)
+        .await?
+        .into_iter()
+        .map(|b| b.url)
+        .collect();
+    assert_eq!(
+        urls,
+        vec!["https://example.com/2023", "https://example.com/2022"]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_wallabag_entry_id_matches_the_sql_function() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let client = db.pg.get().await?;
+
+    let mut bookmark_ids = vec![String::new(), "é-ünïcode".to_string()];
+    bookmark_ids.extend((0..32).map(|_| Uuid::new_v4().to_string()));
+    for bookmark_id in &bookmark_ids {
+        let row = client
+            .query_one("SELECT wallabag_entry_id($1)", &[bookmark_id])
+            .await?;
+        let sql_id: i64 = row.get(0);
+        assert_eq!(
+            sql_id,
+            bookmark::wallabag_entry_id(bookmark_id),
+            "bookmark_id={bookmark_id:?}"
+        );
+    }
+
+    let user_id = create_test_user(&db).await?;
+    let other_id = create_test_user(&db).await?;
+    let saved = create_test_bookmark(
+        user_id,
+        "https://example.com/entry",
+        "Entry",
+        "example.com",
+        None,
+    );
+    bookmark::save(&db.pool, &saved, "content").await?;
+    let entry_id = bookmark::wallabag_entry_id(&saved.bookmark_id);
+    let found = bookmark::get_by_wallabag_entry_id(&db.pool, user_id, entry_id).await?;
+    assert_eq!(found.map(|b| b.bookmark_id), Some(saved.bookmark_id));
+    assert!(
+        bookmark::get_by_wallabag_entry_id(&db.pool, other_id, entry_id)
+            .await?
+            .is_none()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bulk_update_leaves_other_users_and_the_trash_alone() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+    let other_user_id = create_test_user(&db).await?;
+    let tagged = create_test_bookmark(
+        user_id,
+        "https://example.com/tagged",
+        "Tagged",
+        "example.com",
+        Some(vec!["rust".to_string()]),
+    );
+    let untagged = create_test_bookmark(
+        user_id,
+        "https://example.com/untagged",
+        "Untagged",
+        "example.com",
+        None,
+    );
+    let trashed = create_test_bookmark(
+        user_id,
+        "https://example.com/trashed",
+        "Trashed",
+        "example.com",
+        None,
+    );
+    let others = create_test_bookmark(
+        other_user_id,
+        "https://example.com/other",
+        "Other",
+        "example.com",
+        None,
+    );
+    for saved in [&tagged, &untagged, &trashed, &others] {
+        bookmark::save(&db.pool, saved, "content").await?;
+    }
+    bookmark::move_to_trash(&db.pool, user_id, &trashed.bookmark_id).await?;
+    let bookmark_ids: Vec<String> = [&tagged, &untagged, &trashed, &others]
+        .iter()
+        .map(|saved| saved.bookmark_id.clone())
+        .chain(["missing".to_string()])
+        .collect();
+
+    let add_tags = BulkBookmarkAction::AddTags {
+        tags: vec!["Rust".to_string(), "web".to_string()],
+    };
+    assert_eq!(
+        bookmark::bulk_update(&db.pool, user_id, &bookmark_ids, &add_tags).await?,
+        2
+    );
+    for saved in [&tagged, &untagged] {
+        let mut tags = bookmark::get_with_user_data(&db.pool, user_id, &saved.bookmark_id)
+            .await?
+            .and_then(|b| b.tags)
+            .unwrap_or_default();
+        tags.sort();
+        assert_eq!(tags, vec!["rust", "web"]);
+        let (_, _, _, tag_status) = get_processing_state(&db, &saved.bookmark_id).await?;
+        assert_eq!(tag_status, AiGenerationStatus::Done);
+    }
+    assert_eq!(
+        bookmark::get_with_user_data(&db.pool, other_user_id, &others.bookmark_id)
+            .await?
+            .and_then(|b| b.tags),
+        None
+    );
+
+    assert_eq!(
+        bookmark::bulk_update(
+            &db.pool,
+            user_id,
+            &bookmark_ids,
+            &BulkBookmarkAction::Favorite
+        )
+        .await?,
+        2
+    );
+    assert!(
+        bookmark::get_with_user_data(&db.pool, user_id, &untagged.bookmark_id)
+            .await?
+            .expect("bookmark")
+            .favorite
+    );
+    assert_eq!(
+        bookmark::bulk_update(&db.pool, user_id, &bookmark_ids, &BulkBookmarkAction::Trash).await?,
+        2
+    );
+    assert_eq!(bookmark::get_trash(&db.pool, user_id).await?.len(), 3);
+    assert_eq!(
+        bookmark::get_by_user(&db.pool, other_user_id).await?.len(),
+        1
+    );
+
+    Ok(())
+}
@@ -3,12 +3,28 @@
 mod common;
 
 use chrono::{Duration, Utc};
-use common::test_db::{create_test_user, TestDatabase};
-use server::db::bookmark_task;
-use shared::{BookmarkTaskSearchRequest, BookmarkTaskStatus};
+use common::test_db::{create_test_bookmark, create_test_user, TestDatabase};
+use futures::StreamExt;
+use server::db::{bookmark, bookmark_task, notify, task_event};
+use server::events::EventBus;
+use shared::{
+    BookmarkTaskSearchRequest, BookmarkTaskStatus, BulkRetryTasksRequest, ServerEvent, TaskStage,
+};
 use url::Url;
 use uuid::Uuid;
 
+/// Server instances sharing the queue.
+const OWNER: &str = "replica-a";
+const OTHER_OWNER: &str = "replica-b";
+const LEASE: Duration = Duration::minutes(5);
+
+/// Leases the due tasks to `OWNER`, as the daemon does before recording
+/// their outcome.
+async fn lease_due(db: &TestDatabase) -> anyhow::Result<()> {
+    bookmark_task::lease(&db.pool, OWNER, Utc::now() + Duration::seconds(1), LEASE).await?;
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_task_create_and_retrieve() -> anyhow::Result<()> {
     let db = TestDatabase::new().await?;
@@ -55,7 +71,8 @@ async fn test_peek_tasks() -> anyhow::Result<()> {
     }
 
     // Peek tasks - should get all pending tasks
-    let peeked_tasks = bookmark_task::peek(&db.pool, now + Duration::seconds(1)).await?;
+    let peeked_tasks =
+        bookmark_task::lease(&db.pool, OWNER, now + Duration::seconds(1), LEASE).await?;
 
     assert_eq!(peeked_tasks.len(), 3);
 
@@ -65,17 +82,18 @@ async fn test_peek_tasks() -> anyhow::Result<()> {
         assert!(peeked_ids.contains(&task.task_id));
     }
 
-    // Peek again immediately - should get no tasks (next_delivery updated)
-    let peeked_again = bookmark_task::peek(&db.pool, now + Duration::seconds(1)).await?;
+    // Lease again immediately - should get no tasks (still leased)
+    let peeked_again =
+        bookmark_task::lease(&db.pool, OWNER, now + Duration::seconds(1), LEASE).await?;
     assert_eq!(
         peeked_again.len(),
         0,
         "Tasks should not be available immediately after peek"
     );
 
-    // Peek after 5+ minutes - tasks should be available again
+    // Lease expired after 5+ minutes - tasks should be available again
     let future_time = now + Duration::minutes(6);
-    let peeked_future = bookmark_task::peek(&db.pool, future_time).await?;
+    let peeked_future = bookmark_task::lease(&db.pool, OWNER, future_time, LEASE).await?;
     assert_eq!(
         peeked_future.len(),
         3,
@@ -97,7 +115,8 @@ async fn test_peek_limit() -> anyhow::Result<()> {
     }
 
     // Peek should return at most 10 tasks
-    let peeked = bookmark_task::peek(&db.pool, Utc::now() + Duration::seconds(1)).await?;
+    let peeked =
+        bookmark_task::lease(&db.pool, OWNER, Utc::now() + Duration::seconds(1), LEASE).await?;
     assert_eq!(peeked.len(), 10, "Peek should respect the limit of 10");
 
     Ok(())
@@ -110,9 +129,18 @@ async fn test_update_task_status() -> anyhow::Result<()> {
 
     let url = Url::parse("https://example.com/test")?;
     let task = bookmark_task::create(&db.pool, user_id, url, vec![]).await?;
+    lease_due(&db).await?;
 
     // Update to Done status
-    bookmark_task::update(&db.pool, task.clone(), BookmarkTaskStatus::Done, None, None).await?;
+    bookmark_task::update(
+        &db.pool,
+        OWNER,
+        task.clone(),
+        BookmarkTaskStatus::Done,
+        None,
+        None,
+    )
+    .await?;
 
     // Search for the task to verify update
     let search_req = BookmarkTaskSearchRequest {
@@ -127,9 +155,11 @@ async fn test_update_task_status() -> anyhow::Result<()> {
     // Create another task and update to Fail with retry info
     let url2 = Url::parse("https://example.com/fail")?;
     let task2 = bookmark_task::create(&db.pool, user_id, url2, vec![]).await?;
+    lease_due(&db).await?;
 
     bookmark_task::update(
         &db.pool,
+        OWNER,
         task2.clone(),
         BookmarkTaskStatus::Fail,
         Some(3),
@@ -203,9 +233,11 @@ async fn test_search_by_status() -> anyhow::Result<()> {
     let _task3 = bookmark_task::create(&db.pool, user_id, url3, vec![]).await?;
 
     // Update tasks to different statuses
-    bookmark_task::update(&db.pool, task1, BookmarkTaskStatus::Done, None, None).await?;
+    lease_due(&db).await?;
+    bookmark_task::update(&db.pool, OWNER, task1, BookmarkTaskStatus::Done, None, None).await?;
     bookmark_task::update(
         &db.pool,
+        OWNER,
         task2,
         BookmarkTaskStatus::Fail,
         Some(1),
@@ -440,7 +472,8 @@ async fn test_search_combined_filters() -> anyhow::Result<()> {
     bookmark_task::create(&db.pool, user_id, url3, vec!["rust".to_string()]).await?;
 
     // Update one task to Done
-    bookmark_task::update(&db.pool, task2, BookmarkTaskStatus::Done, None, None).await?;
+    lease_due(&db).await?;
+    bookmark_task::update(&db.pool, OWNER, task2, BookmarkTaskStatus::Done, None, None).await?;
 
     // Search with multiple filters: URL pattern + tags + status
     let search_req = BookmarkTaskSearchRequest {
@@ -478,9 +511,11 @@ async fn test_concurrent_peek() -> anyhow::Result<()> {
     let pool1 = db.pool.clone();
     let pool2 = db.pool.clone();
 
-    let handle1 = tokio::spawn(async move { bookmark_task::peek(&pool1, now).await });
+    let handle1 =
+        tokio::spawn(async move { bookmark_task::lease(&pool1, OWNER, now, LEASE).await });
 
-    let handle2 = tokio::spawn(async move { bookmark_task::peek(&pool2, now).await });
+    let handle2 =
+        tokio::spawn(async move { bookmark_task::lease(&pool2, OTHER_OWNER, now, LEASE).await });
 
     let result1 = handle1.await??;
     let result2 = handle2.await??;
@@ -547,3 +582,440 @@ async fn test_user_isolation() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_retry_and_cancel_task() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+    let other_user_id = db.create_user().await?;
+
+    let url = Url::parse("https://example.com/retry")?;
+    let task = bookmark_task::create(&db.pool, user_id, url, vec![]).await?;
+
+    // Pending tasks can't be retried, and other users can't touch the task
+    assert!(bookmark_task::retry(&db.pool, user_id, task.task_id)
+        .await?
+        .is_none());
+    assert!(bookmark_task::cancel(&db.pool, other_user_id, task.task_id)
+        .await?
+        .is_none());
+
+    lease_due(&db).await?;
+    bookmark_task::update(
+        &db.pool,
+        OWNER,
+        task.clone(),
+        BookmarkTaskStatus::Fail,
+        Some(3),
+        Some("Connection timeout".to_string()),
+    )
+    .await?;
+    let retried = bookmark_task::retry(&db.pool, user_id, task.task_id)
+        .await?
+        .expect("failed task is retried");
+    assert_eq!(retried.status, BookmarkTaskStatus::Pending);
+    assert_eq!(retried.retries, None);
+    assert_eq!(retried.fail_reason, None);
+
+    let cancelled = bookmark_task::cancel(&db.pool, user_id, task.task_id)
+        .await?
+        .expect("pending task is cancelled");
+    assert_eq!(cancelled.status, BookmarkTaskStatus::Cancelled);
+
+    // The daemon finishing a cancelled task doesn't resurrect it
+    bookmark_task::update(
+        &db.pool,
+        OWNER,
+        task.clone(),
+        BookmarkTaskStatus::Done,
+        None,
+        None,
+    )
+    .await?;
+    let stored = bookmark_task::get_by_id(&db.pool, user_id, task.task_id)
+        .await?
+        .expect("task exists");
+    assert_eq!(stored.status, BookmarkTaskStatus::Cancelled);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dead_letter_and_bulk_retry() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+
+    for (path, reason) in [
+        ("timeout-1", "Connection timeout"),
+        ("timeout-2", "Connection timeout"),
+        ("not-found", "HTTP 404"),
+    ] {
+        let url = Url::parse(&format!("https://example.com/{path}"))?;
+        let task = bookmark_task::create(&db.pool, user_id, url, vec![]).await?;
+        lease_due(&db).await?;
+        bookmark_task::update(
+            &db.pool,
+            OWNER,
+            task,
+            BookmarkTaskStatus::Fail,
+            None,
+            Some(reason.to_string()),
+        )
+        .await?;
+    }
+    let url = Url::parse("https://example.com/pending")?;
+    bookmark_task::create(&db.pool, user_id, url, vec![]).await?;
+
+    let dead_letter = bookmark_task::dead_letter(&db.pool, user_id).await?;
+    assert_eq!(dead_letter.total_count, 3);
+    assert_eq!(dead_letter.groups.len(), 2);
+    assert_eq!(dead_letter.groups[0].fail_reason, "Connection timeout");
+    assert_eq!(dead_letter.groups[0].task_count, 2);
+
+    let request = BulkRetryTasksRequest {
+        fail_reason: Some("Connection timeout".to_string()),
+        ..Default::default()
+    };
+    let requeued = bookmark_task::retry_matching(&db.pool, user_id, &request).await?;
+    assert_eq!(requeued.len(), 2);
+    assert!(requeued
+        .iter()
+        .all(|task| task.status == BookmarkTaskStatus::Pending && task.fail_reason.is_none()));
+
+    let dead_letter = bookmark_task::dead_letter(&db.pool, user_id).await?;
+    assert_eq!(dead_letter.total_count, 1);
+    assert_eq!(dead_letter.groups[0].fail_reason, "HTTP 404");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_schedule_retry_delays_delivery() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+
+    let url = Url::parse("https://example.com/backoff")?;
+    let task = bookmark_task::create(&db.pool, user_id, url, vec![]).await?;
+    let next_delivery = Utc::now() + Duration::minutes(30);
+    lease_due(&db).await?;
+    bookmark_task::schedule_retry(&db.pool, OWNER, &task, 2, next_delivery).await?;
+
+    assert!(
+        bookmark_task::lease(&db.pool, OWNER, Utc::now() + Duration::minutes(10), LEASE)
+            .await?
+            .is_empty()
+    );
+    let peeked = bookmark_task::lease(&db.pool, OWNER, next_delivery, LEASE).await?;
+    assert_eq!(peeked.len(), 1);
+    assert_eq!(peeked[0].retries, Some(2));
+    assert_eq!(peeked[0].status, BookmarkTaskStatus::Pending);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_with_robots_override() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+
+    let url = Url::parse("https://example.com/private")?;
+    let task =
+        bookmark_task::create_with_robots_override(&db.pool, user_id, url.clone(), vec![], true)
+            .await?;
+    assert!(task.ignore_robots);
+
+    let task = bookmark_task::create(&db.pool, user_id, url, vec![]).await?;
+    assert!(!task.ignore_robots);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_task_event_timeline() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+    let other_user_id = db.create_user().await?;
+
+    let url = Url::parse("https://example.com/timeline")?;
+    let task = bookmark_task::create(&db.pool, user_id, url, vec![]).await?;
+
+    task_event::insert(
+        &db.pool,
+        task.task_id,
+        TaskStage::Fetched,
+        Some("2048 bytes"),
+    )
+    .await?;
+    task_event::insert(
+        &db.pool,
+        task.task_id,
+        TaskStage::Readability,
+        Some("Title"),
+    )
+    .await?;
+
+    // Stages of the AI daemons reach the task only once it's linked to its bookmark
+    let recorded =
+        task_event::insert_for_bookmark(&db.pool, user_id, "bookmark-1", TaskStage::Tagged, None)
+            .await?;
+    assert_eq!(recorded, 0);
+    bookmark_task::set_bookmark_id(&db.pool, task.task_id, "bookmark-1").await?;
+    let recorded =
+        task_event::insert_for_bookmark(&db.pool, user_id, "bookmark-1", TaskStage::Tagged, None)
+            .await?;
+    assert_eq!(recorded, 1);
+
+    let events = task_event::list(&db.pool, user_id, task.task_id).await?;
+    let stages: Vec<TaskStage> = events.iter().map(|event| event.stage).collect();
+    assert_eq!(
+        stages,
+        vec![
+            TaskStage::Fetched,
+            TaskStage::Readability,
+            TaskStage::Tagged
+        ]
+    );
+    assert_eq!(events[0].detail.as_deref(), Some("2048 bytes"));
+    assert!(events.iter().all(|event| event.task_id == task.task_id));
+
+    assert!(task_event::list(&db.pool, other_user_id, task.task_id)
+        .await?
+        .is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_expired_lease_is_taken_over() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+
+    let url = Url::parse("https://example.com/lease")?;
+    let task = bookmark_task::create(&db.pool, user_id, url, vec![]).await?;
+    let now = Utc::now() + Duration::seconds(1);
+
+    let leased = bookmark_task::lease(&db.pool, OWNER, now, LEASE).await?;
+    assert_eq!(leased.len(), 1);
+    assert!(bookmark_task::lease(&db.pool, OTHER_OWNER, now, LEASE)
+        .await?
+        .is_empty());
+
+    // The first replica stopped heartbeating, the second one takes over
+    let later = now + LEASE + Duration::seconds(1);
+    let taken = bookmark_task::lease(&db.pool, OTHER_OWNER, later, LEASE).await?;
+    assert_eq!(taken.len(), 1);
+    assert_eq!(
+        bookmark_task::renew_leases(&db.pool, OWNER, &[task.task_id], later + LEASE).await?,
+        0
+    );
+
+    // Only the current owner records the outcome
+    assert!(
+        !bookmark_task::update(
+            &db.pool,
+            OWNER,
+            task.clone(),
+            BookmarkTaskStatus::Fail,
+            None,
+            None
+        )
+        .await?
+    );
+    assert!(
+        bookmark_task::update(
+            &db.pool,
+            OTHER_OWNER,
+            task.clone(),
+            BookmarkTaskStatus::Done,
+            None,
+            None
+        )
+        .await?
+    );
+    let stored = bookmark_task::get_by_id(&db.pool, user_id, task.task_id)
+        .await?
+        .expect("task exists");
+    assert_eq!(stored.status, BookmarkTaskStatus::Done);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_expired_owner_cannot_overwrite_finished_task() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+
+    let url = Url::parse("https://example.com/late")?;
+    let task = bookmark_task::create(&db.pool, user_id, url, vec![]).await?;
+    let now = Utc::now() + Duration::seconds(1);
+    assert_eq!(
+        bookmark_task::lease(&db.pool, OWNER, now, LEASE)
+            .await?
+            .len(),
+        1
+    );
+    let later = now + LEASE + Duration::seconds(1);
+    assert_eq!(
+        bookmark_task::lease(&db.pool, OTHER_OWNER, later, LEASE)
+            .await?
+            .len(),
+        1
+    );
+
+    // The new owner finishes the task, which releases the lease
+    assert!(
+        bookmark_task::update(
+            &db.pool,
+            OTHER_OWNER,
+            task.clone(),
+            BookmarkTaskStatus::Done,
+            None,
+            None
+        )
+        .await?
+    );
+
+    // The first owner's outcome arrives late and is dropped
+    assert!(
+        !bookmark_task::update(
+            &db.pool,
+            OWNER,
+            task.clone(),
+            BookmarkTaskStatus::Fail,
+            Some(1),
+            Some("Connection timeout".to_string())
+        )
+        .await?
+    );
+    assert!(!bookmark_task::schedule_retry(&db.pool, OWNER, &task, 1, later).await?);
+    let stored = bookmark_task::get_by_id(&db.pool, user_id, task.task_id)
+        .await?
+        .expect("task exists");
+    assert_eq!(stored.status, BookmarkTaskStatus::Done);
+    assert_eq!(stored.fail_reason, None);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_renewed_lease_is_kept() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+
+    let url = Url::parse("https://example.com/heartbeat")?;
+    let task = bookmark_task::create(&db.pool, user_id, url, vec![]).await?;
+    let now = Utc::now() + Duration::seconds(1);
+
+    bookmark_task::lease(&db.pool, OWNER, now, LEASE).await?;
+    let heartbeat = now + Duration::minutes(4);
+    assert_eq!(
+        bookmark_task::renew_leases(&db.pool, OWNER, &[task.task_id], heartbeat + LEASE).await?,
+        1
+    );
+    assert!(
+        bookmark_task::lease(&db.pool, OTHER_OWNER, now + Duration::minutes(6), LEASE)
+            .await?
+            .is_empty()
+    );
+
+    // A retry releases the lease
+    assert!(bookmark_task::schedule_retry(&db.pool, OWNER, &task, 1, heartbeat).await?);
+    let leased = bookmark_task::lease(&db.pool, OTHER_OWNER, heartbeat, LEASE).await?;
+    assert_eq!(leased.len(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_queued_work_notifies_listeners() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+
+    let mut config = tokio_postgres::Config::new();
+    config
+        .host(&db.host)
+        .port(db.port)
+        .dbname(&db.db_name)
+        .user("postgres");
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let listener = tokio::spawn(async move {
+        notify::listen(
+            &config,
+            &[notify::TASK_QUEUED, notify::BOOKMARK_SAVED],
+            |channel, _| {
+                let _ = tx.send(channel.to_string());
+            },
+        )
+        .await
+    });
+    let mut next = async || {
+        tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+            .await
+            .ok()
+            .flatten()
+    };
+
+    // Catching up once listening
+    assert_eq!(next().await.as_deref(), Some(notify::TASK_QUEUED));
+    assert_eq!(next().await.as_deref(), Some(notify::BOOKMARK_SAVED));
+
+    let url = Url::parse("https://example.com/notify")?;
+    bookmark_task::create(&db.pool, user_id, url, vec![]).await?;
+    assert_eq!(next().await.as_deref(), Some(notify::TASK_QUEUED));
+
+    let saved = create_test_bookmark(
+        user_id,
+        "https://example.com/notify",
+        "Notify",
+        "example.com",
+        None,
+    );
+    bookmark::save(&db.pool, &saved, "text").await?;
+    assert_eq!(next().await.as_deref(), Some(notify::BOOKMARK_SAVED));
+
+    listener.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_events_are_relayed_to_other_replicas() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+    let (replica_a, mut relay_a) = EventBus::relayed();
+    let (replica_b, _relay_b) = EventBus::relayed();
+    let mut stream = Box::pin(replica_b.subscribe_user(user_id));
+
+    let mut config = tokio_postgres::Config::new();
+    config
+        .host(&db.host)
+        .port(db.port)
+        .dbname(&db.db_name)
+        .user("postgres");
+    let (listening_tx, mut listening_rx) = tokio::sync::mpsc::unbounded_channel();
+    let listener = tokio::spawn(async move {
+        notify::listen(
+            &config,
+            &[notify::SERVER_EVENT],
+            |_, payload| match payload {
+                Some(payload) => replica_b.publish_relayed(payload),
+                None => {
+                    let _ = listening_tx.send(());
+                }
+            },
+        )
+        .await
+    });
+    listening_rx.recv().await.expect("listening");
+
+    let url = Url::parse("https://example.com/relay")?;
+    let task = bookmark_task::create(&db.pool, user_id, url, vec![]).await?;
+    let event = ServerEvent::TaskUpdated { task };
+    replica_a.publish(user_id, event.clone());
+    let payload = relay_a.recv().await.expect("event relayed");
+    notify::notify(&db.pool, notify::SERVER_EVENT, &payload).await?;
+
+    let received = tokio::time::timeout(std::time::Duration::from_secs(5), stream.next()).await?;
+    assert_eq!(received, Some(event));
+
+    listener.abort();
+    Ok(())
+}
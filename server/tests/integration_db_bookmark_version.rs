@@ -0,0 +1,90 @@
+#![cfg(feature = "integration-tests")]
+
+mod common;
+
+use common::test_db::{create_test_bookmark, create_test_user, TestDatabase};
+use server::db::{bookmark, bookmark_version};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn test_refreshed_capture_keeps_the_previous_ones_as_versions() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+    let other_user_id = create_test_user(&db).await?;
+    let saved = create_test_bookmark(
+        user_id,
+        "https://example.com/page",
+        "Old title",
+        "example.com",
+        None,
+    );
+    let saved = bookmark::save(&db.pool, &saved, "Old text").await?;
+    assert!(
+        bookmark_version::list(&db.pool, user_id, &saved.bookmark_id)
+            .await?
+            .is_empty()
+    );
+
+    let mut refreshed = saved.clone();
+    refreshed.title = "Middle title".into();
+    bookmark::refresh_capture(&db.pool, &refreshed, "Middle text", Some("<p>Old text</p>")).await?;
+    refreshed.title = "New title".into();
+    let updated = bookmark::refresh_capture(&db.pool, &refreshed, "New text", None).await?;
+    assert_eq!(updated.title, "New title");
+
+    let versions = bookmark_version::list(&db.pool, user_id, &saved.bookmark_id).await?;
+    assert_eq!(
+        versions
+            .iter()
+            .map(|v| v.title.as_str())
+            .collect::<Vec<_>>(),
+        vec!["Middle title", "Old title"]
+    );
+    assert!(versions[0].captured_at > versions[1].captured_at);
+    let version = bookmark_version::get(
+        &db.pool,
+        user_id,
+        &saved.bookmark_id,
+        versions[1].version_id,
+    )
+    .await?
+    .expect("version");
+    assert_eq!(version.text_content, "Old text");
+    assert_eq!(version.html.as_deref(), Some("<p>Old text</p>"));
+    let version = bookmark_version::get(
+        &db.pool,
+        user_id,
+        &saved.bookmark_id,
+        versions[0].version_id,
+    )
+    .await?
+    .expect("version");
+    assert_eq!(version.text_content, "Middle text");
+    assert_eq!(version.html, None);
+    assert_eq!(
+        bookmark::get_text_content(&db.pool, user_id, &saved.bookmark_id).await?,
+        Some("New text".into())
+    );
+
+    // Versions stay with the user the bookmark belongs to
+    assert!(
+        bookmark_version::list(&db.pool, other_user_id, &saved.bookmark_id)
+            .await?
+            .is_empty()
+    );
+    assert!(bookmark_version::get(
+        &db.pool,
+        other_user_id,
+        &saved.bookmark_id,
+        versions[0].version_id,
+    )
+    .await?
+    .is_none());
+    assert!(
+        bookmark_version::get(&db.pool, user_id, &saved.bookmark_id, Uuid::new_v4())
+            .await?
+            .is_none()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+#![cfg(feature = "integration-tests")]
+
+mod common;
+
+use chrono::{Duration, Utc};
+use common::test_db::{create_test_bookmark, TestDatabase};
+use server::db::{bookmark, digest, user_settings};
+use shared::UserSettings;
+
+#[tokio::test]
+async fn test_digest_written_once_per_user_and_period() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = db.create_user().await?;
+    let opted_out_user_id = db.create_user().await?;
+
+    for (user_id, url) in [
+        (user_id, "https://example.com/digest-1"),
+        (user_id, "https://example.com/digest-2"),
+        (opted_out_user_id, "https://example.com/digest-3"),
+    ] {
+        let bookmark = create_test_bookmark(user_id, url, "Digest", "example.com", None);
+        bookmark::save(&db.pool, &bookmark, "Some text content").await?;
+    }
+    user_settings::upsert(
+        &db.pool,
+        opted_out_user_id,
+        &UserSettings {
+            digest_enabled: false,
+            ..UserSettings::default()
+        },
+    )
+    .await?;
+
+    let period_start = Utc::now() - Duration::hours(1);
+    let period_end = Utc::now() + Duration::hours(1);
+    assert_eq!(
+        digest::users_due(&db.pool, period_start, period_end).await?,
+        vec![user_id]
+    );
+
+    let bookmarks =
+        digest::bookmarks_in_period(&db.pool, user_id, period_start, period_end, 10).await?;
+    assert_eq!(bookmarks.len(), 2);
+    assert_eq!(bookmarks[0].url, "https://example.com/digest-1");
+
+    let bookmark_ids: Vec<String> = bookmarks
+        .iter()
+        .map(|bookmark| bookmark.bookmark_id.clone())
+        .collect();
+    let stored = digest::insert(
+        &db.pool,
+        user_id,
+        period_start,
+        period_end,
+        &bookmark_ids,
+        "Two articles about digests.",
+    )
+    .await?
+    .expect("digest stored");
+    assert_eq!(stored.bookmark_ids, bookmark_ids);
+
+    let duplicate = digest::insert(
+        &db.pool,
+        user_id,
+        period_start,
+        period_end,
+        &bookmark_ids,
+        "Another take",
+    )
+    .await?;
+    assert!(duplicate.is_none());
+    assert!(digest::users_due(&db.pool, period_start, period_end)
+        .await?
+        .is_empty());
+
+    digest::mark_emailed(&db.pool, user_id, stored.digest_id).await?;
+    assert_eq!(
+        digest::list(&db.pool, user_id, 10).await?,
+        vec![stored.clone()]
+    );
+    assert_eq!(
+        digest::get(&db.pool, user_id, stored.digest_id).await?,
+        Some(stored.clone())
+    );
+    assert_eq!(
+        digest::get(&db.pool, opted_out_user_id, stored.digest_id).await?,
+        None
+    );
+
+    Ok(())
+}
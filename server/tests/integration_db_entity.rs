@@ -0,0 +1,105 @@
+#![cfg(feature = "integration-tests")]
+
+mod common;
+
+use common::test_db::{create_test_bookmark, create_test_user, TestDatabase};
+use server::db::{bookmark, entity, search};
+use shared::{Entity, EntityKind, SearchRequest};
+
+fn entity(kind: EntityKind, name: &str) -> Entity {
+    Entity {
+        kind,
+        name: name.to_string(),
+    }
+}
+
+fn entity_search(entities: &[&str]) -> SearchRequest {
+    SearchRequest {
+        query: None,
+        tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: entities.iter().map(|name| name.to_string()).collect(),
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
+        limit: None,
+        offset: None,
+    }
+}
+
+#[tokio::test]
+async fn test_entities_are_listed_and_searched() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+    let other_user_id = create_test_user(&db).await?;
+
+    let tokio = entity(EntityKind::Project, "Tokio");
+    let mut bookmark_ids = vec![];
+    for (url, entities) in [
+        (
+            "https://example.com/runtime",
+            vec![tokio.clone(), entity(EntityKind::Person, "Carl Lerche")],
+        ),
+        (
+            "https://example.com/channels",
+            vec![tokio.clone(), entity(EntityKind::Project, "Mio")],
+        ),
+        (
+            "https://example.com/kernel",
+            vec![entity(EntityKind::Organization, "Linux Foundation")],
+        ),
+    ] {
+        let saved = create_test_bookmark(user_id, url, url, "example.com", None);
+        bookmark::save(&db.pool, &saved, "content").await?;
+        entity::replace_for_bookmark(&db.pool, user_id, &saved.bookmark_id, &entities).await?;
+        bookmark_ids.push(saved.bookmark_id);
+    }
+    // Replacing drops what the previous analysis found
+    entity::replace_for_bookmark(
+        &db.pool,
+        user_id,
+        &bookmark_ids[1],
+        std::slice::from_ref(&tokio),
+    )
+    .await?;
+
+    let entities = entity::list(&db.pool, user_id, None, None, 10).await?;
+    assert_eq!(
+        entities
+            .iter()
+            .map(|e| (e.name.as_str(), e.count))
+            .collect::<Vec<_>>(),
+        vec![("Tokio", 2), ("Carl Lerche", 1), ("Linux Foundation", 1)]
+    );
+    assert_eq!(entities[0].kind, EntityKind::Project);
+    let entities = entity::list(&db.pool, user_id, Some(EntityKind::Person), None, 10).await?;
+    assert_eq!(entities.len(), 1);
+    assert_eq!(entities[0].name, "Carl Lerche");
+    let entities = entity::list(&db.pool, user_id, None, Some("FOUND"), 10).await?;
+    assert_eq!(entities.len(), 1);
+    assert_eq!(entities[0].name, "Linux Foundation");
+    assert_eq!(
+        entity::list(&db.pool, user_id, None, None, 1).await?.len(),
+        1
+    );
+    assert!(entity::list(&db.pool, other_user_id, None, None, 10)
+        .await?
+        .is_empty());
+
+    let response = search::search(&db.pool, user_id, &entity_search(&["tokio"])).await?;
+    assert_eq!(response.total, 2);
+    let response =
+        search::search(&db.pool, user_id, &entity_search(&["tokio", "carl lerche"])).await?;
+    assert_eq!(response.total, 1);
+    assert_eq!(response.items[0].bookmark.bookmark_id, bookmark_ids[0]);
+    let response = search::search(&db.pool, other_user_id, &entity_search(&["tokio"])).await?;
+    assert_eq!(response.total, 0);
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+#![cfg(feature = "integration-tests")]
+
+mod common;
+
+use common::test_db::{create_test_bookmark, create_test_user, TestDatabase};
+use server::db::{bookmark, entity, graph, link};
+use shared::{Entity, EntityKind};
+use url::Url;
+
+#[tokio::test]
+async fn test_graph_loads_entities_and_links_of_recent_bookmarks() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+    let other_user_id = create_test_user(&db).await?;
+
+    let first = create_test_bookmark(user_id, "https://one.com/first", "First", "one.com", None);
+    bookmark::save(&db.pool, &first, "content").await?;
+    let second = create_test_bookmark(user_id, "https://two.com/second", "Second", "two.com", None);
+    bookmark::save(&db.pool, &second, "content").await?;
+    let tokio = Entity {
+        kind: EntityKind::Project,
+        name: "Tokio".to_string(),
+    };
+    entity::replace_for_bookmark(
+        &db.pool,
+        user_id,
+        &first.bookmark_id,
+        std::slice::from_ref(&tokio),
+    )
+    .await?;
+    let links = vec![
+        Url::parse("https://two.com/second")?,
+        Url::parse("https://three.com/")?,
+    ];
+    link::replace_for_bookmark(&db.pool, user_id, &first.bookmark_id, &links).await?;
+    // Replacing drops the links of the previous extraction
+    link::replace_for_bookmark(&db.pool, user_id, &first.bookmark_id, &links[..1]).await?;
+
+    let data = graph::load(&db.pool, user_id, 10).await?;
+    assert_eq!(
+        data.bookmarks
+            .iter()
+            .map(|b| b.bookmark_id.as_str())
+            .collect::<Vec<_>>(),
+        vec![second.bookmark_id.as_str(), first.bookmark_id.as_str()]
+    );
+    assert_eq!(data.bookmarks[1].title, "First");
+    assert_eq!(data.bookmarks[1].domain, "one.com");
+    assert_eq!(data.entities, vec![(first.bookmark_id.clone(), tokio)]);
+    assert_eq!(data.links.len(), 1);
+    assert_eq!(data.links[0].bookmark_id, first.bookmark_id);
+    assert_eq!(data.links[0].url, "https://two.com/second");
+    assert_eq!(data.links[0].domain, "two.com");
+
+    // Only the entities and links of the loaded bookmarks come along
+    let data = graph::load(&db.pool, user_id, 1).await?;
+    assert_eq!(data.bookmarks.len(), 1);
+    assert_eq!(data.bookmarks[0].bookmark_id, second.bookmark_id);
+    assert!(data.entities.is_empty());
+    assert!(data.links.is_empty());
+
+    let data = graph::load(&db.pool, other_user_id, 10).await?;
+    assert!(data.bookmarks.is_empty());
+
+    Ok(())
+}
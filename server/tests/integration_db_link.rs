@@ -0,0 +1,66 @@
+#![cfg(feature = "integration-tests")]
+
+mod common;
+
+use common::test_db::{create_test_bookmark, create_test_user, TestDatabase};
+use server::db::{bookmark, link};
+use url::Url;
+
+#[tokio::test]
+async fn test_links_resolve_to_saved_bookmarks_and_backlinks() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+    let other_user_id = create_test_user(&db).await?;
+
+    let mut saved = vec![];
+    for url in [
+        "https://example.com/target",
+        "https://example.com/citing",
+        "https://example.com/unrelated",
+    ] {
+        let bookmark = create_test_bookmark(user_id, url, url, "example.com", None);
+        saved.push(bookmark::save(&db.pool, &bookmark, "content").await?);
+    }
+    // The same page saved by someone else isn't linked to
+    let elsewhere = create_test_bookmark(
+        other_user_id,
+        "https://elsewhere.org/page",
+        "Page",
+        "elsewhere.org",
+        None,
+    );
+    bookmark::save(&db.pool, &elsewhere, "content").await?;
+    let links = vec![
+        Url::parse("https://example.com/target")?,
+        Url::parse("https://elsewhere.org/page")?,
+    ];
+    link::replace_for_bookmark(&db.pool, user_id, &saved[1].bookmark_id, &links).await?;
+
+    let outbound = link::list_outbound(&db.pool, user_id, &saved[1].bookmark_id).await?;
+    assert_eq!(outbound.len(), 2);
+    assert_eq!(outbound[0].domain, "elsewhere.org");
+    assert_eq!(outbound[0].bookmark_id, None);
+    assert_eq!(outbound[1].url, "https://example.com/target");
+    assert_eq!(outbound[1].bookmark_id, Some(saved[0].bookmark_id.clone()));
+    assert!(
+        link::list_outbound(&db.pool, other_user_id, &saved[1].bookmark_id)
+            .await?
+            .is_empty()
+    );
+
+    let backlinks = bookmark::get_backlinks(&db.pool, user_id, &saved[0].bookmark_id).await?;
+    assert_eq!(backlinks.len(), 1);
+    assert_eq!(backlinks[0].bookmark_id, saved[1].bookmark_id);
+    assert!(
+        bookmark::get_backlinks(&db.pool, user_id, &saved[2].bookmark_id)
+            .await?
+            .is_empty()
+    );
+
+    // A trashed target is no longer resolved
+    assert!(bookmark::move_to_trash(&db.pool, user_id, &saved[0].bookmark_id).await?);
+    let outbound = link::list_outbound(&db.pool, user_id, &saved[1].bookmark_id).await?;
+    assert_eq!(outbound[1].bookmark_id, None);
+
+    Ok(())
+}
@@ -0,0 +1,53 @@
+#![cfg(feature = "integration-tests")]
+
+mod common;
+
+use chrono::Utc;
+use common::test_db::TestDatabase;
+use server::db::llm_usage::{self, NewLlmUsage};
+use uuid::Uuid;
+
+fn usage(user_id: Option<Uuid>, purpose: &str, success: bool) -> NewLlmUsage {
+    NewLlmUsage {
+        user_id,
+        bookmark_id: None,
+        session_id: None,
+        purpose: purpose.to_string(),
+        request_kind: "text",
+        provider: "ollama".to_string(),
+        model: "qwen3.5:4b".to_string(),
+        input_tokens: Some(100),
+        output_tokens: success.then_some(20),
+        duration_ms: 500,
+        success,
+    }
+}
+
+#[tokio::test]
+async fn test_llm_usage_summarized_per_day_and_purpose() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = db.create_user().await?;
+    let other_user_id = db.create_user().await?;
+
+    llm_usage::insert(&db.pool, &usage(Some(user_id), "analyze_chunk", true)).await?;
+    llm_usage::insert(&db.pool, &usage(Some(user_id), "analyze_chunk", false)).await?;
+    llm_usage::insert(&db.pool, &usage(Some(user_id), "consolidate_tags", true)).await?;
+    llm_usage::insert(&db.pool, &usage(Some(other_user_id), "analyze_chunk", true)).await?;
+    llm_usage::insert(&db.pool, &usage(None, "analyze_chunk", true)).await?;
+
+    let today = Utc::now().date_naive();
+    let days = llm_usage::summarize_by_day(&db.pool, user_id, today).await?;
+    assert_eq!(days.len(), 2);
+
+    let analyze = &days[0];
+    assert_eq!(analyze.day, today);
+    assert_eq!(analyze.purpose, "analyze_chunk");
+    assert_eq!(analyze.calls, 2);
+    assert_eq!(analyze.failed_calls, 1);
+    assert_eq!(analyze.input_tokens, 200);
+    assert_eq!(analyze.output_tokens, 20);
+    assert_eq!(analyze.duration_ms, 1000);
+    assert_eq!(days[1].purpose, "consolidate_tags");
+
+    Ok(())
+}
@@ -2,12 +2,14 @@
 
 mod common;
 
+use chrono::{Days, Utc};
 use common::test_db::{create_test_bookmark, TestDatabase};
 use server::db::{bookmark, chunks, rag, EmbeddingProfile};
-use shared::RagHistoryRequest;
+use shared::{RagFeedbackRequest, RagHistoryRequest, RagRating, TagFilter};
 use uuid::Uuid;
 
 const TEST_EMBEDDING_DIMENSIONS: usize = 16;
+const TEST_EF_SEARCH: u32 = 100;
 
 #[tokio::test]
 async fn test_rag_session_create_and_retrieve() -> anyhow::Result<()> {
@@ -136,6 +138,7 @@ async fn test_rag_history_retrieval() -> anyhow::Result<()> {
     let request = RagHistoryRequest {
         limit: Some(10),
         offset: None,
+        query: None,
     };
     let history = rag::get_rag_history(&db.pool, user_id, &request).await?;
 
@@ -174,6 +177,7 @@ async fn test_rag_history_pagination() -> anyhow::Result<()> {
     let request = RagHistoryRequest {
         limit: Some(2),
         offset: None,
+        query: None,
     };
     let page1 = rag::get_rag_history(&db.pool, user_id, &request).await?;
     assert_eq!(page1.sessions.len(), 2);
@@ -183,6 +187,7 @@ async fn test_rag_history_pagination() -> anyhow::Result<()> {
     let request = RagHistoryRequest {
         limit: Some(2),
         offset: Some(2),
+        query: None,
     };
     let page2 = rag::get_rag_history(&db.pool, user_id, &request).await?;
     assert_eq!(page2.sessions.len(), 2);
@@ -192,6 +197,7 @@ async fn test_rag_history_pagination() -> anyhow::Result<()> {
     let request = RagHistoryRequest {
         limit: Some(2),
         offset: Some(4),
+        query: None,
     };
     let page3 = rag::get_rag_history(&db.pool, user_id, &request).await?;
     assert_eq!(page3.sessions.len(), 1); // Only 1 remaining
@@ -228,6 +234,7 @@ async fn test_rag_history_user_isolation() -> anyhow::Result<()> {
     let request = RagHistoryRequest {
         limit: Some(10),
         offset: None,
+        query: None,
     };
     let user1_history = rag::get_rag_history(&db.pool, user1_id, &request).await?;
     assert_eq!(user1_history.total_count, 2);
@@ -553,8 +560,10 @@ async fn test_search_similar_chunks_with_dynamic_dimensions() -> anyhow::Result<
         user_id,
         vec![0.5; TEST_EMBEDDING_DIMENSIONS],
         TEST_EMBEDDING_DIMENSIONS,
+        TEST_EF_SEARCH,
         5,
         0.99,
+        chunks::ChunkScope::default(),
     )
     .await?;
 
@@ -564,6 +573,333 @@ async fn test_search_similar_chunks_with_dynamic_dimensions() -> anyhow::Result<
     Ok(())
 }
 
+#[tokio::test]
+async fn test_embedding_cache_per_user_and_profile() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = db.create_user().await?;
+    let other_user_id = db.create_user().await?;
+    let profile = EmbeddingProfile {
+        provider: "gemini".to_string(),
+        model: "gemini-embedding-001".to_string(),
+        dimensions: TEST_EMBEDDING_DIMENSIONS,
+    };
+    let hashes = vec!["hash-a".to_string(), "hash-b".to_string()];
+
+    chunks::cache_embeddings(
+        &db.pool,
+        user_id,
+        &profile,
+        vec![("hash-a".to_string(), vec![0.5; TEST_EMBEDDING_DIMENSIONS])],
+    )
+    .await?;
+    chunks::cache_embeddings(
+        &db.pool,
+        user_id,
+        &profile,
+        vec![("hash-a".to_string(), vec![0.1; TEST_EMBEDDING_DIMENSIONS])],
+    )
+    .await?;
+    let cached = chunks::get_cached_embeddings(&db.pool, user_id, &profile, &hashes).await?;
+    assert_eq!(cached.len(), 1);
+    assert_eq!(cached["hash-a"], vec![0.5; TEST_EMBEDDING_DIMENSIONS]);
+    assert!(
+        chunks::get_cached_embeddings(&db.pool, other_user_id, &profile, &hashes)
+            .await?
+            .is_empty()
+    );
+
+    let other = EmbeddingProfile {
+        dimensions: 8,
+        ..profile.clone()
+    };
+    assert!(
+        chunks::get_cached_embeddings(&db.pool, user_id, &other, &hashes)
+            .await?
+            .is_empty()
+    );
+
+    let past = Utc::now() - Days::new(1);
+    assert_eq!(
+        chunks::delete_cached_embeddings_before(&db.pool, past, 10).await?,
+        0
+    );
+    let future = Utc::now() + Days::new(1);
+    assert_eq!(
+        chunks::delete_cached_embeddings_before(&db.pool, future, 10).await?,
+        1
+    );
+
+    Ok(())
+}
+
+/// Deterministic pseudo-random unit vectors, so the benchmark is repeatable.
+fn benchmark_embeddings(count: usize, seed: u64) -> Vec<Vec<f32>> {
+    let mut state = seed;
+    (0..count)
+        .map(|_| {
+            let vector: Vec<f32> = (0..TEST_EMBEDDING_DIMENSIONS)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    (state % 2000) as f32 / 1000.0 - 1.0
+                })
+                .collect();
+            let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+            vector.into_iter().map(|x| x / norm).collect()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_search_similar_chunks_hnsw_benchmark() -> anyhow::Result<()> {
+    const CHUNKS: usize = 5_000;
+    const QUERIES: usize = 20;
+    const TOP: usize = 10;
+
+    let db = TestDatabase::new().await?;
+    let user_id = db.create_user().await?;
+    let bookmark = create_test_bookmark(
+        user_id,
+        "https://example.com/ann-benchmark",
+        "ANN Benchmark",
+        "example.com",
+        None,
+    );
+    bookmark::save(&db.pool, &bookmark, "benchmark").await?;
+    server::db::reconcile_embedding_profile(
+        &db.pool,
+        &EmbeddingProfile {
+            provider: "gemini".to_string(),
+            model: "gemini-embedding-001".to_string(),
+            dimensions: TEST_EMBEDDING_DIMENSIONS,
+        },
+    )
+    .await?;
+
+    let embeddings = benchmark_embeddings(CHUNKS, 0x9E37_79B9_7F4A_7C15);
+    let texts = (0..CHUNKS).map(|i| format!("chunk {i}")).collect();
+    chunks::store_chunks_with_embeddings(
+        &db.pool,
+        &bookmark.bookmark_id,
+        user_id,
+        texts,
+        embeddings.clone(),
+    )
+    .await?;
+    if let server::db::Database::Postgres(pool) = &db.pool {
+        pool.get()
+            .await?
+            .batch_execute("ANALYZE bookmark_chunk")
+            .await?;
+    }
+
+    let queries = benchmark_embeddings(QUERIES, 0xD1B5_4A32_D192_ED03);
+    let mut recall = Vec::new();
+    for ef_search in [10, TEST_EF_SEARCH, 400] {
+        let start = std::time::Instant::now();
+        let mut found = 0;
+        for query in &queries {
+            // Exact neighbours by brute force, chunk texts carry their index
+            let mut exact: Vec<(f32, usize)> = embeddings
+                .iter()
+                .enumerate()
+                .map(|(i, e)| (e.iter().zip(query).map(|(a, b)| a * b).sum(), i))
+                .collect();
+            exact.sort_by(|a, b| b.0.total_cmp(&a.0));
+            let exact: Vec<String> = exact[..TOP]
+                .iter()
+                .map(|(_, i)| format!("chunk {i}"))
+                .collect();
+
+            let matches = chunks::search_similar_chunks(
+                &db.pool,
+                user_id,
+                query.clone(),
+                TEST_EMBEDDING_DIMENSIONS,
+                ef_search,
+                TOP,
+                -1.0,
+                chunks::ChunkScope::default(),
+            )
+            .await?;
+            found += matches
+                .iter()
+                .filter(|m| exact.contains(&m.chunk.chunk_text))
+                .count();
+        }
+        let elapsed = start.elapsed();
+        let ef_recall = found as f64 / (QUERIES * TOP) as f64;
+        println!(
+            "ef_search={ef_search}: recall@{TOP}={ef_recall:.3}, {:?} per query",
+            elapsed / QUERIES as u32
+        );
+        recall.push(ef_recall);
+    }
+
+    // A wider search never finds fewer of the exact neighbours
+    assert!(recall.windows(2).all(|pair| pair[1] + 0.05 >= pair[0]));
+    assert!(recall[recall.len() - 1] >= 0.9, "recall {recall:?}");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_search_similar_chunks_scoped_to_selection() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = db.create_user().await?;
+
+    server::db::reconcile_embedding_profile(
+        &db.pool,
+        &EmbeddingProfile {
+            provider: "gemini".to_string(),
+            model: "gemini-embedding-001".to_string(),
+            dimensions: TEST_EMBEDDING_DIMENSIONS,
+        },
+    )
+    .await?;
+
+    let rust_bookmark = create_test_bookmark(
+        user_id,
+        "https://example.com/rust",
+        "Rust",
+        "example.com",
+        Some(vec!["rust".to_string()]),
+    );
+    let go_bookmark = create_test_bookmark(
+        user_id,
+        "https://example.com/go",
+        "Go",
+        "example.com",
+        Some(vec!["go".to_string()]),
+    );
+    for (bookmark_data, chunk_text) in [(&rust_bookmark, "Rust chunk"), (&go_bookmark, "Go chunk")]
+    {
+        bookmark::save(&db.pool, bookmark_data, &"A".repeat(400)).await?;
+        chunks::store_chunks_with_embeddings(
+            &db.pool,
+            &bookmark_data.bookmark_id,
+            user_id,
+            vec![chunk_text.to_string()],
+            vec![vec![0.5; TEST_EMBEDDING_DIMENSIONS]],
+        )
+        .await?;
+    }
+
+    let tags_filter = TagFilter::Or(vec!["rust".to_string()]);
+    let by_tag = chunks::search_similar_chunks(
+        &db.pool,
+        user_id,
+        vec![0.5; TEST_EMBEDDING_DIMENSIONS],
+        TEST_EMBEDDING_DIMENSIONS,
+        TEST_EF_SEARCH,
+        5,
+        0.5,
+        chunks::ChunkScope {
+            tags_filter: Some(&tags_filter),
+            bookmark_ids: None,
+            workspace_id: None,
+        },
+    )
+    .await?;
+    assert_eq!(by_tag.len(), 1);
+    assert_eq!(by_tag[0].chunk.chunk_text, "Rust chunk");
+
+    let bookmark_ids = vec![go_bookmark.bookmark_id.clone()];
+    let by_id = chunks::search_similar_chunks(
+        &db.pool,
+        user_id,
+        vec![0.5; TEST_EMBEDDING_DIMENSIONS],
+        TEST_EMBEDDING_DIMENSIONS,
+        TEST_EF_SEARCH,
+        5,
+        0.5,
+        chunks::ChunkScope {
+            tags_filter: None,
+            bookmark_ids: Some(&bookmark_ids),
+            workspace_id: None,
+        },
+    )
+    .await?;
+    assert_eq!(by_id.len(), 1);
+    assert_eq!(by_id[0].chunk.chunk_text, "Go chunk");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_search_chunks_hybrid_unions_full_text_matches() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = db.create_user().await?;
+
+    server::db::reconcile_embedding_profile(
+        &db.pool,
+        &EmbeddingProfile {
+            provider: "gemini".to_string(),
+            model: "gemini-embedding-001".to_string(),
+            dimensions: TEST_EMBEDDING_DIMENSIONS,
+        },
+    )
+    .await?;
+
+    let bookmark_data = create_test_bookmark(
+        user_id,
+        "https://example.com/borrowck",
+        "Borrow checker",
+        "example.com",
+        None,
+    );
+    bookmark::save(&db.pool, &bookmark_data, &"A".repeat(400)).await?;
+
+    // The second embedding is orthogonal to the query, so only full-text
+    // search can find that chunk
+    let orthogonal: Vec<f32> = (0..TEST_EMBEDDING_DIMENSIONS)
+        .map(|i| if i % 2 == 0 { 0.5 } else { -0.5 })
+        .collect();
+    chunks::store_chunks_with_embeddings(
+        &db.pool,
+        &bookmark_data.bookmark_id,
+        user_id,
+        vec![
+            "Ownership and lifetimes".to_string(),
+            "The borrowck rejects aliasing mutable references".to_string(),
+        ],
+        vec![vec![0.5; TEST_EMBEDDING_DIMENSIONS], orthogonal],
+    )
+    .await?;
+
+    let matches = chunks::search_chunks_hybrid(
+        &db.pool,
+        user_id,
+        "borrowck",
+        vec![0.5; TEST_EMBEDDING_DIMENSIONS],
+        TEST_EMBEDDING_DIMENSIONS,
+        TEST_EF_SEARCH,
+        5,
+        0.5,
+        chunks::ChunkScope::default(),
+    )
+    .await?;
+    assert_eq!(matches.len(), 2);
+
+    let vector_match = matches
+        .iter()
+        .find(|m| m.chunk.chunk_index == 0)
+        .expect("vector match");
+    assert_eq!(vector_match.vector_rank, Some(1));
+    assert_eq!(vector_match.fts_rank, None);
+
+    let fts_match = matches
+        .iter()
+        .find(|m| m.chunk.chunk_index == 1)
+        .expect("full-text match");
+    assert_eq!(fts_match.vector_rank, None);
+    assert_eq!(fts_match.fts_rank, Some(1));
+    assert!(fts_match.fts_score > 0.0);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_get_bookmarks_without_chunks() -> anyhow::Result<()> {
     let db = TestDatabase::new().await?;
@@ -620,3 +956,148 @@ async fn test_get_bookmarks_without_chunks() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_find_related_bookmarks_by_averaged_embedding() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = db.create_user().await?;
+    let text_content = "A".repeat(400);
+    let embedding = |first: f32, second: f32| {
+        let mut embedding = vec![0.0; TEST_EMBEDDING_DIMENSIONS];
+        embedding[0] = first;
+        embedding[1] = second;
+        embedding
+    };
+
+    let mut bookmark_ids = Vec::new();
+    for (slug, embeddings) in [
+        ("source", vec![embedding(1.0, 0.2), embedding(1.0, -0.2)]),
+        ("near", vec![embedding(0.9, 0.1)]),
+        ("far", vec![embedding(0.0, 1.0)]),
+    ] {
+        let bookmark = create_test_bookmark(
+            user_id,
+            &format!("https://example.com/{slug}"),
+            slug,
+            "example.com",
+            None,
+        );
+        bookmark::save(&db.pool, &bookmark, &text_content).await?;
+        let chunk_texts = (0..embeddings.len())
+            .map(|index| format!("{slug} chunk {index}"))
+            .collect();
+        chunks::store_chunks_with_embeddings(
+            &db.pool,
+            &bookmark.bookmark_id,
+            user_id,
+            chunk_texts,
+            embeddings,
+        )
+        .await?;
+        bookmark_ids.push(bookmark.bookmark_id);
+    }
+
+    let related = chunks::find_related_bookmarks(&db.pool, user_id, &bookmark_ids[0], 5).await?;
+    let related_ids: Vec<&str> = related
+        .iter()
+        .map(|related| related.bookmark.bookmark_id.as_str())
+        .collect();
+    assert_eq!(
+        related_ids,
+        vec![bookmark_ids[1].as_str(), bookmark_ids[2].as_str()]
+    );
+    assert!(related[0].similarity_score > related[1].similarity_score);
+
+    let other_user = db.create_user().await?;
+    assert!(
+        chunks::find_related_bookmarks(&db.pool, other_user, &bookmark_ids[0], 5)
+            .await?
+            .is_empty()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rag_session_feedback_and_quality_report() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = db.create_user().await?;
+
+    let session = rag::create_rag_session(&db.pool, user_id, "What is Tokio?").await?;
+    rag::update_rag_session(&db.pool, session.session_id, user_id, "A runtime.", &[]).await?;
+    let request = RagFeedbackRequest {
+        rating: RagRating::Down,
+        comment: Some("Too short".to_string()),
+    };
+    let rated = rag::set_feedback(&db.pool, session.session_id, user_id, &request)
+        .await?
+        .and_then(|session| session.feedback)
+        .expect("feedback stored");
+    assert_eq!(rated.rating, RagRating::Down);
+    assert_eq!(rated.comment.as_deref(), Some("Too short"));
+
+    // Other users can't rate the session
+    let other_user = db.create_user().await?;
+    assert!(
+        rag::set_feedback(&db.pool, session.session_id, other_user, &request)
+            .await?
+            .is_none()
+    );
+
+    let since = Utc::now().date_naive() - Days::new(1);
+    let report = rag::quality_report(&db.pool, user_id, since, 10).await?;
+    assert_eq!(
+        (report.answered, report.rated_up, report.rated_down),
+        (1, 0, 1)
+    );
+    assert_eq!(report.approval_rate, Some(0.0));
+    assert_eq!(report.comments[0].session_id, session.session_id);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rag_session_title_search_and_delete() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = db.create_user().await?;
+
+    let session =
+        rag::create_rag_session(&db.pool, user_id, "How does Tokio schedule tasks?").await?;
+    rag::update_rag_session(
+        &db.pool,
+        session.session_id,
+        user_id,
+        "With work stealing.",
+        &[],
+    )
+    .await?;
+    rag::set_rag_session_title(&db.pool, session.session_id, user_id, "Tokio scheduler").await?;
+    let other = rag::create_rag_session(&db.pool, user_id, "How long to proof dough?").await?;
+
+    let search = |query: &str| RagHistoryRequest {
+        limit: None,
+        offset: None,
+        query: Some(query.to_string()),
+    };
+    let history = rag::get_rag_history(&db.pool, user_id, &search("scheduler")).await?;
+    assert_eq!(history.total_count, 1);
+    assert_eq!(
+        history.sessions[0].title.as_deref(),
+        Some("Tokio scheduler")
+    );
+    let history = rag::get_rag_history(&db.pool, user_id, &search("stealing")).await?;
+    assert_eq!(history.sessions[0].session_id, session.session_id);
+    let history = rag::get_rag_history(&db.pool, user_id, &search("-tokio")).await?;
+    assert_eq!(history.sessions[0].session_id, other.session_id);
+    assert_eq!(history.total_count, 1);
+
+    // Only the owner deletes the session
+    let other_user = db.create_user().await?;
+    assert!(!rag::delete_rag_session(&db.pool, session.session_id, other_user).await?);
+    assert!(rag::delete_rag_session(&db.pool, session.session_id, user_id).await?);
+    assert!(rag::get_rag_session(&db.pool, session.session_id, user_id)
+        .await?
+        .is_none());
+
+    Ok(())
+}
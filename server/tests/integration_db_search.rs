@@ -2,6 +2,7 @@
 
 mod common;
 
+use chrono::{DateTime, Utc};
 use common::test_db::{create_test_bookmark, create_test_user, TestDatabase};
 use server::db::{bookmark, search};
 use shared::{SearchRequest, TagFilter};
@@ -42,6 +43,17 @@ async fn test_basic_search_without_query() -> anyhow::Result<()> {
     let search_req = SearchRequest {
         query: None,
         tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
         limit: None,
         offset: None,
     };
@@ -123,6 +135,17 @@ async fn test_full_text_search() -> anyhow::Result<()> {
     let search_req = SearchRequest {
         query: Some("rust programming".to_string()),
         tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
         limit: None,
         offset: None,
     };
@@ -143,6 +166,17 @@ async fn test_full_text_search() -> anyhow::Result<()> {
     let search_req2 = SearchRequest {
         query: Some("javascript".to_string()),
         tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
         limit: None,
         offset: None,
     };
@@ -179,6 +213,17 @@ async fn test_search_highlights() -> anyhow::Result<()> {
     let search_req = SearchRequest {
         query: Some("testing".to_string()),
         tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
         limit: None,
         offset: None,
     };
@@ -189,9 +234,9 @@ async fn test_search_highlights() -> anyhow::Result<()> {
     assert_eq!(result.total, 1);
 
     // Verify highlight markup is present
-    let search_match = &result.items[0].search_match;
-    assert!(search_match.is_some());
-    let highlight = search_match.as_ref().unwrap();
+    let snippets = &result.items[0].snippets;
+    assert_eq!(snippets.len(), 1);
+    let highlight = &snippets[0];
     assert!(highlight.contains("<mark>"));
     assert!(highlight.contains("</mark>"));
     assert!(highlight.contains("testing") || highlight.contains("Testing"));
@@ -236,6 +281,17 @@ async fn test_tag_filter_and() -> anyhow::Result<()> {
     let search_req = SearchRequest {
         query: None,
         tags_filter: Some(TagFilter::And(vec!["rust".to_string(), "web".to_string()])),
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
         limit: None,
         offset: None,
     };
@@ -289,6 +345,17 @@ async fn test_tag_filter_or() -> anyhow::Result<()> {
             "rust".to_string(),
             "python".to_string(),
         ])),
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
         limit: None,
         offset: None,
     };
@@ -343,6 +410,17 @@ async fn test_tag_filter_untagged() -> anyhow::Result<()> {
     let search_req = SearchRequest {
         query: None,
         tags_filter: Some(TagFilter::Untagged),
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
         limit: None,
         offset: None,
     };
@@ -388,6 +466,17 @@ async fn test_tag_filter_any() -> anyhow::Result<()> {
     let search_req = SearchRequest {
         query: None,
         tags_filter: Some(TagFilter::Any),
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
         limit: None,
         offset: None,
     };
@@ -421,6 +510,17 @@ async fn test_search_pagination() -> anyhow::Result<()> {
     let search_req = SearchRequest {
         query: None,
         tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
         limit: Some(3),
         offset: None,
     };
@@ -434,6 +534,17 @@ async fn test_search_pagination() -> anyhow::Result<()> {
     let search_req2 = SearchRequest {
         query: None,
         tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
         limit: Some(3),
         offset: Some(2),
     };
@@ -493,6 +604,17 @@ async fn test_tag_aggregation() -> anyhow::Result<()> {
     let search_req = SearchRequest {
         query: None,
         tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
         limit: None,
         offset: None,
     };
@@ -570,6 +692,17 @@ async fn test_combined_search_query_and_tags() -> anyhow::Result<()> {
     let search_req = SearchRequest {
         query: Some("programming".to_string()),
         tags_filter: Some(TagFilter::And(vec!["rust".to_string()])),
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
         limit: None,
         offset: None,
     };
@@ -639,6 +772,17 @@ async fn test_search_ranking() -> anyhow::Result<()> {
     let search_req = SearchRequest {
         query: Some("Rust".to_string()),
         tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
         limit: None,
         offset: None,
     };
@@ -654,7 +798,7 @@ async fn test_search_ranking() -> anyhow::Result<()> {
         if item.bookmark.url.contains("high-relevance")
             || item.bookmark.url.contains("medium-relevance")
         {
-            assert!(item.search_match.is_some());
+            assert!(!item.snippets.is_empty());
         }
     }
 
@@ -690,6 +834,17 @@ async fn test_user_isolation() -> anyhow::Result<()> {
     let search_req = SearchRequest {
         query: Some("programming".to_string()),
         tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
         limit: None,
         offset: None,
     };
@@ -738,6 +893,17 @@ async fn test_search_empty_results() -> anyhow::Result<()> {
     let search_req = SearchRequest {
         query: Some("nonexistent".to_string()),
         tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
         limit: None,
         offset: None,
     };
@@ -752,6 +918,17 @@ async fn test_search_empty_results() -> anyhow::Result<()> {
     let search_req2 = SearchRequest {
         query: None,
         tags_filter: Some(TagFilter::And(vec!["nonexistent".to_string()])),
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
         limit: None,
         offset: None,
     };
@@ -786,6 +963,17 @@ async fn test_search_total_count() -> anyhow::Result<()> {
     let search_req = SearchRequest {
         query: None,
         tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
         limit: Some(3),
         offset: None,
     };
@@ -799,6 +987,17 @@ async fn test_search_total_count() -> anyhow::Result<()> {
     let search_req2 = SearchRequest {
         query: None,
         tags_filter: Some(TagFilter::And(vec!["tag".to_string()])),
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
         limit: Some(5),
         offset: None,
     };
@@ -831,6 +1030,17 @@ async fn test_concurrent_search() -> anyhow::Result<()> {
     let search_req = SearchRequest {
         query: None,
         tags_filter: Some(TagFilter::And(vec!["concurrent".to_string()])),
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
         limit: None,
         offset: None,
     };
@@ -859,3 +1069,260 @@ async fn test_concurrent_search() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_search_uses_detected_language_config() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+
+    let bookmark = create_test_bookmark(
+        user_id,
+        "https://example.com/pt",
+        "Projetos de software livre",
+        "example.com",
+        None,
+    );
+    bookmark::save(
+        &db.pool,
+        &bookmark,
+        "O desenvolvimento de software não é uma tarefa simples, e isso também vale para os \
+         projetos que são mantidos pela comunidade há muito tempo.",
+    )
+    .await?;
+
+    let row = db
+        .pg
+        .get()
+        .await?
+        .query_one(
+            "SELECT language, text_search_config FROM bookmark WHERE bookmark_id = $1",
+            &[&bookmark.bookmark_id],
+        )
+        .await?;
+    assert_eq!(row.get::<_, Option<String>>(0).as_deref(), Some("pt"));
+    assert_eq!(row.get::<_, String>(1), "portuguese");
+
+    // "mantida" only matches "mantidos" with the portuguese stemmer.
+    let search_req = SearchRequest {
+        query: Some("mantida".to_string()),
+        tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
+        limit: None,
+        offset: None,
+    };
+    let result = search::search(&db.pool, user_id, &search_req).await?;
+    assert_eq!(result.total, 1);
+    assert_eq!(result.items[0].bookmark.bookmark_id, bookmark.bookmark_id);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_search_domain_facet_and_filter() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+
+    let bookmarks_data = vec![
+        ("https://lwn.net/1", "Kernel news", "lwn.net"),
+        ("https://lwn.net/2", "Filesystem news", "lwn.net"),
+        ("https://example.com/1", "Example article", "example.com"),
+    ];
+    for (url, title, domain) in &bookmarks_data {
+        let bookmark = create_test_bookmark(user_id, url, title, domain, None);
+        bookmark::save(&db.pool, &bookmark, "Some text content for the article").await?;
+    }
+
+    let search_req = SearchRequest {
+        query: None,
+        tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
+        limit: None,
+        offset: None,
+    };
+    let result = search::search(&db.pool, user_id, &search_req).await?;
+    assert_eq!(result.domains.len(), 2);
+    assert_eq!(result.domains[0].domain, "lwn.net");
+    assert_eq!(result.domains[0].count, 2);
+    assert_eq!(result.domains[1].domain, "example.com");
+    assert_eq!(result.domains[1].count, 1);
+
+    let search_req = SearchRequest {
+        domains: vec!["lwn.net".to_string()],
+        from_date: None,
+        to_date: None,
+        ..search_req
+    };
+    let result = search::search(&db.pool, user_id, &search_req).await?;
+    assert_eq!(result.total, 2);
+    assert!(result
+        .items
+        .iter()
+        .all(|item| item.bookmark.domain == "lwn.net"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_search_date_range_and_month_histogram() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+
+    let bookmarks_data = vec![
+        (
+            "https://example.com/1",
+            "January one",
+            "2024-01-05T10:00:00Z",
+        ),
+        (
+            "https://example.com/2",
+            "January two",
+            "2024-01-20T10:00:00Z",
+        ),
+        ("https://example.com/3", "March", "2024-03-02T10:00:00Z"),
+    ];
+    for (url, title, created_at) in &bookmarks_data {
+        let bookmark = create_test_bookmark(user_id, url, title, "example.com", None);
+        bookmark::save(&db.pool, &bookmark, "Some text content for the article").await?;
+        let created_at: DateTime<Utc> = created_at.parse()?;
+        db.pg
+            .get()
+            .await?
+            .execute(
+                "UPDATE bookmark SET created_at = $1 WHERE bookmark_id = $2",
+                &[&created_at, &bookmark.bookmark_id],
+            )
+            .await?;
+    }
+
+    let search_req = SearchRequest {
+        query: None,
+        tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
+        limit: None,
+        offset: None,
+    };
+    let result = search::search(&db.pool, user_id, &search_req).await?;
+    let months: Vec<(String, i64)> = result
+        .months
+        .iter()
+        .map(|month| (month.month.to_string(), month.count))
+        .collect();
+    assert_eq!(
+        months,
+        vec![("2024-01-01".to_string(), 2), ("2024-03-01".to_string(), 1)]
+    );
+
+    let search_req = SearchRequest {
+        from_date: Some("2024-01-10T00:00:00Z".parse()?),
+        to_date: Some("2024-02-28T00:00:00Z".parse()?),
+        ..search_req
+    };
+    let result = search::search(&db.pool, user_id, &search_req).await?;
+    assert_eq!(result.total, 1);
+    assert_eq!(result.items[0].bookmark.title, "January two");
+    assert_eq!(result.months.len(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_search_query_field_prefixes() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+
+    let bookmarks_data = vec![
+        (
+            "https://github.com/tokio",
+            "Tokio runtime",
+            "github.com",
+            vec!["async".to_string()],
+        ),
+        (
+            "https://lwn.net/rust",
+            "Rust in the kernel",
+            "lwn.net",
+            vec!["kernel".to_string()],
+        ),
+        (
+            "https://example.com/k8s",
+            "Rust operators",
+            "example.com",
+            vec!["kubernetes".to_string()],
+        ),
+    ];
+    for (url, title, domain, tags) in &bookmarks_data {
+        let bookmark = create_test_bookmark(user_id, url, title, domain, Some(tags.clone()));
+        bookmark::save(&db.pool, &bookmark, "Some text content for the article").await?;
+    }
+
+    let search = |query: &str| SearchRequest {
+        query: Some(query.to_string()),
+        tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
+        limit: None,
+        offset: None,
+    };
+    let titles = |result: shared::SearchResponse| -> Vec<String> {
+        let mut titles: Vec<String> = result
+            .items
+            .into_iter()
+            .map(|item| item.bookmark.title)
+            .collect();
+        titles.sort();
+        titles
+    };
+
+    let result = search::search(&db.pool, user_id, &search("title:rust")).await?;
+    assert_eq!(titles(result), vec!["Rust in the kernel", "Rust operators"]);
+
+    let result = search::search(&db.pool, user_id, &search("title:rust -tag:kubernetes")).await?;
+    assert_eq!(titles(result), vec!["Rust in the kernel"]);
+
+    let result = search::search(&db.pool, user_id, &search("domain:GitHub.com")).await?;
+    assert_eq!(titles(result), vec!["Tokio runtime"]);
+
+    let result = search::search(&db.pool, user_id, &search("tag:async article")).await?;
+    assert_eq!(titles(result), vec!["Tokio runtime"]);
+
+    Ok(())
+}
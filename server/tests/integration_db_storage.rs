@@ -0,0 +1,50 @@
+#![cfg(feature = "integration-tests")]
+
+mod common;
+
+use common::test_db::{create_test_bookmark, create_test_user, TestDatabase};
+use server::db::{bookmark, storage};
+use server::error::Error;
+
+#[tokio::test]
+async fn test_storage_usage_and_quota() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+    let other_user_id = db.create_user().await?;
+
+    let usage = storage::usage(&db.pool, user_id).await?;
+    assert_eq!(usage.total_bytes, 0);
+    storage::ensure_within_quota(&db.pool, user_id, Some(1)).await?;
+
+    let saved = bookmark::save(
+        &db.pool,
+        &create_test_bookmark(user_id, "https://example.com/a", "A", "example.com", None),
+        "0123456789",
+    )
+    .await?;
+    storage::set_static_bytes(&db.pool, user_id, &saved.bookmark_id, 1000).await?;
+
+    let usage = storage::usage(&db.pool, user_id).await?;
+    assert_eq!(usage.static_bytes, 1000);
+    assert_eq!(usage.text_bytes, 10);
+    assert_eq!(usage.total_bytes, 1010);
+    assert_eq!(
+        storage::usage(&db.pool, other_user_id).await?.total_bytes,
+        0
+    );
+
+    storage::ensure_within_quota(&db.pool, user_id, None).await?;
+    storage::ensure_within_quota(&db.pool, user_id, Some(2000)).await?;
+    let error = storage::ensure_within_quota(&db.pool, user_id, Some(1010))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        Error::StorageQuotaExceeded {
+            used_bytes: 1010,
+            quota_bytes: 1010
+        }
+    ));
+    storage::ensure_within_quota(&db.pool, other_user_id, Some(1010)).await?;
+    Ok(())
+}
@@ -2,8 +2,11 @@
 
 mod common;
 
-use common::test_db::TestDatabase;
-use server::db::user;
+use common::test_db::{create_test_bookmark, create_test_user, TestDatabase};
+use server::db::user::TokenPurpose;
+use server::db::{audit_log, bookmark, bookmark_task, user, user_settings};
+use shared::{AuditAction, ReaderFontFamily, ReaderSettings, UserSettings};
+use url::Url;
 use uuid::Uuid;
 
 #[tokio::test]
@@ -14,7 +17,8 @@ async fn test_user_create_and_retrieve() -> anyhow::Result<()> {
     let username = "testuser".to_string();
     let password_hash = "hashed_password_123".to_string();
 
-    let created_user = user::create(&db.pool, username.clone(), password_hash.clone()).await?;
+    let created_user =
+        user::create(&db.pool, username.clone(), None, password_hash.clone()).await?;
 
     // Verify user fields
     assert_eq!(created_user.username, username);
@@ -50,11 +54,11 @@ async fn test_duplicate_username_constraint() -> anyhow::Result<()> {
     let password_hash2 = "hash2".to_string();
 
     // Create first user - should succeed
-    let first_user = user::create(&db.pool, username.clone(), password_hash1).await?;
+    let first_user = user::create(&db.pool, username.clone(), None, password_hash1).await?;
     assert_eq!(first_user.username, username);
 
     // Try to create second user with same username - should fail
-    let result = user::create(&db.pool, username, password_hash2).await;
+    let result = user::create(&db.pool, username, None, password_hash2).await;
 
     assert!(result.is_err());
     let error = result.unwrap_err();
@@ -77,21 +81,21 @@ async fn test_case_insensitive_username_uniqueness() -> anyhow::Result<()> {
     let username1 = "TestUser".to_string();
     let password_hash1 = "hash1".to_string();
 
-    let first_user = user::create(&db.pool, username1.clone(), password_hash1).await?;
+    let first_user = user::create(&db.pool, username1.clone(), None, password_hash1).await?;
     assert_eq!(first_user.username, "testuser");
 
     // Try to create user with same username but different case - should fail
     let username2 = "testuser".to_string(); // lowercase version
     let password_hash2 = "hash2".to_string();
 
-    let result = user::create(&db.pool, username2, password_hash2).await;
+    let result = user::create(&db.pool, username2, None, password_hash2).await;
 
     assert!(result.is_err());
     // The constraint should trigger due to LOWER(username) unique index
 
     // Also test with uppercase
     let username3 = "TESTUSER".to_string();
-    let result2 = user::create(&db.pool, username3, "hash3".to_string()).await;
+    let result2 = user::create(&db.pool, username3, None, "hash3".to_string()).await;
     assert!(result2.is_err());
 
     Ok(())
@@ -101,8 +105,13 @@ async fn test_case_insensitive_username_uniqueness() -> anyhow::Result<()> {
 async fn test_get_by_username_is_case_insensitive_and_trimmed() -> anyhow::Result<()> {
     let db = TestDatabase::new().await?;
 
-    let created_user =
-        user::create(&db.pool, "MixedCaseUser".to_string(), "hash1".to_string()).await?;
+    let created_user = user::create(
+        &db.pool,
+        "MixedCaseUser".to_string(),
+        None,
+        "hash1".to_string(),
+    )
+    .await?;
     assert_eq!(created_user.username, "mixedcaseuser");
 
     let retrieved_upper = user::get_by_username(&db.pool, "MIXEDCASEUSER".to_string()).await?;
@@ -156,7 +165,13 @@ async fn test_multiple_users() -> anyhow::Result<()> {
     let mut created_users = Vec::new();
 
     for (username, password_hash) in &users_data {
-        let user = user::create(&db.pool, username.to_string(), password_hash.to_string()).await?;
+        let user = user::create(
+            &db.pool,
+            username.to_string(),
+            None,
+            password_hash.to_string(),
+        )
+        .await?;
         created_users.push(user);
     }
 
@@ -206,7 +221,7 @@ async fn test_username_with_special_characters() -> anyhow::Result<()> {
         let password_hash = format!("hash_for_{}", username);
 
         let created_user =
-            user::create(&db.pool, username.to_string(), password_hash.clone()).await?;
+            user::create(&db.pool, username.to_string(), None, password_hash.clone()).await?;
         assert_eq!(created_user.username, username);
 
         // Verify retrieval works
@@ -217,3 +232,215 @@ async fn test_username_with_special_characters() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_user_update_password() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+
+    let updated = user::update_password(&db.pool, &user_id, "new_hash".to_string()).await?;
+    assert!(updated);
+
+    let user = user::get_by_id(&db.pool, &user_id).await?.unwrap();
+    assert_eq!(user.password_hash, "new_hash");
+
+    // Unknown users are reported as not updated
+    let updated = user::update_password(&db.pool, &Uuid::new_v4(), "hash".to_string()).await?;
+    assert!(!updated);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_user_delete_cascades() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+    let other_user_id = create_test_user(&db).await?;
+
+    let new_bookmark = create_test_bookmark(
+        user_id,
+        "https://example.com/article",
+        "Article",
+        "example.com",
+        None,
+    );
+    bookmark::save(&db.pool, &new_bookmark, "content").await?;
+    bookmark_task::create(
+        &db.pool,
+        user_id,
+        Url::parse("https://example.com/task")?,
+        vec![],
+    )
+    .await?;
+
+    assert!(user::delete(&db.pool, &user_id).await?);
+    assert!(user::get_by_id(&db.pool, &user_id).await?.is_none());
+    assert!(bookmark::get_by_user(&db.pool, user_id).await?.is_empty());
+
+    let client = db.pg.get().await?;
+    let row = client
+        .query_one(
+            "SELECT COUNT(*) FROM bookmark_task WHERE user_id = $1",
+            &[&user_id],
+        )
+        .await?;
+    assert_eq!(row.get::<_, i64>(0), 0);
+
+    // Other users are untouched and a second delete is a no-op
+    assert!(user::get_by_id(&db.pool, &other_user_id).await?.is_some());
+    assert!(!user::delete(&db.pool, &user_id).await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_user_settings_default_and_upsert() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = db.create_user().await?;
+
+    assert_eq!(
+        user_settings::get(&db.pool, user_id).await?,
+        UserSettings::default()
+    );
+
+    let settings = UserSettings {
+        auto_tagging_enabled: false,
+        summaries_enabled: true,
+        summary_language: Some("Portuguese".to_string()),
+        preferred_text_model: Some("qwen3:8b".to_string()),
+        digest_enabled: true,
+        reader: ReaderSettings {
+            font_family: ReaderFontFamily::SansSerif,
+            font_size: 20,
+            line_width: 80,
+            justify: true,
+        },
+    };
+    assert_eq!(
+        user_settings::upsert(&db.pool, user_id, &settings).await?,
+        settings
+    );
+    assert_eq!(user_settings::get(&db.pool, user_id).await?, settings);
+
+    let updated = UserSettings {
+        summaries_enabled: false,
+        ..settings
+    };
+    user_settings::upsert(&db.pool, user_id, &updated).await?;
+    assert_eq!(user_settings::get(&db.pool, user_id).await?, updated);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_user_email_and_tokens() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let created = user::create(
+        &db.pool,
+        "mailuser".to_string(),
+        Some("Mail.User@Example.com".to_string()),
+        "hash".to_string(),
+    )
+    .await?;
+    assert_eq!(created.email.as_deref(), Some("mail.user@example.com"));
+    assert!(created.email_verified_at.is_none());
+
+    // Emails are unique regardless of case
+    let duplicated = user::create(
+        &db.pool,
+        "otheruser".to_string(),
+        Some("MAIL.USER@example.com".to_string()),
+        "hash".to_string(),
+    )
+    .await;
+    assert!(duplicated.is_err());
+
+    let token = user::create_token(
+        &db.pool,
+        &created.user_id,
+        TokenPurpose::VerifyEmail,
+        chrono::Duration::hours(1),
+    )
+    .await?;
+    // Tokens are bound to their purpose and single use
+    assert_eq!(
+        user::consume_token(&db.pool, &token, TokenPurpose::PasswordReset).await?,
+        None
+    );
+    assert_eq!(
+        user::consume_token(&db.pool, &token, TokenPurpose::VerifyEmail).await?,
+        Some(created.user_id)
+    );
+    assert_eq!(
+        user::consume_token(&db.pool, &token, TokenPurpose::VerifyEmail).await?,
+        None
+    );
+    assert!(user::mark_email_verified(&db.pool, &created.user_id).await?);
+    let verified = user::get_by_id(&db.pool, &created.user_id).await?.unwrap();
+    assert!(verified.email_verified_at.is_some());
+
+    // Expired tokens are rejected
+    let expired = user::create_token(
+        &db.pool,
+        &created.user_id,
+        TokenPurpose::PasswordReset,
+        chrono::Duration::seconds(-1),
+    )
+    .await?;
+    assert_eq!(
+        user::consume_token(&db.pool, &expired, TokenPurpose::PasswordReset).await?,
+        None
+    );
+
+    // Redeeming a reset token uses up the other ones of the user
+    let issue = || {
+        user::create_token(
+            &db.pool,
+            &created.user_id,
+            TokenPurpose::PasswordReset,
+            chrono::Duration::hours(1),
+        )
+    };
+    let older = issue().await?;
+    let newer = issue().await?;
+    assert_eq!(
+        user::consume_token(&db.pool, &newer, TokenPurpose::PasswordReset).await?,
+        Some(created.user_id)
+    );
+    assert_eq!(
+        user::consume_token(&db.pool, &older, TokenPurpose::PasswordReset).await?,
+        None
+    );
+
+    // Changing the email resets verification
+    assert!(user::update_email(&db.pool, &created.user_id, "new@example.com").await?);
+    let updated = user::get_by_id(&db.pool, &created.user_id).await?.unwrap();
+    assert_eq!(updated.email.as_deref(), Some("new@example.com"));
+    assert!(updated.email_verified_at.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_audit_events_outlive_their_user() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user = user::create(&db.pool, "audited".to_string(), None, "hash".to_string()).await?;
+    let ip = "203.0.113.7".parse().ok();
+
+    audit_log::insert(&db.pool, user.user_id, AuditAction::SignIn, None, ip).await?;
+    audit_log::insert_for_username(&db.pool, "nobody", AuditAction::SignInFailed, None, ip).await?;
+    // The events only lose their user id
+    assert!(user::delete(&db.pool, &user.user_id).await?);
+
+    assert!(audit_log::list(&db.pool, user.user_id, None, 50)
+        .await?
+        .is_empty());
+    let events = audit_log::list_by_username(&db.pool, "audited", None, 50).await?;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].action, AuditAction::SignIn);
+    let unknown = audit_log::list_by_username(&db.pool, "nobody", None, 50).await?;
+    assert_eq!(unknown.len(), 1);
+    assert_eq!(unknown[0].action, AuditAction::SignInFailed);
+
+    Ok(())
+}
@@ -0,0 +1,165 @@
+#![cfg(feature = "integration-tests")]
+
+mod common;
+
+use chrono::{DateTime, Duration, Utc};
+use common::test_db::{create_test_user, TestDatabase};
+use server::db::user_session;
+use uuid::Uuid;
+
+async fn last_seen(db: &TestDatabase, session_id: Uuid) -> anyhow::Result<DateTime<Utc>> {
+    let row = db
+        .pg
+        .get()
+        .await?
+        .query_one(
+            "SELECT last_seen_at FROM user_session WHERE session_id = $1",
+            &[&session_id],
+        )
+        .await?;
+    Ok(row.get("last_seen_at"))
+}
+
+/// Moves the last sighting of the session `ago` into the past.
+async fn seen_ago(db: &TestDatabase, session_id: Uuid, ago: Duration) -> anyhow::Result<()> {
+    db.pg
+        .get()
+        .await?
+        .execute(
+            "UPDATE user_session SET last_seen_at = $2 WHERE session_id = $1",
+            &[&session_id, &(Utc::now() - ago)],
+        )
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_session_validity_and_expiry() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+    let other_user_id = create_test_user(&db).await?;
+    let ip = "203.0.113.7".parse().ok();
+    let expires_at = Utc::now() + Duration::days(14);
+
+    let phone = user_session::create(&db.pool, user_id, Some("Phone"), ip, expires_at).await?;
+    let laptop = user_session::create(&db.pool, user_id, None, None, expires_at).await?;
+    let expired = user_session::create(
+        &db.pool,
+        user_id,
+        None,
+        None,
+        Utc::now() - Duration::minutes(1),
+    )
+    .await?;
+
+    assert!(user_session::touch(&db.pool, user_id, phone, None).await?);
+    assert!(user_session::touch(&db.pool, user_id, laptop, ip).await?);
+    assert!(!user_session::touch(&db.pool, user_id, expired, None).await?);
+    assert!(!user_session::touch(&db.pool, other_user_id, phone, None).await?);
+    assert!(!user_session::touch(&db.pool, user_id, Uuid::new_v4(), None).await?);
+
+    let sessions = user_session::list(&db.pool, user_id).await?;
+    let mut listed: Vec<Uuid> = sessions.iter().map(|s| s.session_id).collect();
+    listed.sort();
+    let mut expected = vec![phone, laptop];
+    expected.sort();
+    assert_eq!(listed, expected);
+    let phone_session = sessions.iter().find(|s| s.session_id == phone).unwrap();
+    assert_eq!(phone_session.device_label.as_deref(), Some("Phone"));
+    assert_eq!(phone_session.ip.as_deref(), Some("203.0.113.7"));
+    assert_eq!(phone_session.expires_at.timestamp(), expires_at.timestamp());
+    assert!(user_session::list(&db.pool, other_user_id)
+        .await?
+        .is_empty());
+
+    // Expired sessions are dropped once the user signs in again
+    user_session::create(&db.pool, user_id, None, None, expires_at).await?;
+    let count: i64 = db
+        .pg
+        .get()
+        .await?
+        .query_one(
+            "SELECT COUNT(*) FROM user_session WHERE session_id = $1",
+            &[&expired],
+        )
+        .await?
+        .get(0);
+    assert_eq!(count, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_touch_is_throttled() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+    let first_ip = "203.0.113.7".parse().ok();
+    let second_ip = "198.51.100.1".parse().ok();
+    let session_id = user_session::create(
+        &db.pool,
+        user_id,
+        None,
+        first_ip,
+        Utc::now() + Duration::days(14),
+    )
+    .await?;
+
+    // Seen moments ago, the session is valid but its row isn't written again
+    seen_ago(&db, session_id, Duration::seconds(10)).await?;
+    let before = last_seen(&db, session_id).await?;
+    assert!(user_session::touch(&db.pool, user_id, session_id, second_ip).await?);
+    assert_eq!(last_seen(&db, session_id).await?, before);
+    let session = &user_session::list(&db.pool, user_id).await?[0];
+    assert_eq!(session.ip.as_deref(), Some("203.0.113.7"));
+
+    // Past the touch interval, the sighting and the address are updated
+    seen_ago(&db, session_id, Duration::minutes(5)).await?;
+    let before = last_seen(&db, session_id).await?;
+    assert!(user_session::touch(&db.pool, user_id, session_id, second_ip).await?);
+    assert!(last_seen(&db, session_id).await? > before);
+    let session = &user_session::list(&db.pool, user_id).await?[0];
+    assert_eq!(session.ip.as_deref(), Some("198.51.100.1"));
+
+    // Without an address, the known one is kept
+    seen_ago(&db, session_id, Duration::minutes(5)).await?;
+    assert!(user_session::touch(&db.pool, user_id, session_id, None).await?);
+    let session = &user_session::list(&db.pool, user_id).await?[0];
+    assert_eq!(session.ip.as_deref(), Some("198.51.100.1"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_revoke_sessions() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let user_id = create_test_user(&db).await?;
+    let other_user_id = create_test_user(&db).await?;
+    let expires_at = Utc::now() + Duration::days(14);
+
+    let phone = user_session::create(&db.pool, user_id, None, None, expires_at).await?;
+    let laptop = user_session::create(&db.pool, user_id, None, None, expires_at).await?;
+    let tablet = user_session::create(&db.pool, user_id, None, None, expires_at).await?;
+    let other = user_session::create(&db.pool, other_user_id, None, None, expires_at).await?;
+
+    assert!(!user_session::revoke(&db.pool, other_user_id, phone).await?);
+    assert!(user_session::revoke(&db.pool, user_id, phone).await?);
+    assert!(!user_session::revoke(&db.pool, user_id, phone).await?);
+    assert!(!user_session::touch(&db.pool, user_id, phone, None).await?);
+    assert_eq!(user_session::list(&db.pool, user_id).await?.len(), 2);
+
+    // A password change keeps the current session only, a reset none
+    assert_eq!(
+        user_session::revoke_others(&db.pool, user_id, Some(tablet)).await?,
+        1
+    );
+    assert!(user_session::touch(&db.pool, user_id, tablet, None).await?);
+    assert!(!user_session::touch(&db.pool, user_id, laptop, None).await?);
+    assert_eq!(
+        user_session::revoke_others(&db.pool, user_id, None).await?,
+        1
+    );
+    assert!(user_session::list(&db.pool, user_id).await?.is_empty());
+    assert!(user_session::touch(&db.pool, other_user_id, other, None).await?);
+
+    Ok(())
+}
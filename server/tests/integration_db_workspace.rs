@@ -0,0 +1,253 @@
+#![cfg(feature = "integration-tests")]
+
+mod common;
+
+use common::test_db::{create_test_bookmark, create_test_user, TestDatabase};
+use server::db::{bookmark, search, workspace};
+use shared::{Bookmark, SearchRequest, Visibility, WorkspaceRole};
+use uuid::Uuid;
+
+fn workspace_search(workspace_id: Uuid) -> SearchRequest {
+    SearchRequest {
+        query: Some("rust".to_string()),
+        tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: Some(workspace_id),
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
+        limit: None,
+        offset: None,
+    }
+}
+
+async fn save_shared(db: &TestDatabase, bookmark: &Bookmark) -> anyhow::Result<()> {
+    bookmark::save(&db.pool, bookmark, "Ownership and borrowing").await?;
+    bookmark::set_visibility(
+        &db.pool,
+        bookmark.user_id,
+        &bookmark.bookmark_id,
+        Visibility::Workspace,
+    )
+    .await?
+    .expect("bookmark of the user");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_workspace_search_covers_bookmarks_of_members() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let owner_id = create_test_user(&db).await?;
+    let member_id = create_test_user(&db).await?;
+    let outsider_id = create_test_user(&db).await?;
+    let at_work = create_test_bookmark(
+        owner_id,
+        "https://example.com/work",
+        "Rust at work",
+        "example.com",
+        None,
+    );
+    let on_weekends = create_test_bookmark(
+        member_id,
+        "https://example.org/weekend",
+        "Rust on weekends",
+        "example.org",
+        None,
+    );
+    let unshared = create_test_bookmark(
+        member_id,
+        "https://example.net/own",
+        "Rust for me",
+        "example.net",
+        None,
+    );
+    bookmark::save(&db.pool, &unshared, "Ownership and borrowing").await?;
+
+    let team = workspace::create(&db.pool, owner_id, "Team").await?;
+    assert_eq!(team.role, WorkspaceRole::Owner);
+    let workspace_id = team.workspace_id;
+    assert!(workspace::set_member(&db.pool, workspace_id, member_id, WorkspaceRole::Editor).await?);
+    assert!(!workspace::set_member(&db.pool, workspace_id, owner_id, WorkspaceRole::Viewer).await?);
+
+    // Bookmarks start out private
+    bookmark::save(&db.pool, &at_work, "Ownership and borrowing").await?;
+    assert!(
+        !workspace::add_bookmark(&db.pool, workspace_id, owner_id, &at_work.bookmark_id).await?
+    );
+    bookmark::set_visibility(
+        &db.pool,
+        owner_id,
+        &at_work.bookmark_id,
+        Visibility::Workspace,
+    )
+    .await?
+    .expect("bookmark of the owner");
+    save_shared(&db, &on_weekends).await?;
+    assert!(workspace::add_bookmark(&db.pool, workspace_id, owner_id, &at_work.bookmark_id).await?);
+    assert!(
+        workspace::add_bookmark(&db.pool, workspace_id, member_id, &on_weekends.bookmark_id)
+            .await?
+    );
+    assert!(
+        !workspace::add_bookmark(&db.pool, workspace_id, member_id, &at_work.bookmark_id).await?
+    );
+
+    let request = workspace_search(workspace_id);
+    let response = search::search(&db.pool, member_id, &request).await?;
+    let mut found: Vec<String> = response
+        .items
+        .iter()
+        .map(|b| b.bookmark.bookmark_id.clone())
+        .collect();
+    found.sort();
+    let mut expected = vec![at_work.bookmark_id.clone(), on_weekends.bookmark_id.clone()];
+    expected.sort();
+    assert_eq!(found, expected);
+    assert_eq!(
+        search::search(&db.pool, outsider_id, &request).await?.total,
+        0
+    );
+
+    let joined = workspace::list(&db.pool, member_id).await?;
+    assert_eq!(joined.len(), 1);
+    assert_eq!(joined[0].role, WorkspaceRole::Editor);
+    assert_eq!((joined[0].member_count, joined[0].bookmark_count), (2, 2));
+    assert!(workspace::get(&db.pool, outsider_id, workspace_id)
+        .await?
+        .is_none());
+    let members = workspace::members(&db.pool, workspace_id).await?;
+    assert_eq!(
+        members.iter().map(|m| m.user_id).collect::<Vec<_>>(),
+        vec![owner_id, member_id]
+    );
+
+    let read = bookmark::get_in_workspace(&db.pool, workspace_id, &on_weekends.bookmark_id)
+        .await?
+        .expect("bookmark saved into the workspace");
+    assert_eq!(read.user_id, member_id);
+    assert!(
+        bookmark::get_in_workspace(&db.pool, workspace_id, &unshared.bookmark_id)
+            .await?
+            .is_none()
+    );
+    assert!(
+        workspace::shares_bookmark(&db.pool, owner_id, member_id, &on_weekends.bookmark_id).await?
+    );
+    assert!(
+        !workspace::shares_bookmark(&db.pool, outsider_id, member_id, &on_weekends.bookmark_id)
+            .await?
+    );
+    assert!(
+        !workspace::shares_bookmark(&db.pool, owner_id, member_id, &unshared.bookmark_id).await?
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_private_and_removed_bookmarks_leave_the_workspace() -> anyhow::Result<()> {
+    let db = TestDatabase::new().await?;
+    let owner_id = create_test_user(&db).await?;
+    let member_id = create_test_user(&db).await?;
+    let at_work = create_test_bookmark(
+        owner_id,
+        "https://example.com/work",
+        "Rust at work",
+        "example.com",
+        None,
+    );
+    let on_weekends = create_test_bookmark(
+        member_id,
+        "https://example.org/weekend",
+        "Rust on weekends",
+        "example.org",
+        None,
+    );
+    save_shared(&db, &at_work).await?;
+    save_shared(&db, &on_weekends).await?;
+    let workspace_id = workspace::create(&db.pool, owner_id, "Team")
+        .await?
+        .workspace_id;
+    workspace::set_member(&db.pool, workspace_id, member_id, WorkspaceRole::Viewer).await?;
+    workspace::add_bookmark(&db.pool, workspace_id, owner_id, &at_work.bookmark_id).await?;
+    workspace::add_bookmark(&db.pool, workspace_id, member_id, &on_weekends.bookmark_id).await?;
+    let request = workspace_search(workspace_id);
+    assert_eq!(search::search(&db.pool, owner_id, &request).await?.total, 2);
+
+    // Making a bookmark private again hides it from the workspace
+    bookmark::set_visibility(
+        &db.pool,
+        member_id,
+        &on_weekends.bookmark_id,
+        Visibility::Private,
+    )
+    .await?
+    .expect("bookmark of the member");
+    assert_eq!(search::search(&db.pool, owner_id, &request).await?.total, 1);
+    assert!(
+        bookmark::get_in_workspace(&db.pool, workspace_id, &on_weekends.bookmark_id)
+            .await?
+            .is_none()
+    );
+    assert!(
+        !workspace::shares_bookmark(&db.pool, owner_id, member_id, &on_weekends.bookmark_id)
+            .await?
+    );
+    assert_eq!(
+        workspace::get(&db.pool, owner_id, workspace_id)
+            .await?
+            .expect("workspace of the owner")
+            .bookmark_count,
+        1
+    );
+    // Only the user the bookmark belongs to changes its visibility
+    assert!(bookmark::set_visibility(
+        &db.pool,
+        owner_id,
+        &on_weekends.bookmark_id,
+        Visibility::Public
+    )
+    .await?
+    .is_none());
+
+    bookmark::set_visibility(
+        &db.pool,
+        member_id,
+        &on_weekends.bookmark_id,
+        Visibility::Workspace,
+    )
+    .await?
+    .expect("bookmark of the member");
+    assert_eq!(search::search(&db.pool, owner_id, &request).await?.total, 2);
+
+    // Removing a member takes out the bookmarks they saved into it
+    assert!(!workspace::remove_member(&db.pool, workspace_id, owner_id).await?);
+    assert!(workspace::remove_member(&db.pool, workspace_id, member_id).await?);
+    assert!(!workspace::remove_member(&db.pool, workspace_id, member_id).await?);
+    assert_eq!(search::search(&db.pool, owner_id, &request).await?.total, 1);
+    assert_eq!(
+        search::search(&db.pool, member_id, &request).await?.total,
+        0
+    );
+
+    assert!(workspace::remove_bookmark(&db.pool, workspace_id, &at_work.bookmark_id).await?);
+    assert!(!workspace::remove_bookmark(&db.pool, workspace_id, &at_work.bookmark_id).await?);
+    assert_eq!(search::search(&db.pool, owner_id, &request).await?.total, 0);
+
+    assert!(!workspace::delete(&db.pool, member_id, workspace_id).await?);
+    assert!(workspace::delete(&db.pool, owner_id, workspace_id).await?);
+    assert!(workspace::list(&db.pool, owner_id).await?.is_empty());
+    assert!(
+        bookmark::get_with_user_data(&db.pool, owner_id, &at_work.bookmark_id)
+            .await?
+            .is_some()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::sqlite::{create_user, database, new_bookmark};
+use server::db::{activity, audit_log, bookmark, bookmark_task, rag, task_event};
+use shared::{ActivityKind, AuditAction, TaskStage};
+use url::Url;
+
+#[tokio::test]
+async fn activity_merges_events_newest_first() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let other_user_id = create_user(&db).await?;
+    let saved = new_bookmark(user_id, "https://example.com/a", "A", &[]);
+    bookmark::save(&db, &saved, "text").await?;
+    bookmark::save(
+        &db,
+        &new_bookmark(other_user_id, "https://example.com/b", "B", &[]),
+        "text",
+    )
+    .await?;
+    // Saved again later, the stages then go to both tasks
+    for _ in 0..2 {
+        let task = bookmark_task::create(&db, user_id, Url::parse(&saved.url)?, vec![]).await?;
+        bookmark_task::set_bookmark_id(&db, task.task_id, &saved.bookmark_id).await?;
+    }
+    task_event::insert_for_bookmark(
+        &db,
+        user_id,
+        &saved.bookmark_id,
+        TaskStage::Tagged,
+        Some("rust, web"),
+    )
+    .await?;
+    rag::create_rag_session(&db, user_id, "why?").await?;
+    audit_log::insert(
+        &db,
+        user_id,
+        AuditAction::BackupRestored,
+        Some("3 bookmarks"),
+        None,
+    )
+    .await?;
+    audit_log::insert(&db, user_id, AuditAction::SignIn, None, None).await?;
+
+    let items = activity::list(&db, user_id, None, 2).await?;
+    assert_eq!(
+        items.iter().map(|item| item.kind).collect::<Vec<_>>(),
+        vec![ActivityKind::ImportCompleted, ActivityKind::QuestionAsked]
+    );
+    assert_eq!(items[0].detail.as_deref(), Some("3 bookmarks"));
+    assert_eq!(items[1].detail.as_deref(), Some("why?"));
+
+    let older = activity::list(&db, user_id, Some(items[1].created_at), 50).await?;
+    assert_eq!(
+        older.iter().map(|item| item.kind).collect::<Vec<_>>(),
+        vec![ActivityKind::TagsApplied, ActivityKind::BookmarkAdded]
+    );
+    assert_eq!(older[0].detail.as_deref(), Some("rust, web"));
+    assert_eq!(older[1].title.as_deref(), Some("A"));
+    assert_eq!(activity::list(&db, other_user_id, None, 50).await?.len(), 1);
+    Ok(())
+}
@@ -0,0 +1,152 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use common::sqlite::{create_user, database, new_bookmark};
+use server::db::bookmark::AiGenerationStatus;
+use server::db::{ai, bookmark, Database};
+use shared::SummaryStyle;
+
+#[tokio::test]
+async fn summaries_are_written_by_hand_or_requested_again() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let mut bookmark = new_bookmark(user_id, "https://example.com/page", "Page", &["rust"]);
+    bookmark.summary = Some("Generated".to_string());
+    let saved = bookmark::save(&db, &bookmark, "Text").await?;
+    let claim = |db: Database| async move {
+        ai::claim_bookmarks_pending_text_ai(&db, 10, Utc::now(), Duration::minutes(5)).await
+    };
+    assert!(claim(db.clone()).await?.is_empty());
+
+    let written = ai::set_summary(&db, user_id, &saved.bookmark_id, "Mine")
+        .await?
+        .expect("summary written");
+    assert_eq!(written.summary.as_deref(), Some("Mine"));
+    assert!(
+        ai::set_summary(&db, create_user(&db).await?, &saved.bookmark_id, "x")
+            .await?
+            .is_none()
+    );
+
+    assert!(
+        ai::request_summary(
+            &db,
+            user_id,
+            &saved.bookmark_id,
+            Some(SummaryStyle::BulletPoints)
+        )
+        .await?
+    );
+    assert!(!ai::request_summary(&db, user_id, "missing", None).await?);
+    let claimed = claim(db.clone()).await?;
+    assert_eq!(claimed.len(), 1);
+    assert!(claimed[0].needs_summary);
+    assert!(!claimed[0].needs_tags);
+    assert_eq!(claimed[0].summary_style, Some(SummaryStyle::BulletPoints));
+
+    ai::complete_text_ai_outputs(
+        &db,
+        user_id,
+        &saved.bookmark_id,
+        Some("- Point"),
+        claimed[0].bookmark.tags.as_deref(),
+        None,
+        AiGenerationStatus::Done,
+        AiGenerationStatus::Done,
+        1,
+    )
+    .await?;
+    ai::request_summary(&db, user_id, &saved.bookmark_id, None).await?;
+    assert_eq!(claim(db.clone()).await?[0].summary_style, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn skipped_outputs_are_queued_once_turned_back_on() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let saved = bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://example.com/page", "Page", &[]),
+        "Text",
+    )
+    .await?;
+    let claim = |db: Database| async move {
+        ai::claim_bookmarks_pending_text_ai(&db, 10, Utc::now(), Duration::minutes(5)).await
+    };
+    let claimed = claim(db.clone()).await?;
+    assert!(claimed[0].needs_summary && claimed[0].needs_tags);
+
+    // Summaries turned off, tags generated
+    ai::complete_text_ai_outputs(
+        &db,
+        user_id,
+        &saved.bookmark_id,
+        None,
+        Some(&["rust".to_string()]),
+        None,
+        AiGenerationStatus::Skipped,
+        AiGenerationStatus::Done,
+        1,
+    )
+    .await?;
+    assert!(claim(db.clone()).await?.is_empty());
+    assert_eq!(ai::requeue_skipped(&db, user_id, false, true).await?, 0);
+
+    assert_eq!(ai::requeue_skipped(&db, user_id, true, true).await?, 1);
+    let claimed = claim(db.clone()).await?;
+    assert_eq!(claimed.len(), 1);
+    assert!(claimed[0].needs_summary);
+    assert!(!claimed[0].needs_tags);
+    assert_eq!(ai::requeue_skipped(&db, user_id, true, true).await?, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn reprocessing_marks_the_chosen_stages_pending() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let mut bookmark = new_bookmark(user_id, "https://example.com/page", "Page", &["rust"]);
+    bookmark.summary = Some("Generated".to_string());
+    let saved = bookmark::save(&db, &bookmark, "Text").await?;
+    ai::upsert_bookmark_ai_chunk(
+        &db,
+        &ai::BookmarkAiChunk {
+            bookmark_id: saved.bookmark_id.clone(),
+            user_id,
+            chunk_index: 0,
+            chunk_hash: "hash".to_string(),
+            pipeline_version: 1,
+            summary: "Slice".to_string(),
+            tags: vec!["rust".to_string()],
+            entities: vec![],
+        },
+    )
+    .await?;
+    let now = Utc::now() + Duration::seconds(1);
+    let window = Duration::minutes(5);
+    ai::claim_bookmarks_pending_embeddings(&db, 10, now, window).await?;
+    assert!(ai::claim_bookmarks_pending_embeddings(&db, 10, now, window)
+        .await?
+        .is_empty());
+
+    assert!(ai::request_reprocessing(&db, user_id, &saved.bookmark_id, true, false, false).await?);
+    let claimed = ai::claim_bookmarks_pending_text_ai(&db, 10, now, window).await?;
+    assert_eq!(claimed.len(), 1);
+    assert!(claimed[0].needs_tags);
+    assert!(!claimed[0].needs_summary);
+    assert!(
+        ai::list_bookmark_ai_chunks(&db, user_id, &saved.bookmark_id)
+            .await?
+            .is_empty()
+    );
+    assert!(ai::claim_bookmarks_pending_embeddings(&db, 10, now, window)
+        .await?
+        .is_empty());
+
+    assert!(ai::request_reprocessing(&db, user_id, &saved.bookmark_id, false, false, true).await?);
+    let claimed = ai::claim_bookmarks_pending_embeddings(&db, 10, now, window).await?;
+    assert_eq!(claimed.len(), 1);
+    assert!(!ai::request_reprocessing(&db, user_id, "missing", true, true, true).await?);
+    Ok(())
+}
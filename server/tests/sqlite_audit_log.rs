@@ -0,0 +1,68 @@
+mod common;
+
+use common::sqlite::{create_user, database};
+use server::db::{audit_log, user};
+use shared::AuditAction;
+
+#[tokio::test]
+async fn audit_log_pages_newest_first_per_user() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let other_user_id = create_user(&db).await?;
+    let ip = "203.0.113.7".parse().ok();
+
+    audit_log::insert(&db, user_id, AuditAction::SignIn, None, ip).await?;
+    audit_log::insert(&db, user_id, AuditAction::BookmarkDeleted, Some("b1"), ip).await?;
+    audit_log::insert(&db, user_id, AuditAction::BookmarkPurged, Some("b1"), None).await?;
+    audit_log::insert(&db, other_user_id, AuditAction::SignInFailed, None, ip).await?;
+
+    let events = audit_log::list(&db, user_id, None, 2).await?;
+    assert_eq!(
+        events.iter().map(|event| event.action).collect::<Vec<_>>(),
+        vec![AuditAction::BookmarkPurged, AuditAction::BookmarkDeleted]
+    );
+    assert_eq!(events[1].target.as_deref(), Some("b1"));
+    assert_eq!(events[1].ip.as_deref(), Some("203.0.113.7"));
+
+    let older = audit_log::list(&db, user_id, Some(events[1].created_at), 50).await?;
+    assert_eq!(older.len(), 1);
+    assert_eq!(older[0].action, AuditAction::SignIn);
+    assert_eq!(
+        audit_log::list(&db, other_user_id, None, 50).await?.len(),
+        1
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn audit_events_outlive_their_user() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let username = user::get_by_id(&db, &user_id).await?.unwrap().username;
+    let ip = "203.0.113.7".parse().ok();
+
+    audit_log::insert(&db, user_id, AuditAction::SignIn, Some("wallabag"), ip).await?;
+    audit_log::insert_for_username(&db, "nobody", AuditAction::SignInFailed, None, ip).await?;
+    audit_log::insert(&db, user_id, AuditAction::AccountDeleted, None, ip).await?;
+    assert!(user::delete(&db, &user_id).await?);
+
+    assert!(audit_log::list(&db, user_id, None, 50).await?.is_empty());
+    let mut events = audit_log::list_by_username(&db, &username, None, 50)
+        .await?
+        .into_iter()
+        .map(|event| (event.action, event.target))
+        .collect::<Vec<_>>();
+    events.sort_by_key(|(action, _)| action.as_ref().to_string());
+    assert_eq!(
+        events,
+        vec![
+            (AuditAction::AccountDeleted, None),
+            (AuditAction::SignIn, Some("wallabag".to_string()))
+        ]
+    );
+    let unknown = audit_log::list_by_username(&db, "nobody", None, 50).await?;
+    assert_eq!(unknown.len(), 1);
+    assert_eq!(unknown[0].action, AuditAction::SignInFailed);
+    assert_eq!(unknown[0].ip.as_deref(), Some("203.0.113.7"));
+    Ok(())
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use common::sqlite::{create_user, database, new_bookmark};
+use server::db::{
+    self, bookmark, chunks, collection, extraction_rule, fetch_credential, rule, workspace,
+};
+use server::error::Error;
+use shared::{
+    CollectionRequest, ExtractionRuleRequest, RuleActions, RuleConditions, RuleRequest, Visibility,
+    WorkspaceRole,
+};
+
+#[tokio::test]
+async fn users_are_exported_and_imported() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let saved = bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://example.com/a", "A", &["tag"]),
+        "text",
+    )
+    .await?;
+    chunks::store_chunks_with_embeddings(
+        &db,
+        &saved.bookmark_id,
+        user_id,
+        vec!["chunk".to_string()],
+        vec![vec![1.0, 0.0]],
+    )
+    .await?;
+
+    let dump = db::backup::export_user(&db, user_id).await?;
+    assert_eq!(dump.bookmarks.len(), 1);
+    assert_eq!(dump.bookmarks[0].chunks[0].embedding, Some(vec![1.0, 0.0]));
+
+    let restored = database().await?;
+    db::backup::import_user(&restored, &dump, true).await?;
+    assert_eq!(db::backup::user_ids(&restored).await?, vec![user_id]);
+    assert_eq!(
+        bookmark::get_by_user(&restored, user_id).await?,
+        vec![saved]
+    );
+    assert!(
+        chunks::has_chunks_for_bookmark(&restored, &dump.bookmarks[0].bookmark_id, user_id).await?
+    );
+
+    let again = db::backup::import_user(&restored, &dump, true).await;
+    assert!(matches!(again, Err(Error::ConstraintViolation { .. })));
+    Ok(())
+}
+
+#[tokio::test]
+async fn backups_round_trip_everything_a_user_owns() -> anyhow::Result<()> {
+    let db = database().await?;
+    let owner_id = create_user(&db).await?;
+    let member_id = create_user(&db).await?;
+    let first = bookmark::save(
+        &db,
+        &new_bookmark(owner_id, "https://example.com/first", "First", &[]),
+        "First text",
+    )
+    .await?;
+    let second = bookmark::save(
+        &db,
+        &new_bookmark(owner_id, "https://example.com/second", "Second", &[]),
+        "Second text",
+    )
+    .await?;
+    bookmark::refresh_capture(&db, &first, "Refreshed text", Some("<p>First text</p>")).await?;
+    bookmark::mark_read(&db, owner_id, &first.bookmark_id).await?;
+    collection::create(
+        &db,
+        owner_id,
+        &CollectionRequest {
+            name: "Reading list".to_string(),
+            description: Some("Later".to_string()),
+            bookmark_ids: vec![second.bookmark_id.clone(), first.bookmark_id.clone()],
+        },
+    )
+    .await?;
+    rule::create(
+        &db,
+        owner_id,
+        &RuleRequest {
+            name: "Papers".to_string(),
+            enabled: None,
+            conditions: RuleConditions {
+                domain: Some("arxiv.org".to_string()),
+                ..Default::default()
+            },
+            actions: RuleActions {
+                add_tags: vec!["paper".to_string()],
+                favorite: true,
+                skip_ai: false,
+            },
+        },
+    )
+    .await?;
+    extraction_rule::create(
+        &db,
+        owner_id,
+        &ExtractionRuleRequest {
+            domain: "docs.example.com".to_string(),
+            keep_selectors: vec!["main".to_string()],
+            drop_selectors: vec!["nav".to_string()],
+            title_selector: Some("h1".to_string()),
+        },
+    )
+    .await?;
+    fetch_credential::replace(&db, owner_id, vec![("example.com".to_string(), vec![1, 2])]).await?;
+    let team = workspace::create(&db, owner_id, "Team").await?;
+    workspace::set_member(&db, team.workspace_id, member_id, WorkspaceRole::Editor).await?;
+    let shared = new_bookmark(member_id, "https://example.org/shared", "Shared", &[]);
+    bookmark::save(&db, &shared, "Shared text").await?;
+    bookmark::set_visibility(&db, member_id, &shared.bookmark_id, Visibility::Workspace).await?;
+    workspace::add_bookmark(&db, team.workspace_id, member_id, &shared.bookmark_id).await?;
+
+    let owner = db::backup::export_user(&db, owner_id).await?;
+    let member = db::backup::export_user(&db, member_id).await?;
+    assert_eq!(owner.bookmarks[0].versions.len(), 1);
+    assert_eq!(
+        owner.collections[0].bookmark_ids,
+        vec![second.bookmark_id.clone(), first.bookmark_id.clone()]
+    );
+    assert_eq!(member.workspace_memberships.len(), 1);
+    assert_eq!(member.workspace_bookmarks.len(), 1);
+
+    // The member comes first, their workspace only exists once the owner is in
+    let restored = database().await?;
+    for dump in [&member, &owner] {
+        db::backup::import_user(&restored, dump, true).await?;
+    }
+    for dump in [&member, &owner] {
+        db::backup::import_workspace_memberships(
+            &restored,
+            dump.user.user_id,
+            &dump.workspace_memberships,
+            &dump.workspace_bookmarks,
+        )
+        .await?;
+    }
+    for dump in [owner, member] {
+        let again = db::backup::export_user(&restored, dump.user.user_id).await?;
+        assert_eq!(serde_json::to_value(again)?, serde_json::to_value(dump)?);
+    }
+    Ok(())
+}
@@ -0,0 +1,377 @@
+mod common;
+
+use std::collections::HashMap;
+
+use chrono::{Duration, Utc};
+use common::sqlite::{create_user, database, new_bookmark};
+use server::db::{bookmark, search};
+use server::error::Error;
+use shared::{Bookmark, BulkBookmarkAction, SearchRequest, TagOperation};
+use url::Url;
+
+#[tokio::test]
+async fn bookmarks_round_trip_and_reject_duplicates() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+
+    let new = new_bookmark(
+        user_id,
+        "https://example.com/post#comments",
+        "Post",
+        &["Rust"],
+    );
+    let saved = bookmark::save(&db, &new, "Some text").await?;
+    assert_eq!(saved.tags, Some(vec!["rust".to_string()]));
+
+    let duplicate = new_bookmark(user_id, "https://example.com/post", "Again", &[]);
+    let result = bookmark::save(&db, &duplicate, "Some text").await;
+    assert!(matches!(
+        result,
+        Err(Error::ConstraintViolation { ref constraint, .. }) if constraint == "duplicate_bookmark"
+    ));
+
+    let found =
+        bookmark::get_by_canonical_url_and_user_id(&db, "https://example.com/post", user_id)
+            .await?;
+    assert_eq!(
+        found.map(|b| b.bookmark_id),
+        Some(saved.bookmark_id.clone())
+    );
+
+    let updated = bookmark::update_tags(
+        &db,
+        user_id,
+        &saved.bookmark_id,
+        &TagOperation::Append(vec!["web".to_string(), "rust".to_string()]),
+    )
+    .await?;
+    assert_eq!(
+        updated.tags,
+        Some(vec!["rust".to_string(), "web".to_string()])
+    );
+    assert_eq!(bookmark::get_by_tag(&db, user_id, "web").await?.len(), 1);
+    assert_eq!(
+        bookmark::get_text_content(&db, user_id, &saved.bookmark_id).await?,
+        Some("Some text".to_string())
+    );
+
+    assert!(bookmark::delete(&db, user_id, &saved.bookmark_id).await?);
+    assert!(bookmark::get_by_user(&db, user_id).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn tags_are_renamed_and_merged() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let other_user_id = create_user(&db).await?;
+    for (user_id, url, tags) in [
+        (user_id, "https://example.com/1", &["rustlang", "web"][..]),
+        (user_id, "https://example.com/2", &["rustlang", "rust"]),
+        (user_id, "https://example.com/3", &["web"]),
+        (other_user_id, "https://example.com/1", &["rustlang"]),
+    ] {
+        bookmark::save(&db, &new_bookmark(user_id, url, "Post", tags), "text").await?;
+    }
+
+    assert_eq!(
+        bookmark::rename_tag(&db, user_id, "rustlang", "rust").await?,
+        2
+    );
+    let counts: HashMap<_, _> = bookmark::get_tag_count_by_user(&db, user_id)
+        .await?
+        .into_iter()
+        .collect();
+    assert_eq!(
+        counts,
+        HashMap::from([("rust".to_string(), 2), ("web".to_string(), 2)])
+    );
+    assert_eq!(
+        bookmark::get_by_tag(&db, other_user_id, "rustlang")
+            .await?
+            .len(),
+        1
+    );
+    assert_eq!(
+        bookmark::rename_tag(&db, user_id, "rustlang", "rust").await?,
+        0
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn edited_titles_keep_the_extracted_one() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let saved = bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://example.com/page", "Extracted", &[]),
+        "Text about kernels",
+    )
+    .await?;
+    let other = bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://example.com/other", "Other", &[]),
+        "Text",
+    )
+    .await?;
+
+    let edited = bookmark::edit(&db, user_id, &saved.bookmark_id, Some("Edited"), None)
+        .await?
+        .expect("bookmark edited");
+    assert_eq!(edited.title, "Edited");
+    assert_eq!(edited.original_title.as_deref(), Some("Extracted"));
+    let edited = bookmark::edit(&db, user_id, &saved.bookmark_id, Some("Edited again"), None)
+        .await?
+        .expect("bookmark edited");
+    assert_eq!(edited.original_title.as_deref(), Some("Extracted"));
+    let request = SearchRequest {
+        query: Some("again".to_string()),
+        tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
+        limit: None,
+        offset: None,
+    };
+    assert_eq!(search::search(&db, user_id, &request).await?.total, 1);
+
+    let moved = Url::parse("https://Docs.example.org/page#intro")?;
+    let edited = bookmark::edit(&db, user_id, &saved.bookmark_id, None, Some(&moved))
+        .await?
+        .expect("bookmark edited");
+    assert_eq!(edited.title, "Edited again");
+    assert_eq!(edited.domain, "docs.example.org");
+    assert!(bookmark::get_by_canonical_url_and_user_id(
+        &db,
+        "https://docs.example.org/page",
+        user_id
+    )
+    .await?
+    .is_some());
+    let taken = Url::parse("https://example.com/other")?;
+    let error = bookmark::edit(&db, user_id, &saved.bookmark_id, None, Some(&taken))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, Error::ConstraintViolation { ref constraint, .. } if constraint == "duplicate_bookmark")
+    );
+    // Adding the URL edited away again finds its id taken
+    let mut readded = new_bookmark(user_id, "https://example.com/page", "Readded", &[]);
+    readded.bookmark_id = saved.bookmark_id.clone();
+    let error = bookmark::save(&db, &readded, "Text").await.unwrap_err();
+    assert!(
+        matches!(error, Error::ConstraintViolation { ref constraint, .. } if constraint == "duplicate_bookmark")
+    );
+    assert!(bookmark::edit(
+        &db,
+        create_user(&db).await?,
+        &other.bookmark_id,
+        Some("x"),
+        None
+    )
+    .await?
+    .is_none());
+
+    let mut refreshed = edited.clone();
+    refreshed.title = "Extracted anew".into();
+    let refreshed = bookmark::refresh_capture(&db, &refreshed, "New text", None).await?;
+    assert_eq!(refreshed.title, "Edited again");
+    assert_eq!(refreshed.original_title.as_deref(), Some("Extracted anew"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn random_pick_keeps_to_unread_bookmarks_when_asked() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let read = bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://example.com/read", "Read", &["rust"]),
+        "Text",
+    )
+    .await?;
+    let unread = bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://example.com/unread", "Unread", &[]),
+        "Text",
+    )
+    .await?;
+
+    assert!(bookmark::mark_read(&db, user_id, &read.bookmark_id).await?);
+    assert!(bookmark::mark_read(&db, user_id, &read.bookmark_id).await?);
+    assert!(!bookmark::mark_read(&db, user_id, "missing").await?);
+    let picked = bookmark::get_random(&db, user_id, None, true).await?;
+    assert_eq!(picked.map(|b| b.bookmark_id), Some(unread.bookmark_id));
+    assert!(bookmark::get_random(&db, user_id, Some("rust"), true)
+        .await?
+        .is_none());
+    let picked = bookmark::get_random(&db, user_id, Some("rust"), false).await?;
+    assert_eq!(picked.map(|b| b.bookmark_id), Some(read.bookmark_id));
+    Ok(())
+}
+
+#[tokio::test]
+async fn wallabag_entries_are_found_by_their_id() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let other_id = create_user(&db).await?;
+    let saved = bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://example.com/entry", "Entry", &[]),
+        "Text",
+    )
+    .await?;
+    let entry_id = bookmark::wallabag_entry_id(&saved.bookmark_id);
+
+    let found = bookmark::get_by_wallabag_entry_id(&db, user_id, entry_id).await?;
+    assert_eq!(found.map(|b| b.bookmark_id), Some(saved.bookmark_id));
+    assert!(bookmark::get_by_wallabag_entry_id(&db, other_id, entry_id)
+        .await?
+        .is_none());
+    assert!(
+        bookmark::get_by_wallabag_entry_id(&db, user_id, entry_id + 1)
+            .await?
+            .is_none()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn trashed_bookmarks_are_hidden_until_restored_or_purged() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let kept = bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://example.com/kept", "Kept", &["rust"]),
+        "Text",
+    )
+    .await?;
+    let trashed = bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://example.com/trashed", "Trashed", &["rust"]),
+        "Text",
+    )
+    .await?;
+
+    assert!(bookmark::move_to_trash(&db, user_id, &trashed.bookmark_id).await?);
+    assert!(!bookmark::move_to_trash(&db, user_id, &trashed.bookmark_id).await?);
+    let listed = bookmark::get_by_user(&db, user_id).await?;
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].bookmark_id, kept.bookmark_id);
+    assert!(
+        bookmark::get_with_user_data(&db, user_id, &trashed.bookmark_id)
+            .await?
+            .is_none()
+    );
+    let request = SearchRequest {
+        query: None,
+        tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
+        limit: None,
+        offset: None,
+    };
+    assert_eq!(search::search(&db, user_id, &request).await?.total, 1);
+
+    let trash = bookmark::get_trash(&db, user_id).await?;
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0].0.bookmark_id, trashed.bookmark_id);
+    assert!(bookmark::get_expired_trash(&db, trash[0].1, 10)
+        .await?
+        .is_empty());
+    assert!(!bookmark::purge(&db, user_id, &kept.bookmark_id).await?);
+
+    let restored = bookmark::restore(&db, user_id, &trashed.bookmark_id).await?;
+    assert_eq!(
+        restored.map(|b| b.bookmark_id),
+        Some(trashed.bookmark_id.clone())
+    );
+    assert_eq!(bookmark::get_by_user(&db, user_id).await?.len(), 2);
+
+    bookmark::move_to_trash(&db, user_id, &trashed.bookmark_id).await?;
+    assert!(bookmark::restore_by_canonical_url(&db, user_id, "https://example.com/trashed").await?);
+    bookmark::move_to_trash(&db, user_id, &trashed.bookmark_id).await?;
+    let expired = bookmark::get_expired_trash(&db, Utc::now() + Duration::days(1), 10).await?;
+    assert_eq!(expired, vec![(user_id, trashed.bookmark_id.clone())]);
+    assert!(bookmark::purge(&db, user_id, &trashed.bookmark_id).await?);
+    assert!(bookmark::get_trash(&db, user_id).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn bulk_updates_leave_other_users_and_the_trash_alone() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let other_user_id = create_user(&db).await?;
+    let tagged = new_bookmark(user_id, "https://example.com/tagged", "Tagged", &["rust"]);
+    let untagged = new_bookmark(user_id, "https://example.com/untagged", "Untagged", &[]);
+    let trashed = new_bookmark(user_id, "https://example.com/trashed", "Trashed", &[]);
+    let others = new_bookmark(other_user_id, "https://example.com/other", "Other", &[]);
+    for bookmark in [&tagged, &untagged, &trashed, &others] {
+        bookmark::save(&db, bookmark, "Text").await?;
+    }
+    bookmark::move_to_trash(&db, user_id, &trashed.bookmark_id).await?;
+    let bookmark_ids: Vec<String> = [&tagged, &untagged, &trashed, &others]
+        .iter()
+        .map(|bookmark| bookmark.bookmark_id.clone())
+        .chain(["missing".to_string()])
+        .collect();
+
+    let add_tags = BulkBookmarkAction::AddTags {
+        tags: vec!["rust".to_string(), "web".to_string()],
+    };
+    assert_eq!(
+        bookmark::bulk_update(&db, user_id, &bookmark_ids, &add_tags).await?,
+        2
+    );
+    let tags_of = |bookmark: Option<Bookmark>| bookmark.and_then(|bookmark| bookmark.tags);
+    assert_eq!(
+        tags_of(bookmark::get_with_user_data(&db, user_id, &tagged.bookmark_id).await?),
+        Some(vec!["rust".to_string(), "web".to_string()])
+    );
+    assert_eq!(
+        tags_of(bookmark::get_with_user_data(&db, user_id, &untagged.bookmark_id).await?),
+        Some(vec!["rust".to_string(), "web".to_string()])
+    );
+    assert_eq!(
+        tags_of(bookmark::get_with_user_data(&db, other_user_id, &others.bookmark_id).await?),
+        None
+    );
+
+    assert_eq!(
+        bookmark::bulk_update(&db, user_id, &bookmark_ids, &BulkBookmarkAction::Favorite).await?,
+        2
+    );
+    assert!(
+        bookmark::get_with_user_data(&db, user_id, &untagged.bookmark_id)
+            .await?
+            .expect("bookmark")
+            .favorite
+    );
+    assert_eq!(
+        bookmark::bulk_update(&db, user_id, &bookmark_ids, &BulkBookmarkAction::Trash).await?,
+        2
+    );
+    assert!(bookmark::get_by_user(&db, user_id).await?.is_empty());
+    assert_eq!(bookmark::get_trash(&db, user_id).await?.len(), 3);
+    assert_eq!(bookmark::get_by_user(&db, other_user_id).await?.len(), 1);
+    Ok(())
+}
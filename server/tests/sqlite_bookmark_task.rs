@@ -0,0 +1,260 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use common::sqlite::{create_user, database, new_bookmark};
+use server::db::{bookmark, bookmark_task, rag};
+use shared::{BookmarkTaskSearchRequest, BookmarkTaskStatus};
+use url::Url;
+
+/// Server instance leasing tasks.
+const OWNER: &str = "replica-a";
+const LEASE: Duration = Duration::minutes(5);
+
+#[tokio::test]
+async fn retention_deletes_finished_tasks_and_old_sessions() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let url = Url::parse("https://example.com")?;
+    let done = bookmark_task::create(&db, user_id, url.clone(), vec![]).await?;
+    bookmark_task::lease(&db, OWNER, Utc::now() + Duration::seconds(1), LEASE).await?;
+    bookmark_task::update(
+        &db,
+        OWNER,
+        done.clone(),
+        BookmarkTaskStatus::Done,
+        None,
+        None,
+    )
+    .await?;
+    let pending = bookmark_task::create(&db, user_id, url, vec![]).await?;
+    rag::create_rag_session(&db, user_id, "why?").await?;
+
+    let later = Utc::now() + Duration::seconds(1);
+    assert_eq!(
+        bookmark_task::delete_finished(&db, Utc::now() - Duration::days(1), 10).await?,
+        0
+    );
+    assert_eq!(bookmark_task::delete_finished(&db, later, 10).await?, 1);
+    assert!(bookmark_task::get_by_id(&db, user_id, done.task_id)
+        .await?
+        .is_none());
+    assert!(bookmark_task::get_by_id(&db, user_id, pending.task_id)
+        .await?
+        .is_some());
+    assert_eq!(rag::delete_sessions_before(&db, later, 10).await?, 1);
+    assert_eq!(rag::delete_sessions_before(&db, later, 10).await?, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn batches_queue_tasks_and_find_saved_urls() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let saved = new_bookmark(user_id, "https://example.com/saved", "Saved", &[]);
+    bookmark::save(&db, &saved, "text").await?;
+
+    let existing = bookmark::existing_canonical_urls(
+        &db,
+        user_id,
+        &[
+            "https://example.com/saved".to_string(),
+            "https://example.com/new".to_string(),
+        ],
+    )
+    .await?;
+    assert_eq!(
+        existing.into_iter().collect::<Vec<_>>(),
+        vec!["https://example.com/saved".to_string()]
+    );
+
+    let tasks = bookmark_task::create_batch(
+        &db,
+        user_id,
+        vec![
+            Url::parse("https://example.com/new")?,
+            Url::parse("https://example.org/other")?,
+        ],
+        vec!["to-read".to_string()],
+        false,
+    )
+    .await?;
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[1].url, "https://example.org/other");
+    assert_eq!(tasks[0].tags, Some(vec!["to-read".to_string()]));
+    assert_eq!(
+        bookmark_task::lease(&db, OWNER, Utc::now() + Duration::seconds(1), LEASE)
+            .await?
+            .len(),
+        2
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn tasks_are_delivered_once_and_updated() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let task = bookmark_task::create(
+        &db,
+        user_id,
+        Url::parse("https://example.com")?,
+        vec!["tag".to_string()],
+    )
+    .await?;
+    assert_eq!(task.status, BookmarkTaskStatus::Pending);
+    assert_eq!(task.tags, Some(vec!["tag".to_string()]));
+
+    let now = Utc::now() + Duration::seconds(1);
+    let delivered = bookmark_task::lease(&db, OWNER, now, LEASE).await?;
+    assert_eq!(delivered.len(), 1);
+    assert!(bookmark_task::lease(&db, OWNER, now, LEASE)
+        .await?
+        .is_empty());
+
+    bookmark_task::update(
+        &db,
+        OWNER,
+        task.clone(),
+        BookmarkTaskStatus::Done,
+        None,
+        None,
+    )
+    .await?;
+    let stored = bookmark_task::get_by_id(&db, user_id, task.task_id)
+        .await?
+        .expect("task exists");
+    assert_eq!(stored.status, BookmarkTaskStatus::Done);
+    Ok(())
+}
+
+#[tokio::test]
+async fn task_search_counts_every_page_per_status() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let url = Url::parse("https://example.com")?;
+    for _ in 0..3 {
+        bookmark_task::create(&db, user_id, url.clone(), vec![]).await?;
+    }
+    let failed = bookmark_task::create(&db, user_id, url, vec![]).await?;
+    bookmark_task::lease(&db, OWNER, Utc::now() + Duration::seconds(1), LEASE).await?;
+    bookmark_task::update(&db, OWNER, failed, BookmarkTaskStatus::Fail, None, None).await?;
+
+    let request = BookmarkTaskSearchRequest {
+        page_size: Some(1),
+        include_total: true,
+        ..Default::default()
+    };
+    let response = bookmark_task::search(&db, user_id, &request).await?;
+    assert_eq!(response.tasks.len(), 1);
+    assert_eq!(response.total_count, Some(4));
+    let counts: Vec<_> = response
+        .status_counts
+        .iter()
+        .map(|counted| (counted.status.clone(), counted.count))
+        .collect();
+    assert_eq!(
+        counts,
+        vec![
+            (BookmarkTaskStatus::Pending, 3),
+            (BookmarkTaskStatus::Fail, 1)
+        ]
+    );
+
+    let request = BookmarkTaskSearchRequest {
+        status: Some(BookmarkTaskStatus::Pending),
+        ..request
+    };
+    let response = bookmark_task::search(&db, user_id, &request).await?;
+    assert_eq!(response.total_count, Some(3));
+    assert_eq!(response.status_counts.len(), 2);
+
+    let request = BookmarkTaskSearchRequest {
+        include_total: false,
+        ..request
+    };
+    let response = bookmark_task::search(&db, user_id, &request).await?;
+    assert_eq!(response.total_count, None);
+    assert!(response.status_counts.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn task_leases_expire_and_fence_outcomes() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let task =
+        bookmark_task::create(&db, user_id, Url::parse("https://example.com")?, vec![]).await?;
+    let now = Utc::now() + Duration::seconds(1);
+
+    assert_eq!(bookmark_task::lease(&db, OWNER, now, LEASE).await?.len(), 1);
+    assert!(bookmark_task::lease(&db, "replica-b", now, LEASE)
+        .await?
+        .is_empty());
+    let heartbeat = now + Duration::minutes(4);
+    assert_eq!(
+        bookmark_task::renew_leases(&db, OWNER, &[task.task_id], heartbeat + LEASE).await?,
+        1
+    );
+    assert!(
+        bookmark_task::lease(&db, "replica-b", now + Duration::minutes(6), LEASE)
+            .await?
+            .is_empty()
+    );
+
+    let expired = heartbeat + LEASE + Duration::seconds(1);
+    assert_eq!(
+        bookmark_task::lease(&db, "replica-b", expired, LEASE)
+            .await?
+            .len(),
+        1
+    );
+    assert!(
+        !bookmark_task::update(
+            &db,
+            OWNER,
+            task.clone(),
+            BookmarkTaskStatus::Done,
+            None,
+            None
+        )
+        .await?
+    );
+    assert!(bookmark_task::schedule_retry(&db, "replica-b", &task, 1, expired).await?);
+    // The retry released the lease
+    assert_eq!(
+        bookmark_task::lease(&db, OWNER, expired, LEASE)
+            .await?
+            .len(),
+        1
+    );
+
+    // Once the new owner finished the task, the previous one's outcome is dropped
+    assert!(
+        bookmark_task::update(
+            &db,
+            OWNER,
+            task.clone(),
+            BookmarkTaskStatus::Done,
+            None,
+            None
+        )
+        .await?
+    );
+    assert!(
+        !bookmark_task::update(
+            &db,
+            "replica-b",
+            task.clone(),
+            BookmarkTaskStatus::Fail,
+            None,
+            None
+        )
+        .await?
+    );
+    assert!(!bookmark_task::schedule_retry(&db, "replica-b", &task, 2, expired).await?);
+    let stored = bookmark_task::get_by_id(&db, user_id, task.task_id)
+        .await?
+        .expect("task exists");
+    assert_eq!(stored.status, BookmarkTaskStatus::Done);
+    Ok(())
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use common::sqlite::{create_user, database, new_bookmark};
+use server::db::{bookmark, bookmark_version};
+
+#[tokio::test]
+async fn refreshed_capture_keeps_the_previous_one_as_version() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let saved = bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://example.com/page", "Old title", &[]),
+        "Old text",
+    )
+    .await?;
+
+    let mut refreshed = saved.clone();
+    refreshed.title = "New title".into();
+    let updated =
+        bookmark::refresh_capture(&db, &refreshed, "New text", Some("<p>Old text</p>")).await?;
+    assert_eq!(updated.title, "New title");
+
+    let versions = bookmark_version::list(&db, user_id, &saved.bookmark_id).await?;
+    assert_eq!(versions.len(), 1);
+    assert_eq!(versions[0].title, "Old title");
+    let version = bookmark_version::get(&db, user_id, &saved.bookmark_id, versions[0].version_id)
+        .await?
+        .expect("version");
+    assert_eq!(version.text_content, "Old text");
+    assert_eq!(version.html.as_deref(), Some("<p>Old text</p>"));
+    assert_eq!(
+        bookmark::get_text_content(&db, user_id, &saved.bookmark_id).await?,
+        Some("New text".into())
+    );
+    Ok(())
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use common::sqlite::{create_user, database, new_bookmark};
+use server::db::{bookmark, collection, search};
+use server::error::Error;
+use shared::{CollectionRequest, SearchRequest};
+
+#[tokio::test]
+async fn collections_keep_order_and_filter_search() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+
+    let mut ids = vec![];
+    for url in [
+        "https://example.com/one",
+        "https://example.com/two",
+        "https://example.com/three",
+    ] {
+        let saved = bookmark::save(&db, &new_bookmark(user_id, url, url, &[]), "Text").await?;
+        ids.push(saved.bookmark_id);
+    }
+    let request = |bookmark_ids: Vec<String>| CollectionRequest {
+        name: "Reading list".to_string(),
+        description: None,
+        bookmark_ids,
+    };
+    let created =
+        collection::create(&db, user_id, &request(vec![ids[2].clone(), ids[0].clone()])).await?;
+    assert_eq!(created.bookmark_ids, vec![ids[2].clone(), ids[0].clone()]);
+    let in_order = bookmark::get_by_collection(&db, user_id, created.collection_id).await?;
+    assert_eq!(in_order[0].bookmark_id, ids[2]);
+
+    let unknown = collection::create(&db, user_id, &request(vec!["missing".to_string()])).await;
+    assert!(matches!(unknown, Err(Error::UnprocessableEntity { .. })));
+    let other_user = create_user(&db).await?;
+    assert!(collection::get(&db, other_user, created.collection_id)
+        .await?
+        .is_none());
+
+    let search_request = SearchRequest {
+        query: None,
+        tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: Some(created.collection_id),
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
+        limit: None,
+        offset: None,
+    };
+    assert_eq!(
+        search::search(&db, user_id, &search_request).await?.total,
+        2
+    );
+
+    let updated = collection::update(
+        &db,
+        user_id,
+        created.collection_id,
+        &request(vec![ids[1].clone()]),
+    )
+    .await?
+    .expect("collection exists");
+    assert_eq!(updated.bookmark_ids, vec![ids[1].clone()]);
+    assert!(updated.updated_at.is_some());
+
+    // Deleting a bookmark takes it out of its collections
+    bookmark::delete(&db, user_id, &ids[1]).await?;
+    let emptied = collection::get(&db, user_id, created.collection_id)
+        .await?
+        .expect("collection exists");
+    assert!(emptied.bookmark_ids.is_empty());
+    assert!(collection::delete(&db, user_id, created.collection_id).await?);
+    assert!(collection::list(&db, user_id).await?.is_empty());
+    Ok(())
+}
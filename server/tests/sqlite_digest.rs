@@ -0,0 +1,63 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use common::sqlite::{create_user, database, new_bookmark};
+use server::db::{bookmark, digest, rag, storage};
+use shared::RagHistoryRequest;
+
+#[tokio::test]
+async fn digests_sessions_and_storage() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let saved = bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://example.com/a", "A", &[]),
+        "12345",
+    )
+    .await?;
+
+    let period_end = Utc::now() + Duration::minutes(1);
+    let period_start = period_end - Duration::days(7);
+    assert_eq!(
+        digest::users_due(&db, period_start, period_end).await?,
+        vec![user_id]
+    );
+    let ids = vec![saved.bookmark_id.clone()];
+    let stored = digest::insert(&db, user_id, period_start, period_end, &ids, "digest").await?;
+    assert!(stored.is_some());
+    assert!(
+        digest::insert(&db, user_id, period_start, period_end, &ids, "again")
+            .await?
+            .is_none()
+    );
+    assert!(digest::users_due(&db, period_start, period_end)
+        .await?
+        .is_empty());
+
+    let first = digest::issue_feed_token(&db, user_id).await?;
+    assert_eq!(digest::feed_token_user(&db, &first).await?, Some(user_id));
+    let second = digest::issue_feed_token(&db, user_id).await?;
+    assert_eq!(digest::feed_token_user(&db, &first).await?, None);
+    assert_eq!(digest::feed_token_user(&db, &second).await?, Some(user_id));
+
+    let session = rag::create_rag_session(&db, user_id, "why?").await?;
+    assert!(session.updated_at.is_none());
+    rag::update_rag_session(&db, session.session_id, user_id, "because", &[]).await?;
+    let history = rag::get_rag_history(
+        &db,
+        user_id,
+        &RagHistoryRequest {
+            limit: None,
+            offset: None,
+            query: None,
+        },
+    )
+    .await?;
+    assert_eq!(history.total_count, 1);
+    assert_eq!(history.sessions[0].answer.as_deref(), Some("because"));
+
+    storage::set_static_bytes(&db, user_id, &saved.bookmark_id, 100).await?;
+    let usage = storage::usage(&db, user_id).await?;
+    assert_eq!((usage.static_bytes, usage.text_bytes), (100, 5));
+    Ok(())
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use common::sqlite::{create_user, database, new_bookmark};
+use server::db::{bookmark, entity, search};
+use shared::{Entity, EntityKind, SearchRequest};
+
+#[tokio::test]
+async fn entities_are_listed_and_searched() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+
+    let entity = |kind, name: &str| Entity {
+        kind,
+        name: name.to_string(),
+    };
+    let tokio = entity(EntityKind::Project, "Tokio");
+    let mut bookmark_ids = vec![];
+    for (url, entities) in [
+        (
+            "https://example.com/runtime",
+            vec![tokio.clone(), entity(EntityKind::Person, "Carl Lerche")],
+        ),
+        (
+            "https://example.com/channels",
+            vec![tokio.clone(), entity(EntityKind::Project, "Mio")],
+        ),
+        (
+            "https://example.com/kernel",
+            vec![entity(EntityKind::Organization, "Linux Foundation")],
+        ),
+    ] {
+        let saved = bookmark::save(&db, &new_bookmark(user_id, url, url, &[]), "Text").await?;
+        entity::replace_for_bookmark(&db, user_id, &saved.bookmark_id, &entities).await?;
+        bookmark_ids.push(saved.bookmark_id);
+    }
+    // Replacing drops what the previous analysis found
+    entity::replace_for_bookmark(&db, user_id, &bookmark_ids[1], std::slice::from_ref(&tokio))
+        .await?;
+
+    let entities = entity::list(&db, user_id, None, None, 10).await?;
+    assert_eq!(entities.len(), 3);
+    assert_eq!(entities[0].name, "Tokio");
+    assert_eq!(entities[0].count, 2);
+    let entities = entity::list(&db, user_id, Some(EntityKind::Person), None, 10).await?;
+    assert_eq!(entities.len(), 1);
+    let entities = entity::list(&db, user_id, None, Some("FOUND"), 10).await?;
+    assert_eq!(entities[0].name, "Linux Foundation");
+
+    let request = |entities: Vec<String>| SearchRequest {
+        query: None,
+        tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities,
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
+        limit: None,
+        offset: None,
+    };
+    let response = search::search(&db, user_id, &request(vec!["tokio".to_string()])).await?;
+    assert_eq!(response.total, 2);
+    let response = search::search(
+        &db,
+        user_id,
+        &request(vec!["tokio".to_string(), "carl lerche".to_string()]),
+    )
+    .await?;
+    assert_eq!(response.total, 1);
+    Ok(())
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use common::sqlite::{create_user, database};
+use server::db::extraction_rule;
+use shared::ExtractionRuleRequest;
+
+#[tokio::test]
+async fn extraction_rules_are_managed() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let other_user_id = create_user(&db).await?;
+    let mut request = ExtractionRuleRequest {
+        domain: "docs.example.com".to_string(),
+        keep_selectors: vec!["main".to_string()],
+        drop_selectors: vec![".sidebar".to_string(), "nav".to_string()],
+        title_selector: None,
+    };
+
+    let created = extraction_rule::create(&db, user_id, &request).await?;
+    assert_eq!(created.domain, request.domain);
+    assert_eq!(created.drop_selectors, request.drop_selectors);
+    assert_eq!(
+        extraction_rule::list(&db, user_id).await?,
+        vec![created.clone()]
+    );
+    assert!(extraction_rule::list(&db, other_user_id).await?.is_empty());
+
+    request.title_selector = Some("h1".to_string());
+    let updated = extraction_rule::update(&db, user_id, created.rule_id, &request)
+        .await?
+        .expect("rule updated");
+    assert_eq!(updated.title_selector.as_deref(), Some("h1"));
+    assert!(updated.updated_at.is_some());
+    assert_eq!(
+        extraction_rule::get(&db, user_id, created.rule_id).await?,
+        Some(updated)
+    );
+    assert!(
+        extraction_rule::update(&db, other_user_id, created.rule_id, &request)
+            .await?
+            .is_none()
+    );
+    assert!(!extraction_rule::delete(&db, other_user_id, created.rule_id).await?);
+    assert!(extraction_rule::delete(&db, user_id, created.rule_id).await?);
+    assert!(extraction_rule::list(&db, user_id).await?.is_empty());
+    Ok(())
+}
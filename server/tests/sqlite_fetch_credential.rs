@@ -0,0 +1,45 @@
+mod common;
+
+use common::sqlite::{create_user, database};
+use server::db::fetch_credential;
+
+#[tokio::test]
+async fn fetch_credentials_are_replaced_as_a_set() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let other_user_id = create_user(&db).await?;
+
+    fetch_credential::replace(
+        &db,
+        user_id,
+        vec![
+            ("news.example.com".to_string(), vec![1, 2, 3]),
+            ("example.com".to_string(), vec![4, 5]),
+        ],
+    )
+    .await?;
+    let stored = fetch_credential::list(&db, user_id).await?;
+    assert_eq!(
+        stored
+            .iter()
+            .map(|credential| (
+                credential.domain.as_str(),
+                credential.sealed_headers.clone()
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            ("example.com", vec![4, 5]),
+            ("news.example.com", vec![1, 2, 3])
+        ]
+    );
+    assert!(fetch_credential::list(&db, other_user_id).await?.is_empty());
+
+    fetch_credential::replace(&db, user_id, vec![("example.org".to_string(), vec![6])]).await?;
+    let stored = fetch_credential::list(&db, user_id).await?;
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].domain, "example.org");
+
+    fetch_credential::replace(&db, user_id, Vec::new()).await?;
+    assert!(fetch_credential::list(&db, user_id).await?.is_empty());
+    Ok(())
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use common::sqlite::{create_user, database, new_bookmark};
+use server::db::{bookmark, entity, graph, link};
+use shared::{Entity, EntityKind};
+use url::Url;
+
+#[tokio::test]
+async fn graph_loads_entities_and_links_of_recent_bookmarks() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+
+    let first = bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://one.com/first", "First", &[]),
+        "Text",
+    )
+    .await?;
+    let second = bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://two.com/second", "Second", &[]),
+        "Text",
+    )
+    .await?;
+    let tokio = Entity {
+        kind: EntityKind::Project,
+        name: "Tokio".to_string(),
+    };
+    entity::replace_for_bookmark(
+        &db,
+        user_id,
+        &first.bookmark_id,
+        std::slice::from_ref(&tokio),
+    )
+    .await?;
+    let links = vec![
+        Url::parse("https://two.com/second")?,
+        Url::parse("https://three.com/")?,
+    ];
+    link::replace_for_bookmark(&db, user_id, &first.bookmark_id, &links).await?;
+    // Replacing drops the links of the previous extraction
+    link::replace_for_bookmark(&db, user_id, &first.bookmark_id, &links[..1]).await?;
+
+    let data = graph::load(&db, user_id, 10).await?;
+    assert_eq!(data.bookmarks.len(), 2);
+    assert_eq!(data.entities, vec![(first.bookmark_id.clone(), tokio)]);
+    assert_eq!(data.links.len(), 1);
+    assert_eq!(data.links[0].url, "https://two.com/second");
+    assert_eq!(data.links[0].domain, "two.com");
+
+    let data = graph::load(&db, user_id, 1).await?;
+    assert_eq!(data.bookmarks.len(), 1);
+    assert_eq!(data.bookmarks[0].bookmark_id, second.bookmark_id);
+    assert!(data.entities.is_empty());
+    assert!(data.links.is_empty());
+    Ok(())
+}
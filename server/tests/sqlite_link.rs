@@ -0,0 +1,39 @@
+mod common;
+
+use common::sqlite::{create_user, database, new_bookmark};
+use server::db::{bookmark, link};
+use url::Url;
+
+#[tokio::test]
+async fn links_resolve_to_saved_bookmarks_and_backlinks() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+
+    let mut saved = vec![];
+    for url in [
+        "https://example.com/target",
+        "https://example.com/citing",
+        "https://example.com/unrelated",
+    ] {
+        saved.push(bookmark::save(&db, &new_bookmark(user_id, url, url, &[]), "Text").await?);
+    }
+    let links = vec![
+        Url::parse("https://example.com/target")?,
+        Url::parse("https://elsewhere.org/page")?,
+    ];
+    link::replace_for_bookmark(&db, user_id, &saved[1].bookmark_id, &links).await?;
+
+    let outbound = link::list_outbound(&db, user_id, &saved[1].bookmark_id).await?;
+    assert_eq!(outbound.len(), 2);
+    assert_eq!(outbound[0].domain, "elsewhere.org");
+    assert_eq!(outbound[0].bookmark_id, None);
+    assert_eq!(outbound[1].bookmark_id, Some(saved[0].bookmark_id.clone()));
+
+    let backlinks = bookmark::get_backlinks(&db, user_id, &saved[0].bookmark_id).await?;
+    assert_eq!(backlinks.len(), 1);
+    assert_eq!(backlinks[0].bookmark_id, saved[1].bookmark_id);
+    assert!(bookmark::get_backlinks(&db, user_id, &saved[2].bookmark_id)
+        .await?
+        .is_empty());
+    Ok(())
+}
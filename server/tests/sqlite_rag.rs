@@ -0,0 +1,359 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use common::sqlite::{create_user, database, new_bookmark};
+use server::db::{self, bookmark, chunks, rag, user, EmbeddingProfile};
+use shared::{RagFeedbackRequest, RagHistoryRequest, RagRating};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn chunks_are_searched_by_similarity() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let first = bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://example.com/a", "A", &[]),
+        "text",
+    )
+    .await?;
+    let second = bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://example.com/b", "B", &[]),
+        "text",
+    )
+    .await?;
+
+    let stored = chunks::store_chunks_with_embeddings(
+        &db,
+        &first.bookmark_id,
+        user_id,
+        vec!["ownership and borrowing".to_string()],
+        vec![vec![1.0, 0.0, 0.0]],
+    )
+    .await?;
+    chunks::store_chunks_with_embeddings(
+        &db,
+        &second.bookmark_id,
+        user_id,
+        vec!["garbage collection".to_string()],
+        vec![vec![0.8, 0.6, 0.0]],
+    )
+    .await?;
+    assert!(chunks::has_chunks_for_bookmark(&db, &first.bookmark_id, user_id).await?);
+
+    let matches = chunks::search_similar_chunks(
+        &db,
+        user_id,
+        vec![1.0, 0.0, 0.0],
+        3,
+        100,
+        10,
+        0.0,
+        Default::default(),
+    )
+    .await?;
+    assert_eq!(matches.len(), 2);
+    assert_eq!(matches[0].chunk.chunk_id, stored[0].chunk_id);
+    assert!((matches[0].similarity_score - 1.0).abs() < 1e-6);
+
+    let hybrid = chunks::search_chunks_hybrid(
+        &db,
+        user_id,
+        "garbage",
+        vec![1.0, 0.0, 0.0],
+        3,
+        100,
+        10,
+        0.0,
+        Default::default(),
+    )
+    .await?;
+    let garbage = hybrid
+        .iter()
+        .find(|found| found.bookmark.bookmark_id == second.bookmark_id)
+        .expect("full-text match");
+    assert_eq!(garbage.fts_rank, Some(1));
+
+    let related = chunks::find_related_bookmarks(&db, user_id, &first.bookmark_id, 5).await?;
+    assert_eq!(related.len(), 1);
+    assert_eq!(related[0].bookmark.bookmark_id, second.bookmark_id);
+
+    let chunk_ids = [stored[0].chunk_id];
+    assert_eq!(
+        chunks::get_chunks_with_bookmarks_by_ids(&db, user_id, &chunk_ids)
+            .await?
+            .len(),
+        1
+    );
+    assert!(bookmark::move_to_trash(&db, user_id, &first.bookmark_id).await?);
+    assert!(
+        chunks::get_chunks_with_bookmarks_by_ids(&db, user_id, &chunk_ids)
+            .await?
+            .is_empty()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn embedding_profile_change_clears_chunks() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let saved = bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://example.com/a", "A", &[]),
+        "text",
+    )
+    .await?;
+    let profile = EmbeddingProfile {
+        provider: "ollama".to_string(),
+        model: "a".to_string(),
+        dimensions: 2,
+    };
+    db::reconcile_embedding_profile(&db, &profile).await?;
+    db::ensure_embedding_profile(&db, &profile).await?;
+    chunks::store_chunks_with_embeddings(
+        &db,
+        &saved.bookmark_id,
+        user_id,
+        vec!["chunk".to_string()],
+        vec![vec![1.0, 0.0]],
+    )
+    .await?;
+
+    let other = EmbeddingProfile {
+        model: "b".to_string(),
+        ..profile.clone()
+    };
+    assert!(db::ensure_embedding_profile(&db, &other).await.is_err());
+    db::reconcile_embedding_profile(&db, &other).await?;
+    assert_eq!(db::stored_embedding_profile(&db).await?, Some(other));
+    assert!(!chunks::has_chunks_for_bookmark(&db, &saved.bookmark_id, user_id).await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn chunk_details_report_their_embedding() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let saved = bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://example.com/a", "A", &[]),
+        "text",
+    )
+    .await?;
+    let store = || {
+        chunks::store_chunks_with_embeddings(
+            &db,
+            &saved.bookmark_id,
+            user_id,
+            vec!["second".to_string(), "first".to_string()],
+            vec![vec![1.0, 0.0], vec![0.0, 1.0]],
+        )
+    };
+    store().await?;
+    let details = chunks::chunk_details(&db, user_id, &saved.bookmark_id).await?;
+    assert_eq!(details.len(), 2);
+    assert_eq!(details[1].chunk.chunk_index, 1);
+    // Vectors of an unknown profile aren't attributed to any model
+    assert!(details[0].embedding.is_none());
+
+    let profile = EmbeddingProfile {
+        provider: "ollama".to_string(),
+        model: "a".to_string(),
+        dimensions: 2,
+    };
+    db::reconcile_embedding_profile(&db, &profile).await?;
+    store().await?;
+    let details = chunks::chunk_details(&db, user_id, &saved.bookmark_id).await?;
+    let embedding = details[0].embedding.clone().expect("embedded chunk");
+    assert_eq!((embedding.model.as_str(), embedding.dimensions), ("a", 2));
+    assert!(
+        chunks::chunk_details(&db, create_user(&db).await?, &saved.bookmark_id)
+            .await?
+            .is_empty()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn embeddings_are_cached_per_user_and_profile() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let other_user_id = create_user(&db).await?;
+    let profile = EmbeddingProfile {
+        provider: "ollama".to_string(),
+        model: "a".to_string(),
+        dimensions: 2,
+    };
+    let hashes = vec!["hash-a".to_string(), "hash-b".to_string()];
+    assert!(
+        chunks::get_cached_embeddings(&db, user_id, &profile, &hashes)
+            .await?
+            .is_empty()
+    );
+
+    let entry = |embedding: Vec<f32>| vec![("hash-a".to_string(), embedding)];
+    chunks::cache_embeddings(&db, user_id, &profile, entry(vec![1.0, 0.0])).await?;
+    // An entry already cached is kept
+    chunks::cache_embeddings(&db, user_id, &profile, entry(vec![0.0, 1.0])).await?;
+    let cached = chunks::get_cached_embeddings(&db, user_id, &profile, &hashes).await?;
+    assert_eq!(cached.len(), 1);
+    assert_eq!(cached["hash-a"], vec![1.0, 0.0]);
+
+    let other = EmbeddingProfile {
+        model: "b".to_string(),
+        ..profile.clone()
+    };
+    assert!(chunks::get_cached_embeddings(&db, user_id, &other, &hashes)
+        .await?
+        .is_empty());
+    assert!(
+        chunks::get_cached_embeddings(&db, other_user_id, &profile, &hashes)
+            .await?
+            .is_empty()
+    );
+
+    // Entries go away once unused for long, or with their user
+    chunks::cache_embeddings(&db, other_user_id, &profile, entry(vec![0.0, 1.0])).await?;
+    let past = Utc::now() - Duration::days(1);
+    assert_eq!(
+        chunks::delete_cached_embeddings_before(&db, past, 10).await?,
+        0
+    );
+    assert!(user::delete(&db, &other_user_id).await?);
+    let future = Utc::now() + Duration::days(1);
+    assert_eq!(
+        chunks::delete_cached_embeddings_before(&db, future, 10).await?,
+        1
+    );
+    assert!(
+        chunks::get_cached_embeddings(&db, user_id, &profile, &hashes)
+            .await?
+            .is_empty()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn rag_answers_are_rated_and_reported() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let first = rag::create_rag_session(&db, user_id, "why?").await?;
+    rag::update_rag_session(&db, first.session_id, user_id, "because", &[]).await?;
+    let second = rag::create_rag_session(&db, user_id, "how?").await?;
+    rag::update_rag_session(&db, second.session_id, user_id, "somehow", &[]).await?;
+    rag::create_rag_session(&db, user_id, "unanswered").await?;
+
+    let down = RagFeedbackRequest {
+        rating: RagRating::Down,
+        comment: Some("too vague".to_string()),
+    };
+    rag::set_feedback(&db, first.session_id, user_id, &down).await?;
+    let up = RagFeedbackRequest {
+        rating: RagRating::Up,
+        comment: None,
+    };
+    let rated = rag::set_feedback(&db, first.session_id, user_id, &up)
+        .await?
+        .and_then(|session| session.feedback)
+        .expect("rated");
+    assert_eq!((rated.rating, rated.comment), (RagRating::Up, None));
+    rag::set_feedback(&db, second.session_id, user_id, &down).await?;
+    assert!(
+        rag::set_feedback(&db, first.session_id, Uuid::new_v4(), &up)
+            .await?
+            .is_none()
+    );
+
+    let history = rag::get_rag_history(
+        &db,
+        user_id,
+        &RagHistoryRequest {
+            limit: None,
+            offset: None,
+            query: None,
+        },
+    )
+    .await?;
+    assert_eq!(
+        history
+            .sessions
+            .iter()
+            .filter_map(|session| Some(session.feedback.as_ref()?.rating))
+            .collect::<Vec<_>>(),
+        vec![RagRating::Down, RagRating::Up]
+    );
+
+    let since = Utc::now().date_naive() - chrono::Days::new(1);
+    let report = rag::quality_report(&db, user_id, since, 10).await?;
+    assert_eq!(report.days.len(), 1);
+    assert_eq!(
+        (report.answered, report.rated_up, report.rated_down),
+        (2, 1, 1)
+    );
+    assert_eq!(report.approval_rate, Some(0.5));
+    assert_eq!(report.comments.len(), 1);
+    assert_eq!(report.comments[0].question, "how?");
+    assert_eq!(report.comments[0].comment, "too vague");
+    Ok(())
+}
+
+#[tokio::test]
+async fn rag_sessions_are_titled_searched_and_deleted() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let tokio = rag::create_rag_session(&db, user_id, "How does Tokio schedule tasks?").await?;
+    rag::update_rag_session(&db, tokio.session_id, user_id, "With work stealing.", &[]).await?;
+    rag::set_rag_session_title(&db, tokio.session_id, user_id, "Tokio scheduler").await?;
+    let sourdough = rag::create_rag_session(&db, user_id, "How long to proof dough?").await?;
+    rag::update_rag_session(&db, sourdough.session_id, user_id, "Overnight, cold.", &[]).await?;
+
+    let search = |query: &str| {
+        let db = db.clone();
+        let request = RagHistoryRequest {
+            limit: None,
+            offset: None,
+            query: Some(query.to_string()),
+        };
+        async move {
+            let history = rag::get_rag_history(&db, user_id, &request).await?;
+            anyhow::Ok(
+                history
+                    .sessions
+                    .into_iter()
+                    .map(|session| session.session_id)
+                    .collect::<Vec<_>>(),
+            )
+        }
+    };
+    assert_eq!(search("scheduler").await?, vec![tokio.session_id]);
+    assert_eq!(search("stealing").await?, vec![tokio.session_id]);
+    assert_eq!(search("dough -tokio").await?, vec![sourdough.session_id]);
+    assert_eq!(search("-tokio").await?, vec![sourdough.session_id]);
+    assert!(search("rayon").await?.is_empty());
+    assert_eq!(search("  ").await?.len(), 2);
+
+    let history = rag::get_rag_history(
+        &db,
+        user_id,
+        &RagHistoryRequest {
+            limit: None,
+            offset: None,
+            query: Some("tokio".to_string()),
+        },
+    )
+    .await?;
+    assert_eq!(history.total_count, 1);
+    assert_eq!(
+        history.sessions[0].title.as_deref(),
+        Some("Tokio scheduler")
+    );
+
+    assert!(!rag::delete_rag_session(&db, tokio.session_id, Uuid::new_v4()).await?);
+    assert!(rag::delete_rag_session(&db, tokio.session_id, user_id).await?);
+    assert!(rag::get_rag_session(&db, tokio.session_id, user_id)
+        .await?
+        .is_none());
+    assert!(search("scheduler").await?.is_empty());
+    Ok(())
+}
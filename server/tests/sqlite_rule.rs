@@ -0,0 +1,73 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use common::sqlite::{create_user, database, new_bookmark};
+use server::db::{ai, bookmark, rule};
+use shared::{RuleActions, RuleConditions, RuleRequest};
+
+#[tokio::test]
+async fn rules_are_managed_and_favorites_and_skipped_ai_persist() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let other_user_id = create_user(&db).await?;
+    let mut request = RuleRequest {
+        name: "Papers".to_string(),
+        enabled: None,
+        conditions: RuleConditions {
+            domain: Some("arxiv.org".to_string()),
+            ..Default::default()
+        },
+        actions: RuleActions {
+            add_tags: vec!["paper".to_string()],
+            favorite: true,
+            skip_ai: true,
+        },
+    };
+
+    let created = rule::create(&db, user_id, &request).await?;
+    assert!(created.enabled);
+    assert_eq!(created.conditions, request.conditions);
+    assert_eq!(created.actions, request.actions);
+    assert_eq!(rule::list(&db, user_id).await?, vec![created.clone()]);
+    assert!(rule::list(&db, other_user_id).await?.is_empty());
+    assert!(rule::get(&db, other_user_id, created.rule_id)
+        .await?
+        .is_none());
+
+    request.enabled = Some(false);
+    request.actions.skip_ai = false;
+    let updated = rule::update(&db, user_id, created.rule_id, &request)
+        .await?
+        .expect("rule updated");
+    assert!(!updated.enabled);
+    assert!(!updated.actions.skip_ai);
+    assert!(updated.updated_at.is_some());
+    assert_eq!(
+        rule::get(&db, user_id, created.rule_id).await?,
+        Some(updated)
+    );
+    assert!(rule::update(&db, other_user_id, created.rule_id, &request)
+        .await?
+        .is_none());
+    assert!(!rule::delete(&db, other_user_id, created.rule_id).await?);
+    assert!(rule::delete(&db, user_id, created.rule_id).await?);
+    assert!(rule::list(&db, user_id).await?.is_empty());
+
+    let mut bookmark = new_bookmark(user_id, "https://arxiv.org/abs/1", "Paper", &[]);
+    bookmark.favorite = true;
+    let saved = bookmark::save(&db, &bookmark, "Text").await?;
+    assert!(saved.favorite);
+    ai::skip_processing(&db, user_id, &saved.bookmark_id).await?;
+    let pending =
+        ai::claim_bookmarks_pending_text_ai(&db, 10, Utc::now(), Duration::minutes(5)).await?;
+    assert!(pending.is_empty());
+
+    let unmarked = bookmark::set_favorite(&db, user_id, &saved.bookmark_id, false).await?;
+    assert_eq!(unmarked.map(|b| b.favorite), Some(false));
+    assert!(
+        bookmark::set_favorite(&db, other_user_id, &saved.bookmark_id, true)
+            .await?
+            .is_none()
+    );
+    Ok(())
+}
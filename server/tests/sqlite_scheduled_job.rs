@@ -0,0 +1,33 @@
+mod common;
+
+use chrono::{Duration, SubsecRound, Utc};
+use common::sqlite::database;
+use server::db::scheduled_job;
+
+#[tokio::test]
+async fn scheduled_jobs_run_each_occurrence_once() -> anyhow::Result<()> {
+    let db = database().await?;
+    assert!(scheduled_job::last_run(&db, "trash").await?.is_none());
+
+    let due = Utc::now().trunc_subsecs(0) - Duration::minutes(5);
+    let started = due + Duration::seconds(30);
+    assert!(scheduled_job::claim(&db, "trash", due, started).await?);
+    // Another instance waking up for the same occurrence
+    assert!(!scheduled_job::claim(&db, "trash", due, started + Duration::seconds(10)).await?);
+    let run = scheduled_job::last_run(&db, "trash").await?.expect("run");
+    assert_eq!(run.last_started_at, started);
+    assert!(run.last_finished_at.is_none());
+
+    let finished = started + Duration::seconds(2);
+    scheduled_job::finish(&db, "trash", finished, Some("storage unavailable")).await?;
+    let run = scheduled_job::last_run(&db, "trash").await?.expect("run");
+    assert_eq!(run.last_finished_at, Some(finished));
+    assert_eq!(run.last_error.as_deref(), Some("storage unavailable"));
+
+    let next_due = due + Duration::hours(1);
+    assert!(scheduled_job::claim(&db, "trash", next_due, next_due).await?);
+    let run = scheduled_job::last_run(&db, "trash").await?.expect("run");
+    assert_eq!(run.last_started_at, next_due);
+    assert!(run.last_error.is_none());
+    Ok(())
+}
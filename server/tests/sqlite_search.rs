@@ -0,0 +1,328 @@
+mod common;
+
+use chrono::Utc;
+use common::sqlite::{create_user, database, new_bookmark};
+use server::db::bookmark::AiGenerationStatus;
+use server::db::{ai, bookmark, chunks, search, user};
+use shared::{BookmarkKind, ReadingTime, SearchRequest, TagFilter};
+
+#[tokio::test]
+async fn search_matches_text_filters_and_facets() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let other_user = create_user(&db).await?;
+
+    for (user_id, url, title, tags, text) in [
+        (
+            user_id,
+            "https://blog.rust-lang.org/async",
+            "Async Rust",
+            &["rust", "async"][..],
+            "Running futures on executors",
+        ),
+        (
+            user_id,
+            "https://lwn.net/kernel",
+            "Kernel news",
+            &["linux"][..],
+            "The scheduler runs tasks",
+        ),
+        (
+            other_user,
+            "https://example.com/rust",
+            "Rust elsewhere",
+            &["rust"][..],
+            "Running futures",
+        ),
+    ] {
+        bookmark::save(&db, &new_bookmark(user_id, url, title, tags), text).await?;
+    }
+
+    let request = |query: &str| SearchRequest {
+        query: Some(query.to_string()),
+        tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
+        limit: None,
+        offset: None,
+    };
+
+    // Stemmed, so "run" matches both "running" and "runs"
+    let response = search::search(&db, user_id, &request("run")).await?;
+    assert_eq!(response.total, 2);
+    assert_eq!(response.domains.len(), 2);
+
+    let response = search::search(&db, user_id, &request("futures")).await?;
+    assert_eq!(response.total, 1);
+    let item = &response.items[0];
+    assert_eq!(item.bookmark.title, "Async Rust");
+    assert_eq!(
+        item.snippets,
+        vec!["Running <mark>futures</mark> on executors"]
+    );
+    assert_eq!(item.title_highlight, None);
+    let mut tags: Vec<_> = response.tags.iter().map(|tag| tag.tag.as_str()).collect();
+    tags.sort();
+    assert_eq!(tags, ["async", "rust"]);
+
+    let response = search::search(&db, user_id, &request("-futures")).await?;
+    assert_eq!(response.total, 1);
+    assert_eq!(response.items[0].bookmark.title, "Kernel news");
+
+    let response = search::search(&db, user_id, &request("tag:linux")).await?;
+    assert_eq!(response.total, 1);
+    let response = search::search(&db, user_id, &request("-domain:lwn.net")).await?;
+    assert_eq!(response.total, 1);
+    let response = search::search(&db, user_id, &request("title:async")).await?;
+    assert_eq!(response.total, 1);
+    assert!(!response.fuzzy);
+
+    // Nothing matches the typo, the title close to it is suggested instead
+    let response = search::search(&db, user_id, &request("kernl")).await?;
+    assert!(response.fuzzy);
+    assert_eq!(response.total, 1);
+    assert_eq!(response.items[0].bookmark.title, "Kernel news");
+    let response = search::search(&db, user_id, &request("kernl tag:rust")).await?;
+    assert!(!response.fuzzy);
+    assert_eq!(response.total, 0);
+
+    let response = search::search(
+        &db,
+        user_id,
+        &SearchRequest {
+            query: None,
+            tags_filter: Some(TagFilter::Or(vec![
+                "linux".to_string(),
+                "async".to_string(),
+            ])),
+            ..request("")
+        },
+    )
+    .await?;
+    assert_eq!(response.total, 2);
+    assert_eq!(response.months.iter().map(|m| m.count).sum::<i64>(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn suggestions_complete_tags_titles_and_domains() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    for (url, title, tags) in [
+        (
+            "https://kubernetes.io/docs",
+            "Kubernetes docs",
+            &["kubernetes"][..],
+        ),
+        ("https://kubeflow.org", "Kubeflow pipelines", &["ml"][..]),
+        ("https://go.dev/blog", "Learning Go", &["go"][..]),
+    ] {
+        bookmark::save(&db, &new_bookmark(user_id, url, title, tags), "text").await?;
+    }
+
+    let suggestions = search::suggest(&db, user_id, "kube", 5).await?;
+    assert_eq!(suggestions.tags.len(), 1);
+    assert_eq!(suggestions.tags[0].tag, "kubernetes");
+    let mut titles: Vec<_> = suggestions
+        .titles
+        .iter()
+        .map(|t| t.title.as_str())
+        .collect();
+    titles.sort();
+    assert_eq!(titles, ["Kubeflow pipelines", "Kubernetes docs"]);
+    assert_eq!(suggestions.domains.len(), 2);
+
+    // A typo still finds the title, by trigram similarity
+    let suggestions = search::suggest(&db, user_id, "kuberntes", 5).await?;
+    assert_eq!(suggestions.titles[0].title, "Kubernetes docs");
+    assert_eq!(suggestions.titles[0].count, 1);
+
+    let suggestions = search::suggest(&db, user_id, "100%", 5).await?;
+    assert!(suggestions.titles.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn search_filters_by_reading_time() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+
+    for (url, words) in [
+        ("https://example.com/short", 100),
+        ("https://example.com/medium", 2_000),
+        ("https://example.com/long", 10_000),
+    ] {
+        let text = vec!["word"; words].join(" ");
+        bookmark::save(&db, &new_bookmark(user_id, url, url, &[]), &text).await?;
+    }
+
+    let request = |reading_times: Vec<ReadingTime>| SearchRequest {
+        query: None,
+        tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times,
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
+        limit: None,
+        offset: None,
+    };
+
+    let response = search::search(&db, user_id, &request(vec![ReadingTime::Short])).await?;
+    assert_eq!(response.total, 1);
+    let bookmark = &response.items[0].bookmark;
+    assert_eq!(bookmark.word_count, 100);
+    assert_eq!(bookmark.reading_time_minutes, 1);
+
+    let response = search::search(
+        &db,
+        user_id,
+        &request(vec![ReadingTime::Medium, ReadingTime::Long]),
+    )
+    .await?;
+    assert_eq!(response.total, 2);
+    let response = search::search(&db, user_id, &request(vec![])).await?;
+    assert_eq!(response.total, 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn search_filters_by_kind() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+
+    let paper = bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://arxiv.org/abs/1", "A paper", &[]),
+        "Abstract",
+    )
+    .await?;
+    bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://example.com/post", "A post", &[]),
+        "Post",
+    )
+    .await?;
+    ai::complete_text_ai_outputs(
+        &db,
+        user_id,
+        &paper.bookmark_id,
+        None,
+        None,
+        Some(BookmarkKind::Paper),
+        AiGenerationStatus::Done,
+        AiGenerationStatus::Done,
+        1,
+    )
+    .await?;
+
+    let request = |kinds: Vec<BookmarkKind>| SearchRequest {
+        query: None,
+        tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds,
+        entities: vec![],
+        collection: None,
+        workspace: None,
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
+        limit: None,
+        offset: None,
+    };
+    let response = search::search(&db, user_id, &request(vec![BookmarkKind::Paper])).await?;
+    assert_eq!(response.total, 1);
+    assert_eq!(response.items[0].bookmark.kind, Some(BookmarkKind::Paper));
+    let response = search::search(
+        &db,
+        user_id,
+        &request(vec![BookmarkKind::Docs, BookmarkKind::Video]),
+    )
+    .await?;
+    assert_eq!(response.total, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn reindex_rebuilds_tokens_and_queues_missing_embeddings() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let other_user = create_user(&db).await?;
+    let failed = bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://example.com/a", "Ownership", &[]),
+        "text",
+    )
+    .await?;
+    let embedded = bookmark::save(
+        &db,
+        &new_bookmark(user_id, "https://example.com/b", "Borrowing", &[]),
+        "text",
+    )
+    .await?;
+    ai::mark_embedding_failure(
+        &db,
+        user_id,
+        &failed.bookmark_id,
+        AiGenerationStatus::Fail,
+        3,
+        Utc::now(),
+        "timeout",
+    )
+    .await?;
+    chunks::store_chunks_with_embeddings(
+        &db,
+        &embedded.bookmark_id,
+        user_id,
+        vec!["borrowing".to_string()],
+        vec![vec![1.0, 0.0]],
+    )
+    .await?;
+    ai::mark_embedding_success(&db, user_id, &embedded.bookmark_id, 1).await?;
+    bookmark::save(
+        &db,
+        &new_bookmark(other_user, "https://example.com/c", "C", &[]),
+        "text",
+    )
+    .await?;
+
+    let username = user::get_by_id(&db, &user_id)
+        .await?
+        .expect("user")
+        .username;
+    let summary = server::reindex::reindex(&db, Some(&username)).await?;
+    assert_eq!(
+        summary,
+        server::reindex::Summary {
+            users: 1,
+            bookmarks: 2,
+            queued_embeddings: 1,
+            index_rebuilt: false,
+        }
+    );
+    // Queued once, still pending for the next run
+    assert_eq!(
+        server::reindex::reindex(&db, None).await?.queued_embeddings,
+        0
+    );
+    assert!(server::reindex::reindex(&db, Some("nobody")).await.is_err());
+    Ok(())
+}
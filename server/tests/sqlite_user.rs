@@ -0,0 +1,101 @@
+mod common;
+
+use chrono::Duration;
+use common::sqlite::{create_user, database};
+use server::db::{self, user, user_settings};
+use server::error::Error;
+use shared::{ReaderFontFamily, ReaderSettings, UserSettings};
+
+#[tokio::test]
+async fn migrations_are_idempotent() -> anyhow::Result<()> {
+    let db = database().await?;
+    db::run_migrations(&db).await?;
+    db::run_health_check(&db).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn users_are_unique_by_username_and_email() -> anyhow::Result<()> {
+    let db = database().await?;
+    let created = user::create(
+        &db,
+        "Alice".to_string(),
+        Some("Alice@Example.com".to_string()),
+        "hash".to_string(),
+    )
+    .await?;
+    assert_eq!(created.username, "alice");
+
+    let found = user::get_by_username(&db, "ALICE".to_string()).await?;
+    assert_eq!(found.map(|user| user.user_id), Some(created.user_id));
+
+    let duplicate = user::create(&db, "alice".to_string(), None, "hash".to_string()).await;
+    assert!(matches!(
+        duplicate,
+        Err(Error::ConstraintViolation { ref constraint, .. }) if constraint == "unique_username"
+    ));
+    let duplicate = user::create(
+        &db,
+        "bob".to_string(),
+        Some("alice@example.com".to_string()),
+        "hash".to_string(),
+    )
+    .await;
+    assert!(matches!(
+        duplicate,
+        Err(Error::ConstraintViolation { ref constraint, .. }) if constraint == "unique_email"
+    ));
+
+    let issue = |purpose| user::create_token(&db, &created.user_id, purpose, Duration::hours(1));
+    let older = issue(user::TokenPurpose::PasswordReset).await?;
+    let verify = issue(user::TokenPurpose::VerifyEmail).await?;
+    let token = issue(user::TokenPurpose::PasswordReset).await?;
+    assert_eq!(
+        user::consume_token(&db, &token, user::TokenPurpose::PasswordReset).await?,
+        Some(created.user_id)
+    );
+    assert_eq!(
+        user::consume_token(&db, &token, user::TokenPurpose::PasswordReset).await?,
+        None
+    );
+    // Other reset emails are used up with it, tokens for other purposes aren't
+    assert_eq!(
+        user::consume_token(&db, &older, user::TokenPurpose::PasswordReset).await?,
+        None
+    );
+    assert_eq!(
+        user::consume_token(&db, &verify, user::TokenPurpose::VerifyEmail).await?,
+        Some(created.user_id)
+    );
+
+    assert!(user::delete(&db, &created.user_id).await?);
+    assert!(user::get_by_id(&db, &created.user_id).await?.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn reader_settings_persist_with_the_other_settings() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    assert_eq!(
+        user_settings::get(&db, user_id).await?.reader,
+        ReaderSettings::default()
+    );
+
+    let settings = UserSettings {
+        summary_language: Some("Portuguese".to_string()),
+        reader: ReaderSettings {
+            font_family: ReaderFontFamily::Monospace,
+            font_size: 22,
+            line_width: 90,
+            justify: true,
+        },
+        ..UserSettings::default()
+    };
+    assert_eq!(
+        user_settings::upsert(&db, user_id, &settings).await?,
+        settings
+    );
+    assert_eq!(user_settings::get(&db, user_id).await?, settings);
+    Ok(())
+}
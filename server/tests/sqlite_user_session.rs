@@ -0,0 +1,51 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use common::sqlite::{create_user, database};
+use server::db::user_session;
+
+#[tokio::test]
+async fn user_sessions_are_touched_and_revoked() -> anyhow::Result<()> {
+    let db = database().await?;
+    let user_id = create_user(&db).await?;
+    let other_user_id = create_user(&db).await?;
+    let ip = "203.0.113.7".parse().ok();
+    let expires_at = Utc::now() + Duration::days(14);
+
+    let phone = user_session::create(&db, user_id, Some("Phone"), ip, expires_at).await?;
+    let laptop = user_session::create(&db, user_id, None, None, expires_at).await?;
+    let expired =
+        user_session::create(&db, user_id, None, None, Utc::now() - Duration::minutes(1)).await?;
+
+    // Seen moments ago, the session is valid but its row isn't written again
+    assert!(user_session::touch(&db, user_id, laptop, ip).await?);
+    assert!(!user_session::touch(&db, user_id, expired, None).await?);
+    assert!(!user_session::touch(&db, other_user_id, phone, None).await?);
+    let sessions = user_session::list(&db, user_id).await?;
+    assert_eq!(
+        sessions.iter().map(|s| s.session_id).collect::<Vec<_>>(),
+        vec![laptop, phone]
+    );
+    assert_eq!(sessions[0].ip, None);
+    assert_eq!(sessions[1].device_label.as_deref(), Some("Phone"));
+    assert_eq!(sessions[1].ip.as_deref(), Some("203.0.113.7"));
+
+    assert!(!user_session::revoke(&db, other_user_id, phone).await?);
+    assert!(user_session::revoke(&db, user_id, phone).await?);
+    assert!(!user_session::touch(&db, user_id, phone, None).await?);
+    assert_eq!(user_session::list(&db, user_id).await?.len(), 1);
+
+    // A password change keeps the current session only, a reset none
+    let tablet = user_session::create(&db, user_id, None, None, expires_at).await?;
+    let other = user_session::create(&db, other_user_id, None, None, expires_at).await?;
+    assert_eq!(
+        user_session::revoke_others(&db, user_id, Some(tablet)).await?,
+        1
+    );
+    assert!(user_session::touch(&db, user_id, tablet, None).await?);
+    assert!(!user_session::touch(&db, user_id, laptop, None).await?);
+    assert_eq!(user_session::revoke_others(&db, user_id, None).await?, 1);
+    assert!(user_session::list(&db, user_id).await?.is_empty());
+    assert!(user_session::touch(&db, other_user_id, other, None).await?);
+    Ok(())
+}
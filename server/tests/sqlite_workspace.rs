@@ -0,0 +1,120 @@
+mod common;
+
+use common::sqlite::{create_user, database, new_bookmark};
+use server::db::{bookmark, search, workspace};
+use shared::{SearchRequest, Visibility, WorkspaceRole};
+
+#[tokio::test]
+async fn workspace_search_covers_bookmarks_of_members() -> anyhow::Result<()> {
+    let db = database().await?;
+    let owner_id = create_user(&db).await?;
+    let member_id = create_user(&db).await?;
+    let outsider_id = create_user(&db).await?;
+    let at_work = new_bookmark(owner_id, "https://example.com/work", "Rust at work", &[]);
+    let on_weekends = new_bookmark(
+        member_id,
+        "https://example.org/weekend",
+        "Rust on weekends",
+        &[],
+    );
+    let unshared = new_bookmark(member_id, "https://example.net/own", "Rust for me", &[]);
+    for bookmark in [&at_work, &on_weekends, &unshared] {
+        bookmark::save(&db, bookmark, "Ownership and borrowing").await?;
+    }
+
+    let team = workspace::create(&db, owner_id, "Team").await?;
+    assert_eq!(team.role, WorkspaceRole::Owner);
+    let workspace_id = team.workspace_id;
+    assert!(workspace::set_member(&db, workspace_id, member_id, WorkspaceRole::Editor).await?);
+    assert!(!workspace::set_member(&db, workspace_id, owner_id, WorkspaceRole::Viewer).await?);
+    // Bookmarks start out private
+    assert!(!workspace::add_bookmark(&db, workspace_id, owner_id, &at_work.bookmark_id).await?);
+    for (user_id, bookmark) in [(owner_id, &at_work), (member_id, &on_weekends)] {
+        let visible =
+            bookmark::set_visibility(&db, user_id, &bookmark.bookmark_id, Visibility::Workspace)
+                .await?
+                .expect("bookmark of the user");
+        assert_eq!(visible.visibility, Visibility::Workspace);
+    }
+    assert!(workspace::add_bookmark(&db, workspace_id, owner_id, &at_work.bookmark_id).await?);
+    assert!(workspace::add_bookmark(&db, workspace_id, member_id, &on_weekends.bookmark_id).await?);
+    assert!(!workspace::add_bookmark(&db, workspace_id, member_id, &at_work.bookmark_id).await?);
+
+    let request = SearchRequest {
+        query: Some("rust".to_string()),
+        tags_filter: None,
+        domains: vec![],
+        from_date: None,
+        to_date: None,
+        reading_times: vec![],
+        kinds: vec![],
+        entities: vec![],
+        collection: None,
+        workspace: Some(workspace_id),
+        snippet_count: None,
+        snippet_words: None,
+        highlight_title_summary: false,
+        limit: None,
+        offset: None,
+    };
+    let response = search::search(&db, member_id, &request).await?;
+    assert_eq!(response.total, 2);
+    assert_eq!(search::search(&db, outsider_id, &request).await?.total, 0);
+
+    let joined = workspace::list(&db, member_id).await?;
+    assert_eq!(joined.len(), 1);
+    assert_eq!(joined[0].role, WorkspaceRole::Editor);
+    assert_eq!((joined[0].member_count, joined[0].bookmark_count), (2, 2));
+    assert!(workspace::get(&db, outsider_id, workspace_id)
+        .await?
+        .is_none());
+
+    let read = bookmark::get_in_workspace(&db, workspace_id, &on_weekends.bookmark_id)
+        .await?
+        .expect("bookmark saved into the workspace");
+    assert_eq!(read.user_id, member_id);
+    assert!(
+        bookmark::get_in_workspace(&db, workspace_id, &unshared.bookmark_id)
+            .await?
+            .is_none()
+    );
+    assert!(workspace::shares_bookmark(&db, owner_id, member_id, &on_weekends.bookmark_id).await?);
+    assert!(
+        !workspace::shares_bookmark(&db, outsider_id, member_id, &on_weekends.bookmark_id).await?
+    );
+    assert!(!workspace::shares_bookmark(&db, owner_id, member_id, &unshared.bookmark_id).await?);
+
+    // Making a bookmark private again hides it from the workspace
+    bookmark::set_visibility(
+        &db,
+        member_id,
+        &on_weekends.bookmark_id,
+        Visibility::Private,
+    )
+    .await?
+    .expect("bookmark of the member");
+    assert_eq!(search::search(&db, owner_id, &request).await?.total, 1);
+    assert!(
+        bookmark::get_in_workspace(&db, workspace_id, &on_weekends.bookmark_id)
+            .await?
+            .is_none()
+    );
+    assert!(!workspace::shares_bookmark(&db, owner_id, member_id, &on_weekends.bookmark_id).await?);
+    assert_eq!(
+        workspace::get(&db, owner_id, workspace_id)
+            .await?
+            .expect("workspace of the owner")
+            .bookmark_count,
+        1
+    );
+    assert!(
+        bookmark::set_visibility(&db, owner_id, &on_weekends.bookmark_id, Visibility::Public)
+            .await?
+            .is_none()
+    );
+
+    assert!(!workspace::remove_member(&db, workspace_id, owner_id).await?);
+    assert!(workspace::remove_member(&db, workspace_id, member_id).await?);
+    assert_eq!(search::search(&db, owner_id, &request).await?.total, 1);
+    Ok(())
+}
@@ -1,4 +1,6 @@
-use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, NaiveDate, Utc};
 use secrecy::SecretString;
 use serde::{Deserialize, Serialize};
 use strum_macros::{AsRefStr, EnumString};
@@ -6,32 +8,250 @@ use url::Url;
 use uuid::Uuid;
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct UserProfile {
     pub user_id: Uuid,
     pub username: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
     pub created_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct SignUpRequest {
     pub username: String,
+    /// Optional address used for verification, password resets and
+    /// notifications
+    #[serde(default)]
+    pub email: Option<String>,
+    #[cfg_attr(feature = "openapi", schema(value_type = String, format = Password))]
     pub password: SecretString,
+    #[cfg_attr(feature = "openapi", schema(value_type = String, format = Password))]
     pub password_confirmation: SecretString,
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct SignUpResponse {
     pub id: Uuid,
     pub username: String,
 }
 
 #[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct SignInRequest {
     pub username: String,
+    #[cfg_attr(feature = "openapi", schema(value_type = String, format = Password))]
+    pub password: SecretString,
+}
+
+/// Deleting the account takes the current password, like changing it.
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DeleteAccountRequest {
+    #[cfg_attr(feature = "openapi", schema(value_type = String, format = Password))]
     pub password: SecretString,
 }
 
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ChangePasswordRequest {
+    #[cfg_attr(feature = "openapi", schema(value_type = String, format = Password))]
+    pub old_password: SecretString,
+    #[cfg_attr(feature = "openapi", schema(value_type = String, format = Password))]
+    pub new_password: SecretString,
+    #[cfg_attr(feature = "openapi", schema(value_type = String, format = Password))]
+    pub new_password_confirmation: SecretString,
+}
+
+/// Per-user AI preferences, see `GET/PUT /api/v1/settings`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UserSettings {
+    pub auto_tagging_enabled: bool,
+    pub summaries_enabled: bool,
+    /// Language summaries are written in, e.g. "Portuguese" (default: the
+    /// article's language as chosen by the model)
+    pub summary_language: Option<String>,
+    /// Text model used for this user's tags and summaries instead of
+    /// `LLM_TEXT_MODEL`
+    pub preferred_text_model: Option<String>,
+    /// Whether a weekly digest is written for this user
+    pub digest_enabled: bool,
+    /// How bookmarks display in the reader view of the web interface
+    #[serde(default)]
+    pub reader: ReaderSettings,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            auto_tagging_enabled: true,
+            summaries_enabled: true,
+            summary_language: None,
+            preferred_text_model: None,
+            digest_enabled: true,
+            reader: ReaderSettings::default(),
+        }
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, EnumString, AsRefStr,
+)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+pub enum ReaderFontFamily {
+    #[default]
+    Serif,
+    SansSerif,
+    Monospace,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ReaderSettings {
+    pub font_family: ReaderFontFamily,
+    /// In pixels, from 12 to 32
+    pub font_size: i32,
+    /// Longest line in characters, from 40 to 120
+    pub line_width: i32,
+    /// Whether paragraphs are justified instead of aligned to the left
+    pub justify: bool,
+}
+
+impl Default for ReaderSettings {
+    fn default() -> Self {
+        Self {
+            font_family: ReaderFontFamily::Serif,
+            font_size: 18,
+            line_width: 70,
+            justify: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema, utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct LlmUsageRequest {
+    /// How many days back to summarize, including today (default: 30)
+    pub days: Option<u32>,
+}
+
+/// LLM calls made for one day, purpose and model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LlmUsageDay {
+    pub day: NaiveDate,
+    pub purpose: String,
+    pub model: String,
+    pub calls: i64,
+    pub failed_calls: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub duration_ms: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LlmUsageResponse {
+    pub days: Vec<LlmUsageDay>,
+}
+
+/// Link to the captured page of a bookmark, readable without signing in
+/// until `expires_at`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BookmarkShareLink {
+    pub url: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Credential of the Pinboard-compatible API, sent as its `auth_token` query
+/// parameter. Changing the password revokes it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ApiToken {
+    /// `username:token`
+    pub auth_token: String,
+}
+
+/// Bytes a user stores: captured pages and images plus extracted text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct StorageUsage {
+    pub static_bytes: i64,
+    pub text_bytes: i64,
+    pub total_bytes: i64,
+    /// `None` when the server sets no quota.
+    pub quota_bytes: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum AiFeatureState {
+    Available,
+    /// Configured, but its provider or model can't serve it right now
+    Degraded,
+    /// Not configured
+    Disabled,
+}
+
+/// Whether an AI feature works, with what and why not.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AiFeatureStatus {
+    pub state: AiFeatureState,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub reason: Option<String>,
+}
+
+/// AI features of the server: text generation (summaries, tags, digests, RAG
+/// answers) and embeddings (semantic search, RAG retrieval).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AiStatus {
+    pub text: AiFeatureStatus,
+    pub embeddings: AiFeatureStatus,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ChangeEmailRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct VerifyEmailRequest {
+    #[cfg_attr(feature = "openapi", schema(value_type = String, format = Password))]
+    pub token: SecretString,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PasswordResetRequest {
+    pub username: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PasswordResetConfirmRequest {
+    #[cfg_attr(feature = "openapi", schema(value_type = String, format = Password))]
+    pub token: SecretString,
+    #[cfg_attr(feature = "openapi", schema(value_type = String, format = Password))]
+    pub new_password: SecretString,
+    #[cfg_attr(feature = "openapi", schema(value_type = String, format = Password))]
+    pub new_password_confirmation: SecretString,
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct SignInResponse {
     pub user_id: Uuid,
     pub username: String,
@@ -40,12 +260,14 @@ pub struct SignInResponse {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct UserProfileResponse {
     pub user_id: Uuid,
     pub username: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct Bookmark {
     pub bookmark_id: String,
     pub user_id: Uuid,
@@ -56,15 +278,88 @@ pub struct Bookmark {
     pub summary: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
+    /// Words of the extracted text
+    #[serde(default)]
+    pub word_count: i32,
+    /// Estimated, rounded up to whole minutes
+    #[serde(default)]
+    pub reading_time_minutes: i32,
+    /// Assigned by the AI pipeline along with tags, none until it runs
+    #[serde(default)]
+    pub kind: Option<BookmarkKind>,
+    /// Marked by the user, or by one of their rules when it was saved
+    #[serde(default)]
+    pub favorite: bool,
+    /// Title extracted from the page, kept when the user changed `title`
+    #[serde(default)]
+    pub original_title: Option<String>,
+    #[serde(default)]
+    pub visibility: Visibility,
+}
+
+/// Who may see a bookmark besides its owner, the widest audience any sharing
+/// can reach.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, EnumString, AsRefStr,
+)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Visibility {
+    /// The owner only
+    #[default]
+    Private,
+    /// Also members of the workspaces it is saved into
+    Workspace,
+    /// Also anyone holding a share link
+    Public,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct VisibilityRequest {
+    pub visibility: Visibility,
+}
+
+/// Coarse type of content a bookmark points to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumString, AsRefStr)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+pub enum BookmarkKind {
+    /// Blog posts, news and essays
+    Article,
+    /// Academic and research papers
+    Paper,
+    /// Reference documentation, manuals and API docs
+    Docs,
+    Recipe,
+    /// Pages built around a video
+    Video,
+}
+
+impl BookmarkKind {
+    pub const ALL: [BookmarkKind; 5] = [
+        BookmarkKind::Article,
+        BookmarkKind::Paper,
+        BookmarkKind::Docs,
+        BookmarkKind::Recipe,
+        BookmarkKind::Video,
+    ];
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct NewBookmarkRequest {
     pub url: String,
     pub tags: Vec<String>,
+    /// Save the page even if robots.txt or a noindex meta tag opts out
+    #[serde(default)]
+    pub ignore_robots: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct NewBookmarkResponse {
     pub task_id: Uuid,
     pub url: String,
@@ -73,6 +368,7 @@ pub struct NewBookmarkResponse {
 }
 
 #[derive(Debug, Clone, PartialEq, Default, EnumString, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub enum SearchType {
     #[default]
     #[strum(ascii_case_insensitive)]
@@ -83,6 +379,7 @@ pub enum SearchType {
 }
 
 #[derive(Debug, PartialEq, Default, Clone, EnumString, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub enum TagFilterType {
     #[default]
     #[strum(ascii_case_insensitive)]
@@ -93,12 +390,24 @@ pub enum TagFilterType {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct SearchResultItem {
     pub bookmark: Bookmark,
-    pub search_match: Option<String>,
+    /// Fragments of the text around the matches, matched words wrapped in
+    /// `<mark>`. Empty without a text query
+    #[serde(default)]
+    pub snippets: Vec<String>,
+    /// Title with its matches marked, when `highlight_title_summary` is set
+    #[serde(default)]
+    pub title_highlight: Option<String>,
+    /// Summary with its matches marked, when `highlight_title_summary` is
+    /// set
+    #[serde(default)]
+    pub summary_highlight: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub enum TagFilter {
     And(Vec<String>),
     Or(Vec<String>),
@@ -106,51 +415,377 @@ pub enum TagFilter {
     Untagged,
 }
 
+/// Buckets of estimated reading time a search can be narrowed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumString, AsRefStr)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+pub enum ReadingTime {
+    /// Under 5 minutes
+    Short,
+    /// 5 to 20 minutes
+    Medium,
+    /// 20 minutes or more
+    Long,
+}
+
+impl ReadingTime {
+    /// Minutes of the bucket, the upper bound excluded.
+    pub fn minutes(self) -> (Option<i32>, Option<i32>) {
+        match self {
+            ReadingTime::Short => (None, Some(5)),
+            ReadingTime::Medium => (Some(5), Some(20)),
+            ReadingTime::Long => (Some(20), None),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct SearchRequest {
     pub query: Option<String>,
     pub tags_filter: Option<TagFilter>,
+    /// Only bookmarks saved from one of these domains, e.g. "lwn.net"
+    #[serde(default)]
+    pub domains: Vec<String>,
+    /// Only bookmarks saved at or after this instant
+    #[serde(default)]
+    pub from_date: Option<DateTime<Utc>>,
+    /// Only bookmarks saved at or before this instant
+    #[serde(default)]
+    pub to_date: Option<DateTime<Utc>>,
+    /// Only bookmarks in one of these reading time buckets
+    #[serde(default)]
+    pub reading_times: Vec<ReadingTime>,
+    /// Only bookmarks classified as one of these kinds
+    #[serde(default)]
+    pub kinds: Vec<BookmarkKind>,
+    /// Only bookmarks mentioning every one of these entities, by name
+    /// ignoring case, e.g. "tokio"
+    #[serde(default)]
+    pub entities: Vec<String>,
+    /// Only bookmarks in this collection
+    #[serde(default)]
+    pub collection: Option<Uuid>,
+    /// Search the bookmarks saved into this workspace, by any of its
+    /// members, rather than the user's own
+    #[serde(default)]
+    pub workspace: Option<Uuid>,
+    /// Text fragments returned per result, from 1 to 5 (default: 1)
+    #[serde(default)]
+    pub snippet_count: Option<u32>,
+    /// Words per text fragment, from 5 to 100 (default: 35)
+    #[serde(default)]
+    pub snippet_words: Option<u32>,
+    /// Also return the title and summary with their matches highlighted
+    #[serde(default)]
+    pub highlight_title_summary: bool,
     pub limit: Option<i32>,
     pub offset: Option<i32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct SearchResponse {
     pub items: Vec<SearchResultItem>,
     pub tags: Vec<TagCount>,
+    /// Domains with the most matching bookmarks
+    #[serde(default)]
+    pub domains: Vec<DomainCount>,
+    /// Matching bookmarks per month they were saved in, oldest first
+    #[serde(default)]
+    pub months: Vec<MonthCount>,
     pub total: u64,
+    /// Nothing matched the query text as written, the results are the
+    /// bookmarks whose title or domain come close to it, e.g. despite a typo
+    #[serde(default)]
+    pub fuzzy: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct TagsResponse {
     pub tags: Vec<TagCount>,
 }
 
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct TagCount {
     pub tag: String,
     pub count: i64,
 }
 
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DomainCount {
+    pub domain: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TitleCount {
+    pub title: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema, utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct SearchSuggestRequest {
+    /// What has been typed in the search bar so far
+    pub q: String,
+    /// Suggestions of each kind, 5 by default
+    pub limit: Option<u32>,
+}
+
+/// Completions for a search being typed. Each list starts with what begins
+/// with the typed text, followed by what comes close to it by trigram
+/// similarity.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SearchSuggestions {
+    pub tags: Vec<TagCount>,
+    pub titles: Vec<TitleCount>,
+    pub domains: Vec<DomainCount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MonthCount {
+    /// First day of the month, in UTC
+    pub month: NaiveDate,
+    pub count: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct TagsWithCounters {
     pub tags: Vec<TagCount>,
 }
 
+/// Summary written by the user, replacing the generated one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SummaryRequest {
+    pub summary: String,
+}
+
+/// How a summary generated again should read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumString, AsRefStr)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+pub enum SummaryStyle {
+    /// A sentence or two
+    Short,
+    /// A paragraph covering the main points
+    Detailed,
+    /// A handful of bullet points
+    BulletPoints,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RegenerateSummaryRequest {
+    /// The usual summary of up to 3 sentences when absent
+    #[serde(default)]
+    pub style: Option<SummaryStyle>,
+}
+
+/// What of a bookmark gets processed again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ReprocessStage {
+    /// Tags and kind, from a fresh analysis of the article
+    Tags,
+    /// Summary, from a fresh analysis of the article
+    Summary,
+    /// Chunks and embeddings searched by similarity
+    Chunks,
+    /// The page itself, captured again like a refresh does
+    Content,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ReprocessRequest {
+    pub stages: Vec<ReprocessStage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ReprocessResponse {
+    pub stages: Vec<ReprocessStage>,
+    /// Capturing the page again, when `content` was asked for
+    pub task: Option<BookmarkTask>,
+}
+
+/// What a bulk request does to each of its bookmarks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BulkBookmarkAction {
+    /// Adds the tags to the ones each bookmark has
+    AddTags {
+        tags: Vec<String>,
+    },
+    /// Moves the bookmarks to the trash
+    Trash,
+    Favorite,
+    Unfavorite,
+}
+
+/// An action on the bookmarks of `bookmark_ids`, or on every bookmark
+/// matching `search` when given.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BulkBookmarkRequest {
+    #[serde(default)]
+    pub bookmark_ids: Vec<String>,
+    /// Search whose matches are acted on, its `limit` and `offset` ignored
+    pub search: Option<SearchRequest>,
+    pub action: BulkBookmarkAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BulkBookmarkResponse {
+    /// Bookmarks the action was applied to
+    pub updated: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct Tags {
     pub tags: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RenameTagRequest {
+    pub new_name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RenameTagResponse {
+    /// The new name, normalized like every other tag
+    pub tag: String,
+    /// Bookmarks whose tags changed
+    pub bookmark_count: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct Bookmarks {
     pub bookmarks: Vec<Bookmark>,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TrashedBookmark {
+    pub bookmark: Bookmark,
+    pub deleted_at: DateTime<Utc>,
+    /// When it will be removed for good, with its static files
+    pub purge_at: DateTime<Utc>,
+}
+
+/// Deleted bookmarks that can still be restored, the most recent first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Trash {
+    pub bookmarks: Vec<TrashedBookmark>,
+}
+
+/// Pushed to the user on `GET /api/v1/events`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerEvent {
+    TaskUpdated { task: BookmarkTask },
+    BookmarkCreated { bookmark: Bookmark },
+}
+
+/// Task filter of `GET /api/v1/events/tasks`, matched like the task search
+/// against each update. There is no status filter: a listed task leaving the
+/// status of the list is still sent, for the client to update or drop its row.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema, utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct TaskSubscription {
+    /// Part of the task URL
+    pub url: Option<String>,
+    /// Comma separated tags the task must all have
+    pub tags: Option<String>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct NewBookmark {
+    #[cfg_attr(feature = "openapi", schema(value_type = String))]
     pub url: Url,
     pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub ignore_robots: bool,
+}
+
+/// URLs saved at once with the same tags. They are plain strings so that
+/// invalid ones are reported along with the others instead of failing the
+/// request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BatchBookmarkRequest {
+    pub urls: Vec<String>,
+    pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub ignore_robots: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum BatchBookmarkStatus {
+    /// A task was queued to capture the page
+    Accepted,
+    /// Already saved, or given earlier in the batch
+    Duplicate,
+    /// Not a URL, or one that is not fetched
+    Invalid,
+    /// Over the rate limit, send it again later
+    RateLimited,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BatchBookmarkResult {
+    pub url: String,
+    pub status: BatchBookmarkStatus,
+    /// Task of an accepted URL
+    pub task_id: Option<Uuid>,
+    /// Why the URL is invalid
+    pub reason: Option<String>,
+}
+
+/// One result per URL of the request, in the same order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BatchBookmarkResponse {
+    pub results: Vec<BatchBookmarkResult>,
+}
+
+/// Corrects what was extracted for a bookmark, leaving out what is kept.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UpdateBookmarkRequest {
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Canonicalized like the URLs of new bookmarks, the page isn't
+    /// captured again
+    #[serde(default)]
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<String>))]
+    pub url: Option<Url>,
 }
 
 #[derive(Debug, Clone)]
@@ -160,14 +795,17 @@ pub enum TagOperation {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, EnumString, AsRefStr, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub enum BookmarkTaskStatus {
     #[default]
     Done,
     Pending,
     Fail,
+    Cancelled,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct BookmarkTask {
     pub task_id: Uuid,
     pub user_id: Uuid,
@@ -179,9 +817,16 @@ pub struct BookmarkTask {
     pub next_delivery: DateTime<Utc>,
     pub retries: Option<i16>,
     pub fail_reason: Option<String>,
+    #[serde(default)]
+    pub ignore_robots: bool,
+    /// Captures an existing bookmark again, keeping the previous capture
+    /// as a version
+    #[serde(default)]
+    pub refresh: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct BookmarkTaskSearchRequest {
     pub url: Option<String>,
     pub status: Option<BookmarkTaskStatus>,
@@ -190,18 +835,104 @@ pub struct BookmarkTaskSearchRequest {
     pub to_created_at: Option<DateTime<Utc>>,
     pub page_size: Option<u8>,
     pub last_task_id: Option<Uuid>,
+    /// Count the matching tasks, in total and per status, which costs an
+    /// extra query
+    #[serde(default)]
+    pub include_total: bool,
 }
 
+/// Tasks in `status` matching every filter of a search but the status.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TaskStatusCount {
+    pub status: BookmarkTaskStatus,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct BookmarkTaskSearchResponse {
     pub tasks: Vec<BookmarkTask>,
     pub has_more: bool,
+    /// Tasks matching the search across all pages, with `include_total`
     pub total_count: Option<usize>,
+    /// Empty unless `include_total`, statuses without tasks left out
+    #[serde(default)]
+    pub status_counts: Vec<TaskStatusCount>,
+}
+
+/// Processing stages recorded on a task's timeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumString, AsRefStr)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum TaskStage {
+    Fetched,
+    Readability,
+    Images,
+    Saved,
+    Chunked,
+    Tagged,
+    Summarized,
+    Retrying,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TaskEvent {
+    pub event_id: Uuid,
+    pub task_id: Uuid,
+    pub stage: TaskStage,
+    pub detail: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TaskEvents {
+    pub events: Vec<TaskEvent>,
+}
+
+/// Permanently failed tasks sharing the same `fail_reason`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DeadLetterGroup {
+    pub fail_reason: String,
+    pub task_count: i64,
+    pub first_failed_at: DateTime<Utc>,
+    pub last_failed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DeadLetterResponse {
+    pub groups: Vec<DeadLetterGroup>,
+    pub total_count: i64,
+}
+
+/// Filter for requeueing failed tasks in bulk, an empty filter matches every
+/// failed task of the user.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BulkRetryTasksRequest {
+    pub fail_reason: Option<String>,
+    pub url: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub from_created_at: Option<DateTime<Utc>>,
+    pub to_created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BulkRetryTasksResponse {
+    pub requeued: usize,
 }
 
 // RAG (Retrieval-Augmented Generation) types
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct BookmarkChunk {
     pub chunk_id: Uuid,
     pub bookmark_id: String,
@@ -212,20 +943,63 @@ pub struct BookmarkChunk {
     pub updated_at: Option<DateTime<Utc>>,
 }
 
+/// Model a chunk was embedded with and the size of its stored vector.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ChunkEmbedding {
+    pub provider: String,
+    pub model: String,
+    pub dimensions: usize,
+}
+
+/// A stored chunk as RAG retrieves it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ChunkDetail {
+    pub chunk: BookmarkChunk,
+    /// None while the chunk has no embedding, so only full-text search finds it
+    pub embedding: Option<ChunkEmbedding>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BookmarkChunks {
+    pub chunks: Vec<ChunkDetail>,
+}
+
+/// A chunk with the bookmark it was cut from and the chunks around it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ChunkContext {
+    pub detail: ChunkDetail,
+    pub bookmark: Bookmark,
+    pub chunk_count: usize,
+    pub previous: Option<BookmarkChunk>,
+    pub next: Option<BookmarkChunk>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct RagSession {
     pub session_id: Uuid,
     pub user_id: Uuid,
+    /// A few words on the question, generated once it is answered
+    #[serde(default)]
+    pub title: Option<String>,
     pub question: String,
     pub answer: Option<String>,
     pub relevant_chunks: Vec<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
+    /// What the user thought of the answer, once rated
+    #[serde(default)]
+    pub feedback: Option<RagFeedback>,
 }
 
 /// Configuration for hybrid search combining vector similarity with full-text
 /// search
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct HybridSearchConfig {
     /// Enable hybrid search (combines vector + FTS)
     pub enabled: bool,
@@ -233,14 +1007,15 @@ pub struct HybridSearchConfig {
     pub use_rrf: Option<bool>,
     /// RRF k parameter (default: 60)
     pub rrf_k: Option<u32>,
-    /// Weight for vector similarity in weighted average mode (when use_rrf is
-    /// false)
+    /// Weight for the vector list: scales its RRF term, or its score in
+    /// weighted average mode (default: 1.0 for RRF, 0.5 otherwise)
     pub vector_weight: Option<f64>,
-    /// Weight for FTS score in weighted average mode (when use_rrf is false)
+    /// Weight for the full-text list, applied like `vector_weight`
     pub fts_weight: Option<f64>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct RagQueryRequest {
     pub question: String,
     pub max_chunks: Option<usize>,
@@ -249,18 +1024,760 @@ pub struct RagQueryRequest {
     pub max_context_tokens: Option<usize>,
     /// Hybrid search configuration
     pub hybrid_search: Option<HybridSearchConfig>,
+    /// Only consider chunks from bookmarks matching this tag filter
+    #[serde(default)]
+    pub tags_filter: Option<TagFilter>,
+    /// Only consider chunks from these bookmarks
+    #[serde(default)]
+    pub bookmark_ids: Option<Vec<String>>,
+    /// Answer from the bookmarks saved into this workspace rather than the
+    /// user's own
+    #[serde(default)]
+    pub workspace: Option<Uuid>,
+    /// Earlier turns of the conversation, oldest first, that a follow-up
+    /// question may refer to
+    #[serde(default)]
+    pub history: Vec<RagTurn>,
+    /// Order the relevant chunks by an LLM score against the question rather
+    /// than by similarity, at the cost of one more LLM call
+    #[serde(default)]
+    pub rerank: bool,
+}
+
+/// A question of a RAG conversation and the answer it got.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RagTurn {
+    pub question: String,
+    pub answer: String,
+}
+
+/// A question about a single bookmark, answered from its passages only.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AskBookmarkRequest {
+    pub question: String,
+    /// Earlier questions about the bookmark and their answers, oldest first
+    #[serde(default)]
+    pub history: Vec<RagTurn>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct RagQueryResponse {
     pub session_id: Uuid,
     pub question: String,
+    /// Cites its sources with markers like `[1]` or `[1, 3]`
     pub answer: String,
     pub relevant_chunks: Vec<RagChunkMatch>,
+    /// The sources cited by the answer, by number
+    #[serde(default)]
+    pub citations: Vec<RagCitation>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A source cited in a RAG answer, `relevant_chunks[number - 1]` of the
+/// response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RagCitation {
+    /// Number of the `[number]` markers citing the source
+    pub number: usize,
+    pub bookmark_id: String,
+    pub chunk_id: Uuid,
+}
+
+/// Progress and answer events sent by `POST /api/v1/rag/query/stream`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RagStreamEvent {
+    VariationsGenerated { questions: Vec<String> },
+    ChunksRetrieved { count: usize },
+    RelevanceAssessed { relevant: usize, total: usize },
+    AnswerDelta { text: String },
+    Completed { response: RagQueryResponse },
+    Failed { message: String },
+}
+
+/// Weekly summary of the bookmarks saved in `[period_start, period_end)`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Digest {
+    pub digest_id: Uuid,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub bookmark_ids: Vec<String>,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Digests {
+    pub digests: Vec<Digest>,
+}
+
+/// Secret address of the digest feed, for feed readers that can't sign in.
+/// Issuing a new one revokes the previous.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DigestFeedToken {
+    pub token: String,
+    /// Path of the feed on this server, e.g. `/api/v1/digests/feed/{token}`
+    pub path: String,
+}
+
+/// Kinds of named entities extracted from articles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, EnumString, AsRefStr)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+pub enum EntityKind {
+    Person,
+    /// Software, libraries, standards and products
+    Project,
+    /// Companies, foundations and institutions
+    Organization,
+}
+
+/// A person, project or organization an article mentions by name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Entity {
+    pub kind: EntityKind,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct EntityCount {
+    pub kind: EntityKind,
+    pub name: String,
+    /// Bookmarks mentioning it
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema, utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct EntitiesRequest {
+    /// Only entities of this kind
+    pub kind: Option<EntityKind>,
+    /// Only entities whose name contains this, ignoring case
+    pub query: Option<String>,
+    pub limit: Option<u32>,
+}
+
+/// Entities of the user's bookmarks, the most mentioned first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Entities {
+    pub entities: Vec<EntityCount>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum GraphNodeKind {
+    Bookmark,
+    Entity,
+    Domain,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct GraphNode {
+    /// `bookmark:<id>`, `entity:<kind>:<name>` or `domain:<domain>`
+    pub id: String,
+    pub kind: GraphNodeKind,
+    pub label: String,
+    /// Set on entity nodes
+    pub entity_kind: Option<EntityKind>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum GraphEdgeKind {
+    /// Bookmark to an entity it mentions
+    Mentions,
+    /// Bookmark to the domain it was saved from
+    SavedFrom,
+    /// Bookmark to another bookmark or domain its article links to
+    LinksTo,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub kind: GraphEdgeKind,
+    /// Links collapsed into this edge
+    pub weight: u32,
+}
+
+/// Bookmarks connected through the entities they share and the links
+/// between them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Graph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema, utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct GraphRequest {
+    /// How many of the most recent bookmarks to include
+    pub limit: Option<u32>,
+}
+
+/// A named, ordered reading list of bookmarks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Collection {
+    pub collection_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    /// In reading order
+    pub bookmark_ids: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Creates a collection, or replaces all of it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CollectionRequest {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// In reading order, each of them at most once
+    #[serde(default)]
+    pub bookmark_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Collections {
+    pub collections: Vec<Collection>,
+}
+
+/// What a member of a workspace may do. Viewers search and ask its
+/// bookmarks, editors also add and remove them, the owner also manages
+/// members.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, EnumString, AsRefStr,
+)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum WorkspaceRole {
+    Viewer,
+    Editor,
+    Owner,
+}
+
+/// A shared library, as one of its members sees it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Workspace {
+    pub workspace_id: Uuid,
+    pub name: String,
+    /// Role of the requesting user
+    pub role: WorkspaceRole,
+    pub member_count: i64,
+    pub bookmark_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WorkspaceRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Workspaces {
+    pub workspaces: Vec<Workspace>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WorkspaceMember {
+    pub user_id: Uuid,
+    pub username: String,
+    pub role: WorkspaceRole,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WorkspaceMembers {
+    pub members: Vec<WorkspaceMember>,
+}
+
+/// Adds a user to a workspace, or changes the role of a member.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WorkspaceMemberRequest {
+    pub username: String,
+    /// `viewer` or `editor`, a workspace has a single owner
+    pub role: WorkspaceRole,
+}
+
+/// Saves one of the requesting user's bookmarks into a workspace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WorkspaceBookmarkRequest {
+    pub bookmark_id: String,
+}
+
+/// What a rule looks for in a new bookmark, each condition set having to
+/// match.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RuleConditions {
+    /// Glob over the whole URL ignoring case, `*` standing for any run of
+    /// characters, e.g. `https://github.com/*/issues/*`
+    #[serde(default)]
+    pub url_pattern: Option<String>,
+    /// The domain or any of its subdomains
+    #[serde(default)]
+    pub domain: Option<String>,
+    /// At least one of them in the title, ignoring case
+    #[serde(default)]
+    pub title_keywords: Vec<String>,
+}
+
+/// What a rule does to the new bookmarks it matches.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RuleActions {
+    /// Added to the tags given with the bookmark, which then isn't tagged by
+    /// the AI pipeline
+    #[serde(default)]
+    pub add_tags: Vec<String>,
+    #[serde(default)]
+    pub favorite: bool,
+    /// Leaves the bookmark out of summaries, tagging and embeddings
+    #[serde(default)]
+    pub skip_ai: bool,
+}
+
+/// Acts on the bookmarks added after it, when they are saved and before the
+/// AI pipeline gets to them. Every enabled rule matching a bookmark applies.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Rule {
+    pub rule_id: Uuid,
+    pub name: String,
+    pub enabled: bool,
+    pub conditions: RuleConditions,
+    pub actions: RuleActions,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Creates a rule, or replaces all of it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RuleRequest {
+    pub name: String,
+    /// `true` when absent
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    pub conditions: RuleConditions,
+    pub actions: RuleActions,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Rules {
+    pub rules: Vec<Rule>,
+}
+
+/// CSS selectors applied to the pages of a domain and its subdomains when
+/// they are saved, for sites the article extraction gets wrong. Only the most
+/// specific rule matching a page applies.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ExtractionRule {
+    pub rule_id: Uuid,
+    pub domain: String,
+    /// Elements the article is extracted from, the whole page when none
+    /// matches
+    pub keep_selectors: Vec<String>,
+    /// Elements removed before extraction, e.g. banners or related links
+    pub drop_selectors: Vec<String>,
+    /// Element holding the title, in place of the one extracted
+    pub title_selector: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Creates an extraction rule, or replaces all of it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ExtractionRuleRequest {
+    pub domain: String,
+    #[serde(default)]
+    pub keep_selectors: Vec<String>,
+    #[serde(default)]
+    pub drop_selectors: Vec<String>,
+    #[serde(default)]
+    pub title_selector: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ExtractionRules {
+    pub rules: Vec<ExtractionRule>,
+}
+
+/// Headers attached when fetching the pages of a domain and its subdomains,
+/// e.g. a `Cookie` header holding a session behind a login wall.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FetchCredentialRequest {
+    pub domain: String,
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Replaces all of the user's fetch credentials, an empty list removing them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FetchCredentialsRequest {
+    pub credentials: Vec<FetchCredentialRequest>,
+}
+
+/// A stored fetch credential. Header values are never sent back.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FetchCredential {
+    pub domain: String,
+    pub header_names: Vec<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FetchCredentials {
+    pub credentials: Vec<FetchCredential>,
+}
+
+/// A signed-in device. `current` is the one making the request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Session {
+    pub session_id: Uuid,
+    /// User-Agent of the client that signed in
+    pub device_label: Option<String>,
+    /// Address the session was last seen from
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub current: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Sessions {
+    pub sessions: Vec<Session>,
+}
+
+/// Security-relevant and destructive actions kept in a user's audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumString, AsRefStr)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum AuditAction {
+    SignIn,
+    SignInFailed,
+    PasswordChanged,
+    PasswordReset,
+    EmailChanged,
+    ApiTokenIssued,
+    BookmarkDeleted,
+    BookmarkPurged,
+    BookmarksBulkUpdated,
+    BookmarksBatchAdded,
+    BookmarkShared,
+    CollectionDeleted,
+    CollectionShared,
+    SessionRevoked,
+    WorkspaceDeleted,
+    BackupRestored,
+    AccountDeleted,
+}
+
+/// An entry of the audit log. `target` names what the action applied to,
+/// `ip` is the client address when the action came through the HTTP API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AuditEvent {
+    pub event_id: Uuid,
+    pub action: AuditAction,
+    pub target: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema, utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct AuditLogRequest {
+    /// Only events older than this, to page back through the log
+    pub before: Option<DateTime<Utc>>,
+    /// How many events to return, newest first (default: 50, max: 200)
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AuditLog {
+    pub events: Vec<AuditEvent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumString, AsRefStr)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ActivityKind {
+    BookmarkAdded,
+    SummaryGenerated,
+    TagsApplied,
+    QuestionAsked,
+    ImportCompleted,
+}
+
+/// An entry of the activity feed. `bookmark_id` and `title` name the
+/// bookmark it is about, `detail` is the applied tags, the question asked
+/// or the number of bookmarks imported.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ActivityItem {
+    pub kind: ActivityKind,
+    pub bookmark_id: Option<String>,
+    pub title: Option<String>,
+    pub detail: Option<String>,
     pub created_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema, utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct ActivityRequest {
+    /// Only events older than this, to page back through the feed
+    pub before: Option<DateTime<Utc>>,
+    /// How many events to return, newest first (default: 50, max: 200)
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ActivityFeed {
+    pub items: Vec<ActivityItem>,
+}
+
+/// Link to a collection, readable without signing in until `expires_at`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CollectionShareLink {
+    pub url: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A bookmark of a shared collection, as whoever holds the link sees it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SharedCollectionBookmark {
+    pub title: String,
+    pub url: String,
+    pub domain: String,
+    pub summary: Option<String>,
+    /// Captured page, readable as long as the collection link is
+    pub page_url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SharedCollection {
+    pub name: String,
+    pub description: Option<String>,
+    pub bookmarks: Vec<SharedCollectionBookmark>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema, utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct BookmarksRequest {
+    /// Only the bookmark saved from this URL, compared without its fragment
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema, utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct RandomBookmarkRequest {
+    /// Only pick among bookmarks with this tag
+    pub tag: Option<String>,
+    /// Only pick among bookmarks never opened in the reader
+    #[serde(default)]
+    pub unread: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema, utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct RelatedBookmarksRequest {
+    pub limit: Option<u32>,
+}
+
+/// A bookmark similar to the one requested, scored by cosine similarity of
+/// the bookmark-level embeddings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RelatedBookmark {
+    pub bookmark: Bookmark,
+    pub similarity_score: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RelatedBookmarks {
+    pub bookmarks: Vec<RelatedBookmark>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema, utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct TagSuggestionsRequest {
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum TagSuggestionSource {
+    /// Already used on the user's bookmarks
+    Vocabulary,
+    /// Proposed by the LLM while analyzing the article
+    Llm,
+    /// On bookmarks with similar embeddings
+    Similar,
+}
+
+/// A tag the bookmark doesn't have yet, with where it was suggested from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TagSuggestion {
+    pub tag: String,
+    pub score: f64,
+    pub sources: Vec<TagSuggestionSource>,
+}
+
+/// Suggestions for tagging a bookmark, the best first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TagSuggestions {
+    pub suggestions: Vec<TagSuggestion>,
+}
+
+/// A link found in the article of a bookmark.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BookmarkLink {
+    pub url: String,
+    pub domain: String,
+    /// The saved bookmark it points to, if any
+    pub bookmark_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BookmarkLinks {
+    pub outbound: Vec<BookmarkLink>,
+    /// Saved bookmarks whose articles link to this one
+    pub backlinks: Vec<Bookmark>,
+}
+
+/// An earlier capture of a bookmark, replaced when it was fetched again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BookmarkVersion {
+    pub version_id: Uuid,
+    pub title: String,
+    pub word_count: i32,
+    pub captured_at: DateTime<Utc>,
+}
+
+/// Earlier captures of a bookmark, the most recent first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BookmarkVersions {
+    pub versions: Vec<BookmarkVersion>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BookmarkVersionContent {
+    pub version: BookmarkVersion,
+    pub text_content: String,
+    /// Captured page, without its images
+    pub html: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema, utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct BookmarkVersionDiffRequest {
+    /// Version compared from (default: the most recent one)
+    pub from: Option<Uuid>,
+    /// Version compared to (default: the current capture)
+    pub to: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DiffLine {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+/// Changed lines with a few unchanged ones around them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DiffHunk {
+    pub lines: Vec<DiffLine>,
+}
+
+/// What changed in the text between two captures of a bookmark, `None`
+/// standing for the current one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BookmarkVersionDiff {
+    pub from: Option<Uuid>,
+    pub to: Option<Uuid>,
+    pub hunks: Vec<DiffHunk>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct RagChunkMatch {
     pub chunk: BookmarkChunk,
     pub bookmark: Bookmark,
@@ -272,33 +1789,165 @@ pub struct RagChunkMatch {
     pub fts_score: Option<f64>,
     /// Combined score from hybrid search (RRF or weighted average)
     pub combined_score: Option<f64>,
+    /// LLM score from 0 to 10 of the chunk against the question (populated
+    /// when re-ranking is requested)
+    #[serde(default)]
+    pub rerank_score: Option<f64>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct RagChunkInfo {
     pub chunk: BookmarkChunk,
     pub bookmark: Bookmark,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct RagSessionWithSources {
     pub session_id: Uuid,
     pub user_id: Uuid,
+    #[serde(default)]
+    pub title: Option<String>,
     pub question: String,
     pub answer: Option<String>,
     pub sources: Vec<RagChunkInfo>,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub feedback: Option<RagFeedback>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct RagHistoryRequest {
     pub limit: Option<usize>,
     pub offset: Option<usize>,
+    /// Words the title, question or answer must contain, with `"phrases"`,
+    /// `or` and `-excluded` words like the search
+    #[serde(default)]
+    pub query: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct RagHistoryResponse {
     pub sessions: Vec<RagSessionWithSources>,
     pub total_count: usize,
 }
+
+/// Whether a RAG answer was helpful.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumString, AsRefStr)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum RagRating {
+    Up,
+    Down,
+}
+
+/// The rating of a RAG answer, rating it again replaces it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RagFeedbackRequest {
+    pub rating: RagRating,
+    /// What was wrong or right with the answer, up to 2000 characters
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RagFeedback {
+    pub rating: RagRating,
+    pub comment: Option<String>,
+    pub rated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema, utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct RagQualityRequest {
+    /// How many days back to report, including today (default: 30)
+    pub days: Option<u32>,
+}
+
+/// Answers given and rated on one day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RagQualityDay {
+    pub day: NaiveDate,
+    pub answered: i64,
+    pub rated_up: i64,
+    pub rated_down: i64,
+}
+
+/// A rated answer with a comment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RagFeedbackComment {
+    pub session_id: Uuid,
+    pub question: String,
+    pub rating: RagRating,
+    pub comment: String,
+    pub rated_at: DateTime<Utc>,
+}
+
+/// How the RAG answers of a period were rated, to compare prompt and
+/// retrieval changes against real usage.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RagQualityReport {
+    /// Newest day first, days without answers left out
+    pub days: Vec<RagQualityDay>,
+    pub answered: i64,
+    pub rated_up: i64,
+    pub rated_down: i64,
+    /// Share of the rated answers rated up, absent without ratings
+    pub approval_rate: Option<f64>,
+    /// The latest comments, newest first
+    pub comments: Vec<RagFeedbackComment>,
+}
+
+/// Stable machine-readable reason of a [`Problem`], clients branch on this
+/// rather than on messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumString, AsRefStr)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ErrorCode {
+    AuthenticationRequired,
+    ActionNotAllowed,
+    NotFound,
+    InvalidPayload,
+    InvalidToken,
+    MissingCredentials,
+    WrongCredentials,
+    UsernameTaken,
+    EmailTaken,
+    DuplicateBookmark,
+    QuotaExceeded,
+    TooManyRequests,
+    InternalError,
+    /// A code added by a newer server
+    #[serde(other)]
+    Unknown,
+}
+
+/// RFC 7807 `application/problem+json` body of every API error.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Problem {
+    /// Always `about:blank`, `code` tells problems apart
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    /// Reason phrase of the status
+    pub title: String,
+    pub status: u16,
+    pub code: ErrorCode,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Messages per invalid field of the request
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub errors: HashMap<String, Vec<String>>,
+}
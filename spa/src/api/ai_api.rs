@@ -0,0 +1,15 @@
+use gloo_net::http::Request;
+use gloo_net::Error;
+use shared::AiStatus;
+
+pub async fn get_ai_status(token: &str) -> Result<AiStatus, Error> {
+    const ENDPOINT: &str = "/api/v1/ai/status";
+    let response = Request::get(ENDPOINT)
+        .header("Authorization", &format!("Bearer {token}"))
+        .send()
+        .await?
+        .json::<AiStatus>()
+        .await?;
+    log::info!("Api get AI status");
+    Ok(response)
+}
@@ -1,6 +1,7 @@
 use gloo_net::http::Request;
 use gloo_net::Error;
-use shared::{BookmarkTaskSearchRequest, BookmarkTaskSearchResponse};
+use shared::{BookmarkTask, BookmarkTaskSearchRequest, BookmarkTaskSearchResponse};
+use uuid::Uuid;
 
 pub async fn search_tasks(
     token: &String,
@@ -22,3 +23,21 @@ pub async fn search_tasks(
     );
     Ok(response)
 }
+
+/// The task of `task_id`, looked up among the tasks of its `url`.
+pub async fn find_task(
+    token: &String,
+    url: &str,
+    task_id: Uuid,
+) -> Result<Option<BookmarkTask>, Error> {
+    let request = BookmarkTaskSearchRequest {
+        url: Some(url.to_string()),
+        page_size: Some(50),
+        ..Default::default()
+    };
+    let response = search_tasks(token, request).await?;
+    Ok(response
+        .tasks
+        .into_iter()
+        .find(|task| task.task_id == task_id))
+}
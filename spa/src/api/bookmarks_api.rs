@@ -1,6 +1,9 @@
 use gloo_net::http::Request;
 use gloo_net::Error;
-use shared::{Bookmark, NewBookmarkRequest, NewBookmarkResponse, Tags};
+use shared::{
+    AskBookmarkRequest, Bookmark, BulkBookmarkRequest, BulkBookmarkResponse, NewBookmarkRequest,
+    NewBookmarkResponse, RagQueryResponse, TagSuggestions, Tags,
+};
 use uuid::Uuid;
 
 pub async fn add_bookmark(
@@ -89,6 +92,86 @@ pub async fn delete_bookmark(token: &str, id: &str) -> Result<bool, Error> {
     }
 }
 
+pub async fn mark_read(token: &str, id: &str) -> Result<(), Error> {
+    let endpoint = format!("/api/v1/bookmarks/{id}/read");
+    let response = Request::post(&endpoint)
+        .header("Authorization", &format!("Bearer {token}"))
+        .send()
+        .await?;
+    log::info!("Api mark bookmark read, id={id}");
+    match response.status() {
+        204 | 404 => Ok(()),
+        _ => {
+            let response_body = response.text().await?;
+            log::warn!(
+                "Api mark bookmark id={id} read, error = unexpected response, status={status}, response={response_body}",
+                status = response.status(),
+            );
+            Err(Error::GlooError("unexpected response".to_owned()))
+        }
+    }
+}
+
+pub async fn bulk_update(
+    token: &str,
+    request: &BulkBookmarkRequest,
+) -> Result<BulkBookmarkResponse, Error> {
+    const ENDPOINT: &str = "/api/v1/bookmarks/bulk";
+    let request_body = serde_json::to_string(request).expect("Serialize should not fail");
+    let response = Request::post(ENDPOINT)
+        .header("Authorization", &format!("Bearer {token}"))
+        .header("Content-Type", "application/json")
+        .body(request_body)?
+        .send()
+        .await?
+        .json::<BulkBookmarkResponse>()
+        .await?;
+    log::info!(
+        "Api bulk update, action={:?}, updated={}",
+        request.action,
+        response.updated
+    );
+    Ok(response)
+}
+
+/// Answers a question from the passages of the bookmark `id` only.
+pub async fn ask(
+    token: &str,
+    id: &str,
+    request: &AskBookmarkRequest,
+) -> Result<RagQueryResponse, Error> {
+    let endpoint = format!("/api/v1/bookmarks/{id}/ask");
+    let request_body = serde_json::to_string(request).expect("Serialize should not fail");
+    let response = Request::post(&endpoint)
+        .header("Authorization", &format!("Bearer {token}"))
+        .header("Content-Type", "application/json")
+        .body(request_body)?
+        .send()
+        .await?;
+    log::info!("Api ask bookmark, id={id}, status={}", response.status());
+    match response.status() {
+        200 => response.json::<RagQueryResponse>().await,
+        422 => Err(Error::GlooError(
+            "This bookmark is still being processed, try again later.".to_owned(),
+        )),
+        status => Err(Error::GlooError(format!(
+            "Failed to get an answer, status={status}"
+        ))),
+    }
+}
+
+pub async fn get_tag_suggestions(token: &str, id: &str) -> Result<TagSuggestions, Error> {
+    let endpoint = format!("/api/v1/bookmarks/{id}/tag-suggestions");
+    let response = Request::get(&endpoint)
+        .header("Authorization", &format!("Bearer {token}"))
+        .send()
+        .await?
+        .json::<TagSuggestions>()
+        .await?;
+    log::info!("Api get tag suggestions, id={id}");
+    Ok(response)
+}
+
 pub async fn get_content(token: &str, user_id: &Uuid, id: &str) -> Result<Option<String>, Error> {
     let endpoint = format!("/static/{user_id}/{id}/index.html");
     let response = Request::get(&endpoint)
@@ -0,0 +1,159 @@
+//! Server-sent events of `/api/v1/events`, read from a `fetch` response since
+//! `EventSource` can't send the authorization header, like the other streamed
+//! responses.
+
+use js_sys::{Reflect, Uint8Array};
+use shared::{BookmarkTask, ServerEvent, TaskSubscription};
+use url::form_urlencoded;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{AbortSignal, Headers, ReadableStreamDefaultReader, RequestInit, Response};
+
+/// Splits a stream of bytes into the data of its events.
+#[derive(Default)]
+pub struct EventBuffer {
+    pending: Vec<u8>,
+}
+
+impl EventBuffer {
+    /// Data of the events completed by `chunk`, skipping comments such as
+    /// keep-alives.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.pending
+            .extend(chunk.iter().copied().filter(|byte| *byte != b'\r'));
+        let mut events = Vec::new();
+        while let Some(end) = self.pending.windows(2).position(|window| window == b"\n\n") {
+            let block: Vec<u8> = self.pending.drain(..end + 2).collect();
+            let block = String::from_utf8_lossy(&block);
+            let data: Vec<&str> = block
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|data| data.strip_prefix(' ').unwrap_or(data))
+                .collect();
+            if !data.is_empty() {
+                events.push(data.join("\n"));
+            }
+        }
+        events
+    }
+}
+
+/// Emits the data of each event of the body of `response` to `on_data` until
+/// the stream ends or fails.
+pub async fn read_events(
+    response: Response,
+    mut on_data: impl FnMut(String),
+) -> Result<(), JsValue> {
+    if !response.ok() {
+        return Err(JsValue::from_str(&format!(
+            "unexpected response, status={}",
+            response.status()
+        )));
+    }
+    let body = response
+        .body()
+        .ok_or_else(|| JsValue::from_str("no response body"))?;
+    let reader: ReadableStreamDefaultReader = body.get_reader().dyn_into()?;
+
+    let mut buffer = EventBuffer::default();
+    loop {
+        let chunk = JsFuture::from(reader.read()).await?;
+        if Reflect::get(&chunk, &JsValue::from_str("done"))?.is_truthy() {
+            return Ok(());
+        }
+        let bytes: Uint8Array = Reflect::get(&chunk, &JsValue::from_str("value"))?.dyn_into()?;
+        for data in buffer.push(&bytes.to_vec()) {
+            on_data(data);
+        }
+    }
+}
+
+/// Emits the events pushed to the user to `on_event` until the stream ends,
+/// fails or `signal` aborts it.
+pub async fn stream_events(
+    token: &str,
+    signal: &AbortSignal,
+    on_event: impl Fn(ServerEvent),
+) -> Result<(), JsValue> {
+    open_events("/api/v1/events", token, signal, on_event).await
+}
+
+/// Emits the updates of the tasks matching `subscription` to `on_task`, like
+/// [`stream_events`].
+pub async fn stream_task_events(
+    token: &str,
+    subscription: &TaskSubscription,
+    signal: &AbortSignal,
+    on_task: impl Fn(BookmarkTask),
+) -> Result<(), JsValue> {
+    let endpoint = format!("/api/v1/events/tasks?{}", task_query(subscription));
+    open_events(&endpoint, token, signal, |event| {
+        if let ServerEvent::TaskUpdated { task } = event {
+            on_task(task);
+        }
+    })
+    .await
+}
+
+fn task_query(subscription: &TaskSubscription) -> String {
+    let mut serializer = form_urlencoded::Serializer::new(String::new());
+    if let Some(url) = &subscription.url {
+        serializer.append_pair("url", url);
+    }
+    if let Some(tags) = &subscription.tags {
+        serializer.append_pair("tags", tags);
+    }
+    serializer.finish()
+}
+
+async fn open_events(
+    endpoint: &str,
+    token: &str,
+    signal: &AbortSignal,
+    on_event: impl Fn(ServerEvent),
+) -> Result<(), JsValue> {
+    let headers = Headers::new()?;
+    headers.set("Authorization", &format!("Bearer {token}"))?;
+    headers.set("Accept", "text/event-stream")?;
+    let init = RequestInit::new();
+    init.set_headers(&headers);
+    init.set_signal(Some(signal));
+    let window = web_sys::window().ok_or_else(|| JsValue::from_str("no window"))?;
+    let response: Response = JsFuture::from(window.fetch_with_str_and_init(endpoint, &init))
+        .await?
+        .dyn_into()?;
+    log::info!("Api events stream opened");
+    read_events(response, |data| {
+        match serde_json::from_str::<ServerEvent>(&data) {
+            Ok(event) => on_event(event),
+            Err(error) => log::warn!("Fail to parse server event, error={error}"),
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_split_across_chunks() {
+        let mut buffer = EventBuffer::default();
+        assert!(buffer.push(b"data: {\"a\":").is_empty());
+        assert_eq!(
+            buffer.push(b"1}\n\n:\n\ndata: 2\r\n\r\n"),
+            vec!["{\"a\":1}", "2"]
+        );
+        assert_eq!(buffer.push(b"event: x\ndata: 3\ndata:4\n\n"), vec!["3\n4"]);
+        assert!(buffer.push(b"data: 5\n").is_empty());
+    }
+
+    #[test]
+    fn task_subscription_is_encoded_in_the_query() {
+        let subscription = TaskSubscription {
+            url: None,
+            tags: Some("rust, web".into()),
+        };
+        assert_eq!(task_query(&subscription), "tags=rust%2C+web");
+    }
+}
@@ -1,6 +1,9 @@
+pub mod ai_api;
 pub mod auth_api;
 pub mod bookmark_tasks_api;
 pub mod bookmarks_api;
+pub mod events_api;
 pub mod rag_api;
 pub mod search_api;
+pub mod settings_api;
 pub mod tags_api;
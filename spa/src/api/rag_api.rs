@@ -1,6 +1,14 @@
 use gloo_net::http::Request;
-use shared::{RagHistoryRequest, RagHistoryResponse, RagQueryRequest, RagQueryResponse};
+use shared::{
+    RagFeedbackRequest, RagHistoryRequest, RagHistoryResponse, RagQueryRequest, RagQueryResponse,
+    RagSession, RagStreamEvent,
+};
+use uuid::Uuid;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Headers, RequestInit, Response};
 
+use crate::api::events_api;
 use crate::user_session::UserSession;
 
 const RAG_API_BASE_URL: &str = "/api/v1/rag";
@@ -25,6 +33,37 @@ pub async fn query_rag(
     }
 }
 
+/// Emits the progress and answer events of `request` to `on_event` as the
+/// server streams them.
+pub async fn stream_query(
+    user_session: &UserSession,
+    request: &RagQueryRequest,
+    on_event: impl Fn(RagStreamEvent),
+) -> Result<(), JsValue> {
+    let headers = Headers::new()?;
+    headers.set("Authorization", &format!("Bearer {}", user_session.token))?;
+    headers.set("Content-Type", "application/json")?;
+    headers.set("Accept", "text/event-stream")?;
+    let body =
+        serde_json::to_string(request).map_err(|error| JsValue::from_str(&error.to_string()))?;
+    let init = RequestInit::new();
+    init.set_method("POST");
+    init.set_headers(&headers);
+    init.set_body(&JsValue::from_str(&body));
+    let window = web_sys::window().ok_or_else(|| JsValue::from_str("no window"))?;
+    let endpoint = format!("{}/query/stream", RAG_API_BASE_URL);
+    let response: Response = JsFuture::from(window.fetch_with_str_and_init(&endpoint, &init))
+        .await?
+        .dyn_into()?;
+    events_api::read_events(response, |data| {
+        match serde_json::from_str::<RagStreamEvent>(&data) {
+            Ok(event) => on_event(event),
+            Err(error) => log::warn!("Fail to parse RAG stream event, error={error}"),
+        }
+    })
+    .await
+}
+
 pub async fn get_rag_history(
     user_session: &UserSession,
     request: &RagHistoryRequest,
@@ -44,3 +83,47 @@ pub async fn get_rag_history(
         )))
     }
 }
+
+/// Rates the answer of `session_id`, replacing an earlier rating.
+pub async fn send_feedback(
+    user_session: &UserSession,
+    session_id: Uuid,
+    request: &RagFeedbackRequest,
+) -> Result<RagSession, gloo_net::Error> {
+    let response = Request::post(&format!(
+        "{}/sessions/{}/feedback",
+        RAG_API_BASE_URL, session_id
+    ))
+    .header("authorization", &format!("Bearer {}", user_session.token))
+    .json(request)?
+    .send()
+    .await?;
+
+    if response.ok() {
+        response.json::<RagSession>().await
+    } else {
+        Err(gloo_net::Error::GlooError(format!(
+            "RAG feedback failed with status: {}",
+            response.status()
+        )))
+    }
+}
+
+/// Deletes the session `session_id`, `false` when it was already gone.
+pub async fn delete_session(
+    user_session: &UserSession,
+    session_id: Uuid,
+) -> Result<bool, gloo_net::Error> {
+    let response = Request::delete(&format!("{}/sessions/{}", RAG_API_BASE_URL, session_id))
+        .header("authorization", &format!("Bearer {}", user_session.token))
+        .send()
+        .await?;
+
+    match response.status() {
+        204 => Ok(true),
+        404 => Ok(false),
+        status => Err(gloo_net::Error::GlooError(format!(
+            "RAG session deletion failed with status: {status}"
+        ))),
+    }
+}
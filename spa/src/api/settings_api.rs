@@ -0,0 +1,30 @@
+use gloo_net::http::Request;
+use gloo_net::Error;
+use shared::UserSettings;
+
+pub async fn get_settings(token: &str) -> Result<UserSettings, Error> {
+    const ENDPOINT: &str = "/api/v1/settings";
+    let response = Request::get(ENDPOINT)
+        .header("Authorization", &format!("Bearer {token}"))
+        .send()
+        .await?
+        .json::<UserSettings>()
+        .await?;
+    log::info!("Api get settings");
+    Ok(response)
+}
+
+pub async fn update_settings(token: &str, settings: &UserSettings) -> Result<UserSettings, Error> {
+    const ENDPOINT: &str = "/api/v1/settings";
+    let request_body = serde_json::to_string(settings).expect("Serialize should not fail");
+    let response = Request::put(ENDPOINT)
+        .header("Authorization", &format!("Bearer {token}"))
+        .header("Content-Type", "application/json")
+        .body(request_body)?
+        .send()
+        .await?
+        .json::<UserSettings>()
+        .await?;
+    log::info!("Api update settings");
+    Ok(response)
+}
@@ -4,11 +4,23 @@ use yew_hooks::prelude::*;
 
 use crate::api::auth_api;
 use crate::components::composite::login_form::{LoginForm, LoginFormData};
+use crate::components::toast::{use_toaster, ToastProvider};
+use crate::offline;
 use crate::pages::home::Home;
 use crate::user_session::UserSession;
 
 #[function_component(App)]
 pub fn app() -> Html {
+    html! {
+        <ToastProvider>
+            <Session />
+        </ToastProvider>
+    }
+}
+
+#[function_component(Session)]
+fn session() -> Html {
+    let toaster = use_toaster();
     let storage = use_local_storage::<UserSession>("user-session".to_string());
     let logged = use_state(|| false);
     let is_first = use_is_first_mount();
@@ -24,6 +36,12 @@ pub fn app() -> Html {
                         Ok(_) => {
                             logged.set(true);
                         }
+                        // Offline, the session is kept so the bookmarks saved for
+                        // offline reading remain readable
+                        Err(gloo_net::Error::JsError(error)) => {
+                            log::warn!("Fail to reach the server, keeping session. Error={error}");
+                            logged.set(true);
+                        }
                         Err(error) => {
                             log::warn!(
                                 "Fail to fetch user profile, cleaning session. Error={error}"
@@ -47,6 +65,7 @@ pub fn app() -> Html {
         Callback::from(move |event: LoginFormData| {
             let storage = storage.clone();
             let logged = logged.clone();
+            let toaster = toaster.clone();
             spawn_local(async move {
                 match auth_api::login(event.username.clone(), event.password.clone()).await {
                     Ok(response) => {
@@ -64,6 +83,7 @@ pub fn app() -> Html {
                     }
                     Err(error) => {
                         log::warn!("Login failed, error: {error}");
+                        toaster.error("Login failed, check the username and password.");
                         storage.delete();
                         logged.set(false);
                     }
@@ -79,6 +99,11 @@ pub fn app() -> Html {
             log::info!("User logged out");
             storage.delete();
             logged.set(false);
+            spawn_local(async {
+                if let Err(error) = offline::clear().await {
+                    log::warn!("Fail to clear offline bookmarks. Error={error:?}");
+                }
+            });
         })
     };
 
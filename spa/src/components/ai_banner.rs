@@ -0,0 +1,120 @@
+//! Banner telling which AI features the server can't provide, so missing
+//! summaries or semantic results aren't a mystery.
+
+use shared::{AiFeatureState, AiFeatureStatus, AiStatus};
+use yew::platform::spawn_local;
+use yew::prelude::*;
+
+use crate::api::ai_api;
+
+/// One line per AI feature that isn't available, degraded ones first.
+fn unavailable_features(status: &AiStatus) -> Vec<String> {
+    let features = [
+        ("Summaries, tags and answers", &status.text),
+        ("Semantic search", &status.embeddings),
+    ];
+    let line = |(label, feature): &(&str, &AiFeatureStatus)| {
+        let state = match feature.state {
+            AiFeatureState::Degraded => "degraded",
+            _ => "disabled",
+        };
+        match &feature.reason {
+            Some(reason) => format!("{label} {state}: {reason}"),
+            None => format!("{label} {state}"),
+        }
+    };
+    let with_state = |state| {
+        features
+            .iter()
+            .filter(move |(_, feature)| feature.state == state)
+            .map(line)
+    };
+    with_state(AiFeatureState::Degraded)
+        .chain(with_state(AiFeatureState::Disabled))
+        .collect()
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub token: String,
+}
+
+#[function_component(AiBanner)]
+pub fn ai_banner(props: &Props) -> Html {
+    let status = use_state(|| None::<AiStatus>);
+    let dismissed = use_state(|| false);
+
+    {
+        let status = status.clone();
+        use_effect_with(props.token.clone(), move |token| {
+            let token = token.clone();
+            spawn_local(async move {
+                match ai_api::get_ai_status(&token).await {
+                    Ok(ai_status) => status.set(Some(ai_status)),
+                    Err(error) => log::warn!("Fail to load AI status, error={error:?}"),
+                }
+            });
+        });
+    }
+
+    let Some(ai_status) = status.as_ref() else {
+        return html! {};
+    };
+    let lines = unavailable_features(ai_status);
+    if lines.is_empty() || *dismissed {
+        return html! {};
+    }
+    let degraded = [&ai_status.text, &ai_status.embeddings]
+        .iter()
+        .any(|feature| feature.state == AiFeatureState::Degraded);
+    let class = if degraded {
+        "alert alert-warning alert-dismissible mb-3"
+    } else {
+        "alert alert-secondary alert-dismissible mb-3"
+    };
+    let on_dismiss = Callback::from(move |_| dismissed.set(true));
+
+    html! {
+        <div class={class} role="status">
+            <ul class="mb-0 ps-3">
+                { for lines.into_iter().map(|line| html! { <li>{line}</li> }) }
+            </ul>
+            <button type="button" class="btn-close" aria-label="Dismiss" onclick={on_dismiss}></button>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feature(state: AiFeatureState, reason: Option<&str>) -> AiFeatureStatus {
+        AiFeatureStatus {
+            state,
+            provider: Some("ollama".into()),
+            model: Some("llama3.2".into()),
+            reason: reason.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn degraded_features_come_before_disabled_ones() {
+        let status = AiStatus {
+            text: feature(AiFeatureState::Disabled, None),
+            embeddings: feature(AiFeatureState::Degraded, Some("Model is not pulled")),
+        };
+        assert_eq!(
+            unavailable_features(&status),
+            vec![
+                "Semantic search degraded: Model is not pulled".to_string(),
+                "Summaries, tags and answers disabled".to_string(),
+            ]
+        );
+
+        let available = AiStatus {
+            text: feature(AiFeatureState::Available, None),
+            embeddings: feature(AiFeatureState::Available, None),
+        };
+        assert!(unavailable_features(&available).is_empty());
+    }
+}
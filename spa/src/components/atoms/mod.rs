@@ -5,3 +5,4 @@ pub mod input_text;
 pub mod markdown_render;
 pub mod safe_html;
 pub mod select;
+pub mod virtual_list;
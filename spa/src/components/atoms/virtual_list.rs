@@ -0,0 +1,179 @@
+//! A list scrolled with the window that only renders the items near the
+//! viewport, standing in for the others with spacers of their height.
+
+use std::ops::Range;
+
+use wasm_bindgen::JsCast;
+use web_sys::{Element, HtmlElement};
+use yew::prelude::*;
+use yew_hooks::prelude::*;
+
+/// Height of an item not rendered yet
+const ESTIMATED_HEIGHT: f64 = 200.0;
+/// Pixels rendered above and below the viewport
+const OVERSCAN: f64 = 1500.0;
+const INDEX_ATTRIBUTE: &str = "data-virtual-index";
+
+/// Offset of the item at `index` from the top of the list.
+fn offset(heights: &[f64], index: usize) -> f64 {
+    heights[..index.min(heights.len())].iter().sum()
+}
+
+/// The items to render to show `viewport` pixels from `scroll_top` within
+/// the list, plus `overscan` pixels either side.
+pub fn visible_range(
+    heights: &[f64],
+    scroll_top: f64,
+    viewport: f64,
+    overscan: f64,
+) -> Range<usize> {
+    let top = scroll_top - overscan;
+    let bottom = scroll_top + viewport + overscan;
+    let mut start = heights.len();
+    let mut end = heights.len();
+    let mut item_top = 0.0;
+    for (index, height) in heights.iter().enumerate() {
+        let item_bottom = item_top + height;
+        if start == heights.len() && item_bottom > top {
+            start = index;
+        }
+        if item_top >= bottom {
+            end = index;
+            break;
+        }
+        item_top = item_bottom;
+    }
+    start.min(end)..end
+}
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    pub count: usize,
+    /// Renders the item at an index
+    pub render: Callback<usize, Html>,
+    /// Item to bring into view if it isn't rendered, once picked with the
+    /// keyboard
+    #[prop_or_default]
+    pub scroll_to: Option<usize>,
+    /// Emitted when the end of the list comes into view
+    pub on_reach_end: Callback<()>,
+}
+
+#[function_component(VirtualList)]
+pub fn virtual_list(props: &Props) -> Html {
+    let heights = use_mut_ref(Vec::<f64>::new);
+    let list_ref = use_node_ref();
+    let items_ref = use_node_ref();
+    let force_update = use_force_update();
+    let (_, scroll_y) = use_window_scroll();
+    let (_, viewport) = use_window_size();
+
+    heights.borrow_mut().resize(props.count, ESTIMATED_HEIGHT);
+    let list_top = list_ref
+        .cast::<Element>()
+        .map(|list| list.get_bounding_client_rect().top())
+        .unwrap_or_default();
+    let range = visible_range(&heights.borrow(), -list_top, viewport, OVERSCAN);
+    let top_spacer = offset(&heights.borrow(), range.start);
+    let bottom_spacer =
+        offset(&heights.borrow(), props.count) - offset(&heights.borrow(), range.end);
+
+    {
+        let heights = heights.clone();
+        let items_ref = items_ref.clone();
+        use_effect(move || {
+            let Some(items) = items_ref.cast::<Element>() else {
+                return;
+            };
+            let children = items.children();
+            let mut changed = false;
+            for position in 0..children.length() {
+                let Some(item) = children
+                    .item(position)
+                    .and_then(|item| item.dyn_into::<HtmlElement>().ok())
+                else {
+                    continue;
+                };
+                let Some(index) = item
+                    .get_attribute(INDEX_ATTRIBUTE)
+                    .and_then(|index| index.parse::<usize>().ok())
+                else {
+                    continue;
+                };
+                let height = item.offset_height() as f64;
+                if let Some(known) = heights.borrow_mut().get_mut(index) {
+                    if (*known - height).abs() >= 1.0 {
+                        *known = height;
+                        changed = true;
+                    }
+                }
+            }
+            if changed {
+                force_update.force_update();
+            }
+        });
+    }
+
+    {
+        let at_end = props.count > 0 && range.end == props.count;
+        let on_reach_end = props.on_reach_end.clone();
+        use_effect_with((at_end, props.count), move |(at_end, _)| {
+            if *at_end {
+                on_reach_end.emit(());
+            }
+        });
+    }
+
+    {
+        let heights = heights.clone();
+        let rendered = range.clone();
+        use_effect_with(props.scroll_to, move |scroll_to| {
+            if let Some(index) = *scroll_to {
+                if !rendered.contains(&index) {
+                    let window = web_sys::window().expect("window should be available");
+                    let top = scroll_y + list_top + offset(&heights.borrow(), index);
+                    window.scroll_to_with_x_and_y(0.0, top - viewport / 3.0);
+                }
+            }
+        });
+    }
+
+    html! {
+        <div ref={list_ref}>
+            <div style={format!("height: {top_spacer}px;")}></div>
+            <div ref={items_ref}>
+                {
+                    range.map(|index| html! {
+                        <div key={index} data-virtual-index={index.to_string()} style="display: flow-root;">
+                            {props.render.emit(index)}
+                        </div>
+                    }).collect::<Html>()
+                }
+            </div>
+            <div style={format!("height: {bottom_spacer}px;")}></div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_the_items_around_the_viewport() {
+        let heights = vec![100.0; 100];
+        assert_eq!(visible_range(&heights, 0.0, 300.0, 0.0), 0..3);
+        assert_eq!(visible_range(&heights, 250.0, 300.0, 0.0), 2..6);
+        assert_eq!(visible_range(&heights, 250.0, 300.0, 200.0), 0..8);
+        assert_eq!(visible_range(&heights, 9_900.0, 300.0, 0.0), 99..100);
+        assert_eq!(visible_range(&heights, 20_000.0, 300.0, 0.0), 100..100);
+    }
+
+    #[test]
+    fn list_below_the_viewport_renders_from_its_start() {
+        let heights = vec![50.0, 400.0, 50.0];
+        assert_eq!(visible_range(&heights, -1_000.0, 300.0, 0.0), 0..0);
+        assert_eq!(visible_range(&heights, -200.0, 300.0, 0.0), 0..2);
+        assert!(visible_range(&[], 0.0, 300.0, 100.0).is_empty());
+    }
+}
@@ -15,6 +15,7 @@ impl From<AddBookmarkData> for NewBookmarkRequest {
         NewBookmarkRequest {
             url: value.url,
             tags: value.tags,
+            ignore_robots: false,
         }
     }
 }
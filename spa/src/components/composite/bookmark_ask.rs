@@ -0,0 +1,210 @@
+//! Questions about the bookmark open in the reader, answered from its own
+//! passages, each cited passage shown back in the article.
+
+use shared::{AskBookmarkRequest, RagQueryResponse, RagTurn};
+use web_sys::HtmlInputElement;
+use yew::platform::spawn_local;
+use yew::prelude::*;
+
+use crate::api::bookmarks_api;
+use crate::components::atoms::markdown_render::MarkdownRender;
+use crate::components::composite::rag_chat::link_citations;
+use crate::components::passage;
+use crate::user_session::UserSession;
+
+#[derive(Clone, PartialEq, Debug)]
+struct AskTurn {
+    question: String,
+    answer: Option<Result<RagQueryResponse, String>>,
+}
+
+/// The answered turns, sent along a follow-up question.
+fn history(turns: &[AskTurn]) -> Vec<RagTurn> {
+    turns
+        .iter()
+        .filter_map(|turn| match &turn.answer {
+            Some(Ok(response)) => Some(RagTurn {
+                question: turn.question.clone(),
+                answer: response.answer.clone(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub user_session: UserSession,
+    pub bookmark_id: String,
+    /// Opening words of a cited passage to show in the article
+    pub on_passage: Callback<String>,
+}
+
+#[function_component(BookmarkAsk)]
+pub fn bookmark_ask(props: &Props) -> Html {
+    let turns = use_state_eq(Vec::<AskTurn>::new);
+    let question_ref = use_node_ref();
+    let pending = turns.iter().any(|turn| turn.answer.is_none());
+
+    let on_submit = {
+        let turns = turns.clone();
+        let question_ref = question_ref.clone();
+        let token = props.user_session.token.clone();
+        let bookmark_id = props.bookmark_id.clone();
+        Callback::from(move |event: SubmitEvent| {
+            event.prevent_default();
+            let Some(input) = question_ref.cast::<HtmlInputElement>() else {
+                return;
+            };
+            let question = input.value().trim().to_string();
+            if question.is_empty() || turns.iter().any(|turn| turn.answer.is_none()) {
+                return;
+            }
+            input.set_value("");
+            let request = AskBookmarkRequest {
+                question: question.clone(),
+                history: history(&turns),
+            };
+            let mut asked = (*turns).clone();
+            asked.push(AskTurn {
+                question,
+                answer: None,
+            });
+            turns.set(asked.clone());
+
+            let turns = turns.clone();
+            let token = token.clone();
+            let bookmark_id = bookmark_id.clone();
+            spawn_local(async move {
+                let answer = bookmarks_api::ask(&token, &bookmark_id, &request)
+                    .await
+                    .map_err(|error| match error {
+                        gloo_net::Error::GlooError(message) => message,
+                        error => {
+                            log::warn!("Bookmark question failed, error={error:?}");
+                            "Failed to get an answer, try again.".to_string()
+                        }
+                    });
+                if let Some(turn) = asked.last_mut() {
+                    turn.answer = Some(answer);
+                }
+                turns.set(asked);
+            });
+        })
+    };
+
+    html! {
+        <details class="card mb-3">
+            <summary class="card-header">{"Ask this article"}</summary>
+            <div class="card-body">
+                {
+                    turns.iter().enumerate().map(|(index, turn)| html! {
+                        <div key={index} class="mb-3">
+                            <p class="fw-semibold mb-1">{&turn.question}</p>
+                            {render_answer(index, turn, &props.on_passage)}
+                        </div>
+                    }).collect::<Html>()
+                }
+                <form class="input-group" onsubmit={on_submit}>
+                    <input ref={question_ref} type="text" class="form-control"
+                        placeholder="Ask a question about this article..." disabled={pending} />
+                    <button type="submit" class="btn btn-primary" disabled={pending}>
+                        if pending {
+                            <span class="spinner-border spinner-border-sm me-2" role="status"></span>
+                        }
+                        {"Ask"}
+                    </button>
+                </form>
+            </div>
+        </details>
+    }
+}
+
+fn render_answer(index: usize, turn: &AskTurn, on_passage: &Callback<String>) -> Html {
+    let response = match &turn.answer {
+        None => {
+            return html! {
+                <div class="text-muted small">{"Reading the article..."}</div>
+            }
+        }
+        Some(Err(message)) => {
+            return html! {
+                <div class="alert alert-danger py-2 mb-0" role="alert">{message}</div>
+            }
+        }
+        Some(Ok(response)) => response,
+    };
+    let anchor = |number: usize| format!("ask-source-{index}-{number}");
+    let answer = link_citations(&response.answer, |number| {
+        response
+            .citations
+            .iter()
+            .any(|citation| citation.number == number)
+            .then(|| format!("#{}", anchor(number)))
+    });
+    let sources = response
+        .citations
+        .iter()
+        .filter_map(|citation| {
+            let chunk_match = response.relevant_chunks.get(citation.number - 1)?;
+            let opening = passage::opening_words(&chunk_match.chunk.chunk_text);
+            let onclick = {
+                let on_passage = on_passage.clone();
+                Callback::from(move |_: MouseEvent| on_passage.emit(opening.clone()))
+            };
+            let preview: String = chunk_match.chunk.chunk_text.chars().take(120).collect();
+            Some(html! {
+                <li key={citation.number} id={anchor(citation.number)} class="small mb-1">
+                    <span class="badge bg-primary me-2">{citation.number}</span>
+                    <button type="button" class="btn btn-link btn-sm p-0 text-start" onclick={onclick}>
+                        {format!("{preview}...")}
+                    </button>
+                </li>
+            })
+        })
+        .collect::<Html>();
+    html! {
+        <>
+            <MarkdownRender content={answer} />
+            <ol class="list-unstyled mb-0">{sources}</ol>
+        </>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follow_ups_carry_the_answered_turns_only() {
+        let response = RagQueryResponse {
+            session_id: uuid::Uuid::nil(),
+            question: "What is it?".into(),
+            answer: "A runtime [1].".into(),
+            relevant_chunks: Vec::new(),
+            citations: Vec::new(),
+            created_at: chrono::Utc::now(),
+        };
+        let turns = vec![
+            AskTurn {
+                question: "What is it?".into(),
+                answer: Some(Ok(response)),
+            },
+            AskTurn {
+                question: "Why?".into(),
+                answer: Some(Err("Failed".into())),
+            },
+            AskTurn {
+                question: "How?".into(),
+                answer: None,
+            },
+        ];
+        assert_eq!(
+            history(&turns),
+            vec![RagTurn {
+                question: "What is it?".into(),
+                answer: "A runtime [1].".into(),
+            }]
+        );
+    }
+}
@@ -1,11 +1,16 @@
 use log::warn;
-use shared::Bookmark;
+use shared::{Bookmark, ReaderSettings, UserSettings};
+use web_sys::Element;
 use yew::platform::spawn_local;
 use yew::prelude::*;
 
-use crate::api::bookmarks_api;
+use crate::api::{bookmarks_api, settings_api};
 use crate::components::atoms::input_text::{InputText, InputType};
 use crate::components::atoms::safe_html::ArticleHtml;
+use crate::components::composite::bookmark_ask::BookmarkAsk;
+use crate::components::composite::reader_settings_panel::{reader_style, ReaderSettingsPanel};
+use crate::components::passage;
+use crate::offline;
 use crate::router::{self, AppRoute};
 use crate::user_session::UserSession;
 
@@ -13,6 +18,9 @@ use crate::user_session::UserSession;
 pub struct Props {
     pub user_session: UserSession,
     pub bookmark: Bookmark,
+    /// Opening words of a passage to highlight once the article is loaded
+    #[prop_or_default]
+    pub passage: Option<String>,
     pub on_goback: Callback<()>,
     pub on_new_tags: Callback<Vec<String>>,
     pub on_delete: Callback<()>,
@@ -25,6 +33,7 @@ pub fn bookmark_page(props: &Props) -> Html {
     let state = use_state_eq(|| props.bookmark.tags.clone().unwrap_or_default());
     let tags_as_string = state.clone().join(", ");
     let html_content = use_state_eq(|| None);
+    let suggestions = use_state_eq(Vec::<String>::new);
     {
         let state = state.clone();
         let html_content = html_content.clone();
@@ -35,6 +44,39 @@ pub fn bookmark_page(props: &Props) -> Html {
             html_content.set(None);
         });
     }
+    {
+        let suggestions = suggestions.clone();
+        let token = token.clone();
+        let bookmark_id = props.bookmark.bookmark_id.clone();
+        use_effect_with(bookmark_id.clone(), move |_| {
+            suggestions.set(vec![]);
+            spawn_local(async move {
+                match bookmarks_api::get_tag_suggestions(&token, &bookmark_id).await {
+                    Ok(response) => suggestions.set(
+                        response
+                            .suggestions
+                            .into_iter()
+                            .map(|suggestion| suggestion.tag)
+                            .collect(),
+                    ),
+                    Err(error) => {
+                        warn!("Failed to fetch tag suggestions for bookmark_id: {bookmark_id}, error: {error}");
+                    }
+                }
+            });
+        });
+    }
+    {
+        let token = token.clone();
+        let bookmark_id = props.bookmark.bookmark_id.clone();
+        use_effect_with(bookmark_id.clone(), move |_| {
+            spawn_local(async move {
+                if let Err(error) = bookmarks_api::mark_read(&token, &bookmark_id).await {
+                    warn!("Failed to mark bookmark_id: {bookmark_id} read, error: {error}");
+                }
+            });
+        });
+    }
     {
         let html_contentt = html_content.clone();
         let token = token.clone();
@@ -55,6 +97,121 @@ pub fn bookmark_page(props: &Props) -> Html {
         });
     }
 
+    let article_ref = use_node_ref();
+    {
+        let article_ref = article_ref.clone();
+        let loaded = html_content.is_some();
+        use_effect_with((loaded, props.passage.clone()), move |(loaded, passage)| {
+            if let (true, Some(passage), Some(article)) =
+                (*loaded, passage, article_ref.cast::<Element>())
+            {
+                if !passage::highlight(&article, passage) {
+                    warn!("Passage not found in the article, passage: {passage}");
+                }
+            }
+        });
+    }
+
+    let on_ask_passage = {
+        let article_ref = article_ref.clone();
+        Callback::from(move |opening: String| {
+            if let Some(article) = article_ref.cast::<Element>() {
+                if !passage::highlight(&article, &opening) {
+                    warn!("Passage not found in the article, passage: {opening}");
+                }
+            }
+        })
+    };
+
+    let settings = use_state_eq(|| None::<UserSettings>);
+    let show_reader_settings = use_state_eq(|| false);
+    {
+        let settings = settings.clone();
+        let token = token.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                match settings_api::get_settings(&token).await {
+                    Ok(response) => settings.set(Some(response)),
+                    Err(error) => warn!("Failed to fetch reader settings, error: {error}"),
+                }
+            });
+        });
+    }
+
+    let on_reader_settings_change = {
+        let settings = settings.clone();
+        let token = token.clone();
+        Callback::from(move |reader: ReaderSettings| {
+            let Some(current) = (*settings).clone() else {
+                return;
+            };
+            let updated = UserSettings { reader, ..current };
+            settings.set(Some(updated.clone()));
+            let settings = settings.clone();
+            let token = token.clone();
+            spawn_local(async move {
+                match settings_api::update_settings(&token, &updated).await {
+                    Ok(response) => settings.set(Some(response)),
+                    Err(error) => warn!("Failed to save reader settings, error: {error}"),
+                }
+            });
+        })
+    };
+
+    let on_toggle_reader_settings = {
+        let show_reader_settings = show_reader_settings.clone();
+        Callback::from(move |_: MouseEvent| show_reader_settings.set(!*show_reader_settings))
+    };
+
+    // None while unknown or being changed
+    let saved_offline = use_state_eq(|| None::<bool>);
+    {
+        let saved_offline = saved_offline.clone();
+        let bookmark_id = props.bookmark.bookmark_id.clone();
+        use_effect_with(bookmark_id.clone(), move |_| {
+            saved_offline.set(None);
+            if offline::is_supported() {
+                spawn_local(async move {
+                    match offline::is_saved(&bookmark_id).await {
+                        Ok(saved) => saved_offline.set(Some(saved)),
+                        Err(error) => {
+                            warn!("Failed to check offline copy of bookmark_id: {bookmark_id}, error: {error:?}");
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    let on_toggle_offline = {
+        let saved_offline = saved_offline.clone();
+        let token = token.clone();
+        let bookmark_id = props.bookmark.bookmark_id.clone();
+        Callback::from(move |_: MouseEvent| {
+            let Some(saved) = *saved_offline else {
+                return;
+            };
+            let saved_offline = saved_offline.clone();
+            let token = token.clone();
+            let bookmark_id = bookmark_id.clone();
+            saved_offline.set(None);
+            spawn_local(async move {
+                let result = if saved {
+                    offline::remove(&bookmark_id).await
+                } else {
+                    offline::save(&token, &user_id, &bookmark_id).await
+                };
+                match result {
+                    Ok(()) => saved_offline.set(Some(!saved)),
+                    Err(error) => {
+                        warn!("Failed to change offline copy of bookmark_id: {bookmark_id}, error: {error:?}");
+                        saved_offline.set(Some(saved));
+                    }
+                }
+            });
+        })
+    };
+
     let on_tag_change: Callback<String> = {
         let state = state.clone();
         Callback::from(move |text: String| {
@@ -73,6 +230,32 @@ pub fn bookmark_page(props: &Props) -> Html {
         })
     };
 
+    let suggested_tags = suggestions
+        .iter()
+        .filter(|tag| !state.contains(tag))
+        .map(|tag| {
+            let state = state.clone();
+            let tag = tag.clone();
+            let onclick = {
+                let tag = tag.clone();
+                Callback::from(move |_: MouseEvent| {
+                    let mut tags: Vec<String> = (*state)
+                        .iter()
+                        .filter(|tag| !tag.is_empty())
+                        .cloned()
+                        .collect();
+                    tags.push(tag.clone());
+                    state.set(tags);
+                })
+            };
+            html! {
+                <button {onclick} class="btn btn-sm btn-outline-secondary me-1 mb-1" type="button">
+                    {format!("+ {tag}")}
+                </button>
+            }
+        })
+        .collect::<Html>();
+
     let on_save_tags = {
         let callback = props.on_new_tags.clone();
         Callback::from(move |_: MouseEvent| {
@@ -86,7 +269,7 @@ pub fn bookmark_page(props: &Props) -> Html {
             let window = web_sys::window().unwrap();
             let confirmed = window
                 .confirm_with_message(
-                    "Are you sure you want to delete this bookmark? This action cannot be undone.",
+                    "Move this bookmark to the trash? It can be restored until it is purged.",
                 )
                 .unwrap_or(false);
             if confirmed {
@@ -114,6 +297,31 @@ pub fn bookmark_page(props: &Props) -> Html {
         html! { <></> }
     };
 
+    let offline_toggle = if offline::is_supported() {
+        let (label, class) = match *saved_offline {
+            Some(true) => ("Saved offline", "btn btn-success"),
+            Some(false) => ("Save for offline", "btn btn-outline-success"),
+            None => ("Saving...", "btn btn-outline-success"),
+        };
+        html! {
+            <button onclick={on_toggle_offline} class={class} type="button"
+                    disabled={saved_offline.is_none()}>{label}</button>
+        }
+    } else {
+        html! { <></> }
+    };
+
+    let reader = settings
+        .as_ref()
+        .map(|settings| settings.reader.clone())
+        .unwrap_or_default();
+    let reader_settings_panel = match (*show_reader_settings, &*settings) {
+        (true, Some(_)) => html! {
+            <ReaderSettingsPanel settings={reader.clone()} on_change={on_reader_settings_change} />
+        },
+        _ => html! { <></> },
+    };
+
     let back_href = router::href(&AppRoute::Search(Default::default()));
 
     html! {
@@ -146,12 +354,19 @@ pub fn bookmark_page(props: &Props) -> Html {
                               value={tags_as_string} />
                           <button onclick={on_save_tags} class="btn btn-primary" type="button">{"Save"}</button>
                           <button onclick={on_delete} class="btn btn-danger" type="button">{"Delete"}</button>
+                          {offline_toggle}
+                          <button onclick={on_toggle_reader_settings} class="btn btn-outline-secondary" type="button"
+                                  title="Reader settings" disabled={settings.is_none()}>{"Aa"}</button>
                       </div>
+                      {reader_settings_panel}
+                      <div class="mt-2">{suggested_tags}</div>
                   </div>
               </div>
           </div>
           <br/>
-          <div>
+          <BookmarkAsk key={props.bookmark.bookmark_id.clone()} user_session={props.user_session.clone()}
+              bookmark_id={props.bookmark.bookmark_id.clone()} on_passage={on_ask_passage} />
+          <div style={reader_style(&reader)}>
             <style>
             {"
                 figure img {
@@ -162,7 +377,7 @@ pub fn bookmark_page(props: &Props) -> Html {
             </style>
             {summary}
             <h1 class="mb-4">{ props.bookmark.title.clone() }</h1>
-            {article}
+            <div ref={article_ref}>{article}</div>
           </div>
           <div class="mb-3">
               <a href={back_href} class="btn btn-secondary" onclick={on_goback.clone()}>{"< Back to Home"}</a>
@@ -0,0 +1,118 @@
+use shared::BulkBookmarkAction;
+use yew::prelude::*;
+
+use crate::components::atoms::input_text::{InputText, InputType};
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    pub selected_count: usize,
+    pub total_results: u64,
+    /// Every bookmark matching the search is selected, not only the checked
+    pub all_matching: bool,
+    pub on_select_page: Callback<()>,
+    pub on_select_all_matching: Callback<()>,
+    pub on_clear: Callback<()>,
+    pub on_action: Callback<BulkBookmarkAction>,
+    pub on_done: Callback<()>,
+}
+
+#[function_component(BulkToolbar)]
+pub fn bulk_toolbar(props: &Props) -> Html {
+    let tags = use_state_eq(String::new);
+
+    let on_tags_change = {
+        let tags = tags.clone();
+        Callback::from(move |text: String| tags.set(text))
+    };
+
+    let emit = |action: BulkBookmarkAction| {
+        let on_action = props.on_action.clone();
+        Callback::from(move |_: MouseEvent| on_action.emit(action.clone()))
+    };
+
+    let on_add_tags = {
+        let tags = tags.clone();
+        let on_action = props.on_action.clone();
+        Callback::from(move |_: MouseEvent| {
+            let new_tags: Vec<String> = tags
+                .split(',')
+                .map(|tag| tag.trim().to_string())
+                .filter(|tag| !tag.is_empty())
+                .collect();
+            if !new_tags.is_empty() {
+                on_action.emit(BulkBookmarkAction::AddTags { tags: new_tags });
+                tags.set(String::new());
+            }
+        })
+    };
+
+    let on_trash = {
+        let on_action = props.on_action.clone();
+        let count = if props.all_matching {
+            props.total_results as usize
+        } else {
+            props.selected_count
+        };
+        Callback::from(move |_: MouseEvent| {
+            let window = web_sys::window().unwrap();
+            let confirmed = window
+                .confirm_with_message(&format!(
+                    "Move {count} bookmark(s) to the trash? They can be restored until purged."
+                ))
+                .unwrap_or(false);
+            if confirmed {
+                on_action.emit(BulkBookmarkAction::Trash);
+            }
+        })
+    };
+
+    let callback = |callback: &Callback<()>| {
+        let callback = callback.clone();
+        Callback::from(move |_: MouseEvent| callback.emit(()))
+    };
+
+    let selection = if props.all_matching {
+        format!("All {} matching bookmarks selected", props.total_results)
+    } else {
+        format!("{} selected", props.selected_count)
+    };
+    let nothing_selected = !props.all_matching && props.selected_count == 0;
+
+    html! {
+        <div class="card card-body mt-3">
+            <div class="d-flex flex-wrap align-items-center gap-2">
+                <strong class="me-2">{selection}</strong>
+                <button type="button" class="btn btn-sm btn-outline-secondary"
+                        onclick={callback(&props.on_select_page)}>{"Select page"}</button>
+                if !props.all_matching && props.total_results > 0 {
+                    <button type="button" class="btn btn-sm btn-outline-secondary"
+                            onclick={callback(&props.on_select_all_matching)}>
+                        {format!("Select all {} matching", props.total_results)}
+                    </button>
+                }
+                <button type="button" class="btn btn-sm btn-outline-secondary"
+                        onclick={callback(&props.on_clear)}>{"Clear"}</button>
+                <div class="input-group input-group-sm w-auto">
+                    <InputText
+                        id="bulk-tags"
+                        name="bulk-tags"
+                        placeholder="tags, comma separated"
+                        class={classes!("form-control")}
+                        input_type={InputType::Text}
+                        value={Some((*tags).clone())}
+                        on_change={on_tags_change} />
+                    <button type="button" class="btn btn-primary" disabled={nothing_selected}
+                            onclick={on_add_tags}>{"Add tags"}</button>
+                </div>
+                <button type="button" class="btn btn-sm btn-outline-warning" disabled={nothing_selected}
+                        onclick={emit(BulkBookmarkAction::Favorite)}>{"Favorite"}</button>
+                <button type="button" class="btn btn-sm btn-outline-secondary" disabled={nothing_selected}
+                        onclick={emit(BulkBookmarkAction::Unfavorite)}>{"Unfavorite"}</button>
+                <button type="button" class="btn btn-sm btn-danger" disabled={nothing_selected}
+                        onclick={on_trash}>{"Delete"}</button>
+                <button type="button" class="btn btn-sm btn-secondary ms-auto"
+                        onclick={callback(&props.on_done)}>{"Done"}</button>
+            </div>
+        </div>
+    }
+}
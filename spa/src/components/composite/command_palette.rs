@@ -0,0 +1,215 @@
+use wasm_bindgen::JsCast;
+use web_sys::{EventTarget, HtmlInputElement};
+use yew::prelude::*;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum PaletteAction {
+    GoToSearch,
+    GoToTasks,
+    GoToRag,
+    FocusSearch,
+    AddBookmark,
+    TagBookmark,
+    OpenBookmark(String),
+    Logout,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PaletteCommand {
+    pub label: String,
+    pub action: PaletteAction,
+}
+
+impl PaletteCommand {
+    pub fn new(label: impl Into<String>, action: PaletteAction) -> Self {
+        Self {
+            label: label.into(),
+            action,
+        }
+    }
+}
+
+/// The commands whose label has every word of `query`, ignoring case.
+pub fn filter<'a>(commands: &'a [PaletteCommand], query: &str) -> Vec<&'a PaletteCommand> {
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    commands
+        .iter()
+        .filter(|command| {
+            let label = command.label.to_lowercase();
+            words.iter().all(|word| label.contains(word))
+        })
+        .collect()
+}
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    pub open: bool,
+    pub commands: Vec<PaletteCommand>,
+    pub on_action: Callback<PaletteAction>,
+    pub on_close: Callback<()>,
+}
+
+#[function_component(CommandPalette)]
+pub fn command_palette(props: &Props) -> Html {
+    let query = use_state_eq(String::new);
+    let selected = use_state_eq(|| 0usize);
+    let input_ref = use_node_ref();
+
+    {
+        let query = query.clone();
+        let selected = selected.clone();
+        let input_ref = input_ref.clone();
+        use_effect_with(props.open, move |open| {
+            query.set(String::new());
+            selected.set(0);
+            if *open {
+                if let Some(input) = input_ref.cast::<HtmlInputElement>() {
+                    let _ = input.focus();
+                }
+            }
+        });
+    }
+
+    if !props.open {
+        return html! {};
+    }
+
+    let matching: Vec<PaletteCommand> = filter(&props.commands, &query)
+        .into_iter()
+        .cloned()
+        .collect();
+    let selected_index = (*selected).min(matching.len().saturating_sub(1));
+
+    let run = {
+        let on_action = props.on_action.clone();
+        let on_close = props.on_close.clone();
+        Callback::from(move |action: PaletteAction| {
+            on_close.emit(());
+            on_action.emit(action);
+        })
+    };
+
+    let on_input = {
+        let query = query.clone();
+        let selected = selected.clone();
+        Callback::from(move |event: InputEvent| {
+            let target: EventTarget = event.target().expect("Fail to cast to EventTarget");
+            query.set(target.unchecked_into::<HtmlInputElement>().value());
+            selected.set(0);
+        })
+    };
+
+    let on_keydown = {
+        let selected = selected.clone();
+        let matching = matching.clone();
+        let run = run.clone();
+        Callback::from(move |event: KeyboardEvent| match event.key().as_str() {
+            "ArrowDown" => {
+                event.prevent_default();
+                if selected_index + 1 < matching.len() {
+                    selected.set(selected_index + 1);
+                }
+            }
+            "ArrowUp" => {
+                event.prevent_default();
+                selected.set(selected_index.saturating_sub(1));
+            }
+            "Enter" => {
+                event.prevent_default();
+                if let Some(command) = matching.get(selected_index) {
+                    run.emit(command.action.clone());
+                }
+            }
+            _ => {}
+        })
+    };
+
+    let on_backdrop_click = {
+        let on_close = props.on_close.clone();
+        Callback::from(move |event: MouseEvent| {
+            // Clicks within the dialog bubble up to the backdrop too
+            if event.target() == event.current_target() {
+                on_close.emit(());
+            }
+        })
+    };
+
+    let items = matching
+        .iter()
+        .enumerate()
+        .map(|(index, command)| {
+            let classes = if index == selected_index {
+                classes!("list-group-item", "list-group-item-action", "active")
+            } else {
+                classes!("list-group-item", "list-group-item-action")
+            };
+            let onclick = {
+                let run = run.clone();
+                let action = command.action.clone();
+                Callback::from(move |_: MouseEvent| run.emit(action.clone()))
+            };
+            html! {
+                <button type="button" class={classes} {onclick}>{command.label.clone()}</button>
+            }
+        })
+        .collect::<Html>();
+
+    html! {
+        <>
+            <div class="modal d-block" tabindex="-1" role="dialog" aria-label="Command palette"
+                 onclick={on_backdrop_click}>
+                <div class="modal-dialog modal-dialog-scrollable">
+                    <div class="modal-content">
+                        <div class="modal-header">
+                            <input
+                                ref={input_ref}
+                                type="text"
+                                class="form-control"
+                                placeholder="Type a command"
+                                value={(*query).clone()}
+                                oninput={on_input}
+                                onkeydown={on_keydown} />
+                        </div>
+                        <div class="modal-body p-0">
+                            if matching.is_empty() {
+                                <p class="text-muted m-3">{"No matching command"}</p>
+                            } else {
+                                <div class="list-group list-group-flush">{items}</div>
+                            }
+                        </div>
+                        <div class="modal-footer">
+                            <small class="text-muted">
+                                {"j/k move · o open · t tag · / search · ctrl-k palette · esc close"}
+                            </small>
+                        </div>
+                    </div>
+                </div>
+            </div>
+            <div class="modal-backdrop show"></div>
+        </>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_word_must_match() {
+        let commands = vec![
+            PaletteCommand::new("Go to search", PaletteAction::GoToSearch),
+            PaletteCommand::new("Go to tasks", PaletteAction::GoToTasks),
+            PaletteCommand::new("Focus search", PaletteAction::FocusSearch),
+        ];
+        let labels = |query: &str| -> Vec<String> {
+            filter(&commands, query)
+                .into_iter()
+                .map(|command| command.label.clone())
+                .collect()
+        };
+        assert_eq!(labels("").len(), 3);
+        assert_eq!(labels("SEARCH"), vec!["Go to search", "Focus search"]);
+        assert_eq!(labels("go search"), vec!["Go to search"]);
+        assert!(labels("trash").is_empty());
+    }
+}
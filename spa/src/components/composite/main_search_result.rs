@@ -1,19 +1,71 @@
-use shared::SearchResultItem;
+use shared::{BookmarkKind, SearchResultItem};
 use yew::prelude::*;
 
 use crate::components::atoms::safe_html::BlockquoteHtml;
+use crate::components::atoms::virtual_list::VirtualList;
 use crate::router::{self, AppRoute};
 
 #[derive(Debug, Clone, PartialEq, Properties)]
 pub struct Props {
     pub results: Vec<SearchResultItem>,
     pub on_item_selected: Callback<String>,
+    /// Result picked with the keyboard
+    #[prop_or_default]
+    pub selected: Option<usize>,
+    /// Shows a checkbox on every result, for bulk actions
+    #[prop_or_default]
+    pub selectable: bool,
+    #[prop_or_default]
+    pub checked_ids: Vec<String>,
+    #[prop_or_default]
+    pub on_item_checked: Callback<String>,
+    /// Renders only the results near the viewport, for long scrolled lists
+    #[prop_or_default]
+    pub virtualized: bool,
+    #[prop_or_default]
+    pub on_reach_end: Callback<()>,
 }
 
-fn article(callback: Callback<String>, item: SearchResultItem) -> Html {
+/// Id of the element of the result at `index`, to scroll it into view.
+pub fn result_element_id(index: usize) -> String {
+    format!("search-result-{index}")
+}
+
+fn kind_icon(kind: Option<BookmarkKind>) -> Html {
+    let Some(kind) = kind else {
+        return html! { <></> };
+    };
+    let icon = match kind {
+        BookmarkKind::Article => "📰",
+        BookmarkKind::Paper => "🎓",
+        BookmarkKind::Docs => "📘",
+        BookmarkKind::Recipe => "🍳",
+        BookmarkKind::Video => "🎬",
+    };
+    html! {
+        <span class="me-2" title={kind.as_ref().to_string()} aria-label={kind.as_ref().to_string()}>{icon}</span>
+    }
+}
+
+fn checkbox(on_checked: Callback<String>, bookmark_id: String, checked: bool) -> Html {
+    let id = format!("select-{bookmark_id}");
+    let on_change = Callback::from(move |_: Event| on_checked.emit(bookmark_id.clone()));
+    html! {
+        <input id={id} type="checkbox" class="form-check-input me-2" aria-label="Select bookmark"
+               checked={checked} onchange={on_change} />
+    }
+}
+
+fn article(
+    callback: Callback<String>,
+    item: SearchResultItem,
+    selected: bool,
+    check: Option<Html>,
+) -> Html {
     let bookmark_id = item.bookmark.bookmark_id.clone();
     let href = router::href(&AppRoute::Bookmark {
         bookmark_id: bookmark_id.clone(),
+        passage: None,
     });
     let tags = item
         .bookmark
@@ -23,10 +75,11 @@ fn article(callback: Callback<String>, item: SearchResultItem) -> Html {
         .map(|tag| html! { <span key={tag.clone()} class="badge bg-primary me-1">{tag}</span> })
         .collect::<Vec<_>>();
 
-    let search_match = match item.search_match.clone() {
-        Some(html) => html! { <BlockquoteHtml html={html} /> },
-        None => html! { <></>},
-    };
+    let search_match = item
+        .snippets
+        .iter()
+        .map(|html| html! { <BlockquoteHtml html={html.clone()} /> })
+        .collect::<Html>();
     let on_click = Callback::from(move |event: MouseEvent| {
         if router::should_handle_spa_navigation(&event) {
             event.prevent_default();
@@ -42,9 +95,9 @@ fn article(callback: Callback<String>, item: SearchResultItem) -> Html {
     };
 
     html! {
-        <div class="card mb-3">
+        <div class={classes!("card", "mb-3", selected.then_some("border-primary"))}>
             <div class="card-body">
-                <h5 class="card-title">{item.bookmark.title.clone()}</h5>
+                <h5 class="card-title">{check.unwrap_or_default()}{kind_icon(item.bookmark.kind)}{item.bookmark.title.clone()}</h5>
                 <p class="card-text">{search_match}</p>
                 <div>{tags}</div>
                 <small class="text-muted">{"Created at:"} {item.bookmark.created_at}</small>
@@ -55,19 +108,48 @@ fn article(callback: Callback<String>, item: SearchResultItem) -> Html {
     }
 }
 
+fn result(props: &Props, index: usize, item: SearchResultItem) -> Html {
+    let selected = props.selected == Some(index);
+    let check = props.selectable.then(|| {
+        let bookmark_id = item.bookmark.bookmark_id.clone();
+        let checked = props.checked_ids.contains(&bookmark_id);
+        checkbox(props.on_item_checked.clone(), bookmark_id, checked)
+    });
+    html! {
+        <div key={item.bookmark.url.clone()} id={result_element_id(index)}>
+            {article(props.on_item_selected.clone(), item, selected, check)}
+        </div>
+    }
+}
+
 #[function_component(SearchResult)]
 pub fn search_result(props: &Props) -> Html {
-    let results = props.results.clone();
+    if props.virtualized {
+        let render = {
+            let props = props.clone();
+            Callback::from(move |index: usize| {
+                props
+                    .results
+                    .get(index)
+                    .map(|item| result(&props, index, item.clone()))
+                    .unwrap_or_default()
+            })
+        };
+        return html! {
+            <main>
+                <VirtualList
+                    count={props.results.len()}
+                    render={render}
+                    scroll_to={props.selected}
+                    on_reach_end={props.on_reach_end.clone()} />
+            </main>
+        };
+    }
     html! {
         <main>
             {
-                results.into_iter().map(|bookmark| {
-                    let key = bookmark.bookmark.url.clone();
-                    html! {
-                        <div key={key}>
-                            {article(props.on_item_selected.clone(), bookmark)}
-                        </div>
-                    }
+                props.results.iter().enumerate().map(|(index, item)| {
+                    result(props, index, item.clone())
                 }).collect::<Html>()
             }
         </main>
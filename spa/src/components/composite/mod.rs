@@ -1,11 +1,15 @@
 pub mod add_bookmark_modal;
+pub mod bookmark_ask;
 pub mod bookmark_reader;
+pub mod bulk_toolbar;
+pub mod command_palette;
 pub mod login_form;
 pub mod main_search_result;
 pub mod navigation_bar;
 pub mod pagination_controls;
+pub mod rag_chat;
 pub mod rag_history;
-pub mod rag_query;
+pub mod reader_settings_panel;
 pub mod search_bar;
 pub mod tags_filter;
 pub mod tasks_filter;
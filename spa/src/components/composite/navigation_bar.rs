@@ -8,32 +8,53 @@ pub struct Props {
     pub active_page: RouteKind,
     pub on_page_change: Callback<RouteKind>,
     pub on_logout: Callback<()>,
+    #[prop_or_default]
+    pub pending_tasks: usize,
+    /// More tasks are pending than counted
+    #[prop_or_default]
+    pub more_pending_tasks: bool,
 }
 
 #[function_component(NavigationBar)]
 pub fn navigation_bar(props: &Props) -> Html {
-    let render_nav_link =
-        |label: &'static str, route_kind: RouteKind, route: AppRoute, active: RouteKind| {
-            let href = router::href(&route);
-            let classes = if active == route_kind {
-                classes!("nav-link", "active")
-            } else {
-                classes!("nav-link")
-            };
-            let on_page_change = props.on_page_change.clone();
-            let onclick = Callback::from(move |event: MouseEvent| {
-                if router::should_handle_spa_navigation(&event) {
-                    event.prevent_default();
-                    on_page_change.emit(route_kind);
-                }
-            });
-
-            html! {
-                <li class="nav-item">
-                    <a href={href} onclick={onclick} class={classes}>{label}</a>
-                </li>
-            }
+    let render_nav_link = |label: &'static str,
+                           route_kind: RouteKind,
+                           route: AppRoute,
+                           active: RouteKind,
+                           badge: Html| {
+        let href = router::href(&route);
+        let classes = if active == route_kind {
+            classes!("nav-link", "active")
+        } else {
+            classes!("nav-link")
         };
+        let on_page_change = props.on_page_change.clone();
+        let onclick = Callback::from(move |event: MouseEvent| {
+            if router::should_handle_spa_navigation(&event) {
+                event.prevent_default();
+                on_page_change.emit(route_kind);
+            }
+        });
+
+        html! {
+            <li class="nav-item">
+                <a href={href} onclick={onclick} class={classes}>{label}{badge}</a>
+            </li>
+        }
+    };
+
+    let pending_badge = if props.pending_tasks > 0 {
+        let plus = if props.more_pending_tasks { "+" } else { "" };
+        let count = format!("{}{plus}", props.pending_tasks);
+        html! {
+            <span class="badge rounded-pill text-bg-secondary ms-1" title={format!("{count} pending tasks")}>
+                <span class="spinner-border spinner-border-sm me-1" style="width: 0.7rem; height: 0.7rem;" aria-hidden="true"></span>
+                {count}
+            </span>
+        }
+    } else {
+        html! {}
+    };
 
     let home_href = router::href(&AppRoute::Search(Default::default()));
     let on_home_click = {
@@ -59,11 +80,11 @@ pub fn navigation_bar(props: &Props) -> Html {
                 <a class="navbar-brand" href={home_href} onclick={on_home_click}>{"BookMark Hub"}</a>
                 <div class="collapse navbar-collapse">
                     <ul class="navbar-nav me-auto mb-2 mb-lg-0">
-                        {render_nav_link("Search", RouteKind::Search, AppRoute::Search(Default::default()), props.active_page)}
-                        {render_nav_link("Tasks", RouteKind::Tasks, AppRoute::Tasks, props.active_page)}
-                        {render_nav_link("RAG", RouteKind::RAG, AppRoute::RAG { tab: None }, props.active_page)}
+                        {render_nav_link("Search", RouteKind::Search, AppRoute::Search(Default::default()), props.active_page, html! {})}
+                        {render_nav_link("Tasks", RouteKind::Tasks, AppRoute::Tasks, props.active_page, pending_badge)}
+                        {render_nav_link("RAG", RouteKind::RAG, AppRoute::RAG { tab: None }, props.active_page, html! {})}
                     </ul>
-                    <button class="btn btn-sm me-2 btn-outline-primary" data-bs-toggle="modal" data-bs-target="#add-bookmark-modal">
+                    <button id="add-bookmark-button" class="btn btn-sm me-2 btn-outline-primary" data-bs-toggle="modal" data-bs-target="#add-bookmark-modal">
                         {"+ Bookmark"}
                     </button>
                     <span class="navbar-text me-3">{&props.username}</span>
@@ -0,0 +1,700 @@
+//! A conversation with the RAG pipeline, its answers streamed in as they are
+//! written, each followed by the passages it cites and a way to rate it.
+
+use std::rc::Rc;
+
+use shared::{
+    RagChunkMatch, RagFeedbackRequest, RagQueryRequest, RagQueryResponse, RagRating,
+    RagStreamEvent, RagTurn,
+};
+use uuid::Uuid;
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
+use yew::platform::spawn_local;
+use yew::prelude::*;
+
+use crate::api::rag_api;
+use crate::components::atoms::input_switch::InputSwitch;
+use crate::components::atoms::markdown_render::MarkdownRender;
+use crate::components::passage;
+use crate::router::{self, AppRoute};
+use crate::user_session::UserSession;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ChatTurn {
+    pub question: String,
+    /// The answer so far while streamed
+    pub answer: String,
+    /// Step of the pipeline reached before the answer starts
+    pub progress: Option<String>,
+    pub response: Option<RagQueryResponse>,
+    pub error: Option<String>,
+    /// How the user rated the answer
+    pub rating: Option<RagRating>,
+}
+
+impl ChatTurn {
+    fn is_pending(&self) -> bool {
+        self.response.is_none() && self.error.is_none()
+    }
+}
+
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct ChatState {
+    pub turns: Vec<ChatTurn>,
+    /// Counts the conversations started, to drop the events of the streams
+    /// of a conversation left
+    conversation: usize,
+}
+
+impl ChatState {
+    fn is_pending(&self) -> bool {
+        self.turns.iter().any(ChatTurn::is_pending)
+    }
+
+    /// The answered turns, sent along a follow-up question.
+    fn history(&self) -> Vec<RagTurn> {
+        self.turns
+            .iter()
+            .filter_map(|turn| {
+                turn.response.as_ref().map(|response| RagTurn {
+                    question: turn.question.clone(),
+                    answer: response.answer.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+pub enum ChatAction {
+    Ask(String),
+    Event {
+        conversation: usize,
+        turn: usize,
+        event: RagStreamEvent,
+    },
+    /// Ends the turn with `message` unless already answered
+    Failed {
+        conversation: usize,
+        turn: usize,
+        message: String,
+    },
+    Rated {
+        conversation: usize,
+        turn: usize,
+        rating: RagRating,
+    },
+    Reset,
+}
+
+impl Reducible for ChatState {
+    type Action = ChatAction;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        let mut state = (*self).clone();
+        match action {
+            ChatAction::Ask(question) => state.turns.push(ChatTurn {
+                question,
+                answer: String::new(),
+                progress: Some("Searching your bookmarks...".to_string()),
+                response: None,
+                error: None,
+                rating: None,
+            }),
+            ChatAction::Event {
+                conversation,
+                turn,
+                event,
+            } => {
+                let Some(turn) = state
+                    .turns
+                    .get_mut(turn)
+                    .filter(|turn| conversation == self.conversation && turn.is_pending())
+                else {
+                    return self;
+                };
+                match event {
+                    RagStreamEvent::VariationsGenerated { questions } => {
+                        turn.progress = Some(format!(
+                            "Searching with {} variations of the question...",
+                            questions.len()
+                        ));
+                    }
+                    RagStreamEvent::ChunksRetrieved { count } => {
+                        turn.progress = Some(format!("Checking {count} passages..."));
+                    }
+                    RagStreamEvent::RelevanceAssessed { relevant, total } => {
+                        turn.progress =
+                            Some(format!("Answering from {relevant} of {total} passages..."));
+                    }
+                    RagStreamEvent::AnswerDelta { text } => {
+                        turn.progress = None;
+                        turn.answer.push_str(&text);
+                    }
+                    RagStreamEvent::Completed { response } => {
+                        turn.progress = None;
+                        turn.answer = response.answer.clone();
+                        turn.response = Some(response);
+                    }
+                    RagStreamEvent::Failed { message } => {
+                        turn.progress = None;
+                        turn.error = Some(message);
+                    }
+                }
+            }
+            ChatAction::Failed {
+                conversation,
+                turn,
+                message,
+            } => {
+                let Some(turn) = state
+                    .turns
+                    .get_mut(turn)
+                    .filter(|turn| conversation == self.conversation && turn.is_pending())
+                else {
+                    return self;
+                };
+                turn.progress = None;
+                turn.error = Some(message);
+            }
+            ChatAction::Rated {
+                conversation,
+                turn,
+                rating,
+            } => {
+                let Some(turn) = state
+                    .turns
+                    .get_mut(turn)
+                    .filter(|_| conversation == self.conversation)
+                else {
+                    return self;
+                };
+                turn.rating = Some(rating);
+            }
+            ChatAction::Reset => {
+                state.turns.clear();
+                state.conversation += 1;
+            }
+        }
+        Rc::new(state)
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub user_session: UserSession,
+    /// Kept by the page so the conversation outlives a visit to its sources
+    pub chat: UseReducerHandle<ChatState>,
+    pub on_navigate: Callback<AppRoute>,
+}
+
+#[function_component(RagChat)]
+pub fn rag_chat(props: &Props) -> Html {
+    let question = use_state_eq(String::new);
+    let question_ref = use_node_ref();
+    let rerank = use_state_eq(|| false);
+    let pending = props.chat.is_pending();
+
+    let ask = {
+        let question = question.clone();
+        let rerank = rerank.clone();
+        let chat = props.chat.clone();
+        let user_session = props.user_session.clone();
+        Callback::from(move |_: ()| {
+            let text = question.trim().to_string();
+            if text.is_empty() || chat.is_pending() {
+                return;
+            }
+            let conversation = chat.conversation;
+            let turn = chat.turns.len();
+            let request = RagQueryRequest {
+                question: text.clone(),
+                max_chunks: Some(10),
+                similarity_threshold: Some(0.3),
+                max_context_tokens: None,
+                hybrid_search: None,
+                tags_filter: None,
+                bookmark_ids: None,
+                workspace: None,
+                history: chat.history(),
+                rerank: *rerank,
+            };
+            chat.dispatch(ChatAction::Ask(text));
+            question.set(String::new());
+
+            let dispatcher = chat.dispatcher();
+            let user_session = user_session.clone();
+            spawn_local(async move {
+                let result = rag_api::stream_query(&user_session, &request, |event| {
+                    dispatcher.dispatch(ChatAction::Event {
+                        conversation,
+                        turn,
+                        event,
+                    })
+                })
+                .await;
+                let message = match result {
+                    Ok(()) => "The answer was interrupted, try again.".to_string(),
+                    Err(error) => {
+                        log::warn!("RAG query failed, error={error:?}");
+                        "Failed to get an answer, try again.".to_string()
+                    }
+                };
+                dispatcher.dispatch(ChatAction::Failed {
+                    conversation,
+                    turn,
+                    message,
+                });
+            });
+        })
+    };
+
+    let on_input = {
+        let question = question.clone();
+        let question_ref = question_ref.clone();
+        Callback::from(move |_: InputEvent| {
+            if let Some(input) = question_ref.cast::<HtmlTextAreaElement>() {
+                question.set(input.value());
+            }
+        })
+    };
+
+    let on_keydown = {
+        let ask = ask.clone();
+        Callback::from(move |event: KeyboardEvent| {
+            if event.key() == "Enter" && !event.shift_key() {
+                event.prevent_default();
+                ask.emit(());
+            }
+        })
+    };
+
+    let on_submit = {
+        let ask = ask.clone();
+        Callback::from(move |event: SubmitEvent| {
+            event.prevent_default();
+            ask.emit(());
+        })
+    };
+
+    let on_rerank_change = {
+        let rerank = rerank.clone();
+        Callback::from(move |checked: bool| rerank.set(checked))
+    };
+
+    let on_reset = {
+        let chat = props.chat.clone();
+        Callback::from(move |_: MouseEvent| chat.dispatch(ChatAction::Reset))
+    };
+
+    html! {
+        <div>
+            if props.chat.turns.is_empty() {
+                <p class="text-muted">
+                    {"Ask a question about your bookmarks. Follow-up questions can refer to the earlier answers."}
+                </p>
+            }
+            {
+                props.chat.turns.iter().enumerate().map(|(index, turn)| {
+                    let conversation = props.chat.conversation;
+                    let chat = props.chat.clone();
+                    let on_rated = Callback::from(move |rating| {
+                        chat.dispatch(ChatAction::Rated { conversation, turn: index, rating })
+                    });
+                    html! {
+                        <div key={format!("{conversation}-{index}")}>
+                            {render_turn(index, turn, &props.user_session, on_rated, &props.on_navigate)}
+                        </div>
+                    }
+                }).collect::<Html>()
+            }
+            <form class="mt-3" onsubmit={on_submit}>
+                <textarea
+                    ref={question_ref}
+                    id="question"
+                    rows="3"
+                    class="form-control mb-2"
+                    placeholder="Ask a question about your bookmarks... (Enter to send, Shift+Enter for a new line)"
+                    value={(*question).clone()}
+                    oninput={on_input}
+                    onkeydown={on_keydown}
+                />
+                <div class="d-flex gap-2">
+                    <button type="submit" class="btn btn-primary"
+                            disabled={pending || question.trim().is_empty()}>
+                        if pending {
+                            <span class="spinner-border spinner-border-sm me-2" role="status"></span>
+                        }
+                        {"Send"}
+                    </button>
+                    <button type="button" class="btn btn-outline-secondary" onclick={on_reset}
+                            disabled={props.chat.turns.is_empty()}>{"New conversation"}</button>
+                    <div class="ms-auto align-self-center">
+                        <InputSwitch name="rerank" label="Re-rank passages (slower)"
+                            on_change={on_rerank_change} />
+                    </div>
+                </div>
+            </form>
+        </div>
+    }
+}
+
+/// Turns the citation markers of `answer`, like `[1]` or `[1, 3]`, into a
+/// link per number to its `href`, leaving the numbers without one as text.
+pub fn link_citations(answer: &str, href: impl Fn(usize) -> Option<String>) -> String {
+    let mut linked = String::with_capacity(answer.len());
+    let mut rest = answer;
+    while let Some(start) = rest.find('[') {
+        linked.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let marker = rest
+            .find(']')
+            .filter(|end| !rest[end + 1..].starts_with('('))
+            .and_then(|end| {
+                let numbers = rest[1..end]
+                    .split(',')
+                    .map(|number| number.trim().parse::<usize>().ok())
+                    .collect::<Option<Vec<_>>>()?;
+                Some((end, numbers))
+            });
+        let Some((end, numbers)) = marker else {
+            linked.push('[');
+            rest = &rest[1..];
+            continue;
+        };
+        for number in numbers {
+            match href(number) {
+                Some(href) => linked.push_str(&format!("[\\[{number}\\]]({href})")),
+                None => linked.push_str(&format!("\\[{number}\\]")),
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    linked.push_str(rest);
+    linked
+}
+
+fn render_turn(
+    index: usize,
+    turn: &ChatTurn,
+    user_session: &UserSession,
+    on_rated: Callback<RagRating>,
+    on_navigate: &Callback<AppRoute>,
+) -> Html {
+    let anchor = |number: usize| format!("rag-source-{index}-{number}");
+    let (answer, cited, uncited) = match &turn.response {
+        Some(response) => {
+            let is_cited = |number: usize| {
+                response
+                    .citations
+                    .iter()
+                    .any(|citation| citation.number == number)
+            };
+            let answer = link_citations(&turn.answer, |number| {
+                is_cited(number).then(|| format!("#{}", anchor(number)))
+            });
+            let (cited, uncited): (Vec<_>, Vec<_>) = response
+                .relevant_chunks
+                .iter()
+                .enumerate()
+                .map(|(position, chunk_match)| (position + 1, chunk_match))
+                .partition(|(number, _)| is_cited(*number));
+            (answer, cited, uncited)
+        }
+        None => (turn.answer.clone(), Vec::new(), Vec::new()),
+    };
+    let sources = |sources: Vec<(usize, &RagChunkMatch)>| {
+        sources
+            .into_iter()
+            .map(|(number, chunk_match)| {
+                render_source(number, &anchor(number), chunk_match, on_navigate)
+            })
+            .collect::<Html>()
+    };
+    html! {
+        <>
+            <div class="d-flex justify-content-end mb-2">
+                <div class="card text-bg-primary" style="max-width: 80%;">
+                    <div class="card-body py-2" style="white-space: pre-wrap;">{&turn.question}</div>
+                </div>
+            </div>
+            <div class="card mb-3">
+                <div class="card-body">
+                    if let Some(progress) = &turn.progress {
+                        <div class="text-muted">
+                            <span class="spinner-border spinner-border-sm me-2" aria-hidden="true"></span>
+                            {progress}
+                        </div>
+                    }
+                    if !answer.is_empty() {
+                        <MarkdownRender content={answer} />
+                    }
+                    if let Some(error) = &turn.error {
+                        <div class="alert alert-danger mb-0" role="alert">{error}</div>
+                    }
+                    if !cited.is_empty() {
+                        <h6 class="text-muted mt-3">{"Sources"}</h6>
+                        <ol class="list-unstyled mb-0">{sources(cited)}</ol>
+                    }
+                    if !uncited.is_empty() {
+                        <details class="mt-2">
+                            <summary class="small text-muted">
+                                {format!("Passages not cited ({})", uncited.len())}
+                            </summary>
+                            <ol class="list-unstyled mt-2 mb-0">{sources(uncited)}</ol>
+                        </details>
+                    }
+                    if let Some(response) = &turn.response {
+                        <AnswerFeedback user_session={user_session.clone()}
+                            session_id={response.session_id} rating={turn.rating}
+                            on_rated={on_rated} />
+                    }
+                </div>
+            </div>
+        </>
+    }
+}
+
+fn render_source(
+    number: usize,
+    anchor: &str,
+    chunk_match: &RagChunkMatch,
+    on_navigate: &Callback<AppRoute>,
+) -> Html {
+    let route = AppRoute::Bookmark {
+        bookmark_id: chunk_match.bookmark.bookmark_id.clone(),
+        passage: Some(passage::opening_words(&chunk_match.chunk.chunk_text)),
+    };
+    let href = router::href(&route);
+    let onclick = {
+        let on_navigate = on_navigate.clone();
+        Callback::from(move |event: MouseEvent| {
+            if router::should_handle_spa_navigation(&event) {
+                event.prevent_default();
+                on_navigate.emit(route.clone());
+            }
+        })
+    };
+    html! {
+        <li key={number} id={anchor.to_string()} class="mb-2">
+            <span class="badge bg-primary me-2">{number}</span>
+            <a href={href} onclick={onclick} class="text-decoration-none">{&chunk_match.bookmark.title}</a>
+            <small class="text-muted ms-2">
+                {&chunk_match.bookmark.domain}{" · "}
+                {format!("{:.1}%", chunk_match.similarity_score * 100.0)}
+                if let Some(score) = chunk_match.rerank_score {
+                    {format!(" · ranked {score:.0}/10")}
+                }
+            </small>
+            <details class="ms-4">
+                <summary class="small text-muted">{"Passage"}</summary>
+                <div class="border-start border-primary border-3 ps-3 mt-1">
+                    <MarkdownRender content={chunk_match.chunk.chunk_text.clone()} class={Some("small".to_string())} />
+                </div>
+                if let Some(explanation) = &chunk_match.relevance_explanation {
+                    <small class="text-muted fst-italic">
+                        <strong>{"Relevance: "}</strong>{explanation}
+                    </small>
+                }
+            </details>
+        </li>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct FeedbackProps {
+    user_session: UserSession,
+    session_id: Uuid,
+    rating: Option<RagRating>,
+    on_rated: Callback<RagRating>,
+}
+
+/// Rates an answer, then offers to say why.
+#[function_component(AnswerFeedback)]
+fn answer_feedback(props: &FeedbackProps) -> Html {
+    let comment = use_state_eq(String::new);
+    let comment_sent = use_state_eq(|| false);
+    let error = use_state_eq(|| None::<String>);
+
+    let send = {
+        let user_session = props.user_session.clone();
+        let session_id = props.session_id;
+        let on_rated = props.on_rated.clone();
+        let comment_sent = comment_sent.clone();
+        let error = error.clone();
+        Callback::from(move |request: RagFeedbackRequest| {
+            let user_session = user_session.clone();
+            let on_rated = on_rated.clone();
+            let comment_sent = comment_sent.clone();
+            let error = error.clone();
+            spawn_local(async move {
+                match rag_api::send_feedback(&user_session, session_id, &request).await {
+                    Ok(_) => {
+                        error.set(None);
+                        comment_sent.set(request.comment.is_some());
+                        on_rated.emit(request.rating);
+                    }
+                    Err(err) => {
+                        log::warn!("RAG feedback failed, error={err:?}");
+                        error.set(Some("Failed to send the feedback, try again.".to_string()));
+                    }
+                }
+            });
+        })
+    };
+
+    let rate = |rating: RagRating| {
+        let send = send.clone();
+        Callback::from(move |_: MouseEvent| {
+            send.emit(RagFeedbackRequest {
+                rating,
+                comment: None,
+            })
+        })
+    };
+
+    let on_comment_input = {
+        let comment = comment.clone();
+        Callback::from(move |event: InputEvent| {
+            let input: HtmlInputElement = event.target_unchecked_into();
+            comment.set(input.value());
+        })
+    };
+
+    let on_comment_submit = {
+        let send = send.clone();
+        let comment = comment.clone();
+        let rating = props.rating;
+        Callback::from(move |event: SubmitEvent| {
+            event.prevent_default();
+            let text = comment.trim().to_string();
+            if let (Some(rating), false) = (rating, text.is_empty()) {
+                send.emit(RagFeedbackRequest {
+                    rating,
+                    comment: Some(text),
+                });
+            }
+        })
+    };
+
+    let button_class = |rating: RagRating, color: &str| {
+        if props.rating == Some(rating) {
+            format!("btn btn-sm btn-{color}")
+        } else {
+            format!("btn btn-sm btn-outline-{color}")
+        }
+    };
+
+    html! {
+        <div class="mt-3 pt-2 border-top">
+            <div class="d-flex align-items-center gap-2">
+                <small class="text-muted">{"Was this answer helpful?"}</small>
+                <button type="button" class={button_class(RagRating::Up, "success")}
+                        onclick={rate(RagRating::Up)}>{"Yes"}</button>
+                <button type="button" class={button_class(RagRating::Down, "danger")}
+                        onclick={rate(RagRating::Down)}>{"No"}</button>
+            </div>
+            if let Some(error) = &*error {
+                <small class="text-danger">{error}</small>
+            }
+            if *comment_sent {
+                <small class="text-muted">{"Thanks for the feedback."}</small>
+            } else if props.rating.is_some() {
+                <form class="input-group input-group-sm mt-2" onsubmit={on_comment_submit}>
+                    <input type="text" class="form-control" maxlength="2000"
+                        placeholder="What was right or wrong with it? (optional)"
+                        value={(*comment).clone()} oninput={on_comment_input} />
+                    <button type="submit" class="btn btn-outline-secondary"
+                            disabled={comment.trim().is_empty()}>{"Send"}</button>
+                </form>
+            }
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(state: Rc<ChatState>, conversation: usize, event: RagStreamEvent) -> Rc<ChatState> {
+        state.reduce(ChatAction::Event {
+            conversation,
+            turn: 0,
+            event,
+        })
+    }
+
+    #[test]
+    fn answers_stream_into_the_turn_of_their_conversation() {
+        let state = Rc::new(ChatState::default()).reduce(ChatAction::Ask("Why?".into()));
+        let state = event(state, 0, RagStreamEvent::ChunksRetrieved { count: 8 });
+        assert_eq!(
+            state.turns[0].progress.as_deref(),
+            Some("Checking 8 passages...")
+        );
+        let state = event(
+            state,
+            0,
+            RagStreamEvent::AnswerDelta {
+                text: "Because".into(),
+            },
+        );
+        let state = event(state, 0, RagStreamEvent::AnswerDelta { text: "!".into() });
+        assert_eq!(state.turns[0].answer, "Because!");
+        assert!(state.turns[0].progress.is_none());
+        assert!(state.is_pending());
+        assert!(state.history().is_empty());
+
+        let state = state.reduce(ChatAction::Reset);
+        let state = event(state, 0, RagStreamEvent::AnswerDelta { text: "x".into() });
+        assert!(state.turns.is_empty());
+    }
+
+    #[test]
+    fn citation_markers_link_to_their_sources() {
+        let href = |number: usize| (number != 2).then(|| format!("#source-{number}"));
+        assert_eq!(
+            link_citations("Steals work [1, 2]. See [io] and [3](x) [4", href),
+            "Steals work [\\[1\\]](#source-1)\\[2\\]. See [io] and [3](x) [4"
+        );
+    }
+
+    #[test]
+    fn turns_already_answered_ignore_late_failures() {
+        let state = Rc::new(ChatState::default()).reduce(ChatAction::Ask("Why?".into()));
+        let state = event(
+            state,
+            0,
+            RagStreamEvent::Failed {
+                message: "LLM unavailable".into(),
+            },
+        );
+        let state = state.reduce(ChatAction::Failed {
+            conversation: 0,
+            turn: 0,
+            message: "The answer was interrupted, try again.".into(),
+        });
+        assert_eq!(state.turns[0].error.as_deref(), Some("LLM unavailable"));
+        assert!(!state.is_pending());
+    }
+
+    #[test]
+    fn ratings_stay_with_the_turn_of_their_conversation() {
+        let state = Rc::new(ChatState::default()).reduce(ChatAction::Ask("Why?".into()));
+        let state = state.reduce(ChatAction::Rated {
+            conversation: 0,
+            turn: 0,
+            rating: RagRating::Down,
+        });
+        assert_eq!(state.turns[0].rating, Some(RagRating::Down));
+
+        let state = state
+            .reduce(ChatAction::Reset)
+            .reduce(ChatAction::Ask("How?".into()))
+            .reduce(ChatAction::Rated {
+                conversation: 0,
+                turn: 0,
+                rating: RagRating::Up,
+            });
+        assert_eq!(state.turns[0].rating, None);
+    }
+}
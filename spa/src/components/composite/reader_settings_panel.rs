@@ -0,0 +1,121 @@
+use shared::{ReaderFontFamily, ReaderSettings};
+use wasm_bindgen::JsCast;
+use web_sys::{EventTarget, HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+const FONT_FAMILIES: [(ReaderFontFamily, &str); 3] = [
+    (ReaderFontFamily::Serif, "Serif"),
+    (ReaderFontFamily::SansSerif, "Sans serif"),
+    (ReaderFontFamily::Monospace, "Monospace"),
+];
+
+/// Inline style of the reader's article for `settings`.
+pub fn reader_style(settings: &ReaderSettings) -> String {
+    let font_family = match settings.font_family {
+        ReaderFontFamily::Serif => "Georgia, 'Times New Roman', serif",
+        ReaderFontFamily::SansSerif => "system-ui, -apple-system, 'Segoe UI', Roboto, sans-serif",
+        ReaderFontFamily::Monospace => "ui-monospace, SFMono-Regular, Menlo, Consolas, monospace",
+    };
+    let text_align = if settings.justify { "justify" } else { "left" };
+    format!(
+        "font-family: {font_family}; font-size: {}px; max-width: {}ch; text-align: {text_align}; margin: 0 auto;",
+        settings.font_size, settings.line_width
+    )
+}
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    pub settings: ReaderSettings,
+    pub on_change: Callback<ReaderSettings>,
+}
+
+#[function_component(ReaderSettingsPanel)]
+pub fn reader_settings_panel(props: &Props) -> Html {
+    let on_font_family = {
+        let settings = props.settings.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |event: Event| {
+            let target: EventTarget = event.target().expect("Fail to cast to EventTarget");
+            let value = target.unchecked_into::<HtmlSelectElement>().value();
+            if let Ok(font_family) = value.parse() {
+                on_change.emit(ReaderSettings {
+                    font_family,
+                    ..settings.clone()
+                });
+            }
+        })
+    };
+
+    let on_number = |update: fn(&mut ReaderSettings, i32)| {
+        let settings = props.settings.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |event: Event| {
+            let target: EventTarget = event.target().expect("Fail to cast to EventTarget");
+            if let Ok(value) = target.unchecked_into::<HtmlInputElement>().value().parse() {
+                let mut settings = settings.clone();
+                update(&mut settings, value);
+                on_change.emit(settings);
+            }
+        })
+    };
+    let on_font_size = on_number(|settings, value| settings.font_size = value);
+    let on_line_width = on_number(|settings, value| settings.line_width = value);
+
+    let on_justify = {
+        let settings = props.settings.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |event: Event| {
+            let target: EventTarget = event.target().expect("Fail to cast to EventTarget");
+            on_change.emit(ReaderSettings {
+                justify: target.unchecked_into::<HtmlInputElement>().checked(),
+                ..settings.clone()
+            });
+        })
+    };
+
+    let font_options = FONT_FAMILIES
+        .iter()
+        .map(|(font_family, label)| {
+            html! {
+                <option value={font_family.as_ref().to_string()}
+                        selected={*font_family == props.settings.font_family}>
+                    {*label}
+                </option>
+            }
+        })
+        .collect::<Html>();
+
+    html! {
+        <div class="card card-body mt-2">
+            <div class="row g-3 align-items-center">
+                <div class="col-12 col-md-3">
+                    <label for="reader-font-family" class="form-label">{"Font"}</label>
+                    <select id="reader-font-family" class="form-select form-select-sm" onchange={on_font_family}>
+                        {font_options}
+                    </select>
+                </div>
+                <div class="col-12 col-md-3">
+                    <label for="reader-font-size" class="form-label">
+                        {format!("Font size: {}px", props.settings.font_size)}
+                    </label>
+                    <input id="reader-font-size" type="range" class="form-range" min="12" max="32"
+                           value={props.settings.font_size.to_string()} onchange={on_font_size} />
+                </div>
+                <div class="col-12 col-md-3">
+                    <label for="reader-line-width" class="form-label">
+                        {format!("Line width: {} characters", props.settings.line_width)}
+                    </label>
+                    <input id="reader-line-width" type="range" class="form-range" min="40" max="120" step="5"
+                           value={props.settings.line_width.to_string()} onchange={on_line_width} />
+                </div>
+                <div class="col-12 col-md-3">
+                    <div class="form-check form-switch">
+                        <input id="reader-justify" type="checkbox" role="switch" class="form-check-input"
+                               checked={props.settings.justify} onchange={on_justify} />
+                        <label for="reader-justify" class="form-check-label">{"Justify text"}</label>
+                    </div>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -7,6 +7,7 @@ use yew::prelude::*;
 use crate::components::atoms::input_datetime_utc::InputDateTimeUtc;
 use crate::components::atoms::input_text::{InputText, InputType};
 use crate::components::atoms::select::Select;
+use crate::components::toast::use_toaster;
 
 #[derive(PartialEq, Properties)]
 pub struct Props {
@@ -15,9 +16,11 @@ pub struct Props {
 
 #[function_component(TasksFilter)]
 pub fn tasks_filter(props: &Props) -> Html {
+    let toaster = use_toaster();
     let state_handle = use_state(|| BookmarkTaskSearchRequest {
         status: Some(Default::default()),
         page_size: Some(25),
+        include_total: true,
         ..Default::default()
     });
 
@@ -83,6 +86,7 @@ pub fn tasks_filter(props: &Props) -> Html {
         BookmarkTaskStatus::Done.as_ref().to_owned(),
         BookmarkTaskStatus::Pending.as_ref().to_owned(),
         BookmarkTaskStatus::Fail.as_ref().to_owned(),
+        BookmarkTaskStatus::Cancelled.as_ref().to_owned(),
     ];
     let selected_tasks_status = state_handle
         .status
@@ -91,6 +95,7 @@ pub fn tasks_filter(props: &Props) -> Html {
 
     let on_task_status_change = {
         let state_handle = state_handle.clone();
+        let toaster = toaster.clone();
         Callback::from(move |text: String| {
             let mut state = (*state_handle).clone();
             match BookmarkTaskStatus::from_str(&text) {
@@ -99,8 +104,8 @@ pub fn tasks_filter(props: &Props) -> Html {
                 }
                 Err(error) => {
                     state.status = None;
-                    // FIXME: notify user
                     log::error!("Fail to parse BookmarkTaskStatus, error: {error}");
+                    toaster.error(format!("Unknown task status: {text}"));
                 }
             }
             state_handle.set(state);
@@ -1,5 +1,5 @@
 use chrono::SecondsFormat;
-use shared::{BookmarkTask, BookmarkTaskSearchResponse};
+use shared::{BookmarkTask, BookmarkTaskSearchResponse, TaskStatusCount};
 use yew::prelude::*;
 
 #[derive(Debug, Clone, PartialEq, Properties)]
@@ -61,6 +61,24 @@ fn render_bookmark_task(task: &BookmarkTask) -> Html {
     }
 }
 
+fn render_status_counts(status_counts: &[TaskStatusCount]) -> Html {
+    if status_counts.is_empty() {
+        return html! {};
+    }
+    let chips = status_counts
+        .iter()
+        .map(|counted| {
+            let status = counted.status.as_ref().to_lowercase();
+            html! {
+                <span class="badge rounded-pill text-bg-secondary me-2">
+                    {format!("{} {status}", counted.count)}
+                </span>
+            }
+        })
+        .collect::<Html>();
+    html! { <div class="mb-2">{chips}</div> }
+}
+
 #[function_component(TasksTable)]
 pub fn tasks_table(props: &Props) -> Html {
     let content = match &props.response {
@@ -78,7 +96,15 @@ pub fn tasks_table(props: &Props) -> Html {
         }
     };
 
+    let status_counts = props
+        .response
+        .as_ref()
+        .map(|response| render_status_counts(&response.status_counts))
+        .unwrap_or_default();
+
     html! {
+        <>
+        {status_counts}
         <table class="table table-striped table-hover">
             <thead>
               <tr>
@@ -92,5 +118,6 @@ pub fn tasks_table(props: &Props) -> Html {
                 {content}
             </tbody>
         </table>
+        </>
     }
 }
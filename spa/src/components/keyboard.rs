@@ -0,0 +1,193 @@
+//! Global keyboard shortcuts, listened to on the window so they work wherever
+//! the focus is, except while typing in a field.
+
+use std::rc::Rc;
+
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlElement, ScrollIntoViewOptions, ScrollLogicalPosition};
+use yew::prelude::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Shortcut {
+    /// `j`
+    Next,
+    /// `k`
+    Previous,
+    /// `o` or `Enter`
+    Open,
+    /// `t`
+    Tag,
+    /// `/`
+    FocusSearch,
+    /// `ctrl-k` or `cmd-k`
+    TogglePalette,
+    Escape,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct KeyPress {
+    pub key: String,
+    pub ctrl: bool,
+    pub meta: bool,
+    pub alt: bool,
+    /// Focus is on a text field, where keys are meant as text
+    pub editing: bool,
+    /// Focus is on a link or a button, which `Enter` activates itself
+    pub on_control: bool,
+}
+
+impl From<&KeyboardEvent> for KeyPress {
+    fn from(event: &KeyboardEvent) -> Self {
+        let target = event
+            .target()
+            .and_then(|target| target.dyn_into::<HtmlElement>().ok());
+        let tag_name = target
+            .as_ref()
+            .map(|element| element.tag_name().to_uppercase())
+            .unwrap_or_default();
+        let editable = target
+            .as_ref()
+            .is_some_and(|element| element.is_content_editable());
+        Self {
+            key: event.key(),
+            ctrl: event.ctrl_key(),
+            meta: event.meta_key(),
+            alt: event.alt_key(),
+            editing: editable || matches!(tag_name.as_str(), "INPUT" | "TEXTAREA" | "SELECT"),
+            on_control: matches!(tag_name.as_str(), "A" | "BUTTON"),
+        }
+    }
+}
+
+/// The shortcut a key press stands for. The palette and `Escape` work even
+/// while typing, the other shortcuts don't take over the keys of a field.
+pub fn shortcut(press: &KeyPress) -> Option<Shortcut> {
+    let key = press.key.as_str();
+    if (press.ctrl || press.meta) && key.eq_ignore_ascii_case("k") {
+        return Some(Shortcut::TogglePalette);
+    }
+    if key == "Escape" {
+        return Some(Shortcut::Escape);
+    }
+    if press.ctrl || press.meta || press.alt || press.editing {
+        return None;
+    }
+    match key {
+        "j" => Some(Shortcut::Next),
+        "k" => Some(Shortcut::Previous),
+        "o" => Some(Shortcut::Open),
+        "Enter" if !press.on_control => Some(Shortcut::Open),
+        "t" => Some(Shortcut::Tag),
+        "/" => Some(Shortcut::FocusSearch),
+        _ => None,
+    }
+}
+
+/// Emits the shortcuts pressed anywhere in the page to `on_shortcut`, which
+/// may change between renders.
+#[hook]
+pub fn use_shortcuts(on_shortcut: Callback<Shortcut>) {
+    let latest = use_mut_ref(|| on_shortcut.clone());
+    *latest.borrow_mut() = on_shortcut;
+
+    use_effect_with((), move |_| {
+        let window = web_sys::window().expect("window should be available");
+        let latest = Rc::clone(&latest);
+        let callback = Closure::<dyn FnMut(KeyboardEvent)>::wrap(Box::new(move |event| {
+            if let Some(shortcut) = shortcut(&KeyPress::from(&event)) {
+                if shortcut != Shortcut::Escape {
+                    event.prevent_default();
+                }
+                latest.borrow().emit(shortcut);
+            }
+        }));
+        let _ =
+            window.add_event_listener_with_callback("keydown", callback.as_ref().unchecked_ref());
+
+        move || {
+            let _ = window
+                .remove_event_listener_with_callback("keydown", callback.as_ref().unchecked_ref());
+        }
+    });
+}
+
+fn element(id: &str) -> Option<HtmlElement> {
+    web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.get_element_by_id(id))
+        .and_then(|element| element.dyn_into::<HtmlElement>().ok())
+}
+
+/// Focuses the element of `id`, returning whether it was found.
+pub fn focus(id: &str) -> bool {
+    element(id).is_some_and(|element| element.focus().is_ok())
+}
+
+pub fn click(id: &str) {
+    if let Some(element) = element(id) {
+        element.click();
+    }
+}
+
+/// Scrolls the least needed to show the element of `id`.
+pub fn scroll_into_view(id: &str) {
+    if let Some(element) = element(id) {
+        let options = ScrollIntoViewOptions::new();
+        options.set_block(ScrollLogicalPosition::Nearest);
+        element.scroll_into_view_with_scroll_into_view_options(&options);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(key: &str) -> KeyPress {
+        KeyPress {
+            key: key.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn keys_map_to_shortcuts() {
+        assert_eq!(shortcut(&press("j")), Some(Shortcut::Next));
+        assert_eq!(shortcut(&press("k")), Some(Shortcut::Previous));
+        assert_eq!(shortcut(&press("Enter")), Some(Shortcut::Open));
+        assert_eq!(shortcut(&press("/")), Some(Shortcut::FocusSearch));
+        assert_eq!(shortcut(&press("x")), None);
+        let ctrl_k = KeyPress {
+            ctrl: true,
+            ..press("k")
+        };
+        assert_eq!(shortcut(&ctrl_k), Some(Shortcut::TogglePalette));
+    }
+
+    #[test]
+    fn typing_and_controls_keep_their_keys() {
+        let editing = |key: &str| KeyPress {
+            editing: true,
+            ..press(key)
+        };
+        assert_eq!(shortcut(&editing("j")), None);
+        assert_eq!(shortcut(&editing("/")), None);
+        assert_eq!(shortcut(&editing("Escape")), Some(Shortcut::Escape));
+        let meta_k = KeyPress {
+            meta: true,
+            ..editing("K")
+        };
+        assert_eq!(shortcut(&meta_k), Some(Shortcut::TogglePalette));
+
+        let on_link = KeyPress {
+            on_control: true,
+            ..press("Enter")
+        };
+        assert_eq!(shortcut(&on_link), None);
+        let ctrl_j = KeyPress {
+            ctrl: true,
+            ..press("j")
+        };
+        assert_eq!(shortcut(&ctrl_j), None);
+    }
+}
@@ -1,2 +1,8 @@
+pub mod ai_banner;
 pub mod atoms;
 pub mod composite;
+pub mod keyboard;
+pub mod passage;
+pub mod pending_tasks;
+pub mod task_updates;
+pub mod toast;
@@ -0,0 +1,115 @@
+//! Passages of an article, such as the chunks cited by RAG answers, found
+//! back in the rendered article from their opening words.
+
+use wasm_bindgen::JsCast;
+use web_sys::{Element, ScrollIntoViewOptions, ScrollLogicalPosition};
+
+/// Words of a passage kept in its links
+const OPENING_WORDS: usize = 12;
+/// Fewest opening words still matched when the article differs from the
+/// chunk further on
+const MIN_MATCHED_WORDS: usize = 4;
+const BLOCKS: &str = "p, li, h1, h2, h3, h4, h5, h6, blockquote, pre, td";
+const HIGHLIGHT_CLASS: &str = "bg-warning-subtle";
+
+/// Lowercased words of `text`, ignoring punctuation and markdown markup.
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// The opening words identifying the passage of `text`.
+pub fn opening_words(text: &str) -> String {
+    words(text)
+        .into_iter()
+        .take(OPENING_WORDS)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Index of the block where the opening words of `passage` start, read
+/// across the following blocks, or as many of these words as possible down to
+/// a few.
+pub fn find_passage(blocks: &[String], passage: &str) -> Option<usize> {
+    let passage = words(passage);
+    let (block_of_word, text): (Vec<usize>, Vec<String>) = blocks
+        .iter()
+        .enumerate()
+        .flat_map(|(index, block)| words(block).into_iter().map(move |word| (index, word)))
+        .unzip();
+    (MIN_MATCHED_WORDS.min(passage.len()).max(1)..=passage.len())
+        .rev()
+        .find_map(|count| {
+            let opening = &passage[..count];
+            text.windows(count)
+                .position(|window| window == opening)
+                .map(|start| block_of_word[start])
+        })
+}
+
+/// Highlights the block of `container` where `passage` starts and scrolls it
+/// into view, returning whether it was found.
+pub fn highlight(container: &Element, passage: &str) -> bool {
+    let Ok(nodes) = container.query_selector_all(BLOCKS) else {
+        return false;
+    };
+    let elements: Vec<Element> = (0..nodes.length())
+        .filter_map(|index| nodes.item(index))
+        .filter_map(|node| node.dyn_into::<Element>().ok())
+        .collect();
+    let blocks: Vec<String> = elements
+        .iter()
+        .map(|element| element.text_content().unwrap_or_default())
+        .collect();
+    let Some(element) = find_passage(&blocks, passage).map(|index| &elements[index]) else {
+        return false;
+    };
+    element.set_class_name(&format!("{} {HIGHLIGHT_CLASS}", element.class_name()));
+    let options = ScrollIntoViewOptions::new();
+    options.set_block(ScrollLogicalPosition::Center);
+    element.scroll_into_view_with_scroll_into_view_options(&options);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opening_words_drop_markup_and_case() {
+        assert_eq!(
+            opening_words("## Tokio\n\nThe **scheduler** is work-stealing."),
+            "tokio the scheduler is work stealing"
+        );
+        assert_eq!(
+            opening_words(&"word ".repeat(50)).split(' ').count(),
+            OPENING_WORDS
+        );
+    }
+
+    #[test]
+    fn passages_are_found_in_the_block_where_they_start() {
+        let blocks = vec![
+            "Introduction".to_string(),
+            "Tokio is an asynchronous runtime.".to_string(),
+            "The scheduler is work-stealing, each worker has a queue.".to_string(),
+        ];
+        assert_eq!(
+            find_passage(&blocks, "the scheduler is work stealing"),
+            Some(2)
+        );
+        // A chunk opening with a heading matches the block of its first words
+        assert_eq!(
+            find_passage(&blocks, "introduction tokio is an asynchronous runtime"),
+            Some(0)
+        );
+        assert_eq!(
+            find_passage(&blocks, "tokio is an asynchronous framework"),
+            Some(1)
+        );
+        assert_eq!(find_passage(&blocks, "nothing like this at all"), None);
+        assert_eq!(find_passage(&blocks, ""), None);
+    }
+}
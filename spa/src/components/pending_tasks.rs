@@ -0,0 +1,168 @@
+//! The user's pending tasks, loaded once then kept up to date with the
+//! server-sent events.
+
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::rc::Rc;
+use std::time::Duration;
+
+use shared::{BookmarkTask, BookmarkTaskSearchRequest, BookmarkTaskStatus, ServerEvent};
+use uuid::Uuid;
+use web_sys::AbortController;
+use yew::platform::spawn_local;
+use yew::platform::time::sleep;
+use yew::prelude::*;
+
+use crate::api::{bookmark_tasks_api, events_api};
+
+/// Largest page of the task search, beyond which the count is a lower bound
+const LOADED_PENDING_TASKS: u8 = u8::MAX;
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct PendingTasks {
+    task_ids: HashSet<Uuid>,
+    /// More tasks were pending than loaded
+    pub more: bool,
+}
+
+impl PendingTasks {
+    pub fn count(&self) -> usize {
+        self.task_ids.len()
+    }
+}
+
+pub enum PendingTasksAction {
+    Loaded { task_ids: Vec<Uuid>, more: bool },
+    Updated(BookmarkTaskStatus, Uuid),
+}
+
+impl Reducible for PendingTasks {
+    type Action = PendingTasksAction;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        match action {
+            PendingTasksAction::Loaded { task_ids, more } => Rc::new(Self {
+                task_ids: task_ids.into_iter().collect(),
+                more,
+            }),
+            PendingTasksAction::Updated(status, task_id) => {
+                let mut pending = (*self).clone();
+                if status == BookmarkTaskStatus::Pending {
+                    pending.task_ids.insert(task_id);
+                } else {
+                    pending.task_ids.remove(&task_id);
+                }
+                Rc::new(pending)
+            }
+        }
+    }
+}
+
+async fn load(token: &String) -> Option<PendingTasksAction> {
+    let request = BookmarkTaskSearchRequest {
+        status: Some(BookmarkTaskStatus::Pending),
+        page_size: Some(LOADED_PENDING_TASKS),
+        ..Default::default()
+    };
+    match bookmark_tasks_api::search_tasks(token, request).await {
+        Ok(response) => Some(PendingTasksAction::Loaded {
+            task_ids: response.tasks.iter().map(|task| task.task_id).collect(),
+            more: response.has_more,
+        }),
+        Err(error) => {
+            log::warn!("Fail to load pending tasks, error={error}");
+            None
+        }
+    }
+}
+
+/// Pending tasks of the user of `token`, emitting the tasks done to
+/// `on_task_done`, which may change between renders. The events stream is
+/// reopened whenever it drops, reloading the pending tasks missed meanwhile.
+#[hook]
+pub fn use_pending_tasks(
+    token: String,
+    on_task_done: Callback<BookmarkTask>,
+) -> UseReducerHandle<PendingTasks> {
+    let pending = use_reducer(PendingTasks::default);
+    let latest = use_mut_ref(|| on_task_done.clone());
+    *latest.borrow_mut() = on_task_done;
+
+    {
+        let dispatcher = pending.dispatcher();
+        use_effect_with(token, move |token| {
+            let token = token.clone();
+            let stopped = Rc::new(Cell::new(false));
+            let controller = AbortController::new().ok();
+            {
+                let stopped = stopped.clone();
+                let signal = controller.as_ref().map(|controller| controller.signal());
+                spawn_local(async move {
+                    let Some(signal) = signal else {
+                        return;
+                    };
+                    while !stopped.get() {
+                        if let Some(action) = load(&token).await {
+                            dispatcher.dispatch(action);
+                        }
+                        let result = events_api::stream_events(&token, &signal, |event| {
+                            if let ServerEvent::TaskUpdated { task } = event {
+                                dispatcher.dispatch(PendingTasksAction::Updated(
+                                    task.status.clone(),
+                                    task.task_id,
+                                ));
+                                if task.status == BookmarkTaskStatus::Done {
+                                    latest.borrow().emit(task);
+                                }
+                            }
+                        })
+                        .await;
+                        if stopped.get() {
+                            break;
+                        }
+                        if let Err(error) = result {
+                            log::warn!("Events stream failed, error={error:?}");
+                        }
+                        sleep(RECONNECT_DELAY).await;
+                    }
+                });
+            }
+            move || {
+                stopped.set(true);
+                if let Some(controller) = controller {
+                    controller.abort();
+                }
+            }
+        });
+    }
+
+    pending
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tasks_leave_the_pending_ones_once_updated() {
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+        let pending = Rc::new(PendingTasks::default()).reduce(PendingTasksAction::Loaded {
+            task_ids: vec![first],
+            more: false,
+        });
+        let pending = pending.reduce(PendingTasksAction::Updated(
+            BookmarkTaskStatus::Pending,
+            second,
+        ));
+        assert_eq!(pending.count(), 2);
+        let pending = pending.reduce(PendingTasksAction::Updated(BookmarkTaskStatus::Done, first));
+        let pending = pending.reduce(PendingTasksAction::Updated(
+            BookmarkTaskStatus::Fail,
+            Uuid::new_v4(),
+        ));
+        assert_eq!(pending.count(), 1);
+        assert!(pending.task_ids.contains(&second));
+    }
+}
@@ -0,0 +1,189 @@
+//! Live rows of the tasks page: updates of the tasks matching its filter,
+//! streamed by the server while the page is shown.
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use shared::{
+    BookmarkTask, BookmarkTaskSearchRequest, BookmarkTaskSearchResponse, BookmarkTaskStatus,
+    TaskSubscription,
+};
+use web_sys::AbortController;
+use yew::platform::spawn_local;
+use yew::platform::time::sleep;
+use yew::prelude::*;
+
+use crate::api::events_api;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Latest state of each task updated since the list was loaded.
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct TaskUpdates {
+    tasks: Vec<BookmarkTask>,
+}
+
+pub enum TaskUpdatesAction {
+    Cleared,
+    Updated(BookmarkTask),
+}
+
+impl Reducible for TaskUpdates {
+    type Action = TaskUpdatesAction;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        match action {
+            TaskUpdatesAction::Cleared => Rc::new(Self::default()),
+            TaskUpdatesAction::Updated(task) => {
+                let mut updates = (*self).clone();
+                updates.tasks.retain(|known| known.task_id != task.task_id);
+                updates.tasks.push(task);
+                Rc::new(updates)
+            }
+        }
+    }
+}
+
+impl TaskUpdates {
+    /// `response` with its rows replaced by their latest update. Tasks new to
+    /// the filter are only appended to the last page, and only in `status`.
+    pub fn apply(
+        &self,
+        response: &BookmarkTaskSearchResponse,
+        status: Option<&BookmarkTaskStatus>,
+    ) -> BookmarkTaskSearchResponse {
+        let mut response = response.clone();
+        for update in &self.tasks {
+            match response
+                .tasks
+                .iter_mut()
+                .find(|task| task.task_id == update.task_id)
+            {
+                Some(task) => *task = update.clone(),
+                None if !response.has_more
+                    && status.is_none_or(|status| *status == update.status) =>
+                {
+                    response.tasks.push(update.clone())
+                }
+                None => {}
+            }
+        }
+        response
+    }
+}
+
+/// Subscription following the same tasks as `request`, whatever their status.
+pub fn subscription(request: &BookmarkTaskSearchRequest) -> TaskSubscription {
+    TaskSubscription {
+        url: request.url.clone().filter(|url| !url.is_empty()),
+        tags: request
+            .tags
+            .as_ref()
+            .filter(|tags| !tags.is_empty())
+            .map(|tags| tags.join(",")),
+    }
+}
+
+/// Updates of the tasks matching `subscription`, streamed while it's set. The
+/// stream is reopened whenever it drops.
+#[hook]
+pub fn use_task_updates(
+    token: String,
+    subscription: Option<TaskSubscription>,
+) -> UseReducerHandle<TaskUpdates> {
+    let updates = use_reducer(TaskUpdates::default);
+
+    {
+        let dispatcher = updates.dispatcher();
+        use_effect_with((token, subscription), move |(token, subscription)| {
+            dispatcher.dispatch(TaskUpdatesAction::Cleared);
+            let stopped = Rc::new(Cell::new(false));
+            let controller = subscription
+                .as_ref()
+                .and_then(|_| AbortController::new().ok());
+            if let (Some(subscription), Some(controller)) = (subscription.clone(), &controller) {
+                let token = token.clone();
+                let stopped = stopped.clone();
+                let signal = controller.signal();
+                spawn_local(async move {
+                    while !stopped.get() {
+                        let result = events_api::stream_task_events(
+                            &token,
+                            &subscription,
+                            &signal,
+                            |task| dispatcher.dispatch(TaskUpdatesAction::Updated(task)),
+                        )
+                        .await;
+                        if stopped.get() {
+                            break;
+                        }
+                        if let Err(error) = result {
+                            log::warn!("Task events stream failed, error={error:?}");
+                        }
+                        sleep(RECONNECT_DELAY).await;
+                    }
+                });
+            }
+            move || {
+                stopped.set(true);
+                if let Some(controller) = controller {
+                    controller.abort();
+                }
+            }
+        });
+    }
+
+    updates
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn task(status: BookmarkTaskStatus) -> BookmarkTask {
+        BookmarkTask {
+            task_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            url: "https://example.com".into(),
+            status,
+            tags: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            next_delivery: Utc::now(),
+            retries: None,
+            fail_reason: None,
+            ignore_robots: false,
+            refresh: false,
+        }
+    }
+
+    #[test]
+    fn updates_replace_rows_and_fill_the_last_page() {
+        let listed = task(BookmarkTaskStatus::Pending);
+        let mut response = BookmarkTaskSearchResponse {
+            tasks: vec![listed.clone()],
+            has_more: true,
+            total_count: None,
+            status_counts: vec![],
+        };
+        let done = BookmarkTask {
+            status: BookmarkTaskStatus::Done,
+            ..listed
+        };
+        let new = task(BookmarkTaskStatus::Pending);
+        let other_status = task(BookmarkTaskStatus::Fail);
+        let updates = Rc::new(TaskUpdates::default())
+            .reduce(TaskUpdatesAction::Updated(done.clone()))
+            .reduce(TaskUpdatesAction::Updated(new.clone()))
+            .reduce(TaskUpdatesAction::Updated(other_status));
+        let pending = Some(&BookmarkTaskStatus::Pending);
+
+        assert_eq!(updates.apply(&response, pending).tasks, vec![done.clone()]);
+        response.has_more = false;
+        assert_eq!(updates.apply(&response, pending).tasks, vec![done, new]);
+    }
+}
@@ -0,0 +1,215 @@
+//! Notifications shown in a corner of the page, dispatched from any component
+//! below [`ToastProvider`] with the [`Toaster`] of [`use_toaster`].
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use yew::platform::spawn_local;
+use yew::platform::time::sleep;
+use yew::prelude::*;
+
+const SUCCESS_DURATION: Duration = Duration::from_secs(5);
+const ERROR_DURATION: Duration = Duration::from_secs(8);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ToastKind {
+    Success,
+    Error,
+    /// Stays until updated or dismissed
+    InProgress,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ToastId(usize);
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Toast {
+    pub id: ToastId,
+    pub kind: ToastKind,
+    pub message: String,
+}
+
+pub enum ToastAction {
+    /// Shows a toast, or replaces the one of the same id
+    Show(Toast),
+    Dismiss(ToastId),
+}
+
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct Toasts {
+    pub toasts: Vec<Toast>,
+}
+
+impl Reducible for Toasts {
+    type Action = ToastAction;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        let mut toasts = self.toasts.clone();
+        match action {
+            ToastAction::Show(toast) => {
+                match toasts.iter_mut().find(|existing| existing.id == toast.id) {
+                    Some(existing) => *existing = toast,
+                    None => toasts.push(toast),
+                }
+            }
+            ToastAction::Dismiss(id) => toasts.retain(|toast| toast.id != id),
+        }
+        Rc::new(Self { toasts })
+    }
+}
+
+#[derive(Clone)]
+pub struct Toaster {
+    dispatcher: UseReducerDispatcher<Toasts>,
+    next_id: Rc<Cell<usize>>,
+}
+
+impl PartialEq for Toaster {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.next_id, &other.next_id)
+    }
+}
+
+impl Toaster {
+    pub fn success(&self, message: impl Into<String>) {
+        let id = self.next_id();
+        self.update(id, ToastKind::Success, message);
+    }
+
+    pub fn error(&self, message: impl Into<String>) {
+        let id = self.next_id();
+        self.update(id, ToastKind::Error, message);
+    }
+
+    /// Shows a toast staying until [`Toaster::update`] turns it into a
+    /// success or an error.
+    pub fn in_progress(&self, message: impl Into<String>) -> ToastId {
+        let id = self.next_id();
+        self.update(id, ToastKind::InProgress, message);
+        id
+    }
+
+    /// Replaces the toast of `id`, dismissed after a while unless still in
+    /// progress.
+    pub fn update(&self, id: ToastId, kind: ToastKind, message: impl Into<String>) {
+        self.dispatcher.dispatch(ToastAction::Show(Toast {
+            id,
+            kind,
+            message: message.into(),
+        }));
+        let duration = match kind {
+            ToastKind::Success => SUCCESS_DURATION,
+            ToastKind::Error => ERROR_DURATION,
+            ToastKind::InProgress => return,
+        };
+        let dispatcher = self.dispatcher.clone();
+        spawn_local(async move {
+            sleep(duration).await;
+            dispatcher.dispatch(ToastAction::Dismiss(id));
+        });
+    }
+
+    pub fn dismiss(&self, id: ToastId) {
+        self.dispatcher.dispatch(ToastAction::Dismiss(id));
+    }
+
+    fn next_id(&self) -> ToastId {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        ToastId(id)
+    }
+}
+
+/// The toaster of the enclosing [`ToastProvider`].
+#[hook]
+pub fn use_toaster() -> Toaster {
+    use_context::<Toaster>().expect("ToastProvider should wrap the component")
+}
+
+fn toast(toaster: &Toaster, toast: &Toast) -> Html {
+    let (class, role) = match toast.kind {
+        ToastKind::Success => ("text-bg-success", "status"),
+        ToastKind::Error => ("text-bg-danger", "alert"),
+        ToastKind::InProgress => ("text-bg-secondary", "status"),
+    };
+    let on_close = {
+        let toaster = toaster.clone();
+        let id = toast.id;
+        Callback::from(move |_: MouseEvent| toaster.dismiss(id))
+    };
+    html! {
+        <div key={toast.id.0} class={classes!("toast", "show", "align-items-center", "border-0", class)}
+             role={role} aria-live="polite" aria-atomic="true">
+            <div class="d-flex">
+                <div class="toast-body">
+                    if toast.kind == ToastKind::InProgress {
+                        <span class="spinner-border spinner-border-sm me-2" aria-hidden="true"></span>
+                    }
+                    {toast.message.clone()}
+                </div>
+                <button type="button" class="btn-close btn-close-white me-2 m-auto" aria-label="Close"
+                        onclick={on_close}></button>
+            </div>
+        </div>
+    }
+}
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    pub children: Html,
+}
+
+#[function_component(ToastProvider)]
+pub fn toast_provider(props: &Props) -> Html {
+    let toasts = use_reducer(Toasts::default);
+    let toaster = {
+        let dispatcher = toasts.dispatcher();
+        use_memo((), move |_| Toaster {
+            dispatcher,
+            next_id: Rc::new(Cell::new(0)),
+        })
+    };
+
+    html! {
+        <ContextProvider<Toaster> context={(*toaster).clone()}>
+            {props.children.clone()}
+            <div class="toast-container position-fixed bottom-0 end-0 p-3">
+                {for toasts.toasts.iter().map(|item| toast(&toaster, item))}
+            </div>
+        </ContextProvider<Toaster>>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn show(toasts: Rc<Toasts>, id: usize, kind: ToastKind, message: &str) -> Rc<Toasts> {
+        toasts.reduce(ToastAction::Show(Toast {
+            id: ToastId(id),
+            kind,
+            message: message.to_string(),
+        }))
+    }
+
+    #[test]
+    fn toasts_are_replaced_by_id_and_dismissed() {
+        let toasts = Rc::new(Toasts::default());
+        let toasts = show(toasts, 0, ToastKind::InProgress, "Saving");
+        let toasts = show(toasts, 1, ToastKind::Error, "Search failed");
+        let toasts = show(toasts, 0, ToastKind::Success, "Saved");
+        let messages: Vec<&str> = toasts
+            .toasts
+            .iter()
+            .map(|toast| toast.message.as_str())
+            .collect();
+        assert_eq!(messages, vec!["Saved", "Search failed"]);
+        assert_eq!(toasts.toasts[0].kind, ToastKind::Success);
+
+        let toasts = toasts.reduce(ToastAction::Dismiss(ToastId(1)));
+        assert_eq!(toasts.toasts.len(), 1);
+        let toasts = toasts.reduce(ToastAction::Dismiss(ToastId(1)));
+        assert_eq!(toasts.toasts.len(), 1);
+    }
+}
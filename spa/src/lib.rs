@@ -1,6 +1,7 @@
 pub mod api;
 pub mod app;
 pub mod components;
+pub mod offline;
 pub mod pages;
 pub mod router;
 pub mod user_session;
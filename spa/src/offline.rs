@@ -0,0 +1,136 @@
+//! Bookmarks saved for offline reading, kept with the Cache API where the
+//! service worker (`sw.js`) looks for them when the network is unreachable.
+
+use js_sys::Array;
+use uuid::Uuid;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    Cache, CacheStorage, DomParser, Element, Headers, RequestInit, Response, SupportedType,
+};
+
+const SAVED_CACHE_PREFIX: &str = "bookmark-hub-saved-";
+const RECENT_CACHE_PREFIX: &str = "bookmark-hub-recent-";
+
+fn cache_name(bookmark_id: &str) -> String {
+    format!("{SAVED_CACHE_PREFIX}{bookmark_id}")
+}
+
+fn caches() -> Result<CacheStorage, JsValue> {
+    web_sys::window()
+        .ok_or_else(|| JsValue::from_str("no window"))?
+        .caches()
+}
+
+/// Whether offline reading is possible at all, the Cache API being only
+/// available on secure origins.
+pub fn is_supported() -> bool {
+    caches().is_ok()
+}
+
+pub async fn is_saved(bookmark_id: &str) -> Result<bool, JsValue> {
+    let saved = JsFuture::from(caches()?.has(&cache_name(bookmark_id))).await?;
+    Ok(saved.as_bool().unwrap_or(false))
+}
+
+async fn fetch(token: &str, url: &str) -> Result<Response, JsValue> {
+    let headers = Headers::new()?;
+    headers.set("Authorization", &format!("Bearer {token}"))?;
+    let init = RequestInit::new();
+    init.set_headers(&headers);
+    let window = web_sys::window().ok_or_else(|| JsValue::from_str("no window"))?;
+    let response: Response = JsFuture::from(window.fetch_with_str_and_init(url, &init))
+        .await?
+        .dyn_into()?;
+    if !response.ok() {
+        return Err(JsValue::from_str(&format!(
+            "unexpected response, url={url}, status={}",
+            response.status()
+        )));
+    }
+    Ok(response)
+}
+
+/// Images of the page served from the bookmark's own static content, as
+/// requested (signed) and as cached (without the query string).
+fn page_images(html: &str, prefix: &str) -> Result<Vec<(String, String)>, JsValue> {
+    let document = DomParser::new()?.parse_from_string(html, SupportedType::TextHtml)?;
+    let images = document.query_selector_all("img[src]")?;
+    let mut sources = Vec::new();
+    for index in 0..images.length() {
+        let Some(src) = images
+            .get(index)
+            .and_then(|node| node.dyn_into::<Element>().ok())
+            .and_then(|element| element.get_attribute("src"))
+        else {
+            continue;
+        };
+        if !src.starts_with(prefix) {
+            continue;
+        }
+        let key = src.split('?').next().unwrap_or(&src).to_string();
+        if !sources.iter().any(|(_, existing)| existing == &key) {
+            sources.push((src, key));
+        }
+    }
+    Ok(sources)
+}
+
+async fn fill(
+    cache: &Cache,
+    token: &str,
+    user_id: &Uuid,
+    bookmark_id: &str,
+) -> Result<(), JsValue> {
+    let entry = format!("/api/v1/bookmarks/{bookmark_id}");
+    let response = fetch(token, &entry).await?;
+    JsFuture::from(cache.put_with_str(&entry, &response)).await?;
+
+    let prefix = format!("/static/{user_id}/{bookmark_id}/");
+    let page = format!("{prefix}index.html");
+    let response = fetch(token, &page).await?;
+    JsFuture::from(cache.put_with_str(&page, &response.clone()?)).await?;
+    let html = JsFuture::from(response.text()?)
+        .await?
+        .as_string()
+        .unwrap_or_default();
+
+    for (src, key) in page_images(&html, &prefix)? {
+        let response = fetch(token, &src).await?;
+        JsFuture::from(cache.put_with_str(&key, &response)).await?;
+    }
+    Ok(())
+}
+
+/// Pre-fetches the bookmark, its page and the page's images into a cache of
+/// their own, which is dropped whole if any of them fails.
+pub async fn save(token: &str, user_id: &Uuid, bookmark_id: &str) -> Result<(), JsValue> {
+    let caches = caches()?;
+    let name = cache_name(bookmark_id);
+    let cache: Cache = JsFuture::from(caches.open(&name)).await?.dyn_into()?;
+    if let Err(error) = fill(&cache, token, user_id, bookmark_id).await {
+        JsFuture::from(caches.delete(&name)).await?;
+        return Err(error);
+    }
+    log::info!("Bookmark saved for offline reading, id={bookmark_id}");
+    Ok(())
+}
+
+pub async fn remove(bookmark_id: &str) -> Result<(), JsValue> {
+    JsFuture::from(caches()?.delete(&cache_name(bookmark_id))).await?;
+    log::info!("Bookmark removed from offline reading, id={bookmark_id}");
+    Ok(())
+}
+
+/// Forgets every bookmark kept for offline reading, saved or recently read,
+/// so they don't outlive the session of their user.
+pub async fn clear() -> Result<(), JsValue> {
+    let caches = caches()?;
+    let names: Array = JsFuture::from(caches.keys()).await?.dyn_into()?;
+    for name in names.iter().filter_map(|name| name.as_string()) {
+        if name.starts_with(SAVED_CACHE_PREFIX) || name.starts_with(RECENT_CACHE_PREFIX) {
+            JsFuture::from(caches.delete(&name)).await?;
+        }
+    }
+    Ok(())
+}
@@ -1,22 +1,39 @@
+use std::time::Duration;
+
 use shared::{
-    Bookmark, BookmarkTaskSearchRequest, BookmarkTaskSearchResponse, SearchRequest, TagCount,
+    Bookmark, BookmarkTask, BookmarkTaskSearchRequest, BookmarkTaskSearchResponse,
+    BookmarkTaskStatus, BulkBookmarkAction, BulkBookmarkRequest, SearchRequest, TagCount,
     TagFilter,
 };
+use uuid::Uuid;
 use wasm_bindgen::closure::Closure;
 use wasm_bindgen::JsCast;
+use web_sys::{EventTarget, HtmlInputElement};
 use yew::platform::spawn_local;
+use yew::platform::time::sleep;
 use yew::prelude::*;
+use yew_hooks::prelude::*;
 
 use crate::api::{bookmark_tasks_api, bookmarks_api, search_api};
+use crate::components::ai_banner::AiBanner;
 use crate::components::composite::add_bookmark_modal::{AddBookmarkData, AddBookmarkModal};
 use crate::components::composite::bookmark_reader::BookmarkReader;
-use crate::components::composite::main_search_result::SearchResult;
+use crate::components::composite::bulk_toolbar::BulkToolbar;
+use crate::components::composite::command_palette::{
+    CommandPalette, PaletteAction, PaletteCommand,
+};
+use crate::components::composite::main_search_result::{result_element_id, SearchResult};
 use crate::components::composite::navigation_bar::NavigationBar;
 use crate::components::composite::pagination_controls::PaginationControls;
+use crate::components::composite::rag_chat::ChatState;
 use crate::components::composite::search_bar::{SearchBar, SearchInputSubmit};
 use crate::components::composite::tags_filter::{TagCheckedEvent, TagsFilter};
 use crate::components::composite::tasks_filter::TasksFilter;
 use crate::components::composite::tasks_table::TasksTable;
+use crate::components::keyboard::{self, Shortcut};
+use crate::components::pending_tasks::use_pending_tasks;
+use crate::components::task_updates::{self, use_task_updates, TaskUpdatesAction};
+use crate::components::toast::{use_toaster, ToastId, ToastKind, Toaster};
 use crate::router::{self, AppRoute, HistoryEntryState, RouteKind, SearchRouteState};
 use crate::user_session::UserSession;
 
@@ -34,6 +51,7 @@ pub struct HomeState {
     pub current_search_page: usize,
     pub page_size: usize,
     pub total_results: u64,
+    pub fuzzy_results: bool,
 }
 
 #[derive(Clone, PartialEq, Default, Debug)]
@@ -83,12 +101,82 @@ impl From<HomeState> for SearchRequest {
         SearchRequest {
             query,
             tags_filter,
+            domains: vec![],
+            from_date: None,
+            to_date: None,
+            reading_times: vec![],
+            kinds: vec![],
+            entities: vec![],
+            collection: None,
+            workspace: None,
+            snippet_count: None,
+            snippet_words: None,
+            highlight_title_summary: false,
             limit: Some(value.page_size as i32),
             offset,
         }
     }
 }
 
+const TASK_POLL_INTERVAL: Duration = Duration::from_secs(3);
+const TASK_POLL_ATTEMPTS: usize = 100;
+
+/// Follows the task of a new bookmark until it's done, reporting its outcome
+/// in the toast of `toast_id`.
+async fn watch_task(
+    token: String,
+    toaster: Toaster,
+    toast_id: ToastId,
+    url: String,
+    task_id: Uuid,
+) {
+    for _ in 0..TASK_POLL_ATTEMPTS {
+        sleep(TASK_POLL_INTERVAL).await;
+        match bookmark_tasks_api::find_task(&token, &url, task_id).await {
+            Ok(Some(task)) => match task.status {
+                BookmarkTaskStatus::Pending => continue,
+                BookmarkTaskStatus::Done => {
+                    toaster.update(
+                        toast_id,
+                        ToastKind::Success,
+                        format!("Bookmark saved: {url}"),
+                    );
+                    return;
+                }
+                BookmarkTaskStatus::Fail => {
+                    let reason = task
+                        .fail_reason
+                        .unwrap_or_else(|| "unknown error".to_string());
+                    toaster.update(
+                        toast_id,
+                        ToastKind::Error,
+                        format!("Failed to save {url}: {reason}"),
+                    );
+                    return;
+                }
+                BookmarkTaskStatus::Cancelled => {
+                    toaster.update(
+                        toast_id,
+                        ToastKind::Error,
+                        format!("Cancelled saving {url}"),
+                    );
+                    return;
+                }
+            },
+            Ok(None) => {
+                log::warn!("Task of new bookmark not found, task_id={task_id}");
+                break;
+            }
+            Err(error) => log::warn!("Fail to check task, task_id={task_id}, error={error}"),
+        }
+    }
+    toaster.update(
+        toast_id,
+        ToastKind::Success,
+        format!("Still processing {url}, follow it in Tasks"),
+    );
+}
+
 #[derive(Debug, Clone, PartialEq, Properties)]
 pub struct Props {
     pub user_session: UserSession,
@@ -98,6 +186,7 @@ pub struct Props {
 #[function_component(Home)]
 pub fn home(props: &Props) -> Html {
     let token = props.user_session.token.clone();
+    let toaster = use_toaster();
 
     let state_handle = use_state_eq(|| HomeState {
         page_size: 20,
@@ -106,6 +195,8 @@ pub fn home(props: &Props) -> Html {
     });
     let bookmark_detail_handle = use_state_eq(BookmarkDetailState::default);
     let route_handle = use_state_eq(|| router::sync_current_route(HistoryEntryState::Direct));
+    // Bumped to search again on the same route, after a bulk action
+    let search_refresh_handle = use_state_eq(|| 0u32);
 
     {
         let route_handle = route_handle.clone();
@@ -138,7 +229,9 @@ pub fn home(props: &Props) -> Html {
         let bookmark_detail_handle = bookmark_detail_handle.clone();
         let route_handle = route_handle.clone();
         let token = token.clone();
-        use_effect_with((*route_handle).clone(), move |route| match route.clone() {
+        let toaster = toaster.clone();
+        let deps = ((*route_handle).clone(), *search_refresh_handle);
+        use_effect_with(deps, move |(route, _)| match route.clone() {
             AppRoute::Search(search_route) => {
                 bookmark_detail_handle.set(BookmarkDetailState::None);
 
@@ -151,6 +244,7 @@ pub fn home(props: &Props) -> Html {
                 let state_handle = state_handle.clone();
                 let route_handle = route_handle.clone();
                 let token = token.clone();
+                let toaster = toaster.clone();
                 spawn_local(async move {
                     match search_api::search(&token, state.clone().into()).await {
                         Ok(result) => {
@@ -162,15 +256,17 @@ pub fn home(props: &Props) -> Html {
                             new_state.items = result.items;
                             new_state.tags = result.tags;
                             new_state.total_results = result.total;
+                            new_state.fuzzy_results = result.fuzzy;
                             state_handle.set(new_state);
                         }
                         Err(error) => {
                             log::warn!("Failed to load search route, error: {error}");
+                            toaster.error("Search failed, try again.");
                         }
                     }
                 });
             }
-            AppRoute::Bookmark { bookmark_id } => {
+            AppRoute::Bookmark { bookmark_id, .. } => {
                 bookmark_detail_handle.set(BookmarkDetailState::Loading {
                     bookmark_id: bookmark_id.clone(),
                 });
@@ -181,21 +277,13 @@ pub fn home(props: &Props) -> Html {
                 spawn_local(async move {
                     match bookmarks_api::get_by_id(&token, &bookmark_id).await {
                         Ok(Some(bookmark)) => {
-                            if *route_handle
-                                != (AppRoute::Bookmark {
-                                    bookmark_id: bookmark_id.clone(),
-                                })
-                            {
+                            if route_handle.bookmark_id() != Some(bookmark_id.as_str()) {
                                 return;
                             }
                             bookmark_detail_handle.set(BookmarkDetailState::Ready(bookmark));
                         }
                         Ok(None) => {
-                            if *route_handle
-                                == (AppRoute::Bookmark {
-                                    bookmark_id: bookmark_id.clone(),
-                                })
-                            {
+                            if route_handle.bookmark_id() == Some(bookmark_id.as_str()) {
                                 bookmark_detail_handle
                                     .set(BookmarkDetailState::NotFound { bookmark_id });
                             }
@@ -205,11 +293,7 @@ pub fn home(props: &Props) -> Html {
                                 "Failed to fetch bookmark for route, bookmark_id={}, error={error}",
                                 &bookmark_id
                             );
-                            if *route_handle
-                                == (AppRoute::Bookmark {
-                                    bookmark_id: bookmark_id.clone(),
-                                })
-                            {
+                            if route_handle.bookmark_id() == Some(bookmark_id.as_str()) {
                                 bookmark_detail_handle.set(BookmarkDetailState::Error {
                                     bookmark_id,
                                     message: "Failed to load bookmark.".to_string(),
@@ -233,17 +317,60 @@ pub fn home(props: &Props) -> Html {
         })
     };
 
+    let on_task_done = {
+        let route_handle = route_handle.clone();
+        let state_handle = state_handle.clone();
+        let search_refresh_handle = search_refresh_handle.clone();
+        Callback::from(move |task: BookmarkTask| {
+            log::info!("Task done, task_id={}", task.task_id);
+            // New bookmarks land on the first page, reloaded unless more
+            // results were scrolled in below it
+            let first_page_only = state_handle.current_search_page == 1
+                && state_handle.items.len() <= state_handle.page_size;
+            if matches!(*route_handle, AppRoute::Search(_)) && first_page_only {
+                search_refresh_handle.set(*search_refresh_handle + 1);
+            }
+        })
+    };
+    let pending_tasks = use_pending_tasks(token.clone(), on_task_done);
+
+    // Rows of the tasks page kept live for its filter while it's shown
+    let task_subscription = matches!(*route_handle, AppRoute::Tasks)
+        .then(|| task_updates::subscription(&state_handle.bookmark_tasks_request));
+    let task_updates = use_task_updates(token.clone(), task_subscription);
+    {
+        let dispatcher = task_updates.dispatcher();
+        use_effect_with(state_handle.bookmark_tasks_response.clone(), move |_| {
+            dispatcher.dispatch(TaskUpdatesAction::Cleared);
+        });
+    }
+
     let on_new_bookmark = {
         let token = token.clone();
+        let toaster = toaster.clone();
         Callback::from(move |event: AddBookmarkData| {
             let token = token.clone();
+            let toaster = toaster.clone();
+            let toast_id = toaster.in_progress(format!("Adding {}", event.url));
             spawn_local(async move {
+                let url = event.url.clone();
                 match bookmarks_api::add_bookmark(&token, event.into()).await {
-                    Ok(result) => log::info!(
-                        "New bookmark added, response={}",
-                        serde_json::to_string(&result).unwrap()
-                    ),
-                    Err(error) => log::warn!("Add bookmark failed, error: {error}"),
+                    Ok(result) => {
+                        log::info!(
+                            "New bookmark added, response={}",
+                            serde_json::to_string(&result).unwrap()
+                        );
+                        toaster.update(
+                            toast_id,
+                            ToastKind::InProgress,
+                            format!("Processing {}", result.url),
+                        );
+                        watch_task(token, toaster, toast_id, result.url, result.task_id).await;
+                    }
+                    Err(error) => {
+                        log::warn!("Add bookmark failed, error: {error}");
+                        toaster.update(toast_id, ToastKind::Error, format!("Failed to add {url}"));
+                    }
                 }
             })
         })
@@ -300,7 +427,10 @@ pub fn home(props: &Props) -> Html {
     let on_item_selected = {
         let navigate_with_push = navigate_with_push.clone();
         Callback::from(move |bookmark_id: String| {
-            navigate_with_push.emit(AppRoute::Bookmark { bookmark_id });
+            navigate_with_push.emit(AppRoute::Bookmark {
+                bookmark_id,
+                passage: None,
+            });
         })
     };
 
@@ -334,8 +464,10 @@ pub fn home(props: &Props) -> Html {
     let on_new_tags = {
         let bookmark_detail_handle = bookmark_detail_handle.clone();
         let token = token.clone();
+        let toaster = toaster.clone();
         Callback::from(move |event: Vec<String>| {
             let token = token.clone();
+            let toaster = toaster.clone();
             let bookmark_detail_handle = bookmark_detail_handle.clone();
             let detail_state = (*bookmark_detail_handle).clone();
             if let BookmarkDetailState::Ready(bookmark) = detail_state {
@@ -349,6 +481,7 @@ pub fn home(props: &Props) -> Html {
                                 "Fail to set tags to bookmark={}, error={error}",
                                 &bookmark.bookmark_id
                             );
+                            toaster.error("Failed to save the tags.");
                         }
                     }
                 });
@@ -363,8 +496,10 @@ pub fn home(props: &Props) -> Html {
         let route_handle = route_handle.clone();
         let state_handle = state_handle.clone();
         let token = token.clone();
+        let toaster = toaster.clone();
         Callback::from(move |_: ()| {
             let token = token.clone();
+            let toaster = toaster.clone();
             let bookmark_detail_handle = bookmark_detail_handle.clone();
             let route_handle = route_handle.clone();
             let state_handle = state_handle.clone();
@@ -374,6 +509,7 @@ pub fn home(props: &Props) -> Html {
                     match bookmarks_api::delete_bookmark(&token, &bookmark.bookmark_id).await {
                         Ok(true) => {
                             log::info!("Bookmark deleted, id={}", &bookmark.bookmark_id);
+                            toaster.success("Bookmark moved to the trash.");
                             if router::current_entry_was_pushed() {
                                 if let Err(error) = router::back() {
                                     log::error!(
@@ -404,6 +540,7 @@ pub fn home(props: &Props) -> Html {
                                 "Failed to delete bookmark={}, error={error}",
                                 &bookmark.bookmark_id
                             );
+                            toaster.error("Failed to delete the bookmark.");
                         }
                     }
                 });
@@ -425,6 +562,9 @@ pub fn home(props: &Props) -> Html {
         })
     };
 
+    // Kept here so the conversation outlives a visit to its sources
+    let rag_chat_handle = use_reducer(ChatState::default);
+
     let on_rag_tab_change = {
         let navigate_with_push = navigate_with_push.clone();
         Callback::from(move |tab: Option<crate::router::RagTab>| {
@@ -463,16 +603,93 @@ pub fn home(props: &Props) -> Html {
         })
     };
 
+    let infinite_scroll_storage = use_local_storage::<bool>("infinite-scroll".to_string());
+    let infinite_scroll = infinite_scroll_storage.as_ref().copied().unwrap_or(false);
+    let loading_more_handle = use_state_eq(|| false);
+
+    let on_infinite_scroll_change = {
+        let state_handle = state_handle.clone();
+        let navigate_with_push = navigate_with_push.clone();
+        let search_refresh_handle = search_refresh_handle.clone();
+        Callback::from(move |event: Event| {
+            let target: EventTarget = event.target().expect("Fail to cast to EventTarget");
+            infinite_scroll_storage.set(target.unchecked_into::<HtmlInputElement>().checked());
+            // Back to the first page either way, with only its results
+            navigate_with_push.emit(AppRoute::Search(SearchRouteState::new(
+                state_handle.search_input.clone(),
+                state_handle.tags_filter.clone(),
+                1,
+            )));
+            search_refresh_handle.set(*search_refresh_handle + 1);
+        })
+    };
+
+    let on_load_more = {
+        let token = token.clone();
+        let toaster = toaster.clone();
+        let state_handle = state_handle.clone();
+        let route_handle = route_handle.clone();
+        let loading_more_handle = loading_more_handle.clone();
+        Callback::from(move |_: ()| {
+            let AppRoute::Search(search_route) = (*route_handle).clone() else {
+                return;
+            };
+            let loaded = (state_handle.current_search_page - 1) * state_handle.page_size
+                + state_handle.items.len();
+            if *loading_more_handle || loaded >= state_handle.total_results as usize {
+                return;
+            }
+            loading_more_handle.set(true);
+            let mut request: SearchRequest = (*state_handle).clone().into();
+            request.offset = Some(loaded as i32);
+            let token = token.clone();
+            let state_handle = state_handle.clone();
+            let route_handle = route_handle.clone();
+            let loading_more_handle = loading_more_handle.clone();
+            let toaster = toaster.clone();
+            spawn_local(async move {
+                let result = search_api::search(&token, request).await;
+                loading_more_handle.set(false);
+                match result {
+                    Ok(result) => {
+                        if *route_handle != AppRoute::Search(search_route) {
+                            return;
+                        }
+                        let mut new_state = (*state_handle).clone();
+                        for item in result.items {
+                            let bookmark_id = &item.bookmark.bookmark_id;
+                            if !new_state
+                                .items
+                                .iter()
+                                .any(|loaded| &loaded.bookmark.bookmark_id == bookmark_id)
+                            {
+                                new_state.items.push(item);
+                            }
+                        }
+                        new_state.total_results = result.total;
+                        state_handle.set(new_state);
+                    }
+                    Err(error) => {
+                        log::warn!("Failed to load more results, error: {error}");
+                        toaster.error("Failed to load more results.");
+                    }
+                }
+            });
+        })
+    };
+
     let page_cursors_handle = use_state_eq(|| vec![None::<uuid::Uuid>]);
     let current_page_handle = use_state_eq(|| 1usize);
 
     let on_task_filter_submit = {
         let state_handle = state_handle.clone();
         let token = token.clone();
+        let toaster = toaster.clone();
         let page_cursors_handle = page_cursors_handle.clone();
         let current_page_handle = current_page_handle.clone();
         Callback::from(move |event: BookmarkTaskSearchRequest| {
             let token = token.clone();
+            let toaster = toaster.clone();
             let state_handle = state_handle.clone();
             let page_cursors_handle = page_cursors_handle.clone();
             let current_page_handle = current_page_handle.clone();
@@ -490,6 +707,7 @@ pub fn home(props: &Props) -> Html {
                     }
                     Err(error) => {
                         log::error!("Fail to search tasks error={error}");
+                        toaster.error("Failed to load the tasks.");
                         state.bookmark_tasks_response = None;
                         state_handle.set(state);
                     }
@@ -501,10 +719,12 @@ pub fn home(props: &Props) -> Html {
     let on_previous_page = {
         let state_handle = state_handle.clone();
         let token = token.clone();
+        let toaster = toaster.clone();
         let page_cursors_handle = page_cursors_handle.clone();
         let current_page_handle = current_page_handle.clone();
         Callback::from(move |_| {
             let token = token.clone();
+            let toaster = toaster.clone();
             let state_handle = state_handle.clone();
             let page_cursors_handle = page_cursors_handle.clone();
             let current_page_handle = current_page_handle.clone();
@@ -525,6 +745,7 @@ pub fn home(props: &Props) -> Html {
                         }
                         Err(error) => {
                             log::error!("Fail to load previous page, error={error}");
+                            toaster.error("Failed to load the tasks.");
                         }
                     }
                 });
@@ -535,10 +756,12 @@ pub fn home(props: &Props) -> Html {
     let on_next_page = {
         let state_handle = state_handle.clone();
         let token = token.clone();
+        let toaster = toaster.clone();
         let page_cursors_handle = page_cursors_handle.clone();
         let current_page_handle = current_page_handle.clone();
         Callback::from(move |_| {
             let token = token.clone();
+            let toaster = toaster.clone();
             let state_handle = state_handle.clone();
             let page_cursors_handle = page_cursors_handle.clone();
             let current_page_handle = current_page_handle.clone();
@@ -568,6 +791,7 @@ pub fn home(props: &Props) -> Html {
                             }
                             Err(error) => {
                                 log::error!("Fail to load next page, error={error}");
+                                toaster.error("Failed to load the tasks.");
                             }
                         }
                     });
@@ -576,6 +800,273 @@ pub fn home(props: &Props) -> Html {
         })
     };
 
+    let selection_mode_handle = use_state_eq(|| false);
+    let bulk_selected_handle = use_state_eq(Vec::<String>::new);
+    // Every bookmark matching the search is selected, server side
+    let bulk_all_matching_handle = use_state_eq(|| false);
+
+    {
+        let bulk_selected_handle = bulk_selected_handle.clone();
+        let bulk_all_matching_handle = bulk_all_matching_handle.clone();
+        let deps = (
+            state_handle.search_input.clone(),
+            state_handle.tags_filter.clone(),
+        );
+        use_effect_with(deps, move |_| {
+            bulk_selected_handle.set(Vec::new());
+            bulk_all_matching_handle.set(false);
+        });
+    }
+
+    let on_toggle_selection_mode = {
+        let selection_mode_handle = selection_mode_handle.clone();
+        let bulk_selected_handle = bulk_selected_handle.clone();
+        let bulk_all_matching_handle = bulk_all_matching_handle.clone();
+        Callback::from(move |_: ()| {
+            selection_mode_handle.set(!*selection_mode_handle);
+            bulk_selected_handle.set(Vec::new());
+            bulk_all_matching_handle.set(false);
+        })
+    };
+
+    let on_item_checked = {
+        let bulk_selected_handle = bulk_selected_handle.clone();
+        let bulk_all_matching_handle = bulk_all_matching_handle.clone();
+        Callback::from(move |bookmark_id: String| {
+            let mut selected = (*bulk_selected_handle).clone();
+            if let Some(position) = selected.iter().position(|id| id == &bookmark_id) {
+                selected.remove(position);
+            } else {
+                selected.push(bookmark_id);
+            }
+            bulk_selected_handle.set(selected);
+            bulk_all_matching_handle.set(false);
+        })
+    };
+
+    let on_select_page = {
+        let state_handle = state_handle.clone();
+        let bulk_selected_handle = bulk_selected_handle.clone();
+        let bulk_all_matching_handle = bulk_all_matching_handle.clone();
+        Callback::from(move |_: ()| {
+            let mut selected = (*bulk_selected_handle).clone();
+            for item in &state_handle.items {
+                if !selected.contains(&item.bookmark.bookmark_id) {
+                    selected.push(item.bookmark.bookmark_id.clone());
+                }
+            }
+            bulk_selected_handle.set(selected);
+            bulk_all_matching_handle.set(false);
+        })
+    };
+
+    let on_select_all_matching = {
+        let bulk_all_matching_handle = bulk_all_matching_handle.clone();
+        Callback::from(move |_: ()| bulk_all_matching_handle.set(true))
+    };
+
+    let on_clear_selection = {
+        let bulk_selected_handle = bulk_selected_handle.clone();
+        let bulk_all_matching_handle = bulk_all_matching_handle.clone();
+        Callback::from(move |_: ()| {
+            bulk_selected_handle.set(Vec::new());
+            bulk_all_matching_handle.set(false);
+        })
+    };
+
+    let on_bulk_action = {
+        let token = token.clone();
+        let toaster = toaster.clone();
+        let state_handle = state_handle.clone();
+        let bulk_selected_handle = bulk_selected_handle.clone();
+        let bulk_all_matching_handle = bulk_all_matching_handle.clone();
+        let search_refresh_handle = search_refresh_handle.clone();
+        Callback::from(move |action: BulkBookmarkAction| {
+            let request = if *bulk_all_matching_handle {
+                let search = SearchRequest {
+                    limit: None,
+                    offset: None,
+                    ..(*state_handle).clone().into()
+                };
+                BulkBookmarkRequest {
+                    bookmark_ids: vec![],
+                    search: Some(search),
+                    action,
+                }
+            } else {
+                BulkBookmarkRequest {
+                    bookmark_ids: (*bulk_selected_handle).clone(),
+                    search: None,
+                    action,
+                }
+            };
+            let token = token.clone();
+            let bulk_selected_handle = bulk_selected_handle.clone();
+            let bulk_all_matching_handle = bulk_all_matching_handle.clone();
+            let search_refresh_handle = search_refresh_handle.clone();
+            let toaster = toaster.clone();
+            spawn_local(async move {
+                match bookmarks_api::bulk_update(&token, &request).await {
+                    Ok(response) => {
+                        toaster.success(format!("{} bookmark(s) updated.", response.updated));
+                        bulk_selected_handle.set(Vec::new());
+                        bulk_all_matching_handle.set(false);
+                        search_refresh_handle.set(*search_refresh_handle + 1);
+                    }
+                    Err(error) => {
+                        log::error!("Failed to apply bulk action, error={error}");
+                        toaster.error("Failed to apply the action to the selection.");
+                    }
+                }
+            });
+        })
+    };
+
+    let selected_result_handle = use_state_eq(|| None::<usize>);
+    let palette_open_handle = use_state_eq(|| false);
+    // Id of the element to focus once rendered, the tags field of a bookmark
+    // being opened for instance
+    let pending_focus_handle = use_state_eq(|| None::<&'static str>);
+
+    {
+        let selected_result_handle = selected_result_handle.clone();
+        // Kept while more results are appended to the same search
+        let deps = ((*route_handle).clone(), *search_refresh_handle);
+        use_effect_with(deps, move |_| {
+            selected_result_handle.set(None);
+        });
+    }
+    use_effect_with(*selected_result_handle, |selected| {
+        if let Some(index) = selected {
+            keyboard::scroll_into_view(&result_element_id(*index));
+        }
+    });
+    {
+        let pending_focus_handle = pending_focus_handle.clone();
+        use_effect(move || {
+            if let Some(id) = *pending_focus_handle {
+                if keyboard::focus(id) {
+                    pending_focus_handle.set(None);
+                }
+            }
+        });
+    }
+
+    let on_palette_action = {
+        let navigate_with_push = navigate_with_push.clone();
+        let state_handle = state_handle.clone();
+        let route_handle = route_handle.clone();
+        let pending_focus_handle = pending_focus_handle.clone();
+        let on_logout = props.on_logout.clone();
+        Callback::from(move |action: PaletteAction| match action {
+            PaletteAction::GoToSearch => {
+                navigate_with_push.emit(state_handle.current_search_route())
+            }
+            PaletteAction::GoToTasks => navigate_with_push.emit(AppRoute::Tasks),
+            PaletteAction::GoToRag => navigate_with_push.emit(AppRoute::RAG { tab: None }),
+            PaletteAction::FocusSearch => {
+                if !matches!(*route_handle, AppRoute::Search(_)) {
+                    navigate_with_push.emit(state_handle.current_search_route());
+                }
+                pending_focus_handle.set(Some("search"));
+            }
+            PaletteAction::AddBookmark => keyboard::click("add-bookmark-button"),
+            PaletteAction::TagBookmark => pending_focus_handle.set(Some("tags")),
+            PaletteAction::OpenBookmark(bookmark_id) => {
+                navigate_with_push.emit(AppRoute::Bookmark {
+                    bookmark_id,
+                    passage: None,
+                })
+            }
+            PaletteAction::Logout => on_logout.emit(()),
+        })
+    };
+
+    let on_shortcut = {
+        let route_handle = route_handle.clone();
+        let state_handle = state_handle.clone();
+        let selected_result_handle = selected_result_handle.clone();
+        let palette_open_handle = palette_open_handle.clone();
+        let on_palette_action = on_palette_action.clone();
+        Callback::from(move |shortcut: Shortcut| {
+            if *palette_open_handle {
+                if matches!(shortcut, Shortcut::Escape | Shortcut::TogglePalette) {
+                    palette_open_handle.set(false);
+                }
+                return;
+            }
+            let on_search = matches!(*route_handle, AppRoute::Search(_));
+            let result_count = if on_search {
+                state_handle.items.len()
+            } else {
+                0
+            };
+            let selected = *selected_result_handle;
+            let selected_bookmark_id = selected
+                .and_then(|index| state_handle.items.get(index))
+                .filter(|_| on_search)
+                .map(|item| item.bookmark.bookmark_id.clone());
+            match shortcut {
+                Shortcut::TogglePalette => palette_open_handle.set(true),
+                Shortcut::Next if result_count > 0 => selected_result_handle.set(Some(
+                    selected.map_or(0, |index| (index + 1).min(result_count - 1)),
+                )),
+                Shortcut::Previous if result_count > 0 => selected_result_handle
+                    .set(Some(selected.map_or(0, |index| index.saturating_sub(1)))),
+                Shortcut::Open => {
+                    if let Some(bookmark_id) = selected_bookmark_id {
+                        on_palette_action.emit(PaletteAction::OpenBookmark(bookmark_id));
+                    }
+                }
+                Shortcut::Tag => {
+                    if let Some(bookmark_id) = selected_bookmark_id {
+                        on_palette_action.emit(PaletteAction::OpenBookmark(bookmark_id));
+                        on_palette_action.emit(PaletteAction::TagBookmark);
+                    } else if matches!(*route_handle, AppRoute::Bookmark { .. }) {
+                        on_palette_action.emit(PaletteAction::TagBookmark);
+                    }
+                }
+                Shortcut::FocusSearch => on_palette_action.emit(PaletteAction::FocusSearch),
+                _ => {}
+            }
+        })
+    };
+    keyboard::use_shortcuts(on_shortcut);
+
+    let on_palette_close = {
+        let palette_open_handle = palette_open_handle.clone();
+        Callback::from(move |_: ()| palette_open_handle.set(false))
+    };
+
+    let palette_commands = {
+        let mut commands = vec![
+            PaletteCommand::new("Go to search", PaletteAction::GoToSearch),
+            PaletteCommand::new("Go to tasks", PaletteAction::GoToTasks),
+            PaletteCommand::new("Go to RAG", PaletteAction::GoToRag),
+            PaletteCommand::new("Focus search", PaletteAction::FocusSearch),
+            PaletteCommand::new("Add bookmark", PaletteAction::AddBookmark),
+        ];
+        match &*route_handle {
+            AppRoute::Bookmark { .. } => {
+                commands.push(PaletteCommand::new(
+                    "Tag this bookmark",
+                    PaletteAction::TagBookmark,
+                ));
+            }
+            AppRoute::Search(_) => {
+                commands.extend(state_handle.items.iter().map(|item| {
+                    PaletteCommand::new(
+                        format!("Open: {}", item.bookmark.title),
+                        PaletteAction::OpenBookmark(item.bookmark.bookmark_id.clone()),
+                    )
+                }));
+            }
+            _ => {}
+        }
+        commands.push(PaletteCommand::new("Logout", PaletteAction::Logout));
+        commands
+    };
+
     let content = match &*route_handle {
         AppRoute::Search(_) => {
             let has_more = state_handle.current_search_page * state_handle.page_size
@@ -596,23 +1087,75 @@ pub fn home(props: &Props) -> Html {
                             on_submit={on_search_submit}
                             on_clear={Some(on_clear_filters.clone())}
                             has_active_filters={!state_handle.tags_filter.is_empty() || !state_handle.search_input.is_empty()} />
-                        <div class="mt-3">
-                            <SearchResult on_item_selected={on_item_selected} results={state_handle.items.clone()} />
+                        <div class="mt-3 d-flex justify-content-between align-items-center">
+                            <div class="form-check form-switch mb-0">
+                                <input id="infinite-scroll" type="checkbox" role="switch" class="form-check-input"
+                                       checked={infinite_scroll} onchange={on_infinite_scroll_change} />
+                                <label for="infinite-scroll" class="form-check-label">{"Infinite scroll"}</label>
+                            </div>
+                            if !*selection_mode_handle && !state_handle.items.is_empty() {
+                                <button type="button" class="btn btn-sm btn-outline-secondary"
+                                        onclick={on_toggle_selection_mode.reform(|_: MouseEvent| ())}>
+                                    {"Select"}
+                                </button>
+                            }
                         </div>
+                        if *selection_mode_handle {
+                            <BulkToolbar
+                                selected_count={bulk_selected_handle.len()}
+                                total_results={state_handle.total_results}
+                                all_matching={*bulk_all_matching_handle}
+                                on_select_page={on_select_page}
+                                on_select_all_matching={on_select_all_matching}
+                                on_clear={on_clear_selection}
+                                on_action={on_bulk_action}
+                                on_done={on_toggle_selection_mode.clone()} />
+                        }
+                        if state_handle.fuzzy_results {
+                            <div class="alert alert-info mt-3 mb-0" role="status">
+                                {format!("No exact match for \"{}\", showing similar titles and domains instead.", state_handle.search_input)}
+                            </div>
+                        }
                         <div class="mt-3">
-                            <PaginationControls
-                                has_more={has_more}
-                                on_previous={on_search_previous_page}
-                                on_next={on_search_next_page}
-                                current_page={state_handle.current_search_page}
-                                page_size={state_handle.page_size}
-                                current_count={state_handle.items.len()} />
+                            <SearchResult
+                                on_item_selected={on_item_selected}
+                                results={state_handle.items.clone()}
+                                selected={*selected_result_handle}
+                                selectable={*selection_mode_handle}
+                                checked_ids={if *bulk_all_matching_handle {
+                                    state_handle.items.iter().map(|item| item.bookmark.bookmark_id.clone()).collect()
+                                } else {
+                                    (*bulk_selected_handle).clone()
+                                }}
+                                on_item_checked={on_item_checked}
+                                virtualized={infinite_scroll}
+                                on_reach_end={on_load_more} />
                         </div>
+                        if infinite_scroll {
+                            <div class="mt-3 text-center text-muted">
+                                if *loading_more_handle {
+                                    <div class="spinner-border spinner-border-sm me-2" role="status">
+                                        <span class="visually-hidden">{"Loading..."}</span>
+                                    </div>
+                                }
+                                {format!("{} of {} results", state_handle.items.len(), state_handle.total_results)}
+                            </div>
+                        } else {
+                            <div class="mt-3">
+                                <PaginationControls
+                                    has_more={has_more}
+                                    on_previous={on_search_previous_page}
+                                    on_next={on_search_next_page}
+                                    current_page={state_handle.current_search_page}
+                                    page_size={state_handle.page_size}
+                                    current_count={state_handle.items.len()} />
+                            </div>
+                        }
                     </div>
                 </div>
             }
         }
-        AppRoute::Bookmark { .. } => match &*bookmark_detail_handle {
+        AppRoute::Bookmark { passage, .. } => match &*bookmark_detail_handle {
             BookmarkDetailState::Loading { .. } | BookmarkDetailState::None => {
                 html! {
                     <div class="text-center py-5">
@@ -628,6 +1171,7 @@ pub fn home(props: &Props) -> Html {
                         key={bookmark.bookmark_id.clone()}
                         user_session={props.user_session.to_owned()}
                         bookmark={bookmark.to_owned()}
+                        passage={passage.clone()}
                         on_goback={on_goback}
                         on_new_tags={on_new_tags}
                         on_delete={on_delete.clone()} />
@@ -685,7 +1229,17 @@ pub fn home(props: &Props) -> Html {
             html! {
                 <>
                     <TasksFilter on_submit={on_task_filter_submit} />
-                    <TasksTable response={state_handle.bookmark_tasks_response.clone()} />
+                    <TasksTable response={
+                        state_handle
+                            .bookmark_tasks_response
+                            .as_ref()
+                            .map(|response| {
+                                task_updates.apply(
+                                    response,
+                                    state_handle.bookmark_tasks_request.status.as_ref(),
+                                )
+                            })
+                    } />
                     <div class="mt-3">
                         {pagination_controls}
                     </div>
@@ -697,7 +1251,9 @@ pub fn home(props: &Props) -> Html {
                 <crate::pages::rag::RagPage
                     user_session={props.user_session.clone()}
                     tab={*tab}
-                    on_tab_change={on_rag_tab_change.clone()} />
+                    on_tab_change={on_rag_tab_change.clone()}
+                    chat={rag_chat_handle.clone()}
+                    on_navigate={navigate_with_push.clone()} />
             }
         }
     };
@@ -707,11 +1263,19 @@ pub fn home(props: &Props) -> Html {
             <NavigationBar username={props.user_session.username.clone()}
                 active_page={(*route_handle).kind()}
                 on_page_change={on_page_change}
-                on_logout={props.on_logout.clone()} />
+                on_logout={props.on_logout.clone()}
+                pending_tasks={pending_tasks.count()}
+                more_pending_tasks={pending_tasks.more} />
             <div class="container-fluid mt-4 px-3 px-md-4 px-lg-5 pb-4">
+                <AiBanner token={token.clone()} />
                 {content}
             </div>
             <AddBookmarkModal on_submit={on_new_bookmark} />
+            <CommandPalette
+                open={*palette_open_handle}
+                commands={palette_commands}
+                on_action={on_palette_action}
+                on_close={on_palette_close} />
         </>
     }
 }
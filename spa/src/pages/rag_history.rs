@@ -1,5 +1,7 @@
 use chrono::{DateTime, Local};
 use shared::{RagChunkInfo, RagHistoryRequest, RagSessionWithSources};
+use uuid::Uuid;
+use web_sys::HtmlInputElement;
 use yew::platform::spawn_local;
 use yew::prelude::*;
 
@@ -16,7 +18,10 @@ pub struct RagHistoryState {
 
 #[derive(Clone, PartialEq, Debug)]
 pub enum RagHistoryMessage {
+    Loading,
     HistoryLoaded(Result<Vec<RagSessionWithSources>, String>),
+    SessionDeleted(Uuid),
+    Failed(String),
     ClearError,
 }
 
@@ -32,16 +37,23 @@ pub fn rag_history_tab(props: &RagHistoryTabProps) -> Html {
         ..Default::default()
     });
 
-    use_effect_with((), {
+    // The search submitted, the input only searches on submit
+    let query = use_state_eq(String::new);
+    let query_input = use_node_ref();
+
+    use_effect_with((*query).clone(), {
         let state = state.clone();
         let user_session = props.user_session.clone();
-        move |_| {
+        move |query: &String| {
             let state = state.clone();
             let user_session = user_session.clone();
+            let query = query.clone();
+            state.dispatch(RagHistoryMessage::Loading);
             spawn_local(async move {
                 let request = RagHistoryRequest {
                     limit: Some(50),
                     offset: None,
+                    query: (!query.trim().is_empty()).then_some(query),
                 };
 
                 let result = rag_api::get_rag_history(&user_session, &request).await;
@@ -58,6 +70,43 @@ pub fn rag_history_tab(props: &RagHistoryTabProps) -> Html {
         }
     });
 
+    let on_search = {
+        let query = query.clone();
+        let query_input = query_input.clone();
+        Callback::from(move |event: SubmitEvent| {
+            event.prevent_default();
+            if let Some(input) = query_input.cast::<HtmlInputElement>() {
+                query.set(input.value().trim().to_string());
+            }
+        })
+    };
+
+    let on_delete = {
+        let state = state.clone();
+        let user_session = props.user_session.clone();
+        Callback::from(move |session_id: Uuid| {
+            let confirmed = web_sys::window()
+                .and_then(|window| {
+                    window
+                        .confirm_with_message("Delete this question and its answer?")
+                        .ok()
+                })
+                .unwrap_or(false);
+            if !confirmed {
+                return;
+            }
+            let state = state.clone();
+            let user_session = user_session.clone();
+            spawn_local(async move {
+                let action = match rag_api::delete_session(&user_session, session_id).await {
+                    Ok(_) => RagHistoryMessage::SessionDeleted(session_id),
+                    Err(err) => RagHistoryMessage::Failed(format!("Failed to delete: {}", err)),
+                };
+                state.dispatch(action);
+            });
+        })
+    };
+
     let on_clear_error = {
         let state = state.clone();
         Callback::from(move |_| {
@@ -74,12 +123,22 @@ pub fn rag_history_tab(props: &RagHistoryTabProps) -> Html {
                 </div>
             }
 
+            <form class="input-group mb-3" onsubmit={on_search}>
+                <input ref={query_input} type="search" class="form-control"
+                    placeholder="Search questions and answers..." />
+                <button type="submit" class="btn btn-outline-secondary">{"Search"}</button>
+            </form>
+
             if state.is_loading {
                 <div class="text-center">
                     <div class="spinner-border" role="status">
                         <span class="visually-hidden">{"Loading..."}</span>
                     </div>
                 </div>
+            } else if state.history.is_empty() && !query.is_empty() {
+                <div class="text-center py-5">
+                    <h5>{"No questions match your search"}</h5>
+                </div>
             } else if state.history.is_empty() {
                 <div class="text-center py-5">
                     <h5>{"No questions asked yet"}</h5>
@@ -97,7 +156,7 @@ pub fn rag_history_tab(props: &RagHistoryTabProps) -> Html {
                                 let key = format!("{}-{}", session.created_at.timestamp(), index);
                                 html! {
                                     <div key={key}>
-                                        {render_session(session, index)}
+                                        {render_session(session, index, &on_delete)}
                                     </div>
                                 }
                             }).collect::<Html>()
@@ -109,13 +168,22 @@ pub fn rag_history_tab(props: &RagHistoryTabProps) -> Html {
     }
 }
 
-fn render_session(session: &RagSessionWithSources, index: usize) -> Html {
+fn render_session(
+    session: &RagSessionWithSources,
+    index: usize,
+    on_delete: &Callback<Uuid>,
+) -> Html {
     let local_time: DateTime<Local> = session.created_at.into();
     let formatted_time = local_time.format("%Y-%m-%d %H:%M").to_string();
     let item_id = format!("item-{}", index);
     let collapse_id = format!("collapse-{}", index);
     let collapse_target = format!("#collapse-{}", index);
     let is_first = index == 0;
+    let on_delete = {
+        let on_delete = on_delete.clone();
+        let session_id = session.session_id;
+        Callback::from(move |_: MouseEvent| on_delete.emit(session_id))
+    };
 
     html! {
         <div class="accordion-item">
@@ -129,9 +197,17 @@ fn render_session(session: &RagSessionWithSources, index: usize) -> Html {
                     aria-controls={collapse_id.clone()}>
                     <div class="w-100 d-flex justify-content-between align-items-start me-3">
                         <div class="flex-grow-1">
-                            <strong>{&session.question}</strong>
-                            if !session.sources.is_empty() {
-                                <span class="badge bg-secondary ms-2">{session.sources.len()}{" sources"}</span>
+                            if let Some(title) = &session.title {
+                                <strong>{title}</strong>
+                                if !session.sources.is_empty() {
+                                    <span class="badge bg-secondary ms-2">{session.sources.len()}{" sources"}</span>
+                                }
+                                <div class="small text-muted">{&session.question}</div>
+                            } else {
+                                <strong>{&session.question}</strong>
+                                if !session.sources.is_empty() {
+                                    <span class="badge bg-secondary ms-2">{session.sources.len()}{" sources"}</span>
+                                }
                             }
                         </div>
                         <small class="text-muted">{formatted_time}</small>
@@ -170,6 +246,12 @@ fn render_session(session: &RagSessionWithSources, index: usize) -> Html {
                             }
                         </div>
                     }
+
+                    <div class="text-end mt-3">
+                        <button type="button" class="btn btn-sm btn-outline-danger" onclick={on_delete}>
+                            {"Delete"}
+                        </button>
+                    </div>
                 </div>
             </div>
         </div>
@@ -223,6 +305,9 @@ impl Reducible for RagHistoryState {
         let mut state = (*self).clone();
 
         match action {
+            RagHistoryMessage::Loading => {
+                state.is_loading = true;
+            }
             RagHistoryMessage::HistoryLoaded(result) => {
                 state.is_loading = false;
                 match result {
@@ -235,6 +320,14 @@ impl Reducible for RagHistoryState {
                     }
                 }
             }
+            RagHistoryMessage::SessionDeleted(session_id) => {
+                state
+                    .history
+                    .retain(|session| session.session_id != session_id);
+            }
+            RagHistoryMessage::Failed(error) => {
+                state.error_message = Some(error);
+            }
             RagHistoryMessage::ClearError => {
                 state.error_message = None;
             }
@@ -16,9 +16,16 @@ pub enum RagTab {
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub enum AppRoute {
     Search(SearchRouteState),
-    Bookmark { bookmark_id: String },
+    Bookmark {
+        bookmark_id: String,
+        /// Opening words of a passage of the article to scroll to, such as
+        /// a chunk cited by a RAG answer
+        passage: Option<String>,
+    },
     Tasks,
-    RAG { tab: Option<RagTab> },
+    RAG {
+        tab: Option<RagTab>,
+    },
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -85,6 +92,13 @@ impl AppRoute {
             Self::RAG { .. } => RouteKind::RAG,
         }
     }
+
+    pub fn bookmark_id(&self) -> Option<&str> {
+        match self {
+            Self::Bookmark { bookmark_id, .. } => Some(bookmark_id),
+            _ => None,
+        }
+    }
 }
 
 pub fn href(route: &AppRoute) -> String {
@@ -108,7 +122,18 @@ pub fn href(route: &AppRoute) -> String {
                 format!("/?{query}")
             }
         }
-        AppRoute::Bookmark { bookmark_id } => format!("/bookmarks/{bookmark_id}"),
+        AppRoute::Bookmark {
+            bookmark_id,
+            passage,
+        } => match passage {
+            Some(passage) => {
+                let query = form_urlencoded::Serializer::new(String::new())
+                    .append_pair("passage", passage)
+                    .finish();
+                format!("/bookmarks/{bookmark_id}?{query}")
+            }
+            None => format!("/bookmarks/{bookmark_id}"),
+        },
         AppRoute::Tasks => "/tasks".to_string(),
         AppRoute::RAG { tab } => match tab {
             Some(RagTab::History) => "/rag?tab=history".to_string(),
@@ -211,7 +236,8 @@ fn current_entry_state() -> Option<HistoryEntryState> {
 fn parse_path_and_search(pathname: &str, search: &str) -> ParsedRoute {
     let path = normalize_path(pathname);
     let route = match path.as_str() {
-        "/" => AppRoute::Search(parse_search_state(search)),
+        // `/search` and `/read/{id}` are aliases, replaced by the canonical URLs
+        "/" | "/search" => AppRoute::Search(parse_search_state(search)),
         "/tasks" => AppRoute::Tasks,
         "/rag" => AppRoute::RAG {
             tab: parse_rag_tab(search),
@@ -219,12 +245,18 @@ fn parse_path_and_search(pathname: &str, search: &str) -> ParsedRoute {
         "/rag/history" => AppRoute::RAG {
             tab: Some(RagTab::History),
         },
-        _ if path.starts_with("/bookmarks/") => {
-            let bookmark_id = path.trim_start_matches("/bookmarks/").to_string();
+        _ if path.starts_with("/bookmarks/") || path.starts_with("/read/") => {
+            let bookmark_id = path
+                .trim_start_matches("/bookmarks/")
+                .trim_start_matches("/read/")
+                .to_string();
             if bookmark_id.is_empty() || bookmark_id.contains('/') {
                 AppRoute::Search(SearchRouteState::default())
             } else {
-                AppRoute::Bookmark { bookmark_id }
+                AppRoute::Bookmark {
+                    bookmark_id,
+                    passage: parse_passage(search),
+                }
             }
         }
         _ => AppRoute::Search(SearchRouteState::default()),
@@ -252,6 +284,13 @@ fn parse_search_state(search: &str) -> SearchRouteState {
         match key.as_ref() {
             "q" if query.is_empty() => query = value.trim().to_string(),
             "tag" => tags.push(value.into_owned()),
+            "tags" => tags.extend(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                    .map(str::to_string),
+            ),
             "page" if page == 1 => {
                 if let Ok(parsed_page) = value.parse::<usize>() {
                     if parsed_page > 0 {
@@ -266,6 +305,13 @@ fn parse_search_state(search: &str) -> SearchRouteState {
     SearchRouteState::new(query, tags, page)
 }
 
+fn parse_passage(search: &str) -> Option<String> {
+    form_urlencoded::parse(search.trim_start_matches('?').as_bytes())
+        .find(|(key, _)| key == "passage")
+        .map(|(_, value)| value.trim().to_string())
+        .filter(|passage| !passage.is_empty())
+}
+
 fn parse_rag_tab(search: &str) -> Option<RagTab> {
     for (key, value) in form_urlencoded::parse(search.trim_start_matches('?').as_bytes()) {
         if key.as_ref() == "tab" {
@@ -336,10 +382,50 @@ mod tests {
         assert_eq!(
             parsed.route,
             AppRoute::Bookmark {
-                bookmark_id: "abc123".to_string()
+                bookmark_id: "abc123".to_string(),
+                passage: None,
             }
         );
         assert!(!parsed.needs_canonical_url);
+
+        let parsed = parse_path_and_search("/bookmarks/abc123", "?passage=Tokio+uses%20a");
+        assert_eq!(
+            parsed.route,
+            AppRoute::Bookmark {
+                bookmark_id: "abc123".to_string(),
+                passage: Some("Tokio uses a".to_string()),
+            }
+        );
+        assert!(parsed.needs_canonical_url);
+        assert_eq!(
+            href(&parsed.route),
+            "/bookmarks/abc123?passage=Tokio+uses+a"
+        );
+    }
+
+    #[test]
+    fn aliases_are_replaced_by_canonical_routes() {
+        let parsed = parse_path_and_search("/search", "?q=hello&tags=rust,%20yew");
+        assert_eq!(
+            parsed.route,
+            AppRoute::Search(SearchRouteState::new(
+                "hello",
+                vec!["rust".to_string(), "yew".to_string()],
+                1
+            ))
+        );
+        assert!(parsed.needs_canonical_url);
+        assert_eq!(href(&parsed.route), "/?q=hello&tag=rust&tag=yew");
+
+        let parsed = parse_path_and_search("/read/abc123", "");
+        assert_eq!(
+            parsed.route,
+            AppRoute::Bookmark {
+                bookmark_id: "abc123".to_string(),
+                passage: None,
+            }
+        );
+        assert!(parsed.needs_canonical_url);
     }
 
     #[test]
@@ -0,0 +1,148 @@
+// Service worker making the SPA and the bookmarks read recently available
+// offline. Bookmarks saved explicitly for offline reading live in their own
+// `bookmark-hub-saved-{id}` caches, filled and removed by the SPA itself.
+const VERSION = "v1";
+const SHELL_CACHE = `bookmark-hub-shell-${VERSION}`;
+const RECENT_CACHE = `bookmark-hub-recent-${VERSION}`;
+// Entries, pages and images together, kept in the recent cache
+const RECENT_MAX_ENTRIES = 300;
+
+// Ids are the base64url murmur3 hash of the URL, which keeps out routes like
+// `/api/v1/bookmarks/random`
+const BOOKMARK_API = /^\/api\/v1\/bookmarks\/[A-Za-z0-9_-]{22}$/;
+const STATIC_PAGE = /^\/static\/[^/]+\/[^/]+\/index\.html$/;
+const STATIC_ASSET = /^\/static\/[^/]+\/[^/]+\/.+$/;
+
+self.addEventListener("install", (event) => {
+  event.waitUntil(
+    caches
+      .open(SHELL_CACHE)
+      .then((cache) => cache.add("/index.html"))
+      .then(() => self.skipWaiting()),
+  );
+});
+
+self.addEventListener("activate", (event) => {
+  event.waitUntil(
+    caches
+      .keys()
+      .then((names) =>
+        Promise.all(
+          names
+            .filter(
+              (name) =>
+                (name.startsWith("bookmark-hub-shell-") && name !== SHELL_CACHE) ||
+                (name.startsWith("bookmark-hub-recent-") && name !== RECENT_CACHE),
+            )
+            .map((name) => caches.delete(name)),
+        ),
+      )
+      .then(() => self.clients.claim()),
+  );
+});
+
+// Images are signed with a token renewed every hour, so cached copies are
+// looked up without the query string.
+function cached(request) {
+  return caches.match(request, { ignoreSearch: true, ignoreVary: true });
+}
+
+function withoutSearch(url) {
+  return url.origin + url.pathname;
+}
+
+async function remember(key, response) {
+  const cache = await caches.open(RECENT_CACHE);
+  await cache.put(key, response);
+  const keys = await cache.keys();
+  // Keys are kept in insertion order, the oldest go first
+  await Promise.all(
+    keys.slice(0, Math.max(0, keys.length - RECENT_MAX_ENTRIES)).map((old) => cache.delete(old)),
+  );
+}
+
+async function networkFirst(event, url) {
+  try {
+    const response = await fetch(event.request);
+    if (response.ok) {
+      event.waitUntil(remember(withoutSearch(url), response.clone()));
+    }
+    return response;
+  } catch (error) {
+    const fallback = await cached(event.request);
+    if (fallback) {
+      return fallback;
+    }
+    throw error;
+  }
+}
+
+async function cacheFirst(event, url) {
+  const hit = await cached(event.request);
+  if (hit) {
+    return hit;
+  }
+  const response = await fetch(event.request);
+  if (response.ok) {
+    event.waitUntil(remember(withoutSearch(url), response.clone()));
+  }
+  return response;
+}
+
+// The app shell is served from the cache while a fresh copy is fetched for
+// the next visit; Trunk gives the wasm and js files hashed names.
+async function staleWhileRevalidate(event) {
+  const cache = await caches.open(SHELL_CACHE);
+  const hit = await cache.match(event.request);
+  const refresh = fetch(event.request).then((response) => {
+    if (response.ok) {
+      return cache.put(event.request, response.clone()).then(() => response);
+    }
+    return response;
+  });
+  if (hit) {
+    event.waitUntil(refresh.catch(() => undefined));
+    return hit;
+  }
+  return refresh;
+}
+
+async function navigation(event) {
+  try {
+    const response = await fetch(event.request);
+    const html = (response.headers.get("content-type") || "").startsWith("text/html");
+    if (response.ok && html) {
+      const cache = await caches.open(SHELL_CACHE);
+      event.waitUntil(cache.put("/index.html", response.clone()));
+    }
+    return response;
+  } catch (error) {
+    const shell = await caches.match("/index.html");
+    if (shell) {
+      return shell;
+    }
+    throw error;
+  }
+}
+
+self.addEventListener("fetch", (event) => {
+  const request = event.request;
+  if (request.method !== "GET") {
+    return;
+  }
+  const url = new URL(request.url);
+  if (request.mode === "navigate") {
+    event.respondWith(navigation(event));
+  } else if (url.origin !== self.location.origin) {
+    // Bootstrap from the CDN
+    if (url.hostname === "cdn.jsdelivr.net") {
+      event.respondWith(staleWhileRevalidate(event));
+    }
+  } else if (BOOKMARK_API.test(url.pathname) || STATIC_PAGE.test(url.pathname)) {
+    event.respondWith(networkFirst(event, url));
+  } else if (STATIC_ASSET.test(url.pathname)) {
+    event.respondWith(cacheFirst(event, url));
+  } else if (!url.pathname.startsWith("/api/")) {
+    event.respondWith(staleWhileRevalidate(event));
+  }
+});